@@ -0,0 +1,239 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::prelude::{Address, FromBytes, Network};
+
+use bech32::{FromBase32, Variant};
+use core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The human-readable prefix of the account addresses.
+const ADDRESS_PREFIX: &str = "aleo";
+/// The number of characters in an account address.
+const ADDRESS_LENGTH: usize = 63;
+
+/// The reason an account address string is rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddressError {
+    /// The address has the prefix of another network.
+    WrongNetwork { prefix: String, network: &'static str },
+    /// The address does not have the expected number of characters.
+    WrongLength { length: usize },
+    /// The address is not a bech32 string, as it has no separator, mixed case, or characters outside the bech32 alphabet.
+    InvalidCharacters,
+    /// The checksum of the address does not match its data.
+    BadChecksum,
+    /// The data of the address is not a point on the curve.
+    InvalidPoint,
+}
+
+impl Display for AddressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongNetwork { prefix, network } => {
+                write!(
+                    f,
+                    "the address has the prefix '{prefix}', but {network} addresses start with '{ADDRESS_PREFIX}1'"
+                )
+            }
+            Self::WrongLength { length } => {
+                write!(f, "the address has {length} characters, but addresses have {ADDRESS_LENGTH} characters")
+            }
+            Self::InvalidCharacters => write!(f, "the address is not a valid bech32m string"),
+            Self::BadChecksum => write!(f, "the checksum of the address is incorrect (the address has a typo)"),
+            Self::InvalidPoint => write!(f, "the address does not encode a valid account address"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// An account address, which is validated when it is parsed, so that a malformed address is rejected
+/// with the precise reason at the boundary of the node, rather than deep in the ledger.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AccountAddress<N: Network>(Address<N>);
+
+impl<N: Network> AccountAddress<N> {
+    /// Returns the account address.
+    pub const fn address(&self) -> Address<N> {
+        self.0
+    }
+}
+
+impl<N: Network> From<Address<N>> for AccountAddress<N> {
+    fn from(address: Address<N>) -> Self {
+        Self(address)
+    }
+}
+
+impl<N: Network> From<AccountAddress<N>> for Address<N> {
+    fn from(address: AccountAddress<N>) -> Self {
+        address.0
+    }
+}
+
+impl<N: Network> FromStr for AccountAddress<N> {
+    type Err = AddressError;
+
+    /// Parses an account address, checking its prefix, length, checksum, and point, in this order.
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        // Ensure the address has the prefix of this network.
+        let prefix = match address.rsplit_once('1') {
+            Some((prefix, _)) => prefix,
+            None => return Err(AddressError::InvalidCharacters),
+        };
+        if !prefix.eq_ignore_ascii_case(ADDRESS_PREFIX) {
+            return Err(AddressError::WrongNetwork { prefix: prefix.to_string(), network: N::NAME });
+        }
+        // Ensure the address has the expected length.
+        if address.len() != ADDRESS_LENGTH {
+            return Err(AddressError::WrongLength { length: address.len() });
+        }
+        // Decode the address, ensuring the checksum is the bech32m checksum of the data.
+        let data = match bech32::decode(address) {
+            Ok((_, data, Variant::Bech32m)) => data,
+            Ok((_, _, Variant::Bech32)) | Err(bech32::Error::InvalidChecksum) => return Err(AddressError::BadChecksum),
+            Err(_) => return Err(AddressError::InvalidCharacters),
+        };
+        // Decode the data into an account address.
+        let bytes = Vec::<u8>::from_base32(&data).map_err(|_| AddressError::InvalidPoint)?;
+        let address = Address::read_le(&bytes[..]).map_err(|_| AddressError::InvalidPoint)?;
+        Ok(Self::from(address))
+    }
+}
+
+impl<N: Network> Display for AccountAddress<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<N: Network> Serialize for AccountAddress<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, N: Network> Deserialize<'de> for AccountAddress<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        address.parse().map_err(|error| de::Error::custom(format!("Invalid address '{address}' - {error}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{PrivateKey, TestRng, Testnet3, ToBytes};
+
+    use bech32::ToBase32;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a random address, as a string.
+    fn sample_address(rng: &mut TestRng) -> String {
+        Address::<CurrentNetwork>::try_from(PrivateKey::new(rng).unwrap()).unwrap().to_string()
+    }
+
+    /// Returns the given address, encoded with the given prefix and variant.
+    fn encode_address(address: &str, prefix: &str, variant: Variant) -> String {
+        let bytes = Address::<CurrentNetwork>::from_str(address).unwrap().to_bytes_le().unwrap();
+        bech32::encode(prefix, bytes.to_base32(), variant).unwrap()
+    }
+
+    #[test]
+    fn test_valid_addresses() {
+        let rng = &mut TestRng::default();
+        for _ in 0..100 {
+            let address = sample_address(rng);
+            let parsed = AccountAddress::<CurrentNetwork>::from_str(&address).unwrap();
+            assert_eq!(parsed.to_string(), address);
+            assert_eq!(parsed.address(), Address::from_str(&address).unwrap());
+            // An address in upper case is valid.
+            assert_eq!(AccountAddress::<CurrentNetwork>::from_str(&address.to_uppercase()), Ok(parsed));
+        }
+    }
+
+    #[test]
+    fn test_cross_network_addresses() {
+        let rng = &mut TestRng::default();
+        let address = sample_address(rng);
+        for prefix in ["aleot", "tleo", "bc"] {
+            let other_address = encode_address(&address, prefix, Variant::Bech32m);
+            assert_eq!(
+                AccountAddress::<CurrentNetwork>::from_str(&other_address),
+                Err(AddressError::WrongNetwork { prefix: prefix.to_string(), network: CurrentNetwork::NAME })
+            );
+        }
+    }
+
+    #[test]
+    fn test_checksum_corruption() {
+        let rng = &mut TestRng::default();
+        for _ in 0..100 {
+            let address = sample_address(rng);
+            // Replace each character of the data with another character of the bech32 alphabet.
+            for index in ADDRESS_PREFIX.len() + 1..ADDRESS_LENGTH {
+                let character = address.as_bytes()[index] as char;
+                let replacement = match character == 'q' {
+                    true => "p",
+                    false => "q",
+                };
+                let mut corrupted = address.clone();
+                corrupted.replace_range(index..index + 1, replacement);
+                assert_eq!(AccountAddress::<CurrentNetwork>::from_str(&corrupted), Err(AddressError::BadChecksum));
+            }
+        }
+        // An address with a bech32 checksum, instead of a bech32m checksum, is rejected.
+        let address = encode_address(&sample_address(rng), ADDRESS_PREFIX, Variant::Bech32);
+        assert_eq!(AccountAddress::<CurrentNetwork>::from_str(&address), Err(AddressError::BadChecksum));
+    }
+
+    #[test]
+    fn test_malformed_addresses() {
+        let rng = &mut TestRng::default();
+        let address = sample_address(rng);
+        assert_eq!(
+            AccountAddress::<CurrentNetwork>::from_str(&address[..ADDRESS_LENGTH - 1]),
+            Err(AddressError::WrongLength { length: ADDRESS_LENGTH - 1 })
+        );
+        assert_eq!(
+            AccountAddress::<CurrentNetwork>::from_str(&format!("{address}q")),
+            Err(AddressError::WrongLength { length: ADDRESS_LENGTH + 1 })
+        );
+        assert_eq!(AccountAddress::<CurrentNetwork>::from_str(""), Err(AddressError::InvalidCharacters));
+        let mut invalid = address.clone();
+        invalid.replace_range(10..11, "b");
+        assert_eq!(AccountAddress::<CurrentNetwork>::from_str(&invalid), Err(AddressError::InvalidCharacters));
+        let mixed_case = format!("{}{}", &address[..10], address[10..].to_uppercase());
+        assert_eq!(AccountAddress::<CurrentNetwork>::from_str(&mixed_case), Err(AddressError::InvalidCharacters));
+    }
+
+    #[test]
+    fn test_serde() {
+        let rng = &mut TestRng::default();
+        let address = AccountAddress::<CurrentNetwork>::from_str(&sample_address(rng)).unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{address}\""));
+        assert_eq!(serde_json::from_str::<AccountAddress<CurrentNetwork>>(&json).unwrap(), address);
+        // A malformed address is rejected with the reason.
+        let error = serde_json::from_str::<AccountAddress<CurrentNetwork>>("\"aleo1qqq\"").unwrap_err();
+        assert!(error.to_string().contains("the address has 8 characters"));
+    }
+}
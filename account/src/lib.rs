@@ -16,6 +16,9 @@
 
 #![forbid(unsafe_code)]
 
+mod address;
+pub use address::*;
+
 use snarkvm::{
     console::{network::prelude::*, types::Field},
     prelude::*,
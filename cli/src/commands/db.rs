@@ -0,0 +1,445 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_consensus::Consensus;
+use snarkos_node_ledger::{ConsistencyCheck, Ledger, DEFAULT_CONSISTENCY_CHECK_DEPTH};
+use snarkos_node_store::{
+    migrations,
+    rocksdb::{column_names, set_storage_dir, storage_dir, Database, RocksDB},
+    BlockMap,
+    BlockPruner,
+    ChainMmrBackfill,
+    CircuitIndexBackfill,
+    ConsensusDB,
+    DifficultyIndexBackfill,
+    HistoryIndexBackfill,
+    JournalBackfill,
+    MapID,
+    Migration,
+    MigrationProgress,
+    OrderingAudit,
+    SchemaDB,
+    SequenceIndexBackfill,
+    TimestampIndexBackfill,
+};
+use snarkvm::{
+    prelude::{Block, ConsensusStore, FromBytes, Network},
+    synthesizer::store::helpers::MapRead,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use serde::Serialize;
+use std::path::PathBuf;
+
+type CurrentNetwork = snarkvm::prelude::Testnet3;
+
+/// The names of the indexes that can be rebuilt, which are the indexes backfilled by the migrations.
+const INDEX_NAMES: [&str; 7] = ["journal", "circuit", "timestamp", "history", "difficulty", "chain-mmr", "sequence"];
+
+/// The failure of a database command, whose class determines the exit code of the process.
+///
+/// The exit codes are `1` if the operation failed, `2` if an argument is invalid, `3` if there is no database,
+/// `4` if a live node holds the database, and `5` if the integrity scan found an inconsistency.
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error("{}", _0)]
+    Failed(String),
+
+    #[error("{}", _0)]
+    InvalidArgument(String),
+
+    #[error("No database found in '{}'", _0.display())]
+    Missing(PathBuf),
+
+    #[error("The database in '{}' is held by a live node (stop the node and try again)", _0.display())]
+    Locked(PathBuf),
+
+    #[error("Found an inconsistency in the database - {}", _0)]
+    Inconsistent(String),
+}
+
+impl DbError {
+    /// Returns the exit code of the failure.
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::Failed(..) => 1,
+            Self::InvalidArgument(..) => 2,
+            Self::Missing(..) => 3,
+            Self::Locked(..) => 4,
+            Self::Inconsistent(..) => 5,
+        }
+    }
+}
+
+/// Inspects and repairs the database of a stopped node.
+#[derive(Debug, Parser)]
+pub struct Db {
+    /// The path to the database [default: the ledger directory of the node]
+    #[clap(global = true, long)]
+    storage: Option<PathBuf>,
+    /// Enables development mode, specify the unique ID of the local node whose database to open
+    #[clap(global = true, long)]
+    dev: Option<u16>,
+    /// Specify a subcommand.
+    #[clap(subcommand)]
+    command: DbCommand,
+}
+
+/// The commands on the database of a stopped node.
+#[derive(Debug, Parser)]
+pub enum DbCommand {
+    /// Print the schema version, the tip, and the statistics of each column.
+    Info {
+        /// The maximum number of entries scanned in each column
+        #[clap(default_value = "10000000", long)]
+        max_entries: usize,
+    },
+    /// Check the integrity of the latest blocks, and print the report in JSON.
+    Check {
+        /// The number of latest blocks to check [default: 100]
+        #[clap(long, conflicts_with = "deep")]
+        depth: Option<u32>,
+        /// Check every block
+        #[clap(long)]
+        deep: bool,
+        /// Audit the order of the stored transactions of every block against the transactions root in its header
+        #[clap(long, conflicts_with_all = &["depth", "deep"])]
+        ordering: bool,
+        /// Rewrite the stored order of each block whose transactions are found in another order than committed
+        #[clap(long, requires = "ordering")]
+        repair: bool,
+    },
+    /// Remove the blocks above the given height, as a reorganization would.
+    Rollback {
+        /// The height of the block to roll back to
+        #[clap(long)]
+        to_height: u32,
+    },
+    /// Rebuild an index from the blocks
+    /// [options: journal, circuit, timestamp, history, difficulty, chain-mmr, sequence]
+    RebuildIndex {
+        /// The name of the index
+        name: String,
+    },
+    /// Compact every column of the database.
+    Compact,
+}
+
+/// The report of the integrity scan of the database.
+#[derive(Debug, Serialize)]
+struct CheckReport {
+    /// The scope of the scan.
+    check: String,
+    /// The height of the latest block.
+    latest_height: u32,
+    /// The inconsistency at the lowest height, if any.
+    inconsistency: Option<CheckInconsistency>,
+}
+
+/// An inconsistency found by the integrity scan.
+#[derive(Debug, Serialize)]
+struct CheckInconsistency {
+    /// The height of the inconsistent block.
+    height: u32,
+    /// The class of the inconsistency.
+    kind: String,
+    /// The description of the inconsistency.
+    reason: String,
+}
+
+impl Db {
+    /// Runs the command on the database, and classifies its failure, if any.
+    pub fn parse(self) -> Result<String> {
+        self.run::<CurrentNetwork>().map_err(|error| match error.downcast::<DbError>() {
+            Ok(error) => error.into(),
+            Err(error) => DbError::Failed(error.to_string()).into(),
+        })
+    }
+
+    /// Runs the command on the database.
+    fn run<N: Network>(self) -> Result<String> {
+        // Ensure the database exists, as it is created if it is missing.
+        let path = self.storage.clone().unwrap_or_else(|| storage_dir(N::ID, self.dev));
+        if !path.join("CURRENT").exists() {
+            return Err(DbError::Missing(path).into());
+        }
+        // Ensure the index exists, before the database is opened.
+        if let DbCommand::RebuildIndex { name } = &self.command {
+            if !INDEX_NAMES.contains(&name.as_str()) {
+                let error = format!("Unknown index '{name}' [options: {}]", INDEX_NAMES.join(", "));
+                return Err(DbError::InvalidArgument(error).into());
+            }
+        }
+
+        // Set the storage directory, if one is specified.
+        if let Some(storage) = self.storage.clone() {
+            set_storage_dir(storage)?;
+        }
+        // Open the database, which fails if a live node holds its lock.
+        let mut database = match RocksDB::open(N::ID, self.dev) {
+            Ok(database) => database,
+            Err(error) if is_lock_error(&error) => return Err(DbError::Locked(path).into()),
+            Err(error) => return Err(error),
+        };
+
+        match self.command {
+            DbCommand::Info { max_entries } => Self::info::<N>(&database, max_entries),
+            DbCommand::Check { ordering: true, repair, .. } => Self::check_ordering::<N>(self.dev, repair),
+            DbCommand::Check { depth, deep, .. } => {
+                let check = match (depth, deep) {
+                    (_, true) => ConsistencyCheck::Deep,
+                    (Some(depth), false) => ConsistencyCheck::Latest(depth),
+                    (None, false) => ConsistencyCheck::Latest(DEFAULT_CONSISTENCY_CHECK_DEPTH),
+                };
+                Self::check::<N>(self.dev, check)
+            }
+            DbCommand::Rollback { to_height } => Self::rollback::<N>(self.dev, to_height),
+            DbCommand::RebuildIndex { name } => Self::rebuild_index::<N>(&mut database, &name),
+            DbCommand::Compact => Self::compact(&database),
+        }
+    }
+
+    /// Returns the schema version, the tip, and the statistics of each column of the given database.
+    fn info<N: Network>(database: &RocksDB, max_entries: usize) -> Result<String> {
+        // Retrieve the schema version, and the latest version supported by this binary.
+        let version = SchemaDB::open(database).version()?;
+        let latest_version = migrations::<N>()?.latest_version();
+        // Retrieve the tip of the canonical chain.
+        let id_map = database.map::<u32, N::BlockHash>(MapID::Block(BlockMap::ID));
+        let tip = match id_map.keys().max() {
+            Some(height) => match id_map.get(&*height)? {
+                Some(hash) => format!("{} ('{}')", *height, *hash),
+                None => format!("{} (missing hash)", *height),
+            },
+            None => "none".to_string(),
+        };
+
+        // Scan the columns.
+        eprintln!("Scanning the columns of the database...");
+        let statistics = database.statistics(max_entries)?;
+
+        let mut output = format!("Schema version: {version} (supported up to {latest_version})\nTip: {tip}\n");
+        output += &format!("\n{:<36} {:>14} {:>16} {:>16}\n", "Column", "Entries", "Key bytes", "Value bytes");
+        for column in &statistics.columns {
+            // Mark the columns whose scan stopped at the entry limit, as their totals are lower bounds.
+            let marker = if column.is_truncated { "+" } else { "" };
+            output += &format!(
+                "{:<36} {:>14} {:>16} {:>16}\n",
+                column.name,
+                format!("{}{marker}", column.num_entries),
+                column.key_bytes,
+                column.value_bytes
+            );
+        }
+        if let Some(sst_files_bytes) = statistics.sst_files_bytes {
+            output += &format!("\nSST files: {sst_files_bytes} bytes");
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    /// Checks the integrity of the blocks in the given scope, and returns the report in JSON.
+    /// If an inconsistency is found, the report is printed, and the failure is returned.
+    fn check<N: Network>(dev: Option<u16>, check: ConsistencyCheck) -> Result<String> {
+        let ledger = Self::load_ledger::<N>(dev)?;
+
+        eprintln!("Checking the integrity of the ledger ({check:?})...");
+        let inconsistency = ledger.check_consistency(check)?;
+        let report = CheckReport {
+            check: format!("{check:?}"),
+            latest_height: ledger.latest_height(),
+            inconsistency: inconsistency.as_ref().map(|inconsistency| CheckInconsistency {
+                height: inconsistency.height,
+                kind: format!("{:?}", inconsistency.kind),
+                reason: inconsistency.reason.clone(),
+            }),
+        };
+        let report = serde_json::to_string_pretty(&report)?;
+        match inconsistency {
+            Some(inconsistency) => {
+                println!("{report}");
+                Err(DbError::Inconsistent(inconsistency.to_string()).into())
+            }
+            None => Ok(report),
+        }
+    }
+
+    /// Audits the order of the stored transactions of every block, and returns the report in JSON.
+    /// If a block is out of order, and is not repaired, the report is printed, and the failure is returned.
+    fn check_ordering<N: Network>(dev: Option<u16>, repair: bool) -> Result<String> {
+        let audit = OrderingAudit::<N>::open(dev)?;
+
+        eprintln!("Auditing the transaction order of the blocks...");
+        let report = audit.audit(repair, |mismatch| match &mismatch.permutation {
+            Some(permutation) => eprintln!("Block {} is out of order (fix: {permutation:?})", mismatch.height),
+            None => eprintln!("Block {} is out of order (no matching order found)", mismatch.height),
+        })?;
+        let unrepaired = report.mismatches.iter().filter(|mismatch| !mismatch.repaired).count();
+        let output = serde_json::to_string_pretty(&report)?;
+        match unrepaired {
+            0 => Ok(output),
+            _ => {
+                println!("{output}");
+                let error = format!("{unrepaired} blocks have their transactions stored out of order");
+                Err(DbError::Inconsistent(error).into())
+            }
+        }
+    }
+
+    /// Removes the blocks above the given height, through the decommit of a reorganization,
+    /// so that the removed transactions are returned to the memory pool.
+    fn rollback<N: Network>(dev: Option<u16>, to_height: u32) -> Result<String> {
+        let ledger = Self::load_ledger::<N>(dev)?;
+
+        // Ensure there are blocks to remove, and the genesis block is kept.
+        let latest_height = ledger.latest_height();
+        if to_height >= latest_height {
+            let error = format!("Cannot roll back to block {to_height}, the latest block is {latest_height}");
+            return Err(DbError::InvalidArgument(error).into());
+        }
+        // Ensure the blocks to remove are not pruned, as they are loaded in full.
+        let pruned_height = ledger.pruned_height();
+        if to_height < pruned_height {
+            let error = format!("Cannot roll back to block {to_height}, the blocks below {pruned_height} are pruned");
+            return Err(DbError::InvalidArgument(error).into());
+        }
+
+        let consensus = Consensus::new(ledger.clone(), dev.is_some())?;
+        eprintln!("Rolling back {} blocks from block {latest_height}...", latest_height - to_height);
+        let blocks = consensus.decommit_blocks(latest_height - to_height)?;
+        Ok(format!("✅ Rolled back {} blocks to block {to_height} ('{}')", blocks.len(), ledger.latest_hash()))
+    }
+
+    /// Rebuilds the index with the given name from the blocks of the canonical chain.
+    /// The blocks that are already indexed are skipped, so the missing entries are restored.
+    fn rebuild_index<N: Network>(database: &mut RocksDB, name: &str) -> Result<String> {
+        let migration: Box<dyn Migration> = match name {
+            "journal" => Box::new(JournalBackfill::<N>::default()),
+            "circuit" => Box::new(CircuitIndexBackfill::<N>::default()),
+            "timestamp" => Box::new(TimestampIndexBackfill::<N>::default()),
+            "history" => Box::new(HistoryIndexBackfill::<N>::default()),
+            "difficulty" => Box::new(DifficultyIndexBackfill::<N>::default()),
+            "chain-mmr" => Box::new(ChainMmrBackfill::<N>::default()),
+            "sequence" => Box::new(SequenceIndexBackfill::<N>::default()),
+            _ => return Err(DbError::InvalidArgument(format!("Unknown index '{name}'")).into()),
+        };
+
+        // Clear the chunks marked as completed by an interrupted migration, so that every block is visited.
+        let schema = SchemaDB::open(database);
+        schema.clear_chunks(migration.version())?;
+
+        eprintln!("Rebuilding the {name} index...");
+        let mut report_progress = |progress: MigrationProgress| {
+            eprintln!(
+                "Applied {} of {} chunks ({} of {} blocks)",
+                progress.num_chunks_done, progress.num_chunks, progress.num_migrated, progress.num_total
+            );
+            Ok(())
+        };
+        migration.apply(database, None, &mut report_progress)?;
+        schema.clear_chunks(migration.version())?;
+        Ok(format!("✅ Rebuilt the {name} index"))
+    }
+
+    /// Compacts every column of the given database.
+    fn compact(database: &RocksDB) -> Result<String> {
+        let columns = column_names();
+        for (index, column) in columns.iter().enumerate() {
+            eprintln!("Compacting column {} of {} ({column})...", index + 1, columns.len());
+            database.compact_column(column)?;
+        }
+        Ok(format!("✅ Compacted {} columns", columns.len()))
+    }
+
+    /// Loads the ledger from the opened database, without the consistency check, and with the pruned height.
+    pub(super) fn load_ledger<N: Network>(dev: Option<u16>) -> Result<Ledger<N, ConsensusDB<N>>> {
+        // Retrieve the genesis block, which is the stored genesis block in development mode, as it is sampled.
+        let genesis = match dev {
+            Some(_) => {
+                let store = ConsensusStore::<N, ConsensusDB<N>>::open(dev)?;
+                let hash =
+                    store.block_store().get_block_hash(0)?.ok_or_else(|| anyhow!("Missing the genesis block"))?;
+                store.block_store().get_block(&hash)?.ok_or_else(|| anyhow!("Missing the genesis block"))?
+            }
+            None => Block::from_bytes_le(N::genesis_bytes())?,
+        };
+        let pruned_height = BlockPruner::<N>::open(dev)?.pruned_height()?;
+        Ledger::load_pruned(genesis, dev, ConsistencyCheck::Skip, pruned_height)
+    }
+}
+
+/// Returns `true` if the given error of the opening of the database is caused by its lock being held.
+pub(super) fn is_lock_error(error: &anyhow::Error) -> bool {
+    let error = error.to_string();
+    error.contains("While lock file") || error.contains("lock hold by current process")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, CLI};
+
+    #[test]
+    fn test_db_exit_codes() {
+        let errors = [
+            DbError::Failed(String::new()),
+            DbError::InvalidArgument(String::new()),
+            DbError::Missing(PathBuf::new()),
+            DbError::Locked(PathBuf::new()),
+            DbError::Inconsistent(String::new()),
+        ];
+        // Ensure every failure class has a distinct exit code, which is not the code of a success.
+        let mut codes = errors.iter().map(DbError::exit_code).collect::<Vec<_>>();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0));
+    }
+
+    #[test]
+    fn test_db_parse() {
+        let cli = CLI::try_parse_from(["snarkos", "db", "rollback", "--to-height", "5", "--dev", "1"]).unwrap();
+        match cli.command {
+            Command::Db(db) => {
+                assert_eq!(db.dev, Some(1));
+                assert!(matches!(db.command, DbCommand::Rollback { to_height: 5 }));
+            }
+            command => panic!("Unexpected command {command:?}"),
+        }
+        // Ensure the depth and the deep check are exclusive.
+        assert!(CLI::try_parse_from(["snarkos", "db", "check", "--depth", "5", "--deep"]).is_err());
+        // Ensure the repair only applies to the ordering audit.
+        assert!(CLI::try_parse_from(["snarkos", "db", "check", "--ordering", "--repair"]).is_ok());
+        assert!(CLI::try_parse_from(["snarkos", "db", "check", "--repair"]).is_err());
+        assert!(CLI::try_parse_from(["snarkos", "db", "check", "--ordering", "--deep"]).is_err());
+        assert!(CLI::try_parse_from(["snarkos", "db", "rollback"]).is_err());
+    }
+
+    #[test]
+    fn test_db_missing_database() {
+        let path = std::env::temp_dir().join("snarkos-db-missing");
+        let db = Db { storage: Some(path), dev: None, command: DbCommand::Compact };
+        let error = db.parse().unwrap_err();
+        assert_eq!(error.downcast_ref::<DbError>().map(DbError::exit_code), Some(3));
+    }
+
+    #[test]
+    fn test_db_lock_error() {
+        let error = anyhow!("IO error: While lock file: /tmp/ledger/LOCK: Resource temporarily unavailable");
+        assert!(is_lock_error(&error));
+        assert!(!is_lock_error(&anyhow!("IO error: No such file or directory")));
+    }
+}
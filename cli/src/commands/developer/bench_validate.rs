@@ -0,0 +1,260 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{CurrentNetwork, VerifyChain};
+
+use snarkos_node_consensus::{BenchOptions, BenchReport, Consensus};
+use snarkos_node_ledger::Ledger;
+use snarkos_node_store::{rocksdb::TuningProfile, ConsensusDB};
+use snarkvm::prelude::{Block, ConsensusMemory, ConsensusStore, FromBytes, Network, PrivateKey, ToBytes, VM};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+/// The seed of the development genesis block, as in `snarkos start --dev`.
+const DEVELOPMENT_SEED: u64 = 1234567890;
+
+/// Benchmarks the validation and commit pipeline on a fixture chain, or a generated chain, against a temporary
+/// database, and prints the measurements in JSON.
+#[derive(Debug, Parser)]
+pub struct BenchValidate {
+    /// The path to a fixture chain, as a file of binary blocks from its genesis block [default: a generated chain]
+    #[clap(long)]
+    fixture: Option<PathBuf>,
+    /// The number of blocks of the generated chain
+    #[clap(default_value = "50", long, conflicts_with = "fixture")]
+    blocks: u32,
+    /// The maximum number of transactions in each block of the generated chain
+    #[clap(default_value = "2", long, conflicts_with = "fixture")]
+    transactions_per_block: usize,
+    /// The path to save the generated chain to, as a fixture for the next runs
+    #[clap(long, conflicts_with = "fixture")]
+    save_fixture: Option<PathBuf>,
+    /// Disables the verification of the proofs ahead of each block, which fills the cache of the verified transactions
+    #[clap(long)]
+    no_verification_cache: bool,
+    /// Verifies the proofs on a single thread
+    #[clap(long)]
+    no_parallel: bool,
+    /// Enables the bulk-sync options of the storage, which disable the automatic compactions
+    #[clap(long)]
+    bulk_ingest: bool,
+    /// Specify the tuning profile of the storage [options: default, archival, low-memory, bulk-sync] [default: default]
+    #[clap(long)]
+    storage_profile: Option<TuningProfile>,
+    /// The path to the temporary database, which is removed after the run [default: a directory in the temp dir]
+    #[clap(long)]
+    storage: Option<PathBuf>,
+}
+
+/// The measurements of a benchmark run, in JSON.
+#[derive(Debug, Serialize)]
+struct BenchValidateReport {
+    /// The source of the chain, which is `generated`, or the path to the fixture.
+    source: String,
+    /// Whether the proofs were verified ahead of each block.
+    verification_cache: bool,
+    /// Whether the proofs were verified in parallel.
+    parallel: bool,
+    /// Whether the bulk-sync options of the storage were enabled.
+    bulk_ingest: bool,
+    /// The tuning profile of the storage.
+    storage_profile: String,
+    /// The number of blocks that were validated and committed.
+    num_blocks: u32,
+    /// The number of transactions in the blocks.
+    num_transactions: u64,
+    /// The number of threads that verified the proofs.
+    num_threads: usize,
+    /// The time elapsed, in seconds.
+    elapsed_secs: f64,
+    /// The number of blocks validated and committed per second.
+    blocks_per_second: f64,
+    /// The number of transactions validated and committed per second.
+    transactions_per_second: f64,
+    /// The share of the elapsed time spent verifying proofs.
+    proof_verification_share: f64,
+    /// The share of the elapsed time spent committing the blocks to storage.
+    storage_share: f64,
+    /// The peak resident set size of the process, in bytes, if it is known.
+    peak_rss_bytes: Option<u64>,
+}
+
+impl BenchValidate {
+    pub fn parse(self) -> Result<String> {
+        // Prepare the temporary database, which must not hold a ledger already.
+        let storage = self
+            .storage
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join(format!("snarkos-bench-validate-{}", std::process::id())));
+        if storage.exists() {
+            bail!("The benchmark database '{}' already exists", storage.display());
+        }
+        snarkos_node_store::rocksdb::set_storage_dir(storage.clone())?;
+        if let Some(profile) = self.storage_profile {
+            snarkos_node_store::rocksdb::set_tuning_profile(profile)?;
+        }
+
+        // Run the benchmark, and remove the temporary database.
+        let result = self.run::<CurrentNetwork>();
+        if let Err(error) = std::fs::remove_dir_all(&storage) {
+            eprintln!("Failed to remove the benchmark database '{}' - {error}", storage.display());
+        }
+        let report = result?;
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+
+    /// Loads or generates the chain, replays it through the validation and commit pipeline, and returns the report.
+    fn run<N: Network>(&self) -> Result<BenchValidateReport> {
+        // Load the fixture chain, or generate a chain on the development genesis block.
+        let (source, genesis, blocks) = match &self.fixture {
+            Some(path) => {
+                let genesis = Self::fixture_genesis::<N>(path)?;
+                let blocks = VerifyChain::file_blocks::<N>(path, u32::MAX)?.collect::<Result<Vec<_>>>()?;
+                (path.display().to_string(), genesis, blocks)
+            }
+            None => {
+                eprintln!("Generating a chain of {} blocks...", self.blocks);
+                let (genesis, blocks) = self.generate_chain::<N>()?;
+                ("generated".to_string(), genesis, blocks)
+            }
+        };
+
+        // Initialize the ledger in the temporary database, and enable the bulk-sync options, if requested.
+        let ledger = Ledger::<N, ConsensusDB<N>>::load(genesis, None)?;
+        let consensus = Consensus::new(ledger, self.fixture.is_none())?;
+        if self.bulk_ingest {
+            snarkos_node_store::rocksdb::set_bulk_sync(true)?;
+        }
+
+        // Replay the chain.
+        eprintln!("Replaying {} blocks through the validation pipeline...", blocks.len());
+        let options = BenchOptions { verification_cache: !self.no_verification_cache, parallel: !self.no_parallel };
+        let report = snarkos_node_consensus::bench_validation(&consensus, blocks.into_iter().map(Ok), options)?;
+        Ok(self.report(source, &report))
+    }
+
+    /// Returns the development genesis block, and a chain of blocks on top of it.
+    fn generate_chain<N: Network>(&self) -> Result<(Block<N>, Vec<Block<N>>)> {
+        // Initialize the development genesis block, as `snarkos start --dev` does.
+        let mut rng = ChaChaRng::seed_from_u64(DEVELOPMENT_SEED);
+        let private_key = PrivateKey::<N>::new(&mut rng)?;
+        let vm = VM::from(ConsensusStore::<N, ConsensusMemory<N>>::open(None)?)?;
+        let genesis = Block::genesis(&vm, &private_key, &mut rng)?;
+
+        // Generate the chain in an in-memory ledger.
+        let ledger = Ledger::<N, ConsensusMemory<N>>::load(genesis.clone(), None)?;
+        let consensus = Consensus::new(ledger, true)?;
+        let blocks = snarkos_node_consensus::generate_chain(
+            &consensus,
+            &private_key,
+            self.blocks,
+            self.transactions_per_block,
+            &mut rng,
+        )?;
+
+        // Save the chain as a fixture, if requested.
+        if let Some(path) = &self.save_fixture {
+            let mut writer = BufWriter::new(File::create(path)?);
+            for block in std::iter::once(&genesis).chain(&blocks) {
+                block.write_le(&mut writer)?;
+            }
+        }
+        Ok((genesis, blocks))
+    }
+
+    /// Returns the genesis block of the given fixture, which is its first block if it is at height 0,
+    /// or the genesis block of the network otherwise.
+    fn fixture_genesis<N: Network>(path: &PathBuf) -> Result<Block<N>> {
+        let block = Block::<N>::read_le(&mut BufReader::new(File::open(path)?))?;
+        match block.height() {
+            0 => Ok(block),
+            _ => Ok(Block::from_bytes_le(N::genesis_bytes())?),
+        }
+    }
+
+    /// Returns the report of the given measurements, in the options of this run.
+    fn report(&self, source: String, report: &BenchReport) -> BenchValidateReport {
+        BenchValidateReport {
+            source,
+            verification_cache: !self.no_verification_cache,
+            parallel: !self.no_parallel,
+            bulk_ingest: self.bulk_ingest,
+            storage_profile: self.storage_profile.unwrap_or_default().to_string(),
+            num_blocks: report.num_blocks,
+            num_transactions: report.num_transactions,
+            num_threads: report.num_threads,
+            elapsed_secs: report.elapsed.as_secs_f64(),
+            blocks_per_second: report.blocks_per_second(),
+            transactions_per_second: report.transactions_per_second(),
+            proof_verification_share: report.proof_verification_share(),
+            storage_share: report.storage_share(),
+            peak_rss_bytes: peak_rss_bytes(),
+        }
+    }
+}
+
+/// Returns the peak resident set size of the process, in bytes, on Linux.
+fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse::<u64>().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, Developer, CLI};
+
+    #[test]
+    fn test_bench_validate_smoke() {
+        let storage = std::env::temp_dir().join(format!("snarkos-bench-validate-smoke-{}", std::process::id()));
+        let cli = CLI::try_parse_from(["snarkos", "developer", "bench-validate", "--blocks", "50"]).unwrap();
+        let mut bench = match cli.command {
+            Command::Developer(Developer::BenchValidate(bench)) => bench,
+            command => panic!("Unexpected command {command:?}"),
+        };
+        bench.storage = Some(storage.clone());
+
+        // Run the benchmark on a 50-block chain, and ensure every block was replayed.
+        let report: serde_json::Value = serde_json::from_str(&bench.parse().unwrap()).unwrap();
+        assert_eq!(report["source"], "generated");
+        assert_eq!(report["num_blocks"], 50);
+        assert!(report["num_transactions"].as_u64().unwrap() >= 50);
+        assert!(report["blocks_per_second"].as_f64().unwrap() > 0.0);
+        assert!((0.0..=1.0).contains(&report["proof_verification_share"].as_f64().unwrap()));
+        assert!((0.0..=1.0).contains(&report["storage_share"].as_f64().unwrap()));
+        // Ensure the temporary database was removed.
+        assert!(!storage.exists());
+    }
+
+    #[test]
+    fn test_bench_validate_parse() {
+        let command = ["snarkos", "developer", "bench-validate"];
+        assert!(CLI::try_parse_from(command.iter().chain(&["--no-parallel", "--bulk-ingest"])).is_ok());
+        // Ensure a fixture excludes the options of a generated chain.
+        assert!(CLI::try_parse_from(command.iter().chain(&["--fixture", "a", "--blocks", "5"])).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::CurrentNetwork;
+
+use snarkos_node_store::rocksdb::RocksDB;
+use snarkvm::prelude::Network;
+
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+
+/// Dumps the raw entries of a column of an offline database, with hex-encoded keys and values.
+#[derive(Debug, Parser)]
+pub struct DbDump {
+    /// The path to the database, which is opened in read-only mode.
+    #[clap(long)]
+    storage: PathBuf,
+    /// The name of the column, such as `OutputRecordMap`.
+    #[clap(long)]
+    column: String,
+    /// The hex-encoded key from which to start, inclusive.
+    #[clap(long)]
+    start: Option<String>,
+    /// The hex-encoded key at which to stop, exclusive.
+    #[clap(long)]
+    end: Option<String>,
+    /// The maximum number of entries to dump, at most 1000.
+    #[clap(default_value = "100", long)]
+    limit: usize,
+}
+
+impl DbDump {
+    pub fn parse(self) -> Result<String> {
+        // Open the database in read-only mode.
+        let database = RocksDB::open_read_only(self.storage, CurrentNetwork::ID)?;
+        // Dump the entries of the column.
+        let dump = database.dump_column(&self.column, self.start.as_deref(), self.end.as_deref(), self.limit)?;
+        Ok(serde_json::to_string_pretty(&dump)?)
+    }
+}
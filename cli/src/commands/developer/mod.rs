@@ -14,6 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+mod bench_validate;
+pub use bench_validate::*;
+
+mod db_dump;
+pub use db_dump::*;
+
 mod decrypt;
 pub use decrypt::*;
 
@@ -23,12 +29,18 @@ pub use deploy::*;
 mod execute;
 pub use execute::*;
 
+mod replay_block;
+pub use replay_block::*;
+
 mod scan;
 pub use scan::*;
 
 mod transfer;
 pub use transfer::*;
 
+mod verify_chain;
+pub use verify_chain::*;
+
 use snarkvm::{
     file::{AleoFile, Manifest},
     package::Package,
@@ -45,26 +57,38 @@ type CurrentNetwork = snarkvm::prelude::Testnet3;
 /// Commands to manage Aleo accounts.
 #[derive(Debug, Parser)]
 pub enum Developer {
+    /// Benchmark the validation and commit pipeline on a fixture chain or a generated chain.
+    BenchValidate(BenchValidate),
+    /// Dump the raw entries of a column of an offline database.
+    DbDump(DbDump),
     /// Decrypt a ciphertext.
     Decrypt(Decrypt),
     /// Deploy a program.
     Deploy(Deploy),
     /// Execute a program function.
     Execute(Execute),
+    /// Replay a captured block against a storage snapshot.
+    ReplayBlock(ReplayBlock),
     /// Scan the node for records.
     Scan(Scan),
     /// Transfer credits.
     Transfer(Transfer),
+    /// Verify a chain served by a peer or stored in a file, without storing it.
+    VerifyChain(VerifyChain),
 }
 
 impl Developer {
     pub fn parse(self) -> Result<String> {
         match self {
+            Self::BenchValidate(bench_validate) => bench_validate.parse(),
+            Self::DbDump(db_dump) => db_dump.parse(),
             Self::Decrypt(decrypt) => decrypt.parse(),
             Self::Deploy(deploy) => deploy.parse(),
             Self::Execute(execute) => execute.parse(),
+            Self::ReplayBlock(replay_block) => replay_block.parse(),
             Self::Scan(scan) => scan.parse(),
             Self::Transfer(transfer) => transfer.parse(),
+            Self::VerifyChain(verify_chain) => verify_chain.parse(),
         }
     }
 
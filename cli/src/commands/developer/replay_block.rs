@@ -0,0 +1,86 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::CurrentNetwork;
+
+use snarkos_node::DecodeMode;
+use snarkos_node_consensus::Consensus;
+use snarkos_node_ledger::Ledger;
+use snarkos_node_store::ConsensusDB;
+use snarkvm::prelude::{Block, FromBytes, Network};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use std::{path::PathBuf, str::FromStr};
+
+/// Replays a captured block against a storage snapshot, reporting the outcome of every check.
+#[derive(Debug, Parser)]
+pub struct ReplayBlock {
+    /// The path to the block file, in JSON, hex, or binary format.
+    #[clap(long)]
+    block: PathBuf,
+    /// The path to the storage snapshot to replay the block against.
+    #[clap(long)]
+    storage: PathBuf,
+    /// Specify the verbosity of the replay [options: 0, 1, 2]
+    #[clap(default_value = "1", long = "verbosity")]
+    verbosity: u8,
+    /// Ignores any bytes following a hex or binary block, as in the block files written by earlier versions
+    #[clap(long)]
+    lenient: bool,
+}
+
+impl ReplayBlock {
+    pub fn parse(self) -> Result<String> {
+        // Initialize the logger, to trace each check.
+        crate::helpers::initialize_logger(self.verbosity, true, std::env::temp_dir().join("snarkos-replay.log"), None);
+
+        // Load the block.
+        let mode = match self.lenient {
+            true => DecodeMode::Lenient,
+            false => DecodeMode::Strict,
+        };
+        let block = Self::load_block::<CurrentNetwork>(&self.block, mode)?;
+
+        // Open the ledger from the storage snapshot.
+        snarkos_node_store::rocksdb::set_storage_dir(self.storage)?;
+        let genesis = Block::from_bytes_le(CurrentNetwork::genesis_bytes())?;
+        let ledger = Ledger::<CurrentNetwork, ConsensusDB<CurrentNetwork>>::load_unchecked(genesis, None)?;
+        let consensus = Consensus::new(ledger, false)?;
+
+        // Replay the block.
+        let report = snarkos_node_consensus::replay_block(&consensus, &block);
+        match report.is_valid() {
+            true => Ok(report.to_string()),
+            false => bail!("{report}"),
+        }
+    }
+
+    /// Loads the block from the given file, in JSON, hex, or binary format, decoding the bytes in the given mode.
+    fn load_block<N: Network>(path: &PathBuf, mode: DecodeMode) -> Result<Block<N>> {
+        let bytes = std::fs::read(path)?;
+        match std::str::from_utf8(&bytes).map(str::trim) {
+            // Parse the block as JSON.
+            Ok(string) if string.starts_with('{') => Ok(Block::from_str(string)?),
+            // Parse the block as hex.
+            Ok(string) if !string.is_empty() && string.chars().all(|c| c.is_ascii_hexdigit()) => {
+                mode.decode(&hex::decode(string)?)
+            }
+            // Parse the block as binary.
+            _ => mode.decode(&bytes),
+        }
+    }
+}
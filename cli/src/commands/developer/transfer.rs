@@ -15,9 +15,9 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use super::{CurrentNetwork, Developer};
+use snarkos_account::AccountAddress;
 
 use snarkvm::prelude::{
-    Address,
     ConsensusMemory,
     ConsensusStore,
     Locator,
@@ -42,7 +42,7 @@ pub struct Transfer {
     input_record: Record<CurrentNetwork, Plaintext<CurrentNetwork>>,
     /// The recipient address.
     #[clap(parse(try_from_str), long)]
-    recipient: Address<CurrentNetwork>,
+    recipient: AccountAddress<CurrentNetwork>,
     /// The number of gates to transfer.
     #[clap(parse(try_from_str), long)]
     amount: u64,
@@ -0,0 +1,163 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::CurrentNetwork;
+
+use snarkos_node_consensus::Consensus;
+use snarkos_node_ledger::Ledger;
+use snarkvm::prelude::{Block, ConsensusMemory, FromBytes, Network};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// The maximum number of blocks requested from a peer per call.
+const MAX_BLOCK_RANGE: u32 = 50;
+
+/// Verifies a chain served by a peer, or stored in a file, through the full validation pipeline,
+/// in an ephemeral in-memory ledger.
+#[derive(Debug, Parser)]
+pub struct VerifyChain {
+    /// The REST endpoint of the peer (e.g. `http://127.0.0.1:3033`), or the path to a file of binary blocks
+    #[clap(long)]
+    from: String,
+    /// The height of the last block to verify [default: the latest height of the peer, or the last block of the file]
+    #[clap(long)]
+    end: Option<u32>,
+    /// The number of blocks between progress reports
+    #[clap(default_value = "100", long)]
+    progress_interval: u32,
+    /// Specify the verbosity of the verification [options: 0, 1, 2]
+    #[clap(default_value = "1", long = "verbosity")]
+    verbosity: u8,
+}
+
+impl VerifyChain {
+    pub fn parse(self) -> Result<String> {
+        // Initialize the logger.
+        crate::helpers::initialize_logger(self.verbosity, true, std::env::temp_dir().join("snarkos-verify.log"), None);
+
+        // Initialize an ephemeral ledger from the genesis block.
+        let genesis = Block::from_bytes_le(CurrentNetwork::genesis_bytes())?;
+        let ledger = Ledger::<CurrentNetwork, ConsensusMemory<CurrentNetwork>>::load(genesis, None)?;
+        let consensus = Consensus::new(ledger, false)?;
+
+        // Prepare the blocks after the genesis block, from the peer or the file.
+        let end = self.end.unwrap_or(u32::MAX);
+        let blocks: Box<dyn Iterator<Item = Result<Block<CurrentNetwork>>>> =
+            match self.from.starts_with("http://") || self.from.starts_with("https://") {
+                true => Box::new(PeerBlocks::new(self.from.trim_end_matches('/'), end)?),
+                false => Box::new(Self::file_blocks(&PathBuf::from_str(&self.from)?, end)?),
+            };
+
+        // Verify the chain, reporting the progress at the given interval.
+        let interval = self.progress_interval.max(1);
+        let report = snarkos_node_consensus::verify_chain(&consensus, blocks, |progress| {
+            if progress.num_blocks % interval == 0 {
+                println!(
+                    "Verified {} blocks ({} transactions) up to block {} in {} s",
+                    progress.num_blocks,
+                    progress.num_transactions,
+                    progress.height,
+                    progress.elapsed.as_secs()
+                );
+            }
+        })?;
+        match report.is_valid() {
+            true => Ok(report.to_string()),
+            false => bail!("{report}"),
+        }
+    }
+
+    /// Returns the blocks after the genesis block in the given file of binary blocks, up to the given height.
+    pub(super) fn file_blocks<N: Network>(path: &PathBuf, end: u32) -> Result<impl Iterator<Item = Result<Block<N>>>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let blocks = std::iter::from_fn(move || match reader.fill_buf() {
+            // Stop at the end of the file.
+            Ok([]) => None,
+            Ok(_) => Some(Block::<N>::read_le(&mut reader).map_err(Into::into)),
+            Err(error) => Some(Err(error.into())),
+        });
+        // Skip the genesis block, which the ledger is initialized with.
+        Ok(blocks
+            .skip_while(|block| block.as_ref().is_ok_and(|block| block.height() == 0))
+            .take_while(move |block| block.as_ref().map_or(true, |block| block.height() <= end)))
+    }
+}
+
+/// The blocks after the genesis block served by a peer, which are requested in batches as they are verified.
+struct PeerBlocks<N: Network> {
+    /// The REST endpoint of the peer.
+    endpoint: String,
+    /// The height of the next block to request.
+    next_height: u32,
+    /// The height of the last block to request.
+    end_height: u32,
+    /// The blocks that were requested, and are not yet verified.
+    blocks: VecDeque<Block<N>>,
+}
+
+impl<N: Network> PeerBlocks<N> {
+    /// Initializes the blocks served by the given peer, up to the given height or its latest height.
+    fn new(endpoint: &str, end_height: u32) -> Result<Self> {
+        // Request the latest block height from the peer.
+        let latest_height =
+            u32::from_str(&ureq::get(&format!("{endpoint}/testnet3/latest/height")).call()?.into_string()?)?;
+        Ok(Self {
+            endpoint: endpoint.to_string(),
+            next_height: 1,
+            end_height: end_height.min(latest_height),
+            blocks: Default::default(),
+        })
+    }
+
+    /// Requests the next batch of blocks from the peer.
+    fn request_blocks(&mut self) -> Result<()> {
+        let start = self.next_height;
+        let end = start.saturating_add(MAX_BLOCK_RANGE).min(self.end_height.saturating_add(1));
+        let blocks: Vec<Block<N>> =
+            ureq::get(&format!("{}/testnet3/blocks?start={start}&end={end}", self.endpoint)).call()?.into_json()?;
+        // Ensure the peer served the requested blocks.
+        if blocks.len() != (end - start) as usize {
+            bail!("The peer served {} blocks, instead of the blocks {start} to {end}", blocks.len());
+        }
+        self.blocks.extend(blocks);
+        self.next_height = end;
+        Ok(())
+    }
+}
+
+impl<N: Network> Iterator for PeerBlocks<N> {
+    type Item = Result<Block<N>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // If the requested blocks are verified, request the next batch.
+        if self.blocks.is_empty() && self.next_height <= self.end_height {
+            if let Err(error) = self.request_blocks() {
+                // Stop requesting blocks after the error.
+                self.next_height = self.end_height.saturating_add(1);
+                return Some(Err(error));
+            }
+        }
+        self.blocks.pop_front().map(Ok)
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{db::is_lock_error, Db, DbError};
+
+use snarkos_node_cdn::{export_chain_metrics, ChainMetricsExport, ChainMetricsFormat, LedgerMetricsSource};
+use snarkos_node_store::{
+    rocksdb::{set_storage_dir, storage_dir, Database, RocksDB},
+    ChainJournal,
+    DifficultyIndex,
+};
+use snarkvm::prelude::Network;
+
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+
+type CurrentNetwork = snarkvm::prelude::Testnet3;
+
+/// Exports the metrics of the canonical blocks of a stopped node, one row per block, as CSV or Parquet.
+#[derive(Debug, Parser)]
+pub struct ExportMetrics {
+    /// The path to the database [default: the ledger directory of the node]
+    #[clap(long)]
+    storage: Option<PathBuf>,
+    /// Enables development mode, specify the unique ID of the local node whose database to open
+    #[clap(long)]
+    dev: Option<u16>,
+    /// The path to the exported file, which is replaced once it is fully written
+    #[clap(long)]
+    output: PathBuf,
+    /// The file format of the export [options: csv, parquet (requires the `parquet` feature)]
+    #[clap(default_value = "csv", long)]
+    format: ChainMetricsFormat,
+    /// The height of the first exported block, unless the blocks are appended to the previous export
+    #[clap(default_value = "0", long)]
+    from: u32,
+    /// The height of the last exported block [default: the latest block]
+    #[clap(long)]
+    to: Option<u32>,
+    /// Only appends the blocks above the cursor of the previous export, if it is still canonical
+    #[clap(long)]
+    append: bool,
+    /// The path to the cursor of the export [default: the output path with a `.cursor` suffix]
+    #[clap(long)]
+    cursor: Option<PathBuf>,
+}
+
+impl ExportMetrics {
+    /// Runs the export on the database, and classifies its failure, if any.
+    pub fn parse(self) -> Result<String> {
+        self.run::<CurrentNetwork>().map_err(|error| match error.downcast::<DbError>() {
+            Ok(error) => error.into(),
+            Err(error) => DbError::Failed(error.to_string()).into(),
+        })
+    }
+
+    /// Runs the export on the database, and returns its summary in JSON.
+    fn run<N: Network>(self) -> Result<String> {
+        // Ensure the database exists.
+        let path = self.storage.clone().unwrap_or_else(|| storage_dir(N::ID, self.dev));
+        if !path.join("CURRENT").exists() {
+            return Err(DbError::Missing(path).into());
+        }
+
+        // Set the storage directory, if one is specified.
+        if let Some(storage) = self.storage.clone() {
+            set_storage_dir(storage)?;
+        }
+        // Open the database, which fails if a live node holds its lock.
+        if let Err(error) = RocksDB::open(N::ID, self.dev) {
+            return match is_lock_error(&error) {
+                true => Err(DbError::Locked(path).into()),
+                false => Err(error),
+            };
+        }
+        let ledger = Db::load_ledger::<N>(self.dev)?;
+        let difficulty = DifficultyIndex::<N>::open(self.dev)?;
+        let reorgs = ChainJournal::<N>::open(self.dev)?.disconnected_heights(0..ledger.latest_height() + 1)?;
+
+        let request = ChainMetricsExport {
+            output: self.output,
+            format: self.format,
+            from: self.from,
+            to: self.to,
+            append: self.append,
+            cursor: self.cursor,
+        };
+        eprintln!("Exporting the chain metrics to '{}'...", request.output.display());
+        let source = LedgerMetricsSource::new(
+            &ledger,
+            |height| Ok(difficulty.get_entry(height)?.map(|entry| entry.difficulty)),
+            reorgs,
+        );
+        let summary = export_chain_metrics(&source, &request)?;
+        Ok(serde_json::to_string_pretty(&summary)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, CLI};
+
+    #[test]
+    fn test_export_metrics_parse() {
+        let cli = CLI::try_parse_from([
+            "snarkos",
+            "export-metrics",
+            "--output",
+            "metrics.parquet",
+            "--format",
+            "parquet",
+            "--from",
+            "10",
+            "--append",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::ExportMetrics(command) => {
+                assert_eq!(command.output, PathBuf::from("metrics.parquet"));
+                assert_eq!(command.format, ChainMetricsFormat::Parquet);
+                assert_eq!((command.from, command.to, command.append), (10, None, true));
+            }
+            command => panic!("Unexpected command {command:?}"),
+        }
+        // Ensure the export requires its output, and a known format.
+        assert!(CLI::try_parse_from(["snarkos", "export-metrics"]).is_err());
+        assert!(CLI::try_parse_from(["snarkos", "export-metrics", "--output", "out", "--format", "json"]).is_err());
+    }
+
+    #[test]
+    fn test_export_metrics_missing_database() {
+        let path = std::env::temp_dir().join("snarkos-export-metrics-missing");
+        let command = ExportMetrics {
+            storage: Some(path.clone()),
+            dev: None,
+            output: path.join("metrics.csv"),
+            format: ChainMetricsFormat::Csv,
+            from: 0,
+            to: None,
+            append: false,
+            cursor: None,
+        };
+        let error = command.parse().unwrap_err();
+        assert_eq!(error.downcast_ref::<DbError>().map(DbError::exit_code), Some(3));
+    }
+}
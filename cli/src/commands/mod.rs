@@ -20,9 +20,18 @@ pub use account::*;
 mod clean;
 pub use clean::*;
 
+mod db;
+pub use db::*;
+
 mod developer;
 pub use developer::*;
 
+mod export_metrics;
+pub use export_metrics::*;
+
+mod snapshot;
+pub use snapshot::*;
+
 mod start;
 pub use start::*;
 
@@ -49,8 +58,14 @@ pub enum Command {
     Account(Account),
     #[clap(name = "clean")]
     Clean(Clean),
+    #[clap(name = "db")]
+    Db(Db),
     #[clap(subcommand)]
     Developer(Developer),
+    #[clap(name = "export-metrics")]
+    ExportMetrics(ExportMetrics),
+    #[clap(name = "snapshot")]
+    Snapshot(Snapshot),
     #[clap(name = "start")]
     Start(Box<Start>),
     #[clap(name = "update")]
@@ -63,7 +78,10 @@ impl Command {
         match self {
             Self::Account(command) => command.parse(),
             Self::Clean(command) => command.parse(),
+            Self::Db(command) => command.parse(),
             Self::Developer(command) => command.parse(),
+            Self::ExportMetrics(command) => command.parse(),
+            Self::Snapshot(command) => command.parse(),
             Self::Start(command) => command.parse(),
             Self::Update(command) => command.parse(),
         }
@@ -0,0 +1,217 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::{db::is_lock_error, Db, DbError};
+
+use snarkos_node_cdn::{export_canon_since, import_snapshot, SnapshotFileManifest};
+use snarkos_node_consensus::Consensus;
+use snarkos_node_ledger::Ledger;
+use snarkos_node_store::{
+    rocksdb::{set_storage_dir, storage_dir, Database, RocksDB},
+    ChainMmr,
+    ConsensusDB,
+};
+use snarkvm::prelude::{Block, Field, Network};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+type CurrentNetwork = snarkvm::prelude::Testnet3;
+
+/// Exports and imports the canonical blocks of a stopped node, as a base snapshot and its increments.
+#[derive(Debug, Parser)]
+pub struct Snapshot {
+    /// The path to the database [default: the ledger directory of the node]
+    #[clap(global = true, long)]
+    storage: Option<PathBuf>,
+    /// Enables development mode, specify the unique ID of the local node whose database to open
+    #[clap(global = true, long)]
+    dev: Option<u16>,
+    /// Specify a subcommand.
+    #[clap(subcommand)]
+    command: SnapshotCommand,
+}
+
+/// The commands on the snapshots of a stopped node.
+#[derive(Debug, Parser)]
+pub enum SnapshotCommand {
+    /// Export the canonical blocks to a snapshot directory, and print its manifest.
+    Export {
+        /// The snapshot directory, in which the chunks already written are kept
+        #[clap(long)]
+        output: PathBuf,
+        /// The manifest (or directory) of the previous snapshot, to only export the blocks above its tip
+        #[clap(long)]
+        since: Option<PathBuf>,
+    },
+    /// Import a base snapshot and its increments, in order, on top of the latest block.
+    Import {
+        /// The snapshot directories, starting from the base snapshot
+        #[clap(required = true)]
+        snapshots: Vec<PathBuf>,
+    },
+}
+
+impl Snapshot {
+    /// Runs the command on the database, and classifies its failure, if any.
+    pub fn parse(self) -> Result<String> {
+        self.run::<CurrentNetwork>().map_err(|error| match error.downcast::<DbError>() {
+            Ok(error) => error.into(),
+            Err(error) => DbError::Failed(error.to_string()).into(),
+        })
+    }
+
+    /// Runs the command on the database.
+    fn run<N: Network>(self) -> Result<String> {
+        // Ensure the database exists, unless the blocks are imported into a new database of the network.
+        let path = self.storage.clone().unwrap_or_else(|| storage_dir(N::ID, self.dev));
+        let is_import = matches!(self.command, SnapshotCommand::Import { .. });
+        if !path.join("CURRENT").exists() && (!is_import || self.dev.is_some()) {
+            return Err(DbError::Missing(path).into());
+        }
+
+        // Set the storage directory, if one is specified.
+        if let Some(storage) = self.storage.clone() {
+            set_storage_dir(storage)?;
+        }
+        // Open the database, which fails if a live node holds its lock.
+        if let Err(error) = RocksDB::open(N::ID, self.dev) {
+            return match is_lock_error(&error) {
+                true => Err(DbError::Locked(path).into()),
+                false => Err(error),
+            };
+        }
+        let ledger = Db::load_ledger::<N>(self.dev)?;
+        let mmr = ChainMmr::<N>::open(self.dev)?;
+
+        match self.command {
+            SnapshotCommand::Export { output, since } => Self::export(&ledger, &mmr, &output, since.as_deref()),
+            SnapshotCommand::Import { snapshots } => Self::import(&ledger, &mmr, self.dev, &snapshots),
+        }
+    }
+
+    /// Exports the canonical blocks above the tip of the given previous snapshot, if any, to the given directory,
+    /// and returns the manifest in JSON.
+    fn export<N: Network>(
+        ledger: &Ledger<N, ConsensusDB<N>>,
+        mmr: &ChainMmr<N>,
+        output: &Path,
+        since: Option<&Path>,
+    ) -> Result<String> {
+        let since = match since {
+            Some(path) => Some(SnapshotFileManifest::<N>::load(path).map_err(|error| {
+                DbError::InvalidArgument(format!("Invalid previous snapshot '{}' - {error}", path.display()))
+            })?),
+            None => None,
+        };
+
+        eprintln!(
+            "Exporting the blocks above block {} to '{}'...",
+            since.as_ref().map_or(0, |since| since.tip.height),
+            output.display()
+        );
+        let manifest = export_canon_since(ledger, since.as_ref(), Self::chain_root(ledger, mmr)?, output)?;
+        Ok(serde_json::to_string_pretty(&manifest)?)
+    }
+
+    /// Imports the given snapshots in order, checking and committing each block as the sync does.
+    fn import<N: Network>(
+        ledger: &Ledger<N, ConsensusDB<N>>,
+        mmr: &ChainMmr<N>,
+        dev: Option<u16>,
+        snapshots: &[PathBuf],
+    ) -> Result<String> {
+        let consensus = Consensus::new(ledger.clone(), dev.is_some())?;
+        for directory in snapshots {
+            eprintln!("Importing the snapshot '{}' from block {}...", directory.display(), ledger.latest_height());
+            let chain_root = || Self::chain_root(ledger, mmr);
+            let process = |block: Block<N>| {
+                consensus.check_next_block(&block)?;
+                consensus.advance_to_next_block(&block)
+            };
+            import_snapshot(ledger, directory, chain_root, process)
+                .map_err(|error| anyhow!("Failed to import the snapshot '{}' - {error}", directory.display()))?;
+        }
+        Ok(format!(
+            "✅ Imported {} snapshots up to block {} ('{}')",
+            snapshots.len(),
+            ledger.latest_height(),
+            ledger.latest_hash()
+        ))
+    }
+
+    /// Returns the root of the chain commitment, which must cover the blocks of the given ledger.
+    fn chain_root<N: Network>(ledger: &Ledger<N, ConsensusDB<N>>, mmr: &ChainMmr<N>) -> Result<Field<N>> {
+        let commitment = mmr.commitment()?;
+        match commitment.size == ledger.latest_height() + 1 {
+            true => Ok(commitment.root),
+            false => Err(DbError::Inconsistent(format!(
+                "The chain commitment covers {} blocks, instead of {} (rebuild the chain-mmr index)",
+                commitment.size,
+                ledger.latest_height() + 1
+            ))
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, CLI};
+
+    #[test]
+    fn test_snapshot_parse() {
+        let cli = CLI::try_parse_from(["snarkos", "snapshot", "export", "--output", "inc", "--since", "base"]).unwrap();
+        match cli.command {
+            Command::Snapshot(snapshot) => match snapshot.command {
+                SnapshotCommand::Export { output, since } => {
+                    assert_eq!(output, PathBuf::from("inc"));
+                    assert_eq!(since, Some(PathBuf::from("base")));
+                }
+                command => panic!("Unexpected command {command:?}"),
+            },
+            command => panic!("Unexpected command {command:?}"),
+        }
+        // Ensure the snapshots are imported in the given order.
+        let cli = CLI::try_parse_from(["snarkos", "snapshot", "import", "base", "inc1", "inc2", "--dev", "1"]).unwrap();
+        match cli.command {
+            Command::Snapshot(snapshot) => {
+                assert_eq!(snapshot.dev, Some(1));
+                match snapshot.command {
+                    SnapshotCommand::Import { snapshots } => {
+                        assert_eq!(snapshots, ["base", "inc1", "inc2"].map(PathBuf::from))
+                    }
+                    command => panic!("Unexpected command {command:?}"),
+                }
+            }
+            command => panic!("Unexpected command {command:?}"),
+        }
+        // Ensure an export requires its output, and an import requires a snapshot.
+        assert!(CLI::try_parse_from(["snarkos", "snapshot", "export"]).is_err());
+        assert!(CLI::try_parse_from(["snarkos", "snapshot", "import"]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_missing_database() {
+        let path = std::env::temp_dir().join("snarkos-snapshot-missing");
+        let command = SnapshotCommand::Export { output: path.join("snapshot"), since: None };
+        let snapshot = Snapshot { storage: Some(path), dev: None, command };
+        let error = snapshot.parse().unwrap_err();
+        assert_eq!(error.downcast_ref::<DbError>().map(DbError::exit_code), Some(3));
+    }
+}
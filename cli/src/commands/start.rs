@@ -14,20 +14,54 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::helpers::{LogDirectory, NodeConfig, RotationPolicy};
 use snarkos_account::Account;
 use snarkos_display::Display;
-use snarkos_node::{Node, NodeType};
+use snarkos_node::{
+    Compression,
+    DiffusionConfig,
+    Node,
+    NodeRole,
+    NodeType,
+    OnionAddr,
+    Privacy,
+    ProxyConfig,
+    StoragePolicy,
+};
+use snarkos_node_consensus::{ExpiryPolicy, ReplacementPolicy, DEFAULT_EXPIRY_GRACE};
+use snarkos_node_ledger::ConsistencyCheck;
+use snarkos_node_rest::ListenerConfig;
+use snarkos_node_store::rocksdb::TuningProfile;
 use snarkvm::prelude::{Block, ConsensusMemory, ConsensusStore, FromBytes, Network, PrivateKey, Testnet3, VM};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
 use colored::Colorize;
-use core::str::FromStr;
+use core::{str::FromStr, time::Duration};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
-use std::{net::SocketAddr, path::PathBuf};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 use tokio::runtime::{self, Runtime};
 
+/// The default IP address and port for the node server.
+const DEFAULT_NODE_IP: &str = "0.0.0.0:4133";
+/// The default IP address and port for the REST server.
+const DEFAULT_REST_IP: &str = "0.0.0.0:3033";
+/// The default verbosity of the node.
+const DEFAULT_VERBOSITY: u8 = 2;
+/// The default maximum size (in bytes) of a structured log file.
+const DEFAULT_LOG_MAX_SIZE: u64 = 100 * 1024 * 1024;
+/// The default maximum age (in seconds) of a structured log file.
+const DEFAULT_LOG_MAX_AGE: u64 = 24 * 60 * 60;
+/// The default maximum number of structured log files that are retained.
+const DEFAULT_LOG_MAX_FILES: usize = 10;
+/// The default CDN to prefetch initial blocks from.
+const DEFAULT_CDN: &str = "https://testnet3.blocks.aleo.org/phase3";
+
 /// The recommended minimum number of 'open files' limit for a beacon.
 /// Beacons should be able to handle at least 1000 concurrent connections, each requiring 2 sockets.
 #[cfg(target_family = "unix")]
@@ -44,6 +78,10 @@ pub struct Start {
     #[clap(default_value = "3", long = "network")]
     pub network: u16,
 
+    /// Specify the path to a configuration file (TOML), whose settings are overridden by the flags
+    #[clap(long = "config")]
+    pub config: Option<PathBuf>,
+
     /// Specify this node as a beacon, with the account private key as an argument
     #[clap(long = "beacon")]
     pub beacon: Option<String>,
@@ -57,33 +95,188 @@ pub struct Start {
     #[clap(long = "client")]
     pub client: Option<String>,
 
-    /// Specify the IP address and port for the node server
-    #[clap(default_value = "0.0.0.0:4133", long = "node")]
-    pub node: SocketAddr,
-    /// Specify the IP address and port of a peer to connect to
-    #[clap(default_value = "", long = "connect")]
-    pub connect: String,
+    /// Specify the IP address and port for the node server [default: 0.0.0.0:4133]
+    #[clap(long = "node")]
+    pub node: Option<SocketAddr>,
+    /// Specify the role of the node on the network [options: full, outbound-only, client] [default: full]
+    #[clap(long = "role")]
+    pub role: Option<NodeRole>,
+    /// Specify the compression of the large messages sent to the peers that support it [options: none, zstd, zstd:{level}] [default: none]
+    #[clap(long = "compression")]
+    pub compression: Option<String>,
+    /// Specify the IP address and port of a peer to connect to, optionally pinning its public key as `ip:port@key`
+    #[clap(long = "connect")]
+    pub connect: Option<String>,
+    /// Specify the hostnames of the DNS seeds to discover peers from, separated by commas, or an empty string to disable them
+    #[clap(long = "dns-seeds")]
+    pub dns_seeds: Option<String>,
+    /// Specify the IP address and port of a SOCKS5 proxy (such as Tor), through which the outbound connections are made
+    #[clap(long = "proxy")]
+    pub proxy: Option<SocketAddr>,
+    /// Specify the username and password of the proxy, as `username:password`
+    #[clap(long = "proxy-auth")]
+    pub proxy_auth: Option<String>,
+    /// If the flag is set, the node only connects through the proxy, and does not listen unless it has an onion service
+    #[clap(long = "proxy-only")]
+    pub proxy_only: bool,
+    /// Specify the onion address of the node, whose onion service forwards to the node server on a loopback address
+    #[clap(long = "onion-service")]
+    pub onion_service: Option<String>,
+    /// If the flag is set, the node will accept peers without transport encryption (transition mode)
+    #[clap(long = "allow-unencrypted-peers")]
+    pub allow_unencrypted_peers: bool,
+    /// Specify the path to the identity file (the static keypair) of the node, in place of the one in the data directory
+    #[clap(long = "identity")]
+    pub identity: Option<PathBuf>,
+    /// If the flag is set, the identity of the node is replaced with a new one, and the previous one is archived
+    #[clap(long = "rotate-identity", conflicts_with = "identity")]
+    pub rotate_identity: bool,
+    /// Specify the IP address and port of a trusted peer, pinned in `--connect`, to bootstrap an empty ledger from its snapshot
+    #[clap(long = "snapshot-from")]
+    pub snapshot_from: Option<SocketAddr>,
+    /// Specify the maximum number of snapshot streams served to trusted peers, or `0` to disable them [default: 2]
+    #[clap(long = "max-snapshot-streams")]
+    pub max_snapshot_streams: Option<usize>,
+    /// Specify whether local transactions are diffused through a few peers before they are broadcast [options: on, off] [default: on]
+    #[clap(long = "privacy")]
+    pub privacy: Option<Privacy>,
+    /// Specify the number of peers that first receive a local transaction [default: 2]
+    #[clap(long = "diffusion-first-hops")]
+    pub diffusion_first_hops: Option<usize>,
+    /// Specify the mean delay (in milliseconds) before a local transaction is sent to each of the first peers [default: 250]
+    #[clap(long = "diffusion-delay-ms")]
+    pub diffusion_delay_ms: Option<u64>,
+    /// Specify the minimum embargo (in milliseconds) before a local transaction is broadcast [default: 5000]
+    #[clap(long = "diffusion-min-embargo-ms")]
+    pub diffusion_min_embargo_ms: Option<u64>,
+    /// Specify the maximum embargo (in milliseconds) before a local transaction is broadcast [default: 15000]
+    #[clap(long = "diffusion-max-embargo-ms")]
+    pub diffusion_max_embargo_ms: Option<u64>,
 
-    /// Specify the IP address and port for the REST server
-    #[clap(default_value = "0.0.0.0:3033", long = "rest")]
-    pub rest: SocketAddr,
+    /// Specify the IP address and port for the REST server [default: 0.0.0.0:3033]
+    #[clap(long = "rest")]
+    pub rest: Option<SocketAddr>,
     /// If the flag is set, the node will not initialize the REST server
     #[clap(long)]
     pub norest: bool,
+    /// Specify the byte budget of the REST response cache [default: 67108864]
+    #[clap(long = "rest-cache-size")]
+    pub rest_cache_size: Option<usize>,
+    /// Specify the time-to-live (in seconds) of the entries in the REST response cache [default: 600]
+    #[clap(long = "rest-cache-ttl")]
+    pub rest_cache_ttl: Option<u64>,
+    /// Specify an additional REST listener (repeatable), as `<ip:port>` followed by the comma-separated options
+    /// `classes=read+write+admin`, `auth`, `websocket`, and `tls=<cert>:<key>` [default classes: read]
+    #[clap(long = "rest-listener")]
+    pub rest_listeners: Vec<String>,
+    /// If the flag is set, a REST listener may serve the admin methods on a public address without authentication
+    #[clap(long = "rest-allow-public-admin")]
+    pub rest_allow_public_admin: bool,
 
     /// If the flag is set, the node will not render the display
     #[clap(long)]
     pub nodisplay: bool,
-    /// Specify the verbosity of the node [options: 0, 1, 2, 3, 4]
-    #[clap(default_value = "2", long = "verbosity")]
-    pub verbosity: u8,
-    /// Specify the path to the file where logs will be stored
-    #[clap(default_value_os_t = std::env::temp_dir().join("snarkos.log"), long = "logfile")]
-    pub logfile: PathBuf,
-
-    /// Enables the node to prefetch initial blocks from a CDN
-    #[clap(default_value = "https://testnet3.blocks.aleo.org/phase3", long = "cdn")]
-    pub cdn: String,
+    /// Specify the verbosity of the node [options: 0, 1, 2, 3, 4] [default: 2]
+    #[clap(long = "verbosity")]
+    pub verbosity: Option<u8>,
+    /// Specify the path to the file where logs will be stored [default: <temp dir>/snarkos.log]
+    #[clap(long = "logfile")]
+    pub logfile: Option<PathBuf>,
+    /// Specify a directory to write structured (JSON) logs to, with rotation, along with the critical events
+    #[clap(long = "logdir")]
+    pub logdir: Option<PathBuf>,
+    /// Specify the maximum size (in bytes) of a structured log file, beyond which it is rotated [default: 104857600]
+    #[clap(long = "log-max-size")]
+    pub log_max_size: Option<u64>,
+    /// Specify the maximum age (in seconds) of a structured log file, beyond which it is rotated [default: 86400]
+    #[clap(long = "log-max-age")]
+    pub log_max_age: Option<u64>,
+    /// Specify the maximum number of structured log files that are retained [default: 10]
+    #[clap(long = "log-max-files")]
+    pub log_max_files: Option<usize>,
+
+    /// Specify a directory to dump rejected blocks into, for replay with `snarkos developer replay-block`
+    #[clap(long = "dump-rejected-blocks")]
+    pub dump_rejected_blocks: Option<PathBuf>,
+
+    /// Enables replacing conflicting transactions in the memory pool, if the fee of the replacement exceeds
+    /// the combined fees of the evicted transactions by the given increment (in microcredits)
+    #[clap(long = "replace-by-fee")]
+    pub replace_by_fee: Option<u64>,
+
+    /// Enables expiring transactions from the memory pool, if their oldest global state root is older than
+    /// the given admission window (in blocks) plus the expiry grace
+    #[clap(long = "expiry-window")]
+    pub expiry_window: Option<u32>,
+
+    /// Specify the number of blocks past the admission window after which a transaction expires
+    #[clap(long = "expiry-grace")]
+    pub expiry_grace: Option<u32>,
+
+    /// Specify the programs that the transactions must deploy or execute to be admitted into the memory pool,
+    /// separated by commas (a local policy, which does not apply to the blocks of other nodes)
+    #[clap(long = "program-allowlist")]
+    pub program_allowlist: Option<String>,
+
+    /// Specify the increase in the fees of the memory pool (in microcredits) that makes a block template stale
+    #[clap(long = "template-fee-delta")]
+    pub template_fee_delta: Option<u64>,
+
+    /// Specify the byte budget of the blocks queued for verification and commit while syncing
+    #[clap(long = "sync-byte-budget")]
+    pub sync_byte_budget: Option<usize>,
+
+    /// Specify the throughput (in bytes/s) below which a sync peer is demoted, or 0 to never demote [default: 1024]
+    #[clap(long = "sync-throughput-floor")]
+    pub sync_throughput_floor: Option<u64>,
+
+    /// Specify the multiple of the block time without a new block after which the tip is stale, or 0 to disable
+    #[clap(long = "stale-tip-multiple")]
+    pub stale_tip_multiple: Option<u32>,
+
+    /// Specify the number of recent canonical blocks kept in memory for the shallow reorganizations [default: 16]
+    #[clap(long = "recent-blocks")]
+    pub recent_blocks: Option<usize>,
+
+    /// Specify the byte budget of the recent canonical blocks kept in memory [default: 64 MiB]
+    #[clap(long = "recent-blocks-byte-budget")]
+    pub recent_blocks_byte_budget: Option<usize>,
+
+    /// Skips the consistency check of the latest blocks in the ledger on startup
+    #[clap(long = "skip-consistency-check", conflicts_with = "deep-check")]
+    pub skip_consistency_check: bool,
+
+    /// Checks the consistency of every block in the ledger on startup, instead of only the latest blocks
+    #[clap(long = "deep-check")]
+    pub deep_check: bool,
+
+    /// Specify the number of events retained in the journal of the changes to the canonical chain
+    #[clap(long = "journal-retention")]
+    pub journal_retention: Option<u64>,
+
+    /// Prunes the bodies of the blocks beyond the given depth, keeping their headers and indexes [minimum: 4096]
+    #[clap(long = "prune-depth")]
+    pub prune_depth: Option<u32>,
+
+    /// Audits a random historical block at the given interval, in seconds, verifying it again to detect corruptions
+    #[clap(long = "audit-interval")]
+    pub audit_interval: Option<u64>,
+
+    /// Specify the tuning profile of the storage [options: default, archival, low-memory, bulk-sync] [default: default]
+    #[clap(long = "storage-profile")]
+    pub storage_profile: Option<TuningProfile>,
+
+    /// Specify the timeout, in milliseconds, of a storage read [default: 2000]
+    #[clap(long = "storage-read-timeout-ms")]
+    pub storage_read_timeout_ms: Option<u64>,
+
+    /// Specify the timeout, in milliseconds, of a storage batch commit [default: 30000]
+    #[clap(long = "storage-commit-timeout-ms")]
+    pub storage_commit_timeout_ms: Option<u64>,
+
+    /// Enables the node to prefetch initial blocks from a CDN [default: https://testnet3.blocks.aleo.org/phase3]
+    #[clap(long = "cdn")]
+    pub cdn: Option<String>,
     /// Enables development mode, specify a unique ID for this node
     #[clap(long)]
     pub dev: Option<u16>,
@@ -91,18 +284,35 @@ pub struct Start {
 
 impl Start {
     /// Starts the snarkOS node.
-    pub fn parse(self) -> Result<String> {
+    pub fn parse(mut self) -> Result<String> {
+        // Load the configuration file, if one is specified, and apply it to the settings without a flag.
+        let (config, unknown_keys) = match &self.config {
+            Some(path) => NodeConfig::load(path)?,
+            None => Default::default(),
+        };
+        self.apply_config(&config);
         // Initialize the logger.
-        let log_receiver = crate::helpers::initialize_logger(self.verbosity, self.nodisplay, self.logfile.clone());
+        let log_receiver = crate::helpers::initialize_logger(
+            self.verbosity(),
+            self.nodisplay,
+            self.logfile(),
+            Some(self.log_directory()),
+        );
+        // Warn about the unknown keys in the configuration file.
+        Self::log_unknown_keys(&unknown_keys);
         // Initialize the runtime.
         Self::runtime().block_on(async move {
             // Clone the configurations.
             let mut cli = self.clone();
+            // Initialize the settings that can change while the node is running.
+            cli.initialize_live_config(&config).expect("Failed to initialize the live configuration");
             // Parse the network.
             match cli.network {
                 3 => {
                     // Parse the node from the configurations.
-                    let node = cli.parse_node::<Testnet3>().await.expect("Failed to parse the node");
+                    let node = cli.parse_node::<Testnet3>(&config).await.expect("Failed to parse the node");
+                    // Start the alert monitor.
+                    crate::helpers::start_alert_monitor(node.clone()).expect("Failed to start the alert monitor");
                     // If the display is enabled, render the display.
                     if !cli.nodisplay {
                         // Initialize the display.
@@ -121,13 +331,181 @@ impl Start {
 }
 
 impl Start {
+    /// Applies the given configuration file to the settings that are not specified by a flag.
+    fn apply_config(&mut self, config: &NodeConfig) {
+        // Apply the network settings.
+        self.node = self.node.or(config.network.node);
+        self.role = self.role.or(config.network.role);
+        self.compression = self.compression.take().or_else(|| config.network.compression.clone());
+        self.connect = self.connect.take().or_else(|| config.network.connect.clone());
+        self.dns_seeds = self.dns_seeds.take().or_else(|| config.network.dns_seeds.clone());
+        self.proxy = self.proxy.or(config.network.proxy);
+        self.proxy_auth = self.proxy_auth.take().or_else(|| config.network.proxy_auth.clone());
+        self.proxy_only |= config.network.proxy_only.unwrap_or_default();
+        self.onion_service = self.onion_service.take().or_else(|| config.network.onion_service.clone());
+        self.allow_unencrypted_peers |= config.network.allow_unencrypted_peers.unwrap_or_default();
+        self.identity = self.identity.take().or_else(|| config.network.identity.clone());
+        self.snapshot_from = self.snapshot_from.or(config.network.snapshot_from);
+        self.max_snapshot_streams = self.max_snapshot_streams.or(config.network.max_snapshot_streams);
+        self.sync_byte_budget = self.sync_byte_budget.or(config.network.sync_byte_budget);
+        self.sync_throughput_floor = self.sync_throughput_floor.or(config.network.sync_throughput_floor);
+        self.stale_tip_multiple = self.stale_tip_multiple.or(config.network.stale_tip_multiple);
+        self.recent_blocks = self.recent_blocks.or(config.network.recent_blocks);
+        self.recent_blocks_byte_budget = self.recent_blocks_byte_budget.or(config.network.recent_blocks_byte_budget);
+        self.cdn = self.cdn.take().or_else(|| config.network.cdn.clone());
+        self.privacy = self.privacy.or(config.network.privacy);
+        self.diffusion_first_hops = self.diffusion_first_hops.or(config.network.diffusion_first_hops);
+        self.diffusion_delay_ms = self.diffusion_delay_ms.or(config.network.diffusion_delay_ms);
+        self.diffusion_min_embargo_ms = self.diffusion_min_embargo_ms.or(config.network.diffusion_min_embargo_ms);
+        self.diffusion_max_embargo_ms = self.diffusion_max_embargo_ms.or(config.network.diffusion_max_embargo_ms);
+        // Apply the REST settings.
+        self.rest = self.rest.or(config.rest.address);
+        self.norest |= config.rest.disabled.unwrap_or_default();
+        self.rest_cache_size = self.rest_cache_size.or(config.rest.cache_size);
+        self.rest_cache_ttl = self.rest_cache_ttl.or(config.rest.cache_ttl);
+        if self.rest_listeners.is_empty() {
+            self.rest_listeners = config.rest.listeners.clone().unwrap_or_default();
+        }
+        self.rest_allow_public_admin |= config.rest.allow_public_admin.unwrap_or_default();
+        // Apply the memory pool and block production settings.
+        self.replace_by_fee = self.replace_by_fee.or(config.mempool.replace_by_fee);
+        self.expiry_window = self.expiry_window.or(config.mempool.expiry_window);
+        self.expiry_grace = self.expiry_grace.or(config.mempool.expiry_grace);
+        self.program_allowlist = self.program_allowlist.take().or_else(|| config.mempool.program_allowlist.clone());
+        self.template_fee_delta = self.template_fee_delta.or(config.mining.template_fee_delta);
+        // Apply the storage settings.
+        self.dump_rejected_blocks =
+            self.dump_rejected_blocks.take().or_else(|| config.storage.dump_rejected_blocks.clone());
+        self.journal_retention = self.journal_retention.or(config.storage.journal_retention);
+        self.prune_depth = self.prune_depth.or(config.storage.prune_depth);
+        self.audit_interval = self.audit_interval.or(config.storage.audit_interval);
+        self.storage_profile = self.storage_profile.or(config.storage.profile);
+        self.storage_read_timeout_ms = self.storage_read_timeout_ms.or(config.storage.read_timeout_ms);
+        self.storage_commit_timeout_ms = self.storage_commit_timeout_ms.or(config.storage.commit_timeout_ms);
+        // Apply the logging settings.
+        self.verbosity = self.verbosity.or(config.logging.verbosity);
+        self.logfile = self.logfile.take().or_else(|| config.logging.logfile.clone());
+        self.logdir = self.logdir.take().or_else(|| config.logging.directory.clone());
+        self.log_max_size = self.log_max_size.or(config.logging.max_size);
+        self.log_max_age = self.log_max_age.or(config.logging.max_age);
+        self.log_max_files = self.log_max_files.or(config.logging.max_files);
+        self.nodisplay |= config.logging.nodisplay.unwrap_or_default();
+    }
+
+    /// Warns about the given unknown keys of the configuration file, which are ignored.
+    fn log_unknown_keys(unknown_keys: &[String]) {
+        for key in unknown_keys {
+            warn!("Ignoring the unknown key '{key}' in the configuration file");
+        }
+    }
+
+    /// Applies the settings of the given configuration file that can change while the node is running,
+    /// and reloads the file on `SIGHUP`, or when requested through the REST API.
+    fn initialize_live_config(&self, config: &NodeConfig) -> Result<()> {
+        // Apply the log filter, whenever the settings change.
+        let mut receiver = snarkos_node::subscribe_to_live_config();
+        tokio::spawn(async move {
+            loop {
+                let log_filter = receiver.borrow_and_update().log_filter.clone();
+                if let Err(error) = crate::helpers::set_log_filter(log_filter.as_deref()) {
+                    warn!("Failed to apply the log filter - {error}");
+                }
+                if receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+        // Set the initial settings, which the node applies as it starts.
+        snarkos_node::set_live_config(config.live_config());
+        crate::helpers::set_alerts_config(config.alerts.clone());
+
+        // If a configuration file is specified, reload it when requested.
+        if self.config.is_some() {
+            // Reload the configuration file when requested through the REST API.
+            let cli = self.clone();
+            snarkos_node_rest::set_reload_handler(Box::new(move || cli.reload_config()))?;
+
+            // Reload the configuration file on `SIGHUP`.
+            #[cfg(target_family = "unix")]
+            {
+                use tokio::signal::unix::{signal, SignalKind};
+
+                let mut hangups = signal(SignalKind::hangup())?;
+                let cli = self.clone();
+                tokio::spawn(async move {
+                    while hangups.recv().await.is_some() {
+                        if let Err(error) = cli.reload_config() {
+                            warn!("{error}");
+                        }
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Reloads the configuration file, and applies the settings that can change while the node is running.
+    /// If the file is invalid, it is rejected as a whole, and the running settings are left unchanged.
+    fn reload_config(&self) -> Result<()> {
+        let path = self.config.as_ref().ok_or_else(|| anyhow!("No configuration file is specified"))?;
+        // Load and check the configuration file, before applying any of its settings.
+        let (config, unknown_keys) =
+            NodeConfig::load(path).map_err(|error| anyhow!("Failed to reload the configuration - {error}"))?;
+        Self::log_unknown_keys(&unknown_keys);
+        // Apply the settings as a whole.
+        snarkos_node::set_live_config(config.live_config());
+        crate::helpers::set_alerts_config(config.alerts);
+        info!("Reloaded the configuration file '{}'", path.display());
+        Ok(())
+    }
+
+    /// Returns the IP address and port for the node server.
+    fn node_ip(&self) -> SocketAddr {
+        self.node.unwrap_or_else(|| SocketAddr::from_str(DEFAULT_NODE_IP).unwrap())
+    }
+
+    /// Returns the IP address and port for the REST server.
+    fn rest_ip(&self) -> SocketAddr {
+        self.rest.unwrap_or_else(|| SocketAddr::from_str(DEFAULT_REST_IP).unwrap())
+    }
+
+    /// Returns the verbosity of the node.
+    fn verbosity(&self) -> u8 {
+        self.verbosity.unwrap_or(DEFAULT_VERBOSITY)
+    }
+
+    /// Returns the path to the file where logs will be stored.
+    fn logfile(&self) -> PathBuf {
+        self.logfile.clone().unwrap_or_else(|| std::env::temp_dir().join("snarkos.log"))
+    }
+
+    /// Returns the directory of the logs, which holds the critical events, and the structured logs if enabled.
+    /// Without a log directory, the critical events are stored next to the logfile.
+    fn log_directory(&self) -> LogDirectory {
+        match &self.logdir {
+            Some(path) => LogDirectory {
+                path: path.clone(),
+                structured: Some(RotationPolicy {
+                    max_size: self.log_max_size.unwrap_or(DEFAULT_LOG_MAX_SIZE),
+                    max_age: Duration::from_secs(self.log_max_age.unwrap_or(DEFAULT_LOG_MAX_AGE)),
+                    max_files: self.log_max_files.unwrap_or(DEFAULT_LOG_MAX_FILES),
+                }),
+            },
+            None => LogDirectory {
+                path: self.logfile().parent().map(Path::to_path_buf).unwrap_or_else(std::env::temp_dir),
+                structured: None,
+            },
+        }
+    }
+
     /// Returns the initial node(s) to connect to, from the given configurations.
     fn parse_trusted_peers(&self) -> Result<Vec<SocketAddr>> {
-        match self.connect.is_empty() {
+        let connect = self.connect.as_deref().unwrap_or_default();
+        match connect.is_empty() {
             true => Ok(vec![]),
-            false => Ok(self
-                .connect
+            false => Ok(connect
                 .split(',')
+                .map(|peer| peer.split('@').next().unwrap_or_default())
                 .flat_map(|ip| match ip.parse::<SocketAddr>() {
                     Ok(ip) => Some(ip),
                     Err(e) => {
@@ -139,6 +517,65 @@ impl Start {
         }
     }
 
+    /// Returns the hostnames of the DNS seeds, if they are specified.
+    fn parse_dns_seeds(&self) -> Option<Vec<String>> {
+        self.dns_seeds
+            .as_deref()
+            .map(|seeds| seeds.split(',').map(str::trim).filter(|seed| !seed.is_empty()).map(str::to_string).collect())
+    }
+
+    /// Returns the IDs of the programs in the allowlist of the memory pool, if one is specified.
+    fn parse_program_allowlist(&self) -> Option<Vec<String>> {
+        self.program_allowlist.as_deref().map(|programs| {
+            programs.split(',').map(str::trim).filter(|program| !program.is_empty()).map(str::to_string).collect()
+        })
+    }
+
+    /// Returns the proxy of the outbound connections, and the onion address of the node, if a proxy is specified.
+    fn parse_proxy(&self) -> Result<Option<(ProxyConfig, Option<OnionAddr>)>> {
+        let address = match self.proxy {
+            Some(address) => address,
+            None if self.proxy_auth.is_some() || self.proxy_only || self.onion_service.is_some() => {
+                bail!("The proxy settings require a proxy, specified with --proxy")
+            }
+            None => return Ok(None),
+        };
+        let auth = match self.proxy_auth.as_deref().map(|auth| auth.split_once(':')) {
+            Some(Some((username, password))) => Some((username.to_string(), password.to_string())),
+            Some(None) => bail!("The authentication supplied to --proxy-auth must be 'username:password'"),
+            None => None,
+        };
+        let onion_service = match &self.onion_service {
+            Some(onion) => Some(OnionAddr::from_str(onion)?),
+            None => None,
+        };
+        Ok(Some((ProxyConfig { address, auth, proxy_only: self.proxy_only }, onion_service)))
+    }
+
+    /// Returns the additional listeners of the REST server, from the given configurations.
+    fn parse_rest_listeners(&self) -> Result<Vec<ListenerConfig>> {
+        self.rest_listeners
+            .iter()
+            .map(|listener| {
+                ListenerConfig::from_str(listener)
+                    .map_err(|e| anyhow!("The listener supplied to --rest-listener ('{listener}') is malformed: {e}"))
+            })
+            .collect()
+    }
+
+    /// Returns the public keys pinned for the initial node(s) to connect to, from the given configurations.
+    fn parse_pinned_keys(&self) -> Result<HashMap<SocketAddr, Vec<u8>>> {
+        let mut pinned_keys = HashMap::new();
+        for peer in self.connect.as_deref().unwrap_or_default().split(',') {
+            if let Some((ip, key)) = peer.split_once('@') {
+                let key = hex::decode(key)
+                    .map_err(|e| anyhow!("The key supplied to --connect ('{key}') is malformed: {e}"))?;
+                pinned_keys.insert(ip.parse()?, key);
+            }
+        }
+        Ok(pinned_keys)
+    }
+
     /// Returns the CDN to prefetch initial blocks from, from the given configurations.
     fn parse_cdn(&self) -> Option<String> {
         // Disable CDN if:
@@ -146,7 +583,7 @@ impl Start {
         //  2. The user has explicitly disabled CDN.
         //  3. The node is a client (no need to sync).
         //  4. The node is a prover (no need to sync).
-        if self.dev.is_some() || self.cdn.is_empty() || self.client.is_some() || self.prover.is_some() {
+        if self.dev.is_some() || self.cdn.as_deref() == Some("") || self.client.is_some() || self.prover.is_some() {
             None
         }
         // Check for an edge case, where the node defaults to a client.
@@ -155,7 +592,29 @@ impl Start {
         }
         // Enable the CDN otherwise.
         else {
-            Some(self.cdn.clone())
+            Some(self.cdn.clone().unwrap_or_else(|| DEFAULT_CDN.to_string()))
+        }
+    }
+
+    /// Returns the settings of the diffusion of the local transactions, with the defaults for those not specified.
+    fn diffusion_config(&self) -> DiffusionConfig {
+        let default = DiffusionConfig::default();
+        DiffusionConfig {
+            privacy: self.privacy.unwrap_or(default.privacy),
+            first_hops: self.diffusion_first_hops.unwrap_or(default.first_hops),
+            mean_delay: self.diffusion_delay_ms.map_or(default.mean_delay, Duration::from_millis),
+            min_embargo: self.diffusion_min_embargo_ms.map_or(default.min_embargo, Duration::from_millis),
+            max_embargo: self.diffusion_max_embargo_ms.map_or(default.max_embargo, Duration::from_millis),
+        }
+    }
+
+    /// Returns the timeouts, retries, and circuit breaker of the storage operations.
+    fn storage_policy(&self) -> StoragePolicy {
+        let default = StoragePolicy::default();
+        StoragePolicy {
+            read_timeout: self.storage_read_timeout_ms.map_or(default.read_timeout, Duration::from_millis),
+            commit_timeout: self.storage_commit_timeout_ms.map_or(default.commit_timeout, Duration::from_millis),
+            ..default
         }
     }
 
@@ -177,10 +636,10 @@ impl Start {
                 trusted_peers.push(SocketAddr::from_str(&format!("127.0.0.1:{}", 4130 + i))?);
             }
             // Set the node IP to `4130 + dev`.
-            self.node = SocketAddr::from_str(&format!("0.0.0.0:{}", 4130 + dev))?;
+            self.node = Some(SocketAddr::from_str(&format!("0.0.0.0:{}", 4130 + dev))?);
             // Set the REST IP to `3030 + dev`.
             if !self.norest {
-                self.rest = SocketAddr::from_str(&format!("0.0.0.0:{}", 3030 + dev))?;
+                self.rest = Some(SocketAddr::from_str(&format!("0.0.0.0:{}", 3030 + dev))?);
             }
 
             // Initialize an (insecure) fixed RNG.
@@ -239,10 +698,19 @@ impl Start {
 
     /// Returns the node type corresponding to the given configurations.
     #[rustfmt::skip]
-    async fn parse_node<N: Network>(&mut self) -> Result<Node<N>> {
+    async fn parse_node<N: Network>(&mut self, config: &NodeConfig) -> Result<Node<N>> {
         // Print the welcome.
         println!("{}", crate::helpers::welcome_message());
 
+        // Set the storage directory, if one is specified.
+        if let Some(path) = &config.storage.path {
+            snarkos_node_store::rocksdb::set_storage_dir(path.clone())?;
+        }
+        // Set the tuning profile of the storage, if one is specified.
+        if let Some(profile) = self.storage_profile {
+            snarkos_node_store::rocksdb::set_tuning_profile(profile)?;
+        }
+
         // Parse the trusted IPs to connect to.
         let mut trusted_peers = self.parse_trusted_peers()?;
 
@@ -252,15 +720,84 @@ impl Start {
         // Parse the development configurations, and determine the genesis block.
         let genesis = self.parse_development::<N>(&mut trusted_peers)?;
 
+        // Parse the node IP.
+        let node_ip = self.node_ip();
+
         // Parse the REST IP.
         let rest_ip = match self.norest {
             true => None,
-            false => Some(self.rest),
+            false => Some(self.rest_ip()),
         };
 
         // Parse the node account and node type.
         let (account, node_type) = self.parse_account::<N>()?;
 
+        // Parse the static public keys pinned for the trusted peers.
+        let pinned_keys = self.parse_pinned_keys()?;
+        // Ensure the snapshot is only downloaded from a peer whose public key is pinned.
+        if let Some(snapshot_from) = self.snapshot_from {
+            if !pinned_keys.contains_key(&snapshot_from) {
+                bail!("The peer supplied to --snapshot-from ('{snapshot_from}') must be pinned in --connect")
+            }
+        }
+
+        // Set the secret of the JSON web tokens, if one is specified.
+        if let Some(secret) = &config.rest.jwt_secret {
+            snarkos_node_rest::set_jwt_secret(secret.as_bytes().to_vec())?;
+        }
+
+        // Create the directory to dump rejected blocks into, if one is specified.
+        if let Some(path) = &self.dump_rejected_blocks {
+            std::fs::create_dir_all(path)?;
+        }
+
+        // Set the consistency check of the ledger on startup.
+        let consistency_check = match (self.skip_consistency_check, self.deep_check) {
+            (true, _) => ConsistencyCheck::Skip,
+            (false, true) => ConsistencyCheck::Deep,
+            (false, false) => ConsistencyCheck::default(),
+        };
+
+        // Initialize the settings of the node, from the specified options and their defaults.
+        let defaults = snarkos_node::NodeConfig::default();
+        let node_config = snarkos_node::NodeConfig {
+            consistency_check,
+            pinned_keys,
+            allow_plaintext_peers: self.allow_unencrypted_peers,
+            identity: self.identity.clone(),
+            rotate_identity: self.rotate_identity,
+            rejected_blocks_dir: self.dump_rejected_blocks.clone(),
+            response_cache_byte_budget: self.rest_cache_size.unwrap_or(defaults.response_cache_byte_budget),
+            response_cache_ttl: self.rest_cache_ttl.map_or(defaults.response_cache_ttl, Duration::from_secs),
+            rest_listeners: self.parse_rest_listeners()?,
+            allow_public_admin: self.rest_allow_public_admin,
+            replacement_policy: self.replace_by_fee.map(ReplacementPolicy::new),
+            expiry_policy: self.expiry_window.map(|window| ExpiryPolicy {
+                window,
+                grace: self.expiry_grace.unwrap_or(DEFAULT_EXPIRY_GRACE),
+            }),
+            program_allowlist: self.parse_program_allowlist(),
+            template_fee_delta: self.template_fee_delta.unwrap_or(defaults.template_fee_delta),
+            sync_byte_budget: self.sync_byte_budget.unwrap_or(defaults.sync_byte_budget),
+            sync_throughput_floor: self.sync_throughput_floor.unwrap_or(defaults.sync_throughput_floor),
+            stale_tip_multiple: self.stale_tip_multiple.unwrap_or(defaults.stale_tip_multiple),
+            prune_depth: self.prune_depth,
+            audit_interval: self.audit_interval.map(Duration::from_secs),
+            role: self.role.unwrap_or_default(),
+            compression: self.compression.as_deref().map(Compression::from_str).transpose()?.unwrap_or_default(),
+            diffusion: self.diffusion_config(),
+            recent_blocks: self.recent_blocks.unwrap_or(defaults.recent_blocks),
+            recent_blocks_byte_budget: self.recent_blocks_byte_budget.unwrap_or(defaults.recent_blocks_byte_budget),
+            storage_policy: self.storage_policy(),
+            dns_seeds: self.parse_dns_seeds(),
+            proxy: self.parse_proxy()?,
+            snapshot_from: self.snapshot_from,
+            max_snapshot_streams: self.max_snapshot_streams.unwrap_or(defaults.max_snapshot_streams),
+            journal_retention: self.journal_retention.unwrap_or(defaults.journal_retention),
+        };
+        // Ensure the settings are valid.
+        node_config.check()?;
+
         // If the display is not enabled, render the welcome message.
         if self.nodisplay {
             // Print the Aleo address.
@@ -270,7 +807,7 @@ impl Start {
                 "🧭 Starting {} on {} at {}.\n",
                 node_type.description().bold(),
                 N::NAME.bold(),
-                self.node.to_string().bold()
+                node_ip.to_string().bold()
             );
 
             // If the node is running a REST server, print the REST IP and JWT.
@@ -298,10 +835,14 @@ impl Start {
 
         // Initialize the node.
         match node_type {
-            NodeType::Beacon => Node::new_beacon(self.node, rest_ip, account, &trusted_peers, genesis, cdn, self.dev).await,
-            NodeType::Validator => Node::new_validator(self.node, rest_ip, account, &trusted_peers, genesis, cdn, self.dev).await,
-            NodeType::Prover => Node::new_prover(self.node, account, &trusted_peers, genesis, self.dev).await,
-            NodeType::Client => Node::new_client(self.node, account, &trusted_peers, genesis, self.dev).await,
+            NodeType::Beacon => {
+                Node::new_beacon(node_ip, rest_ip, account, &trusted_peers, genesis, cdn, self.dev, node_config).await
+            }
+            NodeType::Validator => {
+                Node::new_validator(node_ip, rest_ip, account, &trusted_peers, genesis, cdn, self.dev, node_config).await
+            }
+            NodeType::Prover => Node::new_prover(node_ip, account, &trusted_peers, genesis, self.dev, node_config).await,
+            NodeType::Client => Node::new_client(node_ip, account, &trusted_peers, genesis, self.dev, node_config).await,
         }
     }
 
@@ -338,6 +879,7 @@ impl Start {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use snarkos_node_rest::MethodClass;
     use snarkvm::prelude::Testnet3;
 
     type CurrentNetwork = Testnet3;
@@ -360,6 +902,92 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_parse_dns_seeds() {
+        let config = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        assert_eq!(config.parse_dns_seeds(), None);
+
+        let config = Start::try_parse_from(["snarkos", "--dns-seeds", ""].iter()).unwrap();
+        assert_eq!(config.parse_dns_seeds(), Some(vec![]));
+
+        let config =
+            Start::try_parse_from(["snarkos", "--dns-seeds", "seed.example.com, seed.example.org:4140"].iter())
+                .unwrap();
+        assert_eq!(
+            config.parse_dns_seeds(),
+            Some(vec!["seed.example.com".to_string(), "seed.example.org:4140".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_program_allowlist() {
+        let config = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        assert_eq!(config.parse_program_allowlist(), None);
+
+        let config =
+            Start::try_parse_from(["snarkos", "--program-allowlist", "credits.aleo, token.aleo,"].iter()).unwrap();
+        assert_eq!(config.parse_program_allowlist(), Some(vec!["credits.aleo".to_string(), "token.aleo".to_string()]));
+    }
+
+    #[test]
+    fn test_parse_proxy() {
+        let config = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        assert!(config.parse_proxy().unwrap().is_none());
+        // Ensure the proxy settings are rejected without a proxy.
+        let config = Start::try_parse_from(["snarkos", "--proxy-only"].iter()).unwrap();
+        assert!(config.parse_proxy().is_err());
+
+        let onion = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion:4133";
+        let args = ["snarkos", "--proxy", "127.0.0.1:9050", "--proxy-auth", "user:secret", "--proxy-only"];
+        let config = Start::try_parse_from(args.iter().chain(["--onion-service", onion].iter())).unwrap();
+        let (proxy, onion_service) = config.parse_proxy().unwrap().unwrap();
+        assert_eq!(proxy.address, SocketAddr::from_str("127.0.0.1:9050").unwrap());
+        assert_eq!(proxy.auth, Some(("user".to_string(), "secret".to_string())));
+        assert!(proxy.proxy_only);
+        assert_eq!(onion_service.unwrap().to_string(), onion);
+
+        // Ensure a malformed authentication is rejected.
+        let config =
+            Start::try_parse_from(["snarkos", "--proxy", "127.0.0.1:9050", "--proxy-auth", "user"].iter()).unwrap();
+        assert!(config.parse_proxy().is_err());
+    }
+
+    #[test]
+    fn test_parse_pinned_keys() {
+        let config = Start::try_parse_from(["snarkos", "--connect", "1.2.3.4:5,6.7.8.9:0"].iter()).unwrap();
+        assert!(config.parse_pinned_keys().unwrap().is_empty());
+
+        let config = Start::try_parse_from(["snarkos", "--connect", "1.2.3.4:5@0a0b,6.7.8.9:0"].iter()).unwrap();
+        assert_eq!(config.parse_trusted_peers().unwrap(), vec![
+            SocketAddr::from_str("1.2.3.4:5").unwrap(),
+            SocketAddr::from_str("6.7.8.9:0").unwrap()
+        ]);
+        let pinned_keys = config.parse_pinned_keys().unwrap();
+        assert_eq!(pinned_keys.len(), 1);
+        assert_eq!(pinned_keys[&SocketAddr::from_str("1.2.3.4:5").unwrap()], vec![0x0a, 0x0b]);
+
+        let config = Start::try_parse_from(["snarkos", "--connect", "1.2.3.4:5@xyz"].iter()).unwrap();
+        assert!(config.parse_pinned_keys().is_err());
+    }
+
+    #[test]
+    fn test_parse_rest_listeners() {
+        let config = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        assert!(config.parse_rest_listeners().unwrap().is_empty());
+
+        let (public, private) = ("0.0.0.0:3034", "127.0.0.1:3035,classes=read+admin");
+        let args = ["snarkos", "--rest-listener", public, "--rest-listener", private];
+        let config = Start::try_parse_from(args.iter()).unwrap();
+        let listeners = config.parse_rest_listeners().unwrap();
+        assert_eq!(listeners.len(), 2);
+        assert_eq!(listeners[0].classes, vec![MethodClass::Read]);
+        assert_eq!(listeners[1].classes, vec![MethodClass::Read, MethodClass::Admin]);
+        assert!(!config.rest_allow_public_admin);
+
+        let config = Start::try_parse_from(["snarkos", "--rest-listener", "0.0.0.0:3034,classes=all"].iter()).unwrap();
+        assert!(config.parse_rest_listeners().is_err());
+    }
+
     #[test]
     fn test_parse_cdn() {
         // Beacon (Prod)
@@ -451,6 +1079,105 @@ mod tests {
         assert!(config.parse_cdn().is_none());
     }
 
+    #[test]
+    fn test_apply_config() {
+        let (config, _) = NodeConfig::from_toml(
+            r#"
+            [network]
+            node = "1.2.3.4:4133"
+            connect = "5.6.7.8:4133"
+            dns_seeds = "seed.example.com"
+            privacy = "off"
+            identity = "/etc/snarkos/identity.key"
+
+            [rest]
+            address = "1.2.3.4:3033"
+            cache_ttl = 60
+
+            [mempool]
+            replace_by_fee = 1000
+            expiry_window = 100
+
+            [logging]
+            verbosity = 1
+            "#,
+        )
+        .unwrap();
+
+        // Ensure the defaults apply without a flag or a configuration file.
+        let mut start = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        start.apply_config(&NodeConfig::default());
+        assert_eq!(start.node_ip(), SocketAddr::from_str(DEFAULT_NODE_IP).unwrap());
+        assert_eq!(start.rest_ip(), SocketAddr::from_str(DEFAULT_REST_IP).unwrap());
+        assert_eq!(start.verbosity(), DEFAULT_VERBOSITY);
+        assert!(start.parse_trusted_peers().unwrap().is_empty());
+
+        // Ensure the configuration file applies to the settings without a flag.
+        let mut start = Start::try_parse_from(["snarkos"].iter()).unwrap();
+        start.apply_config(&config);
+        assert_eq!(start.node_ip(), SocketAddr::from_str("1.2.3.4:4133").unwrap());
+        assert_eq!(start.parse_trusted_peers().unwrap(), vec![SocketAddr::from_str("5.6.7.8:4133").unwrap()]);
+        assert_eq!(start.parse_dns_seeds(), Some(vec!["seed.example.com".to_string()]));
+        assert_eq!(start.identity, Some(PathBuf::from("/etc/snarkos/identity.key")));
+        assert_eq!(start.rest_ip(), SocketAddr::from_str("1.2.3.4:3033").unwrap());
+        assert_eq!(start.rest_cache_ttl, Some(60));
+        assert_eq!(start.replace_by_fee, Some(1000));
+        assert_eq!(start.expiry_window, Some(100));
+        assert_eq!(start.expiry_grace, None);
+        assert_eq!(start.verbosity(), 1);
+        assert_eq!(start.diffusion_config(), DiffusionConfig { privacy: Privacy::Off, ..Default::default() });
+        assert_eq!(start.storage_policy(), StoragePolicy::default());
+
+        // Ensure the flags take precedence over the configuration file.
+        let mut start = Start::try_parse_from(
+            ["snarkos", "--node", "9.9.9.9:4133", "--connect", "", "--replace-by-fee", "5", "--verbosity", "3"].iter(),
+        )
+        .unwrap();
+        start.apply_config(&config);
+        assert_eq!(start.node_ip(), SocketAddr::from_str("9.9.9.9:4133").unwrap());
+        assert!(start.parse_trusted_peers().unwrap().is_empty());
+        assert_eq!(start.replace_by_fee, Some(5));
+        assert_eq!(start.verbosity(), 3);
+        // Ensure the settings without a flag are still applied from the configuration file.
+        assert_eq!(start.rest_ip(), SocketAddr::from_str("1.2.3.4:3033").unwrap());
+        assert_eq!(start.rest_cache_ttl, Some(60));
+
+        // Ensure the identity supplied by the operator is not rotated.
+        assert!(Start::try_parse_from(["snarkos", "--identity", "identity.key", "--rotate-identity"].iter()).is_err());
+    }
+
+    #[test]
+    fn test_reload_config() {
+        // Write a configuration file.
+        let path = std::env::temp_dir().join(format!("snarkos-test-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "[mempool]\nbyte_budget = 1024\n").unwrap();
+        let config = Start::try_parse_from(["snarkos", "--config", path.to_str().unwrap()].iter()).unwrap();
+        let receiver = snarkos_node::subscribe_to_live_config();
+
+        // Ensure the settings of the file are applied.
+        config.reload_config().unwrap();
+        assert_eq!(receiver.borrow().mempool_byte_budget, Some(1024));
+
+        // Ensure a changed byte budget takes effect on reload.
+        std::fs::write(&path, "[mempool]\nbyte_budget = 2048\n\n[network]\nmax_peers = 5\n").unwrap();
+        config.reload_config().unwrap();
+        assert_eq!(*receiver.borrow(), snarkos_node::LiveConfig {
+            mempool_byte_budget: Some(2048),
+            max_peers: Some(5),
+            ..Default::default()
+        });
+
+        // Ensure a malformed file is rejected as a whole, leaving the running settings unchanged.
+        std::fs::write(&path, "[mempool]\nbyte_budget = 4096\n\n[network]\nmax_peers = 0\n").unwrap();
+        assert!(config.reload_config().is_err());
+        std::fs::write(&path, "[mempool\nbyte_budget = 4096\n").unwrap();
+        assert!(config.reload_config().is_err());
+        assert_eq!(receiver.borrow().mempool_byte_budget, Some(2048));
+        assert_eq!(receiver.borrow().max_peers, Some(5));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_parse_development() {
         let prod_genesis = Block::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
@@ -470,8 +1197,8 @@ mod tests {
         let mut trusted_peers = vec![];
         let mut config = Start::try_parse_from(["snarkos", "--dev", "0"].iter()).unwrap();
         let expected_genesis = config.parse_development::<CurrentNetwork>(&mut trusted_peers).unwrap();
-        assert_eq!(config.node, SocketAddr::from_str("0.0.0.0:4130").unwrap());
-        assert_eq!(config.rest, SocketAddr::from_str("0.0.0.0:3030").unwrap());
+        assert_eq!(config.node, Some(SocketAddr::from_str("0.0.0.0:4130").unwrap()));
+        assert_eq!(config.rest, Some(SocketAddr::from_str("0.0.0.0:3030").unwrap()));
         assert_eq!(trusted_peers.len(), 0);
         assert!(config.beacon.is_none());
         assert!(config.validator.is_none());
@@ -482,8 +1209,8 @@ mod tests {
         let mut trusted_peers = vec![];
         let mut config = Start::try_parse_from(["snarkos", "--dev", "0", "--beacon", ""].iter()).unwrap();
         let genesis = config.parse_development::<CurrentNetwork>(&mut trusted_peers).unwrap();
-        assert_eq!(config.node, SocketAddr::from_str("0.0.0.0:4130").unwrap());
-        assert_eq!(config.rest, SocketAddr::from_str("0.0.0.0:3030").unwrap());
+        assert_eq!(config.node, Some(SocketAddr::from_str("0.0.0.0:4130").unwrap()));
+        assert_eq!(config.rest, Some(SocketAddr::from_str("0.0.0.0:3030").unwrap()));
         assert_eq!(trusted_peers.len(), 0);
         assert!(config.beacon.is_some());
         assert!(config.validator.is_none());
@@ -494,8 +1221,8 @@ mod tests {
         let mut trusted_peers = vec![];
         let mut config = Start::try_parse_from(["snarkos", "--dev", "1", "--validator", ""].iter()).unwrap();
         let genesis = config.parse_development::<CurrentNetwork>(&mut trusted_peers).unwrap();
-        assert_eq!(config.node, SocketAddr::from_str("0.0.0.0:4131").unwrap());
-        assert_eq!(config.rest, SocketAddr::from_str("0.0.0.0:3031").unwrap());
+        assert_eq!(config.node, Some(SocketAddr::from_str("0.0.0.0:4131").unwrap()));
+        assert_eq!(config.rest, Some(SocketAddr::from_str("0.0.0.0:3031").unwrap()));
         assert_eq!(trusted_peers.len(), 1);
         assert!(config.beacon.is_none());
         assert!(config.validator.is_some());
@@ -506,8 +1233,8 @@ mod tests {
         let mut trusted_peers = vec![];
         let mut config = Start::try_parse_from(["snarkos", "--dev", "2", "--prover", ""].iter()).unwrap();
         let genesis = config.parse_development::<CurrentNetwork>(&mut trusted_peers).unwrap();
-        assert_eq!(config.node, SocketAddr::from_str("0.0.0.0:4132").unwrap());
-        assert_eq!(config.rest, SocketAddr::from_str("0.0.0.0:3032").unwrap());
+        assert_eq!(config.node, Some(SocketAddr::from_str("0.0.0.0:4132").unwrap()));
+        assert_eq!(config.rest, Some(SocketAddr::from_str("0.0.0.0:3032").unwrap()));
         assert_eq!(trusted_peers.len(), 2);
         assert!(config.beacon.is_none());
         assert!(config.validator.is_none());
@@ -518,8 +1245,8 @@ mod tests {
         let mut trusted_peers = vec![];
         let mut config = Start::try_parse_from(["snarkos", "--dev", "3", "--client", ""].iter()).unwrap();
         let genesis = config.parse_development::<CurrentNetwork>(&mut trusted_peers).unwrap();
-        assert_eq!(config.node, SocketAddr::from_str("0.0.0.0:4133").unwrap());
-        assert_eq!(config.rest, SocketAddr::from_str("0.0.0.0:3033").unwrap());
+        assert_eq!(config.node, Some(SocketAddr::from_str("0.0.0.0:4133").unwrap()));
+        assert_eq!(config.rest, Some(SocketAddr::from_str("0.0.0.0:3033").unwrap()));
         assert_eq!(trusted_peers.len(), 3);
         assert!(config.beacon.is_none());
         assert!(config.validator.is_none());
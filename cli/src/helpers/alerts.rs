@@ -0,0 +1,654 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::helpers::{AlertsConfig, CRITICAL_TARGET};
+use snarkos_node::Node;
+use snarkvm::prelude::Network;
+
+use anyhow::{anyhow, bail, Result};
+use core::{fmt, time::Duration};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    process::Command,
+    time::Instant,
+};
+use tokio::sync::{mpsc, watch};
+use tracing::{
+    field::{Field, Visit},
+    Event,
+    Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+/// The default window (in seconds) of an alert rule.
+pub const DEFAULT_ALERT_WINDOW: u64 = 10 * 60;
+/// The interval at which the alert rules are evaluated, to resolve the alerts whose condition cleared.
+const ALERT_EVALUATION_INTERVAL: Duration = Duration::from_secs(5);
+/// The interval at which the number of connected peers is sampled.
+const PEER_COUNT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The sender of the events observed by the alert monitor, once it is running.
+static ALERT_EVENTS: OnceCell<mpsc::UnboundedSender<AlertEvent>> = OnceCell::new();
+/// The alert settings of the configuration file, which are sent to the alert monitor as a whole.
+static ALERTS_CONFIG: Lazy<watch::Sender<AlertsConfig>> = Lazy::new(|| watch::channel(AlertsConfig::default()).0);
+
+/// The severity of an alert.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for AlertSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// The condition of an alert rule.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "condition", rename_all = "kebab-case")]
+pub enum AlertCondition {
+    /// A reorg of more than `threshold` blocks. It clears after the window passes without another one.
+    ReorgDepth { threshold: u32 },
+    /// An inconsistency found in the storage. It clears after the window passes without another one.
+    IntegrityError,
+    /// At least `threshold` rejected blocks within the window. It clears once fewer remain in the window.
+    InvalidBlocks { threshold: usize },
+    /// Fewer than `floor` connected peers. It clears once the number of connected peers reaches the floor.
+    PeerCount { floor: usize },
+}
+
+/// An alert rule of the configuration file, in an `[[alerts.rules]]` table.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AlertRule {
+    /// The name of the rule, which identifies its alerts.
+    pub name: String,
+    /// The condition of the rule.
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+    /// The severity of the alerts of the rule.
+    #[serde(default)]
+    pub severity: AlertSeverity,
+    /// The window (in seconds) within which a firing alert is not dispatched again.
+    #[serde(default = "default_alert_window")]
+    pub window: u64,
+}
+
+/// Returns the default window (in seconds) of an alert rule.
+fn default_alert_window() -> u64 {
+    DEFAULT_ALERT_WINDOW
+}
+
+/// An event observed by the alert monitor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AlertEvent {
+    /// The ledger was reorganized by the given number of blocks.
+    Reorg { depth: u32 },
+    /// An inconsistency was found in the storage.
+    IntegrityError,
+    /// A block was rejected.
+    InvalidBlock,
+    /// The number of connected peers was sampled.
+    PeerCount(usize),
+}
+
+/// The status of an alert.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    /// The condition of the rule holds.
+    Firing,
+    /// The condition of the rule cleared.
+    Resolved,
+}
+
+impl fmt::Display for AlertStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Firing => write!(f, "firing"),
+            Self::Resolved => write!(f, "resolved"),
+        }
+    }
+}
+
+/// An alert to dispatch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Alert {
+    /// The name of the rule.
+    pub rule: String,
+    /// The severity of the rule.
+    pub severity: AlertSeverity,
+    /// The status of the alert.
+    pub status: AlertStatus,
+    /// The description of the alert.
+    pub message: String,
+}
+
+/// The state of an alert rule.
+#[derive(Clone, Debug, Default)]
+struct RuleState {
+    /// Whether the alert of the rule is firing.
+    is_firing: bool,
+    /// The time of the latest event that met the condition.
+    last_triggered: Option<Instant>,
+    /// The times of the events within the window, for the conditions on a rate.
+    occurrences: VecDeque<Instant>,
+}
+
+/// Evaluates the alert rules against the observed events, and returns the alerts to dispatch.
+///
+/// An alert is dispatched once when its condition is met, and not again while it is firing;
+/// a resolution is dispatched once the condition clears.
+pub struct AlertMonitor {
+    /// The alert rules.
+    rules: Vec<AlertRule>,
+    /// The mapping of `rule name` to the state of the rule.
+    states: HashMap<String, RuleState>,
+}
+
+impl AlertMonitor {
+    /// Initializes a new alert monitor with the given rules.
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self { rules, states: Default::default() }
+    }
+
+    /// Replaces the alert rules, keeping the state of the unchanged rules,
+    /// and returns the resolutions of the alerts of the removed or changed rules.
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for rule in &self.rules {
+            if !rules.contains(rule) {
+                if let Some(state) = self.states.remove(&rule.name) {
+                    if state.is_firing {
+                        alerts.push(Self::alert(
+                            rule,
+                            AlertStatus::Resolved,
+                            "The rule was changed or removed".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        self.rules = rules;
+        alerts
+    }
+
+    /// Observes the given event, and returns the alerts to dispatch.
+    pub fn observe(&mut self, event: AlertEvent, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for rule in &self.rules {
+            let state = self.states.entry(rule.name.clone()).or_default();
+            let window = Duration::from_secs(rule.window);
+
+            match (&rule.condition, event) {
+                (AlertCondition::ReorgDepth { threshold }, AlertEvent::Reorg { depth }) if depth > *threshold => {
+                    state.last_triggered = Some(now);
+                    if !state.is_firing {
+                        state.is_firing = true;
+                        let message = format!("Reorganized {depth} blocks (threshold of {threshold})");
+                        alerts.push(Self::alert(rule, AlertStatus::Firing, message));
+                    }
+                }
+                (AlertCondition::IntegrityError, AlertEvent::IntegrityError) => {
+                    state.last_triggered = Some(now);
+                    if !state.is_firing {
+                        state.is_firing = true;
+                        alerts.push(Self::alert(
+                            rule,
+                            AlertStatus::Firing,
+                            "Found an inconsistency in the storage".into(),
+                        ));
+                    }
+                }
+                (AlertCondition::InvalidBlocks { threshold }, AlertEvent::InvalidBlock) => {
+                    state.last_triggered = Some(now);
+                    state.occurrences.push_back(now);
+                    state.occurrences.retain(|time| now.saturating_duration_since(*time) < window);
+                    if !state.is_firing && state.occurrences.len() >= *threshold {
+                        state.is_firing = true;
+                        let message = format!(
+                            "Rejected {} blocks in {}s (threshold of {threshold})",
+                            state.occurrences.len(),
+                            rule.window
+                        );
+                        alerts.push(Self::alert(rule, AlertStatus::Firing, message));
+                    }
+                }
+                (AlertCondition::PeerCount { floor }, AlertEvent::PeerCount(num_peers)) => {
+                    match (state.is_firing, num_peers < *floor) {
+                        (false, true) => {
+                            state.is_firing = true;
+                            let message = format!("Connected to {num_peers} peers (floor of {floor})");
+                            alerts.push(Self::alert(rule, AlertStatus::Firing, message));
+                        }
+                        (true, false) => {
+                            state.is_firing = false;
+                            let message = format!("Connected to {num_peers} peers (floor of {floor})");
+                            alerts.push(Self::alert(rule, AlertStatus::Resolved, message));
+                        }
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+        alerts
+    }
+
+    /// Resolves the alerts whose condition cleared by the given time, and returns them.
+    pub fn evaluate(&mut self, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for rule in &self.rules {
+            let state = match self.states.get_mut(&rule.name) {
+                Some(state) if state.is_firing => state,
+                _ => continue,
+            };
+            let window = Duration::from_secs(rule.window);
+
+            let is_cleared = match &rule.condition {
+                AlertCondition::ReorgDepth { .. } | AlertCondition::IntegrityError => match state.last_triggered {
+                    Some(time) => now.saturating_duration_since(time) >= window,
+                    None => true,
+                },
+                AlertCondition::InvalidBlocks { threshold } => {
+                    state.occurrences.retain(|time| now.saturating_duration_since(*time) < window);
+                    state.occurrences.len() < *threshold
+                }
+                // The number of peers is cleared by the next sample.
+                AlertCondition::PeerCount { .. } => false,
+            };
+            if is_cleared {
+                state.is_firing = false;
+                let message = format!("The condition cleared for {}s", rule.window);
+                alerts.push(Self::alert(rule, AlertStatus::Resolved, message));
+            }
+        }
+        alerts
+    }
+
+    /// Returns an alert of the given rule.
+    fn alert(rule: &AlertRule, status: AlertStatus, message: String) -> Alert {
+        Alert { rule: rule.name.clone(), severity: rule.severity, status, message }
+    }
+}
+
+/// Dispatches the alerts to the webhook and to the command of the configuration file.
+#[derive(Clone, Debug, Default)]
+pub struct AlertNotifier {
+    /// The URL to `POST` each alert to, as JSON.
+    webhook: Option<String>,
+    /// The command to run for each alert, with the alert in its environment.
+    command: Option<String>,
+}
+
+impl From<&AlertsConfig> for AlertNotifier {
+    fn from(config: &AlertsConfig) -> Self {
+        Self { webhook: config.webhook.clone(), command: config.command.clone() }
+    }
+}
+
+impl AlertNotifier {
+    /// Dispatches the given alert, to the webhook and then to the command, if they are set.
+    pub fn dispatch(&self, alert: &Alert) -> Result<()> {
+        // Send the alert to the webhook.
+        if let Some(webhook) = &self.webhook {
+            ureq::post(webhook)
+                .set("Content-Type", "application/json")
+                .send_string(&serde_json::to_string(alert)?)
+                .map_err(|error| anyhow!("Failed to send the alert '{}' to the webhook - {error}", alert.rule))?;
+        }
+        // Run the command, with the alert in its environment.
+        if let Some(command) = &self.command {
+            #[cfg(target_family = "unix")]
+            let mut process = Command::new("sh");
+            #[cfg(target_family = "unix")]
+            process.arg("-c");
+            #[cfg(not(target_family = "unix"))]
+            let mut process = Command::new("cmd");
+            #[cfg(not(target_family = "unix"))]
+            process.arg("/C");
+
+            let status = process
+                .arg(command)
+                .env("SNARKOS_ALERT_RULE", &alert.rule)
+                .env("SNARKOS_ALERT_SEVERITY", alert.severity.to_string())
+                .env("SNARKOS_ALERT_STATUS", alert.status.to_string())
+                .env("SNARKOS_ALERT_MESSAGE", &alert.message)
+                .status()
+                .map_err(|error| anyhow!("Failed to run the command for the alert '{}' - {error}", alert.rule))?;
+            if !status.success() {
+                bail!("The command for the alert '{}' failed ({status})", alert.rule)
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A layer that sends the critical events (reorgs, integrity failures, and rejected blocks) to the alert monitor.
+#[derive(Default)]
+pub struct AlertLayer {
+    /// The sender of the events, or `None` to send them to the alert monitor of the node, once it is running.
+    sender: Option<mpsc::UnboundedSender<AlertEvent>>,
+}
+
+/// The fields of a critical event that the alert rules depend on.
+#[derive(Default)]
+struct CriticalFields {
+    /// The kind of the event.
+    kind: Option<String>,
+    /// The depth of a reorg.
+    depth: Option<u64>,
+}
+
+impl Visit for CriticalFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "kind" {
+            self.kind = Some(value.to_string());
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "depth" {
+            self.depth = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+impl<S: Subscriber> Layer<S> for AlertLayer {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        if event.metadata().target() != CRITICAL_TARGET {
+            return;
+        }
+        // Retrieve the sender of the events.
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => match ALERT_EVENTS.get() {
+                Some(sender) => sender,
+                None => return,
+            },
+        };
+        // Convert the critical event into an alert event.
+        let mut fields = CriticalFields::default();
+        event.record(&mut fields);
+        let event = match (fields.kind.as_deref(), fields.depth) {
+            (Some("reorg"), Some(depth)) => AlertEvent::Reorg { depth: depth.try_into().unwrap_or(u32::MAX) },
+            (Some("integrity"), _) => AlertEvent::IntegrityError,
+            (Some("invalid-block"), _) => AlertEvent::InvalidBlock,
+            _ => return,
+        };
+        let _ = sender.send(event);
+    }
+}
+
+/// Sets the alert settings, and sends them to the alert monitor if they changed.
+/// The settings must be validated beforehand, as they are applied as a whole.
+pub fn set_alerts_config(config: AlertsConfig) {
+    ALERTS_CONFIG.send_if_modified(|current| match *current == config {
+        true => false,
+        false => {
+            *current = config;
+            true
+        }
+    });
+}
+
+/// Starts the alert monitor, which evaluates the alert rules against the critical events
+/// and the number of connected peers of the given node, and dispatches the alerts.
+pub fn start_alert_monitor<N: Network>(node: Node<N>) -> Result<()> {
+    let (sender, mut events) = mpsc::unbounded_channel();
+    ALERT_EVENTS.set(sender.clone()).map_err(|_| anyhow!("The alert monitor is already running"))?;
+
+    // Sample the number of connected peers.
+    tokio::spawn(async move {
+        let mut samples = tokio::time::interval(PEER_COUNT_INTERVAL);
+        loop {
+            samples.tick().await;
+            if sender.send(AlertEvent::PeerCount(node.number_of_connected_peers())).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Evaluate the alert rules, and apply the changes to the settings.
+    let mut config = ALERTS_CONFIG.subscribe();
+    tokio::spawn(async move {
+        let mut monitor = AlertMonitor::new(config.borrow_and_update().rules.clone());
+        let mut notifier = AlertNotifier::from(&*config.borrow());
+        let mut evaluations = tokio::time::interval(ALERT_EVALUATION_INTERVAL);
+        loop {
+            let alerts = tokio::select! {
+                Some(event) = events.recv() => monitor.observe(event, Instant::now()),
+                _ = evaluations.tick() => monitor.evaluate(Instant::now()),
+                Ok(()) = config.changed() => {
+                    let settings = config.borrow_and_update().clone();
+                    let alerts = monitor.set_rules(settings.rules.clone());
+                    notifier = AlertNotifier::from(&settings);
+                    debug!("Applied the alert settings ({} rules)", settings.rules.len());
+                    alerts
+                }
+                else => break,
+            };
+            if alerts.is_empty() {
+                continue;
+            }
+            // Dispatch the alerts in order, without blocking the monitor.
+            let notifier = notifier.clone();
+            tokio::task::spawn_blocking(move || {
+                for alert in alerts {
+                    info!(
+                        "Dispatching the alert '{}' ({}, {}) - {}",
+                        alert.rule, alert.severity, alert.status, alert.message
+                    );
+                    if let Err(error) = notifier.dispatch(&alert) {
+                        warn!("{error}");
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::NodeConfig;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Returns the alert events of the critical events emitted by the given function.
+    fn critical_events(emit: impl FnOnce()) -> Vec<AlertEvent> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let subscriber = tracing_subscriber::registry().with(AlertLayer { sender: Some(sender) });
+        tracing::subscriber::with_default(subscriber, emit);
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn test_parse_alert_rules() {
+        let (config, unknown_keys) = NodeConfig::from_toml(
+            r#"
+            [alerts]
+            command = "page-oncall"
+
+            [[alerts.rules]]
+            name = "deep-reorg"
+            condition = "reorg-depth"
+            threshold = 3
+            severity = "critical"
+
+            [[alerts.rules]]
+            name = "few-peers"
+            condition = "peer-count"
+            floor = 4
+            window = 60
+            "#,
+        )
+        .unwrap();
+        assert!(unknown_keys.is_empty());
+        assert_eq!(config.alerts.command.as_deref(), Some("page-oncall"));
+        assert_eq!(config.alerts.rules, vec![
+            AlertRule {
+                name: "deep-reorg".to_string(),
+                condition: AlertCondition::ReorgDepth { threshold: 3 },
+                severity: AlertSeverity::Critical,
+                window: DEFAULT_ALERT_WINDOW,
+            },
+            AlertRule {
+                name: "few-peers".to_string(),
+                condition: AlertCondition::PeerCount { floor: 4 },
+                severity: AlertSeverity::Warning,
+                window: 60,
+            },
+        ]);
+
+        // Ensure an unknown condition, a duplicate name, and an invalid webhook are rejected.
+        assert!(NodeConfig::from_toml("[[alerts.rules]]\nname = \"a\"\ncondition = \"finality\"").is_err());
+        let rule = "[[alerts.rules]]\nname = \"a\"\ncondition = \"integrity-error\"\n";
+        assert!(NodeConfig::from_toml(rule).is_ok());
+        assert!(NodeConfig::from_toml(&format!("{rule}{rule}")).is_err());
+        assert!(NodeConfig::from_toml("[alerts]\nwebhook = \"localhost:8080\"").is_err());
+    }
+
+    #[test]
+    fn test_reorg_alert_and_resolution() {
+        let rule = AlertRule {
+            name: "deep-reorg".to_string(),
+            condition: AlertCondition::ReorgDepth { threshold: 3 },
+            severity: AlertSeverity::Critical,
+            window: 60,
+        };
+        let mut monitor = AlertMonitor::new(vec![rule]);
+
+        // Emit a synthetic reorg of 5 blocks, as the ledger does, along with a shallow reorg and an unrelated event.
+        let events = critical_events(|| {
+            warn!(target: CRITICAL_TARGET, kind = "reorg", depth = 5u32, "Truncated the ledger to block 10 (5 blocks)");
+            warn!(target: CRITICAL_TARGET, kind = "reorg", depth = 2u32, "Truncated the ledger to block 13 (2 blocks)");
+            warn!(target: CRITICAL_TARGET, kind = "ban", "Banned '127.0.0.1'");
+            warn!(kind = "reorg", depth = 9u32, "Not a critical event");
+        });
+        assert_eq!(events, vec![AlertEvent::Reorg { depth: 5 }, AlertEvent::Reorg { depth: 2 }]);
+
+        // Observe the events, and evaluate the rule until well past its window.
+        let start = Instant::now();
+        let mut dispatched = Vec::new();
+        for event in events {
+            dispatched.extend(monitor.observe(event, start));
+        }
+        for seconds in (0..=300).step_by(5) {
+            dispatched.extend(monitor.evaluate(start + Duration::from_secs(seconds)));
+        }
+
+        // Ensure exactly one alert and one resolution are dispatched.
+        let statuses = dispatched.iter().map(|alert| (alert.rule.as_str(), alert.status)).collect::<Vec<_>>();
+        assert_eq!(statuses, vec![("deep-reorg", AlertStatus::Firing), ("deep-reorg", AlertStatus::Resolved)]);
+        assert_eq!(dispatched[0].severity, AlertSeverity::Critical);
+        assert_eq!(dispatched[0].message, "Reorganized 5 blocks (threshold of 3)");
+    }
+
+    #[test]
+    fn test_alert_deduplication() {
+        let rules = vec![
+            AlertRule {
+                name: "invalid-blocks".to_string(),
+                condition: AlertCondition::InvalidBlocks { threshold: 3 },
+                severity: AlertSeverity::Warning,
+                window: 60,
+            },
+            AlertRule {
+                name: "few-peers".to_string(),
+                condition: AlertCondition::PeerCount { floor: 4 },
+                severity: AlertSeverity::Warning,
+                window: 60,
+            },
+        ];
+        let mut monitor = AlertMonitor::new(rules.clone());
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+
+        // Ensure the invalid blocks fire once the rate reaches the threshold, and only once while it stays there.
+        assert!(monitor.observe(AlertEvent::InvalidBlock, at(0)).is_empty());
+        assert!(monitor.observe(AlertEvent::InvalidBlock, at(10)).is_empty());
+        assert_eq!(monitor.observe(AlertEvent::InvalidBlock, at(20))[0].status, AlertStatus::Firing);
+        assert!(monitor.observe(AlertEvent::InvalidBlock, at(30)).is_empty());
+        assert!(monitor.evaluate(at(65)).is_empty());
+        // Ensure the alert resolves once the rate drops below the threshold.
+        assert_eq!(monitor.evaluate(at(75))[0].status, AlertStatus::Resolved);
+
+        // Ensure the peer count fires below the floor, and resolves at the floor.
+        assert_eq!(monitor.observe(AlertEvent::PeerCount(2), at(80))[0].status, AlertStatus::Firing);
+        assert!(monitor.observe(AlertEvent::PeerCount(1), at(95)).is_empty());
+        assert!(monitor.evaluate(at(500)).is_empty());
+        assert_eq!(monitor.observe(AlertEvent::PeerCount(4), at(510))[0].status, AlertStatus::Resolved);
+
+        // Ensure a firing alert is resolved when its rule is removed on reload.
+        assert_eq!(monitor.observe(AlertEvent::PeerCount(0), at(520)).len(), 1);
+        let alerts = monitor.set_rules(rules[..1].to_vec());
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].rule.as_str(), alerts[0].status), ("few-peers", AlertStatus::Resolved));
+        assert!(monitor.observe(AlertEvent::PeerCount(0), at(530)).is_empty());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_dispatch_alert_to_command() {
+        let path = std::env::temp_dir().join(format!("alerts-dispatch-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let notifier = AlertNotifier {
+            webhook: None,
+            command: Some(format!(
+                "echo \"$SNARKOS_ALERT_RULE $SNARKOS_ALERT_SEVERITY $SNARKOS_ALERT_STATUS\" >> {}",
+                path.display()
+            )),
+        };
+        let alert = Alert {
+            rule: "deep-reorg".to_string(),
+            severity: AlertSeverity::Critical,
+            status: AlertStatus::Firing,
+            message: "Reorganized 5 blocks (threshold of 3)".to_string(),
+        };
+        notifier.dispatch(&alert).unwrap();
+        notifier.dispatch(&Alert { status: AlertStatus::Resolved, ..alert.clone() }).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "deep-reorg critical firing\ndeep-reorg critical resolved\n"
+        );
+
+        // Ensure a failing command is reported.
+        let notifier = AlertNotifier { webhook: None, command: Some("exit 1".to_string()) };
+        assert!(notifier.dispatch(&Alert { status: AlertStatus::Resolved, ..alert }).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
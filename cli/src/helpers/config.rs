@@ -0,0 +1,445 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::helpers::AlertRule;
+use snarkos_node::{LiveConfig, NodeRole, Privacy};
+use snarkos_node_consensus::CoinbaseRecipients;
+use snarkos_node_rest::ListenerConfig;
+use snarkos_node_store::rocksdb::TuningProfile;
+use snarkvm::prelude::Testnet3;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::Deserialize;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The keys of each section of the configuration file.
+const CONFIG_KEYS: &[(&str, &[&str])] = &[
+    ("storage", &[
+        "path",
+        "dump_rejected_blocks",
+        "journal_retention",
+        "prune_depth",
+        "audit_interval",
+        "profile",
+        "read_timeout_ms",
+        "commit_timeout_ms",
+    ]),
+    ("network", &[
+        "node",
+        "role",
+        "compression",
+        "connect",
+        "dns_seeds",
+        "proxy",
+        "proxy_auth",
+        "proxy_only",
+        "onion_service",
+        "allow_unencrypted_peers",
+        "identity",
+        "snapshot_from",
+        "max_snapshot_streams",
+        "max_peers",
+        "sync_byte_budget",
+        "sync_throughput_floor",
+        "stale_tip_multiple",
+        "recent_blocks",
+        "recent_blocks_byte_budget",
+        "cdn",
+        "privacy",
+        "diffusion_first_hops",
+        "diffusion_delay_ms",
+        "diffusion_min_embargo_ms",
+        "diffusion_max_embargo_ms",
+    ]),
+    ("rest", &[
+        "address",
+        "disabled",
+        "jwt_secret",
+        "rate_limit",
+        "cache_size",
+        "cache_ttl",
+        "slow_request_ms",
+        "listeners",
+        "allow_public_admin",
+    ]),
+    ("mempool", &["byte_budget", "replace_by_fee", "expiry_window", "expiry_grace", "program_allowlist"]),
+    ("mining", &["template_fee_delta", "coinbase_recipients"]),
+    ("logging", &["verbosity", "logfile", "nodisplay", "filter", "directory", "max_size", "max_age", "max_files"]),
+    ("alerts", &["webhook", "command", "rules"]),
+];
+
+/// The configuration file of a node, in TOML, as specified with `snarkos start --config <path>`.
+///
+/// Every setting is optional, and a flag given on the command line takes precedence over the file.
+/// The settings of the `LiveConfig` (the log filter, the REST rate limit, the memory pool byte budget,
+/// the maximum number of peers, and the coinbase recipients) and the alert settings are applied again when the file is reloaded,
+/// on `SIGHUP` or through `POST /testnet3/node/reload`. Changes to the other settings require a restart.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct NodeConfig {
+    /// The storage settings.
+    pub storage: StorageConfig,
+    /// The network settings.
+    pub network: NetworkConfig,
+    /// The REST server settings.
+    pub rest: RestConfig,
+    /// The memory pool settings.
+    pub mempool: MempoolConfig,
+    /// The block production settings.
+    pub mining: MiningConfig,
+    /// The logging settings.
+    pub logging: LoggingConfig,
+    /// The alert settings.
+    pub alerts: AlertsConfig,
+}
+
+/// The `[storage]` section of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// The directory of the ledger.
+    pub path: Option<PathBuf>,
+    /// The directory to dump rejected blocks into.
+    pub dump_rejected_blocks: Option<PathBuf>,
+    /// The number of events retained in the chain journal.
+    pub journal_retention: Option<u64>,
+    /// The depth beyond which the bodies of the blocks are pruned.
+    pub prune_depth: Option<u32>,
+    /// The interval, in seconds, between the audits of the historical blocks.
+    pub audit_interval: Option<u64>,
+    /// The tuning profile of the storage.
+    pub profile: Option<TuningProfile>,
+    /// The timeout, in milliseconds, of a storage read.
+    pub read_timeout_ms: Option<u64>,
+    /// The timeout, in milliseconds, of a storage batch commit.
+    pub commit_timeout_ms: Option<u64>,
+}
+
+/// The `[network]` section of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// The IP address and port for the node server.
+    pub node: Option<SocketAddr>,
+    /// The role in which the node operates on the network, as in `--role`.
+    pub role: Option<NodeRole>,
+    /// The compression of the large messages sent to the peers that support it, as in `--compression`.
+    pub compression: Option<String>,
+    /// The peers to connect to, as in `--connect`.
+    pub connect: Option<String>,
+    /// The hostnames of the DNS seeds, separated by commas, as in `--dns-seeds`.
+    pub dns_seeds: Option<String>,
+    /// The IP address and port of the SOCKS5 proxy of the outbound connections, as in `--proxy`.
+    pub proxy: Option<SocketAddr>,
+    /// The username and password of the proxy, as in `--proxy-auth`.
+    pub proxy_auth: Option<String>,
+    /// Whether the node only connects through the proxy, as in `--proxy-only`.
+    pub proxy_only: Option<bool>,
+    /// The onion address of the node, as in `--onion-service`.
+    pub onion_service: Option<String>,
+    /// Whether peers without transport encryption are accepted.
+    pub allow_unencrypted_peers: Option<bool>,
+    /// The identity file (the static keypair) of the node, supplied by the operator, as in `--identity`.
+    pub identity: Option<PathBuf>,
+    /// The trusted peer to bootstrap an empty ledger from its snapshot, as in `--snapshot-from`.
+    pub snapshot_from: Option<SocketAddr>,
+    /// The maximum number of snapshot streams served to trusted peers, as in `--max-snapshot-streams`.
+    pub max_snapshot_streams: Option<usize>,
+    /// The maximum number of connected peers (live).
+    pub max_peers: Option<u16>,
+    /// The byte budget of the blocks queued for verification and commit while syncing.
+    pub sync_byte_budget: Option<usize>,
+    /// The throughput (in bytes per second) below which a sync peer is demoted, or `0` to never demote.
+    pub sync_throughput_floor: Option<u64>,
+    /// The multiple of the block time without a new block after which the tip is stale, as in `--stale-tip-multiple`.
+    pub stale_tip_multiple: Option<u32>,
+    /// The number of recent canonical blocks kept in memory for the shallow reorganizations.
+    pub recent_blocks: Option<usize>,
+    /// The byte budget of the recent canonical blocks kept in memory.
+    pub recent_blocks_byte_budget: Option<usize>,
+    /// The CDN to prefetch initial blocks from, or an empty string to disable it.
+    pub cdn: Option<String>,
+    /// Whether the local transactions are diffused through a few peers before they are broadcast, as in `--privacy`.
+    pub privacy: Option<Privacy>,
+    /// The number of peers that first receive a local transaction.
+    pub diffusion_first_hops: Option<usize>,
+    /// The mean delay, in milliseconds, before a local transaction is sent to each of the first peers.
+    pub diffusion_delay_ms: Option<u64>,
+    /// The minimum embargo, in milliseconds, before a local transaction is broadcast.
+    pub diffusion_min_embargo_ms: Option<u64>,
+    /// The maximum embargo, in milliseconds, before a local transaction is broadcast.
+    pub diffusion_max_embargo_ms: Option<u64>,
+}
+
+/// The `[rest]` section of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RestConfig {
+    /// The IP address and port for the REST server.
+    pub address: Option<SocketAddr>,
+    /// Whether the REST server is disabled.
+    pub disabled: Option<bool>,
+    /// The secret to sign and verify the JSON web tokens with, instead of a random one.
+    pub jwt_secret: Option<String>,
+    /// The maximum number of requests per second from each IP address (live).
+    pub rate_limit: Option<u32>,
+    /// The byte budget of the REST response cache.
+    pub cache_size: Option<usize>,
+    /// The time-to-live (in seconds) of the entries in the REST response cache.
+    pub cache_ttl: Option<u64>,
+    /// The duration (in milliseconds) after which a request is logged as slow (live).
+    pub slow_request_ms: Option<u64>,
+    /// The additional listeners of the REST server, in the form of `--rest-listener`.
+    pub listeners: Option<Vec<String>>,
+    /// Whether a listener may serve the admin methods on a public address without authentication.
+    pub allow_public_admin: Option<bool>,
+}
+
+/// The `[mempool]` section of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct MempoolConfig {
+    /// The maximum number of bytes of the unconfirmed transactions (live).
+    pub byte_budget: Option<usize>,
+    /// The fee increment (in microcredits) to replace conflicting transactions, if replacements are enabled.
+    pub replace_by_fee: Option<u64>,
+    /// The admission window (in blocks) of the global state roots of the transactions, if expiry is enabled.
+    pub expiry_window: Option<u32>,
+    /// The number of blocks past the admission window after which a transaction expires.
+    pub expiry_grace: Option<u32>,
+    /// The programs that the transactions must deploy or execute to be admitted, separated by commas, if the
+    /// memory pool is restricted to an allowlist.
+    pub program_allowlist: Option<String>,
+}
+
+/// The `[mining]` section of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct MiningConfig {
+    /// The increase in the fees of the memory pool (in microcredits) that makes a block template stale.
+    pub template_fee_delta: Option<u64>,
+    /// The recipients of the coinbase of a produced block, as `address:weight` entries.
+    pub coinbase_recipients: Option<Vec<String>>,
+}
+
+/// The `[logging]` section of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// The verbosity of the node.
+    pub verbosity: Option<u8>,
+    /// The path to the file where logs will be stored.
+    pub logfile: Option<PathBuf>,
+    /// Whether the display is disabled.
+    pub nodisplay: Option<bool>,
+    /// The additional directives of the log filter, such as `snarkos_node_router=debug` (live).
+    pub filter: Option<String>,
+    /// The directory where the structured (JSON) logs will be stored.
+    pub directory: Option<PathBuf>,
+    /// The maximum size (in bytes) of a structured log file, beyond which it is rotated.
+    pub max_size: Option<u64>,
+    /// The maximum age (in seconds) of a structured log file, beyond which it is rotated.
+    pub max_age: Option<u64>,
+    /// The maximum number of structured log files that are retained.
+    pub max_files: Option<usize>,
+}
+
+/// The `[alerts]` section of the configuration file, with the rules in `[[alerts.rules]]` tables (live).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// The URL to `POST` each alert to, as JSON.
+    pub webhook: Option<String>,
+    /// The command to run for each alert, with the alert in its environment (`SNARKOS_ALERT_*`).
+    pub command: Option<String>,
+    /// The alert rules.
+    pub rules: Vec<AlertRule>,
+}
+
+impl NodeConfig {
+    /// Loads and checks the configuration file at the given path, and returns it along with its unknown keys.
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>)> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| anyhow!("Failed to read the configuration file '{}' - {error}", path.display()))?;
+        Self::from_toml(&contents).map_err(|error| anyhow!("Invalid configuration file '{}' - {error}", path.display()))
+    }
+
+    /// Parses and checks the given configuration, in TOML, and returns it along with its unknown keys.
+    pub fn from_toml(contents: &str) -> Result<(Self, Vec<String>)> {
+        // Parse the configuration as a table, to find the unknown keys.
+        let table = match contents.parse::<toml::Value>()? {
+            toml::Value::Table(table) => table,
+            _ => bail!("The configuration must be a table"),
+        };
+        let mut unknown_keys = Vec::new();
+        for (section, value) in &table {
+            match CONFIG_KEYS.iter().find(|(name, _)| name == section) {
+                Some((_, keys)) => match value {
+                    toml::Value::Table(entries) => unknown_keys.extend(
+                        entries
+                            .keys()
+                            .filter(|key| !keys.contains(&key.as_str()))
+                            .map(|key| format!("{section}.{key}")),
+                    ),
+                    _ => bail!("The section '{section}' must be a table"),
+                },
+                None => unknown_keys.push(section.clone()),
+            }
+        }
+        unknown_keys.sort();
+
+        // Deserialize the configuration, which ignores the unknown keys.
+        let config = toml::Value::Table(table).try_into::<Self>()?;
+        config.check()?;
+        Ok((config, unknown_keys))
+    }
+
+    /// Ensures the settings are valid, so that an invalid file is rejected as a whole.
+    fn check(&self) -> Result<()> {
+        ensure!(self.network.max_peers != Some(0), "'network.max_peers' must be greater than 0");
+        ensure!(self.network.diffusion_first_hops != Some(0), "'network.diffusion_first_hops' must be greater than 0");
+        if let (Some(min), Some(max)) = (self.network.diffusion_min_embargo_ms, self.network.diffusion_max_embargo_ms) {
+            ensure!(
+                min <= max,
+                "'network.diffusion_min_embargo_ms' must not exceed 'network.diffusion_max_embargo_ms'"
+            );
+        }
+        ensure!(self.rest.rate_limit != Some(0), "'rest.rate_limit' must be greater than 0");
+        ensure!(self.rest.slow_request_ms != Some(0), "'rest.slow_request_ms' must be greater than 0");
+        ensure!(self.mempool.byte_budget != Some(0), "'mempool.byte_budget' must be greater than 0");
+        if let Some(secret) = &self.rest.jwt_secret {
+            ensure!(secret.len() >= 16, "'rest.jwt_secret' must be at least 16 bytes");
+        }
+        for listener in self.rest.listeners.iter().flatten() {
+            ListenerConfig::from_str(listener).map_err(|error| anyhow!("'rest.listeners' is invalid - {error}"))?;
+        }
+        if let Some(recipients) = &self.mining.coinbase_recipients {
+            CoinbaseRecipients::<Testnet3>::parse(recipients)
+                .map_err(|error| anyhow!("'mining.coinbase_recipients' is invalid - {error}"))?;
+        }
+        ensure!(self.logging.max_size != Some(0), "'logging.max_size' must be greater than 0");
+        ensure!(self.logging.max_files != Some(0), "'logging.max_files' must be greater than 0");
+        if let Some(verbosity) = self.logging.verbosity {
+            ensure!(verbosity <= 4, "'logging.verbosity' must be between 0 and 4 (found {verbosity})");
+        }
+        if let Some(filter) = &self.logging.filter {
+            crate::helpers::parse_log_directives(filter)
+                .map_err(|error| anyhow!("'logging.filter' is malformed - {error}"))?;
+        }
+        if let Some(webhook) = &self.alerts.webhook {
+            ensure!(
+                webhook.starts_with("http://") || webhook.starts_with("https://"),
+                "'alerts.webhook' must be an HTTP(S) URL (found '{webhook}')"
+            );
+        }
+        for (index, rule) in self.alerts.rules.iter().enumerate() {
+            ensure!(
+                self.alerts.rules[..index].iter().all(|other| other.name != rule.name),
+                "'alerts.rules' has a duplicate rule '{}'",
+                rule.name
+            );
+            ensure!(rule.window > 0, "The window of the alert rule '{}' must be greater than 0", rule.name);
+        }
+        Ok(())
+    }
+
+    /// Returns the settings that can change while the node is running.
+    pub fn live_config(&self) -> LiveConfig {
+        LiveConfig {
+            log_filter: self.logging.filter.clone(),
+            rest_rate_limit: self.rest.rate_limit,
+            rest_slow_request_ms: self.rest.slow_request_ms,
+            mempool_byte_budget: self.mempool.byte_budget,
+            max_peers: self.network.max_peers,
+            coinbase_recipients: self.mining.coinbase_recipients.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let (config, unknown_keys) = NodeConfig::from_toml(
+            r#"
+            [network]
+            node = "127.0.0.1:4140"
+            role = "outbound-only"
+            max_peers = 8
+            peers = 3
+
+            [rest]
+            slow_request_ms = 500
+            listeners = ["0.0.0.0:3034,classes=read"]
+
+            [mempool]
+            byte_budget = 1048576
+
+            [logging]
+            filter = "snarkos_node_router=debug"
+
+            [mining]
+            template_fee_delta = 10
+
+            [storage]
+            profile = "archival"
+            commit_timeout_ms = 60000
+
+            [metrics]
+            enabled = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.network.node, Some("127.0.0.1:4140".parse().unwrap()));
+        assert_eq!(config.network.role, Some(NodeRole::OutboundOnly));
+        assert_eq!(config.mining.template_fee_delta, Some(10));
+        assert_eq!(config.storage.profile, Some(TuningProfile::Archival));
+        assert_eq!(config.storage.commit_timeout_ms, Some(60000));
+        assert_eq!(config.rest.listeners, Some(vec!["0.0.0.0:3034,classes=read".to_string()]));
+        assert_eq!(config.live_config(), LiveConfig {
+            log_filter: Some("snarkos_node_router=debug".to_string()),
+            rest_rate_limit: None,
+            rest_slow_request_ms: Some(500),
+            mempool_byte_budget: Some(1048576),
+            max_peers: Some(8),
+            coinbase_recipients: None,
+        });
+        // Ensure the unknown keys are reported by name.
+        assert_eq!(unknown_keys, vec!["metrics".to_string(), "network.peers".to_string()]);
+
+        // Ensure an empty file is valid.
+        assert_eq!(NodeConfig::from_toml("").unwrap(), (NodeConfig::default(), vec![]));
+    }
+
+    #[test]
+    fn test_parse_malformed_config() {
+        // Ensure a file that is not valid TOML is rejected.
+        assert!(NodeConfig::from_toml("[mempool\nbyte_budget = 1").is_err());
+        // Ensure a setting of the wrong type is rejected.
+        assert!(NodeConfig::from_toml("[mempool]\nbyte_budget = \"large\"").is_err());
+        assert!(NodeConfig::from_toml("mempool = 1").is_err());
+        // Ensure an invalid setting is rejected.
+        assert!(NodeConfig::from_toml("[network]\nmax_peers = 0").is_err());
+        assert!(NodeConfig::from_toml("[logging]\nfilter = \"snarkos=loud\"").is_err());
+        assert!(NodeConfig::from_toml("[mining]\ncoinbase_recipients = [\"aleo1invalid:1\"]").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{ensure, Result};
+use parking_lot::Mutex;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// The rotation and retention settings of a log.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// The maximum size of a log file (in bytes), beyond which it is rotated.
+    pub max_size: u64,
+    /// The maximum age of a log file, beyond which it is rotated.
+    pub max_age: Duration,
+    /// The maximum number of log files retained, including the current one.
+    pub max_files: usize,
+}
+
+/// A log written to a directory, whose current file is rotated by size and age, and whose oldest files are pruned.
+///
+/// The files are named `{prefix}.{sequence}.log`, with a sequence number that increases with every rotation,
+/// and every start. An event is written in a single call while holding the lock of the log, and a file is
+/// only rotated between two events, so that concurrent writers never split or lose events across files.
+#[derive(Clone)]
+pub struct RotatingLog(Arc<Mutex<RotatingLogInner>>);
+
+struct RotatingLogInner {
+    /// The directory of the log files.
+    directory: PathBuf,
+    /// The prefix of the names of the log files.
+    prefix: String,
+    /// The rotation and retention settings.
+    policy: RotationPolicy,
+    /// The current log file.
+    file: File,
+    /// The sequence number of the current log file.
+    sequence: u64,
+    /// The number of bytes written to the current log file.
+    size: u64,
+    /// The time at which the current log file was opened.
+    opened_at: Instant,
+}
+
+impl RotatingLog {
+    /// Opens a new log file with the given prefix in the given directory, after the existing ones.
+    pub fn open(directory: &Path, prefix: &str, policy: RotationPolicy) -> Result<Self> {
+        ensure!(policy.max_size > 0, "The maximum size of a log file must be greater than 0");
+        ensure!(policy.max_files > 0, "The maximum number of log files must be greater than 0");
+        std::fs::create_dir_all(directory)?;
+
+        // Start after the latest existing log file.
+        let sequence = log_files(directory, prefix)?.last().map_or(0, |(sequence, _)| sequence + 1);
+        let file = File::create(log_file_path(directory, prefix, sequence))?;
+        let mut inner = RotatingLogInner {
+            directory: directory.to_path_buf(),
+            prefix: prefix.to_string(),
+            policy,
+            file,
+            sequence,
+            size: 0,
+            opened_at: Instant::now(),
+        };
+        inner.prune()?;
+        Ok(Self(Arc::new(Mutex::new(inner))))
+    }
+
+    /// Returns the paths of the retained log files, from the oldest to the current one.
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        let inner = self.0.lock();
+        Ok(log_files(&inner.directory, &inner.prefix)?.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+impl RotatingLogInner {
+    /// Returns `true` if the current log file must be rotated before the given number of bytes are written.
+    fn must_rotate(&self, num_bytes: u64) -> bool {
+        // An empty log file is never rotated, so that an event larger than the maximum size is still written.
+        self.size > 0
+            && (self.size.saturating_add(num_bytes) > self.policy.max_size
+                || self.opened_at.elapsed() >= self.policy.max_age)
+    }
+
+    /// Closes the current log file, opens the next one, and prunes the oldest log files.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let sequence = self.sequence + 1;
+        self.file = File::create(log_file_path(&self.directory, &self.prefix, sequence))?;
+        self.sequence = sequence;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        self.prune().map_err(|error| io::Error::other(error.to_string()))
+    }
+
+    /// Removes the oldest log files beyond the maximum number of log files.
+    fn prune(&mut self) -> Result<()> {
+        let files = log_files(&self.directory, &self.prefix)?;
+        let num_surplus = files.len().saturating_sub(self.policy.max_files);
+        for (_, path) in files.into_iter().take(num_surplus) {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingLog {
+    /// Writes the given event to the current log file, after rotating it if needed.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.0.lock();
+        if inner.must_rotate(buf.len() as u64) {
+            inner.rotate()?;
+        }
+        inner.file.write_all(buf)?;
+        inner.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    /// Flushes the current log file.
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingLog {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Returns the path of the log file with the given prefix and sequence number in the given directory.
+fn log_file_path(directory: &Path, prefix: &str, sequence: u64) -> PathBuf {
+    directory.join(format!("{prefix}.{sequence:06}.log"))
+}
+
+/// Returns the sequence numbers and paths of the log files with the given prefix in the given directory,
+/// in ascending order of sequence number.
+pub fn log_files(directory: &Path, prefix: &str) -> Result<Vec<(u64, PathBuf)>> {
+    let mut files = Vec::new();
+    if !directory.exists() {
+        return Ok(files);
+    }
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let sequence = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(prefix)?.strip_prefix('.')?.strip_suffix(".log")?.parse::<u64>().ok());
+        if let Some(sequence) = sequence {
+            files.push((sequence, path));
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The number of concurrent writers.
+    const NUM_WRITERS: usize = 8;
+    /// The number of events written by each writer.
+    const NUM_EVENTS: usize = 500;
+
+    /// Returns a new, empty directory for the given test.
+    fn sample_directory(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("log-rotation-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    /// Returns the lines of the given log files.
+    fn read_lines(files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .flat_map(|path| std::fs::read_to_string(path).unwrap().lines().map(String::from).collect::<Vec<_>>())
+            .collect()
+    }
+
+    #[test]
+    fn test_rotation_keeps_every_event() {
+        let directory = sample_directory("concurrent");
+        let policy = RotationPolicy { max_size: 4096, max_age: Duration::from_secs(3600), max_files: usize::MAX };
+        let log = RotatingLog::open(&directory, "node", policy).unwrap();
+
+        // Write the events from concurrent writers, each event in a single call.
+        let handles = (0..NUM_WRITERS)
+            .map(|writer| {
+                let mut log = log.clone();
+                std::thread::spawn(move || {
+                    for event in 0..NUM_EVENTS {
+                        log.write_all(format!("{{\"writer\":{writer},\"event\":{event}}}\n").as_bytes()).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().for_each(|handle| handle.join().unwrap());
+
+        // Ensure the log was rotated, every file is within the maximum size, and every event is intact.
+        let files = log.files().unwrap();
+        assert!(files.len() > 1);
+        assert!(files.iter().all(|path| std::fs::metadata(path).unwrap().len() <= policy.max_size));
+        let mut lines = read_lines(&files);
+        assert_eq!(lines.len(), NUM_WRITERS * NUM_EVENTS);
+        lines.sort();
+        lines.dedup();
+        assert_eq!(lines.len(), NUM_WRITERS * NUM_EVENTS);
+        for line in &lines {
+            let event: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(event["writer"].as_u64().unwrap() < NUM_WRITERS as u64);
+        }
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_retention_prunes_oldest_file() {
+        let directory = sample_directory("retention");
+        let policy = RotationPolicy { max_size: 100, max_age: Duration::from_secs(3600), max_files: 3 };
+        let mut log = RotatingLog::open(&directory, "node", policy).unwrap();
+
+        // Write 5 files of 2 events each.
+        let event = [b'x'; 49];
+        for _ in 0..10 {
+            log.write_all(&event).unwrap();
+        }
+
+        // Ensure only the 3 latest files are retained.
+        let files = log_files(&directory, "node").unwrap();
+        assert_eq!(files.iter().map(|(sequence, _)| *sequence).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(read_lines(&log.files().unwrap()).concat().len(), 6 * event.len());
+
+        // Ensure a restart continues after the latest file, and prunes the oldest one.
+        drop(log);
+        let log = RotatingLog::open(&directory, "node", policy).unwrap();
+        let files = log_files(&directory, "node").unwrap();
+        assert_eq!(files.iter().map(|(sequence, _)| *sequence).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(log.files().unwrap().len(), 3);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_by_age() {
+        let directory = sample_directory("age");
+        let policy = RotationPolicy { max_size: u64::MAX, max_age: Duration::from_millis(50), max_files: 10 };
+        let mut log = RotatingLog::open(&directory, "node", policy).unwrap();
+
+        log.write_all(b"first\n").unwrap();
+        log.write_all(b"second\n").unwrap();
+        assert_eq!(log.files().unwrap().len(), 1);
+
+        // Ensure the file is rotated once it is older than the maximum age.
+        std::thread::sleep(policy.max_age);
+        log.write_all(b"third\n").unwrap();
+        let files = log.files().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(read_lines(&files), vec!["first", "second", "third"]);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
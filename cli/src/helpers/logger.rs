@@ -14,19 +14,120 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::helpers::LogWriter;
+use crate::helpers::{log_files, AlertLayer, LogWriter, RotatingLog, RotationPolicy};
+use snarkos_node_rest::StorageTimingLayer;
 
+use anyhow::{anyhow, Result};
 use crossterm::tty::IsTty;
-use std::{fs::File, io, path::Path};
+use once_cell::sync::OnceCell;
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tokio::sync::mpsc;
 use tracing_subscriber::{
+    filter::{filter_fn, Directive},
     layer::{Layer, SubscriberExt},
+    reload,
     util::SubscriberInitExt,
     EnvFilter,
 };
 
+/// The target of the critical events (reorgs, bans, integrity failures, rejected blocks, and shutdowns),
+/// which are always written to the log of the critical events, regardless of the log filters,
+/// and sent to the alert monitor.
+pub const CRITICAL_TARGET: &str = "critical";
+
+/// The prefix of the names of the files of the critical events.
+const CRITICAL_LOG_PREFIX: &str = "snarkos-critical";
+/// The rotation and retention settings of the critical events, which are kept small.
+const CRITICAL_LOG_POLICY: RotationPolicy =
+    RotationPolicy { max_size: 1024 * 1024, max_age: Duration::from_secs(30 * 24 * 60 * 60), max_files: 8 };
+/// The prefix of the names of the files of the structured logs.
+const STRUCTURED_LOG_PREFIX: &str = "snarkos";
+
+/// The directory of the logs of a node, with the critical events and, optionally, the structured logs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogDirectory {
+    /// The path of the directory.
+    pub path: PathBuf,
+    /// The rotation and retention settings of the structured (JSON) logs, or `None` if they are disabled.
+    pub structured: Option<RotationPolicy>,
+}
+
+/// A function that replaces the additional directives of the log filters.
+type LogFilterReloader = Box<dyn Fn(&[Directive]) -> Result<()> + Send + Sync>;
+
+/// The function that replaces the additional directives of the log filters, once the logger is initialized.
+static LOG_FILTER_RELOADER: OnceCell<LogFilterReloader> = OnceCell::new();
+
+/// Returns the log filter for the given verbosity, with the given additional directives.
+fn log_filter(verbosity: u8, directives: &[Directive]) -> EnvFilter {
+    // Filter out undesirable logs.
+    let filter = EnvFilter::from_default_env()
+        .add_directive("mio=off".parse().unwrap())
+        .add_directive("tokio_util=off".parse().unwrap())
+        .add_directive("hyper=off".parse().unwrap())
+        .add_directive("reqwest=off".parse().unwrap())
+        .add_directive("want=off".parse().unwrap())
+        .add_directive("warp=off".parse().unwrap());
+
+    let filter = if verbosity > 3 {
+        filter.add_directive("snarkos_node_tcp=trace".parse().unwrap())
+    } else {
+        filter.add_directive("snarkos_node_tcp=off".parse().unwrap())
+    };
+
+    // Add the additional directives, which take precedence over the directives above for the same target.
+    directives.iter().fold(filter, |filter, directive| filter.add_directive(directive.clone()))
+}
+
+/// Parses the given comma-separated directives of a log filter, such as `snarkos_node_router=debug`.
+pub fn parse_log_directives(directives: &str) -> Result<Vec<Directive>> {
+    directives
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| directive.parse().map_err(|error| anyhow!("Invalid directive '{directive}' - {error}")))
+        .collect()
+}
+
+/// Replaces the additional directives of the log filters, or removes them if `None`.
+/// This has no effect if the logger is not initialized.
+pub fn set_log_filter(directives: Option<&str>) -> Result<()> {
+    let directives = parse_log_directives(directives.unwrap_or_default())?;
+    match LOG_FILTER_RELOADER.get() {
+        Some(reload) => reload(&directives),
+        None => Ok(()),
+    }
+}
+
+/// Returns the last critical event of the previous run with the given log directory,
+/// if the run did not shut down cleanly.
+fn previous_unclean_shutdown(directory: &Path) -> Option<String> {
+    // Retrieve the latest file of the critical events.
+    let (_, path) = log_files(directory, CRITICAL_LOG_PREFIX).ok()?.pop()?;
+    let contents = std::fs::read_to_string(path).ok()?;
+    // Retrieve the last critical event, which is malformed if the run was interrupted while writing it.
+    let last_event = contents.lines().rev().find(|line| !line.trim().is_empty())?;
+    let event = serde_json::from_str::<serde_json::Value>(last_event).unwrap_or_default();
+    match event["fields"]["kind"].as_str() {
+        Some("shutdown") => None,
+        _ => Some(last_event.to_string()),
+    }
+}
+
 /// Initializes the logger.
-pub fn initialize_logger<P: AsRef<Path>>(verbosity: u8, nodisplay: bool, logfile: P) -> mpsc::Receiver<Vec<u8>> {
+///
+/// If a log directory is given, the critical events are written to it, along with the structured logs if enabled.
+pub fn initialize_logger<P: AsRef<Path>>(
+    verbosity: u8,
+    nodisplay: bool,
+    logfile: P,
+    log_directory: Option<LogDirectory>,
+) -> mpsc::Receiver<Vec<u8>> {
     match verbosity {
         0 => std::env::set_var("RUST_LOG", "info"),
         1 => std::env::set_var("RUST_LOG", "debug"),
@@ -34,22 +135,10 @@ pub fn initialize_logger<P: AsRef<Path>>(verbosity: u8, nodisplay: bool, logfile
         _ => std::env::set_var("RUST_LOG", "info"),
     };
 
-    // Filter out undesirable logs. (unfortunately EnvFilter cannot be cloned)
-    let [filter, filter2] = std::array::from_fn(|_| {
-        let filter = EnvFilter::from_default_env()
-            .add_directive("mio=off".parse().unwrap())
-            .add_directive("tokio_util=off".parse().unwrap())
-            .add_directive("hyper=off".parse().unwrap())
-            .add_directive("reqwest=off".parse().unwrap())
-            .add_directive("want=off".parse().unwrap())
-            .add_directive("warp=off".parse().unwrap());
-
-        if verbosity > 3 {
-            filter.add_directive("snarkos_node_tcp=trace".parse().unwrap())
-        } else {
-            filter.add_directive("snarkos_node_tcp=off".parse().unwrap())
-        }
-    });
+    // Initialize the log filters, which can be reloaded. (unfortunately EnvFilter cannot be cloned)
+    let (filter, filter_handle) = reload::Layer::new(log_filter(verbosity, &[]));
+    let (filter2, filter2_handle) = reload::Layer::new(log_filter(verbosity, &[]));
+    let (filter3, filter3_handle) = reload::Layer::new(log_filter(verbosity, &[]));
 
     // Create the directories tree for a logfile if it doesn't exist.
     let logfile_dir = logfile.as_ref().parent().expect("Root directory passed as a logfile");
@@ -61,6 +150,22 @@ pub fn initialize_logger<P: AsRef<Path>>(verbosity: u8, nodisplay: bool, logfile
     let logfile =
         File::options().append(true).create(true).open(logfile).expect("Failed to open the file for writing logs");
 
+    // Check the critical events of the previous run, before the ones of this run are written.
+    let unclean_shutdown = log_directory.as_ref().and_then(|directory| previous_unclean_shutdown(&directory.path));
+    // Open the log of the critical events.
+    let critical_log = log_directory.as_ref().map(|directory| {
+        RotatingLog::open(&directory.path, CRITICAL_LOG_PREFIX, CRITICAL_LOG_POLICY)
+            .expect("Failed to open the log of the critical events")
+    });
+    // Open the structured logs, if enabled.
+    let structured_log = log_directory.as_ref().and_then(|directory| {
+        directory.structured.map(|policy| {
+            RotatingLog::open(&directory.path, STRUCTURED_LOG_PREFIX, policy)
+                .expect("Failed to open the structured logs")
+        })
+    });
+    let is_structured = structured_log.is_some();
+
     // Initialize the log channel.
     let (log_sender, log_receiver) = mpsc::channel(1024);
 
@@ -88,8 +193,50 @@ pub fn initialize_logger<P: AsRef<Path>>(verbosity: u8, nodisplay: bool, logfile
                 .with_target(verbosity > 2)
                 .with_filter(filter2),
         )
+        .with(
+            // Add layer writing the structured logs to the log directory, if enabled
+            structured_log
+                .map(|log| tracing_subscriber::fmt::Layer::default().json().with_writer(log).with_filter(filter3)),
+        )
+        .with(
+            // Add layer writing the critical events to the log directory
+            critical_log.map(|log| {
+                tracing_subscriber::fmt::Layer::default()
+                    .json()
+                    .with_writer(log)
+                    .with_filter(filter_fn(|metadata| metadata.target() == CRITICAL_TARGET))
+            }),
+        )
+        // Add layer sending the critical events to the alert monitor, once it is running
+        .with(AlertLayer::default())
+        // Add layer timing the storage reads of the REST requests, for the slow request log
+        .with(StorageTimingLayer)
         .try_init();
 
+    // Store the function to reload the log filters.
+    let _ = LOG_FILTER_RELOADER.set(Box::new(move |directives| {
+        filter_handle.reload(log_filter(verbosity, directives))?;
+        filter2_handle.reload(log_filter(verbosity, directives))?;
+        if is_structured {
+            filter3_handle.reload(log_filter(verbosity, directives))?;
+        }
+        Ok(())
+    }));
+
+    if log_directory.is_some() {
+        // Warn if the previous run did not shut down cleanly.
+        if let Some(event) = unclean_shutdown {
+            warn!("The previous run of the node did not shut down cleanly (its last critical event is: {event})");
+        }
+        // Record the panics as critical events, as they shut down the node.
+        let panic_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            error!(target: CRITICAL_TARGET, kind = "panic", "Shut down the node (panicked) - {info}");
+            panic_hook(info);
+        }));
+        info!(target: CRITICAL_TARGET, kind = "startup", "Starting snarkOS v{}", env!("CARGO_PKG_VERSION"));
+    }
+
     log_receiver
 }
 
@@ -118,3 +265,40 @@ pub fn welcome_message() -> String {
     output += &"👋 Welcome to Aleo! We thank you for running a node and supporting privacy.\n".bold();
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a critical event of the given kind to a new file of the critical events in the given directory.
+    fn write_critical_event(directory: &Path, kind: &str) {
+        let log = RotatingLog::open(directory, CRITICAL_LOG_PREFIX, CRITICAL_LOG_POLICY).unwrap();
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_subscriber::fmt::Layer::default().json().with_writer(log));
+        tracing::subscriber::with_default(
+            subscriber,
+            || info!(target: CRITICAL_TARGET, kind, "An event of kind '{kind}'"),
+        );
+    }
+
+    #[test]
+    fn test_previous_unclean_shutdown() {
+        let directory = std::env::temp_dir().join(format!("logger-unclean-shutdown-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        // Ensure a first run is not reported.
+        assert_eq!(previous_unclean_shutdown(&directory), None);
+
+        // Ensure a run that ends with a shutdown is not reported.
+        write_critical_event(&directory, "startup");
+        write_critical_event(&directory, "shutdown");
+        assert_eq!(previous_unclean_shutdown(&directory), None);
+
+        // Ensure a run that ends with another event is reported.
+        write_critical_event(&directory, "reorg");
+        let event = previous_unclean_shutdown(&directory).unwrap();
+        assert!(event.contains("An event of kind 'reorg'"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
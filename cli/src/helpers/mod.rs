@@ -14,9 +14,18 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+mod alerts;
+pub use alerts::*;
+
 mod bech32m;
 pub use bech32m::*;
 
+mod config;
+pub use config::*;
+
+mod log_rotation;
+pub use log_rotation::*;
+
 mod log_writer;
 use log_writer::*;
 
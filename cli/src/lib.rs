@@ -19,6 +19,8 @@
 
 #[macro_use]
 extern crate thiserror;
+#[macro_use]
+extern crate tracing;
 
 pub mod commands;
 pub mod helpers;
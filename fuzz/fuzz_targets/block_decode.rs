@@ -0,0 +1,50 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decodes a block in its wire encoding, as received in a block response.
+//!
+//! Every decoded block must re-encode into bytes that decode into the same block.
+
+#![no_main]
+
+use snarkos_node_messages::DataBlocks;
+use snarkvm::prelude::{Block, FromBytes, Testnet3, ToBytes};
+
+use libfuzzer_sys::fuzz_target;
+
+snarkos_fuzz::limit_allocations!();
+
+type CurrentNetwork = Testnet3;
+
+fuzz_target!(|data: &[u8]| {
+    // Decode the block, skipping invalid inputs.
+    if let Ok(block) = Block::<CurrentNetwork>::from_bytes_le(data) {
+        // Ensure the block re-encodes into bytes that decode into the same block.
+        let bytes = block.to_bytes_le().expect("A decoded block must encode");
+        let candidate = Block::<CurrentNetwork>::from_bytes_le(&bytes).expect("A re-encoded block must decode");
+        assert_eq!(candidate, block);
+        assert_eq!(candidate.to_bytes_le().expect("A decoded block must encode"), bytes);
+    }
+
+    // Decode the blocks of a block response, skipping invalid inputs.
+    if let Ok(blocks) = DataBlocks::<CurrentNetwork>::from_bytes_le(data) {
+        // Ensure the blocks re-encode into bytes that decode into the same blocks.
+        let bytes = blocks.to_bytes_le().expect("Decoded blocks must encode");
+        let candidate = DataBlocks::<CurrentNetwork>::from_bytes_le(&bytes).expect("Re-encoded blocks must decode");
+        assert_eq!(candidate, blocks);
+        assert_eq!(candidate.to_bytes_le().expect("Decoded blocks must encode"), bytes);
+    }
+});
@@ -0,0 +1,74 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decodes an inbound frame with the message codec, as received from a peer.
+//!
+//! The first byte of the input selects the frame limit, of the handshake or of the established connection.
+//! Every decoded message must re-encode into a frame that decodes into the same message.
+
+#![no_main]
+
+use snarkos_fuzz::{deserialize_data, serialize};
+use snarkos_node_messages::MessageCodec;
+use snarkvm::prelude::Testnet3;
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use tokio_util::codec::{Decoder, Encoder};
+
+snarkos_fuzz::limit_allocations!();
+
+type CurrentNetwork = Testnet3;
+
+/// Returns a message codec, with the frame limit of an established connection if `is_connected`.
+fn codec(is_connected: bool) -> MessageCodec<CurrentNetwork> {
+    let mut codec = MessageCodec::default();
+    if is_connected {
+        codec.update_max_message_len();
+    }
+    codec
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (is_connected, frame) = match data.split_first() {
+        Some((selector, frame)) => (selector & 1 == 1, frame),
+        None => return,
+    };
+
+    // Decode the frame, skipping inputs that are incomplete or invalid.
+    let mut source = BytesMut::from(frame);
+    let message = match codec(is_connected).decode(&mut source) {
+        Ok(Some(message)) => message,
+        Ok(None) | Err(_) => return,
+    };
+    // Deserialize the deferred objects, as the node does when processing the message.
+    let message = match deserialize_data(message) {
+        Some(message) => message,
+        None => return,
+    };
+
+    // Re-encode the message, and ensure it decodes into the same message.
+    let mut frame = BytesMut::new();
+    codec(true).encode(message.clone(), &mut frame).expect("A decoded message must encode");
+    let candidate =
+        codec(true).decode(&mut frame).expect("A re-encoded message must decode").expect("Incomplete frame");
+    assert!(frame.is_empty(), "{} left {} trailing bytes", message.name(), frame.len());
+    let candidate = deserialize_data(candidate).expect("A re-encoded message must deserialize");
+    assert_eq!(candidate, message);
+
+    // Ensure the encoding is stable.
+    assert_eq!(serialize(&candidate), serialize(&message), "{}", message.name());
+});
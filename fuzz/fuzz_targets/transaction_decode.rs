@@ -0,0 +1,46 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decodes a transaction in its wire encoding, as received in an unconfirmed transaction.
+//!
+//! Every decoded transaction must re-encode into bytes that decode into the same transaction.
+
+#![no_main]
+
+use snarkos_node_messages::Data;
+use snarkvm::prelude::{Testnet3, ToBytes, Transaction};
+
+use libfuzzer_sys::fuzz_target;
+
+snarkos_fuzz::limit_allocations!();
+
+type CurrentNetwork = Testnet3;
+
+fuzz_target!(|data: &[u8]| {
+    // Decode the transaction through its deferred encoding, skipping invalid inputs.
+    let transaction = match Data::<Transaction<CurrentNetwork>>::Buffer(data.to_vec().into()).deserialize_blocking() {
+        Ok(transaction) => transaction,
+        Err(_) => return,
+    };
+
+    // Ensure the transaction re-encodes into bytes that decode into the same transaction.
+    let bytes = transaction.to_bytes_le().expect("A decoded transaction must encode");
+    let candidate = Data::<Transaction<CurrentNetwork>>::Buffer(bytes.clone().into())
+        .deserialize_blocking()
+        .expect("A re-encoded transaction must decode");
+    assert_eq!(candidate, transaction);
+    assert_eq!(candidate.to_bytes_le().expect("A decoded transaction must encode"), bytes);
+});
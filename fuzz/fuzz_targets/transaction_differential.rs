@@ -0,0 +1,45 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decodes a transaction with the native decoder of snarkVM, and with the strict decoder of a framed payload.
+//!
+//! Neither decoder may panic, and whenever both accept the input, they must agree on the transaction and its ID,
+//! which must then convert through the framing of an unconfirmed transaction without drift.
+
+#![no_main]
+
+use snarkos_node_messages::differential::{check_transaction_roundtrip, decode_differential};
+use snarkvm::prelude::Testnet3;
+
+use libfuzzer_sys::fuzz_target;
+
+snarkos_fuzz::limit_allocations!();
+
+type CurrentNetwork = Testnet3;
+
+fuzz_target!(|data: &[u8]| {
+    // Ensure the decoders agree on the input.
+    let transaction = match decode_differential::<CurrentNetwork>(data) {
+        Ok(Some(transaction)) => transaction,
+        Ok(None) => return,
+        Err(error) => panic!("The decoders diverge - {error}"),
+    };
+
+    // Ensure the decoded transaction converts through the framing without drift.
+    if let Err(error) = check_transaction_roundtrip(&transaction, vec![]) {
+        panic!("The framing of transaction '{}' drifts - {error}", transaction.id());
+    }
+});
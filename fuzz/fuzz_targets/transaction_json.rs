@@ -0,0 +1,44 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Parses a transaction from JSON, as received in the body of a REST broadcast.
+//!
+//! Every parsed transaction must re-encode into JSON that parses into the same transaction.
+
+#![no_main]
+
+use snarkvm::prelude::{Testnet3, Transaction};
+
+use libfuzzer_sys::fuzz_target;
+
+snarkos_fuzz::limit_allocations!();
+
+type CurrentNetwork = Testnet3;
+
+fuzz_target!(|data: &[u8]| {
+    // Parse the transaction, skipping invalid inputs.
+    let transaction = match serde_json::from_slice::<Transaction<CurrentNetwork>>(data) {
+        Ok(transaction) => transaction,
+        Err(_) => return,
+    };
+
+    // Ensure the transaction re-encodes into JSON that parses into the same transaction.
+    let json = serde_json::to_vec(&transaction).expect("A parsed transaction must encode");
+    let candidate =
+        serde_json::from_slice::<Transaction<CurrentNetwork>>(&json).expect("A re-encoded transaction must parse");
+    assert_eq!(candidate, transaction);
+    assert_eq!(serde_json::to_vec(&candidate).expect("A parsed transaction must encode"), json);
+});
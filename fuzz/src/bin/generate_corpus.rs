@@ -0,0 +1,79 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Writes the seed corpora of the fuzz targets, from the generators of random, valid messages.
+//!
+//! Usage: `cargo run --release --bin generate_corpus [number of messages]`, from the `fuzz` directory.
+
+use snarkos_node_messages::{
+    test_helpers::{sample_genesis_block, sample_message},
+    DataBlocks,
+    MessageCodec,
+};
+use snarkvm::prelude::{TestRng, Testnet3, ToBytes};
+
+use bytes::BytesMut;
+use std::{fs, path::Path};
+use tokio_util::codec::Encoder;
+
+type CurrentNetwork = Testnet3;
+
+/// The default number of random messages in the seed corpus of the message codec.
+const DEFAULT_NUM_MESSAGES: usize = 1_000;
+
+/// Writes the given seed into the corpus of the given fuzz target.
+fn write_seed(target: &str, name: &str, bytes: &[u8]) {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus").join(target);
+    fs::create_dir_all(&directory).expect("Failed to create the corpus directory");
+    fs::write(directory.join(name), bytes).expect("Failed to write the seed");
+}
+
+fn main() {
+    let num_messages = match std::env::args().nth(1) {
+        Some(number) => number.parse().expect("The number of messages must be an integer"),
+        None => DEFAULT_NUM_MESSAGES,
+    };
+    let rng = &mut TestRng::default();
+
+    // Write the frames of random messages, with the selector of an established connection.
+    for index in 0..num_messages {
+        let message = sample_message(rng);
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        codec.update_max_message_len();
+        let mut frame = BytesMut::from(&[1u8][..]);
+        let mut buffer = BytesMut::new();
+        codec.encode(message.clone(), &mut buffer).expect("Failed to encode a message");
+        frame.extend_from_slice(&buffer);
+        write_seed("message_codec", &format!("{}-{index}", message.name()), &frame);
+    }
+
+    // Write the genesis block, on its own and as a block response.
+    let block = sample_genesis_block();
+    write_seed("block_decode", "genesis", &block.to_bytes_le().expect("Failed to encode the genesis block"));
+    let blocks = DataBlocks(vec![block.clone()]);
+    write_seed("block_decode", "genesis-response", &blocks.to_bytes_le().expect("Failed to encode the genesis block"));
+
+    // Write the genesis transactions, in their wire and JSON encodings.
+    for (index, transaction) in block.transactions().iter().enumerate() {
+        let bytes = transaction.to_bytes_le().expect("Failed to encode a genesis transaction");
+        write_seed("transaction_decode", &format!("genesis-{index}"), &bytes);
+        write_seed("transaction_differential", &format!("genesis-{index}"), &bytes);
+        let json = serde_json::to_vec(transaction).expect("Failed to encode a genesis transaction");
+        write_seed("transaction_json", &format!("genesis-{index}"), &json);
+    }
+
+    println!("Wrote the seed corpora of {num_messages} messages and the genesis block");
+}
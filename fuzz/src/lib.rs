@@ -0,0 +1,102 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Shared helpers of the fuzz targets.
+//!
+//! Every target installs the [`LimitingAllocator`], so that decoding an input which allocates
+//! beyond [`MAX_ALLOCATION`] bytes at once is reported as a crash, rather than passing unnoticed
+//! on a machine with enough memory.
+
+use snarkos_node_messages::{BlockResponse, ChallengeResponse, Data, Message, UnconfirmedTransaction};
+use snarkvm::prelude::Network;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+
+/// The maximum number of bytes of a single allocation.
+/// (The message codec reserves up to the maximum message size of 128 MiB for an incoming frame.)
+pub const MAX_ALLOCATION: usize = 256 * 1024 * 1024; // 256 MiB
+
+/// An allocator that aborts the process on any allocation larger than [`MAX_ALLOCATION`] bytes.
+pub struct LimitingAllocator;
+
+unsafe impl GlobalAlloc for LimitingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Reporting the size would allocate, hence the bare abort; the fuzzer prints the stack trace.
+        if layout.size() > MAX_ALLOCATION {
+            std::process::abort();
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if layout.size() > MAX_ALLOCATION {
+            std::process::abort();
+        }
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > MAX_ALLOCATION {
+            std::process::abort();
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Installs the [`LimitingAllocator`] as the global allocator of a fuzz target.
+#[macro_export]
+macro_rules! limit_allocations {
+    () => {
+        #[global_allocator]
+        static ALLOCATOR: $crate::LimitingAllocator = $crate::LimitingAllocator;
+    };
+}
+
+/// Returns the given message, with its deferred objects deserialized,
+/// or `None` if one of them is invalid (in which case the node drops the message).
+pub fn deserialize_data<N: Network>(message: Message<N>) -> Option<Message<N>> {
+    let message = match message {
+        Message::BlockResponse(BlockResponse { request, blocks }) => {
+            Message::BlockResponse(BlockResponse { request, blocks: Data::Object(blocks.deserialize_blocking().ok()?) })
+        }
+        Message::ChallengeResponse(ChallengeResponse { genesis_header, signature }) => {
+            Message::ChallengeResponse(ChallengeResponse {
+                genesis_header,
+                signature: Data::Object(signature.deserialize_blocking().ok()?),
+            })
+        }
+        Message::UnconfirmedTransaction(UnconfirmedTransaction { transaction_id, transaction, parent_ids }) => {
+            Message::UnconfirmedTransaction(UnconfirmedTransaction {
+                transaction_id,
+                transaction: Data::Object(transaction.deserialize_blocking().ok()?),
+                parent_ids,
+            })
+        }
+        message => message,
+    };
+    Some(message)
+}
+
+/// Returns the serialized bytes of the given message.
+pub fn serialize<N: Network>(message: &Message<N>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    message.serialize(&mut bytes).expect("A decoded message must serialize");
+    bytes
+}
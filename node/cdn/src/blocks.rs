@@ -18,6 +18,7 @@ use snarkos_node_ledger::Ledger;
 use snarkvm::prelude::{cow_to_copied, Block, ConsensusStorage, DeserializeOwned, Network};
 
 use anyhow::{anyhow, bail, Result};
+use bincode::Options;
 use colored::Colorize;
 use core::ops::Range;
 use futures::{Future, StreamExt};
@@ -29,7 +30,7 @@ use std::{
 };
 
 /// The number of blocks per file.
-const BLOCKS_PER_FILE: u32 = 50;
+pub const BLOCKS_PER_FILE: u32 = 50;
 /// The supported network.
 const NETWORK_ID: u16 = 3;
 
@@ -297,14 +298,25 @@ async fn cdn_get<T: 'static + DeserializeOwned + Send>(client: Client, url: &str
         Ok(bytes) => bytes,
         Err(error) => bail!("Failed to parse {ctx}: {error}"),
     };
-    // Parse the objects.
-    match tokio::task::spawn_blocking(move || bincode::deserialize::<T>(&bytes)).await {
+    // Parse the objects, rejecting any trailing bytes.
+    let options = bincode::DefaultOptions::new().with_fixint_encoding();
+    match tokio::task::spawn_blocking(move || options.deserialize::<T>(&bytes)).await {
         Ok(Ok(objects)) => Ok(objects),
         Ok(Err(error)) => bail!("Failed to deserialize {ctx}: {error}"),
         Err(error) => bail!("Failed to join task for {ctx}: {error}"),
     }
 }
 
+/// Serializes the given blocks into a block file, in the encoding of the CDN.
+pub fn serialize_block_file<N: Network>(blocks: &[Block<N>]) -> Result<Vec<u8>> {
+    Ok(bincode::DefaultOptions::new().with_fixint_encoding().serialize(blocks)?)
+}
+
+/// Deserializes the blocks of the given block file, in the encoding of the CDN, rejecting any trailing bytes.
+pub fn deserialize_block_file<N: Network>(bytes: &[u8]) -> Result<Vec<Block<N>>> {
+    Ok(bincode::DefaultOptions::new().with_fixint_encoding().deserialize(bytes)?)
+}
+
 /// Logs the progress of the sync.
 fn log_progress<const OBJECTS_PER_FILE: u32>(
     timer: Instant,
@@ -366,9 +378,11 @@ where
 mod tests {
     use crate::{
         blocks::{cdn_get, cdn_height, handle_dispatch_error, log_progress, BLOCKS_PER_FILE},
+        deserialize_block_file,
         load_blocks,
+        serialize_block_file,
     };
-    use snarkvm::prelude::{Block, Testnet3};
+    use snarkvm::prelude::{Block, FromBytes, Network, Testnet3};
 
     use anyhow::{anyhow, Result};
     use parking_lot::RwLock;
@@ -434,6 +448,18 @@ mod tests {
         check_load_blocks(start_height, end_height, 188);
     }
 
+    #[test]
+    fn test_block_file_roundtrip() {
+        let genesis = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let blocks = vec![genesis.clone(), genesis];
+
+        // Ensure the blocks round-trip, and trailing bytes are rejected.
+        let bytes = serialize_block_file(&blocks).unwrap();
+        assert_eq!(deserialize_block_file::<CurrentNetwork>(&bytes).unwrap(), blocks);
+        let padded = [bytes.as_slice(), &[0u8]].concat();
+        assert!(deserialize_block_file::<CurrentNetwork>(&padded).is_err());
+    }
+
     #[test]
     fn test_cdn_height() {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -20,4 +20,10 @@
 extern crate tracing;
 
 mod blocks;
-pub use blocks::{load_blocks, sync_ledger_with_cdn};
+pub use blocks::{BLOCKS_PER_FILE, deserialize_block_file, load_blocks, serialize_block_file, sync_ledger_with_cdn};
+
+mod metrics;
+pub use metrics::*;
+
+mod snapshot;
+pub use snapshot::*;
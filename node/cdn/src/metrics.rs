@@ -0,0 +1,759 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_ledger::Ledger;
+use snarkvm::prelude::{Block, ConsensusStorage, Network, ToBytes};
+
+use anyhow::{anyhow, bail, ensure, Error, Result};
+use core::{fmt, ops::Range, str::FromStr};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// The number of blocks read at once by an export of the chain metrics, which bounds the memory of the export.
+pub const CHAIN_METRICS_BATCH_SIZE: u32 = 100;
+/// The columns of an export of the chain metrics, in order.
+pub const CHAIN_METRICS_COLUMNS: [&str; 9] = [
+    "height",
+    "block_hash",
+    "timestamp",
+    "num_transactions",
+    "total_fees",
+    "total_size",
+    "difficulty",
+    "interval",
+    "reorg",
+];
+
+/// The metrics of a canonical block, which are a row of an export of the chain metrics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainMetricsRow<N: Network> {
+    /// The height of the block.
+    pub height: u32,
+    /// The hash of the block.
+    pub block_hash: N::BlockHash,
+    /// The timestamp of the block.
+    pub timestamp: i64,
+    /// The number of transactions in the block.
+    pub num_transactions: u32,
+    /// The total fee of the transactions in the block, in microcredits.
+    pub total_fees: u64,
+    /// The total size of the transactions in the block, in bytes.
+    pub total_size: u64,
+    /// The difficulty of the block, relative to the genesis proof target.
+    pub difficulty: f64,
+    /// The number of seconds since the previous block, or `None` for the genesis block.
+    pub interval: Option<i64>,
+    /// Whether the block replaced a block that was disconnected from the canonical chain at its height.
+    pub reorg: bool,
+}
+
+impl<N: Network> ChainMetricsRow<N> {
+    /// Initializes the row of the given block, from the timestamp of its previous block, if any.
+    pub fn new(block: &Block<N>, previous_timestamp: Option<i64>, difficulty: f64, reorg: bool) -> Result<Self> {
+        let mut total_fees = 0u64;
+        let mut total_size = 0u64;
+        for transaction in block.transactions().iter() {
+            total_fees = total_fees
+                .checked_add(*transaction.fee()?)
+                .ok_or_else(|| anyhow!("The fees of block {} overflow", block.height()))?;
+            total_size += transaction.to_bytes_le()?.len() as u64;
+        }
+        Ok(Self {
+            height: block.height(),
+            block_hash: block.hash(),
+            timestamp: block.timestamp(),
+            num_transactions: block.transactions().len() as u32,
+            total_fees,
+            total_size,
+            difficulty,
+            interval: previous_timestamp.map(|previous_timestamp| block.timestamp() - previous_timestamp),
+            reorg,
+        })
+    }
+
+    /// Returns the row as a line of CSV, without the line break.
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.height,
+            self.block_hash,
+            self.timestamp,
+            self.num_transactions,
+            self.total_fees,
+            self.total_size,
+            self.difficulty,
+            self.interval.map_or_else(String::new, |interval| interval.to_string()),
+            self.reorg
+        )
+    }
+}
+
+/// The source of the rows of an export of the chain metrics.
+pub trait ChainMetricsSource<N: Network> {
+    /// Returns the height of the latest canonical block.
+    fn latest_height(&self) -> u32;
+
+    /// Returns the hash of the canonical block at the given height.
+    fn get_hash(&self, height: u32) -> Result<N::BlockHash>;
+
+    /// Returns the rows of the canonical blocks at the given heights, in order.
+    fn get_rows(&self, heights: Range<u32>) -> Result<Vec<ChainMetricsRow<N>>>;
+}
+
+/// The source of the rows of an export of the chain metrics, from the blocks of a ledger.
+pub struct LedgerMetricsSource<'a, N: Network, C: ConsensusStorage<N>, D: Fn(u32) -> Result<Option<f64>>> {
+    /// The ledger.
+    ledger: &'a Ledger<N, C>,
+    /// The function returning the indexed difficulty of the block at the given height, if any.
+    difficulty: D,
+    /// The heights at which a block was disconnected from the canonical chain.
+    reorgs: BTreeSet<u32>,
+}
+
+impl<'a, N: Network, C: ConsensusStorage<N>, D: Fn(u32) -> Result<Option<f64>>> LedgerMetricsSource<'a, N, C, D> {
+    /// Initializes the source from the given ledger, the function returning the indexed difficulty of a block,
+    /// and the heights at which a block was disconnected from the canonical chain.
+    pub fn new(ledger: &'a Ledger<N, C>, difficulty: D, reorgs: BTreeSet<u32>) -> Self {
+        Self { ledger, difficulty, reorgs }
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>, D: Fn(u32) -> Result<Option<f64>>> ChainMetricsSource<N>
+    for LedgerMetricsSource<'_, N, C, D>
+{
+    fn latest_height(&self) -> u32 {
+        self.ledger.latest_height()
+    }
+
+    fn get_hash(&self, height: u32) -> Result<N::BlockHash> {
+        self.ledger.get_hash(height)
+    }
+
+    fn get_rows(&self, heights: Range<u32>) -> Result<Vec<ChainMetricsRow<N>>> {
+        let mut previous_timestamp = match heights.start.checked_sub(1) {
+            Some(height) => Some(self.ledger.get_header(height)?.timestamp()),
+            None => None,
+        };
+        let mut rows = Vec::with_capacity(heights.len());
+        for height in heights {
+            let block = self.ledger.get_block(height)?;
+            // Fall back to the difficulty of the proof target, if the block is not indexed yet.
+            let difficulty = match (self.difficulty)(height)? {
+                Some(difficulty) => difficulty,
+                None => block.proof_target() as f64 / N::GENESIS_PROOF_TARGET.max(1) as f64,
+            };
+            rows.push(ChainMetricsRow::new(&block, previous_timestamp, difficulty, self.reorgs.contains(&height))?);
+            previous_timestamp = Some(block.timestamp());
+        }
+        Ok(rows)
+    }
+}
+
+/// The file format of an export of the chain metrics.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainMetricsFormat {
+    /// Comma-separated values, with a header line.
+    #[default]
+    Csv,
+    /// Apache Parquet, which requires the `parquet` feature.
+    Parquet,
+}
+
+impl FromStr for ChainMetricsFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            _ => bail!("Invalid metrics format '{format}' (expected one of csv, parquet)"),
+        }
+    }
+}
+
+impl fmt::Display for ChainMetricsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+            Self::Parquet => write!(f, "parquet"),
+        }
+    }
+}
+
+/// A request to export the chain metrics over a range of heights.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainMetricsExport {
+    /// The path to the exported file.
+    pub output: PathBuf,
+    /// The file format of the export.
+    #[serde(default)]
+    pub format: ChainMetricsFormat,
+    /// The height of the first exported block, unless the export appends to a previous export.
+    #[serde(default)]
+    pub from: u32,
+    /// The height of the last exported block, which defaults to the latest block.
+    #[serde(default)]
+    pub to: Option<u32>,
+    /// Whether to only append the blocks above the cursor of the previous export, if it is still valid.
+    #[serde(default)]
+    pub append: bool,
+    /// The path to the cursor of the export, which defaults to the output path with a `.cursor` suffix.
+    #[serde(default)]
+    pub cursor: Option<PathBuf>,
+}
+
+impl ChainMetricsExport {
+    /// Returns the path to the cursor of the export.
+    pub fn cursor_path(&self) -> PathBuf {
+        self.cursor.clone().unwrap_or_else(|| with_suffix(&self.output, "cursor"))
+    }
+}
+
+/// The cursor of an export of the chain metrics, which is persisted next to the exported file,
+/// so that the next export only appends the blocks above it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ChainMetricsCursor<N: Network> {
+    /// The network ID.
+    pub network: u16,
+    /// The file format of the export.
+    pub format: ChainMetricsFormat,
+    /// The height of the first exported block.
+    pub start_height: u32,
+    /// The height of the last exported block.
+    pub height: u32,
+    /// The hash of the last exported block.
+    pub block_hash: N::BlockHash,
+    /// The number of rows in the exported file.
+    pub num_rows: u64,
+}
+
+impl<N: Network> ChainMetricsCursor<N> {
+    /// Loads the cursor from the given path.
+    pub fn load(path: &Path) -> Result<Self> {
+        let cursor: Self = serde_json::from_slice(&fs::read(path)?)?;
+        ensure!(cursor.network == N::ID, "The cursor is for network {}, instead of network {}", cursor.network, N::ID);
+        Ok(cursor)
+    }
+}
+
+/// The outcome of an export of the chain metrics.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ChainMetricsSummary<N: Network> {
+    /// The cursor of the exported file.
+    pub cursor: ChainMetricsCursor<N>,
+    /// The number of rows written by the export.
+    pub num_rows: u64,
+    /// Whether the rows were appended to the previous export.
+    pub appended: bool,
+}
+
+/// Returns the given path, with the given suffix appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map_or_else(OsString::new, |file_name| file_name.to_os_string());
+    file_name.push(format!(".{suffix}"));
+    path.with_file_name(file_name)
+}
+
+/// Replaces the file at the given path with the file written by the given function, through a temporary file,
+/// so that a reader of the path never sees a partially written file.
+fn replace_file<T>(path: &Path, write: impl FnOnce(File) -> Result<(T, File)>) -> Result<T> {
+    if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        fs::create_dir_all(directory)?;
+    }
+    let temporary = with_suffix(path, "partial");
+    let result = File::create(&temporary).map_err(Error::from).and_then(write).and_then(|(output, file)| {
+        file.sync_all()?;
+        Ok(output)
+    });
+    match result {
+        Ok(output) => {
+            fs::rename(&temporary, path)?;
+            Ok(output)
+        }
+        Err(error) => {
+            let _ = fs::remove_file(&temporary);
+            Err(error)
+        }
+    }
+}
+
+/// Returns the cursor of the previous export, if the rows above it can be appended to the exported file.
+fn resumable_cursor<N: Network>(
+    source: &impl ChainMetricsSource<N>,
+    request: &ChainMetricsExport,
+) -> Option<ChainMetricsCursor<N>> {
+    let cursor_path = request.cursor_path();
+    if !cursor_path.exists() {
+        return None;
+    }
+    let check = || -> Result<ChainMetricsCursor<N>> {
+        let cursor = ChainMetricsCursor::<N>::load(&cursor_path)?;
+        ensure!(cursor.format == request.format, "The previous export is in the {} format", cursor.format);
+        ensure!(
+            source.get_hash(cursor.height).ok() == Some(cursor.block_hash),
+            "The last exported block {} is no longer canonical",
+            cursor.height
+        );
+        let num_rows = count_rows(&request.output, cursor.format)?;
+        ensure!(num_rows == cursor.num_rows, "The exported file has {num_rows} rows, instead of {}", cursor.num_rows);
+        Ok(cursor)
+    };
+    match check() {
+        Ok(cursor) => Some(cursor),
+        Err(error) => {
+            warn!("Exporting the chain metrics again, as the previous export cannot be appended to - {error}");
+            None
+        }
+    }
+}
+
+/// Exports the metrics of the canonical blocks of the given source to a file, and persists the cursor of the export.
+///
+/// If the request appends to a previous export, whose last block is still canonical, only the blocks above it are
+/// appended, and the export is otherwise written again from the start. The blocks are read in batches of
+/// `CHAIN_METRICS_BATCH_SIZE`, and the file is replaced once it is fully written, so that its readers never see
+/// a partial export.
+pub fn export_chain_metrics<N: Network>(
+    source: &impl ChainMetricsSource<N>,
+    request: &ChainMetricsExport,
+) -> Result<ChainMetricsSummary<N>> {
+    let latest_height = source.latest_height();
+    let end_height = request.to.map_or(latest_height, |to| to.min(latest_height));
+
+    // Resume from the cursor of the previous export, if any.
+    let previous = match request.append {
+        true => resumable_cursor(source, request),
+        false => None,
+    };
+    if let Some(cursor) = previous.as_ref().filter(|cursor| cursor.height >= end_height) {
+        debug!("The chain metrics are already exported up to block {}", cursor.height);
+        return Ok(ChainMetricsSummary { cursor: cursor.clone(), num_rows: 0, appended: true });
+    }
+    let start_height = previous.as_ref().map_or(request.from, |cursor| cursor.height + 1);
+    ensure!(start_height <= end_height, "The export starts at block {start_height}, after block {end_height}");
+
+    // Write the rows, after the rows of the previous export, if any.
+    let existing = previous.as_ref().map(|_| request.output.as_path());
+    let (last_hash, num_rows) = replace_file(&request.output, |file| {
+        let mut writer = rows_writer::<N>(request.format, file, existing)?;
+        let mut last_hash = None;
+        let mut num_rows = 0u64;
+        let mut height = start_height;
+        while height <= end_height {
+            let heights = height..end_height.saturating_add(1).min(height.saturating_add(CHAIN_METRICS_BATCH_SIZE));
+            let rows = source.get_rows(heights.clone())?;
+            ensure!(
+                rows.iter().map(|row| row.height).eq(heights.clone()),
+                "Missing the metrics of blocks {heights:?}"
+            );
+            writer.write_rows(&rows)?;
+            last_hash = rows.last().map(|row| row.block_hash);
+            num_rows += rows.len() as u64;
+            height = heights.end;
+        }
+        let last_hash = last_hash.ok_or_else(|| anyhow!("There are no blocks to export"))?;
+        // Ensure the exported blocks remained canonical, as the chain may reorganize during the export.
+        ensure!(
+            source.get_hash(end_height)? == last_hash,
+            "The chain was reorganized during the export of the metrics up to block {end_height}"
+        );
+        Ok(((last_hash, num_rows), writer.finish()?))
+    })?;
+
+    // Persist the cursor, once the exported file is replaced.
+    let cursor = ChainMetricsCursor {
+        network: N::ID,
+        format: request.format,
+        start_height: previous.as_ref().map_or(start_height, |cursor| cursor.start_height),
+        height: end_height,
+        block_hash: last_hash,
+        num_rows: previous.as_ref().map_or(0, |cursor| cursor.num_rows) + num_rows,
+    };
+    replace_file(&request.cursor_path(), |mut file| {
+        file.write_all(&serde_json::to_vec_pretty(&cursor)?)?;
+        Ok(((), file))
+    })?;
+    Ok(ChainMetricsSummary { cursor, num_rows, appended: previous.is_some() })
+}
+
+/// A writer of the rows of an export of the chain metrics.
+trait RowsWriter<N: Network> {
+    /// Writes the given rows.
+    fn write_rows(&mut self, rows: &[ChainMetricsRow<N>]) -> Result<()>;
+
+    /// Flushes the rows, and returns the written file.
+    fn finish(self: Box<Self>) -> Result<File>;
+}
+
+/// Returns a writer of rows in the given format to the given file, starting with the rows of the given file, if any.
+fn rows_writer<N: Network>(
+    format: ChainMetricsFormat,
+    file: File,
+    existing: Option<&Path>,
+) -> Result<Box<dyn RowsWriter<N>>> {
+    match format {
+        ChainMetricsFormat::Csv => Ok(Box::new(CsvWriter::new(file, existing)?)),
+        #[cfg(feature = "parquet")]
+        ChainMetricsFormat::Parquet => Ok(Box::new(parquet::ParquetWriter::new(file, existing)?)),
+        #[cfg(not(feature = "parquet"))]
+        ChainMetricsFormat::Parquet => bail!("The Parquet format requires the 'parquet' feature"),
+    }
+}
+
+/// Returns the number of rows in the given exported file.
+fn count_rows(path: &Path, format: ChainMetricsFormat) -> Result<u64> {
+    match format {
+        ChainMetricsFormat::Csv => {
+            let mut num_lines = 0u64;
+            for line in BufReader::new(File::open(path)?).lines() {
+                line?;
+                num_lines += 1;
+            }
+            Ok(num_lines.saturating_sub(1))
+        }
+        #[cfg(feature = "parquet")]
+        ChainMetricsFormat::Parquet => parquet::count_rows(path),
+        #[cfg(not(feature = "parquet"))]
+        ChainMetricsFormat::Parquet => bail!("The Parquet format requires the 'parquet' feature"),
+    }
+}
+
+/// A writer of the rows in CSV.
+struct CsvWriter {
+    writer: BufWriter<File>,
+}
+
+impl CsvWriter {
+    /// Initializes the writer, copying the given file, or writing the header line otherwise.
+    fn new(file: File, existing: Option<&Path>) -> Result<Self> {
+        let mut writer = BufWriter::new(file);
+        match existing {
+            Some(path) => {
+                io::copy(&mut File::open(path)?, &mut writer)?;
+            }
+            None => writeln!(writer, "{}", CHAIN_METRICS_COLUMNS.join(","))?,
+        }
+        Ok(Self { writer })
+    }
+}
+
+impl<N: Network> RowsWriter<N> for CsvWriter {
+    fn write_rows(&mut self, rows: &[ChainMetricsRow<N>]) -> Result<()> {
+        for row in rows {
+            writeln!(self.writer, "{}", row.to_csv())?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<File> {
+        self.writer.into_inner().map_err(|error| error.into_error().into())
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet {
+    use super::{ChainMetricsRow, RowsWriter, CHAIN_METRICS_BATCH_SIZE, CHAIN_METRICS_COLUMNS};
+    use snarkvm::prelude::Network;
+
+    use ::parquet::{
+        arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+        file::properties::WriterProperties,
+    };
+    use anyhow::Result;
+    use arrow_array::{
+        ArrayRef,
+        BooleanArray,
+        Float64Array,
+        Int64Array,
+        RecordBatch,
+        StringArray,
+        UInt32Array,
+        UInt64Array,
+    };
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use std::{fs::File, path::Path, sync::Arc};
+
+    /// The maximum number of rows in a row group, which bounds the rows buffered by the writer.
+    const MAX_ROW_GROUP_SIZE: usize = 10 * CHAIN_METRICS_BATCH_SIZE as usize;
+
+    /// Returns the schema of the rows.
+    fn schema() -> SchemaRef {
+        let types = [
+            (DataType::UInt32, false),
+            (DataType::Utf8, false),
+            (DataType::Int64, false),
+            (DataType::UInt32, false),
+            (DataType::UInt64, false),
+            (DataType::UInt64, false),
+            (DataType::Float64, false),
+            (DataType::Int64, true),
+            (DataType::Boolean, false),
+        ];
+        let fields = CHAIN_METRICS_COLUMNS
+            .iter()
+            .zip(types)
+            .map(|(name, (data_type, nullable))| Field::new(*name, data_type, nullable))
+            .collect::<Vec<_>>();
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Returns the number of rows in the given Parquet file.
+    pub(super) fn count_rows(path: &Path) -> Result<u64> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+        Ok(builder.metadata().file_metadata().num_rows() as u64)
+    }
+
+    /// A writer of the rows in Parquet.
+    pub(super) struct ParquetWriter {
+        writer: ArrowWriter<File>,
+        schema: SchemaRef,
+    }
+
+    impl ParquetWriter {
+        /// Initializes the writer, copying the rows of the given file, batch by batch.
+        pub(super) fn new(file: File, existing: Option<&Path>) -> Result<Self> {
+            let schema = schema();
+            let properties = WriterProperties::builder().set_max_row_group_size(MAX_ROW_GROUP_SIZE).build();
+            let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+            if let Some(path) = existing {
+                let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+                    .with_batch_size(CHAIN_METRICS_BATCH_SIZE as usize)
+                    .build()?;
+                for batch in reader {
+                    writer.write(&batch?)?;
+                }
+            }
+            Ok(Self { writer, schema })
+        }
+    }
+
+    impl<N: Network> RowsWriter<N> for ParquetWriter {
+        fn write_rows(&mut self, rows: &[ChainMetricsRow<N>]) -> Result<()> {
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(UInt32Array::from_iter_values(rows.iter().map(|row| row.height))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.block_hash.to_string()))),
+                Arc::new(Int64Array::from_iter_values(rows.iter().map(|row| row.timestamp))),
+                Arc::new(UInt32Array::from_iter_values(rows.iter().map(|row| row.num_transactions))),
+                Arc::new(UInt64Array::from_iter_values(rows.iter().map(|row| row.total_fees))),
+                Arc::new(UInt64Array::from_iter_values(rows.iter().map(|row| row.total_size))),
+                Arc::new(Float64Array::from_iter_values(rows.iter().map(|row| row.difficulty))),
+                Arc::new(rows.iter().map(|row| row.interval).collect::<Int64Array>()),
+                Arc::new(BooleanArray::from(rows.iter().map(|row| row.reorg).collect::<Vec<_>>())),
+            ];
+            self.writer.write(&RecordBatch::try_new(self.schema.clone(), columns)?)?;
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>) -> Result<File> {
+            Ok(self.writer.into_inner()?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Field, FromBytes, Header, Metadata, PrivateKey, TestRng, Testnet3, Zero};
+
+    use once_cell::sync::OnceCell;
+
+    type CurrentNetwork = Testnet3;
+
+    /// The height of the first export of the sample chain.
+    const FIRST_EXPORT_HEIGHT: u32 = 120;
+    /// The height of the sample chain.
+    const CHAIN_HEIGHT: u32 = 250;
+
+    /// A sample chain, which reuses the genesis transactions in every block.
+    struct SampleChain {
+        blocks: Vec<Block<CurrentNetwork>>,
+        /// The height of the latest canonical block.
+        latest_height: u32,
+        /// The heights at which a block was disconnected.
+        reorgs: BTreeSet<u32>,
+    }
+
+    impl ChainMetricsSource<CurrentNetwork> for SampleChain {
+        fn latest_height(&self) -> u32 {
+            self.latest_height
+        }
+
+        fn get_hash(&self, height: u32) -> Result<<CurrentNetwork as Network>::BlockHash> {
+            match height <= self.latest_height {
+                true => Ok(self.blocks[height as usize].hash()),
+                false => bail!("Missing block {height}"),
+            }
+        }
+
+        fn get_rows(&self, heights: Range<u32>) -> Result<Vec<ChainMetricsRow<CurrentNetwork>>> {
+            heights
+                .map(|height| {
+                    let block = &self.blocks[height as usize];
+                    let previous_timestamp = height.checked_sub(1).map(|height| self.blocks[height as usize].timestamp());
+                    ChainMetricsRow::new(block, previous_timestamp, height as f64 / 2.0, self.reorgs.contains(&height))
+                })
+                .collect()
+        }
+    }
+
+    /// Returns the genesis block, followed by `CHAIN_HEIGHT` blocks with varying intervals.
+    fn sample_blocks() -> &'static [Block<CurrentNetwork>] {
+        static INSTANCE: OnceCell<Vec<Block<CurrentNetwork>>> = OnceCell::new();
+        INSTANCE.get_or_init(|| {
+            let rng = &mut TestRng::default();
+            let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+            let mut blocks = vec![Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap()];
+            for height in 1..=CHAIN_HEIGHT {
+                let previous_block = blocks.last().unwrap();
+                let metadata = previous_block.header().metadata();
+                let metadata = Metadata::new(
+                    metadata.network(),
+                    metadata.round() + 1,
+                    height,
+                    metadata.total_supply_in_microcredits(),
+                    metadata.cumulative_proof_target(),
+                    metadata.coinbase_target(),
+                    metadata.proof_target(),
+                    metadata.last_coinbase_target(),
+                    metadata.last_coinbase_timestamp(),
+                    metadata.timestamp() + 1 + (height % 7) as i64,
+                )
+                .unwrap();
+                let transactions = previous_block.transactions().clone();
+                let header = Header::from(
+                    *previous_block.hash(),
+                    transactions.to_root().unwrap(),
+                    Field::zero(),
+                    Field::zero(),
+                    metadata,
+                )
+                .unwrap();
+                let block = Block::new(&private_key, previous_block.hash(), header, transactions, None, rng).unwrap();
+                blocks.push(block);
+            }
+            blocks
+        })
+    }
+
+    /// Returns the sample chain up to the given height.
+    fn sample_chain(latest_height: u32) -> SampleChain {
+        SampleChain { blocks: sample_blocks().to_vec(), latest_height, reorgs: [40, 41, 200].into_iter().collect() }
+    }
+
+    /// Returns a request for an export to a temporary file with the given name.
+    fn sample_request(name: &str, format: ChainMetricsFormat) -> ChainMetricsExport {
+        let directory = std::env::temp_dir().join(format!("snarkos-metrics-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        ChainMetricsExport { output: directory.join("metrics"), format, from: 0, to: None, append: false, cursor: None }
+    }
+
+    /// Exports the sample chain up to `FIRST_EXPORT_HEIGHT`, and appends the blocks up to `CHAIN_HEIGHT`,
+    /// and returns the incremental and the full exports.
+    fn export_twice(name: &str, format: ChainMetricsFormat) -> (ChainMetricsExport, ChainMetricsExport) {
+        let incremental = ChainMetricsExport { append: true, ..sample_request(&format!("{name}-inc"), format) };
+        let summary = export_chain_metrics(&sample_chain(FIRST_EXPORT_HEIGHT), &incremental).unwrap();
+        assert_eq!((summary.num_rows, summary.appended), (FIRST_EXPORT_HEIGHT as u64 + 1, false));
+        let summary = export_chain_metrics(&sample_chain(CHAIN_HEIGHT), &incremental).unwrap();
+        assert_eq!((summary.num_rows, summary.appended), ((CHAIN_HEIGHT - FIRST_EXPORT_HEIGHT) as u64, true));
+        assert_eq!(summary.cursor.height, CHAIN_HEIGHT);
+        assert_eq!(summary.cursor.num_rows, CHAIN_HEIGHT as u64 + 1);
+
+        let full = sample_request(&format!("{name}-full"), format);
+        let summary = export_chain_metrics(&sample_chain(CHAIN_HEIGHT), &full).unwrap();
+        assert_eq!((summary.num_rows, summary.appended), (CHAIN_HEIGHT as u64 + 1, false));
+        (incremental, full)
+    }
+
+    #[test]
+    fn test_incremental_csv_export() {
+        let (incremental, full) = export_twice("csv", ChainMetricsFormat::Csv);
+        let exported = fs::read_to_string(&incremental.output).unwrap();
+        assert_eq!(exported, fs::read_to_string(&full.output).unwrap());
+        assert!(!with_suffix(&incremental.output, "partial").exists());
+
+        // Ensure the rows hold the metrics of the blocks.
+        let lines = exported.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], CHAIN_METRICS_COLUMNS.join(","));
+        let genesis = lines[1].split(',').collect::<Vec<_>>();
+        assert_eq!(genesis[0], "0");
+        assert_eq!(genesis[3], sample_blocks()[0].transactions().len().to_string());
+        assert_eq!(genesis[7], "");
+        let row = lines[41].split(',').collect::<Vec<_>>();
+        assert_eq!((row[0], row[6], row[7], row[8]), ("40", "20", "6", "true"));
+
+        // Ensure an export without new blocks leaves the file untouched.
+        let summary = export_chain_metrics(&sample_chain(CHAIN_HEIGHT), &incremental).unwrap();
+        assert_eq!((summary.num_rows, summary.cursor.height), (0, CHAIN_HEIGHT));
+        assert_eq!(fs::read_to_string(&incremental.output).unwrap(), exported);
+    }
+
+    #[test]
+    fn test_export_after_reorg_or_truncation() {
+        let request = ChainMetricsExport { append: true, ..sample_request("reorg", ChainMetricsFormat::Csv) };
+        export_chain_metrics(&sample_chain(FIRST_EXPORT_HEIGHT), &request).unwrap();
+
+        // Ensure the file is exported again, if the last exported block is no longer canonical.
+        let mut cursor = ChainMetricsCursor::<CurrentNetwork>::load(&request.cursor_path()).unwrap();
+        cursor.block_hash = sample_blocks()[0].hash();
+        fs::write(request.cursor_path(), serde_json::to_vec(&cursor).unwrap()).unwrap();
+        let summary = export_chain_metrics(&sample_chain(FIRST_EXPORT_HEIGHT + 10), &request).unwrap();
+        assert_eq!((summary.num_rows, summary.appended), (FIRST_EXPORT_HEIGHT as u64 + 11, false));
+
+        // Ensure the file is exported again, if its rows do not match the cursor.
+        let exported = fs::read_to_string(&request.output).unwrap();
+        let truncated = exported.lines().take(50).map(|line| format!("{line}\n")).collect::<String>();
+        fs::write(&request.output, truncated).unwrap();
+        let summary = export_chain_metrics(&sample_chain(CHAIN_HEIGHT), &request).unwrap();
+        assert_eq!((summary.num_rows, summary.appended), (CHAIN_HEIGHT as u64 + 1, false));
+
+        // Ensure a range above the latest block is rejected, and leaves the export untouched.
+        let request = ChainMetricsExport { from: CHAIN_HEIGHT + 1, append: false, ..request };
+        assert!(export_chain_metrics(&sample_chain(CHAIN_HEIGHT), &request).is_err());
+        assert_eq!(count_rows(&request.output, ChainMetricsFormat::Csv).unwrap(), CHAIN_HEIGHT as u64 + 1);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_incremental_parquet_export() {
+        use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        // Returns the rows of the given Parquet file, in batches spanning its row groups.
+        let read = |path: &Path| {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+            builder.with_batch_size(1024).build().unwrap().collect::<Result<Vec<_>, _>>().unwrap()
+        };
+        let (incremental, full) = export_twice("parquet", ChainMetricsFormat::Parquet);
+        assert_eq!(read(&incremental.output), read(&full.output));
+        assert_eq!(count_rows(&incremental.output, ChainMetricsFormat::Parquet).unwrap(), CHAIN_HEIGHT as u64 + 1);
+    }
+
+    #[test]
+    fn test_metrics_format() {
+        assert_eq!("parquet".parse::<ChainMetricsFormat>().unwrap(), ChainMetricsFormat::Parquet);
+        assert_eq!(ChainMetricsFormat::Csv.to_string().parse::<ChainMetricsFormat>().unwrap(), ChainMetricsFormat::Csv);
+        assert!("json".parse::<ChainMetricsFormat>().is_err());
+        assert_eq!(with_suffix(Path::new("out/metrics.csv"), "cursor"), PathBuf::from("out/metrics.csv.cursor"));
+    }
+}
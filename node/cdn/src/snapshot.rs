@@ -0,0 +1,515 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{deserialize_block_file, serialize_block_file, BLOCKS_PER_FILE};
+use snarkos_node_ledger::Ledger;
+use snarkvm::prelude::{Block, ConsensusStorage, Field, Network};
+
+use anyhow::{bail, ensure, Result};
+use core::ops::Range;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The name of the manifest file in a snapshot directory, which is written once every chunk is written.
+pub const SNAPSHOT_MANIFEST_FILE: &str = "manifest.json";
+
+/// A block of the canonical chain that a snapshot starts from or ends at, with the root of the Merkle mountain
+/// range over the block hashes up to it, which commits to the full lineage of the block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SnapshotLinkage<N: Network> {
+    /// The height of the block.
+    pub height: u32,
+    /// The hash of the block.
+    pub block_hash: N::BlockHash,
+    /// The root of the Merkle mountain range over the block hashes, up to the block.
+    pub chain_root: Field<N>,
+}
+
+/// A chunk of a snapshot, which is a block file of the CDN, in the same encoding.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotChunk {
+    /// The height of the first block in the chunk.
+    pub start_height: u32,
+    /// The height after the last block in the chunk.
+    pub end_height: u32,
+    /// The SHA-256 hash of the chunk, in hex.
+    pub hash: String,
+}
+
+impl SnapshotChunk {
+    /// Returns the name of the file of the chunk, as named on the CDN.
+    pub fn file_name(&self) -> String {
+        format!("{}.{}.blocks", self.start_height, self.end_height)
+    }
+
+    /// Returns the range of the heights of the blocks in the chunk.
+    pub const fn heights(&self) -> Range<u32> {
+        self.start_height..self.end_height
+    }
+}
+
+/// The manifest of a snapshot directory, which holds the canonical blocks above its base, up to its tip.
+///
+/// A full snapshot starts from the genesis block, and an incremental snapshot starts from the tip of the previous
+/// snapshot, so that a base snapshot and its increments are applied in order. The chunk boundaries are aligned on
+/// the heights of the CDN block files, so that the chunks of successive exports are identical, and a partially
+/// transferred snapshot resumes from the chunks that are missing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SnapshotFileManifest<N: Network> {
+    /// The ID of the network.
+    pub network: u16,
+    /// The hash of the genesis block.
+    pub genesis_hash: N::BlockHash,
+    /// The block that the snapshot starts from, or `None` if the snapshot starts from the genesis block.
+    pub base: Option<SnapshotLinkage<N>>,
+    /// The last block in the snapshot.
+    pub tip: SnapshotLinkage<N>,
+    /// The chunks of the snapshot, in order.
+    pub chunks: Vec<SnapshotChunk>,
+}
+
+impl<N: Network> SnapshotFileManifest<N> {
+    /// Loads the manifest of the given snapshot directory, or the given manifest file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(&fs::read(manifest_path(path))?)?;
+        manifest.check()?;
+        Ok(manifest)
+    }
+
+    /// Returns the height of the block that the snapshot starts from.
+    pub fn base_height(&self) -> u32 {
+        self.base.map_or(0, |base| base.height)
+    }
+
+    /// Checks the manifest is for the network, and its chunks cover the blocks above its base, up to its tip.
+    pub fn check(&self) -> Result<()> {
+        ensure!(self.network == N::ID, "The snapshot is for network {}, instead of network {}", self.network, N::ID);
+        ensure!(
+            self.tip.height > self.base_height(),
+            "The snapshot tip {} is not above its base {}",
+            self.tip.height,
+            self.base_height()
+        );
+        // Ensure the chunks are contiguous.
+        let mut next_height = self.base_height() + 1;
+        for chunk in &self.chunks {
+            ensure!(
+                chunk.start_height == next_height && chunk.end_height > chunk.start_height,
+                "The snapshot chunk '{}' does not follow block {}",
+                chunk.file_name(),
+                next_height - 1
+            );
+            next_height = chunk.end_height;
+        }
+        ensure!(next_height == self.tip.height + 1, "The snapshot chunks do not reach the snapshot tip");
+        Ok(())
+    }
+
+    /// Returns the chunks that are missing from the given snapshot directory, or do not match their hash.
+    pub fn missing_chunks(&self, directory: &Path) -> Vec<&SnapshotChunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| match fs::read(directory.join(chunk.file_name())) {
+                Ok(bytes) => chunk_hash(&bytes) != chunk.hash,
+                Err(_) => true,
+            })
+            .collect()
+    }
+
+    /// Returns the blocks of the given chunk, from the given snapshot directory, once the chunk matches its hash.
+    pub fn read_chunk(&self, directory: &Path, chunk: &SnapshotChunk) -> Result<Vec<Block<N>>> {
+        let bytes = fs::read(directory.join(chunk.file_name()))?;
+        ensure!(chunk_hash(&bytes) == chunk.hash, "Snapshot chunk '{}' does not match its hash", chunk.file_name());
+        let blocks = deserialize_block_file::<N>(&bytes)?;
+        // Ensure the chunk holds the blocks of its range, in order.
+        ensure!(
+            blocks.iter().map(Block::height).eq(chunk.heights()),
+            "Snapshot chunk '{}' does not hold the blocks of its range",
+            chunk.file_name()
+        );
+        Ok(blocks)
+    }
+}
+
+/// Returns the path to the manifest file, if the given path is a snapshot directory, or the given path otherwise.
+pub fn manifest_path(path: &Path) -> PathBuf {
+    match path.is_dir() {
+        true => path.join(SNAPSHOT_MANIFEST_FILE),
+        false => path.to_path_buf(),
+    }
+}
+
+/// Returns the hash of the given chunk bytes, in hex.
+fn chunk_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Returns the ranges of the heights of the chunks from the given start height, up to the given end height,
+/// which are aligned on the heights of the CDN block files, after the genesis block.
+fn chunk_ranges(start_height: u32, end_height: u32) -> Vec<Range<u32>> {
+    let mut ranges = Vec::new();
+    let mut height = start_height;
+    while height < end_height {
+        let boundary = ((height - 1) / BLOCKS_PER_FILE + 1) * BLOCKS_PER_FILE + 1;
+        ranges.push(height..boundary.min(end_height));
+        height = boundary;
+    }
+    ranges
+}
+
+/// Returns `true` if the given chunk bytes hold the canonical blocks of the given ledger, at the given heights.
+fn is_canonical_chunk<N: Network, C: ConsensusStorage<N>>(
+    ledger: &Ledger<N, C>,
+    bytes: &[u8],
+    heights: Range<u32>,
+) -> bool {
+    match deserialize_block_file::<N>(bytes) {
+        Ok(blocks) => {
+            blocks.iter().map(|block| Some(block.hash())).eq(heights.map(|height| ledger.get_hash(height).ok()))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Exports the canonical blocks of the given ledger to the given snapshot directory, and returns its manifest.
+///
+/// If a previous manifest is given, only the blocks above its tip are exported, and the snapshot starts from its
+/// tip, which must be on the canonical chain. The given chain root is the root of the Merkle mountain range over
+/// the block hashes of the ledger, up to its latest block. The chunks already written to the directory are kept,
+/// so that an interrupted export resumes, and the manifest is written last.
+pub fn export_canon_since<N: Network, C: ConsensusStorage<N>>(
+    ledger: &Ledger<N, C>,
+    since: Option<&SnapshotFileManifest<N>>,
+    chain_root: Field<N>,
+    directory: &Path,
+) -> Result<SnapshotFileManifest<N>> {
+    let genesis_hash = ledger.get_hash(0)?;
+    // Ensure the previous snapshot is on the canonical chain.
+    let base = match since {
+        Some(since) => {
+            since.check()?;
+            ensure!(since.genesis_hash == genesis_hash, "The previous snapshot is for another genesis block");
+            let tip = since.tip;
+            match ledger.get_hash(tip.height) {
+                Ok(hash) if hash == tip.block_hash => Some(tip),
+                _ => bail!("The tip of the previous snapshot (block {}) is not canonical", tip.height),
+            }
+        }
+        None => None,
+    };
+    let start_height = base.map_or(0, |base| base.height) + 1;
+    let latest_height = ledger.latest_height();
+    ensure!(start_height <= latest_height, "There are no blocks above the previous snapshot (block {latest_height})");
+
+    // Write the chunks, skipping the chunks that were already written.
+    fs::create_dir_all(directory)?;
+    let mut chunks = Vec::new();
+    for heights in chunk_ranges(start_height, latest_height + 1) {
+        let mut chunk = SnapshotChunk { start_height: heights.start, end_height: heights.end, hash: String::new() };
+        let path = directory.join(chunk.file_name());
+        let bytes = match fs::read(&path) {
+            Ok(bytes) if is_canonical_chunk(ledger, &bytes, heights.clone()) => bytes,
+            _ => {
+                let bytes = serialize_block_file(&ledger.get_blocks(heights)?)?;
+                // Write the chunk to a temporary file first, so that a partially written chunk is not kept.
+                let temporary = path.with_extension("partial");
+                fs::write(&temporary, &bytes)?;
+                fs::rename(&temporary, &path)?;
+                bytes
+            }
+        };
+        chunk.hash = chunk_hash(&bytes);
+        chunks.push(chunk);
+    }
+
+    let tip = SnapshotLinkage { height: latest_height, block_hash: ledger.get_hash(latest_height)?, chain_root };
+    let manifest = SnapshotFileManifest { network: N::ID, genesis_hash, base, tip, chunks };
+    manifest.check()?;
+    fs::write(directory.join(SNAPSHOT_MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+/// Imports the snapshot in the given directory into the given ledger, processing each block above the latest block
+/// with the given function, and returns the height of the snapshot tip.
+///
+/// The snapshot must start from the latest block of the ledger, whose lineage is checked against the chain root of
+/// the snapshot base with the given function, which returns the root of the Merkle mountain range over the block
+/// hashes of the ledger. If the ledger is already above the snapshot base, the blocks it holds are checked against
+/// the snapshot, and skipped, so that an interrupted import resumes.
+pub fn import_snapshot<N: Network, C: ConsensusStorage<N>>(
+    ledger: &Ledger<N, C>,
+    directory: &Path,
+    chain_root: impl Fn() -> Result<Field<N>>,
+    mut process: impl FnMut(Block<N>) -> Result<()>,
+) -> Result<u32> {
+    let manifest = SnapshotFileManifest::<N>::load(directory)?;
+    ensure!(manifest.genesis_hash == ledger.get_hash(0)?, "The snapshot is for another genesis block");
+    // Ensure every chunk was transferred.
+    let missing = manifest.missing_chunks(directory);
+    if !missing.is_empty() {
+        let names = missing.iter().map(|chunk| chunk.file_name()).collect::<Vec<_>>();
+        bail!("The snapshot is missing {} chunks ({})", names.len(), names.join(", "));
+    }
+
+    // Ensure the snapshot starts from a block of the ledger.
+    let latest_height = ledger.latest_height();
+    let base_height = manifest.base_height();
+    ensure!(
+        latest_height >= base_height,
+        "The snapshot starts from block {base_height}, but the ledger is at block {latest_height}"
+    );
+    if let Some(base) = manifest.base {
+        ensure!(
+            ledger.get_hash(base.height)? == base.block_hash,
+            "The snapshot starts from block {} ('{}'), which is not in the ledger",
+            base.height,
+            base.block_hash
+        );
+        if latest_height == base.height {
+            ensure!(chain_root()? == base.chain_root, "The lineage of the ledger does not match the snapshot base");
+        }
+    }
+    // Ensure the ledger is not on another branch, if it is already at or above the snapshot tip.
+    let tip = manifest.tip;
+    if latest_height >= tip.height {
+        ensure!(
+            ledger.get_hash(tip.height)? == tip.block_hash,
+            "The ledger holds another block at the snapshot tip {}",
+            tip.height
+        );
+        return Ok(tip.height);
+    }
+
+    // Process the blocks above the latest block, checking the blocks the ledger already holds.
+    for chunk in manifest.chunks.iter().filter(|chunk| chunk.end_height > latest_height) {
+        for block in manifest.read_chunk(directory, chunk)? {
+            if block.height() <= latest_height {
+                ensure!(
+                    ledger.get_hash(block.height())? == block.hash(),
+                    "The ledger holds another block at height {}",
+                    block.height()
+                );
+                continue;
+            }
+            process(block)?;
+        }
+        debug!("Imported the snapshot chunk '{}'", chunk.file_name());
+    }
+
+    // Ensure the ledger reached the snapshot tip, with the same lineage.
+    ensure!(ledger.latest_hash() == tip.block_hash, "The ledger did not reach the snapshot tip {}", tip.height);
+    ensure!(chain_root()? == tip.chain_root, "The lineage of the ledger does not match the snapshot tip");
+    Ok(tip.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{
+        Address,
+        ConsensusMemory,
+        ConsensusStore,
+        Header,
+        Metadata,
+        PrivateKey,
+        TestRng,
+        Testnet3,
+        Transactions,
+        VM,
+        Zero,
+    };
+
+    use once_cell::sync::OnceCell;
+
+    type CurrentNetwork = Testnet3;
+    type CurrentLedger = Ledger<CurrentNetwork, ConsensusMemory<CurrentNetwork>>;
+
+    /// The height of the base snapshot.
+    const BASE_HEIGHT: u32 = 100;
+    /// The height of the incremental snapshot.
+    const INCREMENT_HEIGHT: u32 = 150;
+
+    /// Returns the stand-in for the chain root of the given ledger, which is its latest state root.
+    fn chain_root(ledger: &CurrentLedger) -> Field<CurrentNetwork> {
+        *ledger.latest_state_root()
+    }
+
+    /// Returns a temporary snapshot directory with the given name.
+    fn snapshot_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("snarkos-snapshot-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    /// Returns a development genesis block, and a chain of `INCREMENT_HEIGHT` transfer blocks on top of it.
+    fn sample_chain() -> &'static (Block<CurrentNetwork>, Vec<Block<CurrentNetwork>>) {
+        static INSTANCE: OnceCell<(Block<CurrentNetwork>, Vec<Block<CurrentNetwork>>)> = OnceCell::new();
+        INSTANCE.get_or_init(|| {
+            let rng = &mut TestRng::default();
+            let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+            let address = Address::try_from(&private_key).unwrap();
+            let store = ConsensusStore::<_, ConsensusMemory<_>>::open(None).unwrap();
+            let genesis = Block::genesis(&VM::from(store).unwrap(), &private_key, rng).unwrap();
+            let ledger = CurrentLedger::load(genesis.clone(), None).unwrap();
+
+            let mut blocks = Vec::new();
+            for _ in 0..INCREMENT_HEIGHT {
+                let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+                let latest_block = ledger.latest_block();
+                let metadata = Metadata::new(
+                    CurrentNetwork::ID,
+                    latest_block.round() + 1,
+                    latest_block.height() + 1,
+                    ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap(),
+                    ledger.latest_cumulative_proof_target(),
+                    latest_block.coinbase_target(),
+                    latest_block.proof_target(),
+                    latest_block.last_coinbase_target(),
+                    latest_block.last_coinbase_timestamp(),
+                    latest_block.timestamp() + 1,
+                )
+                .unwrap();
+                let transactions = Transactions::from(&[transfer]);
+                let header = Header::from(
+                    *ledger.latest_state_root(),
+                    transactions.to_root().unwrap(),
+                    Field::zero(),
+                    Field::zero(),
+                    metadata,
+                )
+                .unwrap();
+                let block = Block::new(&private_key, latest_block.hash(), header, transactions, None, rng).unwrap();
+                ledger.add_next_block(&block).unwrap();
+                blocks.push(block);
+            }
+            (genesis, blocks)
+        })
+    }
+
+    /// Returns a ledger on the sample genesis block, with the sample blocks up to the given height.
+    fn sample_ledger(height: u32) -> CurrentLedger {
+        let (genesis, blocks) = sample_chain();
+        let ledger = CurrentLedger::load(genesis.clone(), None).unwrap();
+        for block in &blocks[..height as usize] {
+            ledger.add_next_block(block).unwrap();
+        }
+        ledger
+    }
+
+    /// Exports the base snapshot at `BASE_HEIGHT`, and the incremental snapshot up to `INCREMENT_HEIGHT`.
+    fn export_snapshots(name: &str) -> (PathBuf, PathBuf) {
+        let (base_dir, increment_dir) = (snapshot_dir(&format!("{name}-base")), snapshot_dir(&format!("{name}-inc")));
+        let ledger = sample_ledger(BASE_HEIGHT);
+        let base = export_canon_since(&ledger, None, chain_root(&ledger), &base_dir).unwrap();
+        let (_, blocks) = sample_chain();
+        for block in &blocks[BASE_HEIGHT as usize..] {
+            ledger.add_next_block(block).unwrap();
+        }
+        export_canon_since(&ledger, Some(&base), chain_root(&ledger), &increment_dir).unwrap();
+        (base_dir, increment_dir)
+    }
+
+    /// Imports the given snapshot into the given ledger.
+    fn import(ledger: &CurrentLedger, directory: &Path) -> Result<u32> {
+        import_snapshot(ledger, directory, || Ok(chain_root(ledger)), |block| ledger.add_next_block(&block))
+    }
+
+    #[test]
+    fn test_chunk_ranges() {
+        assert_eq!(chunk_ranges(1, 101), vec![1..51, 51..101]);
+        assert_eq!(chunk_ranges(101, 151), vec![101..151]);
+        // Ensure the chunks of an unaligned snapshot are aligned on the block files.
+        assert_eq!(chunk_ranges(24, 130), vec![24..51, 51..101, 101..130]);
+        assert!(chunk_ranges(5, 5).is_empty());
+    }
+
+    #[test]
+    fn test_import_base_and_increment() {
+        let (base_dir, increment_dir) = export_snapshots("import");
+        let base = SnapshotFileManifest::<CurrentNetwork>::load(&base_dir).unwrap();
+        let increment =
+            SnapshotFileManifest::<CurrentNetwork>::load(&increment_dir.join(SNAPSHOT_MANIFEST_FILE)).unwrap();
+        assert_eq!(base.base, None);
+        assert_eq!(base.tip.height, BASE_HEIGHT);
+        assert_eq!(increment.base, Some(base.tip));
+        assert_eq!(increment.tip.height, INCREMENT_HEIGHT);
+        assert_eq!(increment.chunks.len(), 1);
+
+        // Import the base and the increment into a fresh ledger.
+        let ledger = sample_ledger(0);
+        assert_eq!(import(&ledger, &base_dir).unwrap(), BASE_HEIGHT);
+        assert_eq!(import(&ledger, &increment_dir).unwrap(), INCREMENT_HEIGHT);
+
+        // Ensure the ledger is equivalent to a ledger synced block by block.
+        let synced = sample_ledger(INCREMENT_HEIGHT);
+        let heights = 0..INCREMENT_HEIGHT + 1;
+        assert_eq!(
+            ledger.get_hashes(heights.clone(), false, usize::MAX).unwrap(),
+            synced.get_hashes(heights, false, usize::MAX).unwrap()
+        );
+        assert_eq!(ledger.latest_state_root(), synced.latest_state_root());
+
+        // Ensure an imported snapshot is not imported again.
+        assert_eq!(import(&ledger, &base_dir).unwrap(), BASE_HEIGHT);
+        assert_eq!(ledger.latest_height(), INCREMENT_HEIGHT);
+
+        // Ensure an interrupted import resumes, and a partially transferred increment is reported.
+        let ledger = sample_ledger(BASE_HEIGHT + 10);
+        let chunk = increment.chunks[0].file_name();
+        fs::rename(increment_dir.join(&chunk), increment_dir.join("moved")).unwrap();
+        assert_eq!(increment.missing_chunks(&increment_dir), vec![&increment.chunks[0]]);
+        assert!(import(&ledger, &increment_dir).is_err());
+        fs::rename(increment_dir.join("moved"), increment_dir.join(&chunk)).unwrap();
+        assert!(increment.missing_chunks(&increment_dir).is_empty());
+        assert_eq!(import(&ledger, &increment_dir).unwrap(), INCREMENT_HEIGHT);
+        assert_eq!(ledger.latest_hash(), synced.latest_hash());
+    }
+
+    #[test]
+    fn test_import_rejects_mismatched_base() {
+        let (base_dir, increment_dir) = export_snapshots("mismatch");
+
+        // Ensure an increment is not applied without its base.
+        let ledger = sample_ledger(0);
+        assert!(import(&ledger, &increment_dir).is_err());
+        assert_eq!(ledger.latest_height(), 0);
+
+        // Ensure an increment whose base tip does not match the ledger is rejected.
+        assert_eq!(import(&ledger, &base_dir).unwrap(), BASE_HEIGHT);
+        let mut increment = SnapshotFileManifest::<CurrentNetwork>::load(&increment_dir).unwrap();
+        let mut base = increment.base.unwrap();
+        base.block_hash = ledger.get_hash(BASE_HEIGHT - 1).unwrap();
+        increment.base = Some(base);
+        fs::write(increment_dir.join(SNAPSHOT_MANIFEST_FILE), serde_json::to_vec(&increment).unwrap()).unwrap();
+        assert!(import(&ledger, &increment_dir).is_err());
+        assert_eq!(ledger.latest_height(), BASE_HEIGHT);
+
+        // Ensure an increment whose base lineage does not match the ledger is rejected.
+        let mut base = increment.base.unwrap();
+        base.block_hash = ledger.latest_hash();
+        base.chain_root = Field::zero();
+        increment.base = Some(base);
+        fs::write(increment_dir.join(SNAPSHOT_MANIFEST_FILE), serde_json::to_vec(&increment).unwrap()).unwrap();
+        assert!(import(&ledger, &increment_dir).is_err());
+        assert_eq!(ledger.latest_height(), BASE_HEIGHT);
+    }
+}
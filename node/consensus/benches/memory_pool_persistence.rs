@@ -0,0 +1,69 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_consensus::{BatchWriter, WriteCoalescer, DEFAULT_FLUSH_BYTES};
+
+use anyhow::Result;
+use core::time::Duration;
+use parking_lot::Mutex;
+use std::{fs::File, io::Write, time::Instant};
+
+/// The number of writes per run.
+const NUM_WRITES: u64 = 2_000;
+/// The size of each write (in bytes), which approximates the size of a transfer.
+const WRITE_SIZE: usize = 2_048;
+
+/// A storage that appends each batch to a file, and syncs it to disk, as a database write does.
+struct FileStorage(Mutex<File>);
+
+impl BatchWriter<u64, Vec<u8>> for FileStorage {
+    fn write_batch(&self, batch: &[(u64, Option<Vec<u8>>)]) -> Result<()> {
+        let mut file = self.0.lock();
+        for (key, value) in batch {
+            file.write_all(&key.to_le_bytes())?;
+            file.write_all(value.as_deref().unwrap_or_default())?;
+        }
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// Writes `NUM_WRITES` insertions through a coalescer with the given byte threshold, and returns the writes per second.
+fn run(flush_bytes: usize) -> Result<f64> {
+    let path = std::env::temp_dir().join(format!("snarkos-memory-pool-bench-{}-{flush_bytes}", std::process::id()));
+    let storage = FileStorage(Mutex::new(File::create(&path)?));
+    let coalescer = WriteCoalescer::new(storage, Duration::from_secs(3600), flush_bytes);
+
+    let timer = Instant::now();
+    for key in 0..NUM_WRITES {
+        coalescer.push([(key, Some(vec![0u8; WRITE_SIZE]), WRITE_SIZE)])?;
+    }
+    coalescer.flush()?;
+    let elapsed = timer.elapsed();
+
+    std::fs::remove_file(&path)?;
+    Ok(NUM_WRITES as f64 / elapsed.as_secs_f64())
+}
+
+fn main() -> Result<()> {
+    // Write each item in its own batch, as the memory pool did without coalescing.
+    let per_item = run(0)?;
+    println!("per-item writes:  {per_item:>12.0} writes/s");
+    // Write the items in coalesced batches.
+    let coalesced = run(DEFAULT_FLUSH_BYTES)?;
+    println!("coalesced writes: {coalesced:>12.0} writes/s ({:.1}x)", coalesced / per_item);
+    Ok(())
+}
@@ -0,0 +1,226 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::prelude::Network;
+
+use anyhow::{bail, Error, Result};
+use core::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+use indexmap::IndexMap;
+
+/// The activation heights of the consensus rules, as `(network ID, rule name, activation height)`.
+///
+/// A rule is enforced on the blocks from its activation height on, while the blocks below it are validated
+/// under the previous rules, so that the historical chain still verifies during sync and reindex.
+/// A rule without an activation height on a network is defined, but never enforced on it.
+pub const RULE_ACTIVATIONS: &[(u16, &str, u32)] = &[
+    (3, "strict_structure", 0),
+    (3, "circuit_rules", 0),
+    (3, "intra_block_duplicates", 0),
+    (3, "header_commitments", 0),
+];
+
+/// A consensus rule, which is enforced from its activation height on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConsensusRule {
+    /// The transactions must pass the structural checks of the ledger.
+    StrictStructure,
+    /// The transitions must use circuits that are valid at the height of their block.
+    CircuitRules,
+    /// The blocks must not contain the same transaction, serial number, or commitment twice.
+    IntraBlockDuplicates,
+    /// The headers commit to the number of transactions and the total fees of their block.
+    HeaderCommitments,
+}
+
+impl ConsensusRule {
+    /// The consensus rules, in order of definition.
+    pub const ALL: [Self; 4] =
+        [Self::StrictStructure, Self::CircuitRules, Self::IntraBlockDuplicates, Self::HeaderCommitments];
+
+    /// Returns the name of the rule.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::StrictStructure => "strict_structure",
+            Self::CircuitRules => "circuit_rules",
+            Self::IntraBlockDuplicates => "intra_block_duplicates",
+            Self::HeaderCommitments => "header_commitments",
+        }
+    }
+}
+
+impl FromStr for ConsensusRule {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match Self::ALL.into_iter().find(|rule| rule.name() == name) {
+            Some(rule) => Ok(rule),
+            None => bail!("Unknown consensus rule '{name}'"),
+        }
+    }
+}
+
+impl Display for ConsensusRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The status of a consensus rule, as of a block height.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RuleStatus {
+    /// The rule is not enforced, as it has no activation height, or its activation height is not reached.
+    Defined,
+    /// The rule is enforced.
+    Active,
+}
+
+impl Display for RuleStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Defined => write!(f, "defined"),
+            Self::Active => write!(f, "active"),
+        }
+    }
+}
+
+/// The deployment of a consensus rule, as of a block height.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RuleDeployment {
+    /// The rule.
+    pub rule: ConsensusRule,
+    /// The status of the rule.
+    pub status: RuleStatus,
+    /// The activation height of the rule, if it is scheduled.
+    pub activation_height: Option<u32>,
+}
+
+/// The table of the activation heights of the consensus rules on a network.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuleActivations {
+    /// The mapping of the rule to its activation height.
+    heights: IndexMap<ConsensusRule, u32>,
+}
+
+impl RuleActivations {
+    /// Initializes the table from the given activations, as `(rule name, activation height)`.
+    pub fn new(activations: &[(&str, u32)]) -> Result<Self> {
+        let mut heights = IndexMap::with_capacity(activations.len());
+        for (name, height) in activations {
+            if heights.insert(ConsensusRule::from_str(name)?, *height).is_some() {
+                bail!("The consensus rule '{name}' has more than one activation height");
+            }
+        }
+        Ok(Self { heights })
+    }
+
+    /// Initializes the table from the activation heights of the consensus rules on the given network.
+    pub fn load<N: Network>() -> Result<Self> {
+        let activations = RULE_ACTIVATIONS
+            .iter()
+            .filter(|(network_id, ..)| *network_id == N::ID)
+            .map(|(_, name, height)| (*name, *height))
+            .collect::<Vec<_>>();
+        Self::new(&activations)
+    }
+
+    /// Returns the activation height of the given rule, if it is scheduled.
+    pub fn activation_height(&self, rule: ConsensusRule) -> Option<u32> {
+        self.heights.get(&rule).copied()
+    }
+
+    /// Returns `true` if the given rule is enforced on a block at the given height.
+    pub fn is_active(&self, rule: ConsensusRule, height: u32) -> bool {
+        self.activation_height(rule).map(|activation_height| activation_height <= height).unwrap_or(false)
+    }
+
+    /// Returns the deployment of each consensus rule, as of a block at the given height.
+    pub fn deployments(&self, height: u32) -> Vec<RuleDeployment> {
+        ConsensusRule::ALL
+            .into_iter()
+            .map(|rule| RuleDeployment {
+                rule,
+                status: match self.is_active(rule, height) {
+                    true => RuleStatus::Active,
+                    false => RuleStatus::Defined,
+                },
+                activation_height: self.activation_height(rule),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::console::network::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_activation_boundary() {
+        let activations = RuleActivations::new(&[("circuit_rules", 10)]).unwrap();
+
+        // Ensure the rule is enforced from its activation height on.
+        assert!(!activations.is_active(ConsensusRule::CircuitRules, 9));
+        assert!(activations.is_active(ConsensusRule::CircuitRules, 10));
+        assert!(activations.is_active(ConsensusRule::CircuitRules, u32::MAX));
+        // Ensure a rule without an activation height is never enforced.
+        assert!(!activations.is_active(ConsensusRule::StrictStructure, u32::MAX));
+
+        // Ensure the deployments report the status and activation height of every rule.
+        assert_eq!(activations.deployments(9), vec![
+            RuleDeployment {
+                rule: ConsensusRule::StrictStructure,
+                status: RuleStatus::Defined,
+                activation_height: None
+            },
+            RuleDeployment {
+                rule: ConsensusRule::CircuitRules,
+                status: RuleStatus::Defined,
+                activation_height: Some(10)
+            },
+            RuleDeployment {
+                rule: ConsensusRule::IntraBlockDuplicates,
+                status: RuleStatus::Defined,
+                activation_height: None
+            },
+            RuleDeployment {
+                rule: ConsensusRule::HeaderCommitments,
+                status: RuleStatus::Defined,
+                activation_height: None
+            },
+        ]);
+        assert_eq!(activations.deployments(10)[1].status, RuleStatus::Active);
+    }
+
+    #[test]
+    fn test_rule_activations() {
+        // Ensure the rule names round-trip.
+        for rule in ConsensusRule::ALL {
+            assert_eq!(ConsensusRule::from_str(rule.name()).unwrap(), rule);
+        }
+        // Ensure an unknown rule is rejected.
+        assert!(RuleActivations::new(&[("memo_uniqueness", 0)]).is_err());
+        // Ensure a rule may only have one activation height.
+        assert!(RuleActivations::new(&[("circuit_rules", 0), ("circuit_rules", 1)]).is_err());
+        // Ensure the activation heights are well-formed, and every rule is active on the current network.
+        let activations = RuleActivations::load::<CurrentNetwork>().unwrap();
+        assert!(ConsensusRule::ALL.into_iter().all(|rule| activations.is_active(rule, 0)));
+    }
+}
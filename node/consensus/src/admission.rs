@@ -0,0 +1,308 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use core::{hash::Hash, time::Duration};
+use indexmap::{IndexMap, IndexSet};
+use parking_lot::{Condvar, Mutex};
+use std::{net::SocketAddr, sync::Arc, time::Instant};
+
+/// The maximum number of validations in flight, beyond which a validation is performed without deduplication.
+pub const MAX_IN_FLIGHT_VALIDATIONS: usize = 1_024;
+/// The duration after which a validation in flight is considered stalled, so that the later arrivals of the same
+/// item stop waiting on it, and validate the item themselves.
+pub const IN_FLIGHT_VALIDATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The outcome of an admission, along with whether this arrival performed the validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Admission<O> {
+    /// This arrival performed the validation. The senders are the peers that sent the item while it was validated,
+    /// including this arrival, which the item is not relayed back to.
+    Validated { outcome: O, senders: IndexSet<SocketAddr> },
+    /// An earlier arrival performed the validation, and shared its outcome.
+    Shared { outcome: O },
+}
+
+impl<O> Admission<O> {
+    /// Returns the outcome of the validation.
+    pub fn outcome(&self) -> &O {
+        match self {
+            Self::Validated { outcome, .. } | Self::Shared { outcome } => outcome,
+        }
+    }
+
+    /// Returns the outcome of the validation.
+    pub fn into_outcome(self) -> O {
+        match self {
+            Self::Validated { outcome, .. } | Self::Shared { outcome } => outcome,
+        }
+    }
+}
+
+/// The state of a validation in flight.
+enum State<O> {
+    /// The validation is in progress.
+    Pending,
+    /// The validation completed with the given outcome.
+    Done(O),
+    /// The validation was abandoned, as it panicked, so the waiters validate the item themselves.
+    Abandoned,
+}
+
+/// A validation in flight, which the later arrivals of the same item wait on.
+struct InFlightEntry<O> {
+    /// The time at which the validation started.
+    started: Instant,
+    /// The state of the validation.
+    state: Mutex<State<O>>,
+    /// The condition variable, which is notified once the validation completes.
+    completed: Condvar,
+    /// The peers that sent the item while it was validated.
+    senders: Mutex<IndexSet<SocketAddr>>,
+}
+
+/// The coordinator of the validations in flight, which deduplicates the concurrent validations of the same item:
+/// the first arrival performs the validation, and the later arrivals wait on its outcome, while contributing the
+/// peer that sent them for the relay accounting.
+///
+/// The number of validations in flight is bounded, and a validation that stalls for longer than the timeout
+/// is no longer waited on, so that a slow validation does not hold up the later arrivals indefinitely.
+pub struct InFlightValidations<K: Copy + Eq + Hash, O: Clone> {
+    /// The validations in flight, in the order they started.
+    entries: Arc<Mutex<IndexMap<K, Arc<InFlightEntry<O>>>>>,
+    /// The duration after which a validation in flight is considered stalled.
+    timeout: Duration,
+}
+
+impl<K: Copy + Eq + Hash, O: Clone> Clone for InFlightValidations<K, O> {
+    fn clone(&self) -> Self {
+        Self { entries: self.entries.clone(), timeout: self.timeout }
+    }
+}
+
+impl<K: Copy + Eq + Hash, O: Clone> Default for InFlightValidations<K, O> {
+    /// Initializes a coordinator with the default timeout.
+    fn default() -> Self {
+        Self::new(IN_FLIGHT_VALIDATION_TIMEOUT)
+    }
+}
+
+impl<K: Copy + Eq + Hash, O: Clone> InFlightValidations<K, O> {
+    /// Initializes a coordinator, whose validations are considered stalled after the given timeout.
+    pub fn new(timeout: Duration) -> Self {
+        Self { entries: Default::default(), timeout }
+    }
+
+    /// Returns the number of validations in flight.
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Returns `true` if no validation is in flight.
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    /// Validates the item with the given key, which was sent by the given peer (if any), with the given function,
+    /// unless the item is already validated, in which case the outcome of that validation is awaited and shared.
+    pub fn validate(&self, key: K, sender: Option<SocketAddr>, validate: impl FnOnce() -> O) -> Admission<O> {
+        let mut entries = self.entries.lock();
+        match entries.get(&key).cloned() {
+            // If the item is validated, and the validation is not stalled, wait on its outcome.
+            Some(entry) if entry.started.elapsed() < self.timeout => {
+                drop(entries);
+                if let Some(sender) = sender {
+                    entry.senders.lock().insert(sender);
+                }
+                match self.wait(&entry) {
+                    Some(outcome) => Admission::Shared { outcome },
+                    // If the validation stalled or was abandoned, validate the item without deduplication.
+                    None => Admission::Validated { outcome: validate(), senders: sender.into_iter().collect() },
+                }
+            }
+            // If the validations in flight are full, validate the item without deduplication.
+            None if entries.len() >= MAX_IN_FLIGHT_VALIDATIONS => {
+                drop(entries);
+                Admission::Validated { outcome: validate(), senders: sender.into_iter().collect() }
+            }
+            // Otherwise, validate the item, replacing a stalled validation, if any.
+            _ => {
+                let entry = Arc::new(InFlightEntry {
+                    started: Instant::now(),
+                    state: Mutex::new(State::Pending),
+                    completed: Condvar::new(),
+                    senders: Mutex::new(sender.into_iter().collect()),
+                });
+                entries.insert(key, entry.clone());
+                drop(entries);
+
+                // Complete the entry once the validation returns, or abandon it if the validation panics.
+                let guard = EntryGuard { validations: self, key, entry: &entry };
+                let outcome = validate();
+                guard.complete(State::Done(outcome.clone()));
+                let senders = entry.senders.lock().clone();
+                Admission::Validated { outcome, senders }
+            }
+        }
+    }
+
+    /// Waits on the given validation until it completes, or stalls. Returns its outcome, if it completed.
+    fn wait(&self, entry: &InFlightEntry<O>) -> Option<O> {
+        let deadline = entry.started + self.timeout;
+        let mut state = entry.state.lock();
+        loop {
+            match &*state {
+                State::Done(outcome) => return Some(outcome.clone()),
+                State::Abandoned => return None,
+                State::Pending => {
+                    if entry.completed.wait_until(&mut state, deadline).timed_out() {
+                        return match &*state {
+                            State::Done(outcome) => Some(outcome.clone()),
+                            _ => None,
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The guard of a validation in flight, which removes it from the coordinator, and notifies its waiters,
+/// once it completes. If the validation panics, the guard abandons it on drop.
+struct EntryGuard<'a, K: Copy + Eq + Hash, O: Clone> {
+    /// The coordinator of the validation.
+    validations: &'a InFlightValidations<K, O>,
+    /// The key of the validated item.
+    key: K,
+    /// The validation in flight.
+    entry: &'a Arc<InFlightEntry<O>>,
+}
+
+impl<K: Copy + Eq + Hash, O: Clone> EntryGuard<'_, K, O> {
+    /// Completes the validation with the given state.
+    fn complete(self, state: State<O>) {
+        self.finish(state);
+        std::mem::forget(self);
+    }
+
+    /// Sets the state of the validation, notifies its waiters, and removes it, unless it was replaced.
+    fn finish(&self, state: State<O>) {
+        *self.entry.state.lock() = state;
+        self.entry.completed.notify_all();
+        let mut entries = self.validations.entries.lock();
+        if entries.get(&self.key).is_some_and(|entry| Arc::ptr_eq(entry, self.entry)) {
+            entries.shift_remove(&self.key);
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash, O: Clone> Drop for EntryGuard<'_, K, O> {
+    fn drop(&mut self) {
+        self.finish(State::Abandoned);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Barrier,
+    };
+
+    /// Returns the address of the peer with the given index.
+    fn peer_ip(index: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], 4130 + index))
+    }
+
+    #[test]
+    fn test_concurrent_validations_are_deduplicated() {
+        let validations = InFlightValidations::<u64, Result<(), String>>::default();
+        let num_validations = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+
+        // Validate the same item from eight peers concurrently, holding the validation until all of them arrived.
+        let handles = (0..8u16)
+            .map(|index| {
+                let (validations, num_validations, barrier) =
+                    (validations.clone(), num_validations.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    if index > 0 {
+                        barrier.wait();
+                    }
+                    validations.validate(7, Some(peer_ip(index)), || {
+                        num_validations.fetch_add(1, Ordering::SeqCst);
+                        if index == 0 {
+                            barrier.wait();
+                            // Allow the other arrivals to register as waiters.
+                            std::thread::sleep(Duration::from_millis(200));
+                        }
+                        Err("invalid".to_string())
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        let admissions = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+
+        // Ensure the item was validated once, and every peer received the outcome.
+        assert_eq!(num_validations.load(Ordering::SeqCst), 1);
+        assert!(admissions.iter().all(|admission| admission.outcome() == &Err("invalid".to_string())));
+        match &admissions[0] {
+            Admission::Validated { senders, .. } => assert_eq!(senders.len(), 8),
+            admission => panic!("Expected the first arrival to validate the item, found {admission:?}"),
+        }
+        assert!(admissions[1..].iter().all(|admission| matches!(admission, Admission::Shared { .. })));
+        assert!(validations.is_empty());
+    }
+
+    #[test]
+    fn test_stalled_validation_is_not_awaited() {
+        let validations = InFlightValidations::<u64, u32>::new(Duration::from_millis(100));
+
+        // Start a validation that stalls beyond the timeout.
+        let validations_ = validations.clone();
+        let stalled = std::thread::spawn(move || {
+            validations_.validate(7, None, || {
+                std::thread::sleep(Duration::from_millis(500));
+                1
+            })
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(validations.len(), 1);
+
+        // Ensure a later arrival stops waiting on the stalled validation, and validates the item itself.
+        let timer = Instant::now();
+        assert_eq!(validations.validate(7, None, || 2), Admission::Validated { outcome: 2, senders: IndexSet::new() });
+        assert!(timer.elapsed() < Duration::from_millis(400));
+
+        // Ensure the stalled validation still completes with its own outcome.
+        assert_eq!(stalled.join().unwrap().into_outcome(), 1);
+        assert!(validations.is_empty());
+    }
+
+    #[test]
+    fn test_panicked_validation_is_abandoned() {
+        let validations = InFlightValidations::<u64, u32>::default();
+
+        // Ensure a validation that panics is removed, rather than awaited forever.
+        let validations_ = validations.clone();
+        assert!(std::thread::spawn(move || validations_.validate(7, None, || panic!("validation panicked")))
+            .join()
+            .is_err());
+        assert!(validations.is_empty());
+        assert_eq!(validations.validate(7, None, || 3).into_outcome(), 3);
+    }
+}
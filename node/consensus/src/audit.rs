@@ -0,0 +1,377 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{BatchWriter, Consensus, ConsensusRule, ReplayCheck, ReplayReport, ReplayStage};
+use snarkos_node_ledger::TransactionStructure;
+use snarkvm::prelude::{Block, ConsensusStorage, Network, Rng};
+
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+
+use ::time::OffsetDateTime;
+use anyhow::{anyhow, bail, ensure, Result};
+use indexmap::IndexMap;
+use parking_lot::RwLock;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// The maximum number of audits that are remembered in the audit log, beyond which the earliest are forgotten.
+pub const MAX_AUDIT_ENTRIES: usize = 4_096;
+
+/// The outcome of the audit of a historical block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEntry<N: Network> {
+    /// The height of the block.
+    pub height: u32,
+    /// The hash of the block.
+    pub hash: N::BlockHash,
+    /// The check that failed, and its error, if the block failed its audit.
+    pub failure: Option<String>,
+    /// The UNIX timestamp (in seconds) at which the block was audited.
+    pub audited_at: i64,
+}
+
+impl<N: Network> AuditEntry<N> {
+    /// Returns `true` if the block passed its audit.
+    pub const fn is_passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// A storage of the audit log, keyed by the sequence number of each audit.
+pub trait AuditLogStorage<N: Network>: BatchWriter<u64, AuditEntry<N>> {
+    /// Returns the persisted audits.
+    fn audit_entries(&self) -> Result<Vec<(u64, AuditEntry<N>)>>;
+}
+
+/// The bounded log of the audits of the historical blocks, in which the canonical blocks are read back from
+/// storage and verified again, to detect a corruption of the stored blocks after they were committed.
+///
+/// The log is kept in memory, and written through to its storage (if any), so it survives a restart.
+/// A failed audit raises the failure flag, which remains raised until it is acknowledged by the operator.
+#[derive(Clone)]
+pub struct AuditLog<N: Network> {
+    /// The audits, in the order of their sequence numbers.
+    entries: Arc<RwLock<IndexMap<u64, AuditEntry<N>>>>,
+    /// The storage of the audits, if they are persisted.
+    storage: Arc<RwLock<Option<Arc<dyn AuditLogStorage<N>>>>>,
+    /// The boolean flag for whether a block failed its audit since the last acknowledgement.
+    has_failure: Arc<AtomicBool>,
+}
+
+impl<N: Network> Default for AuditLog<N> {
+    /// Initializes an empty audit log, which is not persisted.
+    fn default() -> Self {
+        Self { entries: Default::default(), storage: Default::default(), has_failure: Default::default() }
+    }
+}
+
+impl<N: Network> AuditLog<N> {
+    /// Persists the audits to the given storage from then on, and restores the persisted audits,
+    /// raising the failure flag if any of them failed. Returns the number of restored audits.
+    pub fn set_storage(&self, storage: Arc<dyn AuditLogStorage<N>>) -> Result<usize> {
+        // Restore the persisted audits, in the order of their sequence numbers.
+        let persisted = storage.audit_entries()?;
+        let mut entries = self.entries.write();
+        entries.extend(persisted);
+        entries.sort_keys();
+        // Forget the earliest audits beyond the capacity.
+        let evicted = Self::evict(&mut entries);
+        if entries.values().any(|entry| !entry.is_passed()) {
+            self.has_failure.store(true, Ordering::SeqCst);
+        }
+        *self.storage.write() = Some(storage.clone());
+        drop(entries);
+
+        storage.write_batch(&evicted.into_iter().map(|sequence| (sequence, None)).collect::<Vec<_>>())?;
+        Ok(self.entries.read().len())
+    }
+
+    /// Returns the number of remembered audits.
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// Returns `true` if no audit is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    /// Returns the remembered audits, from the earliest to the latest.
+    pub fn entries(&self) -> Vec<(u64, AuditEntry<N>)> {
+        self.entries.read().iter().map(|(sequence, entry)| (*sequence, entry.clone())).collect()
+    }
+
+    /// Returns the latest failed audit, if any is remembered.
+    pub fn latest_failure(&self) -> Option<AuditEntry<N>> {
+        self.entries.read().values().rev().find(|entry| !entry.is_passed()).cloned()
+    }
+
+    /// Returns `true` if a block failed its audit since the last acknowledgement.
+    pub fn has_failure(&self) -> bool {
+        self.has_failure.load(Ordering::SeqCst)
+    }
+
+    /// Lowers the failure flag, once the operator handled the failed audits, and returns `true` if it was raised.
+    /// Note that the failed audits remain in the log.
+    pub fn acknowledge(&self) -> bool {
+        self.has_failure.swap(false, Ordering::SeqCst)
+    }
+
+    /// Records the given audit, raising the failure flag if it failed, and returns its sequence number.
+    pub fn insert(&self, entry: AuditEntry<N>) -> Result<u64> {
+        let mut entries = self.entries.write();
+        let sequence = entries.last().map(|(sequence, _)| sequence + 1).unwrap_or_default();
+        if !entry.is_passed() {
+            self.has_failure.store(true, Ordering::SeqCst);
+        }
+        entries.insert(sequence, entry.clone());
+        let evicted = Self::evict(&mut entries);
+
+        // Write the audit, and the removal of the evicted audits, through to the storage.
+        let mut batch = vec![(sequence, Some(entry))];
+        batch.extend(evicted.into_iter().map(|sequence| (sequence, None)));
+        self.write_batch(&batch)?;
+        Ok(sequence)
+    }
+
+    /// Removes the earliest audits beyond the capacity, and returns their sequence numbers.
+    fn evict(entries: &mut IndexMap<u64, AuditEntry<N>>) -> Vec<u64> {
+        let num_evicted = entries.len().saturating_sub(MAX_AUDIT_ENTRIES);
+        entries.drain(..num_evicted).map(|(sequence, _)| sequence).collect()
+    }
+
+    /// Writes the given batch to the storage, if the audits are persisted.
+    fn write_batch(&self, batch: &[(u64, Option<AuditEntry<N>>)]) -> Result<()> {
+        match (self.storage.read().as_ref(), batch.is_empty()) {
+            (Some(storage), false) => storage.write_batch(batch),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Audits a canonical block picked at random among the blocks whose bodies are not pruned,
+    /// and returns its report, or `None` if the ledger holds no block beyond the genesis block.
+    pub fn audit_random_block<R: Rng>(&self, rng: &mut R) -> Result<Option<ReplayReport<N>>> {
+        let latest_height = self.ledger.latest_height();
+        let start_height = self.ledger.pruned_height().max(1);
+        match start_height <= latest_height {
+            true => self.audit_block(rng.gen_range(start_height..=latest_height)).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Audits the canonical block at the given height, and returns its report.
+    ///
+    /// The block is read back from storage, bypassing the recent blocks in memory, and is verified again with
+    /// the checks that hold for a block at any depth: its header, hash, and signature, its linkage to the previous
+    /// canonical block, its transactions root, and the structure, state roots, and proofs of its transactions.
+    /// The checks that depend on the latest state of the ledger (such as its targets and supply, the coinbase
+    /// solution, and the uniqueness of its contents) are not repeated. The audit holds no lock of consensus,
+    /// and leaves the cache of the verified transactions untouched, so it does not affect the processing of blocks.
+    ///
+    /// On failure, a critical event is logged, and the failure flag of the audit log is raised.
+    pub fn audit_block(&self, height: u32) -> Result<ReplayReport<N>> {
+        ensure!(height <= self.ledger.latest_height(), "Block {height} does not exist in the ledger");
+        let pruned_height = self.ledger.pruned_height();
+        ensure!(height == 0 || height >= pruned_height, "Block {height} is pruned (below {pruned_height})");
+
+        // Read the block from storage, as a snapshot that the later commits do not affect.
+        let hash = self.ledger.get_hash(height)?;
+        let timer = Instant::now();
+        let report = match self.ledger.vm().block_store().get_block(&hash) {
+            Ok(Some(block)) => self.audit_stored_block(height, &hash, &block),
+            result => {
+                let error = result.err().unwrap_or_else(|| anyhow!("The block is missing from storage"));
+                let check = ReplayCheck {
+                    stage: ReplayStage::Block,
+                    name: "read",
+                    error: Some(error.to_string()),
+                    elapsed: timer.elapsed(),
+                };
+                ReplayReport { height, hash, checks: vec![check] }
+            }
+        };
+
+        // Ensure a failed block is still canonical, as a reorganization during the audit breaks its linkage.
+        if !report.is_valid() && self.ledger.get_hash(height).ok() != Some(hash) {
+            bail!("Block {height} ({hash}) was reorganized during its audit");
+        }
+
+        // Record the audit.
+        let failure = report.first_failure().map(|check| {
+            format!("'{}' failed at {} - {}", check.name, check.stage, check.error.as_deref().unwrap_or_default())
+        });
+        #[cfg(feature = "metrics")]
+        metrics::increment_counter!(metrics::audit::BLOCKS);
+        match &failure {
+            None => debug!("Audited block {height} ({hash}) in {} ms", timer.elapsed().as_millis()),
+            Some(failure) => {
+                #[cfg(feature = "metrics")]
+                metrics::increment_counter!(metrics::audit::FAILURES);
+                error!(target: "critical", kind = "integrity", height, "Block {height} ({hash}) failed its audit: {failure}");
+            }
+        }
+        let audited_at = OffsetDateTime::now_utc().unix_timestamp();
+        self.audit_log.insert(AuditEntry { height, hash, failure, audited_at })?;
+
+        Ok(report)
+    }
+
+    /// Runs the checks of an audit on the given block, read from storage as the canonical block
+    /// at the given height with the given hash, and returns its report.
+    fn audit_stored_block(&self, height: u32, hash: &N::BlockHash, block: &Block<N>) -> ReplayReport<N> {
+        let mut checks = Vec::new();
+
+        // Runs the given check, and records its outcome.
+        let mut run = |stage: ReplayStage<N>, name: &'static str, check: &dyn Fn() -> Result<()>| {
+            let timer = Instant::now();
+            let error = check().err().map(|error| error.to_string());
+            checks.push(ReplayCheck { stage, name, error, elapsed: timer.elapsed() });
+        };
+
+        /* Block */
+
+        run(ReplayStage::Block, "header", &|| {
+            ensure!(block.height() == height, "The header is at height {}, not {height}", block.height());
+            ensure!(height > 0 || block.is_genesis(), "Invalid genesis block");
+            ensure!(block.header().is_valid(), "Invalid block header: {:?}", block.header());
+            Ok(())
+        });
+        run(ReplayStage::Block, "hash", &|| self.check_block_hash(block));
+        run(ReplayStage::Block, "linkage", &|| {
+            ensure!(block.hash() == *hash, "The stored block has the hash {}, not {hash}", block.hash());
+            if height > 0 {
+                let previous_hash = self.ledger.get_hash(height - 1)?;
+                ensure!(
+                    block.previous_hash() == previous_hash,
+                    "The previous block hash is {}, not the canonical {previous_hash}",
+                    block.previous_hash()
+                );
+            }
+            Ok(())
+        });
+        run(ReplayStage::Block, "signature", &|| self.check_block_signature(block));
+        run(ReplayStage::Block, "transactions list", &|| self.check_block_transactions_list(block));
+
+        /* Transactions */
+
+        for transaction in block.transactions().iter() {
+            let stage = || ReplayStage::Transaction(transaction.id());
+            run(stage(), "structure", &|| match self.is_active(ConsensusRule::StrictStructure, height) {
+                true => transaction.validate_structure().map_err(Into::into),
+                false => Ok(()),
+            });
+            run(stage(), "state roots", &|| self.check_transaction_state_roots(transaction));
+            // Verify the proofs with the VM, so the audit is not counted in the verification statistics of consensus.
+            run(stage(), "proof", &|| self.ledger.vm().check_transaction(transaction));
+        }
+
+        ReplayReport { height, hash: *hash, checks }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Field, Testnet3, Uniform};
+
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+
+    type CurrentNetwork = Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// A storage of the audit log in memory.
+    #[derive(Default)]
+    struct MemoryStorage(Mutex<HashMap<u64, AuditEntry<CurrentNetwork>>>);
+
+    impl BatchWriter<u64, AuditEntry<CurrentNetwork>> for MemoryStorage {
+        fn write_batch(&self, batch: &[(u64, Option<AuditEntry<CurrentNetwork>>)]) -> Result<()> {
+            let mut entries = self.0.lock();
+            for (sequence, entry) in batch {
+                match entry {
+                    Some(entry) => entries.insert(*sequence, entry.clone()),
+                    None => entries.remove(sequence),
+                };
+            }
+            Ok(())
+        }
+    }
+
+    impl AuditLogStorage<CurrentNetwork> for MemoryStorage {
+        fn audit_entries(&self) -> Result<Vec<(u64, AuditEntry<CurrentNetwork>)>> {
+            Ok(self.0.lock().iter().map(|(sequence, entry)| (*sequence, entry.clone())).collect())
+        }
+    }
+
+    /// Returns an audit of a random block at the given height, which failed if a failure is given.
+    fn sample_entry(height: u32, failure: Option<&str>) -> AuditEntry<CurrentNetwork> {
+        let hash = BlockHash::from(Field::rand(&mut snarkvm::prelude::TestRng::default()));
+        AuditEntry { height, hash, failure: failure.map(str::to_string), audited_at: 100 }
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let storage = Arc::new(MemoryStorage::default());
+        let log = AuditLog::<CurrentNetwork>::default();
+        assert_eq!(log.set_storage(storage.clone()).unwrap(), 0);
+
+        // Record a passed audit, and ensure the failure flag stays lowered.
+        assert_eq!(log.insert(sample_entry(5, None)).unwrap(), 0);
+        assert!(!log.has_failure());
+        assert!(log.latest_failure().is_none());
+
+        // Record a failed audit, and ensure it raises the failure flag.
+        let failed = sample_entry(6, Some("'proof' failed"));
+        assert_eq!(log.insert(failed.clone()).unwrap(), 1);
+        assert!(log.has_failure());
+        assert_eq!(log.latest_failure(), Some(failed.clone()));
+
+        // Ensure an acknowledgement lowers the flag, and keeps the failed audit in the log.
+        assert!(log.acknowledge());
+        assert!(!log.acknowledge());
+        assert_eq!(log.len(), 2);
+
+        // Ensure the audits are restored from the storage, with the failure flag raised again.
+        let restored = AuditLog::<CurrentNetwork>::default();
+        assert_eq!(restored.set_storage(storage).unwrap(), 2);
+        assert!(restored.has_failure());
+        assert_eq!(restored.entries().last(), Some(&(1, failed)));
+        assert_eq!(restored.insert(sample_entry(7, None)).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_audit_log_capacity() {
+        let storage = Arc::new(MemoryStorage::default());
+        let log = AuditLog::<CurrentNetwork>::default();
+        log.set_storage(storage.clone()).unwrap();
+
+        // Ensure the earliest audit is forgotten beyond the capacity, in memory and in storage.
+        for height in 0..=MAX_AUDIT_ENTRIES as u32 {
+            log.insert(sample_entry(height, None)).unwrap();
+        }
+        assert_eq!(log.len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(log.entries().first().map(|(sequence, _)| *sequence), Some(1));
+        assert_eq!(storage.audit_entries().unwrap().len(), MAX_AUDIT_ENTRIES);
+    }
+}
@@ -0,0 +1,392 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{memory_pool::depends_on, Consensus, PolicyStage};
+use snarkos_node_ledger::StructureViolation;
+use snarkvm::prelude::{ConsensusStorage, Network, Process, Transaction};
+
+use anyhow::{bail, ensure, Error, Result};
+use core::{fmt, str::FromStr};
+
+/// The maximum number of transactions in a batch submission.
+pub const MAX_BATCH_TRANSACTIONS: usize = 32;
+
+/// The mode of a batch submission.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// The batch is validated as a whole, and either every transaction is accepted, or none is.
+    #[default]
+    Atomic,
+    /// Each transaction is accepted or rejected independently, in order.
+    BestEffort,
+}
+
+impl FromStr for BatchMode {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "atomic" => Ok(Self::Atomic),
+            "best-effort" => Ok(Self::BestEffort),
+            _ => bail!("Invalid batch mode '{mode}' (expected 'atomic' or 'best-effort')"),
+        }
+    }
+}
+
+impl fmt::Display for BatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Atomic => write!(f, "atomic"),
+            Self::BestEffort => write!(f, "best-effort"),
+        }
+    }
+}
+
+/// The reason an unconfirmed transaction was not accepted into the memory pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionRejection<N: Network> {
+    /// The transaction is already in the memory pool, or earlier in the batch.
+    Duplicate,
+    /// The transaction spends a record that is also spent by the given transaction,
+    /// which is in the memory pool, or earlier in the batch.
+    Conflict { transaction_id: N::TransactionID },
+    /// The transaction failed a check, with the given error.
+    Invalid { reason: String },
+    /// The transaction is valid, but was not accepted because the given transaction of the (atomic) batch was rejected.
+    Aborted { transaction_id: N::TransactionID },
+    /// The batch contains more than the maximum number of transactions.
+    BatchTooLarge { num_transactions: usize },
+    /// The transaction is a coinbase transaction, which is only valid in a block.
+    Coinbase,
+    /// The transaction mints credits (has a negative balance), without being a coinbase transaction.
+    NegativeBalance,
+    /// The fee of the transaction exceeds the total supply of microcredits.
+    ExcessiveFee { fee: u64, total_supply: u64 },
+    /// The transaction uses a circuit that is not valid at the given height.
+    InvalidCircuit { circuit: String, height: u32, valid_from_height: u32, valid_until_height: u32 },
+    /// The transaction is malformed, with the given structure violation.
+    Malformed { violation: StructureViolation },
+    /// The oldest global state root of the transaction is older than the admission window of the memory pool.
+    Stale { state_root_age: u32, window: u32 },
+    /// The transaction recently expired from the memory pool.
+    Expired,
+    /// The transaction was rejected by the given policy hook of the node, with the given reason.
+    Policy { hook: String, reason: String },
+}
+
+impl<N: Network> TransactionRejection<N> {
+    /// Returns the name of the kind of rejection.
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Duplicate => "duplicate",
+            Self::Conflict { .. } => "conflict",
+            Self::Invalid { .. } => "invalid",
+            Self::Aborted { .. } => "aborted",
+            Self::BatchTooLarge { .. } => "batch_too_large",
+            Self::Coinbase => "coinbase",
+            Self::NegativeBalance => "negative_balance",
+            Self::ExcessiveFee { .. } => "excessive_fee",
+            Self::InvalidCircuit { .. } => "invalid_circuit",
+            Self::Malformed { .. } => "malformed",
+            Self::Stale { .. } => "stale",
+            Self::Expired => "expired",
+            Self::Policy { .. } => "policy",
+        }
+    }
+}
+
+impl<N: Network> fmt::Display for TransactionRejection<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate => write!(f, "the transaction was already submitted"),
+            Self::Conflict { transaction_id } => write!(f, "the transaction conflicts with '{transaction_id}'"),
+            Self::Invalid { reason } => write!(f, "{reason}"),
+            Self::Aborted { transaction_id } => {
+                write!(f, "the batch was aborted by the rejection of '{transaction_id}'")
+            }
+            Self::BatchTooLarge { num_transactions } => {
+                write!(f, "the batch contains {num_transactions} transactions (maximum is {MAX_BATCH_TRANSACTIONS})")
+            }
+            Self::Coinbase => write!(f, "the transaction is a coinbase transaction, which is only valid in a block"),
+            Self::NegativeBalance => write!(f, "the transaction mints credits, but is not a coinbase transaction"),
+            Self::ExcessiveFee { fee, total_supply } => {
+                write!(f, "the fee of {fee} microcredits exceeds the total supply of {total_supply}")
+            }
+            Self::InvalidCircuit { circuit, height, valid_from_height, valid_until_height } => {
+                write!(
+                    f,
+                    "the circuit '{circuit}' is not valid at height {height} (valid from {valid_from_height} until {valid_until_height})"
+                )
+            }
+            Self::Malformed { violation } => write!(f, "the transaction is malformed: {violation}"),
+            Self::Stale { state_root_age, window } => {
+                write!(f, "the global state root is {state_root_age} blocks old (window is {window} blocks)")
+            }
+            Self::Expired => write!(f, "the transaction expired from the memory pool"),
+            Self::Policy { hook, reason } => write!(f, "the transaction was rejected by the policy '{hook}': {reason}"),
+        }
+    }
+}
+
+impl<N: Network> std::error::Error for TransactionRejection<N> {}
+
+/// The outcome of a single transaction in a batch submission.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOutcome<N: Network> {
+    /// The ID of the transaction.
+    pub transaction_id: N::TransactionID,
+    /// The reason the transaction was rejected, if it was not accepted into the memory pool.
+    pub rejection: Option<TransactionRejection<N>>,
+}
+
+impl<N: Network> BatchOutcome<N> {
+    /// Returns `true` if the transaction was accepted into the memory pool.
+    pub const fn is_accepted(&self) -> bool {
+        self.rejection.is_none()
+    }
+}
+
+/// The state of the memory pool, as it would be after the accepted transactions of a batch.
+pub(crate) struct BatchState<N: Network> {
+    /// The transactions of the batch that were accepted so far, in order.
+    pub(crate) accepted: Vec<Transaction<N>>,
+    /// The process with the programs deployed by the accepted transactions, if any were deployed.
+    pub(crate) process: Option<Process<N>>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Adds the given ordered batch of unconfirmed transactions to the memory pool, and returns the outcome
+    /// of each transaction, in order.
+    ///
+    /// A transaction may depend on the transactions before it in the batch, by executing a program that they
+    /// deploy. Such a transaction is validated against the state that its parents would produce: its proofs are
+    /// verified with the programs deployed earlier in the batch, while its global state roots must exist in the
+    /// ledger. Note that a transaction that spends a record output earlier in the batch is unsupported, as its
+    /// global state root only exists once its parents are confirmed, and the serial numbers of its inputs cannot
+    /// be matched to the commitments of their outputs.
+    pub fn add_unconfirmed_transactions(
+        &self,
+        transactions: Vec<Transaction<N>>,
+        mode: BatchMode,
+    ) -> Vec<BatchOutcome<N>> {
+        // Ensure the batch does not exceed the maximum number of transactions.
+        if transactions.len() > MAX_BATCH_TRANSACTIONS {
+            let rejection = TransactionRejection::BatchTooLarge { num_transactions: transactions.len() };
+            return transactions
+                .iter()
+                .map(|transaction| BatchOutcome {
+                    transaction_id: transaction.id(),
+                    rejection: Some(rejection.clone()),
+                })
+                .collect();
+        }
+
+        match mode {
+            BatchMode::Atomic => self.add_atomic_batch(transactions),
+            BatchMode::BestEffort => self.add_best_effort_batch(transactions),
+        }
+    }
+
+    /// Adds the given batch to the memory pool, if every transaction in it is valid.
+    fn add_atomic_batch(&self, transactions: Vec<Transaction<N>>) -> Vec<BatchOutcome<N>> {
+        // Simulate the batch, stopping at the first rejected transaction.
+        let mut state = BatchState { accepted: Vec::with_capacity(transactions.len()), process: None };
+        let mut rejected = None;
+        for transaction in &transactions {
+            let result = self
+                .check_memory_pool_conflicts(transaction)
+                .and_then(|()| self.check_batch_transaction(transaction, &state))
+                .and_then(|()| self.apply_batch_transaction(transaction, &mut state));
+            if let Err(rejection) = result {
+                rejected = Some((transaction.id(), rejection));
+                break;
+            }
+        }
+
+        // Add the batch to the memory pool, which may still fail if the memory pool changed during the simulation.
+        if rejected.is_none() {
+            if let Err(error) = self.memory_pool.add_unconfirmed_transactions(&state.accepted) {
+                let rejection = TransactionRejection::Invalid { reason: error.to_string() };
+                return transactions
+                    .iter()
+                    .map(|transaction| BatchOutcome {
+                        transaction_id: transaction.id(),
+                        rejection: Some(rejection.clone()),
+                    })
+                    .collect();
+            }
+        }
+
+        transactions
+            .iter()
+            .map(|transaction| {
+                let transaction_id = transaction.id();
+                let rejection = match &rejected {
+                    None => None,
+                    Some((rejected_id, rejection)) => match *rejected_id == transaction_id {
+                        true => Some(rejection.clone()),
+                        false => Some(TransactionRejection::Aborted { transaction_id: *rejected_id }),
+                    },
+                };
+                BatchOutcome { transaction_id, rejection }
+            })
+            .collect()
+    }
+
+    /// Adds each transaction of the given batch to the memory pool, if it is valid.
+    fn add_best_effort_batch(&self, transactions: Vec<Transaction<N>>) -> Vec<BatchOutcome<N>> {
+        let mut state = BatchState { accepted: Vec::with_capacity(transactions.len()), process: None };
+        transactions
+            .iter()
+            .map(|transaction| {
+                let result = self.check_batch_transaction(transaction, &state).and_then(|()| {
+                    // Add the transaction to the memory pool, which applies the replacement policy, if one is set.
+                    self.memory_pool
+                        .add_unconfirmed_transaction(transaction)
+                        .map_err(|error| TransactionRejection::Invalid { reason: error.to_string() })?;
+                    self.apply_batch_transaction(transaction, &mut state)
+                });
+                BatchOutcome { transaction_id: transaction.id(), rejection: result.err() }
+            })
+            .collect()
+    }
+
+    /// Adds the given unconfirmed transaction to the memory pool, where it depends on the given parents,
+    /// which are in the memory pool already. The transaction is validated against the state that its parents
+    /// would produce, as if they were earlier in its batch.
+    pub(crate) fn add_dependent_unconfirmed_transaction(
+        &self,
+        transaction: &Transaction<N>,
+        parents: &[Transaction<N>],
+    ) -> Result<(), TransactionRejection<N>> {
+        // Simulate the parents, which were checked when they were added to the memory pool.
+        let mut state = BatchState { accepted: Vec::with_capacity(parents.len()), process: None };
+        for parent in parents {
+            self.apply_batch_transaction(parent, &mut state)?;
+        }
+        self.check_memory_pool_conflicts(transaction)?;
+        self.check_batch_transaction(transaction, &state)?;
+        // Add the transaction to the memory pool, which applies the replacement policy, if one is set.
+        self.memory_pool
+            .add_unconfirmed_transaction(transaction)
+            .map_err(|error| TransactionRejection::Invalid { reason: error.to_string() })?;
+        Ok(())
+    }
+
+    /// Checks the given transaction does not conflict with a transaction in the memory pool.
+    fn check_memory_pool_conflicts(&self, transaction: &Transaction<N>) -> Result<(), TransactionRejection<N>> {
+        match self.memory_pool.find_conflicting_transaction(transaction) {
+            Some(transaction_id) => Err(TransactionRejection::Conflict { transaction_id }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the given transaction is valid, in the state produced by the accepted transactions of the batch.
+    fn check_batch_transaction(
+        &self,
+        transaction: &Transaction<N>,
+        state: &BatchState<N>,
+    ) -> Result<(), TransactionRejection<N>> {
+        let transaction_id = transaction.id();
+
+        // Ensure the transaction is not already in the memory pool, or earlier in the batch.
+        if self.memory_pool.contains_unconfirmed_transaction(transaction_id)
+            || state.accepted.iter().any(|accepted| accepted.id() == transaction_id)
+        {
+            return Err(TransactionRejection::Duplicate);
+        }
+
+        // Ensure the transaction does not spend a record that is spent earlier in the batch.
+        for accepted in &state.accepted {
+            if transaction.serial_numbers().any(|serial_number| accepted.serial_numbers().any(|s| s == serial_number)) {
+                return Err(TransactionRejection::Conflict { transaction_id: accepted.id() });
+            }
+        }
+
+        // Ensure the transaction does not mint credits.
+        self.check_transaction_coinbase(transaction)?;
+        // Ensure the transaction has not expired.
+        self.check_transaction_expiry(transaction)?;
+
+        // Ensure the transaction is well-formed and unique.
+        let invalid = |error: Error| TransactionRejection::Invalid { reason: error.to_string() };
+        self.check_transaction_structure(transaction)
+            .map_err(|error| error.downcast::<TransactionRejection<N>>().unwrap_or_else(invalid))?;
+        self.check_transaction_uniqueness(transaction).map_err(invalid)?;
+
+        // Ensure the transaction is valid, in the state produced by its parents in the batch, if it has any.
+        match state.accepted.iter().any(|accepted| depends_on(transaction, accepted)) {
+            true => self.check_dependent_transaction_proof(transaction, state).map_err(invalid)?,
+            false => self.check_transaction_proof(transaction).map_err(invalid)?,
+        }
+
+        // Ensure the transaction is accepted by the policy hooks of the node.
+        self.check_transaction_policy(transaction, PolicyStage::Admission)
+    }
+
+    /// Checks the proofs of the given transaction, which depends on the accepted transactions of the batch.
+    ///
+    /// The proofs are verified with the programs deployed by the batch, while the global state roots must exist
+    /// in the ledger, so a transaction that spends a record output by the batch is rejected.
+    pub(crate) fn check_dependent_transaction_proof(
+        &self,
+        transaction: &Transaction<N>,
+        state: &BatchState<N>,
+    ) -> Result<()> {
+        // Ensure the transaction ID is correct.
+        ensure!(*transaction.id() == transaction.to_root()?, "Incorrect transaction ID ({})", transaction.id());
+
+        match transaction {
+            Transaction::Execute(_, execution, fee) => {
+                // Ensure the fee is present.
+                let fee = match fee {
+                    Some(fee) => fee,
+                    None => bail!("Transaction '{}' is missing a fee (execution)", transaction.id()),
+                };
+                // Ensure the global state roots exist in the ledger.
+                self.check_transaction_state_roots(transaction)?;
+                // Verify the fee and execution with the programs deployed by the batch.
+                let verify = |process: &Process<N>| {
+                    process.verify_fee(fee)?;
+                    process.verify_execution::<true>(execution)
+                };
+                match &state.process {
+                    Some(process) => verify(process),
+                    None => verify(&self.ledger.vm().process().read()),
+                }
+            }
+            // A deployment does not execute the programs deployed by its parents, so it is checked against the ledger.
+            Transaction::Deploy(..) => self.check_transaction_proof(transaction),
+        }
+    }
+
+    /// Adds the given (checked) transaction to the state of the batch.
+    pub(crate) fn apply_batch_transaction(
+        &self,
+        transaction: &Transaction<N>,
+        state: &mut BatchState<N>,
+    ) -> Result<(), TransactionRejection<N>> {
+        // Add the deployed program to the process of the batch, so that later transactions may execute it.
+        if let Transaction::Deploy(_, _, deployment, _) = transaction {
+            let process = state.process.get_or_insert_with(|| self.ledger.vm().process().read().clone());
+            process
+                .add_program(deployment.program())
+                .map_err(|error| TransactionRejection::Invalid { reason: error.to_string() })?;
+        }
+        state.accepted.push(transaction.clone());
+        Ok(())
+    }
+}
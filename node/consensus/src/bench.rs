@@ -0,0 +1,233 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Consensus;
+use snarkos_node_ledger::RecordsFilter;
+use snarkvm::prelude::{
+    Block,
+    ConsensusStorage,
+    CryptoRng,
+    Entry,
+    Identifier,
+    Literal,
+    Network,
+    Plaintext,
+    PrivateKey,
+    Rng,
+    Transaction,
+    Value,
+    ViewKey,
+};
+
+use anyhow::{anyhow, ensure, Result};
+use core::time::Duration;
+use std::{str::FromStr, time::Instant};
+
+/// The fee (in microcredits) of each transaction of a generated chain.
+const GENERATED_TRANSACTION_FEE: u64 = 100;
+
+/// The options of the validation pipeline toggled by a benchmark, to quantify each optimization.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Whether the proofs of each block are verified ahead of its validation, as in the sync pipeline,
+    /// so that the validation finds them in the cache of the verified transactions.
+    pub verification_cache: bool,
+    /// Whether the proofs are verified in parallel, on the threads of the global pool.
+    pub parallel: bool,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { verification_cache: true, parallel: true }
+    }
+}
+
+/// The measurements of a chain replayed through the validation and commit pipeline.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BenchReport {
+    /// The number of blocks that were validated and committed.
+    pub num_blocks: u32,
+    /// The number of transactions in the blocks.
+    pub num_transactions: u64,
+    /// The number of threads that verified the proofs.
+    pub num_threads: usize,
+    /// The time elapsed from the first block to the commit of the last block.
+    pub elapsed: Duration,
+    /// The time spent verifying transaction proofs, summed over the verifying threads.
+    pub proof_verification_time: Duration,
+    /// The time spent committing the blocks to storage.
+    pub storage_time: Duration,
+}
+
+impl BenchReport {
+    /// Returns the number of blocks validated and committed per second.
+    pub fn blocks_per_second(&self) -> f64 {
+        Self::rate(self.num_blocks as f64, self.elapsed)
+    }
+
+    /// Returns the number of transactions validated and committed per second.
+    pub fn transactions_per_second(&self) -> f64 {
+        Self::rate(self.num_transactions as f64, self.elapsed)
+    }
+
+    /// Returns the share of the elapsed time spent verifying proofs, which is estimated as the summed
+    /// verification time spread over the verifying threads.
+    pub fn proof_verification_share(&self) -> f64 {
+        let busy = self.proof_verification_time.as_secs_f64() / self.num_threads.max(1) as f64;
+        Self::share(busy, self.elapsed)
+    }
+
+    /// Returns the share of the elapsed time spent committing the blocks to storage.
+    pub fn storage_share(&self) -> f64 {
+        Self::share(self.storage_time.as_secs_f64(), self.elapsed)
+    }
+
+    /// Returns the given amount per second of the given elapsed time.
+    fn rate(amount: f64, elapsed: Duration) -> f64 {
+        match elapsed.is_zero() {
+            true => 0.0,
+            false => amount / elapsed.as_secs_f64(),
+        }
+    }
+
+    /// Returns the share of the given elapsed time covered by the given seconds, at most `1`.
+    fn share(seconds: f64, elapsed: Duration) -> f64 {
+        match elapsed.is_zero() {
+            true => 0.0,
+            false => (seconds / elapsed.as_secs_f64()).min(1.0),
+        }
+    }
+}
+
+/// Replays the given blocks through the validation and commit pipeline of the given consensus, with the given
+/// options, and returns the measurements. The replay halts with an error at the first invalid block.
+///
+/// If the parallel verification is disabled, the blocks are replayed on a dedicated single-threaded pool.
+pub fn bench_validation<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    blocks: impl Send + Iterator<Item = Result<Block<N>>>,
+    options: BenchOptions,
+) -> Result<BenchReport> {
+    // Verify the proofs on a single thread, if the parallel verification is disabled.
+    #[cfg(feature = "parallel")]
+    if !options.parallel {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+        return pool.install(|| replay_blocks(consensus, blocks, options));
+    }
+    replay_blocks(consensus, blocks, options)
+}
+
+/// Validates and commits each of the given blocks, on the threads of the current pool.
+fn replay_blocks<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    blocks: impl IntoIterator<Item = Result<Block<N>>>,
+    options: BenchOptions,
+) -> Result<BenchReport> {
+    #[cfg(feature = "parallel")]
+    let num_threads = rayon::current_num_threads();
+    #[cfg(not(feature = "parallel"))]
+    let num_threads = 1;
+
+    let mut report = BenchReport {
+        num_blocks: 0,
+        num_transactions: 0,
+        num_threads,
+        elapsed: Duration::ZERO,
+        proof_verification_time: Duration::ZERO,
+        storage_time: Duration::ZERO,
+    };
+    let start_proof_time = consensus.proof_verification_time();
+
+    let timer = Instant::now();
+    for block in blocks {
+        let block = block?;
+
+        // Verify the proofs ahead of the validation, as the sync pipeline does.
+        if options.verification_cache {
+            consensus.preverify_block(&block)?;
+        }
+        // Validate the block.
+        consensus
+            .check_next_block(&block)
+            .map_err(|error| anyhow!("Block {} ({}) is invalid - {error}", block.height(), block.hash()))?;
+        // Commit the block.
+        let commit_timer = Instant::now();
+        consensus.advance_to_next_block(&block)?;
+        report.storage_time += commit_timer.elapsed();
+
+        report.num_blocks += 1;
+        report.num_transactions += block.transactions().len() as u64;
+    }
+    report.elapsed = timer.elapsed();
+    report.proof_verification_time = consensus.proof_verification_time().saturating_sub(start_proof_time);
+
+    Ok(report)
+}
+
+/// Generates a chain of the given number of blocks on top of the latest block of the given consensus, which is
+/// advanced to the last generated block, and returns the blocks in order.
+///
+/// Each block is signed with the given private key, which must be a beacon (such as the signer of a development
+/// genesis block), and contains up to the given number of transactions, which split the unspent credits of the key.
+pub fn generate_chain<N: Network, C: ConsensusStorage<N>, R: Rng + CryptoRng>(
+    consensus: &Consensus<N, C>,
+    private_key: &PrivateKey<N>,
+    num_blocks: u32,
+    transactions_per_block: usize,
+    rng: &mut R,
+) -> Result<Vec<Block<N>>> {
+    ensure!(transactions_per_block > 0, "A generated block requires at least one transaction");
+    let view_key = ViewKey::try_from(private_key)?;
+    let microcredits = Identifier::from_str("microcredits")?;
+
+    let mut blocks = Vec::with_capacity(num_blocks as usize);
+    for _ in 0..num_blocks {
+        // Find the unspent records with credits, each transaction spending one, and another for its fee.
+        let records = consensus
+            .ledger
+            .find_records(&view_key, RecordsFilter::Unspent)?
+            .filter_map(|(_, record)| match record.data().get(&microcredits) {
+                Some(Entry::Private(Plaintext::Literal(Literal::U64(amount), _))) if **amount > 1 => {
+                    Some((**amount, record))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Split the credits of each pair of records in half, so that each block leaves more records to spend.
+        for pair in records.chunks_exact(2).take(transactions_per_block) {
+            let ((amount, record), (_, fee_record)) = (&pair[0], &pair[1]);
+            let inputs = [Value::Record(record.clone()), Value::from_str(&format!("{}u64", amount / 2))?];
+            let transaction = Transaction::execute(
+                consensus.ledger.vm(),
+                private_key,
+                ("credits.aleo", "split"),
+                inputs.iter(),
+                Some((fee_record.clone(), GENERATED_TRANSACTION_FEE)),
+                None,
+                rng,
+            )?;
+            consensus.add_unconfirmed_transaction(transaction)?;
+        }
+
+        // Propose and commit the next block.
+        let block = consensus.propose_next_block(private_key, rng)?;
+        consensus.check_next_block(&block)?;
+        consensus.advance_to_next_block(&block)?;
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
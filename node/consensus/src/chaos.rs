@@ -0,0 +1,108 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Consensus;
+use snarkvm::prelude::{Block, ConsensusStorage, Network};
+
+use anyhow::{bail, Result};
+use core::time::Duration;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// The faults injected into consensus by the tests, to exercise the reorganizations and their recovery.
+///
+/// The controller only exists in the test builds, and with the `chaos` feature, so it is compiled out of
+/// the release builds entirely.
+#[derive(Clone)]
+pub struct ChaosController<N: Network> {
+    /// The tip of the branch that the fork choice prefers over any other, if one is set.
+    preferred_branch: Arc<RwLock<Option<N::BlockHash>>>,
+    /// The height of the block whose next commit fails, if one is set.
+    failing_commit: Arc<RwLock<Option<u32>>>,
+    /// The delay before the block and its indexes are written to storage, if one is set.
+    write_delay: Arc<RwLock<Option<Duration>>>,
+    /// The delay of each verification of the proofs of a transaction, if one is set.
+    verification_delay: Arc<RwLock<Option<Duration>>>,
+}
+
+impl<N: Network> Default for ChaosController<N> {
+    /// Initializes a controller that injects no fault.
+    fn default() -> Self {
+        Self {
+            preferred_branch: Default::default(),
+            failing_commit: Default::default(),
+            write_delay: Default::default(),
+            verification_delay: Default::default(),
+        }
+    }
+}
+
+impl<N: Network> ChaosController<N> {
+    /// Returns the tip of the branch that the fork choice prefers, if one is set.
+    pub fn preferred_branch(&self) -> Option<N::BlockHash> {
+        *self.preferred_branch.read()
+    }
+
+    /// Forces the fork choice to prefer the branch with the given tip over any other, or restores it if none is given.
+    pub fn prefer_branch(&self, tip: Option<N::BlockHash>) {
+        *self.preferred_branch.write() = tip;
+    }
+
+    /// Fails the next commit of the block at the given height, after which the commits succeed again.
+    pub fn fail_commit_at(&self, height: u32) {
+        *self.failing_commit.write() = Some(height);
+    }
+
+    /// Delays the write of each block and its indexes by the given duration, or removes the delay if none is given.
+    pub fn delay_writes(&self, delay: Option<Duration>) {
+        *self.write_delay.write() = delay;
+    }
+
+    /// Delays each verification of the proofs of a transaction by the given duration, or removes the delay
+    /// if none is given.
+    pub fn delay_verifications(&self, delay: Option<Duration>) {
+        *self.verification_delay.write() = delay;
+    }
+
+    /// Applies the faults to the verification of the proofs of a transaction.
+    pub(crate) fn before_verification(&self) {
+        if let Some(delay) = *self.verification_delay.read() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Applies the faults to the commit of the given block, before it is written to storage.
+    pub(crate) fn before_commit(&self, block: &Block<N>) -> Result<()> {
+        if let Some(delay) = *self.write_delay.read() {
+            std::thread::sleep(delay);
+        }
+        let mut failing_commit = self.failing_commit.write();
+        if *failing_commit == Some(block.height()) {
+            *failing_commit = None;
+            bail!("Injected a failure into the commit of block {}", block.height())
+        }
+        Ok(())
+    }
+
+    /// Decommits the given number of latest blocks from the given consensus, as a reorganization would.
+    pub fn trigger_decommit<C: ConsensusStorage<N>>(
+        &self,
+        consensus: &Consensus<N, C>,
+        num_blocks: u32,
+    ) -> Result<Vec<Block<N>>> {
+        consensus.decommit_blocks(num_blocks)
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::TransactionRejection;
+use snarkvm::prelude::{Identifier, Network, ProgramID, Transaction};
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use std::str::FromStr;
+
+/// The consensus rules of the circuits, as `(program ID, function name, valid from height, valid until height)`.
+///
+/// A transition of a listed circuit is only valid in a block within its range of heights (inclusive),
+/// which sunsets a circuit that is superseded by an upgrade. A circuit that is not listed is always valid.
+pub const CIRCUIT_RULES: &[(&str, &str, u32, u32)] = &[];
+
+/// The range of heights (inclusive) at which the transitions of a circuit are valid.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CircuitValidity {
+    /// The first height at which the circuit is valid.
+    pub valid_from_height: u32,
+    /// The last height at which the circuit is valid.
+    pub valid_until_height: u32,
+}
+
+impl CircuitValidity {
+    /// Returns `true` if the circuit is valid at the given height.
+    pub const fn is_valid_at(&self, height: u32) -> bool {
+        self.valid_from_height <= height && height <= self.valid_until_height
+    }
+}
+
+/// The table of the consensus rules of the circuits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitRules<N: Network> {
+    /// The mapping of `(program ID, function name)` to the validity of the circuit.
+    rules: IndexMap<(ProgramID<N>, Identifier<N>), CircuitValidity>,
+}
+
+impl<N: Network> CircuitRules<N> {
+    /// Initializes the table from the given rules, as `(program ID, function name, valid from height, valid until height)`.
+    pub fn new(rules: &[(&str, &str, u32, u32)]) -> Result<Self> {
+        let mut table = IndexMap::with_capacity(rules.len());
+        for (program_id, function_name, valid_from_height, valid_until_height) in rules {
+            let circuit = (ProgramID::from_str(program_id)?, Identifier::from_str(function_name)?);
+            let validity =
+                CircuitValidity { valid_from_height: *valid_from_height, valid_until_height: *valid_until_height };
+            if table.insert(circuit, validity).is_some() {
+                bail!("The circuit '{program_id}/{function_name}' has more than one rule");
+            }
+        }
+        Ok(Self { rules: table })
+    }
+
+    /// Initializes the table from the consensus rules of the circuits.
+    pub fn load() -> Result<Self> {
+        Self::new(CIRCUIT_RULES)
+    }
+
+    /// Returns the validity of the given circuit, if it has a rule.
+    pub fn get(&self, program_id: &ProgramID<N>, function_name: &Identifier<N>) -> Option<CircuitValidity> {
+        self.rules.get(&(*program_id, *function_name)).copied()
+    }
+
+    /// Checks the given circuit is valid in a block at the given height.
+    pub fn check_circuit(
+        &self,
+        program_id: &ProgramID<N>,
+        function_name: &Identifier<N>,
+        height: u32,
+    ) -> Result<(), TransactionRejection<N>> {
+        match self.get(program_id, function_name) {
+            Some(validity) if !validity.is_valid_at(height) => Err(TransactionRejection::InvalidCircuit {
+                circuit: format!("{program_id}/{function_name}"),
+                height,
+                valid_from_height: validity.valid_from_height,
+                valid_until_height: validity.valid_until_height,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks every circuit used by the given transaction is valid in a block at the given height.
+    pub fn check_transaction(&self, transaction: &Transaction<N>, height: u32) -> Result<(), TransactionRejection<N>> {
+        transaction
+            .transitions()
+            .try_for_each(|transition| self.check_circuit(transition.program_id(), transition.function_name(), height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::console::network::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_circuit_sunset_boundary() {
+        let rules = CircuitRules::<CurrentNetwork>::new(&[("credits.aleo", "transfer", 10, 100)]).unwrap();
+        let program_id = ProgramID::from_str("credits.aleo").unwrap();
+        let (transfer, fee) = (Identifier::from_str("transfer").unwrap(), Identifier::from_str("fee").unwrap());
+
+        // Ensure the circuit is valid from its first height, up to and including its last height.
+        assert!(matches!(
+            rules.check_circuit(&program_id, &transfer, 9),
+            Err(TransactionRejection::InvalidCircuit { height: 9, .. })
+        ));
+        assert!(rules.check_circuit(&program_id, &transfer, 10).is_ok());
+        assert!(rules.check_circuit(&program_id, &transfer, 100).is_ok());
+        assert_eq!(
+            rules.check_circuit(&program_id, &transfer, 101),
+            Err(TransactionRejection::InvalidCircuit {
+                circuit: "credits.aleo/transfer".to_string(),
+                height: 101,
+                valid_from_height: 10,
+                valid_until_height: 100,
+            })
+        );
+
+        // Ensure a circuit without a rule is always valid.
+        assert!(rules.check_circuit(&program_id, &fee, 0).is_ok());
+        assert!(rules.check_circuit(&program_id, &fee, u32::MAX).is_ok());
+
+        // Ensure a circuit may only have one rule.
+        assert!(CircuitRules::<CurrentNetwork>::new(&[
+            ("credits.aleo", "transfer", 0, 1),
+            ("credits.aleo", "transfer", 2, 3)
+        ])
+        .is_err());
+        // Ensure the consensus rules are well-formed.
+        assert!(CircuitRules::<CurrentNetwork>::load().is_ok());
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{checked_fees, checked_sum, coinbase_reward, Consensus, TransactionRejection};
+use snarkvm::prelude::{Block, ConsensusStorage, Field, Input, Literal, Network, Plaintext, Transaction};
+
+use anyhow::{bail, Result};
+use core::fmt;
+
+/// The reason a block was rejected by the coinbase rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockRejection<N: Network> {
+    /// The block contains more than one coinbase transaction.
+    MultipleCoinbases { num_coinbases: usize },
+    /// The coinbase transaction is not the first transaction of the block.
+    MisplacedCoinbase { transaction_id: N::TransactionID, index: usize },
+    /// The coinbase transaction mints more than the coinbase reward and the fees of the block.
+    ExcessiveCoinbase { transaction_id: N::TransactionID, amount: u64, maximum: u64 },
+    /// The transaction mints credits (has a negative balance), without being a coinbase transaction.
+    NegativeBalance { transaction_id: N::TransactionID },
+    /// The fees of the block overflow the maximum amount of microcredits.
+    FeeOverflow,
+    /// The fee of the transaction exceeds the total supply of microcredits.
+    SupplyUnderflow { transaction_id: N::TransactionID, fee: u64, total_supply: u64 },
+    /// The amount minted by the transaction overflows the total supply of microcredits.
+    SupplyOverflow { transaction_id: N::TransactionID, minted: u64, total_supply: u64 },
+    /// The block contains the same transaction at two indices.
+    DuplicateTransaction { transaction_id: N::TransactionID, first: usize, second: usize },
+    /// The transactions at the two indices spend the same serial number.
+    DuplicateSerialNumber { serial_number: Field<N>, first: usize, second: usize },
+    /// The transactions at the two indices produce the same commitment.
+    DuplicateCommitment { commitment: Field<N>, first: usize, second: usize },
+}
+
+impl<N: Network> fmt::Display for BlockRejection<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MultipleCoinbases { num_coinbases } => {
+                write!(f, "the block contains {num_coinbases} coinbase transactions (maximum is 1)")
+            }
+            Self::MisplacedCoinbase { transaction_id, index } => {
+                write!(f, "the coinbase transaction '{transaction_id}' is at position {index} (expected 0)")
+            }
+            Self::ExcessiveCoinbase { transaction_id, amount, maximum } => {
+                write!(
+                    f,
+                    "the coinbase transaction '{transaction_id}' mints {amount} microcredits (maximum is {maximum})"
+                )
+            }
+            Self::NegativeBalance { transaction_id } => {
+                write!(f, "the transaction '{transaction_id}' mints credits, but is not a coinbase transaction")
+            }
+            Self::FeeOverflow => write!(f, "the fees of the block overflow the maximum amount of microcredits"),
+            Self::SupplyUnderflow { transaction_id, fee, total_supply } => {
+                write!(
+                    f,
+                    "the transaction '{transaction_id}' pays a fee of {fee} microcredits, which exceeds the total supply of {total_supply}"
+                )
+            }
+            Self::SupplyOverflow { transaction_id, minted, total_supply } => {
+                write!(
+                    f,
+                    "the transaction '{transaction_id}' mints {minted} microcredits, which overflows the total supply of {total_supply}"
+                )
+            }
+            Self::DuplicateTransaction { transaction_id, first, second } => {
+                write!(f, "the transaction '{transaction_id}' is included at positions {first} and {second}")
+            }
+            Self::DuplicateSerialNumber { serial_number, first, second } => {
+                write!(
+                    f,
+                    "the transactions at positions {first} and {second} spend the same serial number '{serial_number}'"
+                )
+            }
+            Self::DuplicateCommitment { commitment, first, second } => {
+                write!(
+                    f,
+                    "the transactions at positions {first} and {second} produce the same commitment '{commitment}'"
+                )
+            }
+        }
+    }
+}
+
+impl<N: Network> std::error::Error for BlockRejection<N> {}
+
+/// Returns `true` if the given transaction mints credits, i.e. calls 'credits.aleo/mint' in any of its transitions.
+///
+/// A coinbase transaction is the only transaction allowed to mint credits, and it does so in its only transition.
+pub fn is_negative_balance<N: Network>(transaction: &Transaction<N>) -> bool {
+    transaction.transitions().any(|transition| {
+        transition.program_id().to_string() == "credits.aleo" && transition.function_name().to_string() == "mint"
+    })
+}
+
+/// Returns the amount of microcredits minted by the given coinbase transaction.
+pub fn coinbase_amount<N: Network>(transaction: &Transaction<N>) -> Result<u64> {
+    match transaction {
+        Transaction::Execute(_, execution, _) if transaction.is_coinbase() => {
+            // Get the input amount of the coinbase transaction.
+            match execution.get(0)?.inputs().get(1) {
+                Some(Input::Public(_, Some(Plaintext::Literal(Literal::U64(amount), _)))) => Ok(**amount),
+                _ => bail!("Invalid coinbase transaction: Missing public input in 'credits.aleo/mint'"),
+            }
+        }
+        _ => bail!("Transaction '{}' is not a coinbase transaction", transaction.id()),
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Checks the coinbase transactions of the given block.
+    ///
+    /// A block may contain at most one coinbase transaction, which must be its first transaction, and may not
+    /// mint more than the coinbase reward at the block height plus the fees of the block. No other transaction
+    /// may mint credits. The genesis block is exempt, as it mints the starting supply.
+    pub fn check_block_coinbase_transactions(&self, block: &Block<N>) -> Result<()> {
+        // Skip the genesis block, as it is checked against the expected genesis block.
+        if block.is_genesis() {
+            return Ok(());
+        }
+
+        // Ensure only the coinbase transactions mint credits.
+        for transaction in block.transactions().iter() {
+            if is_negative_balance(transaction) && !transaction.is_coinbase() {
+                return Err(BlockRejection::<N>::NegativeBalance { transaction_id: transaction.id() }.into());
+            }
+        }
+
+        // Retrieve the coinbase transactions, along with their position in the block.
+        let coinbases = block.transactions().iter().enumerate().filter(|(_, tx)| tx.is_coinbase()).collect::<Vec<_>>();
+        let (index, coinbase) = match coinbases.as_slice() {
+            [] => return Ok(()),
+            [coinbase] => *coinbase,
+            _ => return Err(BlockRejection::<N>::MultipleCoinbases { num_coinbases: coinbases.len() }.into()),
+        };
+        // Ensure the coinbase transaction is the first transaction.
+        if index != 0 {
+            return Err(BlockRejection::<N>::MisplacedCoinbase { transaction_id: coinbase.id(), index }.into());
+        }
+
+        // Compute the coinbase reward, which is only earned by a block with a coinbase solution.
+        let reward = match block.coinbase() {
+            Some(_) => coinbase_reward(
+                self.ledger.last_coinbase_timestamp(),
+                block.timestamp(),
+                block.height(),
+                N::STARTING_SUPPLY,
+                N::ANCHOR_TIME,
+            )?,
+            None => 0,
+        };
+        // Compute the fees of the block.
+        let fees = checked_fees(block.transactions().iter())?;
+        // Ensure the coinbase transaction does not mint more than the reward and the fees.
+        let maximum = checked_sum([reward, fees]).ok_or(BlockRejection::<N>::FeeOverflow)?;
+        let amount = coinbase_amount(coinbase)?;
+        if amount > maximum {
+            return Err(
+                BlockRejection::<N>::ExcessiveCoinbase { transaction_id: coinbase.id(), amount, maximum }.into()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks the given unconfirmed transaction is not a coinbase, and does not otherwise mint credits.
+    ///
+    /// A coinbase transaction is only valid in a block, so it is rejected from the memory pool, except
+    /// before the first block, where it is used to distribute the starting supply in development.
+    pub fn check_transaction_coinbase(&self, transaction: &Transaction<N>) -> Result<(), TransactionRejection<N>> {
+        match transaction.is_coinbase() {
+            true if self.ledger.latest_height() > 0 => Err(TransactionRejection::Coinbase),
+            true => Ok(()),
+            false if is_negative_balance(transaction) => Err(TransactionRejection::NegativeBalance),
+            false => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Consensus, ConsensusRule};
+use snarkos_node_messages::{CommittedHeader, HeaderCommitments};
+use snarkvm::prelude::{Address, Block, ConsensusStorage, Network, Transaction};
+
+use anyhow::{bail, Result};
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the activation height of the header commitments, if it is scheduled.
+    pub fn header_commitments_height(&self) -> Option<u32> {
+        self.rule_activations().activation_height(ConsensusRule::HeaderCommitments)
+    }
+
+    /// Returns the commitments of the given transactions, if the header of a block at the given height commits to them.
+    pub fn commitments_at(&self, height: u32, transactions: &[Transaction<N>]) -> Result<Option<HeaderCommitments>> {
+        match self.is_active(ConsensusRule::HeaderCommitments, height) {
+            true => Ok(Some(HeaderCommitments::from_transactions(transactions.iter())?)),
+            false => Ok(None),
+        }
+    }
+
+    /// Returns the header of the given block, as it is relayed, with the layout of the era of its height.
+    pub fn committed_header(&self, block: &Block<N>) -> Result<CommittedHeader<N>> {
+        CommittedHeader::from_block(block, self.header_commitments_height())
+    }
+
+    /// Checks the given relayed header has the layout of the era of its height, that its commitments are within
+    /// the consensus limits, and that they are signed by the given relayer. This check does not require the body
+    /// of the block, so bogus headers are rejected cheaply.
+    pub fn check_relayed_header(&self, header: &CommittedHeader<N>, relayer: &Address<N>) -> Result<()> {
+        header.check_layout(self.header_commitments_height())?;
+        header.check_signature(relayer)
+    }
+
+    /// Checks the body of the given block matches the commitments of the given header, which must be its header.
+    pub fn check_block_commitments(&self, block: &Block<N>, header: &CommittedHeader<N>) -> Result<()> {
+        // Ensure the committed header is the header of the block.
+        if block.header() != &header.header {
+            bail!("Block {} ({}) does not match the committed header", block.height(), block.hash())
+        }
+        // Ensure the header has the layout of its era, and plausible commitments.
+        header.check_layout(self.header_commitments_height())?;
+        // Ensure the body matches the commitments.
+        match &header.commitments {
+            Some(commitments) => commitments.check_block(block),
+            None => Ok(()),
+        }
+    }
+}
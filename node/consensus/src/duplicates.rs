@@ -0,0 +1,159 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{BlockRejection, Consensus, ConsensusRule};
+use snarkvm::prelude::{Block, ConsensusStorage, Field, Network};
+
+use anyhow::Result;
+use core::hash::Hash;
+use std::collections::HashMap;
+
+/// Returns the first item that repeats, along with the indices of its first and second occurrences.
+///
+/// The map of seen items is allocated once, with the given capacity, which is the number of items.
+fn find_duplicate<T: Copy + Eq + Hash>(
+    items: impl IntoIterator<Item = (usize, T)>,
+    capacity: usize,
+) -> Option<(T, usize, usize)> {
+    let mut seen = HashMap::with_capacity(capacity);
+    for (index, item) in items {
+        if let Some(first) = seen.insert(item, index) {
+            return Some((item, first, index));
+        }
+    }
+    None
+}
+
+/// Checks the transaction IDs of a block are unique, and that its transactions spend pairwise-disjoint
+/// serial numbers and produce pairwise-disjoint commitments. The serial numbers and commitments are
+/// given along with the index of their transaction in the block, which is reported on a duplicate.
+pub fn check_intra_block_duplicates<N: Network>(
+    transaction_ids: &[N::TransactionID],
+    serial_numbers: &[(usize, Field<N>)],
+    commitments: &[(usize, Field<N>)],
+) -> Result<(), BlockRejection<N>> {
+    // Ensure the transaction IDs are unique.
+    if let Some((transaction_id, first, second)) =
+        find_duplicate(transaction_ids.iter().copied().enumerate(), transaction_ids.len())
+    {
+        return Err(BlockRejection::DuplicateTransaction { transaction_id, first, second });
+    }
+    // Ensure no serial number is spent twice.
+    if let Some((serial_number, first, second)) = find_duplicate(serial_numbers.iter().copied(), serial_numbers.len()) {
+        return Err(BlockRejection::DuplicateSerialNumber { serial_number, first, second });
+    }
+    // Ensure no commitment is produced twice.
+    if let Some((commitment, first, second)) = find_duplicate(commitments.iter().copied(), commitments.len()) {
+        return Err(BlockRejection::DuplicateCommitment { commitment, first, second });
+    }
+    Ok(())
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Checks the given block does not contain the same transaction twice, nor two transactions
+    /// that spend the same serial number or produce the same commitment.
+    ///
+    /// This check does not read the ledger, and precedes the verification of the transactions,
+    /// so that a block with duplicates is rejected before it reaches the storage.
+    pub fn check_block_duplicates(&self, block: &Block<N>) -> Result<()> {
+        // Ensure the rule is enforced at the height of the block.
+        if !self.is_active(ConsensusRule::IntraBlockDuplicates, block.height()) {
+            return Ok(());
+        }
+        // Retrieve the transaction IDs, serial numbers, and commitments, with the index of their transaction.
+        let transaction_ids = block.transaction_ids().copied().collect::<Vec<_>>();
+        let serial_numbers = block
+            .transactions()
+            .iter()
+            .enumerate()
+            .flat_map(|(index, transaction)| {
+                transaction.serial_numbers().map(move |serial_number| (index, *serial_number))
+            })
+            .collect::<Vec<_>>();
+        let commitments = block
+            .transactions()
+            .iter()
+            .enumerate()
+            .flat_map(|(index, transaction)| transaction.commitments().map(move |commitment| (index, *commitment)))
+            .collect::<Vec<_>>();
+
+        Ok(check_intra_block_duplicates(&transaction_ids, &serial_numbers, &commitments)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    type CurrentNetwork = Testnet3;
+
+    /// Samples the given number of random fields.
+    fn sample_fields(num_fields: usize, rng: &mut TestRng) -> Vec<Field<CurrentNetwork>> {
+        (0..num_fields).map(|_| Field::rand(rng)).collect()
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        assert_eq!(find_duplicate::<u32>([], 0), None);
+        assert_eq!(find_duplicate([(0, 1), (1, 2), (2, 3)], 3), None);
+        // Ensure the first repeated item is reported, with the indices of both occurrences.
+        assert_eq!(find_duplicate([(0, 1), (1, 2), (2, 1), (3, 2)], 4), Some((1, 0, 2)));
+        // Ensure an item repeated within the same index is reported.
+        assert_eq!(find_duplicate([(0, 1), (1, 2), (1, 2)], 3), Some((2, 1, 1)));
+    }
+
+    #[test]
+    fn test_check_intra_block_duplicates() {
+        let rng = &mut TestRng::default();
+
+        let transaction_ids = sample_fields(4, rng).into_iter().map(Into::into).collect::<Vec<_>>();
+        let serial_numbers =
+            sample_fields(6, rng).into_iter().enumerate().map(|(i, sn)| (i / 2, sn)).collect::<Vec<_>>();
+        let commitments = sample_fields(8, rng).into_iter().enumerate().map(|(i, cm)| (i / 2, cm)).collect::<Vec<_>>();
+        assert_eq!(check_intra_block_duplicates(&transaction_ids, &serial_numbers, &commitments), Ok(()));
+
+        // Ensure a repeated transaction ID is rejected, with the indices of both transactions.
+        let mut ids = transaction_ids.clone();
+        ids[3] = ids[1];
+        assert_eq!(
+            check_intra_block_duplicates(&ids, &serial_numbers, &commitments),
+            Err(BlockRejection::DuplicateTransaction { transaction_id: ids[1], first: 1, second: 3 })
+        );
+
+        // Ensure a serial number spent by two transactions is rejected, with the indices of both transactions.
+        let mut sns = serial_numbers.clone();
+        sns[5].1 = sns[0].1;
+        assert_eq!(
+            check_intra_block_duplicates(&transaction_ids, &sns, &commitments),
+            Err(BlockRejection::DuplicateSerialNumber { serial_number: sns[0].1, first: 0, second: 2 })
+        );
+
+        // Ensure a commitment produced by two transactions is rejected, with the indices of both transactions.
+        let mut cms = commitments.clone();
+        cms[4].1 = cms[3].1;
+        assert_eq!(
+            check_intra_block_duplicates(&transaction_ids, &serial_numbers, &cms),
+            Err(BlockRejection::DuplicateCommitment { commitment: cms[3].1, first: 1, second: 2 })
+        );
+
+        // Ensure the transaction IDs are checked first.
+        assert!(matches!(
+            check_intra_block_duplicates(&ids, &sns, &cms),
+            Err(BlockRejection::DuplicateTransaction { .. })
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::BatchWriter;
+use snarkvm::prelude::{Block, Network, ToBits, ToBytes};
+
+use ::time::OffsetDateTime;
+use anyhow::Result;
+use indexmap::IndexMap;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::{net::SocketAddr, sync::Arc};
+
+/// The maximum number of invalid blocks that are remembered, beyond which the earliest seen are forgotten.
+pub const MAX_INVALID_BLOCKS: usize = 4_096;
+
+/// The verdict on a block that was rejected for its contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidBlock {
+    /// The height of the block.
+    pub height: u32,
+    /// The reason the block was rejected.
+    pub reason: String,
+    /// The UNIX timestamp (in seconds) at which the block was first rejected.
+    pub first_seen: i64,
+    /// The IP address of the peer that sent the block, if it is known.
+    pub peer_ip: Option<SocketAddr>,
+}
+
+/// Returns the key of the verdict on the given block, when it is rejected for a member that its hash does not
+/// commit to, such as its signature, its coinbase solution, or its transactions. The key is derived from the
+/// digest of the whole block, so that the verdict on a malleated copy does not apply to the genuine block.
+pub fn block_contents_key<N: Network>(block: &Block<N>) -> Result<N::BlockHash> {
+    let digest = Sha256::digest(block.to_bytes_le()?);
+    Ok(N::hash_bhp1024(&digest.to_vec().to_bits_le())?.into())
+}
+
+/// A storage of the invalid blocks.
+pub trait InvalidBlockStorage<N: Network>: BatchWriter<N::BlockHash, InvalidBlock> {
+    /// Returns the persisted invalid blocks.
+    fn invalid_blocks(&self) -> Result<Vec<(N::BlockHash, InvalidBlock)>>;
+}
+
+/// The bounded cache of the blocks that were rejected for their contents, which is consulted before any
+/// validation work, so that a block that is sent again is rejected without verifying its proofs again.
+///
+/// A block with an invalid header is keyed by its hash, which commits to the header, while any other rejected
+/// block is keyed by the digest of its contents (see `block_contents_key`).
+///
+/// The cache is kept in memory, and written through to its storage (if any), so it survives a restart.
+/// As a rule activation may make a rejected block valid, the entries can be purged.
+#[derive(Clone)]
+pub struct InvalidBlocks<N: Network> {
+    /// The invalid blocks, in the order they were first seen.
+    blocks: Arc<RwLock<IndexMap<N::BlockHash, InvalidBlock>>>,
+    /// The storage of the invalid blocks, if they are persisted.
+    storage: Arc<RwLock<Option<Arc<dyn InvalidBlockStorage<N>>>>>,
+}
+
+impl<N: Network> Default for InvalidBlocks<N> {
+    /// Initializes an empty cache of invalid blocks, which is not persisted.
+    fn default() -> Self {
+        Self { blocks: Default::default(), storage: Default::default() }
+    }
+}
+
+impl<N: Network> InvalidBlocks<N> {
+    /// Persists the invalid blocks to the given storage from then on, and restores the persisted invalid blocks.
+    /// Returns the number of restored blocks.
+    pub fn set_storage(&self, storage: Arc<dyn InvalidBlockStorage<N>>) -> Result<usize> {
+        // Restore the persisted blocks, in the order they were first seen.
+        let persisted = storage.invalid_blocks()?;
+        let mut blocks = self.blocks.write();
+        blocks.extend(persisted);
+        blocks.sort_by(|_, a, _, b| a.first_seen.cmp(&b.first_seen));
+        // Forget the earliest seen blocks beyond the capacity.
+        let evicted = Self::evict(&mut blocks);
+        *self.storage.write() = Some(storage.clone());
+        drop(blocks);
+
+        storage.write_batch(&evicted.into_iter().map(|hash| (hash, None)).collect::<Vec<_>>())?;
+        Ok(self.blocks.read().len())
+    }
+
+    /// Returns the verdict on the given block, if it is known to be invalid.
+    pub fn get(&self, hash: &N::BlockHash) -> Option<InvalidBlock> {
+        self.blocks.read().get(hash).cloned()
+    }
+
+    /// Returns the verdict on the given block, if it is known to be invalid under its hash or its contents.
+    pub fn get_block(&self, block: &Block<N>) -> Option<InvalidBlock> {
+        if let Some(invalid) = self.get(&block.hash()) {
+            return Some(invalid);
+        }
+        match self.is_empty() {
+            true => None,
+            false => block_contents_key(block).ok().and_then(|key| self.get(&key)),
+        }
+    }
+
+    /// Returns `true` if the given block is known to be invalid.
+    pub fn contains(&self, hash: &N::BlockHash) -> bool {
+        self.blocks.read().contains_key(hash)
+    }
+
+    /// Returns the number of known invalid blocks.
+    pub fn len(&self) -> usize {
+        self.blocks.read().len()
+    }
+
+    /// Returns `true` if no block is known to be invalid.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.read().is_empty()
+    }
+
+    /// Returns the invalid blocks, in the order they were first seen.
+    pub fn blocks(&self) -> Vec<(N::BlockHash, InvalidBlock)> {
+        self.blocks.read().iter().map(|(hash, block)| (*hash, block.clone())).collect()
+    }
+
+    /// Records the given block as invalid for the given reason, as sent by the given peer.
+    /// Note that a block that is already known to be invalid keeps its original verdict.
+    pub fn insert(&self, hash: N::BlockHash, height: u32, reason: String, peer_ip: Option<SocketAddr>) -> Result<()> {
+        let mut blocks = self.blocks.write();
+        if blocks.contains_key(&hash) {
+            return Ok(());
+        }
+        let block = InvalidBlock { height, reason, first_seen: OffsetDateTime::now_utc().unix_timestamp(), peer_ip };
+        blocks.insert(hash, block.clone());
+        let evicted = Self::evict(&mut blocks);
+
+        // Write the block, and the removal of the evicted blocks, through to the storage.
+        let mut batch = vec![(hash, Some(block))];
+        batch.extend(evicted.into_iter().map(|hash| (hash, None)));
+        self.write_batch(&batch)
+    }
+
+    /// Removes the given block from the invalid blocks, or every block if none is given,
+    /// and returns the number of removed blocks.
+    pub fn purge(&self, hash: Option<&N::BlockHash>) -> Result<usize> {
+        let mut blocks = self.blocks.write();
+        let removed = match hash {
+            Some(hash) => blocks.shift_remove(hash).map(|_| vec![*hash]).unwrap_or_default(),
+            None => blocks.drain(..).map(|(hash, _)| hash).collect(),
+        };
+        self.write_batch(&removed.iter().map(|hash| (*hash, None)).collect::<Vec<_>>())?;
+        Ok(removed.len())
+    }
+
+    /// Removes the earliest seen blocks beyond the capacity, and returns their hashes.
+    fn evict(blocks: &mut IndexMap<N::BlockHash, InvalidBlock>) -> Vec<N::BlockHash> {
+        let num_evicted = blocks.len().saturating_sub(MAX_INVALID_BLOCKS);
+        blocks.drain(..num_evicted).map(|(hash, _)| hash).collect()
+    }
+
+    /// Writes the given batch to the storage, if the invalid blocks are persisted.
+    fn write_batch(&self, batch: &[(N::BlockHash, Option<InvalidBlock>)]) -> Result<()> {
+        match (self.storage.read().as_ref(), batch.is_empty()) {
+            (Some(storage), false) => storage.write_batch(batch),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Field, Testnet3, Uniform};
+
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+
+    type CurrentNetwork = Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// A storage of the invalid blocks in memory.
+    #[derive(Default)]
+    struct MemoryStorage(Mutex<HashMap<BlockHash, InvalidBlock>>);
+
+    impl BatchWriter<BlockHash, InvalidBlock> for MemoryStorage {
+        fn write_batch(&self, batch: &[(BlockHash, Option<InvalidBlock>)]) -> Result<()> {
+            let mut blocks = self.0.lock();
+            for (hash, block) in batch {
+                match block {
+                    Some(block) => blocks.insert(*hash, block.clone()),
+                    None => blocks.remove(hash),
+                };
+            }
+            Ok(())
+        }
+    }
+
+    impl InvalidBlockStorage<CurrentNetwork> for MemoryStorage {
+        fn invalid_blocks(&self) -> Result<Vec<(BlockHash, InvalidBlock)>> {
+            Ok(self.0.lock().iter().map(|(hash, block)| (*hash, block.clone())).collect())
+        }
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let rng = &mut snarkvm::prelude::TestRng::default();
+        let storage = Arc::new(MemoryStorage::default());
+        let peer_ip = Some("127.0.0.1:4130".parse().unwrap());
+
+        // Record an invalid block, and ensure its first verdict is kept.
+        let blocks = InvalidBlocks::<CurrentNetwork>::default();
+        assert_eq!(blocks.set_storage(storage.clone()).unwrap(), 0);
+        let hash = BlockHash::from(Field::rand(rng));
+        blocks.insert(hash, 5, "Invalid coinbase".to_string(), peer_ip).unwrap();
+        blocks.insert(hash, 5, "Another reason".to_string(), None).unwrap();
+        let block = blocks.get(&hash).unwrap();
+        assert_eq!((block.height, block.reason.as_str(), block.peer_ip), (5, "Invalid coinbase", peer_ip));
+
+        // Ensure the invalid blocks are restored from the storage.
+        let other = BlockHash::from(Field::rand(rng));
+        blocks.insert(other, 6, "Invalid transaction".to_string(), None).unwrap();
+        let restored = InvalidBlocks::<CurrentNetwork>::default();
+        assert_eq!(restored.set_storage(storage.clone()).unwrap(), 2);
+        assert_eq!(restored.get(&hash), Some(block));
+
+        // Ensure a single block, and then every block, is purged from the cache and the storage.
+        assert_eq!(restored.purge(Some(&hash)).unwrap(), 1);
+        assert!(!restored.contains(&hash));
+        assert_eq!(restored.purge(Some(&hash)).unwrap(), 0);
+        assert_eq!(restored.purge(None).unwrap(), 1);
+        assert!(restored.is_empty());
+        assert!(storage.invalid_blocks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_blocks_capacity() {
+        let rng = &mut snarkvm::prelude::TestRng::default();
+        let storage = Arc::new(MemoryStorage::default());
+        let blocks = InvalidBlocks::<CurrentNetwork>::default();
+        blocks.set_storage(storage.clone()).unwrap();
+
+        // Ensure the earliest seen block is forgotten beyond the capacity, in memory and in storage.
+        let hashes = (0..=MAX_INVALID_BLOCKS).map(|_| BlockHash::from(Field::rand(rng))).collect::<Vec<_>>();
+        for (height, hash) in hashes.iter().enumerate() {
+            blocks.insert(*hash, height as u32, "Invalid block".to_string(), None).unwrap();
+        }
+        assert_eq!(blocks.len(), MAX_INVALID_BLOCKS);
+        assert!(!blocks.contains(&hashes[0]));
+        assert!(blocks.contains(&hashes[MAX_INVALID_BLOCKS]));
+        assert_eq!(storage.invalid_blocks().unwrap().len(), MAX_INVALID_BLOCKS);
+    }
+}
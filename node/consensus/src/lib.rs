@@ -19,31 +19,126 @@
 #[macro_use]
 extern crate tracing;
 
+mod activations;
+pub use activations::*;
+
+mod admission;
+pub use admission::*;
+
+mod batch;
+pub use batch::*;
+
+mod audit;
+pub use audit::*;
+
+mod bench;
+pub use bench::*;
+
+#[cfg(any(test, feature = "chaos"))]
+mod chaos;
+#[cfg(any(test, feature = "chaos"))]
+pub use chaos::*;
+
+mod circuit;
+pub use circuit::*;
+
+mod coinbase;
+pub use coinbase::*;
+
+mod commitments;
+
+mod duplicates;
+pub use duplicates::*;
+
 mod helpers;
 pub use helpers::*;
 
+mod invalid;
+pub use invalid::*;
+
 mod memory_pool;
 pub use memory_pool::*;
 
+mod parameters;
+pub use parameters::*;
+
+mod policy;
+pub use policy::*;
+
+mod precheck;
+pub use precheck::*;
+
+mod recipients;
+pub use recipients::*;
+
+mod reorg;
+
+mod replay;
+pub use replay::*;
+
+mod simulation;
+pub use simulation::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod submissions;
+pub use submissions::*;
+
+mod supply;
+pub use supply::*;
+
+mod template;
+pub use template::*;
+
+mod verify;
+pub use verify::*;
+
+mod waiter;
+pub use waiter::*;
+
 #[cfg(test)]
 mod tests;
 
-use snarkos_node_ledger::Ledger;
+use snarkos_node_ledger::{Ledger, TransactionStructure};
 use snarkvm::prelude::*;
 
-use ::time::OffsetDateTime;
-use anyhow::{anyhow, ensure, Result};
-use indexmap::IndexMap;
-use parking_lot::RwLock;
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use core::time::Duration;
+use indexmap::{IndexMap, IndexSet};
+use parking_lot::{Mutex, RwLock};
 use rayon::iter::ParallelIterator;
-use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 /// The cost in microcredits per byte for the deployment transaction.
 const DEPLOYMENT_FEE_FACTOR: u64 = 1000;
-
+/// The maximum number of pre-verified transactions awaiting their block.
+const MAX_VERIFIED_TRANSACTIONS: usize = 1 << 14;
+/// The maximum number of seconds by which the timestamp of the next block may be ahead of the network-adjusted time.
+pub const MAXIMUM_FUTURE_BLOCK_TIME_IN_SECS: i64 = 600;
+
+/// The consensus module, which validates the blocks and transactions, and advances the ledger.
+///
+/// Its state is split into components that are locked independently: the ledger (see `Ledger`), the memory pool,
+/// the block template, and the cache of the verified transactions. No lock of consensus is held while a proof
+/// is verified, so the only critical section of a block is its commit to the ledger, which readers do not wait on.
+///
+/// The locks are acquired in the following order: `block_template`, `coinbase_recipients`, `miner_snapshot`,
+/// the ledger, the memory pool, `verified_transactions`, and the event bus.
 #[derive(Clone)]
 pub struct Consensus<N: Network, C: ConsensusStorage<N>> {
     /// The ledger.
@@ -52,9 +147,51 @@ pub struct Consensus<N: Network, C: ConsensusStorage<N>> {
     coinbase_puzzle: CoinbasePuzzle<N>,
     /// The memory pool.
     memory_pool: MemoryPool<N>,
+    /// The consensus rules of the circuits.
+    circuit_rules: Arc<CircuitRules<N>>,
+    /// The activation heights of the consensus rules.
+    rule_activations: Arc<RuleActivations>,
+    /// The verifying keys that were loaded and checked at startup.
+    parameters: Arc<ParameterRegistry<N>>,
+    /// The policy hooks of the node, in order of registration.
+    policy_hooks: Arc<Vec<Arc<dyn PolicyHook<N>>>>,
     /// The beacons.
     // TODO (howardwu): Update this to retrieve from a beacons store.
     beacons: Arc<RwLock<IndexMap<Address<N>, ()>>>,
+    /// The cached template for the next block.
+    block_template: Arc<Mutex<Option<Arc<BlockTemplate<N>>>>>,
+    /// The snapshot of the latest block, from which the miner builds the next block.
+    miner_snapshot: Arc<Mutex<Option<MinerSnapshot<N>>>>,
+    /// The increase in the fees of the memory pool that makes a block template stale.
+    template_fee_delta: Arc<RwLock<u64>>,
+    /// The limits on the declared contents of a submitted block, which are checked before its proofs are verified.
+    precheck_limits: Arc<RwLock<PrecheckLimits>>,
+    /// The recipients of the coinbase of the next block, if they are set.
+    coinbase_recipients: Arc<RwLock<Option<CoinbaseRecipients<N>>>>,
+    /// The number of blocks mined for each recipient of the coinbase.
+    mined_blocks: Arc<RwLock<MinedBlocks<N>>>,
+    /// The digests of the transactions whose proofs were verified ahead of their block.
+    verified_transactions: Arc<Mutex<IndexSet<[u8; 32]>>>,
+    /// The admissions of the unconfirmed transactions in flight, which deduplicate their concurrent validations.
+    transaction_validations: InFlightValidations<N::TransactionID, Result<(), String>>,
+    /// The checks of the next blocks in flight, keyed by the digest of their contents,
+    /// which deduplicate their concurrent validations.
+    block_validations: InFlightValidations<N::BlockHash, Result<(), String>>,
+    /// The blocks that were rejected for their contents.
+    invalid_blocks: InvalidBlocks<N>,
+    /// The blocks that were submitted by the miner or through the REST API, and committed.
+    submitted_blocks: SubmittedBlocks<N>,
+    /// The submissions whose blocks are validated in the background.
+    async_submissions: AsyncSubmissions<N>,
+    /// The audits of the historical blocks.
+    audit_log: AuditLog<N>,
+    /// The number of transaction proofs that were verified.
+    num_proof_verifications: Arc<AtomicU64>,
+    /// The total time spent verifying transaction proofs, in nanoseconds, summed over the verifying threads.
+    proof_verification_nanos: Arc<AtomicU64>,
+    /// The faults injected by the tests.
+    #[cfg(any(test, feature = "chaos"))]
+    chaos: ChaosController<N>,
     /// The boolean flag for the development mode.
     #[allow(dead_code)]
     is_dev: bool,
@@ -66,13 +203,37 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
         // Load the coinbase puzzle.
         let coinbase_puzzle = CoinbasePuzzle::<N>::load()?;
 
+        // Initialize the memory pool, which publishes its events on the event bus of the ledger.
+        let memory_pool = MemoryPool::with_event_bus(ledger.event_bus().clone());
+
         // Initialize consensus.
         let mut consensus = Self {
             ledger,
             coinbase_puzzle,
-            memory_pool: Default::default(),
+            memory_pool,
+            circuit_rules: Arc::new(CircuitRules::load()?),
+            rule_activations: Arc::new(RuleActivations::load::<N>()?),
+            parameters: Default::default(),
+            policy_hooks: Default::default(),
             // TODO (howardwu): Update this to retrieve from a validators store.
             beacons: Default::default(),
+            block_template: Default::default(),
+            miner_snapshot: Default::default(),
+            template_fee_delta: Arc::new(RwLock::new(DEFAULT_TEMPLATE_FEE_DELTA)),
+            precheck_limits: Arc::new(RwLock::new(PrecheckLimits::for_network::<N>())),
+            coinbase_recipients: Default::default(),
+            mined_blocks: Default::default(),
+            verified_transactions: Default::default(),
+            transaction_validations: Default::default(),
+            block_validations: Default::default(),
+            invalid_blocks: Default::default(),
+            submitted_blocks: Default::default(),
+            async_submissions: Default::default(),
+            audit_log: Default::default(),
+            num_proof_verifications: Default::default(),
+            proof_verification_nanos: Default::default(),
+            #[cfg(any(test, feature = "chaos"))]
+            chaos: Default::default(),
             is_dev,
         };
 
@@ -85,6 +246,54 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
         Ok(consensus)
     }
 
+    /// Returns the consensus rules of the circuits.
+    pub fn circuit_rules(&self) -> &CircuitRules<N> {
+        &self.circuit_rules
+    }
+
+    /// Sets the consensus rules of the circuits, which apply to the transactions validated from then on.
+    pub fn set_circuit_rules(&mut self, circuit_rules: CircuitRules<N>) {
+        self.circuit_rules = Arc::new(circuit_rules);
+    }
+
+    /// Returns the activation heights of the consensus rules.
+    pub fn rule_activations(&self) -> &RuleActivations {
+        &self.rule_activations
+    }
+
+    /// Sets the activation heights of the consensus rules, which apply to the blocks validated from then on.
+    pub fn set_rule_activations(&mut self, rule_activations: RuleActivations) {
+        self.rule_activations = Arc::new(rule_activations);
+    }
+
+    /// Returns `true` if the given consensus rule is enforced on a block at the given height.
+    pub fn is_active(&self, rule: ConsensusRule, height: u32) -> bool {
+        self.rule_activations.is_active(rule, height)
+    }
+
+    /// Returns the verifying keys that were loaded and checked at startup.
+    pub fn parameters(&self) -> &ParameterRegistry<N> {
+        &self.parameters
+    }
+
+    /// Sets the verifying keys that were loaded and checked at startup,
+    /// ensuring the VM verifies the transactions with the same keys.
+    pub fn set_parameters(&mut self, parameters: ParameterRegistry<N>) -> Result<()> {
+        let process = self.ledger.vm().process();
+        for circuit in parameters.circuits() {
+            // Retrieve the verifying key of the circuit in the VM, for the circuits of programs.
+            let verifying_key = match circuit.split_once('/') {
+                Some((program_id, function_name)) => process.read().get_verifying_key(program_id, function_name)?,
+                None => continue,
+            };
+            if parameters.verifying_key(circuit) != Some(&verifying_key) {
+                bail!("The verifying key for '{circuit}' in the VM does not match its parameter file")
+            }
+        }
+        self.parameters = Arc::new(parameters);
+        Ok(())
+    }
+
     /// Returns the beacon set.
     pub fn beacons(&self) -> IndexMap<Address<N>, ()> {
         self.beacons.read().clone()
@@ -113,20 +322,116 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
         &self.memory_pool
     }
 
+    /// Returns the blocks that were rejected for their contents.
+    pub const fn invalid_blocks(&self) -> &InvalidBlocks<N> {
+        &self.invalid_blocks
+    }
+
+    /// Returns the blocks that were submitted by the miner or through the REST API, and committed.
+    pub const fn submitted_blocks(&self) -> &SubmittedBlocks<N> {
+        &self.submitted_blocks
+    }
+
+    /// Returns the audits of the historical blocks.
+    pub const fn audit_log(&self) -> &AuditLog<N> {
+        &self.audit_log
+    }
+
+    /// Returns the controller of the faults injected by the tests.
+    #[cfg(any(test, feature = "chaos"))]
+    pub const fn chaos(&self) -> &ChaosController<N> {
+        &self.chaos
+    }
+
+    /// Returns the number of transaction proofs that were verified.
+    pub fn num_proof_verifications(&self) -> u64 {
+        self.num_proof_verifications.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total time spent verifying transaction proofs, summed over the verifying threads.
+    pub fn proof_verification_time(&self) -> Duration {
+        Duration::from_nanos(self.proof_verification_nanos.load(Ordering::Relaxed))
+    }
+
     /// Adds the given unconfirmed transaction to the memory pool.
     pub fn add_unconfirmed_transaction(&self, transaction: Transaction<N>) -> Result<()> {
+        self.admit_unconfirmed_transaction(transaction, None).map(|_| ())
+    }
+
+    /// Adds the given unconfirmed transaction, which was sent by the given peer (if any), to the memory pool.
+    ///
+    /// The concurrent admissions of the same transaction are validated once: the first arrival validates it,
+    /// and returns the peers that sent it in the meantime, while the later arrivals share its outcome.
+    pub fn admit_unconfirmed_transaction(
+        &self,
+        transaction: Transaction<N>,
+        peer_ip: Option<SocketAddr>,
+    ) -> Result<Admission<()>> {
+        share_validation(&self.transaction_validations, transaction.id(), peer_ip, || {
+            self.validate_unconfirmed_transaction(transaction)
+        })
+    }
+
+    /// Validates the given unconfirmed transaction, and adds it to the memory pool.
+    fn validate_unconfirmed_transaction(&self, transaction: Transaction<N>) -> Result<()> {
         // Ensure the transaction is not already in the memory pool.
         if self.memory_pool.contains_unconfirmed_transaction(transaction.id()) {
             bail!("Transaction is already in the memory pool.");
         }
+        // If the transaction depends on transactions in the memory pool, validate it against their state.
+        let parents = self.memory_pool.unconfirmed_parents(&transaction);
+        if !parents.is_empty() {
+            return Ok(self.add_dependent_unconfirmed_transaction(&transaction, &parents)?);
+        }
+        // Ensure the transaction does not mint credits.
+        self.check_transaction_coinbase(&transaction)?;
+        // Ensure the transaction has not expired, before verifying it.
+        self.check_transaction_expiry(&transaction)?;
         // Check that the transaction is well-formed and unique.
         self.check_transaction_basic(&transaction)?;
+        // Ensure the transaction is accepted by the policy hooks of the node.
+        self.check_transaction_policy(&transaction, PolicyStage::Admission)?;
         // Insert the transaction to the memory pool.
-        self.memory_pool.add_unconfirmed_transaction(&transaction);
+        self.memory_pool.add_unconfirmed_transaction(&transaction)?;
 
         Ok(())
     }
 
+    /// Restores the unconfirmed transactions persisted by the memory pool, in order of arrival, and removes
+    /// the persisted transactions that are no longer valid. Returns the number of restored transactions.
+    ///
+    /// Note that this must be called before the memory pool accepts any transaction.
+    pub fn restore_unconfirmed_transactions(&self) -> Result<usize> {
+        // Retrieve the persisted transactions, in order of arrival.
+        let mut transactions = self.memory_pool.persisted_transactions()?;
+        transactions.sort_by_key(|(_, arrival_time)| *arrival_time);
+
+        // Add the valid transactions to the memory pool, with their original arrival times.
+        let mut num_restored = 0;
+        let mut invalid = Vec::new();
+        for (transaction, arrival_time) in transactions {
+            let result = self
+                .check_transaction_coinbase(&transaction)
+                .map_err(anyhow::Error::from)
+                .and_then(|_| self.check_transaction_basic(&transaction))
+                .and_then(|_| {
+                    self.check_transaction_policy(&transaction, PolicyStage::Admission).map_err(anyhow::Error::from)
+                })
+                .and_then(|_| self.memory_pool.restore_unconfirmed_transaction(&transaction, arrival_time));
+            match result {
+                Ok(()) => num_restored += 1,
+                Err(error) => {
+                    debug!("Dropped persisted transaction '{}' - {error}", transaction.id());
+                    invalid.push(transaction.id());
+                }
+            }
+        }
+        // Remove the invalid transactions from storage.
+        self.memory_pool.remove_persisted_transactions(&invalid)?;
+
+        Ok(num_restored)
+    }
+
     /// Adds the given unconfirmed solution to the memory pool.
     pub fn add_unconfirmed_solution(&self, solution: &ProverSolution<N>) -> Result<()> {
         // Ensure the prover solution is not already in the memory pool.
@@ -167,7 +472,29 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
     }
 
     /// Returns a candidate for the next block in the ledger.
+    ///
+    /// If a block is committed while the candidate is built, the candidate is aborted and rebuilt,
+    /// so that the proposed block never extends a stale block.
     pub fn propose_next_block<R: Rng + CryptoRng>(&self, private_key: &PrivateKey<N>, rng: &mut R) -> Result<Block<N>> {
+        for _ in 0..=MAX_TEMPLATE_REBUILDS {
+            // Retrieve the snapshot before building the candidate, so that any commit in the meantime makes it stale.
+            let tip = self.miner_tip();
+            let block = self.build_next_block(private_key, rng)?;
+            // If the candidate does not extend the (fresh) snapshot, abort the candidate.
+            match block.previous_hash() == tip.block_hash && !self.is_miner_tip_stale(&tip) {
+                true => return Ok(block),
+                false => {
+                    debug!("Aborted the candidate block at height {}, as the ledger advanced", block.height());
+                    #[cfg(feature = "metrics")]
+                    metrics::increment_counter!(metrics::miner::TEMPLATE_ABORTS);
+                }
+            }
+        }
+        bail!("The ledger advanced during each of the {} attempts to propose the next block", MAX_TEMPLATE_REBUILDS + 1)
+    }
+
+    /// Builds a candidate for the next block in the ledger.
+    fn build_next_block<R: Rng + CryptoRng>(&self, private_key: &PrivateKey<N>, rng: &mut R) -> Result<Block<N>> {
         // Retrieve the latest state root.
         let latest_state_root = *self.ledger.latest_state_root();
         // Retrieve the latest block.
@@ -193,35 +520,9 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
         let prover_solutions =
             self.memory_pool.candidate_solutions(self, latest_height, latest_proof_target, latest_coinbase_target)?;
 
-        // TODO (raychu86): Clean this up or create a `total_supply_delta` in `Transactions`.
         // Calculate the new total supply of microcredits after the block.
-        let mut new_total_supply_in_microcredits = latest_total_supply_in_microcredits;
-        for transaction in transactions.iter() {
-            // Subtract the fee from the total supply.
-            let fee = transaction.fee()?;
-            new_total_supply_in_microcredits = new_total_supply_in_microcredits
-                .checked_sub(*fee)
-                .ok_or_else(|| anyhow!("Fee exceeded total supply of credits"))?;
-
-            // If the transaction is a coinbase, add the amount to the total supply.
-            if transaction.is_coinbase() {
-                match transaction {
-                    Transaction::Execute(_, execution, _) => {
-                        // Get the input amount of the coinbase transaction.
-                        match execution.get(0)?.inputs().get(1) {
-                            Some(Input::Public(_, Some(Plaintext::Literal(Literal::U64(amount), _)))) => {
-                                // Add the public amount minted to the total supply.
-                                new_total_supply_in_microcredits = new_total_supply_in_microcredits
-                                    .checked_add(**amount)
-                                    .ok_or_else(|| anyhow!("Total supply of microcredits overflowed"))?;
-                            }
-                            _ => bail!("Invalid coinbase transaction: Missing public input in 'credits.aleo/mint'"),
-                        }
-                    }
-                    _ => bail!("Invalid coinbase transaction"),
-                }
-            }
-        }
+        let new_total_supply_in_microcredits =
+            next_total_supply(latest_total_supply_in_microcredits, transactions.iter())?;
 
         // Construct the coinbase solution.
         let (coinbase, coinbase_accumulator_point) = match &prover_solutions {
@@ -237,7 +538,7 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
         };
 
         // Fetch the next round state.
-        let next_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let next_timestamp = self.ledger.network_time().now();
         let next_height = latest_height.saturating_add(1);
         let next_round = latest_block.round().saturating_add(1);
 
@@ -342,11 +643,17 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
 
     /// Advances the ledger to the next block.
     pub fn advance_to_next_block(&self, block: &Block<N>) -> Result<()> {
+        // Apply the faults injected by the tests.
+        #[cfg(any(test, feature = "chaos"))]
+        self.chaos.before_commit(block)?;
+
         // Adds the next block to the ledger.
         self.ledger.add_next_block(block)?;
 
-        // Clear the memory pool of unconfirmed transactions that are now invalid.
-        self.memory_pool.clear_invalid_transactions(self);
+        // Clear the memory pool of unconfirmed transactions that conflict with the block.
+        self.memory_pool.clear_conflicting_transactions(self, block);
+        // Clear the memory pool of unconfirmed transactions that expired with the block.
+        self.memory_pool.clear_expired_transactions(self);
 
         // If this starts a new epoch, clear all unconfirmed solutions from the memory pool.
         if block.epoch_number() > self.ledger.latest_epoch_number() {
@@ -381,7 +688,81 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
     }
 
     /// Checks the given block is valid next block.
+    ///
+    /// The concurrent checks of the same block are validated once, and the later arrivals share its outcome.
+    /// The block is identified by the digest of its contents, as a malleated copy of a block has the same hash.
     pub fn check_next_block(&self, block: &Block<N>) -> Result<()> {
+        let key = block_contents_key(block)?;
+        share_validation(&self.block_validations, key, None, || self.validate_next_block(block)).map(|_| ())
+    }
+
+    /// Validates the given block as the next block.
+    fn validate_next_block(&self, block: &Block<N>) -> Result<()> {
+        // Ensure the block was not already rejected for its contents.
+        self.check_block_not_invalid(block)?;
+        // Ensure the block extends the latest block.
+        self.check_block_position(block)?;
+        // Ensure the block does not contain duplicate transactions, serial numbers, or commitments.
+        self.check_block_duplicates(block)?;
+        // Ensure the block contents do not already exist in the ledger.
+        self.check_block_uniqueness(block)?;
+        // Ensure the block header, hash, and signature are valid.
+        self.check_block_header(block)?;
+        // Ensure the block transactions are valid.
+        self.check_block_transactions(block)?;
+        // Ensure the block coinbase solution is valid.
+        self.check_block_coinbase(block)
+    }
+
+    /// Stores the given block, which was rejected as the next block for the given reason, in the side branches
+    /// of the ledger. A block that extends the latest block was rejected for its contents, and is marked as invalid,
+    /// along with the given peer that sent it, so that it is not validated again.
+    ///
+    /// As the block hash only commits to the previous block hash and the header, only a block with an invalid
+    /// header is marked as invalid under its hash, while any other block is marked under the digest of its contents.
+    ///
+    /// Only a block whose hash, header members, and signature are valid is stored in the side branches, so that
+    /// a peer cannot fill them with forged headers. The header members of a block on a side branch are checked
+    /// without the ledger, as they depend on the state of its branch.
+    pub fn insert_rejected_block(&self, block: &Block<N>, reason: &str, peer_ip: Option<SocketAddr>) -> Result<()> {
+        let extends_latest_block = self.check_block_position(block).is_ok();
+        let valid_hash = self.check_block_hash(block).is_ok();
+        let valid_header_members = match block.previous_hash() == self.ledger.latest_hash() {
+            true => self.check_block_header_members(block).is_ok(),
+            false => block.header().is_valid(),
+        };
+        if extends_latest_block {
+            let key = match valid_hash && !valid_header_members {
+                true => block.hash(),
+                false => block_contents_key(block)?,
+            };
+            self.invalid_blocks.insert(key, block.height(), reason.to_string(), peer_ip)?;
+        }
+        // Forget the transactions of the block that were verified ahead of it.
+        let mut verified_transactions = self.verified_transactions.lock();
+        for transaction in block.transactions().values() {
+            verified_transactions.shift_remove(&transaction_digest(transaction)?);
+        }
+        drop(verified_transactions);
+        // Ensure the header of the block is authentic, before it is stored in the side branches.
+        ensure!(valid_hash, "Block {} ({}) has an incorrect block hash", block.height(), block.hash());
+        ensure!(valid_header_members, "Block {} ({}) has an invalid header", block.height(), block.hash());
+        self.check_block_signature(block)?;
+        self.ledger.insert_side_block(block)
+    }
+
+    /// Checks the given block was not already rejected for its contents.
+    pub fn check_block_not_invalid(&self, block: &Block<N>) -> Result<()> {
+        match self.invalid_blocks.get_block(block) {
+            Some(invalid) => {
+                bail!("Block {} ({}) is known to be invalid - {}", block.height(), block.hash(), invalid.reason)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the given block extends the latest block in the ledger.
+    pub fn check_block_position(&self, block: &Block<N>) -> Result<()> {
         // Ensure the previous block hash is correct.
         if self.ledger.latest_hash() != block.previous_hash() {
             bail!("The next block has an incorrect previous block hash")
@@ -420,6 +801,20 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             }
         }
 
+        // Ensure the next block timestamp is not too far ahead of the network-adjusted time. As the block may become
+        // valid later on, this is checked with the position of the block, so that it is not marked as invalid.
+        let next_timestamp = block.timestamp();
+        let maximum_timestamp = self.ledger.network_time().now().saturating_add(MAXIMUM_FUTURE_BLOCK_TIME_IN_SECS);
+        if next_timestamp > maximum_timestamp {
+            bail!("The next block timestamp {next_timestamp} is too far ahead of the network time {maximum_timestamp}")
+        }
+
+        Ok(())
+    }
+
+    /// Checks the transactions, serial numbers, commitments, nonces, and transition public keys
+    /// in the given block do not already exist in the ledger.
+    pub fn check_block_uniqueness(&self, block: &Block<N>) -> Result<()> {
         for transaction_id in block.transaction_ids() {
             // Ensure the transaction in the block do not already exist.
             if self.ledger.contains_transaction_id(transaction_id)? {
@@ -461,6 +856,11 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             }
         }
 
+        Ok(())
+    }
+
+    /// Checks the header, hash, and signature of the given block.
+    pub fn check_block_header(&self, block: &Block<N>) -> Result<()> {
         /* Block Header */
 
         // If the block is the genesis block, check that it is valid.
@@ -468,41 +868,13 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             bail!("Invalid genesis block");
         }
 
-        // Ensure the block header is valid.
-        if !block.header().is_valid() {
-            bail!("Invalid block header: {:?}", block.header());
-        }
+        // Ensure the members of the block header that the block hash commits to are valid.
+        self.check_block_header_members(block)?;
 
         // TODO (raychu86): Include mints from the leader of each round.
-        // TODO (raychu86): Clean this up or create a `total_supply_delta` in `Transactions`.
         // Calculate the new total supply of microcredits after the block.
-        let mut new_total_supply_in_microcredits = self.ledger.latest_total_supply_in_microcredits();
-        for transaction in block.transactions().iter() {
-            // Subtract the fee from the total supply.
-            let fee = transaction.fee()?;
-            new_total_supply_in_microcredits = new_total_supply_in_microcredits
-                .checked_sub(*fee)
-                .ok_or_else(|| anyhow!("Fee exceeded total supply of credits"))?;
-
-            // If the transaction is a coinbase, add the amount to the total supply.
-            if transaction.is_coinbase() {
-                match transaction {
-                    Transaction::Execute(_, execution, _) => {
-                        // Get the input amount of the coinbase transaction.
-                        match execution.get(0)?.inputs().get(1) {
-                            Some(Input::Public(_, Some(Plaintext::Literal(Literal::U64(amount), _)))) => {
-                                // Add the public amount minted to the total supply.
-                                new_total_supply_in_microcredits = new_total_supply_in_microcredits
-                                    .checked_add(**amount)
-                                    .ok_or_else(|| anyhow!("Total supply of microcredits overflowed"))?;
-                            }
-                            _ => bail!("Invalid coinbase transaction: Missing public input in 'credits.aleo/mint'"),
-                        }
-                    }
-                    _ => bail!("Invalid coinbase transaction"),
-                }
-            }
-        }
+        let new_total_supply_in_microcredits =
+            next_total_supply(self.ledger.latest_total_supply_in_microcredits(), block.transactions().iter())?;
 
         // Ensure the total supply in microcredits is correct.
         if new_total_supply_in_microcredits != block.total_supply_in_microcredits() {
@@ -548,6 +920,19 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             }
         }
 
+        // Ensure the block hash and signature are valid.
+        self.check_block_hash(block)?;
+        self.check_block_signature(block)
+    }
+
+    /// Checks the members of the header of the given block that only depend on the header and the ledger.
+    /// As the block hash commits to the header, a block that fails these checks is invalid under its hash.
+    fn check_block_header_members(&self, block: &Block<N>) -> Result<()> {
+        // Ensure the block header is valid.
+        if !block.header().is_valid() {
+            bail!("Invalid block header: {:?}", block.header());
+        }
+
         // Construct the next coinbase target.
         let expected_coinbase_target = coinbase_target(
             self.ledger.last_coinbase_target(),
@@ -568,8 +953,11 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             bail!("Invalid proof target: expected {}, got {}", expected_proof_target, block.proof_target())
         }
 
-        /* Block Hash */
+        Ok(())
+    }
 
+    /// Checks the hash of the given block commits to its previous block hash and its header.
+    pub fn check_block_hash(&self, block: &Block<N>) -> Result<()> {
         // Compute the Merkle root of the block header.
         let header_root = match block.header().to_root() {
             Ok(root) => root,
@@ -589,8 +977,11 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             }
         };
 
-        /* Signature */
+        Ok(())
+    }
 
+    /// Checks the given block is signed by an authorized beacon.
+    pub fn check_block_signature(&self, block: &Block<N>) -> Result<()> {
         // Ensure the block is signed by an authorized beacon.
         let signer = block.signature().to_address();
         if !self.beacons.read().contains_key(&signer) {
@@ -602,6 +993,54 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             bail!("Invalid signature for block {} ({})", block.height(), block.hash());
         }
 
+        Ok(())
+    }
+
+    /// Checks the transactions in the given block are well-formed and unique.
+    pub fn check_block_transactions(&self, block: &Block<N>) -> Result<()> {
+        // Ensure the transactions list is valid.
+        self.check_block_transactions_list(block)?;
+        // Ensure the coinbase transactions are valid.
+        self.check_block_coinbase_transactions(block)?;
+
+        // Ensure each transaction is well-formed and unique.
+        cfg_iter!(block.transactions()).try_for_each(|(_, transaction)| {
+            self.check_transaction_basic(transaction)
+                .map_err(|e| anyhow!("Invalid transaction found in the transactions list: {e}"))
+        })?;
+
+        Ok(())
+    }
+
+    /// Verifies the transactions of the given block ahead of its position in the ledger,
+    /// so that their proofs are not verified again when the block is checked as the next block.
+    ///
+    /// The proofs that fail here are not rejected, as they may depend on a program that is deployed
+    /// by a block which is not yet in the ledger, and are verified again with the next block.
+    pub fn preverify_block(&self, block: &Block<N>) -> Result<()> {
+        // Ensure the block was not already rejected for its contents.
+        self.check_block_not_invalid(block)?;
+        // Ensure the transactions list is valid.
+        self.check_block_transactions_list(block)?;
+        // Ensure the block does not contain duplicates, before their proofs are verified.
+        self.check_block_duplicates(block)?;
+
+        // Verify the proofs of each transaction.
+        let verified = cfg_iter!(block.transactions())
+            .filter(|(_, transaction)| self.verify_transaction_proof(transaction).is_ok())
+            .filter_map(|(_, transaction)| transaction_digest(transaction).ok())
+            .collect::<Vec<_>>();
+
+        // Record the verified transactions, evicting the oldest ones beyond the capacity.
+        let mut verified_transactions = self.verified_transactions.lock();
+        verified_transactions.extend(verified);
+        let num_evicted = verified_transactions.len().saturating_sub(MAX_VERIFIED_TRANSACTIONS);
+        verified_transactions.drain(..num_evicted);
+        Ok(())
+    }
+
+    /// Checks the transactions root and the number of transactions in the given block.
+    pub fn check_block_transactions_list(&self, block: &Block<N>) -> Result<()> {
         /* Transactions */
 
         // Compute the transactions root.
@@ -630,12 +1069,11 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
             bail!("Cannot validate a block with more than {} transactions", Transactions::<N>::MAX_TRANSACTIONS);
         }
 
-        // Ensure each transaction is well-formed and unique.
-        cfg_iter!(block.transactions()).try_for_each(|(_, transaction)| {
-            self.check_transaction_basic(transaction)
-                .map_err(|e| anyhow!("Invalid transaction found in the transactions list: {e}"))
-        })?;
+        Ok(())
+    }
 
+    /// Checks the finalize root and the coinbase solution of the given block.
+    pub fn check_block_coinbase(&self, block: &Block<N>) -> Result<()> {
         /* Finalize Root */
 
         // TODO (raychu86): Properly check the finalize root once `finalize` is integrated.
@@ -692,34 +1130,99 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
 
     /// Checks the given transaction is well-formed and unique.
     pub fn check_transaction_basic(&self, transaction: &Transaction<N>) -> Result<()> {
+        // Ensure the transaction is well-formed.
+        self.check_transaction_structure(transaction)?;
+        // Ensure the transaction is valid.
+        self.check_transaction_proof(transaction)?;
+        // Ensure the transaction contents do not already exist in the ledger.
+        self.check_transaction_uniqueness(transaction)
+    }
+
+    /// Checks the given transaction is new, and pays a sufficient fee.
+    pub fn check_transaction_structure(&self, transaction: &Transaction<N>) -> Result<()> {
         let transaction_id = transaction.id();
+        // Retrieve the height of the next block, under whose rules the transaction is validated.
+        let next_height = self.ledger.latest_height().saturating_add(1);
+
+        // Ensure the transaction is well-formed.
+        if self.is_active(ConsensusRule::StrictStructure, next_height) {
+            if let Err(violation) = transaction.validate_structure() {
+                return Err(TransactionRejection::<N>::Malformed { violation }.into());
+            }
+        }
 
         // Ensure the ledger does not already contain the given transaction ID.
         if self.ledger.contains_transaction_id(&transaction_id)? {
             bail!("Transaction '{transaction_id}' already exists in the ledger")
         }
 
-        // Ensure the transaction does not contain a coinbase.
-        if self.ledger.latest_height() > 0 && transaction.is_coinbase() {
-            bail!("Transaction '{transaction_id}' contains an illegal function call")
-        }
-
         /* Fee */
 
         // TODO (raychu86): Currently ignoring this rule for executions. Revisit this in phase 3.
         // Ensure transactions with a positive balance must pay for its storage in bytes.
         let fee = transaction.fee()?;
+        // Ensure the fee is within the total supply of microcredits.
+        let total_supply = self.ledger.latest_total_supply_in_microcredits();
+        if *fee > total_supply {
+            return Err(TransactionRejection::<N>::ExcessiveFee { fee: *fee, total_supply }.into());
+        }
         if matches!(transaction, Transaction::Deploy(..))
             && u64::try_from(transaction.to_bytes_le()?.len())?.saturating_mul(DEPLOYMENT_FEE_FACTOR) > *fee
         {
             bail!("Transaction '{transaction_id}' has insufficient fee to cover its storage in bytes")
         }
 
+        /* Circuits */
+
+        // Ensure the circuits of the transaction are valid in the next block.
+        if self.is_active(ConsensusRule::CircuitRules, next_height) {
+            self.circuit_rules.check_transaction(transaction, next_height)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the global state roots of the given transaction exist in the ledger.
+    pub fn check_transaction_state_roots(&self, transaction: &Transaction<N>) -> Result<()> {
+        // Ensure the ledger contains each global state root of the execution and fee.
+        for global_state_root in global_state_roots(transaction) {
+            if !self.ledger.contains_state_root(&global_state_root)? {
+                bail!("Global state root '{global_state_root}' does not exist in the ledger")
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the proofs of the given transaction.
+    pub fn check_transaction_proof(&self, transaction: &Transaction<N>) -> Result<()> {
         /* Proof(s) */
 
+        // If the transaction was verified ahead of its block, skip the verification.
+        if let Ok(digest) = transaction_digest(transaction) {
+            if self.verified_transactions.lock().shift_remove(&digest) {
+                return Ok(());
+            }
+        }
+
         // Ensure the transaction is valid.
-        self.ledger.vm().check_transaction(transaction)?;
+        self.verify_transaction_proof(transaction)
+    }
+
+    /// Verifies the proofs of the given transaction, counting the verification.
+    fn verify_transaction_proof(&self, transaction: &Transaction<N>) -> Result<()> {
+        self.num_proof_verifications.fetch_add(1, Ordering::Relaxed);
+        #[cfg(any(test, feature = "chaos"))]
+        self.chaos.before_verification();
+        let timer = Instant::now();
+        let result = self.ledger.vm().check_transaction(transaction);
+        let nanos = u64::try_from(timer.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.proof_verification_nanos.fetch_add(nanos, Ordering::Relaxed);
+        result
+    }
 
+    /// Checks the inputs, outputs, program, and metadata of the given transaction do not already exist in the ledger.
+    pub fn check_transaction_uniqueness(&self, transaction: &Transaction<N>) -> Result<()> {
         /* Input */
 
         // Ensure the ledger does not already contain the given input ID.
@@ -795,3 +1298,45 @@ impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
         Ok(())
     }
 }
+
+/// Validates the item with the given key with the given function, unless it is already validated, in which case
+/// the outcome of that validation is shared. The arrival that validates the item returns its original error.
+fn share_validation<K: Copy + Eq + core::hash::Hash>(
+    validations: &InFlightValidations<K, Result<(), String>>,
+    key: K,
+    peer_ip: Option<SocketAddr>,
+    validate: impl FnOnce() -> Result<()>,
+) -> Result<Admission<()>> {
+    let mut error = None;
+    let admission = validations.validate(key, peer_ip, || {
+        validate().map_err(|e| {
+            let message = e.to_string();
+            error = Some(e);
+            message
+        })
+    });
+    match admission {
+        Admission::Validated { outcome: Ok(()), senders } => Ok(Admission::Validated { outcome: (), senders }),
+        Admission::Shared { outcome: Ok(()) } => Ok(Admission::Shared { outcome: () }),
+        Admission::Validated { outcome: Err(message), .. } | Admission::Shared { outcome: Err(message) } => {
+            Err(error.unwrap_or_else(|| anyhow!(message)))
+        }
+    }
+}
+
+/// Returns the digest of the given transaction, which commits to its proofs, unlike the transaction ID.
+pub(crate) fn transaction_digest<N: Network>(transaction: &Transaction<N>) -> Result<[u8; 32]> {
+    Ok(Sha256::digest(transaction.to_bytes_le()?).into())
+}
+
+/// Returns the global state roots of the execution and fee of the given transaction.
+pub(crate) fn global_state_roots<N: Network>(transaction: &Transaction<N>) -> Vec<N::StateRoot> {
+    match transaction {
+        Transaction::Deploy(_, _, _, fee) => vec![fee.global_state_root()],
+        Transaction::Execute(_, execution, fee) => {
+            let mut roots = vec![execution.global_state_root()];
+            roots.extend(fee.as_ref().map(|fee| fee.global_state_root()));
+            roots
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use core::fmt;
+
+/// The maximum number of abandoned transaction IDs that are remembered.
+pub const MAX_ABANDONED_TRANSACTIONS: usize = 4096;
+
+/// The reason a transaction could not be abandoned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AbandonError {
+    /// The transaction is already confirmed in the ledger.
+    Confirmed,
+    /// The transaction is not in the memory pool.
+    NotInMemoryPool,
+    /// The transaction is in the memory pool, but was not submitted locally.
+    NotLocal,
+}
+
+impl fmt::Display for AbandonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Confirmed => write!(f, "the transaction is already confirmed"),
+            Self::NotInMemoryPool => write!(f, "the transaction is not in the memory pool"),
+            Self::NotLocal => write!(f, "the transaction was not submitted locally"),
+        }
+    }
+}
+
+impl std::error::Error for AbandonError {}
+
+/// The status of a locally submitted transaction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LocalStatus {
+    /// The transaction is in the memory pool.
+    Pending,
+    /// The transaction was abandoned, and removed from the memory pool.
+    Abandoned,
+}
+
+impl fmt::Display for LocalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Abandoned => write!(f, "abandoned"),
+        }
+    }
+}
+
+impl<N: Network> MemoryPool<N> {
+    /// Returns `true` if the given unconfirmed transaction was submitted locally.
+    pub fn is_local_transaction(&self, transaction_id: &N::TransactionID) -> bool {
+        self.local_transactions.read().contains(transaction_id)
+    }
+
+    /// Returns `true` if the given transaction was recently abandoned.
+    pub fn is_abandoned_transaction(&self, transaction_id: &N::TransactionID) -> bool {
+        self.abandoned_transactions.lock().contains(transaction_id)
+    }
+
+    /// Returns the locally submitted transactions that are pending, followed by the recently abandoned ones.
+    pub fn local_transactions(&self) -> Vec<(N::TransactionID, LocalStatus)> {
+        let pending = self.local_transactions.read().iter().map(|id| (*id, LocalStatus::Pending)).collect::<Vec<_>>();
+        let abandoned = self.abandoned_transactions.lock().iter().map(|id| (*id, LocalStatus::Abandoned)).collect();
+        [pending, abandoned].concat()
+    }
+
+    /// Removes the given locally submitted transaction from the memory pool, along with the transactions
+    /// that depend on it, and returns the IDs of the removed transactions.
+    ///
+    /// The abandoned transactions are not remembered as rejected, so that a transaction spending
+    /// the same records can be admitted afterwards.
+    pub fn abandon_transaction(&self, transaction_id: &N::TransactionID) -> Result<Vec<N::TransactionID>> {
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        let mut local_transactions = self.local_transactions.write();
+
+        // Ensure the transaction is in the memory pool, and was submitted locally.
+        if !unconfirmed_transactions.contains_key(transaction_id) {
+            bail!(AbandonError::NotInMemoryPool)
+        }
+        if !local_transactions.contains(transaction_id) {
+            bail!(AbandonError::NotLocal)
+        }
+
+        // Remove the transaction and its dependents, as the cascade is unbounded.
+        let abandoned =
+            Self::with_dependents(&unconfirmed_transactions, vec![*transaction_id], usize::MAX).unwrap_or_default();
+        let rejected = abandoned
+            .iter()
+            .map(|abandoned_id| {
+                unconfirmed_transactions.remove(abandoned_id);
+                local_transactions.remove(abandoned_id);
+                debug!("Abandoned transaction '{abandoned_id}' in the memory pool");
+                EvictedTransaction {
+                    transaction_id: *abandoned_id,
+                    reason: match abandoned_id == transaction_id {
+                        true => "abandoned".to_string(),
+                        false => format!("abandoned with transaction '{transaction_id}'"),
+                    },
+                    replaced_by: None,
+                    expired: false,
+                }
+            })
+            .collect::<Vec<_>>();
+        let is_flush_due = self.queue_writes([], abandoned.iter().copied());
+        drop(local_transactions);
+        drop(unconfirmed_transactions);
+
+        // Remember the abandoned transactions, evicting the oldest ones beyond the maximum.
+        let mut abandoned_transactions = self.abandoned_transactions.lock();
+        abandoned_transactions.extend(abandoned.iter().copied());
+        let num_evicted = abandoned_transactions.len().saturating_sub(MAX_ABANDONED_TRANSACTIONS);
+        abandoned_transactions.drain(..num_evicted);
+        drop(abandoned_transactions);
+
+        self.flush_if_due(is_flush_due);
+        self.notify_evictions(rejected);
+        Ok(abandoned)
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Abandons the given locally submitted transaction, which removes it from the memory pool along with
+    /// the transactions that depend on it, and returns the IDs of the removed transactions.
+    ///
+    /// There is no way to cancel a transaction once it was broadcast, so this only frees its inputs locally.
+    pub fn abandon_transaction(&self, transaction_id: &N::TransactionID) -> Result<Vec<N::TransactionID>> {
+        // Ensure the transaction is not confirmed.
+        if self.ledger.contains_transaction_id(transaction_id)? {
+            bail!(AbandonError::Confirmed)
+        }
+        // Remove the transaction and its dependents from the memory pool.
+        let abandoned = self.memory_pool.abandon_transaction(transaction_id)?;
+        Ok(abandoned)
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::TransactionRejection;
+
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+
+/// The default number of blocks past the admission window after which a transaction expires from the memory pool.
+pub const DEFAULT_EXPIRY_GRACE: u32 = 10;
+/// The maximum number of recently expired transaction IDs that are remembered, to ignore their re-broadcasts.
+pub const MAX_EXPIRED_TRANSACTIONS: usize = 4096;
+
+/// The policy for expiring unconfirmed transactions, based on the age of their oldest global state root.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExpiryPolicy {
+    /// The maximum number of blocks since the oldest global state root of a transaction admitted to the memory pool.
+    pub window: u32,
+    /// The number of blocks past the admission window after which a transaction expires from the memory pool.
+    pub grace: u32,
+}
+
+impl ExpiryPolicy {
+    /// Initializes a new expiry policy with the given admission window, and the default grace.
+    pub const fn new(window: u32) -> Self {
+        Self { window, grace: DEFAULT_EXPIRY_GRACE }
+    }
+
+    /// Returns `true` if a transaction with the given state root age is admitted to the memory pool.
+    pub const fn admits(&self, state_root_age: u32) -> bool {
+        state_root_age <= self.window
+    }
+
+    /// Returns `true` if a transaction with the given state root age expires from the memory pool.
+    pub const fn expires(&self, state_root_age: u32) -> bool {
+        state_root_age > self.window.saturating_add(self.grace)
+    }
+}
+
+impl<N: Network> MemoryPool<N> {
+    /// Returns the policy for expiring unconfirmed transactions, if expiry is enabled.
+    pub fn expiry_policy(&self) -> Option<ExpiryPolicy> {
+        *self.expiry_policy.read()
+    }
+
+    /// Sets the policy for expiring unconfirmed transactions, or disables expiry if `None`.
+    pub fn set_expiry_policy(&self, policy: Option<ExpiryPolicy>) {
+        *self.expiry_policy.write() = policy;
+    }
+
+    /// Returns `true` if the given transaction recently expired from the memory pool.
+    pub fn is_expired_transaction(&self, transaction_id: &N::TransactionID) -> bool {
+        self.expired_transactions.lock().contains(transaction_id)
+    }
+
+    /// Marks the given unconfirmed transaction as submitted locally, so that its expiry is logged as a warning.
+    pub fn mark_local_transaction(&self, transaction_id: N::TransactionID) {
+        if self.contains_unconfirmed_transaction(transaction_id) {
+            self.local_transactions.write().insert(transaction_id);
+        }
+    }
+
+    /// Clears the memory pool of unconfirmed transactions whose oldest global state root is older than the
+    /// admission window plus the grace, and returns the IDs of the removed transactions.
+    ///
+    /// The expired transactions are remembered, so that their re-broadcasts are rejected without being verified.
+    pub fn clear_expired_transactions<C: ConsensusStorage<N>>(
+        &self,
+        consensus: &Consensus<N, C>,
+    ) -> Vec<N::TransactionID> {
+        // Retrieve the expiry policy, if expiry is enabled.
+        let policy = match self.expiry_policy() {
+            Some(policy) => policy,
+            None => return Vec::new(),
+        };
+
+        // Find the expired transactions, outside the lock.
+        let latest_height = consensus.ledger.latest_height();
+        let expired = self
+            .unconfirmed_transactions()
+            .into_iter()
+            .filter_map(|transaction| match consensus.state_root_age(&transaction, latest_height) {
+                Ok(Some(state_root_age)) if policy.expires(state_root_age) => Some((transaction.id(), state_root_age)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Remove the expired transactions that are still in the memory pool.
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        let rejected = expired
+            .into_iter()
+            .filter(|(transaction_id, _)| unconfirmed_transactions.remove(transaction_id).is_some())
+            .map(|(transaction_id, state_root_age)| {
+                trace!("Expired transaction '{transaction_id}' from the memory pool");
+                EvictedTransaction {
+                    transaction_id,
+                    reason: format!(
+                        "expired, as its global state root is {state_root_age} blocks old (window is {} blocks)",
+                        policy.window
+                    ),
+                    replaced_by: None,
+                    expired: true,
+                }
+            })
+            .collect::<Vec<_>>();
+        let transaction_ids = rejected.iter().map(|rejected| rejected.transaction_id).collect::<Vec<_>>();
+        let is_flush_due = self.queue_writes([], transaction_ids.iter().copied());
+
+        // Forget the local transactions that are no longer in the memory pool, and warn about the expired ones.
+        let mut local_transactions = self.local_transactions.write();
+        for transaction_id in &transaction_ids {
+            if local_transactions.contains(transaction_id) {
+                warn!("Local transaction '{transaction_id}' expired from the memory pool - re-create it from a recent state root");
+            }
+        }
+        local_transactions.retain(|transaction_id| unconfirmed_transactions.contains_key(transaction_id));
+        drop(local_transactions);
+        drop(unconfirmed_transactions);
+
+        // Remember the expired transactions, evicting the oldest ones beyond the maximum.
+        let mut expired_transactions = self.expired_transactions.lock();
+        expired_transactions.extend(transaction_ids.iter().copied());
+        let num_evicted = expired_transactions.len().saturating_sub(MAX_EXPIRED_TRANSACTIONS);
+        expired_transactions.drain(..num_evicted);
+        drop(expired_transactions);
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!(metrics::memory_pool::EXPIRED, transaction_ids.len() as u64);
+
+        self.flush_if_due(is_flush_due);
+        self.notify_evictions(rejected);
+        transaction_ids
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Checks the given transaction has not expired, if an expiry policy is set.
+    ///
+    /// A transaction that recently expired from the memory pool is rejected without computing its state root age,
+    /// so that its re-broadcasts are cheap to ignore.
+    pub fn check_transaction_expiry(&self, transaction: &Transaction<N>) -> Result<(), TransactionRejection<N>> {
+        // Retrieve the expiry policy, if expiry is enabled.
+        let policy = match self.memory_pool.expiry_policy() {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        // Ensure the transaction did not recently expire from the memory pool.
+        if self.memory_pool.is_expired_transaction(&transaction.id()) {
+            return Err(TransactionRejection::Expired);
+        }
+        // Ensure the oldest global state root of the transaction is within the admission window.
+        // Note that a global state root that is not in the ledger is left to the proof verification.
+        match self.state_root_age(transaction, self.ledger.latest_height()) {
+            Ok(Some(state_root_age)) if !policy.admits(state_root_age) => {
+                Err(TransactionRejection::Stale { state_root_age, window: policy.window })
+            }
+            Ok(_) => Ok(()),
+            Err(error) => Err(TransactionRejection::Invalid { reason: error.to_string() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_policy() {
+        let policy = ExpiryPolicy { window: 100, grace: 10 };
+
+        // Ensure a transaction is admitted up to the window, and expires only past the grace.
+        assert!(policy.admits(100));
+        assert!(!policy.admits(101));
+        assert!(!policy.expires(110));
+        assert!(policy.expires(111));
+
+        // Ensure the grace does not overflow.
+        let policy = ExpiryPolicy { window: u32::MAX, grace: DEFAULT_EXPIRY_GRACE };
+        assert!(!policy.expires(u32::MAX));
+    }
+}
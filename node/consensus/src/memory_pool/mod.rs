@@ -14,23 +14,86 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+mod abandon;
+pub use abandon::*;
+
+mod expiry;
+pub use expiry::*;
+
+mod persistence;
+pub use persistence::*;
+
 mod solutions;
 mod transactions;
+pub use transactions::fee_rate;
+pub(crate) use transactions::{conflicts_with, depends_on};
+
+use crate::{anchor_block_height, Consensus, PolicyStage, TransactionRejection};
+use snarkos_node_ledger::{Event, EventBus, EvictedTransaction};
+use snarkvm::prelude::{
+    Block,
+    ConsensusStorage,
+    Field,
+    Itertools,
+    Network,
+    ProverSolution,
+    PuzzleCommitment,
+    ToBytes,
+    Transaction,
+};
+
+use ::time::OffsetDateTime;
+use anyhow::{anyhow, bail, ensure, Result};
+use indexmap::IndexSet;
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
-use crate::{anchor_block_height, Consensus};
-use snarkvm::prelude::{ConsensusStorage, Itertools, Network, ProverSolution, PuzzleCommitment, Transaction};
+/// The maximum number of transactions that a replacement may evict from the memory pool, including dependents.
+pub const MAX_REPLACEMENT_EVICTIONS: usize = 100;
 
-use anyhow::{anyhow, Result};
-use parking_lot::RwLock;
-use std::{collections::HashMap, sync::Arc};
+/// The policy for replacing conflicting transactions in the memory pool (replace-by-fee).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReplacementPolicy {
+    /// The amount, in microcredits, by which the fee of a replacement must exceed the combined fees
+    /// of the transactions it evicts.
+    pub fee_increment: u64,
+    /// The maximum number of transactions that a replacement may evict, including dependents.
+    pub max_evictions: usize,
+}
+
+impl ReplacementPolicy {
+    /// Initializes a new replacement policy with the given fee increment.
+    pub const fn new(fee_increment: u64) -> Self {
+        Self { fee_increment, max_evictions: MAX_REPLACEMENT_EVICTIONS }
+    }
+}
 
 #[derive(Clone, Debug)]
 #[allow(clippy::type_complexity)]
 pub struct MemoryPool<N: Network> {
-    /// The pool of unconfirmed transactions.
-    unconfirmed_transactions: Arc<RwLock<HashMap<N::TransactionID, Transaction<N>>>>,
+    /// The pool of unconfirmed transactions and their arrival times.
+    unconfirmed_transactions: Arc<RwLock<HashMap<N::TransactionID, (Transaction<N>, i64)>>>,
     /// The pool of unconfirmed solutions and their proof targets.
     unconfirmed_solutions: Arc<RwLock<HashMap<PuzzleCommitment<N>, (ProverSolution<N>, u64)>>>,
+    /// The event bus, on which the accepted and evicted transactions are published.
+    events: EventBus<N>,
+    /// The policy for replacing conflicting transactions, if replacements are enabled.
+    replacement_policy: Arc<RwLock<Option<ReplacementPolicy>>>,
+    /// The policy for expiring unconfirmed transactions, if expiry is enabled.
+    expiry_policy: Arc<RwLock<Option<ExpiryPolicy>>>,
+    /// The IDs of the transactions that recently expired from the memory pool, in order of expiry.
+    expired_transactions: Arc<Mutex<IndexSet<N::TransactionID>>>,
+    /// The IDs of the unconfirmed transactions that were submitted locally.
+    local_transactions: Arc<RwLock<HashSet<N::TransactionID>>>,
+    /// The IDs of the local transactions that were recently abandoned, in order of abandonment.
+    abandoned_transactions: Arc<Mutex<IndexSet<N::TransactionID>>>,
+    /// The maximum number of bytes of the unconfirmed transactions, if the memory pool is bounded.
+    byte_budget: Arc<RwLock<Option<usize>>>,
+    /// The write coalescer in front of the storage of the unconfirmed transactions, if the memory pool is persisted.
+    storage: Arc<RwLock<Option<Arc<MemoryPoolWriter<N>>>>>,
 }
 
 impl<N: Network> Default for MemoryPool<N> {
@@ -42,6 +105,45 @@ impl<N: Network> Default for MemoryPool<N> {
 impl<N: Network> MemoryPool<N> {
     /// Initializes a new instance of a memory pool.
     pub fn new() -> Self {
-        Self { unconfirmed_transactions: Default::default(), unconfirmed_solutions: Default::default() }
+        Self::with_event_bus(Default::default())
+    }
+
+    /// Initializes a new instance of a memory pool, which publishes its events on the given event bus.
+    pub fn with_event_bus(events: EventBus<N>) -> Self {
+        Self {
+            unconfirmed_transactions: Default::default(),
+            unconfirmed_solutions: Default::default(),
+            events,
+            replacement_policy: Default::default(),
+            expiry_policy: Default::default(),
+            expired_transactions: Default::default(),
+            local_transactions: Default::default(),
+            abandoned_transactions: Default::default(),
+            byte_budget: Default::default(),
+            storage: Default::default(),
+        }
+    }
+
+    /// Returns the policy for replacing conflicting transactions, if replacements are enabled.
+    pub fn replacement_policy(&self) -> Option<ReplacementPolicy> {
+        *self.replacement_policy.read()
+    }
+
+    /// Sets the policy for replacing conflicting transactions, or disables replacements if `None`.
+    pub fn set_replacement_policy(&self, policy: Option<ReplacementPolicy>) {
+        *self.replacement_policy.write() = policy;
+    }
+
+    /// Returns the maximum number of bytes of the unconfirmed transactions, if the memory pool is bounded.
+    pub fn byte_budget(&self) -> Option<usize> {
+        *self.byte_budget.read()
+    }
+
+    /// Sets the maximum number of bytes of the unconfirmed transactions, or unbounds the memory pool if `None`.
+    ///
+    /// Lowering the budget does not evict any transaction, but new transactions are rejected
+    /// until the unconfirmed transactions fit in the budget again.
+    pub fn set_byte_budget(&self, byte_budget: Option<usize>) {
+        *self.byte_budget.write() = byte_budget;
     }
 }
@@ -0,0 +1,406 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use core::{fmt, hash::Hash, time::Duration};
+use indexmap::IndexMap;
+use std::time::Instant;
+
+/// The default interval at which the queued writes of the memory pool are flushed to storage.
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+/// The default number of bytes of queued writes of the memory pool that triggers a flush ahead of the interval.
+pub const DEFAULT_FLUSH_BYTES: usize = 4 * 1024 * 1024;
+
+/// A storage that writes a batch of insertions and removals atomically.
+pub trait BatchWriter<K, V>: Send + Sync {
+    /// Writes the given batch atomically, where `Some` inserts the value of the key, and `None` removes the key.
+    fn write_batch(&self, batch: &[(K, Option<V>)]) -> Result<()>;
+}
+
+impl<K, V, W: BatchWriter<K, V> + ?Sized> BatchWriter<K, V> for Arc<W> {
+    fn write_batch(&self, batch: &[(K, Option<V>)]) -> Result<()> {
+        (**self).write_batch(batch)
+    }
+}
+
+/// A storage of the unconfirmed transactions of the memory pool, and their arrival times.
+pub trait MemoryPoolStorage<N: Network>: BatchWriter<N::TransactionID, (Transaction<N>, i64)> {
+    /// Returns the persisted transactions and their arrival times.
+    fn transactions(&self) -> Result<Vec<(Transaction<N>, i64)>>;
+}
+
+/// A queue of writes in front of a storage, which are flushed as a single batch once the flush interval
+/// has elapsed since the last flush, or the queued writes reach the byte threshold.
+///
+/// Only the latest write of each key is queued, so a key that is inserted and removed between two flushes
+/// is only removed. The flushes are serialized, so the batches reach the storage in the order of the writes.
+///
+/// On a crash, the writes queued since the last flush are lost, while the flushed writes are not.
+/// A removal is flushed before the writes that queued it return, along with the writes queued before it,
+/// so that a removed key never reappears in storage after a crash.
+pub struct WriteCoalescer<K, V, W> {
+    /// The storage.
+    writer: W,
+    /// The queued writes.
+    queue: Mutex<WriteQueue<K, V>>,
+    /// The lock that serializes the flushes.
+    flush_lock: Mutex<()>,
+    /// The interval after which the queued writes are flushed.
+    flush_interval: Duration,
+    /// The number of bytes of queued writes after which they are flushed.
+    flush_bytes: usize,
+}
+
+/// The writes queued since the last flush.
+struct WriteQueue<K, V> {
+    /// The latest write of each key and its size in bytes, in order of the first write since the last flush.
+    writes: IndexMap<K, (Option<V>, usize)>,
+    /// The number of bytes of the queued writes.
+    num_bytes: usize,
+    /// Whether a removal is queued.
+    has_removal: bool,
+    /// The time of the last flush.
+    last_flush: Instant,
+}
+
+impl<K: Clone + Eq + Hash, V, W: BatchWriter<K, V>> WriteCoalescer<K, V, W> {
+    /// Initializes a new write coalescer in front of the given storage, with the given flush interval and byte threshold.
+    pub fn new(writer: W, flush_interval: Duration, flush_bytes: usize) -> Self {
+        Self {
+            writer,
+            queue: Mutex::new(WriteQueue {
+                writes: Default::default(),
+                num_bytes: 0,
+                has_removal: false,
+                last_flush: Instant::now(),
+            }),
+            flush_lock: Default::default(),
+            flush_interval,
+            flush_bytes,
+        }
+    }
+
+    /// Returns the storage.
+    pub const fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the interval after which the queued writes are flushed.
+    pub const fn flush_interval(&self) -> Duration {
+        self.flush_interval
+    }
+
+    /// Returns the number of queued writes.
+    pub fn num_queued(&self) -> usize {
+        self.queue.lock().writes.len()
+    }
+
+    /// Queues the given writes, along with their sizes in bytes, and flushes them if a flush is due.
+    pub fn push(&self, writes: impl IntoIterator<Item = (K, Option<V>, usize)>) -> Result<()> {
+        match self.queue(writes) {
+            true => self.flush().map(|_| ()),
+            false => Ok(()),
+        }
+    }
+
+    /// Queues the given writes, along with their sizes in bytes, and returns `true` if a flush is due,
+    /// because a removal is queued, the flush interval elapsed, or the byte threshold is reached.
+    pub fn queue(&self, writes: impl IntoIterator<Item = (K, Option<V>, usize)>) -> bool {
+        let mut queue = self.queue.lock();
+        for (key, value, num_bytes) in writes {
+            queue.has_removal |= value.is_none();
+            queue.num_bytes += num_bytes;
+            if let Some((_, previous_bytes)) = queue.writes.insert(key, (value, num_bytes)) {
+                queue.num_bytes -= previous_bytes;
+            }
+        }
+        queue.has_removal || queue.num_bytes >= self.flush_bytes || queue.last_flush.elapsed() >= self.flush_interval
+    }
+
+    /// Writes the queued writes to storage as a single batch, and returns the number of written keys.
+    /// If the batch fails, the writes are queued again, unless they were superseded in the meantime.
+    pub fn flush(&self) -> Result<usize> {
+        // Acquire the flush lock, so that the batches are written in order.
+        let _flush_lock = self.flush_lock.lock();
+
+        // Take the queued writes.
+        let (batch, sizes): (Vec<_>, Vec<_>) = {
+            let mut queue = self.queue.lock();
+            queue.num_bytes = 0;
+            queue.has_removal = false;
+            queue.last_flush = Instant::now();
+            core::mem::take(&mut queue.writes).into_iter().map(|(key, (value, size))| ((key, value), size)).unzip()
+        };
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        // Write the batch, or queue the writes again if it fails.
+        match self.writer.write_batch(&batch) {
+            Ok(()) => Ok(batch.len()),
+            Err(error) => {
+                let mut queue = self.queue.lock();
+                for ((key, value), num_bytes) in batch.into_iter().zip(sizes) {
+                    if !queue.writes.contains_key(&key) {
+                        queue.has_removal |= value.is_none();
+                        queue.num_bytes += num_bytes;
+                        queue.writes.insert(key, (value, num_bytes));
+                    }
+                }
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<K, V, W> fmt::Debug for WriteCoalescer<K, V, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let queue = self.queue.lock();
+        f.debug_struct("WriteCoalescer")
+            .field("num_queued", &queue.writes.len())
+            .field("num_bytes", &queue.num_bytes)
+            .field("flush_interval", &self.flush_interval)
+            .field("flush_bytes", &self.flush_bytes)
+            .finish()
+    }
+}
+
+/// The write coalescer in front of the storage of the memory pool.
+pub(super) type MemoryPoolWriter<N> =
+    WriteCoalescer<<N as Network>::TransactionID, (Transaction<N>, i64), Arc<dyn MemoryPoolStorage<N>>>;
+
+impl<N: Network> MemoryPool<N> {
+    /// Persists the unconfirmed transactions to the given storage from then on. The writes are coalesced,
+    /// and flushed once the given interval has elapsed since the last flush, or the given number of bytes is queued.
+    ///
+    /// Note that the transactions accepted since the last flush are lost on a crash, unlike the flushed ones,
+    /// and that the transactions removed from the memory pool are flushed before the removal returns.
+    pub fn set_storage(&self, storage: Arc<dyn MemoryPoolStorage<N>>, flush_interval: Duration, flush_bytes: usize) {
+        *self.storage.write() = Some(Arc::new(WriteCoalescer::new(storage, flush_interval, flush_bytes)));
+    }
+
+    /// Returns the interval at which the queued writes must be flushed, if the memory pool is persisted.
+    pub fn flush_interval(&self) -> Option<Duration> {
+        self.storage.read().as_ref().map(|storage| storage.flush_interval())
+    }
+
+    /// Flushes the queued writes of the memory pool to storage, if it is persisted.
+    /// This is invoked at the flush interval, and during a graceful shutdown.
+    pub fn flush_storage(&self) -> Result<()> {
+        let storage = self.storage.read().clone();
+        match storage {
+            Some(storage) => storage.flush().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the persisted transactions and their arrival times, once the queued writes are flushed.
+    pub fn persisted_transactions(&self) -> Result<Vec<(Transaction<N>, i64)>> {
+        let storage = self.storage.read().clone();
+        match storage {
+            Some(storage) => {
+                storage.flush()?;
+                storage.writer().transactions()
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Adds the given persisted transaction to the memory pool with its original arrival time,
+    /// if it does not exist in, or conflict with, the memory pool, and fits in the byte budget.
+    pub(crate) fn restore_unconfirmed_transaction(
+        &self,
+        transaction: &Transaction<N>,
+        arrival_time: i64,
+    ) -> Result<()> {
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        if unconfirmed_transactions.contains_key(&transaction.id()) {
+            bail!("Transaction '{}' already exists in the memory pool", transaction.id())
+        }
+        if let Some(conflict) = Self::find_conflict(&unconfirmed_transactions, transaction) {
+            bail!("Transaction '{}' conflicts with transaction '{conflict}' in the memory pool", transaction.id())
+        }
+        Self::ensure_byte_budget(&unconfirmed_transactions, [transaction], &[], self.byte_budget())?;
+        unconfirmed_transactions.insert(transaction.id(), (transaction.clone(), arrival_time));
+        drop(unconfirmed_transactions);
+        self.notify_acceptances([transaction]);
+        Ok(())
+    }
+
+    /// Removes the given transactions from storage, if the memory pool is persisted.
+    pub(crate) fn remove_persisted_transactions(&self, transaction_ids: &[N::TransactionID]) -> Result<()> {
+        let storage = self.storage.read().clone();
+        match storage {
+            Some(storage) => storage.push(transaction_ids.iter().map(|transaction_id| (*transaction_id, None, 0))),
+            None => Ok(()),
+        }
+    }
+
+    /// Queues the given insertions and removals of the memory pool, and returns `true` if a flush is due.
+    /// This must be called while holding the write lock on the unconfirmed transactions,
+    /// so that the writes are queued in the order they are applied to the memory pool.
+    pub(super) fn queue_writes<'a>(
+        &self,
+        inserted: impl IntoIterator<Item = (&'a Transaction<N>, i64)>,
+        removed: impl IntoIterator<Item = N::TransactionID>,
+    ) -> bool {
+        let storage = self.storage.read();
+        let storage = match storage.as_ref() {
+            Some(storage) => storage,
+            None => return false,
+        };
+        let removals = removed.into_iter().map(|transaction_id| (transaction_id, None, 0));
+        let insertions = inserted.into_iter().map(|(transaction, arrival_time)| {
+            let num_bytes = transaction.to_bytes_le().map_or(0, |bytes| bytes.len());
+            (transaction.id(), Some((transaction.clone(), arrival_time)), num_bytes)
+        });
+        storage.queue(removals.chain(insertions))
+    }
+
+    /// Flushes the queued writes of the memory pool, if a flush is due. A failed flush is retried with the next one.
+    pub(super) fn flush_if_due(&self, is_due: bool) {
+        if is_due {
+            if let Err(error) = self.flush_storage() {
+                warn!("Failed to persist the memory pool - {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    /// A storage in memory, which counts the batches and fails them on demand.
+    #[derive(Default)]
+    struct MemoryStorage {
+        /// The persisted entries.
+        entries: Mutex<BTreeMap<u64, String>>,
+        /// The number of written batches.
+        num_batches: Mutex<usize>,
+        /// Whether the next batches fail.
+        fail: Mutex<bool>,
+    }
+
+    impl BatchWriter<u64, String> for MemoryStorage {
+        fn write_batch(&self, batch: &[(u64, Option<String>)]) -> Result<()> {
+            ensure!(!*self.fail.lock(), "The storage is unavailable");
+            let mut entries = self.entries.lock();
+            for (key, value) in batch {
+                match value {
+                    Some(value) => entries.insert(*key, value.clone()),
+                    None => entries.remove(key),
+                };
+            }
+            *self.num_batches.lock() += 1;
+            Ok(())
+        }
+    }
+
+    type Coalescer = WriteCoalescer<u64, String, Arc<MemoryStorage>>;
+
+    fn insert(key: u64) -> (u64, Option<String>, usize) {
+        (key, Some(format!("transaction {key}")), 100)
+    }
+
+    fn remove(key: u64) -> (u64, Option<String>, usize) {
+        (key, None, 0)
+    }
+
+    #[test]
+    fn test_coalesce_by_bytes() {
+        let storage = Arc::new(MemoryStorage::default());
+        let coalescer = Coalescer::new(storage.clone(), Duration::from_secs(3600), 1000);
+
+        // Ensure the insertions are queued until the byte threshold is reached.
+        for key in 0..9 {
+            coalescer.push([insert(key)]).unwrap();
+        }
+        assert_eq!(coalescer.num_queued(), 9);
+        assert_eq!(*storage.num_batches.lock(), 0);
+        // Ensure a rewrite of a queued key does not count twice towards the threshold.
+        coalescer.push([insert(0)]).unwrap();
+        assert_eq!(*storage.num_batches.lock(), 0);
+
+        // Ensure the insertions are flushed as a single batch once the threshold is reached.
+        coalescer.push([insert(9)]).unwrap();
+        assert_eq!(coalescer.num_queued(), 0);
+        assert_eq!(*storage.num_batches.lock(), 1);
+        assert_eq!(storage.entries.lock().len(), 10);
+    }
+
+    #[test]
+    fn test_coalesce_by_interval() {
+        let storage = Arc::new(MemoryStorage::default());
+        let coalescer = Coalescer::new(storage.clone(), Duration::from_millis(50), usize::MAX);
+
+        // Ensure the insertions are queued within the flush interval.
+        coalescer.push([insert(0), insert(1)]).unwrap();
+        assert_eq!(*storage.num_batches.lock(), 0);
+
+        // Ensure the insertions are flushed with the first write after the flush interval.
+        std::thread::sleep(Duration::from_millis(60));
+        coalescer.push([insert(2)]).unwrap();
+        assert_eq!(*storage.num_batches.lock(), 1);
+        assert_eq!(storage.entries.lock().keys().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_crash_semantics() {
+        let storage = Arc::new(MemoryStorage::default());
+        let coalescer = Coalescer::new(storage.clone(), Duration::from_secs(3600), usize::MAX);
+
+        // Flush the first insertions, and queue more.
+        coalescer.push([insert(0), insert(1), insert(2)]).unwrap();
+        coalescer.flush().unwrap();
+        coalescer.push([insert(3), insert(4)]).unwrap();
+
+        // Evict a flushed and a queued transaction, which flushes the queue before returning.
+        coalescer.push([remove(1), remove(3)]).unwrap();
+        assert_eq!(*storage.num_batches.lock(), 2);
+
+        // Accept more transactions, and crash before the next flush.
+        coalescer.push([insert(5), insert(6)]).unwrap();
+        drop(coalescer);
+
+        // Ensure the flushed transactions survive the crash, the evicted ones do not reappear,
+        // and only the transactions accepted since the last flush are lost.
+        assert_eq!(storage.entries.lock().keys().copied().collect::<Vec<_>>(), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_failed_flush_is_retried() {
+        let storage = Arc::new(MemoryStorage::default());
+        let coalescer = Coalescer::new(storage.clone(), Duration::from_secs(3600), usize::MAX);
+
+        // Fail the flush of a queued insertion and removal.
+        coalescer.push([insert(0), insert(1)]).unwrap();
+        coalescer.flush().unwrap();
+        *storage.fail.lock() = true;
+        coalescer.push([insert(2)]).unwrap();
+        assert!(coalescer.push([remove(0)]).is_err());
+        assert_eq!(coalescer.num_queued(), 2);
+
+        // Supersede one of the failed writes, and ensure the latest write of each key is flushed on the retry.
+        coalescer.push([insert(0)]).unwrap_err();
+        *storage.fail.lock() = false;
+        assert_eq!(coalescer.flush().unwrap(), 2);
+        assert_eq!(storage.entries.lock().keys().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+}
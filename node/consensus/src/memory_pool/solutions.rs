@@ -81,7 +81,7 @@ impl<N: Network> MemoryPool<N> {
                     }
                 }
             })
-            .sorted_by(|a, b| b.1.1.cmp(&a.1.1))
+            .sorted_by(|a, b| b.1 .1.cmp(&a.1 .1))
             .map(|(_, v)| v.0)
             .unique_by(|s| s.commitment())
             .take(256)
@@ -125,16 +125,23 @@ impl<N: Network> MemoryPool<N> {
 
     /// Clears the memory pool of unconfirmed transactions that are now invalid.
     pub fn clear_invalid_solutions<C: ConsensusStorage<N>>(&self, consensus: &Consensus<N, C>) {
-        self.unconfirmed_solutions.write().retain(|puzzle_commitment, _solution| {
-            // Ensure the prover solution is still valid.
-            match consensus.ledger.contains_puzzle_commitment(puzzle_commitment) {
-                Ok(true) | Err(_) => {
-                    trace!("Removed prover solution '{puzzle_commitment}' from the memory pool");
-                    false
-                }
-                Ok(false) => true,
+        // Retrieve the puzzle commitments, so that they are checked against the ledger outside the lock.
+        let puzzle_commitments = self.unconfirmed_solutions.read().keys().copied().collect::<Vec<_>>();
+        // Ensure the prover solutions are still valid.
+        let invalid = puzzle_commitments
+            .into_iter()
+            .filter(|puzzle_commitment| {
+                !matches!(consensus.ledger.contains_puzzle_commitment(puzzle_commitment), Ok(false))
+            })
+            .collect::<Vec<_>>();
+
+        // Remove the invalid prover solutions.
+        let mut unconfirmed_solutions = self.unconfirmed_solutions.write();
+        for puzzle_commitment in invalid {
+            if unconfirmed_solutions.remove(&puzzle_commitment).is_some() {
+                trace!("Removed prover solution '{puzzle_commitment}' from the memory pool");
             }
-        });
+        }
     }
 
     /// Clears all unconfirmed solutions from the memory pool.
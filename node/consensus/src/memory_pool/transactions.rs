@@ -22,6 +22,11 @@ impl<N: Network> MemoryPool<N> {
         self.unconfirmed_transactions.read().contains_key(&transaction_id)
     }
 
+    /// Returns the unconfirmed transaction with the given ID, if it exists in the memory pool.
+    pub fn unconfirmed_transaction(&self, transaction_id: &N::TransactionID) -> Option<Transaction<N>> {
+        self.unconfirmed_transactions.read().get(transaction_id).map(|(transaction, _)| transaction.clone())
+    }
+
     /// Returns the number of unconfirmed transactions in the memory pool.
     pub fn num_unconfirmed_transactions(&self) -> usize {
         self.unconfirmed_transactions.read().len()
@@ -29,9 +34,36 @@ impl<N: Network> MemoryPool<N> {
 
     /// Returns the unconfirmed transactions in the memory pool.
     pub fn unconfirmed_transactions(&self) -> Vec<Transaction<N>> {
+        self.unconfirmed_transactions.read().values().map(|(transaction, _)| transaction.clone()).collect::<Vec<_>>()
+    }
+
+    /// Returns the unconfirmed transactions in the memory pool, along with their arrival times
+    /// (as UNIX timestamps, in seconds), as a consistent view of the memory pool.
+    pub fn unconfirmed_transactions_with_arrival_times(&self) -> Vec<(Transaction<N>, i64)> {
         self.unconfirmed_transactions.read().values().cloned().collect::<Vec<_>>()
     }
 
+    /// Returns the unconfirmed transactions in the memory pool that the given transaction depends on,
+    /// in order of arrival.
+    pub fn unconfirmed_parents(&self, transaction: &Transaction<N>) -> Vec<Transaction<N>> {
+        let mut parents = self
+            .unconfirmed_transactions_with_arrival_times()
+            .into_iter()
+            .filter(|(parent, _)| depends_on(transaction, parent))
+            .collect::<Vec<_>>();
+        parents.sort_by_key(|(_, arrival_time)| *arrival_time);
+        parents.into_iter().map(|(parent, _)| parent).collect()
+    }
+
+    /// Returns the total fees of the unconfirmed transactions in the memory pool (in microcredits).
+    pub fn unconfirmed_fees(&self) -> u64 {
+        self.unconfirmed_transactions
+            .read()
+            .values()
+            .filter_map(|(transaction, _)| transaction.fee().ok())
+            .fold(0u64, |fees, fee| fees.saturating_add(*fee))
+    }
+
     /// Returns a candidate set of unconfirmed transactions for inclusion in a block.
     pub fn candidate_transactions<C: ConsensusStorage<N>>(&self, consensus: &Consensus<N, C>) -> Vec<Transaction<N>> {
         // TODO (raychu86): Add more sophisticated logic for transaction selection.
@@ -40,70 +72,582 @@ impl<N: Network> MemoryPool<N> {
         let mut transactions = Vec::new();
         let mut input_ids = Vec::new();
         let mut output_ids = Vec::new();
+        let mut program_ids = Vec::new();
 
-        'outer: for transaction in self.unconfirmed_transactions.read().values() {
+        // Retrieve the unconfirmed transactions, so that they are verified outside the lock,
+        // and order them by decreasing fee rate, then by arrival.
+        let mut unconfirmed_transactions = self.unconfirmed_transactions_with_arrival_times();
+        sort_by_fee_rate(&mut unconfirmed_transactions);
+        let unconfirmed_transactions = unconfirmed_transactions.into_iter().map(|(transaction, _)| transaction);
+
+        'outer: for transaction in unconfirmed_transactions {
             // Ensure the transaction is well-formed.
-            if consensus.check_transaction_basic(transaction).is_err() {
+            if consensus.check_transaction_basic(&transaction).is_err() {
                 continue;
             }
 
             // Ensure the input IDs are unique.
             for input_id in transaction.input_ids() {
-                if input_ids.contains(&input_id) {
+                if input_ids.contains(input_id) {
                     continue 'outer;
                 }
             }
             // Ensure the output IDs are unique.
             for output_id in transaction.output_ids() {
-                if output_ids.contains(&output_id) {
+                if output_ids.contains(output_id) {
+                    continue 'outer;
+                }
+            }
+            // Ensure the deployed program IDs are unique.
+            if let Transaction::Deploy(_, _, deployment, _) = &transaction {
+                if program_ids.contains(deployment.program_id()) {
                     continue 'outer;
                 }
             }
+            // Ensure the transaction is accepted by the policy hooks of the node, alone and along with the selected
+            // transactions. Note that the policy hooks are never invoked when a block is validated.
+            if consensus.check_transaction_policy(&transaction, PolicyStage::Template).is_err() {
+                continue 'outer;
+            }
+            transactions.push(transaction);
+            if consensus.check_block_template_policy(&transactions).is_err() {
+                transactions.pop();
+                continue 'outer;
+            }
 
-            transactions.push(transaction.clone());
-            input_ids.extend(transaction.input_ids());
-            output_ids.extend(transaction.output_ids());
+            // Record the deployed program, inputs, and outputs of the selected transaction.
+            let transaction = &transactions[transactions.len() - 1];
+            if let Transaction::Deploy(_, _, deployment, _) = transaction {
+                program_ids.push(*deployment.program_id());
+            }
+            input_ids.extend(transaction.input_ids().copied());
+            output_ids.extend(transaction.output_ids().copied());
         }
 
         transactions
     }
 
-    /// Adds the given unconfirmed transaction to the memory pool.
-    pub fn add_unconfirmed_transaction(&self, transaction: &Transaction<N>) -> bool {
+    /// Adds the given unconfirmed transaction to the memory pool, and returns `true` if it was added.
+    ///
+    /// If the transaction spends a serial number that is already spent by a transaction in the memory pool,
+    /// it is rejected, unless replacements are enabled and it satisfies the replacement policy.
+    pub fn add_unconfirmed_transaction(&self, transaction: &Transaction<N>) -> Result<bool> {
         // Acquire the write lock on the unconfirmed transactions.
         let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
 
         // Ensure the transaction does not already exist in the memory pool.
-        match !unconfirmed_transactions.contains_key(&transaction.id()) {
-            true => {
-                // Add the transaction to the memory pool.
-                unconfirmed_transactions.insert(transaction.id(), transaction.clone());
-                debug!("✉️  Added transaction '{}' to the memory pool", transaction.id());
-                true
-            }
-            false => {
-                trace!("Transaction '{}' already exists in memory pool", transaction.id());
-                false
+        if unconfirmed_transactions.contains_key(&transaction.id()) {
+            trace!("Transaction '{}' already exists in memory pool", transaction.id());
+            return Ok(false);
+        }
+
+        // Find the transactions in the memory pool that spend the same serial numbers.
+        let serial_numbers = transaction.serial_numbers().collect::<HashSet<_>>();
+        let conflicts = unconfirmed_transactions
+            .values()
+            .filter(|(pooled, _)| pooled.serial_numbers().any(|serial_number| serial_numbers.contains(serial_number)))
+            .map(|(pooled, _)| pooled.id())
+            .collect::<Vec<_>>();
+
+        // If the transaction conflicts with the memory pool, determine if it can replace the conflicts.
+        let evicted = match (conflicts.first(), self.replacement_policy()) {
+            (None, _) => Vec::new(),
+            (Some(conflict), None) => {
+                bail!("Transaction '{}' conflicts with transaction '{conflict}' in the memory pool", transaction.id())
+            }
+            (Some(_), Some(policy)) => {
+                Self::replacement_evictions(&unconfirmed_transactions, transaction, conflicts, policy)?
+            }
+        };
+
+        // Ensure the memory pool remains within its byte budget.
+        Self::ensure_byte_budget(&unconfirmed_transactions, [transaction], &evicted, self.byte_budget())?;
+
+        // Evict the replaced transactions, and add the transaction to the memory pool.
+        let mut rejected = Vec::with_capacity(evicted.len());
+        for transaction_id in evicted {
+            unconfirmed_transactions.remove(&transaction_id);
+            debug!("Replaced transaction '{transaction_id}' with '{}' in the memory pool", transaction.id());
+            rejected.push(EvictedTransaction {
+                transaction_id,
+                reason: format!("replaced by transaction '{}'", transaction.id()),
+                replaced_by: Some(transaction.id()),
+                expired: false,
+            });
+        }
+        let arrival_time = arrival_time();
+        unconfirmed_transactions.insert(transaction.id(), (transaction.clone(), arrival_time));
+        debug!("✉️  Added transaction '{}' to the memory pool", transaction.id());
+        // Queue the writes to storage.
+        let is_flush_due =
+            self.queue_writes([(transaction, arrival_time)], rejected.iter().map(|rejected| rejected.transaction_id));
+
+        // Release the write lock, flush the writes if due, and publish the replaced and added transactions.
+        drop(unconfirmed_transactions);
+        self.flush_if_due(is_flush_due);
+        self.notify_evictions(rejected);
+        self.notify_acceptances([transaction]);
+        Ok(true)
+    }
+
+    /// Checks the given transaction would be added to the memory pool by `add_unconfirmed_transaction`, without
+    /// adding it, evicting the transactions it would replace, or publishing any event.
+    pub fn check_admission(&self, transaction: &Transaction<N>) -> Result<(), TransactionRejection<N>> {
+        let unconfirmed_transactions = self.unconfirmed_transactions.read();
+        let invalid = |error: anyhow::Error| TransactionRejection::Invalid { reason: error.to_string() };
+
+        // Ensure the transaction does not already exist in the memory pool.
+        if unconfirmed_transactions.contains_key(&transaction.id()) {
+            return Err(TransactionRejection::Duplicate);
+        }
+
+        // Ensure the transaction does not conflict with the memory pool, or satisfies the replacement policy.
+        let serial_numbers = transaction.serial_numbers().collect::<HashSet<_>>();
+        let conflicts = unconfirmed_transactions
+            .values()
+            .filter(|(pooled, _)| pooled.serial_numbers().any(|serial_number| serial_numbers.contains(serial_number)))
+            .map(|(pooled, _)| pooled.id())
+            .collect::<Vec<_>>();
+        let evicted = match (conflicts.first(), self.replacement_policy()) {
+            (None, _) => Vec::new(),
+            (Some(transaction_id), None) => {
+                return Err(TransactionRejection::Conflict { transaction_id: *transaction_id });
+            }
+            (Some(_), Some(policy)) => {
+                Self::replacement_evictions(&unconfirmed_transactions, transaction, conflicts, policy).map_err(invalid)?
+            }
+        };
+
+        // Ensure the memory pool remains within its byte budget.
+        Self::ensure_byte_budget(&unconfirmed_transactions, [transaction], &evicted, self.byte_budget())
+            .map_err(invalid)
+    }
+
+    /// Adds the given unconfirmed transactions to the memory pool, if none of them already exists in the memory pool,
+    /// or conflicts with it. Conflicting transactions are never replaced by a batch.
+    pub fn add_unconfirmed_transactions(&self, transactions: &[Transaction<N>]) -> Result<()> {
+        // Acquire the write lock on the unconfirmed transactions.
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+
+        // Ensure none of the transactions exists in, or conflicts with, the memory pool.
+        for transaction in transactions {
+            if unconfirmed_transactions.contains_key(&transaction.id()) {
+                bail!("Transaction '{}' already exists in the memory pool", transaction.id())
+            }
+            if let Some(conflict) = Self::find_conflict(&unconfirmed_transactions, transaction) {
+                bail!("Transaction '{}' conflicts with transaction '{conflict}' in the memory pool", transaction.id())
+            }
+        }
+
+        // Ensure the memory pool remains within its byte budget.
+        Self::ensure_byte_budget(&unconfirmed_transactions, transactions, &[], self.byte_budget())?;
+
+        // Add the transactions to the memory pool.
+        let arrival_time = arrival_time();
+        for transaction in transactions {
+            unconfirmed_transactions.insert(transaction.id(), (transaction.clone(), arrival_time));
+            debug!("✉️  Added transaction '{}' to the memory pool", transaction.id());
+        }
+        // Queue the writes to storage.
+        let is_flush_due =
+            self.queue_writes(transactions.iter().map(|transaction| (transaction, arrival_time)), core::iter::empty());
+
+        // Release the write lock, flush the writes if due, and publish the added transactions.
+        drop(unconfirmed_transactions);
+        self.flush_if_due(is_flush_due);
+        self.notify_acceptances(transactions);
+        Ok(())
+    }
+
+    /// Returns the number of bytes of the unconfirmed transactions in the memory pool.
+    pub fn unconfirmed_bytes(&self) -> Result<usize> {
+        Self::num_bytes(self.unconfirmed_transactions.read().values().map(|(transaction, _)| transaction))
+    }
+
+    /// Returns the number of bytes of the given transactions.
+    fn num_bytes<'a>(transactions: impl IntoIterator<Item = &'a Transaction<N>>) -> Result<usize> {
+        transactions
+            .into_iter()
+            .try_fold(0usize, |num_bytes, transaction| Ok(num_bytes.saturating_add(transaction.to_bytes_le()?.len())))
+    }
+
+    /// Ensures the given pool, with the given transactions added and the given transactions evicted,
+    /// does not exceed the given byte budget, if the memory pool is bounded.
+    pub(super) fn ensure_byte_budget<'a>(
+        unconfirmed_transactions: &'a HashMap<N::TransactionID, (Transaction<N>, i64)>,
+        added: impl IntoIterator<Item = &'a Transaction<N>>,
+        evicted: &[N::TransactionID],
+        byte_budget: Option<usize>,
+    ) -> Result<()> {
+        // Return early if the memory pool is unbounded.
+        let byte_budget = match byte_budget {
+            Some(byte_budget) => byte_budget,
+            None => return Ok(()),
+        };
+        // Compute the number of bytes of the memory pool, after the evictions and additions.
+        let remaining = unconfirmed_transactions
+            .iter()
+            .filter(|(transaction_id, _)| !evicted.contains(transaction_id))
+            .map(|(_, (transaction, _))| transaction);
+        let num_bytes = Self::num_bytes(remaining.chain(added))?;
+        ensure!(
+            num_bytes <= byte_budget,
+            "The memory pool is full - adding the transaction(s) requires {num_bytes} bytes (byte budget is {byte_budget})"
+        );
+        Ok(())
+    }
+
+    /// Returns the ID of a transaction in the memory pool that spends a serial number of the given transaction, if any.
+    pub fn find_conflicting_transaction(&self, transaction: &Transaction<N>) -> Option<N::TransactionID> {
+        Self::find_conflict(&self.unconfirmed_transactions.read(), transaction)
+    }
+
+    /// Returns the serial numbers spent by the unconfirmed transactions in the memory pool.
+    pub fn unconfirmed_serial_numbers(&self) -> HashSet<Field<N>> {
+        self.unconfirmed_transactions
+            .read()
+            .values()
+            .flat_map(|(transaction, _)| transaction.serial_numbers().copied().collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Returns the ID of a transaction in the given pool that spends a serial number of the given transaction, if any.
+    pub(super) fn find_conflict(
+        unconfirmed_transactions: &HashMap<N::TransactionID, (Transaction<N>, i64)>,
+        transaction: &Transaction<N>,
+    ) -> Option<N::TransactionID> {
+        let serial_numbers = transaction.serial_numbers().collect::<HashSet<_>>();
+        unconfirmed_transactions
+            .values()
+            .find(|(pooled, _)| pooled.serial_numbers().any(|serial_number| serial_numbers.contains(serial_number)))
+            .map(|(pooled, _)| pooled.id())
+    }
+
+    /// Returns the IDs of the transactions that the given transaction evicts from the memory pool,
+    /// which are the given conflicts and the transactions that depend on them, if the replacement
+    /// satisfies the given policy.
+    fn replacement_evictions(
+        unconfirmed_transactions: &HashMap<N::TransactionID, (Transaction<N>, i64)>,
+        transaction: &Transaction<N>,
+        conflicts: Vec<N::TransactionID>,
+        policy: ReplacementPolicy,
+    ) -> Result<Vec<N::TransactionID>> {
+        // Collect the conflicts and their dependents, bounding the size of the cascade.
+        let evicted = match Self::with_dependents(unconfirmed_transactions, conflicts, policy.max_evictions) {
+            Some(evicted) => evicted,
+            None => bail!(
+                "Transaction '{}' would evict more than {} transactions from the memory pool",
+                transaction.id(),
+                policy.max_evictions
+            ),
+        };
+
+        // Ensure the fee exceeds the combined fees of the evicted transactions by the increment.
+        let mut evicted_fees = 0u64;
+        for transaction_id in &evicted {
+            evicted_fees = evicted_fees.saturating_add(*unconfirmed_transactions[transaction_id].0.fee()?);
+        }
+        let fee = *transaction.fee()?;
+        if fee < evicted_fees.saturating_add(policy.fee_increment) {
+            bail!(
+                "Transaction '{}' has an insufficient fee to replace {} transaction(s) - found {fee}, expected at least {}",
+                transaction.id(),
+                evicted.len(),
+                evicted_fees.saturating_add(policy.fee_increment)
+            )
+        }
+        Ok(evicted)
+    }
+
+    /// Returns the given transactions in the memory pool, followed by the transactions that depend on them,
+    /// or `None` if there are more than `max_transactions` of them.
+    pub(super) fn with_dependents(
+        unconfirmed_transactions: &HashMap<N::TransactionID, (Transaction<N>, i64)>,
+        transaction_ids: Vec<N::TransactionID>,
+        max_transactions: usize,
+    ) -> Option<Vec<N::TransactionID>> {
+        let mut transaction_ids = transaction_ids;
+        let mut index = 0;
+        while index < transaction_ids.len() {
+            let (parent, _) = &unconfirmed_transactions[&transaction_ids[index]];
+            for (transaction_id, (pooled, _)) in unconfirmed_transactions.iter() {
+                if !transaction_ids.contains(transaction_id) && depends_on(pooled, parent) {
+                    transaction_ids.push(*transaction_id);
+                }
+            }
+            if transaction_ids.len() > max_transactions {
+                return None;
             }
+            index += 1;
         }
+        Some(transaction_ids)
     }
 
     /// Clears the memory pool of unconfirmed transactions that are now invalid.
+    ///
+    /// The transactions are verified outside the lock, so that the memory pool remains available meanwhile.
     pub fn clear_invalid_transactions<C: ConsensusStorage<N>>(&self, consensus: &Consensus<N, C>) {
-        self.unconfirmed_transactions.write().retain(|transaction_id, transaction| {
-            // Ensure the transaction is valid.
-            match consensus.check_transaction_basic(transaction) {
-                Ok(_) => true,
-                Err(_) => {
-                    trace!("Removed transaction '{transaction_id}' from the memory pool");
-                    false
+        // Verify the unconfirmed transactions, outside the lock.
+        let invalid = self
+            .unconfirmed_transactions()
+            .into_iter()
+            .filter_map(|transaction| {
+                consensus.check_transaction_basic(&transaction).err().map(|error| (transaction.id(), error))
+            })
+            .collect::<Vec<_>>();
+
+        // Remove the invalid transactions that are still in the memory pool.
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        let rejected = invalid
+            .into_iter()
+            .filter(|(transaction_id, _)| unconfirmed_transactions.remove(transaction_id).is_some())
+            .map(|(transaction_id, error)| {
+                trace!("Removed transaction '{transaction_id}' from the memory pool");
+                EvictedTransaction { transaction_id, reason: error.to_string(), replaced_by: None, expired: false }
+            })
+            .collect::<Vec<_>>();
+        let is_flush_due = self.queue_writes([], rejected.iter().map(|rejected| rejected.transaction_id));
+        drop(unconfirmed_transactions);
+
+        self.flush_if_due(is_flush_due);
+        self.notify_evictions(rejected);
+    }
+
+    /// Clears the memory pool of unconfirmed transactions that conflict with the given (committed) block,
+    /// and returns the IDs of the removed transactions.
+    ///
+    /// Rather than re-verifying every unconfirmed transaction, this removes the transactions that
+    /// share a transaction ID, transition ID, input ID (including serial numbers), tag, output ID
+    /// (including commitments), or deployed program ID with the block, along with the transactions
+    /// that depend on them. Only the remaining transactions whose validity may have changed with the
+    /// block are re-verified: those that depend on a transaction of the block, as they were admitted
+    /// against the state of their parents, and those whose global state roots aged out of the admission
+    /// window, if an expiry policy is set.
+    pub fn clear_conflicting_transactions<C: ConsensusStorage<N>>(
+        &self,
+        consensus: &Consensus<N, C>,
+        block: &Block<N>,
+    ) -> Vec<N::TransactionID> {
+        // Collect the identifiers that are now in the ledger.
+        let mut transaction_ids = HashSet::new();
+        let mut transition_ids = HashSet::new();
+        let mut fields = HashSet::new();
+        let mut program_ids = HashSet::new();
+        for transaction in block.transactions().iter() {
+            transaction_ids.insert(transaction.id());
+            transition_ids.extend(transaction.transition_ids().copied());
+            fields.extend(transaction.input_ids().chain(transaction.tags()).chain(transaction.output_ids()).copied());
+            if let Transaction::Deploy(_, _, deployment, _) = transaction {
+                program_ids.insert(*deployment.program_id());
+            }
+        }
+
+        // Determine if the given unconfirmed transaction conflicts with the block.
+        let conflicts = |transaction: &Transaction<N>| -> Option<&'static str> {
+            if transaction_ids.contains(&transaction.id()) {
+                Some("already exists in the ledger")
+            } else if transaction.is_coinbase() {
+                Some("contains an illegal function call")
+            } else if transaction.transition_ids().any(|id| transition_ids.contains(id)) {
+                Some("contains a transition that already exists in the ledger")
+            } else if transaction.serial_numbers().any(|serial_number| fields.contains(serial_number)) {
+                Some("spends a record that was spent in the ledger")
+            } else if transaction.input_ids().chain(transaction.tags()).any(|id| fields.contains(id)) {
+                Some("contains an input that already exists in the ledger")
+            } else if transaction.output_ids().any(|id| fields.contains(id)) {
+                Some("contains an output that already exists in the ledger")
+            } else {
+                match transaction {
+                    Transaction::Deploy(_, _, deployment, _) if program_ids.contains(deployment.program_id()) => {
+                        Some("deploys a program that already exists in the ledger")
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        let mut rejected = Vec::new();
+        let mut evicted = Vec::new();
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        unconfirmed_transactions.retain(|transaction_id, (transaction, _)| match conflicts(transaction) {
+            None => true,
+            Some(reason) => {
+                trace!("Removed transaction '{transaction_id}' from the memory pool ({reason})");
+                rejected.push(EvictedTransaction {
+                    transaction_id: *transaction_id,
+                    reason: reason.to_string(),
+                    replaced_by: None,
+                    expired: false,
+                });
+                // The dependents of a transaction that is in the block remain valid.
+                if !transaction_ids.contains(transaction_id) {
+                    evicted.push(transaction.clone());
                 }
+                false
             }
         });
+
+        // Remove the transactions that depend on an evicted transaction, as they can no longer be confirmed.
+        let mut index = 0;
+        while index < evicted.len() {
+            let parent_id = evicted[index].id();
+            let dependents = unconfirmed_transactions
+                .iter()
+                .filter(|(_, (transaction, _))| depends_on(transaction, &evicted[index]))
+                .map(|(transaction_id, _)| *transaction_id)
+                .collect::<Vec<_>>();
+            for transaction_id in dependents {
+                if let Some((transaction, _)) = unconfirmed_transactions.remove(&transaction_id) {
+                    trace!("Removed transaction '{transaction_id}' from the memory pool (depends on '{parent_id}')");
+                    rejected.push(EvictedTransaction {
+                        transaction_id,
+                        reason: format!("depends on transaction '{parent_id}', which was evicted"),
+                        replaced_by: None,
+                        expired: false,
+                    });
+                    evicted.push(transaction);
+                }
+            }
+            index += 1;
+        }
+
+        // Collect the remaining transactions that depend on the block, as they must now validate against the ledger.
+        let mut recheck = unconfirmed_transactions
+            .values()
+            .filter(|(transaction, _)| block.transactions().iter().any(|confirmed| depends_on(transaction, confirmed)))
+            .map(|(transaction, _)| transaction.clone())
+            .collect::<Vec<_>>();
+        let mut removed = rejected.iter().map(|rejected| rejected.transaction_id).collect::<Vec<_>>();
+        let is_flush_due = self.queue_writes([], removed.iter().copied());
+        drop(unconfirmed_transactions);
+
+        self.flush_if_due(is_flush_due);
+        self.notify_evictions(rejected);
+
+        // Collect the remaining transactions whose global state roots aged out of the admission window.
+        if let Some(policy) = self.expiry_policy() {
+            let latest_height = consensus.ledger.latest_height();
+            recheck.extend(self.unconfirmed_transactions().into_iter().filter(|transaction| {
+                !recheck.iter().any(|other| other.id() == transaction.id())
+                    && matches!(
+                        consensus.state_root_age(transaction, latest_height),
+                        Ok(Some(state_root_age)) if !policy.admits(state_root_age)
+                    )
+            }));
+        }
+
+        // Re-verify the collected transactions, outside the lock.
+        let invalid = recheck
+            .into_iter()
+            .filter_map(|transaction| {
+                consensus.check_transaction_basic(&transaction).err().map(|error| (transaction.id(), error))
+            })
+            .collect::<Vec<_>>();
+
+        // Remove the invalid transactions that are still in the memory pool.
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        let rejected = invalid
+            .into_iter()
+            .filter(|(transaction_id, _)| unconfirmed_transactions.remove(transaction_id).is_some())
+            .map(|(transaction_id, error)| {
+                trace!("Removed transaction '{transaction_id}' from the memory pool ({error})");
+                EvictedTransaction { transaction_id, reason: error.to_string(), replaced_by: None, expired: false }
+            })
+            .collect::<Vec<_>>();
+        let revalidated = rejected.iter().map(|rejected| rejected.transaction_id).collect::<Vec<_>>();
+        let is_flush_due = self.queue_writes([], revalidated.iter().copied());
+        drop(unconfirmed_transactions);
+
+        self.flush_if_due(is_flush_due);
+        self.notify_evictions(rejected);
+        removed.extend(revalidated);
+        removed
+    }
+
+    /// Returns the event bus, on which the accepted and evicted transactions are published.
+    pub const fn event_bus(&self) -> &EventBus<N> {
+        &self.events
+    }
+
+    /// Publishes the given evicted transactions on the event bus.
+    /// The evicted transactions are no longer tracked as local transactions.
+    pub(super) fn notify_evictions(&self, evicted: Vec<EvictedTransaction<N>>) {
+        if evicted.is_empty() {
+            return;
+        }
+        let mut local_transactions = self.local_transactions.write();
+        evicted.iter().for_each(|evicted| {
+            local_transactions.remove(&evicted.transaction_id);
+        });
+        drop(local_transactions);
+        evicted.into_iter().for_each(|evicted| self.events.publish(Event::TransactionEvicted(evicted)));
+    }
+
+    /// Publishes the given accepted transactions on the event bus.
+    pub(super) fn notify_acceptances<'a>(&self, transactions: impl IntoIterator<Item = &'a Transaction<N>>) {
+        transactions.into_iter().for_each(|transaction| {
+            self.events.publish(Event::TransactionAccepted { transaction: Arc::new(transaction.clone()) })
+        });
     }
 
     /// Clears the memory pool of all unconfirmed transactions.
     pub fn clear_unconfirmed_transactions(&self) {
-        self.unconfirmed_transactions.write().clear();
+        let mut unconfirmed_transactions = self.unconfirmed_transactions.write();
+        let is_flush_due =
+            self.queue_writes([], unconfirmed_transactions.drain().map(|(transaction_id, _)| transaction_id));
+        self.local_transactions.write().clear();
+        drop(unconfirmed_transactions);
+
+        self.flush_if_due(is_flush_due);
+    }
+}
+
+/// Returns `true` if the given child transaction depends on the given parent transaction, because it
+/// consumes one of its outputs, or executes a program that it deploys.
+pub(crate) fn depends_on<N: Network>(child: &Transaction<N>, parent: &Transaction<N>) -> bool {
+    let output_ids = parent.output_ids().collect::<HashSet<_>>();
+    if child.input_ids().any(|input_id| output_ids.contains(input_id)) {
+        return true;
+    }
+    match parent {
+        Transaction::Deploy(_, _, deployment, _) => {
+            child.transitions().any(|transition| transition.program_id() == deployment.program_id())
+        }
+        _ => false,
     }
 }
+
+/// Returns `true` if the given transactions cannot both be added to the ledger, because they spend
+/// the same record, or deploy the same program.
+pub(crate) fn conflicts_with<N: Network>(transaction: &Transaction<N>, other: &Transaction<N>) -> bool {
+    let serial_numbers = other.serial_numbers().collect::<HashSet<_>>();
+    if transaction.serial_numbers().any(|serial_number| serial_numbers.contains(serial_number)) {
+        return true;
+    }
+    match (transaction, other) {
+        (Transaction::Deploy(_, _, deployment, _), Transaction::Deploy(_, _, other_deployment, _)) => {
+            deployment.program_id() == other_deployment.program_id()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the fee rate of the given transaction (in microcredits per byte).
+pub fn fee_rate<N: Network>(transaction: &Transaction<N>) -> Result<f64> {
+    Ok(*transaction.fee()? as f64 / transaction.to_bytes_le()?.len() as f64)
+}
+
+/// Orders the given transactions and their arrival times by decreasing fee rate, then by arrival.
+/// Note that a transaction whose fee rate cannot be computed is ordered last.
+pub(crate) fn sort_by_fee_rate<N: Network>(transactions: &mut [(Transaction<N>, i64)]) {
+    transactions.sort_by_cached_key(|(transaction, arrival_time)| {
+        // The fee rates are non-negative, so their bits are ordered as the fee rates.
+        let fee_rate = fee_rate(transaction).unwrap_or(0.0);
+        (core::cmp::Reverse(fee_rate.to_bits()), *arrival_time, transaction.id().to_string())
+    });
+}
+
+/// Returns the arrival time of a transaction added to the memory pool now, as a UNIX timestamp (in seconds).
+fn arrival_time() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
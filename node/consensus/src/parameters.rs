@@ -0,0 +1,242 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::{
+    parameters::{errors::ParameterError, testnet3},
+    prelude::{FromBytes, Network},
+    synthesizer::VerifyingKey,
+};
+
+use anyhow::{anyhow, bail, Result};
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// A parameter file of a verifying key, with its checksum.
+#[derive(Copy, Clone, Debug)]
+pub struct ParameterFile {
+    /// The circuit of the verifying key, as `program ID/function name`, or `inclusion`.
+    pub circuit: &'static str,
+    /// The name of the parameter file, without its checksum suffix.
+    pub file_name: &'static str,
+    /// The SHA-256 checksum of the parameter file, as a hex string.
+    pub checksum: &'static str,
+    /// The function that downloads the parameter file, and stores it in the resources directory.
+    pub fetch: fn() -> Result<Vec<u8>, ParameterError>,
+}
+
+impl ParameterFile {
+    /// Returns the path to the parameter file in the given directory, which is suffixed with the start of its checksum.
+    pub fn path(&self, directory: &Path) -> PathBuf {
+        directory.join(format!("{}.{}", self.file_name, &self.checksum[..7]))
+    }
+}
+
+/// The parameter files of the verifying keys that are required to verify transactions,
+/// with the checksums of the parameters of the network.
+pub const VERIFYING_KEY_FILES: &[ParameterFile] = &[
+    ParameterFile {
+        circuit: "credits.aleo/mint",
+        file_name: "mint.verifier",
+        checksum: "097e25fcdd47ed7cd9ce51567c9d71e9ae3483bcd247e9c4363e0cce5545ad4d",
+        fetch: testnet3::MintVerifier::load_bytes,
+    },
+    ParameterFile {
+        circuit: "credits.aleo/transfer",
+        file_name: "transfer.verifier",
+        checksum: "78aa4e53cb4c51381856f7f3593d97c6b5cd8c5a53beca5fc1942d7a715cbed5",
+        fetch: testnet3::TransferVerifier::load_bytes,
+    },
+    ParameterFile {
+        circuit: "credits.aleo/join",
+        file_name: "join.verifier",
+        checksum: "f9839073171e2bc9747da5a78eecf252d0fd209a7d20079ff1f36a5b23643501",
+        fetch: testnet3::JoinVerifier::load_bytes,
+    },
+    ParameterFile {
+        circuit: "credits.aleo/split",
+        file_name: "split.verifier",
+        checksum: "cb92a526497dca307e3683373cf3c4b152b0ba1ab14506cd18b3c38798c1eb36",
+        fetch: testnet3::SplitVerifier::load_bytes,
+    },
+    ParameterFile {
+        circuit: "credits.aleo/fee",
+        file_name: "fee.verifier",
+        checksum: "ca1ef410d4a34bc76f7ab87960a375270603dfebd379b0caa2783c47f6ef4474",
+        fetch: testnet3::FeeVerifier::load_bytes,
+    },
+    ParameterFile {
+        circuit: "inclusion",
+        file_name: "inclusion.verifier",
+        checksum: "7ca796c68badd6a225a159396b943ff416abd17b79671702b20e2e009b1e5970",
+        fetch: testnet3::InclusionVerifier::load_bytes,
+    },
+];
+
+/// The verifying keys required to verify transactions, which are loaded and checked at startup,
+/// so that a missing or corrupt parameter file fails the startup instead of the first verification.
+#[derive(Clone, Debug)]
+pub struct ParameterRegistry<N: Network> {
+    /// The mapping of `circuit` to its verifying key.
+    verifying_keys: IndexMap<String, VerifyingKey<N>>,
+}
+
+impl<N: Network> Default for ParameterRegistry<N> {
+    fn default() -> Self {
+        Self { verifying_keys: Default::default() }
+    }
+}
+
+impl<N: Network> ParameterRegistry<N> {
+    /// Loads the verifying keys from the resources directory, downloading the missing parameter files,
+    /// and initializes the keys that the network loads on first use.
+    pub fn load() -> Result<Self> {
+        let directory = aleo_std::aleo_dir().join("resources");
+
+        // Download the missing parameter files, which are stored in the resources directory.
+        for parameter in VERIFYING_KEY_FILES {
+            if !parameter.path(&directory).exists() {
+                info!("Downloading the verifying key for '{}'...", parameter.circuit);
+                if let Err(error) = (parameter.fetch)() {
+                    bail!("The verifying key for '{}' is missing, and failed to download - {error}", parameter.circuit)
+                }
+            }
+        }
+
+        // Load and check the parameter files.
+        let registry = Self::load_from(&directory, VERIFYING_KEY_FILES)?;
+
+        // Initialize the keys that the network loads on first use, as their parameter files are now checked.
+        let _ = N::marlin_fs_parameters();
+        for parameter in VERIFYING_KEY_FILES {
+            if let Some(function_name) = parameter.circuit.strip_prefix("credits.aleo/") {
+                N::get_credits_verifying_key(function_name.to_string())?;
+            }
+        }
+        let _ = N::inclusion_verifying_key();
+
+        Ok(registry)
+    }
+
+    /// Loads the verifying keys of the given parameter files from the given directory,
+    /// ensuring each file exists, matches its checksum, and decodes to a verifying key.
+    pub fn load_from(directory: &Path, parameters: &[ParameterFile]) -> Result<Self> {
+        let mut verifying_keys = IndexMap::with_capacity(parameters.len());
+        for (index, parameter) in parameters.iter().enumerate() {
+            info!("Loading the verifying key for '{}' ({}/{})", parameter.circuit, index + 1, parameters.len());
+            let path = parameter.path(directory);
+
+            // Read the parameter file.
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    bail!("The verifying key for '{}' is missing at '{}' - {error}", parameter.circuit, path.display())
+                }
+            };
+            // Ensure the parameter file matches its checksum.
+            let checksum = format!("{:x}", Sha256::digest(&bytes));
+            if checksum != parameter.checksum {
+                bail!(
+                    "The verifying key for '{}' at '{}' is corrupt (expected checksum {}, found {checksum}) - remove the file to download it again",
+                    parameter.circuit,
+                    path.display(),
+                    parameter.checksum
+                )
+            }
+            // Decode the verifying key.
+            let verifying_key = VerifyingKey::<N>::from_bytes_le(&bytes).map_err(|error| {
+                anyhow!(
+                    "The verifying key for '{}' at '{}' failed to decode - {error}",
+                    parameter.circuit,
+                    path.display()
+                )
+            })?;
+            verifying_keys.insert(parameter.circuit.to_string(), verifying_key);
+        }
+        Ok(Self { verifying_keys })
+    }
+
+    /// Returns the verifying key of the given circuit, if it is in the registry.
+    pub fn verifying_key(&self, circuit: &str) -> Option<&VerifyingKey<N>> {
+        self.verifying_keys.get(circuit)
+    }
+
+    /// Returns the circuits in the registry.
+    pub fn circuits(&self) -> impl '_ + Iterator<Item = &str> {
+        self.verifying_keys.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::console::network::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a new directory with a parameter file of the given contents, and the parameter file with the given checksum.
+    fn sample_parameter(name: &str, contents: &[u8], checksum: &'static str) -> (PathBuf, ParameterFile) {
+        let directory = std::env::temp_dir().join(format!("parameters-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let parameter = ParameterFile {
+            circuit: "credits.aleo/transfer",
+            file_name: "transfer.verifier",
+            checksum,
+            fetch: || Err(ParameterError::RemoteFetchDisabled),
+        };
+        std::fs::write(parameter.path(&directory), contents).unwrap();
+        (directory, parameter)
+    }
+
+    #[test]
+    fn test_missing_parameter_file() {
+        let directory = std::env::temp_dir().join(format!("parameters-missing-{}", std::process::id()));
+        let error = ParameterRegistry::<CurrentNetwork>::load_from(&directory, &VERIFYING_KEY_FILES[..1]).unwrap_err();
+        assert!(error.to_string().starts_with("The verifying key for 'credits.aleo/mint' is missing at"));
+    }
+
+    #[test]
+    fn test_corrupt_parameter_file() {
+        // The checksum of the bytes `[0, 0, 1, 2, 3]`.
+        const CHECKSUM: &str = "a498efa8d0759e7b704095853db9bc1ef8cf65af37bed9d006c4b2917e695061";
+        let (directory, parameter) = sample_parameter("corrupt", &[0, 0, 1, 2, 4], CHECKSUM);
+
+        // Ensure the startup fails on the checksum, naming the circuit and the file.
+        let error = ParameterRegistry::<CurrentNetwork>::load_from(&directory, &[parameter]).unwrap_err();
+        let expected = format!(
+            "The verifying key for 'credits.aleo/transfer' at '{}' is corrupt (expected checksum {CHECKSUM}",
+            parameter.path(&directory).display()
+        );
+        assert!(error.to_string().starts_with(&expected), "{error}");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_undecodable_parameter_file() {
+        // The checksum of the bytes `[0, 0, 1, 2, 3]`.
+        const CHECKSUM: &str = "a498efa8d0759e7b704095853db9bc1ef8cf65af37bed9d006c4b2917e695061";
+        let (directory, parameter) = sample_parameter("undecodable", &[0, 0, 1, 2, 3], CHECKSUM);
+
+        // Ensure the startup fails on the decoding, as the file matches its checksum.
+        let error = ParameterRegistry::<CurrentNetwork>::load_from(&directory, &[parameter]).unwrap_err();
+        let expected = format!(
+            "The verifying key for 'credits.aleo/transfer' at '{}' failed to decode",
+            parameter.path(&directory).display()
+        );
+        assert!(error.to_string().starts_with(&expected), "{error}");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
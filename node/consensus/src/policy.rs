@@ -0,0 +1,181 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Consensus, TransactionRejection};
+use snarkvm::prelude::{ConsensusStorage, Network, ProgramID, Transaction};
+
+use anyhow::Result;
+use indexmap::IndexSet;
+use std::{str::FromStr, sync::Arc};
+
+/// The stage at which a policy hook is invoked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PolicyStage {
+    /// The transaction is being admitted into the memory pool.
+    Admission,
+    /// The transaction is being selected for the template of the next block.
+    Template,
+}
+
+/// The context in which a policy hook is invoked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PolicyContext {
+    /// The height of the next block.
+    pub height: u32,
+    /// The stage at which the hook is invoked.
+    pub stage: PolicyStage,
+}
+
+/// The verdict of a policy hook on a transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyVerdict {
+    /// The transaction is accepted by the policy.
+    Accept,
+    /// The transaction is rejected by the policy, with the given operator-defined reason.
+    Reject { reason: String },
+}
+
+/// A local policy on the transactions that the node admits into its memory pool and selects for its blocks.
+///
+/// The hooks are invoked after the consensus rules, and only for the memory pool and the block template:
+/// the blocks of other nodes are validated by the consensus rules alone, so that a local policy cannot split
+/// the chain. A transaction that is rejected by a policy is still valid when it is received in a block.
+pub trait PolicyHook<N: Network>: Send + Sync {
+    /// Returns the name of the policy, which is reported with its rejections.
+    fn name(&self) -> &str;
+
+    /// Returns the verdict of the policy on the given transaction.
+    fn validate_transaction(&self, transaction: &Transaction<N>, context: PolicyContext) -> PolicyVerdict;
+
+    /// Returns the verdict of the policy on the given transactions of a block template, whose last transaction
+    /// is the candidate being selected. If the verdict is a rejection, the candidate is skipped.
+    fn validate_block_template(&self, _transactions: &[Transaction<N>], _context: PolicyContext) -> PolicyVerdict {
+        PolicyVerdict::Accept
+    }
+}
+
+/// A policy that only accepts the transactions that deploy or execute the listed programs.
+///
+/// Note that the fee of a transaction is not checked, as every transaction pays its fee with `credits.aleo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramAllowlist<N: Network> {
+    /// The IDs of the allowed programs.
+    programs: IndexSet<ProgramID<N>>,
+}
+
+impl<N: Network> ProgramAllowlist<N> {
+    /// Initializes the allowlist from the given program IDs.
+    pub fn new(programs: &[String]) -> Result<Self> {
+        let programs = programs.iter().map(|program_id| ProgramID::from_str(program_id)).collect::<Result<_>>()?;
+        Ok(Self { programs })
+    }
+
+    /// Returns `true` if the given program is allowed.
+    pub fn contains(&self, program_id: &ProgramID<N>) -> bool {
+        self.programs.contains(program_id)
+    }
+}
+
+impl<N: Network> PolicyHook<N> for ProgramAllowlist<N> {
+    fn name(&self) -> &str {
+        "program-allowlist"
+    }
+
+    fn validate_transaction(&self, transaction: &Transaction<N>, _context: PolicyContext) -> PolicyVerdict {
+        // Ensure a deployment deploys an allowed program.
+        if let Transaction::Deploy(_, _, deployment, _) = transaction {
+            if !self.contains(deployment.program_id()) {
+                return PolicyVerdict::Reject {
+                    reason: format!("the program '{}' is not in the allowlist", deployment.program_id()),
+                };
+            }
+        }
+        // Ensure an execution only executes allowed programs, apart from its fee.
+        if let Transaction::Execute(_, execution, _) = transaction {
+            if let Some(transition) = execution.transitions().find(|transition| !self.contains(transition.program_id()))
+            {
+                return PolicyVerdict::Reject {
+                    reason: format!("the program '{}' is not in the allowlist", transition.program_id()),
+                };
+            }
+        }
+        PolicyVerdict::Accept
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the names of the registered policy hooks, in order of registration.
+    pub fn policy_hooks(&self) -> Vec<String> {
+        self.policy_hooks.iter().map(|hook| hook.name().to_string()).collect()
+    }
+
+    /// Registers the given policy hook, which applies to the transactions admitted and selected from then on.
+    pub fn register_policy_hook(&mut self, hook: Arc<dyn PolicyHook<N>>) {
+        Arc::make_mut(&mut self.policy_hooks).push(hook);
+    }
+
+    /// Checks the given transaction is accepted by every registered policy hook, at the given stage.
+    ///
+    /// Note that this must only be called for the memory pool and the block template, never for block validation.
+    pub fn check_transaction_policy(
+        &self,
+        transaction: &Transaction<N>,
+        stage: PolicyStage,
+    ) -> Result<(), TransactionRejection<N>> {
+        let context = PolicyContext { height: self.ledger.latest_height().saturating_add(1), stage };
+        self.check_policy(|hook| hook.validate_transaction(transaction, context))
+    }
+
+    /// Checks the given transactions of a block template, whose last transaction is the candidate being selected,
+    /// are accepted by every registered policy hook.
+    pub fn check_block_template_policy(&self, transactions: &[Transaction<N>]) -> Result<(), TransactionRejection<N>> {
+        let stage = PolicyStage::Template;
+        let context = PolicyContext { height: self.ledger.latest_height().saturating_add(1), stage };
+        self.check_policy(|hook| hook.validate_block_template(transactions, context))
+    }
+
+    /// Returns the rejection of the first registered policy hook that rejects, by the given verdict of each hook.
+    fn check_policy(
+        &self,
+        verdict: impl Fn(&dyn PolicyHook<N>) -> PolicyVerdict,
+    ) -> Result<(), TransactionRejection<N>> {
+        for hook in self.policy_hooks.iter() {
+            if let PolicyVerdict::Reject { reason } = verdict(hook.as_ref()) {
+                return Err(TransactionRejection::Policy { hook: hook.name().to_string(), reason });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::console::network::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_program_allowlist() {
+        let allowlist = ProgramAllowlist::<CurrentNetwork>::new(&["credits.aleo".to_string()]).unwrap();
+        assert!(allowlist.contains(&ProgramID::from_str("credits.aleo").unwrap()));
+        assert!(!allowlist.contains(&ProgramID::from_str("token.aleo").unwrap()));
+        assert_eq!(PolicyHook::<CurrentNetwork>::name(&allowlist), "program-allowlist");
+
+        // Ensure the allowlist rejects an invalid program ID.
+        assert!(ProgramAllowlist::<CurrentNetwork>::new(&["token".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Consensus, MAXIMUM_FUTURE_BLOCK_TIME_IN_SECS};
+use snarkvm::prelude::{coinbase_target, proof_target, Block, ConsensusStorage, Network, ToBytes, Transactions};
+
+use anyhow::Result;
+use core::fmt;
+
+/// The maximum size of a submitted block in bytes, beyond which it fails the pre-checks.
+pub const MAXIMUM_BLOCK_SIZE_IN_BYTES: usize = 16 * 1024 * 1024; // 16 MiB
+
+/// The limits on the declared contents of a submitted block, which are checked before its proofs are verified.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PrecheckLimits {
+    /// The maximum number of transactions in a block.
+    pub max_transactions: usize,
+    /// The maximum size of a serialized block, in bytes.
+    pub max_block_size: usize,
+}
+
+impl PrecheckLimits {
+    /// Returns the default limits of the given network.
+    pub const fn for_network<N: Network>() -> Self {
+        Self { max_transactions: Transactions::<N>::MAX_TRANSACTIONS, max_block_size: MAXIMUM_BLOCK_SIZE_IN_BYTES }
+    }
+}
+
+/// The reason a submitted block failed the pre-checks, which are run before its proofs are verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrecheckFailure<N: Network> {
+    /// The previous block of the block is not in the canonical chain.
+    UnknownParent { previous_hash: N::BlockHash },
+    /// The previous block of the block is a canonical block, but not the latest block.
+    StaleParent { previous_hash: N::BlockHash, parent_height: u32, latest_height: u32 },
+    /// The height of the block does not follow the latest height.
+    IncorrectHeight { height: u32, expected: u32 },
+    /// The round of the block does not follow the latest round.
+    IncorrectRound { round: u64, expected: u64 },
+    /// The timestamp of the block is not after the timestamp of the latest block.
+    StaleTimestamp { timestamp: i64, latest_timestamp: i64 },
+    /// The timestamp of the block is too far ahead of the network-adjusted time.
+    FutureTimestamp { timestamp: i64, maximum: i64 },
+    /// The coinbase target of the block is not the expected coinbase target.
+    IncorrectCoinbaseTarget { coinbase_target: u64, expected: u64 },
+    /// The proof target of the block is not the expected proof target.
+    IncorrectProofTarget { proof_target: u64, expected: u64 },
+    /// The block contains more transactions than allowed.
+    TooManyTransactions { num_transactions: usize, maximum: usize },
+    /// The serialized block is larger than allowed.
+    OversizedBlock { size: usize, maximum: usize },
+    /// The coinbase transactions of the block break the coinbase rules (see `BlockRejection`).
+    InvalidCoinbase(String),
+}
+
+impl<N: Network> PrecheckFailure<N> {
+    /// Returns the code of the failure, as it is reported by the REST API.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownParent { .. } => "unknown_parent",
+            Self::StaleParent { .. } => "stale_parent",
+            Self::IncorrectHeight { .. } => "incorrect_height",
+            Self::IncorrectRound { .. } => "incorrect_round",
+            Self::StaleTimestamp { .. } => "stale_timestamp",
+            Self::FutureTimestamp { .. } => "future_timestamp",
+            Self::IncorrectCoinbaseTarget { .. } => "incorrect_coinbase_target",
+            Self::IncorrectProofTarget { .. } => "incorrect_proof_target",
+            Self::TooManyTransactions { .. } => "too_many_transactions",
+            Self::OversizedBlock { .. } => "oversized_block",
+            Self::InvalidCoinbase(_) => "invalid_coinbase",
+        }
+    }
+}
+
+impl<N: Network> fmt::Display for PrecheckFailure<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownParent { previous_hash } => {
+                write!(f, "the previous block '{previous_hash}' is not in the canonical chain")
+            }
+            Self::StaleParent { previous_hash, parent_height, latest_height } => {
+                write!(
+                    f,
+                    "the previous block '{previous_hash}' is at height {parent_height}, but the latest height is {latest_height}"
+                )
+            }
+            Self::IncorrectHeight { height, expected } => {
+                write!(f, "the block height is {height} (expected {expected})")
+            }
+            Self::IncorrectRound { round, expected } => write!(f, "the block round is {round} (expected {expected})"),
+            Self::StaleTimestamp { timestamp, latest_timestamp } => {
+                write!(f, "the block timestamp {timestamp} is not after the latest timestamp {latest_timestamp}")
+            }
+            Self::FutureTimestamp { timestamp, maximum } => {
+                write!(f, "the block timestamp {timestamp} is too far ahead of the network time (maximum is {maximum})")
+            }
+            Self::IncorrectCoinbaseTarget { coinbase_target, expected } => {
+                write!(f, "the coinbase target is {coinbase_target} (expected {expected})")
+            }
+            Self::IncorrectProofTarget { proof_target, expected } => {
+                write!(f, "the proof target is {proof_target} (expected {expected})")
+            }
+            Self::TooManyTransactions { num_transactions, maximum } => {
+                write!(f, "the block contains {num_transactions} transactions (maximum is {maximum})")
+            }
+            Self::OversizedBlock { size, maximum } => {
+                write!(f, "the block is {size} bytes (maximum is {maximum})")
+            }
+            Self::InvalidCoinbase(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl<N: Network> std::error::Error for PrecheckFailure<N> {}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the limits on the declared contents of a submitted block.
+    pub fn precheck_limits(&self) -> PrecheckLimits {
+        *self.precheck_limits.read()
+    }
+
+    /// Sets the limits on the declared contents of a submitted block.
+    pub fn set_precheck_limits(&self, limits: PrecheckLimits) {
+        *self.precheck_limits.write() = limits;
+    }
+
+    /// Runs the pre-checks of the given block as the next block, which return within milliseconds, as they check
+    /// its position, header fields, limits, and coinbase amount, without verifying any proof or signature.
+    ///
+    /// A block that passes the pre-checks may still be rejected by the full validation (see `check_next_block`).
+    /// Returns the first failure, if any, and an error if the ledger cannot be read.
+    pub fn precheck_block(&self, block: &Block<N>) -> Result<Option<PrecheckFailure<N>>> {
+        // Ensure the block extends the latest block, or report how far behind its parent is.
+        let latest_height = self.ledger.latest_height();
+        let previous_hash = block.previous_hash();
+        if previous_hash != self.ledger.latest_hash() {
+            return match self.ledger.contains_block_hash(&previous_hash)? {
+                true => {
+                    let parent_height = self.ledger.get_height(&previous_hash)?;
+                    Ok(Some(PrecheckFailure::StaleParent { previous_hash, parent_height, latest_height }))
+                }
+                false => Ok(Some(PrecheckFailure::UnknownParent { previous_hash })),
+            };
+        }
+
+        // Ensure the header fields follow the latest block.
+        if block.height() != latest_height + 1 {
+            return Ok(Some(PrecheckFailure::IncorrectHeight { height: block.height(), expected: latest_height + 1 }));
+        }
+        let latest_round = self.ledger.latest_round();
+        if latest_round > 0 && block.round() != latest_round + 1 {
+            return Ok(Some(PrecheckFailure::IncorrectRound { round: block.round(), expected: latest_round + 1 }));
+        }
+        let latest_timestamp = self.ledger.latest_block().timestamp();
+        if block.timestamp() <= latest_timestamp {
+            return Ok(Some(PrecheckFailure::StaleTimestamp { timestamp: block.timestamp(), latest_timestamp }));
+        }
+        let maximum = self.ledger.network_time().now().saturating_add(MAXIMUM_FUTURE_BLOCK_TIME_IN_SECS);
+        if block.timestamp() > maximum {
+            return Ok(Some(PrecheckFailure::FutureTimestamp { timestamp: block.timestamp(), maximum }));
+        }
+
+        // Ensure the targets are the expected targets.
+        let expected_coinbase_target = coinbase_target(
+            self.ledger.last_coinbase_target(),
+            self.ledger.last_coinbase_timestamp(),
+            block.timestamp(),
+            N::ANCHOR_TIME,
+            N::NUM_BLOCKS_PER_EPOCH,
+            N::GENESIS_COINBASE_TARGET,
+        )?;
+        if block.coinbase_target() != expected_coinbase_target {
+            let coinbase_target = block.coinbase_target();
+            return Ok(Some(PrecheckFailure::IncorrectCoinbaseTarget {
+                coinbase_target,
+                expected: expected_coinbase_target,
+            }));
+        }
+        let expected_proof_target = proof_target(expected_coinbase_target, N::GENESIS_PROOF_TARGET);
+        if block.proof_target() != expected_proof_target {
+            let proof_target = block.proof_target();
+            return Ok(Some(PrecheckFailure::IncorrectProofTarget { proof_target, expected: expected_proof_target }));
+        }
+
+        // Ensure the block is within the limits.
+        let limits = self.precheck_limits();
+        let num_transactions = block.transactions().len();
+        if num_transactions > limits.max_transactions {
+            let maximum = limits.max_transactions;
+            return Ok(Some(PrecheckFailure::TooManyTransactions { num_transactions, maximum }));
+        }
+        let size = block.to_bytes_le()?.len();
+        if size > limits.max_block_size {
+            return Ok(Some(PrecheckFailure::OversizedBlock { size, maximum: limits.max_block_size }));
+        }
+
+        // Ensure the coinbase transaction does not mint more than the expected amount.
+        if let Err(error) = self.check_block_coinbase_transactions(block) {
+            return Ok(Some(PrecheckFailure::InvalidCoinbase(error.to_string())));
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Consensus;
+use snarkos_account::AccountAddress;
+use snarkvm::prelude::{Address, ConsensusStorage, Network};
+
+use anyhow::{anyhow, bail, ensure, Error, Result};
+use core::{fmt, str::FromStr};
+use indexmap::IndexMap;
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+
+/// A recipient of the coinbase, with its weight in the split of the coinbase.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CoinbaseRecipient<N: Network> {
+    /// The address of the recipient.
+    pub address: Address<N>,
+    /// The weight of the recipient, relative to the weights of the other recipients.
+    pub weight: u64,
+}
+
+impl<N: Network> FromStr for CoinbaseRecipient<N> {
+    type Err = Error;
+
+    /// Parses a recipient of the form `{address}:{weight}`, or `{address}` with a weight of 1.
+    fn from_str(recipient: &str) -> Result<Self> {
+        let (address, weight) = match recipient.split_once(':') {
+            Some((address, weight)) => (
+                address,
+                weight.parse().map_err(|_| anyhow!("Invalid weight in the coinbase recipient '{recipient}'"))?,
+            ),
+            None => (recipient, 1),
+        };
+        let address = AccountAddress::<N>::from_str(address)
+            .map_err(|error| anyhow!("Invalid address in the coinbase recipient '{recipient}' - {error}"))?;
+        Ok(Self { address: address.into(), weight })
+    }
+}
+
+impl<N: Network> fmt::Display for CoinbaseRecipient<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.address, self.weight)
+    }
+}
+
+/// An output of the coinbase, which pays the given amount to the given recipient.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CoinbaseOutput<N: Network> {
+    /// The address of the recipient.
+    pub recipient: Address<N>,
+    /// The amount paid to the recipient (in microcredits).
+    pub amount: u64,
+}
+
+/// The recipients of the coinbase, which split the coinbase in proportion to their weights.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoinbaseRecipients<N: Network>(Vec<CoinbaseRecipient<N>>);
+
+impl<N: Network> CoinbaseRecipients<N> {
+    /// Initializes the recipients of the coinbase, ensuring there is at least one recipient, each address
+    /// is listed once, each weight is positive, and the sum of the weights does not overflow.
+    pub fn new(recipients: Vec<CoinbaseRecipient<N>>) -> Result<Self> {
+        ensure!(!recipients.is_empty(), "The coinbase must have at least one recipient");
+        for (index, recipient) in recipients.iter().enumerate() {
+            ensure!(
+                recipient.weight > 0,
+                "The weight of the coinbase recipient '{}' must be positive",
+                recipient.address
+            );
+            ensure!(
+                recipients[..index].iter().all(|other| other.address != recipient.address),
+                "The coinbase recipient '{}' is listed twice",
+                recipient.address
+            );
+        }
+        ensure!(
+            recipients.iter().try_fold(0u64, |sum, recipient| sum.checked_add(recipient.weight)).is_some(),
+            "The sum of the weights of the coinbase recipients overflows"
+        );
+        Ok(Self(recipients))
+    }
+
+    /// Initializes the recipients of the coinbase, with the given address as the only recipient.
+    pub fn single(address: Address<N>) -> Self {
+        Self(vec![CoinbaseRecipient { address, weight: 1 }])
+    }
+
+    /// Parses the recipients of the coinbase, each of the form `{address}:{weight}` or `{address}`.
+    pub fn parse<S: AsRef<str>>(recipients: &[S]) -> Result<Self> {
+        Self::new(recipients.iter().map(|recipient| recipient.as_ref().trim().parse()).collect::<Result<_>>()?)
+    }
+
+    /// Returns the recipients of the coinbase.
+    pub fn recipients(&self) -> &[CoinbaseRecipient<N>] {
+        &self.0
+    }
+
+    /// Splits the given amount (in microcredits) among the recipients, in proportion to their weights.
+    /// Each share is rounded down, and the remainder is paid to the first recipient, so that the outputs
+    /// sum to exactly the given amount.
+    pub fn split(&self, amount: u64) -> Vec<CoinbaseOutput<N>> {
+        // The sum of the weights does not overflow, as it is checked on initialization.
+        let total_weight = self.0.iter().map(|recipient| recipient.weight as u128).sum::<u128>();
+        // Compute the share of each recipient, which does not exceed the amount, as the weight does not exceed the total.
+        let mut outputs = self
+            .0
+            .iter()
+            .map(|recipient| CoinbaseOutput {
+                recipient: recipient.address,
+                amount: (amount as u128 * recipient.weight as u128 / total_weight) as u64,
+            })
+            .collect::<Vec<_>>();
+        // Pay the remainder to the first recipient.
+        let remainder = amount - outputs.iter().map(|output| output.amount).sum::<u64>();
+        outputs[0].amount += remainder;
+        outputs
+    }
+}
+
+/// The number of blocks mined for each coinbase recipient, which is written to its file (if any) on each block.
+#[derive(Debug)]
+pub struct MinedBlocks<N: Network> {
+    /// The path to the file of the counters, if they are persisted.
+    path: Option<PathBuf>,
+    /// The number of blocks mined for each recipient.
+    counters: RwLock<IndexMap<Address<N>, u64>>,
+}
+
+impl<N: Network> Default for MinedBlocks<N> {
+    /// Initializes the counters, without persisting them.
+    fn default() -> Self {
+        Self { path: None, counters: Default::default() }
+    }
+}
+
+impl<N: Network> MinedBlocks<N> {
+    /// Loads the counters from the given file, or initializes them if the file does not exist.
+    /// Each line of the file holds an address and its number of mined blocks, separated by a space.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut counters = IndexMap::new();
+        if path.exists() {
+            for line in std::fs::read_to_string(path)?.lines().filter(|line| !line.trim().is_empty()) {
+                match line.split_once(' ') {
+                    Some((address, count)) => {
+                        let address = AccountAddress::<N>::from_str(address).map_err(|error| {
+                            anyhow!("Invalid address '{address}' in the mined blocks file - {error}")
+                        })?;
+                        counters.insert(address.into(), count.trim().parse()?)
+                    }
+                    None => bail!("Invalid line '{line}' in the mined blocks file '{}'", path.display()),
+                };
+            }
+        }
+        Ok(Self { path: Some(path.to_path_buf()), counters: RwLock::new(counters) })
+    }
+
+    /// Returns the number of blocks mined for each recipient.
+    pub fn counters(&self) -> IndexMap<Address<N>, u64> {
+        self.counters.read().clone()
+    }
+
+    /// Records a mined block for each of the given recipients, and writes the counters to their file, if any.
+    pub fn record(&self, recipients: &CoinbaseRecipients<N>) -> Result<()> {
+        let mut counters = self.counters.write();
+        for recipient in recipients.recipients() {
+            *counters.entry(recipient.address).or_default() += 1;
+        }
+        match &self.path {
+            Some(path) => {
+                let contents =
+                    counters.iter().map(|(address, count)| format!("{address} {count}\n")).collect::<String>();
+                // Write the contents to a temporary file, and move it into place.
+                let temporary_path = path.with_extension("tmp");
+                std::fs::write(&temporary_path, contents)?;
+                Ok(std::fs::rename(&temporary_path, path)?)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// The recipients of the coinbase, and the number of blocks mined for each recipient.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinerInfo<N: Network> {
+    /// The recipients of the coinbase of the next block, if they are set.
+    pub recipients: Option<CoinbaseRecipients<N>>,
+    /// The number of blocks mined for each recipient.
+    pub mined_blocks: IndexMap<Address<N>, u64>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the recipients of the coinbase, if they are set.
+    pub fn coinbase_recipients(&self) -> Option<CoinbaseRecipients<N>> {
+        self.coinbase_recipients.read().clone()
+    }
+
+    /// Sets the recipients of the coinbase, which take effect on the next block template.
+    pub fn set_coinbase_recipients(&self, recipients: CoinbaseRecipients<N>) {
+        // Hold the lock of the template, so that no template is built with the previous recipients after this call.
+        let mut template = self.block_template.lock();
+        *self.coinbase_recipients.write() = Some(recipients);
+        *template = None;
+    }
+
+    /// Sets the counters of the blocks mined for each recipient.
+    pub fn set_mined_blocks(&self, mined_blocks: MinedBlocks<N>) {
+        *self.mined_blocks.write() = mined_blocks;
+    }
+
+    /// Records a block mined by this node for each of the current recipients of the coinbase.
+    pub fn record_mined_block(&self) -> Result<()> {
+        match self.coinbase_recipients() {
+            Some(recipients) => self.mined_blocks.read().record(&recipients),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the recipients of the coinbase, and the number of blocks mined for each recipient.
+    pub fn miner_info(&self) -> MinerInfo<N> {
+        MinerInfo { recipients: self.coinbase_recipients(), mined_blocks: self.mined_blocks.read().counters() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{PrivateKey, TestRng, Testnet3};
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a random address.
+    fn sample_address(rng: &mut TestRng) -> Address<CurrentNetwork> {
+        Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_recipients() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (sample_address(rng), sample_address(rng));
+
+        let recipients =
+            CoinbaseRecipients::<CurrentNetwork>::parse(&[format!("{first}:70"), format!("{second}")]).unwrap();
+        assert_eq!(recipients.recipients(), [CoinbaseRecipient { address: first, weight: 70 }, CoinbaseRecipient {
+            address: second,
+            weight: 1
+        }]);
+
+        // Ensure the invalid recipients are rejected.
+        assert!(CoinbaseRecipients::<CurrentNetwork>::parse::<String>(&[]).is_err());
+        assert!(CoinbaseRecipients::<CurrentNetwork>::parse(&["aleo1invalid:70"]).is_err());
+        assert!(CoinbaseRecipients::<CurrentNetwork>::parse(&[format!("{first}:0")]).is_err());
+        assert!(CoinbaseRecipients::<CurrentNetwork>::parse(&[format!("{first}:x")]).is_err());
+        assert!(CoinbaseRecipients::<CurrentNetwork>::parse(&[format!("{first}:1"), format!("{first}:2")]).is_err());
+        assert!(CoinbaseRecipients::<CurrentNetwork>::parse(&[format!("{first}:{}", u64::MAX), format!("{second}")])
+            .is_err());
+
+        // Ensure the reason an address is invalid is reported.
+        let mut corrupted = first.to_string();
+        corrupted.replace_range(10..11, if &corrupted[10..11] == "q" { "p" } else { "q" });
+        let error = CoinbaseRecipients::<CurrentNetwork>::parse(&[format!("{corrupted}:70")]).unwrap_err();
+        assert!(error.to_string().contains("checksum"), "{error}");
+    }
+
+    #[test]
+    fn test_split() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (sample_address(rng), sample_address(rng));
+        let recipients =
+            CoinbaseRecipients::new(vec![CoinbaseRecipient { address: first, weight: 70 }, CoinbaseRecipient {
+                address: second,
+                weight: 30,
+            }])
+            .unwrap();
+
+        // Ensure the outputs sum to exactly the amount, with the remainder paid to the first recipient.
+        for amount in [0, 1, 99, 100, 101, 1_000_003, u64::MAX] {
+            let outputs = recipients.split(amount);
+            assert_eq!(outputs.iter().map(|output| output.amount as u128).sum::<u128>(), amount as u128);
+            assert_eq!(outputs[0].recipient, first);
+            assert_eq!(outputs[1].recipient, second);
+            assert_eq!(outputs[1].amount as u128, amount as u128 * 30 / 100);
+            assert_eq!(outputs[0].amount as u128, amount as u128 - amount as u128 * 30 / 100);
+        }
+        assert_eq!(recipients.split(101).iter().map(|output| output.amount).collect::<Vec<_>>(), vec![71, 30]);
+    }
+
+    #[test]
+    fn test_mined_blocks_survive_restart() {
+        let rng = &mut TestRng::default();
+        let (first, second) = (sample_address(rng), sample_address(rng));
+        let path = std::env::temp_dir().join(format!("consensus-mined-blocks-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Record the mined blocks.
+        let mined_blocks = MinedBlocks::<CurrentNetwork>::open(&path).unwrap();
+        mined_blocks.record(&CoinbaseRecipients::single(first)).unwrap();
+        mined_blocks
+            .record(&CoinbaseRecipients::parse(&[format!("{first}:70"), format!("{second}:30")]).unwrap())
+            .unwrap();
+
+        // Ensure the counters are restored from the file.
+        let mined_blocks = MinedBlocks::<CurrentNetwork>::open(&path).unwrap();
+        assert_eq!(mined_blocks.counters(), IndexMap::from([(first, 2), (second, 1)]));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
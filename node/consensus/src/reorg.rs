@@ -0,0 +1,141 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{BatchMode, Consensus, MAX_BATCH_TRANSACTIONS};
+use snarkvm::prelude::{Block, ConsensusStorage, Network};
+
+use anyhow::{anyhow, bail, ensure, Result};
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns `true` if the fork choice prefers the branch with the given tip over the canonical chain,
+    /// which it does if the branch has a greater cumulative proof target, or an equal one at a greater height.
+    pub fn is_preferred_branch(&self, tip: &Block<N>) -> bool {
+        // If the tests force the fork choice, prefer the given branch only.
+        #[cfg(any(test, feature = "chaos"))]
+        if let Some(preferred_branch) = self.chaos.preferred_branch() {
+            return tip.hash() == preferred_branch;
+        }
+
+        let latest = self.ledger.latest_header();
+        (tip.cumulative_proof_target(), tip.height()) > (latest.cumulative_proof_target(), latest.height())
+    }
+
+    /// Removes the given number of latest blocks from the ledger, and returns them in ascending order of height.
+    ///
+    /// The transactions of the removed blocks that remain valid are returned to the memory pool,
+    /// and the transactions of the memory pool that are no longer valid are evicted.
+    pub fn decommit_blocks(&self, num_blocks: u32) -> Result<Vec<Block<N>>> {
+        let latest_height = self.ledger.latest_height();
+        ensure!(num_blocks > 0, "The number of blocks to decommit must be at least 1");
+        ensure!(
+            num_blocks <= latest_height,
+            "Cannot decommit {num_blocks} blocks, the latest block is {latest_height}"
+        );
+
+        // Retrieve the blocks, before they are removed.
+        let height = latest_height - num_blocks + 1;
+        let blocks =
+            (height..=latest_height).map(|height| self.ledger.get_block(height)).collect::<Result<Vec<_>>>()?;
+        // Remove the blocks from the ledger.
+        self.ledger.truncate(height)?;
+        // Forget the submissions of the removed blocks, so that they can be submitted again.
+        self.submitted_blocks.forget_above(height - 1)?;
+
+        // Return the transactions of the blocks to the memory pool in order, so that the dependent transactions follow.
+        let transactions = blocks
+            .iter()
+            .flat_map(|block| block.transactions().iter().cloned())
+            .filter(|transaction| !transaction.is_coinbase())
+            .collect::<Vec<_>>();
+        let mut num_resurrected = 0;
+        for batch in transactions.chunks(MAX_BATCH_TRANSACTIONS) {
+            let outcomes = self.add_unconfirmed_transactions(batch.to_vec(), BatchMode::BestEffort);
+            num_resurrected += outcomes.iter().filter(|outcome| outcome.is_accepted()).count();
+        }
+        // Evict the transactions of the memory pool that depended on the removed blocks.
+        self.memory_pool.clear_invalid_transactions(self);
+
+        info!(
+            "Decommitted {num_blocks} blocks to block {}, and restored {num_resurrected} of their {} transactions",
+            height - 1,
+            transactions.len()
+        );
+        Ok(blocks)
+    }
+
+    /// Switches the canonical chain to the given branch, which must extend a block of the canonical chain,
+    /// and be preferred by the fork choice.
+    ///
+    /// If a block of the branch fails to commit, the blocks that were removed are committed again,
+    /// so that the canonical chain is left as it was.
+    pub fn reorganize(&self, branch: &[Block<N>]) -> Result<()> {
+        let (first, tip) = match (branch.first(), branch.last()) {
+            (Some(first), Some(tip)) => (first, tip),
+            _ => bail!("Cannot reorganize to an empty branch"),
+        };
+        // Ensure the branch extends the canonical chain.
+        let fork_height =
+            first.height().checked_sub(1).ok_or_else(|| anyhow!("Cannot reorganize the genesis block"))?;
+        ensure!(
+            fork_height <= self.ledger.latest_height() && self.ledger.get_hash(fork_height)? == first.previous_hash(),
+            "Block '{}' does not extend the canonical chain",
+            first.hash()
+        );
+        // Ensure the fork choice prefers the branch.
+        ensure!(self.is_preferred_branch(tip), "The branch to block '{}' is not preferred", tip.hash());
+
+        // Remove the blocks past the fork point.
+        let num_blocks = self.ledger.latest_height() - fork_height;
+        let decommitted = match num_blocks {
+            0 => Vec::new(),
+            _ => self.decommit_blocks(num_blocks)?,
+        };
+
+        // Commit the blocks of the branch.
+        for (index, block) in branch.iter().enumerate() {
+            if let Err(error) = self.check_next_block(block).and_then(|_| self.advance_to_next_block(block)) {
+                warn!("Failed to reorganize to block {} ('{}') - {error}", block.height(), block.hash());
+                // Restore the canonical chain, by removing the committed blocks of the branch,
+                // and committing the removed blocks again.
+                if index > 0 {
+                    self.decommit_blocks(index as u32)?;
+                }
+                for block in &decommitted {
+                    self.advance_to_next_block(block)?;
+                }
+                return Err(error);
+            }
+        }
+        info!("Reorganized {num_blocks} blocks to block {} ('{}')", tip.height(), tip.hash());
+        Ok(())
+    }
+
+    /// Invalidates the given block of the canonical chain, by removing it and the blocks above it from the ledger,
+    /// and marking it as invalid, so that it is rejected until it is purged from the invalid blocks.
+    /// Returns the removed blocks, in ascending order of height.
+    pub fn invalidate_block(&self, hash: &N::BlockHash) -> Result<Vec<Block<N>>> {
+        let height = self.ledger.get_height(hash)?;
+        ensure!(height > 0, "Cannot invalidate the genesis block");
+        ensure!(self.ledger.get_hash(height)? == *hash, "Block '{hash}' is not in the canonical chain");
+
+        // Remove the block and the blocks above it.
+        let blocks = self.decommit_blocks(self.ledger.latest_height() - height + 1)?;
+        // Mark the block as invalid.
+        self.invalid_blocks.insert(*hash, height, "Invalidated by the operator".to_string(), None)?;
+        info!("Invalidated block {height} ('{hash}')");
+        Ok(blocks)
+    }
+}
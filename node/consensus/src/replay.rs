@@ -0,0 +1,155 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Consensus;
+use snarkvm::prelude::{Block, ConsensusStorage, Network};
+
+use anyhow::Result;
+use core::{fmt, time::Duration};
+use std::time::Instant;
+
+/// The stage of the validation pipeline that a check belongs to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayStage<N: Network> {
+    /// A check on the block as a whole.
+    Block,
+    /// A check on the transaction with the given ID.
+    Transaction(N::TransactionID),
+}
+
+impl<N: Network> fmt::Display for ReplayStage<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Block => write!(f, "block"),
+            Self::Transaction(transaction_id) => write!(f, "transaction {transaction_id}"),
+        }
+    }
+}
+
+/// The outcome of a single check in the validation pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayCheck<N: Network> {
+    /// The stage of the check.
+    pub stage: ReplayStage<N>,
+    /// The name of the check.
+    pub name: &'static str,
+    /// The error returned by the check, if it failed.
+    pub error: Option<String>,
+    /// The time taken to run the check.
+    pub elapsed: Duration,
+}
+
+impl<N: Network> ReplayCheck<N> {
+    /// Returns `true` if the check passed.
+    pub const fn is_passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The report of a block replayed through the validation pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayReport<N: Network> {
+    /// The height of the replayed block.
+    pub height: u32,
+    /// The hash of the replayed block.
+    pub hash: N::BlockHash,
+    /// The outcome of each check, in the order they were run.
+    pub checks: Vec<ReplayCheck<N>>,
+}
+
+impl<N: Network> ReplayReport<N> {
+    /// Returns `true` if every check passed.
+    pub fn is_valid(&self) -> bool {
+        self.checks.iter().all(|check| check.is_passed())
+    }
+
+    /// Returns the first check that failed, if any.
+    pub fn first_failure(&self) -> Option<&ReplayCheck<N>> {
+        self.checks.iter().find(|check| !check.is_passed())
+    }
+
+    /// Returns the checks that failed.
+    pub fn failures(&self) -> impl '_ + Iterator<Item = &ReplayCheck<N>> {
+        self.checks.iter().filter(|check| !check.is_passed())
+    }
+}
+
+impl<N: Network> fmt::Display for ReplayReport<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Replay of block {} ({})", self.height, self.hash)?;
+        for check in &self.checks {
+            let outcome = match &check.error {
+                None => "passed".to_string(),
+                Some(error) => format!("FAILED - {error}"),
+            };
+            writeln!(f, "  [{}] {} ({} ms): {outcome}", check.stage, check.name, check.elapsed.as_millis())?;
+        }
+        match self.first_failure() {
+            Some(check) => write!(f, "Block {} is invalid: '{}' failed at {}", self.height, check.name, check.stage),
+            None => write!(f, "Block {} is valid", self.height),
+        }
+    }
+}
+
+/// Replays the given block through the full validation pipeline of the given consensus, without
+/// advancing the ledger, and returns a report with the outcome and timing of every check.
+///
+/// Unlike `Consensus::check_next_block`, this does not stop at the first failure, and checks each
+/// transaction individually, so that the report pinpoints every check that failed.
+pub fn replay_block<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    block: &Block<N>,
+) -> ReplayReport<N> {
+    let mut checks = Vec::new();
+
+    // Runs the given check, and records its outcome.
+    let mut run = |stage: ReplayStage<N>, name: &'static str, check: &dyn Fn() -> Result<()>| {
+        trace!("Replaying the '{name}' check for {stage}");
+        let timer = Instant::now();
+        let error = check().err().map(|error| error.to_string());
+        let elapsed = timer.elapsed();
+        match &error {
+            None => debug!("Replay of '{name}' for {stage} passed ({} ms)", elapsed.as_millis()),
+            Some(error) => warn!("Replay of '{name}' for {stage} failed ({} ms) - {error}", elapsed.as_millis()),
+        }
+        checks.push(ReplayCheck { stage, name, error, elapsed });
+    };
+
+    /* Block */
+
+    run(ReplayStage::Block, "position", &|| consensus.check_block_position(block));
+    run(ReplayStage::Block, "duplicates", &|| consensus.check_block_duplicates(block));
+    run(ReplayStage::Block, "uniqueness", &|| consensus.check_block_uniqueness(block));
+    run(ReplayStage::Block, "header", &|| consensus.check_block_header(block));
+
+    /* Transactions */
+
+    run(ReplayStage::Block, "transactions list", &|| consensus.check_block_transactions_list(block));
+    run(ReplayStage::Block, "coinbase transactions", &|| consensus.check_block_coinbase_transactions(block));
+    for transaction in block.transactions().iter() {
+        let stage = || ReplayStage::Transaction(transaction.id());
+        run(stage(), "structure", &|| consensus.check_transaction_structure(transaction));
+        run(stage(), "state roots", &|| consensus.check_transaction_state_roots(transaction));
+        run(stage(), "double-spend", &|| consensus.check_transaction_uniqueness(transaction));
+        run(stage(), "proof", &|| consensus.check_transaction_proof(transaction));
+    }
+
+    /* Coinbase */
+
+    run(ReplayStage::Block, "coinbase", &|| consensus.check_block_coinbase(block));
+
+    ReplayReport { height: block.height(), hash: block.hash(), checks }
+}
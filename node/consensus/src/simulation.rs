@@ -0,0 +1,189 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    batch::BatchState,
+    memory_pool::{conflicts_with, depends_on, fee_rate},
+    transaction_digest,
+    Consensus,
+    PolicyStage,
+    TransactionRejection,
+};
+use snarkvm::prelude::{ConsensusStorage, Network, ToBytes, Transaction};
+
+use anyhow::{Error, Result};
+use std::collections::HashMap;
+
+/// The outcome of a simulated admission of a transaction into the memory pool, which is checked against
+/// a view of the memory pool and the ledger, without adding the transaction or relaying it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcceptanceSimulation<N: Network> {
+    /// The ID of the transaction.
+    pub transaction_id: N::TransactionID,
+    /// The reasons the transaction would be rejected, in order of the checks, or none if it would be accepted.
+    pub rejections: Vec<TransactionRejection<N>>,
+    /// The fee of the transaction (in microcredits).
+    pub fee: u64,
+    /// The size of the transaction (in bytes).
+    pub size: usize,
+    /// The fee rate of the transaction (in microcredits per byte).
+    pub fee_rate: f64,
+    /// The percentage of the transactions in the memory pool with a lower fee rate than the transaction,
+    /// which is `100` if the memory pool is empty.
+    pub fee_rate_percentile: f64,
+    /// The estimated position of the transaction in the template for the next block, which is ordered by
+    /// decreasing fee rate, or `None` if the transaction would not be selected for the next block.
+    pub template_position: Option<usize>,
+    /// The IDs of the transactions in the memory pool that conflict with the transaction.
+    pub conflicts: Vec<N::TransactionID>,
+    /// The IDs of the transactions in the memory pool that the transaction depends on.
+    pub parents: Vec<N::TransactionID>,
+    /// Whether the proofs of the transaction were verified by the simulation.
+    pub is_proof_verified: bool,
+}
+
+impl<N: Network> AcceptanceSimulation<N> {
+    /// Returns `true` if the transaction would be accepted into the memory pool.
+    pub fn is_accepted(&self) -> bool {
+        self.rejections.is_empty()
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Simulates the admission of the given transaction into the memory pool, and returns the verdict, along with
+    /// the estimated position of the transaction in the next block, and its relations to the memory pool.
+    ///
+    /// The simulation does not add the transaction to the memory pool, evict the transactions it would replace,
+    /// publish any event, or record the transaction as verified or rejected. Its proofs are verified only if
+    /// `verify_proof` is `true`, as the verification is the most expensive check.
+    pub fn simulate_acceptance(
+        &self,
+        transaction: &Transaction<N>,
+        verify_proof: bool,
+    ) -> Result<AcceptanceSimulation<N>> {
+        let transaction_id = transaction.id();
+        let invalid = |error: Error| TransactionRejection::Invalid { reason: error.to_string() };
+
+        // Retrieve the block template first, so that it is built from a memory pool no newer than the view.
+        let template = self.block_template()?;
+        let pooled = self
+            .memory_pool
+            .unconfirmed_transactions_with_arrival_times()
+            .into_iter()
+            .map(|(pooled, _)| (pooled.id(), pooled))
+            .filter(|(pooled_id, _)| *pooled_id != transaction_id)
+            .collect::<HashMap<_, _>>();
+
+        // Find the related transactions in the memory pool.
+        let parents = pooled.values().filter(|other| depends_on(transaction, other)).cloned().collect::<Vec<_>>();
+        let conflicts = pooled.values().filter(|other| conflicts_with(transaction, other)).map(Transaction::id);
+        let conflicts = conflicts.collect::<Vec<_>>();
+
+        // Run the admission checks, collecting every rejection.
+        let mut rejections = Vec::new();
+        if let Err(rejection) = self.memory_pool.check_admission(transaction) {
+            rejections.push(rejection);
+        }
+        if let Err(rejection) = self.check_transaction_coinbase(transaction) {
+            rejections.push(rejection);
+        }
+        if let Err(rejection) = self.check_transaction_expiry(transaction) {
+            rejections.push(rejection);
+        }
+        if let Err(error) = self.check_transaction_structure(transaction) {
+            rejections.push(error.downcast::<TransactionRejection<N>>().unwrap_or_else(invalid));
+        }
+        if let Err(error) = self.check_transaction_uniqueness(transaction) {
+            rejections.push(invalid(error));
+        }
+        if verify_proof {
+            if let Err(error) = self.simulate_transaction_proof(transaction, &parents) {
+                rejections.push(invalid(error));
+            }
+        }
+        if let Err(rejection) = self.check_transaction_policy(transaction, PolicyStage::Admission) {
+            rejections.push(rejection);
+        }
+
+        // Rank the fee rate of the transaction among the memory pool.
+        let pooled_fee_rates = pooled
+            .iter()
+            .map(|(pooled_id, pooled)| Ok((*pooled_id, fee_rate(pooled)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let fee = transaction.fee().map_or(0, |fee| *fee);
+        let size = transaction.to_bytes_le()?.len();
+        let fee_rate = fee as f64 / size as f64;
+        let fee_rate_percentile = match pooled_fee_rates.len() {
+            0 => 100.0,
+            num_transactions => {
+                let num_lower =
+                    pooled_fee_rates.values().filter(|pooled_fee_rate| **pooled_fee_rate < fee_rate).count();
+                num_lower as f64 * 100.0 / num_transactions as f64
+            }
+        };
+
+        // Estimate the position of the transaction in the template, after the selected transactions with a greater
+        // or equal fee rate, as they arrived first. Note that the selected transactions it would replace are skipped,
+        // and that a transaction which depends on the memory pool is not selected until its parents are confirmed.
+        let template_position = match rejections.is_empty() && parents.is_empty() {
+            true => Some(
+                template
+                    .transactions
+                    .iter()
+                    .filter(|selected_id| !conflicts.contains(selected_id))
+                    .filter_map(|selected_id| pooled_fee_rates.get(selected_id))
+                    .filter(|selected_fee_rate| **selected_fee_rate >= fee_rate)
+                    .count(),
+            ),
+            false => None,
+        };
+
+        Ok(AcceptanceSimulation {
+            transaction_id,
+            rejections,
+            fee,
+            size,
+            fee_rate,
+            fee_rate_percentile,
+            template_position,
+            conflicts,
+            parents: parents.iter().map(Transaction::id).collect(),
+            is_proof_verified: verify_proof,
+        })
+    }
+
+    /// Verifies the proofs of the given transaction, in the state produced by its given parents in the memory pool,
+    /// without counting the verification, or consuming the transaction from the verified transactions.
+    fn simulate_transaction_proof(&self, transaction: &Transaction<N>, parents: &[Transaction<N>]) -> Result<()> {
+        // If the transaction was verified ahead of its block, the verification is skipped.
+        if let Ok(digest) = transaction_digest(transaction) {
+            if self.verified_transactions.lock().contains(&digest) {
+                return Ok(());
+            }
+        }
+        match (transaction, parents.is_empty()) {
+            // Verify an execution that depends on the memory pool with the programs deployed by its parents.
+            (Transaction::Execute(..), false) => {
+                let mut state = BatchState { accepted: Vec::with_capacity(parents.len()), process: None };
+                for parent in parents {
+                    self.apply_batch_transaction(parent, &mut state)?;
+                }
+                self.check_dependent_transaction_proof(transaction, &state)
+            }
+            _ => self.ledger.vm().check_transaction(transaction),
+        }
+    }
+}
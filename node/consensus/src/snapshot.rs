@@ -0,0 +1,161 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    global_state_roots,
+    memory_pool::{conflicts_with, depends_on},
+    Consensus,
+};
+use snarkvm::prelude::{ConsensusStorage, Network, ToBytes, Transaction};
+
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// An unconfirmed transaction in the memory pool, annotated with the state that determines whether it is mined.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryPoolEntry<N: Network> {
+    /// The ID of the transaction.
+    pub transaction_id: N::TransactionID,
+    /// The fee of the transaction (in microcredits).
+    pub fee: u64,
+    /// The size of the transaction (in bytes).
+    pub size: usize,
+    /// The fee rate of the transaction (in microcredits per byte).
+    pub fee_rate: f64,
+    /// The UNIX timestamp (in seconds) at which the transaction was added to the memory pool.
+    pub arrival_time: i64,
+    /// The IDs of the transactions in the memory pool that the transaction depends on.
+    pub parents: Vec<N::TransactionID>,
+    /// The IDs of the transactions in the memory pool that depend on the transaction.
+    pub children: Vec<N::TransactionID>,
+    /// The IDs of the transactions in the memory pool that conflict with the transaction,
+    /// of which at most one is selected for a block.
+    pub conflicts: Vec<N::TransactionID>,
+    /// The number of blocks since the oldest global state root of the transaction,
+    /// or `None` if one of its global state roots is not in the ledger.
+    pub state_root_age: Option<u32>,
+    /// Whether the transaction is selected for the current block template.
+    pub in_block_template: bool,
+}
+
+/// A page of the annotated entries of the memory pool, computed from a single view of the memory pool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryPoolSnapshot<N: Network> {
+    /// The latest block height, at the time of the snapshot.
+    pub latest_height: u32,
+    /// The number of transactions in the memory pool, at the time of the snapshot.
+    pub num_transactions: usize,
+    /// The entries of the page, in order of arrival.
+    pub entries: Vec<MemoryPoolEntry<N>>,
+}
+
+/// A view of the memory pool, from which the entries are annotated.
+struct MemoryPoolView<N: Network> {
+    /// The latest block height, when the view was taken.
+    latest_height: u32,
+    /// The unconfirmed transactions and their arrival times, in order of arrival.
+    transactions: Vec<(Transaction<N>, i64)>,
+    /// The IDs of the transactions selected for the current block template.
+    selected: HashSet<N::TransactionID>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the annotated entry of the given transaction, if it is in the memory pool.
+    pub fn memory_pool_entry(&self, transaction_id: &N::TransactionID) -> Result<Option<MemoryPoolEntry<N>>> {
+        let view = self.memory_pool_view()?;
+        match view.transactions.iter().find(|(transaction, _)| transaction.id() == *transaction_id) {
+            Some((transaction, arrival_time)) => {
+                Ok(Some(self.memory_pool_entry_from(&view, transaction, *arrival_time)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the annotated entries of the memory pool, in order of arrival, starting at the given offset,
+    /// and up to the given limit.
+    ///
+    /// The memory pool is copied under a single (short) lock, so that every page entry is annotated
+    /// from the same view, while the memory pool remains available to the other callers.
+    pub fn memory_pool_snapshot(&self, offset: usize, limit: usize) -> Result<MemoryPoolSnapshot<N>> {
+        let view = self.memory_pool_view()?;
+        let entries = view
+            .transactions
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(transaction, arrival_time)| self.memory_pool_entry_from(&view, transaction, *arrival_time))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(MemoryPoolSnapshot { latest_height: view.latest_height, num_transactions: view.transactions.len(), entries })
+    }
+
+    /// Returns the number of blocks between the oldest global state root of the given transaction
+    /// and the given height, or `None` if one of its global state roots is not in the ledger.
+    pub(crate) fn state_root_age(&self, transaction: &Transaction<N>, latest_height: u32) -> Result<Option<u32>> {
+        let mut state_root_age = Some(0);
+        for state_root in global_state_roots(transaction) {
+            state_root_age = match self.ledger.find_block_height_from_state_root(state_root)? {
+                Some(height) => state_root_age.map(|age: u32| age.max(latest_height.saturating_sub(height))),
+                None => None,
+            };
+        }
+        Ok(state_root_age)
+    }
+
+    /// Returns a view of the memory pool.
+    fn memory_pool_view(&self) -> Result<MemoryPoolView<N>> {
+        // Retrieve the block template first, so that it is built from a memory pool no newer than the view.
+        let template = self.block_template()?;
+        let latest_height = self.ledger.latest_height();
+        // Copy the unconfirmed transactions, and order them by arrival.
+        let mut transactions = self.memory_pool.unconfirmed_transactions_with_arrival_times();
+        transactions.sort_by_cached_key(|(transaction, arrival_time)| (*arrival_time, transaction.id().to_string()));
+        Ok(MemoryPoolView { latest_height, transactions, selected: template.transactions.iter().copied().collect() })
+    }
+
+    /// Returns the annotated entry of the given transaction, from the given view of the memory pool.
+    fn memory_pool_entry_from(
+        &self,
+        view: &MemoryPoolView<N>,
+        transaction: &Transaction<N>,
+        arrival_time: i64,
+    ) -> Result<MemoryPoolEntry<N>> {
+        let transaction_id = transaction.id();
+        let fee = *transaction.fee()?;
+        let size = transaction.to_bytes_le()?.len();
+
+        // Find the related transactions in the memory pool.
+        let others = || view.transactions.iter().map(|(other, _)| other).filter(|other| other.id() != transaction_id);
+        let parents = others().filter(|other| depends_on(transaction, other)).map(Transaction::id).collect();
+        let children = others().filter(|other| depends_on(other, transaction)).map(Transaction::id).collect();
+        let conflicts = others().filter(|other| conflicts_with(transaction, other)).map(Transaction::id).collect();
+
+        // Compute the number of blocks since the oldest global state root.
+        let state_root_age = self.state_root_age(transaction, view.latest_height)?;
+
+        Ok(MemoryPoolEntry {
+            transaction_id,
+            fee,
+            size,
+            fee_rate: fee as f64 / size as f64,
+            arrival_time,
+            parents,
+            children,
+            conflicts,
+            state_root_age,
+            in_block_template: view.selected.contains(&transaction_id),
+        })
+    }
+}
@@ -0,0 +1,673 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{BatchWriter, Consensus, PrecheckFailure};
+use snarkvm::prelude::{Block, ConsensusStorage, Network, ToBytes};
+
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+
+use ::time::OffsetDateTime;
+use anyhow::Result;
+use core::{fmt, time::Duration};
+use indexmap::IndexMap;
+use parking_lot::{Mutex, RwLock};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::{sync::Notify, time::Instant};
+
+/// The maximum number of submitted blocks that are remembered, beyond which the earliest accepted are forgotten.
+pub const MAX_SUBMITTED_BLOCKS: usize = 4_096;
+/// The maximum number of asynchronous submissions that are remembered, beyond which the earliest completed are dropped.
+pub const MAX_ASYNC_SUBMISSIONS: usize = 1_024;
+/// The maximum number of pending asynchronous submissions, beyond which new submissions are rejected.
+pub const MAX_PENDING_SUBMISSIONS: usize = 32;
+
+/// The hashes of the submitted blocks, by height and coinbase commitment.
+type CommitmentIndex<N> = HashMap<(u32, [u8; 32]), <N as Network>::BlockHash>;
+/// A pre-checked block, queued for validation under the given submission ID, with its coinbase commitment and source.
+type SubmissionJob<N> = (u64, Block<N>, Option<[u8; 32]>, SubmissionSource);
+
+/// The source of a block submission.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SubmissionSource {
+    /// The internal miner of the node.
+    Miner,
+    /// A block submitted through the REST API.
+    Rest,
+}
+
+impl SubmissionSource {
+    /// Returns the label of the source, as it is reported in the metrics.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Miner => "miner",
+            Self::Rest => "rest",
+        }
+    }
+}
+
+impl fmt::Display for SubmissionSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A block that was submitted and committed to the canonical chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubmittedBlock {
+    /// The height of the block.
+    pub height: u32,
+    /// The commitment to the coinbase of the block, if it has a coinbase (see `coinbase_commitment`).
+    pub coinbase_commitment: Option<[u8; 32]>,
+    /// The UNIX timestamp (in seconds) at which the block was first submitted.
+    pub first_seen: i64,
+}
+
+/// The outcome of a block submission.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmissionOutcome<N: Network> {
+    /// The block was validated and committed to the canonical chain.
+    Accepted,
+    /// The block, or a block with the same coinbase at the same height, was already committed,
+    /// so the submission was short-circuited without validating the block.
+    Duplicate {
+        /// The hash of the block that was committed first.
+        block_hash: N::BlockHash,
+        /// The height of the block that was committed first.
+        height: u32,
+    },
+    /// The block failed the pre-checks, so its proofs were not verified.
+    PrecheckFailed(PrecheckFailure<N>),
+    /// The block is not a valid next block, for the given reason.
+    Rejected(String),
+}
+
+impl<N: Network> SubmissionOutcome<N> {
+    /// Returns the status of the outcome, as it is reported by the REST API.
+    pub const fn status(&self) -> &'static str {
+        match self {
+            Self::Accepted => "accepted",
+            Self::Duplicate { .. } => "duplicate",
+            Self::PrecheckFailed(_) => "precheck_failed",
+            Self::Rejected(_) => "rejected",
+        }
+    }
+}
+
+/// The receipt of an asynchronous block submission.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmissionReceipt<N: Network> {
+    /// The block passed the pre-checks, and is validated in the background under the given submission ID.
+    Pending(u64),
+    /// The submission completed before the proofs of the block were verified, as the block is a duplicate,
+    /// or failed the pre-checks.
+    Completed(SubmissionOutcome<N>),
+    /// The block passed the pre-checks, but was not queued for validation, as the given number of submissions
+    /// are pending already, which is the capacity.
+    Busy { num_pending: usize, capacity: usize },
+}
+
+/// The status of an asynchronous block submission.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubmissionStatus<N: Network> {
+    /// The block is being validated.
+    Pending { block_hash: N::BlockHash },
+    /// The validation of the block completed with the given outcome.
+    Completed { block_hash: N::BlockHash, outcome: SubmissionOutcome<N> },
+}
+
+impl<N: Network> SubmissionStatus<N> {
+    /// Returns the hash of the submitted block.
+    pub const fn block_hash(&self) -> &N::BlockHash {
+        match self {
+            Self::Pending { block_hash } | Self::Completed { block_hash, .. } => block_hash,
+        }
+    }
+
+    /// Returns `true` if the validation of the block completed.
+    pub const fn is_completed(&self) -> bool {
+        matches!(self, Self::Completed { .. })
+    }
+}
+
+/// The queue of the pre-checked blocks, which are validated in order by a single worker.
+struct SubmissionQueue<N: Network> {
+    /// The queued blocks, in the order they were submitted.
+    jobs: VecDeque<SubmissionJob<N>>,
+    /// Whether the worker is running.
+    is_running: bool,
+}
+
+impl<N: Network> Default for SubmissionQueue<N> {
+    /// Initializes an empty queue, without a worker.
+    fn default() -> Self {
+        Self { jobs: Default::default(), is_running: false }
+    }
+}
+
+/// The bounded registry of the asynchronous block submissions, whose status is polled (or awaited) by their ID.
+///
+/// The submissions are kept in memory, in the order they were registered, and the earliest completed submissions
+/// are forgotten beyond the capacity, so a pending submission is never forgotten. At most `MAX_PENDING_SUBMISSIONS`
+/// submissions are pending at once, and their blocks are validated in order by a single worker thread, which exits
+/// once the queue is drained.
+#[derive(Clone)]
+pub struct AsyncSubmissions<N: Network> {
+    /// The ID of the next submission.
+    next_id: Arc<AtomicU64>,
+    /// The status of the submissions, by ID.
+    statuses: Arc<Mutex<IndexMap<u64, SubmissionStatus<N>>>>,
+    /// The notification of the waiters, once a submission completes.
+    completed: Arc<Notify>,
+    /// The queue of the pending submissions.
+    queue: Arc<Mutex<SubmissionQueue<N>>>,
+}
+
+impl<N: Network> Default for AsyncSubmissions<N> {
+    /// Initializes an empty registry of submissions.
+    fn default() -> Self {
+        Self {
+            next_id: Arc::new(AtomicU64::new(1)),
+            statuses: Default::default(),
+            completed: Default::default(),
+            queue: Default::default(),
+        }
+    }
+}
+
+impl<N: Network> AsyncSubmissions<N> {
+    /// Returns the status of the submission with the given ID, if it is known.
+    pub fn get(&self, id: u64) -> Option<SubmissionStatus<N>> {
+        self.statuses.lock().get(&id).cloned()
+    }
+
+    /// Returns the number of pending submissions.
+    pub fn num_pending(&self) -> usize {
+        self.statuses.lock().values().filter(|status| !status.is_completed()).count()
+    }
+
+    /// Waits until the submission with the given ID completes, or the given timeout elapses, without blocking
+    /// the thread, and returns its status, if it is known.
+    pub async fn wait(&self, id: u64, timeout: Duration) -> Option<SubmissionStatus<N>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Register for the notification before checking the status, so that no completion is missed.
+            let completed = self.completed.notified();
+            match self.get(id) {
+                Some(SubmissionStatus::Pending { .. }) => {
+                    if tokio::time::timeout_at(deadline, completed).await.is_err() {
+                        return self.get(id);
+                    }
+                }
+                status => return status,
+            }
+        }
+    }
+
+    /// Registers a pending submission of the block with the given hash, and returns its ID,
+    /// or the number of pending submissions if it reached the capacity.
+    fn register(&self, block_hash: N::BlockHash) -> Result<u64, usize> {
+        let mut statuses = self.statuses.lock();
+        let num_pending = statuses.values().filter(|status| !status.is_completed()).count();
+        if num_pending >= MAX_PENDING_SUBMISSIONS {
+            return Err(num_pending);
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        statuses.insert(id, SubmissionStatus::Pending { block_hash });
+        // Forget the earliest completed submissions beyond the capacity.
+        let mut num_evicted = statuses.len().saturating_sub(MAX_ASYNC_SUBMISSIONS);
+        statuses.retain(|_, status| match status.is_completed() && num_evicted > 0 {
+            true => {
+                num_evicted -= 1;
+                false
+            }
+            false => true,
+        });
+        Ok(id)
+    }
+
+    /// Queues the given job, and returns `true` if a worker must be started to process it.
+    fn enqueue(&self, job: SubmissionJob<N>) -> bool {
+        let mut queue = self.queue.lock();
+        queue.jobs.push_back(job);
+        !std::mem::replace(&mut queue.is_running, true)
+    }
+
+    /// Returns the next queued job, or `None` if the queue is drained, in which case the worker must exit.
+    fn next_job(&self) -> Option<SubmissionJob<N>> {
+        let mut queue = self.queue.lock();
+        let job = queue.jobs.pop_front();
+        queue.is_running = job.is_some();
+        job
+    }
+
+    /// Completes the submission with the given ID with the given outcome, and notifies its waiters.
+    fn complete(&self, id: u64, outcome: SubmissionOutcome<N>) {
+        let mut statuses = self.statuses.lock();
+        if let Some(status) = statuses.get_mut(&id) {
+            *status = SubmissionStatus::Completed { block_hash: *status.block_hash(), outcome };
+        }
+        drop(statuses);
+        self.completed.notify_waiters();
+    }
+}
+
+/// Returns the commitment to the coinbase of the given block, which is the SHA-256 digest of its previous block hash,
+/// its coinbase solution, and the IDs of its coinbase transactions, or `None` if the block has no coinbase.
+///
+/// Two blocks with the same commitment at the same height commit the same mined work, even if they differ in the
+/// extra nonce of their header, so a block that repeats the commitment of a committed block is a duplicate.
+pub fn coinbase_commitment<N: Network>(block: &Block<N>) -> Result<Option<[u8; 32]>> {
+    let coinbase_ids =
+        block.transactions().iter().filter(|transaction| transaction.is_coinbase()).map(|transaction| transaction.id());
+    let coinbase_ids = coinbase_ids.collect::<Vec<_>>();
+    if block.coinbase().is_none() && coinbase_ids.is_empty() {
+        return Ok(None);
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(block.previous_hash().to_bytes_le()?);
+    if let Some(coinbase) = block.coinbase() {
+        hasher.update(coinbase.to_bytes_le()?);
+    }
+    for transaction_id in coinbase_ids {
+        hasher.update(transaction_id.to_bytes_le()?);
+    }
+    Ok(Some(hasher.finalize().into()))
+}
+
+/// A storage of the submitted blocks.
+pub trait SubmittedBlockStorage<N: Network>: BatchWriter<N::BlockHash, SubmittedBlock> {
+    /// Returns the persisted submitted blocks.
+    fn submitted_blocks(&self) -> Result<Vec<(N::BlockHash, SubmittedBlock)>>;
+}
+
+/// The bounded cache of the blocks that were submitted by the internal miner or through the REST API and committed,
+/// which is consulted before any validation work, so that a block that is submitted again is reported as a duplicate
+/// without verifying its proofs again.
+///
+/// Each block is indexed by its hash, and by its height and coinbase commitment. The cache is kept in memory,
+/// and written through to its storage (if any), so it survives a restart. The blocks above the tip are forgotten
+/// when the canonical chain is rolled back, so that a block of an abandoned branch can be submitted again.
+#[derive(Clone)]
+pub struct SubmittedBlocks<N: Network> {
+    /// The submitted blocks, in the order they were committed.
+    blocks: Arc<RwLock<IndexMap<N::BlockHash, SubmittedBlock>>>,
+    /// The hash of the submitted block of each height and coinbase commitment.
+    commitments: Arc<RwLock<CommitmentIndex<N>>>,
+    /// The number of duplicate submissions from each source.
+    duplicates: Arc<RwLock<IndexMap<SubmissionSource, u64>>>,
+    /// The storage of the submitted blocks, if they are persisted.
+    storage: Arc<RwLock<Option<Arc<dyn SubmittedBlockStorage<N>>>>>,
+}
+
+impl<N: Network> Default for SubmittedBlocks<N> {
+    /// Initializes an empty cache of submitted blocks, which is not persisted.
+    fn default() -> Self {
+        Self {
+            blocks: Default::default(),
+            commitments: Default::default(),
+            duplicates: Default::default(),
+            storage: Default::default(),
+        }
+    }
+}
+
+impl<N: Network> SubmittedBlocks<N> {
+    /// Persists the submitted blocks to the given storage from then on, and restores the persisted submitted blocks.
+    /// Returns the number of restored blocks.
+    pub fn set_storage(&self, storage: Arc<dyn SubmittedBlockStorage<N>>) -> Result<usize> {
+        // Restore the persisted blocks, in the order they were first seen.
+        let persisted = storage.submitted_blocks()?;
+        let mut blocks = self.blocks.write();
+        blocks.extend(persisted);
+        blocks.sort_by(|_, a, _, b| a.first_seen.cmp(&b.first_seen));
+        // Forget the earliest seen blocks beyond the capacity.
+        let evicted = Self::evict(&mut blocks);
+        *self.commitments.write() = Self::index(&blocks);
+        *self.storage.write() = Some(storage.clone());
+        drop(blocks);
+
+        storage.write_batch(&evicted.into_iter().map(|hash| (hash, None)).collect::<Vec<_>>())?;
+        Ok(self.blocks.read().len())
+    }
+
+    /// Returns the submitted block with the given hash, if it is known.
+    pub fn get(&self, hash: &N::BlockHash) -> Option<SubmittedBlock> {
+        self.blocks.read().get(hash).cloned()
+    }
+
+    /// Returns `true` if the given block was submitted and committed.
+    pub fn contains(&self, hash: &N::BlockHash) -> bool {
+        self.blocks.read().contains_key(hash)
+    }
+
+    /// Returns the number of known submitted blocks.
+    pub fn len(&self) -> usize {
+        self.blocks.read().len()
+    }
+
+    /// Returns `true` if no submitted block is known.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.read().is_empty()
+    }
+
+    /// Returns the number of duplicate submissions from the given source.
+    pub fn num_duplicates(&self, source: SubmissionSource) -> u64 {
+        self.duplicates.read().get(&source).copied().unwrap_or_default()
+    }
+
+    /// Returns the hash and height of the submitted block that the given block duplicates, if any, which is the block
+    /// itself, or a block with the same coinbase commitment at the same height.
+    pub fn find_duplicate(
+        &self,
+        block: &Block<N>,
+        coinbase_commitment: Option<[u8; 32]>,
+    ) -> Option<(N::BlockHash, u32)> {
+        if let Some(submitted) = self.blocks.read().get(&block.hash()) {
+            return Some((block.hash(), submitted.height));
+        }
+        let commitment = coinbase_commitment?;
+        self.commitments.read().get(&(block.height(), commitment)).map(|hash| (*hash, block.height()))
+    }
+
+    /// Counts a duplicate submission from the given source.
+    pub fn record_duplicate(&self, source: SubmissionSource) {
+        *self.duplicates.write().entry(source).or_default() += 1;
+        #[cfg(feature = "metrics")]
+        metrics::increment_counter!(metrics::miner::DUPLICATE_SUBMISSIONS, "source" => source.as_str());
+    }
+
+    /// Records the given block, with the given coinbase commitment, as submitted and committed.
+    pub fn insert(&self, block: &Block<N>, coinbase_commitment: Option<[u8; 32]>) -> Result<()> {
+        let hash = block.hash();
+        let mut blocks = self.blocks.write();
+        if blocks.contains_key(&hash) {
+            return Ok(());
+        }
+        let submitted = SubmittedBlock {
+            height: block.height(),
+            coinbase_commitment,
+            first_seen: OffsetDateTime::now_utc().unix_timestamp(),
+        };
+        blocks.insert(hash, submitted.clone());
+        let evicted = Self::evict(&mut blocks);
+        drop(blocks);
+
+        // Index the block by its coinbase commitment, and remove the evicted blocks from the index.
+        let mut commitments = self.commitments.write();
+        if let Some(commitment) = coinbase_commitment {
+            commitments.insert((submitted.height, commitment), hash);
+        }
+        commitments.retain(|_, hash| !evicted.contains(hash));
+        drop(commitments);
+
+        // Write the block, and the removal of the evicted blocks, through to the storage.
+        let mut batch = vec![(hash, Some(submitted))];
+        batch.extend(evicted.into_iter().map(|hash| (hash, None)));
+        self.write_batch(&batch)
+    }
+
+    /// Forgets the submitted blocks above the given height, which are no longer in the canonical chain,
+    /// and returns the number of forgotten blocks.
+    pub fn forget_above(&self, height: u32) -> Result<usize> {
+        let mut blocks = self.blocks.write();
+        let forgotten =
+            blocks.iter().filter(|(_, block)| block.height > height).map(|(hash, _)| *hash).collect::<Vec<_>>();
+        if forgotten.is_empty() {
+            return Ok(0);
+        }
+        blocks.retain(|_, block| block.height <= height);
+        *self.commitments.write() = Self::index(&blocks);
+        drop(blocks);
+
+        self.write_batch(&forgotten.iter().map(|hash| (*hash, None)).collect::<Vec<_>>())?;
+        Ok(forgotten.len())
+    }
+
+    /// Removes the earliest seen blocks beyond the capacity, and returns their hashes.
+    fn evict(blocks: &mut IndexMap<N::BlockHash, SubmittedBlock>) -> Vec<N::BlockHash> {
+        let num_evicted = blocks.len().saturating_sub(MAX_SUBMITTED_BLOCKS);
+        blocks.drain(..num_evicted).map(|(hash, _)| hash).collect()
+    }
+
+    /// Returns the index of the given blocks by height and coinbase commitment.
+    fn index(blocks: &IndexMap<N::BlockHash, SubmittedBlock>) -> CommitmentIndex<N> {
+        blocks
+            .iter()
+            .filter_map(|(hash, block)| block.coinbase_commitment.map(|commitment| ((block.height, commitment), *hash)))
+            .collect()
+    }
+
+    /// Writes the given batch to the storage, if the submitted blocks are persisted.
+    fn write_batch(&self, batch: &[(N::BlockHash, Option<SubmittedBlock>)]) -> Result<()> {
+        match (self.storage.read().as_ref(), batch.is_empty()) {
+            (Some(storage), false) => storage.write_batch(batch),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the asynchronous block submissions.
+    pub const fn async_submissions(&self) -> &AsyncSubmissions<N> {
+        &self.async_submissions
+    }
+
+    /// Submits the given block from the given source, which is pre-checked, then validated and committed as the next
+    /// block, unless it duplicates a submitted block, in which case it is reported as a duplicate without validation.
+    ///
+    /// A block that is not a valid next block is rejected, and an error is returned if the commit fails.
+    pub fn submit_block(&self, block: &Block<N>, source: SubmissionSource) -> Result<SubmissionOutcome<N>> {
+        match self.precheck_submission(block, source)? {
+            Ok(coinbase_commitment) => self.complete_submission(block, coinbase_commitment),
+            Err(outcome) => Ok(outcome),
+        }
+    }
+
+    /// Submits the given block from the given source, and returns once it is pre-checked, with the outcome of the
+    /// submission if the block is a duplicate or failed the pre-checks. Otherwise, the block is queued to be validated
+    /// and committed in the background, and the returned submission ID is polled (or awaited) for its outcome
+    /// (see `AsyncSubmissions`), unless the maximum number of submissions are pending already.
+    pub fn submit_block_async(&self, block: Block<N>, source: SubmissionSource) -> Result<SubmissionReceipt<N>> {
+        let coinbase_commitment = match self.precheck_submission(&block, source)? {
+            Ok(coinbase_commitment) => coinbase_commitment,
+            Err(outcome) => return Ok(SubmissionReceipt::Completed(outcome)),
+        };
+
+        // Register the submission, unless the maximum number of submissions are pending.
+        let id = match self.async_submissions.register(block.hash()) {
+            Ok(id) => id,
+            Err(num_pending) => {
+                debug!(
+                    "Block {} ('{}') from the {source} was not queued - {num_pending} pending",
+                    block.height(),
+                    block.hash()
+                );
+                return Ok(SubmissionReceipt::Busy { num_pending, capacity: MAX_PENDING_SUBMISSIONS });
+            }
+        };
+
+        // Queue the block, and start the worker that validates and commits the queued blocks, if it is not running.
+        if self.async_submissions.enqueue((id, block, coinbase_commitment, source)) {
+            let consensus = self.clone();
+            std::thread::spawn(move || consensus.process_submissions());
+        }
+        Ok(SubmissionReceipt::Pending(id))
+    }
+
+    /// Validates and commits the queued blocks in order, until the queue is drained.
+    fn process_submissions(&self) {
+        while let Some((id, block, coinbase_commitment, source)) = self.async_submissions.next_job() {
+            let outcome = self.complete_submission(&block, coinbase_commitment).unwrap_or_else(|error| {
+                warn!("Failed to commit the submitted block {} ('{}') - {error}", block.height(), block.hash());
+                SubmissionOutcome::Rejected(format!("Failed to commit the block: {error}"))
+            });
+            debug!("Completed the submission {id} of block {} from the {source}: {}", block.height(), outcome.status());
+            self.async_submissions.complete(id, outcome);
+        }
+    }
+
+    /// Short-circuits the submission of a duplicate block, and runs the pre-checks of the block otherwise.
+    /// Returns the coinbase commitment of the block if it passed, and the outcome of the submission otherwise.
+    fn precheck_submission(
+        &self,
+        block: &Block<N>,
+        source: SubmissionSource,
+    ) -> Result<Result<Option<[u8; 32]>, SubmissionOutcome<N>>> {
+        // Short-circuit the submission of a block that was already submitted.
+        let coinbase_commitment = coinbase_commitment(block)?;
+        if let Some((block_hash, height)) = self.submitted_blocks.find_duplicate(block, coinbase_commitment) {
+            self.submitted_blocks.record_duplicate(source);
+            debug!("Block {} ('{}') from the {source} duplicates block '{block_hash}'", block.height(), block.hash());
+            return Ok(Err(SubmissionOutcome::Duplicate { block_hash, height }));
+        }
+
+        // Run the pre-checks, before any proof is verified.
+        if let Some(failure) = self.precheck_block(block)? {
+            debug!("Block {} ('{}') from the {source} failed the pre-checks - {failure}", block.height(), block.hash());
+            return Ok(Err(SubmissionOutcome::PrecheckFailed(failure)));
+        }
+        Ok(Ok(coinbase_commitment))
+    }
+
+    /// Validates the given pre-checked block, and commits it as the next block if it is valid.
+    fn complete_submission(
+        &self,
+        block: &Block<N>,
+        coinbase_commitment: Option<[u8; 32]>,
+    ) -> Result<SubmissionOutcome<N>> {
+        // Validate the block.
+        if let Err(error) = self.check_next_block(block) {
+            return Ok(SubmissionOutcome::Rejected(error.to_string()));
+        }
+        // Commit the block, and record it as submitted.
+        self.advance_to_next_block(block)?;
+        self.submitted_blocks.insert(block, coinbase_commitment)?;
+        Ok(SubmissionOutcome::Accepted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::block_on;
+    use snarkvm::prelude::FromBytes;
+
+    use parking_lot::Mutex;
+
+    type CurrentNetwork = snarkvm::prelude::Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// A storage of the submitted blocks in memory.
+    #[derive(Default)]
+    struct MemoryStorage(Mutex<HashMap<BlockHash, SubmittedBlock>>);
+
+    impl BatchWriter<BlockHash, SubmittedBlock> for MemoryStorage {
+        fn write_batch(&self, batch: &[(BlockHash, Option<SubmittedBlock>)]) -> Result<()> {
+            let mut blocks = self.0.lock();
+            for (hash, block) in batch {
+                match block {
+                    Some(block) => blocks.insert(*hash, block.clone()),
+                    None => blocks.remove(hash),
+                };
+            }
+            Ok(())
+        }
+    }
+
+    impl SubmittedBlockStorage<CurrentNetwork> for MemoryStorage {
+        fn submitted_blocks(&self) -> Result<Vec<(BlockHash, SubmittedBlock)>> {
+            Ok(self.0.lock().iter().map(|(hash, block)| (*hash, block.clone())).collect())
+        }
+    }
+
+    #[test]
+    fn test_submitted_blocks() {
+        let genesis = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let commitment = coinbase_commitment(&genesis).unwrap();
+        let storage = Arc::new(MemoryStorage::default());
+
+        // Record a block, and ensure it is found as a duplicate of itself.
+        let blocks = SubmittedBlocks::<CurrentNetwork>::default();
+        assert_eq!(blocks.set_storage(storage.clone()).unwrap(), 0);
+        assert_eq!(blocks.find_duplicate(&genesis, commitment), None);
+        blocks.insert(&genesis, commitment).unwrap();
+        assert_eq!(blocks.find_duplicate(&genesis, commitment), Some((genesis.hash(), 0)));
+
+        // Ensure the duplicates are counted per source.
+        blocks.record_duplicate(SubmissionSource::Rest);
+        assert_eq!(blocks.num_duplicates(SubmissionSource::Rest), 1);
+        assert_eq!(blocks.num_duplicates(SubmissionSource::Miner), 0);
+
+        // Ensure the submitted blocks are restored from the storage.
+        let restored = SubmittedBlocks::<CurrentNetwork>::default();
+        assert_eq!(restored.set_storage(storage.clone()).unwrap(), 1);
+        assert_eq!(restored.get(&genesis.hash()), blocks.get(&genesis.hash()));
+
+        // Ensure only the blocks above the given height are forgotten.
+        assert_eq!(restored.forget_above(0).unwrap(), 0);
+        assert!(restored.contains(&genesis.hash()));
+    }
+
+    #[test]
+    fn test_async_submissions() {
+        let genesis = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let submissions = AsyncSubmissions::<CurrentNetwork>::default();
+
+        // Ensure a pending submission is reported as pending, once the timeout elapses.
+        let id = submissions.register(genesis.hash()).unwrap();
+        let pending = SubmissionStatus::Pending { block_hash: genesis.hash() };
+        assert_eq!(block_on(submissions.wait(id, Duration::from_millis(10))), Some(pending));
+        assert_eq!(submissions.num_pending(), 1);
+
+        // Ensure a waiter is woken up once the submission completes.
+        let submissions_ = submissions.clone();
+        let waiter = std::thread::spawn(move || block_on(submissions_.wait(id, Duration::from_secs(60))));
+        submissions.complete(id, SubmissionOutcome::Accepted);
+        let completed =
+            SubmissionStatus::Completed { block_hash: genesis.hash(), outcome: SubmissionOutcome::Accepted };
+        assert_eq!(waiter.join().unwrap(), Some(completed.clone()));
+        assert_eq!(submissions.get(id), Some(completed));
+        assert_eq!(block_on(submissions.wait(id + 1, Duration::ZERO)), None);
+
+        // Ensure the earliest completed submissions are forgotten beyond the capacity, unlike the pending ones.
+        let pending = submissions.register(genesis.hash()).unwrap();
+        for _ in 0..MAX_ASYNC_SUBMISSIONS {
+            let id = submissions.register(genesis.hash()).unwrap();
+            submissions.complete(id, SubmissionOutcome::Accepted);
+        }
+        assert_eq!(submissions.get(id), None);
+        assert!(submissions.get(pending).is_some());
+        assert_eq!(submissions.num_pending(), 1);
+        assert_eq!(submissions.statuses.lock().len(), MAX_ASYNC_SUBMISSIONS);
+
+        // Ensure a submission is rejected once the maximum number of submissions are pending.
+        for _ in 1..MAX_PENDING_SUBMISSIONS {
+            submissions.register(genesis.hash()).unwrap();
+        }
+        assert_eq!(submissions.register(genesis.hash()), Err(MAX_PENDING_SUBMISSIONS));
+        submissions.complete(pending, SubmissionOutcome::Accepted);
+        assert!(submissions.register(genesis.hash()).is_ok());
+    }
+}
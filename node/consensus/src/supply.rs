@@ -0,0 +1,123 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{coinbase_amount, BlockRejection};
+use snarkvm::prelude::{Network, Transaction};
+
+use anyhow::Result;
+
+/// Returns the sum of the given amounts (in microcredits), or `None` if the sum overflows.
+pub fn checked_sum(amounts: impl IntoIterator<Item = u64>) -> Option<u64> {
+    amounts.into_iter().try_fold(0u64, |sum, amount| sum.checked_add(amount))
+}
+
+/// Returns the sum of the fees of the given transactions (in microcredits).
+///
+/// If the sum overflows, the transactions are rejected with `BlockRejection::FeeOverflow`.
+pub fn checked_fees<'a, N: Network>(transactions: impl IntoIterator<Item = &'a Transaction<N>>) -> Result<u64> {
+    let fees = transactions.into_iter().map(|transaction| Ok(*transaction.fee()?)).collect::<Result<Vec<_>>>()?;
+    checked_sum(fees).ok_or_else(|| BlockRejection::<N>::FeeOverflow.into())
+}
+
+/// Returns the given total supply (in microcredits), after burning the given fee and minting the given amount
+/// of the given transaction.
+///
+/// If the fee exceeds the total supply, or the minted amount overflows it, the transaction is rejected
+/// with `BlockRejection::SupplyUnderflow` or `BlockRejection::SupplyOverflow` respectively.
+pub fn checked_total_supply<N: Network>(
+    total_supply: u64,
+    transaction_id: N::TransactionID,
+    fee: u64,
+    minted: u64,
+) -> Result<u64, BlockRejection<N>> {
+    // Subtract the fee from the total supply.
+    let total_supply = match total_supply.checked_sub(fee) {
+        Some(total_supply) => total_supply,
+        None => return Err(BlockRejection::SupplyUnderflow { transaction_id, fee, total_supply }),
+    };
+    // Add the minted amount to the total supply.
+    match total_supply.checked_add(minted) {
+        Some(total_supply) => Ok(total_supply),
+        None => Err(BlockRejection::SupplyOverflow { transaction_id, minted, total_supply }),
+    }
+}
+
+/// Returns the total supply (in microcredits) after the given transactions, i.e. the given total supply
+/// without the fees of the transactions, and with the amounts minted by the coinbase transactions.
+pub fn next_total_supply<'a, N: Network>(
+    total_supply: u64,
+    transactions: impl IntoIterator<Item = &'a Transaction<N>>,
+) -> Result<u64> {
+    let mut total_supply = total_supply;
+    for transaction in transactions {
+        // Retrieve the fee, and the amount minted by the transaction, if it is a coinbase.
+        let fee = *transaction.fee()?;
+        let minted = match transaction.is_coinbase() {
+            true => coinbase_amount(transaction)?,
+            false => 0,
+        };
+        total_supply = checked_total_supply::<N>(total_supply, transaction.id(), fee, minted)?;
+    }
+    Ok(total_supply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Field, TestRng, Testnet3, Uniform};
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_checked_sum() {
+        assert_eq!(checked_sum([]), Some(0));
+        assert_eq!(checked_sum([1, 2, 3]), Some(6));
+        assert_eq!(checked_sum([u64::MAX]), Some(u64::MAX));
+        assert_eq!(checked_sum([u64::MAX - 1, 1]), Some(u64::MAX));
+
+        // Ensure the fees of a block that sum past the maximum are rejected, rather than wrapped.
+        assert_eq!(checked_sum([u64::MAX, 1]), None);
+        assert_eq!(checked_sum([u64::MAX / 2 + 1, u64::MAX / 2 + 1]), None);
+        assert_eq!(checked_sum(vec![CurrentNetwork::STARTING_SUPPLY; 1 << 20]), None);
+    }
+
+    #[test]
+    fn test_checked_total_supply() {
+        let rng = &mut TestRng::default();
+        let transaction_id = Field::<CurrentNetwork>::rand(rng).into();
+        let total_supply = CurrentNetwork::STARTING_SUPPLY;
+
+        assert_eq!(checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, 0, 0), Ok(total_supply));
+        assert_eq!(checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, 10, 0), Ok(total_supply - 10));
+        assert_eq!(checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, 10, 25), Ok(total_supply + 15));
+        assert_eq!(checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, total_supply, 0), Ok(0));
+
+        // Ensure a fee at the extreme of the range is rejected without a panic.
+        assert_eq!(
+            checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, u64::MAX, 0),
+            Err(BlockRejection::SupplyUnderflow { transaction_id, fee: u64::MAX, total_supply })
+        );
+        assert_eq!(
+            checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, u64::MAX, u64::MAX),
+            Err(BlockRejection::SupplyUnderflow { transaction_id, fee: u64::MAX, total_supply })
+        );
+        // Ensure a minted amount that overflows the total supply is rejected without a panic.
+        assert_eq!(
+            checked_total_supply::<CurrentNetwork>(total_supply, transaction_id, 1, u64::MAX),
+            Err(BlockRejection::SupplyOverflow { transaction_id, minted: u64::MAX, total_supply: total_supply - 1 })
+        );
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{checked_fees, checked_sum, coinbase_reward, CoinbaseOutput, Consensus};
+use snarkos_node_ledger::{Subscription, Topic};
+use snarkos_node_messages::HeaderCommitments;
+use snarkvm::prelude::{Block, ConsensusStorage, Network};
+
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+
+use anyhow::{anyhow, bail, Error, Result};
+use core::{fmt, str::FromStr, time::Duration};
+use std::{sync::Arc, time::Instant};
+
+/// The default increase in the fees of the memory pool (in microcredits) that makes a block template stale.
+pub const DEFAULT_TEMPLATE_FEE_DELTA: u64 = 1_000_000;
+/// The maximum number of times a block template is rebuilt, when the ledger advances while it is built.
+pub const MAX_TEMPLATE_REBUILDS: usize = 3;
+
+/// A read-only snapshot of the latest block, from which the miner builds the next block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TipSnapshot<N: Network> {
+    /// The hash of the latest block.
+    pub block_hash: N::BlockHash,
+    /// The height of the latest block.
+    pub height: u32,
+    /// The round of the latest block.
+    pub round: u64,
+    /// The coinbase target of the latest block.
+    pub coinbase_target: u64,
+    /// The proof target of the latest block.
+    pub proof_target: u64,
+    /// The timestamp of the latest block with a coinbase solution.
+    pub last_coinbase_timestamp: i64,
+}
+
+impl<N: Network> From<&Block<N>> for TipSnapshot<N> {
+    fn from(block: &Block<N>) -> Self {
+        Self {
+            block_hash: block.hash(),
+            height: block.height(),
+            round: block.round(),
+            coinbase_target: block.coinbase_target(),
+            proof_target: block.proof_target(),
+            last_coinbase_timestamp: block.last_coinbase_timestamp(),
+        }
+    }
+}
+
+/// The snapshot of the miner, with the subscription to the committed blocks that refreshes it.
+pub(crate) struct MinerSnapshot<N: Network> {
+    /// The snapshot of the latest block.
+    tip: TipSnapshot<N>,
+    /// The subscription to the connected and disconnected blocks.
+    blocks: Subscription<N>,
+}
+
+/// The identifier of the state a block template was built from, used to long-poll for a fresh template.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LongPollId<N: Network> {
+    /// The hash of the latest block, when the template was built.
+    pub block_hash: N::BlockHash,
+    /// The fees of the memory pool (in microcredits), when the template was built.
+    pub memory_pool_fees: u64,
+}
+
+impl<N: Network> FromStr for LongPollId<N> {
+    type Err = Error;
+
+    /// Parses a long-poll ID of the form `{block_hash}:{memory_pool_fees}`.
+    fn from_str(longpoll_id: &str) -> Result<Self> {
+        match longpoll_id.split_once(':') {
+            Some((block_hash, fees)) => Ok(Self {
+                block_hash: block_hash.parse().map_err(|_| anyhow!("Invalid block hash in long-poll ID"))?,
+                memory_pool_fees: fees.parse().map_err(|_| anyhow!("Invalid fees in long-poll ID"))?,
+            }),
+            None => bail!("Invalid long-poll ID '{longpoll_id}'"),
+        }
+    }
+}
+
+impl<N: Network> fmt::Display for LongPollId<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.block_hash, self.memory_pool_fees)
+    }
+}
+
+/// A template for the next block, with the transactions selected from the memory pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockTemplate<N: Network> {
+    /// The hash of the block the template builds on.
+    pub previous_block_hash: N::BlockHash,
+    /// The height of the next block.
+    pub height: u32,
+    /// The round of the next block.
+    pub round: u64,
+    /// The UNIX timestamp (in seconds) at which the template was built.
+    pub timestamp: i64,
+    /// The latest coinbase target.
+    pub coinbase_target: u64,
+    /// The latest proof target.
+    pub proof_target: u64,
+    /// The IDs of the transactions selected for the next block.
+    pub transactions: Vec<N::TransactionID>,
+    /// The fees of the selected transactions (in microcredits).
+    pub fees: u64,
+    /// The commitments of the header of the next block, from the activation of the `header_commitments` rule on.
+    pub commitments: Option<HeaderCommitments>,
+    /// The coinbase reward of the next block (in microcredits), if it includes a coinbase solution.
+    pub coinbase_reward: u64,
+    /// The outputs of the coinbase, which split the coinbase reward and the fees among the coinbase recipients,
+    /// or none if no recipient is set.
+    pub coinbase: Vec<CoinbaseOutput<N>>,
+    /// The long-poll ID of the template.
+    pub longpoll_id: LongPollId<N>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns the increase in the fees of the memory pool (in microcredits) that makes a block template stale.
+    pub fn template_fee_delta(&self) -> u64 {
+        *self.template_fee_delta.read()
+    }
+
+    /// Sets the increase in the fees of the memory pool (in microcredits) that makes a block template stale.
+    pub fn set_template_fee_delta(&self, fee_delta: u64) {
+        *self.template_fee_delta.write() = fee_delta;
+    }
+
+    /// Returns the template for the next block.
+    ///
+    /// The template is cached until it becomes stale, and is built by a single caller at a time,
+    /// so that concurrent callers share the same template instead of rebuilding it.
+    pub fn block_template(&self) -> Result<Arc<BlockTemplate<N>>> {
+        // Hold the lock while building the template, so that concurrent callers wait for it.
+        let mut template = self.block_template.lock();
+        match &*template {
+            Some(cached) if !self.is_template_stale(&cached.longpoll_id) => Ok(cached.clone()),
+            _ => {
+                let fresh = Arc::new(self.build_block_template()?);
+                *template = Some(fresh.clone());
+                Ok(fresh)
+            }
+        }
+    }
+
+    /// Waits until the template with the given long-poll ID becomes stale, or the given timeout elapses,
+    /// without blocking the thread. The (fresh) template for the next block is then returned by `block_template`.
+    pub async fn wait_for_stale_template(&self, longpoll_id: &LongPollId<N>, timeout: Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        // Subscribe to the events that may make the template stale before the first check, so that none is missed.
+        let invalidations = self.ledger.event_bus().subscribe(&[
+            Topic::BlockConnected,
+            Topic::BlockDisconnected,
+            Topic::TransactionAccepted,
+            Topic::TransactionEvicted,
+        ]);
+        while !self.is_template_stale(longpoll_id) {
+            if tokio::time::timeout_at(deadline, invalidations.recv()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Returns `true` if the template with the given long-poll ID is stale, which is the case if
+    /// the ledger advanced, or the fees of the memory pool increased by at least the fee delta.
+    fn is_template_stale(&self, longpoll_id: &LongPollId<N>) -> bool {
+        self.miner_tip().block_hash != longpoll_id.block_hash
+            || self.memory_pool.unconfirmed_fees()
+                >= longpoll_id.memory_pool_fees.saturating_add(self.template_fee_delta())
+    }
+
+    /// Returns the snapshot of the latest block, from which the miner builds the next block.
+    ///
+    /// The snapshot is only refreshed from the ledger after a block is committed, so that building
+    /// a block template does not contend with the block imports for the ledger.
+    pub fn miner_tip(&self) -> TipSnapshot<N> {
+        let mut snapshot = self.miner_snapshot.lock();
+        match snapshot.as_mut() {
+            // If no block was connected or disconnected since the snapshot was taken, the snapshot is fresh.
+            Some(snapshot) if snapshot.blocks.try_iter().count() == 0 => snapshot.tip,
+            // Otherwise, refresh the snapshot from the ledger.
+            Some(snapshot) => {
+                snapshot.tip = TipSnapshot::from(&self.ledger.latest_block());
+                snapshot.tip
+            }
+            None => {
+                // Subscribe to the blocks before the first read, so that no commit is missed.
+                let blocks = self.subscribe_to_blocks();
+                let tip = TipSnapshot::from(&self.ledger.latest_block());
+                *snapshot = Some(MinerSnapshot { tip, blocks });
+                tip
+            }
+        }
+    }
+
+    /// Returns `true` if a block was committed on top of the given snapshot.
+    pub(crate) fn is_miner_tip_stale(&self, tip: &TipSnapshot<N>) -> bool {
+        self.miner_tip().block_hash != tip.block_hash
+    }
+
+    /// Builds the template for the next block.
+    ///
+    /// If a block is committed while the template is built, the template is aborted and rebuilt
+    /// on the new snapshot, so that the template never extends a stale block.
+    fn build_block_template(&self) -> Result<BlockTemplate<N>> {
+        for _ in 0..=MAX_TEMPLATE_REBUILDS {
+            let timer = Instant::now();
+            // Retrieve the snapshot, and build the template on it.
+            let tip = self.miner_tip();
+            let template = self.build_block_template_on(&tip)?;
+            // If a block was committed in the meantime, abort the template.
+            match self.is_miner_tip_stale(&tip) {
+                true => {
+                    debug!("Aborted the block template at height {}, as the ledger advanced", template.height);
+                    #[cfg(feature = "metrics")]
+                    metrics::increment_counter!(metrics::miner::TEMPLATE_ABORTS);
+                }
+                false => {
+                    debug!(
+                        "Built a block template at height {} with {} transactions in {:?}",
+                        template.height,
+                        template.transactions.len(),
+                        timer.elapsed()
+                    );
+                    #[cfg(feature = "metrics")]
+                    metrics::histogram!(metrics::miner::TEMPLATE_BUILD_DURATION, timer.elapsed().as_secs_f64());
+                    return Ok(template);
+                }
+            }
+        }
+        bail!("The ledger advanced during each of the {} attempts to build a block template", MAX_TEMPLATE_REBUILDS + 1)
+    }
+
+    /// Builds the template for the next block on the given snapshot, and a view of the memory pool.
+    pub(crate) fn build_block_template_on(&self, tip: &TipSnapshot<N>) -> Result<BlockTemplate<N>> {
+        // Retrieve the fees before selecting the transactions, so that any later change makes the template stale.
+        let memory_pool_fees = self.memory_pool.unconfirmed_fees();
+
+        // Select the transactions from the memory pool.
+        let transactions = self.memory_pool.candidate_transactions(self);
+        let fees = checked_fees(&transactions)?;
+
+        let height = tip.height.saturating_add(1);
+        let timestamp = self.ledger.network_time().now();
+        // Compute the coinbase reward, and split it, along with the fees, among the coinbase recipients.
+        let coinbase_reward =
+            coinbase_reward(tip.last_coinbase_timestamp, timestamp, height, N::STARTING_SUPPLY, N::ANCHOR_TIME)?;
+        let coinbase = match self.coinbase_recipients() {
+            Some(recipients) => {
+                let amount = checked_sum([coinbase_reward, fees]).ok_or_else(|| anyhow!("The coinbase overflowed"))?;
+                recipients.split(amount)
+            }
+            None => vec![],
+        };
+        // Commit to the number of transactions and the fees in the header, once the rule is active.
+        // The coinbase transaction, which pays no fee, is counted if the template has coinbase outputs.
+        let commitments = self.commitments_at(height, &transactions)?.map(|commitments| HeaderCommitments {
+            num_transactions: commitments.num_transactions.saturating_add(u32::from(!coinbase.is_empty())),
+            ..commitments
+        });
+
+        Ok(BlockTemplate {
+            previous_block_hash: tip.block_hash,
+            height,
+            round: tip.round.saturating_add(1),
+            timestamp,
+            coinbase_target: tip.coinbase_target,
+            proof_target: tip.proof_target,
+            transactions: transactions.iter().map(|transaction| transaction.id()).collect(),
+            fees,
+            commitments,
+            coinbase_reward,
+            coinbase,
+            longpoll_id: LongPollId { block_hash: tip.block_hash, memory_pool_fees },
+        })
+    }
+}
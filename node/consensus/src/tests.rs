@@ -14,28 +14,62 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use snarkos_node_ledger::{Ledger, RecordsFilter};
+use snarkos_node_ledger::{
+    Event,
+    EvictedTransaction,
+    Ledger,
+    RecordsFilter,
+    Subscription,
+    Topic,
+    MAX_SIDE_BLOCKS_PER_HEIGHT,
+};
+use snarkos_node_messages::{CommittedHeader, HeaderCommitments};
 use snarkvm::{
     console::{
         account::{Address, PrivateKey, ViewKey},
         network::{prelude::*, Testnet3},
-        program::{Entry, Identifier, Literal, Plaintext, Value},
+        program::{Entry, Identifier, Literal, Plaintext, Record, Value},
     },
     prelude::TestRng,
     synthesizer::{
-        block::{Block, Transaction, Transactions},
+        block::{Block, Header, Metadata, Transaction, Transactions, Transition},
         program::Program,
         store::ConsensusStore,
         vm::VM,
+        Query,
     },
 };
 
 use tracing_test::traced_test;
 
 use indexmap::IndexMap;
+use std::sync::Arc;
 
 type CurrentNetwork = Testnet3;
 
+/// Runs the given future to completion on a new single-threaded runtime.
+pub(crate) fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(future)
+}
+
+/// Subscribes to the transactions evicted from the given memory pool.
+fn subscribe_to_evictions(memory_pool: &crate::MemoryPool<CurrentNetwork>) -> Subscription<CurrentNetwork> {
+    memory_pool.event_bus().subscribe(&[Topic::TransactionEvicted])
+}
+
+/// Returns the next evicted transaction queued for the given subscription, if any.
+fn next_eviction(subscription: &Subscription<CurrentNetwork>) -> Option<EvictedTransaction<CurrentNetwork>> {
+    match subscription.try_recv()? {
+        Event::TransactionEvicted(evicted) => Some(evicted),
+        event => panic!("Unexpected event {event:?}"),
+    }
+}
+
+/// Returns the evicted transactions queued for the given subscription.
+fn evictions(subscription: &Subscription<CurrentNetwork>) -> Vec<EvictedTransaction<CurrentNetwork>> {
+    std::iter::from_fn(|| next_eviction(subscription)).collect()
+}
+
 #[cfg(test)]
 pub(crate) mod test_helpers {
     use super::*;
@@ -353,6 +387,69 @@ fn test_ledger_execute() {
     assert!(consensus.add_unconfirmed_transaction(transaction).is_err());
 }
 
+/// Admits the given transaction from eight peers concurrently, and returns their admissions.
+fn admit_from_eight_peers(
+    consensus: &crate::tests::test_helpers::CurrentConsensus,
+    transaction: &Transaction<CurrentNetwork>,
+) -> Vec<anyhow::Result<crate::Admission<()>>> {
+    let barrier = std::sync::Barrier::new(8);
+    std::thread::scope(|scope| {
+        let handles = (0..8u16)
+            .map(|index| {
+                let (consensus, barrier) = (consensus.clone(), &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    let peer_ip = std::net::SocketAddr::from(([10, 0, 0, 1], 4130 + index));
+                    consensus.admit_unconfirmed_transaction(transaction.clone(), Some(peer_ip))
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+#[test]
+#[traced_test]
+fn test_concurrent_admissions_are_deduplicated() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = test_helpers::sample_genesis_consensus(rng);
+
+    // Send the same transaction from eight peers, while its verification is slow enough for all of them to arrive.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.chaos().delay_verifications(Some(std::time::Duration::from_millis(500)));
+    let num_proof_verifications = consensus.num_proof_verifications();
+    let admissions = admit_from_eight_peers(&consensus, &transaction);
+    consensus.chaos().delay_verifications(None);
+
+    // Ensure the proofs were verified once, and every peer received the acceptance.
+    assert_eq!(consensus.num_proof_verifications(), num_proof_verifications + 1);
+    let admissions = admissions.into_iter().map(|admission| admission.unwrap()).collect::<Vec<_>>();
+    // Ensure the peer that validated the transaction is attributed every sender, so it is not relayed back to them.
+    let validated = admissions
+        .iter()
+        .filter_map(|admission| match admission {
+            crate::Admission::Validated { senders, .. } => Some(senders.len()),
+            crate::Admission::Shared { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(validated, vec![8]);
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(transaction.id()));
+
+    // Commit the transaction.
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.check_next_block(&next_block).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure every peer receives the rejection of the transaction, which is now in the ledger.
+    let num_proof_verifications = consensus.num_proof_verifications();
+    assert!(admit_from_eight_peers(&consensus, &transaction).iter().all(|admission| admission.is_err()));
+    assert_eq!(consensus.num_proof_verifications(), num_proof_verifications);
+}
+
 #[test]
 #[traced_test]
 fn test_ledger_execute_many() {
@@ -425,88 +522,2285 @@ fn test_ledger_execute_many() {
 
 #[test]
 #[traced_test]
-fn test_proof_target() {
+fn test_memory_pool_revalidation() {
     let rng = &mut TestRng::default();
 
-    // Sample the genesis private key and address.
+    // Sample a chain of transactions, of which the deployment conflicts with the split, and has a child.
+    let (consensus, [conflicting, committed, child]) = sample_transaction_chain(rng);
+
+    // Sample the genesis private key and view key.
     let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
-    let address = Address::try_from(&private_key).unwrap();
+    let view_key = ViewKey::try_from(private_key).unwrap();
+
+    // Fetch the unspent records, of which the chain spends the first two.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+    assert_eq!(records.len(), 4);
+
+    // Prepare a transaction that spends a different record.
+    let inputs = [Value::Record(records[3].clone()), Value::from_str("1u64").unwrap()];
+    let unrelated = Transaction::execute(
+        consensus.ledger.vm(),
+        &private_key,
+        ("credits.aleo", "split"),
+        inputs.iter(),
+        None,
+        None,
+        rng,
+    )
+    .unwrap();
+
+    // Propose a block containing the split.
+    consensus.add_unconfirmed_transaction(committed.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    assert!(next_block.transactions().contains_key(&committed.id()));
+
+    // Replace the memory pool contents with the deployment, its child, and the unrelated transaction.
+    consensus.clear_memory_pool().unwrap();
+    let outcomes = consensus.add_unconfirmed_transactions(
+        vec![conflicting.clone(), child.clone(), unrelated.clone()],
+        crate::BatchMode::Atomic,
+    );
+    assert!(outcomes.iter().all(|outcome| outcome.is_accepted()), "{outcomes:?}");
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 3);
+
+    // Subscribe to the evicted transactions.
+    let rejections = subscribe_to_evictions(consensus.memory_pool());
+
+    // Commit the block.
+    consensus.check_next_block(&next_block).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the conflicting transaction and its child were evicted in the same commit,
+    // and the unrelated transaction was kept.
+    assert!(!consensus.memory_pool().contains_unconfirmed_transaction(conflicting.id()));
+    assert!(!consensus.memory_pool().contains_unconfirmed_transaction(child.id()));
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(unrelated.id()));
+    assert!(consensus.check_transaction_basic(&conflicting).is_err());
+
+    // Ensure the subscriber was notified of both rejections, with the child after its parent.
+    let rejected = evictions(&rejections);
+    assert_eq!(rejected.iter().map(|rejected| rejected.transaction_id).collect::<Vec<_>>(), vec![
+        conflicting.id(),
+        child.id()
+    ]);
+    assert!(rejected[1].reason.contains(&conflicting.id().to_string()));
+}
+
+#[test]
+#[traced_test]
+fn test_memory_pool_expiry() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+
+    // Sample the genesis consensus, which admits transactions from the latest state root only.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    consensus.memory_pool().set_expiry_policy(Some(crate::ExpiryPolicy { window: 0, grace: 0 }));
+
+    // Fetch the unspent records.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Create a transaction from the genesis state root, and add it to the memory pool as a local transaction.
+    let inputs = [Value::Record(records[0].clone()), Value::from_str("1u64").unwrap()];
+    let transaction = Transaction::execute(
+        consensus.ledger.vm(),
+        &private_key,
+        ("credits.aleo", "split"),
+        inputs.iter(),
+        None,
+        None,
+        rng,
+    )
+    .unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
+    consensus.memory_pool().mark_local_transaction(transaction.id());
+
+    // Subscribe to the evicted transactions.
+    let rejections = subscribe_to_evictions(consensus.memory_pool());
+
+    // Commit a block without the transaction, which advances past the window and the grace.
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the transaction expired, and the subscriber was notified of the expiry.
+    assert!(!consensus.memory_pool().contains_unconfirmed_transaction(transaction.id()));
+    assert!(consensus.memory_pool().is_expired_transaction(&transaction.id()));
+    let rejected = next_eviction(&rejections).unwrap();
+    assert_eq!(rejected.transaction_id, transaction.id());
+    assert!(rejected.expired);
+    assert!(logs_contain("expired from the memory pool"));
+
+    // Ensure a re-broadcast of the transaction is rejected as expired, before it is verified again.
+    let error = consensus.add_unconfirmed_transaction(transaction).unwrap_err();
+    assert_eq!(
+        error.downcast::<crate::TransactionRejection<CurrentNetwork>>().unwrap(),
+        crate::TransactionRejection::Expired
+    );
+}
+
+#[test]
+#[traced_test]
+fn test_memory_pool_replacement() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
 
     // Sample the genesis consensus.
     let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
 
-    // Fetch the proof target and epoch challenge for the block.
-    let proof_target = consensus.ledger.latest_proof_target();
-    let epoch_challenge = consensus.ledger.latest_epoch_challenge().unwrap();
+    // Fetch the unspent records.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Prepare a transaction that splits the first record, paying the given fee with the second record.
+    let split = |fee: u64, rng: &mut TestRng| {
+        let inputs = [Value::Record(records[0].clone()), Value::from_str("1u64").unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            Some((records[1].clone(), fee)),
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    let original = split(100, rng);
+    let bump = split(1000, rng);
+    let insufficient_bump = split(1200, rng);
+
+    // Ensure a conflicting transaction is rejected while replacements are disabled.
+    consensus.add_unconfirmed_transaction(original.clone()).unwrap();
+    assert!(consensus.add_unconfirmed_transaction(bump.clone()).is_err());
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(original.id()));
+
+    // Enable replacements, and subscribe to the evicted transactions.
+    consensus.memory_pool().set_replacement_policy(Some(crate::ReplacementPolicy::new(500)));
+    let rejections = subscribe_to_evictions(consensus.memory_pool());
+
+    // Ensure the transaction with a sufficient fee replaces the original transaction.
+    consensus.add_unconfirmed_transaction(bump.clone()).unwrap();
+    assert!(!consensus.memory_pool().contains_unconfirmed_transaction(original.id()));
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(bump.id()));
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 1);
+
+    // Ensure the subscriber was notified of the replacement.
+    let rejected = next_eviction(&rejections).unwrap();
+    assert_eq!(rejected.transaction_id, original.id());
+    assert_eq!(rejected.replaced_by, Some(bump.id()));
+    assert!(rejections.try_recv().is_none());
+
+    // Ensure a transaction that does not exceed the evicted fee by the increment is rejected.
+    assert!(consensus.add_unconfirmed_transaction(insufficient_bump.clone()).is_err());
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(bump.id()));
+    assert!(!consensus.memory_pool().contains_unconfirmed_transaction(insufficient_bump.id()));
+    assert!(rejections.try_recv().is_none());
+}
 
-    for _ in 0..100 {
-        // Generate a prover solution.
-        let prover_solution = consensus.coinbase_puzzle.prove(&epoch_challenge, address, rng.gen(), None).unwrap();
+#[test]
+#[traced_test]
+fn test_memory_pool_replacement_with_dependents() {
+    let rng = &mut TestRng::default();
 
-        // Check that the prover solution meets the proof target requirement.
-        if prover_solution.to_target().unwrap() >= proof_target {
-            assert!(consensus.add_unconfirmed_solution(&prover_solution).is_ok())
-        } else {
-            assert!(consensus.add_unconfirmed_solution(&prover_solution).is_err())
-        }
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
 
-        // Generate a prover solution with a minimum proof target.
-        let prover_solution = consensus.coinbase_puzzle.prove(&epoch_challenge, address, rng.gen(), Some(proof_target));
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
 
-        // Check that the prover solution meets the proof target requirement.
-        if let Ok(prover_solution) = prover_solution {
-            assert!(prover_solution.to_target().unwrap() >= proof_target);
-            assert!(consensus.add_unconfirmed_solution(&prover_solution).is_ok())
-        }
+    // Fetch the unspent records.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Prepare two deployments of a program, which pay their fees with the same record.
+    let program = Program::<CurrentNetwork>::from_str(
+        r"
+program dependent.aleo;
+
+function hello:
+    input r0 as u32.public;
+    input r1 as u32.private;
+    add r0 r1 into r2;
+    output r2 as u32.private;",
+    )
+    .unwrap();
+    let deploy = |fee: u64, rng: &mut TestRng| {
+        Transaction::deploy(consensus.ledger.vm(), &private_key, &program, (records[0].clone(), fee), None, rng)
+            .unwrap()
+    };
+    let parent = deploy(6_000_000, rng);
+    let replacement = deploy(20_000_000, rng);
+
+    // Commit the parent, so that executions of the program can be created.
+    consensus.add_unconfirmed_transaction(parent.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Create two executions of the program, which depend on the parent.
+    let execute = |rng: &mut TestRng| {
+        let inputs = [Value::<CurrentNetwork>::from_str("1u32").unwrap(), Value::from_str("2u32").unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("dependent.aleo", "hello"),
+            inputs.iter(),
+            None,
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    let children = [execute(rng), execute(rng)];
+
+    // Initialize a memory pool with the parent and its children.
+    let memory_pool = crate::MemoryPool::<CurrentNetwork>::new();
+    memory_pool.add_unconfirmed_transaction(&parent).unwrap();
+    for child in &children {
+        memory_pool.add_unconfirmed_transaction(child).unwrap();
     }
+    assert_eq!(memory_pool.num_unconfirmed_transactions(), 3);
+
+    // Ensure the replacement is rejected if the cascade exceeds the maximum number of evictions.
+    memory_pool.set_replacement_policy(Some(crate::ReplacementPolicy { fee_increment: 1000, max_evictions: 2 }));
+    assert!(memory_pool.add_unconfirmed_transaction(&replacement).is_err());
+    assert_eq!(memory_pool.num_unconfirmed_transactions(), 3);
+
+    // Ensure the replacement evicts the parent and its children atomically.
+    memory_pool.set_replacement_policy(Some(crate::ReplacementPolicy::new(1000)));
+    let rejections = subscribe_to_evictions(&memory_pool);
+    assert!(memory_pool.add_unconfirmed_transaction(&replacement).unwrap());
+    assert_eq!(memory_pool.unconfirmed_transactions(), vec![replacement.clone()]);
+
+    // Ensure the subscriber was notified of every replaced transaction.
+    let mut replaced = evictions(&rejections);
+    assert_eq!(replaced.len(), 3);
+    assert!(replaced.iter().all(|rejected| rejected.replaced_by == Some(replacement.id())));
+    replaced.sort_by_key(|rejected| rejected.transaction_id.to_string());
+    let mut expected = vec![parent.id(), children[0].id(), children[1].id()];
+    expected.sort_by_key(|transaction_id| transaction_id.to_string());
+    assert_eq!(replaced.iter().map(|rejected| rejected.transaction_id).collect::<Vec<_>>(), expected);
 }
 
 #[test]
 #[traced_test]
-fn test_coinbase_target() {
+fn test_memory_pool_byte_budget() {
     let rng = &mut TestRng::default();
 
-    // Sample the genesis private key and address.
+    // Sample a deployment and an execution, which do not conflict.
+    let deployment = crate::tests::test_helpers::sample_deployment_transaction(rng);
+    let execution = crate::tests::test_helpers::sample_execution_transaction(rng);
+    let deployment_size = deployment.to_bytes_le().unwrap().len();
+    let execution_size = execution.to_bytes_le().unwrap().len();
+
+    // Bound the memory pool to the size of the deployment.
+    let memory_pool = crate::MemoryPool::<CurrentNetwork>::new();
+    memory_pool.set_byte_budget(Some(deployment_size));
+    assert!(memory_pool.add_unconfirmed_transaction(&deployment).unwrap());
+    assert_eq!(memory_pool.unconfirmed_bytes().unwrap(), deployment_size);
+
+    // Ensure the execution is rejected, on its own and in a batch, as it exceeds the byte budget.
+    assert!(memory_pool.add_unconfirmed_transaction(&execution).is_err());
+    assert!(memory_pool.add_unconfirmed_transactions(std::slice::from_ref(&execution)).is_err());
+    assert!(!memory_pool.contains_unconfirmed_transaction(execution.id()));
+
+    // Ensure the execution is accepted once the byte budget is raised.
+    memory_pool.set_byte_budget(Some(deployment_size + execution_size));
+    assert!(memory_pool.add_unconfirmed_transaction(&execution).unwrap());
+    assert_eq!(memory_pool.unconfirmed_bytes().unwrap(), deployment_size + execution_size);
+
+    // Ensure lowering the byte budget does not evict the unconfirmed transactions.
+    memory_pool.set_byte_budget(Some(0));
+    assert_eq!(memory_pool.num_unconfirmed_transactions(), 2);
+}
+
+/// Returns a fresh consensus, along with a chain of a deployment and an execution of the deployed program,
+/// and a transaction that spends the same record as the deployment.
+fn sample_transaction_chain(
+    rng: &mut TestRng,
+) -> (crate::tests::test_helpers::CurrentConsensus, [Transaction<CurrentNetwork>; 3]) {
+    // Sample the genesis private key and view key.
     let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
-    let address = Address::try_from(&private_key).unwrap();
+    let view_key = ViewKey::try_from(private_key).unwrap();
 
     // Sample the genesis consensus.
-    let consensus = test_helpers::sample_genesis_consensus(rng);
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Fetch the unspent records.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Prepare a deployment of a program, and a transaction that spends the same record.
+    let program = Program::<CurrentNetwork>::from_str(
+        r"
+program chain.aleo;
+
+function hello:
+    input r0 as u32.public;
+    input r1 as u32.private;
+    add r0 r1 into r2;
+    output r2 as u32.private;",
+    )
+    .unwrap();
+    let parent =
+        Transaction::deploy(consensus.ledger.vm(), &private_key, &program, (records[0].clone(), 6_000_000), None, rng)
+            .unwrap();
+    let inputs = [Value::Record(records[0].clone()), Value::from_str("1u64").unwrap()];
+    let conflict = Transaction::execute(
+        consensus.ledger.vm(),
+        &private_key,
+        ("credits.aleo", "split"),
+        inputs.iter(),
+        None,
+        None,
+        rng,
+    )
+    .unwrap();
+
+    // Commit the deployment, so that an execution of the program can be created.
+    consensus.add_unconfirmed_transaction(parent.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Sample a fresh consensus, in which the deployment is not committed.
+    let fresh = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Create an execution of the program, which depends on the deployment, against the state of the fresh ledger.
+    let inputs = [Value::<CurrentNetwork>::from_str("1u32").unwrap(), Value::from_str("2u32").unwrap()];
+    let child = Transaction::execute(
+        consensus.ledger.vm(),
+        &private_key,
+        ("chain.aleo", "hello"),
+        inputs.iter(),
+        Some((records[1].clone(), 100)),
+        Some(Query::from(fresh.ledger.vm().block_store())),
+        rng,
+    )
+    .unwrap();
+
+    (fresh, [parent, conflict, child])
+}
+
+#[test]
+#[traced_test]
+fn test_add_unconfirmed_transactions() {
+    let rng = &mut TestRng::default();
+
+    // Sample a chain of transactions.
+    let (consensus, [parent, _, child]) = sample_transaction_chain(rng);
+
+    // Ensure the child is rejected on its own, as the program is not deployed.
+    let outcomes = consensus.add_unconfirmed_transactions(vec![child.clone()], crate::BatchMode::BestEffort);
+    assert_eq!(outcomes[0].rejection.as_ref().map(|rejection| rejection.kind()), Some("invalid"));
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 0);
+
+    // Ensure the chain is accepted as a whole, as the child validates against the deployment in the batch.
+    let outcomes =
+        consensus.add_unconfirmed_transactions(vec![parent.clone(), child.clone()], crate::BatchMode::Atomic);
+    assert_eq!(outcomes.iter().map(|outcome| outcome.transaction_id).collect::<Vec<_>>(), vec![
+        parent.id(),
+        child.id()
+    ]);
+    assert!(outcomes.iter().all(|outcome| outcome.is_accepted()), "{outcomes:?}");
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(parent.id()));
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(child.id()));
+
+    // Ensure resubmitting the chain is rejected.
+    let outcomes = consensus.add_unconfirmed_transactions(vec![parent, child], crate::BatchMode::BestEffort);
+    assert!(outcomes.iter().all(|outcome| outcome.rejection == Some(crate::TransactionRejection::Duplicate)));
+}
+
+#[test]
+#[traced_test]
+fn test_add_unconfirmed_transactions_broken_chain() {
+    let rng = &mut TestRng::default();
+
+    // Sample a chain of transactions, which is broken in the middle by a conflicting transaction.
+    let (consensus, [parent, conflict, child]) = sample_transaction_chain(rng);
+    let batch = vec![parent.clone(), conflict.clone(), child.clone()];
+
+    // Ensure the atomic batch is rejected as a whole.
+    let outcomes = consensus.add_unconfirmed_transactions(batch.clone(), crate::BatchMode::Atomic);
+    assert_eq!(outcomes[0].rejection, Some(crate::TransactionRejection::Aborted { transaction_id: conflict.id() }));
+    assert_eq!(outcomes[1].rejection, Some(crate::TransactionRejection::Conflict { transaction_id: parent.id() }));
+    assert_eq!(outcomes[2].rejection, Some(crate::TransactionRejection::Aborted { transaction_id: conflict.id() }));
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 0);
+
+    // Ensure the best-effort batch accepts the transactions around the conflicting transaction.
+    let outcomes = consensus.add_unconfirmed_transactions(batch, crate::BatchMode::BestEffort);
+    assert!(outcomes[0].is_accepted(), "{outcomes:?}");
+    assert_eq!(outcomes[1].rejection, Some(crate::TransactionRejection::Conflict { transaction_id: parent.id() }));
+    assert!(outcomes[2].is_accepted(), "{outcomes:?}");
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(parent.id()));
+    assert!(!consensus.memory_pool().contains_unconfirmed_transaction(conflict.id()));
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(child.id()));
+}
+
+#[test]
+#[traced_test]
+fn test_add_dependent_unconfirmed_transaction() {
+    let rng = &mut TestRng::default();
+
+    // Sample a chain of transactions.
+    let (consensus, [parent, _, child]) = sample_transaction_chain(rng);
+
+    // Ensure the child is rejected on its own, as the program is not deployed.
+    assert!(consensus.add_unconfirmed_transaction(child.clone()).is_err());
+    assert!(consensus.memory_pool().unconfirmed_parents(&child).is_empty());
+
+    // Ensure the child is accepted once its parent is in the memory pool, as it validates against the deployment.
+    consensus.add_unconfirmed_transaction(parent.clone()).unwrap();
+    assert_eq!(consensus.memory_pool().unconfirmed_parents(&child), vec![parent.clone()]);
+    consensus.add_unconfirmed_transaction(child.clone()).unwrap();
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(child.id()));
+    assert!(consensus.memory_pool().unconfirmed_parents(&parent).is_empty());
+}
+
+#[test]
+#[traced_test]
+fn test_abandon_transaction() {
+    let rng = &mut TestRng::default();
+
+    // Sample a chain of transactions, and the genesis private key.
+    let (consensus, [parent, conflict, child]) = sample_transaction_chain(rng);
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let abandon_error = |transaction: &Transaction<CurrentNetwork>| {
+        consensus.abandon_transaction(&transaction.id()).unwrap_err().downcast::<crate::AbandonError>().unwrap()
+    };
+
+    // Submit the chain locally.
+    let outcomes =
+        consensus.add_unconfirmed_transactions(vec![parent.clone(), child.clone()], crate::BatchMode::Atomic);
+    assert!(outcomes.iter().all(|outcome| outcome.is_accepted()), "{outcomes:?}");
+    consensus.memory_pool().mark_local_transaction(parent.id());
+    consensus.memory_pool().mark_local_transaction(child.id());
+
+    // Ensure the conflicting transaction is rejected, as the chain spends the same record.
+    assert!(consensus.add_unconfirmed_transaction(conflict.clone()).is_err());
+    assert_eq!(abandon_error(&conflict), crate::AbandonError::NotInMemoryPool);
+
+    // Subscribe to the evicted transactions.
+    let rejections = subscribe_to_evictions(consensus.memory_pool());
+
+    // Abandon the parent, and ensure the child is abandoned with it.
+    let abandoned = consensus.abandon_transaction(&parent.id()).unwrap();
+    assert_eq!(abandoned, vec![parent.id(), child.id()]);
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 0);
+    assert!(!consensus.memory_pool().is_local_transaction(&parent.id()));
+    assert!(consensus.memory_pool().is_abandoned_transaction(&child.id()));
+    assert_eq!(consensus.memory_pool().local_transactions(), vec![
+        (parent.id(), crate::LocalStatus::Abandoned),
+        (child.id(), crate::LocalStatus::Abandoned)
+    ]);
+    assert_eq!(evictions(&rejections).into_iter().map(|rejected| rejected.transaction_id).collect::<Vec<_>>(), vec![
+        parent.id(),
+        child.id()
+    ]);
+
+    // Ensure a replacement that spends the same record is accepted, as the abandoned chain is not remembered as rejected.
+    consensus.add_unconfirmed_transaction(conflict.clone()).unwrap();
+    assert!(consensus.memory_pool().contains_unconfirmed_transaction(conflict.id()));
+
+    // Ensure a transaction that was not submitted locally cannot be abandoned.
+    assert_eq!(abandon_error(&conflict), crate::AbandonError::NotLocal);
+
+    // Ensure a confirmed transaction cannot be abandoned.
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+    assert_eq!(abandon_error(&conflict), crate::AbandonError::Confirmed);
+}
+
+#[test]
+#[traced_test]
+fn test_memory_pool_snapshot() {
+    let rng = &mut TestRng::default();
+
+    // Sample a chain of transactions, of which the parent and the child are dependent.
+    let (consensus, [parent, conflict, child]) = sample_transaction_chain(rng);
+
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+
+    // Fetch the unspent records, of which the chain spends the first two.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Prepare two deployments of an unrelated program, which conflict with each other.
+    let program = Program::<CurrentNetwork>::from_str(
+        r"
+program rival.aleo;
+
+function hello:
+    input r0 as u32.public;
+    input r1 as u32.private;
+    add r0 r1 into r2;
+    output r2 as u32.private;",
+    )
+    .unwrap();
+    let deploy = |index: usize, rng: &mut TestRng| {
+        Transaction::deploy(
+            consensus.ledger.vm(),
+            &private_key,
+            &program,
+            (records[index].clone(), 6_000_000),
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    let rivals = [deploy(2, rng), deploy(3, rng)];
+
+    // Add the chain and the conflicting deployments to the memory pool.
+    let start = ::time::OffsetDateTime::now_utc().unix_timestamp();
+    let outcomes =
+        consensus.add_unconfirmed_transactions(vec![parent.clone(), child.clone()], crate::BatchMode::Atomic);
+    assert!(outcomes.iter().all(|outcome| outcome.is_accepted()), "{outcomes:?}");
+    for rival in &rivals {
+        consensus.add_unconfirmed_transaction(rival.clone()).unwrap();
+    }
+    let end = ::time::OffsetDateTime::now_utc().unix_timestamp();
+    let entry =
+        |transaction: &Transaction<CurrentNetwork>| consensus.memory_pool_entry(&transaction.id()).unwrap().unwrap();
+
+    // Ensure the parent is annotated with its child, and is selected for the block template.
+    let parent_entry = entry(&parent);
+    assert_eq!(parent_entry.fee, *parent.fee().unwrap());
+    assert_eq!(parent_entry.size, parent.to_bytes_le().unwrap().len());
+    assert!((start..=end).contains(&parent_entry.arrival_time));
+    assert!(parent_entry.parents.is_empty());
+    assert_eq!(parent_entry.children, vec![child.id()]);
+    assert!(parent_entry.conflicts.is_empty());
+    assert_eq!(parent_entry.state_root_age, Some(0));
+    assert!(parent_entry.in_block_template);
+
+    // Ensure the child is annotated with its parent, and is not selected, as the program is not deployed yet.
+    let child_entry = entry(&child);
+    assert_eq!(child_entry.parents, vec![parent.id()]);
+    assert!(child_entry.children.is_empty());
+    assert!(child_entry.conflicts.is_empty());
+    assert!(!child_entry.in_block_template);
+    // Ensure the age of the child is unknown, as it was created on a ledger in which its parent is committed.
+    assert_eq!(child_entry.state_root_age, None);
+
+    // Ensure the conflicting deployments are annotated with each other, and only one is selected.
+    let rival_entries = [entry(&rivals[0]), entry(&rivals[1])];
+    assert_eq!(rival_entries[0].conflicts, vec![rivals[1].id()]);
+    assert_eq!(rival_entries[1].conflicts, vec![rivals[0].id()]);
+    assert!(rival_entries.iter().all(|entry| entry.parents.is_empty() && entry.children.is_empty()));
+    assert_eq!(rival_entries.iter().filter(|entry| entry.in_block_template).count(), 1);
+
+    // Ensure a transaction that is not in the memory pool has no entry.
+    assert!(consensus.memory_pool_entry(&conflict.id()).unwrap().is_none());
+
+    // Ensure the pages of the snapshot cover every transaction once, with the same annotations.
+    let first_page = consensus.memory_pool_snapshot(0, 3).unwrap();
+    let second_page = consensus.memory_pool_snapshot(3, 3).unwrap();
+    assert_eq!(first_page.num_transactions, 4);
+    assert_eq!((first_page.entries.len(), second_page.entries.len()), (3, 1));
+    let mut entries = first_page.entries.into_iter().chain(second_page.entries).collect::<Vec<_>>();
+    assert!(entries.windows(2).all(|pair| pair[0].arrival_time <= pair[1].arrival_time));
+    let mut expected = vec![parent_entry, child_entry, rival_entries[0].clone(), rival_entries[1].clone()];
+    entries.sort_by_key(|entry| entry.transaction_id.to_string());
+    expected.sort_by_key(|entry| entry.transaction_id.to_string());
+    assert_eq!(entries, expected);
+}
+
+#[test]
+#[traced_test]
+fn test_simulate_acceptance() {
+    let rng = &mut TestRng::default();
+
+    // Sample a chain of transactions, of which the conflict spends the same record as the parent.
+    let (consensus, [parent, conflict, child]) = sample_transaction_chain(rng);
+
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+
+    // Fetch the unspent records, of which the chain spends the first two.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Prepare a transaction that splits an unrelated record, paying the given fee.
+    let split = |fee: u64, rng: &mut TestRng| {
+        let inputs = [Value::Record(records[2].clone()), Value::from_str("1u64").unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            Some((records[3].clone(), fee)),
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    let generous = split(50_000_000, rng);
+    let frugal = split(1, rng);
+
+    // Add the parent to the memory pool, and build the block template from it.
+    consensus.add_unconfirmed_transaction(parent.clone()).unwrap();
+    assert_eq!(consensus.block_template().unwrap().transactions, vec![parent.id()]);
+    let num_proof_verifications = consensus.num_proof_verifications();
+    let events =
+        consensus.memory_pool().event_bus().subscribe(&[Topic::TransactionAccepted, Topic::TransactionEvicted]);
+
+    // Ensure a transaction that conflicts with the parent is rejected, with the parent as its conflict.
+    let simulation = consensus.simulate_acceptance(&conflict, false).unwrap();
+    assert!(!simulation.is_accepted());
+    assert_eq!(simulation.rejections, vec![crate::TransactionRejection::Conflict { transaction_id: parent.id() }]);
+    assert_eq!(simulation.conflicts, vec![parent.id()]);
+    assert!(simulation.parents.is_empty());
+    assert_eq!(simulation.template_position, None);
+    assert!(!simulation.is_proof_verified);
+
+    // Ensure a transaction that depends on the parent is reported with it, and is not selected for the next block.
+    let simulation = consensus.simulate_acceptance(&child, false).unwrap();
+    assert_eq!(simulation.parents, vec![parent.id()]);
+    assert_eq!(simulation.template_position, None);
+
+    // Ensure a transaction with a greater fee rate than the parent would be selected first, and its proofs verify.
+    let simulation = consensus.simulate_acceptance(&generous, true).unwrap();
+    assert!(simulation.is_accepted(), "{:?}", simulation.rejections);
+    assert!(simulation.is_proof_verified);
+    assert!(simulation.conflicts.is_empty() && simulation.parents.is_empty());
+    assert_eq!(simulation.fee, 50_000_000);
+    assert_eq!(simulation.size, generous.to_bytes_le().unwrap().len());
+    assert_eq!(simulation.fee_rate_percentile, 100.0);
+    assert_eq!(simulation.template_position, Some(0));
+
+    // Ensure a transaction with a lower fee rate than the parent would be selected after it.
+    let simulation = consensus.simulate_acceptance(&frugal, false).unwrap();
+    assert!(simulation.is_accepted(), "{:?}", simulation.rejections);
+    assert_eq!(simulation.fee_rate_percentile, 0.0);
+    assert_eq!(simulation.template_position, Some(1));
+
+    // Ensure the simulations did not change the memory pool, publish any event, or count a verification.
+    assert_eq!(consensus.memory_pool().unconfirmed_transactions(), vec![parent.clone()]);
+    assert!(events.try_recv().is_none());
+    assert_eq!(consensus.num_proof_verifications(), num_proof_verifications);
+
+    // Ensure the simulated position holds, once the transactions are added to the memory pool.
+    consensus.add_unconfirmed_transaction(generous.clone()).unwrap();
+    consensus.add_unconfirmed_transaction(frugal.clone()).unwrap_err();
+    assert_eq!(consensus.block_template().unwrap().transactions, vec![generous.id(), parent.id()]);
+}
+
+#[test]
+fn test_add_unconfirmed_transactions_too_large() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Ensure a batch exceeding the maximum number of transactions is rejected, in both modes.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    let batch = vec![transaction; crate::MAX_BATCH_TRANSACTIONS + 1];
+    for mode in [crate::BatchMode::Atomic, crate::BatchMode::BestEffort] {
+        let outcomes = consensus.add_unconfirmed_transactions(batch.clone(), mode);
+        assert_eq!(outcomes.len(), batch.len());
+        assert!(outcomes.iter().all(|outcome| {
+            outcome.rejection
+                == Some(crate::TransactionRejection::BatchTooLarge {
+                    num_transactions: crate::MAX_BATCH_TRANSACTIONS + 1,
+                })
+        }));
+        assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 0);
+    }
+}
+
+#[test]
+#[traced_test]
+fn test_wait_for_transaction() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
 
     // Add a transaction to the memory pool.
     let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
-    consensus.add_unconfirmed_transaction(transaction).unwrap();
+    consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
 
-    // Ensure that the ledger can't create a block that satisfies the coinbase target.
-    let proposed_block = consensus.propose_next_block(&private_key, rng).unwrap();
-    // Ensure the block does not contain a coinbase solution.
-    assert!(proposed_block.coinbase().is_none());
+    // Register a waiter for the transaction, and wait on another thread.
+    let waiter = consensus.register_transaction_waiter(transaction.id(), 1).unwrap();
+    let handle = std::thread::spawn(move || block_on(waiter.wait(std::time::Duration::from_secs(600))));
 
-    // Check that the ledger won't generate a block for a cumulative target that does not meet the requirements.
-    let mut cumulative_target = 0u128;
-    let epoch_challenge = consensus.ledger.latest_epoch_challenge().unwrap();
+    // Commit a block containing the transaction.
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
 
-    while cumulative_target < consensus.ledger.latest_coinbase_target() as u128 {
-        // Generate a prover solution.
-        let prover_solution = match consensus.coinbase_puzzle.prove(
-            &epoch_challenge,
-            address,
-            rng.gen(),
-            Some(consensus.ledger.latest_proof_target()),
-        ) {
-            Ok(prover_solution) => prover_solution,
-            Err(_) => continue,
-        };
+    // Ensure the waiter returns as soon as the transaction is confirmed.
+    let status = handle.join().unwrap().unwrap();
+    assert_eq!(status, crate::TransactionStatus::Confirmed { block_hash: next_block.hash(), confirmations: 1 });
 
-        // Try to add the prover solution to the memory pool.
-        if consensus.add_unconfirmed_solution(&prover_solution).is_ok() {
-            // Add to the cumulative target if the prover solution is valid.
-            cumulative_target += prover_solution.to_target().unwrap() as u128;
-        }
-    }
+    // Ensure a waiter for a confirmed transaction returns immediately.
+    let waiter = consensus.register_transaction_waiter(transaction.id(), 1).unwrap();
+    assert_eq!(block_on(waiter.wait(std::time::Duration::ZERO)).unwrap(), crate::TransactionStatus::Confirmed {
+        block_hash: next_block.hash(),
+        confirmations: 1
+    });
 
-    // Ensure that the ledger can create a block that satisfies the coinbase target.
-    let proposed_block = consensus.propose_next_block(&private_key, rng).unwrap();
-    // Ensure the block contains a coinbase solution.
-    assert!(proposed_block.coinbase().is_some());
+    // Ensure the number of confirmations must be nonzero.
+    assert!(consensus.register_transaction_waiter(transaction.id(), 0).is_err());
+}
+
+#[test]
+#[traced_test]
+fn test_wait_for_transaction_timeout() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Add a transaction to the memory pool.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
+
+    // Ensure the waiter times out while the transaction is unconfirmed.
+    let waiter = consensus.register_transaction_waiter(transaction.id(), 1).unwrap();
+    let status = block_on(waiter.wait(std::time::Duration::from_millis(100))).unwrap();
+    assert_eq!(status, crate::TransactionStatus::TimedOut { confirmations: 0 });
+
+    // Commit a block containing the transaction.
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the waiter times out before the requested confirmation depth is reached.
+    let waiter = consensus.register_transaction_waiter(transaction.id(), 2).unwrap();
+    let status = block_on(waiter.wait(std::time::Duration::from_millis(100))).unwrap();
+    assert_eq!(status, crate::TransactionStatus::TimedOut { confirmations: 1 });
+}
+
+#[test]
+#[traced_test]
+fn test_wait_for_transaction_conflict() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Fetch the unspent records.
+    let records: Vec<_> =
+        consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect();
+
+    // Create two transactions that spend the same record.
+    let split = |fee_record: usize, rng: &mut TestRng| {
+        let inputs = [Value::Record(records[0].clone()), Value::from_str("1u64").unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            Some((records[fee_record].clone(), 100u64)),
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    let committed = split(1, rng);
+    let conflicting = split(2, rng);
+
+    // Propose a block containing the first transaction.
+    consensus.add_unconfirmed_transaction(committed.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+
+    // Replace the memory pool contents with the conflicting transaction, and register a waiter for it.
+    consensus.clear_memory_pool().unwrap();
+    consensus.add_unconfirmed_transaction(conflicting.clone()).unwrap();
+    let waiter = consensus.register_transaction_waiter(conflicting.id(), 1).unwrap();
+    let handle = std::thread::spawn(move || block_on(waiter.wait(std::time::Duration::from_secs(600))));
+
+    // Commit the block.
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the waiter reports the conflicting transaction.
+    let status = handle.join().unwrap().unwrap();
+    assert_eq!(status, crate::TransactionStatus::Conflicted { transaction_id: committed.id() });
+}
+
+#[test]
+#[traced_test]
+fn test_block_template_long_poll_on_new_block() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Retrieve the template, and long-poll for a fresh one on another thread.
+    let template = consensus.block_template().unwrap();
+    assert_eq!(template.height, 1);
+    let consensus_ = consensus.clone();
+    let longpoll_id = template.longpoll_id;
+    let handle = std::thread::spawn(move || {
+        let timer = std::time::Instant::now();
+        block_on(consensus_.wait_for_stale_template(&longpoll_id, std::time::Duration::from_secs(600)));
+        let template = consensus_.block_template().unwrap();
+        (template, timer.elapsed())
+    });
+
+    // Commit the next block.
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the long poll returns promptly with a template that builds on the new block.
+    let (fresh, elapsed) = handle.join().unwrap();
+    assert!(elapsed < std::time::Duration::from_secs(60));
+    assert_eq!(fresh.previous_block_hash, next_block.hash());
+    assert_eq!(fresh.height, 2);
+    assert_ne!(fresh.longpoll_id, longpoll_id);
+}
+
+#[test]
+#[traced_test]
+fn test_block_template_rebuilt_on_commit_during_build() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Start building a template on the snapshot of the genesis block.
+    let stale_tip = consensus.miner_tip();
+    assert_eq!(stale_tip.block_hash, consensus.ledger.latest_hash());
+
+    // Commit the next block, while the template is built.
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+    let stale_template = consensus.build_block_template_on(&stale_tip).unwrap();
+
+    // Ensure the miner learns that its snapshot is stale, and refreshes it.
+    assert!(consensus.is_miner_tip_stale(&stale_tip));
+    assert_eq!(consensus.miner_tip().block_hash, next_block.hash());
+    assert_eq!(consensus.miner_tip().height, 1);
+
+    // Ensure the template that is handed out extends the latest block, never the stale one.
+    let template = consensus.block_template().unwrap();
+    assert_eq!(stale_template.previous_block_hash, stale_tip.block_hash);
+    assert_eq!(template.previous_block_hash, next_block.hash());
+    assert_eq!(template.height, 2);
+
+    // Ensure the submitted block extends the latest block, and is accepted.
+    let block = consensus.propose_next_block(&private_key, rng).unwrap();
+    assert_eq!(block.previous_hash(), next_block.hash());
+    consensus.advance_to_next_block(&block).unwrap();
+    assert_eq!(consensus.miner_tip().block_hash, block.hash());
+}
+
+#[test]
+#[traced_test]
+fn test_block_template_long_poll_on_fee_increase() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    // Sample a transaction, which pays a fee of 100 microcredits.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+
+    // Ensure a long poll times out while the template is fresh.
+    let template = consensus.block_template().unwrap();
+    block_on(consensus.wait_for_stale_template(&template.longpoll_id, std::time::Duration::from_millis(100)));
+    assert!(Arc::ptr_eq(&template, &consensus.block_template().unwrap()));
+
+    // Long-poll for a fresh template on another thread.
+    consensus.set_template_fee_delta(100);
+    let consensus_ = consensus.clone();
+    let longpoll_id = template.longpoll_id;
+    let handle = std::thread::spawn(move || {
+        block_on(consensus_.wait_for_stale_template(&longpoll_id, std::time::Duration::from_secs(600)));
+        consensus_.block_template().unwrap()
+    });
+
+    // Add the transaction to the memory pool, which increases its fees by the fee delta.
+    consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
+
+    // Ensure the long poll returns with a template that includes the transaction.
+    let fresh = handle.join().unwrap();
+    assert_eq!(fresh.previous_block_hash, template.previous_block_hash);
+    assert_eq!(fresh.transactions, vec![transaction.id()]);
+    assert_eq!(fresh.fees, 100);
+    assert_eq!(fresh.longpoll_id.memory_pool_fees, 100);
+
+    // Ensure the long-poll ID roundtrips through its string representation.
+    assert_eq!(fresh.longpoll_id.to_string().parse::<crate::LongPollId<CurrentNetwork>>().unwrap(), fresh.longpoll_id);
+}
+
+#[test]
+#[traced_test]
+fn test_block_template_coinbase_split() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    // Sample a transaction, which pays a fee of 100 microcredits.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+
+    // Ensure the template has no coinbase outputs, until the recipients are set.
+    assert!(consensus.block_template().unwrap().coinbase.is_empty());
+
+    // Split the coinbase 70/30 between two recipients.
+    let first = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+    let second = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+    let recipients = crate::CoinbaseRecipients::new(vec![
+        crate::CoinbaseRecipient { address: first, weight: 70 },
+        crate::CoinbaseRecipient { address: second, weight: 30 },
+    ])
+    .unwrap();
+    consensus.set_coinbase_recipients(recipients.clone());
+
+    // Ensure the next template splits the reward and the fees exactly, with the remainder paid to the first recipient.
+    let template = consensus.block_template().unwrap();
+    let total = template.coinbase_reward + template.fees;
+    assert_eq!(template.fees, 100);
+    assert_eq!(template.coinbase.iter().map(|output| output.amount).sum::<u64>(), total);
+    assert_eq!(template.coinbase[1], crate::CoinbaseOutput { recipient: second, amount: total * 30 / 100 });
+    assert_eq!(template.coinbase[0], crate::CoinbaseOutput { recipient: first, amount: total - total * 30 / 100 });
+
+    // Ensure the mined blocks are counted for each recipient.
+    consensus.record_mined_block().unwrap();
+    let info = consensus.miner_info();
+    assert_eq!(info.recipients, Some(recipients));
+    assert_eq!(info.mined_blocks, IndexMap::from([(first, 1), (second, 1)]));
+
+    // Ensure a change of the recipients takes effect on the next template.
+    consensus.set_coinbase_recipients(crate::CoinbaseRecipients::single(second));
+    let template = consensus.block_template().unwrap();
+    let total = template.coinbase_reward + template.fees;
+    assert_eq!(template.coinbase, vec![crate::CoinbaseOutput { recipient: second, amount: total }]);
+}
+
+#[test]
+#[traced_test]
+fn test_block_template_is_cached() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    // Add a transaction to the memory pool, below the fee delta.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+
+    // Retrieve the template from many concurrent callers.
+    let templates = (0..8)
+        .map(|_| {
+            let consensus = consensus.clone();
+            std::thread::spawn(move || consensus.block_template().unwrap())
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    // Ensure the template was built once, and shared by every caller.
+    assert!(templates.windows(2).all(|pair| Arc::ptr_eq(&pair[0], &pair[1])));
+
+    // Ensure the template is reused while the fee increase is below the fee delta.
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+    assert!(Arc::ptr_eq(&templates[0], &consensus.block_template().unwrap()));
+    // Ensure the template is rebuilt once the fee increase reaches the fee delta.
+    consensus.set_template_fee_delta(1);
+    assert!(!Arc::ptr_eq(&templates[0], &consensus.block_template().unwrap()));
+}
+
+#[test]
+#[traced_test]
+fn test_header_commitments() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus, with the header commitments from block 2 on.
+    let mut consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    consensus.set_rule_activations(crate::RuleActivations::new(&[("header_commitments", 2)]).unwrap());
+
+    // Ensure the header of a block before the activation height keeps the legacy layout.
+    let block_1 = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.advance_to_next_block(&block_1).unwrap();
+    let legacy = consensus.committed_header(&block_1).unwrap();
+    assert!(legacy.commitments.is_none());
+    consensus.check_block_commitments(&block_1, &legacy).unwrap();
+
+    // Sample a transaction, which pays a fee of 100 microcredits, and ensure the template commits to it.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+    let template = consensus.block_template().unwrap();
+    assert_eq!(template.height, 2);
+    assert_eq!(template.commitments, Some(HeaderCommitments { num_transactions: 1, total_fees: 100 }));
+
+    // Ensure the header of the next block commits to its body.
+    let block_2 = consensus.propose_next_block(&private_key, rng).unwrap();
+    let committed = consensus.committed_header(&block_2).unwrap();
+    assert_eq!(committed.commitments, Some(HeaderCommitments { num_transactions: 1, total_fees: 100 }));
+    consensus.check_block_commitments(&block_2, &committed).unwrap();
+
+    // Ensure the relayed header is only accepted with the commitments signed by its relayer.
+    let relayer = Address::try_from(&private_key).unwrap();
+    assert!(consensus.check_relayed_header(&committed, &relayer).is_err());
+    let committed = committed.sign(&private_key, rng).unwrap();
+    consensus.check_relayed_header(&committed, &relayer).unwrap();
+    let impostor = Address::try_from(&PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+    assert!(consensus.check_relayed_header(&committed, &impostor).is_err());
+    // Ensure the commitments cannot be altered without invalidating the signature.
+    let mut forged = committed.clone();
+    forged.commitments.as_mut().unwrap().num_transactions = 2;
+    forged.check_layout(consensus.header_commitments_height()).unwrap();
+    assert!(consensus.check_relayed_header(&forged, &relayer).is_err());
+
+    // Ensure a body whose fees mismatch the header is rejected.
+    let mut mismatched = committed.clone();
+    mismatched.commitments.as_mut().unwrap().total_fees = 99;
+    let error = consensus.check_block_commitments(&block_2, &mismatched).unwrap_err();
+    assert!(error.to_string().contains("commits to 99"), "{error}");
+    // Ensure the header of another block is rejected.
+    assert!(consensus.check_block_commitments(&block_1, &committed).is_err());
+
+    // Ensure the relayed headers in the wrong layout, or with implausible commitments, are rejected cheaply.
+    assert!(consensus.check_relayed_header(&CommittedHeader::legacy(*block_2.header()), &relayer).is_err());
+    let mut implausible = committed.clone();
+    implausible.commitments.as_mut().unwrap().num_transactions = u32::MAX;
+    assert!(consensus.check_relayed_header(&implausible.sign(&private_key, rng).unwrap(), &relayer).is_err());
+    let mut premature = legacy;
+    premature.commitments = Some(HeaderCommitments { num_transactions: 1, total_fees: 0 });
+    assert!(consensus.check_relayed_header(&premature.sign(&private_key, rng).unwrap(), &relayer).is_err());
+}
+
+#[test]
+#[traced_test]
+fn test_replay_block() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose the next block with a transaction.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+
+    // Ensure the valid block replays without failures.
+    let report = crate::replay_block(&consensus, &next_block);
+    assert!(report.is_valid(), "{report}");
+    assert_eq!(report.height, next_block.height());
+
+    // Commit the block, so that replaying it again is invalid.
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the report pinpoints the failing checks.
+    let report = crate::replay_block(&consensus, &next_block);
+    assert!(!report.is_valid());
+    let first_failure = report.first_failure().unwrap();
+    assert_eq!(first_failure.stage, crate::ReplayStage::Block);
+    assert_eq!(first_failure.name, "position");
+    assert!(report.failures().any(|check| {
+        check.stage == crate::ReplayStage::Transaction(transaction.id()) && check.name == "double-spend"
+    }));
+    // Ensure the checks unaffected by the commit still pass.
+    assert!(report.checks.iter().any(|check| check.name == "header" && check.is_passed()));
+    assert!(report.checks.iter().any(|check| check.name == "state roots" && check.is_passed()));
+}
+
+/// Samples a coinbase transaction, which mints the given amount of microcredits to the genesis address.
+fn sample_coinbase_transaction(
+    consensus: &crate::tests::test_helpers::CurrentConsensus,
+    amount: u64,
+    rng: &mut TestRng,
+) -> Transaction<CurrentNetwork> {
+    // Initialize the caller.
+    let caller_private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let address = Address::try_from(&caller_private_key).unwrap();
+
+    // Prepare the inputs.
+    let inputs = [
+        Value::<CurrentNetwork>::from_str(&address.to_string()).unwrap(),
+        Value::<CurrentNetwork>::from_str(&format!("{amount}u64")).unwrap(),
+    ]
+    .into_iter();
+
+    // Authorize and execute the mint, without a fee, as a coinbase transaction does not require one.
+    let vm = consensus.ledger.vm();
+    let authorization = vm.authorize(&caller_private_key, "credits.aleo", "mint", inputs, rng).unwrap();
+    let transaction = Transaction::execute_authorization(vm, authorization, None, None, rng).unwrap();
+    assert!(transaction.is_coinbase());
+    transaction
+}
+
+#[test]
+#[traced_test]
+fn test_block_with_two_coinbases() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose the next block with two coinbase transactions.
+    consensus.add_unconfirmed_transaction(sample_coinbase_transaction(&consensus, 1, rng)).unwrap();
+    consensus.add_unconfirmed_transaction(sample_coinbase_transaction(&consensus, 2, rng)).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    assert_eq!(next_block.transactions().len(), 2);
+
+    // Ensure the block is rejected.
+    let error = consensus.check_next_block(&next_block).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<crate::BlockRejection<CurrentNetwork>>(),
+        Some(&crate::BlockRejection::MultipleCoinbases { num_coinbases: 2 })
+    );
+}
+
+#[test]
+#[traced_test]
+fn test_known_invalid_block() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose an invalid next block, and record it as rejected by the peer that sent it.
+    consensus.add_unconfirmed_transaction(sample_coinbase_transaction(&consensus, 1, rng)).unwrap();
+    consensus.add_unconfirmed_transaction(sample_coinbase_transaction(&consensus, 2, rng)).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    let error = consensus.check_next_block(&next_block).unwrap_err();
+    let peer_ip = "127.0.0.1:4130".parse().unwrap();
+    consensus.insert_rejected_block(&next_block, &error.to_string(), Some(peer_ip)).unwrap();
+    let invalid = consensus.invalid_blocks().get_block(&next_block).unwrap();
+    assert_eq!((invalid.height, invalid.reason, invalid.peer_ip), (1, error.to_string(), Some(peer_ip)));
+    // Ensure the block, rejected for its transactions, is not marked as invalid under its hash.
+    assert!(!consensus.invalid_blocks().contains(&next_block.hash()));
+
+    // Ensure the block is rejected again without verifying any proof.
+    let num_proof_verifications = consensus.num_proof_verifications();
+    let error = consensus.check_next_block(&next_block).unwrap_err();
+    assert!(error.to_string().contains("known to be invalid"));
+    assert!(consensus.preverify_block(&next_block).is_err());
+    assert_eq!(consensus.num_proof_verifications(), num_proof_verifications);
+
+    // Ensure the block is validated again once it is purged.
+    assert_eq!(consensus.invalid_blocks().purge(None).unwrap(), 1);
+    let error = consensus.check_next_block(&next_block).unwrap_err();
+    assert!(!error.to_string().contains("known to be invalid"));
+}
+
+#[test]
+#[traced_test]
+fn test_malleated_block_does_not_invalidate_genuine_block() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose the next block, and a copy of it signed by an unauthorized key, which has the same hash.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    let malleated = Block::new(
+        &PrivateKey::new(rng).unwrap(),
+        next_block.previous_hash(),
+        *next_block.header(),
+        next_block.transactions().clone(),
+        next_block.coinbase().cloned(),
+        rng,
+    )
+    .unwrap();
+    assert_eq!(malleated.hash(), next_block.hash());
+
+    // Ensure the malleated copy is rejected, and rejected again once it is recorded as invalid.
+    // As its signature is unauthorized, it is not stored in the side branches.
+    let error = consensus.check_next_block(&malleated).unwrap_err();
+    let peer_ip = "127.0.0.1:4130".parse().unwrap();
+    assert!(consensus.insert_rejected_block(&malleated, &error.to_string(), Some(peer_ip)).is_err());
+    assert_eq!(consensus.ledger.chain_tips().len(), 1);
+    assert_eq!(consensus.invalid_blocks().get_block(&malleated).unwrap().peer_ip, Some(peer_ip));
+    assert!(consensus.check_next_block(&malleated).unwrap_err().to_string().contains("known to be invalid"));
+
+    // Ensure the genuine block is still accepted.
+    assert!(consensus.invalid_blocks().get_block(&next_block).is_none());
+    consensus.check_next_block(&next_block).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+    assert_eq!(consensus.ledger.latest_hash(), next_block.hash());
+}
+
+#[test]
+#[traced_test]
+fn test_preverified_transactions() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose the next block, and verify its transaction ahead of it.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.preverify_block(&next_block).unwrap();
+    let digest = crate::transaction_digest(&transaction).unwrap();
+    assert!(consensus.verified_transactions.lock().contains(&digest));
+
+    // Ensure the verified transactions of a rejected block are forgotten.
+    consensus.insert_rejected_block(&next_block, "Rejected by the test", None).unwrap();
+    assert!(consensus.verified_transactions.lock().is_empty());
+
+    // Ensure the proof of the transaction is verified again when the block is checked, and consumed on success.
+    consensus.invalid_blocks().purge(None).unwrap();
+    consensus.preverify_block(&next_block).unwrap();
+    let num_proof_verifications = consensus.num_proof_verifications();
+    consensus.check_next_block(&next_block).unwrap();
+    assert_eq!(consensus.num_proof_verifications(), num_proof_verifications);
+    assert!(consensus.verified_transactions.lock().is_empty());
+}
+
+#[test]
+#[traced_test]
+fn test_rejected_blocks_are_bounded() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose the next block.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    let (round, height, timestamp) = (next_block.round(), next_block.height(), next_block.timestamp());
+    let targets = (next_block.coinbase_target(), next_block.proof_target());
+    let previous_hash = next_block.previous_hash();
+
+    // Flood the rejected blocks with copies of the next block in different rounds, which have distinct hashes.
+    let num_blocks = 3 * MAX_SIDE_BLOCKS_PER_HEIGHT as u64;
+    let flood = (1..=num_blocks)
+        .map(|i| {
+            let metadata = sample_metadata(&next_block, round + i, height, timestamp, targets);
+            sample_resigned_block(&private_key, &next_block, previous_hash, metadata, rng)
+        })
+        .collect::<Vec<_>>();
+    for block in &flood {
+        consensus.insert_rejected_block(block, "Rejected by the test", None).unwrap();
+    }
+
+    // Ensure only the latest side blocks at the height are kept.
+    let tips = consensus.ledger.chain_tips();
+    assert_eq!(tips.len(), 1 + MAX_SIDE_BLOCKS_PER_HEIGHT);
+    let kept = &flood[flood.len() - MAX_SIDE_BLOCKS_PER_HEIGHT..];
+    assert!(kept.iter().all(|block| tips.iter().any(|tip| tip.hash == block.hash())));
+
+    // Ensure a block with an invalid header, or an unauthorized signature, is not stored.
+    let metadata = sample_metadata(&next_block, round, height, timestamp, (targets.0 + 1, targets.1));
+    let invalid = sample_resigned_block(&private_key, &next_block, previous_hash, metadata, rng);
+    assert!(consensus.insert_rejected_block(&invalid, "Rejected by the test", None).is_err());
+    let metadata = sample_metadata(&next_block, round + num_blocks + 1, height, timestamp, targets);
+    let unauthorized = sample_resigned_block(&PrivateKey::new(rng).unwrap(), &next_block, previous_hash, metadata, rng);
+    assert!(consensus.insert_rejected_block(&unauthorized, "Rejected by the test", None).is_err());
+    assert_eq!(consensus.ledger.chain_tips(), tips);
+}
+
+#[test]
+#[traced_test]
+fn test_block_with_misplaced_coinbase() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Propose the next block with a coinbase transaction after a deployment.
+    let deployment = crate::tests::test_helpers::sample_deployment_transaction(rng);
+    let coinbase = sample_coinbase_transaction(&consensus, 1, rng);
+    consensus.add_unconfirmed_transaction(deployment).unwrap();
+    consensus.add_unconfirmed_transaction(coinbase.clone()).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    assert_eq!(next_block.transactions().len(), 2);
+
+    // Ensure the block is rejected.
+    let error = consensus.check_next_block(&next_block).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<crate::BlockRejection<CurrentNetwork>>(),
+        Some(&crate::BlockRejection::MisplacedCoinbase { transaction_id: coinbase.id(), index: 1 })
+    );
+}
+
+#[test]
+#[traced_test]
+fn test_block_with_duplicate_serial_numbers() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Prepare two transactions that spend the same record.
+    let record = consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().next().unwrap().1;
+    let split = |amount: &str, rng: &mut TestRng| {
+        let inputs = [Value::Record(record.clone()), Value::from_str(amount).unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            None,
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    let first = split("1u64", rng);
+    let second = split("2u64", rng);
+
+    // Propose the next block with the first transaction, and include the second transaction as well.
+    consensus.add_unconfirmed_transaction(first.clone()).unwrap();
+    let block = consensus.propose_next_block(&private_key, rng).unwrap();
+    let transactions = Transactions::from(&[first.clone(), second]);
+    let next_block = Block::new(&private_key, block.previous_hash(), *block.header(), transactions, None, rng).unwrap();
+
+    // Ensure the block is rejected, with the positions of both transactions.
+    let error = consensus.check_next_block(&next_block).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<crate::BlockRejection<CurrentNetwork>>(),
+        Some(&crate::BlockRejection::DuplicateSerialNumber {
+            serial_number: *first.serial_numbers().next().unwrap(),
+            first: 0,
+            second: 1,
+        })
+    );
+}
+
+#[test]
+#[traced_test]
+fn test_add_unconfirmed_coinbase() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Advance past the genesis block, after which coinbase transactions are only valid in a block.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    consensus.check_next_block(&next_block).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+
+    // Ensure the coinbase transaction is rejected from the memory pool, on its own and in a batch.
+    let coinbase = sample_coinbase_transaction(&consensus, 1, rng);
+    let error = consensus.add_unconfirmed_transaction(coinbase.clone()).unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<crate::TransactionRejection<CurrentNetwork>>(),
+        Some(&crate::TransactionRejection::Coinbase)
+    );
+    let outcomes = consensus.add_unconfirmed_transactions(vec![coinbase], crate::BatchMode::BestEffort);
+    assert_eq!(outcomes[0].rejection, Some(crate::TransactionRejection::Coinbase));
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 0);
+}
+
+#[test]
+#[traced_test]
+fn test_proof_target() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key and address.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let address = Address::try_from(&private_key).unwrap();
+
+    // Sample the genesis consensus.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Fetch the proof target and epoch challenge for the block.
+    let proof_target = consensus.ledger.latest_proof_target();
+    let epoch_challenge = consensus.ledger.latest_epoch_challenge().unwrap();
+
+    for _ in 0..100 {
+        // Generate a prover solution.
+        let prover_solution = consensus.coinbase_puzzle.prove(&epoch_challenge, address, rng.gen(), None).unwrap();
+
+        // Check that the prover solution meets the proof target requirement.
+        if prover_solution.to_target().unwrap() >= proof_target {
+            assert!(consensus.add_unconfirmed_solution(&prover_solution).is_ok())
+        } else {
+            assert!(consensus.add_unconfirmed_solution(&prover_solution).is_err())
+        }
+
+        // Generate a prover solution with a minimum proof target.
+        let prover_solution = consensus.coinbase_puzzle.prove(&epoch_challenge, address, rng.gen(), Some(proof_target));
+
+        // Check that the prover solution meets the proof target requirement.
+        if let Ok(prover_solution) = prover_solution {
+            assert!(prover_solution.to_target().unwrap() >= proof_target);
+            assert!(consensus.add_unconfirmed_solution(&prover_solution).is_ok())
+        }
+    }
+}
+
+#[test]
+#[traced_test]
+fn test_coinbase_target() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key and address.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let address = Address::try_from(&private_key).unwrap();
+
+    // Sample the genesis consensus.
+    let consensus = test_helpers::sample_genesis_consensus(rng);
+
+    // Add a transaction to the memory pool.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(transaction).unwrap();
+
+    // Ensure that the ledger can't create a block that satisfies the coinbase target.
+    let proposed_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    // Ensure the block does not contain a coinbase solution.
+    assert!(proposed_block.coinbase().is_none());
+
+    // Check that the ledger won't generate a block for a cumulative target that does not meet the requirements.
+    let mut cumulative_target = 0u128;
+    let epoch_challenge = consensus.ledger.latest_epoch_challenge().unwrap();
+
+    while cumulative_target < consensus.ledger.latest_coinbase_target() as u128 {
+        // Generate a prover solution.
+        let prover_solution = match consensus.coinbase_puzzle.prove(
+            &epoch_challenge,
+            address,
+            rng.gen(),
+            Some(consensus.ledger.latest_proof_target()),
+        ) {
+            Ok(prover_solution) => prover_solution,
+            Err(_) => continue,
+        };
+
+        // Try to add the prover solution to the memory pool.
+        if consensus.add_unconfirmed_solution(&prover_solution).is_ok() {
+            // Add to the cumulative target if the prover solution is valid.
+            cumulative_target += prover_solution.to_target().unwrap() as u128;
+        }
+    }
+
+    // Ensure that the ledger can create a block that satisfies the coinbase target.
+    let proposed_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    // Ensure the block contains a coinbase solution.
+    assert!(proposed_block.coinbase().is_some());
+}
+
+/// Returns the amount of microcredits of the given record.
+fn microcredits(record: &Record<CurrentNetwork, Plaintext<CurrentNetwork>>) -> u64 {
+    match record.data().get(&Identifier::from_str("microcredits").unwrap()) {
+        Some(Entry::Private(Plaintext::Literal(Literal::U64(amount), _))) => **amount,
+        _ => 0,
+    }
+}
+
+/// Samples a chain of the given number of blocks on top of the genesis block, in which each block splits
+/// the largest unspent record of the genesis address. Returns the blocks, along with a transaction that
+/// spends the same record as the first block.
+fn sample_chain(num_blocks: u32, rng: &mut TestRng) -> (Vec<Block<CurrentNetwork>>, Transaction<CurrentNetwork>) {
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+    // Sample the genesis consensus, which produces the chain.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Splits the given record, without a fee.
+    let split = |record: &Record<CurrentNetwork, Plaintext<CurrentNetwork>>, amount: &str, rng: &mut TestRng| {
+        let inputs = [Value::Record(record.clone()), Value::from_str(amount).unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            None,
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+
+    let mut blocks = Vec::with_capacity(num_blocks as usize);
+    let mut double_spend = None;
+    for height in 1..=num_blocks {
+        // Select the largest unspent record.
+        let record = consensus
+            .ledger
+            .find_records(&view_key, RecordsFilter::Unspent)
+            .unwrap()
+            .map(|(_, record)| record)
+            .max_by_key(microcredits)
+            .unwrap();
+        // Prepare a transaction that spends the record of the first block again.
+        if height == 1 {
+            double_spend = Some(split(&record, "2u64", rng));
+        }
+
+        // Produce the next block with the split.
+        consensus.add_unconfirmed_transaction(split(&record, "1u64", rng)).unwrap();
+        let block = consensus.propose_next_block(&private_key, rng).unwrap();
+        consensus.advance_to_next_block(&block).unwrap();
+        blocks.push(block);
+    }
+    (blocks, double_spend.unwrap())
+}
+
+#[test]
+#[traced_test]
+fn test_verify_chain() {
+    const NUM_BLOCKS: u32 = 500;
+    const INVALID_HEIGHT: u32 = 300;
+
+    let rng = &mut TestRng::default();
+
+    // Sample a valid chain.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let (blocks, double_spend) = sample_chain(NUM_BLOCKS, rng);
+
+    // Ensure the valid chain is verified by a fresh consensus, with the progress of each block.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let mut heights = Vec::new();
+    let report =
+        crate::verify_chain(&consensus, blocks.iter().cloned().map(Ok), |progress| heights.push(progress.height))
+            .unwrap();
+    assert!(report.is_valid(), "{report}");
+    assert_eq!(report.progress.height, NUM_BLOCKS);
+    assert_eq!(report.progress.num_blocks, NUM_BLOCKS);
+    assert_eq!(report.latest_hash, blocks.last().unwrap().hash());
+    assert_eq!(heights, (1..=NUM_BLOCKS).collect::<Vec<_>>());
+
+    // Replace the block at the invalid height with a block that spends the record of the first block again.
+    let header = blocks[INVALID_HEIGHT as usize - 1].header();
+    let transactions = Transactions::from(std::slice::from_ref(&double_spend));
+    let header = Header::from(
+        header.previous_state_root(),
+        transactions.to_root().unwrap(),
+        header.finalize_root(),
+        header.coinbase_accumulator_point(),
+        *header.metadata(),
+    )
+    .unwrap();
+    let previous_hash = blocks[INVALID_HEIGHT as usize - 2].hash();
+    let invalid_block = Block::new(&private_key, previous_hash, header, transactions, None, rng).unwrap();
+    let mut invalid_chain = blocks;
+    invalid_chain[INVALID_HEIGHT as usize - 1] = invalid_block.clone();
+
+    // Ensure the verification halts at the double spend, without retrieving the blocks after it.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let mut num_retrieved = 0;
+    let blocks = invalid_chain.into_iter().inspect(|_| num_retrieved += 1).map(Ok);
+    let report = crate::verify_chain(&consensus, blocks, |_| ()).unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(num_retrieved, INVALID_HEIGHT);
+    assert_eq!(report.progress.height, INVALID_HEIGHT - 1);
+    assert_eq!(consensus.ledger.latest_height(), INVALID_HEIGHT - 1);
+
+    // Ensure the report pinpoints the double spend.
+    let invalid_block_report = report.invalid_block.unwrap();
+    assert_eq!(invalid_block_report.height, INVALID_HEIGHT);
+    assert_eq!(invalid_block_report.hash, invalid_block.hash());
+    assert!(invalid_block_report.failures().any(|check| {
+        check.stage == crate::ReplayStage::Transaction(double_spend.id()) && check.name == "double-spend"
+    }));
+}
+
+#[test]
+#[traced_test]
+fn test_sync_across_rule_activation() {
+    const ACTIVATION_HEIGHT: u32 = 3;
+
+    let rng = &mut TestRng::default();
+
+    // Sample a chain, in which each block splits a record.
+    let (blocks, _) = sample_chain(ACTIVATION_HEIGHT + 1, rng);
+
+    // Sunset the split circuit after the genesis block, under a rule that activates at the activation height.
+    let circuit_rules = crate::CircuitRules::new(&[("credits.aleo", "split", 0, 0)]).unwrap();
+    let rule_activations = crate::RuleActivations::new(&[
+        ("strict_structure", 0),
+        ("circuit_rules", ACTIVATION_HEIGHT),
+        ("intra_block_duplicates", 0),
+    ])
+    .unwrap();
+    let mut consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    consensus.set_circuit_rules(circuit_rules);
+    consensus.set_rule_activations(rule_activations);
+    assert!(!consensus.is_active(crate::ConsensusRule::CircuitRules, ACTIVATION_HEIGHT - 1));
+    assert!(consensus.is_active(crate::ConsensusRule::CircuitRules, ACTIVATION_HEIGHT));
+
+    // Ensure the blocks below the activation height are validated under the old rules,
+    // and the block at the activation height, which splits a record just as the block before it, is rejected.
+    let report = crate::verify_chain(&consensus, blocks.iter().cloned().map(Ok), |_| ()).unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(report.progress.height, ACTIVATION_HEIGHT - 1);
+    assert_eq!(report.latest_hash, blocks[ACTIVATION_HEIGHT as usize - 2].hash());
+    let invalid_block_report = report.invalid_block.unwrap();
+    assert_eq!(invalid_block_report.height, ACTIVATION_HEIGHT);
+    assert!(invalid_block_report.failures().any(|check| check.name == "structure"));
+    assert!(
+        invalid_block_report.failures().all(|check| check.error.as_ref().unwrap().contains("credits.aleo/split")),
+        "{invalid_block_report}"
+    );
+}
+
+/// Samples a branch of the given number of blocks on top of the latest block of the given consensus, in which each
+/// block splits the given record, and then the larger record of the split. Returns the blocks, along with the last
+/// transaction of the branch.
+fn sample_branch(
+    consensus: &crate::tests::test_helpers::CurrentConsensus,
+    mut record: Record<CurrentNetwork, Plaintext<CurrentNetwork>>,
+    num_blocks: u32,
+    rng: &mut TestRng,
+) -> (Vec<Block<CurrentNetwork>>, Transaction<CurrentNetwork>) {
+    // Sample the genesis private key and view key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+
+    let mut blocks = Vec::with_capacity(num_blocks as usize);
+    let mut transaction = None;
+    for _ in 0..num_blocks {
+        // Split the record, without a fee.
+        let inputs = [Value::Record(record), Value::from_str("1u64").unwrap()];
+        let split = Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            None,
+            None,
+            rng,
+        )
+        .unwrap();
+
+        // Produce the next block with the split.
+        consensus.add_unconfirmed_transaction(split.clone()).unwrap();
+        let block = consensus.propose_next_block(&private_key, rng).unwrap();
+        consensus.advance_to_next_block(&block).unwrap();
+        blocks.push(block);
+
+        // Select the larger record of the split.
+        let commitments = split.commitments().collect::<Vec<_>>();
+        record = consensus
+            .ledger
+            .find_records(&view_key, RecordsFilter::Unspent)
+            .unwrap()
+            .filter(|(commitment, _)| commitments.contains(&commitment))
+            .map(|(_, record)| record)
+            .max_by_key(microcredits)
+            .unwrap();
+        transaction = Some(split);
+    }
+    (blocks, transaction.unwrap())
+}
+
+/// Returns the unspent records of the genesis address, in the order of the ledger.
+fn sample_genesis_records(
+    consensus: &crate::tests::test_helpers::CurrentConsensus,
+    rng: &mut TestRng,
+) -> Vec<Record<CurrentNetwork, Plaintext<CurrentNetwork>>> {
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let view_key = ViewKey::try_from(private_key).unwrap();
+    consensus.ledger.find_records(&view_key, RecordsFilter::Unspent).unwrap().map(|(_, record)| record).collect()
+}
+
+/// Asserts the given consensus is consistent, and that its canonical chain ends with the given blocks.
+fn assert_consistent(consensus: &crate::tests::test_helpers::CurrentConsensus, blocks: &[Block<CurrentNetwork>]) {
+    // Ensure the indexes of the ledger are consistent with its blocks.
+    assert!(consensus.ledger.check_consistency(snarkos_node_ledger::ConsistencyCheck::Deep).unwrap().is_none());
+
+    // Ensure the canonical chain ends with the given blocks.
+    let tip = blocks.last().unwrap();
+    assert_eq!(consensus.ledger.latest_height(), tip.height());
+    assert_eq!(consensus.ledger.latest_hash(), tip.hash());
+    for block in blocks {
+        assert_eq!(consensus.ledger.get_hash(block.height()).unwrap(), block.hash());
+        for transaction in block.transactions().iter() {
+            // Ensure the transaction is committed, along with its serial numbers, and is no longer in the memory pool.
+            assert!(consensus.ledger.contains_transaction_id(&transaction.id()).unwrap());
+            for serial_number in transaction.serial_numbers() {
+                assert!(consensus.ledger.contains_serial_number(serial_number).unwrap());
+            }
+            assert!(!consensus.memory_pool.contains_unconfirmed_transaction(transaction.id()));
+        }
+    }
+}
+
+#[test]
+#[traced_test]
+fn test_reorganize() {
+    let rng = &mut TestRng::default();
+
+    // Sample two consensus instances, which share the genesis block, and the records of the genesis address.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let other_consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let records = sample_genesis_records(&consensus, rng);
+    assert!(records.len() >= 2);
+
+    // Sample a canonical chain of 50 blocks, and a competing branch of 51 blocks that spends another record.
+    let (canonical, last_transaction) = sample_branch(&consensus, records[0].clone(), 50, rng);
+    let (branch, _) = sample_branch(&other_consensus, records[1].clone(), 51, rng);
+    assert_consistent(&consensus, &canonical);
+
+    // Add a transaction that depends on the last block of the canonical chain to the memory pool.
+    let dependent = {
+        let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+        let view_key = ViewKey::try_from(private_key).unwrap();
+        let commitments = last_transaction.commitments().collect::<Vec<_>>();
+        let record = consensus
+            .ledger
+            .find_records(&view_key, RecordsFilter::Unspent)
+            .unwrap()
+            .find(|(commitment, _)| commitments.contains(&commitment))
+            .map(|(_, record)| record)
+            .unwrap();
+        let inputs = [Value::Record(record), Value::from_str("1u64").unwrap()];
+        Transaction::execute(
+            consensus.ledger.vm(),
+            &private_key,
+            ("credits.aleo", "split"),
+            inputs.iter(),
+            None,
+            None,
+            rng,
+        )
+        .unwrap()
+    };
+    consensus.add_unconfirmed_transaction(dependent.clone()).unwrap();
+
+    // Ensure the fork choice prefers the longer branch, as neither branch contains a coinbase solution.
+    assert!(consensus.is_preferred_branch(branch.last().unwrap()));
+    assert!(!consensus.is_preferred_branch(&branch[48]));
+    // Ensure a branch that does not extend the canonical chain is rejected.
+    assert!(consensus.reorganize(&branch[1..]).is_err());
+    assert_consistent(&consensus, &canonical);
+
+    // Reorganize to the branch.
+    consensus.reorganize(&branch).unwrap();
+    assert_consistent(&consensus, &branch);
+
+    // Ensure the transaction of the first removed block is restored to the memory pool, as it is anchored at the
+    // genesis block, and the transactions that depend on the removed blocks are not.
+    let first_transaction = canonical[0].transactions().iter().find(|transaction| !transaction.is_coinbase()).unwrap();
+    assert!(consensus.memory_pool.contains_unconfirmed_transaction(first_transaction.id()));
+    assert!(!consensus.memory_pool.contains_unconfirmed_transaction(last_transaction.id()));
+    assert!(!consensus.memory_pool.contains_unconfirmed_transaction(dependent.id()));
+    for block in &canonical {
+        assert!(!consensus.ledger.contains_block_hash(&block.hash()).unwrap());
+    }
+
+    // Ensure the canonical chain is no longer preferred.
+    assert!(!consensus.is_preferred_branch(canonical.last().unwrap()));
+}
+
+#[test]
+#[traced_test]
+fn test_reorganize_with_failed_commit() {
+    let rng = &mut TestRng::default();
+
+    // Sample a canonical chain of 4 blocks, and a competing branch of 6 blocks that spends another record.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let other_consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let records = sample_genesis_records(&consensus, rng);
+    let (canonical, _) = sample_branch(&consensus, records[0].clone(), 4, rng);
+    let (branch, _) = sample_branch(&other_consensus, records[1].clone(), 6, rng);
+
+    // Fail the commit of a block in the middle of the branch.
+    consensus.chaos().fail_commit_at(4);
+    assert!(consensus.reorganize(&branch).is_err());
+
+    // Ensure the canonical chain is restored, with its indexes.
+    assert_consistent(&consensus, &canonical);
+    for block in &branch[..3] {
+        assert!(!consensus.ledger.contains_block_hash(&block.hash()).unwrap());
+    }
+
+    // Ensure the reorganization succeeds once the failure has been injected.
+    consensus.reorganize(&branch).unwrap();
+    assert_consistent(&consensus, &branch);
+
+    // Ensure the latest blocks can be decommitted, as a reorganization would.
+    let decommitted = consensus.chaos().trigger_decommit(&consensus, 2).unwrap();
+    assert_eq!(decommitted, branch[4..]);
+    assert_consistent(&consensus, &branch[..4]);
+    assert!(consensus.chaos().trigger_decommit(&consensus, 5).is_err());
+    assert!(consensus.chaos().trigger_decommit(&consensus, 0).is_err());
+}
+
+#[test]
+#[traced_test]
+fn test_reorganize_back_and_forth() {
+    const NUM_REORGANIZATIONS: usize = 10;
+
+    let rng = &mut TestRng::default();
+
+    // Sample two short branches, which spend different records.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let other_consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let records = sample_genesis_records(&consensus, rng);
+    let (first, _) = sample_branch(&consensus, records[0].clone(), 2, rng);
+    let (second, _) = sample_branch(&other_consensus, records[1].clone(), 3, rng);
+
+    // Delay the writes, so that the reorganizations interleave with the commits as they would under load.
+    consensus.chaos().delay_writes(Some(std::time::Duration::from_millis(5)));
+
+    // Alternate between the branches, by forcing the fork choice to prefer each in turn.
+    for index in 0..NUM_REORGANIZATIONS {
+        let (branch, other) = if index % 2 == 0 { (&second, &first) } else { (&first, &second) };
+        // Ensure the branch is only reorganized to once it is preferred.
+        consensus.chaos().prefer_branch(Some(other.last().unwrap().hash()));
+        assert!(consensus.reorganize(branch).is_err());
+
+        consensus.chaos().prefer_branch(Some(branch.last().unwrap().hash()));
+        consensus.reorganize(branch).unwrap();
+        assert_consistent(&consensus, branch);
+        // Ensure the transactions of the other branch are no longer committed.
+        for transaction in other.iter().flat_map(|block| block.transactions().iter()) {
+            assert!(!consensus.ledger.contains_transaction_id(&transaction.id()).unwrap());
+        }
+    }
+
+    // Ensure the fork choice is restored, once the preference is removed.
+    consensus.chaos().prefer_branch(None);
+    consensus.chaos().delay_writes(None);
+    assert!(consensus.is_preferred_branch(second.last().unwrap()));
+}
+
+/// A policy hook that caps the number of transactions in a block template.
+struct TemplateCap(usize);
+
+impl crate::PolicyHook<CurrentNetwork> for TemplateCap {
+    fn name(&self) -> &str {
+        "template-cap"
+    }
+
+    fn validate_transaction(&self, _: &Transaction<CurrentNetwork>, _: crate::PolicyContext) -> crate::PolicyVerdict {
+        crate::PolicyVerdict::Accept
+    }
+
+    fn validate_block_template(
+        &self,
+        transactions: &[Transaction<CurrentNetwork>],
+        _: crate::PolicyContext,
+    ) -> crate::PolicyVerdict {
+        match transactions.len() > self.0 {
+            true => {
+                let reason = format!("the template is capped at {} transactions", self.0);
+                crate::PolicyVerdict::Reject { reason }
+            }
+            false => crate::PolicyVerdict::Accept,
+        }
+    }
+}
+
+#[test]
+#[traced_test]
+fn test_policy_rejected_transaction_is_valid_in_peer_block() {
+    let rng = &mut TestRng::default();
+
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Sample a consensus with a policy that only allows the sample program, and a peer consensus without a policy.
+    let mut consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let allowlist = crate::ProgramAllowlist::new(&["testing.aleo".to_string()]).unwrap();
+    consensus.register_policy_hook(Arc::new(allowlist));
+    assert_eq!(consensus.policy_hooks(), vec!["program-allowlist".to_string()]);
+    let peer_consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+
+    // Ensure an execution of `credits.aleo` is rejected by the policy at admission, with the reason of the hook.
+    let transaction = crate::tests::test_helpers::sample_execution_transaction(rng);
+    let error = consensus.add_unconfirmed_transaction(transaction.clone()).unwrap_err();
+    let rejection = error.downcast::<crate::TransactionRejection<CurrentNetwork>>().unwrap();
+    assert_eq!(rejection.kind(), "policy");
+    assert_eq!(rejection, crate::TransactionRejection::Policy {
+        hook: "program-allowlist".to_string(),
+        reason: "the program 'credits.aleo' is not in the allowlist".to_string(),
+    });
+
+    // Ensure the rejection is reported in a batch submission, and by a simulation.
+    let outcomes = consensus.add_unconfirmed_transactions(vec![transaction.clone()], crate::BatchMode::BestEffort);
+    assert_eq!(outcomes[0].rejection, Some(rejection.clone()));
+    let simulation = consensus.simulate_acceptance(&transaction, false).unwrap();
+    assert_eq!(simulation.rejections, vec![rejection]);
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 0);
+
+    // Ensure a transaction that bypasses the admission checks is still excluded from the block template.
+    consensus.memory_pool().add_unconfirmed_transaction(&transaction).unwrap();
+    assert!(consensus.block_template().unwrap().transactions.is_empty());
+
+    // Ensure the transaction is valid when it is received in the block of a peer without the policy.
+    peer_consensus.add_unconfirmed_transaction(transaction.clone()).unwrap();
+    let next_block = peer_consensus.propose_next_block(&private_key, rng).unwrap();
+    assert!(next_block.transactions().contains_key(&transaction.id()));
+    consensus.check_next_block(&next_block).unwrap();
+    consensus.advance_to_next_block(&next_block).unwrap();
+    assert!(consensus.ledger.contains_transaction_id(&transaction.id()).unwrap());
+}
+
+#[test]
+#[traced_test]
+fn test_policy_caps_block_template() {
+    let rng = &mut TestRng::default();
+
+    // Sample a consensus with a policy that caps the block template at a single transaction.
+    let mut consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    consensus.register_policy_hook(Arc::new(TemplateCap(1)));
+
+    // Add a deployment and an execution, which do not conflict, and are both admitted.
+    let deployment = crate::tests::test_helpers::sample_deployment_transaction(rng);
+    let execution = crate::tests::test_helpers::sample_execution_transaction(rng);
+    consensus.add_unconfirmed_transaction(deployment).unwrap();
+    consensus.add_unconfirmed_transaction(execution).unwrap();
+    assert_eq!(consensus.memory_pool().num_unconfirmed_transactions(), 2);
+
+    // Ensure the block template only selects a single transaction.
+    assert_eq!(consensus.block_template().unwrap().transactions.len(), 1);
+    assert_eq!(consensus.memory_pool().candidate_transactions(&consensus).len(), 1);
+}
+
+#[test]
+#[traced_test]
+fn test_bench_validation() {
+    let rng = &mut TestRng::default();
+
+    // Generate a chain on the genesis block.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let blocks = crate::generate_chain(&consensus, &private_key, 3, 2, rng).unwrap();
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(consensus.ledger.latest_height(), 3);
+    let num_transactions = blocks.iter().map(|block| block.transactions().len() as u64).sum::<u64>();
+    assert!(num_transactions >= 3);
+
+    // Ensure the chain is replayed with and without each optimization.
+    for (verification_cache, parallel) in [(true, true), (false, false)] {
+        let genesis = consensus.ledger.get_block(0).unwrap();
+        let ledger = crate::tests::test_helpers::CurrentLedger::load(genesis, None).unwrap();
+        let replica = crate::tests::test_helpers::CurrentConsensus::new(ledger, true).unwrap();
+        let options = crate::BenchOptions { verification_cache, parallel };
+        let report = crate::bench_validation(&replica, blocks.clone().into_iter().map(Ok), options).unwrap();
+        assert_eq!(report.num_blocks, 3);
+        assert_eq!(report.num_transactions, num_transactions);
+        assert_eq!(replica.ledger.latest_hash(), blocks[2].hash());
+        assert!(!report.proof_verification_time.is_zero());
+        assert!((0.0..=1.0).contains(&report.storage_share()));
+        if !parallel {
+            assert_eq!(report.num_threads, 1);
+        }
+    }
+
+    // Ensure the replay halts at an invalid block, which does not extend the ledger.
+    let genesis = consensus.ledger.get_block(0).unwrap();
+    let ledger = crate::tests::test_helpers::CurrentLedger::load(genesis, None).unwrap();
+    let replica = crate::tests::test_helpers::CurrentConsensus::new(ledger, true).unwrap();
+    let options = crate::BenchOptions::default();
+    assert!(crate::bench_validation(&replica, blocks[1..].iter().cloned().map(Ok), options).is_err());
+}
+
+#[test]
+#[traced_test]
+fn test_submit_duplicate_block() {
+    let rng = &mut TestRng::default();
+
+    // Generate a chain on the genesis block, and submit it to a replica.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let blocks = crate::generate_chain(&consensus, &private_key, 2, 1, rng).unwrap();
+    let genesis = consensus.ledger.get_block(0).unwrap();
+    let ledger = crate::tests::test_helpers::CurrentLedger::load(genesis, None).unwrap();
+    let replica = crate::tests::test_helpers::CurrentConsensus::new(ledger, true).unwrap();
+    for block in &blocks {
+        let outcome = replica.submit_block(block, crate::SubmissionSource::Rest).unwrap();
+        assert_eq!(outcome, crate::SubmissionOutcome::Accepted);
+    }
+    assert_eq!(replica.submitted_blocks().len(), 2);
+
+    // Ensure the second submission of a block is short-circuited, without verifying any proof.
+    let num_proof_verifications = replica.num_proof_verifications();
+    let outcome = replica.submit_block(&blocks[1], crate::SubmissionSource::Miner).unwrap();
+    assert_eq!(outcome, crate::SubmissionOutcome::Duplicate { block_hash: blocks[1].hash(), height: 2 });
+    assert_eq!(replica.num_proof_verifications(), num_proof_verifications);
+    assert_eq!(replica.submitted_blocks().num_duplicates(crate::SubmissionSource::Miner), 1);
+    assert_eq!(replica.submitted_blocks().num_duplicates(crate::SubmissionSource::Rest), 0);
+
+    // Invalidate the first block, and ensure the submissions of the removed blocks are forgotten.
+    assert_eq!(replica.invalidate_block(&blocks[0].hash()).unwrap(), blocks);
+    assert_eq!(replica.ledger.latest_height(), 0);
+    assert!(replica.submitted_blocks().is_empty());
+
+    // Ensure the block is validated again once it is submitted, and rejected as it was invalidated.
+    match replica.submit_block(&blocks[0], crate::SubmissionSource::Rest).unwrap() {
+        crate::SubmissionOutcome::Rejected(reason) => assert!(reason.contains("known to be invalid")),
+        outcome => panic!("Unexpected outcome {outcome:?}"),
+    }
+    // Ensure the block is fully processed, and accepted, once it is purged from the invalid blocks.
+    assert_eq!(replica.invalid_blocks().purge(Some(&blocks[0].hash())).unwrap(), 1);
+    let num_proof_verifications = replica.num_proof_verifications();
+    let outcome = replica.submit_block(&blocks[0], crate::SubmissionSource::Rest).unwrap();
+    assert_eq!(outcome, crate::SubmissionOutcome::Accepted);
+    assert!(replica.num_proof_verifications() > num_proof_verifications);
+    assert_eq!(replica.ledger.latest_hash(), blocks[0].hash());
+}
+
+#[test]
+#[traced_test]
+fn test_audit_corrupted_block() {
+    let rng = &mut TestRng::default();
+
+    // Generate a chain on the genesis block, and ensure each of its blocks passes its audit.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let blocks = crate::generate_chain(&consensus, &private_key, 3, 1, rng).unwrap();
+    for block in &blocks {
+        assert!(consensus.audit_block(block.height()).unwrap().is_valid());
+    }
+    assert!(!consensus.audit_log().has_failure());
+
+    // Corrupt the stored proof of a transition in the middle block, with the proof of a transition of the last block.
+    let transition_store = consensus.ledger.vm().transition_store();
+    let transition = blocks[1].transactions().iter().next().unwrap().transitions().next().unwrap();
+    let other = blocks[2].transactions().iter().next().unwrap().transitions().next().unwrap();
+    let corrupted = Transition::new(
+        *transition.program_id(),
+        *transition.function_name(),
+        transition.inputs().to_vec(),
+        transition.outputs().to_vec(),
+        transition.finalize().cloned(),
+        other.proof().clone(),
+        *transition.tpk(),
+        *transition.tcm(),
+    )
+    .unwrap();
+    assert_eq!(corrupted.id(), transition.id());
+    transition_store.remove(transition.id()).unwrap();
+    transition_store.insert(&corrupted).unwrap();
+
+    // Ensure the audit of the middle block detects the corrupted proof, and raises the failure flag.
+    let num_proof_verifications = consensus.num_proof_verifications();
+    let report = consensus.audit_block(blocks[1].height()).unwrap();
+    let failure = report.first_failure().unwrap();
+    assert_eq!(failure.name, "proof");
+    assert_eq!(failure.stage, crate::ReplayStage::Transaction(blocks[1].transactions().iter().next().unwrap().id()));
+    assert!(consensus.audit_log().has_failure());
+    let entry = consensus.audit_log().latest_failure().unwrap();
+    assert_eq!((entry.height, entry.hash), (blocks[1].height(), blocks[1].hash()));
+    // Ensure the other blocks still pass their audit.
+    assert!(consensus.audit_block(blocks[0].height()).unwrap().is_valid());
+    assert!(consensus.audit_block(blocks[2].height()).unwrap().is_valid());
+
+    // Ensure the audits left the processing of the blocks untouched.
+    assert_eq!(consensus.num_proof_verifications(), num_proof_verifications);
+    let next = crate::generate_chain(&consensus, &private_key, 1, 1, rng).unwrap();
+    assert_eq!(consensus.ledger.latest_hash(), next[0].hash());
+    assert!(consensus.audit_random_block(rng).unwrap().is_some());
+}
+
+/// Returns the metadata of the given block, with the given round, height, timestamp, and coinbase and proof targets.
+fn sample_metadata(
+    block: &Block<CurrentNetwork>,
+    round: u64,
+    height: u32,
+    timestamp: i64,
+    (coinbase_target, proof_target): (u64, u64),
+) -> Metadata<CurrentNetwork> {
+    Metadata::new(
+        block.network(),
+        round,
+        height,
+        block.total_supply_in_microcredits(),
+        block.cumulative_proof_target(),
+        coinbase_target,
+        proof_target,
+        block.last_coinbase_target(),
+        block.last_coinbase_timestamp(),
+        timestamp,
+    )
+    .unwrap()
+}
+
+/// Returns a copy of the given block with the given metadata, signed again on top of the given previous block.
+fn sample_resigned_block(
+    private_key: &PrivateKey<CurrentNetwork>,
+    block: &Block<CurrentNetwork>,
+    previous_hash: <CurrentNetwork as Network>::BlockHash,
+    metadata: Metadata<CurrentNetwork>,
+    rng: &mut TestRng,
+) -> Block<CurrentNetwork> {
+    let header = block.header();
+    let header = Header::from(
+        header.previous_state_root(),
+        header.transactions_root(),
+        header.finalize_root(),
+        header.coinbase_accumulator_point(),
+        metadata,
+    )
+    .unwrap();
+    Block::new(private_key, previous_hash, header, block.transactions().clone(), block.coinbase().cloned(), rng)
+        .unwrap()
+}
+
+#[test]
+#[traced_test]
+fn test_submit_block_prechecks() {
+    let rng = &mut TestRng::default();
+
+    // Generate a chain on the genesis block, and initialize a replica on the genesis block.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let blocks = crate::generate_chain(&consensus, &private_key, 2, 1, rng).unwrap();
+    let genesis = consensus.ledger.get_block(0).unwrap();
+    let ledger = crate::tests::test_helpers::CurrentLedger::load(genesis.clone(), None).unwrap();
+    let replica = crate::tests::test_helpers::CurrentConsensus::new(ledger, true).unwrap();
+
+    // Submits the given block, and returns its failed pre-check, ensuring no proof was verified.
+    let precheck = |block: &Block<CurrentNetwork>| {
+        let num_proof_verifications = replica.num_proof_verifications();
+        let outcome = replica.submit_block(block, crate::SubmissionSource::Rest).unwrap();
+        assert_eq!(replica.num_proof_verifications(), num_proof_verifications);
+        match outcome {
+            crate::SubmissionOutcome::PrecheckFailed(failure) => failure,
+            outcome => panic!("Unexpected outcome {outcome:?}"),
+        }
+    };
+
+    // Ensure a block whose previous block is unknown fails the pre-checks.
+    let failure = precheck(&blocks[1]);
+    assert_eq!(failure, crate::PrecheckFailure::UnknownParent { previous_hash: blocks[0].hash() });
+    assert_eq!(failure.code(), "unknown_parent");
+    let outcome = replica.submit_block(&blocks[0], crate::SubmissionSource::Rest).unwrap();
+    assert_eq!(outcome, crate::SubmissionOutcome::Accepted);
+
+    // Ensure a block on a canonical block below the latest block fails the pre-checks.
+    let (latest, next) = (&blocks[0], &blocks[1]);
+    let targets = (next.coinbase_target(), next.proof_target());
+    let metadata = sample_metadata(latest, latest.round(), latest.height(), latest.timestamp() + 1, targets);
+    let stale = sample_resigned_block(&private_key, latest, genesis.hash(), metadata, rng);
+    assert_eq!(precheck(&stale), crate::PrecheckFailure::StaleParent {
+        previous_hash: genesis.hash(),
+        parent_height: 0,
+        latest_height: 1
+    });
+
+    // Ensure the header fields of a block on the latest block are checked.
+    let (round, height, timestamp) = (next.round(), next.height(), next.timestamp());
+    let mut resign = |metadata| sample_resigned_block(&private_key, next, latest.hash(), metadata, rng);
+    let block = resign(sample_metadata(next, round, height + 1, timestamp, targets));
+    assert_eq!(precheck(&block), crate::PrecheckFailure::IncorrectHeight { height: height + 1, expected: height });
+    let block = resign(sample_metadata(next, round + 1, height, timestamp, targets));
+    assert_eq!(precheck(&block), crate::PrecheckFailure::IncorrectRound { round: round + 1, expected: round });
+    let block = resign(sample_metadata(next, round, height, latest.timestamp(), targets));
+    assert_eq!(precheck(&block), crate::PrecheckFailure::StaleTimestamp {
+        timestamp: latest.timestamp(),
+        latest_timestamp: latest.timestamp()
+    });
+    let future = replica.ledger.network_time().now() + 2 * crate::MAXIMUM_FUTURE_BLOCK_TIME_IN_SECS;
+    let block = resign(sample_metadata(next, round, height, future, targets));
+    assert!(
+        matches!(precheck(&block), crate::PrecheckFailure::FutureTimestamp { timestamp, .. } if timestamp == future)
+    );
+    let block = resign(sample_metadata(next, round, height, timestamp, (targets.0 + 1, targets.1)));
+    assert_eq!(precheck(&block), crate::PrecheckFailure::IncorrectCoinbaseTarget {
+        coinbase_target: targets.0 + 1,
+        expected: targets.0
+    });
+    let block = resign(sample_metadata(next, round, height, timestamp, (targets.0, targets.1 + 1)));
+    assert_eq!(precheck(&block), crate::PrecheckFailure::IncorrectProofTarget {
+        proof_target: targets.1 + 1,
+        expected: targets.1
+    });
+
+    // Ensure the declared contents of the block are checked against the limits.
+    let limits = replica.precheck_limits();
+    replica.set_precheck_limits(crate::PrecheckLimits { max_transactions: 0, ..limits });
+    assert_eq!(precheck(next), crate::PrecheckFailure::TooManyTransactions { num_transactions: 1, maximum: 0 });
+    replica.set_precheck_limits(crate::PrecheckLimits { max_block_size: 1_000, ..limits });
+    let size = next.to_bytes_le().unwrap().len();
+    assert_eq!(precheck(next), crate::PrecheckFailure::OversizedBlock { size, maximum: 1_000 });
+    replica.set_precheck_limits(limits);
+
+    // Ensure the valid next block passes the pre-checks, and is accepted.
+    assert_eq!(replica.precheck_block(next).unwrap(), None);
+    let outcome = replica.submit_block(next, crate::SubmissionSource::Miner).unwrap();
+    assert_eq!(outcome, crate::SubmissionOutcome::Accepted);
+
+    // Ensure a block with two coinbase transactions fails the pre-checks.
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    consensus.add_unconfirmed_transaction(sample_coinbase_transaction(&consensus, 1, rng)).unwrap();
+    consensus.add_unconfirmed_transaction(sample_coinbase_transaction(&consensus, 2, rng)).unwrap();
+    let next_block = consensus.propose_next_block(&private_key, rng).unwrap();
+    let reason = crate::BlockRejection::<CurrentNetwork>::MultipleCoinbases { num_coinbases: 2 }.to_string();
+    assert_eq!(consensus.precheck_block(&next_block).unwrap(), Some(crate::PrecheckFailure::InvalidCoinbase(reason)));
+}
+
+#[test]
+#[traced_test]
+fn test_submit_block_async() {
+    let rng = &mut TestRng::default();
+
+    // Generate a chain on the genesis block, and initialize a replica on the genesis block.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    let consensus = crate::tests::test_helpers::sample_genesis_consensus(rng);
+    let blocks = crate::generate_chain(&consensus, &private_key, 2, 1, rng).unwrap();
+    let genesis = consensus.ledger.get_block(0).unwrap();
+    let ledger = crate::tests::test_helpers::CurrentLedger::load(genesis, None).unwrap();
+    let replica = crate::tests::test_helpers::CurrentConsensus::new(ledger, true).unwrap();
+    let submissions = replica.async_submissions();
+
+    // Ensure a block that fails the pre-checks is reported immediately, without a submission ID.
+    match replica.submit_block_async(blocks[1].clone(), crate::SubmissionSource::Rest).unwrap() {
+        crate::SubmissionReceipt::Completed(crate::SubmissionOutcome::PrecheckFailed(failure)) => {
+            assert_eq!(failure.code(), "unknown_parent")
+        }
+        receipt => panic!("Unexpected receipt {receipt:?}"),
+    }
+    assert_eq!(submissions.num_pending(), 0);
+
+    // Submit the blocks in order, and ensure each submission completes with the advance of the canonical chain.
+    for block in &blocks {
+        let id = match replica.submit_block_async(block.clone(), crate::SubmissionSource::Rest).unwrap() {
+            crate::SubmissionReceipt::Pending(id) => id,
+            receipt => panic!("Unexpected receipt {receipt:?}"),
+        };
+        assert_eq!(submissions.get(id).unwrap().block_hash(), &block.hash());
+        let status = block_on(submissions.wait(id, std::time::Duration::from_secs(600))).unwrap();
+        let completed = crate::SubmissionStatus::Completed {
+            block_hash: block.hash(),
+            outcome: crate::SubmissionOutcome::Accepted,
+        };
+        assert_eq!(status, completed);
+        assert_eq!(replica.ledger.latest_hash(), block.hash());
+        // Ensure the verdict remains available to a later poll.
+        assert_eq!(submissions.get(id), Some(completed));
+    }
+    assert_eq!(submissions.num_pending(), 0);
+    assert_eq!(replica.submitted_blocks().len(), 2);
+
+    // Ensure a resubmission is reported as a duplicate, and an unknown submission is not found.
+    let receipt = replica.submit_block_async(blocks[1].clone(), crate::SubmissionSource::Miner).unwrap();
+    let duplicate = crate::SubmissionOutcome::Duplicate { block_hash: blocks[1].hash(), height: 2 };
+    assert_eq!(receipt, crate::SubmissionReceipt::Completed(duplicate));
+    assert_eq!(block_on(submissions.wait(u64::MAX, std::time::Duration::ZERO)), None);
 }
@@ -0,0 +1,118 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{replay_block, Consensus, ReplayReport};
+use snarkvm::prelude::{Block, ConsensusStorage, Network};
+
+use anyhow::Result;
+use core::{fmt, time::Duration};
+use std::time::Instant;
+
+/// The progress of a chain verification, reported after each verified block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerifyProgress {
+    /// The height of the latest verified block.
+    pub height: u32,
+    /// The number of blocks verified so far.
+    pub num_blocks: u32,
+    /// The number of transactions verified so far.
+    pub num_transactions: u64,
+    /// The time elapsed since the start of the verification.
+    pub elapsed: Duration,
+}
+
+/// The report of a chain streamed through the validation pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainReport<N: Network> {
+    /// The progress of the verification, up to the last valid block.
+    pub progress: VerifyProgress,
+    /// The hash of the last valid block.
+    pub latest_hash: N::BlockHash,
+    /// The report of the first invalid block, at which the verification halted, if any.
+    pub invalid_block: Option<ReplayReport<N>>,
+}
+
+impl<N: Network> ChainReport<N> {
+    /// Returns `true` if every block of the chain is valid.
+    pub const fn is_valid(&self) -> bool {
+        self.invalid_block.is_none()
+    }
+}
+
+impl<N: Network> fmt::Display for ChainReport<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let progress = &self.progress;
+        writeln!(
+            f,
+            "Verified {} blocks and {} transactions in {} s, up to block {} ({})",
+            progress.num_blocks,
+            progress.num_transactions,
+            progress.elapsed.as_secs(),
+            progress.height,
+            self.latest_hash
+        )?;
+        match &self.invalid_block {
+            Some(report) => write!(f, "The chain is invalid at block {}\n{report}", report.height),
+            None => write!(f, "The chain is valid"),
+        }
+    }
+}
+
+/// Streams the given blocks through the full validation pipeline of the given consensus, and commits each
+/// valid block to its ledger, so that the next block is validated against the state it extends.
+///
+/// The verification halts at the first invalid block, whose report pinpoints every check that failed.
+/// The given function is called with the progress after each valid block. An error is only returned
+/// if a block could not be retrieved from the source, or a valid block could not be committed.
+pub fn verify_chain<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    blocks: impl IntoIterator<Item = Result<Block<N>>>,
+    mut on_progress: impl FnMut(&VerifyProgress),
+) -> Result<ChainReport<N>> {
+    // Start a timer.
+    let timer = Instant::now();
+    let mut progress = VerifyProgress {
+        height: consensus.ledger.latest_height(),
+        num_blocks: 0,
+        num_transactions: 0,
+        elapsed: timer.elapsed(),
+    };
+    let mut latest_hash = consensus.ledger.latest_hash();
+
+    for block in blocks {
+        let block = block?;
+
+        // Replay the block, and halt at the first invalid block.
+        let report = replay_block(consensus, &block);
+        if !report.is_valid() {
+            progress.elapsed = timer.elapsed();
+            return Ok(ChainReport { progress, latest_hash, invalid_block: Some(report) });
+        }
+
+        // Commit the block, so that the next block is validated against it.
+        consensus.ledger.add_next_block(&block)?;
+
+        // Report the progress.
+        progress.height = block.height();
+        progress.num_blocks += 1;
+        progress.num_transactions += block.transactions().len() as u64;
+        progress.elapsed = timer.elapsed();
+        latest_hash = block.hash();
+        on_progress(&progress);
+    }
+
+    Ok(ChainReport { progress, latest_hash, invalid_block: None })
+}
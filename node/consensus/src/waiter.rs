@@ -0,0 +1,130 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Consensus;
+use snarkos_node_ledger::{Event, Ledger, Subscription, Topic};
+use snarkvm::prelude::{ConsensusStorage, Field, Network};
+
+use anyhow::{ensure, Result};
+use core::time::Duration;
+use tokio::time::Instant;
+
+/// The outcome of waiting for a transaction to be included in the ledger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionStatus<N: Network> {
+    /// The transaction reached the requested confirmation depth.
+    Confirmed { block_hash: N::BlockHash, confirmations: u32 },
+    /// A conflicting transaction, which spends a record shared with the transaction, was confirmed first.
+    Conflicted { transaction_id: N::TransactionID },
+    /// The timeout elapsed before the transaction reached the requested confirmation depth.
+    TimedOut { confirmations: u32 },
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Consensus<N, C> {
+    /// Returns a subscription to the blocks that are connected to, or disconnected from, the ledger.
+    pub fn subscribe_to_blocks(&self) -> Subscription<N> {
+        self.ledger.event_bus().subscribe(&[Topic::BlockConnected, Topic::BlockDisconnected])
+    }
+
+    /// Registers a waiter for the given transaction to reach the given number of confirmations.
+    ///
+    /// The waiter is subscribed to the blocks before it first inspects the ledger,
+    /// so that no commit is missed between the registration and the wait.
+    pub fn register_transaction_waiter(
+        &self,
+        transaction_id: N::TransactionID,
+        min_confirmations: u32,
+    ) -> Result<TransactionWaiter<N, C>> {
+        // Ensure the number of confirmations is nonzero.
+        ensure!(min_confirmations > 0, "The number of confirmations must be at least 1");
+        // Subscribe to the blocks.
+        let blocks = self.subscribe_to_blocks();
+        // Retrieve the serial numbers of the transaction, if it is in the memory pool.
+        let serial_numbers = match self.memory_pool.unconfirmed_transaction(&transaction_id) {
+            Some(transaction) => transaction.serial_numbers().copied().collect(),
+            None => Vec::new(),
+        };
+        Ok(TransactionWaiter { ledger: self.ledger.clone(), transaction_id, min_confirmations, serial_numbers, blocks })
+    }
+}
+
+/// A waiter for a transaction to reach a number of confirmations.
+pub struct TransactionWaiter<N: Network, C: ConsensusStorage<N>> {
+    /// The ledger.
+    ledger: Ledger<N, C>,
+    /// The ID of the transaction.
+    transaction_id: N::TransactionID,
+    /// The number of confirmations to wait for.
+    min_confirmations: u32,
+    /// The serial numbers of the transaction, used to detect conflicting transactions.
+    serial_numbers: Vec<Field<N>>,
+    /// The subscription to the connected and disconnected blocks.
+    blocks: Subscription<N>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> TransactionWaiter<N, C> {
+    /// Waits until the transaction reaches the requested number of confirmations, a conflicting
+    /// transaction is confirmed, or the given timeout elapses, without blocking the thread.
+    ///
+    /// The status is re-derived from the ledger after every connected or disconnected block, so if the transaction
+    /// is un-confirmed (e.g. by a reorg), the wait resumes until it is confirmed again.
+    /// No consensus lock is held while waiting.
+    pub async fn wait(self, timeout: Duration) -> Result<TransactionStatus<N>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Determine the current status of the transaction.
+            let confirmations = match self.status()? {
+                TransactionStatus::TimedOut { confirmations } => confirmations,
+                status => return Ok(status),
+            };
+            // Wait for the next connected or disconnected block.
+            match tokio::time::timeout_at(deadline, self.blocks.recv()).await {
+                Ok(Event::BlockConnected { height, .. } | Event::BlockDisconnected { height, .. }) => {
+                    trace!("Re-checking transaction '{}' after block {height}", self.transaction_id)
+                }
+                Ok(_) => continue,
+                Err(_) => return Ok(TransactionStatus::TimedOut { confirmations }),
+            }
+        }
+    }
+
+    /// Returns the current status of the transaction in the ledger, where `TimedOut` indicates
+    /// that the requested number of confirmations has not been reached yet.
+    fn status(&self) -> Result<TransactionStatus<N>> {
+        // If the transaction is in the ledger, compute its number of confirmations.
+        if let Some(block_hash) = self.ledger.find_block_hash(&self.transaction_id)? {
+            let height = self.ledger.get_height(&block_hash)?;
+            let confirmations = self.ledger.latest_height().saturating_sub(height) + 1;
+            return match confirmations >= self.min_confirmations {
+                true => Ok(TransactionStatus::Confirmed { block_hash, confirmations }),
+                false => Ok(TransactionStatus::TimedOut { confirmations }),
+            };
+        }
+        // Otherwise, check if a conflicting transaction spent one of its records.
+        for serial_number in &self.serial_numbers {
+            if self.ledger.contains_serial_number(serial_number)? {
+                let transition_id = self.ledger.find_transition_id(serial_number)?;
+                match self.ledger.find_transaction_id_from_transition_id(&transition_id)? {
+                    // The transaction was committed since the check above, so re-check it on the next block.
+                    Some(transaction_id) if transaction_id == self.transaction_id => break,
+                    Some(transaction_id) => return Ok(TransactionStatus::Conflicted { transaction_id }),
+                    None => continue,
+                }
+            }
+        }
+        Ok(TransactionStatus::TimedOut { confirmations: 0 })
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_ledger::SerialNumberFilter;
+use snarkvm::prelude::{Field, Testnet3, Uniform};
+
+use std::{collections::HashSet, time::Instant};
+
+type CurrentNetwork = Testnet3;
+
+/// The number of spent serial numbers in the synthetic chain.
+const NUM_SERIAL_NUMBERS: usize = 5_000_000;
+/// The number of lookups per run.
+const NUM_LOOKUPS: usize = 1_000_000;
+
+/// Returns the average latency (in nanoseconds) of looking up each of the given serial numbers.
+fn lookup_latency(serial_numbers: &[Field<CurrentNetwork>], contains: impl Fn(&Field<CurrentNetwork>) -> bool) -> f64 {
+    let timer = Instant::now();
+    let num_found = serial_numbers.iter().filter(|serial_number| contains(serial_number)).count();
+    let elapsed = timer.elapsed();
+    std::hint::black_box(num_found);
+    elapsed.as_nanos() as f64 / serial_numbers.len() as f64
+}
+
+fn main() {
+    let rng = &mut rand::thread_rng();
+
+    // Sample the spent serial numbers of the synthetic chain, and the serial numbers of new transactions.
+    let spent = (0..NUM_SERIAL_NUMBERS).map(|_| Field::<CurrentNetwork>::rand(rng)).collect::<Vec<_>>();
+    let unspent = (0..NUM_LOOKUPS).map(|_| Field::<CurrentNetwork>::rand(rng)).collect::<Vec<_>>();
+
+    // Build the set of the spent serial numbers.
+    let timer = Instant::now();
+    let set = spent.iter().copied().collect::<HashSet<_>>();
+    let set_build = timer.elapsed();
+    // Estimate the size of the set, as a slot and a control byte per bucket.
+    let set_bytes = set.capacity() * (core::mem::size_of::<Field<CurrentNetwork>>() + 1);
+
+    // Build the filter of the spent serial numbers, with room for twice the number of serial numbers, as the ledger does.
+    let timer = Instant::now();
+    let mut filter = SerialNumberFilter::<CurrentNetwork>::with_capacity(NUM_SERIAL_NUMBERS * 2);
+    for serial_number in &spent {
+        assert!(filter.insert(serial_number));
+    }
+    let filter_build = timer.elapsed();
+
+    println!("{NUM_SERIAL_NUMBERS} spent serial numbers");
+    println!("set:    {:>8.1} MiB, built in {:>6} ms", set_bytes as f64 / (1 << 20) as f64, set_build.as_millis());
+    println!(
+        "filter: {:>8.1} MiB, built in {:>6} ms ({:.1}x smaller)",
+        filter.size_in_bytes() as f64 / (1 << 20) as f64,
+        filter_build.as_millis(),
+        set_bytes as f64 / filter.size_in_bytes() as f64
+    );
+
+    // Look up the unspent serial numbers, which the filter rules out, and the spent serial numbers,
+    // which the filter reports as maybe spent, and the ledger confirms in the index.
+    let spent_lookups = &spent[..NUM_LOOKUPS];
+    println!(
+        "unspent lookups: set {:>6.1} ns, filter {:>6.1} ns",
+        lookup_latency(&unspent, |serial_number| set.contains(serial_number)),
+        lookup_latency(&unspent, |serial_number| filter.contains(serial_number))
+    );
+    println!(
+        "spent lookups:   set {:>6.1} ns, filter {:>6.1} ns (before the index is read)",
+        lookup_latency(spent_lookups, |serial_number| set.contains(serial_number)),
+        lookup_latency(spent_lookups, |serial_number| filter.contains(serial_number))
+    );
+
+    // Report the false positives, which are resolved by the index.
+    let num_false_positives = unspent.iter().filter(|serial_number| filter.contains(serial_number)).count();
+    println!(
+        "false positives: {num_false_positives} of {NUM_LOOKUPS} ({:.4}%)",
+        num_false_positives as f64 * 100.0 / NUM_LOOKUPS as f64
+    );
+}
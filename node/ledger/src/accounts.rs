@@ -0,0 +1,209 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+/// The direction of a record in the history of an account.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordDirection {
+    /// The record was created for the account.
+    Received,
+    /// The record of the account was spent.
+    Spent,
+}
+
+/// An entry in the history of an account, for a transaction that created or spent a record of the account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountHistoryEntry<N: Network> {
+    /// The height of the block containing the transaction.
+    pub height: u32,
+    /// The ID of the transaction.
+    pub transaction_id: N::TransactionID,
+    /// Whether the record was received or spent by the transaction.
+    pub direction: RecordDirection,
+    /// The commitment of the record.
+    pub commitment: Field<N>,
+    /// The amount of microcredits of the record, if it could be decrypted as a credits record.
+    pub amount: Option<u64>,
+}
+
+/// A page of the history of an account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountHistoryPage<N: Network> {
+    /// The latest block height, at the time of the page.
+    pub latest_height: u32,
+    /// The number of entries in the history of the account.
+    pub num_entries: usize,
+    /// The entries of the page, in order of height.
+    pub entries: Vec<AccountHistoryEntry<N>>,
+}
+
+/// The history of a registered account, which is kept in memory, and follows the canonical chain.
+#[derive(Clone)]
+pub(crate) struct AccountHistory<N: Network> {
+    /// The view key of the account.
+    view_key: ViewKey<N>,
+    /// The x-coordinate of the address of the account.
+    address_x_coordinate: Field<N>,
+    /// The `sk_tag` of the graph key of the account, which derives the tags of its records.
+    sk_tag: Field<N>,
+    /// The entries, in order of height.
+    entries: Vec<AccountHistoryEntry<N>>,
+    /// The index of the received entry of each record of the account, by the tag that spends the record.
+    received: IndexMap<Field<N>, usize>,
+}
+
+impl<N: Network> AccountHistory<N> {
+    /// Initializes an empty history for the given view key.
+    fn new(view_key: ViewKey<N>) -> Result<Self> {
+        let sk_tag = match GraphKey::try_from(&view_key) {
+            Ok(graph_key) => graph_key.sk_tag(),
+            Err(e) => bail!("Failed to derive the graph key from the view key: {e}"),
+        };
+        let address_x_coordinate = view_key.to_address().to_x_coordinate();
+        Ok(Self { view_key, address_x_coordinate, sk_tag, entries: Vec::new(), received: IndexMap::new() })
+    }
+
+    /// Appends the records of the account that are spent or received by the given block.
+    /// The spends of a transaction are recorded before its outputs, and are joined against the received records.
+    fn push_block(&mut self, block: &Block<N>) {
+        let height = block.height();
+        for transaction in block.transactions().iter() {
+            for tag in transaction.tags() {
+                if let Some(index) = self.received.get(tag) {
+                    let received = &self.entries[*index];
+                    let entry = AccountHistoryEntry {
+                        height,
+                        transaction_id: transaction.id(),
+                        direction: RecordDirection::Spent,
+                        commitment: received.commitment,
+                        amount: received.amount,
+                    };
+                    self.entries.push(entry);
+                }
+            }
+            for (commitment, record) in transaction.records() {
+                if !record.is_owner_with_address_x_coordinate(&self.view_key, &self.address_x_coordinate) {
+                    continue;
+                }
+                match Record::<N, Plaintext<N>>::tag(self.sk_tag, *commitment) {
+                    Ok(tag) => {
+                        self.received.insert(tag, self.entries.len());
+                    }
+                    Err(e) => warn!("Failed to derive the tag of record '{commitment}': {e}"),
+                }
+                let amount = record.decrypt(&self.view_key).ok().and_then(|record| microcredits(&record));
+                self.entries.push(AccountHistoryEntry {
+                    height,
+                    transaction_id: transaction.id(),
+                    direction: RecordDirection::Received,
+                    commitment: *commitment,
+                    amount,
+                });
+            }
+        }
+    }
+
+    /// Returns `true` if the given transaction spends a received record of the account, or creates a record
+    /// owned by the account.
+    pub(crate) fn is_affected_by(&self, transaction: &Transaction<N>) -> bool {
+        transaction.tags().any(|tag| self.received.contains_key(tag))
+            || transaction.records().any(|(_, record)| {
+                record.is_owner_with_address_x_coordinate(&self.view_key, &self.address_x_coordinate)
+            })
+    }
+
+    /// Removes the entries at or above the given height, as the blocks were removed from the canonical chain.
+    fn truncate(&mut self, height: u32) {
+        let num_entries = self.entries.partition_point(|entry| entry.height < height);
+        self.entries.truncate(num_entries);
+        self.received.retain(|_, index| *index < num_entries);
+    }
+}
+
+/// Returns the amount of microcredits of the given record, if it is a credits record.
+fn microcredits<N: Network>(record: &Record<N, Plaintext<N>>) -> Option<u64> {
+    match record.data().get(&Identifier::from_str("microcredits").ok()?) {
+        Some(Entry::Private(Plaintext::Literal(Literal::U64(amount), _))) => Some(**amount),
+        _ => None,
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Registers the account of the given view key, and returns its address. The history of the account is
+    /// scanned from the genesis block, and then follows the canonical chain, until the node restarts.
+    pub fn register_account(&self, view_key: ViewKey<N>) -> Result<Address<N>> {
+        let address = view_key.to_address();
+        // Acquire the commit lock, so that no block is added or removed during the scan.
+        let _commit_lock = self.commit_lock.lock();
+        if self.account_histories.read().contains_key(&address) {
+            return Ok(address);
+        }
+        // Ensure the blocks are not pruned, as their records are scanned.
+        let pruned_height = self.pruned_height();
+        ensure!(
+            pruned_height == 0,
+            "Cannot scan the history of '{address}', the blocks below {pruned_height} are pruned"
+        );
+
+        // Scan the canonical chain for the records of the account.
+        let mut history = AccountHistory::new(view_key)?;
+        for height in 0..=self.latest_height() {
+            history.push_block(&self.get_block(height)?);
+        }
+        debug!("Registered the account '{address}', with {} entries in its history", history.entries.len());
+        self.account_histories.write().insert(address, history);
+        Ok(address)
+    }
+
+    /// Unregisters the account of the given address, and returns `true` if it was registered.
+    pub fn unregister_account(&self, address: &Address<N>) -> bool {
+        self.account_histories.write().shift_remove(address).is_some()
+    }
+
+    /// Returns the given page of the history of the given registered account, starting at the given offset.
+    pub fn get_account_history(
+        &self,
+        address: &Address<N>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<AccountHistoryPage<N>> {
+        // Retrieve the latest height first, as the histories are never locked with the current block.
+        let latest_height = self.latest_height();
+        let histories = self.account_histories.read();
+        let history = histories.get(address).ok_or_else(|| anyhow!("The account '{address}' is not registered"))?;
+        Ok(AccountHistoryPage {
+            latest_height,
+            num_entries: history.entries.len(),
+            entries: history.entries.iter().skip(offset).take(limit).cloned().collect(),
+        })
+    }
+
+    /// Appends the given block, which was just added, to the histories of the registered accounts.
+    pub(crate) fn push_account_histories(&self, block: &Block<N>) {
+        for history in self.account_histories.write().values_mut() {
+            history.push_block(block);
+        }
+    }
+
+    /// Removes the entries at or above the given height, which were just removed, from the histories
+    /// of the registered accounts.
+    pub(crate) fn truncate_account_histories(&self, height: u32) {
+        for history in self.account_histories.write().values_mut() {
+            history.truncate(height);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use core::fmt;
+use indexmap::IndexSet;
+
+/// The depth below the latest block, beyond which the side branches are discarded.
+pub const MAX_SIDE_BRANCH_DEPTH: u32 = 4096;
+/// The maximum number of side blocks, beyond which the oldest side block is discarded.
+pub const MAX_SIDE_BLOCKS: usize = 4096;
+/// The maximum number of side blocks at the same height, beyond which the oldest of them is discarded.
+pub const MAX_SIDE_BLOCKS_PER_HEIGHT: usize = 8;
+
+/// The status of a chain tip.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChainTipStatus {
+    /// The tip of the canonical chain.
+    Active,
+    /// The tip of a side branch, whose blocks were all validated in the canonical chain.
+    ValidFork,
+    /// The tip of a side branch, which contains a block that is invalid.
+    Invalid,
+    /// The tip of a side branch, which contains a block that was stored without being validated.
+    HeadersOnly,
+}
+
+impl fmt::Display for ChainTipStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Active => write!(f, "active"),
+            Self::ValidFork => write!(f, "valid-fork"),
+            Self::Invalid => write!(f, "invalid"),
+            Self::HeadersOnly => write!(f, "headers-only"),
+        }
+    }
+}
+
+/// A chain tip known to the ledger.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChainTip<N: Network> {
+    /// The height of the tip.
+    pub height: u32,
+    /// The block hash of the tip.
+    pub hash: N::BlockHash,
+    /// The number of blocks from the fork point with the canonical chain to the tip (0 for the canonical tip).
+    pub branch_length: u32,
+    /// The cumulative proof target at the tip.
+    pub cumulative_proof_target: u128,
+    /// The status of the tip.
+    pub status: ChainTipStatus,
+}
+
+/// A block of a side branch, which is not in the canonical chain.
+#[derive(Copy, Clone, Debug)]
+struct SideBlock<N: Network> {
+    /// The height of the block.
+    height: u32,
+    /// The block hash of the previous block.
+    previous_hash: N::BlockHash,
+    /// The cumulative proof target of the block.
+    cumulative_proof_target: u128,
+    /// Whether the block was validated, as it was in the canonical chain.
+    is_validated: bool,
+}
+
+/// The side branches known to the ledger, which are formed by the blocks that are not in the canonical chain.
+///
+/// The side blocks are bounded in total, and at each height, by discarding the oldest side blocks first.
+#[derive(Clone, Debug)]
+pub(crate) struct SideBranches<N: Network> {
+    /// The mapping of `block hash` to the side block, in the order in which they were inserted.
+    blocks: IndexMap<N::BlockHash, SideBlock<N>>,
+    /// The block hashes of the side blocks that are invalid.
+    invalid: IndexSet<N::BlockHash>,
+}
+
+impl<N: Network> Default for SideBranches<N> {
+    fn default() -> Self {
+        Self { blocks: Default::default(), invalid: Default::default() }
+    }
+}
+
+impl<N: Network> SideBranches<N> {
+    /// Returns the number of side blocks.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns `true` if the given block is a side block.
+    #[cfg(test)]
+    pub(crate) fn contains(&self, hash: &N::BlockHash) -> bool {
+        self.blocks.contains_key(hash)
+    }
+
+    /// Inserts the given header as a side block, keeping its validation if it is already known.
+    /// Note that the oldest side block is discarded if the side blocks at its height, or in total, are full.
+    pub(crate) fn insert(
+        &mut self,
+        hash: N::BlockHash,
+        previous_hash: N::BlockHash,
+        header: &Header<N>,
+        is_validated: bool,
+    ) {
+        if let Some(side_block) = self.blocks.get_mut(&hash) {
+            side_block.is_validated |= is_validated;
+            return;
+        }
+        let height = header.height();
+        // Discard the oldest side block at the height, if the height is full.
+        let num_blocks_at_height = self.blocks.values().filter(|side_block| side_block.height == height).count();
+        if num_blocks_at_height >= MAX_SIDE_BLOCKS_PER_HEIGHT {
+            if let Some(index) = self.blocks.values().position(|side_block| side_block.height == height) {
+                self.discard(index);
+            }
+        }
+        // Discard the oldest side block, if the side blocks are full.
+        if self.blocks.len() >= MAX_SIDE_BLOCKS {
+            self.discard(0);
+        }
+        let block = SideBlock {
+            height,
+            previous_hash,
+            cumulative_proof_target: header.cumulative_proof_target(),
+            is_validated,
+        };
+        self.blocks.insert(hash, block);
+    }
+
+    /// Discards the side block at the given index.
+    fn discard(&mut self, index: usize) {
+        if let Some((hash, _)) = self.blocks.shift_remove_index(index) {
+            self.invalid.shift_remove(&hash);
+        }
+    }
+
+    /// Removes the given block, which is now in the canonical chain, and discards the side blocks
+    /// that are deeper than the maximum side branch depth.
+    fn connect(&mut self, hash: &N::BlockHash, height: u32) {
+        self.blocks.shift_remove(hash);
+        self.invalid.shift_remove(hash);
+        let minimum_height = height.saturating_sub(MAX_SIDE_BRANCH_DEPTH);
+        self.blocks.retain(|_, side_block| side_block.height >= minimum_height);
+        let blocks = &self.blocks;
+        self.invalid.retain(|hash| blocks.contains_key(hash));
+    }
+
+    /// Returns the tips of the side branches, in descending order of height.
+    fn tips(&self) -> Vec<ChainTip<N>> {
+        // Determine the side blocks that are the parent of another side block.
+        let parents = self.blocks.values().map(|side_block| side_block.previous_hash).collect::<IndexSet<_>>();
+
+        let mut tips = self
+            .blocks
+            .iter()
+            .filter(|(hash, _)| !parents.contains(*hash))
+            .map(|(hash, tip)| {
+                // Walk the branch back to the fork point, which is the first block that is not a side block.
+                let (mut branch_length, mut is_invalid, mut is_validated) = (0, false, true);
+                let mut current = *hash;
+                while let Some(side_block) = self.blocks.get(&current) {
+                    branch_length += 1;
+                    is_invalid |= self.invalid.contains(&current);
+                    is_validated &= side_block.is_validated;
+                    current = side_block.previous_hash;
+                }
+                let status = match (is_invalid, is_validated) {
+                    (true, _) => ChainTipStatus::Invalid,
+                    (false, true) => ChainTipStatus::ValidFork,
+                    (false, false) => ChainTipStatus::HeadersOnly,
+                };
+                ChainTip {
+                    height: tip.height,
+                    hash: *hash,
+                    branch_length,
+                    cumulative_proof_target: tip.cumulative_proof_target,
+                    status,
+                }
+            })
+            .collect::<Vec<_>>();
+        tips.sort_by_key(|tip| core::cmp::Reverse(tip.height));
+        tips
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Returns the tip of the canonical chain, followed by the tips of the side branches, in descending order of height.
+    pub fn chain_tips(&self) -> Vec<ChainTip<N>> {
+        let latest_block = self.latest_block();
+        let active = ChainTip {
+            height: latest_block.height(),
+            hash: latest_block.hash(),
+            branch_length: 0,
+            cumulative_proof_target: latest_block.cumulative_proof_target(),
+            status: ChainTipStatus::Active,
+        };
+        core::iter::once(active).chain(self.side_branches.read().tips()).collect()
+    }
+
+    /// Stores the header of the given block, which is not in the canonical chain, as a side block.
+    /// The block is not validated, so the caller must authenticate its header, and its previous block
+    /// must be in the canonical chain or a side branch.
+    pub fn insert_side_block(&self, block: &Block<N>) -> Result<()> {
+        let (hash, previous_hash) = (block.hash(), block.previous_hash());
+        // Ensure the block is not in the canonical chain.
+        if self.contains_block_hash(&hash)? {
+            bail!("Block '{hash}' is already in the canonical chain")
+        }
+        // Ensure the block is connected to the known blocks.
+        let mut side_branches = self.side_branches.write();
+        if !side_branches.blocks.contains_key(&previous_hash) && !self.contains_block_hash(&previous_hash)? {
+            bail!("The previous block '{previous_hash}' of block '{hash}' is unknown")
+        }
+        side_branches.insert(hash, previous_hash, block.header(), false);
+        Ok(())
+    }
+
+    /// Marks the given side block as invalid, along with the side blocks that descend from it.
+    pub fn mark_block_invalid(&self, hash: &N::BlockHash) -> Result<()> {
+        let mut side_branches = self.side_branches.write();
+        // Ensure the block is a side block.
+        if !side_branches.blocks.contains_key(hash) {
+            bail!("Block '{hash}' is not in a side branch")
+        }
+        side_branches.invalid.insert(*hash);
+        Ok(())
+    }
+
+    /// Stores the canonical blocks from the given height onwards as validated side blocks, as they are about to be removed.
+    pub(crate) fn disconnect_side_blocks(
+        &self,
+        height: u32,
+        latest_height: u32,
+        blocks: &[Result<Block<N>>],
+    ) -> Result<()> {
+        let store = self.vm.block_store();
+        let mut side_branches = self.side_branches.write();
+        for (height, block) in (height..=latest_height).zip(blocks) {
+            // If the block was retrieved, use its header.
+            if let Ok(block) = block {
+                side_branches.insert(block.hash(), block.previous_hash(), block.header(), true);
+                continue;
+            }
+            // Otherwise, retrieve the header from storage.
+            let hash = match store.get_block_hash(height)? {
+                Some(hash) => hash,
+                None => bail!("Block {height} does not exist in storage"),
+            };
+            match (store.get_previous_block_hash(height)?, store.get_block_header(&hash)?) {
+                (Some(previous_hash), Some(header)) => side_branches.insert(hash, previous_hash, &header, true),
+                _ => bail!("Block {height} ('{hash}') is missing its header in storage"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the given block from the side branches, as it was added to the canonical chain.
+    pub(crate) fn connect_side_block(&self, block: &Block<N>) {
+        self.side_branches.write().connect(&block.hash(), block.height());
+    }
+}
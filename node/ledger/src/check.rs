@@ -0,0 +1,387 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use snarkvm::synthesizer::block::Input;
+
+use core::fmt;
+
+/// The default number of latest blocks that are checked for consistency when the ledger is loaded.
+pub const DEFAULT_CONSISTENCY_CHECK_DEPTH: u32 = 100;
+
+/// The scope of the consistency check that is performed when the ledger is loaded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConsistencyCheck {
+    /// Skips the consistency check.
+    Skip,
+    /// Checks the given number of latest blocks.
+    Latest(u32),
+    /// Checks every block in the ledger.
+    Deep,
+}
+
+impl Default for ConsistencyCheck {
+    fn default() -> Self {
+        Self::Latest(DEFAULT_CONSISTENCY_CHECK_DEPTH)
+    }
+}
+
+/// The class of an inconsistency in the ledger.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InconsistencyKind {
+    /// The block hash, header, signature, coinbase solution, or state root of the block is missing.
+    MissingBlock,
+    /// A transaction listed in the block does not resolve.
+    MissingTransaction,
+    /// An index entry (transaction, transition, commitment, serial number, puzzle commitment, or
+    /// state root) derived from the block is missing, or points elsewhere.
+    MissingIndex,
+    /// The total supply of the block does not match its recomputation.
+    InvalidTotalSupply,
+    /// The cumulative proof target of the block does not match its recomputation.
+    InvalidCumulativeProofTarget,
+}
+
+/// An inconsistency in the ledger, found at the given block height.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inconsistency {
+    /// The height of the inconsistent block.
+    pub height: u32,
+    /// The class of the inconsistency.
+    pub kind: InconsistencyKind,
+    /// The description of the inconsistency.
+    pub reason: String,
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at block {} - {}", self.kind, self.height, self.reason)
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Checks the consistency of the ledger, and truncates it to the latest consistent block if an inconsistency is found.
+    /// Returns the inconsistency that caused the truncation, if any.
+    pub fn repair_consistency(&self, check: ConsistencyCheck) -> Result<Option<Inconsistency>> {
+        match self.check_consistency(check)? {
+            Some(inconsistency) => {
+                warn!(target: "critical", kind = "integrity", "Found an inconsistency in the ledger: {inconsistency}");
+                // Ensure the genesis block is consistent.
+                if inconsistency.height == 0 {
+                    bail!("The genesis block is inconsistent (run 'snarkos clean' and try again)")
+                }
+                // Truncate the ledger to the latest consistent block.
+                self.truncate(inconsistency.height)?;
+                Ok(Some(inconsistency))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Checks the consistency of the blocks in the given scope, and returns the inconsistency at the lowest height, if any.
+    ///
+    /// For every block, this checks that the block exists, that every listed transaction resolves, that the
+    /// indexes derived from the block point back to it, and that the total supply and cumulative proof target
+    /// match their recomputation from the previous block.
+    pub fn check_consistency(&self, check: ConsistencyCheck) -> Result<Option<Inconsistency>> {
+        // Retrieve the latest height in storage.
+        let latest_height = match self.vm.block_store().heights().max() {
+            Some(height) => *height,
+            None => bail!("Failed to load blocks from the ledger"),
+        };
+        // Determine the start height of the check.
+        let start_height = match check {
+            ConsistencyCheck::Skip => return Ok(None),
+            ConsistencyCheck::Latest(depth) => latest_height.saturating_sub(depth.saturating_sub(1)),
+            ConsistencyCheck::Deep => 0,
+        };
+        // Skip the pruned blocks, as their transactions are no longer in storage.
+        let start_height = start_height.max(self.pruned_height()).min(latest_height);
+        info!("Checking the consistency of blocks {start_height} to {latest_height}...");
+
+        // Retrieve the header preceding the start height, to recompute the totals of the first block.
+        let mut previous_header = match start_height.checked_sub(1) {
+            Some(height) => match self.vm.block_store().get_block_hash(height)? {
+                Some(hash) => self.vm.block_store().get_block_header(&hash)?,
+                None => None,
+            },
+            None => None,
+        };
+
+        // Check the blocks in ascending order, stopping at the first inconsistency.
+        for height in start_height..=latest_height {
+            match self.check_block_consistency(height, previous_header.as_ref()) {
+                Ok(header) => previous_header = Some(header),
+                Err((kind, reason)) => return Ok(Some(Inconsistency { height, kind, reason })),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes the blocks from the given height onwards, and sets the latest block to the block preceding it.
+    ///
+    /// Note that the removal relies on the transaction IDs of every removed block to be present in storage.
+    pub fn truncate(&self, height: u32) -> Result<()> {
+        // Ensure the genesis block is not removed.
+        ensure!(height > 0, "Cannot truncate the genesis block");
+        // Acquire the commit lock, to serialize the writes to the ledger.
+        let _commit_lock = self.commit_lock.lock();
+        // Retrieve the latest height in storage.
+        let latest_height = match self.vm.block_store().heights().max() {
+            Some(height) => *height,
+            None => bail!("Failed to load blocks from the ledger"),
+        };
+        // Ensure there are blocks to remove.
+        ensure!(height <= latest_height, "Cannot truncate to block {height}, the latest block is {latest_height}");
+        // Ensure the latest block remains unpruned, as the blocks are loaded in full.
+        let pruned_height = self.pruned_height();
+        ensure!(
+            height > pruned_height,
+            "Cannot truncate to block {}, the blocks below {pruned_height} are pruned",
+            height - 1
+        );
+
+        // Retrieve the blocks that are discarded, from memory if they are recent blocks.
+        let discarded = (height..=latest_height).map(|height| self.get_recent_block(height)).collect::<Vec<_>>();
+        // Log the blocks that are discarded.
+        for (height, block) in (height..=latest_height).zip(&discarded) {
+            match block {
+                Ok(block) => {
+                    let ids = block.transactions().transaction_ids().map(ToString::to_string).collect::<Vec<_>>();
+                    warn!("Discarding block {height} ('{}') with transactions [{}]", block.hash(), ids.join(", "))
+                }
+                Err(error) => warn!("Discarding block {height} (unresolved block - {error})"),
+            }
+        }
+
+        // Retrieve the hashes of the discarded blocks, before they are removed.
+        let discarded_hashes = (height..=latest_height)
+            .zip(&discarded)
+            .map(|(height, block)| match block {
+                Ok(block) => Ok((height, block.hash())),
+                Err(_) => match self.vm.block_store().get_block_hash(height)? {
+                    Some(hash) => Ok((height, hash)),
+                    None => bail!("Missing the hash of block {height}"),
+                },
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Keep the blocks that are discarded as a side branch.
+        self.disconnect_side_blocks(height, latest_height, &discarded)?;
+
+        // Remove the discarded blocks from memory, and move the current block back before removing the blocks,
+        // so that they are not observed while they are removed.
+        let block = Arc::new(self.get_recent_block(height - 1)?);
+        self.recent_blocks.write().truncate(height);
+        *self.current_block.write() = block.clone();
+
+        // Remove the blocks.
+        self.vm.block_store().remove_last_n(latest_height - height + 1).map_err(|error| {
+            anyhow!(
+                "Failed to truncate the ledger to block {} - {error} (run 'snarkos clean' and try again)",
+                height - 1
+            )
+        })?;
+        // Remove the serial numbers of the discarded blocks from the filter.
+        self.remove_spent_serial_numbers(&discarded);
+        // Remove the discarded blocks from the histories of the registered accounts.
+        self.truncate_account_histories(height);
+        // Rewind the counts of the ledger digest to the current block.
+        self.rewind_tip_digest(&block, &discarded);
+        // Queue the removal of the blocks for the shadow storage, if it is enabled.
+        self.push_shadow_truncation(height);
+        // Publish the disconnected blocks, from the tip.
+        for (height, hash) in discarded_hashes.into_iter().rev() {
+            self.events.publish(Event::BlockDisconnected { height, hash });
+        }
+        let depth = latest_height - height + 1;
+        warn!(target: "critical", kind = "reorg", depth, "Truncated the ledger to block {} ({depth} blocks)", height - 1);
+
+        // Set the current epoch challenge, as the current block is already set.
+        *self.current_epoch_challenge.write() = Some(self.get_epoch_challenge(height - 1)?);
+        Ok(())
+    }
+
+    /// Checks the consistency of the block at the given height, and returns its header.
+    fn check_block_consistency(
+        &self,
+        height: u32,
+        previous_header: Option<&Header<N>>,
+    ) -> Result<Header<N>, (InconsistencyKind, String)> {
+        use InconsistencyKind::*;
+
+        let store = self.vm.block_store();
+
+        /* Block */
+
+        // Ensure the block hash exists, and maps back to the height.
+        let hash = match store.get_block_hash(height) {
+            Ok(Some(hash)) => hash,
+            Ok(None) => return Err((MissingBlock, "missing block hash".to_string())),
+            Err(error) => return Err((MissingBlock, error.to_string())),
+        };
+        match store.get_block_height(&hash) {
+            Ok(Some(block_height)) if block_height == height => (),
+            _ => return Err((MissingBlock, format!("block '{hash}' does not map back to its height"))),
+        }
+        // Ensure the block header exists, and is at the height.
+        let header = match store.get_block_header(&hash) {
+            Ok(Some(header)) if header.height() == height => header,
+            Ok(Some(header)) => {
+                return Err((MissingBlock, format!("header of block '{hash}' is at {}", header.height())))
+            }
+            _ => return Err((MissingBlock, format!("missing header for block '{hash}'"))),
+        };
+        // Ensure the block signature exists.
+        if !matches!(store.get_block_signature(&hash), Ok(Some(_))) {
+            return Err((MissingBlock, format!("missing signature for block '{hash}'")));
+        }
+        // Ensure the block coinbase solution exists.
+        let coinbase = store.get_block_coinbase(&hash).map_err(|error| (MissingBlock, error.to_string()))?;
+        // Ensure the state root exists.
+        let state_root = match store.get_state_root(height) {
+            Ok(Some(state_root)) => state_root,
+            _ => return Err((MissingBlock, format!("missing state root for block '{hash}'"))),
+        };
+
+        /* Transactions */
+
+        // Ensure every listed transaction resolves.
+        let transactions = match store.get_block_transactions(&hash) {
+            Ok(Some(transactions)) => transactions,
+            Ok(None) => return Err((MissingBlock, format!("missing transaction IDs for block '{hash}'"))),
+            Err(error) => return Err((MissingTransaction, error.to_string())),
+        };
+        // Ensure the transactions match the transactions root in the header.
+        match transactions.to_root() {
+            Ok(root) if root == header.transactions_root() => (),
+            _ => return Err((MissingTransaction, format!("transactions of block '{hash}' do not match its header"))),
+        }
+
+        /* Indexes */
+
+        // Ensure the state root maps back to the height.
+        match store.find_block_height_from_state_root(state_root) {
+            Ok(Some(block_height)) if block_height == height => (),
+            _ => return Err((MissingIndex, format!("state root '{state_root}' does not map to the block"))),
+        }
+        for transaction in transactions.values() {
+            let transaction_id = transaction.id();
+            // Ensure the transaction maps back to the block.
+            match store.find_block_hash(&transaction_id) {
+                Ok(Some(block_hash)) if block_hash == hash => (),
+                _ => return Err((MissingIndex, format!("transaction '{transaction_id}' does not map to the block"))),
+            }
+            // Ensure every transition maps back to the transaction.
+            for transition_id in transaction.transition_ids() {
+                match self.vm.transaction_store().find_transaction_id_from_transition_id(transition_id) {
+                    Ok(Some(id)) if id == transaction_id => (),
+                    _ => {
+                        return Err((
+                            MissingIndex,
+                            format!("transition '{transition_id}' does not map to '{transaction_id}'"),
+                        ));
+                    }
+                }
+            }
+            // Ensure every commitment is indexed.
+            for commitment in transaction.commitments() {
+                if !matches!(self.vm.transition_store().contains_commitment(commitment), Ok(true)) {
+                    return Err((MissingIndex, format!("commitment '{commitment}' is not indexed")));
+                }
+            }
+            // Ensure every serial number is indexed.
+            for serial_number in transaction.serial_numbers() {
+                if !matches!(self.vm.transition_store().contains_serial_number(serial_number), Ok(true)) {
+                    return Err((MissingIndex, format!("serial number '{serial_number}' is not indexed")));
+                }
+            }
+        }
+        // Ensure every puzzle commitment maps back to the block.
+        for puzzle_commitment in
+            coinbase.iter().flat_map(|coinbase| coinbase.partial_solutions()).map(|s| s.commitment())
+        {
+            match store.find_block_hash_from_puzzle_commitment(&puzzle_commitment) {
+                Ok(Some(block_hash)) if block_hash == hash => (),
+                _ => {
+                    return Err((
+                        MissingIndex,
+                        format!("puzzle commitment '{puzzle_commitment}' does not map to the block"),
+                    ))
+                }
+            }
+        }
+
+        /* Totals */
+
+        // Recompute the totals from the previous block, if it is available.
+        if let Some(previous_header) = previous_header {
+            // Ensure the total supply matches its recomputation.
+            let total_supply = next_total_supply(previous_header.total_supply_in_microcredits(), &transactions)
+                .map_err(|error| (InvalidTotalSupply, error.to_string()))?;
+            if total_supply != header.total_supply_in_microcredits() {
+                return Err((
+                    InvalidTotalSupply,
+                    format!("expected {total_supply}, found {}", header.total_supply_in_microcredits()),
+                ));
+            }
+            // Ensure the cumulative proof target matches its recomputation.
+            let block_proof_target = match &coinbase {
+                Some(coinbase) => coinbase
+                    .to_cumulative_proof_target()
+                    .map_err(|error| (InvalidCumulativeProofTarget, error.to_string()))?,
+                None => 0,
+            };
+            let cumulative_proof_target = previous_header.cumulative_proof_target().saturating_add(block_proof_target);
+            if cumulative_proof_target != header.cumulative_proof_target() {
+                return Err((
+                    InvalidCumulativeProofTarget,
+                    format!("expected {cumulative_proof_target}, found {}", header.cumulative_proof_target()),
+                ));
+            }
+        }
+
+        Ok(header)
+    }
+}
+
+/// Returns the total supply in microcredits after the given transactions, i.e. the given total supply
+/// without the fees of the transactions, and with the amounts minted by the coinbase transactions.
+fn next_total_supply<N: Network>(total_supply_in_microcredits: u64, transactions: &Transactions<N>) -> Result<u64> {
+    let mut total_supply_in_microcredits = total_supply_in_microcredits;
+    for transaction in transactions.values() {
+        // Subtract the fee from the total supply.
+        total_supply_in_microcredits = total_supply_in_microcredits
+            .checked_sub(*transaction.fee()?)
+            .ok_or_else(|| anyhow!("Fee exceeded total supply of credits"))?;
+
+        // If the transaction is a coinbase, add the amount to the total supply.
+        if transaction.is_coinbase() {
+            match transaction {
+                Transaction::Execute(_, execution, _) => match execution.get(0)?.inputs().get(1) {
+                    Some(Input::Public(_, Some(Plaintext::Literal(Literal::U64(amount), _)))) => {
+                        total_supply_in_microcredits = total_supply_in_microcredits
+                            .checked_add(**amount)
+                            .ok_or_else(|| anyhow!("Total supply of microcredits overflowed"))?;
+                    }
+                    _ => bail!("Invalid coinbase transaction: Missing public input in 'credits.aleo/mint'"),
+                },
+                _ => bail!("Invalid coinbase transaction"),
+            }
+        }
+    }
+    Ok(total_supply_in_microcredits)
+}
@@ -62,7 +62,15 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
     }
 
     /// Returns `true` if the given serial number exists.
+    ///
+    /// The filter of the spent serial numbers rules out the unspent serial numbers, while a serial number
+    /// that may be spent is always confirmed in the index, so the result never depends on the filter alone.
     pub fn contains_serial_number(&self, serial_number: &Field<N>) -> Result<bool> {
+        // If the filter rules out the serial number, it is unspent.
+        if !self.spent_filter.read().contains(serial_number) {
+            return Ok(false);
+        }
+        // Confirm the serial number in the index.
         self.vm.transition_store().contains_serial_number(serial_number)
     }
 
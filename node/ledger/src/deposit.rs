@@ -0,0 +1,242 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use snarkvm::console::program::{BlockPath, TransactionsPath};
+
+/// The version of the encoding of a deposit proof.
+const DEPOSIT_PROOF_VERSION: u8 = 1;
+
+/// A proof that a transaction is included in the canonical chain, below a given tip, which an exchange
+/// may verify offline against a trusted header of the tip, with `verify_deposit_proof`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepositProof<N: Network> {
+    /// The transaction.
+    transaction: Transaction<N>,
+    /// The header of the block containing the transaction.
+    header: Header<N>,
+    /// The hash of the block preceding the block containing the transaction.
+    previous_block_hash: N::BlockHash,
+    /// The Merkle path from the transaction ID to the transactions root of the header.
+    transactions_path: TransactionsPath<N>,
+    /// The Merkle path from the block hash to the ledger digest of the tip header (its previous state root),
+    /// or `None` if the block is the tip.
+    block_path: Option<BlockPath<N>>,
+    /// The height of the tip.
+    tip_height: u32,
+    /// The difference between the cumulative proof target of the tip and of the block.
+    chain_work: u128,
+}
+
+impl<N: Network> DepositProof<N> {
+    /// Initializes a new deposit proof.
+    pub const fn new(
+        transaction: Transaction<N>,
+        header: Header<N>,
+        previous_block_hash: N::BlockHash,
+        transactions_path: TransactionsPath<N>,
+        block_path: Option<BlockPath<N>>,
+        tip_height: u32,
+        chain_work: u128,
+    ) -> Self {
+        Self { transaction, header, previous_block_hash, transactions_path, block_path, tip_height, chain_work }
+    }
+
+    /// Returns the transaction.
+    pub const fn transaction(&self) -> &Transaction<N> {
+        &self.transaction
+    }
+
+    /// Returns the header of the block containing the transaction.
+    pub const fn header(&self) -> &Header<N> {
+        &self.header
+    }
+
+    /// Returns the hash of the block preceding the block containing the transaction.
+    pub const fn previous_block_hash(&self) -> N::BlockHash {
+        self.previous_block_hash
+    }
+
+    /// Returns the Merkle path from the transaction ID to the transactions root of the header.
+    pub const fn transactions_path(&self) -> &TransactionsPath<N> {
+        &self.transactions_path
+    }
+
+    /// Returns the Merkle path from the block hash to the ledger digest of the tip header, if the block is not the tip.
+    pub const fn block_path(&self) -> Option<&BlockPath<N>> {
+        self.block_path.as_ref()
+    }
+
+    /// Returns the height of the tip.
+    pub const fn tip_height(&self) -> u32 {
+        self.tip_height
+    }
+
+    /// Returns the number of confirmations of the transaction, which is `1` if the block is the tip.
+    pub fn confirmations(&self) -> u32 {
+        self.tip_height.saturating_sub(self.header.height()) + 1
+    }
+
+    /// Returns the difference between the cumulative proof target of the tip and of the block.
+    pub const fn chain_work(&self) -> u128 {
+        self.chain_work
+    }
+
+    /// Returns the hash of the block containing the transaction.
+    pub fn block_hash(&self) -> Result<N::BlockHash> {
+        let preimage = (*self.previous_block_hash).to_bits_le().into_iter().chain(self.header.to_root()?.to_bits_le());
+        Ok(N::hash_bhp1024(&preimage.collect::<Vec<_>>())?.into())
+    }
+}
+
+impl<N: Network> ToBytes for DepositProof<N> {
+    /// Writes the deposit proof to the given writer.
+    fn write_le<W: Write>(&self, mut writer: W) -> IoResult<()> {
+        DEPOSIT_PROOF_VERSION.write_le(&mut writer)?;
+        self.transaction.write_le(&mut writer)?;
+        self.header.write_le(&mut writer)?;
+        self.previous_block_hash.write_le(&mut writer)?;
+        self.transactions_path.write_le(&mut writer)?;
+        match &self.block_path {
+            Some(block_path) => {
+                true.write_le(&mut writer)?;
+                block_path.write_le(&mut writer)?;
+            }
+            None => false.write_le(&mut writer)?,
+        }
+        self.tip_height.write_le(&mut writer)?;
+        self.chain_work.write_le(&mut writer)
+    }
+}
+
+impl<N: Network> FromBytes for DepositProof<N> {
+    /// Reads the deposit proof from the given reader.
+    fn read_le<R: Read>(mut reader: R) -> IoResult<Self> {
+        // Ensure the version is supported.
+        let version = u8::read_le(&mut reader)?;
+        if version != DEPOSIT_PROOF_VERSION {
+            return Err(error(format!("Unsupported version of the deposit proof ({version})")));
+        }
+        let transaction = Transaction::read_le(&mut reader)?;
+        let header = Header::read_le(&mut reader)?;
+        let previous_block_hash = N::BlockHash::read_le(&mut reader)?;
+        let transactions_path = TransactionsPath::read_le(&mut reader)?;
+        let block_path = match bool::read_le(&mut reader)? {
+            true => Some(BlockPath::read_le(&mut reader)?),
+            false => None,
+        };
+        let tip_height = u32::read_le(&mut reader)?;
+        let chain_work = u128::read_le(&mut reader)?;
+        Ok(Self { transaction, header, previous_block_hash, transactions_path, block_path, tip_height, chain_work })
+    }
+}
+
+/// Checks that the given deposit proof proves its transaction is included in the chain ending at the given
+/// trusted header, and that its confirmations and chain work are consistent with the trusted header.
+pub fn verify_deposit_proof<N: Network>(proof: &DepositProof<N>, trusted_header: &Header<N>) -> Result<()> {
+    let header = proof.header();
+    // Ensure the proof is for the trusted tip.
+    ensure!(
+        proof.tip_height() == trusted_header.height(),
+        "The deposit proof is for the tip at block {}, not the trusted header at block {}",
+        proof.tip_height(),
+        trusted_header.height()
+    );
+    ensure!(header.height() <= proof.tip_height(), "Block {} is above the tip of the deposit proof", header.height());
+    // Ensure the chain work is the difference between the cumulative proof targets.
+    let chain_work = trusted_header.cumulative_proof_target().checked_sub(header.cumulative_proof_target());
+    ensure!(chain_work == Some(proof.chain_work()), "The chain work of the deposit proof is incorrect");
+
+    // Ensure the transaction belongs to the transactions root of its header.
+    let transaction_id = proof.transaction().id();
+    ensure!(
+        N::verify_merkle_path_bhp(proof.transactions_path(), &header.transactions_root(), &transaction_id.to_bits_le()),
+        "Transaction '{transaction_id}' does not belong to the transactions root '{}'",
+        header.transactions_root()
+    );
+
+    // Ensure the block belongs to the chain ending at the trusted header.
+    match proof.block_path() {
+        // If the block is the tip, ensure its header is the trusted header.
+        None => ensure!(header == trusted_header, "The header of the deposit proof is not the trusted header"),
+        // Otherwise, ensure the block is in the ledger Merkle tree at the previous state root of the trusted header.
+        Some(block_path) => {
+            let block_hash = proof.block_hash()?;
+            ensure!(
+                *block_path.leaf_index() == header.height() as u64,
+                "The block path of the deposit proof is not for block {}",
+                header.height()
+            );
+            ensure!(
+                N::verify_merkle_path_bhp(block_path, &trusted_header.previous_state_root(), &block_hash.to_bits_le()),
+                "Block '{block_hash}' does not belong to the chain of the trusted header"
+            );
+        }
+    }
+    Ok(())
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Returns a proof that the given transaction is included in the canonical chain, below the latest block.
+    /// The transaction, its block, and the tip are retrieved from a single snapshot of the canonical chain.
+    pub fn get_deposit_proof(&self, transaction_id: &N::TransactionID) -> Result<DepositProof<N>> {
+        // Retrieve the tip.
+        let tip = self.current_block.read().clone();
+        // Retrieve the block containing the transaction.
+        let height = match self.find_block_hash(transaction_id)? {
+            Some(block_hash) => self.get_height(&block_hash)?,
+            None => bail!("Transaction '{transaction_id}' does not exist in the ledger"),
+        };
+        ensure!(
+            height <= tip.height(),
+            "Transaction '{transaction_id}' is not below the tip at block {}",
+            tip.height()
+        );
+        let block = self.get_block(height)?;
+        let transaction = match block.transactions().get(transaction_id) {
+            Some(transaction) => transaction.clone(),
+            None => bail!("Transaction '{transaction_id}' is missing from block {height}"),
+        };
+
+        // Compute the Merkle path of the transaction, against the transactions root.
+        let transactions_path = block.transactions().to_path(*transaction_id)?;
+        // Compute the Merkle path of the block, against the ledger digest of the previous block of the tip.
+        let block_path = match height < tip.height() {
+            true => Some(self.prove_block_at_digest(tip.height() - 1, height, block.hash())?),
+            false => None,
+        };
+
+        // Ensure the blocks were not reorganized while the proof was computed.
+        ensure!(
+            self.get_hash(tip.height()).ok() == Some(tip.hash()) && self.get_hash(height).ok() == Some(block.hash()),
+            "The ledger was reorganized while computing the deposit proof of transaction '{transaction_id}'"
+        );
+
+        let proof = DepositProof::new(
+            transaction,
+            *block.header(),
+            block.previous_hash(),
+            transactions_path,
+            block_path,
+            tip.height(),
+            tip.cumulative_proof_target().saturating_sub(block.cumulative_proof_target()),
+        );
+        // Ensure the deposit proof is valid.
+        verify_deposit_proof(&proof, tip.header())?;
+        Ok(proof)
+    }
+}
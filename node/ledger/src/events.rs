@@ -0,0 +1,368 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::{console::network::Network, synthesizer::block::Transaction};
+
+use core::{fmt, time::Duration};
+use parking_lot::{Condvar, Mutex};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use tokio::sync::Notify;
+
+/// The default number of events queued for a subscriber, beyond which its new events are dropped.
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// The topic of an event, to which a subscriber subscribes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Topic {
+    BlockConnected,
+    BlockDisconnected,
+    TransactionAccepted,
+    TransactionEvicted,
+    PeerConnected,
+    PeerDisconnected,
+    PeerBanned,
+    SyncStateChanged,
+}
+
+/// A transaction that was evicted from the memory pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvictedTransaction<N: Network> {
+    /// The ID of the evicted transaction.
+    pub transaction_id: N::TransactionID,
+    /// The reason the transaction was evicted.
+    pub reason: String,
+    /// The ID of the transaction that replaced the evicted transaction, if it was replaced.
+    pub replaced_by: Option<N::TransactionID>,
+    /// Whether the transaction expired from the memory pool.
+    pub expired: bool,
+}
+
+/// An event of the node, which is published on the event bus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<N: Network> {
+    /// The block, with the IDs of its transactions, was connected to the tip of the canonical chain.
+    BlockConnected { height: u32, hash: N::BlockHash, transaction_ids: Vec<N::TransactionID> },
+    /// The block was disconnected from the tip of the canonical chain.
+    BlockDisconnected { height: u32, hash: N::BlockHash },
+    /// The transaction was accepted into the memory pool.
+    TransactionAccepted { transaction: Arc<Transaction<N>> },
+    /// The transaction was evicted from the memory pool.
+    TransactionEvicted(EvictedTransaction<N>),
+    /// The peer completed the handshake, and is connected.
+    PeerConnected { peer_ip: SocketAddr },
+    /// The peer was disconnected.
+    PeerDisconnected { peer_ip: SocketAddr },
+    /// The IP address (with or without a port) was banned.
+    PeerBanned { ip: String },
+    /// The node started or stopped syncing blocks from its peers, at the given canon height.
+    SyncStateChanged { is_syncing: bool, canon_height: u32 },
+}
+
+impl<N: Network> Event<N> {
+    /// Returns the topic of the event.
+    pub const fn topic(&self) -> Topic {
+        match self {
+            Self::BlockConnected { .. } => Topic::BlockConnected,
+            Self::BlockDisconnected { .. } => Topic::BlockDisconnected,
+            Self::TransactionAccepted { .. } => Topic::TransactionAccepted,
+            Self::TransactionEvicted(..) => Topic::TransactionEvicted,
+            Self::PeerConnected { .. } => Topic::PeerConnected,
+            Self::PeerDisconnected { .. } => Topic::PeerDisconnected,
+            Self::PeerBanned { .. } => Topic::PeerBanned,
+            Self::SyncStateChanged { .. } => Topic::SyncStateChanged,
+        }
+    }
+}
+
+/// The predicate of a filtered subscription, which is evaluated on each event of its topics before it is queued.
+pub type EventFilter<N> = Arc<dyn Fn(&Event<N>) -> bool + Send + Sync>;
+
+/// The queue of events of a subscriber.
+struct SubscriberQueue<N: Network> {
+    /// The topics of the subscriber.
+    topics: Vec<Topic>,
+    /// The predicate of the events of the subscriber, if its subscription is filtered.
+    filter: Option<EventFilter<N>>,
+    /// The maximum number of queued events.
+    capacity: usize,
+    /// The queued events, in order of publication.
+    events: Mutex<VecDeque<Event<N>>>,
+    /// The condition variable that is notified when an event is queued.
+    condvar: Condvar,
+    /// The notification of the asynchronous receivers, when an event is queued.
+    notify: Notify,
+    /// The number of events that were dropped, as the queue was full.
+    num_dropped: AtomicU64,
+}
+
+impl<N: Network> SubscriberQueue<N> {
+    /// Queues the given event, or drops it if the queue is full.
+    fn push(&self, event: Event<N>) {
+        let mut events = self.events.lock();
+        match events.len() < self.capacity {
+            true => {
+                events.push_back(event);
+                self.condvar.notify_all();
+                self.notify.notify_waiters();
+            }
+            false => {
+                self.num_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// The event bus, on which the components of the node publish their events to the subscribers of their topic.
+///
+/// Each subscriber has a bounded queue, so a slow subscriber only loses its own (newest) events,
+/// which are counted, while the publishers and the other subscribers are never blocked.
+pub struct EventBus<N: Network> {
+    /// The queues of the subscribers, by subscription ID.
+    subscribers: Arc<Mutex<HashMap<u64, Arc<SubscriberQueue<N>>>>>,
+    /// The ID of the next subscription.
+    next_id: Arc<AtomicU64>,
+}
+
+impl<N: Network> Clone for EventBus<N> {
+    fn clone(&self) -> Self {
+        Self { subscribers: self.subscribers.clone(), next_id: self.next_id.clone() }
+    }
+}
+
+impl<N: Network> Default for EventBus<N> {
+    fn default() -> Self {
+        Self { subscribers: Default::default(), next_id: Default::default() }
+    }
+}
+
+impl<N: Network> fmt::Debug for EventBus<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus").field("num_subscribers", &self.num_subscribers()).finish()
+    }
+}
+
+impl<N: Network> EventBus<N> {
+    /// Subscribes to the given topics, with the default capacity.
+    pub fn subscribe(&self, topics: &[Topic]) -> Subscription<N> {
+        self.subscribe_with_capacity(topics, DEFAULT_SUBSCRIPTION_CAPACITY)
+    }
+
+    /// Subscribes to the given topics, queueing up to the given number of events.
+    /// The subscription is deregistered when it is dropped.
+    pub fn subscribe_with_capacity(&self, topics: &[Topic], capacity: usize) -> Subscription<N> {
+        self.register(topics, None, capacity)
+    }
+
+    /// Subscribes to the events of the given topics that satisfy the given predicate, with the default capacity.
+    /// The events that do not satisfy the predicate are never queued, nor counted as dropped.
+    pub fn subscribe_filtered(&self, topics: &[Topic], filter: EventFilter<N>) -> Subscription<N> {
+        self.register(topics, Some(filter), DEFAULT_SUBSCRIPTION_CAPACITY)
+    }
+
+    /// Registers a subscription to the given topics, with the given predicate and capacity.
+    fn register(&self, topics: &[Topic], filter: Option<EventFilter<N>>, capacity: usize) -> Subscription<N> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let queue = Arc::new(SubscriberQueue {
+            topics: topics.to_vec(),
+            filter,
+            capacity,
+            events: Default::default(),
+            condvar: Default::default(),
+            notify: Default::default(),
+            num_dropped: Default::default(),
+        });
+        self.subscribers.lock().insert(id, queue.clone());
+        Subscription { id, queue, subscribers: self.subscribers.clone() }
+    }
+
+    /// Publishes the given event to the subscribers of its topic, whose predicate it satisfies.
+    ///
+    /// The predicates are evaluated once the subscribers are no longer locked, so that they may read the ledger.
+    pub fn publish(&self, event: Event<N>) {
+        let topic = event.topic();
+        let queues =
+            self.subscribers.lock().values().filter(|queue| queue.topics.contains(&topic)).cloned().collect::<Vec<_>>();
+        queues
+            .into_iter()
+            .filter(|queue| queue.filter.as_ref().map_or(true, |filter| filter(&event)))
+            .for_each(|queue| queue.push(event.clone()));
+    }
+
+    /// Returns the number of subscriptions.
+    pub fn num_subscribers(&self) -> usize {
+        self.subscribers.lock().len()
+    }
+}
+
+/// A subscription to topics of the event bus, which is deregistered when it is dropped.
+pub struct Subscription<N: Network> {
+    /// The ID of the subscription.
+    id: u64,
+    /// The queue of events of the subscription.
+    queue: Arc<SubscriberQueue<N>>,
+    /// The queues of the subscribers of the event bus.
+    subscribers: Arc<Mutex<HashMap<u64, Arc<SubscriberQueue<N>>>>>,
+}
+
+impl<N: Network> Subscription<N> {
+    /// Returns the topics of the subscription.
+    pub fn topics(&self) -> &[Topic] {
+        &self.queue.topics
+    }
+
+    /// Returns the number of events that were dropped, as the queue was full.
+    pub fn num_dropped(&self) -> u64 {
+        self.queue.num_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the next queued event, if any.
+    pub fn try_recv(&self) -> Option<Event<N>> {
+        self.queue.events.lock().pop_front()
+    }
+
+    /// Returns the queued events, in order of publication.
+    pub fn try_iter(&self) -> impl '_ + Iterator<Item = Event<N>> {
+        std::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Blocks until an event is queued, or the given timeout elapses, and returns the next event, if any.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Event<N>> {
+        let deadline = Instant::now() + timeout;
+        let mut events = self.queue.events.lock();
+        while events.is_empty() {
+            if self.queue.condvar.wait_until(&mut events, deadline).timed_out() {
+                break;
+            }
+        }
+        events.pop_front()
+    }
+
+    /// Waits until an event is queued, without blocking the thread, and returns the next event.
+    pub async fn recv(&self) -> Event<N> {
+        loop {
+            // Register for the notification before checking the queue, so that no event is missed.
+            let notified = self.queue.notify.notified();
+            if let Some(event) = self.try_recv() {
+                return event;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl<N: Network> Drop for Subscription<N> {
+    fn drop(&mut self) {
+        self.subscribers.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Block, FromBytes, Testnet3};
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a block connected event at the given height, with the hash of the genesis block.
+    fn block_connected(height: u32) -> Event<CurrentNetwork> {
+        let genesis = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        Event::BlockConnected { height, hash: genesis.hash(), transaction_ids: vec![] }
+    }
+
+    #[test]
+    fn test_slow_subscriber_loses_only_its_own_events() {
+        let bus = EventBus::<CurrentNetwork>::default();
+        let slow = bus.subscribe_with_capacity(&[Topic::BlockConnected], 2);
+        let fast = bus.subscribe_with_capacity(&[Topic::BlockConnected], 2);
+
+        // Publish the events, while the fast subscriber keeps up.
+        let events = (1..=5).map(block_connected).collect::<Vec<_>>();
+        for event in &events {
+            bus.publish(event.clone());
+            assert_eq!(fast.try_recv().as_ref(), Some(event));
+        }
+
+        // Ensure the slow subscriber kept the oldest events, and counted the dropped ones.
+        assert_eq!(slow.try_iter().collect::<Vec<_>>(), events[..2]);
+        assert_eq!(slow.num_dropped(), 3);
+        assert_eq!(fast.num_dropped(), 0);
+
+        // Ensure the slow subscriber receives the events again once it catches up.
+        bus.publish(block_connected(6));
+        assert!(slow.recv_timeout(Duration::from_millis(10)).is_some());
+    }
+
+    #[test]
+    fn test_topic_filtering() {
+        let bus = EventBus::<CurrentNetwork>::default();
+        let blocks = bus.subscribe(&[Topic::BlockConnected, Topic::BlockDisconnected]);
+        let peers = bus.subscribe(&[Topic::PeerConnected]);
+
+        // Publish an event of every topic of the subscribers, and of another topic.
+        let connected = block_connected(1);
+        let disconnected = match block_connected(1) {
+            Event::BlockConnected { height, hash, .. } => Event::BlockDisconnected { height, hash },
+            _ => unreachable!(),
+        };
+        let peer = Event::PeerConnected { peer_ip: "127.0.0.1:4133".parse().unwrap() };
+        for event in
+            [connected.clone(), peer.clone(), Event::PeerBanned { ip: "1.2.3.4".to_string() }, disconnected.clone()]
+        {
+            bus.publish(event);
+        }
+
+        // Ensure each subscriber receives exactly the events of its topics, in order.
+        assert_eq!(blocks.try_iter().collect::<Vec<_>>(), vec![connected, disconnected]);
+        assert_eq!(peers.try_iter().collect::<Vec<_>>(), vec![peer]);
+        assert!(peers.recv_timeout(Duration::from_millis(10)).is_none());
+    }
+
+    #[test]
+    fn test_filtered_subscription() {
+        let bus = EventBus::<CurrentNetwork>::default();
+        let even = bus.subscribe_filtered(
+            &[Topic::BlockConnected],
+            Arc::new(|event| matches!(event, Event::BlockConnected { height, .. } if height % 2 == 0)),
+        );
+        let all = bus.subscribe(&[Topic::BlockConnected]);
+
+        // Ensure the filtered subscriber only queues the events that satisfy its predicate, without dropping others.
+        let events = (1..=4).map(block_connected).collect::<Vec<_>>();
+        events.iter().for_each(|event| bus.publish(event.clone()));
+        assert_eq!(even.try_iter().collect::<Vec<_>>(), vec![events[1].clone(), events[3].clone()]);
+        assert_eq!(all.try_iter().collect::<Vec<_>>(), events);
+        assert_eq!(even.num_dropped(), 0);
+    }
+
+    #[test]
+    fn test_subscription_deregisters_on_drop() {
+        let bus = EventBus::<CurrentNetwork>::default();
+        let subscription = bus.subscribe(&[Topic::SyncStateChanged]);
+        assert_eq!(bus.num_subscribers(), 1);
+        drop(subscription);
+        assert_eq!(bus.num_subscribers(), 0);
+        // Ensure publishing without subscribers is a no-op.
+        bus.publish(Event::SyncStateChanged { is_syncing: true, canon_height: 0 });
+    }
+}
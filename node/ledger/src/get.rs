@@ -16,6 +16,20 @@
 
 use super::*;
 
+/// The maximum number of heights that may be scanned in a single call.
+pub const MAX_HEIGHTS_PER_SCAN: usize = 1000;
+
+/// Returns the heights in the given range, clamped to `latest_height` and truncated to `limit` heights.
+/// If `reverse` is set, the heights are in descending order and truncated from the end of the range.
+fn clamp_heights(heights: Range<u32>, latest_height: u32, reverse: bool, limit: usize) -> Vec<u32> {
+    // Clamp the end of the range to include the latest height.
+    let heights = heights.start..heights.end.min(latest_height.saturating_add(1));
+    match reverse {
+        true => heights.rev().take(limit).collect(),
+        false => heights.take(limit).collect(),
+    }
+}
+
 impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
     /// Returns the state root that contains the given `block height`.
     pub fn get_state_root(&self, block_height: u32) -> Result<Option<N::StateRoot>> {
@@ -24,6 +38,10 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
 
     /// Returns a state path for the given commitment.
     pub fn get_state_path_for_commitment(&self, commitment: &Field<N>) -> Result<StatePath<N>> {
+        // Ensure the block containing the commitment is not pruned, as the state path is computed from its body.
+        if let Some(height) = self.find_block_height_from_commitment(commitment)? {
+            self.ensure_not_pruned(height)?;
+        }
         self.vm.block_store().get_state_path_for_commitment(commitment)
     }
 
@@ -45,11 +63,18 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         if height == 0 {
             return Ok(self.genesis.clone());
         }
+        // If the block is a recent canonical block, return it from memory.
+        let recent_block = self.recent_blocks.read().get(height);
+        if let Some(block) = recent_block {
+            return Ok(block.block().clone());
+        }
         // Retrieve the block hash.
-        let block_hash = match self.vm.block_store().get_block_hash(height)? {
+        let block_hash = match self.get_canon_hash(height)? {
             Some(block_hash) => block_hash,
             None => bail!("Block {height} does not exist in storage"),
         };
+        // Ensure the block is not pruned.
+        self.ensure_not_pruned(height)?;
         // Retrieve the block.
         match self.vm.block_store().get_block(&block_hash)? {
             Some(block) => Ok(block),
@@ -63,8 +88,70 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         cfg_into_iter!(heights).map(|height| self.get_block(height)).collect()
     }
 
+    /// Returns the latest block height and the block hashes in the given block range, up to `limit` hashes.
+    /// The range is inclusive of the start and exclusive of the end, and is clamped to the latest block height.
+    /// If `reverse` is set, the hashes are returned from the end of the range towards the start.
+    pub fn get_hashes(&self, heights: Range<u32>, reverse: bool, limit: usize) -> Result<(u32, Vec<N::BlockHash>)> {
+        self.scan_heights(heights, reverse, limit, |height| self.get_hash(height))
+    }
+
+    /// Returns the latest block height and the block headers in the given block range, up to `limit` headers.
+    /// The range is inclusive of the start and exclusive of the end, and is clamped to the latest block height.
+    /// If `reverse` is set, the headers are returned from the end of the range towards the start.
+    pub fn get_headers(&self, heights: Range<u32>, reverse: bool, limit: usize) -> Result<(u32, Vec<Header<N>>)> {
+        self.scan_heights(heights, reverse, limit, |height| self.get_header(height))
+    }
+
+    /// Returns the latest block height and the result of `f` for each height in the given block range.
+    /// The heights are clamped to the latest height at the start of the scan, so the results are consistent
+    /// with the returned height, without blocking the blocks added during the scan.
+    fn scan_heights<T: Send>(
+        &self,
+        heights: Range<u32>,
+        reverse: bool,
+        limit: usize,
+        f: impl Fn(u32) -> Result<T> + Send + Sync,
+    ) -> Result<(u32, Vec<T>)> {
+        // Retrieve the latest height.
+        let latest_height = self.latest_height();
+        // Clamp the heights to the latest height and the given limit.
+        let heights = clamp_heights(heights, latest_height, reverse, limit.min(MAX_HEIGHTS_PER_SCAN));
+        // Retrieve the items.
+        let items = cfg_into_iter!(heights).map(f).collect::<Result<Vec<_>>>()?;
+
+        Ok((latest_height, items))
+    }
+
+    /// Returns the block hash for the given block height, if the block is in the canonical chain.
+    ///
+    /// The blocks above the current block are not returned, as they may still be written to storage.
+    fn get_canon_hash(&self, height: u32) -> Result<Option<N::BlockHash>> {
+        match height <= self.latest_height() {
+            true => self.vm.block_store().get_block_hash(height),
+            false => Ok(None),
+        }
+    }
+
+    /// Returns `true` if the block with the given block hash is in the canonical chain.
+    pub(crate) fn is_canon_hash(&self, block_hash: &N::BlockHash) -> Result<bool> {
+        match self.vm.block_store().get_block_height(block_hash)? {
+            Some(height) => Ok(height <= self.latest_height()),
+            None => Ok(false),
+        }
+    }
+
     /// Returns the block for the given block hash.
     pub fn get_block_by_hash(&self, block_hash: &N::BlockHash) -> Result<Block<N>> {
+        // If the block is a recent canonical block, return it from memory.
+        let recent_block = self.recent_blocks.read().get_by_hash(block_hash);
+        if let Some(block) = recent_block {
+            return Ok(block.block().clone());
+        }
+        // Ensure the block is in the canonical chain, and is not pruned.
+        match self.vm.block_store().get_block_height(block_hash)? {
+            Some(height) if height <= self.latest_height() => self.ensure_not_pruned(height)?,
+            _ => bail!("Block '{block_hash}' does not exist in storage"),
+        }
         // Retrieve the block.
         match self.vm.block_store().get_block(block_hash)? {
             Some(block) => Ok(block),
@@ -75,8 +162,8 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
     /// Returns the block height for the given block hash.
     pub fn get_height(&self, block_hash: &N::BlockHash) -> Result<u32> {
         match self.vm.block_store().get_block_height(block_hash)? {
-            Some(height) => Ok(height),
-            None => bail!("Missing block height for block '{block_hash}'"),
+            Some(height) if height <= self.latest_height() => Ok(height),
+            _ => bail!("Missing block height for block '{block_hash}'"),
         }
     }
 
@@ -86,7 +173,7 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         if height == 0 {
             return Ok(self.genesis.hash());
         }
-        match self.vm.block_store().get_block_hash(height)? {
+        match self.get_canon_hash(height)? {
             Some(block_hash) => Ok(block_hash),
             None => bail!("Missing block hash for block {height}"),
         }
@@ -98,6 +185,10 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         if height == 0 {
             return Ok(N::BlockHash::default());
         }
+        // Ensure the block is in the canonical chain.
+        if height > self.latest_height() {
+            bail!("Missing previous block hash for block {height}")
+        }
         match self.vm.block_store().get_previous_block_hash(height)? {
             Some(previous_hash) => Ok(previous_hash),
             None => bail!("Missing previous block hash for block {height}"),
@@ -111,7 +202,7 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
             return Ok(*self.genesis.header());
         }
         // Retrieve the block hash.
-        let block_hash = match self.vm.block_store().get_block_hash(height)? {
+        let block_hash = match self.get_canon_hash(height)? {
             Some(block_hash) => block_hash,
             None => bail!("Block {height} does not exist in storage"),
         };
@@ -129,10 +220,12 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
             return Ok(self.genesis.transactions().clone());
         }
         // Retrieve the block hash.
-        let block_hash = match self.vm.block_store().get_block_hash(height)? {
+        let block_hash = match self.get_canon_hash(height)? {
             Some(block_hash) => block_hash,
             None => bail!("Block {height} does not exist in storage"),
         };
+        // Ensure the block is not pruned.
+        self.ensure_not_pruned(height)?;
         // Retrieve the block transaction.
         match self.vm.block_store().get_block_transactions(&block_hash)? {
             Some(transactions) => Ok(transactions),
@@ -142,6 +235,13 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
 
     /// Returns the transaction for the given transaction ID.
     pub fn get_transaction(&self, transaction_id: N::TransactionID) -> Result<Transaction<N>> {
+        // Ensure the transaction is in a block of the canonical chain, which is not pruned.
+        match self.vm.block_store().find_block_hash(&transaction_id)? {
+            Some(block_hash) if self.is_canon_hash(&block_hash)? => {
+                self.ensure_not_pruned(self.get_height(&block_hash)?)?
+            }
+            _ => bail!("Missing transaction for ID {transaction_id}"),
+        }
         // Retrieve the transaction.
         match self.vm.transaction_store().get_transaction(&transaction_id)? {
             Some(transaction) => Ok(transaction),
@@ -164,10 +264,12 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
             return Ok(self.genesis.coinbase().cloned());
         }
         // Retrieve the block hash.
-        let block_hash = match self.vm.block_store().get_block_hash(height)? {
+        let block_hash = match self.get_canon_hash(height)? {
             Some(block_hash) => block_hash,
             None => bail!("Block {height} does not exist in storage"),
         };
+        // Ensure the block is not pruned.
+        self.ensure_not_pruned(height)?;
         // Retrieve the block coinbase solution.
         self.vm.block_store().get_block_coinbase(&block_hash)
     }
@@ -179,7 +281,7 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
             return Ok(*self.genesis.signature());
         }
         // Retrieve the block hash.
-        let block_hash = match self.vm.block_store().get_block_hash(height)? {
+        let block_hash = match self.get_canon_hash(height)? {
             Some(block_hash) => block_hash,
             None => bail!("Block {height} does not exist in storage"),
         };
@@ -211,4 +313,55 @@ mod tests {
         // Ensure the genesis block matches.
         assert_eq!(genesis, candidate);
     }
+
+    #[test]
+    fn test_get_hashes() {
+        // Load the genesis block.
+        let genesis = Block::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+
+        // Initialize a new ledger.
+        let ledger = CurrentLedger::load(genesis.clone(), None).unwrap();
+
+        // Retrieve the genesis hash.
+        let (latest_height, hashes) = ledger.get_hashes(0..10, false, 10).unwrap();
+        assert_eq!(latest_height, 0);
+        assert_eq!(hashes, vec![genesis.hash()]);
+        // Retrieve the genesis hash in reverse.
+        let (latest_height, hashes) = ledger.get_hashes(0..10, true, 10).unwrap();
+        assert_eq!(latest_height, 0);
+        assert_eq!(hashes, vec![genesis.hash()]);
+        // Retrieve the genesis header.
+        let (latest_height, headers) = ledger.get_headers(0..1, false, 1).unwrap();
+        assert_eq!(latest_height, 0);
+        assert_eq!(headers, vec![*genesis.header()]);
+
+        // Ensure a start beyond the latest height returns no hashes.
+        let (latest_height, hashes) = ledger.get_hashes(5..10, false, 10).unwrap();
+        assert_eq!(latest_height, 0);
+        assert!(hashes.is_empty());
+        // Ensure a limit of zero returns no hashes.
+        let (_, hashes) = ledger.get_hashes(0..10, false, 0).unwrap();
+        assert!(hashes.is_empty());
+    }
+
+    #[test]
+    fn test_clamp_heights() {
+        // Ensure the heights are clamped to the latest height.
+        assert_eq!(clamp_heights(0..10, 4, false, 100), vec![0, 1, 2, 3, 4]);
+        assert_eq!(clamp_heights(0..3, 4, false, 100), vec![0, 1, 2]);
+        assert_eq!(clamp_heights(2..u32::MAX, u32::MAX, false, 3), vec![2, 3, 4]);
+
+        // Ensure the heights are reversed, and truncated from the end of the range.
+        assert_eq!(clamp_heights(0..10, 4, true, 100), vec![4, 3, 2, 1, 0]);
+        assert_eq!(clamp_heights(0..10, 20, true, 3), vec![9, 8, 7]);
+
+        // Ensure the heights are truncated to the limit.
+        assert_eq!(clamp_heights(0..10, 20, false, 3), vec![0, 1, 2]);
+        assert!(clamp_heights(0..10, 20, false, 0).is_empty());
+
+        // Ensure out-of-range starts return no heights.
+        assert!(clamp_heights(5..10, 4, false, 100).is_empty());
+        assert!(clamp_heights(5..10, 4, true, 100).is_empty());
+        assert!(clamp_heights(Range { start: 10, end: 5 }, 20, false, 100).is_empty());
+    }
 }
@@ -19,11 +19,53 @@
 #[macro_use]
 extern crate tracing;
 
+mod accounts;
+pub use accounts::*;
+
+mod branches;
+pub use branches::*;
+
+mod check;
+pub use check::*;
+
 mod contains;
+
+mod deposit;
+pub use deposit::*;
+
+mod events;
+pub use events::*;
+
 mod find;
-mod get;
 mod iterators;
 
+mod membership;
+pub use membership::*;
+
+mod network_time;
+pub use network_time::*;
+
+mod get;
+pub use get::MAX_HEIGHTS_PER_SCAN;
+
+mod pruning;
+pub use pruning::*;
+
+mod recent;
+pub use recent::*;
+
+mod shadow;
+pub use shadow::*;
+
+mod spent;
+pub use spent::*;
+
+mod structure;
+pub use structure::*;
+
+mod subscriptions;
+pub use subscriptions::*;
+
 #[cfg(test)]
 mod tests;
 
@@ -47,9 +89,15 @@ use aleo_std::prelude::{finish, lap, timer};
 use anyhow::Result;
 use core::ops::Range;
 use indexmap::IndexMap;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rand::{prelude::IteratorRandom, rngs::OsRng};
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -70,6 +118,20 @@ pub enum RecordsFilter<N: Network> {
     SlowUnspent(PrivateKey<N>),
 }
 
+/// The ledger, which reads and writes the blocks of the canonical chain.
+///
+/// The blocks are visible to readers once the current block (the canon pointer) is swapped to them,
+/// so a block that is being written to storage is never observed partially. The writes are serialized
+/// by the commit lock, which is held for the whole write, while the current block is only locked to swap it.
+///
+/// The locks are acquired in the following order: `commit_lock`, `current_block`, `current_epoch_challenge`.
+/// The `digest_tree` lock is never held with the commit lock, and is acquired before `current_block`.
+/// The `side_branches` lock is acquired last, and the `recent_blocks` lock is never held with another lock.
+/// The `tip_digest` lock is acquired after the commit lock, and before `current_block`.
+/// The `shadow` lock is acquired after the commit lock, and is never held with `current_block`.
+/// The `spent_filter` lock is never held with another lock, and is written before `current_block` is swapped.
+/// The `account_histories` lock is acquired after the commit lock, and is never held with another lock.
+/// The events are published last, once the blocks are visible to readers.
 #[derive(Clone)]
 pub struct Ledger<N: Network, C: ConsensusStorage<N>> {
     /// The VM state.
@@ -77,34 +139,86 @@ pub struct Ledger<N: Network, C: ConsensusStorage<N>> {
     /// The genesis block.
     genesis: Block<N>,
     /// The current block.
-    current_block: Arc<RwLock<Block<N>>>,
+    current_block: Arc<RwLock<Arc<Block<N>>>>,
     /// The current epoch challenge.
     current_epoch_challenge: Arc<RwLock<Option<EpochChallenge<N>>>>,
+    /// The lock that serializes the writes to the ledger.
+    commit_lock: Arc<Mutex<()>>,
+    /// The height below which the bodies of the blocks are pruned.
+    pruned_height: Arc<AtomicU32>,
+    /// The ledger Merkle tree at the ledger digest of the last requested membership proof, with its height.
+    digest_tree: Arc<Mutex<DigestTree<N>>>,
+    /// The side branches, which are formed by the known blocks that are not in the canonical chain.
+    side_branches: Arc<RwLock<SideBranches<N>>>,
+    /// The most recent canonical blocks, which are kept in memory for the shallow reorganizations.
+    recent_blocks: Arc<RwLock<RecentBlocks<N>>>,
+    /// The ledger digest of the latest block, with the number of commitments and serial numbers, once they are counted.
+    tip_digest: Arc<Mutex<Option<LedgerDigest<N>>>>,
+    /// The event bus, on which the connected and disconnected blocks are published.
+    events: EventBus<N>,
+    /// The shadow storage, to which the canon mutations are applied in the background, if it is enabled.
+    shadow: Arc<RwLock<Option<Shadow<N>>>>,
+    /// The filter of the spent serial numbers, which rules out the unspent serial numbers before the index is read.
+    spent_filter: Arc<RwLock<SerialNumberFilter<N>>>,
+    /// The histories of the registered accounts, by their address.
+    account_histories: Arc<RwLock<IndexMap<Address<N>, AccountHistory<N>>>>,
+    /// The network-adjusted time, against which the timestamps of the next blocks are checked.
+    network_time: NetworkTime,
 }
 
 impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
-    /// Loads the ledger from storage.
+    /// Loads the ledger from storage, checking the consistency of the latest blocks.
     pub fn load(genesis: Block<N>, dev: Option<u16>) -> Result<Self> {
+        Self::load_with_check(genesis, dev, ConsistencyCheck::default())
+    }
+
+    /// Loads the ledger from storage, with the given consistency check.
+    /// If the check finds an inconsistency, the ledger is truncated to the latest consistent block.
+    pub fn load_with_check(genesis: Block<N>, dev: Option<u16>, check: ConsistencyCheck) -> Result<Self> {
+        Self::load_pruned(genesis, dev, check, 0)
+    }
+
+    /// Loads the ledger from storage, with the given consistency check, and the height below which the bodies
+    /// of the blocks are pruned. The pruned blocks are excluded from the consistency check.
+    pub fn load_pruned(
+        genesis: Block<N>,
+        dev: Option<u16>,
+        check: ConsistencyCheck,
+        pruned_height: u32,
+    ) -> Result<Self> {
         let timer = timer!("Ledger::load");
 
         // Retrieve the genesis hash.
         let genesis_hash = genesis.hash();
         // Initialize the ledger.
-        let ledger = Self::load_unchecked(genesis, dev)?;
+        let ledger = Self::open(genesis, dev)?;
+        // Set the pruned height.
+        ledger.set_pruned_height(pruned_height);
 
         // Ensure the ledger contains the correct genesis block.
         if !ledger.contains_block_hash(&genesis_hash)? {
             bail!("Incorrect genesis block (run 'snarkos clean' and try again)")
         }
 
+        // Check the consistency of the ledger, truncating it to the latest consistent block if needed.
+        ledger.repair_consistency(check)?;
+        lap!(timer, "Check consistency ({check:?})");
+
+        // Set the latest block.
+        ledger.load_latest_block()?;
+        lap!(timer, "Initialize ledger");
+
         // Retrieve the latest height.
         let latest_height =
             *ledger.vm.block_store().heights().max().ok_or_else(|| anyhow!("Failed to load blocks from the ledger"))?;
 
-        // Safety check the existence of `NUM_BLOCKS` random blocks.
+        // Safety check the existence of `NUM_BLOCKS` random blocks, which are not pruned.
         const NUM_BLOCKS: usize = 1000;
-        let block_heights: Vec<u32> = (0..=latest_height)
-            .choose_multiple(&mut OsRng::default(), core::cmp::min(NUM_BLOCKS, latest_height as usize));
+        let start_height = ledger.pruned_height().min(latest_height);
+        let block_heights: Vec<u32> = (start_height..=latest_height).choose_multiple(
+            &mut OsRng::default(),
+            core::cmp::min(NUM_BLOCKS, (latest_height - start_height) as usize),
+        );
         cfg_into_iter!(block_heights).try_for_each(|height| {
             ledger.get_block(height)?;
             Ok::<_, Error>(())
@@ -119,6 +233,23 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
     pub fn load_unchecked(genesis: Block<N>, dev: Option<u16>) -> Result<Self> {
         let timer = timer!("Ledger::load_unchecked");
 
+        // Initialize the ledger.
+        let ledger = Self::open(genesis, dev)?;
+        lap!(timer, "Open ledger");
+
+        // Set the latest block.
+        ledger.load_latest_block()?;
+        lap!(timer, "Initialize ledger");
+
+        finish!(timer);
+        Ok(ledger)
+    }
+
+    /// Opens the ledger from storage, initializing the genesis block if the storage is empty.
+    /// Note that the latest block is set to the genesis block, until it is loaded from storage.
+    fn open(genesis: Block<N>, dev: Option<u16>) -> Result<Self> {
+        let timer = timer!("Ledger::open");
+
         // Initialize the consensus store.
         let store = match ConsensusStore::<N, C>::open(dev) {
             Ok(store) => store,
@@ -131,11 +262,22 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         lap!(timer, "Initialize a new VM");
 
         // Initialize the ledger.
-        let mut ledger = Self {
+        let ledger = Self {
             vm,
             genesis: genesis.clone(),
-            current_block: Arc::new(RwLock::new(genesis.clone())),
+            current_block: Arc::new(RwLock::new(Arc::new(genesis.clone()))),
             current_epoch_challenge: Default::default(),
+            commit_lock: Default::default(),
+            pruned_height: Default::default(),
+            digest_tree: Default::default(),
+            side_branches: Default::default(),
+            recent_blocks: Default::default(),
+            tip_digest: Default::default(),
+            events: Default::default(),
+            shadow: Default::default(),
+            spent_filter: Default::default(),
+            account_histories: Default::default(),
+            network_time: Default::default(),
         };
 
         // If the block store is empty, initialize the genesis block.
@@ -145,22 +287,40 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         }
         lap!(timer, "Initialize genesis");
 
+        finish!(timer);
+        Ok(ledger)
+    }
+
+    /// Sets the latest block and epoch challenge from storage.
+    fn load_latest_block(&self) -> Result<()> {
         // Retrieve the latest height.
         let latest_height =
-            *ledger.vm.block_store().heights().max().ok_or_else(|| anyhow!("Failed to load blocks from the ledger"))?;
-        // Fetch the latest block.
-        let block = ledger
-            .get_block(latest_height)
+            *self.vm.block_store().heights().max().ok_or_else(|| anyhow!("Failed to load blocks from the ledger"))?;
+        // Fetch the latest block from storage, as it is not yet visible through the current block.
+        let block = self
+            .get_stored_block(latest_height)
             .map_err(|_| anyhow!("Failed to load block {latest_height} from the ledger"))?;
 
+        // Rebuild the filter of the spent serial numbers from storage.
+        self.rebuild_spent_filter();
+        // Keep the block in memory, as the recent canonical blocks start from it.
+        self.push_recent_block(&block);
         // Set the current block.
-        ledger.current_block = Arc::new(RwLock::new(block));
+        *self.current_block.write() = Arc::new(block);
         // Set the current epoch challenge.
-        ledger.current_epoch_challenge = Arc::new(RwLock::new(Some(ledger.get_epoch_challenge(latest_height)?)));
-        lap!(timer, "Initialize ledger");
+        *self.current_epoch_challenge.write() = Some(self.get_epoch_challenge(latest_height)?);
+        Ok(())
+    }
 
-        finish!(timer);
-        Ok(ledger)
+    /// Returns the block for the given block height from storage, even if it is not in the canonical chain yet.
+    fn get_stored_block(&self, height: u32) -> Result<Block<N>> {
+        match self.vm.block_store().get_block_hash(height)? {
+            Some(block_hash) => match self.vm.block_store().get_block(&block_hash)? {
+                Some(block) => Ok(block),
+                None => bail!("Block {height} ('{block_hash}') does not exist in storage"),
+            },
+            None => bail!("Block {height} does not exist in storage"),
+        }
     }
 
     /// Returns the VM.
@@ -168,6 +328,16 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
         &self.vm
     }
 
+    /// Returns the event bus, on which the connected and disconnected blocks are published.
+    pub const fn event_bus(&self) -> &EventBus<N> {
+        &self.events
+    }
+
+    /// Returns the network-adjusted time, which the router samples from the handshakes of the peers.
+    pub const fn network_time(&self) -> &NetworkTime {
+        &self.network_time
+    }
+
     /// Returns the latest state root.
     pub fn latest_state_root(&self) -> N::StateRoot {
         self.vm.block_store().current_state_root()
@@ -175,7 +345,9 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
 
     /// Returns the latest block.
     pub fn latest_block(&self) -> Block<N> {
-        self.current_block.read().clone()
+        // Clone the block outside the lock, as it may be large.
+        let current_block = self.current_block.read().clone();
+        (*current_block).clone()
     }
 
     /// Returns the latest round number.
@@ -240,7 +412,9 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
 
     /// Returns the latest block transactions.
     pub fn latest_transactions(&self) -> Transactions<N> {
-        self.current_block.read().transactions().clone()
+        // Clone the transactions outside the lock, as they may be large.
+        let current_block = self.current_block.read().clone();
+        current_block.transactions().clone()
     }
 
     /// Returns the latest epoch number.
@@ -257,15 +431,30 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
     }
 
     /// Adds the given block as the next block in the chain.
+    ///
+    /// The block is written to storage while the readers continue to observe the current block,
+    /// and becomes visible to them at once, when the current block is swapped to it.
     pub fn add_next_block(&self, block: &Block<N>) -> Result<()> {
-        // Acquire the write lock on the current block.
-        let mut current_block = self.current_block.write();
+        // Acquire the commit lock, to serialize the writes to the ledger.
+        let _commit_lock = self.commit_lock.lock();
         // Update the VM.
         self.vm.add_next_block(block)?;
-        // Update the current block.
-        *current_block = block.clone();
-        // Drop the write lock on the current block.
-        drop(current_block);
+        // Insert the serial numbers of the block in the filter, before the block is visible to readers.
+        self.insert_spent_serial_numbers(block);
+        // Append the block to the histories of the registered accounts.
+        self.push_account_histories(block);
+        // Swap the current block, dropping the previous block outside the lock.
+        let next_block = Arc::new(block.clone());
+        let previous_block = std::mem::replace(&mut *self.current_block.write(), next_block.clone());
+        drop(previous_block);
+        // Queue the block for the shadow storage, if it is enabled.
+        self.push_shadow_block(next_block);
+        // Remove the block from the side branches, if it was disconnected before.
+        self.connect_side_block(block);
+        // Keep the block in memory, as a recent canonical block.
+        self.push_recent_block(block);
+        // Advance the counts of the ledger digest to the block.
+        self.advance_tip_digest(block);
 
         // If the block is the start of a new epoch, or the epoch challenge has not been set, update the current epoch challenge.
         if block.height() % N::NUM_BLOCKS_PER_EPOCH == 0 || self.current_epoch_challenge.read().is_none() {
@@ -273,6 +462,13 @@ impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
             self.current_epoch_challenge.write().clone_from(&self.get_epoch_challenge(block.height()).ok());
         }
 
+        // Publish the connected block.
+        self.events.publish(Event::BlockConnected {
+            height: block.height(),
+            hash: block.hash(),
+            transaction_ids: block.transaction_ids().copied().collect(),
+        });
+
         Ok(())
     }
 
@@ -0,0 +1,430 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use snarkvm::console::program::{BlockPath, BlockTree};
+
+use core::{cmp::Ordering as CmpOrdering, fmt};
+
+/// The number of latest ledger digests (the state roots of the latest blocks) against which
+/// a membership proof may be generated.
+pub const LEDGER_DIGEST_HISTORY: u32 = 4096;
+
+/// The maximum number of ledger digests returned by a single history request.
+pub const MAX_DIGESTS_PER_REQUEST: u32 = 1000;
+
+/// The ledger Merkle tree at a ledger digest, with the height of the block of the digest.
+pub(crate) type DigestTree<N> = Option<(u32, BlockTree<N>)>;
+
+/// A ledger digest, with the number of commitments and serial numbers in the ledger at its block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LedgerDigest<N: Network> {
+    /// The height of the block of the ledger digest.
+    pub height: u32,
+    /// The hash of the block of the ledger digest.
+    pub block_hash: N::BlockHash,
+    /// The ledger digest, which is the root of the ledger Merkle tree over the block hashes.
+    pub digest: N::StateRoot,
+    /// The number of commitments in the ledger, up to and including the block.
+    pub num_commitments: u64,
+    /// The number of serial numbers in the ledger, up to and including the block.
+    pub num_serial_numbers: u64,
+}
+
+/// Returns the number of commitments and serial numbers in the given transactions.
+fn count_commitments<N: Network>(transactions: &Transactions<N>) -> (u64, u64) {
+    (transactions.commitments().count() as u64, transactions.serial_numbers().count() as u64)
+}
+
+/// The error returned for a ledger digest that is older than the retained history.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DigestOutOfHistory {
+    /// The height of the block of the requested ledger digest.
+    pub height: u32,
+    /// The height of the latest block.
+    pub latest_height: u32,
+}
+
+impl fmt::Display for DigestOutOfHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The ledger digest of block {} is older than the retained history (the digests since block {})",
+            self.height,
+            self.latest_height.saturating_sub(LEDGER_DIGEST_HISTORY - 1)
+        )
+    }
+}
+
+impl std::error::Error for DigestOutOfHistory {}
+
+/// A proof that a commitment is in the ledger Merkle tree at a given ledger digest.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LedgerMembershipProof<N: Network> {
+    /// The commitment.
+    commitment: Field<N>,
+    /// The state path from the commitment to the ledger digest.
+    state_path: StatePath<N>,
+}
+
+impl<N: Network> LedgerMembershipProof<N> {
+    /// Initializes a new membership proof for the given commitment and state path.
+    pub const fn new(commitment: Field<N>, state_path: StatePath<N>) -> Self {
+        Self { commitment, state_path }
+    }
+
+    /// Returns the commitment.
+    pub const fn commitment(&self) -> &Field<N> {
+        &self.commitment
+    }
+
+    /// Returns the ledger digest.
+    pub const fn digest(&self) -> N::StateRoot {
+        self.state_path.global_state_root()
+    }
+
+    /// Returns the index of the block (its height) in the ledger Merkle tree.
+    pub fn leaf_index(&self) -> u64 {
+        *self.state_path.block_path().leaf_index()
+    }
+
+    /// Returns the nodes of the Merkle path from the block hash to the ledger digest.
+    pub fn path(&self) -> &[Field<N>] {
+        self.state_path.block_path().siblings()
+    }
+
+    /// Returns the state path from the commitment to the ledger digest.
+    pub const fn state_path(&self) -> &StatePath<N> {
+        &self.state_path
+    }
+}
+
+/// Checks that the given membership proof proves its commitment is in the ledger Merkle tree at the given digest.
+pub fn verify_ledger_membership_proof<N: Network>(
+    proof: &LedgerMembershipProof<N>,
+    digest: N::StateRoot,
+) -> Result<()> {
+    let state_path = proof.state_path();
+    // Ensure the proof is for the given ledger digest.
+    ensure!(state_path.global_state_root() == digest, "The membership proof is not for the ledger digest '{digest}'");
+    // Ensure the transition leaf is the commitment.
+    ensure!(
+        state_path.transition_leaf().id() == *proof.commitment(),
+        "The membership proof is not for the commitment '{}'",
+        proof.commitment()
+    );
+    // Ensure the commitment belongs to its transition.
+    ensure!(
+        N::verify_merkle_path_bhp(
+            state_path.transition_path(),
+            &state_path.transaction_leaf().id(),
+            &state_path.transition_leaf().to_bits_le()
+        ),
+        "Commitment '{}' does not belong to transition '{}'",
+        proof.commitment(),
+        state_path.transaction_leaf().id()
+    );
+    // Ensure the transition belongs to its transaction.
+    ensure!(
+        N::verify_merkle_path_bhp(
+            state_path.transaction_path(),
+            state_path.transaction_id(),
+            &state_path.transaction_leaf().to_bits_le()
+        ),
+        "Transition '{}' does not belong to transaction '{}'",
+        state_path.transaction_leaf().id(),
+        state_path.transaction_id()
+    );
+    // Ensure the header leaf is the transactions root.
+    ensure!(
+        state_path.header_leaf().index() == 1,
+        "The header leaf of the membership proof must be the transactions root"
+    );
+    // Ensure the transaction belongs to its block.
+    ensure!(
+        N::verify_merkle_path_bhp(
+            state_path.transactions_path(),
+            &state_path.header_leaf().id(),
+            &state_path.transaction_id().to_bits_le()
+        ),
+        "Transaction '{}' does not belong to the transactions root '{}'",
+        state_path.transaction_id(),
+        state_path.header_leaf().id()
+    );
+    // Ensure the transactions root belongs to the block header.
+    ensure!(
+        N::verify_merkle_path_bhp(
+            state_path.header_path(),
+            state_path.header_root(),
+            &state_path.header_leaf().to_bits_le()
+        ),
+        "The transactions root '{}' does not belong to block '{}'",
+        state_path.header_leaf().id(),
+        state_path.block_hash()
+    );
+    // Ensure the block hash is correct.
+    let preimage =
+        (*state_path.previous_block_hash()).to_bits_le().into_iter().chain(state_path.header_root().to_bits_le());
+    ensure!(
+        *state_path.block_hash() == N::hash_bhp1024(&preimage.collect::<Vec<_>>())?,
+        "Block hash '{}' is incorrect",
+        state_path.block_hash()
+    );
+    // Ensure the block belongs to the ledger digest.
+    ensure!(
+        N::verify_merkle_path_bhp(state_path.block_path(), &digest, &state_path.block_hash().to_bits_le()),
+        "Block '{}' does not belong to the ledger digest '{digest}'",
+        state_path.block_hash()
+    );
+    Ok(())
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Returns a proof that the given commitment is in the ledger Merkle tree at the given ledger digest,
+    /// which must be the state root of one of the latest `LEDGER_DIGEST_HISTORY` blocks.
+    pub fn generate_ledger_membership_proof(
+        &self,
+        commitment: &Field<N>,
+        at_digest: N::StateRoot,
+    ) -> Result<LedgerMembershipProof<N>> {
+        // Retrieve the height of the block of the ledger digest.
+        let latest_height = self.latest_height();
+        let height = match self.find_block_height_from_state_root(at_digest)? {
+            Some(height) if height <= latest_height => height,
+            _ => bail!("Ledger digest '{at_digest}' does not exist in the ledger"),
+        };
+        // Ensure the ledger digest is within the retained history.
+        if latest_height - height >= LEDGER_DIGEST_HISTORY {
+            return Err(DigestOutOfHistory { height, latest_height }.into());
+        }
+        // Retrieve the height of the block containing the commitment.
+        let commitment_height = match self.find_block_height_from_commitment(commitment)? {
+            Some(commitment_height) if commitment_height <= height => commitment_height,
+            Some(commitment_height) => {
+                bail!("Commitment '{commitment}' was added in block {commitment_height}, after the ledger digest")
+            }
+            None => bail!("Commitment '{commitment}' does not exist in the ledger"),
+        };
+
+        // Retrieve the state path for the commitment, against the latest ledger digest.
+        let state_path = self.get_state_path_for_commitment(commitment)?;
+        // Compute the Merkle path of the block, against the given ledger digest.
+        let block_path = self.prove_block_at_digest(height, commitment_height, state_path.block_hash())?;
+
+        // Construct the membership proof.
+        let proof = LedgerMembershipProof::new(
+            *commitment,
+            StatePath::from(
+                at_digest,
+                block_path,
+                state_path.block_hash(),
+                state_path.previous_block_hash(),
+                *state_path.header_root(),
+                state_path.header_path().clone(),
+                *state_path.header_leaf(),
+                state_path.transactions_path().clone(),
+                *state_path.transaction_id(),
+                state_path.transaction_path().clone(),
+                *state_path.transaction_leaf(),
+                state_path.transition_path().clone(),
+                *state_path.transition_leaf(),
+            ),
+        );
+        // Ensure the membership proof is valid.
+        verify_ledger_membership_proof(&proof, at_digest)?;
+        Ok(proof)
+    }
+
+    /// Returns the Merkle path of the given block hash at the given height, in the ledger Merkle tree
+    /// at the ledger digest of the block at `digest_height`.
+    ///
+    /// The tree of the last requested digest is cached, and the tree of the next requested digest
+    /// is derived from it by appending or removing the block hashes in between.
+    pub(crate) fn prove_block_at_digest(
+        &self,
+        digest_height: u32,
+        height: u32,
+        block_hash: N::BlockHash,
+    ) -> Result<BlockPath<N>> {
+        // Retrieve the ledger digest.
+        let digest = match self.get_state_root(digest_height)? {
+            Some(digest) => digest,
+            None => bail!("Missing ledger digest for block {digest_height}"),
+        };
+
+        let mut cached_tree = self.digest_tree.lock();
+        // Derive the tree from the cached tree, if it is still in the canonical chain, or reconstruct it otherwise.
+        let tree = match cached_tree.take() {
+            Some((cached_height, tree))
+                if cached_height <= self.latest_height()
+                    && self.get_state_root(cached_height)? == Some((*tree.root()).into()) =>
+            {
+                match cached_height.cmp(&digest_height) {
+                    CmpOrdering::Equal => tree,
+                    CmpOrdering::Less => {
+                        tree.prepare_append(&self.block_hash_leaves(cached_height + 1..digest_height + 1)?)?
+                    }
+                    CmpOrdering::Greater => tree.prepare_remove_last_n((cached_height - digest_height) as usize)?,
+                }
+            }
+            _ => N::merkle_tree_bhp(&self.block_hash_leaves(0..digest_height + 1)?)?,
+        };
+        // Ensure the tree is at the ledger digest.
+        ensure!(
+            digest == (*tree.root()).into(),
+            "Failed to reconstruct the ledger Merkle tree at block {digest_height}"
+        );
+
+        // Compute the Merkle path of the block.
+        let block_path = tree.prove(height as usize, &block_hash.to_bits_le())?;
+        // Cache the tree.
+        *cached_tree = Some((digest_height, tree));
+        Ok(block_path)
+    }
+
+    /// Returns the leaves of the ledger Merkle tree, for the block hashes in the given range of heights.
+    fn block_hash_leaves(&self, heights: Range<u32>) -> Result<Vec<Vec<bool>>> {
+        heights.map(|height| Ok(self.get_hash(height)?.to_bits_le())).collect()
+    }
+
+    /// Returns the ledger digest at the given height, which defaults to the latest block, with the number of
+    /// commitments and serial numbers in the ledger at its block. The height must be one of the latest
+    /// `LEDGER_DIGEST_HISTORY` blocks.
+    pub fn get_ledger_digest(&self, height: Option<u32>) -> Result<LedgerDigest<N>> {
+        // Retrieve the ledger digest of the latest block, with its counts.
+        let tip = self.tip_digest()?;
+        let height = height.unwrap_or(tip.height);
+        ensure!(
+            height <= tip.height,
+            "Block {height} does not exist in the ledger (the latest block is {})",
+            tip.height
+        );
+        // Ensure the ledger digest is within the retained history.
+        if tip.height - height >= LEDGER_DIGEST_HISTORY {
+            return Err(DigestOutOfHistory { height, latest_height: tip.height }.into());
+        }
+
+        // Rewind the counts from the latest block to the given height.
+        let mut digest = tip;
+        for block_height in (height + 1..=tip.height).rev() {
+            let (num_commitments, num_serial_numbers) = count_commitments(&self.get_transactions(block_height)?);
+            digest.num_commitments = digest.num_commitments.saturating_sub(num_commitments);
+            digest.num_serial_numbers = digest.num_serial_numbers.saturating_sub(num_serial_numbers);
+        }
+        digest.height = height;
+        digest.block_hash = self.get_hash(height)?;
+        digest.digest = match self.get_state_root(height)? {
+            Some(digest) => digest,
+            None => bail!("Missing ledger digest for block {height}"),
+        };
+
+        // Ensure the blocks were not reorganized while the counts were rewound.
+        ensure!(
+            self.get_hash(tip.height).ok() == Some(tip.block_hash),
+            "The ledger was reorganized while retrieving the ledger digest of block {height}"
+        );
+        Ok(digest)
+    }
+
+    /// Returns the latest `count` ledger digests, with the heights of their blocks, starting from the latest block.
+    pub fn get_ledger_digest_history(&self, count: u32) -> Result<Vec<(u32, N::StateRoot)>> {
+        ensure!(count > 0, "The number of ledger digests must be greater than 0");
+        ensure!(
+            count <= MAX_DIGESTS_PER_REQUEST,
+            "The number of ledger digests must be at most {MAX_DIGESTS_PER_REQUEST} (found {count})"
+        );
+        // Retrieve the digests of the latest blocks, until the genesis block.
+        let latest_height = self.latest_height();
+        (latest_height.saturating_sub(count - 1)..=latest_height)
+            .rev()
+            .map(|height| match self.get_state_root(height)? {
+                Some(digest) => Ok((height, digest)),
+                None => bail!("Missing ledger digest for block {height}"),
+            })
+            .collect()
+    }
+
+    /// Returns the ledger digest of the latest block, with the number of commitments and serial numbers in the ledger.
+    /// The counts are computed from storage on the first call, and are kept up to date with the blocks afterwards.
+    fn tip_digest(&self) -> Result<LedgerDigest<N>> {
+        if let Some(tip) = *self.tip_digest.lock() {
+            return Ok(tip);
+        }
+        // Acquire the commit lock, so that the counts match the latest block.
+        let _commit_lock = self.commit_lock.lock();
+        let mut tip_digest = self.tip_digest.lock();
+        if let Some(tip) = *tip_digest {
+            return Ok(tip);
+        }
+        // Count the commitments and serial numbers in storage.
+        let latest_block = self.current_block.read().clone();
+        let tip = LedgerDigest {
+            height: latest_block.height(),
+            block_hash: latest_block.hash(),
+            digest: self.latest_state_root(),
+            num_commitments: self.vm.transition_store().commitments().count() as u64,
+            num_serial_numbers: self.vm.transition_store().serial_numbers().count() as u64,
+        };
+        *tip_digest = Some(tip);
+        Ok(tip)
+    }
+
+    /// Advances the ledger digest of the latest block to the given block, which was just added.
+    pub(crate) fn advance_tip_digest(&self, block: &Block<N>) {
+        let mut tip_digest = self.tip_digest.lock();
+        // If the counts are not computed yet, or do not extend to the block, they are computed again on the next call.
+        *tip_digest = match *tip_digest {
+            Some(tip) if tip.block_hash == block.previous_hash() => {
+                let (num_commitments, num_serial_numbers) = count_commitments(block.transactions());
+                Some(LedgerDigest {
+                    height: block.height(),
+                    block_hash: block.hash(),
+                    digest: self.latest_state_root(),
+                    num_commitments: tip.num_commitments + num_commitments,
+                    num_serial_numbers: tip.num_serial_numbers + num_serial_numbers,
+                })
+            }
+            _ => None,
+        };
+    }
+
+    /// Rewinds the ledger digest of the latest block to the given block, as the given blocks after it were just removed.
+    pub(crate) fn rewind_tip_digest(&self, block: &Block<N>, removed: &[Result<Block<N>>]) {
+        let mut tip_digest = self.tip_digest.lock();
+        // Ensure the counts are at the last removed block, and every removed block was retrieved.
+        let is_tip = |tip: &LedgerDigest<N>| match removed.last() {
+            Some(Ok(last)) => last.hash() == tip.block_hash,
+            _ => false,
+        };
+        *tip_digest = match *tip_digest {
+            Some(mut tip) if is_tip(&tip) && removed.iter().all(|removed| removed.is_ok()) => {
+                for removed in removed.iter().flatten() {
+                    let (num_commitments, num_serial_numbers) = count_commitments(removed.transactions());
+                    tip.num_commitments = tip.num_commitments.saturating_sub(num_commitments);
+                    tip.num_serial_numbers = tip.num_serial_numbers.saturating_sub(num_serial_numbers);
+                }
+                Some(LedgerDigest {
+                    height: block.height(),
+                    block_hash: block.hash(),
+                    digest: self.latest_state_root(),
+                    ..tip
+                })
+            }
+            _ => None,
+        };
+    }
+}
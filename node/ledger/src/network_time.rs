@@ -0,0 +1,259 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use indexmap::IndexMap;
+use parking_lot::RwLock;
+use std::{
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The maximum number of peer clock samples, beyond which the oldest sample is replaced.
+pub const MAXIMUM_TIME_SAMPLES: usize = 200;
+/// The minimum number of peer clock samples, below which the local clock is not adjusted.
+pub const MINIMUM_TIME_SAMPLES: usize = 5;
+/// The maximum adjustment of the local clock, in seconds, so that the peers cannot shift the time of the node
+/// further than this, however many of them report a skewed clock.
+pub const MAXIMUM_TIME_ADJUSTMENT_IN_SECS: i64 = 300;
+/// The offset of the local clock from the peers, in seconds, above which the local clock is reported as skewed.
+pub const TIME_SKEW_WARNING_IN_SECS: i64 = 60;
+/// The maximum offset of a peer clock sample, in seconds, beyond which the sample is clamped, as the peers
+/// choose the timestamps they report.
+pub const MAXIMUM_TIME_SAMPLE_OFFSET_IN_SECS: i64 = 24 * 3600;
+
+/// The network-adjusted time, which offsets the local clock by the median offset of the clocks of the peers,
+/// as reported in their handshakes.
+///
+/// A single sample is kept per IP address, so that a host cannot outweigh the other peers by connecting
+/// from several ports, and the adjustment is capped at `MAXIMUM_TIME_ADJUSTMENT_IN_SECS`.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkTime {
+    /// The offsets of the clocks of the peers from the local clock, in seconds, by IP address, in order of insertion.
+    samples: Arc<RwLock<IndexMap<IpAddr, i64>>>,
+    /// Whether the skew of the local clock was reported, and not resolved since.
+    is_skew_reported: Arc<AtomicBool>,
+}
+
+impl NetworkTime {
+    /// Records the given UNIX timestamp (in seconds), which the peer with the given IP address reported.
+    pub fn insert_timestamp(&self, peer_ip: IpAddr, peer_timestamp: i64) {
+        self.insert_offset(peer_ip, peer_timestamp.saturating_sub(local_timestamp()));
+    }
+
+    /// Records the given offset (in seconds) of the clock of the peer with the given IP address.
+    /// The offset is clamped at `MAXIMUM_TIME_SAMPLE_OFFSET_IN_SECS` in either direction, and replaces
+    /// the previous sample of the peer, and the oldest sample once the samples are full.
+    pub fn insert_offset(&self, peer_ip: IpAddr, offset: i64) {
+        let offset = offset.clamp(-MAXIMUM_TIME_SAMPLE_OFFSET_IN_SECS, MAXIMUM_TIME_SAMPLE_OFFSET_IN_SECS);
+        let mut samples = self.samples.write();
+        samples.shift_remove(&peer_ip);
+        if samples.len() >= MAXIMUM_TIME_SAMPLES {
+            samples.shift_remove_index(0);
+        }
+        samples.insert(peer_ip, offset);
+        let (median_offset, num_samples) = (median(samples.values().copied()), samples.len());
+        drop(samples);
+
+        // Report the skew of the local clock once, until it is resolved.
+        match is_skewed(median_offset) {
+            true => {
+                if !self.is_skew_reported.swap(true, Ordering::AcqRel) {
+                    warn!(
+                        target: "critical",
+                        kind = "time-skew",
+                        median_offset,
+                        "The local clock is {}s {} the clocks of {} peers - check the clock of this node",
+                        median_offset.unsigned_abs(),
+                        if median_offset > 0 { "behind" } else { "ahead of" },
+                        num_samples,
+                    );
+                }
+            }
+            false => {
+                if self.is_skew_reported.swap(false, Ordering::AcqRel) {
+                    info!("The local clock is no longer skewed (offset of {median_offset}s from the peers)");
+                }
+            }
+        }
+    }
+
+    /// Returns the number of peer clock samples.
+    pub fn num_samples(&self) -> usize {
+        self.samples.read().len()
+    }
+
+    /// Returns the median offset (in seconds) of the clocks of the peers from the local clock, without the cap,
+    /// or `0` if there are fewer than `MINIMUM_TIME_SAMPLES` samples.
+    pub fn median_offset(&self) -> i64 {
+        median(self.samples.read().values().copied())
+    }
+
+    /// Returns the adjustment (in seconds) of the local clock, which is the median offset, capped at
+    /// `MAXIMUM_TIME_ADJUSTMENT_IN_SECS` in either direction.
+    pub fn offset(&self) -> i64 {
+        self.median_offset().clamp(-MAXIMUM_TIME_ADJUSTMENT_IN_SECS, MAXIMUM_TIME_ADJUSTMENT_IN_SECS)
+    }
+
+    /// Returns `true` if the median offset of the clocks of the peers exceeds `TIME_SKEW_WARNING_IN_SECS`.
+    pub fn is_skewed(&self) -> bool {
+        is_skewed(self.median_offset())
+    }
+
+    /// Returns the network-adjusted UNIX timestamp, in seconds.
+    pub fn now(&self) -> i64 {
+        local_timestamp().saturating_add(self.offset())
+    }
+}
+
+/// Returns the median of the given offsets, or `0` if there are fewer than `MINIMUM_TIME_SAMPLES` offsets.
+fn median(offsets: impl Iterator<Item = i64>) -> i64 {
+    let mut offsets = offsets.collect::<Vec<_>>();
+    if offsets.len() < MINIMUM_TIME_SAMPLES {
+        return 0;
+    }
+    offsets.sort_unstable();
+    let middle = offsets.len() / 2;
+    match offsets.len() % 2 {
+        // Average the middle offsets without overflowing.
+        0 => {
+            let (lower, upper) = (offsets[middle - 1], offsets[middle]);
+            lower / 2 + upper / 2 + (lower % 2 + upper % 2) / 2
+        }
+        _ => offsets[middle],
+    }
+}
+
+/// Returns `true` if the given median offset exceeds `TIME_SKEW_WARNING_IN_SECS`.
+fn is_skewed(median_offset: i64) -> bool {
+    median_offset.unsigned_abs() > TIME_SKEW_WARNING_IN_SECS as u64
+}
+
+/// Returns the UNIX timestamp of the local clock, in seconds.
+fn local_timestamp() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the IP address of the peer with the given index.
+    fn peer_ip(index: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, index])
+    }
+
+    #[test]
+    fn test_median_offset() {
+        let time = NetworkTime::default();
+        // Ensure the local clock is not adjusted below the minimum number of samples.
+        for (index, offset) in [40, -10, 30, 20].into_iter().enumerate() {
+            time.insert_offset(peer_ip(index as u8), offset);
+        }
+        assert_eq!((time.num_samples(), time.median_offset(), time.offset()), (4, 0, 0));
+
+        // Ensure the median resists an outlier, with an odd and an even number of samples.
+        time.insert_offset(peer_ip(4), 10_000);
+        assert_eq!((time.median_offset(), time.offset()), (30, 30));
+        time.insert_offset(peer_ip(5), -10_000);
+        assert_eq!(time.median_offset(), 25);
+
+        // Ensure a peer replaces its own sample, rather than adding another one.
+        time.insert_offset(peer_ip(4), -20);
+        assert_eq!((time.num_samples(), time.median_offset()), (6, 5));
+        assert!(!time.is_skewed());
+    }
+
+    #[test]
+    fn test_offset_is_capped() {
+        let time = NetworkTime::default();
+        // Ensure a majority of peers cannot shift the time by more than the cap, in either direction.
+        for index in 0..MINIMUM_TIME_SAMPLES as u8 {
+            time.insert_offset(peer_ip(index), 24 * 3600);
+        }
+        assert_eq!(time.median_offset(), 24 * 3600);
+        assert_eq!(time.offset(), MAXIMUM_TIME_ADJUSTMENT_IN_SECS);
+        for index in 0..MINIMUM_TIME_SAMPLES as u8 {
+            time.insert_offset(peer_ip(index), -24 * 3600);
+        }
+        assert_eq!(time.offset(), -MAXIMUM_TIME_ADJUSTMENT_IN_SECS);
+
+        // Ensure the oldest samples are replaced once the samples are full.
+        for index in 0..MAXIMUM_TIME_SAMPLES {
+            time.insert_offset(IpAddr::from([10, 1, (index / 256) as u8, (index % 256) as u8]), 0);
+        }
+        assert_eq!((time.num_samples(), time.offset()), (MAXIMUM_TIME_SAMPLES, 0));
+    }
+
+    #[test]
+    fn test_extreme_timestamps_are_clamped() {
+        let time = NetworkTime::default();
+        // Ensure the extreme timestamps of the peers neither overflow the median, nor exceed the sample window.
+        for index in 0..3 {
+            time.insert_timestamp(peer_ip(index), i64::MIN);
+        }
+        for index in 3..6 {
+            time.insert_timestamp(peer_ip(index), i64::MAX);
+        }
+        assert_eq!(time.median_offset(), 0);
+        time.insert_timestamp(peer_ip(6), i64::MIN);
+        assert_eq!(time.median_offset(), -MAXIMUM_TIME_SAMPLE_OFFSET_IN_SECS);
+        assert_eq!(time.offset(), -MAXIMUM_TIME_ADJUSTMENT_IN_SECS);
+        assert!(time.is_skewed());
+    }
+
+    #[test]
+    fn test_skew_warning_threshold() {
+        let time = NetworkTime::default();
+        // Ensure an offset at the threshold is not reported as a skew.
+        for index in 0..MINIMUM_TIME_SAMPLES as u8 {
+            time.insert_offset(peer_ip(index), TIME_SKEW_WARNING_IN_SECS);
+        }
+        assert!(!time.is_skewed());
+        assert!(!time.is_skew_reported.load(Ordering::Acquire));
+
+        // Ensure an offset beyond the threshold is reported, once the majority of peers reports it.
+        for index in 0..MINIMUM_TIME_SAMPLES as u8 / 2 {
+            time.insert_offset(peer_ip(index), -TIME_SKEW_WARNING_IN_SECS - 1);
+        }
+        assert!(!time.is_skewed());
+        time.insert_offset(peer_ip(MINIMUM_TIME_SAMPLES as u8 / 2), -TIME_SKEW_WARNING_IN_SECS - 1);
+        assert!(time.is_skewed());
+        assert!(time.is_skew_reported.load(Ordering::Acquire));
+        assert_eq!(time.offset(), -TIME_SKEW_WARNING_IN_SECS - 1);
+
+        // Ensure the report is cleared once the peers agree with the local clock again.
+        for index in 0..MINIMUM_TIME_SAMPLES as u8 {
+            time.insert_offset(peer_ip(index), 0);
+        }
+        assert!(!time.is_skewed());
+        assert!(!time.is_skew_reported.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_now_is_adjusted() {
+        let time = NetworkTime::default();
+        for index in 0..MINIMUM_TIME_SAMPLES as u8 {
+            time.insert_timestamp(peer_ip(index), local_timestamp() + 100);
+        }
+        // Allow for the local clock to tick in between the samples and the check.
+        let adjustment = time.now() - local_timestamp();
+        assert!((99..=101).contains(&adjustment), "{adjustment}");
+    }
+}
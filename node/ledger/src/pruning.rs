@@ -0,0 +1,109 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use core::fmt;
+
+/// The error returned for the data of a block whose body was pruned from storage.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlockPruned {
+    /// The height of the requested block.
+    pub height: u32,
+    /// The height below which the blocks are pruned.
+    pub pruned_height: u32,
+}
+
+impl fmt::Display for BlockPruned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Block {} was pruned (the blocks below {} are pruned)", self.height, self.pruned_height)
+    }
+}
+
+impl std::error::Error for BlockPruned {}
+
+/// The metadata of a confirmed transaction, which remains available once its block is pruned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionMetadata<N: Network> {
+    /// The ID of the transaction.
+    pub id: N::TransactionID,
+    /// The hash of the block containing the transaction.
+    pub block_hash: N::BlockHash,
+    /// The height of the block containing the transaction.
+    pub block_height: u32,
+    /// The ID of the deployed program, if the transaction is a deployment.
+    pub program_id: Option<ProgramID<N>>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Returns the height below which the bodies of the blocks are pruned, or `0` if no block is pruned.
+    pub fn pruned_height(&self) -> u32 {
+        self.pruned_height.load(Ordering::SeqCst)
+    }
+
+    /// Sets the height below which the bodies of the blocks are pruned, which never decreases.
+    ///
+    /// Note that the height must be set before the blocks are pruned from storage,
+    /// so that the readers never observe a partially pruned block.
+    pub fn set_pruned_height(&self, height: u32) {
+        self.pruned_height.fetch_max(height, Ordering::SeqCst);
+    }
+
+    /// Returns an error if the body of the block at the given height is pruned.
+    /// Note that the genesis block is never pruned.
+    pub(crate) fn ensure_not_pruned(&self, height: u32) -> Result<()> {
+        let pruned_height = self.pruned_height();
+        match height > 0 && height < pruned_height {
+            true => Err(BlockPruned { height, pruned_height }.into()),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns the metadata of the confirmed transaction with the given transaction ID.
+    pub fn get_transaction_metadata(&self, transaction_id: N::TransactionID) -> Result<TransactionMetadata<N>> {
+        // Retrieve the block containing the transaction.
+        let block_hash = match self.vm.block_store().find_block_hash(&transaction_id)? {
+            Some(block_hash) if self.is_canon_hash(&block_hash)? => block_hash,
+            _ => bail!("Missing transaction for ID {transaction_id}"),
+        };
+        // Retrieve the metadata from the indexes, which are kept when the block is pruned.
+        Ok(TransactionMetadata {
+            id: transaction_id,
+            block_hash,
+            block_height: self.get_height(&block_hash)?,
+            program_id: self.vm.transaction_store().get_program_id(&transaction_id)?,
+        })
+    }
+
+    /// Returns the height of the block containing the given commitment, if it is found.
+    pub(crate) fn find_block_height_from_commitment(&self, commitment: &Field<N>) -> Result<Option<u32>> {
+        // Retrieve the transition containing the commitment.
+        let transition_id = match self.vm.transition_store().find_transition_id(commitment) {
+            Ok(transition_id) => transition_id,
+            Err(_) => return Ok(None),
+        };
+        // Retrieve the transaction containing the transition.
+        let transaction_id = match self.vm.transaction_store().find_transaction_id_from_transition_id(&transition_id)? {
+            Some(transaction_id) => transaction_id,
+            None => return Ok(None),
+        };
+        // Retrieve the height of the block containing the transaction.
+        match self.vm.block_store().find_block_hash(&transaction_id)? {
+            Some(block_hash) => self.vm.block_store().get_block_height(&block_hash),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use std::{collections::VecDeque, net::SocketAddr, sync::atomic::AtomicU64};
+
+/// The default number of recent canonical blocks that are kept in memory.
+pub const DEFAULT_RECENT_BLOCKS: usize = 16;
+/// The default byte budget of the recent canonical blocks that are kept in memory.
+pub const DEFAULT_RECENT_BLOCKS_BYTE_BUDGET: usize = 64 * 1024 * 1024;
+
+/// A block, along with the size of its serialization in bytes, and the peer that sent it (if any).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerialBlock<N: Network> {
+    /// The block.
+    block: Block<N>,
+    /// The size of the serialized block, in bytes.
+    num_bytes: usize,
+    /// The IP address of the peer that sent the block, if it was received from a peer.
+    peer_ip: Option<SocketAddr>,
+}
+
+impl<N: Network> SerialBlock<N> {
+    /// Initializes a new serial block.
+    pub const fn new(block: Block<N>, num_bytes: usize) -> Self {
+        Self { block, num_bytes, peer_ip: None }
+    }
+
+    /// Returns the serial block, as sent by the given peer.
+    pub fn with_peer_ip(mut self, peer_ip: SocketAddr) -> Self {
+        self.peer_ip = Some(peer_ip);
+        self
+    }
+
+    /// Returns the block.
+    pub const fn block(&self) -> &Block<N> {
+        &self.block
+    }
+
+    /// Returns the size of the serialized block, in bytes.
+    pub const fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Returns the IP address of the peer that sent the block, if it was received from a peer.
+    pub const fn peer_ip(&self) -> Option<SocketAddr> {
+        self.peer_ip
+    }
+
+    /// Returns the block, consuming the serial block.
+    pub fn into_block(self) -> Block<N> {
+        self.block
+    }
+}
+
+/// The number of lookups of the recent blocks that were served from memory, and that fell back to storage.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecentBlocksStats {
+    /// The number of lookups served from memory.
+    pub hits: u64,
+    /// The number of lookups that fell back to storage.
+    pub misses: u64,
+}
+
+/// The most recent canonical blocks, which are kept in memory to handle shallow reorganizations
+/// and to serve the blocks at the tip without reading them from storage.
+///
+/// The blocks are contiguous, and are bounded by both a number of blocks and a byte budget,
+/// so that a few large blocks do not exhaust the memory. The latest block is always kept.
+#[derive(Debug)]
+pub struct RecentBlocks<N: Network> {
+    /// The blocks, in increasing order of height.
+    blocks: VecDeque<Arc<SerialBlock<N>>>,
+    /// The total size of the blocks, in bytes.
+    num_bytes: usize,
+    /// The maximum number of blocks.
+    max_blocks: usize,
+    /// The maximum total size of the blocks, in bytes.
+    byte_budget: usize,
+    /// The number of lookups served from memory.
+    hits: AtomicU64,
+    /// The number of lookups that fell back to storage.
+    misses: AtomicU64,
+}
+
+impl<N: Network> Default for RecentBlocks<N> {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_BLOCKS, DEFAULT_RECENT_BLOCKS_BYTE_BUDGET)
+    }
+}
+
+impl<N: Network> RecentBlocks<N> {
+    /// Initializes the recent blocks, with the given maximum number of blocks and byte budget.
+    pub fn new(max_blocks: usize, byte_budget: usize) -> Self {
+        Self {
+            blocks: Default::default(),
+            num_bytes: 0,
+            max_blocks,
+            byte_budget,
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    /// Returns the number of blocks in memory.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns `true` if there are no blocks in memory.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the total size of the blocks in memory, in bytes.
+    pub const fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Returns the number of lookups served from memory, and that fell back to storage.
+    pub fn stats(&self) -> RecentBlocksStats {
+        RecentBlocksStats { hits: self.hits.load(Ordering::Relaxed), misses: self.misses.load(Ordering::Relaxed) }
+    }
+
+    /// Sets the maximum number of blocks and the byte budget, evicting the oldest blocks beyond them.
+    /// A maximum of zero blocks disables the recent blocks.
+    pub fn set_capacity(&mut self, max_blocks: usize, byte_budget: usize) {
+        self.max_blocks = max_blocks;
+        self.byte_budget = byte_budget;
+        if max_blocks == 0 {
+            self.clear();
+        }
+        self.evict();
+    }
+
+    /// Adds the given block as the latest canonical block.
+    /// If the block does not extend the latest block in memory, the previous blocks are dropped.
+    pub fn push(&mut self, block: Arc<SerialBlock<N>>) {
+        // If the recent blocks are disabled, skip the block.
+        if self.max_blocks == 0 {
+            return;
+        }
+        // Ensure the blocks remain contiguous.
+        if let Some(latest) = self.blocks.back() {
+            if latest.block().height() + 1 != block.block().height()
+                || latest.block().hash() != block.block().previous_hash()
+            {
+                self.clear();
+            }
+        }
+        self.num_bytes += block.num_bytes();
+        self.blocks.push_back(block);
+        self.evict();
+    }
+
+    /// Removes the blocks at and above the given height, which are no longer canonical.
+    pub fn truncate(&mut self, height: u32) {
+        while self.blocks.back().is_some_and(|block| block.block().height() >= height) {
+            if let Some(block) = self.blocks.pop_back() {
+                self.num_bytes -= block.num_bytes();
+            }
+        }
+    }
+
+    /// Returns the block at the given height, if it is in memory, and records the lookup.
+    pub fn get(&self, height: u32) -> Option<Arc<SerialBlock<N>>> {
+        let block = match self.blocks.front() {
+            Some(oldest) if height >= oldest.block().height() => {
+                self.blocks.get((height - oldest.block().height()) as usize).cloned()
+            }
+            _ => None,
+        };
+        self.record(block)
+    }
+
+    /// Returns the block with the given hash, if it is in memory, and records the lookup.
+    pub fn get_by_hash(&self, hash: &N::BlockHash) -> Option<Arc<SerialBlock<N>>> {
+        let block = self.blocks.iter().rev().find(|block| block.block().hash() == *hash).cloned();
+        self.record(block)
+    }
+
+    /// Records whether the lookup was served from memory.
+    fn record(&self, block: Option<Arc<SerialBlock<N>>>) -> Option<Arc<SerialBlock<N>>> {
+        match block.is_some() {
+            true => self.hits.fetch_add(1, Ordering::Relaxed),
+            false => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        block
+    }
+
+    /// Removes the oldest blocks beyond the maximum number of blocks or the byte budget, keeping the latest block.
+    fn evict(&mut self) {
+        while self.blocks.len() > self.max_blocks.max(1) || (self.blocks.len() > 1 && self.num_bytes > self.byte_budget)
+        {
+            if let Some(block) = self.blocks.pop_front() {
+                self.num_bytes -= block.num_bytes();
+            }
+        }
+    }
+
+    /// Removes all blocks.
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.num_bytes = 0;
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Sets the maximum number of recent canonical blocks that are kept in memory, and their byte budget.
+    pub fn set_recent_blocks_capacity(&self, max_blocks: usize, byte_budget: usize) {
+        self.recent_blocks.write().set_capacity(max_blocks, byte_budget);
+    }
+
+    /// Returns the number of lookups of the recent blocks served from memory, and that fell back to storage.
+    pub fn recent_blocks_stats(&self) -> RecentBlocksStats {
+        self.recent_blocks.read().stats()
+    }
+
+    /// Returns the canonical block at the given height, from memory if it is a recent block, or from storage otherwise.
+    pub(crate) fn get_recent_block(&self, height: u32) -> Result<Block<N>> {
+        // Retrieve the block from memory, releasing the lock before it is cloned.
+        let recent_block = self.recent_blocks.read().get(height);
+        match recent_block {
+            Some(block) => Ok(block.block().clone()),
+            None => self.get_stored_block(height),
+        }
+    }
+
+    /// Adds the given block to the recent canonical blocks, as the latest block.
+    /// If the size of the block cannot be computed, the block is skipped, and the blocks before it are dropped.
+    pub(crate) fn push_recent_block(&self, block: &Block<N>) {
+        // Compute the size of the block outside the lock.
+        match block.to_bytes_le() {
+            Ok(bytes) => self.recent_blocks.write().push(Arc::new(SerialBlock::new(block.clone(), bytes.len()))),
+            Err(error) => {
+                warn!("Failed to keep block {} in memory - {error}", block.height());
+                self.recent_blocks.write().truncate(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the genesis block, with the given size.
+    fn sample_block(num_bytes: usize) -> Arc<SerialBlock<CurrentNetwork>> {
+        let genesis = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        Arc::new(SerialBlock::new(genesis, num_bytes))
+    }
+
+    #[test]
+    fn test_recent_blocks_lookups() {
+        let block = sample_block(100);
+        let mut recent = RecentBlocks::<CurrentNetwork>::new(4, 250);
+
+        // Ensure a block that does not extend the latest block replaces the blocks.
+        recent.push(block.clone());
+        recent.push(block.clone());
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent.num_bytes(), 100);
+        assert!(recent.get(0).is_some());
+        assert!(recent.get(1).is_none());
+        assert!(recent.get_by_hash(&block.block().hash()).is_some());
+        assert_eq!(recent.stats(), RecentBlocksStats { hits: 2, misses: 1 });
+
+        // Ensure the latest block is kept even beyond the byte budget.
+        recent.push(sample_block(1000));
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent.num_bytes(), 1000);
+
+        // Ensure the truncation removes the blocks at and above the height.
+        recent.truncate(1);
+        assert_eq!(recent.len(), 1);
+        recent.truncate(0);
+        assert!(recent.is_empty());
+        assert_eq!(recent.num_bytes(), 0);
+
+        // Ensure disabling the recent blocks drops them.
+        recent.push(block.clone());
+        recent.set_capacity(0, 250);
+        assert!(recent.is_empty());
+        recent.push(block);
+        assert!(recent.is_empty());
+    }
+}
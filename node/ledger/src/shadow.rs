@@ -0,0 +1,489 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use snarkvm::synthesizer::{
+    store::{BlockStorage, BlockStore},
+    ConsensusMemory,
+};
+
+use core::{fmt, time::Duration};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        mpsc,
+    },
+    thread,
+    time::Instant,
+};
+
+/// The maximum number of mutations queued for the shadow storage, beyond which it falls out of sync.
+pub const MAX_SHADOW_QUEUE: usize = 1024;
+/// The number of blocks applied to the shadow storage between two periodic comparisons.
+pub const SHADOW_COMPARISON_INTERVAL: u32 = 100;
+/// The number of heights sampled by a comparison, in addition to the tip.
+pub const SHADOW_SAMPLE_SIZE: usize = 16;
+/// The maximum number of divergences kept for the report.
+pub const MAX_SHADOW_DIVERGENCES: usize = 64;
+/// The maximum duration to wait for the queued mutations to be applied, before an on-demand comparison.
+const SHADOW_DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A difference between the primary storage and the shadow storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The key that differs, such as `block_hash/17`, `serial_number/{serial_number}`, or `count/transactions`.
+    pub key: String,
+    /// The value in the primary storage, or `None` if it is missing.
+    pub primary: Option<String>,
+    /// The value in the shadow storage, or `None` if it is missing.
+    pub shadow: Option<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let primary = self.primary.as_deref().unwrap_or("missing");
+        let shadow = self.shadow.as_deref().unwrap_or("missing");
+        write!(f, "'{}' differs (primary: {primary}, shadow: {shadow})", self.key)
+    }
+}
+
+/// The report of the shadow storage, and of its comparisons to the primary storage.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShadowReport {
+    /// Whether the shadow storage is enabled.
+    pub is_enabled: bool,
+    /// Whether every canon mutation since the shadow storage was enabled has been applied to it.
+    pub is_synced: bool,
+    /// The latest height of the primary storage.
+    pub primary_height: u32,
+    /// The latest height of the shadow storage, if it has a block.
+    pub shadow_height: Option<u32>,
+    /// The number of mutations (or existing blocks) that are waiting to be applied to the shadow storage.
+    pub num_queued: usize,
+    /// The number of mutations applied to the shadow storage.
+    pub num_applied: u64,
+    /// The number of mutations that failed to apply to the shadow storage.
+    pub num_failures: u64,
+    /// The number of comparisons between the primary storage and the shadow storage.
+    pub num_comparisons: u64,
+    /// The height of the shadow storage at the last comparison.
+    pub last_comparison_height: Option<u32>,
+    /// The number of divergences found by the comparisons.
+    pub num_divergences: u64,
+    /// The latest divergences, up to `MAX_SHADOW_DIVERGENCES`.
+    pub divergences: Vec<Divergence>,
+    /// The last error of the shadow storage.
+    pub last_error: Option<String>,
+}
+
+/// A canon mutation, which is applied to the shadow storage.
+enum ShadowOp<N: Network> {
+    /// The block was added to the canonical chain.
+    Connect(Arc<Block<N>>),
+    /// The blocks from the given height onwards were removed from the canonical chain.
+    Truncate(u32),
+}
+
+#[derive(Default)]
+struct ShadowStats {
+    /// The number of mutations applied to the shadow storage.
+    num_applied: u64,
+    /// The number of mutations that failed to apply to the shadow storage.
+    num_failures: u64,
+    /// The number of comparisons.
+    num_comparisons: u64,
+    /// The height of the shadow storage at the last comparison.
+    last_comparison_height: Option<u32>,
+    /// The number of divergences found by the comparisons.
+    num_divergences: u64,
+    /// The latest divergences.
+    divergences: VecDeque<Divergence>,
+    /// The last error of the shadow storage.
+    last_error: Option<String>,
+}
+
+/// The state of the shadow storage, which is shared by the ledger and the shadow worker.
+struct ShadowState<N: Network> {
+    /// The shadow VM, which is locked while a mutation is applied, or while it is compared.
+    vm: Mutex<VM<N, ConsensusMemory<N>>>,
+    /// The number of mutations (or existing blocks) that are waiting to be applied.
+    num_queued: AtomicUsize,
+    /// Whether every canon mutation has been applied, which is required for the comparisons.
+    is_synced: AtomicBool,
+    /// The statistics of the shadow storage.
+    stats: Mutex<ShadowStats>,
+}
+
+impl<N: Network> ShadowState<N> {
+    /// Marks the shadow storage as out of sync, with the given error.
+    fn desync(&self, error: String) {
+        warn!("The shadow storage is out of sync, and is no longer compared - {error}");
+        self.is_synced.store(false, Ordering::SeqCst);
+        let mut stats = self.stats.lock();
+        stats.num_failures += 1;
+        stats.last_error = Some(error);
+    }
+
+    /// Records the divergences found by a comparison at the given height of the shadow storage.
+    fn record_comparison(&self, height: u32, divergences: Vec<Divergence>) {
+        for divergence in &divergences {
+            warn!(target: "critical", kind = "storage-divergence", height, "The shadow storage diverged - {divergence}");
+        }
+        let mut stats = self.stats.lock();
+        stats.num_comparisons += 1;
+        stats.last_comparison_height = Some(height);
+        stats.num_divergences += divergences.len() as u64;
+        stats.divergences.extend(divergences);
+        while stats.divergences.len() > MAX_SHADOW_DIVERGENCES {
+            stats.divergences.pop_front();
+        }
+    }
+}
+
+/// The shadow storage, which applies every canon mutation to an in-memory storage on a background thread.
+/// The worker stops once the shadow storage is dropped.
+pub(crate) struct Shadow<N: Network> {
+    /// The sender of the canon mutations to the worker.
+    sender: mpsc::SyncSender<ShadowOp<N>>,
+    /// The state of the shadow storage.
+    state: Arc<ShadowState<N>>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Enables the shadow storage, which applies every canon mutation to an in-memory storage in the background,
+    /// and periodically compares it to the primary storage. The existing blocks are applied to it first.
+    ///
+    /// The shadow storage never affects the ledger: a mutation that can not be queued or applied only marks
+    /// the shadow storage as out of sync, until it is enabled again.
+    pub fn enable_shadow(&self) -> Result<()> {
+        // Acquire the commit lock, so that every mutation is applied exactly once to the shadow storage.
+        let _commit_lock = self.commit_lock.lock();
+        // Ensure the bodies of the blocks are available, as the existing blocks are applied first.
+        ensure!(self.pruned_height() == 0, "Cannot enable the shadow storage on a pruned ledger");
+        // Retrieve the latest height, up to which the existing blocks are applied.
+        let latest_height = self.latest_height();
+
+        let mut shadow = self.shadow.write();
+        // Ensure the shadow storage is not enabled already.
+        if shadow.is_some() {
+            return Ok(());
+        }
+        // Initialize the shadow VM.
+        let vm = VM::from(ConsensusStore::<N, ConsensusMemory<N>>::open(None)?)?;
+        let state = Arc::new(ShadowState {
+            vm: Mutex::new(vm),
+            num_queued: AtomicUsize::new(latest_height as usize + 1),
+            is_synced: AtomicBool::new(true),
+            stats: Default::default(),
+        });
+
+        // Spawn the worker, which applies the existing blocks, and then the queued mutations.
+        let (sender, receiver) = mpsc::sync_channel(MAX_SHADOW_QUEUE);
+        let (primary, worker_state) = (self.vm.clone(), state.clone());
+        thread::Builder::new()
+            .name("shadow-storage".to_string())
+            .spawn(move || run_shadow(primary, worker_state, receiver, latest_height))?;
+
+        *shadow = Some(Shadow { sender, state });
+        info!("Enabled the shadow storage (applying {} existing blocks)", latest_height + 1);
+        Ok(())
+    }
+
+    /// Disables the shadow storage, and releases its memory once the worker stops.
+    pub fn disable_shadow(&self) {
+        if self.shadow.write().take().is_some() {
+            info!("Disabled the shadow storage");
+        }
+    }
+
+    /// Returns `true` if the shadow storage is enabled.
+    pub fn is_shadow_enabled(&self) -> bool {
+        self.shadow.read().is_some()
+    }
+
+    /// Returns the report of the shadow storage, without comparing it.
+    pub fn shadow_report(&self) -> ShadowReport {
+        let primary_height = self.latest_height();
+        let state = match &*self.shadow.read() {
+            Some(shadow) => shadow.state.clone(),
+            None => return ShadowReport { primary_height, ..Default::default() },
+        };
+        let shadow_height = state.vm.lock().block_store().heights().max().map(|height| *height);
+        let stats = state.stats.lock();
+        ShadowReport {
+            is_enabled: true,
+            is_synced: state.is_synced.load(Ordering::SeqCst),
+            primary_height,
+            shadow_height,
+            num_queued: state.num_queued.load(Ordering::SeqCst),
+            num_applied: stats.num_applied,
+            num_failures: stats.num_failures,
+            num_comparisons: stats.num_comparisons,
+            last_comparison_height: stats.last_comparison_height,
+            num_divergences: stats.num_divergences,
+            divergences: stats.divergences.iter().cloned().collect(),
+            last_error: stats.last_error.clone(),
+        }
+    }
+
+    /// Compares the shadow storage to the primary storage, once the queued mutations are applied,
+    /// and returns the report of the shadow storage.
+    pub fn compare_shadow(&self) -> Result<ShadowReport> {
+        let state = match &*self.shadow.read() {
+            Some(shadow) => shadow.state.clone(),
+            None => bail!("The shadow storage is not enabled"),
+        };
+        // Wait for the queued mutations to be applied.
+        let timer = Instant::now();
+        while state.num_queued.load(Ordering::SeqCst) > 0 {
+            ensure!(timer.elapsed() < SHADOW_DRAIN_TIMEOUT, "Timed out waiting for the shadow storage to catch up");
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Ensure the shadow storage is in sync, as the comparison would otherwise report spurious divergences.
+        ensure!(state.is_synced.load(Ordering::SeqCst), "The shadow storage is out of sync (enable it again)");
+        compare_shadow(&self.vm, &state)?;
+        Ok(self.shadow_report())
+    }
+
+    /// Queues the given mutation for the shadow storage, if it is enabled, without waiting on the worker.
+    fn push_shadow(&self, op: ShadowOp<N>) {
+        if let Some(shadow) = &*self.shadow.read() {
+            shadow.state.num_queued.fetch_add(1, Ordering::SeqCst);
+            if let Err(error) = shadow.sender.try_send(op) {
+                shadow.state.num_queued.fetch_sub(1, Ordering::SeqCst);
+                if let mpsc::TrySendError::Full(_) = error {
+                    if shadow.state.is_synced.load(Ordering::SeqCst) {
+                        shadow.state.desync(format!("more than {MAX_SHADOW_QUEUE} mutations are queued"));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues the given block for the shadow storage, as it was added to the canonical chain.
+    pub(crate) fn push_shadow_block(&self, block: Arc<Block<N>>) {
+        self.push_shadow(ShadowOp::Connect(block))
+    }
+
+    /// Queues the removal of the blocks from the given height onwards for the shadow storage.
+    pub(crate) fn push_shadow_truncation(&self, height: u32) {
+        self.push_shadow(ShadowOp::Truncate(height))
+    }
+
+    /// Runs the given function on the shadow VM, if the shadow storage is enabled.
+    #[cfg(test)]
+    pub(crate) fn with_shadow_vm<T>(&self, f: impl FnOnce(&VM<N, ConsensusMemory<N>>) -> T) -> Option<T> {
+        self.shadow.read().as_ref().map(|shadow| f(&shadow.state.vm.lock()))
+    }
+}
+
+/// Applies the existing blocks up to the given height to the shadow storage, and then the queued mutations,
+/// until the shadow storage is disabled.
+fn run_shadow<N: Network, C: ConsensusStorage<N>>(
+    primary: VM<N, C>,
+    state: Arc<ShadowState<N>>,
+    receiver: mpsc::Receiver<ShadowOp<N>>,
+    latest_height: u32,
+) {
+    // Apply the existing blocks from the primary storage.
+    for height in 0..=latest_height {
+        if state.is_synced.load(Ordering::SeqCst) {
+            let result = match primary.block_store().get_block_hash(height) {
+                Ok(Some(hash)) => match primary.block_store().get_block(&hash) {
+                    Ok(Some(block)) => state.vm.lock().add_next_block(&block),
+                    Ok(None) => Err(anyhow!("Block {height} ('{hash}') does not exist in storage")),
+                    Err(error) => Err(error),
+                },
+                Ok(None) => Err(anyhow!("Block {height} does not exist in storage")),
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(()) => state.stats.lock().num_applied += 1,
+                Err(error) => state.desync(format!("Failed to apply the existing block {height} - {error}")),
+            }
+        }
+        state.num_queued.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // Apply the queued mutations, until the shadow storage is dropped.
+    while let Ok(op) = receiver.recv() {
+        if state.is_synced.load(Ordering::SeqCst) {
+            match apply_shadow_op(&state.vm.lock(), op) {
+                Ok(()) => state.stats.lock().num_applied += 1,
+                Err(error) => state.desync(format!("Failed to apply a mutation - {error}")),
+            }
+        }
+        // Compare the shadow storage periodically, once it caught up with the primary storage.
+        if state.num_queued.fetch_sub(1, Ordering::SeqCst) == 1 && state.is_synced.load(Ordering::SeqCst) {
+            let shadow_height = state.vm.lock().block_store().heights().max().map(|height| *height).unwrap_or(0);
+            let last_comparison_height = state.stats.lock().last_comparison_height.unwrap_or(0);
+            if shadow_height >= last_comparison_height.saturating_add(SHADOW_COMPARISON_INTERVAL) {
+                if let Err(error) = compare_shadow(&primary, &state) {
+                    warn!("Failed to compare the shadow storage - {error}");
+                }
+            }
+        }
+    }
+    debug!("Stopped the shadow storage");
+}
+
+/// Applies the given mutation to the shadow VM.
+fn apply_shadow_op<N: Network>(vm: &VM<N, ConsensusMemory<N>>, op: ShadowOp<N>) -> Result<()> {
+    match op {
+        ShadowOp::Connect(block) => vm.add_next_block(&block),
+        ShadowOp::Truncate(height) => match vm.block_store().heights().max().map(|height| *height) {
+            Some(latest_height) if latest_height >= height => {
+                vm.block_store().remove_last_n(latest_height - height + 1)
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Compares the shadow storage to the primary storage, at the latest height of the shadow storage,
+/// and records the divergences.
+///
+/// The tip and a sample of the blocks are looked up in both storages, and the index entry counts
+/// are compared if the primary storage is at the same height.
+fn compare_shadow<N: Network, C: ConsensusStorage<N>>(primary: &VM<N, C>, state: &ShadowState<N>) -> Result<()> {
+    let vm = state.vm.lock();
+    let (primary, shadow) = (primary.block_store(), vm.block_store());
+    // Retrieve the latest height of the shadow storage.
+    let shadow_height = match shadow.heights().max() {
+        Some(height) => *height,
+        None => bail!("The shadow storage is empty"),
+    };
+
+    let mut divergences = Vec::new();
+    // Compare the index entry counts, if the primary storage is at the same height.
+    if primary.heights().max().map(|height| *height) == Some(shadow_height) {
+        let primary_counts = count_entries(primary);
+        let shadow_counts = count_entries(shadow);
+        // Ensure the primary storage did not advance while it was counted.
+        if primary.heights().max().map(|height| *height) == Some(shadow_height) {
+            for ((name, primary_count), (_, shadow_count)) in primary_counts.into_iter().zip(shadow_counts) {
+                compare_entry(&mut divergences, format!("count/{name}"), Some(primary_count), Some(shadow_count));
+            }
+        }
+    }
+    // Compare the tip, and a sample of the blocks.
+    let mut heights = (0..shadow_height).choose_multiple(&mut OsRng, SHADOW_SAMPLE_SIZE);
+    heights.push(shadow_height);
+    for height in heights {
+        compare_block(&mut divergences, primary, shadow, height)?;
+    }
+
+    state.record_comparison(shadow_height, divergences);
+    Ok(())
+}
+
+/// Returns the number of entries in the indexes of the given block store.
+fn count_entries<N: Network, B: BlockStorage<N>>(store: &BlockStore<N, B>) -> [(&'static str, usize); 6] {
+    let transitions = store.transition_store();
+    [
+        ("blocks", store.heights().count()),
+        ("state_roots", store.state_roots().count()),
+        ("transactions", store.transaction_store().transaction_ids().count()),
+        ("transitions", transitions.transition_ids().count()),
+        ("serial_numbers", transitions.serial_numbers().count()),
+        ("commitments", transitions.commitments().count()),
+    ]
+}
+
+/// Compares the block at the given height, and the lookups of its transactions, in both block stores.
+fn compare_block<N: Network, P: BlockStorage<N>, S: BlockStorage<N>>(
+    divergences: &mut Vec<Divergence>,
+    primary: &BlockStore<N, P>,
+    shadow: &BlockStore<N, S>,
+    height: u32,
+) -> Result<()> {
+    // Compare the block hash.
+    let hash = primary.get_block_hash(height)?;
+    if !compare_entry(divergences, format!("block_hash/{height}"), hash, shadow.get_block_hash(height).ok().flatten()) {
+        return Ok(());
+    }
+    let hash = match hash {
+        Some(hash) => hash,
+        None => return Ok(()),
+    };
+    // Compare the header.
+    let primary_root = primary.get_block_header(&hash)?.map(|header| header.to_root()).transpose()?;
+    let shadow_root = shadow.get_block_header(&hash).ok().flatten().and_then(|header| header.to_root().ok());
+    compare_entry(divergences, format!("header/{height}"), primary_root, shadow_root);
+
+    // Compare the lookups of the transactions.
+    let transactions = match primary.get_block_transactions(&hash)? {
+        Some(transactions) => transactions,
+        None => bail!("Block {height} ('{hash}') is missing its transactions in storage"),
+    };
+    for transaction in transactions.values() {
+        let id = transaction.id();
+        let contains = shadow.transaction_store().contains_transaction_id(&id).ok();
+        compare_entry(divergences, format!("transaction/{id}"), Some(true), contains);
+        let block_hash = shadow.find_block_hash(&id).ok().flatten();
+        compare_entry(divergences, format!("transaction_block/{id}"), Some(hash), block_hash);
+        for serial_number in transaction.serial_numbers() {
+            let contains = shadow.transition_store().contains_serial_number(serial_number).ok();
+            compare_entry(divergences, format!("serial_number/{serial_number}"), Some(true), contains);
+        }
+        for commitment in transaction.commitments() {
+            let contains = shadow.transition_store().contains_commitment(commitment).ok();
+            compare_entry(divergences, format!("commitment/{commitment}"), Some(true), contains);
+        }
+    }
+    Ok(())
+}
+
+/// Records a divergence if the given values of the key differ, and returns `true` if they are equal.
+fn compare_entry<T: PartialEq + ToString>(
+    divergences: &mut Vec<Divergence>,
+    key: String,
+    primary: Option<T>,
+    shadow: Option<T>,
+) -> bool {
+    match primary == shadow {
+        true => true,
+        false => {
+            let (primary, shadow) = (primary.map(|value| value.to_string()), shadow.map(|value| value.to_string()));
+            divergences.push(Divergence { key, primary, shadow });
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_entry() {
+        let mut divergences = vec![];
+        assert!(compare_entry(&mut divergences, "count/blocks".to_string(), Some(3), Some(3)));
+        assert!(!compare_entry(&mut divergences, "count/transactions".to_string(), Some(3), Some(2)));
+        assert!(!compare_entry(&mut divergences, "transaction/at1".to_string(), Some(true), None));
+        assert_eq!(divergences, vec![
+            Divergence {
+                key: "count/transactions".to_string(),
+                primary: Some("3".to_string()),
+                shadow: Some("2".to_string())
+            },
+            Divergence { key: "transaction/at1".to_string(), primary: Some("true".to_string()), shadow: None },
+        ]);
+        assert_eq!(divergences[1].to_string(), "'transaction/at1' differs (primary: true, shadow: missing)");
+    }
+}
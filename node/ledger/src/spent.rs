@@ -0,0 +1,322 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use core::{
+    hash::{BuildHasher, Hash, Hasher},
+    marker::PhantomData,
+};
+use std::collections::hash_map::RandomState;
+
+/// The number of fingerprints in each bucket of the serial number filter.
+const BUCKET_SIZE: usize = 4;
+/// The maximum number of fingerprints that are relocated to insert a serial number, before the filter is full.
+const MAX_RELOCATIONS: usize = 500;
+/// The fraction of the slots that are expected to be occupied at the capacity of the filter.
+const LOAD_FACTOR: f64 = 0.95;
+/// The minimum capacity of the serial number filter of the ledger.
+const MIN_FILTER_CAPACITY: usize = 1 << 16;
+
+/// A cuckoo filter of the spent serial numbers, which answers whether a serial number is definitely unspent,
+/// or maybe spent. Each serial number is stored as a 16-bit fingerprint, in one of its two candidate buckets,
+/// so the filter uses about 2 bytes per serial number, with a false-positive rate of about 0.012%.
+///
+/// A serial number that was inserted is always reported as maybe spent (there are no false negatives),
+/// as long as only the inserted serial numbers are removed. Once the filter is full, an insertion fails,
+/// and every serial number is reported as maybe spent, until the filter is rebuilt with a larger capacity.
+#[derive(Clone, Debug)]
+pub struct SerialNumberFilter<N: Network> {
+    /// The buckets of fingerprints, whose number is a power of two, where `0` is an empty slot.
+    buckets: Vec<[u16; BUCKET_SIZE]>,
+    /// The fingerprint that could not be placed in a bucket, with its bucket index, if the filter is full.
+    victim: Option<(usize, u16)>,
+    /// The number of fingerprints in the filter.
+    num_items: usize,
+    /// The keyed hasher of the serial numbers, so that the fingerprints cannot be predicted by a peer.
+    hasher: RandomState,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> Default for SerialNumberFilter<N> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl<N: Network> SerialNumberFilter<N> {
+    /// Initializes an empty filter that holds at least the given number of serial numbers.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let num_buckets = ((capacity as f64 / LOAD_FACTOR / BUCKET_SIZE as f64).ceil() as usize).next_power_of_two();
+        Self {
+            buckets: vec![[0; BUCKET_SIZE]; num_buckets],
+            victim: None,
+            num_items: 0,
+            hasher: RandomState::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of serial numbers in the filter.
+    pub const fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the filter contains no serial numbers.
+    pub const fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Returns the number of serial numbers the filter holds at its load factor.
+    pub fn capacity(&self) -> usize {
+        (self.buckets.len() as f64 * BUCKET_SIZE as f64 * LOAD_FACTOR) as usize
+    }
+
+    /// Returns the size of the filter in memory, in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.buckets.len() * core::mem::size_of::<[u16; BUCKET_SIZE]>()
+    }
+
+    /// Returns `true` if the filter is full, in which case every serial number is reported as maybe spent.
+    pub const fn is_full(&self) -> bool {
+        self.victim.is_some()
+    }
+
+    /// Returns `false` if the given serial number is definitely unspent, and `true` if it may be spent.
+    pub fn contains(&self, serial_number: &Field<N>) -> bool {
+        // If the filter is full, a fingerprint may be missing, so every serial number may be spent.
+        if self.is_full() {
+            return true;
+        }
+        let (index, fingerprint) = self.locate(serial_number);
+        self.buckets[index].contains(&fingerprint)
+            || self.buckets[self.alternate(index, fingerprint)].contains(&fingerprint)
+    }
+
+    /// Inserts the given serial number, and returns `false` if the filter is full, in which case it must be rebuilt.
+    pub fn insert(&mut self, serial_number: &Field<N>) -> bool {
+        // Ensure the filter is not full.
+        if self.is_full() {
+            return false;
+        }
+        let (index, fingerprint) = self.locate(serial_number);
+        self.num_items += 1;
+        // Place the fingerprint in one of its buckets, relocating the fingerprints of a bucket if both are full.
+        if let Some(victim) = self.place(index, fingerprint) {
+            self.victim = Some(victim);
+            return false;
+        }
+        true
+    }
+
+    /// Removes the given serial number, which must have been inserted, and returns `true` if it was found.
+    pub fn remove(&mut self, serial_number: &Field<N>) -> bool {
+        let (index, fingerprint) = self.locate(serial_number);
+        let alternate = self.alternate(index, fingerprint);
+        // Remove the fingerprint from one of its buckets, or from the victim slot.
+        let is_removed = [index, alternate].into_iter().any(|index| {
+            match self.buckets[index].iter_mut().find(|slot| **slot == fingerprint) {
+                Some(slot) => {
+                    *slot = 0;
+                    true
+                }
+                None => false,
+            }
+        });
+        let is_removed = match is_removed {
+            true => true,
+            false => match self.victim {
+                Some((victim_index, victim)) if victim == fingerprint && [index, alternate].contains(&victim_index) => {
+                    self.victim = None;
+                    true
+                }
+                _ => false,
+            },
+        };
+        if is_removed {
+            self.num_items -= 1;
+            // Place the victim again, as a slot was freed.
+            if let Some((victim_index, victim)) = self.victim.take() {
+                self.victim = self.place(victim_index, victim);
+            }
+        }
+        is_removed
+    }
+
+    /// Returns the bucket index and fingerprint of the given serial number.
+    fn locate(&self, serial_number: &Field<N>) -> (usize, u16) {
+        let mut hasher = self.hasher.build_hasher();
+        serial_number.hash(&mut hasher);
+        let hash = hasher.finish();
+        // Use the upper bits for the fingerprint, which is never `0`, as it marks an empty slot.
+        let fingerprint = ((hash >> 48) as u16).max(1);
+        ((hash as usize) & (self.buckets.len() - 1), fingerprint)
+    }
+
+    /// Returns the other bucket index of the given fingerprint, which maps back to the given bucket index.
+    fn alternate(&self, index: usize, fingerprint: u16) -> usize {
+        (index ^ (fingerprint as usize).wrapping_mul(0x5bd1_e995)) & (self.buckets.len() - 1)
+    }
+
+    /// Places the given fingerprint in the given bucket or its alternate bucket, relocating the fingerprints
+    /// of the buckets if both are full. Returns the fingerprint that could not be placed, if any.
+    fn place(&mut self, index: usize, fingerprint: u16) -> Option<(usize, u16)> {
+        let alternate = self.alternate(index, fingerprint);
+        for index in [index, alternate] {
+            if let Some(slot) = self.buckets[index].iter_mut().find(|slot| **slot == 0) {
+                *slot = fingerprint;
+                return None;
+            }
+        }
+        // Relocate the fingerprints, starting from a random bucket of the two.
+        let (mut index, mut fingerprint) = match rand::random::<bool>() {
+            true => (index, fingerprint),
+            false => (alternate, fingerprint),
+        };
+        for _ in 0..MAX_RELOCATIONS {
+            // Swap the fingerprint with a random fingerprint of the bucket, and move the latter to its alternate bucket.
+            let slot = rand::random::<usize>() % BUCKET_SIZE;
+            core::mem::swap(&mut fingerprint, &mut self.buckets[index][slot]);
+            index = self.alternate(index, fingerprint);
+            if let Some(slot) = self.buckets[index].iter_mut().find(|slot| **slot == 0) {
+                *slot = fingerprint;
+                return None;
+            }
+        }
+        Some((index, fingerprint))
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Rebuilds the filter of the spent serial numbers from the serial number index in storage,
+    /// with room for twice the number of serial numbers.
+    pub(crate) fn rebuild_spent_filter(&self) {
+        let num_serial_numbers = self.vm.transition_store().serial_numbers().count();
+        let mut filter = SerialNumberFilter::with_capacity((num_serial_numbers * 2).max(MIN_FILTER_CAPACITY));
+        for serial_number in self.vm.transition_store().serial_numbers() {
+            filter.insert(&serial_number);
+        }
+        debug!("Rebuilt the filter of {} spent serial numbers ({} bytes)", filter.len(), filter.size_in_bytes());
+        *self.spent_filter.write() = filter;
+    }
+
+    /// Inserts the serial numbers of the given block, which was just added, in the filter of the spent serial numbers.
+    pub(crate) fn insert_spent_serial_numbers(&self, block: &Block<N>) {
+        let mut filter = self.spent_filter.write();
+        let is_full = block.serial_numbers().any(|serial_number| !filter.insert(serial_number));
+        drop(filter);
+        // If the filter is full, rebuild it with a larger capacity, which includes the block.
+        if is_full {
+            self.rebuild_spent_filter();
+        }
+    }
+
+    /// Removes the serial numbers of the given blocks, which were just removed, from the filter of the spent
+    /// serial numbers. If a block could not be retrieved, the filter is rebuilt instead.
+    pub(crate) fn remove_spent_serial_numbers(&self, removed: &[Result<Block<N>>]) {
+        match removed.iter().all(|block| block.is_ok()) {
+            true => {
+                let mut filter = self.spent_filter.write();
+                for serial_number in removed.iter().flatten().flat_map(|block| block.serial_numbers()) {
+                    filter.remove(serial_number);
+                }
+            }
+            false => self.rebuild_spent_filter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    type CurrentNetwork = Testnet3;
+
+    /// Samples the given number of random serial numbers.
+    fn sample_serial_numbers(num_serial_numbers: usize, rng: &mut TestRng) -> Vec<Field<CurrentNetwork>> {
+        (0..num_serial_numbers).map(|_| Field::rand(rng)).collect()
+    }
+
+    #[test]
+    fn test_no_false_negatives() {
+        let rng = &mut TestRng::default();
+        let serial_numbers = sample_serial_numbers(10_000, rng);
+
+        // Ensure every inserted serial number is reported as maybe spent, up to the capacity.
+        let mut filter = SerialNumberFilter::<CurrentNetwork>::with_capacity(serial_numbers.len());
+        assert!(filter.capacity() >= serial_numbers.len());
+        for serial_number in &serial_numbers {
+            assert!(filter.insert(serial_number));
+        }
+        assert_eq!(filter.len(), serial_numbers.len());
+        assert!(serial_numbers.iter().all(|serial_number| filter.contains(serial_number)));
+
+        // Ensure the false-positive rate is within the bound of the fingerprints.
+        let num_false_positives =
+            sample_serial_numbers(100_000, rng).iter().filter(|serial_number| filter.contains(serial_number)).count();
+        assert!(num_false_positives < 100, "{num_false_positives} false positives");
+    }
+
+    #[test]
+    fn test_remove() {
+        let rng = &mut TestRng::default();
+        let serial_numbers = sample_serial_numbers(1_000, rng);
+
+        let mut filter = SerialNumberFilter::<CurrentNetwork>::with_capacity(serial_numbers.len());
+        for serial_number in &serial_numbers {
+            assert!(filter.insert(serial_number));
+        }
+        // Remove half of the serial numbers, as a reorg does.
+        let (removed, kept) = serial_numbers.split_at(serial_numbers.len() / 2);
+        for serial_number in removed {
+            assert!(filter.remove(serial_number));
+        }
+        assert_eq!(filter.len(), kept.len());
+        // Ensure the kept serial numbers are still reported as maybe spent.
+        assert!(kept.iter().all(|serial_number| filter.contains(serial_number)));
+        // Ensure (almost all of) the removed serial numbers are reported as unspent.
+        assert!(removed.iter().filter(|serial_number| filter.contains(serial_number)).count() < 5);
+    }
+
+    #[test]
+    fn test_full_filter() {
+        let rng = &mut TestRng::default();
+
+        // Fill a small filter beyond its slots.
+        let mut filter = SerialNumberFilter::<CurrentNetwork>::with_capacity(64);
+        let num_slots = filter.buckets.len() * BUCKET_SIZE;
+        let serial_numbers = sample_serial_numbers(num_slots + 1, rng);
+        let num_inserted = serial_numbers.iter().take_while(|serial_number| filter.insert(serial_number)).count();
+        assert!(num_inserted < serial_numbers.len());
+        assert!(filter.is_full());
+
+        // Ensure every serial number is reported as maybe spent, including the serial numbers that were not inserted.
+        assert!(serial_numbers.iter().all(|serial_number| filter.contains(serial_number)));
+        assert!(sample_serial_numbers(100, rng).iter().all(|serial_number| filter.contains(serial_number)));
+        // Ensure no further serial number is inserted.
+        assert!(!filter.insert(&Field::rand(rng)));
+
+        // Ensure the filter is no longer full once serial numbers are removed, and the victim is placed again.
+        let (removed, kept) = serial_numbers[..=num_inserted].split_at(num_slots / 2);
+        for serial_number in removed {
+            assert!(filter.remove(serial_number));
+        }
+        assert!(!filter.is_full());
+        assert!(kept.iter().all(|serial_number| filter.contains(serial_number)));
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use snarkvm::synthesizer::block::{Input, Output, Transition};
+
+use core::fmt;
+
+/// A violation of the structure of a transaction, which is found without the ledger or a proof verification.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StructureViolation {
+    /// The transition outputs a different number of encrypted records than record commitments.
+    RecordCountMismatch { num_records: usize, num_commitments: usize },
+    /// The transition carries a different number of input values than non-record input hashes.
+    InputValueCountMismatch { num_values: usize, num_inputs: usize },
+    /// The transition carries a different number of output values than non-record output hashes.
+    OutputValueCountMismatch { num_values: usize, num_outputs: usize },
+    /// The execution or fee spends records, without an inclusion proof for them.
+    MissingInclusionProof { num_serial_numbers: usize },
+}
+
+impl fmt::Display for StructureViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RecordCountMismatch { num_records, num_commitments } => {
+                write!(f, "a transition has {num_records} encrypted records for {num_commitments} record commitments")
+            }
+            Self::InputValueCountMismatch { num_values, num_inputs } => {
+                write!(f, "a transition has {num_values} input values for {num_inputs} input hashes")
+            }
+            Self::OutputValueCountMismatch { num_values, num_outputs } => {
+                write!(f, "a transition has {num_values} output values for {num_outputs} output hashes")
+            }
+            Self::MissingInclusionProof { num_serial_numbers } => {
+                write!(f, "{num_serial_numbers} serial numbers are spent without an inclusion proof")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StructureViolation {}
+
+/// A check of the structure of a transaction, which is cheap enough to run before a transaction is verified.
+///
+/// The transaction ID is already recomputed from the transitions when a transaction is decoded, and an empty
+/// execution cannot be decoded, so this only checks the parts that the ID does not commit to.
+pub trait TransactionStructure {
+    /// Returns an error naming the counts involved, if the transaction is malformed.
+    fn validate_structure(&self) -> Result<(), StructureViolation>;
+}
+
+impl<N: Network> TransactionStructure for Transaction<N> {
+    fn validate_structure(&self) -> Result<(), StructureViolation> {
+        // Ensure each transition is well-formed.
+        for transition in self.transitions() {
+            validate_transition(transition)?;
+        }
+        // Ensure the records spent by the execution and by the fee are proven to exist.
+        match self {
+            Transaction::Deploy(_, _, _, fee) => {
+                validate_inclusion([fee.transition()].into_iter(), fee.inclusion_proof().is_some())
+            }
+            Transaction::Execute(_, execution, fee) => {
+                validate_inclusion(execution.transitions(), execution.inclusion_proof().is_some())?;
+                match fee {
+                    Some(fee) => validate_inclusion([fee.transition()].into_iter(), fee.inclusion_proof().is_some()),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Ensures the given transition carries the encrypted record of each record commitment,
+/// and the value of each non-record input and output hash.
+fn validate_transition<N: Network>(transition: &Transition<N>) -> Result<(), StructureViolation> {
+    // Ensure each record commitment has its encrypted record.
+    let num_commitments = transition.commitments().count();
+    let num_records = transition.records().count();
+    if num_records != num_commitments {
+        return Err(StructureViolation::RecordCountMismatch { num_records, num_commitments });
+    }
+
+    // Ensure each non-record input hash has its value.
+    let (num_inputs, num_values) =
+        transition.inputs().iter().fold((0, 0), |(num_inputs, num_values), input| match input {
+            Input::Constant(_, value) | Input::Public(_, value) => {
+                (num_inputs + 1, num_values + value.is_some() as usize)
+            }
+            Input::Private(_, value) => (num_inputs + 1, num_values + value.is_some() as usize),
+            Input::Record(..) | Input::ExternalRecord(..) => (num_inputs, num_values),
+        });
+    if num_values != num_inputs {
+        return Err(StructureViolation::InputValueCountMismatch { num_values, num_inputs });
+    }
+
+    // Ensure each non-record output hash has its value.
+    let (num_outputs, num_values) =
+        transition.outputs().iter().fold((0, 0), |(num_outputs, num_values), output| match output {
+            Output::Constant(_, value) | Output::Public(_, value) => {
+                (num_outputs + 1, num_values + value.is_some() as usize)
+            }
+            Output::Private(_, value) => (num_outputs + 1, num_values + value.is_some() as usize),
+            Output::Record(..) | Output::ExternalRecord(..) => (num_outputs, num_values),
+        });
+    if num_values != num_outputs {
+        return Err(StructureViolation::OutputValueCountMismatch { num_values, num_outputs });
+    }
+    Ok(())
+}
+
+/// Ensures the given transitions, which share an inclusion proof, have one if they spend any record.
+fn validate_inclusion<'a, N: Network>(
+    transitions: impl Iterator<Item = &'a Transition<N>>,
+    has_inclusion_proof: bool,
+) -> Result<(), StructureViolation> {
+    let num_serial_numbers = transitions.map(|transition| transition.serial_numbers().count()).sum();
+    match num_serial_numbers > 0 && !has_inclusion_proof {
+        true => Err(StructureViolation::MissingInclusionProof { num_serial_numbers }),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+/// The filter of a subscription to the transactions accepted into the memory pool.
+/// The conditions that are set must all hold, and a filter without conditions matches every transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionFilter<N: Network> {
+    /// The minimum value balance of the transaction, i.e. its fee, in microcredits.
+    pub min_value_balance: Option<u64>,
+    /// The circuit (program ID and function name) of one of the transitions of the transaction.
+    pub circuit: Option<(ProgramID<N>, Identifier<N>)>,
+    /// The registered account of which the transaction spends or creates a record.
+    pub account: Option<Address<N>>,
+}
+
+impl<N: Network> Default for TransactionFilter<N> {
+    /// Initializes a filter without conditions.
+    fn default() -> Self {
+        Self { min_value_balance: None, circuit: None, account: None }
+    }
+}
+
+impl<N: Network> TransactionFilter<N> {
+    /// Returns `true` if the given transaction satisfies the value and circuit conditions of the filter.
+    /// The account condition is evaluated against the history of the account, by the ledger.
+    pub fn matches(&self, transaction: &Transaction<N>) -> bool {
+        if let Some(min_value_balance) = self.min_value_balance {
+            if transaction.fee().map_or(true, |fee| *fee < min_value_balance) {
+                return false;
+            }
+        }
+        if let Some((program_id, function_name)) = &self.circuit {
+            if !transaction
+                .transitions()
+                .any(|transition| transition.program_id() == program_id && transition.function_name() == function_name)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>> Ledger<N, C> {
+    /// Subscribes to the transactions accepted into the memory pool that match the given filter.
+    ///
+    /// The account of the filter must be registered, as its records are matched against its history.
+    /// If the account is unregistered later on, the subscription no longer matches any transaction.
+    pub fn subscribe_transactions(&self, filter: TransactionFilter<N>) -> Result<Subscription<N>> {
+        if let Some(address) = &filter.account {
+            ensure!(self.account_histories.read().contains_key(address), "The account '{address}' is not registered");
+        }
+        let account_histories = self.account_histories.clone();
+        let predicate = move |event: &Event<N>| match event {
+            Event::TransactionAccepted { transaction } => {
+                let is_affected = |address: &Address<N>| {
+                    account_histories.read().get(address).map_or(false, |history| history.is_affected_by(transaction))
+                };
+                filter.matches(transaction) && filter.account.as_ref().map_or(true, is_affected)
+            }
+            _ => false,
+        };
+        Ok(self.events.subscribe_filtered(&[Topic::TransactionAccepted], Arc::new(predicate)))
+    }
+}
@@ -14,11 +14,50 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{tests::test_helpers::CurrentLedger, Ledger};
+use crate::{
+    tests::test_helpers::CurrentLedger,
+    verify_deposit_proof,
+    verify_ledger_membership_proof,
+    BlockPruned,
+    ChainTip,
+    ChainTipStatus,
+    ConsistencyCheck,
+    DepositProof,
+    Event,
+    InconsistencyKind,
+    Ledger,
+    LedgerMembershipProof,
+    RecordDirection,
+    SideBranches,
+    StructureViolation,
+    TransactionFilter,
+    TransactionStructure,
+    MAX_SIDE_BLOCKS,
+    MAX_SIDE_BLOCKS_PER_HEIGHT,
+};
 use snarkvm::{
-    console::network::{prelude::*, Testnet3},
+    console::{
+        account::{Address, PrivateKey, ViewKey},
+        network::{prelude::*, Testnet3},
+        program::{BlockPath, Identifier, ProgramID, StatePath, Value},
+        types::{Field, U64},
+    },
     prelude::TestRng,
-    synthesizer::{block::Block, store::ConsensusStore, vm::VM, ConsensusMemory},
+    synthesizer::{
+        block::{Block, Header, Input, Metadata, Output, Transaction, Transactions, Transition},
+        store::ConsensusStore,
+        vm::VM,
+        ConsensusMemory,
+        Execution,
+    },
+};
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 type CurrentNetwork = Testnet3;
@@ -105,3 +144,1031 @@ fn test_state_path() {
 
     let _state_path = ledger.get_state_path_for_commitment(commitment).unwrap();
 }
+
+/// Returns the next block on top of the ledger with the given transactions, total supply, and cumulative proof target.
+fn sample_next_block(
+    ledger: &CurrentLedger,
+    private_key: &PrivateKey<CurrentNetwork>,
+    transactions: &[Transaction<CurrentNetwork>],
+    total_supply_in_microcredits: u64,
+    cumulative_proof_target: u128,
+    rng: &mut TestRng,
+) -> Block<CurrentNetwork> {
+    let latest_block = ledger.latest_block();
+    let transactions = Transactions::from(transactions);
+    let metadata = Metadata::new(
+        CurrentNetwork::ID,
+        latest_block.round() + 1,
+        latest_block.height() + 1,
+        total_supply_in_microcredits,
+        cumulative_proof_target,
+        latest_block.coinbase_target(),
+        latest_block.proof_target(),
+        latest_block.last_coinbase_target(),
+        latest_block.last_coinbase_timestamp(),
+        latest_block.timestamp() + 1,
+    )
+    .unwrap();
+    let header = Header::from(
+        *ledger.latest_state_root(),
+        transactions.to_root().unwrap(),
+        Field::zero(),
+        Field::zero(),
+        metadata,
+    )
+    .unwrap();
+    Block::new(private_key, latest_block.hash(), header, transactions, None, rng).unwrap()
+}
+
+/// Returns a ledger with a transfer block on top of the genesis block, along with the private key and the transfer.
+fn sample_ledger_with_transfer(
+    rng: &mut TestRng,
+) -> (CurrentLedger, PrivateKey<CurrentNetwork>, Transaction<CurrentNetwork>) {
+    // Sample the genesis private key.
+    let private_key = crate::tests::test_helpers::sample_genesis_private_key(rng);
+    // Initialize the store.
+    let store = ConsensusStore::<_, ConsensusMemory<_>>::open(None).unwrap();
+    // Create a genesis block.
+    let genesis = Block::genesis(&VM::from(store).unwrap(), &private_key, rng).unwrap();
+    // Initialize the ledger with the genesis block.
+    let ledger = CurrentLedger::load(genesis, None).unwrap();
+
+    // Create a transfer, and add it in the next block.
+    let address = Address::try_from(&private_key).unwrap();
+    let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+    let block = sample_next_block(
+        &ledger,
+        &private_key,
+        core::slice::from_ref(&transfer),
+        total_supply,
+        cumulative_proof_target,
+        rng,
+    );
+    ledger.add_next_block(&block).unwrap();
+    assert_eq!(ledger.latest_height(), 1);
+
+    (ledger, private_key, transfer)
+}
+
+/// Ensures the check finds the given class of inconsistency at block 1, and that the repair truncates the ledger to the genesis block.
+fn assert_inconsistency_is_repaired(ledger: &CurrentLedger, kind: InconsistencyKind) {
+    let block_hash = ledger.get_hash(1).unwrap();
+
+    // Ensure the inconsistency is detected, and the bounded check covers it.
+    let inconsistency = ledger.check_consistency(ConsistencyCheck::Latest(1)).unwrap().unwrap();
+    assert_eq!(inconsistency.height, 1);
+    assert_eq!(inconsistency.kind, kind);
+    assert_eq!(ledger.check_consistency(ConsistencyCheck::Deep).unwrap(), Some(inconsistency.clone()));
+
+    // Ensure the repair truncates the ledger to the genesis block.
+    assert_eq!(ledger.repair_consistency(ConsistencyCheck::Deep).unwrap(), Some(inconsistency));
+    assert_eq!(ledger.latest_height(), 0);
+    assert_eq!(ledger.latest_hash(), ledger.get_hash(0).unwrap());
+    assert!(!ledger.contains_block_hash(&block_hash).unwrap());
+    assert!(ledger.check_consistency(ConsistencyCheck::Deep).unwrap().is_none());
+}
+
+#[test]
+fn test_consistency_check() {
+    let rng = &mut TestRng::default();
+
+    // Ensure a consistent ledger passes the check.
+    let (ledger, _, _) = sample_ledger_with_transfer(rng);
+    assert!(ledger.check_consistency(ConsistencyCheck::default()).unwrap().is_none());
+    assert!(ledger.check_consistency(ConsistencyCheck::Deep).unwrap().is_none());
+    assert!(ledger.repair_consistency(ConsistencyCheck::Deep).unwrap().is_none());
+    assert_eq!(ledger.latest_height(), 1);
+
+    // Ensure the check can be skipped.
+    let transition_ids = ledger.latest_transactions().transition_ids().copied().collect::<Vec<_>>();
+    for transition_id in transition_ids {
+        ledger.vm().transition_store().remove(&transition_id).unwrap();
+    }
+    assert!(ledger.check_consistency(ConsistencyCheck::Skip).unwrap().is_none());
+}
+
+#[test]
+fn test_consistency_check_missing_transaction() {
+    let rng = &mut TestRng::default();
+
+    // Remove the contents of the transfer, as if it was partially written.
+    let (ledger, _, transfer) = sample_ledger_with_transfer(rng);
+    for transition_id in transfer.transition_ids() {
+        ledger.vm().transition_store().remove(transition_id).unwrap();
+    }
+    assert_inconsistency_is_repaired(&ledger, InconsistencyKind::MissingTransaction);
+}
+
+#[test]
+fn test_consistency_check_missing_index() {
+    let rng = &mut TestRng::default();
+
+    // Store a transaction with the same execution as the transfer, which re-indexes its transitions to the new transaction.
+    let (ledger, _, transfer) = sample_ledger_with_transfer(rng);
+    let execution = match &transfer {
+        Transaction::Execute(_, execution, _) => execution.clone(),
+        _ => unreachable!("The transfer is an execution"),
+    };
+    let duplicate = Transaction::from_execution(execution, None).unwrap();
+    assert_ne!(duplicate.id(), transfer.id());
+    ledger.vm().transaction_store().insert(&duplicate).unwrap();
+
+    assert_inconsistency_is_repaired(&ledger, InconsistencyKind::MissingIndex);
+}
+
+#[test]
+fn test_consistency_check_invalid_total_supply() {
+    let rng = &mut TestRng::default();
+
+    // Add a block that mints credits out of thin air.
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let address = Address::try_from(&private_key).unwrap();
+    let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() + 1;
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+    let block = sample_next_block(&ledger, &private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+    ledger.add_next_block(&block).unwrap();
+
+    // Ensure the invalid block is found, and only it is discarded.
+    let inconsistency = ledger.check_consistency(ConsistencyCheck::Deep).unwrap().unwrap();
+    assert_eq!(inconsistency.height, 2);
+    assert_eq!(inconsistency.kind, InconsistencyKind::InvalidTotalSupply);
+    assert_eq!(ledger.repair_consistency(ConsistencyCheck::Latest(2)).unwrap(), Some(inconsistency));
+    assert_eq!(ledger.latest_height(), 1);
+    assert!(!ledger.contains_block_hash(&block.hash()).unwrap());
+}
+
+#[test]
+fn test_consistency_check_invalid_cumulative_proof_target() {
+    let rng = &mut TestRng::default();
+
+    // Add a block that claims work without a coinbase solution.
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let address = Address::try_from(&private_key).unwrap();
+    let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target() + 1;
+    let block = sample_next_block(&ledger, &private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+    ledger.add_next_block(&block).unwrap();
+
+    // Ensure the invalid block is found, and only it is discarded.
+    let inconsistency = ledger.check_consistency(ConsistencyCheck::Deep).unwrap().unwrap();
+    assert_eq!(inconsistency.height, 2);
+    assert_eq!(inconsistency.kind, InconsistencyKind::InvalidCumulativeProofTarget);
+    assert_eq!(ledger.repair_consistency(ConsistencyCheck::Latest(2)).unwrap(), Some(inconsistency));
+    assert_eq!(ledger.latest_height(), 1);
+    assert!(!ledger.contains_block_hash(&block.hash()).unwrap());
+}
+
+#[test]
+fn test_uncommitted_block_is_not_observed() {
+    let rng = &mut TestRng::default();
+
+    // Prepare the next block, without adding it to the ledger.
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let address = Address::try_from(&private_key).unwrap();
+    let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+    let block = sample_next_block(
+        &ledger,
+        &private_key,
+        core::slice::from_ref(&transfer),
+        total_supply,
+        cumulative_proof_target,
+        rng,
+    );
+
+    // Write the block to storage, as if it was in the middle of its commit.
+    ledger.vm().block_store().insert(&block).unwrap();
+
+    // Ensure the block is not observed, as the current block was not swapped to it.
+    assert_eq!(ledger.latest_height(), 1);
+    assert!(ledger.get_block(2).is_err());
+    assert!(ledger.get_hash(2).is_err());
+    assert!(ledger.get_header(2).is_err());
+    assert!(ledger.get_transactions(2).is_err());
+    assert!(ledger.get_block_by_hash(&block.hash()).is_err());
+    assert!(ledger.get_height(&block.hash()).is_err());
+    assert!(ledger.get_transaction(transfer.id()).is_err());
+    assert_eq!(
+        ledger.get_hashes(0..10, false, 10).unwrap(),
+        (1, vec![ledger.get_hash(0).unwrap(), ledger.get_hash(1).unwrap()])
+    );
+
+    // Commit the block.
+    ledger.vm().block_store().remove_last_n(1).unwrap();
+    ledger.add_next_block(&block).unwrap();
+
+    // Ensure the block is observed in full.
+    assert_eq!(ledger.latest_height(), 2);
+    assert_eq!(ledger.get_block(2).unwrap(), block);
+    assert_eq!(ledger.get_block_by_hash(&block.hash()).unwrap(), block);
+    assert_eq!(ledger.get_height(&block.hash()).unwrap(), 2);
+    assert_eq!(ledger.get_transaction(transfer.id()).unwrap(), transfer);
+}
+
+#[test]
+fn test_concurrent_reads_during_commits() {
+    /// The number of blocks committed during the reads.
+    const NUM_BLOCKS: u32 = 3;
+    /// The number of concurrent readers.
+    const NUM_READERS: usize = 4;
+    /// The maximum latency of a read, which does not wait on the commits.
+    const MAX_READ_LATENCY: Duration = Duration::from_secs(1);
+
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let address = Address::try_from(&private_key).unwrap();
+    let is_done = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        // Spawn the readers, which ensure every block they observe is fully committed.
+        let readers = (0..NUM_READERS)
+            .map(|_| {
+                scope.spawn(|| {
+                    let (mut num_reads, mut max_latency) = (0, Duration::ZERO);
+                    while !is_done.load(Ordering::SeqCst) {
+                        let timer = Instant::now();
+                        // Retrieve the latest block, and ensure it is observed in full.
+                        let latest_block = ledger.latest_block();
+                        assert_eq!(ledger.get_block(latest_block.height()).unwrap(), latest_block);
+                        assert_eq!(ledger.get_block_by_hash(&latest_block.hash()).unwrap(), latest_block);
+                        for transaction_id in latest_block.transaction_ids() {
+                            assert_eq!(ledger.get_transaction(*transaction_id).unwrap().id(), *transaction_id);
+                        }
+                        // Ensure the hashes are consistent with the latest height.
+                        let (latest_height, hashes) = ledger.get_hashes(0..u32::MAX, false, usize::MAX).unwrap();
+                        assert!(latest_height >= latest_block.height());
+                        assert_eq!(hashes.len(), latest_height as usize + 1);
+
+                        max_latency = max_latency.max(timer.elapsed());
+                        num_reads += 1;
+                    }
+                    (num_reads, max_latency)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Commit the blocks, while the readers are running.
+        for _ in 0..NUM_BLOCKS {
+            let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+            let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+            let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+            let block =
+                sample_next_block(&ledger, &private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+            ledger.add_next_block(&block).unwrap();
+        }
+        is_done.store(true, Ordering::SeqCst);
+
+        // Ensure the readers did not deadlock, nor wait on the commits.
+        for reader in readers {
+            let (num_reads, max_latency) = reader.join().unwrap();
+            assert!(num_reads > 0);
+            assert!(max_latency < MAX_READ_LATENCY, "A read took {max_latency:?}");
+        }
+    });
+    assert_eq!(ledger.latest_height(), 1 + NUM_BLOCKS);
+}
+
+#[test]
+fn test_pruned_blocks() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, transfer) = sample_ledger_with_transfer(rng);
+    let address = Address::try_from(&private_key).unwrap();
+    let block_hash = ledger.get_hash(1).unwrap();
+
+    // Prune the block containing the transfer.
+    ledger.set_pruned_height(2);
+    assert_eq!(ledger.pruned_height(), 2);
+    // Ensure the pruned height never decreases.
+    ledger.set_pruned_height(1);
+    assert_eq!(ledger.pruned_height(), 2);
+
+    // Ensure the new blocks are still committed.
+    for _ in 0..2 {
+        let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+        let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+        let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+        let block = sample_next_block(&ledger, &private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+        ledger.add_next_block(&block).unwrap();
+        assert_eq!(ledger.get_block(block.height()).unwrap(), block);
+    }
+    assert_eq!(ledger.latest_height(), 3);
+
+    // Ensure the body of the pruned block returns the pruned error.
+    let expected = BlockPruned { height: 1, pruned_height: 2 };
+    assert_eq!(ledger.get_block(1).unwrap_err().downcast::<BlockPruned>().unwrap(), expected);
+    assert_eq!(ledger.get_block_by_hash(&block_hash).unwrap_err().downcast::<BlockPruned>().unwrap(), expected);
+    assert_eq!(ledger.get_transactions(1).unwrap_err().downcast::<BlockPruned>().unwrap(), expected);
+    assert_eq!(ledger.get_transaction(transfer.id()).unwrap_err().downcast::<BlockPruned>().unwrap(), expected);
+    // Ensure the genesis block and the header of the pruned block remain available.
+    assert!(ledger.get_block(0).is_ok());
+    assert_eq!(ledger.get_header(1).unwrap().height(), 1);
+    // Ensure a missing block still returns a different error.
+    assert!(ledger.get_block(10).unwrap_err().downcast::<BlockPruned>().is_err());
+
+    // Ensure the metadata of the transfer remains available.
+    let metadata = ledger.get_transaction_metadata(transfer.id()).unwrap();
+    assert_eq!(metadata.id, transfer.id());
+    assert_eq!(metadata.block_hash, block_hash);
+    assert_eq!(metadata.block_height, 1);
+    assert_eq!(metadata.program_id, None);
+
+    // Ensure the consistency check skips the pruned blocks, and the pruned blocks are not truncated.
+    assert!(ledger.check_consistency(ConsistencyCheck::Deep).unwrap().is_none());
+    assert!(ledger.truncate(1).is_err());
+    assert_eq!(ledger.latest_height(), 3);
+}
+
+#[test]
+fn test_ledger_membership_proof() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let address = Address::try_from(&private_key).unwrap();
+
+    // Add blocks with transfers on top of the ledger.
+    for _ in 0..2 {
+        let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+        let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+        let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+        let block = sample_next_block(&ledger, &private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+        ledger.add_next_block(&block).unwrap();
+    }
+    assert_eq!(ledger.latest_height(), 3);
+
+    for height in 0..=3 {
+        let block = ledger.get_block(height).unwrap();
+        let commitment = *block.transactions().commitments().next().unwrap();
+
+        for digest_height in 0..=3 {
+            let digest = ledger.get_state_root(digest_height).unwrap().unwrap();
+            let proof = ledger.generate_ledger_membership_proof(&commitment, digest);
+            // Ensure the commitment is not proven against a digest from before its block.
+            if digest_height < height {
+                assert!(proof.is_err());
+                continue;
+            }
+            // Ensure the proof is valid at its digest only.
+            let proof = proof.unwrap();
+            assert_eq!(proof.digest(), digest);
+            assert_eq!(proof.leaf_index(), height as u64);
+            assert!(verify_ledger_membership_proof(&proof, digest).is_ok());
+            let other_digest = ledger.get_state_root((digest_height + 1) % 4).unwrap().unwrap();
+            assert!(verify_ledger_membership_proof(&proof, other_digest).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_ledger_membership_proof_tampered_path() {
+    let rng = &mut TestRng::default();
+    let (ledger, _, transfer) = sample_ledger_with_transfer(rng);
+
+    // Generate a proof for the transfer against the latest digest.
+    let commitment = *transfer.commitments().next().unwrap();
+    let digest = ledger.latest_state_root();
+    let proof = ledger.generate_ledger_membership_proof(&commitment, digest).unwrap();
+    assert!(verify_ledger_membership_proof(&proof, digest).is_ok());
+
+    // Tamper with each node of the path, and ensure the proof is rejected.
+    for index in 0..proof.path().len() {
+        let mut path = proof.path().to_vec();
+        path[index] += Field::one();
+        let block_path = BlockPath::try_from((U64::new(proof.leaf_index()), path)).unwrap();
+
+        let state_path = proof.state_path();
+        let tampered = LedgerMembershipProof::new(
+            commitment,
+            StatePath::from(
+                digest,
+                block_path,
+                state_path.block_hash(),
+                state_path.previous_block_hash(),
+                *state_path.header_root(),
+                state_path.header_path().clone(),
+                *state_path.header_leaf(),
+                state_path.transactions_path().clone(),
+                *state_path.transaction_id(),
+                state_path.transaction_path().clone(),
+                *state_path.transaction_leaf(),
+                state_path.transition_path().clone(),
+                *state_path.transition_leaf(),
+            ),
+        );
+        assert!(verify_ledger_membership_proof(&tampered, digest).is_err());
+    }
+}
+
+/// Adds a block with a transfer on top of the given ledger, and returns the block.
+fn add_transfer_block(
+    ledger: &CurrentLedger,
+    private_key: &PrivateKey<CurrentNetwork>,
+    rng: &mut TestRng,
+) -> Block<CurrentNetwork> {
+    let address = Address::try_from(private_key).unwrap();
+    let transfer = ledger.create_transfer(private_key, address, 1).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+    let block = sample_next_block(ledger, private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+    ledger.add_next_block(&block).unwrap();
+    block
+}
+
+/// Returns the chain tip of the given block, with the given branch length and status.
+fn chain_tip(block: &Block<CurrentNetwork>, branch_length: u32, status: ChainTipStatus) -> ChainTip<CurrentNetwork> {
+    ChainTip {
+        height: block.height(),
+        hash: block.hash(),
+        branch_length,
+        cumulative_proof_target: block.cumulative_proof_target(),
+        status,
+    }
+}
+
+#[test]
+fn test_chain_tips() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let fork_point = ledger.latest_block();
+    assert_eq!(ledger.chain_tips(), vec![chain_tip(&fork_point, 0, ChainTipStatus::Active)]);
+
+    // Add a branch of 2 blocks, and reorganize to a competing branch of 1 block.
+    let branch_a = (0..2).map(|_| add_transfer_block(&ledger, &private_key, rng)).collect::<Vec<_>>();
+    ledger.truncate(2).unwrap();
+    assert_eq!(ledger.chain_tips(), vec![
+        chain_tip(&fork_point, 0, ChainTipStatus::Active),
+        chain_tip(&branch_a[1], 2, ChainTipStatus::ValidFork),
+    ]);
+    let branch_b = add_transfer_block(&ledger, &private_key, rng);
+
+    // Ensure both branches are reported, with their lengths from the fork point.
+    assert_eq!(ledger.chain_tips(), vec![
+        chain_tip(&branch_b, 0, ChainTipStatus::Active),
+        chain_tip(&branch_a[1], 2, ChainTipStatus::ValidFork),
+    ]);
+
+    // Reorganize back to the first branch, and ensure the statuses are swapped.
+    ledger.truncate(2).unwrap();
+    for block in &branch_a {
+        ledger.add_next_block(block).unwrap();
+    }
+    assert_eq!(ledger.chain_tips(), vec![
+        chain_tip(&branch_a[1], 0, ChainTipStatus::Active),
+        chain_tip(&branch_b, 1, ChainTipStatus::ValidFork),
+    ]);
+
+    // Ensure a side block that is marked as invalid is reported as invalid.
+    ledger.mark_block_invalid(&branch_b.hash()).unwrap();
+    assert_eq!(ledger.chain_tips()[1], chain_tip(&branch_b, 1, ChainTipStatus::Invalid));
+    // Ensure a canonical block cannot be marked as invalid.
+    assert!(ledger.mark_block_invalid(&branch_a[0].hash()).is_err());
+
+    // Ensure an unvalidated side block is reported as headers-only, and cannot be stored if it is canonical.
+    let address = Address::try_from(&private_key).unwrap();
+    let transfer = ledger.create_transfer(&private_key, address, 1).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+    let block = sample_next_block(&ledger, &private_key, &[transfer], total_supply, cumulative_proof_target, rng);
+    ledger.insert_side_block(&block).unwrap();
+    assert_eq!(ledger.chain_tips()[1], chain_tip(&block, 1, ChainTipStatus::HeadersOnly));
+    assert!(ledger.insert_side_block(&branch_a[1]).is_err());
+}
+
+#[test]
+fn test_side_branches_are_bounded() {
+    let rng = &mut TestRng::default();
+    let (ledger, _, _) = sample_ledger_with_transfer(rng);
+    let latest_block = ledger.latest_block();
+
+    // Returns a header at the given height, and a block hash.
+    let header = |height: u32| {
+        let metadata = Metadata::new(
+            CurrentNetwork::ID,
+            height as u64,
+            height,
+            latest_block.total_supply_in_microcredits(),
+            latest_block.cumulative_proof_target(),
+            latest_block.coinbase_target(),
+            latest_block.proof_target(),
+            latest_block.last_coinbase_target(),
+            latest_block.last_coinbase_timestamp(),
+            latest_block.timestamp() + 1,
+        )
+        .unwrap();
+        Header::from(Field::zero(), Field::zero(), Field::zero(), Field::zero(), metadata).unwrap()
+    };
+    let hash = |index: u64| <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(index));
+
+    // Flood a single height, and ensure the oldest side blocks at the height are discarded.
+    let mut side_branches = SideBranches::<CurrentNetwork>::default();
+    let next_header = header(latest_block.height() + 1);
+    let num_blocks = 2 * MAX_SIDE_BLOCKS_PER_HEIGHT as u64;
+    for index in 0..num_blocks {
+        side_branches.insert(hash(index), latest_block.hash(), &next_header, false);
+    }
+    assert_eq!(side_branches.len(), MAX_SIDE_BLOCKS_PER_HEIGHT);
+    assert!(!side_branches.contains(&hash(num_blocks - MAX_SIDE_BLOCKS_PER_HEIGHT as u64 - 1)));
+    assert!(side_branches.contains(&hash(num_blocks - MAX_SIDE_BLOCKS_PER_HEIGHT as u64)));
+
+    // Flood many heights, and ensure the oldest side blocks are discarded.
+    let headers = (0..(MAX_SIDE_BLOCKS / MAX_SIDE_BLOCKS_PER_HEIGHT) as u32)
+        .map(|offset| header(latest_block.height() + 2 + offset))
+        .collect::<Vec<_>>();
+    for (index, header) in headers.iter().flat_map(|header| [header; MAX_SIDE_BLOCKS_PER_HEIGHT]).enumerate() {
+        side_branches.insert(hash(num_blocks + index as u64), latest_block.hash(), header, false);
+    }
+    assert_eq!(side_branches.len(), MAX_SIDE_BLOCKS);
+    assert!(!side_branches.contains(&hash(num_blocks - 1)));
+    assert!(side_branches.contains(&hash(num_blocks)));
+
+    // Ensure a side block that is already known does not discard another side block.
+    side_branches.insert(hash(num_blocks), latest_block.hash(), &headers[0], true);
+    assert_eq!(side_branches.len(), MAX_SIDE_BLOCKS);
+    assert!(side_branches.contains(&hash(num_blocks + 1)));
+}
+
+#[test]
+fn test_shallow_reorg_uses_recent_blocks() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let blocks = (0..3).map(|_| add_transfer_block(&ledger, &private_key, rng)).collect::<Vec<_>>();
+    assert_eq!(ledger.latest_height(), 4);
+
+    // Ensure a shallow reorganization is handled without falling back to storage.
+    let misses = ledger.recent_blocks_stats().misses;
+    ledger.truncate(3).unwrap();
+    assert_eq!(ledger.recent_blocks_stats().misses, misses);
+    assert_eq!(ledger.latest_block(), blocks[0]);
+    assert_eq!(ledger.chain_tips()[1], chain_tip(&blocks[2], 2, ChainTipStatus::ValidFork));
+    // Ensure the recent blocks are served from memory, and the discarded blocks are not.
+    let hits = ledger.recent_blocks_stats().hits;
+    assert_eq!(ledger.get_block(2).unwrap(), blocks[0]);
+    assert_eq!(ledger.get_block_by_hash(&blocks[0].hash()).unwrap(), blocks[0]);
+    assert_eq!(ledger.recent_blocks_stats().hits, hits + 2);
+    assert!(ledger.get_block(3).is_err());
+
+    // Keep only the latest block in memory, and ensure a deeper reorganization falls back to storage.
+    ledger.set_recent_blocks_capacity(1, usize::MAX);
+    ledger.truncate(2).unwrap();
+    assert!(ledger.recent_blocks_stats().misses > misses);
+    assert_eq!(ledger.latest_height(), 1);
+    assert_eq!(ledger.chain_tips()[1], chain_tip(&blocks[2], 3, ChainTipStatus::ValidFork));
+
+    // Ensure the byte budget bounds the blocks in memory, and the ledger advances again.
+    ledger.set_recent_blocks_capacity(16, 1);
+    let block = add_transfer_block(&ledger, &private_key, rng);
+    let misses = ledger.recent_blocks_stats().misses;
+    assert_eq!(ledger.get_block(2).unwrap(), block);
+    assert_eq!(ledger.get_block(1).unwrap(), ledger.get_blocks(1..2).unwrap()[0]);
+    assert_eq!(ledger.recent_blocks_stats().misses, misses + 2);
+}
+
+/// Returns the number of commitments and serial numbers in the blocks up to and including the given height.
+fn count_commitments(ledger: &CurrentLedger, height: u32) -> (u64, u64) {
+    (0..=height).fold((0, 0), |(num_commitments, num_serial_numbers), height| {
+        let transactions = ledger.get_transactions(height).unwrap();
+        (
+            num_commitments + transactions.commitments().count() as u64,
+            num_serial_numbers + transactions.serial_numbers().count() as u64,
+        )
+    })
+}
+
+#[test]
+fn test_ledger_digest_across_reorg() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let blocks = (0..2).map(|_| add_transfer_block(&ledger, &private_key, rng)).collect::<Vec<_>>();
+
+    // Ensure the latest ledger digest is the latest state root, which the transactions may be built against.
+    let tip = ledger.get_ledger_digest(None).unwrap();
+    assert_eq!((tip.height, tip.block_hash, tip.digest), (3, blocks[1].hash(), ledger.latest_state_root()));
+    assert!(ledger.contains_state_root(&tip.digest).unwrap());
+    assert_eq!((tip.num_commitments, tip.num_serial_numbers), count_commitments(&ledger, 3));
+    // Ensure the ledger digest of an earlier block matches its state root and counts.
+    let digest = ledger.get_ledger_digest(Some(2)).unwrap();
+    assert_eq!((digest.height, digest.block_hash), (2, blocks[0].hash()));
+    assert_eq!(Some(digest.digest), ledger.get_state_root(2).unwrap());
+    assert_eq!((digest.num_commitments, digest.num_serial_numbers), count_commitments(&ledger, 2));
+    assert!(ledger.get_ledger_digest(Some(4)).is_err());
+
+    // Ensure the history lists the latest digests, starting from the latest block.
+    let history = ledger.get_ledger_digest_history(2).unwrap();
+    assert_eq!(history, vec![(3, tip.digest), (2, digest.digest)]);
+    assert_eq!(ledger.get_ledger_digest_history(10).unwrap().len(), 4);
+    assert!(ledger.get_ledger_digest_history(0).is_err());
+    assert!(ledger.get_ledger_digest_history(crate::MAX_DIGESTS_PER_REQUEST + 1).is_err());
+
+    // Reorganize to a competing block, and ensure the discarded digest is no longer accepted or listed.
+    ledger.truncate(3).unwrap();
+    assert_eq!(ledger.get_ledger_digest(None).unwrap(), digest);
+    assert!(!ledger.contains_state_root(&tip.digest).unwrap());
+    let block = add_transfer_block(&ledger, &private_key, rng);
+    let tip = ledger.get_ledger_digest(None).unwrap();
+    assert_eq!((tip.height, tip.block_hash, tip.digest), (3, block.hash(), ledger.latest_state_root()));
+    assert!(ledger.contains_state_root(&tip.digest).unwrap());
+    assert_eq!((tip.num_commitments, tip.num_serial_numbers), count_commitments(&ledger, 3));
+    assert_eq!(ledger.get_ledger_digest_history(2).unwrap(), vec![(3, tip.digest), (2, digest.digest)]);
+}
+
+#[test]
+fn test_spent_filter_across_reorg() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let block = add_transfer_block(&ledger, &private_key, rng);
+
+    // Ensure the serial numbers of the ledger are in the filter, and are confirmed by the index.
+    let serial_numbers = ledger.serial_numbers().map(|serial_number| *serial_number).collect::<Vec<_>>();
+    assert!(!serial_numbers.is_empty());
+    for serial_number in &serial_numbers {
+        assert!(ledger.spent_filter.read().contains(serial_number));
+        assert!(ledger.contains_serial_number(serial_number).unwrap());
+    }
+
+    // Insert an unspent serial number in the filter, as a false positive, and ensure the index resolves it as unspent.
+    let false_positive = Field::rand(rng);
+    ledger.spent_filter.write().insert(&false_positive);
+    assert!(ledger.spent_filter.read().contains(&false_positive));
+    assert!(!ledger.contains_serial_number(&false_positive).unwrap());
+
+    // Reorganize the block away, and ensure its serial numbers are unspent again.
+    ledger.truncate(2).unwrap();
+    for serial_number in block.serial_numbers() {
+        assert!(!ledger.contains_serial_number(serial_number).unwrap());
+    }
+    // Ensure a rebuilt filter matches the index, without the false positive.
+    ledger.rebuild_spent_filter();
+    assert_eq!(ledger.spent_filter.read().len(), serial_numbers.len() - block.serial_numbers().count());
+    assert!(ledger.serial_numbers().all(|serial_number| ledger.spent_filter.read().contains(&serial_number)));
+    assert!(!ledger.contains_serial_number(&false_positive).unwrap());
+}
+
+/// Returns the given execution, with its first transition rebuilt from the given inputs and outputs.
+fn rebuild_execution(
+    transaction: &Transaction<CurrentNetwork>,
+    inputs: impl FnOnce(Vec<Input<CurrentNetwork>>) -> Vec<Input<CurrentNetwork>>,
+    outputs: impl FnOnce(Vec<Output<CurrentNetwork>>) -> Vec<Output<CurrentNetwork>>,
+) -> Transaction<CurrentNetwork> {
+    let execution = match transaction {
+        Transaction::Execute(_, execution, _) => execution,
+        _ => panic!("Expected an execution"),
+    };
+    // Rebuild the first transition, which recomputes its ID.
+    let mut transitions = execution.transitions().cloned().collect::<Vec<_>>();
+    let transition = &transitions[0];
+    transitions[0] = Transition::new(
+        *transition.program_id(),
+        *transition.function_name(),
+        inputs(transition.inputs().to_vec()),
+        outputs(transition.outputs().to_vec()),
+        transition.finalize().cloned(),
+        transition.proof().clone(),
+        *transition.tpk(),
+        *transition.tcm(),
+    )
+    .unwrap();
+    // Rebuild the execution, without the inclusion proof.
+    let execution = Execution::from(transitions.into_iter(), execution.global_state_root(), None).unwrap();
+    Transaction::from_execution(execution, None).unwrap()
+}
+
+#[test]
+fn test_validate_structure() {
+    let rng = &mut TestRng::default();
+
+    // Ensure the genesis transactions are well-formed.
+    let genesis = sample_genesis_block();
+    for transaction in genesis.transactions().values() {
+        assert_eq!(transaction.validate_structure(), Ok(()));
+    }
+    let transaction = genesis.transactions().values().next().unwrap();
+    // Ensure an unchanged rebuild of the transaction is well-formed.
+    assert_eq!(rebuild_execution(transaction, |inputs| inputs, |outputs| outputs).validate_structure(), Ok(()));
+
+    // Ensure a record commitment without its encrypted record is rejected.
+    let malformed = rebuild_execution(
+        transaction,
+        |inputs| inputs,
+        |outputs| {
+            outputs
+                .into_iter()
+                .map(|output| match output {
+                    Output::Record(commitment, checksum, _) => Output::Record(commitment, checksum, None),
+                    output => output,
+                })
+                .collect()
+        },
+    );
+    let num_commitments = transaction.commitments().count();
+    assert!(num_commitments > 0);
+    assert_eq!(
+        malformed.validate_structure(),
+        Err(StructureViolation::RecordCountMismatch { num_records: 0, num_commitments })
+    );
+
+    // Ensure a public input without its value is rejected.
+    let malformed = rebuild_execution(
+        transaction,
+        |inputs| {
+            inputs
+                .into_iter()
+                .map(|input| match input {
+                    Input::Public(hash, _) => Input::Public(hash, None),
+                    input => input,
+                })
+                .collect()
+        },
+        |outputs| outputs,
+    );
+    let num_inputs = transaction.transitions().next().unwrap().inputs().len();
+    assert!(num_inputs > 0);
+    assert_eq!(
+        malformed.validate_structure(),
+        Err(StructureViolation::InputValueCountMismatch { num_values: 0, num_inputs })
+    );
+
+    // Ensure a spent record without an inclusion proof is rejected.
+    let malformed = rebuild_execution(
+        transaction,
+        |mut inputs| {
+            inputs.push(Input::Record(Uniform::rand(rng), Uniform::rand(rng)));
+            inputs
+        },
+        |outputs| outputs,
+    );
+    assert_eq!(
+        malformed.validate_structure(),
+        Err(StructureViolation::MissingInclusionProof { num_serial_numbers: 1 })
+    );
+}
+
+#[test]
+fn test_shadow_storage() {
+    /// The number of blocks synced with the shadow storage enabled.
+    const NUM_BLOCKS: u32 = 200;
+
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    assert!(!ledger.is_shadow_enabled());
+    assert!(ledger.compare_shadow().is_err());
+
+    // Enable the shadow storage, which applies the existing blocks first.
+    ledger.enable_shadow().unwrap();
+    assert!(ledger.is_shadow_enabled());
+
+    // Sync the blocks, with a shallow reorganization along the way.
+    for _ in 1..NUM_BLOCKS {
+        add_transfer_block(&ledger, &private_key, rng);
+    }
+    ledger.truncate(NUM_BLOCKS).unwrap();
+    add_transfer_block(&ledger, &private_key, rng);
+    assert_eq!(ledger.latest_height(), NUM_BLOCKS);
+
+    // Ensure the shadow storage matches the primary storage.
+    let report = ledger.compare_shadow().unwrap();
+    assert!(report.is_enabled && report.is_synced);
+    assert_eq!((report.primary_height, report.shadow_height), (NUM_BLOCKS, Some(NUM_BLOCKS)));
+    assert_eq!(report.num_queued, 0);
+    // Ensure every block, the truncation, and the replacing block were applied.
+    assert_eq!(report.num_applied, NUM_BLOCKS as u64 + 3);
+    assert_eq!(report.num_failures, 0);
+    assert!(report.num_comparisons > 0);
+    assert_eq!(report.num_divergences, 0, "{:?}", report.divergences);
+
+    // Corrupt the shadow storage, by removing a transaction of the latest block.
+    let transaction_id = *ledger.latest_block().transaction_ids().next().unwrap();
+    ledger.with_shadow_vm(|vm| vm.block_store().transaction_store().remove(&transaction_id).unwrap()).unwrap();
+
+    // Ensure the divergence is detected, with the exact key that differs.
+    let report = ledger.compare_shadow().unwrap();
+    assert!(report.num_divergences > 0);
+    let divergence =
+        report.divergences.iter().find(|divergence| divergence.key == format!("transaction/{transaction_id}"));
+    assert_eq!(divergence.unwrap().shadow, Some("false".to_string()));
+    assert!(report.divergences.iter().any(|divergence| divergence.key == "count/transactions"));
+
+    // Disable the shadow storage, and ensure the ledger is unaffected.
+    ledger.disable_shadow();
+    assert!(!ledger.is_shadow_enabled());
+    assert!(!ledger.shadow_report().is_enabled);
+    assert!(ledger.compare_shadow().is_err());
+    add_transfer_block(&ledger, &private_key, rng);
+    assert_eq!(ledger.latest_height(), NUM_BLOCKS + 1);
+}
+
+#[test]
+fn test_deposit_proof() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, transfer) = sample_ledger_with_transfer(rng);
+    let blocks = (0..2).map(|_| add_transfer_block(&ledger, &private_key, rng)).collect::<Vec<_>>();
+    let tip = ledger.latest_header();
+
+    // Ensure the deposit proof of the transfer verifies against the tip.
+    let proof = ledger.get_deposit_proof(&transfer.id()).unwrap();
+    assert_eq!(proof.transaction(), &transfer);
+    assert_eq!(proof.header(), &ledger.get_header(1).unwrap());
+    assert_eq!(proof.block_hash().unwrap(), ledger.get_hash(1).unwrap());
+    assert_eq!((proof.tip_height(), proof.confirmations()), (3, 3));
+    assert_eq!(proof.chain_work(), tip.cumulative_proof_target() - proof.header().cumulative_proof_target());
+    verify_deposit_proof(&proof, &tip).unwrap();
+    // Ensure the deposit proof is verified from its bytes, as an exchange would offline.
+    let decoded = DepositProof::read_le(&proof.to_bytes_le().unwrap()[..]).unwrap();
+    assert_eq!(decoded, proof);
+    verify_deposit_proof(&decoded, &tip).unwrap();
+
+    // Ensure the deposit proof of a transaction in the tip has a single confirmation.
+    let latest = blocks[1].transactions().values().next().unwrap();
+    let latest_proof = ledger.get_deposit_proof(&latest.id()).unwrap();
+    assert_eq!(latest_proof.confirmations(), 1);
+    assert!(latest_proof.block_path().is_none());
+    verify_deposit_proof(&latest_proof, &tip).unwrap();
+
+    // Ensure a mismatched trusted header is rejected.
+    assert!(verify_deposit_proof(&proof, &ledger.get_header(2).unwrap()).is_err());
+    assert!(verify_deposit_proof(&latest_proof, &ledger.get_header(2).unwrap()).is_err());
+    // Ensure a proof with a mismatched header is rejected.
+    let middle = ledger.get_deposit_proof(&blocks[0].transactions().values().next().unwrap().id()).unwrap();
+    let tampered = DepositProof::new(
+        proof.transaction().clone(),
+        *middle.header(),
+        proof.previous_block_hash(),
+        proof.transactions_path().clone(),
+        proof.block_path().cloned(),
+        proof.tip_height(),
+        proof.chain_work(),
+    );
+    assert!(verify_deposit_proof(&tampered, &tip).is_err());
+    // Ensure a proof with a tampered block path is rejected.
+    let tampered = DepositProof::new(
+        proof.transaction().clone(),
+        *proof.header(),
+        proof.previous_block_hash(),
+        proof.transactions_path().clone(),
+        middle.block_path().cloned(),
+        proof.tip_height(),
+        proof.chain_work(),
+    );
+    assert!(verify_deposit_proof(&tampered, &tip).is_err());
+    // Ensure a proof with a tampered transactions path is rejected.
+    let tampered = DepositProof::new(
+        latest.clone(),
+        *proof.header(),
+        proof.previous_block_hash(),
+        proof.transactions_path().clone(),
+        proof.block_path().cloned(),
+        proof.tip_height(),
+        proof.chain_work(),
+    );
+    assert!(verify_deposit_proof(&tampered, &tip).is_err());
+    // Ensure a proof with tampered chain work is rejected.
+    let tampered = DepositProof::new(
+        proof.transaction().clone(),
+        *proof.header(),
+        proof.previous_block_hash(),
+        proof.transactions_path().clone(),
+        proof.block_path().cloned(),
+        proof.tip_height(),
+        proof.chain_work() + 1,
+    );
+    assert!(verify_deposit_proof(&tampered, &tip).is_err());
+
+    // Ensure a missing transaction has no deposit proof.
+    ledger.truncate(3).unwrap();
+    assert!(ledger.get_deposit_proof(&latest.id()).is_err());
+}
+
+/// Adds a block with a transfer of the given amount from the given private key to the given address,
+/// and returns the transfer.
+fn add_transfer_to_block(
+    ledger: &CurrentLedger,
+    private_key: &PrivateKey<CurrentNetwork>,
+    to: Address<CurrentNetwork>,
+    amount: u64,
+    rng: &mut TestRng,
+) -> Transaction<CurrentNetwork> {
+    let transfer = ledger.create_transfer(private_key, to, amount).unwrap();
+    let total_supply = ledger.latest_total_supply_in_microcredits() - *transfer.fee().unwrap();
+    let cumulative_proof_target = ledger.latest_cumulative_proof_target();
+    let block = sample_next_block(
+        ledger,
+        private_key,
+        core::slice::from_ref(&transfer),
+        total_supply,
+        cumulative_proof_target,
+        rng,
+    );
+    ledger.add_next_block(&block).unwrap();
+    transfer
+}
+
+#[test]
+fn test_account_history() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+
+    // Register a new account, whose history is empty.
+    let account_private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+    let address = ledger.register_account(ViewKey::try_from(&account_private_key).unwrap()).unwrap();
+    assert_eq!(address, Address::try_from(&account_private_key).unwrap());
+    assert_eq!(ledger.get_account_history(&address, 0, 10).unwrap().num_entries, 0);
+
+    // Receive a record, and spend it in the next block.
+    let receive = add_transfer_to_block(&ledger, &private_key, address, 100, rng);
+    let genesis_address = Address::try_from(&private_key).unwrap();
+    let spend = add_transfer_to_block(&ledger, &account_private_key, genesis_address, 30, rng);
+
+    // Ensure the history contains the received record, its spend, and the change of the spend, in order.
+    let page = ledger.get_account_history(&address, 0, 10).unwrap();
+    assert_eq!(page.latest_height, 3);
+    assert_eq!(page.num_entries, 3);
+    let entries = page.entries;
+    assert_eq!((entries[0].height, entries[0].transaction_id), (2, receive.id()));
+    assert_eq!((entries[0].direction, entries[0].amount), (RecordDirection::Received, Some(100)));
+    assert_eq!((entries[1].height, entries[1].transaction_id), (3, spend.id()));
+    assert_eq!((entries[1].direction, entries[1].amount), (RecordDirection::Spent, Some(100)));
+    assert_eq!(entries[1].commitment, entries[0].commitment);
+    assert_eq!((entries[2].height, entries[2].transaction_id), (3, spend.id()));
+    assert_eq!((entries[2].direction, entries[2].amount), (RecordDirection::Received, Some(70)));
+
+    // Ensure the history is paginated.
+    assert_eq!(ledger.get_account_history(&address, 1, 1).unwrap().entries, entries[1..2]);
+    assert!(ledger.get_account_history(&address, 3, 10).unwrap().entries.is_empty());
+
+    // Ensure an account that registers later scans the same history.
+    assert!(ledger.unregister_account(&address));
+    assert!(ledger.get_account_history(&address, 0, 10).is_err());
+    ledger.register_account(ViewKey::try_from(&account_private_key).unwrap()).unwrap();
+    assert_eq!(ledger.get_account_history(&address, 0, 10).unwrap().entries, entries);
+
+    // Reorganize the spend away, and ensure only the received record remains.
+    ledger.truncate(3).unwrap();
+    let page = ledger.get_account_history(&address, 0, 10).unwrap();
+    assert_eq!(page.latest_height, 2);
+    assert_eq!(page.entries, entries[..1]);
+
+    // Ensure the received record is joined again, when it is spent on the new branch.
+    let spend = add_transfer_to_block(&ledger, &account_private_key, genesis_address, 40, rng);
+    let entries = ledger.get_account_history(&address, 0, 10).unwrap().entries;
+    assert_eq!(entries.len(), 3);
+    assert_eq!((entries[1].transaction_id, entries[1].direction), (spend.id(), RecordDirection::Spent));
+    assert_eq!((entries[1].amount, entries[2].amount), (Some(100), Some(60)));
+}
+
+#[test]
+fn test_transaction_subscription_filters() {
+    let rng = &mut TestRng::default();
+    let (ledger, private_key, _) = sample_ledger_with_transfer(rng);
+    let genesis_address = Address::try_from(&private_key).unwrap();
+    let account_private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+    let account = Address::try_from(&account_private_key).unwrap();
+
+    // Ensure a filter on an unregistered account is rejected.
+    let by_account = TransactionFilter { account: Some(account), ..Default::default() };
+    assert!(ledger.subscribe_transactions(by_account.clone()).is_err());
+    ledger.register_account(ViewKey::try_from(&account_private_key).unwrap()).unwrap();
+
+    // Subscribe with a filter on the value balance, on the circuit, and on the account and the circuit.
+    let transfer = (ProgramID::from_str("credits.aleo").unwrap(), Identifier::from_str("transfer").unwrap());
+    let by_value = ledger
+        .subscribe_transactions(TransactionFilter { min_value_balance: Some(100), ..Default::default() })
+        .unwrap();
+    let by_circuit =
+        ledger.subscribe_transactions(TransactionFilter { circuit: Some(transfer), ..Default::default() }).unwrap();
+    let by_account =
+        ledger.subscribe_transactions(TransactionFilter { circuit: Some(transfer), ..by_account }).unwrap();
+
+    // Generate a transfer to the account, a split that pays a fee, and a transfer to the genesis account.
+    let records = ledger.find_unspent_records(&ViewKey::try_from(&private_key).unwrap()).unwrap();
+    let records = records.values().cloned().collect::<Vec<_>>();
+    let to_account = ledger.create_transfer(&private_key, account, 10).unwrap();
+    let inputs = [Value::Record(records[0].clone()), Value::from_str("1u64").unwrap()];
+    let fee = Some((records[1].clone(), 100u64));
+    let split =
+        Transaction::execute(ledger.vm(), &private_key, ("credits.aleo", "split"), inputs.iter(), fee, None, rng)
+            .unwrap();
+    let to_genesis = ledger.create_transfer(&private_key, genesis_address, 10).unwrap();
+    assert_eq!(*split.fee().unwrap(), 100);
+
+    // Publish the transactions, as the memory pool accepts them.
+    for transaction in [&to_account, &split, &to_genesis] {
+        ledger.event_bus().publish(Event::TransactionAccepted { transaction: Arc::new(transaction.clone()) });
+    }
+
+    // Ensure each subscriber receives exactly the transactions that match its filter, in order.
+    let received = |subscription: &crate::Subscription<CurrentNetwork>| {
+        subscription
+            .try_iter()
+            .map(|event| match event {
+                Event::TransactionAccepted { transaction } => transaction.id(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(received(&by_value), vec![split.id()]);
+    assert_eq!(received(&by_circuit), vec![to_account.id(), to_genesis.id()]);
+    assert_eq!(received(&by_account), vec![to_account.id()]);
+    assert_eq!(by_value.num_dropped() + by_circuit.num_dropped() + by_account.num_dropped(), 0);
+}
@@ -0,0 +1,50 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Regenerates the committed test vectors from the code, or checks that they did not drift from it.
+//!
+//! Usage: `cargo run -p snarkos-node-messages --features test-vectors --bin generate-test-vectors [-- --check]`
+
+use snarkos_node_messages::test_vectors::{TestVectors, TEST_VECTORS_PATH};
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+fn main() -> Result<()> {
+    let is_check = match std::env::args().nth(1).as_deref() {
+        None => false,
+        Some("--check") => true,
+        Some(argument) => bail!("Unknown argument '{argument}' (expected '--check')"),
+    };
+
+    // Generate the test vectors from the code.
+    let expected = TestVectors::generate()?.to_json()?;
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(TEST_VECTORS_PATH);
+
+    match is_check {
+        // Ensure the committed test vectors match the code.
+        true => match std::fs::read_to_string(&path)? == expected {
+            true => println!("The test vectors in '{}' are up to date", path.display()),
+            false => bail!("The test vectors in '{}' drifted from the code, regenerate them", path.display()),
+        },
+        // Write the test vectors.
+        false => {
+            std::fs::write(&path, expected)?;
+            println!("Wrote the test vectors to '{}'", path.display());
+        }
+    }
+    Ok(())
+}
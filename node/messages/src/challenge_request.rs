@@ -19,10 +19,20 @@ use super::*;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ChallengeRequest<N: Network> {
     pub version: u32,
+    pub network_id: u16,
     pub listener_port: u16,
     pub node_type: NodeType,
     pub address: Address<N>,
+    pub genesis_hash: N::BlockHash,
     pub nonce: u64,
+    /// The height below which the peer pruned the bodies of its blocks, or `0` if it serves every block.
+    pub pruned_height: u32,
+    /// The capabilities the peer serves, or every capability if the peer is on a version without them.
+    pub capabilities: Capabilities,
+    /// The UNIX timestamp (in seconds) of the clock of the peer, if the peer reports it.
+    pub timestamp: Option<i64>,
+    /// The node ID of the peer, if the peer reports it, which requires the timestamp to be reported as well.
+    pub node_id: Option<NodeId>,
 }
 
 impl<N: Network> MessageTrait for ChallengeRequest<N> {
@@ -35,22 +45,103 @@ impl<N: Network> MessageTrait for ChallengeRequest<N> {
     /// Serializes the message into the buffer.
     #[inline]
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        Ok(bincode::serialize_into(
-            writer,
-            &(self.version, self.listener_port, self.node_type, self.address, self.nonce),
-        )?)
+        bincode::serialize_into(
+            &mut *writer,
+            &(
+                self.version,
+                self.network_id,
+                self.listener_port,
+                self.node_type,
+                self.address,
+                self.genesis_hash,
+                self.nonce,
+            ),
+        )?;
+        // Append the extensions, from the version that introduced them on.
+        if self.version >= Message::<N>::EXTENDED_VERSION {
+            if self.node_id.is_some() && self.timestamp.is_none() {
+                bail!("The node ID of a challenge request requires its timestamp");
+            }
+            bincode::serialize_into(writer, &(self.pruned_height, self.capabilities, self.timestamp, self.node_id))?;
+        }
+        Ok(())
     }
 
     /// Deserializes the given buffer into a message.
     #[inline]
     fn deserialize(bytes: BytesMut) -> Result<Self> {
-        let (version, listener_port, node_type, address, nonce) = bincode::deserialize_from(&mut bytes.reader())?;
-        Ok(Self { version, listener_port, node_type, address, nonce })
+        let mut reader = bytes.reader();
+        let (version, network_id, listener_port, node_type, address, genesis_hash, nonce) =
+            bincode::deserialize_from(&mut reader)?;
+        // Read the extensions, which are absent from the requests of the peers on an earlier version,
+        // as these serve every block and every capability, and report neither their timestamp nor their node ID.
+        let (pruned_height, capabilities, timestamp, node_id) = match version >= Message::<N>::EXTENDED_VERSION {
+            true => bincode::deserialize_from(&mut reader)?,
+            false => (0, Capabilities::ALL, None, None),
+        };
+        // Ensure the request has no trailing bytes.
+        let num_trailing = reader.get_ref().remaining();
+        if num_trailing > 0 {
+            bail!("Found {num_trailing} trailing bytes after a challenge request of version {version}");
+        }
+        if node_id.is_some() && timestamp.is_none() {
+            bail!("The node ID of a challenge request requires its timestamp");
+        }
+        Ok(Self {
+            version,
+            network_id,
+            listener_port,
+            node_type,
+            address,
+            genesis_hash,
+            nonce,
+            pruned_height,
+            capabilities,
+            timestamp,
+            node_id,
+        })
     }
 }
 
 impl<N: Network> ChallengeRequest<N> {
-    pub fn new(listener_port: u16, node_type: NodeType, address: Address<N>, nonce: u64) -> Self {
-        Self { version: Message::<N>::VERSION, listener_port, node_type, address, nonce }
+    pub fn new(
+        listener_port: u16,
+        node_type: NodeType,
+        address: Address<N>,
+        genesis_hash: N::BlockHash,
+        nonce: u64,
+        pruned_height: u32,
+    ) -> Self {
+        Self {
+            version: Message::<N>::VERSION,
+            network_id: N::ID,
+            listener_port,
+            node_type,
+            address,
+            genesis_hash,
+            nonce,
+            pruned_height,
+            capabilities: Capabilities::ALL,
+            timestamp: None,
+            node_id: None,
+        }
+    }
+
+    /// Sets the capabilities advertised in the challenge request.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Sets the UNIX timestamp (in seconds) reported in the challenge request.
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the node ID reported in the challenge request, which requires the timestamp to be reported as well.
+    pub fn with_node_id(mut self, node_id: NodeId) -> Self {
+        self.node_id = Some(node_id);
+        self
     }
 }
@@ -0,0 +1,105 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! A differential harness of the two encodings of a transaction: the native encoding of snarkVM, and the framing
+//! of the transaction in an `UnconfirmedTransaction` message, whose payload is decoded in strict mode.
+//!
+//! The harness is shared by the tests of this crate, and by the `transaction_differential` fuzz target
+//! (with the `test` feature). An input on which the encodings diverge is committed as a regression fixture
+//! in `fixtures/transaction_differential`.
+
+use crate::{Data, Message, TrailingBytes, UnconfirmedTransaction, MAX_PARENT_TXS};
+use snarkvm::prelude::{FromBytes, Network, ToBytes, Transaction};
+
+use anyhow::{bail, ensure, Result};
+use bytes::{Bytes, BytesMut};
+
+/// The path of the regression fixtures of the harness, relative to the root of this crate.
+pub const REGRESSION_FIXTURES_PATH: &str = "fixtures/transaction_differential";
+
+/// Checks the given transaction converts through both encodings without drift: the framing of the transaction,
+/// with the given parents, in an `UnconfirmedTransaction` message is the identity, the transaction re-encodes into
+/// the same native bytes after the round trip, and both encodings carry the same transaction ID.
+pub fn check_transaction_roundtrip<N: Network>(
+    transaction: &Transaction<N>,
+    mut parent_ids: Vec<N::TransactionID>,
+) -> Result<()> {
+    let transaction_id = transaction.id();
+    let bytes = transaction.to_bytes_le()?;
+
+    // Frame the transaction in a message, and decode the message.
+    let message = UnconfirmedTransaction::new(transaction_id, Data::Object(transaction.clone()));
+    let message = Message::UnconfirmedTransaction(message.with_parents(parent_ids.clone()));
+    let mut buffer = Vec::new();
+    message.serialize(&mut buffer)?;
+    let candidate = match Message::<N>::deserialize(BytesMut::from(&buffer[..]))? {
+        Message::UnconfirmedTransaction(candidate) => candidate,
+        candidate => bail!("Transaction '{transaction_id}' was framed into a '{}' message", candidate.name()),
+    };
+    parent_ids.truncate(MAX_PARENT_TXS);
+    ensure!(candidate.parent_ids == parent_ids, "The parents of transaction '{transaction_id}' changed in the frame");
+
+    // Ensure the framed transaction decodes into the same transaction, with the same native bytes and ID.
+    let framed_id = candidate.transaction_id;
+    let candidate = candidate.transaction.deserialize_blocking()?;
+    ensure!(&candidate == transaction, "Transaction '{transaction_id}' changed in the frame");
+    ensure!(candidate.to_bytes_le()? == bytes, "Transaction '{transaction_id}' re-encodes into other native bytes");
+    ensure!(
+        framed_id == transaction_id && candidate.id() == transaction_id,
+        "Transaction '{transaction_id}' is framed as '{framed_id}', and decodes as '{}'",
+        candidate.id()
+    );
+
+    // Ensure the native bytes decode into the same transaction.
+    ensure!(
+        Transaction::<N>::from_bytes_le(&bytes)? == *transaction,
+        "Transaction '{transaction_id}' changed in the native encoding"
+    );
+    Ok(())
+}
+
+/// Decodes the given bytes with the native decoder of snarkVM, and with the strict decoder of a framed payload,
+/// and returns the transaction if both decoders accept the bytes, or `None` if both reject them.
+///
+/// The decoders may only differ on the bytes that follow a transaction, which the native decoder ignores.
+/// Any other divergence, or a disagreement on the decoded transaction or its ID, is returned as an error.
+pub fn decode_differential<N: Network>(bytes: &[u8]) -> Result<Option<Transaction<N>>> {
+    let native = Transaction::<N>::from_bytes_le(bytes);
+    let framed = Data::<Transaction<N>>::Buffer(Bytes::copy_from_slice(bytes)).deserialize_blocking();
+
+    match (native, framed) {
+        (Ok(native), Ok(framed)) => {
+            ensure!(
+                native.id() == framed.id(),
+                "The decoders disagree on the transaction ID ('{}' and '{}')",
+                native.id(),
+                framed.id()
+            );
+            ensure!(native == framed, "The decoders disagree on transaction '{}'", native.id());
+            Ok(Some(framed))
+        }
+        (Ok(native), Err(error)) => {
+            // Ensure the strict decoder only rejects the bytes that follow the native encoding of the transaction.
+            let length = native.to_bytes_le()?.len();
+            match error.downcast_ref::<TrailingBytes>() {
+                Some(trailing) if trailing.offset == length => Ok(None),
+                _ => bail!("Only the native decoder accepts transaction '{}' - {error}", native.id()),
+            }
+        }
+        (Err(error), Ok(framed)) => bail!("Only the strict decoder accepts transaction '{}' - {error}", framed.id()),
+        (Err(_), Err(_)) => Ok(None),
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+/// The maximum number of transaction IDs in a `GetParentTxs` request, or in the parents of an announcement.
+pub const MAX_PARENT_TXS: usize = 16;
+
+/// A request for the unconfirmed transactions with the given IDs, which are the parents that the peer announced
+/// for a transaction it relayed, and that are unknown to this node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GetParentTxs<N: Network> {
+    pub transaction_ids: Vec<N::TransactionID>,
+}
+
+impl<N: Network> MessageTrait for GetParentTxs<N> {
+    /// Returns the message name.
+    #[inline]
+    fn name(&self) -> String {
+        "GetParentTxs".to_string()
+    }
+
+    /// Serializes the message into the buffer.
+    #[inline]
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_transaction_ids::<N, _>(writer, &self.transaction_ids)
+    }
+
+    /// Deserializes the given buffer into a message.
+    #[inline]
+    fn deserialize(bytes: BytesMut) -> Result<Self> {
+        let mut reader = bytes.reader();
+        let transaction_ids = read_transaction_ids::<N, _>(&mut reader)?;
+        match reader.into_inner().remaining() == 0 {
+            true => Ok(Self { transaction_ids }),
+            false => bail!("Invalid 'GetParentTxs' message"),
+        }
+    }
+}
+
+/// Writes the given transaction IDs, prefixed with their number.
+pub(crate) fn write_transaction_ids<N: Network, W: Write>(
+    writer: &mut W,
+    transaction_ids: &[N::TransactionID],
+) -> Result<()> {
+    if transaction_ids.len() > MAX_PARENT_TXS {
+        bail!("Found {} transaction IDs (maximum is {MAX_PARENT_TXS})", transaction_ids.len());
+    }
+    writer.write_all(&(transaction_ids.len() as u16).to_le_bytes())?;
+    for transaction_id in transaction_ids {
+        writer.write_all(&transaction_id.to_bytes_le()?)?;
+    }
+    Ok(())
+}
+
+/// Reads the transaction IDs written by `write_transaction_ids`.
+pub(crate) fn read_transaction_ids<N: Network, R: Read>(reader: &mut R) -> Result<Vec<N::TransactionID>> {
+    let num_transaction_ids = u16::read_le(&mut *reader)? as usize;
+    if num_transaction_ids > MAX_PARENT_TXS {
+        bail!("Found {num_transaction_ids} transaction IDs (maximum is {MAX_PARENT_TXS})");
+    }
+    (0..num_transaction_ids).map(|_| Ok(N::TransactionID::read_le(&mut *reader)?)).collect()
+}
@@ -17,8 +17,14 @@
 use snarkvm::prelude::{has_duplicates, Network};
 
 use anyhow::{bail, ensure, Result};
+use core::{fmt, marker::PhantomData};
 use indexmap::{indexmap, IndexMap};
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
 use std::collections::{btree_map::IntoIter, BTreeMap};
 
 /// The number of recent blocks (near tip).
@@ -31,11 +37,41 @@ pub const CHECKPOINT_INTERVAL: u32 = 10_000; // 10,000 block intervals
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlockLocators<N: Network> {
     /// The map of recent blocks.
+    #[serde(deserialize_with = "deserialize_locators::<_, N>")]
     pub recents: IndexMap<u32, N::BlockHash>,
     /// The map of block checkpoints.
+    #[serde(deserialize_with = "deserialize_locators::<_, N>")]
     pub checkpoints: IndexMap<u32, N::BlockHash>,
 }
 
+/// Deserializes a map of block locators, without trusting the number of entries it declares.
+///
+/// The map is received from the peers, and the number of entries declared in its encoding is only
+/// used as a hint, as preallocating a map with an arbitrary number of entries aborts the process.
+fn deserialize_locators<'de, D: Deserializer<'de>, N: Network>(
+    deserializer: D,
+) -> Result<IndexMap<u32, N::BlockHash>, D::Error> {
+    struct LocatorsVisitor<N: Network>(PhantomData<N>);
+
+    impl<'de, N: Network> Visitor<'de> for LocatorsVisitor<N> {
+        type Value = IndexMap<u32, N::BlockHash>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of block heights to block hashes")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+            let mut locators = IndexMap::with_capacity(access.size_hint().unwrap_or(0).min(NUM_RECENTS));
+            while let Some((height, hash)) = access.next_entry()? {
+                locators.insert(height, hash);
+            }
+            Ok(locators)
+        }
+    }
+
+    deserializer.deserialize_map(LocatorsVisitor::<N>(PhantomData))
+}
+
 impl<N: Network> IntoIterator for BlockLocators<N> {
     type IntoIter = IntoIter<u32, N::BlockHash>;
     type Item = (u32, N::BlockHash);
@@ -0,0 +1,234 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::prelude::{Block, Network, Transaction};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use ciborium::value::Value;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The CBOR tag of an encoded transaction (the ASCII bytes `alet`).
+pub const CBOR_TAG_TRANSACTION: u64 = 0x616c_6574;
+/// The CBOR tag of an encoded block (the ASCII bytes `aleb`).
+pub const CBOR_TAG_BLOCK: u64 = 0x616c_6562;
+
+/// The canonical CBOR encoding of an object.
+///
+/// The object is encoded as a tagged, self-describing CBOR data item with the same structure as
+/// its JSON representation, following the deterministic encoding rules of RFC 8949 (section 4.2.1):
+/// integers and lengths use their shortest form, lengths are definite, and map keys are sorted
+/// by their encoded bytes.
+pub trait CborEncoding: Serialize + DeserializeOwned {
+    /// The CBOR tag of the object.
+    const CBOR_TAG: u64;
+
+    /// Returns the canonical CBOR encoding of the object.
+    fn to_cbor(&self) -> Result<Vec<u8>> {
+        // Convert the object into a CBOR value, with its map keys sorted.
+        let value = to_cbor_value(serde_json::to_value(self)?)?;
+        // Encode the tagged value.
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&Value::Tag(Self::CBOR_TAG, Box::new(value)), &mut bytes)
+            .map_err(|e| anyhow!("Failed to encode as CBOR: {e}"))?;
+        Ok(bytes)
+    }
+
+    /// Returns the object from the given CBOR encoding.
+    fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        // Decode the tagged value.
+        let value: Value = ciborium::de::from_reader(bytes).map_err(|e| anyhow!("Failed to decode CBOR: {e}"))?;
+        match value {
+            Value::Tag(tag, value) if tag == Self::CBOR_TAG => Ok(serde_json::from_value(from_cbor_value(*value)?)?),
+            Value::Tag(tag, _) => bail!("Invalid CBOR tag - expected {}, found {tag}", Self::CBOR_TAG),
+            _ => bail!("Invalid CBOR data item - expected a tag"),
+        }
+    }
+}
+
+impl<N: Network> CborEncoding for Transaction<N> {
+    const CBOR_TAG: u64 = CBOR_TAG_TRANSACTION;
+}
+
+impl<N: Network> CborEncoding for Block<N> {
+    const CBOR_TAG: u64 = CBOR_TAG_BLOCK;
+}
+
+/// Returns the CBOR value of the given JSON value, with the keys of every map sorted by their encoded bytes.
+fn to_cbor_value(value: serde_json::Value) -> Result<Value> {
+    use serde_json::Value as Json;
+
+    Ok(match value {
+        Json::Null => Value::Null,
+        Json::Bool(boolean) => Value::Bool(boolean),
+        Json::Number(number) => match (number.as_u64(), number.as_i64()) {
+            (Some(number), _) => Value::Integer(number.into()),
+            (None, Some(number)) => Value::Integer(number.into()),
+            (None, None) => bail!("Failed to encode as CBOR: floats have no canonical encoding"),
+        },
+        Json::String(string) => Value::Text(string),
+        Json::Array(values) => Value::Array(values.into_iter().map(to_cbor_value).collect::<Result<_>>()?),
+        Json::Object(entries) => {
+            let mut entries = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = Value::Text(key);
+                    let mut encoded_key = Vec::new();
+                    ciborium::ser::into_writer(&key, &mut encoded_key)
+                        .map_err(|e| anyhow!("Failed to encode as CBOR: {e}"))?;
+                    Ok((encoded_key, (key, to_cbor_value(value)?)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Map(entries.into_iter().map(|(_, entry)| entry).collect())
+        }
+    })
+}
+
+/// Returns the JSON value of the given CBOR value.
+fn from_cbor_value(value: Value) -> Result<serde_json::Value> {
+    use serde_json::Value as Json;
+
+    Ok(match value {
+        Value::Null => Json::Null,
+        Value::Bool(boolean) => Json::Bool(boolean),
+        Value::Integer(integer) => match (u64::try_from(integer), i64::try_from(integer)) {
+            (Ok(number), _) => Json::from(number),
+            (_, Ok(number)) => Json::from(number),
+            _ => bail!("Invalid CBOR integer - {}", i128::from(integer)),
+        },
+        Value::Text(string) => Json::String(string),
+        Value::Array(values) => Json::Array(values.into_iter().map(from_cbor_value).collect::<Result<_>>()?),
+        Value::Map(entries) => {
+            let mut map = serde_json::Map::with_capacity(entries.len());
+            for (key, value) in entries {
+                let key = match key {
+                    Value::Text(key) => key,
+                    _ => bail!("Invalid CBOR map key - expected a text string"),
+                };
+                ensure!(map.insert(key, from_cbor_value(value)?).is_none(), "Invalid CBOR map - duplicate key");
+            }
+            Json::Object(map)
+        }
+        _ => bail!("Invalid CBOR data item - unsupported major type"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_vectors::TestVectors, Data, Message, MessageCodec, UnconfirmedTransaction};
+    use snarkvm::prelude::{FromBytes, Testnet3, ToBytes};
+
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    type CurrentNetwork = Testnet3;
+
+    /// The canonical CBOR encoding of the genesis block.
+    const GOLDEN_BLOCK: &[u8] = include_bytes!("../../fixtures/genesis_block.cbor");
+    /// The canonical CBOR encoding of the first transaction in the genesis block.
+    const GOLDEN_TRANSACTION: &[u8] = include_bytes!("../../fixtures/genesis_transaction.cbor");
+
+    /// Returns the genesis block, from the test vectors.
+    fn sample_block() -> Block<CurrentNetwork> {
+        TestVectors::load().unwrap().blocks[0].block().unwrap()
+    }
+
+    /// Returns the first transaction in the genesis block, from the test vectors.
+    fn sample_transaction() -> Transaction<CurrentNetwork> {
+        TestVectors::load().unwrap().transactions[0].transaction().unwrap()
+    }
+
+    /// Encodes and decodes the given message with the framed binary codec.
+    fn frame_roundtrip(message: Message<CurrentNetwork>) -> Message<CurrentNetwork> {
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        let mut bytes = BytesMut::new();
+        codec.encode(message, &mut bytes).unwrap();
+        codec.decode(&mut bytes).unwrap().unwrap()
+    }
+
+    #[test]
+    fn golden_block() {
+        let block = sample_block();
+        assert_eq!(block.to_cbor().unwrap(), GOLDEN_BLOCK);
+        assert_eq!(Block::<CurrentNetwork>::from_cbor(GOLDEN_BLOCK).unwrap(), block);
+    }
+
+    #[test]
+    fn golden_transaction() {
+        let transaction = sample_transaction();
+        assert_eq!(transaction.to_cbor().unwrap(), GOLDEN_TRANSACTION);
+        assert_eq!(Transaction::<CurrentNetwork>::from_cbor(GOLDEN_TRANSACTION).unwrap(), transaction);
+    }
+
+    #[test]
+    fn cross_format_block() {
+        // CBOR -> struct -> binary -> struct.
+        let block = Block::<CurrentNetwork>::from_cbor(GOLDEN_BLOCK).unwrap();
+        let candidate = Block::<CurrentNetwork>::from_bytes_le(&block.to_bytes_le().unwrap()).unwrap();
+        assert_eq!(candidate, sample_block());
+        assert_eq!(candidate.to_bytes_le().unwrap(), CurrentNetwork::genesis_bytes());
+        assert_eq!(candidate.to_cbor().unwrap(), GOLDEN_BLOCK);
+    }
+
+    #[test]
+    fn cross_format_transaction() {
+        // CBOR -> struct -> framed binary -> struct.
+        let transaction = Transaction::<CurrentNetwork>::from_cbor(GOLDEN_TRANSACTION).unwrap();
+        let message =
+            Message::UnconfirmedTransaction(UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction)));
+        let candidate = match frame_roundtrip(message) {
+            Message::UnconfirmedTransaction(message) => message.transaction.deserialize_blocking().unwrap(),
+            message => panic!("Unexpected message: {}", message.name()),
+        };
+        assert_eq!(candidate, sample_transaction());
+        assert_eq!(candidate.to_cbor().unwrap(), GOLDEN_TRANSACTION);
+    }
+
+    #[test]
+    fn canonical_map_keys() {
+        // Ensure every map in the encoding has its keys sorted by their encoded bytes.
+        fn check_sorted(value: &Value) {
+            match value {
+                Value::Map(entries) => {
+                    let keys: Vec<_> = entries
+                        .iter()
+                        .map(|(key, _)| {
+                            let mut bytes = Vec::new();
+                            ciborium::ser::into_writer(key, &mut bytes).unwrap();
+                            bytes
+                        })
+                        .collect();
+                    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+                    entries.iter().for_each(|(_, value)| check_sorted(value));
+                }
+                Value::Array(values) => values.iter().for_each(check_sorted),
+                Value::Tag(_, value) => check_sorted(value),
+                _ => (),
+            }
+        }
+        let value: Value = ciborium::de::from_reader(GOLDEN_BLOCK).unwrap();
+        assert!(matches!(value, Value::Tag(CBOR_TAG_BLOCK, _)));
+        check_sorted(&value);
+    }
+
+    #[test]
+    fn mismatched_tag_is_rejected() {
+        assert!(Block::<CurrentNetwork>::from_cbor(GOLDEN_TRANSACTION).is_err());
+        assert!(Transaction::<CurrentNetwork>::from_cbor(GOLDEN_BLOCK).is_err());
+        assert!(Block::<CurrentNetwork>::from_cbor(&GOLDEN_BLOCK[..GOLDEN_BLOCK.len() - 1]).is_err());
+    }
+}
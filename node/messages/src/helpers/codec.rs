@@ -14,22 +14,28 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+use super::compression::{compress, decompress, Compression, CompressionStats};
 use crate::Message;
 use snarkvm::prelude::Network;
 
 use ::bytes::{BufMut, BytesMut};
 use core::marker::PhantomData;
+use std::sync::Arc;
 use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
 
 /// The maximum size of a message that can be transmitted during the handshake.
-const MAXIMUM_HANDSHAKE_MESSAGE_SIZE: usize = 1024 * 1024; // 1 MiB
+pub(crate) const MAXIMUM_HANDSHAKE_MESSAGE_SIZE: usize = 1024 * 1024; // 1 MiB
 
 /// The maximum size of a message that can be transmitted in the network.
-const MAXIMUM_MESSAGE_SIZE: usize = 128 * 1024 * 1024; // 128 MiB
+pub(crate) const MAXIMUM_MESSAGE_SIZE: usize = 128 * 1024 * 1024; // 128 MiB
 
 /// The codec used to decode and encode network `Message`s.
 pub struct MessageCodec<N: Network> {
     codec: LengthDelimitedCodec,
+    /// The compression of the messages, which is only enabled if both peers advertise it.
+    compression: Compression,
+    /// The number of bytes of the compressed messages, before and after their compression.
+    compression_stats: Arc<CompressionStats>,
     _phantom: PhantomData<N>,
 }
 
@@ -38,6 +44,13 @@ impl<N: Network> MessageCodec<N> {
     pub fn update_max_message_len(&mut self) {
         self.codec = LengthDelimitedCodec::builder().max_frame_length(MAXIMUM_MESSAGE_SIZE).little_endian().new_codec();
     }
+
+    /// Enables the given compression of the messages, which adds a header to every message,
+    /// recording the compressed messages in the given statistics. Both peers must enable it.
+    pub fn enable_compression(&mut self, compression: Compression, stats: Arc<CompressionStats>) {
+        self.compression = compression;
+        self.compression_stats = stats;
+    }
 }
 
 impl<N: Network> Default for MessageCodec<N> {
@@ -47,6 +60,8 @@ impl<N: Network> Default for MessageCodec<N> {
                 .max_frame_length(MAXIMUM_HANDSHAKE_MESSAGE_SIZE)
                 .little_endian()
                 .new_codec(),
+            compression: Default::default(),
+            compression_stats: Default::default(),
             _phantom: Default::default(),
         }
     }
@@ -62,9 +77,16 @@ impl<N: Network> Encoder<Message<N>> for MessageCodec<N> {
             // This error should never happen, the conversion is for greater compatibility.
             .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "serialization error"))?;
 
-        let serialized_message = dst.split_to(dst.len()).freeze();
+        let serialized_message = dst.split_to(dst.len());
 
-        self.codec.encode(serialized_message, dst)
+        match self.compression {
+            Compression::None => self.codec.encode(serialized_message.freeze(), dst),
+            Compression::Zstd(level) => {
+                let mut frame = BytesMut::new();
+                compress(&serialized_message, level, &self.compression_stats, &mut frame)?;
+                self.codec.encode(frame.freeze(), dst)
+            }
+        }
     }
 }
 
@@ -78,6 +100,11 @@ impl<N: Network> Decoder for MessageCodec<N> {
             Some(bytes) => bytes,
             None => return Ok(None),
         };
+        // Decompress the message, if the compression is enabled.
+        let bytes = match self.compression {
+            Compression::None => bytes,
+            Compression::Zstd(..) => decompress(bytes, self.codec.max_frame_length(), &self.compression_stats)?,
+        };
 
         // Convert the bytes to a message, or fail if it is not valid.
         match Message::deserialize(bytes) {
@@ -0,0 +1,238 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use ::bytes::{Buf, BufMut, BytesMut};
+use std::{
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The minimum size of a serialized message for it to be compressed, as smaller messages barely shrink.
+pub const COMPRESSION_THRESHOLD: usize = 1024; // 1 KiB
+/// The zstd level used when none is specified.
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+/// The maximum zstd level, as the higher levels use far more memory for little gain.
+pub const MAX_ZSTD_LEVEL: i32 = 19;
+
+/// The size of the header of a message on a compressed connection, consisting of the compression flag
+/// and the uncompressed length of the message.
+const HEADER_LEN: usize = 1 + 4;
+/// The flag of a message that is sent as is.
+const FLAG_UNCOMPRESSED: u8 = 0;
+/// The flag of a message that is compressed with zstd.
+const FLAG_ZSTD: u8 = 1;
+
+/// The compression of the messages sent to the peers that advertise the `COMPRESSION` capability.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// The messages are never compressed, and the capability is not advertised.
+    #[default]
+    None,
+    /// The messages above `COMPRESSION_THRESHOLD` are compressed with zstd, at the given level.
+    Zstd(i32),
+}
+
+impl Compression {
+    /// Returns `true` if the messages are compressed.
+    pub const fn is_enabled(&self) -> bool {
+        matches!(self, Self::Zstd(..))
+    }
+}
+
+impl core::str::FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(compression: &str) -> Result<Self, Self::Err> {
+        match compression.split_once(':') {
+            None if compression == "none" => Ok(Self::None),
+            None if compression == "zstd" => Ok(Self::Zstd(DEFAULT_ZSTD_LEVEL)),
+            Some(("zstd", level)) => match level.parse::<i32>() {
+                Ok(level) if (1..=MAX_ZSTD_LEVEL).contains(&level) => Ok(Self::Zstd(level)),
+                _ => anyhow::bail!("Invalid zstd level '{level}' (expected 1 to {MAX_ZSTD_LEVEL})"),
+            },
+            _ => anyhow::bail!("Invalid compression '{compression}' (expected 'none', 'zstd', or 'zstd:{{level}}')"),
+        }
+    }
+}
+
+impl core::fmt::Display for Compression {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Zstd(level) => write!(f, "zstd:{level}"),
+        }
+    }
+}
+
+/// The number of bytes of the compressed messages of a connection, before and after their compression.
+/// The messages that are sent as is are not counted.
+#[derive(Debug, Default)]
+pub struct CompressionStats {
+    /// The number of bytes of the compressed messages sent, before their compression.
+    uncompressed_bytes_sent: AtomicU64,
+    /// The number of bytes of the compressed messages sent, after their compression.
+    compressed_bytes_sent: AtomicU64,
+    /// The number of bytes of the compressed messages received, after their decompression.
+    uncompressed_bytes_received: AtomicU64,
+    /// The number of bytes of the compressed messages received, before their decompression.
+    compressed_bytes_received: AtomicU64,
+}
+
+impl CompressionStats {
+    /// Returns the number of bytes (uncompressed, compressed) of the compressed messages sent.
+    pub fn sent(&self) -> (u64, u64) {
+        (self.uncompressed_bytes_sent.load(Ordering::Relaxed), self.compressed_bytes_sent.load(Ordering::Relaxed))
+    }
+
+    /// Returns the number of bytes (uncompressed, compressed) of the compressed messages received.
+    pub fn received(&self) -> (u64, u64) {
+        (
+            self.uncompressed_bytes_received.load(Ordering::Relaxed),
+            self.compressed_bytes_received.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Writes the given serialized message to `dst`, prefixed with its header, and compresses it if it is large enough
+/// and it shrinks.
+pub(crate) fn compress(message: &[u8], level: i32, stats: &CompressionStats, dst: &mut BytesMut) -> io::Result<()> {
+    let uncompressed_len = u32::try_from(message.len()).map_err(|_| io::ErrorKind::InvalidInput)?;
+
+    if message.len() >= COMPRESSION_THRESHOLD {
+        let compressed = zstd::bulk::compress(message, level)?;
+        if compressed.len() < message.len() {
+            dst.reserve(HEADER_LEN + compressed.len());
+            dst.put_u8(FLAG_ZSTD);
+            dst.put_u32_le(uncompressed_len);
+            dst.extend_from_slice(&compressed);
+
+            stats.uncompressed_bytes_sent.fetch_add(message.len() as u64, Ordering::Relaxed);
+            stats.compressed_bytes_sent.fetch_add(compressed.len() as u64, Ordering::Relaxed);
+            return Ok(());
+        }
+    }
+
+    dst.reserve(HEADER_LEN + message.len());
+    dst.put_u8(FLAG_UNCOMPRESSED);
+    dst.put_u32_le(uncompressed_len);
+    dst.extend_from_slice(message);
+    Ok(())
+}
+
+/// Returns the serialized message in the given frame, decompressing it if needed. The uncompressed length
+/// in the header is checked against the given maximum before any decompression, and the decompression stops
+/// as soon as it exceeds it, so that a small frame cannot expand into an oversized message.
+pub(crate) fn decompress(mut frame: BytesMut, max_len: usize, stats: &CompressionStats) -> io::Result<BytesMut> {
+    if frame.len() < HEADER_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "missing compression header"));
+    }
+    let flag = frame.get_u8();
+    let uncompressed_len = frame.get_u32_le() as usize;
+    if uncompressed_len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the uncompressed message is too large ({uncompressed_len} > {max_len} bytes)"),
+        ));
+    }
+
+    match flag {
+        FLAG_UNCOMPRESSED if frame.len() == uncompressed_len => Ok(frame),
+        FLAG_ZSTD => {
+            // The decompression fails if the message exceeds the declared length.
+            let message = zstd::bulk::decompress(&frame, uncompressed_len)?;
+            if message.len() != uncompressed_len {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "mismatched uncompressed length"));
+            }
+
+            stats.uncompressed_bytes_received.fetch_add(message.len() as u64, Ordering::Relaxed);
+            stats.compressed_bytes_received.fetch_add(frame.len() as u64, Ordering::Relaxed);
+            Ok(BytesMut::from(&message[..]))
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid compression header")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::str::FromStr;
+
+    #[test]
+    fn test_compression_from_str() {
+        assert_eq!(Compression::from_str("none").unwrap(), Compression::None);
+        assert_eq!(Compression::from_str("zstd").unwrap(), Compression::Zstd(DEFAULT_ZSTD_LEVEL));
+        assert_eq!(Compression::from_str("zstd:9").unwrap(), Compression::Zstd(9));
+        for compression in [Compression::None, Compression::Zstd(1), Compression::Zstd(MAX_ZSTD_LEVEL)] {
+            assert_eq!(Compression::from_str(&compression.to_string()).unwrap(), compression);
+        }
+        for compression in ["zstd:0", "zstd:20", "zstd:fast", "gzip", "zstd:"] {
+            assert!(Compression::from_str(compression).is_err(), "{compression}");
+        }
+    }
+
+    #[test]
+    fn test_small_messages_are_not_compressed() {
+        let stats = CompressionStats::default();
+        let message = vec![7u8; COMPRESSION_THRESHOLD - 1];
+
+        let mut frame = BytesMut::new();
+        compress(&message, DEFAULT_ZSTD_LEVEL, &stats, &mut frame).unwrap();
+        assert_eq!(frame[0], FLAG_UNCOMPRESSED);
+        assert_eq!(frame.len(), HEADER_LEN + message.len());
+        assert_eq!(decompress(frame, usize::MAX, &stats).unwrap(), message);
+        assert_eq!((stats.sent(), stats.received()), ((0, 0), (0, 0)));
+    }
+
+    #[test]
+    fn test_large_messages_are_compressed() {
+        let stats = CompressionStats::default();
+        let message = vec![7u8; 64 * COMPRESSION_THRESHOLD];
+
+        let mut frame = BytesMut::new();
+        compress(&message, DEFAULT_ZSTD_LEVEL, &stats, &mut frame).unwrap();
+        assert_eq!(frame[0], FLAG_ZSTD);
+        let compressed_len = (frame.len() - HEADER_LEN) as u64;
+        assert!(compressed_len < message.len() as u64);
+        assert_eq!(decompress(frame, message.len(), &stats).unwrap(), message);
+        assert_eq!(stats.sent(), (message.len() as u64, compressed_len));
+        assert_eq!(stats.received(), (message.len() as u64, compressed_len));
+    }
+
+    #[test]
+    fn test_decompression_size_cap() {
+        let stats = CompressionStats::default();
+        let message = vec![0u8; 1024 * 1024];
+        let mut frame = BytesMut::new();
+        compress(&message, DEFAULT_ZSTD_LEVEL, &stats, &mut frame).unwrap();
+        // Ensure the bomb is small on the wire.
+        assert!(frame.len() < 1024);
+
+        // Ensure a declared length above the maximum is rejected before any decompression.
+        assert!(decompress(frame.clone(), message.len() - 1, &stats).is_err());
+
+        // Ensure a message that expands beyond its declared length is rejected.
+        let mut understated = frame.clone();
+        understated[1..HEADER_LEN].copy_from_slice(&1024u32.to_le_bytes());
+        assert!(decompress(understated, message.len(), &stats).is_err());
+
+        // Ensure a message that is shorter than its declared length is rejected.
+        let mut overstated = frame;
+        overstated[1..HEADER_LEN].copy_from_slice(&(message.len() as u32 + 1).to_le_bytes());
+        assert!(decompress(overstated, 2 * message.len(), &stats).is_err());
+        assert_eq!(stats.received(), (0, 0));
+    }
+}
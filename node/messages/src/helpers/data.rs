@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::DecodeMode;
 use snarkvm::prelude::{FromBytes, ToBytes};
 
 use ::bytes::Bytes;
@@ -23,6 +24,8 @@ use tokio::task;
 
 /// This object enables deferred deserialization / ahead-of-time serialization for objects that
 /// take a while to deserialize / serialize, in order to allow these operations to be non-blocking.
+///
+/// A buffer is deserialized in strict mode, as it is received from the network, so any trailing bytes are rejected.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Data<T: FromBytes + ToBytes + Send + 'static> {
     Object(T),
@@ -33,7 +36,7 @@ impl<T: FromBytes + ToBytes + Send + 'static> Data<T> {
     pub async fn deserialize(self) -> Result<T> {
         match self {
             Self::Object(x) => Ok(x),
-            Self::Buffer(bytes) => match task::spawn_blocking(move || T::from_bytes_le(&bytes)).await {
+            Self::Buffer(bytes) => match task::spawn_blocking(move || DecodeMode::Strict.decode(&bytes)).await {
                 Ok(x) => x,
                 Err(err) => Err(err.into()),
             },
@@ -43,7 +46,7 @@ impl<T: FromBytes + ToBytes + Send + 'static> Data<T> {
     pub fn deserialize_blocking(self) -> Result<T> {
         match self {
             Self::Object(x) => Ok(x),
-            Self::Buffer(bytes) => T::from_bytes_le(&bytes),
+            Self::Buffer(bytes) => DecodeMode::Strict.decode(&bytes),
         }
     }
 
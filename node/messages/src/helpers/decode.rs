@@ -0,0 +1,63 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::prelude::FromBytes;
+
+use anyhow::Result;
+use core::fmt;
+
+/// The mode in which an object is decoded from its bytes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// The object must consume the entire input, as for the payloads received from the network or the user.
+    /// Otherwise, two byte strings would decode to the same object, while hashing differently as messages.
+    #[default]
+    Strict,
+    /// The bytes following the object are ignored, as for the objects written by earlier versions.
+    Lenient,
+}
+
+/// The error of an object that is followed by bytes it did not consume, in strict mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TrailingBytes {
+    /// The offset of the first byte that was not consumed.
+    pub offset: usize,
+    /// The number of bytes that were not consumed.
+    pub remaining: usize,
+}
+
+impl fmt::Display for TrailingBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Found {} trailing bytes at offset {}", self.remaining, self.offset)
+    }
+}
+
+impl std::error::Error for TrailingBytes {}
+
+impl DecodeMode {
+    /// Decodes an object from the given bytes, in this mode.
+    pub fn decode<T: FromBytes>(self, bytes: &[u8]) -> Result<T> {
+        let mut reader = bytes;
+        let object = T::read_le(&mut reader)?;
+        // Ensure the entire input was consumed, in strict mode.
+        match (self, reader.is_empty()) {
+            (Self::Strict, false) => {
+                Err(TrailingBytes { offset: bytes.len() - reader.len(), remaining: reader.len() }.into())
+            }
+            _ => Ok(object),
+        }
+    }
+}
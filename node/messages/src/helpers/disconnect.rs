@@ -49,4 +49,19 @@ pub enum DisconnectReason {
     YouNeedToSyncFirst,
     /// The peer's listening port is closed.
     YourPortIsClosed(u16),
+    /// The peer is on a different network, judging by its network ID.
+    InvalidNetworkId,
+    /// The peer is on a different chain, judging by its genesis block hash.
+    InvalidGenesisHash,
+    /// The peer's static public key does not match the key pinned for it.
+    PinnedKeyMismatch,
+    /// The peer's node ID does not match its static public key.
+    NodeIdMismatch,
+}
+
+impl DisconnectReason {
+    /// Returns `true` if the peer was dropped for being on a different network or chain.
+    pub const fn is_chain_mismatch(&self) -> bool {
+        matches!(self, Self::InvalidNetworkId | Self::InvalidGenesisHash)
+    }
 }
@@ -0,0 +1,266 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkvm::prelude::{
+    error,
+    Address,
+    Block,
+    CryptoRng,
+    Field,
+    FromBytes,
+    Header,
+    Network,
+    PrivateKey,
+    Rng,
+    Signature,
+    ToBits,
+    ToBytes,
+    Transaction,
+    Transactions,
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Result as IoResult, Write};
+
+/// The number of transactions and the total fees of a block, which are committed along with its header
+/// from the activation of the `header_commitments` rule on, so that the light clients and the sync pool
+/// can reason about a block before its body is downloaded.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HeaderCommitments {
+    /// The number of transactions in the block.
+    pub num_transactions: u32,
+    /// The total fees of the transactions in the block (in microcredits).
+    pub total_fees: u64,
+}
+
+impl HeaderCommitments {
+    /// The size (in bytes) of the serialized commitments.
+    pub const SIZE_IN_BYTES: usize = 4 + 8;
+
+    /// Initializes the commitments of the given transactions.
+    pub fn from_transactions<'a, N: Network>(
+        transactions: impl IntoIterator<Item = &'a Transaction<N>>,
+    ) -> Result<Self> {
+        let (mut num_transactions, mut total_fees) = (0u32, 0u64);
+        for transaction in transactions {
+            num_transactions = num_transactions
+                .checked_add(1)
+                .ok_or_else(|| anyhow!("The number of transactions of the block overflows a u32"))?;
+            total_fees = total_fees
+                .checked_add(*transaction.fee()?)
+                .ok_or_else(|| anyhow!("The fees of the block overflow the maximum amount of microcredits"))?;
+        }
+        Ok(Self { num_transactions, total_fees })
+    }
+
+    /// Initializes the commitments of the body of the given block.
+    pub fn from_block<N: Network>(block: &Block<N>) -> Result<Self> {
+        Self::from_transactions(block.transactions().values())
+    }
+
+    /// Checks the commitments are within the consensus limits, which does not require the body of the block.
+    pub fn check_plausibility<N: Network>(&self) -> Result<()> {
+        // Ensure the number of transactions is within the allowed range.
+        ensure!(self.num_transactions > 0, "The header commits to an empty transactions list");
+        ensure!(
+            self.num_transactions as usize <= Transactions::<N>::MAX_TRANSACTIONS,
+            "The header commits to {} transactions (maximum is {})",
+            self.num_transactions,
+            Transactions::<N>::MAX_TRANSACTIONS
+        );
+        // Ensure the fees do not exceed the supply of microcredits.
+        ensure!(
+            self.total_fees <= N::STARTING_SUPPLY,
+            "The header commits to {} microcredits of fees (maximum is {})",
+            self.total_fees,
+            N::STARTING_SUPPLY
+        );
+        Ok(())
+    }
+
+    /// Checks the commitments match the body of the given block.
+    pub fn check_block<N: Network>(&self, block: &Block<N>) -> Result<()> {
+        let body = Self::from_block(block)?;
+        if body.num_transactions != self.num_transactions {
+            bail!(
+                "Block {} has {} transactions, but its header commits to {}",
+                block.height(),
+                body.num_transactions,
+                self.num_transactions
+            )
+        }
+        if body.total_fees != self.total_fees {
+            bail!(
+                "Block {} pays {} microcredits of fees, but its header commits to {}",
+                block.height(),
+                body.total_fees,
+                self.total_fees
+            )
+        }
+        Ok(())
+    }
+}
+
+impl ToBytes for HeaderCommitments {
+    fn write_le<W: Write>(&self, mut writer: W) -> IoResult<()> {
+        self.num_transactions.write_le(&mut writer)?;
+        self.total_fees.write_le(&mut writer)
+    }
+}
+
+impl FromBytes for HeaderCommitments {
+    fn read_le<R: Read>(mut reader: R) -> IoResult<Self> {
+        let num_transactions = u32::read_le(&mut reader)?;
+        let total_fees = u64::read_le(&mut reader)?;
+        Ok(Self { num_transactions, total_fees })
+    }
+}
+
+/// A block header, as it is relayed on the network, along with its commitments from the activation
+/// of the `header_commitments` rule on.
+///
+/// The legacy layout is the header alone, so that the headers of the blocks below the activation height
+/// are encoded as before. The commitments follow the header from the activation height on, along with the
+/// signature of the root of the committed header by the node that computed them from the body of the block,
+/// as the header itself does not bind them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommittedHeader<N: Network> {
+    /// The block header.
+    pub header: Header<N>,
+    /// The commitments of the block, which are absent below the activation height.
+    pub commitments: Option<HeaderCommitments>,
+    /// The signature of the root of the committed header, which is present if and only if the commitments are.
+    pub signature: Option<Signature<N>>,
+}
+
+impl<N: Network> CommittedHeader<N> {
+    /// Initializes the committed header of the given block, with the layout of the era of its height.
+    pub fn from_block(block: &Block<N>, activation_height: Option<u32>) -> Result<Self> {
+        let commitments = match Self::is_committed_at(block.height(), activation_height) {
+            true => Some(HeaderCommitments::from_block(block)?),
+            false => None,
+        };
+        Ok(Self { header: *block.header(), commitments, signature: None })
+    }
+
+    /// Initializes a committed header in the legacy layout.
+    pub const fn legacy(header: Header<N>) -> Self {
+        Self { header, commitments: None, signature: None }
+    }
+
+    /// Signs the root of the committed header with the given private key, if the header carries commitments.
+    /// The commitments must have been computed from the body of the block, as the signer vouches for them.
+    pub fn sign<R: Rng + CryptoRng>(mut self, private_key: &PrivateKey<N>, rng: &mut R) -> Result<Self> {
+        self.signature = match self.commitments {
+            Some(_) => Some(private_key.sign(&[self.to_root()?], rng)?),
+            None => None,
+        };
+        Ok(self)
+    }
+
+    /// Returns `true` if the headers at the given height carry their commitments.
+    pub fn is_committed_at(height: u32, activation_height: Option<u32>) -> bool {
+        activation_height.map_or(false, |activation_height| height >= activation_height)
+    }
+
+    /// Returns the height of the block.
+    pub fn height(&self) -> u32 {
+        self.header.height()
+    }
+
+    /// Checks the header has the layout of the era of its height, and that its commitments are plausible.
+    pub fn check_layout(&self, activation_height: Option<u32>) -> Result<()> {
+        let height = self.height();
+        match (Self::is_committed_at(height, activation_height), &self.commitments) {
+            (true, Some(commitments)) => commitments.check_plausibility::<N>(),
+            (true, None) => bail!("The header of block {height} is missing its commitments"),
+            (false, Some(_)) => bail!("The header of block {height} has commitments before their activation"),
+            (false, None) => Ok(()),
+        }
+    }
+
+    /// Checks the commitments of the header, if any, are signed by the given address, which is the address
+    /// of the peer that relayed the header. The headers in the legacy layout carry no signature.
+    pub fn check_signature(&self, signer: &Address<N>) -> Result<()> {
+        let height = self.height();
+        match (&self.commitments, &self.signature) {
+            (Some(_), Some(signature)) => {
+                ensure!(
+                    signature.verify(signer, &[self.to_root()?]),
+                    "The commitments of the header of block {height} are not signed by '{signer}'"
+                );
+                Ok(())
+            }
+            (Some(_), None) => bail!("The commitments of the header of block {height} are not signed"),
+            (None, Some(_)) => bail!("The header of block {height} is signed without commitments"),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Returns the root of the committed header, which is the root of the header in the legacy layout,
+    /// and the hash of the root of the header and the commitments otherwise.
+    pub fn to_root(&self) -> Result<Field<N>> {
+        let header_root = self.header.to_root()?;
+        match &self.commitments {
+            Some(commitments) => N::hash_bhp1024(
+                &[
+                    header_root.to_bits_le(),
+                    Field::<N>::from_u32(commitments.num_transactions).to_bits_le(),
+                    Field::<N>::from_u64(commitments.total_fees).to_bits_le(),
+                ]
+                .concat(),
+            ),
+            None => Ok(header_root),
+        }
+    }
+}
+
+impl<N: Network> ToBytes for CommittedHeader<N> {
+    fn write_le<W: Write>(&self, mut writer: W) -> IoResult<()> {
+        self.header.write_le(&mut writer)?;
+        match (&self.commitments, &self.signature) {
+            (Some(commitments), Some(signature)) => {
+                commitments.write_le(&mut writer)?;
+                signature.write_le(&mut writer)
+            }
+            (Some(_), None) => Err(error("Cannot write the header commitments without their signature")),
+            (None, Some(_)) => Err(error("Cannot write the signature of a header without commitments")),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl<N: Network> FromBytes for CommittedHeader<N> {
+    /// Reads the committed header, whose commitments and their signature are present if any bytes follow the header.
+    /// The layout is checked against the era of the height with `CommittedHeader::check_layout`,
+    /// and the signature against the relaying peer with `CommittedHeader::check_signature`.
+    fn read_le<R: Read>(mut reader: R) -> IoResult<Self> {
+        let header = Header::read_le(&mut reader)?;
+        let mut remaining = Vec::new();
+        reader.read_to_end(&mut remaining)?;
+        if remaining.is_empty() {
+            return Ok(Self::legacy(header));
+        }
+        let mut remaining = &remaining[..];
+        let commitments = HeaderCommitments::read_le(&mut remaining)?;
+        let signature = Signature::read_le(&mut remaining)?;
+        if !remaining.is_empty() {
+            return Err(error(format!("Found {} trailing bytes after the header commitments", remaining.len())));
+        }
+        Ok(Self { header, commitments: Some(commitments), signature: Some(signature) })
+    }
+}
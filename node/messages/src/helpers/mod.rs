@@ -17,17 +17,40 @@
 pub mod block_locators;
 pub use block_locators::*;
 
+#[cfg(feature = "cbor")]
+mod cbor;
+#[cfg(feature = "cbor")]
+pub use cbor::*;
+
 mod codec;
 pub use codec::MessageCodec;
 
-#[allow(unused)]
+mod compression;
+pub use compression::{Compression, CompressionStats, COMPRESSION_THRESHOLD, DEFAULT_ZSTD_LEVEL, MAX_ZSTD_LEVEL};
+
 mod noise_codec;
+pub use noise_codec::*;
 
 mod data;
 pub use data::Data;
 
+mod decode;
+pub use decode::*;
+
 mod disconnect;
 pub use disconnect::DisconnectReason;
 
+mod header_commitments;
+pub use header_commitments::*;
+
+mod node_id;
+pub use node_id::NodeId;
+
+mod node_role;
+pub use node_role::*;
+
 mod node_type;
 pub use node_type::*;
+
+mod onion_addr;
+pub use onion_addr::OnionAddr;
@@ -0,0 +1,85 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The number of bytes in a node ID.
+const NODE_ID_SIZE: usize = 20;
+
+/// The identity of a node, which is derived from its static public key, and so is stable across its addresses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId([u8; NODE_ID_SIZE]);
+
+impl NodeId {
+    /// Returns the node ID of the given static public key, which is the truncated SHA-256 digest of the key.
+    pub fn from_public_key(public_key: &[u8]) -> Self {
+        let digest = Sha256::digest(public_key);
+        let mut node_id = [0u8; NODE_ID_SIZE];
+        node_id.copy_from_slice(&digest[..NODE_ID_SIZE]);
+        Self(node_id)
+    }
+}
+
+impl core::str::FromStr for NodeId {
+    type Err = anyhow::Error;
+
+    /// Parses a node ID of the form `<40 hexadecimal characters>`.
+    fn from_str(node_id: &str) -> Result<Self, Self::Err> {
+        if node_id.len() != 2 * NODE_ID_SIZE {
+            anyhow::bail!("'{node_id}' is not a node ID of {NODE_ID_SIZE} bytes");
+        }
+        if !node_id.bytes().all(|character| character.is_ascii_hexdigit()) {
+            anyhow::bail!("Invalid characters in '{node_id}'");
+        }
+        let mut bytes = [0u8; NODE_ID_SIZE];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&node_id[2 * index..2 * index + 2], 16)?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl core::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::str::FromStr;
+
+    #[test]
+    fn test_node_id() {
+        let node_id = NodeId::from_public_key(&[7u8; 32]);
+        assert_eq!(NodeId::from_public_key(&[7u8; 32]), node_id);
+        assert_ne!(NodeId::from_public_key(&[8u8; 32]), node_id);
+
+        // Ensure the node ID round-trips through its string representation.
+        let string = node_id.to_string();
+        assert_eq!(string.len(), 40);
+        assert_eq!(NodeId::from_str(&string).unwrap(), node_id);
+        assert_eq!(NodeId::from_str(&string.to_uppercase()).unwrap(), node_id);
+
+        // Ensure the malformed node IDs are rejected.
+        assert!(NodeId::from_str(&string[..38]).is_err());
+        assert!(NodeId::from_str(&format!("{}zz", &string[..38])).is_err());
+        assert!(NodeId::from_str(&format!("{}+1", &string[..37])).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// The capabilities a node advertises to its peers in the handshake.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, Hash)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    /// The node serves every capability.
+    pub const ALL: Self = Self(Self::LISTENS.0 | Self::RELAYS.0 | Self::SERVES_BLOCKS.0);
+    /// The node serves the body of every block, as it did not prune any block.
+    /// Note that the capability is not part of `ALL`, as it depends on the blocks the node pruned.
+    pub const ARCHIVAL: Self = Self(1 << 4);
+    /// The node decompresses the messages it receives, so the large messages sent to it may be compressed.
+    /// Note that the capability is not part of `ALL`, as it depends on the compression the node is configured with.
+    pub const COMPRESSION: Self = Self(1 << 7);
+    /// The node accepts inbound connections on its listener port.
+    pub const LISTENS: Self = Self(1);
+    /// The node serves none of the optional capabilities.
+    pub const NONE: Self = Self(0);
+    /// The node dials onion addresses through its proxy, so it is sent the onion addresses of its peers.
+    /// Note that the capability is not part of `ALL`, as the peers that do not advertise it have no proxy.
+    pub const ONION_ADDRS: Self = Self(1 << 3);
+    /// The node fetches the unknown parents of the transactions it receives, so it is sent the extended announcements
+    /// of the transactions, with their parents. Note that the capability is not part of `ALL`, as it does not depend
+    /// on the role of the node.
+    pub const PARENT_TXS: Self = Self(1 << 5);
+    /// The node relays the unconfirmed transactions and solutions it receives.
+    pub const RELAYS: Self = Self(1 << 1);
+    /// The node serves blocks to the peers that request them.
+    pub const SERVES_BLOCKS: Self = Self(1 << 2);
+    /// The node serves the snapshot of its ledger to its trusted peers, in chunks. Note that the capability is not
+    /// part of `ALL`, as it depends on the blocks the node pruned, and on the snapshot streams it allows.
+    pub const SERVES_SNAPSHOTS: Self = Self(1 << 6);
+
+    /// Returns `true` if the node serves every capability in `other`.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the capabilities served by either `self` or `other`.
+    pub const fn union(&self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Returns the capabilities served by `self`, except those in `other`.
+    pub const fn without(&self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl core::fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let names = [
+            (Self::LISTENS, "listens"),
+            (Self::RELAYS, "relays"),
+            (Self::SERVES_BLOCKS, "serves-blocks"),
+            (Self::ONION_ADDRS, "onion-addrs"),
+            (Self::ARCHIVAL, "archival"),
+            (Self::PARENT_TXS, "parent-txs"),
+            (Self::SERVES_SNAPSHOTS, "serves-snapshots"),
+            (Self::COMPRESSION, "compression"),
+        ]
+        .into_iter()
+        .filter(|(capability, _)| self.contains(*capability))
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>();
+        match names.is_empty() {
+            true => write!(f, "none"),
+            false => write!(f, "{}", names.join(",")),
+        }
+    }
+}
+
+/// The role in which a node operates on the network, which determines the capabilities it serves.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeRole {
+    /// A full node listens for inbound connections, relays messages, and serves blocks.
+    #[default]
+    Full,
+    /// An outbound-only node does not listen for inbound connections, yet relays messages and serves blocks.
+    OutboundOnly,
+    /// A client node does not listen for inbound connections, relay messages, or serve blocks.
+    Client,
+}
+
+impl NodeRole {
+    /// Returns the capabilities served by the node role.
+    pub const fn capabilities(&self) -> Capabilities {
+        match self {
+            Self::Full => Capabilities::ALL,
+            Self::OutboundOnly => Capabilities::RELAYS.union(Capabilities::SERVES_BLOCKS),
+            Self::Client => Capabilities::NONE,
+        }
+    }
+
+    /// Returns `true` if the node role accepts inbound connections.
+    pub const fn listens(&self) -> bool {
+        self.capabilities().contains(Capabilities::LISTENS)
+    }
+}
+
+impl core::str::FromStr for NodeRole {
+    type Err = anyhow::Error;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "full" => Ok(Self::Full),
+            "outbound-only" => Ok(Self::OutboundOnly),
+            "client" => Ok(Self::Client),
+            _ => anyhow::bail!("Invalid node role '{role}' (expected 'full', 'outbound-only', or 'client')"),
+        }
+    }
+}
+
+impl core::fmt::Display for NodeRole {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Full => "full",
+            Self::OutboundOnly => "outbound-only",
+            Self::Client => "client",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::str::FromStr;
+
+    #[test]
+    fn test_node_role_capabilities() {
+        assert_eq!(NodeRole::default(), NodeRole::Full);
+        assert_eq!(NodeRole::Full.capabilities(), Capabilities::ALL);
+        assert!(NodeRole::Full.listens());
+
+        // Ensure an outbound-only node relays and serves blocks, without listening.
+        let capabilities = NodeRole::OutboundOnly.capabilities();
+        assert!(!capabilities.contains(Capabilities::LISTENS));
+        assert!(capabilities.contains(Capabilities::RELAYS));
+        assert!(capabilities.contains(Capabilities::SERVES_BLOCKS));
+        assert_eq!(capabilities.to_string(), "relays,serves-blocks");
+
+        // Ensure a client node serves no capability.
+        assert_eq!(NodeRole::Client.capabilities(), Capabilities::NONE);
+        assert_eq!(Capabilities::NONE.to_string(), "none");
+
+        // Ensure the onion addresses are advertised apart from every other capability.
+        assert!(!Capabilities::ALL.contains(Capabilities::ONION_ADDRS));
+        let capabilities = Capabilities::ALL.union(Capabilities::ONION_ADDRS).without(Capabilities::LISTENS);
+        assert_eq!(capabilities.to_string(), "relays,serves-blocks,onion-addrs");
+
+        // Ensure the archival capability is advertised apart from every other capability.
+        assert!(!Capabilities::ALL.contains(Capabilities::ARCHIVAL));
+        let capabilities = Capabilities::ALL.union(Capabilities::ARCHIVAL);
+        assert_eq!(capabilities.to_string(), "listens,relays,serves-blocks,archival");
+
+        // Ensure the parent fetching is advertised apart from every other capability.
+        assert!(!Capabilities::ALL.contains(Capabilities::PARENT_TXS));
+        assert_eq!(Capabilities::NONE.union(Capabilities::PARENT_TXS).to_string(), "parent-txs");
+
+        // Ensure the snapshot serving is advertised apart from every other capability.
+        assert!(!Capabilities::ALL.contains(Capabilities::SERVES_SNAPSHOTS));
+        let capabilities = Capabilities::ARCHIVAL.union(Capabilities::SERVES_SNAPSHOTS);
+        assert_eq!(capabilities.to_string(), "archival,serves-snapshots");
+
+        // Ensure the compression is advertised apart from every other capability.
+        assert!(!Capabilities::ALL.contains(Capabilities::COMPRESSION));
+        assert_eq!(Capabilities::RELAYS.union(Capabilities::COMPRESSION).to_string(), "relays,compression");
+    }
+
+    #[test]
+    fn test_node_role_from_str() {
+        for role in [NodeRole::Full, NodeRole::OutboundOnly, NodeRole::Client] {
+            assert_eq!(NodeRole::from_str(&role.to_string()).unwrap(), role);
+        }
+        assert!(NodeRole::from_str("listener").is_err());
+    }
+}
@@ -14,27 +14,42 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{io, sync::Arc};
+use super::codec::{MAXIMUM_HANDSHAKE_MESSAGE_SIZE, MAXIMUM_MESSAGE_SIZE};
+use crate::{Compression, CompressionStats, Message, MessageCodec};
+use snarkvm::prelude::Network;
 
-use bytes::{Buf, BufMut, Bytes, BytesMut};
-use kadmium::{codec::MessageCodec as KadmiumCodec, message::Message as KadmiumMessage};
+use ::bytes::{Buf, BufMut, Bytes, BytesMut};
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
-use snarkvm::prelude::Testnet3;
 use snow::{HandshakeState, StatelessTransportState};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
 
-use crate::{Message, MessageCodec};
-
-type CurrentNetwork = Testnet3;
+/// The Noise protocol used to encrypt the connections between peers.
+pub const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
 
 /// The maximum message size for noise messages. If the data to be encrypted exceeds it, it is chunked.
 const MAX_MESSAGE_LEN: usize = 65535;
+/// The size of the authentication tag appended to each encrypted chunk.
+const TAG_LEN: usize = 16;
+/// The size of the header of an encrypted message, consisting of the message type flag and the nonce.
+const HEADER_LEN: usize = 1 + 8;
+
+/// Returns the maximum size of the ciphertext for a plaintext of the given size.
+const fn max_ciphertext_len(plaintext_len: usize) -> usize {
+    let num_chunks = (plaintext_len + MAX_MESSAGE_LEN - TAG_LEN - 1) / (MAX_MESSAGE_LEN - TAG_LEN);
+    HEADER_LEN + plaintext_len + num_chunks * TAG_LEN
+}
 
 #[repr(u8)]
 pub enum MessageType {
     Bytes = 0,
     SnarkOS,
-    Kadmium,
 }
 
 impl TryFrom<u8> for MessageType {
@@ -44,25 +59,22 @@ impl TryFrom<u8> for MessageType {
         match value {
             0 => Ok(MessageType::Bytes),
             1 => Ok(MessageType::SnarkOS),
-            2 => Ok(MessageType::Kadmium),
             _ => Err(format!("u8 value: {value} doesn't correspond to a message variant")),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub enum MessageOrBytes {
+pub enum MessageOrBytes<N: Network> {
     Bytes(Bytes),
-    Message(Box<Message<CurrentNetwork>>),
-    KadmiumMessage(KadmiumMessage),
+    Message(Box<Message<N>>),
 }
 
-impl MessageOrBytes {
+impl<N: Network> MessageOrBytes<N> {
     fn message_type(&self) -> MessageType {
         match self {
             MessageOrBytes::Bytes(_) => MessageType::Bytes,
             MessageOrBytes::Message(_) => MessageType::SnarkOS,
-            MessageOrBytes::KadmiumMessage(_) => MessageType::Kadmium,
         }
     }
 }
@@ -70,10 +82,26 @@ impl MessageOrBytes {
 #[derive(Clone)]
 pub struct PostHandshakeState {
     state: Arc<StatelessTransportState>,
-    tx_nonce: u64,
+    /// The hash of the handshake, which is unique to the session.
+    handshake_hash: Arc<[u8]>,
+    /// The next nonce to encrypt with. It is shared by every codec of the connection, so that no nonce is reused.
+    tx_nonce: Arc<AtomicU64>,
+    /// The lowest nonce that is accepted for decryption, so that messages cannot be replayed.
     rx_nonce: u64,
 }
 
+impl PostHandshakeState {
+    /// Returns the static public key of the peer.
+    pub fn remote_static(&self) -> Option<&[u8]> {
+        self.state.get_remote_static()
+    }
+
+    /// Returns the hash of the handshake, which binds the session to both static keys.
+    pub fn handshake_hash(&self) -> &[u8] {
+        &self.handshake_hash
+    }
+}
+
 pub enum NoiseState {
     Handshake(Box<HandshakeState>),
     PostHandshake(PostHandshakeState),
@@ -89,45 +117,83 @@ impl Clone for NoiseState {
 }
 
 impl NoiseState {
+    /// Returns `true` if the handshake is finished.
+    pub fn is_handshake_finished(&self) -> bool {
+        match self {
+            Self::Handshake(noise_state) => noise_state.is_handshake_finished(),
+            Self::PostHandshake(..) => true,
+        }
+    }
+
     pub fn into_post_handshake_state(self) -> Self {
         if let Self::Handshake(noise_state) = self {
+            let handshake_hash = noise_state.get_handshake_hash().into();
             let noise_state = noise_state.into_stateless_transport_mode().expect("handshake isn't finished");
-            Self::PostHandshake(PostHandshakeState { state: Arc::new(noise_state), tx_nonce: 0, rx_nonce: 0 })
+            Self::PostHandshake(PostHandshakeState {
+                state: Arc::new(noise_state),
+                handshake_hash,
+                tx_nonce: Default::default(),
+                rx_nonce: 0,
+            })
         } else {
             panic!()
         }
     }
 }
 
-pub struct NoiseCodec {
+pub struct NoiseCodec<N: Network> {
     codec: LengthDelimitedCodec,
-    kadmium_codec: KadmiumCodec,
-    snarkos_codec: MessageCodec<CurrentNetwork>,
+    snarkos_codec: MessageCodec<N>,
     noise_state: NoiseState,
 }
 
-impl NoiseCodec {
+impl<N: Network> NoiseCodec<N> {
     pub fn new(noise_state: NoiseState) -> Self {
         Self {
-            codec: LengthDelimitedCodec::new(),
-            kadmium_codec: KadmiumCodec::new(),
+            codec: LengthDelimitedCodec::builder()
+                .max_frame_length(max_ciphertext_len(MAXIMUM_HANDSHAKE_MESSAGE_SIZE))
+                .new_codec(),
             snarkos_codec: MessageCodec::default(),
             noise_state,
         }
     }
+
+    /// Returns the state of the Noise protocol.
+    pub fn noise_state(&self) -> &NoiseState {
+        &self.noise_state
+    }
+
+    /// Switches the codec to transport mode, once the handshake is finished.
+    pub fn into_post_handshake(self) -> Self {
+        Self { noise_state: self.noise_state.into_post_handshake_state(), ..self }
+    }
+
+    /// Increases the maximum permitted message size post-handshake.
+    pub fn update_max_message_len(&mut self) {
+        // The length prefix of the inner frame is included in the plaintext.
+        self.codec.set_max_frame_length(max_ciphertext_len(MAXIMUM_MESSAGE_SIZE + 4));
+        self.snarkos_codec.update_max_message_len();
+    }
+
+    /// Enables the given compression of the messages, before their encryption.
+    pub fn enable_compression(&mut self, compression: Compression, stats: Arc<CompressionStats>) {
+        self.snarkos_codec.enable_compression(compression, stats);
+    }
 }
 
-impl Encoder<MessageOrBytes> for NoiseCodec {
+impl<N: Network> Encoder<MessageOrBytes<N>> for NoiseCodec<N> {
     type Error = io::Error;
 
-    fn encode(&mut self, message_or_bytes: MessageOrBytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+    fn encode(&mut self, message_or_bytes: MessageOrBytes<N>, dst: &mut BytesMut) -> Result<(), Self::Error> {
         let message_type = message_or_bytes.message_type();
 
         let ciphertext = match self.noise_state {
             NoiseState::Handshake(ref mut noise) => {
                 match message_or_bytes {
                     // Don't allow message sending before the noise handshake has completed.
-                    MessageOrBytes::Message(_) | MessageOrBytes::KadmiumMessage(_) => unimplemented!(),
+                    MessageOrBytes::Message(_) => {
+                        return Err(Self::Error::new(io::ErrorKind::InvalidInput, "the handshake isn't finished"));
+                    }
                     MessageOrBytes::Bytes(bytes) => {
                         let mut buffer = [0u8; MAX_MESSAGE_LEN + 1];
                         let len = noise
@@ -147,15 +213,19 @@ impl Encoder<MessageOrBytes> for NoiseCodec {
                 let mut bytes = BytesMut::new();
                 match message_or_bytes {
                     // Don't allow sending raw bytes after the noise handshake has completed.
-                    MessageOrBytes::Bytes(_) => unimplemented!(),
+                    MessageOrBytes::Bytes(_) => {
+                        return Err(Self::Error::new(io::ErrorKind::InvalidInput, "the handshake is finished"));
+                    }
                     MessageOrBytes::Message(message) => self.snarkos_codec.encode(*message, &mut bytes)?,
-                    MessageOrBytes::KadmiumMessage(message) => self.kadmium_codec.encode(message, &mut bytes)?,
                 }
 
                 // Chunk the payload if necessary.
-                let chunked_plaintext_msg: Vec<_> = bytes.chunks(MAX_MESSAGE_LEN - 16).collect();
+                let chunked_plaintext_msg: Vec<_> = bytes.chunks(MAX_MESSAGE_LEN - TAG_LEN).collect();
                 let num_chunks = chunked_plaintext_msg.len() as u64;
 
+                // Reserve a nonce for each chunk.
+                let tx_nonce = noise.tx_nonce.fetch_add(num_chunks, Ordering::SeqCst);
+
                 // Encrypt the resulting bytes with Noise.
                 let encrypted_chunks: Vec<io::Result<Vec<u8>>> = chunked_plaintext_msg
                     .into_par_iter()
@@ -165,7 +235,7 @@ impl Encoder<MessageOrBytes> for NoiseCodec {
 
                         let len = noise
                             .state
-                            .write_message(noise.tx_nonce + nonce_offset as u64, plaintext_chunk, &mut buffer)
+                            .write_message(tx_nonce + nonce_offset as u64, plaintext_chunk, &mut buffer)
                             .map_err(|e| Self::Error::new(io::ErrorKind::InvalidInput, e))?;
 
                         buffer.truncate(len);
@@ -177,13 +247,13 @@ impl Encoder<MessageOrBytes> for NoiseCodec {
                 let mut buffer = BytesMut::new();
                 // Set the message type flag.
                 buffer.put_u8(message_type as u8);
+                // Set the nonce of the first chunk.
+                buffer.put_u64_le(tx_nonce);
 
                 for chunk in encrypted_chunks {
                     buffer.extend_from_slice(&chunk?)
                 }
 
-                noise.tx_nonce += num_chunks;
-
                 buffer
             }
         };
@@ -193,13 +263,16 @@ impl Encoder<MessageOrBytes> for NoiseCodec {
     }
 }
 
-impl Decoder for NoiseCodec {
+impl<N: Network> Decoder for NoiseCodec<N> {
     type Error = io::Error;
-    type Item = MessageOrBytes;
+    type Item = MessageOrBytes<N>;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         // Decode the ciphertext with the length-delimited codec.
-        let (flag, bytes) = if let Some(mut bytes) = self.codec.decode(src)? {
+        let (flag, mut bytes) = if let Some(mut bytes) = self.codec.decode(src)? {
+            if bytes.is_empty() {
+                return Err(io::ErrorKind::InvalidData.into());
+            }
             let flag =
                 MessageType::try_from(bytes.get_u8()).map_err(|e| Self::Error::new(io::ErrorKind::InvalidData, e))?;
             (flag, bytes)
@@ -209,9 +282,9 @@ impl Decoder for NoiseCodec {
 
         let msg = match self.noise_state {
             NoiseState::Handshake(ref mut noise) => {
-                if let MessageType::SnarkOS | MessageType::Kadmium = flag {
-                    // Ignore any messages before the noise handshake has completed.
-                    return Ok(None);
+                // Reject any messages before the noise handshake has completed.
+                if let MessageType::SnarkOS = flag {
+                    return Err(Self::Error::new(io::ErrorKind::InvalidData, "the handshake isn't finished"));
                 }
 
                 // Decrypt the ciphertext in handshake mode.
@@ -222,9 +295,18 @@ impl Decoder for NoiseCodec {
             }
 
             NoiseState::PostHandshake(ref mut noise) => {
-                // Ignore raw bytes after the noise handshake has completed.
+                // Reject raw bytes after the noise handshake has completed.
                 if let MessageType::Bytes = flag {
-                    return Ok(None);
+                    return Err(Self::Error::new(io::ErrorKind::InvalidData, "the handshake is finished"));
+                }
+
+                // Retrieve the nonce of the first chunk, and ensure it was not used before.
+                if bytes.len() < 8 {
+                    return Err(io::ErrorKind::InvalidData.into());
+                }
+                let rx_nonce = bytes.get_u64_le();
+                if rx_nonce < noise.rx_nonce {
+                    return Err(Self::Error::new(io::ErrorKind::InvalidData, "the message nonce was already used"));
                 }
 
                 // Noise decryption.
@@ -240,7 +322,7 @@ impl Decoder for NoiseCodec {
                         // Decrypt the ciphertext in post-handshake mode.
                         let len = noise
                             .state
-                            .read_message(noise.rx_nonce + nonce_offset as u64, encrypted_chunk, &mut buffer)
+                            .read_message(rx_nonce + nonce_offset as u64, encrypted_chunk, &mut buffer)
                             .map_err(|_| io::ErrorKind::InvalidData)?;
 
                         buffer.truncate(len);
@@ -248,23 +330,18 @@ impl Decoder for NoiseCodec {
                     })
                     .collect();
 
-                noise.rx_nonce += num_chunks;
-
-                // Collect chunks into plaintext to be passed to the message codecs.
+                // Collect chunks into plaintext to be passed to the message codec.
                 let mut plaintext = BytesMut::new();
                 for chunk in decrypted_chunks {
                     plaintext.extend_from_slice(&chunk?);
                 }
 
-                // Decode with message codecs.
-                match flag {
-                    MessageType::SnarkOS => {
-                        self.snarkos_codec.decode(&mut plaintext)?.map(|msg| MessageOrBytes::Message(Box::new(msg)))
-                    }
-                    MessageType::Kadmium => {
-                        self.kadmium_codec.decode(&mut plaintext)?.map(MessageOrBytes::KadmiumMessage)
-                    }
-                    _ => unreachable!("bytes variant was handled as an early return"),
+                noise.rx_nonce = rx_nonce + num_chunks;
+
+                // Decode with the message codec, which must consume the entire plaintext.
+                match self.snarkos_codec.decode(&mut plaintext)? {
+                    Some(message) if plaintext.is_empty() => Some(MessageOrBytes::Message(Box::new(message))),
+                    _ => return Err(Self::Error::new(io::ErrorKind::InvalidData, "invalid message plaintext")),
                 }
             }
         };
@@ -273,13 +350,84 @@ impl Decoder for NoiseCodec {
     }
 }
 
+/// The codec used to decode and encode the `Message`s exchanged with a connected peer,
+/// which are encrypted unless the peer does not support transport encryption.
+pub enum PeerCodec<N: Network> {
+    Plaintext(MessageCodec<N>),
+    Encrypted(NoiseCodec<N>),
+}
+
+impl<N: Network> PeerCodec<N> {
+    /// Returns the post-handshake state of the Noise protocol, if the connection is encrypted.
+    pub fn post_handshake_state(&self) -> Option<&PostHandshakeState> {
+        match self {
+            Self::Encrypted(codec) => match codec.noise_state() {
+                NoiseState::PostHandshake(state) => Some(state),
+                NoiseState::Handshake(..) => None,
+            },
+            Self::Plaintext(..) => None,
+        }
+    }
+
+    /// Increases the maximum permitted message size post-handshake.
+    pub fn update_max_message_len(&mut self) {
+        match self {
+            Self::Plaintext(codec) => codec.update_max_message_len(),
+            Self::Encrypted(codec) => codec.update_max_message_len(),
+        }
+    }
+
+    /// Enables the given compression of the messages post-handshake, recording the compressed messages
+    /// in the given statistics. Both peers must enable it, so it is only enabled if both advertise it.
+    pub fn enable_compression(&mut self, compression: Compression, stats: Arc<CompressionStats>) {
+        match self {
+            Self::Plaintext(codec) => codec.enable_compression(compression, stats),
+            Self::Encrypted(codec) => codec.enable_compression(compression, stats),
+        }
+    }
+}
+
+impl<N: Network> Default for PeerCodec<N> {
+    fn default() -> Self {
+        Self::Plaintext(Default::default())
+    }
+}
+
+impl<N: Network> Encoder<Message<N>> for PeerCodec<N> {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: Message<N>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match self {
+            Self::Plaintext(codec) => codec.encode(message, dst),
+            Self::Encrypted(codec) => codec.encode(MessageOrBytes::Message(Box::new(message)), dst),
+        }
+    }
+}
+
+impl<N: Network> Decoder for PeerCodec<N> {
+    type Error = io::Error;
+    type Item = Message<N>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self {
+            Self::Plaintext(codec) => codec.decode(src),
+            Self::Encrypted(codec) => match codec.decode(src)? {
+                Some(MessageOrBytes::Message(message)) => Ok(Some(*message)),
+                Some(MessageOrBytes::Bytes(..)) => Err(io::ErrorKind::InvalidData.into()),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use snarkvm::prelude::{Address, Group, TestRng, Uniform};
+    use snarkvm::prelude::{Address, Group, TestRng, Testnet3, Uniform};
 
     use crate::{
         BlockRequest,
+        Capabilities,
         ChallengeRequest,
         Disconnect,
         DisconnectReason,
@@ -292,8 +440,10 @@ mod tests {
     };
     use snow::{params::NoiseParams, Builder};
 
-    fn handshake_xx() -> (NoiseCodec, NoiseCodec) {
-        let params: NoiseParams = "Noise_XX_25519_ChaChaPoly_BLAKE2s".parse().unwrap();
+    type CurrentNetwork = Testnet3;
+
+    fn handshake_xx() -> (NoiseCodec<CurrentNetwork>, NoiseCodec<CurrentNetwork>) {
+        let params: NoiseParams = NOISE_PARAMS.parse().unwrap();
 
         let initiator_builder = Builder::new(params.clone());
         let initiator_kp = initiator_builder.generate_keypair().unwrap();
@@ -332,7 +482,7 @@ mod tests {
         (initiator_codec, responder_codec)
     }
 
-    fn assert_roundtrip(msg: MessageOrBytes) {
+    fn assert_roundtrip(msg: MessageOrBytes<CurrentNetwork>) {
         let (mut initiator_codec, mut responder_codec) = handshake_xx();
         let mut ciphertext = BytesMut::new();
 
@@ -354,10 +504,16 @@ mod tests {
 
         let challenge_request = MessageOrBytes::Message(Box::new(Message::ChallengeRequest(ChallengeRequest {
             version: 0,
+            network_id: 0,
             listener_port: 0,
             node_type: NodeType::Client,
             address: Address::new(Group::rand(rng)),
+            genesis_hash: Default::default(),
             nonce: 0,
+            pruned_height: 0,
+            capabilities: Capabilities::ALL,
+            timestamp: None,
+            node_id: None,
         })));
 
         assert_roundtrip(challenge_request);
@@ -380,7 +536,7 @@ mod tests {
 
     #[test]
     fn peer_response_roundtrip() {
-        let peer_response = MessageOrBytes::Message(Box::new(Message::PeerResponse(PeerResponse { peers: vec![] })));
+        let peer_response = MessageOrBytes::Message(Box::new(Message::PeerResponse(PeerResponse::new(vec![]))));
         assert_roundtrip(peer_response);
     }
 
@@ -403,4 +559,82 @@ mod tests {
         let puzzle_request = MessageOrBytes::Message(Box::new(Message::PuzzleRequest(PuzzleRequest)));
         assert_roundtrip(puzzle_request);
     }
+
+    #[test]
+    fn chunked_roundtrip() {
+        // Ensure a message larger than the maximum noise message size is chunked and reassembled.
+        let (mut initiator_codec, mut responder_codec) = handshake_xx();
+        initiator_codec.update_max_message_len();
+        responder_codec.update_max_message_len();
+
+        let peers = (0..20_000u16).map(|port| ([127, 0, 0, 1], port).into()).collect();
+        let peer_response = MessageOrBytes::Message(Box::new(Message::PeerResponse(PeerResponse::new(peers))));
+
+        let mut ciphertext = BytesMut::new();
+        assert!(initiator_codec.encode(peer_response.clone(), &mut ciphertext).is_ok());
+        assert!(ciphertext.len() > MAX_MESSAGE_LEN);
+        assert_eq!(responder_codec.decode(&mut ciphertext).unwrap().unwrap(), peer_response);
+    }
+
+    #[test]
+    fn compressed_roundtrip() {
+        // Ensure a large message is compressed before its encryption, and decoded into the same message.
+        let (mut initiator_codec, mut responder_codec) = handshake_xx();
+        let stats = Arc::new(CompressionStats::default());
+        for codec in [&mut initiator_codec, &mut responder_codec] {
+            codec.update_max_message_len();
+            codec.enable_compression(Compression::Zstd(crate::DEFAULT_ZSTD_LEVEL), stats.clone());
+        }
+
+        let peers = (0..20_000u16).map(|port| ([127, 0, 0, 1], port).into()).collect();
+        let peer_response = MessageOrBytes::Message(Box::new(Message::PeerResponse(PeerResponse::new(peers))));
+
+        let mut ciphertext = BytesMut::new();
+        assert!(initiator_codec.encode(peer_response.clone(), &mut ciphertext).is_ok());
+        assert_eq!(responder_codec.decode(&mut ciphertext).unwrap().unwrap(), peer_response);
+
+        let (uncompressed, compressed) = stats.sent();
+        assert!(compressed > 0 && compressed < uncompressed);
+        assert_eq!(stats.received(), (uncompressed, compressed));
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let (mut initiator_codec, mut responder_codec) = handshake_xx();
+        let ping = MessageOrBytes::Message(Box::new(Message::Ping(Ping::new(NodeType::Client, None))));
+
+        // Flip a bit in the encrypted payload.
+        let mut ciphertext = BytesMut::new();
+        initiator_codec.encode(ping, &mut ciphertext).unwrap();
+        let last = ciphertext.len() - 1;
+        ciphertext[last] ^= 1;
+
+        assert!(responder_codec.decode(&mut ciphertext).is_err());
+    }
+
+    #[test]
+    fn replayed_ciphertext_is_rejected() {
+        let (mut initiator_codec, mut responder_codec) = handshake_xx();
+        let ping = MessageOrBytes::Message(Box::new(Message::Ping(Ping::new(NodeType::Client, None))));
+
+        let mut ciphertext = BytesMut::new();
+        initiator_codec.encode(ping.clone(), &mut ciphertext).unwrap();
+        let replayed = ciphertext.clone();
+
+        // Ensure the message is accepted once, but not when replayed.
+        assert_eq!(responder_codec.decode(&mut ciphertext).unwrap().unwrap(), ping);
+        assert!(responder_codec.decode(&mut replayed.clone()).is_err());
+    }
+
+    #[test]
+    fn plaintext_is_rejected() {
+        let (_, mut responder_codec) = handshake_xx();
+        let ping = Message::Ping(Ping::<CurrentNetwork>::new(NodeType::Client, None));
+
+        // Ensure an unencrypted message is rejected after the handshake.
+        let mut plaintext = BytesMut::new();
+        MessageCodec::default().encode(ping, &mut plaintext).unwrap();
+
+        assert!(responder_codec.decode(&mut plaintext).is_err());
+    }
 }
@@ -0,0 +1,146 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use std::net::{Ipv6Addr, SocketAddr};
+
+/// The number of characters in the host of a (version 3) onion address, without the `.onion` suffix.
+const ONION_HOST_LENGTH: usize = 56;
+/// The version of the onion addresses, which is the last byte of the decoded host.
+const ONION_VERSION: u8 = 3;
+/// The prefix of the virtual addresses of the onion addresses, which is the `fd87:d87e:eb43::/48` range
+/// of the unique local addresses, so that they never collide with a reachable address.
+const VIRTUAL_PREFIX: [u8; 6] = [0xfd, 0x87, 0xd8, 0x7e, 0xeb, 0x43];
+
+/// The address of an onion service, which is only reachable through Tor.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OnionAddr {
+    /// The host, in lowercase and without the `.onion` suffix.
+    host: String,
+    /// The port.
+    port: u16,
+    /// The decoded host, which consists of the public key, the checksum, and the version.
+    decoded: [u8; 35],
+}
+
+impl OnionAddr {
+    /// Returns the host, including the `.onion` suffix.
+    pub fn host(&self) -> String {
+        format!("{}.onion", self.host)
+    }
+
+    /// Returns the port.
+    pub const fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the virtual address of the onion address, under which it is tracked alongside the IP addresses.
+    /// The virtual address is derived from the public key, and is never dialed without a proxy.
+    pub fn virtual_addr(&self) -> SocketAddr {
+        let mut octets = [0u8; 16];
+        octets[..6].copy_from_slice(&VIRTUAL_PREFIX);
+        octets[6..].copy_from_slice(&self.decoded[..10]);
+        SocketAddr::new(Ipv6Addr::from(octets).into(), self.port)
+    }
+
+    /// Returns `true` if the given address is the virtual address of an onion address.
+    pub fn is_virtual_addr(addr: &SocketAddr) -> bool {
+        match addr {
+            SocketAddr::V6(addr) => addr.ip().octets()[..6] == VIRTUAL_PREFIX,
+            SocketAddr::V4(_) => false,
+        }
+    }
+}
+
+impl core::str::FromStr for OnionAddr {
+    type Err = anyhow::Error;
+
+    /// Parses an onion address of the form `<56 base32 characters>.onion:<port>`.
+    /// Note that the checksum of the host is not verified, as the proxy rejects the malformed hosts.
+    fn from_str(addr: &str) -> Result<Self, Self::Err> {
+        let (host, port) = addr.rsplit_once(':').ok_or_else(|| anyhow::anyhow!("Missing the port in '{addr}'"))?;
+        let port = port.parse::<u16>().map_err(|_| anyhow::anyhow!("Invalid port in '{addr}'"))?;
+        let host = host.to_ascii_lowercase();
+        let host = host.strip_suffix(".onion").ok_or_else(|| anyhow::anyhow!("'{addr}' is not an onion address"))?;
+        if host.len() != ONION_HOST_LENGTH {
+            anyhow::bail!("'{addr}' is not a version {ONION_VERSION} onion address");
+        }
+        let decoded = decode_base32(host).ok_or_else(|| anyhow::anyhow!("Invalid characters in '{addr}'"))?;
+        let decoded: [u8; 35] = decoded.try_into().map_err(|_| anyhow::anyhow!("Invalid length of '{addr}'"))?;
+        if decoded[34] != ONION_VERSION {
+            anyhow::bail!("'{addr}' is not a version {ONION_VERSION} onion address");
+        }
+        Ok(Self { host: host.to_string(), port, decoded })
+    }
+}
+
+impl core::fmt::Display for OnionAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.onion:{}", self.host, self.port)
+    }
+}
+
+/// Decodes the given lowercase base32 (RFC 4648, without padding) string.
+fn decode_base32(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for character in input.bytes() {
+        let value = match character {
+            b'a'..=b'z' => character - b'a',
+            b'2'..=b'7' => character - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::str::FromStr;
+
+    /// The onion address of the Tor Project.
+    const ONION: &str = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion:4133";
+
+    #[test]
+    fn test_onion_addr() {
+        let onion = OnionAddr::from_str(ONION).unwrap();
+        assert_eq!(onion.to_string(), ONION);
+        assert_eq!(onion.port(), 4133);
+        assert_eq!(OnionAddr::from_str(&ONION.to_uppercase().replace("ONION", "onion")).unwrap(), onion);
+
+        // Ensure the virtual address is a unique local address, which is derived from the public key.
+        let virtual_addr = onion.virtual_addr();
+        assert!(OnionAddr::is_virtual_addr(&virtual_addr));
+        assert_eq!(virtual_addr.port(), 4133);
+        assert_eq!(OnionAddr::from_str(ONION).unwrap().virtual_addr(), virtual_addr);
+        assert!(!OnionAddr::is_virtual_addr(&"[2001:db8::1]:4133".parse().unwrap()));
+        assert!(!OnionAddr::is_virtual_addr(&"127.0.0.1:4133".parse().unwrap()));
+
+        // Ensure the malformed addresses are rejected.
+        assert!(OnionAddr::from_str("2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion").is_err());
+        assert!(OnionAddr::from_str("expyuzz4wqqyqhjn.onion:4133").is_err());
+        assert!(OnionAddr::from_str("2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.com:4133").is_err());
+        assert!(OnionAddr::from_str("1gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion:4133").is_err());
+    }
+}
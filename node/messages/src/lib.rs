@@ -22,6 +22,18 @@ extern crate tracing;
 pub mod helpers;
 pub use helpers::*;
 
+#[cfg(any(test, feature = "test"))]
+pub mod differential;
+
+#[cfg(any(test, feature = "test"))]
+pub mod test_helpers;
+
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
+
+#[cfg(test)]
+mod tests;
+
 mod beacon_propose;
 pub use beacon_propose::BeaconPropose;
 
@@ -46,6 +58,10 @@ pub use challenge_response::ChallengeResponse;
 mod disconnect;
 pub use disconnect::Disconnect;
 
+mod get_parent_txs;
+pub use get_parent_txs::{GetParentTxs, MAX_PARENT_TXS};
+use get_parent_txs::{read_transaction_ids, write_transaction_ids};
+
 mod peer_request;
 pub use peer_request::PeerRequest;
 
@@ -64,6 +80,15 @@ pub use puzzle_request::PuzzleRequest;
 mod puzzle_response;
 pub use puzzle_response::PuzzleResponse;
 
+mod snapshot_chunk;
+pub use snapshot_chunk::SnapshotChunk;
+
+mod snapshot_manifest;
+pub use snapshot_manifest::{SnapshotManifest, MAX_SNAPSHOT_BLOCKS_PER_CHUNK, MAX_SNAPSHOT_CHUNKS};
+
+mod snapshot_request;
+pub use snapshot_request::SnapshotRequest;
+
 mod unconfirmed_solution;
 pub use unconfirmed_solution::UnconfirmedSolution;
 
@@ -85,7 +110,7 @@ use snarkvm::prelude::{
     Transaction,
 };
 
-use ::bytes::{Buf, BytesMut};
+use ::bytes::{Buf, Bytes, BytesMut};
 use anyhow::{bail, Result};
 use std::{
     fmt,
@@ -116,19 +141,32 @@ pub enum Message<N: Network> {
     ChallengeRequest(ChallengeRequest<N>),
     ChallengeResponse(ChallengeResponse<N>),
     Disconnect(Disconnect),
+    GetParentTxs(GetParentTxs<N>),
     PeerRequest(PeerRequest),
     PeerResponse(PeerResponse),
     Ping(Ping<N>),
     Pong(Pong),
     PuzzleRequest(PuzzleRequest),
     PuzzleResponse(PuzzleResponse<N>),
+    SnapshotChunk(SnapshotChunk),
+    SnapshotManifest(SnapshotManifest<N>),
+    SnapshotRequest(SnapshotRequest),
     UnconfirmedSolution(UnconfirmedSolution<N>),
     UnconfirmedTransaction(UnconfirmedTransaction<N>),
 }
 
 impl<N: Network> Message<N> {
+    /// The first version of the network protocol with the extensions of the challenge request, and the commitments
+    /// of the headers in the puzzle responses. Peers on an earlier version are only accepted by nodes in transition
+    /// mode, and are sent neither the extensions nor the messages of the capabilities they do not advertise.
+    pub const EXTENDED_VERSION: u32 = 8;
+    /// The first version of the network protocol with transport encryption.
+    pub const NOISE_MIN_VERSION: u32 = 7;
+    /// The last version of the network protocol without transport encryption.
+    /// Peers on this version are only accepted by nodes in transition mode.
+    pub const PLAINTEXT_VERSION: u32 = 6;
     /// The version of the network protocol; it can be incremented in order to force users to update.
-    pub const VERSION: u32 = 5;
+    pub const VERSION: u32 = 8;
 
     /// Returns the message name.
     #[inline]
@@ -142,12 +180,16 @@ impl<N: Network> Message<N> {
             Self::ChallengeRequest(message) => message.name(),
             Self::ChallengeResponse(message) => message.name(),
             Self::Disconnect(message) => message.name(),
+            Self::GetParentTxs(message) => message.name(),
             Self::PeerRequest(message) => message.name(),
             Self::PeerResponse(message) => message.name(),
             Self::Ping(message) => message.name(),
             Self::Pong(message) => message.name(),
             Self::PuzzleRequest(message) => message.name(),
             Self::PuzzleResponse(message) => message.name(),
+            Self::SnapshotChunk(message) => message.name(),
+            Self::SnapshotManifest(message) => message.name(),
+            Self::SnapshotRequest(message) => message.name(),
             Self::UnconfirmedSolution(message) => message.name(),
             Self::UnconfirmedTransaction(message) => message.name(),
         }
@@ -172,7 +214,16 @@ impl<N: Network> Message<N> {
             Self::PuzzleRequest(..) => 12,
             Self::PuzzleResponse(..) => 13,
             Self::UnconfirmedSolution(..) => 14,
-            Self::UnconfirmedTransaction(..) => 15,
+            // An extended announcement, with the parents of the transaction, is sent under its own message ID,
+            // as only the peers that advertise `PARENT_TXS` are able to read it.
+            Self::UnconfirmedTransaction(message) => match message.parent_ids.is_empty() {
+                true => 15,
+                false => 17,
+            },
+            Self::GetParentTxs(..) => 16,
+            Self::SnapshotRequest(..) => 18,
+            Self::SnapshotManifest(..) => 19,
+            Self::SnapshotChunk(..) => 20,
         }
     }
 
@@ -190,12 +241,16 @@ impl<N: Network> Message<N> {
             Self::ChallengeRequest(message) => message.serialize(writer),
             Self::ChallengeResponse(message) => message.serialize(writer),
             Self::Disconnect(message) => message.serialize(writer),
+            Self::GetParentTxs(message) => message.serialize(writer),
             Self::PeerRequest(message) => message.serialize(writer),
             Self::PeerResponse(message) => message.serialize(writer),
             Self::Ping(message) => message.serialize(writer),
             Self::Pong(message) => message.serialize(writer),
             Self::PuzzleRequest(message) => message.serialize(writer),
             Self::PuzzleResponse(message) => message.serialize(writer),
+            Self::SnapshotChunk(message) => message.serialize(writer),
+            Self::SnapshotManifest(message) => message.serialize(writer),
+            Self::SnapshotRequest(message) => message.serialize(writer),
             Self::UnconfirmedSolution(message) => message.serialize(writer),
             Self::UnconfirmedTransaction(message) => message.serialize(writer),
         }
@@ -230,6 +285,11 @@ impl<N: Network> Message<N> {
             13 => Self::PuzzleResponse(MessageTrait::deserialize(bytes)?),
             14 => Self::UnconfirmedSolution(MessageTrait::deserialize(bytes)?),
             15 => Self::UnconfirmedTransaction(MessageTrait::deserialize(bytes)?),
+            16 => Self::GetParentTxs(MessageTrait::deserialize(bytes)?),
+            17 => Self::UnconfirmedTransaction(UnconfirmedTransaction::deserialize_with_parents(bytes)?),
+            18 => Self::SnapshotRequest(MessageTrait::deserialize(bytes)?),
+            19 => Self::SnapshotManifest(MessageTrait::deserialize(bytes)?),
+            20 => Self::SnapshotChunk(MessageTrait::deserialize(bytes)?),
             _ => bail!("Unknown message ID {id}"),
         };
 
@@ -19,6 +19,15 @@ use super::*;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PeerResponse {
     pub peers: Vec<SocketAddr>,
+    /// The onion addresses of the peers, which are only sent to the peers that advertise `ONION_ADDRS`.
+    pub onion_peers: Vec<OnionAddr>,
+}
+
+impl PeerResponse {
+    /// Initializes a new peer response with the given IP addresses, and without onion addresses.
+    pub fn new(peers: Vec<SocketAddr>) -> Self {
+        Self { peers, onion_peers: Default::default() }
+    }
 }
 
 impl MessageTrait for PeerResponse {
@@ -31,12 +40,28 @@ impl MessageTrait for PeerResponse {
     /// Serializes the message into the buffer.
     #[inline]
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        Ok(bincode::serialize_into(writer, &self.peers)?)
+        bincode::serialize_into(&mut *writer, &self.peers)?;
+        // Append the onion addresses (if any), which the peers without them treat as trailing bytes.
+        if !self.onion_peers.is_empty() {
+            let onion_peers = self.onion_peers.iter().map(|onion| onion.to_string()).collect::<Vec<_>>();
+            bincode::serialize_into(writer, &onion_peers)?;
+        }
+        Ok(())
     }
 
     /// Deserializes the given buffer into a message.
     #[inline]
     fn deserialize(bytes: BytesMut) -> Result<Self> {
-        Ok(Self { peers: bincode::deserialize_from(&mut bytes.reader())? })
+        let mut reader = bytes.reader();
+        let peers = bincode::deserialize_from(&mut reader)?;
+        // Read the onion addresses, which are absent from the responses without them.
+        let onion_peers = match reader.get_ref().remaining() == 0 {
+            true => Default::default(),
+            false => {
+                let onion_peers: Vec<String> = bincode::deserialize_from(&mut reader)?;
+                onion_peers.iter().map(|onion| onion.parse()).collect::<Result<_>>()?
+            }
+        };
+        Ok(Self { peers, onion_peers })
     }
 }
@@ -19,7 +19,7 @@ use super::*;
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PuzzleResponse<N: Network> {
     pub epoch_challenge: EpochChallenge<N>,
-    pub block_header: Data<Header<N>>,
+    pub block_header: Data<CommittedHeader<N>>,
 }
 
 impl<N: Network> MessageTrait for PuzzleResponse<N> {
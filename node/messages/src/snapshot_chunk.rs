@@ -0,0 +1,53 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+/// One chunk of the snapshot of a peer. The bytes are opaque to the messages, and are only decoded
+/// once their hash was checked against the manifest of the snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotChunk {
+    /// The index of the chunk in the manifest.
+    pub index: u32,
+    /// The bytes of the chunk.
+    pub bytes: Bytes,
+}
+
+impl MessageTrait for SnapshotChunk {
+    /// Returns the message name.
+    #[inline]
+    fn name(&self) -> String {
+        format!("SnapshotChunk {}", self.index)
+    }
+
+    /// Serializes the message into the buffer.
+    #[inline]
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.index.to_le_bytes())?;
+        writer.write_all(&self.bytes)?;
+        Ok(())
+    }
+
+    /// Deserializes the given buffer into a message.
+    #[inline]
+    fn deserialize(mut bytes: BytesMut) -> Result<Self> {
+        if bytes.remaining() < 4 {
+            bail!("Invalid 'SnapshotChunk' message");
+        }
+        let index = bytes.get_u32_le();
+        Ok(Self { index, bytes: bytes.freeze() })
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+/// The maximum number of chunks in a snapshot manifest.
+pub const MAX_SNAPSHOT_CHUNKS: u32 = 1 << 16;
+/// The maximum number of blocks in a snapshot chunk.
+pub const MAX_SNAPSHOT_BLOCKS_PER_CHUNK: u32 = 1_000;
+
+/// The manifest of the snapshot of a peer, which is sent before its chunks.
+///
+/// The snapshot covers the canonical blocks from height 1 up to `height`, as the requesting node already
+/// holds the genesis block. The blocks are split into chunks of `blocks_per_chunk` blocks, and the hash
+/// of every chunk is committed to in the manifest, so that a corrupted chunk is detected before it is decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotManifest<N: Network> {
+    /// The height of the last block in the snapshot.
+    pub height: u32,
+    /// The hash of the last block in the snapshot.
+    pub block_hash: N::BlockHash,
+    /// The number of blocks in each chunk.
+    pub blocks_per_chunk: u32,
+    /// The SHA-256 hash of each chunk.
+    pub chunk_hashes: Vec<[u8; 32]>,
+}
+
+impl<N: Network> SnapshotManifest<N> {
+    /// Initializes a new manifest, for the given chunk hashes.
+    pub fn new(blocks_per_chunk: u32, chunk_hashes: Vec<[u8; 32]>, block_hash: N::BlockHash) -> Result<Self> {
+        let height = match u32::try_from(chunk_hashes.len()).ok().and_then(|n| n.checked_mul(blocks_per_chunk)) {
+            Some(height) => height,
+            None => bail!("The snapshot of {} chunks is too large", chunk_hashes.len()),
+        };
+        let manifest = Self { height, block_hash, blocks_per_chunk, chunk_hashes };
+        manifest.check()?;
+        Ok(manifest)
+    }
+
+    /// Returns the number of chunks in the snapshot.
+    pub fn num_chunks(&self) -> u32 {
+        self.chunk_hashes.len() as u32
+    }
+
+    /// Returns the range of the heights of the blocks in the given chunk.
+    pub fn chunk_range(&self, index: u32) -> Range<u32> {
+        let start = index * self.blocks_per_chunk + 1;
+        start..start + self.blocks_per_chunk
+    }
+
+    /// Returns the hash of the given chunk bytes.
+    pub fn chunk_hash(bytes: &[u8]) -> [u8; 32] {
+        Sha256::digest(bytes).into()
+    }
+
+    /// Checks the manifest is consistent, and within the limits of a snapshot.
+    pub fn check(&self) -> Result<()> {
+        if self.blocks_per_chunk == 0 || self.blocks_per_chunk > MAX_SNAPSHOT_BLOCKS_PER_CHUNK {
+            bail!(
+                "Found {} blocks per snapshot chunk (maximum is {MAX_SNAPSHOT_BLOCKS_PER_CHUNK})",
+                self.blocks_per_chunk
+            )
+        }
+        if self.chunk_hashes.len() > MAX_SNAPSHOT_CHUNKS as usize {
+            bail!("Found {} snapshot chunks (maximum is {MAX_SNAPSHOT_CHUNKS})", self.chunk_hashes.len())
+        }
+        if self.height != self.num_chunks() * self.blocks_per_chunk {
+            bail!("The snapshot height {} does not match its {} chunks", self.height, self.num_chunks())
+        }
+        Ok(())
+    }
+
+    /// Checks the given bytes are the chunk at the given index.
+    pub fn verify_chunk(&self, index: u32, bytes: &[u8]) -> Result<()> {
+        match self.chunk_hashes.get(index as usize) {
+            Some(hash) if *hash == Self::chunk_hash(bytes) => Ok(()),
+            Some(_) => bail!("Snapshot chunk {index} does not match its hash in the manifest"),
+            None => bail!("Snapshot chunk {index} is not in the manifest ({} chunks)", self.num_chunks()),
+        }
+    }
+}
+
+impl<N: Network> MessageTrait for SnapshotManifest<N> {
+    /// Returns the message name.
+    #[inline]
+    fn name(&self) -> String {
+        "SnapshotManifest".to_string()
+    }
+
+    /// Serializes the message into the buffer.
+    #[inline]
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.check()?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&self.block_hash.to_bytes_le()?)?;
+        writer.write_all(&self.blocks_per_chunk.to_le_bytes())?;
+        writer.write_all(&self.num_chunks().to_le_bytes())?;
+        for hash in &self.chunk_hashes {
+            writer.write_all(hash)?;
+        }
+        Ok(())
+    }
+
+    /// Deserializes the given buffer into a message.
+    #[inline]
+    fn deserialize(bytes: BytesMut) -> Result<Self> {
+        let mut reader = bytes.reader();
+        let height = u32::read_le(&mut reader)?;
+        let block_hash = N::BlockHash::read_le(&mut reader)?;
+        let blocks_per_chunk = u32::read_le(&mut reader)?;
+        let num_chunks = u32::read_le(&mut reader)?;
+        if num_chunks > MAX_SNAPSHOT_CHUNKS {
+            bail!("Found {num_chunks} snapshot chunks (maximum is {MAX_SNAPSHOT_CHUNKS})");
+        }
+        let mut chunk_hashes = Vec::with_capacity(num_chunks as usize);
+        for _ in 0..num_chunks {
+            let mut hash = [0u8; 32];
+            reader.read_exact(&mut hash)?;
+            chunk_hashes.push(hash);
+        }
+        if reader.into_inner().remaining() != 0 {
+            bail!("Invalid 'SnapshotManifest' message");
+        }
+        let manifest = Self { height, block_hash, blocks_per_chunk, chunk_hashes };
+        manifest.check()?;
+        Ok(manifest)
+    }
+}
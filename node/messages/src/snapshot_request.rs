@@ -0,0 +1,64 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+/// A request for the snapshot manifest of a peer, or for one chunk of the snapshot it describes.
+/// The request is only sent to the trusted peers that advertise `SERVES_SNAPSHOTS`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SnapshotRequest {
+    /// The index of the requested chunk, or `None` for the manifest.
+    pub chunk: Option<u32>,
+}
+
+impl MessageTrait for SnapshotRequest {
+    /// Returns the message name.
+    #[inline]
+    fn name(&self) -> String {
+        match self.chunk {
+            Some(index) => format!("SnapshotRequest {index}"),
+            None => "SnapshotRequest".to_string(),
+        }
+    }
+
+    /// Serializes the message into the buffer.
+    #[inline]
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self.chunk {
+            Some(index) => {
+                writer.write_all(&[1u8])?;
+                writer.write_all(&index.to_le_bytes())?;
+            }
+            None => writer.write_all(&[0u8])?,
+        }
+        Ok(())
+    }
+
+    /// Deserializes the given buffer into a message.
+    #[inline]
+    fn deserialize(bytes: BytesMut) -> Result<Self> {
+        let mut reader = bytes.reader();
+        let chunk = match u8::read_le(&mut reader)? {
+            0 => None,
+            1 => Some(u32::read_le(&mut reader)?),
+            variant => bail!("Invalid 'SnapshotRequest' variant {variant}"),
+        };
+        match reader.into_inner().remaining() == 0 {
+            true => Ok(Self { chunk }),
+            false => bail!("Invalid 'SnapshotRequest' message"),
+        }
+    }
+}
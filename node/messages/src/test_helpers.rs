@@ -0,0 +1,203 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Generators of random, valid messages, shared by the round-trip tests of the encodings
+//! and by the tests of the dependent crates (with the `test` feature).
+
+use crate::{
+    block_locators::test_helpers::sample_block_locators,
+    BlockRequest,
+    BlockResponse,
+    Capabilities,
+    ChallengeRequest,
+    ChallengeResponse,
+    Data,
+    DataBlocks,
+    Disconnect,
+    DisconnectReason,
+    GetParentTxs,
+    Message,
+    NodeId,
+    NodeType,
+    PeerRequest,
+    PeerResponse,
+    Ping,
+    Pong,
+    PuzzleRequest,
+    SnapshotChunk,
+    SnapshotManifest,
+    SnapshotRequest,
+    UnconfirmedTransaction,
+    MAX_PARENT_TXS,
+};
+use snarkvm::prelude::{
+    Address,
+    Block,
+    CryptoRng,
+    Field,
+    FromBytes,
+    Network,
+    PrivateKey,
+    Rng,
+    Testnet3,
+    Transaction,
+    Uniform,
+};
+
+use std::net::{IpAddr, SocketAddr};
+
+type CurrentNetwork = Testnet3;
+
+/// The maximum number of peers in a sampled `PeerResponse`.
+const MAX_SAMPLE_PEERS: usize = 32;
+/// The maximum latest height of the sampled block locators.
+const MAX_SAMPLE_LOCATOR_HEIGHT: u32 = 20_000;
+/// The maximum number of chunks in a sampled `SnapshotManifest`.
+const MAX_SAMPLE_SNAPSHOT_CHUNKS: usize = 64;
+
+/// Returns the genesis block.
+pub fn sample_genesis_block() -> Block<CurrentNetwork> {
+    Block::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap()
+}
+
+/// Returns a random transaction of the genesis block.
+pub fn sample_genesis_transaction<R: Rng>(rng: &mut R) -> Transaction<CurrentNetwork> {
+    let block = sample_genesis_block();
+    let index = rng.gen_range(0..block.transactions().len());
+    let transaction = block.transactions().iter().nth(index).unwrap().clone();
+    transaction
+}
+
+/// Returns a random node type.
+pub fn sample_node_type<R: Rng>(rng: &mut R) -> NodeType {
+    [NodeType::Client, NodeType::Prover, NodeType::Validator, NodeType::Beacon][rng.gen_range(0..4)]
+}
+
+/// Returns a random disconnect reason.
+pub fn sample_disconnect_reason<R: Rng>(rng: &mut R) -> DisconnectReason {
+    let reasons = [
+        DisconnectReason::ExceededForkRange,
+        DisconnectReason::InvalidChallengeResponse,
+        DisconnectReason::InvalidForkDepth,
+        DisconnectReason::INeedToSyncFirst,
+        DisconnectReason::NoReasonGiven,
+        DisconnectReason::ProtocolViolation,
+        DisconnectReason::OutdatedClientVersion,
+        DisconnectReason::PeerHasDisconnected,
+        DisconnectReason::PeerRefresh,
+        DisconnectReason::ShuttingDown,
+        DisconnectReason::SyncComplete,
+        DisconnectReason::TooManyFailures,
+        DisconnectReason::TooManyPeers,
+        DisconnectReason::YouNeedToSyncFirst,
+        DisconnectReason::YourPortIsClosed(rng.gen()),
+        DisconnectReason::InvalidNetworkId,
+        DisconnectReason::InvalidGenesisHash,
+        DisconnectReason::PinnedKeyMismatch,
+    ];
+    reasons[rng.gen_range(0..reasons.len())].clone()
+}
+
+/// Returns a random IPv4 or IPv6 socket address.
+pub fn sample_socket_addr<R: Rng>(rng: &mut R) -> SocketAddr {
+    let ip = match rng.gen() {
+        true => IpAddr::from(rng.gen::<[u8; 4]>()),
+        false => IpAddr::from(rng.gen::<[u8; 16]>()),
+    };
+    SocketAddr::new(ip, rng.gen())
+}
+
+/// Returns a random number of random transaction IDs, of at least the given number, and at most `MAX_PARENT_TXS`.
+pub fn sample_transaction_ids<R: Rng>(rng: &mut R, min: usize) -> Vec<<CurrentNetwork as Network>::TransactionID> {
+    (0..rng.gen_range(min..=MAX_PARENT_TXS)).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect()
+}
+
+/// Returns a random message, of a random kind, with bounded contents.
+///
+/// The objects that are expensive to produce (blocks, transactions, and signatures)
+/// are taken from the genesis block, or signed with a fresh private key.
+pub fn sample_message<R: Rng + CryptoRng>(rng: &mut R) -> Message<CurrentNetwork> {
+    match rng.gen_range(0..16) {
+        0 => {
+            let start_height = rng.gen();
+            Message::BlockRequest(BlockRequest { start_height, end_height: start_height.saturating_add(rng.gen()) })
+        }
+        1 => Message::BlockResponse(BlockResponse {
+            request: BlockRequest { start_height: 0, end_height: 1 },
+            blocks: Data::Object(DataBlocks(vec![sample_genesis_block()])),
+        }),
+        2 => {
+            let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+            Message::ChallengeRequest(ChallengeRequest {
+                version: rng.gen(),
+                network_id: rng.gen(),
+                listener_port: rng.gen(),
+                node_type: sample_node_type(rng),
+                address: Address::try_from(private_key).unwrap(),
+                genesis_hash: sample_genesis_block().hash(),
+                nonce: rng.gen(),
+                pruned_height: rng.gen(),
+                capabilities: Capabilities(rng.gen()),
+                timestamp: Some(rng.gen()),
+                node_id: Some(NodeId::from_public_key(&rng.gen::<[u8; 32]>())),
+            })
+        }
+        3 => {
+            let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+            let message = rng.gen::<[u8; 32]>();
+            Message::ChallengeResponse(ChallengeResponse {
+                genesis_header: *sample_genesis_block().header(),
+                signature: Data::Object(private_key.sign_bytes(&message, rng).unwrap()),
+            })
+        }
+        4 => Message::Disconnect(Disconnect { reason: sample_disconnect_reason(rng) }),
+        5 => Message::PeerRequest(PeerRequest),
+        6 => {
+            let num_peers = rng.gen_range(0..=MAX_SAMPLE_PEERS);
+            Message::PeerResponse(PeerResponse::new((0..num_peers).map(|_| sample_socket_addr(rng)).collect()))
+        }
+        7 => Message::Ping(Ping {
+            version: rng.gen(),
+            node_type: sample_node_type(rng),
+            block_locators: match rng.gen() {
+                true => Some(sample_block_locators(rng.gen_range(0..=MAX_SAMPLE_LOCATOR_HEIGHT))),
+                false => None,
+            },
+        }),
+        8 => Message::Pong(Pong { is_fork: [Some(true), Some(false), None][rng.gen_range(0..3)] }),
+        9 => Message::PuzzleRequest(PuzzleRequest),
+        10 => Message::GetParentTxs(GetParentTxs { transaction_ids: sample_transaction_ids(rng, 0) }),
+        11 => {
+            let transaction = sample_genesis_transaction(rng);
+            let message = UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction));
+            Message::UnconfirmedTransaction(message.with_parents(sample_transaction_ids(rng, 1)))
+        }
+        12 => Message::SnapshotRequest(SnapshotRequest { chunk: rng.gen() }),
+        13 => {
+            let chunk_hashes = (0..rng.gen_range(0..=MAX_SAMPLE_SNAPSHOT_CHUNKS)).map(|_| rng.gen()).collect();
+            let block_hash = Field::<CurrentNetwork>::rand(rng).into();
+            Message::SnapshotManifest(SnapshotManifest::new(rng.gen_range(1..=100), chunk_hashes, block_hash).unwrap())
+        }
+        14 => {
+            let bytes = (0..rng.gen_range(0..1024)).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+            Message::SnapshotChunk(SnapshotChunk { index: rng.gen(), bytes: bytes.into() })
+        }
+        _ => {
+            let transaction = sample_genesis_transaction(rng);
+            Message::UnconfirmedTransaction(UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction)))
+        }
+    }
+}
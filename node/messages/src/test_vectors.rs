@@ -0,0 +1,218 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+//! Canonical test vectors of the encodings of blocks, headers, and transactions, for the implementations
+//! in other languages (with the `test-vectors` feature), and for the golden tests of this crate.
+//!
+//! The vectors are generated deterministically from the genesis block, and committed in
+//! `fixtures/test_vectors.json`, which is regenerated (or checked, with `--check`) by running
+//! `cargo run -p snarkos-node-messages --features test-vectors --bin generate-test-vectors`.
+
+use crate::{BlockRequest, BlockResponse, Data, DataBlocks, Message, MessageCodec, UnconfirmedTransaction};
+use snarkvm::prelude::{Block, FromBytes, Header, Network, Testnet3, ToBytes, Transaction};
+
+use anyhow::{anyhow, Result};
+use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
+use tokio_util::codec::Encoder;
+
+type CurrentNetwork = Testnet3;
+
+/// The committed test vectors, in JSON.
+pub const TEST_VECTORS: &str = include_str!("../fixtures/test_vectors.json");
+/// The path of the committed test vectors, relative to the root of this crate.
+pub const TEST_VECTORS_PATH: &str = "fixtures/test_vectors.json";
+
+/// The test vectors of a network.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    /// The ID of the network.
+    pub network_id: u16,
+    /// The vectors of the blocks.
+    pub blocks: Vec<BlockVector>,
+    /// The vectors of the block headers, as kept by the ledger for the pruned blocks.
+    pub headers: Vec<HeaderVector>,
+    /// The vectors of the transactions.
+    pub transactions: Vec<TransactionVector>,
+}
+
+/// The expected encodings of a block. The byte encodings are in hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockVector {
+    /// The name of the vector.
+    pub name: String,
+    /// The height of the block.
+    pub height: u32,
+    /// The block hash.
+    pub hash: String,
+    /// The block hash of the previous block.
+    pub previous_hash: String,
+    /// The Merkle root of the block header.
+    pub header_root: String,
+    /// The Merkle root of the transactions.
+    pub transactions_root: String,
+    /// The IDs of the transactions, in order.
+    pub transaction_ids: Vec<String>,
+    /// The binary encoding of the block.
+    pub bytes: String,
+    /// The frame of a `BlockResponse` with the block, as sent to a peer.
+    pub framed: String,
+    /// The JSON encoding of the block.
+    pub json: String,
+}
+
+/// The expected encodings of a block header. The byte encodings are in hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderVector {
+    /// The name of the vector.
+    pub name: String,
+    /// The height of the block.
+    pub height: u32,
+    /// The Merkle root of the block header.
+    pub header_root: String,
+    /// The binary encoding of the block header.
+    pub bytes: String,
+    /// The JSON encoding of the block header.
+    pub json: String,
+}
+
+/// The expected encodings of a transaction. The byte encodings are in hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionVector {
+    /// The name of the vector.
+    pub name: String,
+    /// The transaction ID.
+    pub id: String,
+    /// Whether the transaction is a coinbase transaction (a call to `credits.aleo/mint`).
+    pub is_coinbase: bool,
+    /// The number of inputs of the transitions.
+    pub num_inputs: usize,
+    /// The number of outputs of the transitions.
+    pub num_outputs: usize,
+    /// The binary encoding of the transaction.
+    pub bytes: String,
+    /// The frame of an `UnconfirmedTransaction` with the transaction, as sent to a peer.
+    pub framed: String,
+    /// The JSON encoding of the transaction.
+    pub json: String,
+}
+
+impl TestVectors {
+    /// Returns the committed test vectors.
+    pub fn load() -> Result<Self> {
+        serde_json::from_str(TEST_VECTORS).map_err(|error| anyhow!("Malformed test vectors - {error}"))
+    }
+
+    /// Generates the test vectors from the genesis block.
+    pub fn generate() -> Result<Self> {
+        let block = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes())?;
+        Ok(Self {
+            network_id: CurrentNetwork::ID,
+            blocks: vec![BlockVector::new("genesis", &block)?],
+            headers: vec![HeaderVector::new("genesis-header", block.header())?],
+            transactions: block
+                .transactions()
+                .iter()
+                .enumerate()
+                .map(|(index, transaction)| TransactionVector::new(&format!("genesis-coinbase-{index}"), transaction))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Returns the test vectors in pretty-printed JSON, as committed.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(format!("{}\n", serde_json::to_string_pretty(self)?))
+    }
+}
+
+impl BlockVector {
+    /// Returns the vector of the given block.
+    fn new(name: &str, block: &Block<CurrentNetwork>) -> Result<Self> {
+        let message = Message::BlockResponse(BlockResponse {
+            request: BlockRequest { start_height: block.height(), end_height: block.height() + 1 },
+            blocks: Data::Object(DataBlocks(vec![block.clone()])),
+        });
+        Ok(Self {
+            name: name.to_string(),
+            height: block.height(),
+            hash: block.hash().to_string(),
+            previous_hash: block.previous_hash().to_string(),
+            header_root: block.header().to_root()?.to_string(),
+            transactions_root: block.header().transactions_root().to_string(),
+            transaction_ids: block.transaction_ids().map(ToString::to_string).collect(),
+            bytes: hex::encode(block.to_bytes_le()?),
+            framed: hex::encode(frame(message)?),
+            json: serde_json::to_string(block)?,
+        })
+    }
+
+    /// Returns the block of the vector.
+    pub fn block(&self) -> Result<Block<CurrentNetwork>> {
+        Block::from_bytes_le(&hex::decode(&self.bytes)?)
+    }
+}
+
+impl HeaderVector {
+    /// Returns the vector of the given block header.
+    fn new(name: &str, header: &Header<CurrentNetwork>) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            height: header.height(),
+            header_root: header.to_root()?.to_string(),
+            bytes: hex::encode(header.to_bytes_le()?),
+            json: serde_json::to_string(header)?,
+        })
+    }
+
+    /// Returns the block header of the vector.
+    pub fn header(&self) -> Result<Header<CurrentNetwork>> {
+        Header::from_bytes_le(&hex::decode(&self.bytes)?)
+    }
+}
+
+impl TransactionVector {
+    /// Returns the vector of the given transaction.
+    fn new(name: &str, transaction: &Transaction<CurrentNetwork>) -> Result<Self> {
+        let message = Message::UnconfirmedTransaction(UnconfirmedTransaction::new(
+            transaction.id(),
+            Data::Object(transaction.clone()),
+        ));
+        Ok(Self {
+            name: name.to_string(),
+            id: transaction.id().to_string(),
+            is_coinbase: transaction.is_coinbase(),
+            num_inputs: transaction.transitions().map(|transition| transition.inputs().len()).sum(),
+            num_outputs: transaction.transitions().map(|transition| transition.outputs().len()).sum(),
+            bytes: hex::encode(transaction.to_bytes_le()?),
+            framed: hex::encode(frame(message)?),
+            json: serde_json::to_string(transaction)?,
+        })
+    }
+
+    /// Returns the transaction of the vector.
+    pub fn transaction(&self) -> Result<Transaction<CurrentNetwork>> {
+        Transaction::from_bytes_le(&hex::decode(&self.bytes)?)
+    }
+}
+
+/// Returns the frame of the given message, as sent to a peer.
+fn frame(message: Message<CurrentNetwork>) -> Result<Vec<u8>> {
+    let mut codec = MessageCodec::<CurrentNetwork>::default();
+    codec.update_max_message_len();
+    let mut frame = BytesMut::new();
+    codec.encode(message, &mut frame)?;
+    Ok(frame.to_vec())
+}
@@ -0,0 +1,596 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    differential::{check_transaction_roundtrip, decode_differential, REGRESSION_FIXTURES_PATH},
+    test_helpers::{sample_genesis_block, sample_genesis_transaction, sample_message, sample_transaction_ids},
+    test_vectors::{TestVectors, TEST_VECTORS},
+    BlockResponse,
+    Capabilities,
+    ChallengeRequest,
+    ChallengeResponse,
+    CommittedHeader,
+    Data,
+    DataBlocks,
+    DecodeMode,
+    GetParentTxs,
+    HeaderCommitments,
+    Message,
+    MessageCodec,
+    NodeId,
+    NodeRole,
+    NodeType,
+    OnionAddr,
+    PeerResponse,
+    Ping,
+    PuzzleResponse,
+    SnapshotManifest,
+    SnapshotRequest,
+    TrailingBytes,
+    UnconfirmedTransaction,
+    MAX_PARENT_TXS,
+    MAX_SNAPSHOT_BLOCKS_PER_CHUNK,
+};
+use snarkvm::prelude::{
+    Address,
+    Block,
+    EpochChallenge,
+    Field,
+    FromBytes,
+    Header,
+    Network,
+    PrivateKey,
+    Rng,
+    TestRng,
+    Testnet3,
+    ToBytes,
+    Transaction,
+    Uniform,
+};
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+type CurrentNetwork = Testnet3;
+
+/// The number of random messages checked by each round-trip test.
+const NUM_ITERATIONS: usize = 256;
+
+/// Returns the given message, with its deferred objects deserialized, so that it can be compared
+/// with the message it was decoded from.
+fn deserialize_data(message: Message<CurrentNetwork>) -> Message<CurrentNetwork> {
+    match message {
+        Message::BlockResponse(BlockResponse { request, blocks }) => Message::BlockResponse(BlockResponse {
+            request,
+            blocks: Data::Object(blocks.deserialize_blocking().unwrap()),
+        }),
+        Message::ChallengeResponse(ChallengeResponse { genesis_header, signature }) => {
+            Message::ChallengeResponse(ChallengeResponse {
+                genesis_header,
+                signature: Data::Object(signature.deserialize_blocking().unwrap()),
+            })
+        }
+        Message::UnconfirmedTransaction(UnconfirmedTransaction { transaction_id, transaction, parent_ids }) => {
+            Message::UnconfirmedTransaction(UnconfirmedTransaction {
+                transaction_id,
+                transaction: Data::Object(transaction.deserialize_blocking().unwrap()),
+                parent_ids,
+            })
+        }
+        message => message,
+    }
+}
+
+/// Returns the serialized bytes of the given message.
+fn serialize(message: &Message<CurrentNetwork>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    message.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_framed_roundtrip() {
+    let rng = &mut TestRng::default();
+
+    for _ in 0..NUM_ITERATIONS {
+        let message = sample_message(rng);
+        let expected_bytes = serialize(&message);
+
+        // Encode the message into a frame.
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        codec.update_max_message_len();
+        let mut frame = BytesMut::new();
+        codec.encode(message.clone(), &mut frame).unwrap();
+
+        // Ensure the length prefix of the frame matches the number of bytes of the message.
+        assert_eq!(frame.len(), 4 + expected_bytes.len(), "{}", message.name());
+        assert_eq!((&frame[..4]).get_u32_le() as usize, expected_bytes.len(), "{}", message.name());
+
+        // Ensure every strict prefix of the frame is incomplete, rather than invalid.
+        // (The codec keeps the length of a partially-received frame, hence a fresh codec for each prefix.)
+        for length in [0, 1, 3, 4, frame.len() / 2, frame.len() - 1] {
+            let mut codec = MessageCodec::<CurrentNetwork>::default();
+            codec.update_max_message_len();
+            let mut prefix = BytesMut::from(&frame[..length]);
+            assert!(codec.decode(&mut prefix).unwrap().is_none(), "{} (prefix of {length} bytes)", message.name());
+        }
+
+        // Ensure the frame decodes into the same message, which serializes into the same bytes.
+        let candidate = codec.decode(&mut frame).unwrap().unwrap();
+        assert!(frame.is_empty());
+        assert_eq!(serialize(&candidate), expected_bytes, "{}", message.name());
+        assert_eq!(deserialize_data(candidate), message);
+    }
+}
+
+#[test]
+fn test_challenge_request_extensions() {
+    let rng = &mut TestRng::default();
+
+    let address = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+    let node_id = NodeId::from_public_key(&[7u8; 32]);
+    let request = ChallengeRequest::new(4133, NodeType::Client, address, sample_genesis_block().hash(), 7, 100)
+        .with_capabilities(NodeRole::OutboundOnly.capabilities())
+        .with_timestamp(1_700_000_000)
+        .with_node_id(node_id);
+    let deserialize = |bytes: &[u8]| Message::<CurrentNetwork>::deserialize(BytesMut::from(bytes));
+
+    // Ensure the extensions are read from a request of the current version.
+    let bytes = serialize(&Message::ChallengeRequest(request.clone()));
+    assert_eq!(deserialize(&bytes).unwrap(), Message::ChallengeRequest(request.clone()));
+    // Ensure a truncated or padded request is rejected.
+    assert!(deserialize(&bytes[..bytes.len() - 1]).is_err());
+    assert!(deserialize(&[&bytes[..], &[0u8]].concat()).is_err());
+
+    // Ensure the extensions are not written for a peer on an earlier version, which is read as serving every block
+    // and every capability, and a padded request is rejected.
+    let legacy = ChallengeRequest { version: Message::<CurrentNetwork>::NOISE_MIN_VERSION, ..request.clone() };
+    let bytes = serialize(&Message::ChallengeRequest(legacy.clone()));
+    let expected = ChallengeRequest {
+        pruned_height: 0,
+        capabilities: Capabilities::ALL,
+        timestamp: None,
+        node_id: None,
+        ..legacy
+    };
+    assert_eq!(deserialize(&bytes).unwrap(), Message::ChallengeRequest(expected));
+    assert!(deserialize(&[&bytes[..], &0u32.to_le_bytes()].concat()).is_err());
+
+    // Ensure a node ID is not reported without a timestamp.
+    let request = ChallengeRequest { timestamp: None, ..request };
+    assert!(Message::ChallengeRequest(request).serialize(&mut Vec::<u8>::new()).is_err());
+}
+
+#[test]
+fn test_peer_response_with_onion_peers() {
+    let onion: OnionAddr = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion:4133".parse().unwrap();
+    let peers = vec!["127.0.0.1:4133".parse().unwrap()];
+    let without_onion_peers = serialize(&Message::<CurrentNetwork>::PeerResponse(PeerResponse::new(peers.clone())));
+    let response = PeerResponse { peers, onion_peers: vec![onion] };
+    let bytes = serialize(&Message::<CurrentNetwork>::PeerResponse(response.clone()));
+
+    // Ensure the onion addresses are read back.
+    let candidate = Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).unwrap();
+    assert_eq!(candidate, Message::PeerResponse(response));
+    // Ensure the onion addresses are trailing bytes, so the response without them is unchanged.
+    assert_eq!(&bytes[..without_onion_peers.len()], &without_onion_peers[..]);
+    let candidate = Message::<CurrentNetwork>::deserialize(BytesMut::from(&without_onion_peers[..])).unwrap();
+    assert!(matches!(candidate, Message::PeerResponse(response) if response.onion_peers.is_empty()));
+}
+
+#[test]
+fn test_unconfirmed_transaction_with_parents() {
+    let rng = &mut TestRng::default();
+
+    let transaction = sample_genesis_block().transactions().iter().next().unwrap().clone();
+    let message = UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction));
+    let parent_ids = (0..3).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect::<Vec<_>>();
+    let extended = message.clone().with_parents(parent_ids.clone());
+
+    // Ensure the announcement without parents keeps its message ID, and the extended announcement has its own.
+    assert_eq!(Message::UnconfirmedTransaction(message.clone()).id(), 15);
+    assert_eq!(Message::UnconfirmedTransaction(extended.clone()).id(), 17);
+
+    // Ensure the parents are read back from the extended announcement.
+    let bytes = serialize(&Message::UnconfirmedTransaction(extended));
+    match Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).unwrap() {
+        Message::UnconfirmedTransaction(candidate) => {
+            assert_eq!(candidate.transaction_id, message.transaction_id);
+            assert_eq!(candidate.parent_ids, parent_ids);
+            assert!(candidate.transaction.deserialize_blocking().is_ok());
+        }
+        candidate => panic!("Unexpected message: {}", candidate.name()),
+    }
+
+    // Ensure the parents are truncated to the maximum, and a request for more parents is rejected.
+    let parent_ids = (0..MAX_PARENT_TXS + 1).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect::<Vec<_>>();
+    assert_eq!(message.with_parents(parent_ids.clone()).parent_ids.len(), MAX_PARENT_TXS);
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(&(parent_ids.len() as u16).to_le_bytes());
+    for parent_id in &parent_ids {
+        bytes.extend_from_slice(&parent_id.to_bytes_le().unwrap());
+    }
+    assert!(Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).is_err());
+    let request = Message::GetParentTxs(GetParentTxs::<CurrentNetwork> { transaction_ids: parent_ids });
+    assert!(request.serialize(&mut vec![]).is_err());
+}
+
+#[test]
+fn test_snapshot_manifest() {
+    let rng = &mut TestRng::default();
+
+    let chunks = (0..6u8).map(|index| vec![index; 100]).collect::<Vec<_>>();
+    let chunk_hashes = chunks.iter().map(|chunk| SnapshotManifest::<CurrentNetwork>::chunk_hash(chunk)).collect();
+    let block_hash = Field::<CurrentNetwork>::rand(rng).into();
+    let manifest = SnapshotManifest::<CurrentNetwork>::new(50, chunk_hashes, block_hash).unwrap();
+
+    // Ensure the chunks cover the blocks after the genesis block, up to the height of the snapshot.
+    assert_eq!(manifest.height, 300);
+    assert_eq!(manifest.num_chunks(), 6);
+    assert_eq!(manifest.chunk_range(0), 1..51);
+    assert_eq!(manifest.chunk_range(5), 251..301);
+
+    // Ensure only the chunk at its index matches its hash.
+    assert!(manifest.verify_chunk(2, &chunks[2]).is_ok());
+    assert!(manifest.verify_chunk(3, &chunks[2]).is_err());
+    assert!(manifest.verify_chunk(6, &chunks[2]).is_err());
+    let mut corrupted = chunks[2].clone();
+    corrupted[0] ^= 1;
+    assert!(manifest.verify_chunk(2, &corrupted).is_err());
+
+    // Ensure the manifest round-trips.
+    let bytes = serialize(&Message::SnapshotManifest(manifest.clone()));
+    let candidate = Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).unwrap();
+    assert_eq!(candidate, Message::SnapshotManifest(manifest.clone()));
+
+    // Ensure a manifest whose height does not match its chunks is rejected.
+    let mut inconsistent = manifest.clone();
+    inconsistent.height += 1;
+    assert!(inconsistent.check().is_err());
+    let mut bytes = serialize(&Message::SnapshotManifest(manifest));
+    bytes[2..6].copy_from_slice(&301u32.to_le_bytes());
+    assert!(Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).is_err());
+
+    // Ensure empty and oversized chunks are rejected.
+    assert!(SnapshotManifest::<CurrentNetwork>::new(0, vec![[0u8; 32]], block_hash).is_err());
+    let blocks_per_chunk = MAX_SNAPSHOT_BLOCKS_PER_CHUNK + 1;
+    assert!(SnapshotManifest::<CurrentNetwork>::new(blocks_per_chunk, vec![[0u8; 32]], block_hash).is_err());
+}
+
+#[test]
+fn test_snapshot_request() {
+    // Ensure the requests of the manifest and of a chunk round-trip, and an unknown variant is rejected.
+    for chunk in [None, Some(0), Some(u32::MAX)] {
+        let message = Message::<CurrentNetwork>::SnapshotRequest(SnapshotRequest { chunk });
+        let bytes = serialize(&message);
+        assert_eq!(Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).unwrap(), message);
+    }
+    assert!(Message::<CurrentNetwork>::deserialize(BytesMut::from(&[18u8, 0, 2][..])).is_err());
+    assert!(Message::<CurrentNetwork>::deserialize(BytesMut::from(&[18u8, 0, 0, 0][..])).is_err());
+}
+
+#[test]
+fn test_data_roundtrip() {
+    let block = sample_genesis_block();
+
+    // Ensure the deferred (de)serialization of a transaction is the identity, for every genesis transaction.
+    for transaction in block.transactions().iter() {
+        let bytes = transaction.to_bytes_le().unwrap();
+        let mut buffer = Vec::new();
+        Data::Object(transaction.clone()).serialize_blocking_into(&mut buffer).unwrap();
+        assert_eq!(buffer, bytes);
+
+        let candidate = Data::<Transaction<CurrentNetwork>>::Buffer(buffer.into()).deserialize_blocking().unwrap();
+        assert_eq!(&candidate, transaction);
+        assert_eq!(candidate.to_bytes_le().unwrap(), bytes);
+    }
+
+    // Ensure the deferred (de)serialization of the blocks is the identity.
+    let blocks = DataBlocks(vec![block]);
+    let bytes = blocks.to_bytes_le().unwrap();
+    let candidate = Data::<DataBlocks<CurrentNetwork>>::Buffer(bytes.clone().into()).deserialize_blocking().unwrap();
+    assert_eq!(candidate, blocks);
+    assert_eq!(candidate.to_bytes_le().unwrap(), bytes);
+    assert_eq!(DataBlocks::<CurrentNetwork>::from_bytes_le(&bytes).unwrap(), blocks);
+}
+
+#[test]
+fn test_trailing_bytes_are_rejected() {
+    let block = sample_genesis_block();
+    let transaction = block.transactions().iter().next().unwrap().clone();
+    let bytes = transaction.to_bytes_le().unwrap();
+    let padded = [bytes.as_slice(), &[0u8, 1, 2]].concat();
+
+    // Ensure a transaction followed by trailing bytes is rejected in strict mode, with the offset of the trailing bytes.
+    let error = DecodeMode::Strict.decode::<Transaction<CurrentNetwork>>(&padded).unwrap_err();
+    assert_eq!(error.downcast_ref::<TrailingBytes>(), Some(&TrailingBytes { offset: bytes.len(), remaining: 3 }));
+    assert_eq!(DecodeMode::Strict.decode::<Transaction<CurrentNetwork>>(&bytes).unwrap(), transaction);
+
+    // Ensure the payload of a message received from the network is decoded in strict mode.
+    let message = Message::<CurrentNetwork>::UnconfirmedTransaction(UnconfirmedTransaction::new(
+        transaction.id(),
+        Data::Object(transaction.clone()),
+    ));
+    let mut buffer = Vec::new();
+    message.serialize(&mut buffer).unwrap();
+    buffer.extend_from_slice(&[0u8, 1, 2]);
+    match Message::<CurrentNetwork>::deserialize(BytesMut::from(&buffer[..])).unwrap() {
+        Message::UnconfirmedTransaction(message) => {
+            let error = message.transaction.deserialize_blocking().unwrap_err();
+            assert!(error.downcast_ref::<TrailingBytes>().is_some());
+        }
+        message => panic!("Unexpected message {}", message.name()),
+    }
+
+    // Ensure the blocks of a block response are decoded in strict mode.
+    let blocks = [DataBlocks(vec![block]).to_bytes_le().unwrap().as_slice(), &[0u8]].concat();
+    assert!(Data::<DataBlocks<CurrentNetwork>>::Buffer(blocks.into()).deserialize_blocking().is_err());
+
+    // Ensure a legacy value followed by trailing bytes still loads in lenient mode.
+    assert_eq!(DecodeMode::Lenient.decode::<Transaction<CurrentNetwork>>(&padded).unwrap(), transaction);
+}
+
+#[test]
+fn test_transaction_differential_roundtrip() {
+    let rng = &mut TestRng::default();
+
+    for _ in 0..NUM_ITERATIONS {
+        // Ensure a transaction, framed with random parents, converts through both encodings without drift.
+        let transaction = sample_genesis_transaction(rng);
+        check_transaction_roundtrip(&transaction, sample_transaction_ids(rng, 0)).unwrap();
+        // Ensure both decoders accept its native bytes, and agree on the transaction.
+        let bytes = transaction.to_bytes_le().unwrap();
+        assert_eq!(decode_differential::<CurrentNetwork>(&bytes).unwrap(), Some(transaction));
+    }
+}
+
+#[test]
+fn test_transaction_differential_mutations() {
+    let rng = &mut TestRng::default();
+
+    // Ensure the decoders agree on the random mutations of the native bytes of a transaction.
+    for _ in 0..NUM_ITERATIONS {
+        let mut bytes = sample_genesis_transaction(rng).to_bytes_le().unwrap();
+        match rng.gen_range(0..3) {
+            0 => {
+                let index = rng.gen_range(0..bytes.len());
+                bytes[index] ^= rng.gen_range(1..=u8::MAX);
+            }
+            1 => bytes.truncate(rng.gen_range(0..bytes.len())),
+            _ => bytes.extend((0..rng.gen_range(1..8)).map(|_| rng.gen::<u8>())),
+        }
+        if let Some(transaction) = decode_differential::<CurrentNetwork>(&bytes).unwrap() {
+            check_transaction_roundtrip(&transaction, vec![]).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_transaction_differential_regressions() {
+    // Ensure the decoders agree on every input they diverged on, or that was found by the fuzz target.
+    let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(REGRESSION_FIXTURES_PATH);
+    let mut num_fixtures = 0;
+    for entry in std::fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        let bytes = std::fs::read(&path).unwrap();
+        match decode_differential::<CurrentNetwork>(&bytes) {
+            Ok(Some(transaction)) => check_transaction_roundtrip(&transaction, vec![]).unwrap(),
+            Ok(None) => (),
+            Err(error) => panic!("The decoders diverge on '{}' - {error}", path.display()),
+        }
+        num_fixtures += 1;
+    }
+    assert!(num_fixtures > 0);
+}
+
+#[test]
+fn test_vectors_are_up_to_date() {
+    // Ensure the committed test vectors match the code (run `generate-test-vectors` to regenerate them).
+    let expected = TestVectors::generate().unwrap();
+    assert_eq!(TestVectors::load().unwrap(), expected);
+    assert_eq!(TEST_VECTORS, expected.to_json().unwrap());
+}
+
+#[test]
+fn test_vectors_roundtrip() {
+    let vectors = TestVectors::load().unwrap();
+
+    /// Decodes the given frame into a message, with its deferred objects deserialized.
+    fn decode_frame(framed: &str) -> Message<CurrentNetwork> {
+        let mut codec = MessageCodec::<CurrentNetwork>::default();
+        codec.update_max_message_len();
+        let mut frame = BytesMut::from(&hex::decode(framed).unwrap()[..]);
+        let message = codec.decode(&mut frame).unwrap().unwrap();
+        assert!(frame.is_empty());
+        deserialize_data(message)
+    }
+
+    // Ensure every block decodes from each of its encodings, into its expected hashes and roots.
+    for vector in &vectors.blocks {
+        let block = vector.block().unwrap();
+        assert_eq!(hex::encode(block.to_bytes_le().unwrap()), vector.bytes, "{}", vector.name);
+        assert_eq!(block.hash().to_string(), vector.hash);
+        assert_eq!(block.previous_hash().to_string(), vector.previous_hash);
+        assert_eq!(block.header().to_root().unwrap().to_string(), vector.header_root);
+        assert_eq!(block.header().transactions_root().to_string(), vector.transactions_root);
+        assert_eq!(block.transaction_ids().map(ToString::to_string).collect::<Vec<_>>(), vector.transaction_ids);
+        assert_eq!(serde_json::from_str::<Block<CurrentNetwork>>(&vector.json).unwrap(), block);
+        match decode_frame(&vector.framed) {
+            Message::BlockResponse(response) => {
+                assert_eq!(response.blocks.deserialize_blocking().unwrap(), DataBlocks(vec![block]))
+            }
+            message => panic!("Unexpected message: {}", message.name()),
+        }
+    }
+
+    // Ensure every block header decodes from each of its encodings, into its expected root.
+    for vector in &vectors.headers {
+        let header = vector.header().unwrap();
+        assert_eq!(hex::encode(header.to_bytes_le().unwrap()), vector.bytes, "{}", vector.name);
+        assert_eq!(header.height(), vector.height);
+        assert_eq!(header.to_root().unwrap().to_string(), vector.header_root);
+        assert_eq!(serde_json::from_str::<Header<CurrentNetwork>>(&vector.json).unwrap(), header);
+    }
+
+    // Ensure every transaction decodes from each of its encodings, into its expected ID and shape.
+    for vector in &vectors.transactions {
+        let transaction = vector.transaction().unwrap();
+        assert_eq!(hex::encode(transaction.to_bytes_le().unwrap()), vector.bytes, "{}", vector.name);
+        assert_eq!(transaction.id().to_string(), vector.id);
+        assert_eq!(transaction.is_coinbase(), vector.is_coinbase);
+        let num_inputs = transaction.transitions().map(|transition| transition.inputs().len()).sum::<usize>();
+        let num_outputs = transaction.transitions().map(|transition| transition.outputs().len()).sum::<usize>();
+        assert_eq!((num_inputs, num_outputs), (vector.num_inputs, vector.num_outputs));
+        assert_eq!(serde_json::from_str::<Transaction<CurrentNetwork>>(&vector.json).unwrap(), transaction);
+        match decode_frame(&vector.framed) {
+            Message::UnconfirmedTransaction(message) => {
+                assert_eq!(message.transaction_id.to_string(), vector.id);
+                assert_eq!(message.transaction.deserialize_blocking().unwrap(), transaction);
+            }
+            message => panic!("Unexpected message: {}", message.name()),
+        }
+    }
+}
+
+#[test]
+fn test_committed_header_eras() {
+    let rng = &mut TestRng::default();
+    let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+    let signer = Address::try_from(&private_key).unwrap();
+
+    let block = sample_genesis_block();
+    let commitments = HeaderCommitments::from_block(&block).unwrap();
+    assert_eq!(commitments.num_transactions as usize, block.transactions().len());
+
+    // Ensure a header below the activation height keeps the legacy layout, which is the header alone.
+    let legacy = CommittedHeader::from_block(&block, Some(1)).unwrap();
+    assert_eq!(legacy, CommittedHeader::legacy(*block.header()));
+    assert_eq!(legacy.to_bytes_le().unwrap(), block.header().to_bytes_le().unwrap());
+    assert_eq!(legacy.to_root().unwrap(), block.header().to_root().unwrap());
+    assert!(CommittedHeader::from_block(&block, None).unwrap().commitments.is_none());
+
+    // Ensure a header from the activation height on is followed by its commitments, which are hashed into its root,
+    // and by the signature of its root, without which it cannot be relayed.
+    let unsigned = CommittedHeader::from_block(&block, Some(0)).unwrap();
+    assert_eq!(unsigned.commitments, Some(commitments));
+    assert!(unsigned.to_bytes_le().is_err());
+    assert!(unsigned.check_signature(&signer).is_err());
+    let committed = unsigned.sign(&private_key, rng).unwrap();
+    committed.check_signature(&signer).unwrap();
+    legacy.check_signature(&signer).unwrap();
+    let bytes = committed.to_bytes_le().unwrap();
+    let signature_size = committed.signature.unwrap().to_bytes_le().unwrap().len();
+    assert_eq!(bytes.len(), legacy.to_bytes_le().unwrap().len() + HeaderCommitments::SIZE_IN_BYTES + signature_size);
+    assert_ne!(committed.to_root().unwrap(), legacy.to_root().unwrap());
+
+    // Ensure the signature binds the commitments to the signer.
+    let impostor = Address::try_from(&PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+    assert!(committed.check_signature(&impostor).is_err());
+    let mut forged = committed.clone();
+    forged.commitments.as_mut().unwrap().total_fees += 1;
+    assert!(forged.check_signature(&signer).is_err());
+    let mut stripped = legacy.clone();
+    stripped.signature = committed.signature;
+    assert!(stripped.check_signature(&signer).is_err());
+
+    // Ensure both eras decode, and are checked against the era of their height.
+    for (header, activation_height) in [(&legacy, Some(1)), (&committed, Some(0))] {
+        let candidate = CommittedHeader::<CurrentNetwork>::read_le(&header.to_bytes_le().unwrap()[..]).unwrap();
+        assert_eq!(&candidate, header);
+        candidate.check_layout(activation_height).unwrap();
+    }
+    assert!(legacy.check_layout(Some(0)).is_err());
+    assert!(committed.check_layout(Some(1)).is_err());
+    assert!(committed.check_layout(None).is_err());
+    // Ensure truncated or padded commitments are rejected.
+    assert!(CommittedHeader::<CurrentNetwork>::read_le(&bytes[..bytes.len() - 1]).is_err());
+    assert!(CommittedHeader::<CurrentNetwork>::read_le(&[&bytes[..], &[0u8]].concat()[..]).is_err());
+
+    // Ensure a puzzle response carries the committed header.
+    let epoch_challenge =
+        EpochChallenge::<CurrentNetwork>::new(0, block.hash(), CurrentNetwork::COINBASE_PUZZLE_DEGREE).unwrap();
+    let message = Message::PuzzleResponse(PuzzleResponse { epoch_challenge, block_header: Data::Object(committed) });
+    match Message::<CurrentNetwork>::deserialize(BytesMut::from(&serialize(&message)[..])).unwrap() {
+        Message::PuzzleResponse(response) => {
+            let header = response.block_header.deserialize_blocking().unwrap();
+            assert_eq!(header.commitments, Some(commitments));
+            header.check_signature(&signer).unwrap();
+        }
+        message => panic!("Unexpected message: {}", message.name()),
+    }
+}
+
+#[test]
+fn test_implausible_header_commitments() {
+    let commitments = HeaderCommitments { num_transactions: 1, total_fees: 0 };
+    commitments.check_plausibility::<CurrentNetwork>().unwrap();
+
+    // Ensure the commitments beyond the consensus limits are rejected without the body of the block.
+    let empty = HeaderCommitments { num_transactions: 0, ..commitments };
+    assert!(empty.check_plausibility::<CurrentNetwork>().is_err());
+    let oversized = HeaderCommitments { num_transactions: u32::MAX, ..commitments };
+    assert!(oversized.check_plausibility::<CurrentNetwork>().is_err());
+    let overpaid = HeaderCommitments { total_fees: u64::MAX, ..commitments };
+    assert!(overpaid.check_plausibility::<CurrentNetwork>().is_err());
+}
+
+#[test]
+fn test_ping_with_oversized_locators_is_rejected() {
+    // Serialize a ping without block locators.
+    let mut bytes = serialize(&Message::Ping(Ping::<CurrentNetwork>::new(NodeType::Client, None)));
+    assert_eq!(bytes.pop(), Some(0));
+
+    // Declare block locators with an arbitrary number of recent blocks, followed by a single entry.
+    // (Found by the `message_codec` fuzz target: the declared length used to be preallocated, aborting the process.)
+    bytes.push(1);
+    bytes.extend_from_slice(&(u64::MAX / 2).to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&[0u8; 32]);
+
+    // Ensure the ping is rejected, rather than aborting the process.
+    assert!(Message::<CurrentNetwork>::deserialize(BytesMut::from(&bytes[..])).is_err());
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_json_and_cbor_roundtrip() {
+    use crate::CborEncoding;
+    use snarkvm::prelude::Block;
+
+    let block = sample_genesis_block();
+
+    // Ensure the JSON encoding of the block and its transactions round-trips.
+    let candidate: Block<CurrentNetwork> = serde_json::from_str(&serde_json::to_string(&block).unwrap()).unwrap();
+    assert_eq!(candidate, block);
+    for transaction in block.transactions().iter() {
+        let candidate: Transaction<CurrentNetwork> =
+            serde_json::from_str(&serde_json::to_string(transaction).unwrap()).unwrap();
+        assert_eq!(&candidate, transaction);
+    }
+
+    // Ensure the CBOR encoding of the block and its transactions round-trips, and is stable.
+    let bytes = block.to_cbor().unwrap();
+    let candidate = Block::<CurrentNetwork>::from_cbor(&bytes).unwrap();
+    assert_eq!(candidate, block);
+    assert_eq!(candidate.to_cbor().unwrap(), bytes);
+    for transaction in block.transactions().iter() {
+        let bytes = transaction.to_cbor().unwrap();
+        let candidate = Transaction::<CurrentNetwork>::from_cbor(&bytes).unwrap();
+        assert_eq!(&candidate, transaction);
+        assert_eq!(candidate.to_cbor().unwrap(), bytes);
+    }
+}
@@ -20,6 +20,34 @@ use super::*;
 pub struct UnconfirmedTransaction<N: Network> {
     pub transaction_id: N::TransactionID,
     pub transaction: Data<Transaction<N>>,
+    /// The IDs of the transactions in the memory pool of the sender that the transaction depends on, which are
+    /// only sent to the peers that advertise `PARENT_TXS`, in an extended announcement.
+    pub parent_ids: Vec<N::TransactionID>,
+}
+
+impl<N: Network> UnconfirmedTransaction<N> {
+    /// Initializes a new announcement of the given transaction, without parents.
+    pub fn new(transaction_id: N::TransactionID, transaction: Data<Transaction<N>>) -> Self {
+        Self { transaction_id, transaction, parent_ids: Default::default() }
+    }
+
+    /// Returns the announcement with the given parents, which are truncated to `MAX_PARENT_TXS`.
+    pub fn with_parents(mut self, mut parent_ids: Vec<N::TransactionID>) -> Self {
+        parent_ids.truncate(MAX_PARENT_TXS);
+        self.parent_ids = parent_ids;
+        self
+    }
+
+    /// Deserializes the given buffer into an extended announcement, whose parents precede the transaction.
+    pub fn deserialize_with_parents(bytes: BytesMut) -> Result<Self> {
+        let mut reader = bytes.reader();
+        let transaction_id = N::TransactionID::read_le(&mut reader)?;
+        let parent_ids = read_transaction_ids::<N, _>(&mut reader)?;
+        if parent_ids.is_empty() {
+            bail!("Invalid 'UnconfirmedTransaction' message (an extended announcement without parents)");
+        }
+        Ok(Self { transaction_id, transaction: Data::Buffer(reader.into_inner().freeze()), parent_ids })
+    }
 }
 
 impl<N: Network> MessageTrait for UnconfirmedTransaction<N> {
@@ -33,6 +61,10 @@ impl<N: Network> MessageTrait for UnconfirmedTransaction<N> {
     #[inline]
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
         writer.write_all(&self.transaction_id.to_bytes_le()?)?;
+        // Write the parents of an extended announcement, which is sent under its own message ID.
+        if !self.parent_ids.is_empty() {
+            write_transaction_ids::<N, _>(writer, &self.parent_ids)?;
+        }
         self.transaction.serialize_blocking_into(writer)
     }
 
@@ -40,9 +72,6 @@ impl<N: Network> MessageTrait for UnconfirmedTransaction<N> {
     #[inline]
     fn deserialize(bytes: BytesMut) -> Result<Self> {
         let mut reader = bytes.reader();
-        Ok(Self {
-            transaction_id: N::TransactionID::read_le(&mut reader)?,
-            transaction: Data::Buffer(reader.into_inner().freeze()),
-        })
+        Ok(Self::new(N::TransactionID::read_le(&mut reader)?, Data::Buffer(reader.into_inner().freeze())))
     }
 }
@@ -23,10 +23,14 @@ pub use names::*;
 
 /// Initialises the metrics and returns a handle to the task running the metrics exporter.
 pub fn initialize() -> tokio::task::JoinHandle<()> {
-    use metrics_exporter_prometheus::PrometheusBuilder;
-
-    // Build the recorder and set as global.
-    let (recorder, exporter) = PrometheusBuilder::new().build().expect("can't build the prometheus exporter");
+    use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+    // Build the recorder and set as global, exporting the request durations as histograms rather than summaries.
+    let (recorder, exporter) = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(rest::REQUEST_DURATION.to_string()), &rest::REQUEST_DURATION_BUCKETS)
+        .expect("can't set the buckets of the request durations")
+        .build()
+        .expect("can't build the prometheus exporter");
     metrics::set_boxed_recorder(Box::new(recorder)).expect("can't set the prometheus exporter");
 
     // Spawn a dedicated task for the exporter on the runtime.
@@ -42,7 +46,13 @@ pub fn initialize() -> tokio::task::JoinHandle<()> {
 }
 
 fn register_metrics() {
+    for name in COUNTER_NAMES {
+        register_counter!(name);
+    }
     for name in GAUGE_NAMES {
         register_gauge!(name);
     }
+    for name in HISTOGRAM_NAMES {
+        register_histogram!(name);
+    }
 }
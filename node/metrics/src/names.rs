@@ -14,14 +14,83 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-pub const GAUGE_NAMES: [&str; 4] = [blocks::HEIGHT, peers::CONNECTED, peers::CANDIDATE, peers::RESTRICTED];
+pub const COUNTER_NAMES: [&str; 10] = [
+    audit::BLOCKS,
+    audit::FAILURES,
+    memory_pool::EXPIRED,
+    miner::DUPLICATE_SUBMISSIONS,
+    miner::TEMPLATE_ABORTS,
+    peers::MISMATCHED,
+    rest::CACHE_HITS,
+    rest::CACHE_MISSES,
+    rest::ERRORS,
+    rest::SLOW_REQUESTS,
+];
+
+pub const GAUGE_NAMES: [&str; 8] = [
+    blocks::HEIGHT,
+    peers::CONNECTED,
+    peers::CANDIDATE,
+    peers::RESTRICTED,
+    sync::QUEUE_BYTES,
+    sync::QUEUE_BLOCKS,
+    sync::ORPHAN_BYTES,
+    sync::ORPHAN_BLOCKS,
+];
+
+pub const HISTOGRAM_NAMES: [&str; 6] = [
+    miner::TEMPLATE_BUILD_DURATION,
+    rest::REQUEST_DURATION,
+    rest::REQUEST_BYTES,
+    rest::RESPONSE_BYTES,
+    sync::STALL_DURATION,
+    sync::ORPHAN_RESOLUTION_DURATION,
+];
+
+pub mod audit {
+    pub const BLOCKS: &str = "snarkos_audit_blocks_total";
+    pub const FAILURES: &str = "snarkos_audit_failures_total";
+}
 
 pub mod blocks {
     pub const HEIGHT: &str = "snarkos_blocks_height_total";
 }
 
+pub mod memory_pool {
+    pub const EXPIRED: &str = "snarkos_memory_pool_expired_total";
+}
+
+pub mod miner {
+    pub const DUPLICATE_SUBMISSIONS: &str = "snarkos_miner_duplicate_submissions_total";
+    pub const TEMPLATE_ABORTS: &str = "snarkos_miner_template_aborts_total";
+    pub const TEMPLATE_BUILD_DURATION: &str = "snarkos_miner_template_build_duration_seconds";
+}
+
 pub mod peers {
     pub const CONNECTED: &str = "snarkos_peers_connected_total";
     pub const CANDIDATE: &str = "snarkos_peers_candidate_total";
     pub const RESTRICTED: &str = "snarkos_peers_restricted_total";
+    pub const MISMATCHED: &str = "snarkos_peers_mismatched_total";
+}
+
+pub mod rest {
+    pub const CACHE_HITS: &str = "snarkos_rest_cache_hits_total";
+    pub const CACHE_MISSES: &str = "snarkos_rest_cache_misses_total";
+    pub const REQUEST_DURATION: &str = "snarkos_rest_request_duration_seconds";
+    pub const REQUEST_BYTES: &str = "snarkos_rest_request_bytes";
+    pub const RESPONSE_BYTES: &str = "snarkos_rest_response_bytes";
+    pub const ERRORS: &str = "snarkos_rest_errors_total";
+    pub const SLOW_REQUESTS: &str = "snarkos_rest_slow_requests_total";
+
+    /// The upper bounds (in seconds) of the buckets of the request durations.
+    pub const REQUEST_DURATION_BUCKETS: [f64; 11] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+}
+
+pub mod sync {
+    pub const QUEUE_BYTES: &str = "snarkos_sync_queue_bytes";
+    pub const QUEUE_BLOCKS: &str = "snarkos_sync_queue_blocks";
+    pub const STALL_DURATION: &str = "snarkos_sync_stall_duration_seconds";
+    pub const ORPHAN_BYTES: &str = "snarkos_sync_orphan_bytes";
+    pub const ORPHAN_BLOCKS: &str = "snarkos_sync_orphan_blocks";
+    pub const ORPHAN_RESOLUTION_DURATION: &str = "snarkos_sync_orphan_resolution_duration_seconds";
 }
@@ -0,0 +1,458 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use snarkvm::console::{
+    account::{PrivateKey, ViewKey},
+    program::{Entry, Literal, Plaintext, Record, Value},
+    types::U64,
+};
+
+use std::{collections::HashSet, fmt};
+use tokio::sync::Semaphore;
+use warp::{http::StatusCode, reply::Response};
+
+/// The default maximum number of transfers that are proven concurrently.
+pub const DEFAULT_MAX_CONCURRENT_PROOFS: usize = 2;
+
+/// The reason a transfer could not be created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The account has no unspent records, other than those spent by the memory pool or by transfers in progress.
+    NoSpendableRecords,
+    /// No spendable record covers the required amount, and the largest spendable record holds the given balance.
+    InsufficientFunds { required: u64, largest_record: u64 },
+    /// The transaction could not be proven, with the given error.
+    ProvingFailed { reason: String },
+    /// The transaction was not admitted to the memory pool, with the given error.
+    Rejected { reason: String },
+    /// The records of the account could not be read from the ledger, with the given error.
+    Storage { reason: String },
+}
+
+impl BuildError {
+    /// Returns the error code of the error.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::NoSpendableRecords => "no_spendable_records",
+            Self::InsufficientFunds { .. } => "insufficient_funds",
+            Self::ProvingFailed { .. } => "proving_failed",
+            Self::Rejected { .. } => "rejected",
+            Self::Storage { .. } => "storage_error",
+        }
+    }
+
+    /// Returns the status code of the error.
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::NoSpendableRecords | Self::InsufficientFunds { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Rejected { .. } => StatusCode::BAD_REQUEST,
+            Self::ProvingFailed { .. } | Self::Storage { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Returns the reply for the error, with its error code and message.
+    pub fn reply(&self) -> Response {
+        let body = serde_json::json!({ "code": self.code(), "message": self.to_string() });
+        reply::with_status(reply::json(&body), self.status()).into_response()
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSpendableRecords => write!(f, "The account has no spendable records"),
+            Self::InsufficientFunds { required, largest_record } => {
+                write!(f, "No spendable record covers {required} microcredits (the largest holds {largest_record})")
+            }
+            Self::ProvingFailed { reason } => write!(f, "Failed to prove the transfer: {reason}"),
+            Self::Rejected { reason } => write!(f, "The memory pool rejected the transfer: {reason}"),
+            Self::Storage { reason } => write!(f, "Failed to read the records of the account: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl warp::reject::Reject for BuildError {}
+
+/// A record selected to pay a transfer or its fee, with its commitment.
+type SelectedRecord<N> = (Field<N>, Record<N, Plaintext<N>>);
+
+/// A transfer that was created and admitted to the memory pool.
+#[derive(Clone, Debug)]
+pub struct CreatedTransfer<N: Network> {
+    /// The transaction of the transfer.
+    pub transaction: Transaction<N>,
+    /// The commitment of the record that returns the change of the transfer to the sender.
+    pub change_commitment: Field<N>,
+    /// The commitment of the record that returns the change of the fee to the sender, if a fee is paid.
+    pub fee_change_commitment: Option<Field<N>>,
+}
+
+/// The `create_transfer` request object.
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct CreateTransferRequest<N: Network> {
+    /// The private key of the sender.
+    private_key: PrivateKey<N>,
+    /// The address of the recipient.
+    to: Address<N>,
+    /// The amount to transfer (in microcredits).
+    amount: u64,
+    /// The fee to pay (in microcredits) [default: 0].
+    #[serde(default)]
+    fee: u64,
+}
+
+/// The `create_transfer` response object.
+#[derive(Serialize)]
+struct CreateTransferResponse {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The commitment of the change record of the transfer.
+    change_commitment: String,
+    /// The commitment of the change record of the fee, if a fee is paid.
+    fee_change_commitment: Option<String>,
+}
+
+/// Creates transfers from the unspent records of an account, proves them locally, and admits them to the memory pool.
+#[derive(Clone)]
+pub struct TransactionBuilder<N: Network, C: ConsensusStorage<N>> {
+    /// The ledger.
+    ledger: Ledger<N, C>,
+    /// The consensus module.
+    consensus: Consensus<N, C>,
+    /// The permits to prove a transfer, which bound the number of concurrent proofs.
+    permits: Arc<Semaphore>,
+    /// The commitments of the records selected by the transfers in progress.
+    reserved: Arc<Mutex<HashSet<Field<N>>>>,
+}
+
+impl<N: Network, C: 'static + ConsensusStorage<N>> TransactionBuilder<N, C> {
+    /// Initializes a new builder, which proves at most the given number of transfers concurrently.
+    pub fn new(ledger: Ledger<N, C>, consensus: Consensus<N, C>, max_concurrent_proofs: usize) -> Self {
+        Self {
+            ledger,
+            consensus,
+            permits: Arc::new(Semaphore::new(max_concurrent_proofs.max(1))),
+            reserved: Default::default(),
+        }
+    }
+
+    /// Creates a transfer of the given amount from the account of the given private key to the given address,
+    /// paying the given fee, and adds it to the memory pool.
+    pub async fn create_transfer(
+        &self,
+        private_key: PrivateKey<N>,
+        to: Address<N>,
+        amount: u64,
+        fee: u64,
+    ) -> Result<CreatedTransfer<N>, BuildError> {
+        // Wait for a permit, as proving a transfer saturates the CPU.
+        let _permit = self.permits.acquire().await.map_err(|e| BuildError::ProvingFailed { reason: e.to_string() })?;
+
+        // Select the records, prove the transfer, and admit it to the memory pool on a dedicated blocking worker.
+        let builder = self.clone();
+        match tokio::task::spawn_blocking(move || builder.build_transfer(&private_key, to, amount, fee)).await {
+            Ok(transfer) => transfer,
+            Err(error) => Err(BuildError::ProvingFailed { reason: error.to_string() }),
+        }
+    }
+
+    /// Creates a transfer, and adds it to the memory pool.
+    fn build_transfer(
+        &self,
+        private_key: &PrivateKey<N>,
+        to: Address<N>,
+        amount: u64,
+        fee: u64,
+    ) -> Result<CreatedTransfer<N>, BuildError> {
+        // Select and reserve the records, until the transfer is admitted to the memory pool or fails.
+        let (record, fee_record) = self.reserve_records(private_key, amount, fee)?;
+        let commitments = [Some(record.0), fee_record.as_ref().map(|(commitment, _)| *commitment)];
+        let result =
+            self.prove_and_admit(private_key, to, amount, record.1, fee_record.map(|(_, record)| (record, fee)));
+        self.reserved.lock().retain(|commitment| !commitments.contains(&Some(*commitment)));
+        let transaction = result?;
+
+        // Find the change records, which are the second output of the transfer and the output of the fee.
+        let mut transitions = transaction.transitions();
+        let change_commitment = match transitions.next().and_then(|transition| transition.commitments().nth(1)) {
+            Some(commitment) => *commitment,
+            None => return Err(BuildError::ProvingFailed { reason: "The transfer has no change record".to_string() }),
+        };
+        let fee_change_commitment = match fee {
+            0 => None,
+            _ => transitions.last().and_then(|transition| transition.commitments().next().copied()),
+        };
+
+        Ok(CreatedTransfer { transaction, change_commitment, fee_change_commitment })
+    }
+
+    /// Selects and reserves the spendable records of the account to pay the given amount and fee,
+    /// and returns the records with their commitments.
+    fn reserve_records(
+        &self,
+        private_key: &PrivateKey<N>,
+        amount: u64,
+        fee: u64,
+    ) -> Result<(SelectedRecord<N>, Option<SelectedRecord<N>>), BuildError> {
+        // Fetch the unspent records of the account.
+        let view_key = ViewKey::try_from(private_key).map_err(|e| BuildError::Storage { reason: e.to_string() })?;
+        let mut records =
+            self.ledger.find_unspent_records(&view_key).map_err(|e| BuildError::Storage { reason: e.to_string() })?;
+
+        // Exclude the records that are spent by the memory pool, or reserved by the transfers in progress.
+        let pending = self.consensus.memory_pool().unconfirmed_serial_numbers();
+        let mut reserved = self.reserved.lock();
+        records.retain(|commitment, _| {
+            !reserved.contains(commitment)
+                && Record::<N, Plaintext<N>>::serial_number(*private_key, *commitment)
+                    .map_or(false, |serial_number| !pending.contains(&serial_number))
+        });
+
+        // Select the records, and reserve them.
+        let balances =
+            records.iter().map(|(commitment, record)| (*commitment, microcredits(record))).collect::<Vec<_>>();
+        let (commitment, fee_commitment) = select_records(&balances, amount, fee)?;
+        reserved.insert(commitment);
+        reserved.extend(fee_commitment);
+
+        let take = |commitment: Field<N>| (commitment, records[&commitment].clone());
+        Ok((take(commitment), fee_commitment.map(take)))
+    }
+
+    /// Proves the transfer of the given amount from the given record, paying the fee from the given record,
+    /// and adds the transaction to the memory pool.
+    fn prove_and_admit(
+        &self,
+        private_key: &PrivateKey<N>,
+        to: Address<N>,
+        amount: u64,
+        record: Record<N, Plaintext<N>>,
+        fee: Option<(Record<N, Plaintext<N>>, u64)>,
+    ) -> Result<Transaction<N>, BuildError> {
+        // Prove the transfer.
+        let inputs = [
+            Value::Record(record),
+            Value::Plaintext(Plaintext::from(Literal::Address(to))),
+            Value::Plaintext(Plaintext::from(Literal::U64(U64::new(amount)))),
+        ];
+        let rng = &mut rand::thread_rng();
+        let transaction = Transaction::execute(
+            self.ledger.vm(),
+            private_key,
+            ("credits.aleo", "transfer"),
+            inputs.into_iter(),
+            fee,
+            None,
+            rng,
+        )
+        .map_err(|e| BuildError::ProvingFailed { reason: e.to_string() })?;
+
+        // Add the transaction to the memory pool, and mark it as local, so that its expiry is reported.
+        self.consensus
+            .add_unconfirmed_transaction(transaction.clone())
+            .map_err(|e| BuildError::Rejected { reason: e.to_string() })?;
+        self.consensus.memory_pool().mark_local_transaction(transaction.id());
+
+        Ok(transaction)
+    }
+}
+
+/// Returns the microcredits of the given record.
+fn microcredits<N: Network>(record: &Record<N, Plaintext<N>>) -> u64 {
+    match Identifier::from_str("microcredits").ok().and_then(|microcredits| record.data().get(&microcredits).cloned()) {
+        Some(Entry::Private(Plaintext::Literal(Literal::U64(amount), _))) => *amount,
+        _ => 0,
+    }
+}
+
+/// Selects a record that covers the given amount, and a distinct record that covers the given fee if it is nonzero,
+/// from the given commitments and balances, and returns their commitments.
+///
+/// The larger of the two requirements is covered first, each by the smallest sufficient record.
+fn select_records<F: Copy + PartialEq>(
+    balances: &[(F, u64)],
+    amount: u64,
+    fee: u64,
+) -> Result<(F, Option<F>), BuildError> {
+    // Returns the smallest record that covers the given requirement, other than the excluded record.
+    let smallest = |required: u64, excluded: Option<F>| {
+        balances
+            .iter()
+            .filter(|(commitment, balance)| *balance >= required && Some(*commitment) != excluded)
+            .min_by_key(|(_, balance)| *balance)
+            .map(|(commitment, _)| *commitment)
+    };
+    // Returns the error for the given requirement, given the excluded record.
+    let insufficient = |required: u64, excluded: Option<F>| {
+        let largest_record = balances
+            .iter()
+            .filter(|(commitment, _)| Some(*commitment) != excluded)
+            .map(|(_, balance)| *balance)
+            .max()
+            .unwrap_or_default();
+        BuildError::InsufficientFunds { required, largest_record }
+    };
+
+    if balances.is_empty() {
+        return Err(BuildError::NoSpendableRecords);
+    }
+    if fee == 0 {
+        return smallest(amount, None).map(|commitment| (commitment, None)).ok_or_else(|| insufficient(amount, None));
+    }
+
+    // Cover the larger requirement first, so that the smaller one may use any of the remaining records.
+    let (first, second) = if amount >= fee { (amount, fee) } else { (fee, amount) };
+    let larger = smallest(first, None).ok_or_else(|| insufficient(first, None))?;
+    let smaller = smallest(second, Some(larger)).ok_or_else(|| insufficient(second, Some(larger)))?;
+    match amount >= fee {
+        true => Ok((larger, Some(smaller))),
+        false => Ok((smaller, Some(larger))),
+    }
+}
+
+impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
+    /// Initializes the routes of the transaction builder.
+    pub(crate) fn builder_routes(&self) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        // POST /testnet3/transaction/createTransfer
+        warp::post()
+            .and(warp::path!("testnet3" / "transaction" / "createTransfer"))
+            .and(with_auth())
+            .and(warp::body::content_length_limit(16 * 1024))
+            .and(warp::body::json())
+            .and(with(self.builder.clone()))
+            .and(with(self.routing.clone()))
+            .and_then(Self::create_transfer)
+    }
+
+    /// Creates a transfer from the records of the given account, adds it to the memory pool, and broadcasts it.
+    async fn create_transfer(
+        _auth: (),
+        request: CreateTransferRequest<N>,
+        builder: Option<TransactionBuilder<N, C>>,
+        routing: Arc<R>,
+    ) -> Result<impl Reply, Rejection> {
+        let builder = match builder {
+            Some(builder) => builder,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+
+        // Create the transfer.
+        let CreateTransferRequest { private_key, to, amount, fee } = request;
+        let transfer = builder.create_transfer(private_key, to, amount, fee).await.map_err(reject::custom)?;
+
+        // Diffuse the transaction to the network.
+        let transaction_id = transfer.transaction.id();
+        routing.diffuse(UnconfirmedTransaction::new(transaction_id, Data::Object(transfer.transaction)));
+
+        Ok(reply::json(&CreateTransferResponse {
+            transaction_id: transaction_id.to_string(),
+            change_commitment: transfer.change_commitment.to_string(),
+            fee_change_commitment: transfer.fee_change_commitment.map(|commitment| commitment.to_string()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::{
+        prelude::{Block, TestRng, Testnet3},
+        synthesizer::{store::ConsensusStore, ConsensusMemory, VM},
+    };
+
+    type CurrentNetwork = Testnet3;
+    type CurrentBuilder = TransactionBuilder<CurrentNetwork, ConsensusMemory<CurrentNetwork>>;
+
+    /// Returns a builder for a ledger, whose genesis records belong to the given private key.
+    fn sample_builder(private_key: &PrivateKey<CurrentNetwork>, rng: &mut TestRng) -> CurrentBuilder {
+        let vm = VM::from(ConsensusStore::open(None).unwrap()).unwrap();
+        let genesis = Block::genesis(&vm, private_key, rng).unwrap();
+        let ledger = Ledger::load(genesis, None).unwrap();
+        let consensus = Consensus::new(ledger.clone(), true).unwrap();
+        TransactionBuilder::new(ledger, consensus, 1)
+    }
+
+    #[test]
+    fn test_select_records() {
+        // Ensure an account without records has no spendable records.
+        assert_eq!(select_records::<u8>(&[], 10, 0), Err(BuildError::NoSpendableRecords));
+
+        // Ensure the smallest record that covers the amount is selected.
+        let balances = [(0u8, 50), (1, 20), (2, 30)];
+        assert_eq!(select_records(&balances, 25, 0), Ok((2, None)));
+        assert_eq!(select_records(&balances, 50, 0), Ok((0, None)));
+        assert_eq!(
+            select_records(&balances, 51, 0),
+            Err(BuildError::InsufficientFunds { required: 51, largest_record: 50 })
+        );
+
+        // Ensure the larger requirement is covered first, so that both are covered when possible.
+        assert_eq!(select_records(&balances, 25, 40), Ok((2, Some(0))));
+        assert_eq!(select_records(&balances, 40, 25), Ok((0, Some(2))));
+        assert_eq!(select_records(&balances, 20, 20), Ok((1, Some(2))));
+
+        // Ensure the fee is paid from a distinct record.
+        assert_eq!(
+            select_records(&[(0u8, 50)], 10, 10),
+            Err(BuildError::InsufficientFunds { required: 10, largest_record: 0 })
+        );
+        assert_eq!(
+            select_records(&[(0u8, 50), (1, 20)], 30, 30),
+            Err(BuildError::InsufficientFunds { required: 30, largest_record: 20 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_transfer() {
+        let rng = &mut TestRng::default();
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+        let builder = sample_builder(&private_key, rng);
+        let recipient = Address::try_from(PrivateKey::<CurrentNetwork>::new(rng).unwrap()).unwrap();
+
+        // Ensure an account without records is reported as such.
+        let stranger = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+        let error = builder.create_transfer(stranger, recipient, 1, 0).await.unwrap_err();
+        assert_eq!(error, BuildError::NoSpendableRecords);
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Ensure a transfer that no record covers is reported as insufficient funds.
+        let error = builder.create_transfer(private_key, recipient, u64::MAX, 0).await.unwrap_err();
+        assert!(matches!(error, BuildError::InsufficientFunds { required: u64::MAX, .. }), "{error}");
+        assert_eq!(error.code(), "insufficient_funds");
+
+        // Create a transfer with a fee.
+        let transfer = builder.create_transfer(private_key, recipient, 100, 10).await.unwrap();
+        let transaction_id = transfer.transaction.id();
+
+        // Ensure the transfer is in the memory pool, and its change records are outputs of the transaction.
+        let memory_pool = builder.consensus.memory_pool();
+        assert!(memory_pool.contains_unconfirmed_transaction(transaction_id));
+        let commitments = transfer.transaction.commitments().copied().collect::<Vec<_>>();
+        assert!(commitments.contains(&transfer.change_commitment));
+        assert!(commitments.contains(&transfer.fee_change_commitment.unwrap()));
+        assert!(builder.reserved.lock().is_empty());
+
+        // Ensure the records spent by the memory pool are not selected again, as the memory pool rejects conflicts.
+        let transfer = builder.create_transfer(private_key, recipient, 100, 0).await.unwrap();
+        assert!(memory_pool.contains_unconfirmed_transaction(transfer.transaction.id()));
+        assert_eq!(memory_pool.num_unconfirmed_transactions(), 2);
+        assert_eq!(transfer.fee_change_commitment, None);
+    }
+}
@@ -28,9 +28,17 @@ use warp::{reject, Filter, Rejection};
 /// The time a jwt token is valid for.
 pub const EXPIRATION: i64 = 10 * 365 * 24 * 60 * 60; // 10 years.
 
+/// The JWT secret for the node instance.
+static SECRET: OnceCell<Vec<u8>> = OnceCell::new();
+
+/// Sets the JWT secret for the node instance, instead of a random one.
+/// This must be called before any token is issued, and can only be called once.
+pub fn set_jwt_secret(secret: Vec<u8>) -> Result<()> {
+    SECRET.set(secret).map_err(|_| anyhow!("The JWT secret is already set"))
+}
+
 /// Returns the JWT secret for the node instance.
 fn jwt_secret() -> &'static Vec<u8> {
-    static SECRET: OnceCell<Vec<u8>> = OnceCell::new();
     SECRET.get_or_init(|| {
         let seed: [u8; 16] = ::rand::thread_rng().gen();
         seed.to_vec()
@@ -69,26 +77,29 @@ impl Claims {
 
 /// Checks the authorization header for a valid token.
 pub fn with_auth() -> impl Filter<Extract = ((),), Error = Rejection> + Clone {
-    warp::header::<String>("authorization").and_then(|token: String| async move {
-        if !token.starts_with("Bearer ") {
-            return Err(reject::custom(RestError::Request("Invalid authorization header.".to_string())));
-        }
+    warp::header::<String>("authorization").and_then(|token: String| async move { verify_token(&token) })
+}
 
-        // Decode the claims from the token.
-        match decode::<Claims>(
-            token.trim_start_matches("Bearer "),
-            &DecodingKey::from_secret(jwt_secret()),
-            &Validation::new(Algorithm::HS256),
-        ) {
-            Ok(decoded) => {
-                let claims = decoded.claims;
-                if claims.is_expired() {
-                    return Err(reject::custom(RestError::Request("Expired JSON Web Token.".to_string())));
-                }
-
-                Ok(())
+/// Ensures the given authorization header holds a valid, unexpired token.
+pub(crate) fn verify_token(token: &str) -> Result<(), Rejection> {
+    if !token.starts_with("Bearer ") {
+        return Err(reject::custom(RestError::Request("Invalid authorization header.".to_string())));
+    }
+
+    // Decode the claims from the token.
+    match decode::<Claims>(
+        token.trim_start_matches("Bearer "),
+        &DecodingKey::from_secret(jwt_secret()),
+        &Validation::new(Algorithm::HS256),
+    ) {
+        Ok(decoded) => {
+            let claims = decoded.claims;
+            if claims.is_expired() {
+                return Err(reject::custom(RestError::Request("Expired JSON Web Token.".to_string())));
             }
-            Err(_) => Err(reject::custom(RestError::Request("Unauthorized caller.".to_string()))),
+
+            Ok(())
         }
-    })
+        Err(_) => Err(reject::custom(RestError::Request("Unauthorized caller.".to_string()))),
+    }
 }
@@ -0,0 +1,427 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+use snarkvm::prelude::Network;
+
+use anyhow::Result;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use warp::{http::header::CONTENT_TYPE, reply::Response, Reply};
+
+/// The default maximum number of bytes of the cached responses.
+pub const DEFAULT_CACHE_BYTE_BUDGET: usize = 64 * 1024 * 1024;
+/// The default time after which a cached response expires.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+/// The number of blocks on top of a block, after which the block is considered buried,
+/// and its responses are no longer keyed by the latest block.
+pub const CACHE_BURIAL_DEPTH: u32 = 10;
+
+/// A serialized response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedResponse {
+    /// The serialized body of the response.
+    body: Arc<Vec<u8>>,
+    /// The content type of the response.
+    content_type: &'static str,
+}
+
+impl CachedResponse {
+    /// Initializes a new response with the given body and content type.
+    pub fn new(body: Vec<u8>, content_type: &'static str) -> Self {
+        Self { body: Arc::new(body), content_type }
+    }
+
+    /// Initializes a new response with the JSON encoding of the given object.
+    pub fn json<T: Serialize>(object: &T) -> Result<Self> {
+        Ok(Self::new(serde_json::to_vec(object)?, "application/json"))
+    }
+
+    /// Returns the serialized body of the response.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+impl Reply for CachedResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(self.body.to_vec().into());
+        response.headers_mut().insert(CONTENT_TYPE, self.content_type.parse().expect("invalid content type"));
+        response
+    }
+}
+
+/// The hits and misses of the cache for a method.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CacheStatistics {
+    /// The number of requests served from the cache.
+    pub hits: u64,
+    /// The number of requests that were computed.
+    pub misses: u64,
+    /// The ratio of the requests served from the cache.
+    pub hit_rate: f64,
+}
+
+/// The key of a cached response.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// The name of the method.
+    method: &'static str,
+    /// The parameters of the request.
+    params: String,
+    /// The epoch of the latest block, for responses that are not buried.
+    epoch: Option<u64>,
+}
+
+/// A cached response, with the block it was computed from.
+struct CacheEntry<N: Network> {
+    /// The cached response.
+    response: CachedResponse,
+    /// The height and hash of the block the response was computed from, if any.
+    anchor: Option<(u32, N::BlockHash)>,
+    /// The time at which the response was cached.
+    inserted_at: Instant,
+}
+
+/// The state of the response cache.
+struct CacheState<N: Network> {
+    /// The height and hash of the latest block.
+    tip: Option<(u32, N::BlockHash)>,
+    /// The epoch of the latest block, which increments whenever the latest block changes.
+    epoch: u64,
+    /// The cached responses, in least-recently used order.
+    entries: LinkedHashMap<CacheKey, CacheEntry<N>>,
+    /// The number of bytes of the cached responses.
+    num_bytes: usize,
+    /// The hits and misses for each method.
+    statistics: BTreeMap<&'static str, (u64, u64)>,
+}
+
+impl<N: Network> CacheState<N> {
+    /// Removes the entries that do not satisfy the given predicate.
+    fn retain(&mut self, mut predicate: impl FnMut(&CacheKey, &CacheEntry<N>) -> bool) {
+        let stale = self.entries.iter().filter(|(key, entry)| !predicate(key, entry)).map(|(key, _)| key.clone());
+        for key in stale.collect::<Vec<_>>() {
+            self.remove(&key);
+        }
+    }
+
+    /// Removes the entry for the given key.
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.num_bytes -= entry.response.body.len();
+        }
+    }
+}
+
+/// A cache of serialized responses for expensive read-only requests.
+///
+/// Responses for blocks that are buried under `CACHE_BURIAL_DEPTH` blocks are cached until they expire,
+/// while the remaining responses are keyed by the epoch of the latest block, and are recomputed once the
+/// latest block changes. On a reorg, the responses computed from a block that is no longer canonical are evicted.
+pub struct ResponseCache<N: Network> {
+    /// The maximum number of bytes of the cached responses.
+    byte_budget: usize,
+    /// The time after which a cached response expires.
+    ttl: Duration,
+    /// The state of the cache.
+    state: Mutex<CacheState<N>>,
+}
+
+impl<N: Network> Default for ResponseCache<N> {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_BYTE_BUDGET, DEFAULT_CACHE_TTL)
+    }
+}
+
+impl<N: Network> ResponseCache<N> {
+    /// Initializes a new response cache with the given byte budget and time-to-live.
+    pub fn new(byte_budget: usize, ttl: Duration) -> Self {
+        Self {
+            byte_budget,
+            ttl,
+            state: Mutex::new(CacheState {
+                tip: None,
+                epoch: 0,
+                entries: Default::default(),
+                num_bytes: 0,
+                statistics: Default::default(),
+            }),
+        }
+    }
+
+    /// Returns the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.state.lock().entries.len()
+    }
+
+    /// Returns `true` if there are no cached responses.
+    pub fn is_empty(&self) -> bool {
+        self.state.lock().entries.is_empty()
+    }
+
+    /// Returns the number of bytes of the cached responses.
+    pub fn num_bytes(&self) -> usize {
+        self.state.lock().num_bytes
+    }
+
+    /// Returns the hits and misses of the cache for each method.
+    pub fn statistics(&self) -> BTreeMap<&'static str, CacheStatistics> {
+        self.state
+            .lock()
+            .statistics
+            .iter()
+            .map(|(method, (hits, misses))| {
+                let hit_rate = match hits + misses {
+                    0 => 0.0,
+                    total => *hits as f64 / total as f64,
+                };
+                (*method, CacheStatistics { hits: *hits, misses: *misses, hit_rate })
+            })
+            .collect()
+    }
+
+    /// Updates the latest block of the cache, where `canonical_hash` returns the canonical block hash at a height.
+    ///
+    /// If the latest block changed, the responses that are not buried are evicted. If the previous latest block
+    /// is no longer canonical (i.e. a reorg occurred), the responses computed from a non-canonical block are evicted.
+    pub fn update_tip(&self, height: u32, hash: N::BlockHash, canonical_hash: impl Fn(u32) -> Option<N::BlockHash>) {
+        let mut state = self.state.lock();
+        // If the latest block is unchanged, return early.
+        if state.tip == Some((height, hash)) {
+            return;
+        }
+
+        // Evict the responses that are keyed by the previous epoch.
+        state.epoch += 1;
+        state.retain(|key, _| key.epoch.is_none());
+
+        // If the previous latest block is no longer canonical, evict the responses of the disconnected blocks.
+        if let Some((previous_height, previous_hash)) = state.tip {
+            if canonical_hash(previous_height) != Some(previous_hash) {
+                debug!("Evicting the cached responses for the blocks disconnected from block {previous_height}");
+                state.retain(|_, entry| match entry.anchor {
+                    Some((height, hash)) => canonical_hash(height) == Some(hash),
+                    None => true,
+                });
+            }
+        }
+        state.tip = Some((height, hash));
+    }
+
+    /// Returns the cached response for the given method and parameters, or computes and caches it.
+    ///
+    /// The `anchor` is the height and hash of the block the response is computed from, if any.
+    /// Responses without an anchor, or with an anchor that is not buried, are keyed by the epoch of the latest block.
+    pub fn get_or_compute<E>(
+        &self,
+        method: &'static str,
+        params: String,
+        anchor: Option<(u32, N::BlockHash)>,
+        compute: impl FnOnce() -> core::result::Result<CachedResponse, E>,
+    ) -> core::result::Result<CachedResponse, E> {
+        // Construct the key of the response.
+        let key = {
+            let state = self.state.lock();
+            let latest_height = state.tip.map(|(height, _)| height).unwrap_or_default();
+            let is_buried =
+                matches!(anchor, Some((height, _)) if height.saturating_add(CACHE_BURIAL_DEPTH) <= latest_height);
+            CacheKey { method, params, epoch: (!is_buried).then_some(state.epoch) }
+        };
+
+        // Return the cached response, if it exists and has not expired.
+        {
+            let mut state = self.state.lock();
+            let ttl = self.ttl;
+            match state.entries.get_refresh(&key) {
+                Some(entry) if entry.inserted_at.elapsed() < ttl => {
+                    let response = entry.response.clone();
+                    Self::record(&mut state, method, true);
+                    return Ok(response);
+                }
+                Some(_) => state.remove(&key),
+                None => (),
+            }
+            Self::record(&mut state, method, false);
+        }
+
+        // Compute the response, without holding the lock.
+        let response = compute()?;
+
+        // Cache the response, if it fits in the byte budget.
+        let num_bytes = response.body.len();
+        if num_bytes <= self.byte_budget {
+            let mut state = self.state.lock();
+            // Ensure the response was not computed for a previous epoch.
+            if key.epoch.is_none() || key.epoch == Some(state.epoch) {
+                state.remove(&key);
+                // Evict the least-recently used responses, until the response fits in the byte budget.
+                while state.num_bytes + num_bytes > self.byte_budget {
+                    match state.entries.pop_front() {
+                        Some((_, entry)) => state.num_bytes -= entry.response.body.len(),
+                        None => break,
+                    }
+                }
+                state.num_bytes += num_bytes;
+                state.entries.insert(key, CacheEntry {
+                    response: response.clone(),
+                    anchor,
+                    inserted_at: Instant::now(),
+                });
+            }
+        }
+        Ok(response)
+    }
+
+    /// Records a hit or a miss for the given method.
+    fn record(state: &mut CacheState<N>, method: &'static str, is_hit: bool) {
+        let (hits, misses) = state.statistics.entry(method).or_default();
+        match is_hit {
+            true => {
+                *hits += 1;
+                #[cfg(feature = "metrics")]
+                metrics::increment_counter!(metrics::rest::CACHE_HITS, "method" => method);
+            }
+            false => {
+                *misses += 1;
+                #[cfg(feature = "metrics")]
+                metrics::increment_counter!(metrics::rest::CACHE_MISSES, "method" => method);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Field, TestRng, Testnet3, Uniform};
+
+    use std::cell::Cell;
+
+    type CurrentNetwork = Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// Returns a chain of `num_blocks` random block hashes.
+    fn sample_chain(num_blocks: u32, rng: &mut TestRng) -> Vec<BlockHash> {
+        (0..num_blocks).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect()
+    }
+
+    /// Returns the cached `get_block` response for the given height in the given chain, and whether it was computed.
+    fn get_block(cache: &ResponseCache<CurrentNetwork>, chain: &[BlockHash], height: u32) -> (CachedResponse, bool) {
+        let computed = Cell::new(false);
+        let hash = chain[height as usize];
+        let response = cache
+            .get_or_compute::<()>("get_block", height.to_string(), Some((height, hash)), || {
+                computed.set(true);
+                Ok(CachedResponse::new(hash.to_string().into_bytes(), "application/json"))
+            })
+            .unwrap();
+        (response, computed.get())
+    }
+
+    /// Updates the latest block of the cache to the tip of the given chain.
+    fn update_tip(cache: &ResponseCache<CurrentNetwork>, chain: &[BlockHash]) {
+        let height = chain.len() as u32 - 1;
+        cache.update_tip(height, chain[height as usize], |height| chain.get(height as usize).copied());
+    }
+
+    #[test]
+    fn test_cache_hits() {
+        let cache = ResponseCache::<CurrentNetwork>::default();
+        let chain = sample_chain(20, &mut TestRng::default());
+        update_tip(&cache, &chain);
+
+        // Ensure the response is computed once, and served from the cache afterwards.
+        let (response, computed) = get_block(&cache, &chain, 5);
+        assert!(computed);
+        assert_eq!(get_block(&cache, &chain, 5), (response.clone(), false));
+        assert_eq!(response.body(), chain[5].to_string().as_bytes());
+
+        let statistics = cache.statistics()["get_block"];
+        assert_eq!((statistics.hits, statistics.misses), (1, 1));
+        assert_eq!(statistics.hit_rate, 0.5);
+        assert_eq!(cache.num_bytes(), response.body().len());
+    }
+
+    #[test]
+    fn test_cache_reorg() {
+        let rng = &mut TestRng::default();
+        let cache = ResponseCache::<CurrentNetwork>::default();
+        let chain = sample_chain(20, rng);
+        update_tip(&cache, &chain);
+
+        // Cache two buried blocks.
+        let deep_height = 2;
+        let disconnected_height = 8;
+        assert!(get_block(&cache, &chain, deep_height).1);
+        assert!(get_block(&cache, &chain, disconnected_height).1);
+        assert_eq!(cache.len(), 2);
+
+        // Reorg the chain from the second block onwards.
+        let mut fork = chain[..disconnected_height as usize].to_vec();
+        fork.extend(sample_chain(15, rng));
+        update_tip(&cache, &fork);
+
+        // Ensure the response for the disconnected block was evicted, and the other buried block survived.
+        assert_eq!(cache.len(), 1);
+        assert!(!get_block(&cache, &fork, deep_height).1);
+        let (response, computed) = get_block(&cache, &fork, disconnected_height);
+        assert!(computed);
+        assert_eq!(response.body(), fork[disconnected_height as usize].to_string().as_bytes());
+    }
+
+    #[test]
+    fn test_cache_shallow_entries_follow_the_tip() {
+        let cache = ResponseCache::<CurrentNetwork>::default();
+        let chain = sample_chain(20, &mut TestRng::default());
+        update_tip(&cache, &chain[..19]);
+
+        // Ensure a response near the tip is recomputed once the tip advances.
+        assert!(get_block(&cache, &chain, 15).1);
+        assert!(!get_block(&cache, &chain, 15).1);
+        update_tip(&cache, &chain);
+        assert!(get_block(&cache, &chain, 15).1);
+    }
+
+    #[test]
+    fn test_cache_byte_budget_and_ttl() {
+        let chain = sample_chain(40, &mut TestRng::default());
+
+        // Ensure the least-recently used responses are evicted to stay within the byte budget.
+        let entry_size = chain[0].to_string().len();
+        let cache = ResponseCache::<CurrentNetwork>::new(2 * entry_size, DEFAULT_CACHE_TTL);
+        update_tip(&cache, &chain);
+        for height in 0..3 {
+            assert!(get_block(&cache, &chain, height).1);
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.num_bytes(), 2 * entry_size);
+        assert!(get_block(&cache, &chain, 0).1);
+
+        // Ensure expired responses are recomputed.
+        let cache = ResponseCache::<CurrentNetwork>::new(DEFAULT_CACHE_BYTE_BUDGET, Duration::ZERO);
+        update_tip(&cache, &chain);
+        assert!(get_block(&cache, &chain, 0).1);
+        assert!(get_block(&cache, &chain, 0).1);
+    }
+}
@@ -18,6 +18,14 @@
 #[derive(Debug)]
 pub enum RestError {
     Request(String),
+    /// The requested data belongs to a block whose body was pruned.
+    Pruned(String),
+    /// The requested ledger digest is older than the retained history.
+    OutOfHistory(String),
+    /// The requested method is not available on the listener that received the request.
+    NotAvailable(String),
+    /// The request cannot be served until the pending work of the node completes.
+    Unavailable(String),
 }
 
 impl warp::reject::Reject for RestError {}
@@ -0,0 +1,292 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{verify_token, with, RestError};
+
+use anyhow::{anyhow, bail, ensure, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::SocketAddr, path::PathBuf, str::FromStr, sync::Arc};
+use warp::{path::FullPath, reject, Filter, Rejection};
+
+/// The paths (after the network) of the write methods, which submit transactions to the memory pool.
+const WRITE_PATHS: &[&str] = &["transaction/broadcast", "transactions/broadcast", "transaction/createTransfer"];
+/// The path prefixes (after the network) of the admin methods, which also require a token on every listener.
+const ADMIN_PATHS: &[&str] = &[
+    "memoryPool/local",
+    "memoryPool/abandon",
+    "block/submit",
+    "block/submission",
+    "peers/policy",
+    "node/storage",
+    "node/invalidBlocks",
+    "node/invalidateBlock",
+    "node/audit",
+    "node/reload",
+    "accounts",
+];
+/// The path prefixes (after the network) of the WebSocket subscriptions.
+const SUBSCRIPTION_PATHS: &[&str] = &["chain/subscribe", "memoryPool/subscribe"];
+
+/// The class of a REST method, which determines the listeners it is available on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MethodClass {
+    /// The methods that read the state of the ledger, the memory pool, or the node.
+    Read,
+    /// The methods that submit transactions to the memory pool.
+    Write,
+    /// The methods that inspect or change the peers, the storage, or the accounts of the node.
+    Admin,
+}
+
+impl MethodClass {
+    /// Returns the class of the method at the given request path.
+    pub fn of(path: &str) -> Self {
+        let path = after_network(path);
+        if WRITE_PATHS.iter().any(|prefix| has_prefix(path, prefix)) {
+            Self::Write
+        } else if ADMIN_PATHS.iter().any(|prefix| has_prefix(path, prefix)) {
+            Self::Admin
+        } else {
+            Self::Read
+        }
+    }
+}
+
+impl FromStr for MethodClass {
+    type Err = Error;
+
+    fn from_str(class: &str) -> Result<Self> {
+        match class {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "admin" => Ok(Self::Admin),
+            _ => bail!("Invalid method class '{class}' (expected 'read', 'write', or 'admin')"),
+        }
+    }
+}
+
+impl fmt::Display for MethodClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+            Self::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// The certificate and private key with which a listener serves TLS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// The path to the PEM-encoded certificate chain.
+    pub cert_path: PathBuf,
+    /// The path to the PEM-encoded private key.
+    pub key_path: PathBuf,
+}
+
+/// A listener of the REST server, with the classes of the methods it serves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListenerConfig {
+    /// The IP address and port of the listener.
+    pub ip: SocketAddr,
+    /// The classes of the methods available on the listener.
+    pub classes: Vec<MethodClass>,
+    /// Whether every request on the listener requires a token.
+    pub auth: bool,
+    /// Whether the WebSocket subscriptions are available on the listener.
+    pub websocket: bool,
+    /// The certificate and private key to serve TLS with, if any.
+    pub tls: Option<TlsConfig>,
+}
+
+impl ListenerConfig {
+    /// Initializes a listener on the given IP with every method class and the WebSocket subscriptions,
+    /// which is the primary listener of the REST server.
+    pub fn new(ip: SocketAddr) -> Self {
+        Self {
+            ip,
+            classes: vec![MethodClass::Read, MethodClass::Write, MethodClass::Admin],
+            auth: false,
+            websocket: true,
+            tls: None,
+        }
+    }
+
+    /// Returns `true` if the methods of the given class are available on the listener.
+    pub fn allows(&self, class: MethodClass) -> bool {
+        self.classes.contains(&class)
+    }
+
+    /// Ensures the listener serves at least one method class, and does not serve the admin methods on a public
+    /// address without authentication, unless `allow_public_admin` is set.
+    pub fn check(&self, allow_public_admin: bool) -> Result<()> {
+        ensure!(!self.classes.is_empty(), "The REST listener on '{}' has no method classes", self.ip);
+        if self.allows(MethodClass::Admin) && !self.auth && !self.ip.ip().is_loopback() && !allow_public_admin {
+            bail!(
+                "The REST listener on '{}' serves the admin methods on a public address without authentication - \
+                 add 'auth' to the listener, bind it to a loopback address, or set '--rest-allow-public-admin'",
+                self.ip
+            )
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ListenerConfig {
+    type Err = Error;
+
+    /// Parses a listener of the form `<ip:port>[,classes=<class>+...][,auth][,websocket][,tls=<cert>:<key>]`,
+    /// which serves only the read methods if no classes are given.
+    fn from_str(listener: &str) -> Result<Self> {
+        let mut options = listener.split(',').map(str::trim);
+        let ip = options.next().unwrap_or_default();
+        let ip = SocketAddr::from_str(ip).map_err(|_| anyhow!("Invalid REST listener address '{ip}'"))?;
+
+        let mut config = Self { ip, classes: vec![MethodClass::Read], auth: false, websocket: false, tls: None };
+        for option in options {
+            match option.split_once('=') {
+                Some(("classes", classes)) => {
+                    config.classes = classes.split('+').map(MethodClass::from_str).collect::<Result<_>>()?;
+                }
+                Some(("tls", paths)) => {
+                    let (cert_path, key_path) = paths
+                        .split_once(':')
+                        .ok_or_else(|| anyhow!("Invalid TLS option '{paths}' (expected '<cert>:<key>')"))?;
+                    config.tls = Some(TlsConfig { cert_path: cert_path.into(), key_path: key_path.into() });
+                }
+                None if option == "auth" => config.auth = true,
+                None if option == "websocket" => config.websocket = true,
+                _ => bail!("Invalid option '{option}' for the REST listener on '{ip}'"),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Returns the given request path without its leading network segment.
+fn after_network(path: &str) -> &str {
+    path.trim_start_matches('/').split_once('/').map_or("", |(_, path)| path)
+}
+
+/// Returns `true` if the segments of the given path start with those of the given prefix.
+fn has_prefix(path: &str, prefix: &str) -> bool {
+    let mut segments = path.split('/');
+    prefix.split('/').all(|segment| segments.next() == Some(segment))
+}
+
+/// Rejects the requests for the methods that are not available on the given listener before they are routed,
+/// and requires a token on every request if the listener requires authentication.
+pub fn with_listener(listener: Arc<ListenerConfig>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with(listener))
+        .and_then(|path: FullPath, token: Option<String>, listener: Arc<ListenerConfig>| async move {
+            let path = path.as_str();
+            let class = MethodClass::of(path);
+            if !listener.allows(class) {
+                let message = format!("The {class} method '{path}' is not available on this endpoint");
+                return Err(reject::custom(RestError::NotAvailable(message)));
+            }
+            let is_subscription = SUBSCRIPTION_PATHS.iter().any(|prefix| has_prefix(after_network(path), prefix));
+            if !listener.websocket && is_subscription {
+                let message = format!("The subscription '{path}' is not available on this endpoint");
+                return Err(reject::custom(RestError::NotAvailable(message)));
+            }
+            match (listener.auth, token) {
+                (true, Some(token)) => verify_token(&token),
+                (true, None) => Err(reject::custom(RestError::Request("Missing authorization header.".to_string()))),
+                (false, _) => Ok(()),
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle_rejection;
+
+    use warp::http::StatusCode;
+
+    #[test]
+    fn test_method_class() {
+        assert_eq!(MethodClass::of("/testnet3/latest/height"), MethodClass::Read);
+        assert_eq!(MethodClass::of("/testnet3/transaction/simulate"), MethodClass::Read);
+        assert_eq!(MethodClass::of("/testnet3/transaction/broadcast"), MethodClass::Write);
+        assert_eq!(MethodClass::of("/testnet3/node/storage/dump/blocks"), MethodClass::Admin);
+        assert_eq!(MethodClass::of("/testnet3/accounts/aleo1abc/history"), MethodClass::Admin);
+        assert_eq!(MethodClass::of("/testnet3/block/submit"), MethodClass::Admin);
+        assert_eq!(MethodClass::of("/testnet3/block/submission/7"), MethodClass::Admin);
+        assert_eq!(MethodClass::of("/testnet3/block/template"), MethodClass::Read);
+        // Ensure the prefixes are matched by segment.
+        assert_eq!(MethodClass::of("/testnet3/transaction/broadcastX"), MethodClass::Read);
+    }
+
+    #[test]
+    fn test_listener_config() {
+        let listener = ListenerConfig::from_str("0.0.0.0:3034,classes=read+write,websocket,tls=a.pem:b.pem").unwrap();
+        assert_eq!(listener.ip, SocketAddr::from(([0, 0, 0, 0], 3034)));
+        assert_eq!(listener.classes, vec![MethodClass::Read, MethodClass::Write]);
+        assert!(!listener.auth && listener.websocket);
+        assert_eq!(listener.tls, Some(TlsConfig { cert_path: "a.pem".into(), key_path: "b.pem".into() }));
+
+        // Ensure a listener serves the read methods by default.
+        assert_eq!(ListenerConfig::from_str("0.0.0.0:3034").unwrap().classes, vec![MethodClass::Read]);
+        assert!(ListenerConfig::from_str("0.0.0.0:3034,classes=root").is_err());
+        assert!(ListenerConfig::from_str("0.0.0.0:3034,cors").is_err());
+    }
+
+    #[test]
+    fn test_public_admin() {
+        // Ensure the admin methods are only served on a public address with authentication, or the override.
+        let public = ListenerConfig::from_str("0.0.0.0:3034,classes=admin").unwrap();
+        assert!(public.check(false).unwrap_err().to_string().contains("--rest-allow-public-admin"));
+        assert!(public.check(true).is_ok());
+        assert!(ListenerConfig::from_str("0.0.0.0:3034,classes=admin,auth").unwrap().check(false).is_ok());
+        assert!(ListenerConfig::from_str("127.0.0.1:3034,classes=admin").unwrap().check(false).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_two_listeners() {
+        // A stub of the broadcast method, which accepts every transaction.
+        let broadcast = warp::post().and(warp::path!("testnet3" / "transaction" / "broadcast")).map(|| "accepted");
+        let serve = |listener: ListenerConfig| {
+            with_listener(Arc::new(listener)).and(broadcast).recover(handle_rejection)
+        };
+        let public = serve(ListenerConfig::from_str("0.0.0.0:3034,classes=read").unwrap());
+        let private = serve(ListenerConfig::new(SocketAddr::from(([127, 0, 0, 1], 3033))));
+
+        // Ensure the transaction is rejected on the public listener, with a dedicated error.
+        let request = || warp::test::request().method("POST").path("/testnet3/transaction/broadcast");
+        let response = request().reply(&public).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(String::from_utf8_lossy(response.body()).contains("not available on this endpoint"));
+
+        // Ensure the transaction is accepted on the private listener.
+        let response = request().reply(&private).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "accepted");
+
+        // Ensure the subscriptions are only available on the listeners that enable them.
+        let subscribe = warp::path!("testnet3" / "chain" / "subscribe" / "blocks").map(|| "subscribed");
+        let public = with_listener(Arc::new(ListenerConfig::from_str("0.0.0.0:3034").unwrap()))
+            .and(subscribe)
+            .recover(handle_rejection);
+        let response = warp::test::request().path("/testnet3/chain/subscribe/blocks").reply(&public).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
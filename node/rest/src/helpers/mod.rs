@@ -17,11 +17,29 @@
 mod auth;
 pub use auth::*;
 
+mod cache;
+pub use cache::*;
+
 mod error;
 pub use error::*;
 
+mod listener;
+pub use listener::*;
+
 mod middleware;
 pub use middleware::*;
 
 mod or_reject;
 pub use or_reject::*;
+
+mod raw;
+pub use raw::*;
+
+mod rate_limit;
+pub use rate_limit::*;
+
+mod reload;
+pub use reload::*;
+
+mod telemetry;
+pub use telemetry::*;
@@ -15,6 +15,7 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::RestError;
+use snarkos_node_ledger::{BlockPruned, DigestOutOfHistory};
 
 use anyhow::Result;
 use warp::{reject, Rejection};
@@ -28,6 +29,10 @@ pub trait OrReject<T> {
 impl<T> OrReject<T> for anyhow::Result<T> {
     /// Returns the result if it is successful, otherwise returns a rejection.
     fn or_reject(self) -> Result<T, Rejection> {
-        self.map_err(|e| reject::custom(RestError::Request(e.to_string())))
+        self.map_err(|e| match (e.is::<BlockPruned>(), e.is::<DigestOutOfHistory>()) {
+            (true, _) => reject::custom(RestError::Pruned(e.to_string())),
+            (_, true) => reject::custom(RestError::OutOfHistory(e.to_string())),
+            _ => reject::custom(RestError::Request(e.to_string())),
+        })
     }
 }
@@ -0,0 +1,114 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{with, RestError};
+
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use warp::{reject, Filter, Rejection};
+
+/// The duration of a rate limiting window.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// A limiter of the number of requests per second from each IP address.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    /// The maximum number of requests per second from each IP address, if requests are limited.
+    limit: RwLock<Option<u32>>,
+    /// The map of IP addresses to the start of their current window, and their number of requests in it.
+    windows: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// Returns the maximum number of requests per second from each IP address, if requests are limited.
+    pub fn limit(&self) -> Option<u32> {
+        *self.limit.read()
+    }
+
+    /// Sets the maximum number of requests per second from each IP address, or disables the limit if `None`.
+    pub fn set_limit(&self, limit: Option<u32>) {
+        *self.limit.write() = limit;
+        // Reset the windows, as their counts were accumulated under the previous limit.
+        self.windows.lock().clear();
+    }
+
+    /// Records a request from the given IP address, and returns `true` if it is within the limit.
+    pub fn check(&self, ip: IpAddr) -> bool {
+        // Return early if requests are not limited.
+        let limit = match self.limit() {
+            Some(limit) => limit,
+            None => return true,
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock();
+        // Remove the expired windows, so that the map does not grow with every IP address seen.
+        windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        // Count the request in the current window of the IP address.
+        let (_, num_requests) = windows.entry(ip).or_insert((now, 0));
+        *num_requests = num_requests.saturating_add(1);
+        *num_requests <= limit
+    }
+}
+
+/// Rejects the request if its IP address has exceeded the limit of the given rate limiter.
+pub fn with_rate_limit(limiter: Arc<RateLimiter>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and(with(limiter))
+        .and_then(|addr: Option<SocketAddr>, limiter: Arc<RateLimiter>| async move {
+            match addr {
+                Some(addr) if !limiter.check(addr.ip()) => {
+                    Err(reject::custom(RestError::Request("Too many requests.".to_string())))
+                }
+                _ => Ok(()),
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let limiter = RateLimiter::default();
+        let (ip0, ip1) = (IpAddr::from([127, 0, 0, 1]), IpAddr::from([127, 0, 0, 2]));
+
+        // Ensure requests are unlimited by default.
+        assert!((0..100).all(|_| limiter.check(ip0)));
+
+        // Ensure the requests beyond the limit are rejected, for each IP address.
+        limiter.set_limit(Some(2));
+        assert!(limiter.check(ip0));
+        assert!(limiter.check(ip0));
+        assert!(!limiter.check(ip0));
+        assert!(limiter.check(ip1));
+
+        // Ensure the requests are accepted again in the next window.
+        std::thread::sleep(RATE_LIMIT_WINDOW);
+        assert!(limiter.check(ip0));
+
+        // Ensure disabling the limit takes effect immediately.
+        limiter.set_limit(None);
+        assert!((0..100).all(|_| limiter.check(ip0)));
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{CachedResponse, RestError};
+
+use anyhow::Result;
+use warp::{
+    http::StatusCode,
+    reply::{self, Response},
+    Rejection,
+    Reply,
+};
+
+/// The media type of the raw binary encoding of an object.
+pub const RAW_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Returns `true` if the given `Accept` header requests the raw binary encoding.
+pub fn accepts_raw(accept: Option<&str>) -> bool {
+    accept.is_some_and(|accept| {
+        // Ignore the parameters of each media range, such as the quality value.
+        accept.split(',').any(|range| range.split(';').next().unwrap_or_default().trim() == RAW_CONTENT_TYPE)
+    })
+}
+
+/// Returns the response for the given binary encoding, as the raw bytes if the given `Accept` header
+/// requests them, and as a hex-encoded JSON string otherwise.
+pub fn raw_response(bytes: Vec<u8>, accept: Option<&str>) -> Result<CachedResponse> {
+    match accepts_raw(accept) {
+        true => Ok(CachedResponse::new(bytes, RAW_CONTENT_TYPE)),
+        false => CachedResponse::json(&hex::encode(bytes)),
+    }
+}
+
+/// Returns the reply for the given rejection, if it has a dedicated status code.
+pub fn rejection_reply(rejection: &Rejection) -> Option<Response> {
+    // The transfer could not be created, for the reason given by its error code.
+    #[cfg(feature = "builder")]
+    if let Some(error) = rejection.find::<crate::BuildError>() {
+        return Some(error.reply());
+    }
+    match rejection.find::<RestError>() {
+        // The block exists, but its body is no longer stored.
+        Some(RestError::Pruned(message)) => {
+            Some(reply::with_status(reply::json(&message), StatusCode::GONE).into_response())
+        }
+        // The method exists, but its class is not enabled on the listener.
+        Some(RestError::NotAvailable(message)) => {
+            Some(reply::with_status(reply::json(&message), StatusCode::FORBIDDEN).into_response())
+        }
+        // The node is busy with the pending requests, and the request may be retried later.
+        Some(RestError::Unavailable(message)) => {
+            Some(reply::with_status(reply::json(&message), StatusCode::SERVICE_UNAVAILABLE).into_response())
+        }
+        _ => None,
+    }
+}
+
+/// Recovers the rejections that have a dedicated status code, and passes the remaining rejections through.
+pub async fn handle_rejection(rejection: Rejection) -> Result<Response, Rejection> {
+    match rejection_reply(&rejection) {
+        Some(response) => Ok(response),
+        None => Err(rejection),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Block, FromBytes, Network, Testnet3, ToBytes};
+
+    use warp::reject;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_accepts_raw() {
+        assert!(!accepts_raw(None));
+        assert!(!accepts_raw(Some("application/json")));
+        assert!(!accepts_raw(Some("*/*")));
+        assert!(accepts_raw(Some("application/octet-stream")));
+        assert!(accepts_raw(Some("application/json;q=0.5, application/octet-stream;q=0.9")));
+    }
+
+    #[test]
+    fn test_raw_response() {
+        let block = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let transaction = block.transactions().iter().next().unwrap();
+        let bytes = transaction.to_bytes_le().unwrap();
+
+        // Fetch the transaction both as hex and as raw bytes.
+        let hex = raw_response(bytes.clone(), None).unwrap();
+        let raw = raw_response(bytes.clone(), Some(RAW_CONTENT_TYPE)).unwrap();
+
+        // Ensure the hex decodes to exactly the raw bytes.
+        let hex: String = serde_json::from_slice(hex.body()).unwrap();
+        assert_eq!(hex::decode(hex).unwrap(), raw.body());
+        assert_eq!(raw.body(), bytes);
+
+        // Ensure the raw bytes are labelled as such.
+        let response = raw.into_response();
+        assert_eq!(response.headers()["content-type"], RAW_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn test_pruned_rejection() {
+        // Ensure a pruned block is reported as gone.
+        let rejection = reject::custom(RestError::Pruned("block 5 was pruned".to_string()));
+        let response = rejection_reply(&rejection).unwrap();
+        assert_eq!(response.status(), StatusCode::GONE);
+
+        // Ensure a busy node asks to retry later.
+        let rejection = reject::custom(RestError::Unavailable("32 submissions are pending".to_string()));
+        assert_eq!(rejection_reply(&rejection).unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Ensure the remaining rejections are passed through.
+        assert!(rejection_reply(&reject::custom(RestError::Request("error".to_string()))).is_none());
+        assert!(rejection_reply(&reject::not_found()).is_none());
+    }
+}
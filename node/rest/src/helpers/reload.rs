@@ -0,0 +1,37 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+
+/// A handler that reloads the configuration of the node.
+pub type ReloadHandler = Box<dyn Fn() -> Result<()> + Send + Sync>;
+
+/// The handler that reloads the configuration of the node, if one is set.
+static RELOAD_HANDLER: OnceCell<ReloadHandler> = OnceCell::new();
+
+/// Sets the handler that reloads the configuration of the node, when requested through the REST API.
+pub fn set_reload_handler(handler: ReloadHandler) -> Result<()> {
+    RELOAD_HANDLER.set(handler).map_err(|_| anyhow!("The reload handler is already set"))
+}
+
+/// Reloads the configuration of the node, with the handler that is set.
+pub fn reload_config() -> Result<()> {
+    match RELOAD_HANDLER.get() {
+        Some(handler) => handler(),
+        None => bail!("The node was not started with a configuration file"),
+    }
+}
@@ -0,0 +1,577 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{with, RestError};
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+use snarkos_node_store::rocksdb::{REQUEST_SPAN, STORAGE_TARGET};
+
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Span,
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::Context,
+    registry::{LookupSpan, Registry},
+    Layer,
+};
+use warp::{
+    body::BodyDeserializeError,
+    filters::path::FullPath,
+    http::{header::CONTENT_LENGTH, HeaderMap, Method, StatusCode},
+    hyper::body::HttpBody,
+    reject::{
+        InvalidHeader,
+        InvalidQuery,
+        LengthRequired,
+        MethodNotAllowed,
+        MissingHeader,
+        PayloadTooLarge,
+        UnsupportedMediaType,
+    },
+    reply::Response,
+    Filter,
+    Rejection,
+    Reply,
+};
+
+/// The default duration after which a request is logged as slow.
+pub const DEFAULT_SLOW_REQUEST_THRESHOLD: Duration = Duration::from_secs(1);
+/// The upper bounds (in seconds) of the latency buckets of each method, besides the last bucket, which is unbounded.
+/// These are the buckets of the request durations exported to the metrics endpoint.
+pub const LATENCY_BUCKETS: [f64; 11] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// The maximum number of methods that are tracked separately, beyond which the requests are tracked as `other`.
+const MAX_METHODS: usize = 256;
+/// The number of recent slow requests that are kept.
+const MAX_SLOW_REQUESTS: usize = 32;
+/// The maximum length of the logged parameters of a request.
+const MAX_PARAMS_LENGTH: usize = 256;
+/// The maximum length of a path segment that is not a parameter.
+const MAX_SEGMENT_LENGTH: usize = 32;
+/// The substrings of the names of the query parameters whose values are redacted, in lowercase.
+const SENSITIVE_NAMES: &[&str] = &["key", "token", "secret", "auth", "password", "signature"];
+/// The prefixes of the values that are redacted wherever they appear: view keys, private keys, and web tokens.
+const SENSITIVE_PREFIXES: &[&str] = &["AViewKey1", "APrivateKey1", "eyJ"];
+/// The placeholder of a redacted value.
+const REDACTED: &str = "<redacted>";
+
+/// The latency, sizes, and errors of the requests of a method.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MethodStatistics {
+    /// The number of requests.
+    pub num_requests: u64,
+    /// The number of requests in each latency bucket, with the unbounded bucket last.
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    /// The total duration of the requests, in milliseconds.
+    pub total_duration_ms: u64,
+    /// The total number of bytes of the request bodies.
+    pub request_bytes: u64,
+    /// The total number of bytes of the response bodies.
+    pub response_bytes: u64,
+    /// The number of failed requests, by status code.
+    pub errors: BTreeMap<u16, u64>,
+}
+
+/// The storage reads of a request, for an operation on a column.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StorageReads {
+    /// The number of reads.
+    pub count: u64,
+    /// The total duration of the reads, in microseconds.
+    pub duration_us: u64,
+}
+
+/// A request that took longer than the slow request threshold.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SlowRequest {
+    /// The method, as the HTTP method and the path, with the parameters replaced by `{}`.
+    pub method: String,
+    /// The parameters, with the view keys and authentication material redacted.
+    pub params: String,
+    /// The status code of the response.
+    pub status: u16,
+    /// The duration of the request, in milliseconds.
+    pub duration_ms: u64,
+    /// The storage reads of the request, by operation and column.
+    pub storage: BTreeMap<String, StorageReads>,
+}
+
+/// The latency histograms, sizes, and error counts of the requests of each method, and the recent slow requests.
+#[derive(Debug)]
+pub struct RequestTelemetry {
+    /// The duration after which a request is logged as slow.
+    slow_threshold: RwLock<Duration>,
+    /// The statistics of each method.
+    methods: Mutex<BTreeMap<String, MethodStatistics>>,
+    /// The recent slow requests, from the oldest.
+    slow_requests: Mutex<VecDeque<SlowRequest>>,
+}
+
+impl Default for RequestTelemetry {
+    fn default() -> Self {
+        Self {
+            slow_threshold: RwLock::new(DEFAULT_SLOW_REQUEST_THRESHOLD),
+            methods: Default::default(),
+            slow_requests: Default::default(),
+        }
+    }
+}
+
+impl RequestTelemetry {
+    /// Returns the duration after which a request is logged as slow.
+    pub fn slow_threshold(&self) -> Duration {
+        *self.slow_threshold.read()
+    }
+
+    /// Sets the duration after which a request is logged as slow, or restores the default if `None`.
+    pub fn set_slow_threshold(&self, threshold: Option<Duration>) {
+        *self.slow_threshold.write() = threshold.unwrap_or(DEFAULT_SLOW_REQUEST_THRESHOLD);
+    }
+
+    /// Returns the statistics of each method.
+    pub fn statistics(&self) -> BTreeMap<String, MethodStatistics> {
+        self.methods.lock().clone()
+    }
+
+    /// Returns the recent slow requests, from the oldest.
+    pub fn slow_requests(&self) -> Vec<SlowRequest> {
+        self.slow_requests.lock().iter().cloned().collect()
+    }
+
+    /// Records a request of the given method, and logs it if it is slow.
+    fn record(&self, method: String, params: impl FnOnce() -> String, outcome: RequestOutcome) {
+        let RequestOutcome { status, duration, request_bytes, response_bytes } = outcome;
+        let is_error = status.is_client_error() || status.is_server_error();
+
+        // Update the statistics of the method, tracking the methods beyond the limit as `other`.
+        let mut methods = self.methods.lock();
+        let label = match methods.len() < MAX_METHODS || methods.contains_key(&method) {
+            true => method.clone(),
+            false => "other".to_string(),
+        };
+        let statistics = methods.entry(label.clone()).or_default();
+        statistics.num_requests += 1;
+        let bucket = LATENCY_BUCKETS.iter().position(|bound| duration.as_secs_f64() <= *bound);
+        statistics.latency_buckets[bucket.unwrap_or(LATENCY_BUCKETS.len())] += 1;
+        statistics.total_duration_ms += duration.as_millis() as u64;
+        statistics.request_bytes += request_bytes;
+        statistics.response_bytes += response_bytes;
+        if is_error {
+            *statistics.errors.entry(status.as_u16()).or_default() += 1;
+        }
+        drop(methods);
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!(metrics::rest::REQUEST_DURATION, duration.as_secs_f64(), "method" => label.clone());
+            metrics::histogram!(metrics::rest::REQUEST_BYTES, request_bytes as f64, "method" => label.clone());
+            metrics::histogram!(metrics::rest::RESPONSE_BYTES, response_bytes as f64, "method" => label.clone());
+            if is_error {
+                let code = status.as_u16().to_string();
+                metrics::increment_counter!(metrics::rest::ERRORS, "method" => label.clone(), "code" => code);
+            }
+        }
+
+        // Log the request if it is slow, with the storage reads of its span.
+        if duration >= self.slow_threshold() {
+            let request = SlowRequest {
+                method,
+                params: params(),
+                status: status.as_u16(),
+                duration_ms: duration.as_millis() as u64,
+                storage: current_storage_reads(),
+            };
+            warn!(
+                method = %request.method,
+                params = %request.params,
+                status = request.status,
+                duration_ms = request.duration_ms,
+                storage = %StorageBreakdown(&request.storage),
+                "Slow request '{}' ({} ms)",
+                request.method,
+                request.duration_ms
+            );
+            #[cfg(feature = "metrics")]
+            metrics::increment_counter!(metrics::rest::SLOW_REQUESTS, "method" => label);
+
+            let mut slow_requests = self.slow_requests.lock();
+            if slow_requests.len() >= MAX_SLOW_REQUESTS {
+                slow_requests.pop_front();
+            }
+            slow_requests.push_back(request);
+        }
+    }
+}
+
+/// The outcome of a request.
+struct RequestOutcome {
+    /// The status code of the response.
+    status: StatusCode,
+    /// The duration of the request.
+    duration: Duration,
+    /// The number of bytes of the request body.
+    request_bytes: u64,
+    /// The number of bytes of the response body.
+    response_bytes: u64,
+}
+
+/// Records the latency, sizes, and status of the requests of the given filter, and logs the slow requests.
+/// Each request is served within a request span, in which the storage reads are timed.
+pub fn with_telemetry<F, T>(
+    filter: F,
+    telemetry: Arc<RequestTelemetry>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (T,), Error = Rejection> + Clone + Send + Sync + 'static,
+    T: Reply,
+{
+    // Catch the rejections, so that they are recorded before they are passed through.
+    let filter = filter
+        .map(|reply: T| Ok::<_, Rejection>(reply.into_response()))
+        .or_else(|rejection: Rejection| async move { Ok::<_, Rejection>((Err(rejection),)) });
+
+    warp::any()
+        .map(Instant::now)
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::headers_cloned())
+        .and(filter)
+        .and(with(telemetry))
+        .and_then(
+            |start: Instant,
+             method: Method,
+             path: FullPath,
+             query: String,
+             headers: HeaderMap,
+             result: Result<Response, Rejection>,
+             telemetry: Arc<RequestTelemetry>| async move {
+                let (status, response_bytes) = match &result {
+                    Ok(response) => (response.status(), response.body().size_hint().exact().unwrap_or_default()),
+                    Err(rejection) => (rejection_status(rejection), 0),
+                };
+                // Track the unknown paths together, so that their number is bounded.
+                let method = match status == StatusCode::NOT_FOUND && result.is_err() {
+                    true => "unknown".to_string(),
+                    false => request_method(&method, path.as_str()),
+                };
+                let request_bytes = headers
+                    .get(CONTENT_LENGTH)
+                    .and_then(|length| length.to_str().ok()?.parse().ok())
+                    .unwrap_or_default();
+                let outcome = RequestOutcome { status, duration: start.elapsed(), request_bytes, response_bytes };
+                telemetry.record(method, || redacted_params(path.as_str(), &query), outcome);
+                result
+            },
+        )
+        .with(warp::trace(|info| info_span!(REQUEST_SPAN, method = %info.method())))
+}
+
+/// Returns the method of a request, as its HTTP method and its path, with the parameters replaced by `{}`.
+fn request_method(method: &Method, path: &str) -> String {
+    let path = path
+        .split('/')
+        .enumerate()
+        // The first segment (after the leading slash) is the network.
+        .map(|(index, segment)| match index > 1 && is_parameter(segment) {
+            true => "{}",
+            false => segment,
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{method} {path}")
+}
+
+/// Returns `true` if the given path segment is a parameter, such as a height, an ID, or a program.
+fn is_parameter(segment: &str) -> bool {
+    segment.len() > MAX_SEGMENT_LENGTH || segment.contains('.') || segment.bytes().any(|byte| byte.is_ascii_digit())
+}
+
+/// Returns the parameters of a request, as its path parameters and its query, with the sensitive values redacted.
+fn redacted_params(path: &str, query: &str) -> String {
+    let path_params =
+        path.split('/').skip(2).filter(|segment| is_parameter(segment)).map(|segment| redact(segment).to_string());
+    let query_params = query.split('&').filter(|pair| !pair.is_empty()).map(|pair| match pair.split_once('=') {
+        Some((name, _)) if SENSITIVE_NAMES.iter().any(|sensitive| name.to_lowercase().contains(sensitive)) => {
+            format!("{name}={REDACTED}")
+        }
+        Some((name, value)) => format!("{name}={}", redact(value)),
+        None => redact(pair).to_string(),
+    });
+    let mut params = path_params.chain(query_params).collect::<Vec<_>>().join(", ");
+    // Truncate the parameters, on a character boundary.
+    if params.len() > MAX_PARAMS_LENGTH {
+        let length = (0..=MAX_PARAMS_LENGTH).rev().find(|length| params.is_char_boundary(*length)).unwrap_or_default();
+        params.truncate(length);
+        params.push_str("...");
+    }
+    params
+}
+
+/// Returns the given value, or the redacted placeholder if it is a view key, a private key, or a web token.
+fn redact(value: &str) -> &str {
+    match SENSITIVE_PREFIXES.iter().any(|prefix| value.starts_with(prefix)) {
+        true => REDACTED,
+        false => value,
+    }
+}
+
+/// Returns the status code with which the given rejection is replied to, as warp prefers the most severe one.
+fn rejection_status(rejection: &Rejection) -> StatusCode {
+    if rejection.is_not_found() {
+        StatusCode::NOT_FOUND
+    } else if rejection.find::<RestError>().is_some() {
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else if rejection.find::<UnsupportedMediaType>().is_some() {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    } else if rejection.find::<PayloadTooLarge>().is_some() {
+        StatusCode::PAYLOAD_TOO_LARGE
+    } else if rejection.find::<LengthRequired>().is_some() {
+        StatusCode::LENGTH_REQUIRED
+    } else if rejection.find::<MethodNotAllowed>().is_some()
+        && rejection.find::<InvalidQuery>().is_none()
+        && rejection.find::<InvalidHeader>().is_none()
+        && rejection.find::<MissingHeader>().is_none()
+        && rejection.find::<BodyDeserializeError>().is_none()
+    {
+        // The method is only reported as not allowed if the request matches no route otherwise.
+        StatusCode::METHOD_NOT_ALLOWED
+    } else {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Returns the storage reads timed within the current request span, if the storage timing layer is installed.
+fn current_storage_reads() -> BTreeMap<String, StorageReads> {
+    Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>()?;
+            registry.span(id)?.extensions().get::<RequestStorageReads>().map(|reads| reads.0.clone())
+        })
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// The storage reads of a request, in the extensions of its span.
+#[derive(Default)]
+struct RequestStorageReads(BTreeMap<String, StorageReads>);
+
+/// A storage read in progress, in the extensions of its span.
+struct StorageRead {
+    /// The operation and the column of the read.
+    label: String,
+    /// The time at which the read started.
+    started_at: Instant,
+}
+
+/// A layer that times the storage reads within the request spans, and adds them to the storage reads of the request.
+#[derive(Debug, Default)]
+pub struct StorageTimingLayer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for StorageTimingLayer {
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        let span = match context.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let metadata = attributes.metadata();
+        if metadata.name() == REQUEST_SPAN {
+            span.extensions_mut().insert(RequestStorageReads::default());
+        } else if metadata.target() == STORAGE_TARGET {
+            let mut fields = StorageFields::default();
+            attributes.record(&mut fields);
+            let label = format!("{} {}", fields.operation, fields.column).trim().to_string();
+            span.extensions_mut().insert(StorageRead { label, started_at: Instant::now() });
+        }
+    }
+
+    fn on_close(&self, id: Id, context: Context<'_, S>) {
+        let span = match context.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let read = match span.extensions_mut().remove::<StorageRead>() {
+            Some(read) => read,
+            None => return,
+        };
+        // Add the read to the storage reads of the enclosing request.
+        if let Some(request) = span.scope().skip(1).find(|span| span.name() == REQUEST_SPAN) {
+            if let Some(reads) = request.extensions_mut().get_mut::<RequestStorageReads>() {
+                let entry = reads.0.entry(read.label).or_default();
+                entry.count += 1;
+                entry.duration_us += read.started_at.elapsed().as_micros() as u64;
+            }
+        }
+    }
+}
+
+/// The fields of a storage span.
+#[derive(Default)]
+struct StorageFields {
+    /// The operation.
+    operation: String,
+    /// The column.
+    column: String,
+}
+
+impl Visit for StorageFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "operation" => self.operation = value.to_string(),
+            "column" => self.column = value.to_string(),
+            _ => (),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+/// The storage reads of a request, formatted as `{operation} {column}: {count} in {duration} us`.
+struct StorageBreakdown<'a>(&'a BTreeMap<String, StorageReads>);
+
+impl fmt::Display for StorageBreakdown<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (label, reads)) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{label}: {} in {} us", reads.count, reads.duration_us)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// A storage that delays each read by the given duration, within a storage span.
+    #[derive(Clone)]
+    struct DelayedStorage {
+        /// The duration of each read.
+        delay: Duration,
+    }
+
+    impl DelayedStorage {
+        /// Returns the block height for the given height, after the delay.
+        fn get(&self, height: u32) -> u32 {
+            let _span =
+                trace_span!(target: STORAGE_TARGET, "storage", operation = "get", column = "BlockIDMap").entered();
+            std::thread::sleep(self.delay);
+            height
+        }
+    }
+
+    /// Returns a `GET /testnet3/block/{height}` route, served from the given storage, with the given telemetry.
+    fn route(
+        storage: DelayedStorage,
+        telemetry: Arc<RequestTelemetry>,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        let route = warp::get()
+            .and(warp::path!("testnet3" / "block" / u32))
+            .and(with(storage))
+            .map(|height: u32, storage: DelayedStorage| warp::reply::json(&storage.get(height)));
+        with_telemetry(route, telemetry)
+    }
+
+    #[test]
+    fn test_request_method_and_params() {
+        let view_key = "AViewKey1mSnpFFC8Mj4fXbK5YiWgZ3mjiV8CxA79bYNa8ymUpTrw";
+
+        // Ensure the parameters are replaced in the method, and the network is not.
+        assert_eq!(request_method(&Method::GET, "/testnet3/block/5"), "GET /testnet3/block/{}");
+        assert_eq!(request_method(&Method::GET, "/testnet3/program/credits.aleo"), "GET /testnet3/program/{}");
+        assert_eq!(
+            request_method(&Method::POST, "/testnet3/transaction/broadcast"),
+            "POST /testnet3/transaction/broadcast"
+        );
+
+        // Ensure the view keys and authentication material are redacted, from the path and the query.
+        assert_eq!(redacted_params("/testnet3/block/5", "verbose=true"), "5, verbose=true");
+        let params =
+            redacted_params(&format!("/testnet3/records/{view_key}"), "view_key=abc&auth=xyz&token=eyJhbGc&start=3");
+        assert_eq!(params, "<redacted>, view_key=<redacted>, auth=<redacted>, token=<redacted>, start=3");
+        assert_eq!(redacted_params("/testnet3/latest/height", &format!("key={view_key}")), "key=<redacted>");
+        assert_eq!(redacted_params("/testnet3/latest/height", view_key), "<redacted>");
+    }
+
+    #[tokio::test]
+    async fn test_slow_request() {
+        // Time the storage reads within the request spans.
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(StorageTimingLayer));
+
+        let telemetry = Arc::new(RequestTelemetry::default());
+        telemetry.set_slow_threshold(Some(Duration::from_millis(20)));
+
+        // Ensure a fast request is recorded, but not logged.
+        let fast = route(DelayedStorage { delay: Duration::ZERO }, telemetry.clone());
+        let response = warp::test::request().path("/testnet3/block/5").reply(&fast).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(telemetry.slow_requests().is_empty());
+
+        // Ensure a slow request is recorded in the bucket of its duration.
+        let slow = route(DelayedStorage { delay: Duration::from_millis(30) }, telemetry.clone());
+        let response = warp::test::request()
+            .path("/testnet3/block/7?view_key=AViewKey1mSnpFFC8Mj4fXbK5YiWgZ3mjiV8CxA79bYNa8ymUpTrw&verbose=true")
+            .reply(&slow)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let statistics = telemetry.statistics();
+        let statistics = &statistics["GET /testnet3/block/{}"];
+        assert_eq!(statistics.num_requests, 2);
+        assert_eq!(statistics.response_bytes, 2);
+        // The slow request takes at least 30 ms, so it is above the bucket of 25 ms.
+        let bucket = LATENCY_BUCKETS.iter().position(|bound| *bound == 0.025).unwrap();
+        assert_eq!(statistics.latency_buckets[bucket + 1..].iter().sum::<u64>(), 1);
+        assert!(statistics.errors.is_empty());
+
+        // Ensure the slow request is logged with its redacted parameters and its storage reads.
+        let slow_requests = telemetry.slow_requests();
+        assert_eq!(slow_requests.len(), 1);
+        let request = &slow_requests[0];
+        assert_eq!(request.method, "GET /testnet3/block/{}");
+        assert_eq!(request.params, "7, view_key=<redacted>, verbose=true");
+        assert_eq!(request.status, 200);
+        assert!(request.duration_ms >= 30);
+        let reads = request.storage["get BlockIDMap"];
+        assert_eq!(reads.count, 1);
+        assert!(reads.duration_us >= 30_000);
+
+        // Ensure the failed requests are counted by status code.
+        let response = warp::test::request().method("POST").path("/testnet3/block/7").reply(&slow).await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let response = warp::test::request().path("/testnet3/unknown").reply(&slow).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let statistics = telemetry.statistics();
+        assert_eq!(statistics["POST /testnet3/block/{}"].errors, BTreeMap::from([(405, 1)]));
+        assert_eq!(statistics["unknown"].errors, BTreeMap::from([(404, 1)]));
+    }
+}
@@ -15,33 +15,112 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 #![forbid(unsafe_code)]
+#![recursion_limit = "256"]
 
 #[macro_use]
 extern crate tracing;
 
+#[cfg(feature = "builder")]
+mod builder;
+#[cfg(feature = "builder")]
+pub use builder::*;
+
 mod helpers;
 pub use helpers::*;
 
 mod routes;
 pub use routes::*;
 
-use snarkos_node_consensus::Consensus;
-use snarkos_node_ledger::Ledger;
-use snarkos_node_messages::{Data, Message, UnconfirmedTransaction};
-use snarkos_node_router::{Router, Routing};
+use snarkos_node_cdn::{export_chain_metrics, ChainMetricsExport, LedgerMetricsSource};
+use snarkos_node_consensus::{
+    AcceptanceSimulation,
+    BatchMode,
+    BatchOutcome,
+    BlockTemplate,
+    Consensus,
+    LongPollId,
+    MemoryPoolEntry,
+    MinerInfo,
+    RuleDeployment,
+    SubmissionOutcome,
+    SubmissionReceipt,
+    SubmissionSource,
+    SubmissionStatus,
+    TransactionRejection,
+    TransactionStatus,
+};
+use snarkos_node_ledger::{
+    AccountHistoryEntry,
+    ChainTip,
+    DepositProof,
+    Event,
+    Ledger,
+    LedgerDigest,
+    LedgerMembershipProof,
+    NetworkTime,
+    RecordDirection,
+    ShadowReport,
+    Subscription,
+    Topic,
+    TransactionFilter,
+    TransactionMetadata,
+    LEDGER_DIGEST_HISTORY,
+    MAX_HEIGHTS_PER_SCAN,
+};
+#[cfg(feature = "cbor")]
+use snarkos_node_messages::CborEncoding;
+use snarkos_node_messages::{Data, HeaderCommitments, UnconfirmedTransaction};
+use snarkos_node_router::{PeerPolicy, Router, Routing, StaleTip, SyncSource};
+use snarkos_node_store::{
+    rocksdb::{dump_column, storage_statistics, MAX_ENTRIES_PER_COLUMN},
+    BlockSubscription,
+    BlockTimeMode,
+    ChainEvent,
+    ChainJournal,
+    ChainMmr,
+    CircuitIndex,
+    CommitmentStatus,
+    DifficultyEntry,
+    DifficultyIndex,
+    HistoryIndex,
+    SequenceIndex,
+    SerialNumberStatus,
+    TimestampIndex,
+    HASHRATE_WINDOW,
+};
 use snarkvm::{
-    console::{account::Address, program::ProgramID, types::Field},
-    prelude::{cfg_into_iter, Network},
-    synthesizer::{ConsensusStorage, Program, Transaction},
+    console::{
+        account::{Address, ViewKey},
+        program::{Identifier, ProgramID},
+        types::Field,
+    },
+    prelude::{cfg_into_iter, Network, ToBytes},
+    synthesizer::{
+        block::{Block, Transactions},
+        ConsensusStorage,
+        Program,
+        Transaction,
+    },
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, ensure, Result};
+use futures_util::{SinkExt, StreamExt};
 use http::header::HeaderName;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, str::FromStr, sync::Arc};
-use tokio::task::JoinHandle;
-use warp::{reject, reply, Filter, Rejection, Reply};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc, time::Duration};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+};
+use warp::{
+    reject,
+    reply,
+    ws::{Message as WsMessage, WebSocket, Ws},
+    Filter,
+    Rejection,
+    Reply,
+};
 
 /// A REST API server for the ledger.
 #[derive(Clone)]
@@ -52,22 +131,94 @@ pub struct Rest<N: Network, C: ConsensusStorage<N>, R: Routing<N>> {
     ledger: Ledger<N, C>,
     /// The node (routing).
     routing: Arc<R>,
+    /// The cache of serialized responses.
+    cache: Arc<ResponseCache<N>>,
+    /// The journal of the changes to the canonical chain.
+    journal: ChainJournal<N>,
+    /// The index of the confirmed transactions by circuit.
+    circuits: CircuitIndex<N>,
+    /// The index of the canonical blocks by timestamp.
+    timestamps: TimestampIndex<N>,
+    /// The index of the serial numbers and commitments by block height.
+    history: HistoryIndex<N>,
+    /// The index of the difficulty and estimated hashrate by block height.
+    difficulty: DifficultyIndex<N>,
+    /// The Merkle mountain range over the canonical block hashes.
+    mmr: ChainMmr<N>,
+    /// The index of the global sequence numbers of the canonical transactions.
+    sequences: SequenceIndex<N>,
+    /// The limiter of the number of requests per second from each IP address.
+    rate_limiter: Arc<RateLimiter>,
+    /// The latency histograms, sizes, and error counts of the requests of each method.
+    telemetry: Arc<RequestTelemetry>,
+    /// The permits to wait on a long-poll request, which bound the number of concurrent waiters.
+    long_polls: Arc<Semaphore>,
+    /// The builder of the transfers from the records of local accounts, if the consensus module is enabled.
+    #[cfg(feature = "builder")]
+    builder: Option<TransactionBuilder<N, C>>,
     /// The server handles.
     handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
 }
 
 impl<N: Network, C: 'static + ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
-    /// Initializes a new instance of the server.
+    /// Initializes a new instance of the server, on each of the given listeners.
     pub fn start(
-        rest_ip: SocketAddr,
+        listeners: Vec<ListenerConfig>,
         consensus: Option<Consensus<N, C>>,
         ledger: Ledger<N, C>,
         routing: Arc<R>,
+        cache: ResponseCache<N>,
     ) -> Result<Self> {
+        // Ensure the listeners are bound to distinct addresses.
+        ensure!(!listeners.is_empty(), "The REST server requires at least one listener");
+        for (index, listener) in listeners.iter().enumerate() {
+            ensure!(
+                listeners[..index].iter().all(|other| other.ip != listener.ip),
+                "Found more than one REST listener on '{}'",
+                listener.ip
+            );
+        }
+        // Open the journal of the changes to the canonical chain.
+        let journal = ChainJournal::open(ledger.vm().block_store().dev())?;
+        // Open the index of the confirmed transactions by circuit.
+        let circuits = CircuitIndex::open(ledger.vm().block_store().dev())?;
+        // Open the index of the canonical blocks by timestamp.
+        let timestamps = TimestampIndex::open(ledger.vm().block_store().dev())?;
+        // Open the index of the serial numbers and commitments by block height.
+        let history = HistoryIndex::open(ledger.vm().block_store().dev())?;
+        // Open the index of the difficulty and estimated hashrate by block height.
+        let difficulty = DifficultyIndex::open(ledger.vm().block_store().dev())?;
+        // Open the Merkle mountain range over the canonical block hashes.
+        let mmr = ChainMmr::open(ledger.vm().block_store().dev())?;
+        // Open the index of the global sequence numbers of the canonical transactions.
+        let sequences = SequenceIndex::open(ledger.vm().block_store().dev())?;
+        // Initialize the builder of the transfers, which submits them to the memory pool.
+        #[cfg(feature = "builder")]
+        let builder = consensus
+            .clone()
+            .map(|consensus| TransactionBuilder::new(ledger.clone(), consensus, DEFAULT_MAX_CONCURRENT_PROOFS));
         // Initialize the server.
-        let mut server = Self { consensus, ledger, routing, handles: Default::default() };
+        let mut server = Self {
+            consensus,
+            ledger,
+            routing,
+            cache: Arc::new(cache),
+            journal,
+            circuits,
+            timestamps,
+            history,
+            difficulty,
+            mmr,
+            sequences,
+            rate_limiter: Default::default(),
+            telemetry: Default::default(),
+            long_polls: Arc::new(Semaphore::new(MAX_LONG_POLLS)),
+            #[cfg(feature = "builder")]
+            builder,
+            handles: Default::default(),
+        };
         // Spawn the server.
-        server.spawn_server(rest_ip);
+        server.spawn_server(listeners);
         // Return the server.
         Ok(server)
     }
@@ -79,6 +230,21 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         &self.ledger
     }
 
+    /// Returns the cache of serialized responses.
+    pub const fn cache(&self) -> &Arc<ResponseCache<N>> {
+        &self.cache
+    }
+
+    /// Returns the limiter of the number of requests per second from each IP address.
+    pub const fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    /// Returns the latency histograms, sizes, and error counts of the requests of each method.
+    pub const fn telemetry(&self) -> &Arc<RequestTelemetry> {
+        &self.telemetry
+    }
+
     /// Returns the handles.
     pub const fn handles(&self) -> &Arc<Mutex<Vec<JoinHandle<()>>>> {
         &self.handles
@@ -86,14 +252,17 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
 }
 
 impl<N: Network, C: 'static + ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
-    /// Initializes the server.
-    fn spawn_server(&mut self, rest_ip: SocketAddr) {
+    /// Initializes the server, with one task serving each of the given listeners.
+    fn spawn_server(&mut self, listeners: Vec<ListenerConfig>) {
         let cors = warp::cors()
             .allow_any_origin()
             .allow_header(HeaderName::from_static("content-type"))
             .allow_methods(vec!["GET", "POST", "OPTIONS"]);
 
-        // Initialize the routes.
+        // Initialize the routes, which are shared by the listeners.
+        #[cfg(feature = "builder")]
+        let routes = self.routes().or(self.builder_routes());
+        #[cfg(not(feature = "builder"))]
         let routes = self.routes();
 
         // Add custom logging for each request.
@@ -102,10 +271,27 @@ impl<N: Network, C: 'static + ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R>
             None => debug!("Received '{} {}' ({})", info.method(), info.path(), info.status()),
         });
 
-        // Spawn the server.
-        self.handles.lock().push(tokio::spawn(async move {
-            // Start the server.
-            warp::serve(routes.with(cors).with(custom_log)).run(rest_ip).await
-        }))
+        for listener in listeners {
+            // Serve the routes behind the rate limit and the method classes of the listener,
+            // and report the rejections with a dedicated status code.
+            let routes = with_rate_limit(self.rate_limiter.clone())
+                .and(with_listener(Arc::new(listener.clone())))
+                .and(routes.clone())
+                .recover(handle_rejection);
+            // Record the latency, sizes, and status of each request, and log the slow requests.
+            let routes = with_telemetry(routes, self.telemetry.clone()).with(cors.clone()).with(custom_log);
+
+            // Spawn the listener.
+            let ListenerConfig { ip, tls, .. } = listener;
+            self.handles.lock().push(tokio::spawn(async move {
+                // Start the listener.
+                match tls {
+                    Some(tls) => {
+                        warp::serve(routes).tls().cert_path(tls.cert_path).key_path(tls.key_path).run(ip).await
+                    }
+                    None => warp::serve(routes).run(ip).await,
+                }
+            }))
+        }
     }
 }
@@ -15,6 +15,7 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use super::*;
+use futures_util::future::Either;
 
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
@@ -27,6 +28,1283 @@ struct BlockRange {
     end: u32,
 }
 
+/// The `get_block_hashes` query object.
+#[derive(Deserialize, Serialize)]
+struct BlockHashRange {
+    /// The starting block height (inclusive).
+    start: u32,
+    /// The ending block height (exclusive).
+    end: u32,
+    /// If `true`, the hashes are returned from the end of the range towards the start.
+    #[serde(default)]
+    reverse: bool,
+    /// The maximum number of hashes to return.
+    limit: Option<usize>,
+    /// If `true`, the block headers are returned instead of the block hashes.
+    #[serde(default)]
+    verbose: bool,
+}
+
+/// The `get_block_by_time` query object.
+#[derive(Deserialize, Serialize)]
+struct BlockTimeQuery {
+    /// The block to select for the timestamp (`before`, `after`, or `nearest`).
+    #[serde(default)]
+    mode: BlockTimeMode,
+}
+
+/// The `get_block_by_time` response object.
+#[derive(Serialize)]
+struct BlockTimeResponse {
+    /// The block height.
+    height: u32,
+    /// The block hash.
+    hash: String,
+    /// The block timestamp.
+    timestamp: i64,
+}
+
+/// The `get_difficulty_history` query object.
+#[derive(Deserialize, Serialize)]
+struct DifficultyHistoryQuery {
+    /// The height of the first block.
+    from: u32,
+    /// The height of the last block [default: the latest height].
+    to: Option<u32>,
+    /// The number of blocks between the sampled blocks [default: 1].
+    stride: Option<u32>,
+}
+
+/// An entry of the `get_difficulty_history` response object.
+#[derive(Serialize)]
+struct DifficultyResponse {
+    /// The block height.
+    height: u32,
+    /// The block timestamp.
+    timestamp: i64,
+    /// The proof target of the block.
+    proof_target: u64,
+    /// The coinbase target of the block.
+    coinbase_target: u64,
+    /// The difficulty of the block, relative to the genesis proof target.
+    difficulty: f64,
+    /// The estimated hashrate as of the block, in work per second over the trailing blocks.
+    hashrate: f64,
+}
+
+impl From<(u32, DifficultyEntry)> for DifficultyResponse {
+    fn from((height, entry): (u32, DifficultyEntry)) -> Self {
+        Self {
+            height,
+            timestamp: entry.timestamp,
+            proof_target: entry.proof_target,
+            coinbase_target: entry.coinbase_target,
+            difficulty: entry.difficulty(),
+            hashrate: entry.hashrate(),
+        }
+    }
+}
+
+/// The `get_network_hashrate` query object.
+#[derive(Deserialize, Serialize)]
+struct NetworkHashrateQuery {
+    /// The number of latest blocks over which the hashrate is estimated [default: `HASHRATE_WINDOW`].
+    window: Option<u32>,
+}
+
+/// The `get_serial_number_status` and `get_commitment_status` query object.
+#[derive(Deserialize, Serialize)]
+struct RecordStatusQuery {
+    /// The block height as of which the status is returned [default: the latest height].
+    height: Option<u32>,
+}
+
+/// The `get_serial_number_status` and `get_commitment_status` response object.
+#[derive(Serialize)]
+struct RecordStatusResponse {
+    /// The status (`unspent`, `spent`, `absent`, `created`, or `unknown` above the canonical chain).
+    status: &'static str,
+    /// The height of the block that spent or created the record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    /// The ID of the transaction that spent or created the record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction_id: Option<String>,
+}
+
+impl RecordStatusResponse {
+    /// Returns the response with the given status, and the given block height and transaction ID, if any.
+    fn new<N: Network>(status: &'static str, spend: Option<(u32, N::TransactionID)>) -> Self {
+        Self {
+            status,
+            height: spend.map(|(height, _)| height),
+            transaction_id: spend.map(|(_, transaction_id)| transaction_id.to_string()),
+        }
+    }
+}
+
+/// The `get_block_hashes` response object.
+#[derive(Serialize)]
+struct BlockHashes<T: Serialize> {
+    /// The latest block height, at the time of the scan.
+    latest_height: u32,
+    /// The block hashes (or block headers, if verbose) in the requested range.
+    blocks: Vec<T>,
+}
+
+/// The encoding of a response.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    /// The JSON encoding.
+    #[default]
+    Json,
+    /// The canonical CBOR encoding (`application/cbor`).
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+/// The `encoding` query object.
+#[derive(Deserialize, Serialize)]
+struct EncodingQuery {
+    /// The encoding of the response.
+    #[serde(default)]
+    encoding: Encoding,
+}
+
+/// The maximum number of chain events returned per call.
+const MAX_CHAIN_EVENTS: usize = 1000;
+
+/// The `get_chain_events` query object.
+#[derive(Deserialize, Serialize)]
+struct ChainEventRange {
+    /// The sequence number after which to return events (exclusive).
+    #[serde(default)]
+    since: u64,
+    /// The maximum number of events to return.
+    limit: Option<usize>,
+}
+
+/// The `get_chain_events` response object.
+#[derive(Serialize)]
+#[serde(bound = "")]
+struct ChainEvents<N: Network> {
+    /// The latest sequence number in the journal, at the time of the request.
+    latest_sequence: u64,
+    /// The journaled events, in sequence order.
+    events: Vec<SequencedChainEvent<N>>,
+}
+
+/// A journaled event and its sequence number.
+#[derive(Serialize)]
+#[serde(bound = "")]
+struct SequencedChainEvent<N: Network> {
+    /// The sequence number of the event.
+    sequence: u64,
+    /// The event.
+    event: ChainEvent<N>,
+}
+
+/// The maximum number of events read at a time for a block subscription.
+const MAX_SUBSCRIPTION_EVENTS: usize = 100;
+/// The maximum interval at which a block subscription checks for new events, once it has caught up,
+/// if no block is connected or disconnected in the meantime.
+const SUBSCRIPTION_POLL_INTERVAL_IN_MS: u64 = 500;
+
+/// The `subscribe_blocks` query object.
+#[derive(Deserialize, Serialize)]
+struct BlockSubscriptionQuery {
+    /// The height from which to stream the canonical blocks, before the new blocks.
+    start_height: Option<u32>,
+    /// If `true`, the transactions of the connected blocks are included.
+    #[serde(default)]
+    include_transactions: bool,
+    /// The token from which to resume a previous subscription, instead of the start height.
+    resume_token: Option<String>,
+}
+
+/// An event sent to a block subscriber.
+#[derive(Serialize)]
+#[serde(bound = "")]
+struct BlockNotification<N: Network> {
+    /// The token from which to resume the subscription, after this event.
+    resume_token: String,
+    /// The event.
+    event: ChainEvent<N>,
+    /// The transactions of the connected block, if they were requested and the block is still stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transactions: Option<Transactions<N>>,
+}
+
+/// The maximum number of transaction subscriptions held by a WebSocket session.
+const MAX_TRANSACTION_SUBSCRIPTIONS: usize = 16;
+/// The interval at which a WebSocket session checks its transaction subscriptions for new transactions.
+const TRANSACTION_SUBSCRIPTION_POLL_INTERVAL_IN_MS: u64 = 100;
+
+/// The filter of a transaction subscription, as sent by the subscriber.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TransactionFilterRequest {
+    /// The minimum value balance (fee) of the transactions, in microcredits.
+    min_value_balance: Option<u64>,
+    /// The hex-encoded prefix of the memo of the transactions.
+    memo_prefix: Option<String>,
+    /// The circuit of one of the transitions of the transactions, as `{program_id}/{function_name}`.
+    circuit: Option<String>,
+    /// The registered account of the transactions, which requires an authenticated session.
+    account: Option<String>,
+}
+
+impl TransactionFilterRequest {
+    /// Validates the request, and returns the filter of the subscription.
+    fn into_filter<N: Network>(self, is_authenticated: bool) -> Result<TransactionFilter<N>> {
+        ensure!(self.memo_prefix.is_none(), "The transactions of this network carry no memo to filter by");
+        let circuit = match self.circuit {
+            Some(circuit) => match circuit.split_once('/') {
+                Some((program_id, function_name)) => {
+                    Some((ProgramID::from_str(program_id)?, Identifier::from_str(function_name)?))
+                }
+                None => bail!("Invalid circuit '{circuit}' (expected '{{program_id}}/{{function_name}}')"),
+            },
+            None => None,
+        };
+        let account = match self.account {
+            Some(address) => {
+                ensure!(is_authenticated, "The account filter requires an authenticated session");
+                Some(Address::from_str(&address)?)
+            }
+            None => None,
+        };
+        Ok(TransactionFilter { min_value_balance: self.min_value_balance, circuit, account })
+    }
+}
+
+/// A request of the subscriber, on a transaction subscription session.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TransactionSessionRequest {
+    /// Subscribes to the transactions that match the filter.
+    Subscribe(TransactionFilterRequest),
+    /// Cancels the subscription with the given ID.
+    Unsubscribe(u64),
+}
+
+/// The `get_request_statistics` response object.
+#[derive(Serialize)]
+struct RequestStatistics {
+    /// The duration after which a request is logged as slow, in milliseconds.
+    slow_threshold_ms: u64,
+    /// The latency histograms, sizes, and error counts of the requests of each method.
+    methods: BTreeMap<String, MethodStatistics>,
+    /// The recent slow requests, from the oldest.
+    slow_requests: Vec<SlowRequest>,
+}
+
+/// The maximum number of transaction IDs of a circuit returned per call.
+const MAX_CIRCUIT_TRANSACTIONS: usize = 1000;
+
+/// The `get_circuit_transactions` query object.
+#[derive(Deserialize, Serialize)]
+struct CircuitTransactionRange {
+    /// The index of the first transaction to return, in order of confirmation.
+    #[serde(default)]
+    start: u64,
+    /// The maximum number of transaction IDs to return.
+    limit: Option<usize>,
+}
+
+/// The statistics of a circuit, in the `get_circuit_stats` response.
+#[derive(Serialize)]
+struct CircuitStats {
+    /// The circuit, as `{programID}/{functionName}`.
+    circuit: String,
+    /// The number of confirmed transactions using the circuit.
+    num_transactions: u64,
+    /// The first height at which the circuit is valid, if it has a consensus rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valid_from_height: Option<u32>,
+    /// The last height at which the circuit is valid, if it has a consensus rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valid_until_height: Option<u32>,
+}
+
+/// The maximum time to wait on a long-poll request, in milliseconds.
+const MAX_WAIT_TIMEOUT_IN_MS: u64 = 300_000;
+/// The maximum number of long-poll requests that wait concurrently, beyond which new ones are rejected.
+const MAX_LONG_POLLS: usize = 256;
+
+/// The `wait_for_transaction` query object.
+#[derive(Deserialize, Serialize)]
+struct TransactionWait {
+    /// The number of confirmations to wait for.
+    #[serde(default = "TransactionWait::default_confirmations")]
+    confirmations: u32,
+    /// The maximum time to wait, in milliseconds.
+    #[serde(default = "TransactionWait::default_timeout")]
+    timeout: u64,
+}
+
+impl TransactionWait {
+    const fn default_confirmations() -> u32 {
+        1
+    }
+
+    const fn default_timeout() -> u64 {
+        MAX_WAIT_TIMEOUT_IN_MS
+    }
+}
+
+/// The `wait_for_transaction` response object.
+#[derive(Serialize)]
+struct TransactionWaitStatus {
+    /// The status of the transaction, one of `confirmed`, `conflicted`, or `timeout`.
+    status: &'static str,
+    /// The number of confirmations of the transaction.
+    confirmations: u32,
+    /// The hash of the block containing the transaction, if it was confirmed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_hash: Option<String>,
+    /// The ID of the conflicting transaction that was confirmed instead, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflicting_transaction_id: Option<String>,
+}
+
+impl<N: Network> From<TransactionStatus<N>> for TransactionWaitStatus {
+    fn from(status: TransactionStatus<N>) -> Self {
+        match status {
+            TransactionStatus::Confirmed { block_hash, confirmations } => Self {
+                status: "confirmed",
+                confirmations,
+                block_hash: Some(block_hash.to_string()),
+                conflicting_transaction_id: None,
+            },
+            TransactionStatus::Conflicted { transaction_id } => Self {
+                status: "conflicted",
+                confirmations: 0,
+                block_hash: None,
+                conflicting_transaction_id: Some(transaction_id.to_string()),
+            },
+            TransactionStatus::TimedOut { confirmations } => {
+                Self { status: "timeout", confirmations, block_hash: None, conflicting_transaction_id: None }
+            }
+        }
+    }
+}
+
+/// The `transactions_broadcast` query object.
+#[derive(Deserialize, Serialize)]
+struct BatchQuery {
+    /// The mode of the batch, either `atomic` (the default) or `best-effort`.
+    #[serde(default)]
+    mode: Option<String>,
+}
+
+/// The outcome of a transaction in the `transactions_broadcast` response.
+#[derive(Serialize)]
+struct BatchTransactionStatus {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The status of the transaction, one of `accepted` or `rejected`.
+    status: &'static str,
+    /// The kind of rejection, one of `duplicate`, `conflict`, `invalid`, `aborted`, or `batch_too_large`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rejection: Option<&'static str>,
+    /// The reason the transaction was rejected, if it was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// The ID of the transaction that caused the rejection, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflicting_transaction_id: Option<String>,
+}
+
+impl<N: Network> From<BatchOutcome<N>> for BatchTransactionStatus {
+    fn from(outcome: BatchOutcome<N>) -> Self {
+        let transaction_id = outcome.transaction_id.to_string();
+        match outcome.rejection {
+            None => Self {
+                transaction_id,
+                status: "accepted",
+                rejection: None,
+                reason: None,
+                conflicting_transaction_id: None,
+            },
+            Some(rejection) => {
+                let conflicting_transaction_id = match &rejection {
+                    TransactionRejection::Conflict { transaction_id }
+                    | TransactionRejection::Aborted { transaction_id } => Some(transaction_id.to_string()),
+                    _ => None,
+                };
+                Self {
+                    transaction_id,
+                    status: "rejected",
+                    rejection: Some(rejection.kind()),
+                    reason: Some(rejection.to_string()),
+                    conflicting_transaction_id,
+                }
+            }
+        }
+    }
+}
+
+/// The `simulate_acceptance` query object.
+#[derive(Deserialize, Serialize)]
+struct SimulationQuery {
+    /// If `true`, the proofs of the transaction are verified by the simulation.
+    #[serde(default)]
+    verify_proof: bool,
+}
+
+/// A reason the transaction would be rejected, in the `simulate_acceptance` response.
+#[derive(Serialize)]
+struct SimulatedRejection {
+    /// The kind of rejection, as in the `transactions_broadcast` response.
+    kind: &'static str,
+    /// The reason the transaction would be rejected.
+    reason: String,
+    /// The ID of the transaction in the memory pool that causes the rejection, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflicting_transaction_id: Option<String>,
+}
+
+/// The `simulate_acceptance` response object.
+#[derive(Serialize)]
+struct AcceptanceSimulationResponse {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The verdict of the simulation, one of `accepted` or `rejected`.
+    verdict: &'static str,
+    /// The reasons the transaction would be rejected, in order of the checks.
+    rejections: Vec<SimulatedRejection>,
+    /// The fee of the transaction (in microcredits).
+    fee: u64,
+    /// The size of the transaction (in bytes).
+    size: usize,
+    /// The fee rate of the transaction (in microcredits per byte).
+    fee_rate: f64,
+    /// The percentage of the transactions in the memory pool with a lower fee rate.
+    fee_rate_percentile: f64,
+    /// The estimated position of the transaction in the next block, if it would be selected for it.
+    template_position: Option<usize>,
+    /// The IDs of the transactions in the memory pool that conflict with the transaction.
+    conflicts: Vec<String>,
+    /// The IDs of the transactions in the memory pool that the transaction depends on.
+    parents: Vec<String>,
+    /// Whether the proofs of the transaction were verified.
+    proof_verified: bool,
+}
+
+impl<N: Network> From<AcceptanceSimulation<N>> for AcceptanceSimulationResponse {
+    fn from(simulation: AcceptanceSimulation<N>) -> Self {
+        let to_strings = |ids: Vec<N::TransactionID>| ids.iter().map(|id| id.to_string()).collect();
+        Self {
+            transaction_id: simulation.transaction_id.to_string(),
+            verdict: match simulation.is_accepted() {
+                true => "accepted",
+                false => "rejected",
+            },
+            rejections: simulation
+                .rejections
+                .iter()
+                .map(|rejection| SimulatedRejection {
+                    kind: rejection.kind(),
+                    reason: rejection.to_string(),
+                    conflicting_transaction_id: match rejection {
+                        TransactionRejection::Conflict { transaction_id } => Some(transaction_id.to_string()),
+                        _ => None,
+                    },
+                })
+                .collect(),
+            fee: simulation.fee,
+            size: simulation.size,
+            fee_rate: simulation.fee_rate,
+            fee_rate_percentile: simulation.fee_rate_percentile,
+            template_position: simulation.template_position,
+            conflicts: to_strings(simulation.conflicts),
+            parents: to_strings(simulation.parents),
+            proof_verified: simulation.is_proof_verified,
+        }
+    }
+}
+
+/// The `get_block_template` query object.
+#[derive(Deserialize, Serialize)]
+struct BlockTemplateQuery {
+    /// The long-poll ID of a previous template, to wait for the template to become stale.
+    longpollid: Option<String>,
+    /// The maximum time to wait, in milliseconds.
+    #[serde(default = "TransactionWait::default_timeout")]
+    timeout: u64,
+}
+
+/// The `get_block_template` response object.
+#[derive(Serialize)]
+struct BlockTemplateResponse {
+    /// The hash of the block the template builds on.
+    previous_block_hash: String,
+    /// The height of the next block.
+    height: u32,
+    /// The round of the next block.
+    round: u64,
+    /// The UNIX timestamp (in seconds) at which the template was built.
+    timestamp: i64,
+    /// The latest coinbase target.
+    coinbase_target: u64,
+    /// The latest proof target.
+    proof_target: u64,
+    /// The IDs of the transactions selected for the next block.
+    transactions: Vec<String>,
+    /// The fees of the selected transactions (in microcredits).
+    fees: u64,
+    /// The number of transactions and the total fees the header commits to, once the commitments are active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    header_commitments: Option<HeaderCommitments>,
+    /// The coinbase reward of the next block (in microcredits), if it includes a coinbase solution.
+    coinbase_reward: u64,
+    /// The outputs of the coinbase, which split the coinbase reward and the fees among the coinbase recipients.
+    coinbase: Vec<CoinbaseOutputResponse>,
+    /// The long-poll ID of the template.
+    longpollid: String,
+}
+
+/// An output of the coinbase, in the `get_block_template` response object.
+#[derive(Serialize)]
+struct CoinbaseOutputResponse {
+    /// The address of the recipient.
+    recipient: String,
+    /// The amount paid to the recipient (in microcredits).
+    amount: u64,
+}
+
+impl<N: Network> From<&BlockTemplate<N>> for BlockTemplateResponse {
+    fn from(template: &BlockTemplate<N>) -> Self {
+        Self {
+            previous_block_hash: template.previous_block_hash.to_string(),
+            height: template.height,
+            round: template.round,
+            timestamp: template.timestamp,
+            coinbase_target: template.coinbase_target,
+            proof_target: template.proof_target,
+            transactions: template.transactions.iter().map(|id| id.to_string()).collect(),
+            fees: template.fees,
+            header_commitments: template.commitments,
+            coinbase_reward: template.coinbase_reward,
+            coinbase: template
+                .coinbase
+                .iter()
+                .map(|output| CoinbaseOutputResponse { recipient: output.recipient.to_string(), amount: output.amount })
+                .collect(),
+            longpollid: template.longpoll_id.to_string(),
+        }
+    }
+}
+
+/// The `get_block_submission` query object.
+#[derive(Deserialize, Serialize)]
+struct SubmissionQuery {
+    /// The maximum time to wait for the submission to complete, in milliseconds [default: 0, to poll].
+    #[serde(default)]
+    timeout: u64,
+}
+
+/// The `submit_block` and `get_block_submission` response object.
+#[derive(Serialize)]
+struct SubmitBlockResponse {
+    /// The status of the submission, which is `pending`, `accepted`, `duplicate`, `precheck_failed`, or `rejected`.
+    status: &'static str,
+    /// The hash of the submitted block.
+    block_hash: String,
+    /// The ID of the submission, if the block passed the pre-checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submission_id: Option<u64>,
+    /// The hash of the block that was committed first, if the submission is a duplicate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<String>,
+    /// The code of the failed pre-check, if the block failed the pre-checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    /// The reason the block was rejected, if it was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+impl SubmitBlockResponse {
+    /// Returns the response to the submission of the given block, with the given ID (if any) and outcome.
+    fn new<N: Network>(block_hash: N::BlockHash, submission_id: Option<u64>, outcome: SubmissionOutcome<N>) -> Self {
+        let status = outcome.status();
+        let (duplicate_of, code, reason) = match outcome {
+            SubmissionOutcome::Accepted => (None, None, None),
+            SubmissionOutcome::Duplicate { block_hash, .. } => (Some(block_hash.to_string()), None, None),
+            SubmissionOutcome::PrecheckFailed(failure) => (None, Some(failure.code()), Some(failure.to_string())),
+            SubmissionOutcome::Rejected(reason) => (None, None, Some(reason)),
+        };
+        Self { status, block_hash: block_hash.to_string(), submission_id, duplicate_of, code, reason }
+    }
+
+    /// Returns the response to the submission with the given ID, and the given status.
+    fn from_status<N: Network>(submission_id: u64, status: SubmissionStatus<N>) -> Self {
+        match status {
+            SubmissionStatus::Pending { block_hash } => Self {
+                status: "pending",
+                block_hash: block_hash.to_string(),
+                submission_id: Some(submission_id),
+                duplicate_of: None,
+                code: None,
+                reason: None,
+            },
+            SubmissionStatus::Completed { block_hash, outcome } => Self::new(block_hash, Some(submission_id), outcome),
+        }
+    }
+}
+
+/// The `get_chain_info` response object.
+#[derive(Serialize)]
+struct ChainInfoResponse {
+    /// The height of the latest block.
+    height: u32,
+    /// The hash of the latest block.
+    hash: String,
+    /// The deployment of each consensus rule, as of the next block.
+    rules: Vec<RuleDeploymentResponse>,
+}
+
+/// The deployment of a consensus rule, in the `get_chain_info` response object.
+#[derive(Serialize)]
+struct RuleDeploymentResponse {
+    /// The name of the rule.
+    name: &'static str,
+    /// The status of the rule, as `defined` or `active`.
+    status: String,
+    /// The activation height of the rule, if it is scheduled.
+    height: Option<u32>,
+}
+
+impl From<RuleDeployment> for RuleDeploymentResponse {
+    fn from(deployment: RuleDeployment) -> Self {
+        Self {
+            name: deployment.rule.name(),
+            status: deployment.status.to_string(),
+            height: deployment.activation_height,
+        }
+    }
+}
+
+/// The `get_miner_info` response object.
+#[derive(Serialize)]
+struct MinerInfoResponse {
+    /// The recipients of the coinbase, with their weights.
+    recipients: Vec<CoinbaseRecipientResponse>,
+}
+
+/// A recipient of the coinbase, in the `get_miner_info` response object.
+#[derive(Serialize)]
+struct CoinbaseRecipientResponse {
+    /// The address of the recipient.
+    address: String,
+    /// The weight of the recipient, relative to the weights of the other recipients.
+    weight: u64,
+    /// The number of blocks mined by this node for the recipient.
+    mined_blocks: u64,
+}
+
+impl<N: Network> From<MinerInfo<N>> for MinerInfoResponse {
+    fn from(info: MinerInfo<N>) -> Self {
+        let recipients = info.recipients.as_ref().map_or(&[][..], |recipients| recipients.recipients());
+        Self {
+            recipients: recipients
+                .iter()
+                .map(|recipient| CoinbaseRecipientResponse {
+                    address: recipient.address.to_string(),
+                    weight: recipient.weight,
+                    mined_blocks: info.mined_blocks.get(&recipient.address).copied().unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The maximum number of memory pool entries returned per call.
+const MAX_MEMORY_POOL_ENTRIES: usize = 100;
+
+/// The `get_memory_pool_snapshot` query object.
+#[derive(Deserialize, Serialize)]
+struct MemoryPoolPage {
+    /// The number of entries to skip, in order of arrival.
+    #[serde(default)]
+    offset: usize,
+    /// The maximum number of entries to return.
+    limit: Option<usize>,
+}
+
+/// The maximum number of account history entries returned per call.
+const MAX_ACCOUNT_HISTORY_ENTRIES: usize = 100;
+
+/// The `get_account_history` query object.
+#[derive(Deserialize, Serialize)]
+struct AccountHistoryQuery {
+    /// The number of entries to skip, in order of height.
+    #[serde(default)]
+    offset: usize,
+    /// The maximum number of entries to return.
+    limit: Option<usize>,
+}
+
+/// An entry of the `get_account_history` response object.
+#[derive(Serialize)]
+struct AccountHistoryEntryResponse {
+    /// The height of the block containing the transaction.
+    height: u32,
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// Whether the record was `received` or `spent` by the transaction.
+    direction: &'static str,
+    /// The commitment of the record.
+    commitment: String,
+    /// The amount of microcredits of the record, if it could be decrypted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<u64>,
+}
+
+impl<N: Network> From<AccountHistoryEntry<N>> for AccountHistoryEntryResponse {
+    fn from(entry: AccountHistoryEntry<N>) -> Self {
+        Self {
+            height: entry.height,
+            transaction_id: entry.transaction_id.to_string(),
+            direction: match entry.direction {
+                RecordDirection::Received => "received",
+                RecordDirection::Spent => "spent",
+            },
+            commitment: entry.commitment.to_string(),
+            amount: entry.amount,
+        }
+    }
+}
+
+/// The `get_account_history` response object.
+#[derive(Serialize)]
+struct AccountHistoryResponse {
+    /// The latest block height, at the time of the request.
+    latest_height: u32,
+    /// The number of entries in the history of the account.
+    num_entries: usize,
+    /// The entries of the requested page, in order of height.
+    entries: Vec<AccountHistoryEntryResponse>,
+}
+
+/// The `get_transaction_metadata` response object.
+#[derive(Serialize)]
+struct TransactionMetadataResponse {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The hash of the block containing the transaction.
+    block_hash: String,
+    /// The height of the block containing the transaction.
+    block_height: u32,
+    /// The global sequence number of the transaction in the canonical chain.
+    sequence: u64,
+    /// The type of the transaction, one of `deploy` or `execute`.
+    kind: &'static str,
+    /// The ID of the deployed program, if the transaction is a deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program_id: Option<String>,
+}
+
+impl TransactionMetadataResponse {
+    /// Initializes the response from the given metadata and global sequence number of the transaction.
+    fn new<N: Network>(metadata: TransactionMetadata<N>, sequence: u64) -> Self {
+        Self {
+            transaction_id: metadata.id.to_string(),
+            block_hash: metadata.block_hash.to_string(),
+            block_height: metadata.block_height,
+            sequence,
+            kind: match metadata.program_id {
+                Some(_) => "deploy",
+                None => "execute",
+            },
+            program_id: metadata.program_id.map(|program_id| program_id.to_string()),
+        }
+    }
+}
+
+/// The `get_ledger_membership_proof` query object.
+#[derive(Deserialize, Serialize)]
+#[serde(bound = "")]
+struct LedgerDigestQuery<N: Network> {
+    /// The ledger digest to prove against, which defaults to the latest state root.
+    digest: Option<N::StateRoot>,
+}
+
+/// The `get_ledger_membership_proof` response object.
+#[derive(Serialize)]
+struct LedgerMembershipProofResponse {
+    /// The commitment.
+    commitment: String,
+    /// The ledger digest.
+    digest: String,
+    /// The index of the block containing the commitment, in the ledger Merkle tree.
+    leaf_index: u64,
+    /// The nodes of the Merkle path from the block to the ledger digest, as hex-encoded bytes.
+    path: Vec<String>,
+    /// The state path from the commitment to the ledger digest.
+    state_path: String,
+}
+
+impl<N: Network> TryFrom<LedgerMembershipProof<N>> for LedgerMembershipProofResponse {
+    type Error = anyhow::Error;
+
+    fn try_from(proof: LedgerMembershipProof<N>) -> Result<Self> {
+        Ok(Self {
+            commitment: proof.commitment().to_string(),
+            digest: proof.digest().to_string(),
+            leaf_index: proof.leaf_index(),
+            path: proof.path().iter().map(|node| Ok(hex::encode(node.to_bytes_le()?))).collect::<Result<_>>()?,
+            state_path: proof.state_path().to_string(),
+        })
+    }
+}
+
+/// The `get_deposit_proof` response object.
+#[derive(Serialize)]
+struct DepositProofResponse {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The transaction, as hex-encoded bytes.
+    transaction: String,
+    /// The header of the block containing the transaction, as hex-encoded bytes.
+    header: String,
+    /// The height of the block containing the transaction.
+    height: u32,
+    /// The hash of the block containing the transaction.
+    block_hash: String,
+    /// The height of the tip against which the proof is made.
+    tip_height: u32,
+    /// The number of confirmations of the transaction, which is `1` if its block is the tip.
+    confirmations: u32,
+    /// The difference between the cumulative proof target of the tip and of the block.
+    chain_work: u128,
+    /// The deposit proof, as hex-encoded bytes, which is verified offline with `verify_deposit_proof`.
+    proof: String,
+}
+
+impl<N: Network> TryFrom<DepositProof<N>> for DepositProofResponse {
+    type Error = anyhow::Error;
+
+    fn try_from(proof: DepositProof<N>) -> Result<Self> {
+        Ok(Self {
+            transaction_id: proof.transaction().id().to_string(),
+            transaction: hex::encode(proof.transaction().to_bytes_le()?),
+            header: hex::encode(proof.header().to_bytes_le()?),
+            height: proof.header().height(),
+            block_hash: proof.block_hash()?.to_string(),
+            tip_height: proof.tip_height(),
+            confirmations: proof.confirmations(),
+            chain_work: proof.chain_work(),
+            proof: hex::encode(proof.to_bytes_le()?),
+        })
+    }
+}
+
+/// The `get_ledger_digest` query object.
+#[derive(Deserialize, Serialize)]
+struct LedgerDigestHeightQuery {
+    /// The height of the block, which defaults to the latest block.
+    height: Option<u32>,
+}
+
+/// The `get_ledger_digest_history` query object.
+#[derive(Deserialize, Serialize)]
+struct LedgerDigestHistoryQuery {
+    /// The number of latest ledger digests.
+    count: u32,
+}
+
+/// The `get_ledger_digest` response object.
+#[derive(Serialize)]
+struct LedgerDigestResponse {
+    /// The height of the block of the ledger digest.
+    height: u32,
+    /// The hash of the block of the ledger digest.
+    block_hash: String,
+    /// The ledger digest, which is the root of the ledger Merkle tree over the block hashes.
+    digest: String,
+    /// The number of commitments in the ledger at the block.
+    num_commitments: u64,
+    /// The number of serial numbers in the ledger at the block.
+    num_serial_numbers: u64,
+    /// The number of latest ledger digests against which the node serves membership proofs.
+    max_digest_age: u32,
+}
+
+impl<N: Network> From<LedgerDigest<N>> for LedgerDigestResponse {
+    fn from(digest: LedgerDigest<N>) -> Self {
+        Self {
+            height: digest.height,
+            block_hash: digest.block_hash.to_string(),
+            digest: digest.digest.to_string(),
+            num_commitments: digest.num_commitments,
+            num_serial_numbers: digest.num_serial_numbers,
+            max_digest_age: LEDGER_DIGEST_HISTORY,
+        }
+    }
+}
+
+/// A ledger digest in the `get_ledger_digest_history` response.
+#[derive(Serialize)]
+struct HistoricalDigest {
+    /// The height of the block of the ledger digest.
+    height: u32,
+    /// The ledger digest.
+    digest: String,
+}
+
+/// The `get_ledger_digest_history` response object.
+#[derive(Serialize)]
+struct LedgerDigestHistoryResponse {
+    /// The ledger digests, starting from the latest block.
+    digests: Vec<HistoricalDigest>,
+    /// The number of latest ledger digests against which the node serves membership proofs.
+    max_digest_age: u32,
+}
+
+/// A chain tip in the `get_chain_tips` response.
+#[derive(Serialize)]
+struct ChainTipResponse {
+    /// The height of the tip.
+    height: u32,
+    /// The block hash of the tip.
+    hash: String,
+    /// The number of blocks from the fork point with the canonical chain to the tip.
+    branch_length: u32,
+    /// The cumulative proof target at the tip.
+    cumulative_proof_target: u128,
+    /// The status of the tip (`active`, `valid-fork`, `invalid`, or `headers-only`).
+    status: String,
+}
+
+impl<N: Network> From<ChainTip<N>> for ChainTipResponse {
+    fn from(tip: ChainTip<N>) -> Self {
+        Self {
+            height: tip.height,
+            hash: tip.hash.to_string(),
+            branch_length: tip.branch_length,
+            cumulative_proof_target: tip.cumulative_proof_target,
+            status: tip.status.to_string(),
+        }
+    }
+}
+
+/// A divergence in the `get_shadow_report` response.
+#[derive(Serialize)]
+struct DivergenceResponse {
+    /// The key that differs between the primary storage and the shadow storage.
+    key: String,
+    /// The value in the primary storage, if it exists.
+    primary: Option<String>,
+    /// The value in the shadow storage, if it exists.
+    shadow: Option<String>,
+}
+
+/// The `get_shadow_report` response object.
+#[derive(Serialize)]
+struct ShadowReportResponse {
+    /// Whether the shadow storage is enabled.
+    enabled: bool,
+    /// Whether every canon mutation since the shadow storage was enabled has been applied to it.
+    synced: bool,
+    /// The latest height of the primary storage.
+    primary_height: u32,
+    /// The latest height of the shadow storage.
+    shadow_height: Option<u32>,
+    /// The number of mutations that are waiting to be applied to the shadow storage.
+    queued: usize,
+    /// The number of mutations applied to the shadow storage.
+    applied: u64,
+    /// The number of mutations that failed to apply to the shadow storage.
+    failures: u64,
+    /// The number of comparisons between the primary storage and the shadow storage.
+    comparisons: u64,
+    /// The height of the shadow storage at the last comparison.
+    last_comparison_height: Option<u32>,
+    /// The number of divergences found by the comparisons.
+    num_divergences: u64,
+    /// The latest divergences.
+    divergences: Vec<DivergenceResponse>,
+    /// The last error of the shadow storage.
+    last_error: Option<String>,
+}
+
+impl From<ShadowReport> for ShadowReportResponse {
+    fn from(report: ShadowReport) -> Self {
+        Self {
+            enabled: report.is_enabled,
+            synced: report.is_synced,
+            primary_height: report.primary_height,
+            shadow_height: report.shadow_height,
+            queued: report.num_queued,
+            applied: report.num_applied,
+            failures: report.num_failures,
+            comparisons: report.num_comparisons,
+            last_comparison_height: report.last_comparison_height,
+            num_divergences: report.num_divergences,
+            divergences: report
+                .divergences
+                .into_iter()
+                .map(|divergence| DivergenceResponse {
+                    key: divergence.key,
+                    primary: divergence.primary,
+                    shadow: divergence.shadow,
+                })
+                .collect(),
+            last_error: report.last_error,
+        }
+    }
+}
+
+/// The `get_node_state` response.
+#[derive(Serialize)]
+struct NodeStateResponse {
+    /// The type of the node.
+    node_type: String,
+    /// The node ID of the node, which is derived from its static public key.
+    node_id: String,
+    /// The role of the node on the network (`full`, `outbound-only`, or `client`).
+    role: String,
+    /// The capabilities the node advertises to its peers.
+    capabilities: String,
+    /// Whether the node is listening for inbound connections.
+    listening: bool,
+    /// The number of connected peers.
+    connected_peers: usize,
+    /// The progress of the latest schema migration applied by the node, if one was applied.
+    migration: Option<snarkos_node_store::MigrationStatus>,
+    /// The throughput (in bytes per second) below which a sync peer is demoted, or `0` if peers are never demoted.
+    sync_throughput_floor: u64,
+    /// The assignments of the peers as sources of blocks while syncing.
+    sync_sources: Vec<SyncSourceResponse>,
+    /// The number of seconds since the canon last advanced.
+    secs_since_canon_advance: u64,
+    /// The stale tip, if the canon has not advanced for too long while a peer advertises a higher block height.
+    stale_tip: Option<StaleTipResponse>,
+    /// The network-adjusted time of the node.
+    network_time: NetworkTimeResponse,
+}
+
+/// The network-adjusted time of the node, in the `get_node_state` response.
+#[derive(Serialize)]
+struct NetworkTimeResponse {
+    /// The adjustment (in seconds) of the local clock, which is capped.
+    offset_secs: i64,
+    /// The median offset (in seconds) of the clocks of the peers from the local clock, without the cap.
+    median_offset_secs: i64,
+    /// The number of peer clock samples.
+    num_samples: usize,
+    /// Whether the local clock is skewed from the clocks of the peers.
+    skewed: bool,
+}
+
+impl From<NetworkTime> for NetworkTimeResponse {
+    fn from(time: NetworkTime) -> Self {
+        Self {
+            offset_secs: time.offset(),
+            median_offset_secs: time.median_offset(),
+            num_samples: time.num_samples(),
+            skewed: time.is_skewed(),
+        }
+    }
+}
+
+/// The stale tip of the node, in the `get_node_state` response.
+#[derive(Serialize)]
+struct StaleTipResponse {
+    /// The latest block height of the node.
+    canon_height: u32,
+    /// The highest block height advertised by a peer.
+    peer_height: u32,
+    /// The number of seconds since the canon last advanced.
+    stale_secs: u64,
+    /// Whether the tip is stale long enough for the DNS seeds to be re-queried.
+    escalated: bool,
+}
+
+impl From<StaleTip> for StaleTipResponse {
+    fn from(stale_tip: StaleTip) -> Self {
+        Self {
+            canon_height: stale_tip.canon_height,
+            peer_height: stale_tip.peer_height,
+            stale_secs: stale_tip.stale_for.as_secs(),
+            escalated: stale_tip.is_escalated,
+        }
+    }
+}
+
+/// The assignment of a peer as a source of blocks, in the `get_node_state` response.
+#[derive(Serialize)]
+struct SyncSourceResponse {
+    /// The IP of the peer.
+    peer_ip: String,
+    /// The class of the peer (`archival`, `pruned`, or `headers-only`).
+    class: String,
+    /// The latest block height of the peer.
+    height: u32,
+    /// The height below which the peer pruned the bodies of its blocks.
+    pruned_height: u32,
+    /// The throughput (in bytes per second) of the recent block responses of the peer, if enough were measured.
+    throughput: Option<f64>,
+    /// Whether the peer is demoted, as it delivered blocks below the throughput floor.
+    demoted: bool,
+    /// The heights of the blocks that are requested from the peer, and not received yet.
+    requested_heights: Vec<u32>,
+}
+
+impl From<SyncSource> for SyncSourceResponse {
+    fn from(source: SyncSource) -> Self {
+        Self {
+            peer_ip: source.peer_ip.to_string(),
+            class: source.class.to_string(),
+            height: source.height,
+            pruned_height: source.pruned_height,
+            throughput: source.throughput,
+            demoted: source.is_demoted,
+            requested_heights: source.requested_heights,
+        }
+    }
+}
+
+/// The `dump_storage_column` query object.
+#[derive(Deserialize, Serialize)]
+struct StorageDumpQuery {
+    /// The hex-encoded key from which to start, inclusive.
+    #[serde(default)]
+    start: Option<String>,
+    /// The hex-encoded key at which to stop, exclusive.
+    #[serde(default)]
+    end: Option<String>,
+    /// The maximum number of entries to return, at most `MAX_RAW_ENTRIES`.
+    #[serde(default = "StorageDumpQuery::default_limit")]
+    limit: usize,
+}
+
+impl StorageDumpQuery {
+    const fn default_limit() -> usize {
+        100
+    }
+}
+
+/// The `import_peer_policy` query object.
+#[derive(Deserialize, Serialize)]
+struct PeerPolicyImport {
+    /// If `true`, the valid entries replace the current peer policy, instead of being merged into it.
+    #[serde(default)]
+    replace: bool,
+}
+
+/// The `get_local_transactions` response object.
+#[derive(Serialize)]
+struct LocalTransactionResponse {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The status of the transaction, which is `pending` or `abandoned`.
+    status: String,
+}
+
+/// The `get_invalid_blocks` response object.
+#[derive(Serialize)]
+struct InvalidBlockResponse {
+    /// The hash of the block.
+    block_hash: String,
+    /// The height of the block.
+    height: u32,
+    /// The reason the block was rejected.
+    reason: String,
+    /// The UNIX timestamp (in seconds) at which the block was first rejected.
+    first_seen: i64,
+    /// The IP address of the peer that sent the block, if it is known.
+    peer_ip: Option<String>,
+}
+
+/// The `purge_invalid_blocks` query object.
+#[derive(Deserialize, Serialize)]
+#[serde(bound = "")]
+struct InvalidBlockPurgeQuery<N: Network> {
+    /// The hash of the block to purge, which defaults to every invalid block.
+    hash: Option<N::BlockHash>,
+}
+
+/// The `get_audit_log` response object.
+#[derive(Serialize)]
+struct AuditLogResponse {
+    /// Whether a block failed its audit since the last acknowledgement.
+    has_failure: bool,
+    /// The remembered audits, from the earliest to the latest.
+    audits: Vec<AuditResponse>,
+}
+
+/// An audit of a historical block, in the `get_audit_log` response object.
+#[derive(Serialize)]
+struct AuditResponse {
+    /// The height of the block.
+    height: u32,
+    /// The hash of the block.
+    block_hash: String,
+    /// The check that failed, and its error, if the block failed its audit.
+    failure: Option<String>,
+    /// The UNIX timestamp (in seconds) at which the block was audited.
+    audited_at: i64,
+}
+
+/// The `get_memory_pool_entry` response object.
+#[derive(Serialize)]
+struct MemoryPoolEntryResponse {
+    /// The ID of the transaction.
+    transaction_id: String,
+    /// The fee of the transaction (in microcredits).
+    fee: u64,
+    /// The size of the transaction (in bytes).
+    size: usize,
+    /// The fee rate of the transaction (in microcredits per byte).
+    fee_rate: f64,
+    /// The UNIX timestamp (in seconds) at which the transaction was added to the memory pool.
+    arrival_time: i64,
+    /// The IDs of the transactions in the memory pool that the transaction depends on.
+    parents: Vec<String>,
+    /// The IDs of the transactions in the memory pool that depend on the transaction.
+    children: Vec<String>,
+    /// The IDs of the transactions in the memory pool that conflict with the transaction.
+    conflicts: Vec<String>,
+    /// The number of blocks since the oldest global state root of the transaction, if it is known.
+    state_root_age: Option<u32>,
+    /// Whether the transaction is selected for the current block template.
+    in_block_template: bool,
+}
+
+impl<N: Network> From<MemoryPoolEntry<N>> for MemoryPoolEntryResponse {
+    fn from(entry: MemoryPoolEntry<N>) -> Self {
+        let to_strings = |ids: Vec<N::TransactionID>| ids.iter().map(|id| id.to_string()).collect();
+        Self {
+            transaction_id: entry.transaction_id.to_string(),
+            fee: entry.fee,
+            size: entry.size,
+            fee_rate: entry.fee_rate,
+            arrival_time: entry.arrival_time,
+            parents: to_strings(entry.parents),
+            children: to_strings(entry.children),
+            conflicts: to_strings(entry.conflicts),
+            state_root_age: entry.state_root_age,
+            in_block_template: entry.in_block_template,
+        }
+    }
+}
+
+/// The `get_memory_pool_snapshot` response object.
+#[derive(Serialize)]
+struct MemoryPoolSnapshotResponse {
+    /// The latest block height, at the time of the snapshot.
+    latest_height: u32,
+    /// The number of transactions in the memory pool, at the time of the snapshot.
+    num_transactions: usize,
+    /// The entries of the requested page, in order of arrival.
+    entries: Vec<MemoryPoolEntryResponse>,
+}
+
 impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
     /// Initializes the routes, given the ledger and ledger sender.
     pub fn routes(&self) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
@@ -42,9 +1320,10 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(with(self.ledger.clone()))
             .and_then(Self::latest_hash);
 
-        // GET /testnet3/latest/block
+        // GET /testnet3/latest/block?encoding={json|cbor}
         let latest_block = warp::get()
             .and(warp::path!("testnet3" / "latest" / "block"))
+            .and(warp::query::<EncodingQuery>())
             .and(with(self.ledger.clone()))
             .and_then(Self::latest_block);
 
@@ -54,10 +1333,12 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(with(self.ledger.clone()))
             .and_then(Self::latest_state_root);
 
-        // GET /testnet3/block/{height}
+        // GET /testnet3/block/{height}?encoding={json|cbor}
         let get_block = warp::get()
             .and(warp::path!("testnet3" / "block" / u32))
+            .and(warp::query::<EncodingQuery>())
             .and(with(self.ledger.clone()))
+            .and(with(self.cache.clone()))
             .and_then(Self::get_block);
 
         // GET /testnet3/blocks?start={start_height}&end={end_height}
@@ -67,13 +1348,55 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(with(self.ledger.clone()))
             .and_then(Self::get_blocks);
 
-        // GET /testnet3/block/{blockHash}
+        // GET /testnet3/blocks/hashes?start={start_height}&end={end_height}&reverse={bool}&limit={limit}&verbose={bool}
+        let get_block_hashes = warp::get()
+            .and(warp::path!("testnet3" / "blocks" / "hashes"))
+            .and(warp::query::<BlockHashRange>())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_block_hashes);
+
+        // GET /testnet3/block/time/{unixSeconds}?mode={before|after|nearest}
+        let get_block_by_time = warp::get()
+            .and(warp::path!("testnet3" / "block" / "time" / i64))
+            .and(warp::query::<BlockTimeQuery>())
+            .and(with(self.ledger.clone()))
+            .and(with(self.timestamps.clone()))
+            .and_then(Self::get_block_by_time);
+
+        // GET /testnet3/difficulty/history?from={height}&to={height}&stride={stride}
+        let get_difficulty_history = warp::get()
+            .and(warp::path!("testnet3" / "difficulty" / "history"))
+            .and(warp::query::<DifficultyHistoryQuery>())
+            .and(with(self.difficulty.clone()))
+            .and_then(Self::get_difficulty_history);
+
+        // GET /testnet3/networkHashrate?window={window}
+        let get_network_hashrate = warp::get()
+            .and(warp::path!("testnet3" / "networkHashrate"))
+            .and(warp::query::<NetworkHashrateQuery>())
+            .and(with(self.difficulty.clone()))
+            .and_then(Self::get_network_hashrate);
+
+        // GET /testnet3/block/{blockHash}?encoding={json|cbor}
         let get_block_by_hash = warp::get()
             .and(warp::path!("testnet3" / "block" / ..))
             .and(warp::path::param::<N::BlockHash>())
+            .and(warp::path::end())
+            .and(warp::query::<EncodingQuery>())
             .and(with(self.ledger.clone()))
+            .and(with(self.cache.clone()))
             .and_then(Self::get_block_by_hash);
 
+        // GET /testnet3/block/{blockHash}/raw
+        let get_raw_block = warp::get()
+            .and(warp::path!("testnet3" / "block" / ..))
+            .and(warp::path::param::<N::BlockHash>())
+            .and(warp::path!("raw"))
+            .and(warp::header::optional::<String>("accept"))
+            .and(with(self.ledger.clone()))
+            .and(with(self.cache.clone()))
+            .and_then(Self::get_raw_block);
+
         // GET /testnet3/height/{blockHash}
         let get_block_height_by_hash = warp::get()
             .and(warp::path!("testnet3" / "height" / ..))
@@ -87,20 +1410,139 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(with(self.ledger.clone()))
             .and_then(Self::get_block_transactions);
 
-        // GET /testnet3/transaction/{transactionID}
+        // GET /testnet3/transaction/{transactionID}?encoding={json|cbor}
         let get_transaction = warp::get()
             .and(warp::path!("testnet3" / "transaction" / ..))
             .and(warp::path::param::<N::TransactionID>())
             .and(warp::path::end())
+            .and(warp::query::<EncodingQuery>())
             .and(with(self.ledger.clone()))
+            .and(with(self.cache.clone()))
             .and_then(Self::get_transaction);
 
+        // GET /testnet3/transaction/{transactionID}/raw
+        let get_raw_transaction = warp::get()
+            .and(warp::path!("testnet3" / "transaction" / ..))
+            .and(warp::path::param::<N::TransactionID>())
+            .and(warp::path!("raw"))
+            .and(warp::header::optional::<String>("accept"))
+            .and(with(self.ledger.clone()))
+            .and(with(self.cache.clone()))
+            .and_then(Self::get_raw_transaction);
+
+        // GET /testnet3/transaction/{transactionID}/depositProof
+        let get_deposit_proof = warp::get()
+            .and(warp::path!("testnet3" / "transaction" / ..))
+            .and(warp::path::param::<N::TransactionID>())
+            .and(warp::path!("depositProof"))
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_deposit_proof);
+
+        // GET /testnet3/transaction/metadata/{transactionID}
+        let get_transaction_metadata = warp::get()
+            .and(warp::path!("testnet3" / "transaction" / "metadata" / ..))
+            .and(warp::path::param::<N::TransactionID>())
+            .and(warp::path::end())
+            .and(with(self.ledger.clone()))
+            .and(with(self.sequences.clone()))
+            .and_then(Self::get_transaction_metadata);
+
+        // GET /testnet3/transaction/sequence/{sequence}
+        let get_transaction_by_sequence = warp::get()
+            .and(warp::path!("testnet3" / "transaction" / "sequence" / ..))
+            .and(warp::path::param::<u64>())
+            .and(warp::path::end())
+            .and(warp::query::<EncodingQuery>())
+            .and(with(self.ledger.clone()))
+            .and(with(self.sequences.clone()))
+            .and_then(Self::get_transaction_by_sequence);
+
+        // GET /testnet3/transaction/wait/{transactionID}?confirmations={confirmations}&timeout={timeout_in_ms}
+        let wait_for_transaction = warp::get()
+            .and(warp::path!("testnet3" / "transaction" / "wait" / ..))
+            .and(warp::path::param::<N::TransactionID>())
+            .and(warp::path::end())
+            .and(warp::query::<TransactionWait>())
+            .and(with(self.consensus.clone()))
+            .and(with(self.long_polls.clone()))
+            .and_then(Self::wait_for_transaction);
+
+        // GET /testnet3/block/template?longpollid={longpollID}&timeout={timeout_in_ms}
+        let get_block_template = warp::get()
+            .and(warp::path!("testnet3" / "block" / "template"))
+            .and(warp::query::<BlockTemplateQuery>())
+            .and(with(self.consensus.clone()))
+            .and(with(self.long_polls.clone()))
+            .and_then(Self::get_block_template);
+
+        // POST /testnet3/block/submit
+        let submit_block = warp::post()
+            .and(warp::path!("testnet3" / "block" / "submit"))
+            .and(warp::body::content_length_limit(16 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::submit_block);
+
+        // GET /testnet3/block/submission/{submissionID}?timeout={timeout_in_ms}
+        let get_block_submission = warp::get()
+            .and(warp::path!("testnet3" / "block" / "submission" / u64))
+            .and(warp::query::<SubmissionQuery>())
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and(with(self.long_polls.clone()))
+            .and_then(Self::get_block_submission);
+
+        // GET /testnet3/chain/info
+        let get_chain_info = warp::get()
+            .and(warp::path!("testnet3" / "chain" / "info"))
+            .and(with(self.consensus.clone()))
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_chain_info);
+
+        // GET /testnet3/miner/info
+        let get_miner_info = warp::get()
+            .and(warp::path!("testnet3" / "miner" / "info"))
+            .and(with(self.consensus.clone()))
+            .and_then(Self::get_miner_info);
+
         // GET /testnet3/memoryPool/transactions
         let get_memory_pool_transactions = warp::get()
             .and(warp::path!("testnet3" / "memoryPool" / "transactions"))
             .and(with(self.consensus.clone()))
             .and_then(Self::get_memory_pool_transactions);
 
+        // GET /testnet3/memoryPool/transaction/{transactionID}
+        let get_memory_pool_entry = warp::get()
+            .and(warp::path!("testnet3" / "memoryPool" / "transaction" / ..))
+            .and(warp::path::param::<N::TransactionID>())
+            .and(warp::path::end())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::get_memory_pool_entry);
+
+        // GET /testnet3/memoryPool/snapshot?offset={offset}&limit={limit}
+        let get_memory_pool_snapshot = warp::get()
+            .and(warp::path!("testnet3" / "memoryPool" / "snapshot"))
+            .and(warp::query::<MemoryPoolPage>())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::get_memory_pool_snapshot);
+
+        // GET /testnet3/memoryPool/local
+        let get_local_transactions = warp::get()
+            .and(warp::path!("testnet3" / "memoryPool" / "local"))
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::get_local_transactions);
+
+        // POST /testnet3/memoryPool/abandon/{transactionID}
+        let abandon_transaction = warp::post()
+            .and(warp::path!("testnet3" / "memoryPool" / "abandon" / ..))
+            .and(warp::path::param::<N::TransactionID>())
+            .and(warp::path::end())
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::abandon_transaction);
+
         // GET /testnet3/program/{programID}
         let get_program = warp::get()
             .and(warp::path!("testnet3" / "program" / ..))
@@ -115,8 +1557,71 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(warp::path::param::<Field<N>>())
             .and(warp::path::end())
             .and(with(self.ledger.clone()))
+            .and(with(self.cache.clone()))
             .and_then(Self::get_state_path_for_commitment);
 
+        // GET /testnet3/serialNumber/{serialNumber}/status?height={height}
+        let get_serial_number_status = warp::get()
+            .and(warp::path!("testnet3" / "serialNumber" / ..))
+            .and(warp::path::param::<Field<N>>())
+            .and(warp::path!("status"))
+            .and(warp::query::<RecordStatusQuery>())
+            .and(with(self.ledger.clone()))
+            .and(with(self.history.clone()))
+            .and_then(Self::get_serial_number_status);
+
+        // GET /testnet3/commitment/{commitment}/status?height={height}
+        let get_commitment_status = warp::get()
+            .and(warp::path!("testnet3" / "commitment" / ..))
+            .and(warp::path::param::<Field<N>>())
+            .and(warp::path!("status"))
+            .and(warp::query::<RecordStatusQuery>())
+            .and(with(self.ledger.clone()))
+            .and(with(self.history.clone()))
+            .and_then(Self::get_commitment_status);
+
+        // GET /testnet3/membershipProof/{commitment}?digest={digest}
+        let get_ledger_membership_proof = warp::get()
+            .and(warp::path!("testnet3" / "membershipProof" / ..))
+            .and(warp::path::param::<Field<N>>())
+            .and(warp::path::end())
+            .and(warp::query::<LedgerDigestQuery<N>>())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_ledger_membership_proof);
+
+        // GET /testnet3/ledgerDigest?height={height}
+        let get_ledger_digest = warp::get()
+            .and(warp::path!("testnet3" / "ledgerDigest"))
+            .and(warp::query::<LedgerDigestHeightQuery>())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_ledger_digest);
+
+        // GET /testnet3/ledgerDigest/history?count={count}
+        let get_ledger_digest_history = warp::get()
+            .and(warp::path!("testnet3" / "ledgerDigest" / "history"))
+            .and(warp::query::<LedgerDigestHistoryQuery>())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_ledger_digest_history);
+
+        // GET /testnet3/chain/commitment
+        let get_chain_commitment = warp::get()
+            .and(warp::path!("testnet3" / "chain" / "commitment"))
+            .and(with(self.mmr.clone()))
+            .and_then(Self::get_chain_commitment);
+
+        // GET /testnet3/chain/proof/{height}
+        let get_chain_proof = warp::get()
+            .and(warp::path!("testnet3" / "chain" / "proof" / u32))
+            .and(with(self.ledger.clone()))
+            .and(with(self.mmr.clone()))
+            .and_then(Self::get_chain_proof);
+
+        // GET /testnet3/chainTips
+        let get_chain_tips = warp::get()
+            .and(warp::path!("testnet3" / "chainTips"))
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_chain_tips);
+
         // GET /testnet3/beacons
         let get_beacons = warp::get()
             .and(warp::path!("testnet3" / "beacons"))
@@ -141,12 +1646,223 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(with(self.routing.router().clone()))
             .and_then(Self::get_peers_all_metrics);
 
+        // GET /testnet3/peers/info
+        let get_peers_info = warp::get()
+            .and(warp::path!("testnet3" / "peers" / "info"))
+            .and(with(self.routing.router().clone()))
+            .and_then(Self::get_peers_info);
+
+        // GET /testnet3/peers/history
+        let get_peers_history = warp::get()
+            .and(warp::path!("testnet3" / "peers" / "history"))
+            .and(with(self.routing.router().clone()))
+            .and_then(Self::get_peers_history);
+
+        // GET /testnet3/peers/relay
+        let get_peers_relay = warp::get()
+            .and(warp::path!("testnet3" / "peers" / "relay"))
+            .and(with(self.routing.router().clone()))
+            .and_then(Self::get_peers_relay);
+
+        // GET /testnet3/peers/policy
+        let export_peer_policy = warp::get()
+            .and(warp::path!("testnet3" / "peers" / "policy"))
+            .and(with_auth())
+            .and(with(self.routing.router().clone()))
+            .and_then(Self::export_peer_policy);
+
+        // POST /testnet3/peers/policy?replace={bool}
+        let import_peer_policy = warp::post()
+            .and(warp::path!("testnet3" / "peers" / "policy"))
+            .and(with_auth())
+            .and(warp::query::<PeerPolicyImport>())
+            .and(warp::body::content_length_limit(16 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(with(self.routing.router().clone()))
+            .and_then(Self::import_peer_policy);
+
         // GET /testnet3/node/address
         let get_node_address = warp::get()
             .and(warp::path!("testnet3" / "node" / "address"))
             .and(with(self.routing.router().address()))
             .and_then(|address: Address<N>| async move { Ok::<_, Rejection>(reply::json(&address.to_string())) });
 
+        // GET /testnet3/node/publicKey
+        let get_node_public_key = warp::get()
+            .and(warp::path!("testnet3" / "node" / "publicKey"))
+            .and(with(hex::encode(self.routing.router().noise_public_key())))
+            .and_then(|public_key: String| async move { Ok::<_, Rejection>(reply::json(&public_key)) });
+
+        // GET /testnet3/node/state
+        let get_node_state = warp::get()
+            .and(warp::path!("testnet3" / "node" / "state"))
+            .and(with(self.routing.router().clone()))
+            .and_then(Self::get_node_state);
+
+        // GET /testnet3/node/storage
+        let get_storage_statistics = warp::get()
+            .and(warp::path!("testnet3" / "node" / "storage"))
+            .and(with_auth())
+            .and_then(Self::get_storage_statistics);
+
+        // GET /testnet3/node/storage/dump/{column}?start={key}&end={key}&limit={limit}
+        let dump_storage_column = warp::get()
+            .and(warp::path!("testnet3" / "node" / "storage" / "dump" / String))
+            .and(warp::query::<StorageDumpQuery>())
+            .and(with_auth())
+            .and_then(Self::dump_storage_column);
+
+        // POST /testnet3/node/storage/compact/{column}
+        let compact_column = warp::post()
+            .and(warp::path!("testnet3" / "node" / "storage" / "compact" / String))
+            .and(with_auth())
+            .and_then(Self::compact_column);
+
+        // POST /testnet3/node/storage/bulkSync/{enabled}
+        let set_bulk_sync = warp::post()
+            .and(warp::path!("testnet3" / "node" / "storage" / "bulkSync" / bool))
+            .and(with_auth())
+            .and_then(Self::set_bulk_sync);
+
+        // GET /testnet3/node/storage/shadow
+        let get_shadow_report = warp::get()
+            .and(warp::path!("testnet3" / "node" / "storage" / "shadow"))
+            .and(with_auth())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_shadow_report);
+
+        // POST /testnet3/node/storage/shadow/{enabled}
+        let set_shadow = warp::post()
+            .and(warp::path!("testnet3" / "node" / "storage" / "shadow" / bool))
+            .and(with_auth())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::set_shadow);
+
+        // GET /testnet3/node/invalidBlocks
+        let get_invalid_blocks = warp::get()
+            .and(warp::path!("testnet3" / "node" / "invalidBlocks"))
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::get_invalid_blocks);
+
+        // POST /testnet3/node/invalidBlocks/purge?hash={blockHash}
+        let purge_invalid_blocks = warp::post()
+            .and(warp::path!("testnet3" / "node" / "invalidBlocks" / "purge"))
+            .and(warp::query::<InvalidBlockPurgeQuery<N>>())
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::purge_invalid_blocks);
+
+        // POST /testnet3/node/invalidateBlock/{blockHash}
+        let invalidate_block = warp::post()
+            .and(warp::path!("testnet3" / "node" / "invalidateBlock" / ..))
+            .and(warp::path::param::<N::BlockHash>())
+            .and(warp::path::end())
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::invalidate_block);
+
+        // GET /testnet3/node/audit
+        let get_audit_log = warp::get()
+            .and(warp::path!("testnet3" / "node" / "audit"))
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::get_audit_log);
+
+        // POST /testnet3/node/audit/acknowledge
+        let acknowledge_audit_failures = warp::post()
+            .and(warp::path!("testnet3" / "node" / "audit" / "acknowledge"))
+            .and(with_auth())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::acknowledge_audit_failures);
+
+        // POST /testnet3/node/metrics/export
+        let export_metrics = warp::post()
+            .and(warp::path!("testnet3" / "node" / "metrics" / "export"))
+            .and(with_auth())
+            .and(warp::body::content_length_limit(16 * 1024))
+            .and(warp::body::json())
+            .and(with(self.ledger.clone()))
+            .and(with(self.difficulty.clone()))
+            .and(with(self.journal.clone()))
+            .and_then(Self::export_metrics);
+
+        // POST /testnet3/node/reload
+        let reload =
+            warp::post().and(warp::path!("testnet3" / "node" / "reload")).and(with_auth()).and_then(Self::reload);
+
+        // GET /testnet3/node/cache
+        let get_cache_statistics =
+            warp::get().and(warp::path!("testnet3" / "node" / "cache")).and(with(self.cache.clone())).and_then(
+                |cache: Arc<ResponseCache<N>>| async move { Ok::<_, Rejection>(reply::json(&cache.statistics())) },
+            );
+
+        // GET /testnet3/node/requests
+        let get_request_statistics = warp::get()
+            .and(warp::path!("testnet3" / "node" / "requests"))
+            .and(with(self.telemetry.clone()))
+            .and_then(Self::get_request_statistics);
+
+        // POST /testnet3/accounts/register
+        let register_account = warp::post()
+            .and(warp::path!("testnet3" / "accounts" / "register"))
+            .and(with_auth())
+            .and(warp::body::content_length_limit(1024))
+            .and(warp::body::json())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::register_account);
+
+        // GET /testnet3/accounts/{address}/history?offset={offset}&limit={limit}
+        let get_account_history = warp::get()
+            .and(warp::path!("testnet3" / "accounts" / ..))
+            .and(warp::path::param::<Address<N>>())
+            .and(warp::path!("history"))
+            .and(with_auth())
+            .and(warp::query::<AccountHistoryQuery>())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::get_account_history);
+
+        // GET /testnet3/chain/events?since={sequence}&limit={limit}
+        let get_chain_events = warp::get()
+            .and(warp::path!("testnet3" / "chain" / "events"))
+            .and(warp::query::<ChainEventRange>())
+            .and(with(self.journal.clone()))
+            .and_then(Self::get_chain_events);
+
+        // GET /testnet3/chain/subscribe/blocks?start_height={height}&include_transactions={bool}&resume_token={token}
+        let subscribe_blocks = warp::get()
+            .and(warp::path!("testnet3" / "chain" / "subscribe" / "blocks"))
+            .and(warp::query::<BlockSubscriptionQuery>())
+            .and(warp::ws())
+            .and(with(self.ledger.clone()))
+            .and(with(self.journal.clone()))
+            .and_then(Self::subscribe_blocks);
+
+        // GET /testnet3/memoryPool/subscribe/transactions
+        let subscribe_transactions = warp::get()
+            .and(warp::path!("testnet3" / "memoryPool" / "subscribe" / "transactions"))
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::ws())
+            .and(with(self.ledger.clone()))
+            .and_then(Self::subscribe_transactions);
+
+        // GET /testnet3/circuits/stats
+        let get_circuit_stats = warp::get()
+            .and(warp::path!("testnet3" / "circuits" / "stats"))
+            .and(with(self.consensus.clone()))
+            .and(with(self.circuits.clone()))
+            .and_then(Self::get_circuit_stats);
+
+        // GET /testnet3/circuit/{programID}/{functionName}/transactions?start={index}&limit={limit}
+        let get_circuit_transactions = warp::get()
+            .and(warp::path!("testnet3" / "circuit" / ..))
+            .and(warp::path::param::<ProgramID<N>>())
+            .and(warp::path::param::<Identifier<N>>())
+            .and(warp::path!("transactions"))
+            .and(warp::query::<CircuitTransactionRange>())
+            .and(with(self.circuits.clone()))
+            .and_then(Self::get_circuit_transactions);
+
         // GET /testnet3/find/blockHash/{transactionID}
         let find_block_hash = warp::get()
             .and(warp::path!("testnet3" / "find" / "blockHash" / ..))
@@ -188,30 +1904,107 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
             .and(with(self.routing.clone()))
             .and_then(Self::transaction_broadcast);
 
+        // POST /testnet3/transactions/broadcast?mode={atomic|best-effort}
+        let transactions_broadcast = warp::post()
+            .and(warp::path!("testnet3" / "transactions" / "broadcast"))
+            .and(warp::query::<BatchQuery>())
+            .and(warp::body::content_length_limit(64 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(with(self.consensus.clone()))
+            .and(with(self.routing.clone()))
+            .and_then(Self::transactions_broadcast);
+
+        // POST /testnet3/transaction/simulate?verify_proof={bool}
+        let simulate_acceptance = warp::post()
+            .and(warp::path!("testnet3" / "transaction" / "simulate"))
+            .and(warp::query::<SimulationQuery>())
+            .and(warp::body::content_length_limit(16 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(with(self.consensus.clone()))
+            .and_then(Self::simulate_acceptance);
+
         // Return the list of routes.
         latest_height
             .or(latest_hash)
             .or(latest_block)
             .or(latest_state_root)
+            .or(get_block_template)
+            .or(submit_block)
+            .or(get_block_submission)
+            .or(get_chain_info)
+            .or(get_miner_info)
             .or(get_block)
             .or(get_blocks)
+            .or(get_block_hashes)
+            .or(get_block_by_time)
+            .or(get_difficulty_history)
+            .or(get_network_hashrate)
+            .or(get_serial_number_status)
+            .or(get_commitment_status)
             .or(get_block_by_hash)
+            .or(get_raw_block)
             .or(get_block_height_by_hash)
             .or(get_block_transactions)
             .or(get_transaction)
+            .or(get_raw_transaction)
+            .or(get_deposit_proof)
+            .or(get_transaction_metadata)
+            .or(get_transaction_by_sequence)
+            .or(wait_for_transaction)
             .or(get_memory_pool_transactions)
+            .or(get_memory_pool_entry)
+            .or(get_memory_pool_snapshot)
+            .or(get_local_transactions)
+            .or(abandon_transaction)
             .or(get_program)
             .or(get_state_path_for_commitment)
+            .or(get_ledger_membership_proof)
+            .or(get_ledger_digest)
+            .or(get_ledger_digest_history)
+            .or(get_chain_commitment)
+            .or(get_chain_proof)
+            .or(get_chain_tips)
             .or(get_beacons)
             .or(get_peers_count)
             .or(get_peers_all)
             .or(get_peers_all_metrics)
+            .or(get_peers_info)
+            .or(get_peers_history)
+            .or(get_peers_relay)
+            .or(export_peer_policy)
+            .or(import_peer_policy)
             .or(get_node_address)
+            .or(get_node_public_key)
+            .or(get_node_state)
+            .or(get_storage_statistics)
+            .or(dump_storage_column)
+            .or(compact_column)
+            .or(set_bulk_sync)
+            .or(get_shadow_report)
+            .or(set_shadow)
+            .or(get_invalid_blocks)
+            .or(purge_invalid_blocks)
+            .or(invalidate_block)
+            .or(get_audit_log)
+            .or(acknowledge_audit_failures)
+            .or(export_metrics)
+            .or(reload)
+            .or(get_cache_statistics)
+            .or(get_request_statistics)
+            .or(register_account)
+            .or(get_account_history)
+            .or(get_chain_events)
+            .or(subscribe_blocks)
+            .or(subscribe_transactions)
+            .or(get_circuit_stats)
+            .or(get_circuit_transactions)
             .or(find_block_hash)
             .or(find_transaction_id_from_program_id)
             .or(find_transaction_id_from_transition_id)
             .or(find_transition_id)
             .or(transaction_broadcast)
+            .or(transactions_broadcast)
+            .or(simulate_acceptance)
     }
 }
 
@@ -221,70 +2014,427 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         Ok(reply::json(&ledger.latest_height()))
     }
 
-    /// Returns the latest block hash.
-    async fn latest_hash(ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.latest_hash()))
+    /// Returns the latest block hash.
+    async fn latest_hash(ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&ledger.latest_hash()))
+    }
+
+    /// Returns the latest block.
+    async fn latest_block(query: EncodingQuery, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        encode(&ledger.latest_block(), query.encoding)
+    }
+
+    /// Returns the latest state root.
+    async fn latest_state_root(ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&ledger.latest_state_root()))
+    }
+
+    /// Returns the block for the given block height.
+    async fn get_block(
+        height: u32,
+        query: EncodingQuery,
+        ledger: Ledger<N, C>,
+        cache: Arc<ResponseCache<N>>,
+    ) -> Result<impl Reply, Rejection> {
+        let anchor = (height, ledger.get_hash(height).or_reject()?);
+        let params = format!("{height}:{:?}", query.encoding);
+        cached(&cache, &ledger, "get_block", params, Some(anchor), || {
+            serialize(&ledger.get_block(height).or_reject()?, query.encoding)
+        })
+    }
+
+    /// Returns the blocks for the given block range.
+    async fn get_blocks(block_range: BlockRange, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        let start_height = block_range.start;
+        let end_height = block_range.end;
+
+        const MAX_BLOCK_RANGE: u32 = 50;
+
+        // Ensure the end height is greater than the start height.
+        if start_height > end_height {
+            return Err(reject::custom(RestError::Request("Invalid block range".to_string())));
+        }
+        // Ensure the block range is bounded.
+        else if end_height - start_height > MAX_BLOCK_RANGE {
+            return Err(reject::custom(RestError::Request(format!(
+                "Cannot request more than {MAX_BLOCK_RANGE} blocks per call (requested {})",
+                end_height - start_height
+            ))));
+        }
+
+        let blocks = cfg_into_iter!((start_height..end_height))
+            .map(|height| ledger.get_block(height).or_reject())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(reply::json(&blocks))
+    }
+
+    /// Returns the latest block height and the block hashes (or block headers, if verbose) for the given block range.
+    async fn get_block_hashes(range: BlockHashRange, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        // Ensure the end height is greater than the start height.
+        if range.start > range.end {
+            return Err(reject::custom(RestError::Request("Invalid block range".to_string())));
+        }
+
+        // Clamp the limit to the maximum number of blocks per call.
+        let limit = range.limit.unwrap_or(MAX_HEIGHTS_PER_SCAN).min(MAX_HEIGHTS_PER_SCAN);
+        let heights = range.start..range.end;
+
+        match range.verbose {
+            true => {
+                let (latest_height, blocks) = ledger.get_headers(heights, range.reverse, limit).or_reject()?;
+                Ok(reply::json(&BlockHashes { latest_height, blocks }))
+            }
+            false => {
+                let (latest_height, blocks) = ledger.get_hashes(heights, range.reverse, limit).or_reject()?;
+                Ok(reply::json(&BlockHashes { latest_height, blocks }))
+            }
+        }
+    }
+
+    /// Returns the block for the given block hash.
+    async fn get_block_by_hash(
+        hash: N::BlockHash,
+        query: EncodingQuery,
+        ledger: Ledger<N, C>,
+        cache: Arc<ResponseCache<N>>,
+    ) -> Result<impl Reply, Rejection> {
+        let anchor = (ledger.get_height(&hash).or_reject()?, hash);
+        let params = format!("{hash}:{:?}", query.encoding);
+        cached(&cache, &ledger, "get_block_by_hash", params, Some(anchor), || {
+            serialize(&ledger.get_block_by_hash(&hash).or_reject()?, query.encoding)
+        })
+    }
+
+    /// Returns the canonical binary encoding of the block for the given block hash,
+    /// as raw bytes if requested by the `Accept` header, and as hex otherwise.
+    async fn get_raw_block(
+        hash: N::BlockHash,
+        accept: Option<String>,
+        ledger: Ledger<N, C>,
+        cache: Arc<ResponseCache<N>>,
+    ) -> Result<impl Reply, Rejection> {
+        let anchor = (ledger.get_height(&hash).or_reject()?, hash);
+        let params = format!("{hash}:{}", accepts_raw(accept.as_deref()));
+        cached(&cache, &ledger, "get_raw_block", params, Some(anchor), || {
+            let bytes = ledger.get_block_by_hash(&hash).or_reject()?.to_bytes_le().or_reject()?;
+            raw_response(bytes, accept.as_deref()).or_reject()
+        })
+    }
+
+    /// Returns the block height for the given block hash.
+    async fn get_block_height_by_hash(hash: N::BlockHash, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&ledger.get_height(&hash).or_reject()?))
+    }
+
+    /// Returns the height, hash, and timestamp of the block selected for the given UNIX timestamp, if there is one.
+    async fn get_block_by_time(
+        timestamp: i64,
+        query: BlockTimeQuery,
+        ledger: Ledger<N, C>,
+        timestamps: TimestampIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let response = match timestamps.get_height_by_time(timestamp, query.mode).or_reject()? {
+            Some(height) => Some(BlockTimeResponse {
+                height,
+                hash: ledger.get_hash(height).or_reject()?.to_string(),
+                timestamp: ledger.get_header(height).or_reject()?.timestamp(),
+            }),
+            None => None,
+        };
+        Ok(reply::json(&response))
+    }
+
+    /// Returns the difficulty and estimated hashrate of the blocks in the given range, every `stride` blocks.
+    async fn get_difficulty_history(
+        query: DifficultyHistoryQuery,
+        difficulty: DifficultyIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let entries =
+            difficulty.get_history(query.from, query.to.unwrap_or(u32::MAX), query.stride.unwrap_or(1)).or_reject()?;
+        Ok(reply::json(&entries.into_iter().map(DifficultyResponse::from).collect::<Vec<_>>()))
+    }
+
+    /// Returns the estimated hashrate of the network over the given number of latest blocks.
+    async fn get_network_hashrate(
+        query: NetworkHashrateQuery,
+        difficulty: DifficultyIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&difficulty.get_network_hashrate(query.window.unwrap_or(HASHRATE_WINDOW)).or_reject()?))
+    }
+
+    /// Returns the commitment to the canonical chain, which is the root of the Merkle mountain range over its blocks.
+    async fn get_chain_commitment(mmr: ChainMmr<N>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&mmr.commitment().or_reject()?))
+    }
+
+    /// Returns a proof that the canonical block at the given height is under the current chain commitment.
+    async fn get_chain_proof(height: u32, ledger: Ledger<N, C>, mmr: ChainMmr<N>) -> Result<impl Reply, Rejection> {
+        let block_hash = ledger.get_hash(height).or_reject()?;
+        Ok(reply::json(&mmr.generate_chain_proof(height, block_hash).or_reject()?))
+    }
+
+    /// Returns whether the given serial number was spent as of the given block height, in the canonical chain.
+    async fn get_serial_number_status(
+        serial_number: Field<N>,
+        query: RecordStatusQuery,
+        ledger: Ledger<N, C>,
+        history: HistoryIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let height = query.height.unwrap_or_else(|| ledger.latest_height());
+        let response = match history.is_serial_spent_at(&serial_number, height).or_reject()? {
+            SerialNumberStatus::Unspent => RecordStatusResponse::new::<N>("unspent", None),
+            SerialNumberStatus::Spent { height, transaction_id } => {
+                RecordStatusResponse::new::<N>("spent", Some((height, transaction_id)))
+            }
+            SerialNumberStatus::Unknown => RecordStatusResponse::new::<N>("unknown", None),
+        };
+        Ok(reply::json(&response))
+    }
+
+    /// Returns whether the given commitment was created as of the given block height, in the canonical chain.
+    async fn get_commitment_status(
+        commitment: Field<N>,
+        query: RecordStatusQuery,
+        ledger: Ledger<N, C>,
+        history: HistoryIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let height = query.height.unwrap_or_else(|| ledger.latest_height());
+        let response = match history.is_commitment_created_at(&commitment, height).or_reject()? {
+            CommitmentStatus::Absent => RecordStatusResponse::new::<N>("absent", None),
+            CommitmentStatus::Created { height, transaction_id } => {
+                RecordStatusResponse::new::<N>("created", Some((height, transaction_id)))
+            }
+            CommitmentStatus::Unknown => RecordStatusResponse::new::<N>("unknown", None),
+        };
+        Ok(reply::json(&response))
+    }
+
+    /// Returns the transactions for the given block height.
+    async fn get_block_transactions(height: u32, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&ledger.get_transactions(height).or_reject()?))
+    }
+
+    /// Returns the transaction for the given transaction ID.
+    async fn get_transaction(
+        transaction_id: N::TransactionID,
+        query: EncodingQuery,
+        ledger: Ledger<N, C>,
+        cache: Arc<ResponseCache<N>>,
+    ) -> Result<impl Reply, Rejection> {
+        // Retrieve the block containing the transaction, if it is confirmed.
+        let anchor = match ledger.find_block_hash(&transaction_id).or_reject()? {
+            Some(block_hash) => Some((ledger.get_height(&block_hash).or_reject()?, block_hash)),
+            None => None,
+        };
+        let params = format!("{transaction_id}:{:?}", query.encoding);
+        cached(&cache, &ledger, "get_transaction", params, anchor, || {
+            serialize(&ledger.get_transaction(transaction_id).or_reject()?, query.encoding)
+        })
+    }
+
+    /// Returns the canonical binary encoding of the transaction for the given transaction ID,
+    /// as raw bytes if requested by the `Accept` header, and as hex otherwise.
+    async fn get_raw_transaction(
+        transaction_id: N::TransactionID,
+        accept: Option<String>,
+        ledger: Ledger<N, C>,
+        cache: Arc<ResponseCache<N>>,
+    ) -> Result<impl Reply, Rejection> {
+        // Retrieve the block containing the transaction, if it is confirmed.
+        let anchor = match ledger.find_block_hash(&transaction_id).or_reject()? {
+            Some(block_hash) => Some((ledger.get_height(&block_hash).or_reject()?, block_hash)),
+            None => None,
+        };
+        let params = format!("{transaction_id}:{}", accepts_raw(accept.as_deref()));
+        cached(&cache, &ledger, "get_raw_transaction", params, anchor, || {
+            let bytes = ledger.get_transaction(transaction_id).or_reject()?.to_bytes_le().or_reject()?;
+            raw_response(bytes, accept.as_deref()).or_reject()
+        })
+    }
+
+    /// Returns a proof that the transaction is included in the canonical chain below the latest block,
+    /// which an exchange may verify offline against a trusted header of the tip.
+    async fn get_deposit_proof(
+        transaction_id: N::TransactionID,
+        ledger: Ledger<N, C>,
+    ) -> Result<impl Reply, Rejection> {
+        // Compute the proof in a blocking task, as the ledger Merkle tree may be reconstructed.
+        match tokio::task::spawn_blocking(move || ledger.get_deposit_proof(&transaction_id)).await {
+            Ok(proof) => Ok(reply::json(&DepositProofResponse::try_from(proof.or_reject()?).or_reject()?)),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to compute the deposit proof: {error}"))))
+            }
+        }
     }
 
-    /// Returns the latest block.
-    async fn latest_block(ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.latest_block()))
+    /// Returns the metadata of the transaction for the given transaction ID, which remains available once its block is pruned.
+    async fn get_transaction_metadata(
+        transaction_id: N::TransactionID,
+        ledger: Ledger<N, C>,
+        sequences: SequenceIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let metadata = ledger.get_transaction_metadata(transaction_id).or_reject()?;
+        let sequence = match sequences.get_sequence(&transaction_id).or_reject()? {
+            Some(sequence) => sequence,
+            None => {
+                return Err(reject::custom(RestError::Request(format!(
+                    "Missing the sequence number of transaction '{transaction_id}'"
+                ))));
+            }
+        };
+        Ok(reply::json(&TransactionMetadataResponse::new(metadata, sequence)))
     }
 
-    /// Returns the latest state root.
-    async fn latest_state_root(ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.latest_state_root()))
+    /// Returns the canonical transaction with the given global sequence number.
+    async fn get_transaction_by_sequence(
+        sequence: u64,
+        query: EncodingQuery,
+        ledger: Ledger<N, C>,
+        sequences: SequenceIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let transaction_id = match sequences.get_transaction_id(sequence).or_reject()? {
+            Some(transaction_id) => transaction_id,
+            None => {
+                return Err(reject::custom(RestError::Request(format!(
+                    "Missing the transaction with sequence number {sequence}"
+                ))));
+            }
+        };
+        serialize(&ledger.get_transaction(transaction_id).or_reject()?, query.encoding)
     }
 
-    /// Returns the block for the given block height.
-    async fn get_block(height: u32, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.get_block(height).or_reject()?))
+    /// Waits for the given transaction to reach the requested number of confirmations,
+    /// and returns its status once it does, a conflicting transaction is confirmed, or the timeout elapses.
+    async fn wait_for_transaction(
+        transaction_id: N::TransactionID,
+        query: TransactionWait,
+        consensus: Option<Consensus<N, C>>,
+        long_polls: Arc<Semaphore>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let _permit = acquire_long_poll(long_polls)?;
+        // Register the waiter before waiting, so that no block is missed.
+        let waiter = consensus.register_transaction_waiter(transaction_id, query.confirmations).or_reject()?;
+        let timeout = Duration::from_millis(query.timeout.min(MAX_WAIT_TIMEOUT_IN_MS));
+        let status = waiter.wait(timeout).await.or_reject()?;
+        Ok(reply::json(&TransactionWaitStatus::from(status)))
     }
 
-    /// Returns the blocks for the given block range.
-    async fn get_blocks(block_range: BlockRange, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        let start_height = block_range.start;
-        let end_height = block_range.end;
-
-        const MAX_BLOCK_RANGE: u32 = 50;
+    /// Returns the template for the next block. If a long-poll ID is given, this waits until
+    /// the corresponding template becomes stale (or the timeout elapses) before returning.
+    async fn get_block_template(
+        query: BlockTemplateQuery,
+        consensus: Option<Consensus<N, C>>,
+        long_polls: Arc<Semaphore>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let longpoll_id = query.longpollid.map(|id| id.parse::<LongPollId<N>>()).transpose().or_reject()?;
+        let timeout = Duration::from_millis(query.timeout.min(MAX_WAIT_TIMEOUT_IN_MS));
 
-        // Ensure the end height is greater than the start height.
-        if start_height > end_height {
-            return Err(reject::custom(RestError::Request("Invalid block range".to_string())));
+        // Wait for the template with the given long-poll ID to become stale, if one is given.
+        if let Some(longpoll_id) = longpoll_id {
+            let _permit = acquire_long_poll(long_polls)?;
+            consensus.wait_for_stale_template(&longpoll_id, timeout).await;
         }
-        // Ensure the block range is bounded.
-        else if end_height - start_height > MAX_BLOCK_RANGE {
-            return Err(reject::custom(RestError::Request(format!(
-                "Cannot request more than {MAX_BLOCK_RANGE} blocks per call (requested {})",
-                end_height - start_height
-            ))));
+        // Build the template in a blocking task, as it verifies the selected transactions.
+        match tokio::task::spawn_blocking(move || consensus.block_template()).await {
+            Ok(template) => Ok(reply::json(&BlockTemplateResponse::from(&*template.or_reject()?))),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to build the block template: {error}"))))
+            }
         }
-
-        let blocks = cfg_into_iter!((start_height..end_height))
-            .map(|height| ledger.get_block(height).or_reject())
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(reply::json(&blocks))
     }
 
-    /// Returns the block for the given block hash.
-    async fn get_block_by_hash(hash: N::BlockHash, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.get_block_by_hash(&hash).or_reject()?))
+    /// Submits the given block, which is pre-checked, and returns the outcome of the submission if the block
+    /// duplicates a submitted block, or failed the pre-checks. Otherwise, the block is validated and committed
+    /// in the background, and the ID of the submission is returned, to poll (or await) its outcome.
+    async fn submit_block(
+        block: Block<N>,
+        _auth: (),
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Pre-check the block in a blocking task, as it reads the ledger.
+        let block_hash = block.hash();
+        match tokio::task::spawn_blocking(move || consensus.submit_block_async(block, SubmissionSource::Rest)).await {
+            Ok(receipt) => {
+                let response = match receipt.or_reject()? {
+                    SubmissionReceipt::Pending(id) => {
+                        SubmitBlockResponse::from_status(id, SubmissionStatus::<N>::Pending { block_hash })
+                    }
+                    SubmissionReceipt::Completed(outcome) => SubmitBlockResponse::new(block_hash, None, outcome),
+                    SubmissionReceipt::Busy { num_pending, capacity } => {
+                        return Err(reject::custom(RestError::Unavailable(format!(
+                            "Block '{block_hash}' was not queued, as {num_pending} submissions are pending \
+                             (capacity is {capacity}) - retry once they complete"
+                        ))));
+                    }
+                };
+                Ok(reply::json(&response))
+            }
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to submit the block: {error}")))),
+        }
     }
 
-    /// Returns the block height for the given block hash.
-    async fn get_block_height_by_hash(hash: N::BlockHash, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.get_height(&hash).or_reject()?))
+    /// Returns the status of the block submission with the given ID, once it completes or the timeout elapses.
+    async fn get_block_submission(
+        submission_id: u64,
+        query: SubmissionQuery,
+        _auth: (),
+        consensus: Option<Consensus<N, C>>,
+        long_polls: Arc<Semaphore>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let submissions = consensus.async_submissions();
+        // Wait for the submission to complete, unless it is polled.
+        let status = match query.timeout {
+            0 => submissions.get(submission_id),
+            timeout => {
+                let _permit = acquire_long_poll(long_polls)?;
+                submissions.wait(submission_id, Duration::from_millis(timeout.min(MAX_WAIT_TIMEOUT_IN_MS))).await
+            }
+        };
+        match status {
+            Some(status) => Ok(reply::json(&SubmitBlockResponse::from_status(submission_id, status))),
+            None => Err(reject::custom(RestError::Request(format!("Unknown block submission {submission_id}")))),
+        }
     }
 
-    /// Returns the transactions for the given block height.
-    async fn get_block_transactions(height: u32, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.get_transactions(height).or_reject()?))
+    /// Returns the latest block, and the deployment of each consensus rule.
+    async fn get_chain_info(consensus: Option<Consensus<N, C>>, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Retrieve the latest block, and the deployment of the rules as of the next block.
+        let height = ledger.latest_height();
+        let rules = consensus.rule_activations().deployments(height.saturating_add(1));
+        Ok(reply::json(&ChainInfoResponse {
+            height,
+            hash: ledger.latest_hash().to_string(),
+            rules: rules.into_iter().map(RuleDeploymentResponse::from).collect(),
+        }))
     }
 
-    /// Returns the transaction for the given transaction ID.
-    async fn get_transaction(transaction_id: N::TransactionID, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.get_transaction(transaction_id).or_reject()?))
+    /// Returns the recipients of the coinbase, and the number of blocks mined by this node for each recipient.
+    async fn get_miner_info(consensus: Option<Consensus<N, C>>) -> Result<impl Reply, Rejection> {
+        match consensus {
+            Some(consensus) => Ok(reply::json(&MinerInfoResponse::from(consensus.miner_info()))),
+            None => Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        }
     }
 
     /// Returns the transactions in the memory pool.
@@ -295,6 +2445,208 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         }
     }
 
+    /// Returns the annotated memory pool entry of the given transaction.
+    async fn get_memory_pool_entry(
+        transaction_id: N::TransactionID,
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Annotate the entry in a blocking task, as the block template may be rebuilt.
+        let memory_pool = consensus.memory_pool().clone();
+        let entry = tokio::task::spawn_blocking(move || consensus.memory_pool_entry(&transaction_id)).await;
+        match entry {
+            Ok(entry) => match entry.or_reject()? {
+                Some(entry) => Ok(reply::json(&MemoryPoolEntryResponse::from(entry))),
+                None => match (
+                    memory_pool.is_expired_transaction(&transaction_id),
+                    memory_pool.is_abandoned_transaction(&transaction_id),
+                ) {
+                    (true, _) => Err(reject::custom(RestError::Request(format!(
+                        "Transaction '{transaction_id}' expired from the memory pool"
+                    )))),
+                    (false, true) => {
+                        Err(reject::custom(RestError::Request(format!("Transaction '{transaction_id}' was abandoned"))))
+                    }
+                    (false, false) => Err(reject::custom(RestError::Request(format!(
+                        "Transaction '{transaction_id}' is not in the memory pool"
+                    )))),
+                },
+            },
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to annotate the transaction: {error}"))))
+            }
+        }
+    }
+
+    /// Returns the locally submitted transactions that are pending, followed by the recently abandoned ones.
+    async fn get_local_transactions(_auth: (), consensus: Option<Consensus<N, C>>) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let local_transactions = consensus
+            .memory_pool()
+            .local_transactions()
+            .into_iter()
+            .map(|(transaction_id, status)| LocalTransactionResponse {
+                transaction_id: transaction_id.to_string(),
+                status: status.to_string(),
+            })
+            .collect::<Vec<_>>();
+        Ok(reply::json(&local_transactions))
+    }
+
+    /// Abandons the given locally submitted transaction, along with the transactions that depend on it,
+    /// and returns the IDs of the abandoned transactions.
+    async fn abandon_transaction(
+        transaction_id: N::TransactionID,
+        _auth: (),
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Abandon the transaction in a blocking task, as it looks up the ledger.
+        match tokio::task::spawn_blocking(move || consensus.abandon_transaction(&transaction_id)).await {
+            Ok(abandoned) => Ok(reply::json(&abandoned.or_reject()?)),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to abandon the transaction: {error}"))))
+            }
+        }
+    }
+
+    /// Returns the blocks that are known to be invalid, in the order they were first seen.
+    async fn get_invalid_blocks(_auth: (), consensus: Option<Consensus<N, C>>) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let invalid_blocks = consensus
+            .invalid_blocks()
+            .blocks()
+            .into_iter()
+            .map(|(block_hash, block)| InvalidBlockResponse {
+                block_hash: block_hash.to_string(),
+                height: block.height,
+                reason: block.reason,
+                first_seen: block.first_seen,
+                peer_ip: block.peer_ip.map(|peer_ip| peer_ip.to_string()),
+            })
+            .collect::<Vec<_>>();
+        Ok(reply::json(&invalid_blocks))
+    }
+
+    /// Purges the given block from the known invalid blocks, or every block if none is given,
+    /// so that it is validated again, and returns the number of purged blocks.
+    async fn purge_invalid_blocks(
+        query: InvalidBlockPurgeQuery<N>,
+        _auth: (),
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Purge the blocks in a blocking task, as it writes to the database.
+        match tokio::task::spawn_blocking(move || consensus.invalid_blocks().purge(query.hash.as_ref())).await {
+            Ok(purged) => Ok(reply::json(&purged.or_reject()?)),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to purge the invalid blocks: {error}"))))
+            }
+        }
+    }
+
+    /// Invalidates the given block of the canonical chain, which is removed from the ledger along with the blocks
+    /// above it, and marked as invalid until it is purged, and returns the hashes of the removed blocks.
+    async fn invalidate_block(
+        block_hash: N::BlockHash,
+        _auth: (),
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Invalidate the block in a blocking task, as it writes to the database.
+        match tokio::task::spawn_blocking(move || consensus.invalidate_block(&block_hash)).await {
+            Ok(blocks) => {
+                let hashes = blocks.or_reject()?.iter().map(|block| block.hash().to_string()).collect::<Vec<_>>();
+                Ok(reply::json(&hashes))
+            }
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to invalidate the block: {error}")))),
+        }
+    }
+
+    /// Returns the audits of the historical blocks, and whether a block failed its audit since the last acknowledgement.
+    async fn get_audit_log(_auth: (), consensus: Option<Consensus<N, C>>) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let audits = consensus
+            .audit_log()
+            .entries()
+            .into_iter()
+            .map(|(_, entry)| AuditResponse {
+                height: entry.height,
+                block_hash: entry.hash.to_string(),
+                failure: entry.failure,
+                audited_at: entry.audited_at,
+            })
+            .collect();
+        Ok(reply::json(&AuditLogResponse { has_failure: consensus.audit_log().has_failure(), audits }))
+    }
+
+    /// Lowers the audit failure flag, once the failed audits were handled, and returns `true` if it was raised.
+    async fn acknowledge_audit_failures(
+        _auth: (),
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        Ok(reply::json(&consensus.audit_log().acknowledge()))
+    }
+
+    /// Returns a page of the annotated memory pool entries, in order of arrival.
+    async fn get_memory_pool_snapshot(
+        page: MemoryPoolPage,
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        let limit = page.limit.unwrap_or(MAX_MEMORY_POOL_ENTRIES);
+        // Ensure the number of entries is bounded.
+        if limit > MAX_MEMORY_POOL_ENTRIES {
+            return Err(reject::custom(RestError::Request(format!(
+                "Cannot request more than {MAX_MEMORY_POOL_ENTRIES} entries per call (requested {limit})"
+            ))));
+        }
+
+        // Annotate the entries in a blocking task, as the block template may be rebuilt.
+        let snapshot = tokio::task::spawn_blocking(move || consensus.memory_pool_snapshot(page.offset, limit)).await;
+        match snapshot {
+            Ok(snapshot) => {
+                let snapshot = snapshot.or_reject()?;
+                Ok(reply::json(&MemoryPoolSnapshotResponse {
+                    latest_height: snapshot.latest_height,
+                    num_transactions: snapshot.num_transactions,
+                    entries: snapshot.entries.into_iter().map(MemoryPoolEntryResponse::from).collect(),
+                }))
+            }
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to snapshot the memory pool: {error}"))))
+            }
+        }
+    }
+
     /// Returns the program for the given program ID.
     async fn get_program(program_id: ProgramID<N>, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
         let program = if program_id == ProgramID::<N>::from_str("credits.aleo").or_reject()? {
@@ -310,8 +2662,55 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
     async fn get_state_path_for_commitment(
         commitment: Field<N>,
         ledger: Ledger<N, C>,
+        cache: Arc<ResponseCache<N>>,
+    ) -> Result<impl Reply, Rejection> {
+        // The state path is computed from the latest state root, so it is cached for the latest block only.
+        cached(&cache, &ledger, "get_state_path_for_commitment", commitment.to_string(), None, || {
+            CachedResponse::json(&ledger.get_state_path_for_commitment(&commitment).or_reject()?).or_reject()
+        })
+    }
+
+    /// Returns a proof that the given commitment is in the ledger Merkle tree at the given ledger digest.
+    async fn get_ledger_membership_proof(
+        commitment: Field<N>,
+        query: LedgerDigestQuery<N>,
+        ledger: Ledger<N, C>,
+    ) -> Result<impl Reply, Rejection> {
+        let digest = query.digest.unwrap_or_else(|| ledger.latest_state_root());
+        let proof = ledger.generate_ledger_membership_proof(&commitment, digest).or_reject()?;
+        Ok(reply::json(&LedgerMembershipProofResponse::try_from(proof).or_reject()?))
+    }
+
+    /// Returns the ledger digest at the given height, which defaults to the latest block, with the number of
+    /// commitments and serial numbers in the ledger at its block.
+    async fn get_ledger_digest(query: LedgerDigestHeightQuery, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        // Retrieve the ledger digest in a blocking task, as the counts may be computed from storage.
+        match tokio::task::spawn_blocking(move || ledger.get_ledger_digest(query.height)).await {
+            Ok(digest) => Ok(reply::json(&LedgerDigestResponse::from(digest.or_reject()?))),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to retrieve the ledger digest: {error}"))))
+            }
+        }
+    }
+
+    /// Returns the latest ledger digests, with the heights of their blocks.
+    async fn get_ledger_digest_history(
+        query: LedgerDigestHistoryQuery,
+        ledger: Ledger<N, C>,
     ) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&ledger.get_state_path_for_commitment(&commitment).or_reject()?))
+        let digests = ledger.get_ledger_digest_history(query.count).or_reject()?;
+        Ok(reply::json(&LedgerDigestHistoryResponse {
+            digests: digests
+                .into_iter()
+                .map(|(height, digest)| HistoricalDigest { height, digest: digest.to_string() })
+                .collect(),
+            max_digest_age: LEDGER_DIGEST_HISTORY,
+        }))
+    }
+
+    /// Returns the tip of the canonical chain, followed by the tips of the known side branches.
+    async fn get_chain_tips(ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&ledger.chain_tips().into_iter().map(ChainTipResponse::from).collect::<Vec<_>>()))
     }
 
     /// Returns the list of current beacons.
@@ -322,6 +2721,25 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         }
     }
 
+    /// Returns the type, role, and capabilities of the node, along with the progress of its schema migration,
+    /// the peers it syncs blocks from, its stale tip, if any, and its network-adjusted time.
+    async fn get_node_state(router: Router<N>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&NodeStateResponse {
+            node_type: router.node_type().to_string(),
+            node_id: router.node_id().to_string(),
+            role: router.role().to_string(),
+            capabilities: router.capabilities().to_string(),
+            listening: router.is_listening(),
+            connected_peers: router.number_of_connected_peers(),
+            migration: snarkos_node_store::migration_status(),
+            sync_throughput_floor: router.sync().throughput_floor(),
+            sync_sources: router.sync().get_sync_sources().into_iter().map(SyncSourceResponse::from).collect(),
+            secs_since_canon_advance: router.sync().time_since_canon_advance().as_secs(),
+            stale_tip: router.sync().stale_tip().map(StaleTipResponse::from),
+            network_time: router.network_time().into(),
+        }))
+    }
+
     /// Returns the number of peers connected to the node.
     async fn get_peers_count(router: Router<N>) -> Result<impl Reply, Rejection> {
         Ok(reply::json(&router.number_of_connected_peers()))
@@ -337,6 +2755,460 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         Ok(reply::json(&router.connected_metrics()))
     }
 
+    /// Returns the statistics of the peers connected to the node.
+    async fn get_peers_info(router: Router<N>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&router.peer_info()))
+    }
+
+    /// Returns the statistics of the peers known to the node, which are not connected.
+    async fn get_peers_history(router: Router<N>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&router.peer_history()))
+    }
+
+    /// Returns the depth and the counters of the relay queues of the peers.
+    async fn get_peers_relay(router: Router<N>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&router.relay_queues().stats()))
+    }
+
+    /// Returns the peer policy of the node (its banned, pinned, and whitelisted peers).
+    async fn export_peer_policy(_auth: (), router: Router<N>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&router.export_peer_policy()))
+    }
+
+    /// Imports the given peer policy into the node, and returns the outcome of each entry.
+    async fn import_peer_policy(
+        _auth: (),
+        query: PeerPolicyImport,
+        policy: PeerPolicy,
+        router: Router<N>,
+    ) -> Result<impl Reply, Rejection> {
+        // Import the peer policy in a blocking task, as it writes the peer book file.
+        match tokio::task::spawn_blocking(move || router.import_peer_policy(policy, query.replace)).await {
+            Ok(results) => Ok(reply::json(&results)),
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to import the peer policy: {error}")))),
+        }
+    }
+
+    /// Returns the storage statistics of the node.
+    async fn get_storage_statistics(_auth: ()) -> Result<impl Reply, Rejection> {
+        // Compute the storage statistics in a blocking task, as it scans the database.
+        match tokio::task::spawn_blocking(|| storage_statistics(MAX_ENTRIES_PER_COLUMN)).await {
+            Ok(statistics) => Ok(reply::json(&statistics.or_reject()?)),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to compute storage statistics: {error}"))))
+            }
+        }
+    }
+
+    /// Returns the raw entries of the given column of the storage, with hex-encoded keys and values.
+    async fn dump_storage_column(column: String, query: StorageDumpQuery, _auth: ()) -> Result<impl Reply, Rejection> {
+        // Read the entries in a blocking task, as it scans the database.
+        match tokio::task::spawn_blocking(move || {
+            dump_column(&column, query.start.as_deref(), query.end.as_deref(), query.limit)
+        })
+        .await
+        {
+            Ok(dump) => Ok(reply::json(&dump.or_reject()?)),
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to dump the column: {error}")))),
+        }
+    }
+
+    /// Compacts the given column of the storage.
+    async fn compact_column(column: String, _auth: ()) -> Result<impl Reply, Rejection> {
+        // Compact the column in a blocking task, as the compaction may take a while.
+        match tokio::task::spawn_blocking(move || snarkos_node_store::rocksdb::compact_column(&column)).await {
+            Ok(result) => {
+                result.or_reject()?;
+                Ok(reply::json(&true))
+            }
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to compact the column: {error}")))),
+        }
+    }
+
+    /// Enables or disables the bulk-sync options of the storage, which disable the automatic compactions.
+    /// The node restores the options of its tuning profile once it reaches the tip.
+    async fn set_bulk_sync(enabled: bool, _auth: ()) -> Result<impl Reply, Rejection> {
+        snarkos_node_store::rocksdb::set_bulk_sync(enabled).or_reject()?;
+        Ok(reply::json(&enabled))
+    }
+
+    /// Compares the shadow storage to the primary storage, if it is enabled, and returns the report of the shadow storage.
+    async fn get_shadow_report(_auth: (), ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        // Compare the storages in a blocking task, as it waits for the shadow storage to catch up.
+        match tokio::task::spawn_blocking(move || match ledger.is_shadow_enabled() {
+            true => ledger.compare_shadow(),
+            false => Ok(ledger.shadow_report()),
+        })
+        .await
+        {
+            Ok(report) => Ok(reply::json(&ShadowReportResponse::from(report.or_reject()?))),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to compare the shadow storage: {error}"))))
+            }
+        }
+    }
+
+    /// Enables or disables the shadow storage, which applies every canon mutation to an in-memory storage.
+    async fn set_shadow(enabled: bool, _auth: (), ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        // Toggle the shadow storage in a blocking task, as enabling it waits on the commit lock.
+        match tokio::task::spawn_blocking(move || match enabled {
+            true => ledger.enable_shadow(),
+            false => {
+                ledger.disable_shadow();
+                Ok(())
+            }
+        })
+        .await
+        {
+            Ok(result) => {
+                result.or_reject()?;
+                Ok(reply::json(&enabled))
+            }
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to toggle the shadow storage: {error}"))))
+            }
+        }
+    }
+
+    /// Exports the metrics of the canonical blocks to a file of the node, and returns the summary of the export.
+    async fn export_metrics(
+        _auth: (),
+        request: ChainMetricsExport,
+        ledger: Ledger<N, C>,
+        difficulty: DifficultyIndex<N>,
+        journal: ChainJournal<N>,
+    ) -> Result<impl Reply, Rejection> {
+        // Export the metrics in a blocking task, as the export reads every block in the range.
+        match tokio::task::spawn_blocking(move || {
+            let reorgs = journal.disconnected_heights(0..ledger.latest_height() + 1)?;
+            let source = LedgerMetricsSource::new(
+                &ledger,
+                |height| Ok(difficulty.get_entry(height)?.map(|entry| entry.difficulty())),
+                reorgs,
+            );
+            export_chain_metrics(&source, &request)
+        })
+        .await
+        {
+            Ok(summary) => Ok(reply::json(&summary.or_reject()?)),
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to export the metrics: {error}")))),
+        }
+    }
+
+    /// Reloads the configuration of the node, and applies the settings that can change while the node is running.
+    async fn reload(_auth: ()) -> Result<impl Reply, Rejection> {
+        // Reload the configuration in a blocking task, as it reads the configuration file.
+        match tokio::task::spawn_blocking(reload_config).await {
+            Ok(result) => {
+                result.or_reject()?;
+                Ok(reply::json(&true))
+            }
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to reload the configuration: {error}"))))
+            }
+        }
+    }
+
+    /// Returns the latency histograms, sizes, and error counts of the requests of each method,
+    /// and the recent slow requests.
+    async fn get_request_statistics(telemetry: Arc<RequestTelemetry>) -> Result<impl Reply, Rejection> {
+        Ok(reply::json(&RequestStatistics {
+            slow_threshold_ms: telemetry.slow_threshold().as_millis() as u64,
+            methods: telemetry.statistics(),
+            slow_requests: telemetry.slow_requests(),
+        }))
+    }
+
+    /// Registers the account of the given view key, whose history is then indexed, and returns its address.
+    async fn register_account(_auth: (), view_key: String, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
+        let view_key = ViewKey::<N>::from_str(&view_key).or_reject()?;
+        // Register the account in a blocking task, as the history of the account is scanned from the genesis block.
+        match tokio::task::spawn_blocking(move || ledger.register_account(view_key)).await {
+            Ok(address) => Ok(reply::json(&address.or_reject()?.to_string())),
+            Err(error) => Err(reject::custom(RestError::Request(format!("Failed to register the account: {error}")))),
+        }
+    }
+
+    /// Returns a page of the history of the given registered account, in order of height.
+    async fn get_account_history(
+        address: Address<N>,
+        _auth: (),
+        query: AccountHistoryQuery,
+        ledger: Ledger<N, C>,
+    ) -> Result<impl Reply, Rejection> {
+        let limit = query.limit.unwrap_or(MAX_ACCOUNT_HISTORY_ENTRIES);
+        // Ensure the number of entries is bounded.
+        if limit > MAX_ACCOUNT_HISTORY_ENTRIES {
+            return Err(reject::custom(RestError::Request(format!(
+                "Cannot request more than {MAX_ACCOUNT_HISTORY_ENTRIES} entries per call (requested {limit})"
+            ))));
+        }
+
+        let page = ledger.get_account_history(&address, query.offset, limit).or_reject()?;
+        Ok(reply::json(&AccountHistoryResponse {
+            latest_height: page.latest_height,
+            num_entries: page.num_entries,
+            entries: page.entries.into_iter().map(AccountHistoryEntryResponse::from).collect(),
+        }))
+    }
+
+    /// Returns the journaled changes to the canonical chain after the given sequence number.
+    async fn get_chain_events(range: ChainEventRange, journal: ChainJournal<N>) -> Result<impl Reply, Rejection> {
+        let limit = range.limit.unwrap_or(MAX_CHAIN_EVENTS);
+        // Ensure the number of events is bounded.
+        if limit > MAX_CHAIN_EVENTS {
+            return Err(reject::custom(RestError::Request(format!(
+                "Cannot request more than {MAX_CHAIN_EVENTS} events per call (requested {limit})"
+            ))));
+        }
+
+        // Read the latest sequence number, so that the client can tell whether it has caught up.
+        let latest_sequence = journal.latest_sequence();
+        let events = journal
+            .events(range.since, limit)
+            .or_reject()?
+            .into_iter()
+            .map(|(sequence, event)| SequencedChainEvent { sequence, event })
+            .collect();
+
+        Ok(reply::json(&ChainEvents { latest_sequence, events }))
+    }
+
+    /// Upgrades the connection to a WebSocket, which streams the changes to the canonical chain from the given
+    /// start height or resume token, or from the next block otherwise.
+    async fn subscribe_blocks(
+        query: BlockSubscriptionQuery,
+        ws: Ws,
+        ledger: Ledger<N, C>,
+        journal: ChainJournal<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let include_transactions = query.include_transactions;
+        // Start the subscription before the upgrade, so that an invalid request is rejected.
+        let ledger_clone = ledger.clone();
+        let subscription = tokio::task::spawn_blocking(move || match query.resume_token {
+            Some(token) => BlockSubscription::resume(journal, token.parse()?),
+            None => {
+                let next_height = || ledger_clone.latest_height() + 1;
+                let start_height = query.start_height.unwrap_or_else(next_height);
+                BlockSubscription::start(journal, start_height, next_height)
+            }
+        });
+        let subscription = match subscription.await {
+            Ok(subscription) => subscription.or_reject()?,
+            Err(error) => {
+                return Err(reject::custom(RestError::Request(format!("Failed to start the subscription: {error}"))));
+            }
+        };
+        Ok(ws.on_upgrade(move |socket| Self::stream_blocks(socket, subscription, ledger, include_transactions)))
+    }
+
+    /// Streams the events of the given subscription to the given WebSocket, until it is closed.
+    async fn stream_blocks(
+        mut socket: WebSocket,
+        mut subscription: BlockSubscription<N>,
+        ledger: Ledger<N, C>,
+        include_transactions: bool,
+    ) {
+        // Subscribe to the connected and disconnected blocks, which are journaled before they are published.
+        let blocks = Arc::new(ledger.event_bus().subscribe(&[Topic::BlockConnected, Topic::BlockDisconnected]));
+        loop {
+            // Retrieve the next events, in a blocking task, as they are read from storage.
+            let ledger = ledger.clone();
+            let task = tokio::task::spawn_blocking(move || {
+                let notifications = Self::next_notifications(&mut subscription, &ledger, include_transactions);
+                (subscription, notifications)
+            });
+            let notifications = match task.await {
+                Ok((returned, Ok(notifications))) => {
+                    subscription = returned;
+                    notifications
+                }
+                Ok((_, Err(error))) => {
+                    warn!("Closing a block subscription - {error}");
+                    let _ = socket
+                        .send(WsMessage::text(serde_json::json!({ "error": error.to_string() }).to_string()))
+                        .await;
+                    let _ = socket.close().await;
+                    return;
+                }
+                Err(error) => {
+                    warn!("Closing a block subscription - {error}");
+                    let _ = socket.close().await;
+                    return;
+                }
+            };
+
+            // Send the events.
+            let is_idle = notifications.is_empty();
+            for notification in notifications {
+                if socket.send(WsMessage::text(notification)).await.is_err() {
+                    return;
+                }
+            }
+
+            // Once the subscriber has caught up, wait for new events, until the subscriber closes the connection.
+            if is_idle {
+                let interval = Duration::from_millis(SUBSCRIPTION_POLL_INTERVAL_IN_MS);
+                let blocks = blocks.clone();
+                let block_event = tokio::task::spawn_blocking(move || blocks.recv_timeout(interval));
+                match futures_util::future::select(socket.next(), block_event).await {
+                    // Ignore the messages from the subscriber.
+                    Either::Left((Some(Ok(message)), _)) if !message.is_close() => (),
+                    // Stop once the connection is closed.
+                    Either::Left(_) => return,
+                    // Retrieve the new events, once a block is connected or disconnected, or the interval elapsed.
+                    Either::Right(_) => (),
+                }
+            }
+        }
+    }
+
+    /// Returns the next events of the given subscription, serialized for the subscriber.
+    fn next_notifications(
+        subscription: &mut BlockSubscription<N>,
+        ledger: &Ledger<N, C>,
+        include_transactions: bool,
+    ) -> Result<Vec<String>> {
+        // Retrieve the next events, with the canonical blocks that are older than the journal.
+        let events = subscription.next_events(MAX_SUBSCRIPTION_EVENTS, |height| {
+            let block = ledger.get_block(height)?;
+            let transaction_ids = block.transaction_ids().copied().collect();
+            Ok(ChainEvent::Connected { height, hash: block.hash(), transaction_ids })
+        })?;
+
+        events
+            .into_iter()
+            .map(|(token, event)| {
+                // Retrieve the transactions of the connected block, if they were requested.
+                let transactions = match (&event, include_transactions) {
+                    (ChainEvent::Connected { hash, .. }, true) => {
+                        ledger.get_block_by_hash(hash).ok().map(|block| block.transactions().clone())
+                    }
+                    _ => None,
+                };
+                let notification = BlockNotification { resume_token: token.to_string(), event, transactions };
+                Ok(serde_json::to_string(&notification)?)
+            })
+            .collect()
+    }
+
+    /// Upgrades the connection to a WebSocket, on which the subscriber holds any number of subscriptions to the
+    /// transactions accepted into the memory pool, each with its own filter. The account filters require the
+    /// session to be authenticated.
+    async fn subscribe_transactions(
+        token: Option<String>,
+        ws: Ws,
+        ledger: Ledger<N, C>,
+    ) -> Result<impl Reply, Rejection> {
+        // Authenticate the session before the upgrade, so that an invalid token is rejected.
+        let is_authenticated = match token {
+            Some(token) => verify_token(&token).map(|()| true)?,
+            None => false,
+        };
+        Ok(ws.on_upgrade(move |socket| Self::stream_transactions(socket, ledger, is_authenticated)))
+    }
+
+    /// Serves the transaction subscriptions of the given WebSocket session, until it is closed.
+    ///
+    /// The subscriber sends `{"subscribe": {filter}}` and `{"unsubscribe": id}` requests, and receives the
+    /// matching transactions as `{"subscription": id, "transaction": transaction}`.
+    async fn stream_transactions(mut socket: WebSocket, ledger: Ledger<N, C>, is_authenticated: bool) {
+        let mut subscriptions = BTreeMap::<u64, Subscription<N>>::new();
+        let mut next_id = 0;
+        loop {
+            // Send the transactions of each subscription, which were filtered before they were queued.
+            for (id, subscription) in &subscriptions {
+                for event in subscription.try_iter() {
+                    if let Event::TransactionAccepted { transaction } = event {
+                        let notification = serde_json::json!({ "subscription": id, "transaction": *transaction });
+                        if socket.send(WsMessage::text(notification.to_string())).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // Wait for a request of the subscriber, until the next check of the subscriptions.
+            let interval = Duration::from_millis(TRANSACTION_SUBSCRIPTION_POLL_INTERVAL_IN_MS);
+            let interval = Box::pin(tokio::time::sleep(interval));
+            let message = match futures_util::future::select(socket.next(), interval).await {
+                Either::Left((Some(Ok(message)), _)) if !message.is_close() => message,
+                // Stop once the connection is closed.
+                Either::Left(_) => return,
+                Either::Right(_) => continue,
+            };
+            // Ignore the messages that are not requests, such as pings.
+            let request = match message.to_str() {
+                Ok(request) => request,
+                Err(()) => continue,
+            };
+
+            // Handle the request, and respond with its outcome.
+            let response = match serde_json::from_str::<TransactionSessionRequest>(request) {
+                Ok(TransactionSessionRequest::Subscribe(filter)) => {
+                    let subscription = match subscriptions.len() < MAX_TRANSACTION_SUBSCRIPTIONS {
+                        true => filter.into_filter(is_authenticated).and_then(|f| ledger.subscribe_transactions(f)),
+                        false => Err(anyhow!("Cannot hold more than {MAX_TRANSACTION_SUBSCRIPTIONS} subscriptions")),
+                    };
+                    match subscription {
+                        Ok(subscription) => {
+                            subscriptions.insert(next_id, subscription);
+                            next_id += 1;
+                            serde_json::json!({ "subscribed": next_id - 1 })
+                        }
+                        Err(error) => serde_json::json!({ "error": error.to_string() }),
+                    }
+                }
+                Ok(TransactionSessionRequest::Unsubscribe(id)) => match subscriptions.remove(&id) {
+                    Some(_) => serde_json::json!({ "unsubscribed": id }),
+                    None => serde_json::json!({ "error": format!("The subscription {id} does not exist") }),
+                },
+                Err(error) => serde_json::json!({ "error": format!("Invalid request - {error}") }),
+            };
+            if socket.send(WsMessage::text(response.to_string())).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Returns the number of confirmed transactions using each circuit, along with its consensus rule, if it has one.
+    async fn get_circuit_stats(
+        consensus: Option<Consensus<N, C>>,
+        circuits: CircuitIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let stats = circuits
+            .circuits()
+            .into_iter()
+            .map(|((program_id, function_name), num_transactions)| {
+                let validity = consensus.as_ref().and_then(|c| c.circuit_rules().get(&program_id, &function_name));
+                CircuitStats {
+                    circuit: format!("{program_id}/{function_name}"),
+                    num_transactions,
+                    valid_from_height: validity.map(|validity| validity.valid_from_height),
+                    valid_until_height: validity.map(|validity| validity.valid_until_height),
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(reply::json(&stats))
+    }
+
+    /// Returns the IDs of the confirmed transactions using the given circuit, in order of confirmation.
+    async fn get_circuit_transactions(
+        program_id: ProgramID<N>,
+        function_name: Identifier<N>,
+        range: CircuitTransactionRange,
+        circuits: CircuitIndex<N>,
+    ) -> Result<impl Reply, Rejection> {
+        let limit = range.limit.unwrap_or(MAX_CIRCUIT_TRANSACTIONS);
+        // Ensure the number of transaction IDs is bounded.
+        if limit > MAX_CIRCUIT_TRANSACTIONS {
+            return Err(reject::custom(RestError::Request(format!(
+                "Cannot request more than {MAX_CIRCUIT_TRANSACTIONS} transactions per call (requested {limit})"
+            ))));
+        }
+        Ok(reply::json(&circuits.transactions(&(program_id, function_name), range.start, limit).or_reject()?))
+    }
+
     /// Returns the block hash that contains the given `transaction ID`.
     async fn find_block_hash(transaction_id: N::TransactionID, ledger: Ledger<N, C>) -> Result<impl Reply, Rejection> {
         Ok(reply::json(&ledger.find_block_hash(&transaction_id).or_reject()?))
@@ -373,18 +3245,139 @@ impl<N: Network, C: ConsensusStorage<N>, R: Routing<N>> Rest<N, C, R> {
         if let Some(consensus) = consensus {
             // Add the unconfirmed transaction to the memory pool.
             consensus.add_unconfirmed_transaction(transaction.clone()).or_reject()?;
+            // Mark the transaction as local, so that its expiry from the memory pool is reported.
+            consensus.memory_pool().mark_local_transaction(transaction.id());
         }
 
         // Prepare the unconfirmed transaction message.
         let transaction_id = transaction.id();
-        let message = Message::UnconfirmedTransaction(UnconfirmedTransaction {
-            transaction_id,
-            transaction: Data::Object(transaction),
-        });
+        let message = UnconfirmedTransaction::new(transaction_id, Data::Object(transaction));
 
-        // Broadcast the transaction.
-        routing.propagate(message, &[]);
+        // Diffuse the transaction to the network.
+        routing.diffuse(message);
 
         Ok(transaction_id.to_string())
     }
+
+    /// Adds the given ordered batch of transactions to the memory pool in the given mode,
+    /// broadcasts the accepted transactions, and returns the outcome of each transaction.
+    async fn transactions_broadcast(
+        query: BatchQuery,
+        transactions: Vec<Transaction<N>>,
+        consensus: Option<Consensus<N, C>>,
+        routing: Arc<R>,
+    ) -> Result<impl Reply, Rejection> {
+        // Parse the mode of the batch.
+        let mode = match &query.mode {
+            Some(mode) => BatchMode::from_str(mode).or_reject()?,
+            None => BatchMode::default(),
+        };
+
+        // If the consensus module is enabled, add the unconfirmed transactions to the memory pool.
+        let outcomes = match consensus {
+            Some(consensus) => {
+                // Validate the batch in a blocking task, as it verifies the proofs of every transaction.
+                let batch = transactions.clone();
+                match tokio::task::spawn_blocking(move || consensus.add_unconfirmed_transactions(batch, mode)).await {
+                    Ok(outcomes) => outcomes,
+                    Err(error) => {
+                        return Err(reject::custom(RestError::Request(format!(
+                            "Failed to add the transactions: {error}"
+                        ))));
+                    }
+                }
+            }
+            None => transactions
+                .iter()
+                .map(|transaction| BatchOutcome { transaction_id: transaction.id(), rejection: None })
+                .collect(),
+        };
+
+        // Diffuse the accepted transactions to the network, in order.
+        for (transaction, outcome) in transactions.into_iter().zip(&outcomes) {
+            if outcome.is_accepted() {
+                routing.diffuse(UnconfirmedTransaction::new(outcome.transaction_id, Data::Object(transaction)));
+            }
+        }
+
+        Ok(reply::json(&outcomes.into_iter().map(BatchTransactionStatus::from).collect::<Vec<_>>()))
+    }
+
+    /// Simulates the admission of the given transaction into the memory pool, and returns the verdict,
+    /// without adding the transaction to the memory pool, or broadcasting it.
+    async fn simulate_acceptance(
+        query: SimulationQuery,
+        transaction: Transaction<N>,
+        consensus: Option<Consensus<N, C>>,
+    ) -> Result<impl Reply, Rejection> {
+        let consensus = match consensus {
+            Some(consensus) => consensus,
+            None => return Err(reject::custom(RestError::Request("Invalid endpoint".to_string()))),
+        };
+        // Simulate in a blocking task, as the proofs may be verified, and the block template rebuilt.
+        let simulation =
+            tokio::task::spawn_blocking(move || consensus.simulate_acceptance(&transaction, query.verify_proof)).await;
+        match simulation {
+            Ok(simulation) => Ok(reply::json(&AcceptanceSimulationResponse::from(simulation.or_reject()?))),
+            Err(error) => {
+                Err(reject::custom(RestError::Request(format!("Failed to simulate the transaction: {error}"))))
+            }
+        }
+    }
+}
+
+/// Returns a permit to wait on a long-poll request, or rejects the request if the maximum number of
+/// long-poll requests are already waiting. The permit is released once it is dropped.
+fn acquire_long_poll(long_polls: Arc<Semaphore>) -> Result<OwnedSemaphorePermit, Rejection> {
+    long_polls.try_acquire_owned().map_err(|_| {
+        reject::custom(RestError::Unavailable(format!(
+            "The maximum number of long-poll requests ({MAX_LONG_POLLS}) are waiting - retry later"
+        )))
+    })
+}
+
+/// Returns the cached response for the given method and parameters, or computes and caches it,
+/// after updating the cache to the latest block of the ledger.
+fn cached<N: Network, C: ConsensusStorage<N>>(
+    cache: &ResponseCache<N>,
+    ledger: &Ledger<N, C>,
+    method: &'static str,
+    params: String,
+    anchor: Option<(u32, N::BlockHash)>,
+    compute: impl FnOnce() -> Result<CachedResponse, Rejection>,
+) -> Result<CachedResponse, Rejection> {
+    cache.update_tip(ledger.latest_height(), ledger.latest_hash(), |height| ledger.get_hash(height).ok());
+    cache.get_or_compute(method, params, anchor, compute)
+}
+
+/// Returns the serialized response for the given object in the given encoding.
+#[cfg(feature = "cbor")]
+fn serialize<T: CborEncoding>(object: &T, encoding: Encoding) -> Result<CachedResponse, Rejection> {
+    match encoding {
+        Encoding::Json => CachedResponse::json(object).or_reject(),
+        Encoding::Cbor => Ok(CachedResponse::new(object.to_cbor().or_reject()?, "application/cbor")),
+    }
+}
+
+/// Returns the serialized response for the given object in the given encoding.
+#[cfg(not(feature = "cbor"))]
+fn serialize<T: Serialize>(object: &T, _encoding: Encoding) -> Result<CachedResponse, Rejection> {
+    CachedResponse::json(object).or_reject()
+}
+
+/// Returns the reply for the given object in the given encoding.
+#[cfg(feature = "cbor")]
+fn encode<T: CborEncoding>(object: &T, encoding: Encoding) -> Result<reply::Response, Rejection> {
+    match encoding {
+        Encoding::Json => Ok(reply::json(object).into_response()),
+        Encoding::Cbor => {
+            Ok(reply::with_header(object.to_cbor().or_reject()?, "content-type", "application/cbor").into_response())
+        }
+    }
+}
+
+/// Returns the reply for the given object in the given encoding.
+#[cfg(not(feature = "cbor"))]
+fn encode<T: Serialize>(object: &T, _encoding: Encoding) -> Result<reply::Response, Rejection> {
+    Ok(reply::json(object).into_response())
 }
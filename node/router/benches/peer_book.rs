@@ -0,0 +1,150 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_messages::NodeType;
+use snarkos_node_router::{PeerBook, PeerState};
+use snarkvm::prelude::{Field, Network, Testnet3, Uniform};
+
+use linked_hash_map::LinkedHashMap;
+use parking_lot::RwLock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+
+type CurrentNetwork = Testnet3;
+type TransactionID = <CurrentNetwork as Network>::TransactionID;
+
+/// The number of simulated peers.
+const NUM_PEERS: u16 = 200;
+/// The number of messages received from each peer per run.
+const NUM_MESSAGES_PER_PEER: usize = 5_000;
+/// The maximum number of transaction IDs remembered by the global inventory.
+const MAX_CACHE_SIZE: usize = 1 << 17;
+/// The interval in between aggregations of the connection states into the peer book.
+const AGGREGATION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The previous layout of the peer book, with the statistics of every peer and the inventory of every peer
+/// behind a single lock each, which is write-locked by every message.
+#[derive(Default)]
+struct GlobalPeerBook {
+    /// The number of messages received from each peer, by message type.
+    statistics: RwLock<HashMap<SocketAddr, BTreeMap<String, u64>>>,
+    /// The transaction IDs received from each peer, with their last seen timestamp.
+    inventory: RwLock<LinkedHashMap<(SocketAddr, TransactionID), OffsetDateTime>>,
+}
+
+impl GlobalPeerBook {
+    /// Records a transaction received from the given peer.
+    fn record(&self, peer_ip: SocketAddr, transaction_id: TransactionID) -> bool {
+        if let Some(messages) = self.statistics.write().get_mut(&peer_ip) {
+            *messages.entry("UnconfirmedTransaction".to_string()).or_default() += 1;
+        }
+        let mut inventory = self.inventory.write();
+        let seen_before = inventory.insert((peer_ip, transaction_id), OffsetDateTime::now_utc()).is_some();
+        while inventory.len() > MAX_CACHE_SIZE {
+            inventory.pop_front();
+        }
+        seen_before
+    }
+}
+
+/// Returns the throughput (in messages per second) of receiving the given transactions from the given peers,
+/// with each thread handling the messages of its own subset of the peers, as the connection tasks do.
+fn throughput(
+    peer_ips: &[SocketAddr],
+    transaction_ids: &[TransactionID],
+    num_threads: usize,
+    record: impl Fn(SocketAddr, TransactionID) -> bool + Sync,
+) -> f64 {
+    let timer = Instant::now();
+    std::thread::scope(|scope| {
+        for thread in 0..num_threads {
+            let record = &record;
+            scope.spawn(move || {
+                let peer_ips = peer_ips.iter().skip(thread).step_by(num_threads).collect::<Vec<_>>();
+                for transaction_id in transaction_ids {
+                    for peer_ip in &peer_ips {
+                        std::hint::black_box(record(**peer_ip, *transaction_id));
+                    }
+                }
+            });
+        }
+    });
+    (peer_ips.len() * transaction_ids.len()) as f64 / timer.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let rng = &mut rand::thread_rng();
+    let num_threads = std::thread::available_parallelism().map_or(4, |threads| threads.get());
+
+    // Sample the peers and the transactions they relay.
+    let peer_ips = (0..NUM_PEERS).map(|port| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 4130 + port));
+    let peer_ips = peer_ips.collect::<Vec<_>>();
+    let transaction_ids =
+        (0..NUM_MESSAGES_PER_PEER).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect::<Vec<_>>();
+
+    // Measure the previous layout.
+    let global = GlobalPeerBook::default();
+    global.statistics.write().extend(peer_ips.iter().map(|peer_ip| (*peer_ip, BTreeMap::new())));
+    let global_throughput = throughput(&peer_ips, &transaction_ids, num_threads, |peer_ip, transaction_id| {
+        global.record(peer_ip, transaction_id)
+    });
+
+    // Measure the sharded layout, with the connection states aggregated into the peer book in the background.
+    let book = Arc::new(PeerBook::<CurrentNetwork>::default());
+    let mut peer_states = HashMap::with_capacity(peer_ips.len());
+    for peer_ip in &peer_ips {
+        book.record_connection(*peer_ip, NodeType::Client, 1, true);
+        peer_states.insert(*peer_ip, Arc::new(PeerState::<CurrentNetwork>::new(*peer_ip, 0, book.delta_sender())));
+    }
+    let peer_states = RwLock::new(peer_states);
+    let is_done = Arc::new(AtomicBool::new(false));
+    let aggregator = {
+        let (book, is_done) = (book.clone(), is_done.clone());
+        std::thread::spawn(move || {
+            while !is_done.load(Ordering::Relaxed) {
+                std::thread::sleep(AGGREGATION_INTERVAL);
+                book.aggregate();
+            }
+        })
+    };
+    let sharded_throughput = throughput(&peer_ips, &transaction_ids, num_threads, |peer_ip, transaction_id| {
+        let state = peer_states.read().get(&peer_ip).cloned().unwrap();
+        state.record_inbound("UnconfirmedTransaction");
+        state.insert_inbound_transaction(transaction_id).is_some()
+    });
+    is_done.store(true, Ordering::Relaxed);
+    aggregator.join().unwrap();
+
+    // Ensure the statistics converge to the same counts.
+    peer_states.read().values().for_each(|state| state.flush());
+    book.aggregate();
+    for peer_ip in &peer_ips {
+        let received = book.get(peer_ip).unwrap().messages_received.get("UnconfirmedTransaction").copied();
+        assert_eq!(received, Some(NUM_MESSAGES_PER_PEER as u64));
+    }
+
+    println!("{NUM_PEERS} peers, {NUM_MESSAGES_PER_PEER} messages per peer, {num_threads} threads");
+    println!("global lock:   {:>12.0} msgs/s", global_throughput);
+    println!("sharded state: {:>12.0} msgs/s ({:.1}x)", sharded_throughput, sharded_throughput / global_throughput);
+}
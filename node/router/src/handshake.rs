@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Peer, Router};
+use crate::{Peer, PeerTransport, Router};
 use snarkos_node_messages::{
     ChallengeRequest,
     ChallengeResponse,
@@ -22,18 +22,18 @@ use snarkos_node_messages::{
     Disconnect,
     DisconnectReason,
     Message,
-    MessageCodec,
     MessageTrait,
+    NodeId,
+    PeerCodec,
 };
 use snarkos_node_tcp::{ConnectionSide, Tcp, P2P};
 use snarkvm::prelude::{error, Address, Header, Network};
 
 use anyhow::{bail, Result};
-use futures::SinkExt;
 use rand::{rngs::OsRng, Rng};
 use std::{io, net::SocketAddr};
+use time::OffsetDateTime;
 use tokio::net::TcpStream;
-use tokio_stream::StreamExt;
 use tokio_util::codec::Framed;
 
 impl<N: Network> P2P for Router<N> {
@@ -44,10 +44,14 @@ impl<N: Network> P2P for Router<N> {
 }
 
 /// A macro unwrapping the expected handshake message or returning an error for unexpected messages.
+/// If given, the `$on_disconnect` closure is invoked with the reason of a received disconnect message.
 #[macro_export]
 macro_rules! expect_message {
-    ($msg_ty:path, $framed:expr, $peer_addr:expr) => {
-        match $framed.try_next().await? {
+    ($msg_ty:path, $transport:expr, $peer_addr:expr) => {
+        $crate::expect_message!($msg_ty, $transport, $peer_addr, |_| {})
+    };
+    ($msg_ty:path, $transport:expr, $peer_addr:expr, $on_disconnect:expr) => {
+        match $crate::PeerTransport::next_message(&mut $transport).await? {
             // Received the expected message, proceed.
             Some($msg_ty(data)) => {
                 trace!("Received '{}' from '{}'", data.name(), $peer_addr);
@@ -55,7 +59,8 @@ macro_rules! expect_message {
             }
             // Received a disconnect message, abort.
             Some(Message::Disconnect(reason)) => {
-                return Err(error(format!("'{}' disconnected: {reason:?}", $peer_addr)))
+                ($on_disconnect)(&reason.reason);
+                return Err(error(format!("'{}' disconnected: {reason:?}", $peer_addr)));
             }
             // Received an unexpected message, abort.
             Some(ty) => {
@@ -77,10 +82,14 @@ macro_rules! expect_message {
 /// A macro for cutting a handshake short if message verification fails.
 #[macro_export]
 macro_rules! handle_verification {
-    ($result:expr, $framed:expr, $peer_addr:expr) => {
+    ($result:expr, $transport:expr, $peer_addr:expr) => {
         if let Some(reason) = $result {
             trace!("Sending 'Disconnect' to '{}'", $peer_addr);
-            $framed.send(Message::Disconnect(Disconnect { reason: reason.clone() })).await?;
+            $crate::PeerTransport::send_message(
+                &mut $transport,
+                Message::Disconnect(Disconnect { reason: reason.clone() }),
+            )
+            .await?;
             return Err(error(format!("Dropped '{}' for reason: {reason:?}", $peer_addr)));
         }
     };
@@ -94,7 +103,32 @@ impl<N: Network> Router<N> {
         stream: &'a mut TcpStream,
         peer_side: ConnectionSide,
         genesis_header: Header<N>,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, MessageCodec<N>>)> {
+        genesis_hash: N::BlockHash,
+    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, PeerCodec<N>>)> {
+        // Construct the stream.
+        let framed = Framed::new(stream, PeerCodec::<N>::default());
+
+        // Perform the handshake, and increase the message size limit if it succeeded.
+        let (peer_ip, mut framed) =
+            self.handshake_with_transport(peer_addr, framed, peer_side, genesis_header, genesis_hash).await?;
+        framed.codec_mut().update_max_message_len();
+        // Compress the messages that follow the handshake, if both peers advertised the compression.
+        if let Some(stats) = self.compression_stats(&peer_ip) {
+            framed.codec_mut().enable_compression(self.compression(), stats);
+        }
+
+        Ok((peer_ip, framed))
+    }
+
+    /// Executes the handshake protocol over the given transport.
+    pub async fn handshake_with_transport<T: PeerTransport<N>>(
+        &self,
+        peer_addr: SocketAddr,
+        transport: T,
+        peer_side: ConnectionSide,
+        genesis_header: Header<N>,
+        genesis_hash: N::BlockHash,
+    ) -> io::Result<(SocketAddr, T)> {
         // If this is an inbound connection, we log it, but don't know the listening address yet.
         // Otherwise, we can immediately register the listening address.
         let mut peer_ip = if peer_side == ConnectionSide::Initiator {
@@ -106,10 +140,10 @@ impl<N: Network> Router<N> {
         };
 
         // Perform the handshake; we pass on a mutable reference to peer_ip in case the process is broken at any point in time.
-        let mut handshake_result = if peer_side == ConnectionSide::Responder {
-            self.handshake_inner_initiator(peer_addr, &mut peer_ip, stream, genesis_header).await
+        let handshake_result = if peer_side == ConnectionSide::Responder {
+            self.handshake_inner_initiator(peer_addr, &mut peer_ip, transport, genesis_header, genesis_hash).await
         } else {
-            self.handshake_inner_responder(peer_addr, &mut peer_ip, stream, genesis_header).await
+            self.handshake_inner_responder(peer_addr, &mut peer_ip, transport, genesis_header, genesis_hash).await
         };
 
         // Remove the address from the collection of connecting peers (if the handshake got to the point where it's known).
@@ -117,26 +151,26 @@ impl<N: Network> Router<N> {
             self.connecting_peers.lock().remove(&ip);
         }
 
-        // If the handshake succeeded, announce it and increase the message size limit.
-        if let Ok((ref peer_ip, ref mut framed)) = handshake_result {
-            info!("Connected to '{peer_ip}'");
-            framed.codec_mut().update_max_message_len();
+        // If the handshake succeeded, announce it.
+        if let Ok((ref peer_ip, _)) = handshake_result {
+            match self.is_encrypted(peer_ip) {
+                true => info!("Connected to '{peer_ip}'"),
+                false => info!("Connected to '{peer_ip}' (unencrypted)"),
+            }
         }
 
         handshake_result
     }
 
     /// The connection initiator side of the handshake.
-    async fn handshake_inner_initiator<'a>(
-        &'a self,
+    async fn handshake_inner_initiator<T: PeerTransport<N>>(
+        &self,
         peer_addr: SocketAddr,
         peer_ip: &mut Option<SocketAddr>,
-        stream: &'a mut TcpStream,
+        mut transport: T,
         genesis_header: Header<N>,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, MessageCodec<N>>)> {
-        // Construct the stream.
-        let mut framed = Framed::new(stream, MessageCodec::<N>::default());
-
+        genesis_hash: N::BlockHash,
+    ) -> io::Result<(SocketAddr, T)> {
         // This value is immediately guaranteed to be present, so it can be unwrapped.
         let peer_ip = peer_ip.unwrap();
 
@@ -148,63 +182,136 @@ impl<N: Network> Router<N> {
         let our_nonce = rng.gen();
 
         // Send a challenge request to the peer.
-        let our_request = ChallengeRequest::new(self.local_ip().port(), self.node_type, self.address(), our_nonce);
+        let our_request = ChallengeRequest::new(
+            self.local_ip().port(),
+            self.node_type,
+            self.address(),
+            genesis_hash,
+            our_nonce,
+            self.pruned_height(),
+        )
+        .with_capabilities(self.capabilities())
+        .with_timestamp(OffsetDateTime::now_utc().unix_timestamp())
+        .with_node_id(self.node_id());
         trace!("Sending '{}' to '{peer_addr}'", our_request.name());
-        framed.send(Message::ChallengeRequest(our_request)).await?;
+        transport.send_message(Message::ChallengeRequest(our_request)).await?;
 
-        /* Step 2: Receive the peer's challenge response followed by the challenge request. */
+        /* Step 2: Receive the peer's challenge request and encrypt the connection, followed by the challenge response. */
 
-        // Listen for the challenge response message.
-        let peer_response = expect_message!(Message::ChallengeResponse, framed, peer_addr);
+        // If the peer rejected our network or chain, restrict it to avoid a reconnect loop. The peer is not tagged
+        // as mismatched, as the reason is only claimed by the peer.
+        let on_disconnect = |reason: &DisconnectReason| {
+            if reason.is_chain_mismatch() {
+                self.insert_restricted_peer(peer_ip);
+            }
+        };
 
-        // Listen for the challenge request message.
-        let peer_request = expect_message!(Message::ChallengeRequest, framed, peer_addr);
+        // A peer with transport encryption replies with its challenge request, whereas
+        // a peer without transport encryption replies with its challenge response first.
+        let (peer_request, peer_response) = match transport.next_message().await? {
+            Some(Message::ChallengeRequest(peer_request)) => {
+                trace!("Received '{}' from '{peer_addr}'", peer_request.name());
+
+                // Verify the challenge request. If a disconnect reason was returned, send the disconnect message and abort.
+                handle_verification!(
+                    self.verify_challenge_request(peer_addr, &peer_request, genesis_hash, true),
+                    transport,
+                    peer_addr
+                );
+
+                // Encrypt the connection.
+                transport = self
+                    .encrypt_connection(peer_addr, peer_ip, peer_request.node_id, transport, ConnectionSide::Initiator)
+                    .await?;
+
+                // Listen for the challenge response message.
+                let peer_response = expect_message!(Message::ChallengeResponse, transport, peer_addr, on_disconnect);
+                (peer_request, peer_response)
+            }
+            Some(Message::ChallengeResponse(peer_response)) => {
+                trace!("Received '{}' from '{peer_addr}'", peer_response.name());
+
+                // Listen for the challenge request message.
+                let peer_request = expect_message!(Message::ChallengeRequest, transport, peer_addr, on_disconnect);
+
+                // Verify the challenge request. If a disconnect reason was returned, send the disconnect message and abort.
+                handle_verification!(
+                    self.verify_challenge_request(peer_addr, &peer_request, genesis_hash, false),
+                    transport,
+                    peer_addr
+                );
+                (peer_request, peer_response)
+            }
+            // Received a disconnect message, abort.
+            Some(Message::Disconnect(reason)) => {
+                on_disconnect(&reason.reason);
+                return Err(error(format!("'{peer_addr}' disconnected: {reason:?}")));
+            }
+            // Received an unexpected message, abort.
+            Some(ty) => {
+                return Err(error(format!(
+                    "'{peer_addr}' did not follow the handshake protocol: received {:?} instead of ChallengeRequest",
+                    ty.name()
+                )));
+            }
+            // Received nothing.
+            None => return Err(error(format!("'{peer_addr}' disconnected before sending \"ChallengeRequest\""))),
+        };
+
+        // Retrieve the handshake hash, if the connection is encrypted.
+        let handshake_hash = transport.noise_state().map(|state| state.handshake_hash().to_vec());
 
         // Verify the challenge response. If a disconnect reason was returned, send the disconnect message and abort.
         handle_verification!(
-            self.verify_challenge_response(peer_addr, peer_request.address, peer_response, genesis_header, our_nonce)
-                .await,
-            framed,
+            self.verify_challenge_response(
+                peer_addr,
+                peer_request.address,
+                peer_response,
+                genesis_header,
+                our_nonce,
+                handshake_hash.as_deref()
+            )
+            .await,
+            transport,
             peer_addr
         );
 
-        // Verify the challenge request. If a disconnect reason was returned, send the disconnect message and abort.
-        handle_verification!(self.verify_challenge_request(peer_addr, &peer_request), framed, peer_addr);
-
         /* Step 3: Send the challenge response. */
 
         // Sign the counterparty nonce.
         let our_signature = self
             .account
-            .sign_bytes(&peer_request.nonce.to_le_bytes(), rng)
+            .sign_bytes(&challenge_message(peer_request.nonce, handshake_hash.as_deref()), rng)
             .map_err(|_| error(format!("Failed to sign the challenge request nonce from '{peer_addr}'")))?;
 
         // Send the challenge response.
         let our_response = ChallengeResponse { genesis_header, signature: Data::Object(our_signature) };
         trace!("Sending '{}' to '{peer_addr}'", our_response.name());
-        framed.send(Message::ChallengeResponse(our_response)).await?;
+        transport.send_message(Message::ChallengeResponse(our_response)).await?;
 
-        // Add the peer to the router.
-        self.insert_connected_peer(Peer::new(peer_ip, &peer_request), peer_addr);
+        // Sample the clock of the peer, now that it completed the handshake.
+        if let Some(timestamp) = peer_request.timestamp {
+            self.network_time().insert_timestamp(peer_ip.ip(), timestamp);
+        }
+        // Add the peer to the router, as an outbound connection.
+        self.insert_connected_peer(Peer::new(peer_ip, &peer_request, peer_node_id(&transport), true), peer_addr);
 
-        Ok((peer_ip, framed))
+        Ok((peer_ip, transport))
     }
 
     /// The connection responder side of the handshake.
-    async fn handshake_inner_responder<'a>(
-        &'a self,
+    async fn handshake_inner_responder<T: PeerTransport<N>>(
+        &self,
         peer_addr: SocketAddr,
         peer_ip: &mut Option<SocketAddr>,
-        stream: &'a mut TcpStream,
+        mut transport: T,
         genesis_header: Header<N>,
-    ) -> io::Result<(SocketAddr, Framed<&mut TcpStream, MessageCodec<N>>)> {
-        // Construct the stream.
-        let mut framed = Framed::new(stream, MessageCodec::<N>::default());
-
+        genesis_hash: N::BlockHash,
+    ) -> io::Result<(SocketAddr, T)> {
         /* Step 1: Receive the challenge request. */
 
         // Listen for the challenge request message.
-        let peer_request = expect_message!(Message::ChallengeRequest, framed, peer_addr);
+        let peer_request = expect_message!(Message::ChallengeRequest, transport, peer_addr);
 
         // Obtain the peer's listening address.
         *peer_ip = Some(SocketAddr::new(peer_addr.ip(), peer_request.listener_port));
@@ -215,50 +322,146 @@ impl<N: Network> Router<N> {
             return Err(error(format!("{forbidden_message}")));
         }
 
+        // Determine if the peer supports transport encryption.
+        let is_encrypted = peer_request.version >= Message::<N>::NOISE_MIN_VERSION;
+
         // Verify the challenge request. If a disconnect reason was returned, send the disconnect message and abort.
-        handle_verification!(self.verify_challenge_request(peer_addr, &peer_request), framed, peer_addr);
+        handle_verification!(
+            self.verify_challenge_request(peer_addr, &peer_request, genesis_hash, is_encrypted),
+            transport,
+            peer_addr
+        );
 
-        /* Step 2: Send the challenge response followed by own challenge request. */
+        /* Step 2: Send the challenge request and encrypt the connection, followed by the challenge response. */
 
         // Initialize an RNG.
         let rng = &mut OsRng;
 
+        // Sample a random nonce.
+        let our_nonce = rng.gen();
+
+        // Prepare the challenge request.
+        let our_request = ChallengeRequest::new(
+            self.local_ip().port(),
+            self.node_type,
+            self.address(),
+            genesis_hash,
+            our_nonce,
+            self.pruned_height(),
+        )
+        .with_capabilities(self.capabilities())
+        .with_timestamp(OffsetDateTime::now_utc().unix_timestamp())
+        .with_node_id(self.node_id());
+
+        // If the peer supports transport encryption, send the challenge request, and encrypt the connection.
+        let our_request = match is_encrypted {
+            true => {
+                trace!("Sending '{}' to '{peer_addr}'", our_request.name());
+                transport.send_message(Message::ChallengeRequest(our_request)).await?;
+
+                transport = self
+                    .encrypt_connection(peer_addr, peer_ip, peer_request.node_id, transport, ConnectionSide::Responder)
+                    .await?;
+                None
+            }
+            false => Some(our_request),
+        };
+
+        // Retrieve the handshake hash, if the connection is encrypted.
+        let handshake_hash = transport.noise_state().map(|state| state.handshake_hash().to_vec());
+
         // Sign the counterparty nonce.
         let our_signature = self
             .account
-            .sign_bytes(&peer_request.nonce.to_le_bytes(), rng)
+            .sign_bytes(&challenge_message(peer_request.nonce, handshake_hash.as_deref()), rng)
             .map_err(|_| error(format!("Failed to sign the challenge request nonce from '{peer_addr}'")))?;
 
-        // Sample a random nonce.
-        let our_nonce = rng.gen();
-
         // Send the challenge response.
         let our_response = ChallengeResponse { genesis_header, signature: Data::Object(our_signature) };
         trace!("Sending '{}' to '{peer_addr}'", our_response.name());
-        framed.send(Message::ChallengeResponse(our_response)).await?;
+        transport.send_message(Message::ChallengeResponse(our_response)).await?;
 
-        // Send the challenge request.
-        let our_request = ChallengeRequest::new(self.local_ip().port(), self.node_type, self.address(), our_nonce);
-        trace!("Sending '{}' to '{peer_addr}'", our_request.name());
-        framed.send(Message::ChallengeRequest(our_request)).await?;
+        // If the peer does not support transport encryption, send the challenge request after the response.
+        if let Some(our_request) = our_request {
+            trace!("Sending '{}' to '{peer_addr}'", our_request.name());
+            transport.send_message(Message::ChallengeRequest(our_request)).await?;
+        }
 
         /* Step 3: Receive the challenge response. */
 
-        // Listen for the challenge response message.
-        let peer_response = expect_message!(Message::ChallengeResponse, framed, peer_addr);
+        // Listen for the challenge response message. If the peer rejected our network or chain, restrict it.
+        let peer_response =
+            expect_message!(Message::ChallengeResponse, transport, peer_addr, |reason: &DisconnectReason| {
+                if reason.is_chain_mismatch() {
+                    self.insert_restricted_peer(peer_ip);
+                }
+            });
 
         // Verify the challenge response. If a disconnect reason was returned, send the disconnect message and abort.
         handle_verification!(
-            self.verify_challenge_response(peer_addr, peer_request.address, peer_response, genesis_header, our_nonce)
-                .await,
-            framed,
+            self.verify_challenge_response(
+                peer_addr,
+                peer_request.address,
+                peer_response,
+                genesis_header,
+                our_nonce,
+                handshake_hash.as_deref()
+            )
+            .await,
+            transport,
             peer_addr
         );
 
-        // Add the peer to the router.
-        self.insert_connected_peer(Peer::new(peer_ip, &peer_request), peer_addr);
+        // Sample the clock of the peer, now that it completed the handshake.
+        if let Some(timestamp) = peer_request.timestamp {
+            self.network_time().insert_timestamp(peer_ip.ip(), timestamp);
+        }
+        // Add the peer to the router, as an inbound connection.
+        self.insert_connected_peer(Peer::new(peer_ip, &peer_request, peer_node_id(&transport), false), peer_addr);
 
-        Ok((peer_ip, framed))
+        Ok((peer_ip, transport))
+    }
+
+    /// Encrypts the connection with the Noise handshake, and ensures the peer's static public key
+    /// matches the key pinned for it, if any, and the node ID it reported, if any.
+    /// The `node_side` is the connection side of this node.
+    ///
+    /// Note that an in-memory transport has no Noise session, and is thus only accepted for a peer without a pinned key.
+    async fn encrypt_connection<T: PeerTransport<N>>(
+        &self,
+        peer_addr: SocketAddr,
+        peer_ip: SocketAddr,
+        peer_node_id: Option<NodeId>,
+        transport: T,
+        node_side: ConnectionSide,
+    ) -> io::Result<T> {
+        // Perform the Noise handshake.
+        let mut transport = transport.encrypt(self.noise.private_key(), node_side).await?;
+        let noise_state = transport.noise_state().cloned();
+
+        // Ensure the peer's static public key matches the pinned key.
+        if let Some(pinned_key) = self.pinned_key(&peer_ip) {
+            if noise_state.as_ref().and_then(|state| state.remote_static()) != Some(pinned_key.as_slice()) {
+                warn!("Dropping '{peer_addr}' with a static public key that does not match the pinned key");
+                handle_verification!(Some(DisconnectReason::PinnedKeyMismatch), transport, peer_addr);
+            }
+        }
+
+        // Ensure the node ID reported by the peer is derived from its static public key.
+        let node_id = noise_state.as_ref().and_then(|state| state.remote_static()).map(NodeId::from_public_key);
+        if let (Some(peer_node_id), Some(node_id)) = (peer_node_id, node_id) {
+            if peer_node_id != node_id {
+                warn!("Dropping '{peer_addr}' with a node ID that does not match its static public key");
+                handle_verification!(Some(DisconnectReason::NodeIdMismatch), transport, peer_addr);
+            }
+        }
+
+        // Store the transport state, to be used by the codecs of the connection.
+        if let Some(noise_state) = noise_state {
+            self.noise_states.write().insert(peer_ip, noise_state);
+        }
+
+        Ok(transport)
     }
 
     /// Ensure the peer is allowed to connect.
@@ -267,6 +470,10 @@ impl<N: Network> Router<N> {
         if self.is_local_ip(&peer_ip) {
             bail!("Dropping connection request from '{peer_ip}' (attempted to self-connect)")
         }
+        // Ensure the node does not surpass the maximum number of peer connections.
+        if self.number_of_connected_peers() >= self.max_connected_peers() {
+            bail!("Dropping connection request from '{peer_ip}' (maximum peers reached)")
+        }
         // Ensure the node is not already connecting to this peer.
         if !self.connecting_peers.lock().insert(peer_ip) {
             bail!("Dropping connection request from '{peer_ip}' (already shaking hands as the initiator)")
@@ -279,8 +486,12 @@ impl<N: Network> Router<N> {
         if self.is_restricted(&peer_ip) {
             bail!("Dropping connection request from '{peer_ip}' (restricted)")
         }
-        // Ensure the peer is not spamming connection attempts.
-        if !peer_ip.ip().is_loopback() {
+        // Ensure the peer is not banned.
+        if self.is_banned(&peer_ip) {
+            bail!("Dropping connection request from '{peer_ip}' (banned)")
+        }
+        // Ensure the peer is not spamming connection attempts, unless it is whitelisted.
+        if !peer_ip.ip().is_loopback() && !self.is_whitelisted(&peer_ip) {
             // Add this connection attempt and retrieve the number of attempts.
             let num_attempts = self.cache.insert_inbound_connection(peer_ip.ip(), Self::RADIO_SILENCE_IN_SECS as i64);
             // Ensure the connecting peer has not surpassed the connection attempt limit.
@@ -298,16 +509,56 @@ impl<N: Network> Router<N> {
         &self,
         peer_addr: SocketAddr,
         message: &ChallengeRequest<N>,
+        expected_genesis_hash: N::BlockHash,
+        is_encrypted: bool,
     ) -> Option<DisconnectReason> {
         // Retrieve the components of the challenge request.
-        let &ChallengeRequest { version, listener_port: _, node_type, address, nonce: _ } = message;
+        let &ChallengeRequest {
+            version,
+            network_id,
+            listener_port,
+            node_type,
+            address,
+            genesis_hash,
+            nonce: _,
+            pruned_height: _,
+            capabilities: _,
+            timestamp: _,
+            node_id: _,
+        } = message;
 
         // Ensure the message protocol version is not outdated.
-        if version < Message::<N>::VERSION {
+        if version < self.minimum_version() {
             warn!("Dropping '{peer_addr}' on version {version} (outdated)");
             return Some(DisconnectReason::OutdatedClientVersion);
         }
 
+        // Ensure the peer follows the handshake of its version, to prevent a downgrade to an unencrypted connection.
+        if is_encrypted != (version >= Message::<N>::NOISE_MIN_VERSION) {
+            warn!("Dropping '{peer_addr}' on version {version} (unexpected handshake)");
+            return Some(DisconnectReason::ProtocolViolation);
+        }
+
+        // Ensure a peer with a pinned key uses an encrypted connection.
+        if !is_encrypted && self.pinned_key(&SocketAddr::new(peer_addr.ip(), listener_port)).is_some() {
+            warn!("Dropping '{peer_addr}' with a pinned key on an unencrypted connection");
+            return Some(DisconnectReason::PinnedKeyMismatch);
+        }
+
+        // Ensure the peer is on the same network.
+        if network_id != N::ID {
+            warn!("Dropping '{peer_addr}' on network {network_id} (expected network {})", N::ID);
+            self.insert_mismatched_peer(SocketAddr::new(peer_addr.ip(), listener_port));
+            return Some(DisconnectReason::InvalidNetworkId);
+        }
+
+        // Ensure the peer is on the same chain.
+        if genesis_hash != expected_genesis_hash {
+            warn!("Dropping '{peer_addr}' with genesis block '{genesis_hash}' (expected '{expected_genesis_hash}')");
+            self.insert_mismatched_peer(SocketAddr::new(peer_addr.ip(), listener_port));
+            return Some(DisconnectReason::InvalidGenesisHash);
+        }
+
         // TODO (howardwu): Remove this after Phase 2.
         if !self.is_dev
             && node_type.is_beacon()
@@ -328,6 +579,7 @@ impl<N: Network> Router<N> {
         response: ChallengeResponse<N>,
         expected_genesis_header: Header<N>,
         expected_nonce: u64,
+        handshake_hash: Option<&[u8]>,
     ) -> Option<DisconnectReason> {
         // Retrieve the components of the challenge response.
         let ChallengeResponse { genesis_header, signature } = response;
@@ -348,7 +600,7 @@ impl<N: Network> Router<N> {
         };
 
         // Verify the signature.
-        if !signature.verify_bytes(&peer_address, &expected_nonce.to_le_bytes()) {
+        if !signature.verify_bytes(&peer_address, &challenge_message(expected_nonce, handshake_hash)) {
             warn!("Handshake with '{peer_addr}' failed (invalid signature)");
             return Some(DisconnectReason::InvalidChallengeResponse);
         }
@@ -356,3 +608,17 @@ impl<N: Network> Router<N> {
         None
     }
 }
+
+/// Returns the message signed in a challenge response, which binds the nonce to the encrypted session, if any.
+fn challenge_message(nonce: u64, handshake_hash: Option<&[u8]>) -> Vec<u8> {
+    let mut message = nonce.to_le_bytes().to_vec();
+    if let Some(handshake_hash) = handshake_hash {
+        message.extend_from_slice(handshake_hash);
+    }
+    message
+}
+
+/// Returns the node ID of the peer, if it is authenticated by the static public key of an encrypted transport.
+fn peer_node_id<N: Network, T: PeerTransport<N>>(transport: &T) -> Option<NodeId> {
+    transport.noise_state().and_then(|state| state.remote_static()).map(NodeId::from_public_key)
+}
@@ -14,11 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Outbound, Router, REDUNDANCY_FACTOR};
+use crate::{Outbound, Router, StaleTip, SyncPeerClass, REDUNDANCY_FACTOR};
 use snarkos_node_messages::{DisconnectReason, Message, PeerRequest, PuzzleRequest};
 use snarkvm::prelude::Network;
 
 use colored::Colorize;
+use core::cmp::{Ordering, Reverse};
 use rand::{prelude::IteratorRandom, rngs::OsRng};
 
 /// A helper function to compute the maximum of two numbers.
@@ -39,6 +40,8 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
     const MEDIAN_NUMBER_OF_PEERS: usize = max(Self::MAXIMUM_NUMBER_OF_PEERS / 2, Self::MINIMUM_NUMBER_OF_PEERS);
     /// The maximum number of peers permitted to maintain connections with.
     const MAXIMUM_NUMBER_OF_PEERS: usize = 21;
+    /// The maximum number of outbound peers rotated in each heartbeat, while the tip is stale.
+    const MAXIMUM_STALE_TIP_ROTATIONS: usize = 2;
 
     /// Handles the heartbeat request.
     fn heartbeat(&self) {
@@ -55,6 +58,8 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
         self.handle_bootstrap_peers();
         // Keep the trusted peers connected.
         self.handle_trusted_peers();
+        // Rotate the outbound peers, if the tip is stale.
+        self.handle_stale_tip();
         // Keep the puzzle request up to date.
         self.handle_puzzle_request();
     }
@@ -109,8 +114,6 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
             return;
         }
 
-        // Retrieve the trusted peers.
-        let trusted = self.router().trusted_peers();
         // Retrieve the bootstrap peers.
         let bootstrap = self.router().bootstrap_peers();
 
@@ -119,7 +122,7 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
             .router()
             .get_connected_peers()
             .iter()
-            .filter(|peer| !trusted.contains(&peer.ip()) && !bootstrap.contains(&peer.ip()))
+            .filter(|peer| !self.router().is_trusted(&peer.ip()) && !bootstrap.contains(&peer.ip()))
             .min_by_key(|peer| peer.last_seen())
             .map(|peer| peer.ip());
 
@@ -139,16 +142,16 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
     fn handle_connected_peers(&self) {
         // Obtain the number of connected peers.
         let num_connected = self.router().number_of_connected_peers();
+        // Obtain the maximum number of connected peers, which may have been lowered while the node is running.
+        let max_peers = Self::MAXIMUM_NUMBER_OF_PEERS.min(self.router().max_connected_peers());
         // Compute the number of surplus peers.
-        let num_surplus = num_connected.saturating_sub(Self::MAXIMUM_NUMBER_OF_PEERS);
+        let num_surplus = num_connected.saturating_sub(max_peers);
         // Compute the number of deficit peers.
-        let num_deficient = Self::MEDIAN_NUMBER_OF_PEERS.saturating_sub(num_connected);
+        let num_deficient = Self::MEDIAN_NUMBER_OF_PEERS.min(max_peers).saturating_sub(num_connected);
 
         if num_surplus > 0 {
             debug!("Exceeded maximum number of connected peers, disconnecting from {num_surplus} peers");
 
-            // Retrieve the trusted peers.
-            let trusted = self.router().trusted_peers();
             // Retrieve the bootstrap peers.
             let bootstrap = self.router().bootstrap_peers();
 
@@ -162,7 +165,7 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
                 .router()
                 .connected_peers()
                 .into_iter()
-                .filter(|peer_ip| !trusted.contains(peer_ip) && !bootstrap.contains(peer_ip))
+                .filter(|peer_ip| !self.router().is_trusted(peer_ip) && !bootstrap.contains(peer_ip))
                 .choose_multiple(rng, num_surplus);
 
             // Proceed to send disconnect requests to these peers.
@@ -187,8 +190,12 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
             // Initialize an RNG.
             let rng = &mut OsRng::default();
 
+            // Resolve the DNS seeds, if there are not enough candidate peers.
+            if self.router().number_of_candidate_peers() < num_deficient {
+                self.router().spawn_dns_seed_resolution();
+            }
             // Attempt to connect to more peers.
-            for peer_ip in self.router().candidate_peers().into_iter().choose_multiple(rng, num_deficient) {
+            for peer_ip in self.router().select_candidate_peers(num_deficient, max_peers, rng) {
                 self.router().connect(peer_ip);
             }
             // Request more peers from the connected peers.
@@ -254,6 +261,87 @@ pub trait Heartbeat<N: Network>: Outbound<N> {
         }
     }
 
+    /// This function rotates the lowest-quality outbound peers for fresh ones, while the tip is stale,
+    /// and re-queries the DNS seeds once the tip is stale beyond the escalation threshold.
+    fn handle_stale_tip(&self) {
+        let sync = self.router().sync();
+        // Clear the stale tip once the canon advances, or once no peer advertises a higher block height.
+        let stale_tip = match sync.stale_tip() {
+            Some(stale_tip) => stale_tip,
+            None => {
+                if sync.end_stale_tip() {
+                    info!("The tip is no longer stale (at block {})", sync.latest_canon_height());
+                }
+                return;
+            }
+        };
+        let StaleTip { canon_height, peer_height, stale_for, is_escalated } = stale_tip;
+        if sync.begin_stale_tip_report() {
+            warn!(
+                target: "critical",
+                kind = "stale-tip",
+                canon_height,
+                peer_height,
+                "The tip is stale at block {canon_height} for {}s, while a peer advertises block {peer_height}",
+                stale_for.as_secs()
+            );
+        }
+
+        // Re-query the DNS seeds once, regardless of their latest resolution.
+        if is_escalated && sync.begin_stale_tip_escalation() {
+            warn!("The tip is still stale after {}s, re-querying the DNS seeds", stale_for.as_secs());
+            self.router().dns_seeds().reset_resolution();
+            self.router().spawn_dns_seed_resolution();
+        }
+
+        // Initialize an RNG.
+        let rng = &mut OsRng::default();
+        // Select the fresh peers to connect to, or request more peers if there are none.
+        let max_peers = Self::MAXIMUM_NUMBER_OF_PEERS.min(self.router().max_connected_peers());
+        let candidate_peers = self.router().select_candidate_peers(Self::MAXIMUM_STALE_TIP_ROTATIONS, max_peers, rng);
+        if candidate_peers.is_empty() {
+            for peer_ip in self.router().connected_peers().into_iter().choose_multiple(rng, 3) {
+                self.send(peer_ip, Message::PeerRequest(PeerRequest));
+            }
+            return;
+        }
+
+        // Retrieve the bootstrap peers.
+        let bootstrap = self.router().bootstrap_peers();
+
+        // Rank the outbound peers from the lowest quality: the peers that cannot serve the blocks above the canon
+        // come first, followed by the demoted peers, the slowest peers, and the most misbehaving peers.
+        let mut outbound_peers = self
+            .router()
+            .get_connected_peers()
+            .into_iter()
+            .filter(|peer| {
+                peer.is_outbound() && !self.router().is_trusted(&peer.ip()) && !bootstrap.contains(&peer.ip())
+            })
+            .map(|peer| {
+                let peer_ip = peer.ip();
+                let serves_tip = sync.get_peer_class(&peer_ip) != SyncPeerClass::HeadersOnly
+                    && sync.get_peer_height(&peer_ip).is_some_and(|height| height > canon_height);
+                let is_promoted = !sync.is_demoted(&peer_ip);
+                let throughput = sync.get_peer_throughput(&peer_ip).unwrap_or(0.0);
+                let misbehavior = self.router().peer_book().get(&peer_ip).map_or(0, |peer| peer.misbehavior_score);
+                (peer_ip, (serves_tip, is_promoted, throughput, Reverse(misbehavior)))
+            })
+            .collect::<Vec<_>>();
+        outbound_peers.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        // Disconnect from the lowest-quality outbound peers, and connect to the fresh peers in their place.
+        for (peer_ip, _) in outbound_peers.into_iter().take(candidate_peers.len()) {
+            info!("Disconnecting from '{peer_ip}' (stale tip at block {canon_height})");
+            self.send(peer_ip, Message::Disconnect(DisconnectReason::PeerRefresh.into()));
+            // Disconnect from this peer.
+            self.router().disconnect(peer_ip);
+        }
+        for peer_ip in candidate_peers {
+            self.router().connect(peer_ip);
+        }
+    }
+
     /// This function updates the coinbase puzzle if network has updated.
     fn handle_puzzle_request(&self) {
         // Retrieve the node type.
@@ -15,11 +15,10 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use snarkos_node_messages::BlockRequest;
-use snarkvm::prelude::{Network, PuzzleCommitment};
+use snarkvm::prelude::Network;
 
-use core::hash::Hash;
+use core::{hash::Hash, marker::PhantomData};
 use indexmap::{IndexMap, IndexSet};
-use linked_hash_map::LinkedHashMap;
 use parking_lot::RwLock;
 use std::{
     collections::VecDeque,
@@ -27,14 +26,8 @@ use std::{
 };
 use time::{Duration, OffsetDateTime};
 
-/// The maximum number of items to store in a cache map.
-const MAX_CACHE_SIZE: usize = 1 << 17;
-
-/// A helper containing the peer IP and solution commitment.
-type SolutionKey<N> = (SocketAddr, PuzzleCommitment<N>);
-/// A helper containing the peer IP and transaction ID.
-type TransactionKey<N> = (SocketAddr, <N as Network>::TransactionID);
-
+/// The cache of the recent requests of each peer. Note that the inventory known to each peer is kept
+/// in the state of its connection (see `PeerState`).
 #[derive(Debug)]
 pub struct Cache<N: Network> {
     /// The map of peer connections to their recent timestamps.
@@ -43,18 +36,12 @@ pub struct Cache<N: Network> {
     seen_inbound_messages: RwLock<IndexMap<SocketAddr, VecDeque<OffsetDateTime>>>,
     /// The map of peer IPs to their recent timestamps.
     seen_inbound_puzzle_requests: RwLock<IndexMap<SocketAddr, VecDeque<OffsetDateTime>>>,
-    /// The map of solution commitments to their last seen timestamp.
-    seen_inbound_solutions: RwLock<LinkedHashMap<SolutionKey<N>, OffsetDateTime>>,
-    /// The map of transaction IDs to their last seen timestamp.
-    seen_inbound_transactions: RwLock<LinkedHashMap<TransactionKey<N>, OffsetDateTime>>,
     /// The map of peer IPs to their block requests.
     seen_outbound_block_requests: RwLock<IndexMap<SocketAddr, IndexSet<BlockRequest>>>,
     /// The map of peer IPs to the number of puzzle requests.
     seen_outbound_puzzle_requests: RwLock<IndexMap<SocketAddr, u16>>,
-    /// The map of solution commitments to their last seen timestamp.
-    seen_outbound_solutions: RwLock<LinkedHashMap<SolutionKey<N>, OffsetDateTime>>,
-    /// The map of transaction IDs to their last seen timestamp.
-    seen_outbound_transactions: RwLock<LinkedHashMap<TransactionKey<N>, OffsetDateTime>>,
+    /// The network.
+    _phantom: PhantomData<N>,
 }
 
 impl<N: Network> Default for Cache<N> {
@@ -71,12 +58,9 @@ impl<N: Network> Cache<N> {
             seen_inbound_connections: Default::default(),
             seen_inbound_messages: Default::default(),
             seen_inbound_puzzle_requests: Default::default(),
-            seen_inbound_solutions: RwLock::new(LinkedHashMap::with_capacity(MAX_CACHE_SIZE)),
-            seen_inbound_transactions: RwLock::new(LinkedHashMap::with_capacity(MAX_CACHE_SIZE)),
             seen_outbound_block_requests: Default::default(),
             seen_outbound_puzzle_requests: Default::default(),
-            seen_outbound_solutions: RwLock::new(LinkedHashMap::with_capacity(MAX_CACHE_SIZE)),
-            seen_outbound_transactions: RwLock::new(LinkedHashMap::with_capacity(MAX_CACHE_SIZE)),
+            _phantom: PhantomData,
         }
     }
 }
@@ -96,24 +80,6 @@ impl<N: Network> Cache<N> {
     pub fn insert_inbound_puzzle_request(&self, peer_ip: SocketAddr) -> usize {
         Self::retain_and_insert(&self.seen_inbound_puzzle_requests, peer_ip, 60)
     }
-
-    /// Inserts a solution commitment into the cache, returning the previously seen timestamp if it existed.
-    pub fn insert_inbound_solution(
-        &self,
-        peer_ip: SocketAddr,
-        solution: PuzzleCommitment<N>,
-    ) -> Option<OffsetDateTime> {
-        Self::refresh_and_insert(&self.seen_inbound_solutions, (peer_ip, solution))
-    }
-
-    /// Inserts a transaction ID into the cache, returning the previously seen timestamp if it existed.
-    pub fn insert_inbound_transaction(
-        &self,
-        peer_ip: SocketAddr,
-        transaction: N::TransactionID,
-    ) -> Option<OffsetDateTime> {
-        Self::refresh_and_insert(&self.seen_inbound_transactions, (peer_ip, transaction))
-    }
 }
 
 impl<N: Network> Cache<N> {
@@ -150,24 +116,6 @@ impl<N: Network> Cache<N> {
     pub fn decrement_outbound_puzzle_requests(&self, peer_ip: SocketAddr) -> u16 {
         Self::decrement_counter(&self.seen_outbound_puzzle_requests, peer_ip)
     }
-
-    /// Inserts a solution commitment into the cache, returning the previously seen timestamp if it existed.
-    pub fn insert_outbound_solution(
-        &self,
-        peer_ip: SocketAddr,
-        solution: PuzzleCommitment<N>,
-    ) -> Option<OffsetDateTime> {
-        Self::refresh_and_insert(&self.seen_outbound_solutions, (peer_ip, solution))
-    }
-
-    /// Inserts a transaction ID into the cache, returning the previously seen timestamp if it existed.
-    pub fn insert_outbound_transaction(
-        &self,
-        peer_ip: SocketAddr,
-        transaction: N::TransactionID,
-    ) -> Option<OffsetDateTime> {
-        Self::refresh_and_insert(&self.seen_outbound_transactions, (peer_ip, transaction))
-    }
 }
 
 impl<N: Network> Cache<N> {
@@ -212,120 +160,4 @@ impl<N: Network> Cache<N> {
         // Return the updated counter.
         *entry
     }
-
-    /// Updates the map by enforcing the maximum cache size.
-    fn refresh<K: Eq + Hash, V>(map: &RwLock<LinkedHashMap<K, V>>) {
-        let mut map_write = map.write();
-        while map_write.len() >= MAX_CACHE_SIZE {
-            map_write.pop_front();
-        }
-    }
-
-    /// Updates the map by enforcing the maximum cache size, and inserts the given key.
-    /// Returns the previously seen timestamp if it existed.
-    fn refresh_and_insert<K: Eq + Hash>(
-        map: &RwLock<LinkedHashMap<K, OffsetDateTime>>,
-        key: K,
-    ) -> Option<OffsetDateTime> {
-        Self::refresh(map);
-        map.write().insert(key, OffsetDateTime::now_utc())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use snarkvm::prelude::Testnet3;
-
-    use std::net::Ipv4Addr;
-
-    type CurrentNetwork = Testnet3;
-
-    #[test]
-    fn test_inbound_solution() {
-        let cache = Cache::<CurrentNetwork>::default();
-        let peer_ip = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234);
-        let solution = PuzzleCommitment::<CurrentNetwork>::default();
-
-        // Check that the cache is empty.
-        assert_eq!(cache.seen_inbound_solutions.read().len(), 0);
-
-        // Insert a solution.
-        assert!(cache.insert_inbound_solution(peer_ip, solution).is_none());
-
-        // Check that the cache contains the solution.
-        assert_eq!(cache.seen_inbound_solutions.read().len(), 1);
-
-        // Insert the same solution again.
-        assert!(cache.insert_inbound_solution(peer_ip, solution).is_some());
-
-        // Check that the cache still contains the solution.
-        assert_eq!(cache.seen_inbound_solutions.read().len(), 1);
-    }
-
-    #[test]
-    fn test_inbound_transaction() {
-        let cache = Cache::<CurrentNetwork>::default();
-        let peer_ip = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234);
-        let transaction = Default::default();
-
-        // Check that the cache is empty.
-        assert_eq!(cache.seen_inbound_transactions.read().len(), 0);
-
-        // Insert a transaction.
-        assert!(cache.insert_inbound_transaction(peer_ip, transaction).is_none());
-
-        // Check that the cache contains the transaction.
-        assert_eq!(cache.seen_inbound_transactions.read().len(), 1);
-
-        // Insert the same transaction again.
-        assert!(cache.insert_inbound_transaction(peer_ip, transaction).is_some());
-
-        // Check that the cache still contains the transaction.
-        assert_eq!(cache.seen_inbound_transactions.read().len(), 1);
-    }
-
-    #[test]
-    fn test_outbound_solution() {
-        let cache = Cache::<CurrentNetwork>::default();
-        let peer_ip = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234);
-        let solution = PuzzleCommitment::<CurrentNetwork>::default();
-
-        // Check that the cache is empty.
-        assert_eq!(cache.seen_outbound_solutions.read().len(), 0);
-
-        // Insert a solution.
-        assert!(cache.insert_outbound_solution(peer_ip, solution).is_none());
-
-        // Check that the cache contains the solution.
-        assert_eq!(cache.seen_outbound_solutions.read().len(), 1);
-
-        // Insert the same solution again.
-        assert!(cache.insert_outbound_solution(peer_ip, solution).is_some());
-
-        // Check that the cache still contains the solution.
-        assert_eq!(cache.seen_outbound_solutions.read().len(), 1);
-    }
-
-    #[test]
-    fn test_outbound_transaction() {
-        let cache = Cache::<CurrentNetwork>::default();
-        let peer_ip = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234);
-        let transaction = Default::default();
-
-        // Check that the cache is empty.
-        assert_eq!(cache.seen_outbound_transactions.read().len(), 0);
-
-        // Insert a transaction.
-        assert!(cache.insert_outbound_transaction(peer_ip, transaction).is_none());
-
-        // Check that the cache contains the transaction.
-        assert_eq!(cache.seen_outbound_transactions.read().len(), 1);
-
-        // Insert the same transaction again.
-        assert!(cache.insert_outbound_transaction(peer_ip, transaction).is_some());
-
-        // Check that the cache still contains the transaction.
-        assert_eq!(cache.seen_outbound_transactions.read().len(), 1);
-    }
 }
@@ -0,0 +1,337 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_messages::UnconfirmedTransaction;
+use snarkvm::prelude::Network;
+
+use anyhow::{bail, ensure, Result};
+use core::{fmt, str::FromStr};
+use parking_lot::{Mutex, RwLock};
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, net::SocketAddr};
+use tokio::time::{Duration, Instant};
+
+/// The default number of peers to which a local transaction is first sent.
+pub const DEFAULT_FIRST_HOPS: usize = 2;
+/// The default mean of the delay before a local transaction is sent to each first hop, in milliseconds.
+pub const DEFAULT_MEAN_DELAY_IN_MS: u64 = 250;
+/// The default minimum embargo before a local transaction is broadcast to every peer, in milliseconds.
+pub const DEFAULT_MIN_EMBARGO_IN_MS: u64 = 5_000;
+/// The default maximum embargo before a local transaction is broadcast to every peer, in milliseconds.
+pub const DEFAULT_MAX_EMBARGO_IN_MS: u64 = 15_000;
+/// The maximum number of local transactions under embargo, beyond which local transactions are broadcast immediately.
+const MAX_EMBARGOED_TRANSACTIONS: usize = 1_024;
+
+/// Whether the origin of the local transactions is hidden from the network.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Privacy {
+    /// Local transactions are first sent to a few random peers, and broadcast after an embargo.
+    #[default]
+    On,
+    /// Local transactions are broadcast to every peer immediately.
+    Off,
+}
+
+impl FromStr for Privacy {
+    type Err = anyhow::Error;
+
+    fn from_str(privacy: &str) -> Result<Self, Self::Err> {
+        match privacy {
+            "on" => Ok(Self::On),
+            "off" => Ok(Self::Off),
+            _ => bail!("Invalid privacy setting '{privacy}' (expected 'on' or 'off')"),
+        }
+    }
+}
+
+impl fmt::Display for Privacy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::On => "on",
+            Self::Off => "off",
+        })
+    }
+}
+
+/// The settings of the diffusion of the local transactions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DiffusionConfig {
+    /// Whether the local transactions are diffused, or broadcast to every peer immediately.
+    pub privacy: Privacy,
+    /// The number of peers to which a local transaction is first sent.
+    pub first_hops: usize,
+    /// The mean of the exponentially distributed delay before a local transaction is sent to each first hop.
+    pub mean_delay: Duration,
+    /// The minimum embargo before a local transaction is broadcast to every peer.
+    pub min_embargo: Duration,
+    /// The maximum embargo before a local transaction is broadcast to every peer.
+    pub max_embargo: Duration,
+}
+
+impl Default for DiffusionConfig {
+    fn default() -> Self {
+        Self {
+            privacy: Privacy::On,
+            first_hops: DEFAULT_FIRST_HOPS,
+            mean_delay: Duration::from_millis(DEFAULT_MEAN_DELAY_IN_MS),
+            min_embargo: Duration::from_millis(DEFAULT_MIN_EMBARGO_IN_MS),
+            max_embargo: Duration::from_millis(DEFAULT_MAX_EMBARGO_IN_MS),
+        }
+    }
+}
+
+impl DiffusionConfig {
+    /// Ensures the settings are valid.
+    pub fn check(&self) -> Result<()> {
+        ensure!(self.first_hops > 0, "The number of first hops must be greater than 0");
+        ensure!(self.min_embargo <= self.max_embargo, "The minimum embargo must not exceed the maximum embargo");
+        Ok(())
+    }
+
+    /// Returns a random embargo, uniformly distributed between the minimum and the maximum embargo.
+    fn sample_embargo<R: Rng>(&self, rng: &mut R) -> Duration {
+        match self.min_embargo < self.max_embargo {
+            true => rng.gen_range(self.min_embargo..=self.max_embargo),
+            false => self.min_embargo,
+        }
+    }
+
+    /// Returns a random delay, exponentially distributed with the configured mean.
+    fn sample_delay<R: Rng>(&self, rng: &mut R) -> Duration {
+        // Sample from the unit interval, excluding zero, and apply the inverse of the distribution function.
+        let uniform: f64 = 1.0 - rng.gen::<f64>();
+        self.mean_delay.mul_f64(-uniform.ln())
+    }
+}
+
+/// A local transaction under embargo.
+struct Embargo<N: Network> {
+    /// The message of the transaction.
+    message: UnconfirmedTransaction<N>,
+    /// The first hops that are yet to be sent the transaction, with the time at which to send it.
+    first_hops: Vec<(SocketAddr, Instant)>,
+    /// The time at which the transaction is broadcast to every peer.
+    deadline: Instant,
+}
+
+/// The diffusion of the local transactions, which hides the node that submitted a transaction from the observers
+/// of the network. A local transaction is first sent to a few random outbound peers, each after an exponentially
+/// distributed delay, and broadcast to every peer once its randomized embargo ends, or once it is received from
+/// the network, whichever comes first.
+pub struct Diffusion<N: Network> {
+    /// The settings of the diffusion.
+    config: RwLock<DiffusionConfig>,
+    /// The local transactions under embargo.
+    embargoes: Mutex<HashMap<N::TransactionID, Embargo<N>>>,
+}
+
+impl<N: Network> Default for Diffusion<N> {
+    fn default() -> Self {
+        Self { config: Default::default(), embargoes: Default::default() }
+    }
+}
+
+impl<N: Network> Diffusion<N> {
+    /// Returns the settings of the diffusion.
+    pub fn config(&self) -> DiffusionConfig {
+        *self.config.read()
+    }
+
+    /// Sets the settings of the diffusion, which apply to the local transactions submitted afterwards.
+    pub fn set_config(&self, config: DiffusionConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Returns the number of local transactions under embargo.
+    pub fn num_embargoed(&self) -> usize {
+        self.embargoes.lock().len()
+    }
+
+    /// Returns `true` if the given local transaction is under embargo.
+    pub fn is_embargoed(&self, transaction_id: &N::TransactionID) -> bool {
+        self.embargoes.lock().contains_key(transaction_id)
+    }
+
+    /// Places the given local transaction under embargo, with random first hops among the given candidates,
+    /// and returns the first hops that are yet to be sent the transaction. If privacy is off, there are
+    /// no candidates, or too many transactions are under embargo, this returns `None`, and the transaction
+    /// must be broadcast immediately.
+    pub fn embargo<R: Rng>(
+        &self,
+        message: UnconfirmedTransaction<N>,
+        candidates: &[SocketAddr],
+        rng: &mut R,
+    ) -> Option<Vec<SocketAddr>> {
+        let config = self.config();
+        if config.privacy == Privacy::Off || candidates.is_empty() {
+            return None;
+        }
+
+        let mut embargoes = self.embargoes.lock();
+        // If the transaction is already under embargo, keep its first hops.
+        if let Some(embargo) = embargoes.get(&message.transaction_id) {
+            return Some(embargo.first_hops.iter().map(|(peer_ip, _)| *peer_ip).collect());
+        }
+        if embargoes.len() >= MAX_EMBARGOED_TRANSACTIONS {
+            return None;
+        }
+
+        // Select the first hops, and schedule each of them within the embargo.
+        let now = Instant::now();
+        let deadline = now + config.sample_embargo(rng);
+        let first_hops = candidates
+            .choose_multiple(rng, config.first_hops)
+            .map(|peer_ip| (*peer_ip, (now + config.sample_delay(rng)).min(deadline)))
+            .collect::<Vec<_>>();
+        let peer_ips = first_hops.iter().map(|(peer_ip, _)| *peer_ip).collect();
+
+        embargoes.insert(message.transaction_id, Embargo { message, first_hops, deadline });
+        Some(peer_ips)
+    }
+
+    /// Ends the embargo of the given local transaction, as it was received from the network,
+    /// and returns the transaction to broadcast, if it was under embargo.
+    pub fn end_embargo(&self, transaction_id: &N::TransactionID) -> Option<UnconfirmedTransaction<N>> {
+        self.embargoes.lock().remove(transaction_id).map(|embargo| embargo.message)
+    }
+
+    /// Returns the transactions to send to their first hops, and the transactions to broadcast
+    /// as their embargo ended, as of the given time.
+    #[allow(clippy::type_complexity)]
+    pub fn due(&self, now: Instant) -> (Vec<(SocketAddr, UnconfirmedTransaction<N>)>, Vec<UnconfirmedTransaction<N>>) {
+        let mut sends = Vec::new();
+        let mut broadcasts = Vec::new();
+
+        self.embargoes.lock().retain(|_, embargo| {
+            // Collect the first hops that are due.
+            embargo.first_hops.retain(|(peer_ip, time)| match *time <= now {
+                true => {
+                    sends.push((*peer_ip, embargo.message.clone()));
+                    false
+                }
+                false => true,
+            });
+            // Collect the transaction for the broadcast, if its embargo ended.
+            match embargo.deadline <= now {
+                true => {
+                    broadcasts.push(embargo.message.clone());
+                    false
+                }
+                false => true,
+            }
+        });
+
+        (sends, broadcasts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node_messages::Data;
+    use snarkvm::prelude::{Block, FromBytes, Testnet3};
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the message of a sample transaction.
+    fn sample_message() -> UnconfirmedTransaction<CurrentNetwork> {
+        let block = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let transaction = block.transactions().iter().next().unwrap().clone();
+        UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction))
+    }
+
+    /// Returns the given number of sample peer IPs.
+    fn sample_peers(num_peers: u16) -> Vec<SocketAddr> {
+        (0..num_peers).map(|port| SocketAddr::from(([127, 0, 0, 1], 4130 + port))).collect()
+    }
+
+    #[test]
+    fn test_privacy_parsing() {
+        assert_eq!(Privacy::default(), Privacy::On);
+        assert_eq!(Privacy::from_str("off").unwrap(), Privacy::Off);
+        assert_eq!(Privacy::from_str(&Privacy::On.to_string()).unwrap(), Privacy::On);
+        assert!(Privacy::from_str("maybe").is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_embargo_sends_to_first_hops_before_broadcast() {
+        let rng = &mut rand::thread_rng();
+        let diffusion = Diffusion::<CurrentNetwork>::default();
+        let config = diffusion.config();
+        let message = sample_message();
+
+        // Ensure only the configured number of first hops are selected, among the candidates.
+        let peers = sample_peers(8);
+        let first_hops = diffusion.embargo(message.clone(), &peers, rng).unwrap();
+        assert_eq!(first_hops.len(), config.first_hops);
+        assert!(first_hops.iter().all(|peer_ip| peers.contains(peer_ip)));
+        assert!(diffusion.is_embargoed(&message.transaction_id));
+        // Ensure a transaction is placed under embargo only once.
+        assert_eq!(diffusion.embargo(message.clone(), &peers, rng), Some(first_hops.clone()));
+        assert_eq!(diffusion.num_embargoed(), 1);
+
+        // Ensure only the first hops are sent the transaction before the embargo ends.
+        let (sends, broadcasts) = diffusion.due(Instant::now() + config.min_embargo - Duration::from_millis(1));
+        assert!(broadcasts.is_empty());
+        assert!(sends.iter().all(|(peer_ip, _)| first_hops.contains(peer_ip)));
+
+        // Ensure the transaction is broadcast once the embargo ends, and each first hop is sent the transaction once.
+        let (remaining, broadcasts) = diffusion.due(Instant::now() + config.max_embargo);
+        assert_eq!(sends.len() + remaining.len(), config.first_hops);
+        assert_eq!(broadcasts.len(), 1);
+        assert_eq!(broadcasts[0].transaction_id, message.transaction_id);
+        assert_eq!(diffusion.num_embargoed(), 0);
+    }
+
+    #[test]
+    fn test_embargo_ends_when_seen_from_network() {
+        let rng = &mut rand::thread_rng();
+        let diffusion = Diffusion::<CurrentNetwork>::default();
+        let message = sample_message();
+
+        diffusion.embargo(message.clone(), &sample_peers(3), rng).unwrap();
+        // Ensure the transaction is returned for the broadcast once, when it is seen from the network.
+        assert_eq!(diffusion.end_embargo(&message.transaction_id).unwrap().transaction_id, message.transaction_id);
+        assert!(diffusion.end_embargo(&message.transaction_id).is_none());
+        let (sends, broadcasts) = diffusion.due(Instant::now() + Duration::from_secs(3600));
+        assert!(sends.is_empty() && broadcasts.is_empty());
+    }
+
+    #[test]
+    fn test_no_embargo_without_privacy() {
+        let rng = &mut rand::thread_rng();
+        let diffusion = Diffusion::<CurrentNetwork>::default();
+
+        // Ensure there is no embargo without candidates.
+        assert!(diffusion.embargo(sample_message(), &[], rng).is_none());
+
+        // Ensure there is no embargo with privacy off.
+        diffusion.set_config(DiffusionConfig { privacy: Privacy::Off, ..Default::default() });
+        assert!(diffusion.embargo(sample_message(), &sample_peers(3), rng).is_none());
+        assert_eq!(diffusion.num_embargoed(), 0);
+    }
+
+    #[test]
+    fn test_diffusion_config_check() {
+        assert!(DiffusionConfig::default().check().is_ok());
+        assert!(DiffusionConfig { first_hops: 0, ..Default::default() }.check().is_err());
+        let min_embargo = Duration::from_secs(2);
+        assert!(DiffusionConfig { min_embargo, max_embargo: min_embargo, ..Default::default() }.check().is_ok());
+        assert!(DiffusionConfig { min_embargo, max_embargo: min_embargo / 2, ..Default::default() }.check().is_err());
+    }
+}
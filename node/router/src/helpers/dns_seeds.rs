@@ -0,0 +1,265 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_tcp::{resolve_through_proxy, ProxyConfig};
+use snarkvm::prelude::Network;
+
+use anyhow::Result;
+use indexmap::IndexSet;
+use parking_lot::{Mutex, RwLock};
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+use tokio::time::{timeout, Duration, Instant};
+
+/// The DNS seeds of Testnet3.
+// TODO: List the seed hostnames of Testnet3 once they are operated.
+const TESTNET3_DNS_SEEDS: &[&str] = &[];
+/// The port of the addresses resolved from a DNS seed, unless the seed specifies one.
+pub const DEFAULT_DNS_SEED_PORT: u16 = 4133;
+/// The maximum duration of the resolution of a DNS seed.
+pub const DNS_SEED_TIMEOUT: Duration = Duration::from_secs(5);
+/// The minimum duration in between two resolutions of the DNS seeds.
+pub const DNS_SEED_INTERVAL: Duration = Duration::from_secs(300);
+/// The maximum number of addresses accepted from each DNS seed.
+pub const MAXIMUM_ADDRESSES_PER_SEED: usize = 16;
+/// The maximum number of addresses accepted from each DNS seed in the same network group.
+pub const MAXIMUM_ADDRESSES_PER_GROUP: usize = 2;
+
+/// Returns the DNS seeds of the given network.
+pub fn default_dns_seeds<N: Network>() -> Vec<String> {
+    match N::ID {
+        3 => TESTNET3_DNS_SEEDS.iter().map(|host| host.to_string()).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the network group of the given address, which is its `/16` prefix for IPv4, and its `/32` prefix for IPv6.
+pub fn network_group(addr: &SocketAddr) -> IpAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) => IpAddr::V4(Ipv4Addr::from(u32::from(ip) & 0xffff_0000)),
+        IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & (u128::from(u32::MAX) << 96))),
+    }
+}
+
+/// A resolver of the hostnames of the DNS seeds.
+#[async_trait]
+pub trait SeedResolver: Send + Sync {
+    /// Returns the addresses of the given hostname, with the given port.
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>>;
+}
+
+/// The resolver of the operating system.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemResolver;
+
+#[async_trait]
+impl SeedResolver for SystemResolver {
+    /// Returns the addresses of the given hostname, with the given port.
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        Ok(tokio::net::lookup_host((host, port)).await?.collect())
+    }
+}
+
+/// The resolver of the proxy, which resolves the hostnames through Tor, so that the resolution is not leaked.
+#[derive(Clone, Debug)]
+pub struct ProxyResolver(pub ProxyConfig);
+
+#[async_trait]
+impl SeedResolver for ProxyResolver {
+    /// Returns the address of the given hostname, with the given port.
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        Ok(vec![SocketAddr::new(resolve_through_proxy(&self.0, host).await?, port)])
+    }
+}
+
+/// The DNS seeds, which are resolved into candidate peers when the node starts and when it runs out of candidate peers.
+///
+/// A DNS seed is not trusted: the addresses accepted from each seed are capped in number and in network group,
+/// and are chosen at random among its results, while the peers sourced from the seeds are tagged, so that they
+/// never fill more than half of the outbound connections.
+pub struct DnsSeeds {
+    /// The hostnames of the DNS seeds, each with an optional port.
+    hosts: RwLock<Vec<String>>,
+    /// The resolver of the hostnames.
+    resolver: RwLock<Arc<dyn SeedResolver>>,
+    /// The addresses that were sourced from the DNS seeds.
+    seed_peers: RwLock<IndexSet<SocketAddr>>,
+    /// The time of the latest resolution, if the seeds were resolved.
+    last_resolution: Mutex<Option<Instant>>,
+}
+
+impl Default for DnsSeeds {
+    /// Initializes the DNS seeds, without any hostname.
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl fmt::Debug for DnsSeeds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsSeeds").field("hosts", &*self.hosts.read()).finish()
+    }
+}
+
+impl DnsSeeds {
+    /// Initializes the DNS seeds with the given hostnames, which are resolved by the operating system.
+    pub fn new(hosts: Vec<String>) -> Self {
+        Self {
+            hosts: RwLock::new(hosts),
+            resolver: RwLock::new(Arc::new(SystemResolver)),
+            seed_peers: Default::default(),
+            last_resolution: Default::default(),
+        }
+    }
+
+    /// Returns the hostnames of the DNS seeds.
+    pub fn hosts(&self) -> Vec<String> {
+        self.hosts.read().clone()
+    }
+
+    /// Sets the hostnames of the DNS seeds.
+    pub fn set_hosts(&self, hosts: Vec<String>) {
+        *self.hosts.write() = hosts;
+    }
+
+    /// Sets the resolver of the hostnames.
+    pub fn set_resolver(&self, resolver: Arc<dyn SeedResolver>) {
+        *self.resolver.write() = resolver;
+    }
+
+    /// Returns `true` if the given address was sourced from the DNS seeds.
+    pub fn is_seed_peer(&self, peer_ip: &SocketAddr) -> bool {
+        self.seed_peers.read().contains(peer_ip)
+    }
+
+    /// Returns the addresses that were sourced from the DNS seeds.
+    pub fn seed_peers(&self) -> Vec<SocketAddr> {
+        self.seed_peers.read().iter().copied().collect()
+    }
+
+    /// Tags the given addresses as sourced from the DNS seeds.
+    pub fn insert_seed_peers(&self, peer_ips: &[SocketAddr]) {
+        self.seed_peers.write().extend(peer_ips.iter().copied());
+    }
+
+    /// Returns `true` if the DNS seeds are due for a resolution, and records the resolution if so.
+    pub fn begin_resolution(&self) -> bool {
+        let mut last_resolution = self.last_resolution.lock();
+        match *last_resolution {
+            Some(instant) if instant.elapsed() < DNS_SEED_INTERVAL => false,
+            _ => {
+                *last_resolution = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Forgets the latest resolution, so that the next resolution is not held back by `DNS_SEED_INTERVAL`.
+    pub fn reset_resolution(&self) {
+        *self.last_resolution.lock() = None;
+    }
+
+    /// Resolves the DNS seeds, and returns the addresses accepted from them.
+    /// A seed that fails or does not respond within `DNS_SEED_TIMEOUT` is skipped.
+    pub async fn resolve(&self) -> Vec<SocketAddr> {
+        let resolver = self.resolver.read().clone();
+        let mut addresses = IndexSet::new();
+        for seed in self.hosts() {
+            // Split the port from the hostname, if one is specified.
+            let (host, port) = match seed.rsplit_once(':').map(|(host, port)| (host, port.parse::<u16>())) {
+                Some((host, Ok(port))) => (host.to_string(), port),
+                _ => (seed.clone(), DEFAULT_DNS_SEED_PORT),
+            };
+            // Resolve the hostname, within the timeout.
+            match timeout(DNS_SEED_TIMEOUT, resolver.resolve(&host, port)).await {
+                Ok(Ok(results)) => {
+                    let accepted = select_addresses(results, &mut rand::thread_rng());
+                    debug!("Resolved {} addresses from the DNS seed '{seed}'", accepted.len());
+                    addresses.extend(accepted);
+                }
+                Ok(Err(error)) => warn!("Failed to resolve the DNS seed '{seed}' - {error}"),
+                Err(_) => warn!("Failed to resolve the DNS seed '{seed}' - timed out"),
+            }
+        }
+        addresses.into_iter().collect()
+    }
+}
+
+/// Returns a random subset of the given addresses from a DNS seed, with at most `MAXIMUM_ADDRESSES_PER_SEED`
+/// addresses, and at most `MAXIMUM_ADDRESSES_PER_GROUP` addresses in each network group.
+pub fn select_addresses<R: Rng>(mut addresses: Vec<SocketAddr>, rng: &mut R) -> Vec<SocketAddr> {
+    // Randomize the order of the addresses, so that the seed does not choose the accepted addresses.
+    addresses.sort_unstable();
+    addresses.dedup();
+    addresses.shuffle(rng);
+
+    let mut groups = HashMap::<IpAddr, usize>::new();
+    addresses
+        .into_iter()
+        .filter(|addr| !addr.ip().is_unspecified() && addr.port() != 0)
+        .filter(|addr| {
+            // Ensure the network group of the address is not full.
+            let count = groups.entry(network_group(addr)).or_default();
+            *count += 1;
+            *count <= MAXIMUM_ADDRESSES_PER_GROUP
+        })
+        .take(MAXIMUM_ADDRESSES_PER_SEED)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_group() {
+        let group = |addr: &str| network_group(&addr.parse().unwrap());
+        assert_eq!(group("1.2.3.4:4133"), group("1.2.200.100:4130"));
+        assert_ne!(group("1.2.3.4:4133"), group("1.3.3.4:4133"));
+        assert_eq!(group("[2001:db8::1]:4133"), group("[2001:db8:ffff::2]:4133"));
+        assert_ne!(group("[2001:db8::1]:4133"), group("[2001:db9::1]:4133"));
+    }
+
+    #[test]
+    fn test_select_addresses() {
+        let rng = &mut rand::thread_rng();
+
+        // Ensure the addresses in the same network group are capped.
+        let same_group = (1..=10).map(|i| SocketAddr::from(([10, 0, 0, i], 4133))).collect::<Vec<_>>();
+        let selected = select_addresses(same_group.clone(), rng);
+        assert_eq!(selected.len(), MAXIMUM_ADDRESSES_PER_GROUP);
+        assert!(selected.iter().all(|addr| same_group.contains(addr)));
+
+        // Ensure the addresses of a seed are capped, and are chosen among its results.
+        let distinct = (1..=100).map(|i| SocketAddr::from(([10, i, 0, 1], 4133))).collect::<Vec<_>>();
+        let selected = select_addresses(distinct.clone(), rng);
+        assert_eq!(selected.len(), MAXIMUM_ADDRESSES_PER_SEED);
+        assert!(selected.iter().all(|addr| distinct.contains(addr)));
+        assert_ne!(selected, distinct[..MAXIMUM_ADDRESSES_PER_SEED]);
+
+        // Ensure the duplicate and invalid addresses are dropped.
+        let invalid = vec![SocketAddr::from(([10, 0, 0, 1], 4133)); 5]
+            .into_iter()
+            .chain([SocketAddr::from(([0, 0, 0, 0], 4133)), SocketAddr::from(([10, 1, 0, 1], 0))])
+            .collect();
+        assert_eq!(select_addresses(invalid, rng), vec![SocketAddr::from(([10, 0, 0, 1], 4133))]);
+    }
+}
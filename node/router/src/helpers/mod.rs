@@ -17,11 +17,47 @@
 mod cache;
 pub use cache::Cache;
 
+mod diffusion;
+pub use diffusion::*;
+
+mod dns_seeds;
+pub use dns_seeds::*;
+
+mod noise;
+pub use noise::*;
+
+mod orphans;
+pub use orphans::*;
+
 mod peer;
 pub use peer::*;
 
+mod peer_book;
+pub use peer_book::*;
+
+mod peer_state;
+pub use peer_state::*;
+
+mod peer_policy;
+pub use peer_policy::{BannedPeer, PeerPolicy, PinnedPeer, PolicyEntryResult, PolicyEntryStatus};
+
+mod pipeline;
+pub use pipeline::*;
+
+mod relay;
+pub use relay::*;
+
 mod resolver;
 pub(crate) use resolver::*;
 
+mod snapshot;
+pub use snapshot::*;
+
+mod storage;
+pub use storage::*;
+
 mod sync;
 pub use sync::*;
+
+mod transport;
+pub use transport::*;
@@ -0,0 +1,316 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_messages::{MessageOrBytes, NoiseCodec, NoiseState, PeerCodec, NOISE_PARAMS};
+use snarkos_node_tcp::ConnectionSide;
+use snarkvm::prelude::{error, Network};
+
+use anyhow::{bail, ensure, Result};
+use bytes::Bytes;
+use futures::SinkExt;
+use snow::{
+    params::NoiseParams,
+    resolvers::{CryptoResolver, DefaultResolver},
+    Builder,
+    Keypair,
+};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+use time::OffsetDateTime;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+/// The size of a static key in bytes.
+pub(crate) const KEY_SIZE: usize = 32;
+
+/// The transport encryption configuration of the router.
+pub struct NoiseConfig {
+    /// The static keypair of the node.
+    keypair: Keypair,
+    /// The static public keys pinned for the trusted peers.
+    pinned_keys: HashMap<SocketAddr, Vec<u8>>,
+    /// If `true`, peers without transport encryption are accepted (transition mode).
+    allow_plaintext: bool,
+}
+
+impl NoiseConfig {
+    /// Initializes a new configuration with the given static keypair.
+    pub fn new(keypair: Keypair) -> Self {
+        Self { keypair, pinned_keys: Default::default(), allow_plaintext: false }
+    }
+
+    /// Pins the given static public keys for the given peers.
+    pub fn with_pinned_keys(mut self, pinned_keys: HashMap<SocketAddr, Vec<u8>>) -> Self {
+        self.pinned_keys = pinned_keys;
+        self
+    }
+
+    /// Sets whether peers without transport encryption are accepted.
+    pub fn with_plaintext_peers(mut self, allow_plaintext: bool) -> Self {
+        self.allow_plaintext = allow_plaintext;
+        self
+    }
+
+    /// Returns the static private key of the node.
+    pub(crate) fn private_key(&self) -> &[u8] {
+        &self.keypair.private
+    }
+
+    /// Returns the static public key of the node.
+    pub fn public_key(&self) -> &[u8] {
+        &self.keypair.public
+    }
+
+    /// Returns the static public key pinned for the given peer IP, if any.
+    pub fn pinned_key(&self, peer_ip: &SocketAddr) -> Option<&[u8]> {
+        self.pinned_keys.get(peer_ip).map(|key| key.as_slice())
+    }
+
+    /// Returns the static public keys pinned for the trusted peers.
+    pub fn pinned_keys(&self) -> &HashMap<SocketAddr, Vec<u8>> {
+        &self.pinned_keys
+    }
+
+    /// Returns `true` if peers without transport encryption are accepted.
+    pub fn allows_plaintext(&self) -> bool {
+        self.allow_plaintext
+    }
+}
+
+/// Generates a new static keypair.
+pub fn generate_keypair() -> Result<Keypair> {
+    Ok(Builder::new(NOISE_PARAMS.parse()?).generate_keypair()?)
+}
+
+/// Loads the static keypair from the given file, which must only be accessible by its owner.
+pub fn load_keypair(path: &Path) -> Result<Keypair> {
+    // Ensure the keypair is not exposed to the other users, before reading it.
+    let metadata = std::fs::metadata(path)?;
+    ensure!(metadata.is_file(), "The keypair file '{}' is not a file", path.display());
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode() & 0o777;
+        ensure!(
+            mode & 0o077 == 0,
+            "The keypair file '{}' is accessible by other users (mode {mode:o}), restrict it to its owner (mode 600)",
+            path.display()
+        );
+    }
+
+    // Load the keypair, which is stored as the private key followed by the public key.
+    let bytes = std::fs::read(path)?;
+    ensure!(bytes.len() == 2 * KEY_SIZE, "The keypair file '{}' is malformed", path.display());
+    let (private, public) = bytes.split_at(KEY_SIZE);
+    // Ensure the public key is the one of the private key, as the node ID is derived from the public key.
+    ensure!(
+        derive_public_key(private)? == public,
+        "The public key in the keypair file '{}' does not match its private key",
+        path.display()
+    );
+    Ok(Keypair { private: private.to_vec(), public: public.to_vec() })
+}
+
+/// Returns the static public key of the given static private key.
+fn derive_public_key(private_key: &[u8]) -> Result<Vec<u8>> {
+    let params: NoiseParams = NOISE_PARAMS.parse()?;
+    let mut dh = match DefaultResolver.resolve_dh(&params.dh) {
+        Some(dh) => dh,
+        None => bail!("The key exchange {:?} is unsupported", params.dh),
+    };
+    dh.set(private_key);
+    Ok(dh.pubkey().to_vec())
+}
+
+/// Loads the static keypair from the given file, or generates and saves a new one if the file does not exist.
+pub fn load_or_generate_keypair(path: &Path) -> Result<Keypair> {
+    match path.exists() {
+        true => load_keypair(path),
+        false => generate_and_save_keypair(path),
+    }
+}
+
+/// Archives the static keypair in the given file, if any, and generates and saves a new one in its place.
+/// Returns the new keypair, along with the path of the archived keypair file, i.e. `{path}.{timestamp}.bak`.
+pub fn rotate_keypair(path: &Path) -> Result<(Keypair, Option<PathBuf>)> {
+    let archive_path = match path.exists() {
+        true => {
+            // Ensure the archived keypair is a valid one, so that a malformed file is not silently discarded.
+            load_keypair(path)?;
+            let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+            file_name.push(format!(".{}.bak", OffsetDateTime::now_utc().unix_timestamp()));
+            let archive_path = path.with_file_name(file_name);
+            ensure!(!archive_path.exists(), "The archived keypair file '{}' already exists", archive_path.display());
+            std::fs::rename(path, &archive_path)?;
+            Some(archive_path)
+        }
+        false => None,
+    };
+    Ok((generate_and_save_keypair(path)?, archive_path))
+}
+
+/// Generates a new static keypair, and saves it in the given file.
+fn generate_and_save_keypair(path: &Path) -> Result<Keypair> {
+    // Generate a new keypair.
+    let keypair = generate_keypair()?;
+
+    // Save the keypair in a temporary file, which is only accessible by the owner from its creation.
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    let temp_path = path.with_file_name(file_name);
+    // Remove the temporary file of an interrupted save, if any.
+    match std::fs::remove_file(&temp_path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+        _ => (),
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp_path)?;
+    file.write_all(&[keypair.private.as_slice(), keypair.public.as_slice()].concat())?;
+    file.sync_all()?;
+    drop(file);
+
+    // Move the temporary file in place, so that the keypair file is never partially written.
+    std::fs::rename(&temp_path, path)?;
+    Ok(keypair)
+}
+
+/// Performs the Noise XX handshake on the given stream with the given static private key,
+/// and returns the stream in transport mode. The `node_side` is the connection side of this node.
+pub async fn noise_handshake<N: Network, T: AsyncRead + AsyncWrite + Unpin>(
+    framed: Framed<T, PeerCodec<N>>,
+    private_key: &[u8],
+    node_side: ConnectionSide,
+) -> io::Result<Framed<T, PeerCodec<N>>> {
+    // Initialize the handshake state.
+    let params = NOISE_PARAMS.parse().map_err(|e| error(format!("{e}")))?;
+    let builder = Builder::new(params).local_private_key(private_key);
+    let noise_state = match node_side {
+        ConnectionSide::Initiator => builder.build_initiator(),
+        ConnectionSide::Responder => builder.build_responder(),
+    }
+    .map_err(|e| error(format!("{e}")))?;
+
+    // Switch the stream to the Noise codec, retaining any buffered bytes.
+    let mut framed = framed.map_codec(|_| NoiseCodec::new(NoiseState::Handshake(Box::new(noise_state))));
+
+    match node_side {
+        ConnectionSide::Initiator => {
+            // -> e
+            framed.send(MessageOrBytes::Bytes(Bytes::new())).await?;
+            // <- e, ee, s, es
+            expect_noise_message(&mut framed).await?;
+            // -> s, se
+            framed.send(MessageOrBytes::Bytes(Bytes::new())).await?;
+        }
+        ConnectionSide::Responder => {
+            // -> e
+            expect_noise_message(&mut framed).await?;
+            // <- e, ee, s, es
+            framed.send(MessageOrBytes::Bytes(Bytes::new())).await?;
+            // -> s, se
+            expect_noise_message(&mut framed).await?;
+        }
+    }
+
+    // Ensure the handshake is finished, and switch the stream to transport mode.
+    if !framed.codec().noise_state().is_handshake_finished() {
+        return Err(error("The Noise handshake did not finish"));
+    }
+    Ok(framed.map_codec(|codec| PeerCodec::Encrypted(codec.into_post_handshake())))
+}
+
+/// Receives the next Noise handshake message from the given stream.
+async fn expect_noise_message<N: Network, T: AsyncRead + AsyncWrite + Unpin>(
+    framed: &mut Framed<T, NoiseCodec<N>>,
+) -> io::Result<()> {
+    match framed.try_next().await? {
+        Some(MessageOrBytes::Bytes(_)) => Ok(()),
+        Some(MessageOrBytes::Message(message)) => {
+            Err(error(format!("Received {:?} during the Noise handshake", message.name())))
+        }
+        None => Err(error("Disconnected during the Noise handshake")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_rotate_keypair() {
+        let directory = std::env::temp_dir().join(format!("noise-keypair-{}", std::process::id()));
+        let path = directory.join("noise.key");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        // Ensure the keypair is generated once, and reloaded afterwards.
+        let keypair = load_or_generate_keypair(&path).unwrap();
+        assert_eq!(load_or_generate_keypair(&path).unwrap().public, keypair.public);
+        assert_eq!(load_keypair(&path).unwrap().private, keypair.private);
+
+        // Ensure a rotation archives the keypair, and replaces it with a new one.
+        let (rotated, archive_path) = rotate_keypair(&path).unwrap();
+        let archive_path = archive_path.unwrap();
+        assert_ne!(rotated.public, keypair.public);
+        assert_eq!(load_keypair(&path).unwrap().public, rotated.public);
+        assert_eq!(load_keypair(&archive_path).unwrap().public, keypair.public);
+
+        // Ensure a keypair that is accessible by the other users is rejected.
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(load_keypair(&path).is_err());
+            assert!(load_or_generate_keypair(&path).is_err());
+            assert!(rotate_keypair(&path).is_err());
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+            assert_eq!(load_keypair(&path).unwrap().public, rotated.public);
+        }
+
+        // Ensure the keypair file is only accessible by its owner.
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert!(!directory.join("noise.key.tmp").exists());
+        }
+
+        // Ensure a keypair whose public key does not match its private key is rejected.
+        std::fs::write(&path, [rotated.private.as_slice(), keypair.public.as_slice()].concat()).unwrap();
+        let error = load_keypair(&path).unwrap_err();
+        assert!(error.to_string().contains("does not match its private key"));
+        assert!(load_or_generate_keypair(&path).is_err());
+
+        // Ensure a malformed keypair is rejected.
+        std::fs::write(&path, [0u8; KEY_SIZE]).unwrap();
+        assert!(load_keypair(&path).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_messages::UnconfirmedTransaction;
+use snarkvm::prelude::{Network, Transaction};
+
+use anyhow::{bail, Result};
+use indexmap::{IndexMap, IndexSet};
+use parking_lot::RwLock;
+use std::net::SocketAddr;
+use tokio::time::{Duration, Instant};
+
+/// The maximum number of orphan transactions held by the node.
+pub const MAX_ORPHAN_TRANSACTIONS: usize = 512;
+/// The maximum number of orphan transactions held for each peer.
+pub const MAX_ORPHANS_PER_PEER: usize = 32;
+/// The maximum number of outstanding parent requests to each peer.
+pub const MAX_PARENT_REQUESTS_PER_PEER: usize = 16;
+/// The duration in seconds after which an orphan transaction, or a parent request, is dropped.
+pub const ORPHAN_TIMEOUT_IN_SECS: u64 = 30;
+
+/// A transaction relayed by a peer before its parents.
+#[derive(Clone, Debug)]
+pub struct Orphan<N: Network> {
+    /// The IP of the peer that relayed the transaction.
+    pub peer_ip: SocketAddr,
+    /// The message of the transaction, as it was received.
+    pub message: UnconfirmedTransaction<N>,
+    /// The (deserialized) transaction.
+    pub transaction: Transaction<N>,
+    /// The IDs of the parents that this node is yet to receive.
+    missing: IndexSet<N::TransactionID>,
+    /// The time at which the transaction was received.
+    timestamp: Instant,
+}
+
+/// The orphan pool, which holds the transactions relayed before their parents, while their parents are fetched
+/// from the peers that relayed them.
+pub struct OrphanTransactions<N: Network> {
+    /// The map of the IDs of the orphan transactions to the orphans.
+    orphans: RwLock<IndexMap<N::TransactionID, Orphan<N>>>,
+    /// The map of the IDs of the requested parents to the peer they were requested from, and the request time.
+    requests: RwLock<IndexMap<N::TransactionID, (SocketAddr, Instant)>>,
+}
+
+impl<N: Network> Default for OrphanTransactions<N> {
+    fn default() -> Self {
+        Self { orphans: Default::default(), requests: Default::default() }
+    }
+}
+
+impl<N: Network> OrphanTransactions<N> {
+    /// Returns the number of orphan transactions.
+    pub fn len(&self) -> usize {
+        self.orphans.read().len()
+    }
+
+    /// Returns `true` if there are no orphan transactions.
+    pub fn is_empty(&self) -> bool {
+        self.orphans.read().is_empty()
+    }
+
+    /// Returns `true` if the given transaction is an orphan.
+    pub fn contains(&self, transaction_id: &N::TransactionID) -> bool {
+        self.orphans.read().contains_key(transaction_id)
+    }
+
+    /// Returns the number of outstanding parent requests to the given peer.
+    pub fn num_requests(&self, peer_ip: &SocketAddr) -> usize {
+        self.requests.read().values().filter(|(ip, _)| ip == peer_ip).count()
+    }
+
+    /// Inserts the given transaction, relayed by the given peer before the given parents, and returns the IDs of
+    /// the parents to request from the peer, which excludes the parents already requested from any peer.
+    ///
+    /// This fails if the orphan pool is full, or if the peer exceeds its orphans or its outstanding requests.
+    pub fn insert(
+        &self,
+        peer_ip: SocketAddr,
+        message: UnconfirmedTransaction<N>,
+        transaction: Transaction<N>,
+        missing: Vec<N::TransactionID>,
+    ) -> Result<Vec<N::TransactionID>> {
+        // Drop the orphans and the requests that timed out.
+        self.expire(Instant::now());
+
+        let mut orphans = self.orphans.write();
+        let mut requests = self.requests.write();
+        // If the transaction is already an orphan, its parents are already requested.
+        if orphans.contains_key(&message.transaction_id) {
+            return Ok(vec![]);
+        }
+        // Ensure the orphan pool is not full.
+        if orphans.len() >= MAX_ORPHAN_TRANSACTIONS {
+            bail!("The orphan pool is full ({MAX_ORPHAN_TRANSACTIONS} transactions)")
+        }
+        // Ensure the peer does not exceed its orphans.
+        let num_orphans = orphans.values().filter(|orphan| orphan.peer_ip == peer_ip).count();
+        if num_orphans >= MAX_ORPHANS_PER_PEER {
+            bail!("Peer '{peer_ip}' has {num_orphans} orphan transactions (maximum is {MAX_ORPHANS_PER_PEER})")
+        }
+        // Ensure the peer does not exceed its outstanding requests.
+        let missing = missing.into_iter().collect::<IndexSet<_>>();
+        let to_request = missing.iter().filter(|id| !requests.contains_key(*id)).copied().collect::<Vec<_>>();
+        let num_requests = requests.values().filter(|(ip, _)| *ip == peer_ip).count();
+        if num_requests + to_request.len() > MAX_PARENT_REQUESTS_PER_PEER {
+            let maximum = MAX_PARENT_REQUESTS_PER_PEER;
+            bail!("Peer '{peer_ip}' has {num_requests} outstanding parent requests (maximum is {maximum})")
+        }
+
+        let now = Instant::now();
+        requests.extend(to_request.iter().map(|id| (*id, (peer_ip, now))));
+        orphans.insert(message.transaction_id, Orphan { peer_ip, message, transaction, missing, timestamp: now });
+        Ok(to_request)
+    }
+
+    /// Marks the given parent as received, and removes and returns the orphans that are no longer missing
+    /// any parent, in order of arrival.
+    pub fn resolve(&self, parent_id: &N::TransactionID) -> Vec<Orphan<N>> {
+        self.requests.write().shift_remove(parent_id);
+
+        let mut orphans = self.orphans.write();
+        let mut ready = Vec::new();
+        orphans.retain(|_, orphan| {
+            orphan.missing.shift_remove(parent_id);
+            match orphan.missing.is_empty() {
+                true => {
+                    ready.push(orphan.clone());
+                    false
+                }
+                false => true,
+            }
+        });
+        ready
+    }
+
+    /// Removes the orphans relayed by the given peer, and the parent requests sent to it.
+    pub fn remove_peer(&self, peer_ip: &SocketAddr) {
+        self.orphans.write().retain(|_, orphan| orphan.peer_ip != *peer_ip);
+        self.requests.write().retain(|_, (ip, _)| ip != peer_ip);
+    }
+
+    /// Removes the orphans and the parent requests older than `ORPHAN_TIMEOUT_IN_SECS`, as of the given time.
+    pub fn expire(&self, now: Instant) {
+        let timeout = Duration::from_secs(ORPHAN_TIMEOUT_IN_SECS);
+        self.orphans.write().retain(|_, orphan| now.saturating_duration_since(orphan.timestamp) < timeout);
+        self.requests.write().retain(|_, (_, timestamp)| now.saturating_duration_since(*timestamp) < timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node_messages::Data;
+    use snarkvm::prelude::{Block, Field, FromBytes, Testnet3, Uniform};
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a sample transaction, along with its message.
+    fn sample_orphan() -> (UnconfirmedTransaction<CurrentNetwork>, Transaction<CurrentNetwork>) {
+        let block = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let transaction = block.transactions().iter().next().unwrap().clone();
+        (UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction.clone())), transaction)
+    }
+
+    /// Returns the given number of random transaction IDs.
+    fn sample_ids(num_ids: usize) -> Vec<<CurrentNetwork as Network>::TransactionID> {
+        let rng = &mut rand::thread_rng();
+        (0..num_ids).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect()
+    }
+
+    #[test]
+    fn test_orphan_resolved_by_its_parents() {
+        let orphans = OrphanTransactions::<CurrentNetwork>::default();
+        let peer_ip = SocketAddr::from(([127, 0, 0, 1], 4130));
+        let (message, transaction) = sample_orphan();
+        let parents = sample_ids(2);
+
+        // Ensure every missing parent is requested once.
+        assert_eq!(orphans.insert(peer_ip, message.clone(), transaction.clone(), parents.clone()).unwrap(), parents);
+        assert_eq!(orphans.insert(peer_ip, message.clone(), transaction, parents.clone()).unwrap(), vec![]);
+        assert!(orphans.contains(&message.transaction_id));
+        assert_eq!(orphans.num_requests(&peer_ip), 2);
+
+        // Ensure the orphan is only returned once all of its parents are received.
+        assert!(orphans.resolve(&parents[0]).is_empty());
+        let ready = orphans.resolve(&parents[1]);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].message.transaction_id, message.transaction_id);
+        assert_eq!(ready[0].peer_ip, peer_ip);
+        assert!(orphans.is_empty());
+        assert_eq!(orphans.num_requests(&peer_ip), 0);
+    }
+
+    #[test]
+    fn test_orphan_limits() {
+        let orphans = OrphanTransactions::<CurrentNetwork>::default();
+        let peer_ip = SocketAddr::from(([127, 0, 0, 1], 4130));
+        let (message, transaction) = sample_orphan();
+
+        // Ensure a peer is unable to exceed its outstanding parent requests.
+        let parents = sample_ids(MAX_PARENT_REQUESTS_PER_PEER + 1);
+        assert!(orphans.insert(peer_ip, message.clone(), transaction.clone(), parents).is_err());
+        assert!(orphans.is_empty());
+
+        // Ensure a peer is unable to exceed its orphans.
+        for id in sample_ids(MAX_ORPHANS_PER_PEER) {
+            let message = UnconfirmedTransaction { transaction_id: id, ..message.clone() };
+            orphans.insert(peer_ip, message, transaction.clone(), vec![]).unwrap();
+        }
+        assert!(orphans.insert(peer_ip, message.clone(), transaction.clone(), vec![]).is_err());
+        assert_eq!(orphans.len(), MAX_ORPHANS_PER_PEER);
+
+        // Ensure the orphans of a peer are removed with the peer.
+        let other_ip = SocketAddr::from(([127, 0, 0, 1], 4131));
+        orphans.insert(other_ip, message.clone(), transaction, sample_ids(1)).unwrap();
+        orphans.remove_peer(&peer_ip);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans.num_requests(&other_ip), 1);
+
+        // Ensure the orphans and the requests are dropped once they time out.
+        orphans.expire(Instant::now() + Duration::from_secs(ORPHAN_TIMEOUT_IN_SECS));
+        assert!(orphans.is_empty());
+        assert_eq!(orphans.num_requests(&other_ip), 0);
+    }
+}
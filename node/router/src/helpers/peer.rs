@@ -14,10 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use snarkos_node_messages::{ChallengeRequest, NodeType};
+use snarkos_node_messages::{Capabilities, ChallengeRequest, NodeId, NodeType};
 use snarkvm::prelude::{Address, Network};
 
-use std::{net::SocketAddr, time::Instant};
+use std::net::SocketAddr;
+use tokio::time::Instant;
 
 /// The state for each connected peer.
 #[derive(Clone, Debug)]
@@ -30,6 +31,14 @@ pub struct Peer<N: Network> {
     node_type: NodeType,
     /// The message version of the peer.
     version: u32,
+    /// The height below which the peer pruned the bodies of its blocks, or `0` if it serves every block.
+    pruned_height: u32,
+    /// The capabilities the peer advertised in its handshake.
+    capabilities: Capabilities,
+    /// The node ID of the peer, if it is authenticated by an encrypted connection.
+    node_id: Option<NodeId>,
+    /// If `true`, this node initiated the connection to the peer.
+    is_outbound: bool,
     /// The timestamp of the first message received from the peer.
     first_seen: Instant,
     /// The timestamp of the last message received from this peer.
@@ -37,13 +46,22 @@ pub struct Peer<N: Network> {
 }
 
 impl<N: Network> Peer<N> {
-    /// Initializes a new instance of `Peer`.
-    pub fn new(listening_ip: SocketAddr, challenge_request: &ChallengeRequest<N>) -> Self {
+    /// Initializes a new instance of `Peer`, with the node ID authenticated by the connection, if any.
+    pub fn new(
+        listening_ip: SocketAddr,
+        challenge_request: &ChallengeRequest<N>,
+        node_id: Option<NodeId>,
+        is_outbound: bool,
+    ) -> Self {
         Self {
             peer_ip: listening_ip,
             address: challenge_request.address,
             node_type: challenge_request.node_type,
             version: challenge_request.version,
+            pruned_height: challenge_request.pruned_height,
+            capabilities: challenge_request.capabilities,
+            node_id,
+            is_outbound,
             first_seen: Instant::now(),
             last_seen: Instant::now(),
         }
@@ -89,6 +107,46 @@ impl<N: Network> Peer<N> {
         self.version
     }
 
+    /// Returns the height below which the peer pruned the bodies of its blocks, or `0` if it serves every block.
+    pub const fn pruned_height(&self) -> u32 {
+        self.pruned_height
+    }
+
+    /// Returns the capabilities the peer advertised in its handshake.
+    pub const fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Returns the node ID of the peer, if it is authenticated by an encrypted connection.
+    pub const fn node_id(&self) -> Option<NodeId> {
+        self.node_id
+    }
+
+    /// Returns `true` if the peer accepts inbound connections.
+    pub const fn is_listening(&self) -> bool {
+        self.capabilities.contains(Capabilities::LISTENS)
+    }
+
+    /// Returns `true` if the peer relays the unconfirmed transactions and solutions it receives.
+    pub const fn is_relaying(&self) -> bool {
+        self.capabilities.contains(Capabilities::RELAYS)
+    }
+
+    /// Returns `true` if the peer serves blocks.
+    pub const fn is_serving_blocks(&self) -> bool {
+        self.capabilities.contains(Capabilities::SERVES_BLOCKS)
+    }
+
+    /// Returns `true` if the peer serves the body of every block, as it did not prune any block.
+    pub const fn is_archival(&self) -> bool {
+        self.capabilities.contains(Capabilities::SERVES_BLOCKS.union(Capabilities::ARCHIVAL))
+    }
+
+    /// Returns `true` if this node initiated the connection to the peer.
+    pub const fn is_outbound(&self) -> bool {
+        self.is_outbound
+    }
+
     /// Returns the first seen timestamp of the peer.
     pub fn first_seen(&self) -> Instant {
         self.first_seen
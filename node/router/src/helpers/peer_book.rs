@@ -0,0 +1,669 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::peer_policy::{LegacyPolicyState, PeerPolicy, PolicyEntryResult, PolicyState};
+use snarkos_node_messages::{NodeId, NodeType};
+use snarkvm::prelude::Network;
+
+use anyhow::Result;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+
+/// The maximum number of block hashes and transaction IDs remembered to determine the first delivery.
+const MAX_DELIVERIES: usize = 1 << 14;
+/// The maximum number of peers whose statistics are kept, beyond which the least-recently-seen peers are evicted.
+const MAX_PEER_STATISTICS: usize = 1 << 12;
+/// The weight of a new sample in the exponentially-weighted moving average of the latency.
+const LATENCY_EWMA_WEIGHT: f64 = 0.2;
+/// The maximum penalty added to the misbehavior score of a peer for sending a block that is known to be invalid.
+pub const MAX_INVALID_BLOCK_PENALTY: u64 = 64;
+
+/// The statistics of a peer, accumulated over all of its connections.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerStatistics {
+    /// The IP address of the peer, with the port set to the listener port.
+    pub ip: SocketAddr,
+    /// The UNIX timestamp (in seconds) of the first connection with the peer.
+    pub first_seen: i64,
+    /// The UNIX timestamp (in seconds) of the last message received from the peer.
+    pub last_seen: i64,
+    /// The number of connections established with the peer.
+    pub connections: u64,
+    /// The node type announced by the peer in its last handshake.
+    pub node_type: NodeType,
+    /// The message version announced by the peer in its last handshake.
+    pub version: u32,
+    /// `true` if the last connection with the peer was encrypted.
+    pub is_encrypted: bool,
+    /// The number of bytes sent to the peer.
+    pub bytes_sent: u64,
+    /// The number of bytes received from the peer.
+    pub bytes_received: u64,
+    /// The number of messages sent to the peer, by message type.
+    pub messages_sent: BTreeMap<String, u64>,
+    /// The number of messages received from the peer, by message type.
+    pub messages_received: BTreeMap<String, u64>,
+    /// The number of blocks that were first delivered to this node by the peer.
+    pub blocks_first_delivered: u64,
+    /// The number of transactions that were first delivered to this node by the peer.
+    pub transactions_first_delivered: u64,
+    /// The exponentially-weighted moving average of the round-trip latency (in milliseconds), if measured.
+    pub latency_ms: Option<f64>,
+    /// The number of protocol violations committed by the peer.
+    pub misbehavior_score: u64,
+    /// The number of times the peer sent a block that was already known to be invalid.
+    pub known_invalid_blocks: u64,
+}
+
+impl PeerStatistics {
+    /// Initializes the statistics of a newly-seen peer.
+    fn new(ip: SocketAddr, node_type: NodeType, version: u32) -> Self {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        Self {
+            ip,
+            first_seen: now,
+            last_seen: now,
+            connections: 0,
+            node_type,
+            version,
+            is_encrypted: false,
+            bytes_sent: 0,
+            bytes_received: 0,
+            messages_sent: Default::default(),
+            messages_received: Default::default(),
+            blocks_first_delivered: 0,
+            transactions_first_delivered: 0,
+            latency_ms: None,
+            misbehavior_score: 0,
+            known_invalid_blocks: 0,
+        }
+    }
+}
+
+/// The changes to the statistics of a peer, accumulated by its connection, and applied to the peer book in batches.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PeerDelta {
+    /// The UNIX timestamp (in seconds) of the last message received from the peer, if any was received.
+    pub last_seen: Option<i64>,
+    /// The number of messages sent to the peer, by message type.
+    pub messages_sent: BTreeMap<String, u64>,
+    /// The number of messages received from the peer, by message type.
+    pub messages_received: BTreeMap<String, u64>,
+    /// The number of blocks that were first delivered to this node by the peer.
+    pub blocks_first_delivered: u64,
+    /// The number of transactions that were first delivered to this node by the peer.
+    pub transactions_first_delivered: u64,
+    /// The round-trip latencies (in milliseconds) measured with the peer, in order.
+    pub latencies_ms: Vec<f64>,
+    /// The penalties added to the misbehavior score of the peer.
+    pub misbehavior_score: u64,
+    /// The number of times the peer sent a block that was already known to be invalid.
+    pub known_invalid_blocks: u64,
+}
+
+impl PeerDelta {
+    /// Returns `true` if the delta does not change the statistics.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns the number of messages sent to and received from the peer in the delta.
+    pub fn num_messages(&self) -> u64 {
+        self.messages_sent.values().chain(self.messages_received.values()).sum()
+    }
+
+    /// Applies the delta to the given statistics.
+    fn apply(self, peer: &mut PeerStatistics) {
+        if let Some(last_seen) = self.last_seen {
+            peer.last_seen = peer.last_seen.max(last_seen);
+        }
+        for (name, count) in self.messages_sent {
+            *peer.messages_sent.entry(name).or_default() += count;
+        }
+        for (name, count) in self.messages_received {
+            *peer.messages_received.entry(name).or_default() += count;
+        }
+        peer.blocks_first_delivered += self.blocks_first_delivered;
+        peer.transactions_first_delivered += self.transactions_first_delivered;
+        for latency in self.latencies_ms {
+            peer.latency_ms = Some(match peer.latency_ms {
+                Some(average) => average + LATENCY_EWMA_WEIGHT * (latency - average),
+                None => latency,
+            });
+        }
+        peer.misbehavior_score += self.misbehavior_score;
+        peer.known_invalid_blocks += self.known_invalid_blocks;
+    }
+}
+
+/// The contents of the peer book file.
+#[derive(Default, Serialize, Deserialize)]
+struct PeerBookFile {
+    /// The statistics of every known peer.
+    statistics: Vec<PeerStatistics>,
+    /// The peer policy of the node.
+    policy: PolicyState,
+}
+
+/// The contents of the peer book file written by the nodes without trusted node IDs.
+#[derive(Deserialize)]
+struct LegacyPeerBookFile {
+    /// The statistics of every known peer.
+    statistics: Vec<PeerStatistics>,
+    /// The peer policy of the node.
+    policy: LegacyPolicyState,
+}
+
+/// The peer book, which keeps the statistics of the peers this node has been connected to,
+/// along with the peer policy (the banned, pinned, whitelisted, and trusted peers) of the node.
+///
+/// The statistics are kept for at most `MAX_PEER_STATISTICS` peers, as the peers are free to connect from
+/// any address. Beyond the maximum, the statistics of the least-recently-seen peer that is not connected are evicted.
+///
+/// The statistics are kept in memory, and written to the peer book file (if any) in batches,
+/// on every call to `flush`, so that recording a message never touches the storage.
+///
+/// The messages of a connected peer are not recorded here, but in the state of its connection (see `PeerState`),
+/// which sends the accumulated changes as a `PeerDelta` over a channel. The deltas are applied in batches, on every
+/// call to `aggregate`, so that the connections do not contend on the statistics of every peer.
+pub struct PeerBook<N: Network> {
+    /// The path to the peer book file, if the statistics are persisted.
+    path: Option<PathBuf>,
+    /// The map of peer IPs to their statistics.
+    statistics: RwLock<HashMap<SocketAddr, PeerStatistics>>,
+    /// The peer policy of the node.
+    policy: RwLock<PolicyState>,
+    /// The map of connected peer IPs to the number of bytes (sent, received) already accounted for in their connection.
+    traffic: Mutex<HashMap<SocketAddr, (u64, u64)>>,
+    /// The sender of the deltas of the statistics, which is cloned into the state of each connection.
+    delta_sender: mpsc::UnboundedSender<(SocketAddr, PeerDelta)>,
+    /// The receiver of the deltas of the statistics, which are not yet applied.
+    delta_receiver: Mutex<mpsc::UnboundedReceiver<(SocketAddr, PeerDelta)>>,
+    /// The recently-delivered block hashes.
+    delivered_blocks: Mutex<LinkedHashMap<N::BlockHash, ()>>,
+    /// The recently-delivered transaction IDs.
+    delivered_transactions: Mutex<LinkedHashMap<N::TransactionID, ()>>,
+    /// The flag indicating that the statistics changed since the last flush.
+    is_dirty: AtomicBool,
+}
+
+impl<N: Network> Default for PeerBook<N> {
+    /// Initializes a peer book that is not persisted.
+    fn default() -> Self {
+        Self::with_contents(None, Default::default())
+    }
+}
+
+impl<N: Network> PeerBook<N> {
+    /// Loads the peer book from the given file, or initializes an empty one if the file does not exist.
+    pub fn open(path: &Path) -> Result<Self> {
+        let contents = match path.exists() {
+            true => {
+                let bytes = std::fs::read(path)?;
+                // A peer book file without trusted node IDs holds the legacy peer policy,
+                // and a peer book file without a peer policy holds the statistics only.
+                match bincode::deserialize::<PeerBookFile>(&bytes) {
+                    Ok(contents) => contents,
+                    Err(_) => match bincode::deserialize::<LegacyPeerBookFile>(&bytes) {
+                        Ok(legacy) => PeerBookFile { statistics: legacy.statistics, policy: legacy.policy.into() },
+                        Err(_) => PeerBookFile { statistics: bincode::deserialize(&bytes)?, policy: Default::default() },
+                    },
+                }
+            }
+            false => Default::default(),
+        };
+        Ok(Self::with_contents(Some(path.to_path_buf()), contents))
+    }
+
+    /// Initializes a peer book with the given path and contents.
+    fn with_contents(path: Option<PathBuf>, contents: PeerBookFile) -> Self {
+        let (delta_sender, delta_receiver) = mpsc::unbounded_channel();
+        // Keep the statistics of the most-recently-seen peers, up to the maximum.
+        let mut statistics = contents.statistics;
+        statistics.sort_unstable_by_key(|peer| core::cmp::Reverse(peer.last_seen));
+        statistics.truncate(MAX_PEER_STATISTICS);
+        Self {
+            path,
+            statistics: RwLock::new(statistics.into_iter().map(|peer| (peer.ip, peer)).collect()),
+            policy: RwLock::new(contents.policy),
+            traffic: Default::default(),
+            delta_sender,
+            delta_receiver: Mutex::new(delta_receiver),
+            delivered_blocks: Default::default(),
+            delivered_transactions: Default::default(),
+            is_dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the path to the peer book file, if the statistics are persisted.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the statistics of the given peer IP, if it is known.
+    pub fn get(&self, peer_ip: &SocketAddr) -> Option<PeerStatistics> {
+        self.statistics.read().get(peer_ip).cloned()
+    }
+
+    /// Returns the statistics of every known peer.
+    pub fn statistics(&self) -> Vec<PeerStatistics> {
+        self.statistics.read().values().cloned().collect()
+    }
+
+    /// Writes the statistics to the peer book file, if they changed since the last flush.
+    pub fn flush(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if !self.is_dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        // Serialize the statistics and the peer policy.
+        let contents = PeerBookFile { statistics: self.statistics(), policy: self.policy.read().clone() };
+        let bytes = match bincode::serialize(&contents) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                self.is_dirty.store(true, Ordering::SeqCst);
+                return Err(error.into());
+            }
+        };
+        // Write the contents to a temporary file, and move it into place.
+        let temporary_path = path.with_extension("tmp");
+        if let Err(error) = std::fs::write(&temporary_path, bytes).and_then(|_| std::fs::rename(&temporary_path, path))
+        {
+            self.is_dirty.store(true, Ordering::SeqCst);
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    /// Returns a sender of the deltas of the statistics, which are applied on the next aggregation.
+    pub fn delta_sender(&self) -> mpsc::UnboundedSender<(SocketAddr, PeerDelta)> {
+        self.delta_sender.clone()
+    }
+
+    /// Applies the deltas of the statistics received since the last aggregation, under a single lock,
+    /// and returns the number of applied deltas. Note that the deltas of unknown peers are discarded.
+    pub fn aggregate(&self) -> usize {
+        // Receive the pending deltas, before the statistics are locked.
+        let mut deltas = Vec::new();
+        {
+            let mut delta_receiver = self.delta_receiver.lock();
+            while let Ok(delta) = delta_receiver.try_recv() {
+                deltas.push(delta);
+            }
+        }
+        if deltas.is_empty() {
+            return 0;
+        }
+        let num_deltas = deltas.len();
+        let mut statistics = self.statistics.write();
+        for (peer_ip, delta) in deltas {
+            if let Some(peer) = statistics.get_mut(&peer_ip) {
+                delta.apply(peer);
+            }
+        }
+        self.is_dirty.store(true, Ordering::SeqCst);
+        num_deltas
+    }
+
+    /// Updates the statistics of the given peer with the given function, if the peer is known.
+    fn update<F: FnOnce(&mut PeerStatistics)>(&self, peer_ip: &SocketAddr, update_fn: F) {
+        if let Some(statistics) = self.statistics.write().get_mut(peer_ip) {
+            update_fn(statistics);
+            self.is_dirty.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<N: Network> PeerBook<N> {
+    /// Returns `true` if the given peer IP is banned.
+    pub fn is_banned(&self, peer_ip: &SocketAddr) -> bool {
+        self.policy.read().is_banned(peer_ip, OffsetDateTime::now_utc().unix_timestamp())
+    }
+
+    /// Returns `true` if the given peer IP is exempt from rate limiting.
+    pub fn is_whitelisted(&self, peer_ip: &SocketAddr) -> bool {
+        self.policy.read().is_whitelisted(peer_ip)
+    }
+
+    /// Returns `true` if the given node ID is trusted by the peer policy.
+    pub fn is_trusted(&self, node_id: &NodeId) -> bool {
+        self.policy.read().is_trusted(node_id)
+    }
+
+    /// Returns the static public key pinned for the given peer IP by the peer policy, if any.
+    pub fn pinned_key(&self, peer_ip: &SocketAddr) -> Option<Vec<u8>> {
+        self.policy.read().pinned_key(peer_ip).map(|key| key.to_vec())
+    }
+
+    /// Returns the peer policy document, without the expired bans.
+    pub fn export_policy(&self) -> PeerPolicy {
+        self.policy.read().export(OffsetDateTime::now_utc().unix_timestamp())
+    }
+
+    /// Imports the given peer policy document, and returns the outcome of each entry.
+    ///
+    /// The entries are merged into the current policy, unless `replace` is set,
+    /// in which case the valid entries replace the current policy.
+    pub fn import_policy(&self, policy: PeerPolicy, replace: bool) -> Vec<PolicyEntryResult> {
+        let results = self.policy.write().import(policy, replace, OffsetDateTime::now_utc().unix_timestamp());
+        self.is_dirty.store(true, Ordering::SeqCst);
+        results
+    }
+}
+
+impl<N: Network> PeerBook<N> {
+    /// Records a new connection with the given peer, which completed the handshake.
+    pub fn record_connection(&self, peer_ip: SocketAddr, node_type: NodeType, version: u32, is_encrypted: bool) {
+        // Reset the traffic accounted for in the connection.
+        let mut traffic = self.traffic.lock();
+        traffic.insert(peer_ip, (0, 0));
+        // Evict the least-recently-seen peer that is not connected, if a new peer exceeds the maximum.
+        let mut statistics = self.statistics.write();
+        if !statistics.contains_key(&peer_ip) && statistics.len() >= MAX_PEER_STATISTICS {
+            let evicted_ip = statistics
+                .values()
+                .filter(|peer| !traffic.contains_key(&peer.ip))
+                .min_by_key(|peer| peer.last_seen)
+                .map(|peer| peer.ip);
+            if let Some(evicted_ip) = evicted_ip {
+                statistics.remove(&evicted_ip);
+            }
+        }
+        // Update the statistics of the peer.
+        let peer = statistics.entry(peer_ip).or_insert_with(|| PeerStatistics::new(peer_ip, node_type, version));
+        peer.last_seen = OffsetDateTime::now_utc().unix_timestamp();
+        peer.connections += 1;
+        peer.node_type = node_type;
+        peer.version = version;
+        peer.is_encrypted = is_encrypted;
+        self.is_dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// Records the end of the connection with the given peer.
+    pub fn record_disconnection(&self, peer_ip: &SocketAddr) {
+        self.traffic.lock().remove(peer_ip);
+    }
+
+    /// Records the total number of bytes (sent, received) in the current connection with the given peer.
+    pub fn record_traffic(&self, peer_ip: &SocketAddr, bytes_sent: u64, bytes_received: u64) {
+        // Compute the number of bytes since the last update.
+        let (new_sent, new_received) = match self.traffic.lock().get_mut(peer_ip) {
+            Some((sent, received)) => {
+                let new_bytes = (bytes_sent.saturating_sub(*sent), bytes_received.saturating_sub(*received));
+                (*sent, *received) = (bytes_sent.max(*sent), bytes_received.max(*received));
+                new_bytes
+            }
+            None => return,
+        };
+        if new_sent > 0 || new_received > 0 {
+            self.update(peer_ip, |peer| {
+                peer.bytes_sent += new_sent;
+                peer.bytes_received += new_received;
+            });
+        }
+    }
+
+    /// Inserts the given delivered blocks, and returns the number of them that were not delivered before.
+    pub fn insert_delivered_blocks(&self, block_hashes: impl IntoIterator<Item = N::BlockHash>) -> u64 {
+        insert_deliveries(&mut self.delivered_blocks.lock(), block_hashes)
+    }
+
+    /// Inserts the given delivered transaction, and returns `true` if it was not delivered before.
+    pub fn insert_delivered_transaction(&self, transaction_id: N::TransactionID) -> bool {
+        insert_deliveries(&mut self.delivered_transactions.lock(), [transaction_id]) > 0
+    }
+}
+
+/// Inserts the given items into the given deliveries, evicting the oldest ones beyond the maximum,
+/// and returns the number of items that were not delivered before.
+fn insert_deliveries<T: core::hash::Hash + Eq>(
+    deliveries: &mut LinkedHashMap<T, ()>,
+    items: impl IntoIterator<Item = T>,
+) -> u64 {
+    let mut num_new = 0;
+    for item in items {
+        if deliveries.insert(item, ()).is_none() {
+            num_new += 1;
+        }
+    }
+    while deliveries.len() > MAX_DELIVERIES {
+        deliveries.pop_front();
+    }
+    num_new
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BannedPeer, PeerState};
+    use snarkvm::prelude::{Field, Testnet3, Uniform};
+
+    use std::{
+        collections::BTreeSet,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    type CurrentNetwork = Testnet3;
+
+    fn sample_ip(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    #[test]
+    fn test_record_and_reload() {
+        let directory = std::env::temp_dir().join(format!("peer-book-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("peers.bin");
+        let _ = std::fs::remove_file(&path);
+
+        let peer_ip = sample_ip(4130);
+        let book = PeerBook::<CurrentNetwork>::open(&path).unwrap();
+        let state = PeerState::<CurrentNetwork>::new(peer_ip, 0, book.delta_sender());
+        // Messages from unknown peers are not recorded.
+        state.record_inbound("Ping");
+        state.flush();
+        assert_eq!(book.aggregate(), 1);
+        assert!(book.get(&peer_ip).is_none());
+
+        book.record_connection(peer_ip, NodeType::Validator, 7, true);
+        state.record_inbound("Ping");
+        state.record_inbound("Ping");
+        state.record_outbound("Pong");
+        book.record_traffic(&peer_ip, 100, 200);
+        book.record_traffic(&peer_ip, 150, 200);
+        state.record_misbehavior();
+
+        let rng = &mut rand::thread_rng();
+        let block_hash = Field::<CurrentNetwork>::rand(rng).into();
+        state.record_blocks_first_delivered(book.insert_delivered_blocks([block_hash, block_hash]));
+        state.record_blocks_first_delivered(book.insert_delivered_blocks([block_hash]));
+
+        // Ensure the messages are only counted once the delta is aggregated.
+        assert!(book.get(&peer_ip).unwrap().messages_received.is_empty());
+        state.flush();
+        assert_eq!(book.aggregate(), 1);
+
+        // Nothing is written until the book is flushed.
+        assert!(!path.exists());
+        book.flush().unwrap();
+
+        let peer = PeerBook::<CurrentNetwork>::open(&path).unwrap().get(&peer_ip).unwrap();
+        assert_eq!(peer.connections, 1);
+        assert_eq!(peer.node_type, NodeType::Validator);
+        assert_eq!(peer.version, 7);
+        assert!(peer.is_encrypted);
+        assert_eq!(peer.messages_received.get("Ping"), Some(&2));
+        assert_eq!(peer.messages_sent.get("Pong"), Some(&1));
+        assert_eq!((peer.bytes_sent, peer.bytes_received), (150, 200));
+        assert_eq!(peer.blocks_first_delivered, 1);
+        assert_eq!(peer.misbehavior_score, 1);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_known_invalid_block_penalty() {
+        let book = PeerBook::<CurrentNetwork>::default();
+        let peer_ip = sample_ip(4133);
+        book.record_connection(peer_ip, NodeType::Validator, 7, true);
+
+        // Ensure the penalty doubles with every known invalid block, up to the maximum.
+        let state = PeerState::<CurrentNetwork>::new(peer_ip, 0, book.delta_sender());
+        let penalties = (0..9).map(|_| state.record_known_invalid_block()).collect::<Vec<_>>();
+        assert_eq!(penalties, vec![1, 2, 4, 8, 16, 32, 64, 64, 64]);
+        state.flush();
+        book.aggregate();
+        let peer = book.get(&peer_ip).unwrap();
+        assert_eq!(peer.known_invalid_blocks, 9);
+        assert_eq!(peer.misbehavior_score, penalties.iter().sum::<u64>());
+
+        // Ensure a new connection resumes from the known invalid blocks of the peer.
+        let state = PeerState::<CurrentNetwork>::new(peer_ip, peer.known_invalid_blocks, book.delta_sender());
+        assert_eq!(state.record_known_invalid_block(), MAX_INVALID_BLOCK_PENALTY);
+    }
+
+    #[test]
+    fn test_statistics_eviction() {
+        let book = PeerBook::<CurrentNetwork>::default();
+        for port in 0..MAX_PEER_STATISTICS as u16 {
+            book.record_connection(sample_ip(port), NodeType::Client, 7, false);
+            book.record_disconnection(&sample_ip(port));
+        }
+        // Make the connected peer the least recently seen, followed by a disconnected peer.
+        book.record_connection(sample_ip(1), NodeType::Client, 7, false);
+        book.statistics.write().get_mut(&sample_ip(1)).unwrap().last_seen = 0;
+        book.statistics.write().get_mut(&sample_ip(2)).unwrap().last_seen = 1;
+
+        // Ensure a new peer evicts the least-recently-seen peer that is not connected.
+        let peer_ip = sample_ip(MAX_PEER_STATISTICS as u16);
+        book.record_connection(peer_ip, NodeType::Client, 7, false);
+        assert_eq!(book.statistics().len(), MAX_PEER_STATISTICS);
+        assert!(book.get(&peer_ip).is_some());
+        assert!(book.get(&sample_ip(1)).is_some());
+        assert!(book.get(&sample_ip(2)).is_none());
+
+        // Ensure a loaded peer book keeps the most-recently-seen peers, up to the maximum.
+        let mut statistics = book.statistics();
+        let mut oldest = PeerStatistics::new(sample_ip(2), NodeType::Client, 7);
+        oldest.last_seen = 0;
+        statistics.push(oldest);
+        let book =
+            PeerBook::<CurrentNetwork>::with_contents(None, PeerBookFile { statistics, policy: Default::default() });
+        assert_eq!(book.statistics().len(), MAX_PEER_STATISTICS);
+        assert!(book.get(&sample_ip(2)).is_none());
+    }
+
+    #[test]
+    fn test_policy_reload() {
+        let directory = std::env::temp_dir().join(format!("peer-book-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("peers.bin");
+
+        // Write a peer book file without a peer policy.
+        let peer_ip = sample_ip(4132);
+        std::fs::write(&path, bincode::serialize(&vec![PeerStatistics::new(peer_ip, NodeType::Client, 7)]).unwrap())
+            .unwrap();
+
+        // Ensure the statistics are loaded, and the peer policy is empty.
+        let book = PeerBook::<CurrentNetwork>::open(&path).unwrap();
+        assert!(book.get(&peer_ip).is_some());
+        assert_eq!(book.export_policy(), PeerPolicy::default());
+
+        let policy = PeerPolicy {
+            banned: vec![BannedPeer { ip: peer_ip.to_string(), expires_at: None, reason: "spam".to_string() }],
+            ..Default::default()
+        };
+        book.import_policy(policy.clone(), false);
+        assert!(book.is_banned(&peer_ip));
+        book.flush().unwrap();
+
+        // Ensure the peer policy is written along with the statistics.
+        let book = PeerBook::<CurrentNetwork>::open(&path).unwrap();
+        assert!(book.get(&peer_ip).is_some());
+        assert!(book.is_banned(&peer_ip));
+        assert_eq!(book.export_policy(), policy);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_policy_reload() {
+        let directory = std::env::temp_dir().join(format!("peer-book-legacy-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("peers.bin");
+
+        // Write a peer book file with a peer policy without trusted node IDs, i.e. the banned peers
+        // (with the expiry and the reason of their ban), the pinned keys, and the whitelisted peers.
+        let peer_ip = sample_ip(4134);
+        let banned = BTreeMap::from([(peer_ip, (None::<i64>, "spam".to_string()))]);
+        let policy = (banned, BTreeMap::<SocketAddr, Vec<u8>>::new(), BTreeSet::from([peer_ip]));
+        let statistics = vec![PeerStatistics::new(peer_ip, NodeType::Client, 7)];
+        std::fs::write(&path, bincode::serialize(&(statistics, policy)).unwrap()).unwrap();
+
+        // Ensure the statistics and the legacy peer policy are loaded, without trusted node IDs.
+        let book = PeerBook::<CurrentNetwork>::open(&path).unwrap();
+        assert!(book.get(&peer_ip).is_some());
+        assert!(book.is_banned(&peer_ip));
+        assert!(book.is_whitelisted(&peer_ip));
+        assert!(book.export_policy().trusted.is_empty());
+
+        // Ensure the trusted node IDs are written along with the rest of the peer policy.
+        let node_id = NodeId::from_public_key(&[7; 32]);
+        book.import_policy(PeerPolicy { trusted: vec![node_id.to_string()], ..Default::default() }, false);
+        book.flush().unwrap();
+        let book = PeerBook::<CurrentNetwork>::open(&path).unwrap();
+        assert!(book.is_banned(&peer_ip));
+        assert!(book.is_trusted(&node_id));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_latency() {
+        let peer_ip = sample_ip(4131);
+        let book = PeerBook::<CurrentNetwork>::default();
+        book.record_connection(peer_ip, NodeType::Client, 7, false);
+        let state = PeerState::<CurrentNetwork>::new(peer_ip, 0, book.delta_sender());
+
+        // A `Pong` without a preceding `Ping` is not measured.
+        state.record_inbound("Pong");
+        state.flush();
+        book.aggregate();
+        assert_eq!(book.get(&peer_ip).unwrap().latency_ms, None);
+
+        state.record_outbound("Ping");
+        state.record_inbound("Pong");
+        state.flush();
+        book.aggregate();
+        assert!(book.get(&peer_ip).unwrap().latency_ms.is_some());
+
+        // The in-memory book is never written.
+        assert!(book.path().is_none());
+        book.flush().unwrap();
+    }
+}
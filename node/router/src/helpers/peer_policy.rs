@@ -0,0 +1,311 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::noise::KEY_SIZE;
+use snarkos_node_messages::NodeId;
+
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::SocketAddr,
+    str::FromStr,
+};
+
+/// A peer policy document, with which nodes share their banned, pinned, whitelisted, and trusted peers.
+///
+/// The entries are kept as given, so that each one is validated on its own when the document is imported.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerPolicy {
+    /// The banned peers, which are refused on connect.
+    pub banned: Vec<BannedPeer>,
+    /// The peers whose static public key is pinned.
+    pub pinned: Vec<PinnedPeer>,
+    /// The peer IPs that are exempt from rate limiting.
+    pub whitelisted: Vec<String>,
+    /// The node IDs of the trusted peers, which keep their standing regardless of their IP.
+    pub trusted: Vec<String>,
+}
+
+/// A banned peer, in a peer policy document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BannedPeer {
+    /// The IP address of the peer, with its listener port.
+    pub ip: String,
+    /// The UNIX timestamp (in seconds) at which the ban expires, or `None` if the ban is permanent.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// The reason for the ban.
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// A peer with a pinned static public key, in a peer policy document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedPeer {
+    /// The IP address of the peer, with its listener port.
+    pub ip: String,
+    /// The static public key of the peer, in hex.
+    pub public_key: String,
+}
+
+/// The outcome of importing an entry of a peer policy document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyEntryStatus {
+    /// The entry was applied.
+    Applied,
+    /// The entry was skipped, as it is a ban that already expired.
+    SkippedExpired,
+    /// The entry was rejected, for the given reason.
+    Invalid(String),
+}
+
+/// The result of importing an entry of a peer policy document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyEntryResult {
+    /// The section of the entry (`banned`, `pinned`, `whitelisted`, or `trusted`).
+    pub section: String,
+    /// The IP address (or the node ID, for a `trusted` entry) of the entry, as given.
+    pub ip: String,
+    /// The outcome of the import.
+    pub status: PolicyEntryStatus,
+}
+
+impl PolicyEntryResult {
+    /// Initializes the result of the given entry, from the outcome of its validation.
+    fn new(section: &str, ip: &str, outcome: Result<PolicyEntryStatus>) -> Self {
+        let status = outcome.unwrap_or_else(|error| PolicyEntryStatus::Invalid(error.to_string()));
+        Self { section: section.to_string(), ip: ip.to_string(), status }
+    }
+}
+
+/// A ban of a peer, as applied by the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Ban {
+    /// The UNIX timestamp (in seconds) at which the ban expires, or `None` if the ban is permanent.
+    expires_at: Option<i64>,
+    /// The reason for the ban.
+    reason: String,
+}
+
+impl Ban {
+    /// Returns `true` if the ban expired at the given UNIX timestamp.
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+/// The peer policy applied by the node, which is persisted in the peer book.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PolicyState {
+    /// The map of banned peer IPs to their ban.
+    banned: BTreeMap<SocketAddr, Ban>,
+    /// The map of peer IPs to their pinned static public key.
+    pinned_keys: BTreeMap<SocketAddr, Vec<u8>>,
+    /// The set of peer IPs that are exempt from rate limiting.
+    whitelisted: BTreeSet<SocketAddr>,
+    /// The set of node IDs of the trusted peers.
+    trusted: BTreeSet<NodeId>,
+}
+
+/// The peer policy persisted in the peer book by the nodes without trusted node IDs.
+#[derive(Deserialize)]
+pub(crate) struct LegacyPolicyState {
+    banned: BTreeMap<SocketAddr, Ban>,
+    pinned_keys: BTreeMap<SocketAddr, Vec<u8>>,
+    whitelisted: BTreeSet<SocketAddr>,
+}
+
+impl From<LegacyPolicyState> for PolicyState {
+    fn from(state: LegacyPolicyState) -> Self {
+        let LegacyPolicyState { banned, pinned_keys, whitelisted } = state;
+        Self { banned, pinned_keys, whitelisted, trusted: Default::default() }
+    }
+}
+
+impl PolicyState {
+    /// Returns `true` if the given peer IP is banned at the given UNIX timestamp.
+    pub(crate) fn is_banned(&self, peer_ip: &SocketAddr, now: i64) -> bool {
+        matches!(self.banned.get(peer_ip), Some(ban) if !ban.is_expired(now))
+    }
+
+    /// Returns `true` if the given peer IP is exempt from rate limiting.
+    pub(crate) fn is_whitelisted(&self, peer_ip: &SocketAddr) -> bool {
+        self.whitelisted.contains(peer_ip)
+    }
+
+    /// Returns `true` if the given node ID is trusted.
+    pub(crate) fn is_trusted(&self, node_id: &NodeId) -> bool {
+        self.trusted.contains(node_id)
+    }
+
+    /// Returns the static public key pinned for the given peer IP, if any.
+    pub(crate) fn pinned_key(&self, peer_ip: &SocketAddr) -> Option<&[u8]> {
+        self.pinned_keys.get(peer_ip).map(|key| key.as_slice())
+    }
+
+    /// Returns the peer policy document, without the bans that expired at the given UNIX timestamp.
+    pub(crate) fn export(&self, now: i64) -> PeerPolicy {
+        PeerPolicy {
+            banned: self
+                .banned
+                .iter()
+                .filter(|(_, ban)| !ban.is_expired(now))
+                .map(|(ip, ban)| BannedPeer {
+                    ip: ip.to_string(),
+                    expires_at: ban.expires_at,
+                    reason: ban.reason.clone(),
+                })
+                .collect(),
+            pinned: self
+                .pinned_keys
+                .iter()
+                .map(|(ip, key)| PinnedPeer { ip: ip.to_string(), public_key: hex::encode(key) })
+                .collect(),
+            whitelisted: self.whitelisted.iter().map(|ip| ip.to_string()).collect(),
+            trusted: self.trusted.iter().map(|node_id| node_id.to_string()).collect(),
+        }
+    }
+
+    /// Imports the given peer policy document at the given UNIX timestamp, and returns the outcome of each entry.
+    ///
+    /// The entries are merged into the current policy, with an entry replacing the current one for the same peer IP,
+    /// unless `replace` is set, in which case the valid entries replace the current policy as a whole.
+    pub(crate) fn import(&mut self, policy: PeerPolicy, replace: bool, now: i64) -> Vec<PolicyEntryResult> {
+        if replace {
+            *self = Self::default();
+        }
+        // Remove the bans that expired.
+        self.banned.retain(|_, ban| !ban.is_expired(now));
+
+        let mut results = Vec::with_capacity(
+            policy.banned.len() + policy.pinned.len() + policy.whitelisted.len() + policy.trusted.len(),
+        );
+        for entry in policy.banned {
+            let outcome = parse_ip(&entry.ip).map(|ip| {
+                let ban = Ban { expires_at: entry.expires_at, reason: entry.reason };
+                match ban.is_expired(now) {
+                    true => PolicyEntryStatus::SkippedExpired,
+                    false => {
+                        self.banned.insert(ip, ban);
+                        PolicyEntryStatus::Applied
+                    }
+                }
+            });
+            results.push(PolicyEntryResult::new("banned", &entry.ip, outcome));
+        }
+        for entry in policy.pinned {
+            let outcome = parse_ip(&entry.ip).and_then(|ip| {
+                let key = hex::decode(&entry.public_key).map_err(|error| anyhow!("Malformed public key - {error}"))?;
+                ensure!(key.len() == KEY_SIZE, "The public key must be {KEY_SIZE} bytes (found {})", key.len());
+                self.pinned_keys.insert(ip, key);
+                Ok(PolicyEntryStatus::Applied)
+            });
+            results.push(PolicyEntryResult::new("pinned", &entry.ip, outcome));
+        }
+        for entry in policy.whitelisted {
+            let outcome = parse_ip(&entry).map(|ip| {
+                self.whitelisted.insert(ip);
+                PolicyEntryStatus::Applied
+            });
+            results.push(PolicyEntryResult::new("whitelisted", &entry, outcome));
+        }
+        for entry in policy.trusted {
+            let outcome = NodeId::from_str(&entry).map(|node_id| {
+                self.trusted.insert(node_id);
+                PolicyEntryStatus::Applied
+            });
+            results.push(PolicyEntryResult::new("trusted", &entry, outcome));
+        }
+        results
+    }
+}
+
+/// Parses the given peer IP, which must include the listener port.
+fn parse_ip(ip: &str) -> Result<SocketAddr> {
+    ip.parse().map_err(|error| anyhow!("Malformed IP address - {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_policy() {
+        let now = 1_000;
+        let mut state = PolicyState::default();
+        let policy = PeerPolicy {
+            banned: vec![
+                BannedPeer { ip: "1.2.3.4:4133".to_string(), expires_at: None, reason: "spam".to_string() },
+                BannedPeer { ip: "1.2.3.5:4133".to_string(), expires_at: Some(now), reason: String::new() },
+                BannedPeer { ip: "1.2.3.6".to_string(), expires_at: None, reason: String::new() },
+            ],
+            pinned: vec![
+                PinnedPeer { ip: "1.2.3.7:4133".to_string(), public_key: hex::encode([7; KEY_SIZE]) },
+                PinnedPeer { ip: "1.2.3.8:4133".to_string(), public_key: "0a0b".to_string() },
+            ],
+            whitelisted: vec!["1.2.3.9:4133".to_string()],
+            trusted: vec![NodeId::from_public_key(&[7; KEY_SIZE]).to_string(), "1.2.3.7:4133".to_string()],
+        };
+        let statuses = state.import(policy, false, now).into_iter().map(|result| result.status).collect::<Vec<_>>();
+        assert_eq!(statuses[0], PolicyEntryStatus::Applied);
+        assert_eq!(statuses[1], PolicyEntryStatus::SkippedExpired);
+        assert!(matches!(statuses[2], PolicyEntryStatus::Invalid(_)));
+        assert_eq!(statuses[3], PolicyEntryStatus::Applied);
+        assert!(matches!(statuses[4], PolicyEntryStatus::Invalid(_)));
+        assert_eq!(statuses[5], PolicyEntryStatus::Applied);
+        assert_eq!(statuses[6], PolicyEntryStatus::Applied);
+        assert!(matches!(statuses[7], PolicyEntryStatus::Invalid(_)));
+
+        let banned_ip = "1.2.3.4:4133".parse().unwrap();
+        assert!(state.is_banned(&banned_ip, now));
+        assert!(!state.is_banned(&"1.2.3.5:4133".parse().unwrap(), now));
+        assert_eq!(state.pinned_key(&"1.2.3.7:4133".parse().unwrap()), Some([7; KEY_SIZE].as_slice()));
+        assert!(state.is_whitelisted(&"1.2.3.9:4133".parse().unwrap()));
+        assert!(state.is_trusted(&NodeId::from_public_key(&[7; KEY_SIZE])));
+
+        // Ensure the exported policy imports into the same state.
+        let mut other = PolicyState::default();
+        let results = other.import(state.export(now), false, now);
+        assert!(results.iter().all(|result| result.status == PolicyEntryStatus::Applied));
+        assert_eq!(other, state);
+
+        // Ensure a merge keeps the current entries, whereas a replacement removes them.
+        let policy = PeerPolicy { whitelisted: vec!["1.2.3.10:4133".to_string()], ..Default::default() };
+        state.import(policy.clone(), false, now);
+        assert!(state.is_banned(&banned_ip, now));
+        state.import(policy, true, now);
+        assert!(!state.is_banned(&banned_ip, now));
+        assert!(state.is_whitelisted(&"1.2.3.10:4133".parse().unwrap()));
+        assert!(!state.is_whitelisted(&"1.2.3.9:4133".parse().unwrap()));
+
+        // Ensure a ban is lifted once it expires.
+        let policy = PeerPolicy {
+            banned: vec![BannedPeer {
+                ip: "1.2.3.4:4133".to_string(),
+                expires_at: Some(now + 10),
+                reason: String::new(),
+            }],
+            ..Default::default()
+        };
+        state.import(policy, false, now);
+        assert!(state.is_banned(&banned_ip, now + 9));
+        assert!(!state.is_banned(&banned_ip, now + 10));
+        assert!(state.export(now + 10).banned.is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::peer_book::{PeerDelta, MAX_INVALID_BLOCK_PENALTY};
+use snarkos_node_messages::CompressionStats;
+use snarkvm::prelude::{Network, PuzzleCommitment};
+
+use core::hash::Hash;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+
+/// The maximum number of solution commitments and transaction IDs remembered in each direction, for each peer.
+pub const MAX_KNOWN_INVENTORY: usize = 1 << 12;
+/// The number of messages after which the delta of a connection is sent to the peer book, ahead of the aggregation.
+const MAX_DELTA_MESSAGES: u64 = 256;
+
+/// The mutable state of the connection with a peer, which is only touched by the messages of that peer.
+///
+/// The state records the messages of the peer into a pending `PeerDelta`, which is sent to the peer book
+/// on every call to `flush`, and keeps the inventory known to the peer, so that the messages of different
+/// peers never contend on a lock.
+pub struct PeerState<N: Network> {
+    /// The IP address of the peer, with the port set to the listener port.
+    peer_ip: SocketAddr,
+    /// The changes to the statistics of the peer, which are not yet sent to the peer book.
+    delta: Mutex<PeerDelta>,
+    /// The timestamp of the last `Ping` sent to the peer, awaiting a `Pong`.
+    ping: Mutex<Option<Instant>>,
+    /// The number of known invalid blocks the peer sent, over all of its connections.
+    known_invalid_blocks: AtomicU64,
+    /// The solution commitments received from the peer, with their last seen timestamp.
+    inbound_solutions: Mutex<LinkedHashMap<PuzzleCommitment<N>, OffsetDateTime>>,
+    /// The transaction IDs received from the peer, with their last seen timestamp.
+    inbound_transactions: Mutex<LinkedHashMap<N::TransactionID, OffsetDateTime>>,
+    /// The solution commitments sent to the peer, with their last seen timestamp.
+    outbound_solutions: Mutex<LinkedHashMap<PuzzleCommitment<N>, OffsetDateTime>>,
+    /// The transaction IDs sent to the peer, with their last seen timestamp.
+    outbound_transactions: Mutex<LinkedHashMap<N::TransactionID, OffsetDateTime>>,
+    /// The number of bytes of the compressed messages, which is shared by the codecs of the connection.
+    compression_stats: Arc<CompressionStats>,
+    /// The sender of the deltas to the peer book.
+    delta_sender: mpsc::UnboundedSender<(SocketAddr, PeerDelta)>,
+}
+
+impl<N: Network> PeerState<N> {
+    /// Initializes the state of a new connection with the given peer, which already sent the given number of
+    /// known invalid blocks, and whose deltas are sent over the given channel.
+    pub fn new(
+        peer_ip: SocketAddr,
+        known_invalid_blocks: u64,
+        delta_sender: mpsc::UnboundedSender<(SocketAddr, PeerDelta)>,
+    ) -> Self {
+        Self {
+            peer_ip,
+            delta: Default::default(),
+            ping: Default::default(),
+            known_invalid_blocks: AtomicU64::new(known_invalid_blocks),
+            inbound_solutions: Default::default(),
+            inbound_transactions: Default::default(),
+            outbound_solutions: Default::default(),
+            outbound_transactions: Default::default(),
+            compression_stats: Default::default(),
+            delta_sender,
+        }
+    }
+
+    /// Returns the IP address of the peer, with the port set to the listener port.
+    pub const fn ip(&self) -> SocketAddr {
+        self.peer_ip
+    }
+
+    /// Returns the number of bytes of the compressed messages exchanged with the peer, before and after
+    /// their compression.
+    pub const fn compression_stats(&self) -> &Arc<CompressionStats> {
+        &self.compression_stats
+    }
+
+    /// Returns the changes to the statistics of the peer, which are not yet sent to the peer book.
+    pub fn pending_delta(&self) -> PeerDelta {
+        self.delta.lock().clone()
+    }
+
+    /// Sends the pending changes to the statistics of the peer to the peer book, if there are any.
+    pub fn flush(&self) {
+        let delta = std::mem::take(&mut *self.delta.lock());
+        if !delta.is_empty() {
+            // The peer book outlives the connections, so the delta is only dropped on shutdown.
+            let _ = self.delta_sender.send((self.peer_ip, delta));
+        }
+    }
+
+    /// Updates the pending delta with the given function, and sends it to the peer book once it holds
+    /// `MAX_DELTA_MESSAGES` messages, which bounds the staleness of the statistics of a busy peer.
+    fn update<F: FnOnce(&mut PeerDelta)>(&self, update_fn: F) {
+        let is_full = {
+            let mut delta = self.delta.lock();
+            update_fn(&mut delta);
+            delta.num_messages() >= MAX_DELTA_MESSAGES
+        };
+        if is_full {
+            self.flush();
+        }
+    }
+}
+
+impl<N: Network> PeerState<N> {
+    /// Records a message with the given name that was sent to the peer.
+    pub fn record_outbound(&self, message_name: &str) {
+        // If the message is a `Ping`, start measuring the latency.
+        if message_name == "Ping" {
+            *self.ping.lock() = Some(Instant::now());
+        }
+        self.update(|delta| *delta.messages_sent.entry(message_name.to_string()).or_default() += 1);
+    }
+
+    /// Records a message with the given name that was received from the peer.
+    pub fn record_inbound(&self, message_name: &str) {
+        // If the message is a `Pong`, measure the latency.
+        let latency = match message_name == "Pong" {
+            true => self.ping.lock().take().map(|sent| sent.elapsed().as_secs_f64() * 1000.0),
+            false => None,
+        };
+        self.update(|delta| {
+            delta.last_seen = Some(OffsetDateTime::now_utc().unix_timestamp());
+            *delta.messages_received.entry(message_name.to_string()).or_default() += 1;
+            delta.latencies_ms.extend(latency);
+        });
+    }
+
+    /// Records the given number of blocks that were first delivered to this node by the peer.
+    pub fn record_blocks_first_delivered(&self, num_blocks: u64) {
+        if num_blocks > 0 {
+            self.update(|delta| delta.blocks_first_delivered += num_blocks);
+        }
+    }
+
+    /// Records a transaction that was first delivered to this node by the peer.
+    pub fn record_transaction_first_delivered(&self) {
+        self.update(|delta| delta.transactions_first_delivered += 1);
+    }
+
+    /// Records a protocol violation by the peer.
+    pub fn record_misbehavior(&self) {
+        self.update(|delta| delta.misbehavior_score += 1);
+    }
+
+    /// Records that the peer sent a block that is already known to be invalid, and returns the penalty
+    /// added to its misbehavior score, which doubles with every such block, up to `MAX_INVALID_BLOCK_PENALTY`.
+    pub fn record_known_invalid_block(&self) -> u64 {
+        let doubling = self.known_invalid_blocks.fetch_add(1, Ordering::Relaxed).min(u32::MAX as u64) as u32;
+        let penalty = 1u64.checked_shl(doubling).unwrap_or(u64::MAX).min(MAX_INVALID_BLOCK_PENALTY);
+        self.update(|delta| {
+            delta.known_invalid_blocks += 1;
+            delta.misbehavior_score += penalty;
+        });
+        penalty
+    }
+}
+
+impl<N: Network> PeerState<N> {
+    /// Inserts a solution commitment received from the peer, returning the previously seen timestamp if it existed.
+    pub fn insert_inbound_solution(&self, solution: PuzzleCommitment<N>) -> Option<OffsetDateTime> {
+        insert_known(&self.inbound_solutions, solution)
+    }
+
+    /// Inserts a transaction ID received from the peer, returning the previously seen timestamp if it existed.
+    pub fn insert_inbound_transaction(&self, transaction: N::TransactionID) -> Option<OffsetDateTime> {
+        insert_known(&self.inbound_transactions, transaction)
+    }
+
+    /// Inserts a solution commitment sent to the peer, returning the previously seen timestamp if it existed.
+    pub fn insert_outbound_solution(&self, solution: PuzzleCommitment<N>) -> Option<OffsetDateTime> {
+        insert_known(&self.outbound_solutions, solution)
+    }
+
+    /// Inserts a transaction ID sent to the peer, returning the previously seen timestamp if it existed.
+    pub fn insert_outbound_transaction(&self, transaction: N::TransactionID) -> Option<OffsetDateTime> {
+        insert_known(&self.outbound_transactions, transaction)
+    }
+
+    /// Removes a transaction ID sent to the peer, so that the transaction is sent again, if the peer requests it.
+    pub fn remove_outbound_transaction(&self, transaction: &N::TransactionID) -> Option<OffsetDateTime> {
+        self.outbound_transactions.lock().remove(transaction)
+    }
+}
+
+/// Inserts the given key into the given inventory, evicting the oldest keys beyond `MAX_KNOWN_INVENTORY`,
+/// and returns the previously seen timestamp if it existed.
+fn insert_known<K: Eq + Hash>(inventory: &Mutex<LinkedHashMap<K, OffsetDateTime>>, key: K) -> Option<OffsetDateTime> {
+    let mut inventory = inventory.lock();
+    let previous = inventory.insert(key, OffsetDateTime::now_utc());
+    while inventory.len() > MAX_KNOWN_INVENTORY {
+        inventory.pop_front();
+    }
+    previous
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::{Field, Testnet3, Uniform};
+
+    use std::net::Ipv4Addr;
+
+    type CurrentNetwork = Testnet3;
+
+    fn sample_state() -> (PeerState<CurrentNetwork>, mpsc::UnboundedReceiver<(SocketAddr, PeerDelta)>) {
+        let (delta_sender, delta_receiver) = mpsc::unbounded_channel();
+        let peer_ip = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234);
+        (PeerState::new(peer_ip, 0, delta_sender), delta_receiver)
+    }
+
+    #[test]
+    fn test_known_inventory() {
+        let (state, _) = sample_state();
+        let solution = PuzzleCommitment::<CurrentNetwork>::default();
+        let transaction = Default::default();
+
+        // Ensure each inventory remembers its items independently.
+        assert!(state.insert_inbound_solution(solution).is_none());
+        assert!(state.insert_inbound_solution(solution).is_some());
+        assert!(state.insert_inbound_transaction(transaction).is_none());
+        assert!(state.insert_inbound_transaction(transaction).is_some());
+        assert!(state.insert_outbound_solution(solution).is_none());
+        assert!(state.insert_outbound_solution(solution).is_some());
+        assert!(state.insert_outbound_transaction(transaction).is_none());
+        assert!(state.insert_outbound_transaction(transaction).is_some());
+        assert_eq!(state.inbound_transactions.lock().len(), 1);
+
+        // Ensure the oldest items are evicted beyond the maximum.
+        let rng = &mut rand::thread_rng();
+        for _ in 0..MAX_KNOWN_INVENTORY {
+            state.insert_inbound_transaction(Field::<CurrentNetwork>::rand(rng).into());
+        }
+        assert_eq!(state.inbound_transactions.lock().len(), MAX_KNOWN_INVENTORY);
+        assert!(state.insert_inbound_transaction(transaction).is_none());
+    }
+
+    #[test]
+    fn test_flush_delta() {
+        let (state, mut delta_receiver) = sample_state();
+
+        // Ensure an empty delta is not sent.
+        state.flush();
+        assert!(delta_receiver.try_recv().is_err());
+
+        // Ensure the pending delta is sent on a flush, and reset.
+        state.record_inbound("Ping");
+        state.record_outbound("Pong");
+        state.record_misbehavior();
+        state.flush();
+        let (peer_ip, delta) = delta_receiver.try_recv().unwrap();
+        assert_eq!(peer_ip, state.ip());
+        assert_eq!(delta.messages_received.get("Ping"), Some(&1));
+        assert_eq!(delta.messages_sent.get("Pong"), Some(&1));
+        assert_eq!(delta.misbehavior_score, 1);
+        assert!(state.pending_delta().is_empty());
+
+        // Ensure the delta of a busy peer is sent without a flush.
+        for _ in 0..MAX_DELTA_MESSAGES {
+            state.record_inbound("Ping");
+        }
+        let (_, delta) = delta_receiver.try_recv().unwrap();
+        assert_eq!(delta.num_messages(), MAX_DELTA_MESSAGES);
+        assert!(state.pending_delta().is_empty());
+    }
+}
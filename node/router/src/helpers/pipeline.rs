@@ -0,0 +1,512 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+pub use snarkos_node_ledger::SerialBlock;
+
+use crate::{OperationClass, StorageGuard};
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+use snarkvm::prelude::Network;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use tokio::{
+    sync::{mpsc, OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// The default byte budget of the block processing pipeline (256 MiB).
+pub const DEFAULT_PIPELINE_BYTE_BUDGET: usize = 256 * 1024 * 1024;
+/// The number of items that are verified concurrently.
+pub const NUM_PIPELINE_VERIFIERS: usize = 4;
+
+/// An item of the block processing pipeline, which is accounted for by its size in bytes.
+pub trait PipelineItem: Send + Sync + 'static {
+    /// Returns the block height of the item.
+    fn height(&self) -> u32;
+
+    /// Returns the size of the item, in bytes.
+    fn num_bytes(&self) -> usize;
+}
+
+impl<N: Network> PipelineItem for SerialBlock<N> {
+    fn height(&self) -> u32 {
+        self.block().height()
+    }
+
+    fn num_bytes(&self) -> usize {
+        SerialBlock::num_bytes(self)
+    }
+}
+
+/// The stages of the block processing pipeline.
+pub trait PipelineStages<T: PipelineItem>: Clone + Send + Sync + 'static {
+    /// Verifies the given item. This is called concurrently, ahead of the commit of the previous items.
+    fn verify(&self, item: &T) -> Result<()>;
+
+    /// Commits the given item. This is called in order, after the item is verified.
+    fn commit(&self, item: T) -> Result<()>;
+
+    /// Handles the rejection of the item at the given height, after which the pipeline resumes from this height.
+    fn reject(&self, height: u32, error: anyhow::Error);
+}
+
+/// The room reserved for an item in a queue, which is released when the item leaves the queue.
+struct Reservation {
+    /// The permits of the byte budget.
+    _permit: OwnedSemaphorePermit,
+    /// The number of items in the queue.
+    num_items: Arc<AtomicUsize>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.num_items.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A queue of the block processing pipeline, bounded by a byte budget.
+struct ByteQueue {
+    /// The name of the stage consuming the queue.
+    stage: &'static str,
+    /// The remaining byte budget of the queue.
+    budget: Arc<Semaphore>,
+    /// The byte budget of the queue.
+    capacity: u32,
+    /// The number of items in the queue.
+    num_items: Arc<AtomicUsize>,
+}
+
+impl ByteQueue {
+    /// Initializes a new queue with the given byte budget.
+    fn new(stage: &'static str, capacity: usize) -> Self {
+        // Bound the byte budget, as the permits of a reservation are counted in 32 bits.
+        let capacity = capacity.clamp(1, u32::MAX as usize) as u32;
+        Self { stage, budget: Arc::new(Semaphore::new(capacity as usize)), capacity, num_items: Default::default() }
+    }
+
+    /// Returns the number of bytes in the queue.
+    fn num_bytes(&self) -> usize {
+        self.capacity as usize - self.budget.available_permits()
+    }
+
+    /// Returns the number of items in the queue.
+    fn num_items(&self) -> usize {
+        self.num_items.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of permits for an item of the given size.
+    /// An item larger than the byte budget takes the entire budget, so that it is processed alone.
+    fn num_permits(&self, num_bytes: usize) -> u32 {
+        num_bytes.clamp(1, self.capacity as usize) as u32
+    }
+
+    /// Reserves room for an item of the given size, waiting for the earlier items to leave the queue if needed.
+    async fn reserve(&self, num_bytes: usize) -> Result<Reservation> {
+        // Attempt to reserve the room right away.
+        let permit = match self.budget.clone().try_acquire_many_owned(self.num_permits(num_bytes)) {
+            Ok(permit) => permit,
+            Err(_) => {
+                // Otherwise, wait for the room, and record the duration of the stall.
+                let timer = Instant::now();
+                let permit = self.budget.clone().acquire_many_owned(self.num_permits(num_bytes)).await?;
+                trace!("The {} queue stalled for {:?}", self.stage, timer.elapsed());
+                #[cfg(feature = "metrics")]
+                metrics::histogram!(metrics::sync::STALL_DURATION, timer.elapsed().as_secs_f64(), "stage" => self.stage);
+                permit
+            }
+        };
+        self.num_items.fetch_add(1, Ordering::SeqCst);
+        Ok(Reservation { _permit: permit, num_items: self.num_items.clone() })
+    }
+
+    /// Updates the metrics of the queue.
+    fn update_metrics(&self) {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!(metrics::sync::QUEUE_BYTES, self.num_bytes() as f64, "stage" => self.stage);
+            metrics::gauge!(metrics::sync::QUEUE_BLOCKS, self.num_items() as f64, "stage" => self.stage);
+        }
+    }
+}
+
+/// An item in a queue of the pipeline.
+struct Queued<T: PipelineItem> {
+    /// The item.
+    item: T,
+    /// The generation of the pipeline, at the time the item entered it.
+    generation: u64,
+    /// The room reserved for the item in its queue.
+    reservation: Reservation,
+}
+
+/// The position of the pipeline.
+#[derive(Clone, Debug)]
+struct Position {
+    /// The block height of the next item to enter the pipeline.
+    next_height: u32,
+    /// The generation of the pipeline, which increments on every rejection, to discard the items after it.
+    generation: u64,
+    /// The block heights of the rejected items, indexed by the generation they ended.
+    rejected_heights: Vec<u32>,
+}
+
+impl Position {
+    /// Returns `true` if the item at the given height, from the given generation, was not discarded by a rejection.
+    fn is_current(&self, generation: u64, height: u32) -> bool {
+        self.rejected_heights.iter().skip(generation as usize).all(|rejected_height| height < *rejected_height)
+    }
+}
+
+/// A block processing pipeline, which verifies and commits the items pushed into it, in order.
+///
+/// Each stage consumes a queue bounded by its share of the byte budget, so that the pipeline
+/// stalls the producer instead of buffering without bound when a later stage falls behind.
+pub struct BlockPipeline<T: PipelineItem> {
+    /// The sender of the items to verify.
+    sender: mpsc::UnboundedSender<Queued<T>>,
+    /// The queue of the items to verify.
+    verify_queue: Arc<ByteQueue>,
+    /// The queue of the items to commit.
+    commit_queue: Arc<ByteQueue>,
+    /// The position of the pipeline.
+    position: Arc<Mutex<Position>>,
+    /// The number of producers waiting for room in the verify queue.
+    num_stalled: Arc<AtomicUsize>,
+    /// The stage handles.
+    handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl<T: PipelineItem> Clone for BlockPipeline<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            verify_queue: self.verify_queue.clone(),
+            commit_queue: self.commit_queue.clone(),
+            position: self.position.clone(),
+            num_stalled: self.num_stalled.clone(),
+            handles: self.handles.clone(),
+        }
+    }
+}
+
+impl<T: PipelineItem> BlockPipeline<T> {
+    /// Starts a new pipeline, expecting the given height next, with the given byte budget split between the stages.
+    /// The commits are guarded by the given storage guard, which switches the node into degraded mode when they stall.
+    pub fn start<S: PipelineStages<T>>(next_height: u32, byte_budget: usize, stages: S, storage: StorageGuard) -> Self {
+        let (sender, verify_receiver) = mpsc::unbounded_channel();
+        let (commit_sender, mut commit_receiver) = mpsc::unbounded_channel::<Queued<T>>();
+
+        let pipeline = Self {
+            sender,
+            verify_queue: Arc::new(ByteQueue::new("verify", byte_budget / 2)),
+            commit_queue: Arc::new(ByteQueue::new("commit", byte_budget / 2)),
+            position: Arc::new(Mutex::new(Position { next_height, generation: 0, rejected_heights: Vec::new() })),
+            num_stalled: Default::default(),
+            handles: Default::default(),
+        };
+
+        // Start the verify stage.
+        let (pipeline_clone, stages_clone) = (pipeline.clone(), stages.clone());
+        let verify_handle = tokio::spawn(async move {
+            UnboundedReceiverStream::new(verify_receiver)
+                .map(|queued: Queued<T>| {
+                    let (pipeline, stages) = (pipeline_clone.clone(), stages_clone.clone());
+                    async move {
+                        let (height, generation) = (queued.item.height(), queued.generation);
+                        // Skip the verification of the items that were discarded.
+                        if !pipeline.is_current(generation, height) {
+                            return None;
+                        }
+                        // Verify the item in a blocking task, as verification is CPU-bound.
+                        let stages_clone = stages.clone();
+                        match tokio::task::spawn_blocking(move || {
+                            let result = stages_clone.verify(&queued.item);
+                            (queued, result)
+                        })
+                        .await
+                        {
+                            Ok(verified) => Some(verified),
+                            Err(error) => {
+                                let error = anyhow!("The verify stage of the block pipeline panicked - {error}");
+                                pipeline.reject(&stages, height, generation, error);
+                                None
+                            }
+                        }
+                    }
+                })
+                .buffered(NUM_PIPELINE_VERIFIERS)
+                .for_each(|verified| {
+                    let (pipeline, stages, commit_sender) =
+                        (pipeline_clone.clone(), stages_clone.clone(), commit_sender.clone());
+                    async move {
+                        // Skip the items that were discarded.
+                        let (Queued { item, generation, reservation }, result) = match verified {
+                            Some(verified) => verified,
+                            None => return,
+                        };
+                        // Discard the items after a rejection.
+                        if !pipeline.is_current(generation, item.height()) {
+                            return;
+                        }
+                        // If the item is invalid, reject it.
+                        if let Err(error) = result {
+                            return pipeline.reject(&stages, item.height(), generation, error);
+                        }
+                        // Move the item into the commit queue, waiting for room if the commit stage falls behind.
+                        let commit_reservation = match pipeline.commit_queue.reserve(item.num_bytes()).await {
+                            Ok(commit_reservation) => commit_reservation,
+                            Err(error) => return warn!("Failed to reserve room in the commit queue - {error}"),
+                        };
+                        drop(reservation);
+                        if commit_sender.send(Queued { item, generation, reservation: commit_reservation }).is_err() {
+                            warn!("Failed to send an item to the commit stage of the block pipeline");
+                        }
+                        pipeline.update_metrics();
+                    }
+                })
+                .await
+        });
+
+        // Start the commit stage.
+        let pipeline_clone = pipeline.clone();
+        let commit_handle = tokio::spawn(async move {
+            while let Some(Queued { item, generation, reservation }) = commit_receiver.recv().await {
+                let height = item.height();
+                // Discard the items after a rejection.
+                if !pipeline_clone.is_current(generation, height) {
+                    continue;
+                }
+                // Commit the item in a blocking task, as it writes to storage.
+                let stages_clone = stages.clone();
+                let result = storage.run_once(OperationClass::Commit, move || stages_clone.commit(item)).await;
+                // Release the room of the item, as it left the pipeline.
+                drop(reservation);
+                // If the commit failed, reject the item.
+                if let Err(error) = result {
+                    pipeline_clone.reject(&stages, height, generation, error);
+                }
+                pipeline_clone.update_metrics();
+            }
+        });
+
+        pipeline.handles.lock().extend([verify_handle, commit_handle]);
+        pipeline
+    }
+
+    /// Returns the block height of the next item to push into the pipeline.
+    pub fn next_height(&self) -> u32 {
+        self.position.lock().next_height
+    }
+
+    /// Returns `true` if a producer is waiting for room in the verify queue.
+    pub fn is_stalled(&self) -> bool {
+        self.num_stalled.load(Ordering::SeqCst) > 0
+    }
+
+    /// Returns the number of bytes in the verify and commit queues.
+    pub fn num_bytes(&self) -> (usize, usize) {
+        (self.verify_queue.num_bytes(), self.commit_queue.num_bytes())
+    }
+
+    /// Returns the number of items in the verify and commit queues.
+    pub fn num_items(&self) -> (usize, usize) {
+        (self.verify_queue.num_items(), self.commit_queue.num_items())
+    }
+
+    /// Pushes the given item into the pipeline, waiting for room in the verify queue if needed.
+    /// Returns `false` if the item is not the next item, in which case it is dropped.
+    pub async fn push(&self, item: T) -> bool {
+        // Ensure the item is the next item.
+        let expected = self.position.lock().clone();
+        if item.height() != expected.next_height {
+            return false;
+        }
+
+        // Reserve room for the item in the verify queue.
+        self.num_stalled.fetch_add(1, Ordering::SeqCst);
+        let reservation = self.verify_queue.reserve(item.num_bytes()).await;
+        self.num_stalled.fetch_sub(1, Ordering::SeqCst);
+        let reservation = match reservation {
+            Ok(reservation) => reservation,
+            Err(error) => {
+                warn!("Failed to reserve room in the verify queue - {error}");
+                return false;
+            }
+        };
+
+        // Ensure the pipeline did not move while waiting for room.
+        let mut position = self.position.lock();
+        if item.height() != position.next_height || position.generation != expected.generation {
+            return false;
+        }
+        // Send the item to the verify stage.
+        if self.sender.send(Queued { item, generation: expected.generation, reservation }).is_err() {
+            warn!("Failed to send an item to the verify stage of the block pipeline");
+            return false;
+        }
+        position.next_height += 1;
+        drop(position);
+
+        self.update_metrics();
+        true
+    }
+
+    /// Shuts down the pipeline, discarding the items in it.
+    pub fn shut_down(&self) {
+        self.handles.lock().iter().for_each(|handle| handle.abort());
+    }
+}
+
+impl<T: PipelineItem> BlockPipeline<T> {
+    /// Returns `true` if the item at the given height, from the given generation, was not discarded by a rejection.
+    fn is_current(&self, generation: u64, height: u32) -> bool {
+        self.position.lock().is_current(generation, height)
+    }
+
+    /// Rejects the item at the given height, discarding the items after it, and resumes the pipeline from this height.
+    fn reject<S: PipelineStages<T>>(&self, stages: &S, height: u32, generation: u64, error: anyhow::Error) {
+        {
+            let mut position = self.position.lock();
+            // Ensure the item was not already discarded.
+            if !position.is_current(generation, height) {
+                return;
+            }
+            position.next_height = height;
+            position.generation += 1;
+            position.rejected_heights.push(height);
+        }
+        stages.reject(height, error);
+    }
+
+    /// Updates the metrics of the queues.
+    fn update_metrics(&self) {
+        self.verify_queue.update_metrics();
+        self.commit_queue.update_metrics();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::bail;
+    use deadline::deadline;
+    use std::time::Duration;
+
+    /// A synthetic block, with a payload of the given size.
+    struct SampleBlock {
+        height: u32,
+        payload: Vec<u8>,
+    }
+
+    impl PipelineItem for SampleBlock {
+        fn height(&self) -> u32 {
+            self.height
+        }
+
+        fn num_bytes(&self) -> usize {
+            self.payload.len()
+        }
+    }
+
+    /// The stages, which reject the blocks at the invalid heights once, and record the committed and rejected heights.
+    #[derive(Clone, Default)]
+    struct SampleStages {
+        invalid: Arc<Mutex<Vec<u32>>>,
+        committed: Arc<Mutex<Vec<u32>>>,
+        rejected: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl PipelineStages<SampleBlock> for SampleStages {
+        fn verify(&self, block: &SampleBlock) -> Result<()> {
+            let mut invalid = self.invalid.lock();
+            match invalid.iter().position(|height| *height == block.height) {
+                Some(index) => {
+                    invalid.remove(index);
+                    bail!("Block {} is invalid", block.height)
+                }
+                None => Ok(()),
+            }
+        }
+
+        fn commit(&self, block: SampleBlock) -> Result<()> {
+            self.committed.lock().push(block.height);
+            Ok(())
+        }
+
+        fn reject(&self, height: u32, _error: anyhow::Error) {
+            self.rejected.lock().push(height);
+        }
+    }
+
+    fn sample_block(height: u32) -> SampleBlock {
+        SampleBlock { height, payload: vec![0u8; 16] }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_commits_in_order() {
+        let stages = SampleStages::default();
+        let pipeline = BlockPipeline::start(1, 1024, stages.clone(), Default::default());
+
+        // Ensure only the next block is accepted.
+        assert!(!pipeline.push(sample_block(2)).await);
+        for height in 1..=100 {
+            assert!(pipeline.push(sample_block(height)).await);
+        }
+        assert_eq!(pipeline.next_height(), 101);
+
+        // Ensure the blocks are committed in order.
+        let committed = stages.committed.clone();
+        deadline!(Duration::from_secs(5), move || committed.lock().len() == 100);
+        assert_eq!(*stages.committed.lock(), (1..=100).collect::<Vec<_>>());
+        assert_eq!(pipeline.num_bytes(), (0, 0));
+        assert_eq!(pipeline.num_items(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_resumes_after_rejection() {
+        let stages = SampleStages::default();
+        stages.invalid.lock().push(5);
+        let pipeline = BlockPipeline::start(1, 1024, stages.clone(), Default::default());
+
+        // Push the blocks, of which the ones after the rejection are discarded.
+        for height in 1..=10 {
+            pipeline.push(sample_block(height)).await;
+        }
+        let rejected = stages.rejected.clone();
+        deadline!(Duration::from_secs(5), move || !rejected.lock().is_empty());
+        assert_eq!(*stages.rejected.lock(), vec![5]);
+        assert_eq!(pipeline.next_height(), 5);
+
+        // Push the blocks again, from the rejected block.
+        for height in 5..=10 {
+            assert!(pipeline.push(sample_block(height)).await);
+        }
+        let committed = stages.committed.clone();
+        deadline!(Duration::from_secs(5), move || committed.lock().len() == 10);
+        assert_eq!(*stages.committed.lock(), (1..=10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,395 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_messages::UnconfirmedTransaction;
+use snarkvm::prelude::Network;
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+};
+use tokio::time::Instant;
+
+/// The maximum number of transactions queued for the relay to each peer.
+pub const MAX_RELAY_QUEUE_LEN: usize = 1_024;
+/// The maximum number of transactions relayed to each peer in a flush of its queue.
+pub const RELAY_BUDGET_PER_FLUSH: usize = 16;
+/// The share of the budget of a flush, in percent, that the transactions of a single source peer may take,
+/// as long as the transactions of other sources are queued.
+pub const RELAY_SOURCE_SHARE_IN_PERCENT: usize = 25;
+/// The priority gained by a queued transaction for every second it waits, in microcredits per byte,
+/// so that the transactions with a low fee rate are eventually relayed.
+pub const RELAY_AGING_PER_SEC: f64 = 0.1;
+
+/// The statistics of the relay queue of a peer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RelayStats {
+    /// The number of transactions queued for the relay to the peer.
+    pub depth: usize,
+    /// The number of transactions relayed to the peer from its queue.
+    pub relayed: u64,
+    /// The number of transactions dropped from the queue, as it was full.
+    pub dropped: u64,
+    /// The number of transactions dropped from the queue, as they were mined or evicted from the memory pool.
+    pub purged: u64,
+}
+
+/// A transaction queued for the relay to a peer.
+struct QueuedRelay<N: Network> {
+    /// The message of the transaction.
+    message: UnconfirmedTransaction<N>,
+    /// The fee rate of the transaction, in microcredits per byte.
+    fee_rate: f64,
+    /// The IP of the peer that sent the transaction, or `None` if it was submitted to this node.
+    source: Option<SocketAddr>,
+    /// The time at which the transaction was queued.
+    queued_at: Instant,
+}
+
+impl<N: Network> QueuedRelay<N> {
+    /// Returns the priority of the transaction as of the given time, which is its fee rate, aged by its wait.
+    fn priority(&self, now: Instant) -> f64 {
+        self.fee_rate + now.saturating_duration_since(self.queued_at).as_secs_f64() * RELAY_AGING_PER_SEC
+    }
+}
+
+/// The relay queue of a peer.
+struct RelayQueue<N: Network> {
+    /// The queued transactions, in the order they were queued.
+    entries: IndexMap<N::TransactionID, QueuedRelay<N>>,
+    /// The statistics of the queue, whose depth is filled in as they are read.
+    stats: RelayStats,
+}
+
+impl<N: Network> Default for RelayQueue<N> {
+    fn default() -> Self {
+        Self { entries: Default::default(), stats: Default::default() }
+    }
+}
+
+impl<N: Network> RelayQueue<N> {
+    /// Drops the queued transaction with the lowest priority among the source with the most queued transactions,
+    /// so that a flood from one peer only displaces its own transactions.
+    fn evict(&mut self, now: Instant) {
+        let mut counts = HashMap::<Option<SocketAddr>, usize>::new();
+        for entry in self.entries.values() {
+            *counts.entry(entry.source).or_default() += 1;
+        }
+        let source = match counts.into_iter().max_by_key(|(_, count)| *count) {
+            Some((source, _)) => source,
+            None => return,
+        };
+        // Drop the most recently queued transaction among those of the lowest priority.
+        let lowest = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.source == source)
+            .min_by(|(_, a), (_, b)| a.priority(now).total_cmp(&b.priority(now)).then(b.queued_at.cmp(&a.queued_at)))
+            .map(|(transaction_id, _)| *transaction_id);
+        if let Some(transaction_id) = lowest {
+            self.entries.shift_remove(&transaction_id);
+            self.stats.dropped += 1;
+        }
+    }
+
+    /// Removes and returns up to `budget` transactions in order of priority, as of the given time. The transactions
+    /// of a source are capped at its share of the budget, unless the other sources leave the budget unused.
+    fn pop(&mut self, now: Instant, budget: usize) -> Vec<UnconfirmedTransaction<N>> {
+        // Order the queued transactions by decreasing priority, then by the order they were queued.
+        let mut order = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, (transaction_id, entry))| (entry.priority(now), index, *transaction_id, entry.source))
+            .collect::<Vec<_>>();
+        order.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        // Select the transactions of each source up to its share of the budget.
+        let share = (budget * RELAY_SOURCE_SHARE_IN_PERCENT / 100).max(1);
+        let mut selected = Vec::with_capacity(budget);
+        let mut deferred = Vec::new();
+        let mut num_selected = HashMap::<Option<SocketAddr>, usize>::new();
+        for (_, _, transaction_id, source) in order {
+            if selected.len() >= budget {
+                break;
+            }
+            let count = num_selected.entry(source).or_default();
+            match *count < share {
+                true => {
+                    *count += 1;
+                    selected.push(transaction_id);
+                }
+                false => deferred.push(transaction_id),
+            }
+        }
+        // If the other sources left the budget unused, fill it with the deferred transactions, in order of priority.
+        let remaining = budget.saturating_sub(selected.len());
+        selected.extend(deferred.into_iter().take(remaining));
+
+        let messages = selected
+            .iter()
+            .filter_map(|transaction_id| self.entries.shift_remove(transaction_id))
+            .map(|entry| entry.message)
+            .collect::<Vec<_>>();
+        self.stats.relayed += messages.len() as u64;
+        messages
+    }
+}
+
+/// The relay queues of the peers, which order the unconfirmed transactions relayed to each peer by their fee rate,
+/// so that a burst of transactions with a low fee rate does not delay those with a high fee rate.
+///
+/// Each flush relays up to `RELAY_BUDGET_PER_FLUSH` transactions to each peer, of which the transactions received
+/// from a single peer take at most `RELAY_SOURCE_SHARE_IN_PERCENT` percent while others are queued, and a queued
+/// transaction gains priority as it waits, so that the transactions with a low fee rate are not starved.
+pub struct RelayQueues<N: Network> {
+    /// The map of peer IPs to their relay queue.
+    queues: Mutex<HashMap<SocketAddr, RelayQueue<N>>>,
+}
+
+impl<N: Network> Default for RelayQueues<N> {
+    fn default() -> Self {
+        Self { queues: Default::default() }
+    }
+}
+
+impl<N: Network> RelayQueues<N> {
+    /// Returns the number of transactions queued for the relay to the given peer.
+    pub fn depth(&self, peer_ip: &SocketAddr) -> usize {
+        self.queues.lock().get(peer_ip).map_or(0, |queue| queue.entries.len())
+    }
+
+    /// Returns the statistics of the relay queues, by peer IP.
+    pub fn stats(&self) -> BTreeMap<SocketAddr, RelayStats> {
+        self.queues
+            .lock()
+            .iter()
+            .map(|(peer_ip, queue)| (*peer_ip, RelayStats { depth: queue.entries.len(), ..queue.stats }))
+            .collect()
+    }
+
+    /// Queues the given transaction, with the given fee rate (in microcredits per byte), for the relay to the given
+    /// peer. The source is the peer that sent the transaction, or `None` if it was submitted to this node.
+    /// If the queue is full, the transaction with the lowest priority of the source with the most queued
+    /// transactions is dropped.
+    pub fn push(
+        &self,
+        peer_ip: SocketAddr,
+        message: UnconfirmedTransaction<N>,
+        fee_rate: f64,
+        source: Option<SocketAddr>,
+    ) {
+        let mut queues = self.queues.lock();
+        let queue = queues.entry(peer_ip).or_default();
+        // If the transaction is already queued for the peer, keep its place.
+        if queue.entries.contains_key(&message.transaction_id) {
+            return;
+        }
+        // Ensure the fee rate is a valid priority.
+        let fee_rate = match fee_rate.is_finite() {
+            true => fee_rate.max(0.0),
+            false => 0.0,
+        };
+        let now = Instant::now();
+        queue.entries.insert(message.transaction_id, QueuedRelay { message, fee_rate, source, queued_at: now });
+        if queue.entries.len() > MAX_RELAY_QUEUE_LEN {
+            queue.evict(now);
+        }
+    }
+
+    /// Removes and returns the transactions to relay to each peer in this flush, as of the given time.
+    pub fn flush(&self, now: Instant) -> Vec<(SocketAddr, UnconfirmedTransaction<N>)> {
+        let mut queues = self.queues.lock();
+        let mut relays = Vec::new();
+        for (peer_ip, queue) in queues.iter_mut().filter(|(_, queue)| !queue.entries.is_empty()) {
+            relays.extend(queue.pop(now, RELAY_BUDGET_PER_FLUSH).into_iter().map(|message| (*peer_ip, message)));
+        }
+        relays
+    }
+
+    /// Drops the given transactions from the relay queues, as they were mined or evicted from the memory pool.
+    pub fn purge(&self, transaction_ids: &[N::TransactionID]) {
+        if transaction_ids.is_empty() {
+            return;
+        }
+        let transaction_ids = transaction_ids.iter().collect::<HashSet<_>>();
+        for queue in self.queues.lock().values_mut() {
+            let depth = queue.entries.len();
+            queue.entries.retain(|transaction_id, _| !transaction_ids.contains(transaction_id));
+            queue.stats.purged += (depth - queue.entries.len()) as u64;
+        }
+    }
+
+    /// Removes the relay queue of the given peer.
+    pub fn remove_peer(&self, peer_ip: &SocketAddr) {
+        self.queues.lock().remove(peer_ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node_messages::Data;
+    use snarkvm::prelude::{Block, Field, FromBytes, Testnet3, Uniform};
+
+    use tokio::time::Duration;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the given number of messages of sample transactions, each with a random ID.
+    fn sample_messages(num_messages: usize) -> Vec<UnconfirmedTransaction<CurrentNetwork>> {
+        let block = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let transaction = block.transactions().iter().next().unwrap().clone();
+        let message = UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction));
+        let rng = &mut rand::thread_rng();
+        (0..num_messages)
+            .map(|_| UnconfirmedTransaction { transaction_id: Field::<CurrentNetwork>::rand(rng).into(), ..message.clone() })
+            .collect()
+    }
+
+    /// Returns the IP of the peer with the given index.
+    fn peer_ip(index: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 4130 + index))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_high_fee_transaction_overtakes_a_flood() {
+        let queues = RelayQueues::<CurrentNetwork>::default();
+        let (peer, spammer) = (peer_ip(0), Some(peer_ip(1)));
+
+        // Saturate the queue with transactions of a low fee rate, beyond its capacity.
+        for message in sample_messages(MAX_RELAY_QUEUE_LEN + 100) {
+            queues.push(peer, message, 0.01, spammer);
+        }
+        assert_eq!(queues.depth(&peer), MAX_RELAY_QUEUE_LEN);
+        assert_eq!(queues.stats()[&peer].dropped, 100);
+
+        // Ensure a transaction with a high fee rate, from the same source, displaces a low-fee transaction,
+        // and is relayed in the next flush.
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let high_fee = sample_messages(1).remove(0);
+        queues.push(peer, high_fee.clone(), 10.0, spammer);
+        let relays = queues.flush(Instant::now());
+        assert_eq!(relays.len(), RELAY_BUDGET_PER_FLUSH);
+        assert_eq!(relays[0], (peer, high_fee));
+
+        let stats = queues.stats()[&peer];
+        assert_eq!(stats.depth, MAX_RELAY_QUEUE_LEN - RELAY_BUDGET_PER_FLUSH);
+        assert_eq!((stats.relayed, stats.dropped), (RELAY_BUDGET_PER_FLUSH as u64, 101));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_aging_relays_low_fee_transactions() {
+        let queues = RelayQueues::<CurrentNetwork>::default();
+        let peer = peer_ip(0);
+        let low_fee = sample_messages(1).remove(0);
+        queues.push(peer, low_fee.clone(), 0.0, Some(peer_ip(1)));
+
+        // Keep the budget full with transactions of a higher fee rate, and ensure the low-fee transaction
+        // is relayed once its wait outweighs the difference in fee rates.
+        let (fee_rate, interval) = (1.0, Duration::from_millis(100));
+        let max_flushes = (fee_rate / RELAY_AGING_PER_SEC / interval.as_secs_f64()) as usize + 2;
+        let mut num_flushes = 0;
+        loop {
+            num_flushes += 1;
+            assert!(num_flushes <= max_flushes, "The low-fee transaction was not relayed");
+            for message in sample_messages(RELAY_BUDGET_PER_FLUSH) {
+                queues.push(peer, message, fee_rate, Some(peer_ip(1)));
+            }
+            tokio::time::advance(interval).await;
+            if queues.flush(Instant::now()).iter().any(|(_, message)| *message == low_fee) {
+                break;
+            }
+        }
+        assert!(num_flushes > 1);
+    }
+
+    #[test]
+    fn test_source_is_capped_at_its_share() {
+        let queues = RelayQueues::<CurrentNetwork>::default();
+        let (peer, flooder) = (peer_ip(0), Some(peer_ip(1)));
+        let share = RELAY_BUDGET_PER_FLUSH * RELAY_SOURCE_SHARE_IN_PERCENT / 100;
+
+        // Queue a flood from one source with a higher fee rate than the transactions of the other sources,
+        // each of which queues its share of the budget.
+        let flood = sample_messages(4 * RELAY_BUDGET_PER_FLUSH);
+        for message in &flood {
+            queues.push(peer, message.clone(), 2.0, flooder);
+        }
+        let others = sample_messages(RELAY_BUDGET_PER_FLUSH - share);
+        for (index, messages) in others.chunks(share).enumerate() {
+            for message in messages {
+                queues.push(peer, message.clone(), 1.0, Some(peer_ip(2 + index as u16)));
+            }
+        }
+
+        // Ensure the flood takes its share of the flush, and the other sources the remainder.
+        let relays = queues.flush(Instant::now());
+        assert_eq!(relays.len(), RELAY_BUDGET_PER_FLUSH);
+        assert_eq!(relays.iter().filter(|(_, message)| flood.contains(message)).count(), share);
+        assert!(others.iter().all(|message| relays.contains(&(peer, message.clone()))));
+
+        // Ensure the flood takes the whole budget once the other sources have nothing queued.
+        let relays = queues.flush(Instant::now());
+        assert_eq!(relays.len(), RELAY_BUDGET_PER_FLUSH);
+        assert!(relays.iter().all(|(_, message)| flood.contains(message)));
+    }
+
+    #[test]
+    fn test_overflow_evicts_from_the_largest_source() {
+        let queues = RelayQueues::<CurrentNetwork>::default();
+        let (peer, flooder, other) = (peer_ip(0), Some(peer_ip(1)), Some(peer_ip(2)));
+
+        // Ensure a flood, even with a higher fee rate, displaces its own transactions rather than the others.
+        let others = sample_messages(10);
+        for message in &others {
+            queues.push(peer, message.clone(), 0.0, other);
+        }
+        for message in sample_messages(MAX_RELAY_QUEUE_LEN) {
+            queues.push(peer, message, 5.0, flooder);
+        }
+        assert_eq!(queues.depth(&peer), MAX_RELAY_QUEUE_LEN);
+        assert_eq!(queues.stats()[&peer].dropped, 10);
+        let queued = queues.queues.lock()[&peer].entries.keys().copied().collect::<HashSet<_>>();
+        assert!(others.iter().all(|message| queued.contains(&message.transaction_id)));
+    }
+
+    #[test]
+    fn test_purge_and_remove_peer() {
+        let queues = RelayQueues::<CurrentNetwork>::default();
+        let messages = sample_messages(4);
+        for peer in [peer_ip(0), peer_ip(1)] {
+            for message in &messages {
+                queues.push(peer, message.clone(), 1.0, None);
+            }
+            // Ensure a transaction is queued once for each peer.
+            queues.push(peer, messages[0].clone(), 1.0, None);
+        }
+
+        // Ensure the purged transactions are dropped from every queue, and counted.
+        let purged = messages[..2].iter().map(|message| message.transaction_id).collect::<Vec<_>>();
+        queues.purge(&purged);
+        let stats = queues.stats();
+        assert!(stats.values().all(|stats| *stats == RelayStats { depth: 2, purged: 2, ..Default::default() }));
+        assert!(queues.flush(Instant::now()).iter().all(|(_, message)| !purged.contains(&message.transaction_id)));
+
+        queues.remove_peer(&peer_ip(0));
+        assert_eq!(queues.stats().len(), 1);
+    }
+}
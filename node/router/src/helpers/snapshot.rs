@@ -0,0 +1,279 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use snarkos_node_messages::{SnapshotChunk, SnapshotManifest};
+use snarkvm::prelude::Network;
+
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tokio::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+/// The default maximum number of snapshot streams served concurrently by a node.
+pub const DEFAULT_MAX_SNAPSHOT_STREAMS: usize = 2;
+/// The maximum number of chunk requests served to each snapshot stream, per second.
+pub const MAX_SNAPSHOT_CHUNKS_PER_SECOND: usize = 8;
+/// The interval in milliseconds in between the chunk requests of a snapshot download,
+/// which keeps the download well within the rate limit of the serving peer.
+pub const SNAPSHOT_CHUNK_INTERVAL_IN_MS: u64 = 250;
+/// The duration in seconds after which an idle snapshot stream is closed.
+pub const SNAPSHOT_STREAM_TIMEOUT_IN_SECS: u64 = 60;
+/// The duration in seconds to wait for the response to a snapshot request.
+pub const SNAPSHOT_RESPONSE_TIMEOUT_IN_SECS: u64 = 30;
+/// The maximum number of attempts to fetch the manifest, or a chunk, of a snapshot.
+pub const MAX_SNAPSHOT_ATTEMPTS: usize = 3;
+
+/// The response of a peer to a snapshot request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotResponse<N: Network> {
+    /// The manifest of the snapshot.
+    Manifest(SnapshotManifest<N>),
+    /// A chunk of the snapshot.
+    Chunk(SnapshotChunk),
+}
+
+/// A snapshot stream served to a peer.
+struct SnapshotStream {
+    /// The times of the chunk requests of the last second.
+    requests: VecDeque<Instant>,
+    /// The time of the last request of the stream.
+    last_seen: Instant,
+}
+
+/// The receiver of the responses to the snapshot requests of a download.
+pub struct SnapshotReceiver<N: Network> {
+    /// The receiver of the responses.
+    receiver: mpsc::Receiver<SnapshotResponse<N>>,
+    /// The time of the last chunk request.
+    last_request: Option<Instant>,
+}
+
+impl<N: Network> SnapshotReceiver<N> {
+    /// Waits until the next chunk request is due, so that the download stays within the rate limit of the peer.
+    pub async fn pace(&mut self) {
+        if let Some(last_request) = self.last_request {
+            tokio::time::sleep_until(last_request + Duration::from_millis(SNAPSHOT_CHUNK_INTERVAL_IN_MS)).await;
+        }
+        self.last_request = Some(Instant::now());
+    }
+
+    /// Waits for the response to the request of the given chunk (or of the manifest, if `None`), skipping the late
+    /// responses to the earlier requests. This returns `None` if no response is received in time.
+    pub async fn next(&mut self, chunk: Option<u32>) -> Option<SnapshotResponse<N>> {
+        let deadline = Instant::now() + Duration::from_secs(SNAPSHOT_RESPONSE_TIMEOUT_IN_SECS);
+        loop {
+            let response = tokio::time::timeout_at(deadline, self.receiver.recv()).await.ok()??;
+            match (&response, chunk) {
+                (SnapshotResponse::Manifest(..), None) => return Some(response),
+                (SnapshotResponse::Chunk(candidate), Some(index)) if candidate.index == index => return Some(response),
+                _ => trace!("Skipping a late snapshot response"),
+            }
+        }
+    }
+}
+
+/// The snapshot streams served by this node, and the snapshot download of this node, if one is in progress.
+pub struct Snapshots<N: Network> {
+    /// The maximum number of snapshot streams served concurrently, where `0` disables the serving of snapshots.
+    max_streams: AtomicUsize,
+    /// The map of the peers to their snapshot streams.
+    streams: Mutex<HashMap<SocketAddr, SnapshotStream>>,
+    /// The peer of the snapshot download in progress, and the sender of its responses.
+    download: Mutex<Option<(SocketAddr, mpsc::Sender<SnapshotResponse<N>>)>>,
+}
+
+impl<N: Network> Default for Snapshots<N> {
+    fn default() -> Self {
+        Self { max_streams: Default::default(), streams: Default::default(), download: Default::default() }
+    }
+}
+
+impl<N: Network> Snapshots<N> {
+    /// Returns the maximum number of snapshot streams served concurrently.
+    pub fn max_streams(&self) -> usize {
+        self.max_streams.load(Ordering::Relaxed)
+    }
+
+    /// Sets the maximum number of snapshot streams served concurrently, where `0` disables the serving of snapshots.
+    /// This must be called before the routing is initialized, as it determines the advertised capabilities.
+    pub fn set_max_streams(&self, max_streams: usize) {
+        self.max_streams.store(max_streams, Ordering::Relaxed);
+    }
+
+    /// Returns the number of open snapshot streams.
+    pub fn num_streams(&self) -> usize {
+        let mut streams = self.streams.lock();
+        Self::expire(&mut streams, Instant::now());
+        streams.len()
+    }
+
+    /// Opens (or refreshes) the snapshot stream of the given peer, which fails if the maximum number
+    /// of concurrent streams is reached.
+    pub fn open_stream(&self, peer_ip: SocketAddr) -> Result<()> {
+        let now = Instant::now();
+        let mut streams = self.streams.lock();
+        Self::expire(&mut streams, now);
+        if let Some(stream) = streams.get_mut(&peer_ip) {
+            stream.last_seen = now;
+            return Ok(());
+        }
+        let max_streams = self.max_streams();
+        if streams.len() >= max_streams {
+            bail!("Serving {} snapshot streams (maximum is {max_streams})", streams.len())
+        }
+        streams.insert(peer_ip, SnapshotStream { requests: Default::default(), last_seen: now });
+        Ok(())
+    }
+
+    /// Records a chunk request of the given peer, which fails if the peer has no open stream,
+    /// or if it exceeds `MAX_SNAPSHOT_CHUNKS_PER_SECOND`.
+    pub fn allow_chunk(&self, peer_ip: SocketAddr) -> Result<()> {
+        let now = Instant::now();
+        let mut streams = self.streams.lock();
+        Self::expire(&mut streams, now);
+        let stream = match streams.get_mut(&peer_ip) {
+            Some(stream) => stream,
+            None => bail!("Peer '{peer_ip}' has no open snapshot stream"),
+        };
+        while stream.requests.front().map_or(false, |time| now.duration_since(*time) >= Duration::from_secs(1)) {
+            stream.requests.pop_front();
+        }
+        if stream.requests.len() >= MAX_SNAPSHOT_CHUNKS_PER_SECOND {
+            bail!("Peer '{peer_ip}' exceeded {MAX_SNAPSHOT_CHUNKS_PER_SECOND} snapshot chunk requests per second")
+        }
+        stream.requests.push_back(now);
+        stream.last_seen = now;
+        Ok(())
+    }
+
+    /// Starts a snapshot download from the given peer, and returns the receiver of its responses.
+    /// This fails if a snapshot download is already in progress.
+    pub fn start_download(&self, peer_ip: SocketAddr) -> Result<SnapshotReceiver<N>> {
+        let mut download = self.download.lock();
+        if let Some((download_ip, _)) = &*download {
+            bail!("A snapshot download from '{download_ip}' is already in progress")
+        }
+        let (sender, receiver) = mpsc::channel(MAX_SNAPSHOT_ATTEMPTS);
+        *download = Some((peer_ip, sender));
+        Ok(SnapshotReceiver { receiver, last_request: None })
+    }
+
+    /// Ends the snapshot download in progress.
+    pub fn finish_download(&self) {
+        *self.download.lock() = None;
+    }
+
+    /// Returns the peer of the snapshot download in progress, if any.
+    pub fn download_peer(&self) -> Option<SocketAddr> {
+        self.download.lock().as_ref().map(|(peer_ip, _)| *peer_ip)
+    }
+
+    /// Delivers the given response of the given peer to the snapshot download in progress, and returns `false`
+    /// if the response is unsolicited, as no snapshot is being downloaded from the peer.
+    pub fn deliver(&self, peer_ip: SocketAddr, response: SnapshotResponse<N>) -> bool {
+        match &*self.download.lock() {
+            Some((download_ip, sender)) if *download_ip == peer_ip => {
+                // The responses in excess of the outstanding requests are dropped, as they are unsolicited.
+                if sender.try_send(response).is_err() {
+                    trace!("Dropped a snapshot response from '{peer_ip}'");
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Closes the snapshot stream of the given peer.
+    pub fn remove_peer(&self, peer_ip: &SocketAddr) {
+        self.streams.lock().remove(peer_ip);
+    }
+
+    /// Closes the snapshot streams that were idle for `SNAPSHOT_STREAM_TIMEOUT_IN_SECS` seconds.
+    fn expire(streams: &mut HashMap<SocketAddr, SnapshotStream>, now: Instant) {
+        let timeout = Duration::from_secs(SNAPSHOT_STREAM_TIMEOUT_IN_SECS);
+        streams.retain(|_, stream| now.duration_since(stream.last_seen) < timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkvm::prelude::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    fn test_snapshot_stream_limits() {
+        let snapshots = Snapshots::<CurrentNetwork>::default();
+        let (peer_a, peer_b, peer_c) =
+            ("1.1.1.1:4130".parse().unwrap(), "2.2.2.2:4130".parse().unwrap(), "3.3.3.3:4130".parse().unwrap());
+
+        // Ensure no stream is served by default.
+        assert!(snapshots.open_stream(peer_a).is_err());
+
+        // Ensure the number of concurrent streams is capped, and a stream is refreshed by its peer.
+        snapshots.set_max_streams(2);
+        assert!(snapshots.open_stream(peer_a).is_ok());
+        assert!(snapshots.open_stream(peer_b).is_ok());
+        assert!(snapshots.open_stream(peer_a).is_ok());
+        assert!(snapshots.open_stream(peer_c).is_err());
+        assert_eq!(snapshots.num_streams(), 2);
+
+        // Ensure the chunk requests require an open stream, and are rate limited.
+        assert!(snapshots.allow_chunk(peer_c).is_err());
+        for _ in 0..MAX_SNAPSHOT_CHUNKS_PER_SECOND {
+            assert!(snapshots.allow_chunk(peer_a).is_ok());
+        }
+        assert!(snapshots.allow_chunk(peer_a).is_err());
+        assert!(snapshots.allow_chunk(peer_b).is_ok());
+
+        // Ensure a stream is closed when its peer disconnects.
+        snapshots.remove_peer(&peer_a);
+        assert!(snapshots.open_stream(peer_c).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_download() {
+        let snapshots = Snapshots::<CurrentNetwork>::default();
+        let (peer_a, peer_b) = ("1.1.1.1:4130".parse().unwrap(), "2.2.2.2:4130".parse().unwrap());
+        let chunk = |index| SnapshotResponse::Chunk(SnapshotChunk { index, bytes: vec![index as u8].into() });
+
+        // Ensure the responses are unsolicited without a download.
+        assert!(!snapshots.deliver(peer_a, chunk(0)));
+
+        // Ensure only one download is in progress, and only the responses of its peer are delivered.
+        let mut receiver = snapshots.start_download(peer_a).unwrap();
+        assert!(snapshots.start_download(peer_b).is_err());
+        assert_eq!(snapshots.download_peer(), Some(peer_a));
+        assert!(!snapshots.deliver(peer_b, chunk(1)));
+
+        // Ensure a late response to an earlier request is skipped.
+        assert!(snapshots.deliver(peer_a, chunk(0)));
+        assert!(snapshots.deliver(peer_a, chunk(1)));
+        assert_eq!(receiver.next(Some(1)).await, Some(chunk(1)));
+
+        snapshots.finish_download();
+        assert!(snapshots.download_peer().is_none());
+        assert!(!snapshots.deliver(peer_a, chunk(2)));
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use core::time::Duration;
+use parking_lot::RwLock;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+/// The default timeout (in milliseconds) of a storage read.
+pub const DEFAULT_STORAGE_READ_TIMEOUT_IN_MS: u64 = 2_000;
+/// The default timeout (in milliseconds) of a storage batch commit.
+pub const DEFAULT_STORAGE_COMMIT_TIMEOUT_IN_MS: u64 = 30_000;
+/// The default number of retries of a storage operation that failed with a transient error.
+pub const DEFAULT_STORAGE_MAX_RETRIES: u32 = 3;
+/// The default delay (in milliseconds) before a storage operation is retried.
+pub const DEFAULT_STORAGE_RETRY_DELAY_IN_MS: u64 = 100;
+/// The default number of consecutive timeouts after which the node switches into degraded mode.
+pub const DEFAULT_STORAGE_BREAKER_THRESHOLD: u32 = 3;
+
+/// The class of a storage operation, which determines its timeout.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OperationClass {
+    /// A read, which is abandoned when it times out.
+    Read,
+    /// A batch commit, which is awaited after it times out, as a write cannot be abandoned without
+    /// losing the order of the writes.
+    Commit,
+}
+
+/// The timeouts, retries, and circuit breaker of the storage operations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StoragePolicy {
+    /// The timeout of a storage read.
+    pub read_timeout: Duration,
+    /// The timeout of a storage batch commit.
+    pub commit_timeout: Duration,
+    /// The number of retries of a storage operation that failed with a transient (busy) error.
+    pub max_retries: u32,
+    /// The delay before a storage operation is retried.
+    pub retry_delay: Duration,
+    /// The number of consecutive timeouts after which the node switches into degraded mode.
+    pub breaker_threshold: u32,
+}
+
+impl Default for StoragePolicy {
+    fn default() -> Self {
+        Self {
+            read_timeout: Duration::from_millis(DEFAULT_STORAGE_READ_TIMEOUT_IN_MS),
+            commit_timeout: Duration::from_millis(DEFAULT_STORAGE_COMMIT_TIMEOUT_IN_MS),
+            max_retries: DEFAULT_STORAGE_MAX_RETRIES,
+            retry_delay: Duration::from_millis(DEFAULT_STORAGE_RETRY_DELAY_IN_MS),
+            breaker_threshold: DEFAULT_STORAGE_BREAKER_THRESHOLD,
+        }
+    }
+}
+
+impl StoragePolicy {
+    /// Ensures the settings are valid.
+    pub fn check(&self) -> Result<()> {
+        ensure!(!self.read_timeout.is_zero(), "The storage read timeout must be greater than 0");
+        ensure!(!self.commit_timeout.is_zero(), "The storage commit timeout must be greater than 0");
+        ensure!(self.breaker_threshold > 0, "The storage breaker threshold must be greater than 0");
+        Ok(())
+    }
+
+    /// Returns the timeout of the given class of operations.
+    pub const fn timeout(&self, class: OperationClass) -> Duration {
+        match class {
+            OperationClass::Read => self.read_timeout,
+            OperationClass::Commit => self.commit_timeout,
+        }
+    }
+}
+
+/// Returns `true` if the given error is transient, i.e. the storage was busy, and the operation may be retried.
+fn is_transient(error: &anyhow::Error) -> bool {
+    let message = format!("{error:#}").to_lowercase();
+    message.contains("busy") || message.contains("try again")
+}
+
+/// The guard of the storage operations, which runs them off the async runtime with a timeout,
+/// retries the transient failures, and trips a circuit breaker after repeated timeouts.
+///
+/// While the breaker is tripped, the node is in degraded mode: it stops requesting new blocks, and answers
+/// its peers from what it holds in memory. The breaker resets on the first operation that completes in time.
+#[derive(Clone, Debug, Default)]
+pub struct StorageGuard {
+    /// The timeouts, retries, and circuit breaker of the storage operations.
+    policy: Arc<RwLock<StoragePolicy>>,
+    /// The number of consecutive timeouts.
+    num_timeouts: Arc<AtomicU32>,
+    /// The boolean flag for whether the node is in degraded mode.
+    is_degraded: Arc<AtomicBool>,
+}
+
+impl StorageGuard {
+    /// Initializes a new storage guard with the given policy.
+    pub fn new(policy: StoragePolicy) -> Self {
+        Self { policy: Arc::new(RwLock::new(policy)), ..Default::default() }
+    }
+
+    /// Returns the timeouts, retries, and circuit breaker of the storage operations.
+    pub fn policy(&self) -> StoragePolicy {
+        *self.policy.read()
+    }
+
+    /// Sets the timeouts, retries, and circuit breaker of the storage operations.
+    pub fn set_policy(&self, policy: StoragePolicy) {
+        *self.policy.write() = policy;
+    }
+
+    /// Returns `true` if the node is in degraded mode, as the storage stalled.
+    pub fn is_degraded(&self) -> bool {
+        self.is_degraded.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of consecutive timeouts.
+    pub fn num_timeouts(&self) -> u32 {
+        self.num_timeouts.load(Ordering::SeqCst)
+    }
+
+    /// Runs the given storage operation in a blocking task, with the timeout of its class,
+    /// and retries it while it fails with a transient error, up to the maximum number of retries.
+    pub async fn run<T, F>(&self, class: OperationClass, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: Fn() -> Result<T> + Send + Sync + 'static,
+    {
+        let operation = Arc::new(operation);
+        let mut num_retries = 0;
+        loop {
+            let operation = operation.clone();
+            match self.run_once(class, move || operation()).await {
+                Err(error) if is_transient(&error) && num_retries < self.policy().max_retries => {
+                    num_retries += 1;
+                    debug!("Retrying a storage operation ({num_retries}) - {error}");
+                    tokio::time::sleep(self.policy().retry_delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs the given storage operation in a blocking task once, with the timeout of its class.
+    pub async fn run_once<T, F>(&self, class: OperationClass, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let timeout = self.policy().timeout(class);
+        let mut handle = tokio::task::spawn_blocking(operation);
+        let result = match tokio::time::timeout(timeout, &mut handle).await {
+            Ok(result) => {
+                // The storage responded in time, so it is not stalled.
+                self.record_success();
+                result
+            }
+            Err(_) => {
+                self.record_timeout();
+                match class {
+                    OperationClass::Read => bail!("The storage read timed out after {} ms", timeout.as_millis()),
+                    OperationClass::Commit => handle.await,
+                }
+            }
+        };
+        result.map_err(|error| anyhow!("The storage operation panicked - {error}"))?
+    }
+
+    /// Records an operation that completed in time, which resumes normal operation if the node is degraded.
+    fn record_success(&self) {
+        self.num_timeouts.store(0, Ordering::SeqCst);
+        if self.is_degraded.swap(false, Ordering::SeqCst) {
+            info!("Resumed normal operation, as the storage responds again");
+        }
+    }
+
+    /// Records an operation that timed out, which switches the node into degraded mode after repeated timeouts.
+    fn record_timeout(&self) {
+        let num_timeouts = self.num_timeouts.fetch_add(1, Ordering::SeqCst) + 1;
+        if num_timeouts >= self.policy().breaker_threshold && !self.is_degraded.swap(true, Ordering::SeqCst) {
+            warn!(
+                target: "critical",
+                kind = "storage-stall",
+                "Switched to degraded mode after {num_timeouts} storage timeouts - stopped requesting new blocks"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parking_lot::Mutex;
+    use std::{sync::atomic::AtomicUsize, thread, time::Instant};
+
+    /// A storage that stalls for the given delay, or fails as busy the given number of times, before each operation.
+    #[derive(Clone, Default)]
+    struct DelayedStorage {
+        /// The delay before each operation.
+        delay: Arc<Mutex<Duration>>,
+        /// The number of operations that remain to fail as busy.
+        num_busy: Arc<AtomicU32>,
+        /// The number of operations that were attempted.
+        num_attempts: Arc<AtomicUsize>,
+    }
+
+    impl DelayedStorage {
+        /// Reads the value, after the delay.
+        fn read(&self) -> Result<u32> {
+            self.num_attempts.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(*self.delay.lock());
+            if self.num_busy.load(Ordering::SeqCst) > 0 {
+                self.num_busy.fetch_sub(1, Ordering::SeqCst);
+                bail!("Resource busy: the write stall is in progress");
+            }
+            Ok(7)
+        }
+    }
+
+    /// Returns a policy with short timeouts, and a breaker that trips after two timeouts.
+    fn sample_policy() -> StoragePolicy {
+        StoragePolicy {
+            read_timeout: Duration::from_millis(50),
+            commit_timeout: Duration::from_millis(50),
+            max_retries: 2,
+            retry_delay: Duration::from_millis(1),
+            breaker_threshold: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_breaker_trips_and_recovers() {
+        let storage = DelayedStorage::default();
+        let guard = StorageGuard::new(sample_policy());
+
+        // Keep answering pings on the runtime, while the storage stalls.
+        let num_pongs = Arc::new(AtomicUsize::new(0));
+        let num_pongs_clone = num_pongs.clone();
+        let pinger = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                num_pongs_clone.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        // Stall the storage, and ensure the reads time out, until the breaker trips.
+        *storage.delay.lock() = Duration::from_millis(200);
+        let timer = Instant::now();
+        for _ in 0..2 {
+            let storage = storage.clone();
+            assert!(guard.run(OperationClass::Read, move || storage.read()).await.is_err());
+        }
+        assert!(timer.elapsed() < Duration::from_millis(200));
+        assert!(guard.is_degraded());
+        assert_eq!(guard.num_timeouts(), 2);
+        // Ensure the pings kept flowing during the stall.
+        assert!(num_pongs.load(Ordering::SeqCst) >= 5);
+
+        // Ensure a commit that times out is awaited, rather than abandoned.
+        let storage_clone = storage.clone();
+        assert_eq!(guard.run_once(OperationClass::Commit, move || storage_clone.read()).await.unwrap(), 7);
+        assert!(guard.is_degraded());
+
+        // Resume the storage, and ensure the node resumes normal operation.
+        *storage.delay.lock() = Duration::ZERO;
+        let storage_clone = storage.clone();
+        assert_eq!(guard.run(OperationClass::Read, move || storage_clone.read()).await.unwrap(), 7);
+        assert!(!guard.is_degraded());
+        assert_eq!(guard.num_timeouts(), 0);
+        pinger.abort();
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried() {
+        let storage = DelayedStorage::default();
+        let guard = StorageGuard::new(sample_policy());
+
+        // Ensure an operation that is busy fewer times than the maximum number of retries succeeds.
+        storage.num_busy.store(2, Ordering::SeqCst);
+        let storage_clone = storage.clone();
+        assert_eq!(guard.run(OperationClass::Read, move || storage_clone.read()).await.unwrap(), 7);
+        assert_eq!(storage.num_attempts.load(Ordering::SeqCst), 3);
+
+        // Ensure an operation that stays busy fails after the maximum number of retries.
+        storage.num_busy.store(5, Ordering::SeqCst);
+        let storage_clone = storage.clone();
+        assert!(guard.run(OperationClass::Read, move || storage_clone.read()).await.is_err());
+        assert_eq!(storage.num_attempts.load(Ordering::SeqCst), 6);
+
+        // Ensure the other errors are not retried, and do not trip the breaker.
+        let num_attempts = Arc::new(AtomicUsize::new(0));
+        let num_attempts_clone = num_attempts.clone();
+        let result = guard
+            .run(OperationClass::Read, move || -> Result<()> {
+                num_attempts_clone.fetch_add(1, Ordering::SeqCst);
+                bail!("Missing block hash for height 5")
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(num_attempts.load(Ordering::SeqCst), 1);
+        assert!(!guard.is_degraded());
+    }
+}
@@ -14,18 +14,27 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use snarkos_node_messages::BlockLocators;
+use crate::{SerialBlock, StorageGuard};
+use snarkos_node_ledger::{Event, EventBus};
+use snarkos_node_messages::{BlockLocators, CommittedHeader, CHECKPOINT_INTERVAL, NUM_RECENTS};
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
 use snarkvm::prelude::{Block, Network};
 
 use anyhow::{bail, ensure, Result};
 use colored::Colorize;
 use core::hash::Hash;
-use indexmap::{IndexMap, IndexSet};
+use indexmap::{indexset, IndexMap, IndexSet};
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use rand::{prelude::IteratorRandom, CryptoRng, Rng};
-use std::{collections::BTreeMap, net::SocketAddr, time::Instant};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
 
 pub const REDUNDANCY_FACTOR: usize = 3;
 pub const EXTRA_REDUNDANCY_FACTOR: usize = REDUNDANCY_FACTOR * 2;
@@ -35,9 +44,115 @@ pub const BLOCK_REQUEST_TIMEOUT_IN_SECS: u64 = 15; // 15 seconds
 pub const MAX_BLOCK_REQUESTS: usize = 50; // 50 requests
 pub const MAX_BLOCK_REQUEST_TIMEOUTS: usize = 5; // 5 timeouts
 
+pub const MAX_ORPHAN_BLOCKS: usize = MAX_BLOCK_REQUESTS * 2; // 100 blocks
+pub const MAX_ORPHAN_BYTES: usize = 256 * 1024 * 1024; // 256 MiB
+
+pub const MAX_COMMITTED_HEADERS: usize = NUM_RECENTS; // 100 headers
+
+pub const DEFAULT_THROUGHPUT_FLOOR: u64 = 1024; // 1 KiB/s
+pub const THROUGHPUT_WINDOW: usize = 8; // 8 responses
+pub const MIN_THROUGHPUT_SAMPLES: usize = 4; // 4 responses
+pub const THROUGHPUT_DEMOTION_IN_SECS: u64 = 300; // 5 minutes
+
+pub const DEFAULT_STALE_TIP_MULTIPLE: u32 = 8; // 8 block times
+pub const STALE_TIP_ESCALATION_FACTOR: u32 = 3; // 3 stale tip thresholds
+
 /// A tuple of the block hash (optional), previous block hash (optional), and sync IPs.
 pub type SyncRequest<N> = (Option<<N as Network>::BlockHash>, Option<<N as Network>::BlockHash>, IndexSet<SocketAddr>);
 
+/// The arrival of a block response in the sync pool.
+#[derive(Copy, Clone, Debug)]
+struct Arrival {
+    /// The peer IP that provided the block.
+    peer_ip: SocketAddr,
+    /// The timestamp of the arrival of the block.
+    timestamp: Instant,
+    /// Whether the parent of the block was missing when the block arrived.
+    is_orphan: bool,
+}
+
+/// The throughput of the recent block responses of a peer in the sync pool.
+#[derive(Clone, Debug, Default)]
+struct Throughput {
+    /// The sizes (in bytes) of the most recent block responses, and the durations since their requests.
+    samples: VecDeque<(usize, Duration)>,
+}
+
+impl Throughput {
+    /// Records a block response of the given size, which arrived after the given duration since its request.
+    fn record(&mut self, num_bytes: usize, elapsed: Duration) {
+        if self.samples.len() >= THROUGHPUT_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((num_bytes, elapsed));
+    }
+
+    /// Returns the throughput (in bytes per second), if enough block responses were recorded.
+    fn bytes_per_sec(&self) -> Option<f64> {
+        if self.samples.len() < MIN_THROUGHPUT_SAMPLES {
+            return None;
+        }
+        let num_bytes = self.samples.iter().map(|(num_bytes, _)| *num_bytes).sum::<usize>();
+        let elapsed = self.samples.iter().map(|(_, elapsed)| elapsed.as_secs_f64()).sum::<f64>();
+        Some(num_bytes as f64 / elapsed.max(f64::EPSILON))
+    }
+}
+
+/// The condition of a node whose canon has not advanced for longer than the stale tip threshold,
+/// while a peer advertises a higher block height.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StaleTip {
+    /// The latest block height in the sync pool.
+    pub canon_height: u32,
+    /// The highest block height advertised by a peer.
+    pub peer_height: u32,
+    /// The duration since the canon last advanced.
+    pub stale_for: Duration,
+    /// Whether the canon is stale beyond `STALE_TIP_ESCALATION_FACTOR` thresholds, which re-queries the DNS seeds.
+    pub is_escalated: bool,
+}
+
+/// The class of a peer in the sync pool, which is based on the capabilities it advertised in the handshake,
+/// and determines the blocks that are requested from it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SyncPeerClass {
+    /// The peer serves the body of every block.
+    Archival,
+    /// The peer serves the bodies of the blocks at and above its pruned height.
+    Pruned,
+    /// The peer does not serve blocks, and only provides its block locators.
+    HeadersOnly,
+}
+
+impl core::fmt::Display for SyncPeerClass {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Archival => "archival",
+            Self::Pruned => "pruned",
+            Self::HeadersOnly => "headers-only",
+        })
+    }
+}
+
+/// The assignment of a peer as a source of blocks in the sync pool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncSource {
+    /// The IP of the peer.
+    pub peer_ip: SocketAddr,
+    /// The class of the peer.
+    pub class: SyncPeerClass,
+    /// The latest block height of the peer.
+    pub height: u32,
+    /// The height below which the peer pruned the bodies of its blocks.
+    pub pruned_height: u32,
+    /// The throughput (in bytes per second) of the recent block responses of the peer, if enough were measured.
+    pub throughput: Option<f64>,
+    /// Whether the peer is demoted, as it delivered blocks below the throughput floor.
+    pub is_demoted: bool,
+    /// The heights of the blocks that are requested from the peer, and not received yet.
+    pub requested_heights: Vec<u32>,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct PeerPair(SocketAddr, SocketAddr);
 
@@ -66,6 +181,8 @@ impl Hash for PeerPair {
 /// - the `request_timestamps` map remains unchanged.
 /// - When a response is removed/completed, the `requests` map and `request_timestamps` map also remove the entry for the request height.
 /// - When a request is timed out, the `requests`, `request_timestamps`, and `responses` map remove the entry for the request height;
+/// - When an orphan block (a response that is not connected to the canonical map) is evicted, the `requests`,
+///   `request_timestamps`, and `responses` map remove the entry for the request height.
 #[derive(Debug)]
 pub struct Sync<N: Network> {
     local_ip: OnceCell<SocketAddr>,
@@ -84,13 +201,52 @@ pub struct Sync<N: Network> {
     requests: RwLock<BTreeMap<u32, SyncRequest<N>>>,
     /// The map of block height to the received blocks.
     /// Removing an entry from this map must remove the corresponding entry from the requests map.
-    responses: RwLock<BTreeMap<u32, Block<N>>>,
+    responses: RwLock<BTreeMap<u32, SerialBlock<N>>>,
+    /// The map of block height to the arrival of the received blocks.
+    /// Each entry is removed when its corresponding entry in the responses map is removed.
+    arrivals: RwLock<BTreeMap<u32, Arrival>>,
+    /// The map of the missing parent heights of the orphan blocks to the peer IPs that were asked for the parent.
+    /// This map is used to ask another peer for the parent, when the request to a peer times out.
+    parent_requests: RwLock<BTreeMap<u32, IndexSet<SocketAddr>>>,
+    /// The map of block height to the committed headers that were relayed ahead of their blocks.
+    /// This map is used to check the body of a block response against the commitments of its header.
+    committed_headers: RwLock<BTreeMap<u32, CommittedHeader<N>>>,
     /// The map of block height to the timestamp of the last time the block was requested.
     /// This map is used to determine which requests to remove if they have been pending for too long.
     request_timestamps: RwLock<BTreeMap<u32, Instant>>,
     /// The map of (timed out) peer IPs to their request timestamps.
     /// This map is used to determine which peers to remove if they have timed out too many times.
     request_timeouts: RwLock<IndexMap<SocketAddr, Vec<Instant>>>,
+    /// The map of peer IPs to the height below which they pruned the bodies of their blocks.
+    /// This map is used to avoid requesting pruned blocks from the peers.
+    pruned_heights: RwLock<IndexMap<SocketAddr, u32>>,
+    /// The set of peer IPs that do not serve blocks.
+    /// This set is used to avoid requesting blocks from the peers that decline block requests.
+    non_serving_peers: RwLock<IndexSet<SocketAddr>>,
+    /// The set of peer IPs that serve the body of every block.
+    /// This set is used to request the blocks that other peers pruned from the archival peers only.
+    archival_peers: RwLock<IndexSet<SocketAddr>>,
+    /// The map of peer IPs to the throughput of their recent block responses.
+    throughputs: RwLock<IndexMap<SocketAddr, Throughput>>,
+    /// The map of demoted peer IPs to the timestamp of their demotion.
+    /// This map is used to request blocks from other peers, while the demoted peers deliver below the throughput floor.
+    demoted_peers: RwLock<IndexMap<SocketAddr, Instant>>,
+    /// The throughput (in bytes per second) below which a peer is demoted, or `0` if peers are never demoted.
+    throughput_floor: AtomicU64,
+    /// The timestamp of the last time the canon advanced.
+    last_canon_advance: RwLock<Instant>,
+    /// The duration (in milliseconds) without a canon advance after which the tip is stale, or `0` if never.
+    stale_tip_threshold: AtomicU64,
+    /// The boolean flag for whether the stale tip was reported, which is reset once the tip is no longer stale.
+    is_stale_tip_reported: AtomicBool,
+    /// The boolean flag for whether the stale tip was escalated, which is reset once the tip is no longer stale.
+    is_stale_tip_escalated: AtomicBool,
+    /// The event bus, on which the changes of the sync state are published.
+    events: OnceCell<EventBus<N>>,
+    /// The boolean flag for whether the node is syncing blocks from its peers.
+    is_syncing: AtomicBool,
+    /// The guard of the storage operations, which stops the block requests while the storage stalls.
+    storage: OnceCell<StorageGuard>,
 }
 
 impl<N: Network> Default for Sync<N> {
@@ -102,8 +258,24 @@ impl<N: Network> Default for Sync<N> {
             common_ancestors: Default::default(),
             requests: Default::default(),
             responses: Default::default(),
+            arrivals: Default::default(),
+            parent_requests: Default::default(),
+            committed_headers: Default::default(),
             request_timestamps: Default::default(),
             request_timeouts: Default::default(),
+            pruned_heights: Default::default(),
+            non_serving_peers: Default::default(),
+            archival_peers: Default::default(),
+            throughputs: Default::default(),
+            demoted_peers: Default::default(),
+            throughput_floor: AtomicU64::new(DEFAULT_THROUGHPUT_FLOOR),
+            last_canon_advance: RwLock::new(Instant::now()),
+            stale_tip_threshold: AtomicU64::new(DEFAULT_STALE_TIP_MULTIPLE as u64 * N::ANCHOR_TIME as u64 * 1000),
+            is_stale_tip_reported: Default::default(),
+            is_stale_tip_escalated: Default::default(),
+            events: Default::default(),
+            is_syncing: Default::default(),
+            storage: Default::default(),
         }
     }
 }
@@ -176,9 +348,145 @@ impl<N: Network> Sync<N> {
         self.request_timestamps.read().get(&height).copied()
     }
 
+    /// Returns the class of the given peer, based on the capabilities it advertised in the handshake.
+    pub fn get_peer_class(&self, peer_ip: &SocketAddr) -> SyncPeerClass {
+        if self.non_serving_peers.read().contains(peer_ip) {
+            SyncPeerClass::HeadersOnly
+        } else if self.archival_peers.read().contains(peer_ip) {
+            SyncPeerClass::Archival
+        } else {
+            SyncPeerClass::Pruned
+        }
+    }
+
+    /// Returns the throughput (in bytes per second) of the recent block responses of the given peer,
+    /// if enough of them were measured.
+    pub fn get_peer_throughput(&self, peer_ip: &SocketAddr) -> Option<f64> {
+        self.throughputs.read().get(peer_ip).and_then(Throughput::bytes_per_sec)
+    }
+
+    /// Returns `true` if the given peer is demoted, as it delivered blocks below the throughput floor.
+    pub fn is_demoted(&self, peer_ip: &SocketAddr) -> bool {
+        self.demoted_peers.read().contains_key(peer_ip)
+    }
+
+    /// Returns the throughput (in bytes per second) below which a peer is demoted, or `0` if peers are never demoted.
+    pub fn throughput_floor(&self) -> u64 {
+        self.throughput_floor.load(Ordering::Relaxed)
+    }
+
+    /// Sets the throughput (in bytes per second) below which a peer is demoted, or `0` to never demote peers.
+    pub fn set_throughput_floor(&self, bytes_per_sec: u64) {
+        self.throughput_floor.store(bytes_per_sec, Ordering::Relaxed);
+    }
+
+    /// Returns the duration without a canon advance after which the tip is stale, or `None` if it is never stale.
+    pub fn stale_tip_threshold(&self) -> Option<Duration> {
+        match self.stale_tip_threshold.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Sets the stale tip threshold to the given multiple of the target block time, or `0` to never detect a stale tip.
+    pub fn set_stale_tip_multiple(&self, multiple: u32) {
+        self.set_stale_tip_threshold(Duration::from_secs(multiple as u64 * N::ANCHOR_TIME as u64));
+    }
+
+    /// Sets the duration without a canon advance after which the tip is stale, or zero to never detect a stale tip.
+    pub fn set_stale_tip_threshold(&self, threshold: Duration) {
+        self.stale_tip_threshold.store(threshold.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the duration since the canon last advanced.
+    pub fn time_since_canon_advance(&self) -> Duration {
+        self.last_canon_advance.read().elapsed()
+    }
+
+    /// Returns the stale tip, if the canon has not advanced within the stale tip threshold while a peer advertises
+    /// a higher block height. A slow block does not make the tip stale, as long as no peer claims a higher tip.
+    pub fn stale_tip(&self) -> Option<StaleTip> {
+        let threshold = self.stale_tip_threshold()?;
+        let stale_for = self.time_since_canon_advance();
+        if stale_for < threshold {
+            return None;
+        }
+        let canon_height = self.latest_canon_height();
+        let peer_height = self.locators.read().values().map(|locators| locators.latest_locator_height()).max()?;
+        match peer_height > canon_height {
+            true => Some(StaleTip {
+                canon_height,
+                peer_height,
+                stale_for,
+                is_escalated: stale_for >= threshold * STALE_TIP_ESCALATION_FACTOR,
+            }),
+            false => None,
+        }
+    }
+
+    /// Returns `true` if the stale tip is not reported yet, and records its report if so.
+    pub fn begin_stale_tip_report(&self) -> bool {
+        !self.is_stale_tip_reported.swap(true, Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the stale tip is not escalated yet, and records its escalation if so.
+    pub fn begin_stale_tip_escalation(&self) -> bool {
+        !self.is_stale_tip_escalated.swap(true, Ordering::Relaxed)
+    }
+
+    /// Clears the report and escalation of the stale tip, and returns `true` if it was reported.
+    pub fn end_stale_tip(&self) -> bool {
+        self.is_stale_tip_escalated.store(false, Ordering::Relaxed);
+        self.is_stale_tip_reported.swap(false, Ordering::Relaxed)
+    }
+
+    /// Returns the assignments of the peers as sources of blocks, in the order of their block locators.
+    pub fn get_sync_sources(&self) -> Vec<SyncSource> {
+        let requests = self.requests.read();
+        let pruned_heights = self.pruned_heights.read();
+        self.locators
+            .read()
+            .iter()
+            .map(|(peer_ip, locators)| SyncSource {
+                peer_ip: *peer_ip,
+                class: self.get_peer_class(peer_ip),
+                height: locators.latest_locator_height(),
+                pruned_height: pruned_heights.get(peer_ip).copied().unwrap_or(0),
+                throughput: self.get_peer_throughput(peer_ip),
+                is_demoted: self.is_demoted(peer_ip),
+                requested_heights: requests
+                    .iter()
+                    .filter(|(_, (_, _, sync_ips))| sync_ips.contains(peer_ip))
+                    .map(|(height, _)| *height)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the number of orphan blocks, which are the received blocks that are not connected to the canonical map.
+    pub fn num_orphans(&self) -> usize {
+        let responses = self.responses.read();
+        self.orphan_heights(&responses).len()
+    }
+
+    /// Returns the total size of the orphan blocks, in bytes.
+    pub fn num_orphan_bytes(&self) -> usize {
+        let responses = self.responses.read();
+        self.orphan_heights(&responses)
+            .iter()
+            .filter_map(|height| responses.get(height))
+            .map(SerialBlock::num_bytes)
+            .sum()
+    }
+
     /// Inserts a canonical block hash for the given block height, overriding an existing entry if it exists.
     pub fn insert_canon_locator(&self, height: u32, hash: N::BlockHash) {
-        if let Some(previous_hash) = self.canon.write().insert(height, hash) {
+        let mut canon = self.canon.write();
+        // Record the advance of the canon, if the block height is above the latest one.
+        if canon.keys().last().map_or(true, |latest_height| height > *latest_height) {
+            *self.last_canon_advance.write() = Instant::now();
+        }
+        if let Some(previous_hash) = canon.insert(height, hash) {
             // Warn if this insert overrides a different previous block hash.
             if previous_hash != hash {
                 let change = format!("(from {previous_hash} to {hash})").dimmed();
@@ -187,6 +495,83 @@ impl<N: Network> Sync<N> {
         }
     }
 
+    /// Inserts the given committed header, which was relayed ahead of its block, so that the body of the block
+    /// is checked against its commitments when it arrives. The headers at or below the canon height are ignored,
+    /// and the lowest headers are evicted beyond `MAX_COMMITTED_HEADERS`.
+    pub fn insert_committed_header(&self, header: CommittedHeader<N>) {
+        let height = header.height();
+        if header.commitments.is_none() || height <= self.latest_canon_height() {
+            return;
+        }
+        let mut committed_headers = self.committed_headers.write();
+        committed_headers.insert(height, header);
+        // Drop the headers of the blocks that became canon, and the lowest headers beyond the limit.
+        *committed_headers = committed_headers.split_off(&self.latest_canon_height().saturating_add(1));
+        while committed_headers.len() > MAX_COMMITTED_HEADERS {
+            committed_headers.pop_first();
+        }
+    }
+
+    /// Returns the committed header that was relayed for the given block height, if any.
+    pub fn get_committed_header(&self, height: u32) -> Option<CommittedHeader<N>> {
+        self.committed_headers.read().get(&height).cloned()
+    }
+
+    /// Removes the canonical block hashes at and above the given block height.
+    /// Sets the event bus, on which the changes of the sync state are published.
+    pub fn set_event_bus(&self, events: EventBus<N>) {
+        self.events.set(events).expect("The event bus was set more than once");
+    }
+
+    /// Sets the guard of the storage operations, which stops the block requests while the storage stalls.
+    pub fn set_storage_guard(&self, storage: StorageGuard) {
+        self.storage.set(storage).expect("The storage guard was set more than once");
+    }
+
+    /// Returns `true` if the node is in degraded mode, as the storage stalled.
+    pub fn is_degraded(&self) -> bool {
+        self.storage.get().is_some_and(|storage| storage.is_degraded())
+    }
+
+    /// Returns `true` if the node is syncing blocks from its peers.
+    pub fn is_syncing(&self) -> bool {
+        self.is_syncing.load(Ordering::Relaxed)
+    }
+
+    /// Updates the sync state, and publishes it on the event bus if it changed.
+    fn update_sync_state(&self, is_syncing: bool) {
+        if self.is_syncing.swap(is_syncing, Ordering::Relaxed) != is_syncing {
+            if let Some(events) = self.events.get() {
+                events.publish(Event::SyncStateChanged { is_syncing, canon_height: self.latest_canon_height() });
+            }
+        }
+    }
+
+    /// Returns the block locators of the canonical blocks in the sync pool, which are held in memory,
+    /// so that they can be sent to the peers while the storage stalls.
+    pub fn get_canon_locators(&self) -> Result<BlockLocators<N>> {
+        let canon = self.canon.read();
+        let latest_height = canon.keys().last().copied().unwrap_or(0);
+        let get_locator = |height| match canon.get(&height) {
+            Some(hash) => Ok((height, *hash)),
+            None => bail!("Missing the canon block hash at height {height}"),
+        };
+        // Retrieve the recent block hashes, and the checkpoint block hashes.
+        let recents = (latest_height.saturating_sub(NUM_RECENTS as u32 - 1)..=latest_height)
+            .map(get_locator)
+            .collect::<Result<IndexMap<_, _>>>()?;
+        let checkpoints =
+            (0..=latest_height).step_by(CHECKPOINT_INTERVAL as usize).map(get_locator).collect::<Result<_>>()?;
+        // Ensure the block locators are well-formed.
+        let locators = BlockLocators::new(recents, checkpoints);
+        locators.ensure_is_valid()?;
+        Ok(locators)
+    }
+
+    pub fn remove_canon_locators(&self, height: u32) {
+        self.canon.write().split_off(&height);
+    }
+
     /// Inserts the block locators as canonical, overriding any existing entries.
     pub fn insert_canon_locators(&self, locators: BlockLocators<N>) -> Result<()> {
         // Ensure the given block locators are well-formed.
@@ -217,14 +602,29 @@ impl<N: Network> Sync<N> {
     pub fn prepare_block_requests(&self) -> Vec<(u32, SyncRequest<N>)> {
         // Remove timed out block requests.
         self.remove_timed_out_block_requests();
+        // Restore the demoted peers whose demotion expired.
+        self.expire_demotions();
+        // Evict the oldest orphan blocks, if there are too many of them.
+        self.evict_orphans();
+        // Do not request new blocks while the storage stalls, as they could not be committed.
+        if self.is_degraded() {
+            return Vec::new();
+        }
+        // Prepare the block requests for the missing parents of the orphan blocks.
+        let mut block_requests = self.construct_parent_requests(&mut rand::thread_rng());
         // Prepare the block requests.
-        if let Some((sync_peers, min_common_ancestor)) = self.find_sync_peers_inner() {
-            // Return the list of block requests.
-            self.construct_requests(sync_peers, min_common_ancestor, &mut rand::thread_rng())
-        } else {
-            // Return an empty list of block requests.
-            Vec::new()
+        let sync_peers = self.find_sync_peers_inner();
+        // Update the sync state, as the node syncs if it found peers to sync from.
+        self.update_sync_state(sync_peers.is_some());
+        if let Some((sync_peers, min_common_ancestor)) = sync_peers {
+            // Retrieve the heights of the parent requests, which take precedence over the other block requests.
+            let parent_heights = block_requests.iter().map(|(height, _)| *height).collect::<IndexSet<_>>();
+            // Append the block requests for the heights that are not requested as parents.
+            let requests = self.construct_requests(sync_peers, min_common_ancestor, &mut rand::thread_rng());
+            block_requests.extend(requests.into_iter().filter(|(height, _)| !parent_heights.contains(height)));
         }
+        // Return the list of block requests.
+        block_requests
     }
 
     /// Inserts a block request for the given height.
@@ -243,36 +643,58 @@ impl<N: Network> Sync<N> {
     /// Inserts the given block response, after checking that the request exists and the response is well-formed.
     /// On success, this function removes the peer IP from the requests map.
     /// On failure, this function removes all block requests from the given peer IP.
-    pub fn insert_block_response(&self, peer_ip: SocketAddr, block: Block<N>) -> Result<()> {
+    pub fn insert_block_response(&self, peer_ip: SocketAddr, block: SerialBlock<N>) -> Result<()> {
         // Retrieve the block height.
-        let height = block.height();
+        let height = block.block().height();
 
         // Ensure the block (response) from the peer is well-formed. On failure, remove all block requests to the peer.
-        if let Err(error) = self.check_block_response(&peer_ip, &block) {
+        if let Err(error) = self.check_block_response(&peer_ip, block.block()) {
             // Remove all block requests to the peer.
             self.remove_block_requests_to_peer(&peer_ip);
             return Err(error);
         }
+        // Attribute the block to the peer, so that it is held accountable if the block is invalid.
+        let block = block.with_peer_ip(peer_ip);
 
         // Remove the peer IP from the request entry.
         if let Some((_, _, sync_ips)) = self.requests.write().get_mut(&height) {
             sync_ips.remove(&peer_ip);
         }
 
+        // Determine if the parent of the candidate block is canon.
+        let is_parent_canon = self.canon.read().contains_key(&height.saturating_sub(1));
+
         // Acquire the write lock on the responses map.
         let mut responses = self.responses.write();
         // Insert the candidate block into the responses map.
-        if let Some(existing_block) = responses.insert(height, block.clone()) {
+        match responses.insert(height, block.clone()) {
             // If the candidate block was already present, ensure it is the same block.
-            if block != existing_block {
-                // Remove the candidate block.
-                responses.remove(&height);
-                // Remove all block requests to the peer.
-                self.remove_block_requests_to_peer(&peer_ip);
-                bail!("Candidate block {height} from '{peer_ip}' is malformed");
+            Some(existing_block) => {
+                if block.block() != existing_block.block() {
+                    // Remove the candidate block.
+                    responses.remove(&height);
+                    self.arrivals.write().remove(&height);
+                    // Remove all block requests to the peer.
+                    self.remove_block_requests_to_peer(&peer_ip);
+                    bail!("Candidate block {height} from '{peer_ip}' is malformed");
+                }
             }
+            // Otherwise, record the arrival of the candidate block.
+            None => {
+                let is_orphan = !is_parent_canon && !responses.contains_key(&height.saturating_sub(1));
+                self.arrivals.write().insert(height, Arrival { peer_ip, timestamp: Instant::now(), is_orphan });
+            }
+        }
+        drop(responses);
+
+        // Measure the throughput of the peer, from the time the block was requested.
+        let requested_at = self.request_timestamps.read().get(&height).copied();
+        if let Some(requested_at) = requested_at {
+            self.update_throughput(peer_ip, block.num_bytes(), requested_at.elapsed());
         }
 
+        // Evict the oldest orphan blocks, if there are too many of them.
+        self.evict_orphans();
         Ok(())
     }
 
@@ -334,6 +756,36 @@ impl<N: Network> Sync<N> {
         self.remove_block_requests_to_peer(peer_ip);
         // Remove the timeouts for the peer.
         self.request_timeouts.write().remove(peer_ip);
+        // Remove the pruned height of the peer.
+        self.pruned_heights.write().remove(peer_ip);
+        // Remove the peer from the non-serving peers.
+        self.non_serving_peers.write().remove(peer_ip);
+        // Remove the peer from the archival peers.
+        self.archival_peers.write().remove(peer_ip);
+        // Remove the throughput and demotion of the peer.
+        self.throughputs.write().remove(peer_ip);
+        self.demoted_peers.write().remove(peer_ip);
+    }
+
+    /// Sets the height below which the given peer pruned the bodies of its blocks.
+    pub fn set_peer_pruned_height(&self, peer_ip: SocketAddr, pruned_height: u32) {
+        self.pruned_heights.write().insert(peer_ip, pruned_height);
+    }
+
+    /// Sets whether the given peer serves blocks.
+    pub fn set_peer_serves_blocks(&self, peer_ip: SocketAddr, serves_blocks: bool) {
+        match serves_blocks {
+            true => self.non_serving_peers.write().remove(&peer_ip),
+            false => self.non_serving_peers.write().insert(peer_ip),
+        };
+    }
+
+    /// Sets whether the given peer serves the body of every block.
+    pub fn set_peer_archival(&self, peer_ip: SocketAddr, is_archival: bool) {
+        match is_archival {
+            true => self.archival_peers.write().insert(peer_ip),
+            false => self.archival_peers.write().remove(&peer_ip),
+        };
     }
 
     /// Removes the block request for the given peer IP, if it exists.
@@ -379,12 +831,13 @@ impl<N: Network> Sync<N> {
         self.requests.write().remove(&height);
         // Remove the response entry for the given height.
         self.responses.write().remove(&height);
+        self.arrivals.write().remove(&height);
         // Remove the request timestamp entry for the given height.
         self.request_timestamps.write().remove(&height);
     }
 
     /// Removes and returns the block response for the given height, if the request is complete.
-    pub fn remove_block_response(&self, height: u32) -> Option<Block<N>> {
+    pub fn remove_block_response(&self, height: u32) -> Option<SerialBlock<N>> {
         // Determine if the request is complete.
         let is_request_complete =
             self.requests.read().get(&height).map(|(_, _, peer_ips)| peer_ips.is_empty()).unwrap_or(false);
@@ -395,6 +848,17 @@ impl<N: Network> Sync<N> {
         }
         // Remove the request entry for the given height.
         self.requests.write().remove(&height);
+        // Record the time it took to connect the block, if its parent was missing when it arrived.
+        if let Some(arrival) = self.arrivals.write().remove(&height) {
+            if arrival.is_orphan {
+                #[cfg(feature = "metrics")]
+                metrics::histogram!(
+                    metrics::sync::ORPHAN_RESOLUTION_DURATION,
+                    arrival.timestamp.elapsed().as_secs_f64()
+                );
+                trace!("Orphan block {height} from '{}' was connected", arrival.peer_ip);
+            }
+        }
         // Remove the response entry for the given height.
         self.responses.write().remove(&height)
     }
@@ -445,6 +909,14 @@ impl<N: Network> Sync<N> {
             if !sync_ips.contains(peer_ip) {
                 bail!("The sync pool did not request block {height} from '{peer_ip}'")
             }
+            // Ensure the body matches the commitments of its header, if the header was relayed ahead of the block.
+            if let Some(header) = self.committed_headers.read().get(&height) {
+                if let (true, Some(commitments)) = (&header.header == block.header(), &header.commitments) {
+                    if let Err(error) = commitments.check_block(block) {
+                        bail!("Candidate block {height} from '{peer_ip}' does not match its header - {error}")
+                    }
+                }
+            }
             Ok(())
         } else {
             bail!("The sync pool did not request block {height}")
@@ -488,6 +960,7 @@ impl<N: Network> Sync<N> {
                 }
                 // Remove the response entry for the given height.
                 responses.remove(height);
+                self.arrivals.write().remove(height);
                 // Increment the number of timed out block requests.
                 num_timed_out_block_requests += 1;
             }
@@ -508,6 +981,153 @@ impl<N: Network> Sync<N> {
         num_timed_out_block_requests
     }
 
+    /// Records a block response of the given size from the given peer, which arrived after the given duration
+    /// since its request, and demotes the peer if its throughput is below the floor.
+    fn update_throughput(&self, peer_ip: SocketAddr, num_bytes: usize, elapsed: Duration) {
+        let floor = self.throughput_floor();
+        // Acquire the write lock on the throughputs map.
+        let mut throughputs = self.throughputs.write();
+        let throughput = throughputs.entry(peer_ip).or_default();
+        throughput.record(num_bytes, elapsed);
+
+        // If the throughput is below the floor, demote the peer, and restart the measurement of its throughput.
+        if let Some(bytes_per_sec) = throughput.bytes_per_sec() {
+            if bytes_per_sec < floor as f64 {
+                throughputs.remove(&peer_ip);
+                self.demoted_peers.write().insert(peer_ip, Instant::now());
+                debug!("Demoted sync peer '{peer_ip}', as it delivers {bytes_per_sec:.0} bytes/s (floor is {floor})");
+            }
+        }
+    }
+
+    /// Restores the demoted peers whose demotion expired, so that blocks are requested from them again.
+    fn expire_demotions(&self) {
+        self.demoted_peers
+            .write()
+            .retain(|_, timestamp| timestamp.elapsed().as_secs() < THROUGHPUT_DEMOTION_IN_SECS);
+    }
+
+    /// Returns the heights of the orphan blocks, which are the given block responses that are not connected
+    /// to the canonical map through the other block responses.
+    fn orphan_heights(&self, responses: &BTreeMap<u32, SerialBlock<N>>) -> Vec<u32> {
+        // Compute the next height that is connected to the canonical map.
+        let mut next_height = self.latest_canon_height() + 1;
+        // Collect the heights above the connected block responses.
+        let mut orphan_heights = Vec::new();
+        for height in responses.range(next_height..).map(|(height, _)| *height) {
+            match height == next_height {
+                true => next_height += 1,
+                false => orphan_heights.push(height),
+            }
+        }
+        orphan_heights
+    }
+
+    /// Evicts the orphan blocks, from the oldest to the newest arrival, until the orphan blocks are within
+    /// `MAX_ORPHAN_BLOCKS` and `MAX_ORPHAN_BYTES`. The evicted blocks are requested again once their parents are connected.
+    /// Returns the number of evicted orphan blocks.
+    fn evict_orphans(&self) -> usize {
+        // Acquire the write lock on the requests map.
+        let mut requests = self.requests.write();
+        // Acquire the write lock on the responses map.
+        let mut responses = self.responses.write();
+        // Acquire the write lock on the request timestamps map.
+        let mut request_timestamps = self.request_timestamps.write();
+        // Acquire the write lock on the arrivals map.
+        let mut arrivals = self.arrivals.write();
+
+        // Retrieve the orphan blocks, from the oldest to the newest arrival.
+        let mut orphan_heights = self.orphan_heights(&responses);
+        orphan_heights.sort_by_key(|height| arrivals.get(height).map(|arrival| arrival.timestamp));
+
+        // Compute the number and total size of the orphan blocks.
+        let mut num_orphans = orphan_heights.len();
+        let mut num_bytes =
+            orphan_heights.iter().filter_map(|height| responses.get(height)).map(SerialBlock::num_bytes).sum::<usize>();
+
+        // Evict the oldest orphan blocks, until the orphan blocks are within the limits.
+        let mut num_evicted = 0;
+        for height in orphan_heights {
+            if num_orphans <= MAX_ORPHAN_BLOCKS && num_bytes <= MAX_ORPHAN_BYTES {
+                break;
+            }
+            // Remove the response entry for the given height.
+            if let Some(block) = responses.remove(&height) {
+                num_bytes = num_bytes.saturating_sub(block.num_bytes());
+            }
+            // Remove the request entry for the given height.
+            requests.remove(&height);
+            request_timestamps.remove(&height);
+            arrivals.remove(&height);
+            num_orphans -= 1;
+            num_evicted += 1;
+        }
+        if num_evicted > 0 {
+            debug!("Evicted {num_evicted} orphan block(s) from the sync pool");
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!(metrics::sync::ORPHAN_BLOCKS, num_orphans as f64);
+            metrics::gauge!(metrics::sync::ORPHAN_BYTES, num_bytes as f64);
+        }
+        num_evicted
+    }
+
+    /// Returns the block requests for the missing parents of the orphan blocks. The parent is requested
+    /// from the peer that provided the orphan block, and when that request times out, from another peer.
+    fn construct_parent_requests<R: Rng + CryptoRng>(&self, rng: &mut R) -> Vec<(u32, SyncRequest<N>)> {
+        // Retrieve the latest canon height.
+        let latest_canon_height = self.latest_canon_height();
+
+        // Acquire the write lock on the parent requests map.
+        let mut parent_requests = self.parent_requests.write();
+        // Remove the parent requests that are now canon.
+        parent_requests.retain(|height, _| *height > latest_canon_height);
+
+        // Acquire the read locks on the sync state.
+        let requests = self.requests.read();
+        let responses = self.responses.read();
+        let arrivals = self.arrivals.read();
+        let locators = self.locators.read();
+        let pruned_heights = self.pruned_heights.read();
+        let non_serving_peers = self.non_serving_peers.read();
+        let archival_peers = self.archival_peers.read();
+
+        let mut block_requests = Vec::new();
+        for (height, block) in responses.iter().filter(|(height, _)| **height > latest_canon_height + 1) {
+            let parent_height = height - 1;
+            // Skip the block if its parent is already requested or received.
+            if requests.contains_key(&parent_height) || responses.contains_key(&parent_height) {
+                continue;
+            }
+            // Retrieve the peer that provided the orphan block.
+            let provider_ip = match arrivals.get(height) {
+                Some(arrival) => arrival.peer_ip,
+                None => continue,
+            };
+            // Retrieve the peers that were already asked for the parent.
+            let asked_ips = parent_requests.entry(parent_height).or_default();
+            // Select the provider, or otherwise another peer that has the parent and was not asked for it yet.
+            let peer_ip = match asked_ips.contains(&provider_ip) {
+                false => Some(provider_ip),
+                true => locators
+                    .iter()
+                    .filter(|(peer_ip, _)| !asked_ips.contains(*peer_ip) && !non_serving_peers.contains(*peer_ip))
+                    .filter(|(peer_ip, _)| serves_block(&archival_peers, &pruned_heights, peer_ip, parent_height))
+                    .filter(|(_, locators)| locators.latest_locator_height() >= parent_height)
+                    .map(|(peer_ip, _)| *peer_ip)
+                    .choose(rng),
+            };
+            // Request the parent, expecting the previous block hash of the orphan block.
+            if let Some(peer_ip) = peer_ip {
+                asked_ips.insert(peer_ip);
+                block_requests.push((parent_height, (Some(block.block().previous_hash()), None, indexset![peer_ip])));
+            }
+        }
+        block_requests
+    }
+
     /// Returns the sync peers and their minimum common ancestor, if the node needs to sync.
     fn find_sync_peers_inner(&self) -> Option<(IndexMap<SocketAddr, BlockLocators<N>>, u32)> {
         // Retrieve the latest canon height.
@@ -521,12 +1141,17 @@ impl<N: Network> Sync<N> {
             .map(|(peer_ip, timestamps)| (*peer_ip, timestamps.len()))
             .collect::<IndexMap<_, _>>();
 
-        // Pick a set of peers above the latest canon height, and include their locators.
+        // Retrieve the peers that do not serve blocks.
+        let non_serving_peers = self.non_serving_peers.read().clone();
+
+        // Pick a set of peers above the latest canon height, which serve blocks, and include their locators.
+        // Note that the peers that pruned the next block are included, as they serve the blocks above it.
         let candidate_locators: IndexMap<_, _> = self
             .locators
             .read()
             .iter()
             .filter(|(_, locators)| locators.latest_locator_height() > latest_canon_height)
+            .filter(|(ip, _)| !non_serving_peers.contains(*ip))
             .filter(|(ip, _)| timeouts.get(*ip).map(|count| *count < MAX_BLOCK_REQUEST_TIMEOUTS).unwrap_or(true))
             .sorted_by(|(_, a), (_, b)| b.latest_locator_height().cmp(&a.latest_locator_height()))
             .take(NUM_SYNC_CANDIDATE_PEERS)
@@ -588,6 +1213,13 @@ impl<N: Network> Sync<N> {
             return None;
         }
 
+        // If none of the sync peers serves the next block, as they all pruned it, then return early.
+        let archival_peers = self.archival_peers.read();
+        let pruned_heights = self.pruned_heights.read();
+        if !sync_peers.keys().any(|ip| serves_block(&archival_peers, &pruned_heights, ip, latest_canon_height + 1)) {
+            return None;
+        }
+
         Some((sync_peers, min_common_ancestor))
     }
 
@@ -613,6 +1245,11 @@ impl<N: Network> Sync<N> {
 
         let mut requests = Vec::with_capacity((start_height..end_height).len());
 
+        // Acquire the read locks on the classes and demotions of the peers.
+        let archival_peers = self.archival_peers.read();
+        let pruned_heights = self.pruned_heights.read();
+        let demoted_peers = self.demoted_peers.read();
+
         for height in start_height..end_height {
             // Ensure the current height is not canonized or already requested.
             if self.check_block_request(height).is_err() {
@@ -632,8 +1269,27 @@ impl<N: Network> Sync<N> {
                 }
             }
 
+            // Retrieve the sync peers that serve the block, as the blocks below the pruned height of a peer
+            // are only requested from the archival peers.
+            let serving_ips = sync_peers
+                .keys()
+                .filter(|ip| serves_block(&archival_peers, &pruned_heights, ip, height))
+                .copied()
+                .collect::<Vec<_>>();
+            // Rotate away from the demoted peers, unless no other sync peer serves the block.
+            let (demoted_ips, preferred_ips) =
+                serving_ips.into_iter().partition::<Vec<_>, _>(|ip| demoted_peers.contains_key(ip));
+            let candidate_ips = match preferred_ips.is_empty() {
+                true => demoted_ips,
+                false => preferred_ips,
+            };
+            // Skip the block if no sync peer serves it.
+            if candidate_ips.is_empty() {
+                continue;
+            }
+
             // Pick the sync peers.
-            let sync_ips = sync_peers.keys().copied().choose_multiple(rng, num_sync_ips);
+            let sync_ips = candidate_ips.into_iter().choose_multiple(rng, num_sync_ips);
 
             // Append the request.
             requests.push((height, (hash, previous_hash, sync_ips.into_iter().collect())));
@@ -643,6 +1299,17 @@ impl<N: Network> Sync<N> {
     }
 }
 
+/// Returns `true` if the given peer serves the body of the block at the given height,
+/// as it is archival, or did not prune the block.
+fn serves_block(
+    archival_peers: &IndexSet<SocketAddr>,
+    pruned_heights: &IndexMap<SocketAddr, u32>,
+    peer_ip: &SocketAddr,
+    height: u32,
+) -> bool {
+    archival_peers.contains(peer_ip) || pruned_heights.get(peer_ip).map_or(true, |pruned| *pruned <= height)
+}
+
 /// If any peer is detected to be dishonest in this function, it will not set the hash or previous hash,
 /// in order to allow the caller to determine what to do.
 fn construct_request<N: Network>(
@@ -722,7 +1389,7 @@ mod tests {
         sample_block_locators,
         sample_block_locators_with_fork,
     };
-    use snarkvm::prelude::Field;
+    use snarkvm::prelude::{Field, FromBytes, Header, Metadata, PrivateKey, TestRng, Zero};
 
     use indexmap::indexset;
     use snarkos_node_messages::{CHECKPOINT_INTERVAL, NUM_RECENTS};
@@ -749,6 +1416,49 @@ mod tests {
         sync
     }
 
+    /// Returns the genesis block, followed by a chain of the given number of blocks, which reuse the genesis transactions.
+    fn sample_blocks(num_blocks: u32, rng: &mut TestRng) -> Vec<Block<CurrentNetwork>> {
+        let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+        let mut blocks = vec![Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap()];
+        for _ in 0..num_blocks {
+            let previous_block = blocks.last().unwrap();
+            let metadata = previous_block.header().metadata();
+            let metadata = Metadata::new(
+                metadata.network(),
+                metadata.round() + 1,
+                metadata.height() + 1,
+                metadata.total_supply_in_microcredits(),
+                metadata.cumulative_proof_target(),
+                metadata.coinbase_target(),
+                metadata.proof_target(),
+                metadata.last_coinbase_target(),
+                metadata.last_coinbase_timestamp(),
+                metadata.timestamp() + 1,
+            )
+            .unwrap();
+            let transactions = previous_block.transactions().clone();
+            let header = Header::from(
+                *previous_block.hash(),
+                transactions.to_root().unwrap(),
+                Field::zero(),
+                Field::zero(),
+                metadata,
+            )
+            .unwrap();
+            let block = Block::new(&private_key, previous_block.hash(), header, transactions, None, rng).unwrap();
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    /// Returns the sync pool, with the canonical map initialized to the given genesis block.
+    fn sample_sync_at_genesis(genesis: &Block<CurrentNetwork>) -> Sync<CurrentNetwork> {
+        let sync = Sync::<CurrentNetwork>::default();
+        sync.set_local_ip(sample_local_ip());
+        sync.insert_canon_locator(0, genesis.hash());
+        sync
+    }
+
     /// Checks that the sync pool (starting at genesis) returns the correct requests.
     fn check_prepare_block_requests(sync: Sync<CurrentNetwork>, min_common_ancestor: u32, peers: IndexSet<SocketAddr>) {
         // Check test assumptions are met.
@@ -840,6 +1550,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sync_state_changes_are_published() {
+        let sync = sample_sync_at_height(0);
+        let events = EventBus::default();
+        let subscription = events.subscribe(&[snarkos_node_ledger::Topic::SyncStateChanged]);
+        sync.set_event_bus(events);
+
+        // Ensure the sync state is unchanged while there are no peers to sync from.
+        assert!(sync.prepare_block_requests().is_empty());
+        assert!(!sync.is_syncing());
+        assert!(subscription.try_recv().is_none());
+
+        // Ensure the node starts syncing once a peer is ahead, and the change is only published once.
+        sync.update_peer_locators(sample_peer_ip(1), sample_block_locators(10)).unwrap();
+        assert!(!sync.prepare_block_requests().is_empty());
+        assert!(!sync.prepare_block_requests().is_empty());
+        assert!(sync.is_syncing());
+        assert_eq!(subscription.try_recv(), Some(Event::SyncStateChanged { is_syncing: true, canon_height: 0 }));
+        assert!(subscription.try_recv().is_none());
+
+        // Ensure the node stops syncing once the peer is removed.
+        sync.remove_peer(&sample_peer_ip(1));
+        assert!(sync.prepare_block_requests().is_empty());
+        assert_eq!(subscription.try_recv(), Some(Event::SyncStateChanged { is_syncing: false, canon_height: 0 }));
+    }
+
+    #[test]
+    fn test_get_canon_locators() {
+        for height in [0, 10, 99, 100, 10_150] {
+            let sync = sample_sync_at_height(height);
+            assert_eq!(sync.get_canon_locators().unwrap(), sample_block_locators(height));
+        }
+        // Ensure the block locators are not returned if a canon block hash is missing.
+        let sync = sample_sync_at_height(200);
+        sync.canon.write().remove(&150);
+        assert!(sync.get_canon_locators().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_no_block_requests_while_degraded() {
+        let sync = sample_sync_at_height(0);
+        let guard = StorageGuard::new(crate::StoragePolicy {
+            read_timeout: core::time::Duration::from_millis(10),
+            breaker_threshold: 1,
+            ..Default::default()
+        });
+        sync.set_storage_guard(guard.clone());
+        sync.update_peer_locators(sample_peer_ip(1), sample_block_locators(10)).unwrap();
+
+        // Stall a storage read, which trips the breaker.
+        let stall = || {
+            std::thread::sleep(core::time::Duration::from_millis(100));
+            Ok(())
+        };
+        assert!(guard.run(crate::OperationClass::Read, stall).await.is_err());
+        assert!(sync.is_degraded());
+        // Ensure no blocks are requested while the node is degraded.
+        assert!(sync.prepare_block_requests().is_empty());
+
+        // Ensure the blocks are requested again once the storage responds.
+        guard.run(crate::OperationClass::Read, || Ok(())).await.unwrap();
+        assert!(!sync.is_degraded());
+        assert!(!sync.prepare_block_requests().is_empty());
+    }
+
     #[test]
     fn test_prepare_block_requests_with_leading_fork_at_11() {
         let sync = sample_sync_at_height(0);
@@ -1077,6 +1852,72 @@ mod tests {
         assert_eq!(sync.get_peer_height(&peer_ip), None);
     }
 
+    #[test]
+    fn test_find_sync_peers_includes_pruned_peers() {
+        let sync = sample_sync_at_height(0);
+
+        let (peer_1, peer_2) = (sample_peer_ip(1), sample_peer_ip(2));
+        sync.update_peer_locators(peer_1, sample_block_locators(100)).unwrap();
+        sync.update_peer_locators(peer_2, sample_block_locators(100)).unwrap();
+
+        // Ensure a peer that pruned the next block is a sync peer, as it serves the blocks above its pruned height.
+        sync.set_peer_pruned_height(peer_1, 1);
+        sync.set_peer_pruned_height(peer_2, 50);
+        let (sync_peers, _) = sync.find_sync_peers().unwrap();
+        assert!(sync_peers.contains_key(&peer_1));
+        assert!(sync_peers.contains_key(&peer_2));
+
+        // Ensure the blocks below the pruned height of a peer are not requested from it.
+        let requests = sync.prepare_block_requests();
+        assert_eq!(requests.len(), MAX_BLOCK_REQUESTS);
+        for (height, (_, _, sync_ips)) in requests {
+            match height < 50 {
+                true => assert_eq!(sync_ips, indexset![peer_1]),
+                false => assert!(sync_ips.is_subset(&indexset![peer_1, peer_2])),
+            }
+        }
+
+        // Ensure there are no sync peers if every peer pruned the next block, unless one of them is archival.
+        sync.set_peer_pruned_height(peer_1, 10);
+        assert!(sync.find_sync_peers().is_none());
+        sync.set_peer_archival(peer_1, true);
+        assert_eq!(sync.get_peer_class(&peer_1), SyncPeerClass::Archival);
+        assert!(sync.find_sync_peers().is_some());
+
+        // Ensure the pruned height is cleared with the peer.
+        sync.remove_peer(&peer_2);
+        sync.update_peer_locators(peer_2, sample_block_locators(100)).unwrap();
+        let source = sync.get_sync_sources().into_iter().find(|source| source.peer_ip == peer_2).unwrap();
+        assert_eq!(source.class, SyncPeerClass::Pruned);
+        assert_eq!(source.pruned_height, 0);
+    }
+
+    #[test]
+    fn test_find_sync_peers_skips_non_serving_peers() {
+        let sync = sample_sync_at_height(0);
+
+        let (peer_1, peer_2) = (sample_peer_ip(1), sample_peer_ip(2));
+        sync.update_peer_locators(peer_1, sample_block_locators(100)).unwrap();
+        sync.update_peer_locators(peer_2, sample_block_locators(100)).unwrap();
+
+        // Ensure a peer that does not serve blocks is not a sync peer.
+        sync.set_peer_serves_blocks(peer_1, true);
+        sync.set_peer_serves_blocks(peer_2, false);
+        let (sync_peers, _) = sync.find_sync_peers().unwrap();
+        assert!(sync_peers.contains_key(&peer_1));
+        assert!(!sync_peers.contains_key(&peer_2));
+
+        // Ensure the peer is a sync peer once it serves blocks.
+        sync.set_peer_serves_blocks(peer_2, true);
+        assert!(sync.find_sync_peers().unwrap().0.contains_key(&peer_2));
+
+        // Ensure the flag is cleared with the peer.
+        sync.set_peer_serves_blocks(peer_2, false);
+        sync.remove_peer(&peer_2);
+        sync.update_peer_locators(peer_2, sample_block_locators(100)).unwrap();
+        assert!(sync.find_sync_peers().unwrap().0.contains_key(&peer_2));
+    }
+
     #[test]
     fn test_locators_insert_remove_insert() {
         let sync = sample_sync_at_height(0);
@@ -1141,5 +1982,167 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_orphans_delivered_in_reverse() {
+        let rng = &mut TestRng::default();
+        let blocks = sample_blocks(5, rng);
+        let sync = sample_sync_at_genesis(&blocks[0]);
+        let peer_ip = sample_peer_ip(1);
+
+        // Request the blocks from the peer.
+        for height in 1..=5 {
+            sync.insert_block_request(height, (None, None, indexset![peer_ip])).unwrap();
+        }
+        // Deliver the blocks in reverse order, which are orphans until the first block arrives.
+        for block in blocks[2..].iter().rev() {
+            sync.insert_block_response(peer_ip, SerialBlock::new(block.clone(), 1)).unwrap();
+            assert_eq!(sync.num_orphans(), (6 - block.height()) as usize);
+        }
+        // The chain is not connected yet, as the next block is missing.
+        assert!(sync.remove_block_response(sync.latest_canon_height() + 1).is_none());
+        sync.insert_block_response(peer_ip, SerialBlock::new(blocks[1].clone(), 1)).unwrap();
+        assert_eq!(sync.num_orphans(), 0);
+
+        // Connect the blocks, in order.
+        while let Some(block) = sync.remove_block_response(sync.latest_canon_height() + 1) {
+            sync.insert_canon_locator(block.block().height(), block.block().hash());
+        }
+        // Check that all blocks are canon.
+        assert_eq!(sync.latest_canon_height(), 5);
+        for block in &blocks {
+            assert_eq!(sync.get_canon_hash(block.height()), Some(block.hash()));
+        }
+        assert_eq!(sync.num_orphan_bytes(), 0);
+    }
+
+    #[test]
+    fn test_committed_headers_check_block_responses() {
+        let rng = &mut TestRng::default();
+        let blocks = sample_blocks(3, rng);
+        let sync = sample_sync_at_genesis(&blocks[0]);
+        let peer_ip = sample_peer_ip(1);
+
+        // Relay the committed headers ahead of their blocks, with mismatching fees for the last block.
+        let mut headers = blocks[1..].iter().map(|block| CommittedHeader::from_block(block, Some(0)).unwrap());
+        let (first, second) = (headers.next().unwrap(), headers.next().unwrap());
+        let mut third = headers.next().unwrap();
+        third.commitments.as_mut().unwrap().total_fees += 1;
+        for header in [first.clone(), second, third] {
+            sync.insert_committed_header(header);
+        }
+        // The legacy headers and the headers of canon blocks are not held.
+        sync.insert_committed_header(CommittedHeader::legacy(*blocks[0].header()));
+        assert!(sync.get_committed_header(0).is_none());
+        assert_eq!(sync.get_committed_header(1), Some(first));
+
+        // Ensure the bodies that match their headers are accepted.
+        for height in 1..=3 {
+            sync.insert_block_request(height, (None, None, indexset![peer_ip])).unwrap();
+        }
+        for block in &blocks[1..3] {
+            sync.insert_block_response(peer_ip, SerialBlock::new(block.clone(), 1)).unwrap();
+        }
+        // Ensure the body whose fees mismatch its header is rejected, along with the requests to the peer.
+        let error = sync.insert_block_response(peer_ip, SerialBlock::new(blocks[3].clone(), 1)).unwrap_err();
+        assert!(error.to_string().contains("does not match its header"), "{error}");
+        assert!(sync.get_block_request(3).is_none());
+
+        // Ensure the headers of the blocks that become canon are dropped.
+        sync.insert_canon_locator(1, blocks[1].hash());
+        sync.insert_committed_header(CommittedHeader::from_block(&blocks[3], Some(0)).unwrap());
+        assert!(sync.get_committed_header(1).is_none());
+        assert!(sync.get_committed_header(2).is_some());
+    }
+
+    #[test]
+    fn test_orphan_parent_requests() {
+        let rng = &mut TestRng::default();
+        let blocks = sample_blocks(5, rng);
+        let sync = sample_sync_at_genesis(&blocks[0]);
+        let (peer_1, peer_2) = (sample_peer_ip(1), sample_peer_ip(2));
+
+        // Deliver the last block from the first peer.
+        sync.insert_block_request(5, (None, None, indexset![peer_1])).unwrap();
+        sync.insert_block_response(peer_1, SerialBlock::new(blocks[5].clone(), 1)).unwrap();
+
+        // Check that the parent is requested from the peer that provided the orphan block.
+        let requests = sync.prepare_block_requests();
+        assert_eq!(requests, vec![(4, (Some(blocks[4].hash()), None, indexset![peer_1]))]);
+        sync.insert_block_request(4, requests[0].1.clone()).unwrap();
+        // The parent is not requested again while the request is pending.
+        assert!(sync.prepare_block_requests().is_empty());
+
+        // Time out the request, and check that the parent is requested from another peer.
+        sync.remove_block_request(4);
+        sync.update_peer_locators(peer_2, sample_block_locators(10)).unwrap();
+        let requests = sync.prepare_block_requests();
+        assert!(requests.contains(&(4, (Some(blocks[4].hash()), None, indexset![peer_2]))));
+    }
+
+    #[test]
+    fn test_orphan_flood_is_capped() {
+        let rng = &mut TestRng::default();
+        let blocks = sample_blocks(10, rng);
+        let sync = sample_sync_at_genesis(&blocks[0]);
+        let peer_ip = sample_peer_ip(1);
+
+        // Flood the sync pool with orphan blocks, as the first block is never delivered.
+        let num_bytes = MAX_ORPHAN_BYTES / 4;
+        for block in &blocks[2..] {
+            sync.insert_block_request(block.height(), (None, None, indexset![peer_ip])).unwrap();
+            sync.insert_block_response(peer_ip, SerialBlock::new(block.clone(), num_bytes)).unwrap();
+            // Check that the orphan blocks are within the byte limit.
+            assert!(sync.num_orphan_bytes() <= MAX_ORPHAN_BYTES);
+        }
+        assert_eq!(sync.num_orphans(), 4);
+        assert_eq!(sync.num_orphan_bytes(), 4 * num_bytes);
+
+        // Check that the oldest orphan blocks were evicted, along with their requests.
+        for height in 2..=6 {
+            assert!(sync.get_block_request(height).is_none());
+            assert!(sync.get_block_request_timestamp(height).is_none());
+        }
+        for height in 7..=10 {
+            assert!(sync.get_block_request(height).is_some());
+        }
+    }
+
+    #[test]
+    fn test_throttled_peer_is_demoted() {
+        let rng = &mut TestRng::default();
+        let blocks = sample_blocks(MIN_THROUGHPUT_SAMPLES as u32, rng);
+        let sync = sample_sync_at_genesis(&blocks[0]);
+        sync.set_throughput_floor(1024);
+        let (slow_peer, fast_peer) = (sample_peer_ip(1), sample_peer_ip(2));
+
+        // Deliver the blocks from the throttled peer, which delivers each block 10 seconds after it is requested.
+        for block in &blocks[1..] {
+            // Ensure the peer is not demoted before its throughput is measured.
+            assert!(!sync.is_demoted(&slow_peer));
+            sync.insert_block_request(block.height(), (None, None, indexset![slow_peer])).unwrap();
+            let requested_at = Instant::now().checked_sub(Duration::from_secs(10)).unwrap();
+            sync.request_timestamps.write().insert(block.height(), requested_at);
+            sync.insert_block_response(slow_peer, SerialBlock::new(block.clone(), 1024)).unwrap();
+        }
+        // Ensure the peer is demoted, and the measurement of its throughput restarts.
+        assert!(sync.is_demoted(&slow_peer));
+        assert_eq!(sync.get_peer_throughput(&slow_peer), None);
+
+        // Ensure the next blocks are requested from the other peer, while the throttled peer is demoted.
+        sync.update_peer_locators(slow_peer, sample_block_locators(10)).unwrap();
+        sync.update_peer_locators(fast_peer, sample_block_locators(10)).unwrap();
+        let requests = sync.prepare_block_requests();
+        assert!(!requests.is_empty());
+        for (_, (_, _, sync_ips)) in requests {
+            assert_eq!(sync_ips, indexset![fast_peer]);
+        }
+
+        // Ensure the throttled peer is restored once its demotion expires.
+        let demoted_at = Instant::now().checked_sub(Duration::from_secs(THROUGHPUT_DEMOTION_IN_SECS + 1)).unwrap();
+        sync.demoted_peers.write().insert(slow_peer, demoted_at);
+        sync.prepare_block_requests();
+        assert!(!sync.is_demoted(&slow_peer));
+    }
+
     // TODO: duplicate responses, ensure fails.
 }
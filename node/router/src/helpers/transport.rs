@@ -0,0 +1,358 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::noise_handshake;
+use snarkos_node_messages::{Message, PeerCodec, PostHandshakeState};
+use snarkos_node_tcp::ConnectionSide;
+use snarkvm::prelude::{error, Network};
+
+use futures::SinkExt;
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::mpsc,
+    time::{sleep_until, Instant},
+};
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+/// A bidirectional channel of messages with a peer, over which the protocol handlers run.
+#[async_trait]
+pub trait PeerTransport<N: Network>: Sized + Send {
+    /// Returns the (ambiguous) address of the peer.
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+
+    /// Returns the transport state of the Noise session, if the transport is encrypted.
+    fn noise_state(&self) -> Option<&PostHandshakeState>;
+
+    /// Sends the given message to the peer.
+    async fn send_message(&mut self, message: Message<N>) -> io::Result<()>;
+
+    /// Returns the next message from the peer, or `None` if the peer closed the transport.
+    async fn next_message(&mut self) -> io::Result<Option<Message<N>>>;
+
+    /// Encrypts the transport with the Noise handshake. The `node_side` is the connection side of this node.
+    async fn encrypt(self, private_key: &[u8], node_side: ConnectionSide) -> io::Result<Self>;
+
+    /// Closes the transport.
+    async fn close(&mut self) -> io::Result<()>;
+}
+
+/// A byte stream to a peer, over which messages are framed with the peer codec.
+pub trait PeerStream: AsyncRead + AsyncWrite + Unpin + Send {
+    /// Returns the (ambiguous) address of the peer.
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl PeerStream for TcpStream {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+}
+
+impl PeerStream for &mut TcpStream {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+}
+
+#[async_trait]
+impl<N: Network, S: PeerStream> PeerTransport<N> for Framed<S, PeerCodec<N>> {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+
+    fn noise_state(&self) -> Option<&PostHandshakeState> {
+        self.codec().post_handshake_state()
+    }
+
+    async fn send_message(&mut self, message: Message<N>) -> io::Result<()> {
+        self.send(message).await
+    }
+
+    async fn next_message(&mut self) -> io::Result<Option<Message<N>>> {
+        self.try_next().await
+    }
+
+    async fn encrypt(self, private_key: &[u8], node_side: ConnectionSide) -> io::Result<Self> {
+        noise_handshake(self, private_key, node_side).await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        SinkExt::<Message<N>>::close(self).await
+    }
+}
+
+/// The conditions of an in-memory link, which are applied to the messages sent over it.
+///
+/// The faults are sampled from an RNG seeded with `seed`, so that a run is reproducible.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// The delay before a sent message is delivered.
+    pub latency: Duration,
+    /// The number of sent messages that are held back and delivered in a shuffled order, or `0` for in-order delivery.
+    /// Held messages are delivered once the window is full, or when the link is flushed or closed.
+    pub reorder_window: usize,
+    /// The probability that a sent message is dropped.
+    pub drop_rate: f64,
+    /// The seed of the RNG that samples the faults.
+    pub seed: u64,
+}
+
+/// The state of one direction of an in-memory link.
+struct LinkState<N: Network> {
+    /// The conditions of the link.
+    conditions: LinkConditions,
+    /// The RNG that samples the faults.
+    rng: StdRng,
+    /// The messages held back for reordering.
+    pending: Vec<Message<N>>,
+    /// Whether the link was closed.
+    is_closed: bool,
+}
+
+/// The sending half of one direction of an in-memory link.
+///
+/// A delivery is the time at which the message is delivered, with `None` marking the closure of the link.
+#[derive(Clone)]
+pub struct MemoryLink<N: Network> {
+    /// The sender of the deliveries.
+    sender: mpsc::UnboundedSender<Option<(Instant, Message<N>)>>,
+    /// The state of the link.
+    state: Arc<Mutex<LinkState<N>>>,
+}
+
+impl<N: Network> MemoryLink<N> {
+    /// Returns the conditions of the link.
+    pub fn conditions(&self) -> LinkConditions {
+        self.state.lock().conditions
+    }
+
+    /// Updates the conditions of the link, reseeding the RNG that samples the faults.
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        let mut state = self.state.lock();
+        state.conditions = conditions;
+        state.rng = StdRng::seed_from_u64(conditions.seed);
+    }
+
+    /// Sends the given message over the link, subject to the conditions of the link.
+    /// Note that a dropped message is not an error, as the peer has no way of knowing it was sent.
+    pub fn send(&self, message: Message<N>) -> io::Result<()> {
+        let mut state = self.state.lock();
+        if state.is_closed || self.sender.is_closed() {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        // Drop the message with the configured probability.
+        let drop_rate = state.conditions.drop_rate;
+        if drop_rate > 0.0 && state.rng.gen_bool(drop_rate.min(1.0)) {
+            trace!("Dropped '{}' on an in-memory link", message.name());
+            return Ok(());
+        }
+        // Hold the message back, and deliver the held messages once the reorder window is full.
+        state.pending.push(message);
+        match state.pending.len() >= state.conditions.reorder_window {
+            true => self.deliver(&mut state),
+            false => Ok(()),
+        }
+    }
+
+    /// Delivers the messages held back for reordering.
+    pub fn flush(&self) -> io::Result<()> {
+        self.deliver(&mut self.state.lock())
+    }
+
+    /// Delivers the messages held back for reordering, and closes the link.
+    pub fn close(&self) {
+        let mut state = self.state.lock();
+        if !state.is_closed {
+            let _ = self.deliver(&mut state);
+            state.is_closed = true;
+            let _ = self.sender.send(None);
+        }
+    }
+
+    /// Returns `true` if the link was closed by either side.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().is_closed || self.sender.is_closed()
+    }
+
+    /// Delivers the held messages in a shuffled order, after the latency of the link.
+    fn deliver(&self, state: &mut LinkState<N>) -> io::Result<()> {
+        let LinkState { conditions, rng, pending, .. } = state;
+        pending.shuffle(rng);
+        let deliver_at = Instant::now() + conditions.latency;
+        for message in pending.drain(..) {
+            self.sender.send(Some((deliver_at, message))).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        }
+        Ok(())
+    }
+}
+
+/// An in-memory transport to a peer, for deterministic tests of the protocol handlers.
+///
+/// The transport carries messages rather than bytes, so it has no Noise session; the handshake over
+/// an in-memory transport proceeds as over an encrypted connection, without a handshake hash.
+pub struct MemoryTransport<N: Network> {
+    /// The (ambiguous) address of the peer.
+    peer_addr: SocketAddr,
+    /// The link to the peer.
+    link: MemoryLink<N>,
+    /// The receiver of the deliveries from the peer.
+    receiver: mpsc::UnboundedReceiver<Option<(Instant, Message<N>)>>,
+}
+
+impl<N: Network> MemoryTransport<N> {
+    /// Initializes a pair of connected transports, between the nodes at the given addresses.
+    /// The first transport is held by the node at `addr_a`, and the second by the node at `addr_b`.
+    pub fn pair(addr_a: SocketAddr, addr_b: SocketAddr, conditions: LinkConditions) -> (Self, Self) {
+        let (sender_a, receiver_b) = mpsc::unbounded_channel();
+        let (sender_b, receiver_a) = mpsc::unbounded_channel();
+        // Seed each direction of the link differently, so that their faults are independent.
+        let conditions_b = LinkConditions { seed: conditions.seed.wrapping_add(1), ..conditions };
+        let transport_a = Self { peer_addr: addr_b, link: Self::link_with(sender_a, conditions), receiver: receiver_a };
+        let transport_b =
+            Self { peer_addr: addr_a, link: Self::link_with(sender_b, conditions_b), receiver: receiver_b };
+        (transport_a, transport_b)
+    }
+
+    /// Returns the link to the peer, which may be used to send messages after the handshake.
+    pub fn link(&self) -> &MemoryLink<N> {
+        &self.link
+    }
+
+    /// Initializes a link with the given sender and conditions.
+    fn link_with(
+        sender: mpsc::UnboundedSender<Option<(Instant, Message<N>)>>,
+        conditions: LinkConditions,
+    ) -> MemoryLink<N> {
+        let state = LinkState {
+            conditions,
+            rng: StdRng::seed_from_u64(conditions.seed),
+            pending: Default::default(),
+            is_closed: false,
+        };
+        MemoryLink { sender, state: Arc::new(Mutex::new(state)) }
+    }
+}
+
+#[async_trait]
+impl<N: Network> PeerTransport<N> for MemoryTransport<N> {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer_addr)
+    }
+
+    fn noise_state(&self) -> Option<&PostHandshakeState> {
+        None
+    }
+
+    async fn send_message(&mut self, message: Message<N>) -> io::Result<()> {
+        self.link.send(message)
+    }
+
+    async fn next_message(&mut self) -> io::Result<Option<Message<N>>> {
+        match self.receiver.recv().await {
+            Some(Some((deliver_at, message))) => {
+                // Wait for the latency of the link.
+                sleep_until(deliver_at).await;
+                Ok(Some(message))
+            }
+            Some(None) | None => Ok(None),
+        }
+    }
+
+    async fn encrypt(self, _private_key: &[u8], _node_side: ConnectionSide) -> io::Result<Self> {
+        match self.link.is_closed() {
+            true => Err(error(format!("'{}' closed the transport", self.peer_addr))),
+            false => Ok(self),
+        }
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.link.close();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snarkos_node_messages::{NodeType, Ping, Pong};
+    use snarkvm::prelude::Testnet3;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the given number of distinct messages.
+    fn sample_messages(num_messages: u32) -> Vec<Message<CurrentNetwork>> {
+        (0..num_messages)
+            .map(|i| Message::Ping(Ping { version: i, node_type: NodeType::Client, block_locators: None }))
+            .collect()
+    }
+
+    /// Sends the given messages from `a` to `b`, closes the link, and returns the messages received by `b`.
+    async fn exchange(conditions: LinkConditions, messages: Vec<Message<CurrentNetwork>>) -> Vec<u32> {
+        let (a_ip, b_ip) = ("127.0.0.1:4130".parse().unwrap(), "127.0.0.1:4131".parse().unwrap());
+        let (mut a, mut b) = MemoryTransport::<CurrentNetwork>::pair(a_ip, b_ip, conditions);
+        for message in messages {
+            a.send_message(message).await.unwrap();
+        }
+        a.close().await.unwrap();
+
+        let mut received = vec![];
+        while let Some(message) = b.next_message().await.unwrap() {
+            match message {
+                Message::Ping(ping) => received.push(ping.version),
+                message => panic!("Unexpected message {}", message.name()),
+            }
+        }
+        received
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memory_link_conditions() {
+        // Ensure a perfect link delivers every message in order, after its latency.
+        let start = Instant::now();
+        let conditions = LinkConditions { latency: Duration::from_secs(5), ..Default::default() };
+        assert_eq!(exchange(conditions, sample_messages(10)).await, (0..10).collect::<Vec<_>>());
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+
+        // Ensure a reordering link delivers every message, in the same order for the same seed.
+        let conditions = LinkConditions { reorder_window: 4, seed: 7, ..Default::default() };
+        let reordered = exchange(conditions, sample_messages(10)).await;
+        assert_ne!(reordered, (0..10).collect::<Vec<_>>());
+        assert_eq!(reordered, exchange(conditions, sample_messages(10)).await);
+        let mut sorted = reordered.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+        // Ensure the messages are only reordered within their window.
+        assert!(reordered.chunks(4).enumerate().all(|(i, chunk)| chunk.iter().all(|v| *v / 4 == i as u32)));
+
+        // Ensure a lossy link drops the same messages for the same seed, and keeps the order of the others.
+        let conditions = LinkConditions { drop_rate: 0.5, seed: 3, ..Default::default() };
+        let delivered = exchange(conditions, sample_messages(100)).await;
+        assert!(!delivered.is_empty() && delivered.len() < 100);
+        assert!(delivered.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(delivered, exchange(conditions, sample_messages(100)).await);
+
+        // Ensure a closed link rejects further messages.
+        let (a_ip, b_ip) = ("127.0.0.1:4130".parse().unwrap(), "127.0.0.1:4131".parse().unwrap());
+        let (mut a, _b) = MemoryTransport::<CurrentNetwork>::pair(a_ip, b_ip, Default::default());
+        a.close().await.unwrap();
+        assert!(a.send_message(Message::Pong(Pong { is_fork: None })).await.is_err());
+    }
+}
@@ -14,23 +14,34 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Outbound, Peer};
+use crate::{Outbound, Peer, SerialBlock, SnapshotResponse};
+use snarkos_node_ledger::TransactionStructure;
 use snarkos_node_messages::{
     BeaconPropose,
     BlockRequest,
+    Capabilities,
+    CommittedHeader,
+    Data,
     DataBlocks,
+    GetParentTxs,
     Message,
+    OnionAddr,
     PeerResponse,
     Ping,
     Pong,
+    SnapshotChunk,
+    SnapshotManifest,
+    SnapshotRequest,
     UnconfirmedSolution,
     UnconfirmedTransaction,
 };
 use snarkos_node_tcp::protocols::Reading;
-use snarkvm::prelude::{Block, EpochChallenge, Header, Network, ProverSolution, Transaction};
+use snarkvm::prelude::{Block, EpochChallenge, Network, ProverSolution, ToBytes, Transaction};
 
 use anyhow::{bail, ensure, Result};
-use std::{net::SocketAddr, time::Instant};
+use bytes::Bytes;
+use std::{collections::VecDeque, net::SocketAddr};
+use tokio::time::Instant;
 
 #[async_trait]
 pub trait Inbound<N: Network>: Reading + Outbound<N> {
@@ -47,9 +58,24 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
             None => bail!("Unable to resolve the (ambiguous) peer address '{peer_addr}'"),
         };
 
-        // Drop the peer, if they have sent more than 1000 messages in the last 5 seconds.
+        // Record the message in the peer book.
+        self.router().record_inbound(&peer_ip, &message.name());
+        // Determine whether the peer is disconnecting gracefully.
+        let is_disconnect = matches!(message, Message::Disconnect(..));
+
+        // Handle the message, and record any protocol violation in the peer book.
+        let result = self.handle_inbound(peer_ip, message).await;
+        if result.is_err() && !is_disconnect {
+            self.router().record_misbehavior(&peer_ip);
+        }
+        result
+    }
+
+    /// Handles the inbound message from the given peer IP.
+    async fn handle_inbound(&self, peer_ip: SocketAddr, message: Message<N>) -> Result<()> {
+        // Drop the peer, if they have sent more than 1000 messages in the last 5 seconds, unless it is whitelisted.
         let num_messages = self.router().cache.insert_inbound_message(peer_ip, 5);
-        if num_messages >= 1000 {
+        if num_messages >= 1000 && !self.router().is_whitelisted(&peer_ip) {
             bail!("Dropping '{peer_ip}' for spamming messages (num_messages = {num_messages})")
         }
 
@@ -82,8 +108,12 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                 //  Only the block proposer should be able to send a valid block signature. This message type should not
                 //  be propagated by any other peers.
                 // Handle the block proposal.
+                let block_hash = block.hash();
                 match self.beacon_propose(peer_ip, serialized, block) {
-                    true => Ok(()),
+                    true => {
+                        self.router().record_blocks(&peer_ip, [block_hash]);
+                        Ok(())
+                    }
                     false => bail!("Peer '{peer_ip}' sent an invalid block proposal"),
                 }
             }
@@ -116,6 +146,11 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                 if end_height - start_height > DataBlocks::<N>::MAXIMUM_NUMBER_OF_BLOCKS as u32 {
                     bail!("Block request from '{peer_ip}' has an excessive range ({start_height}..{end_height})")
                 }
+                // If this node does not serve blocks, decline the block request, as advertised in the handshake.
+                if !self.router().capabilities().contains(Capabilities::SERVES_BLOCKS) {
+                    debug!("Declining a block request from '{peer_ip}' (this node does not serve blocks)");
+                    return Ok(());
+                }
 
                 match self.block_request(peer_ip, message) {
                     true => Ok(()),
@@ -130,6 +165,12 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                     bail!("Peer '{peer_ip}' is not following the protocol (unexpected block response)")
                 }
 
+                // Retrieve the size of the serialized blocks, before they are deserialized.
+                let num_bytes = match &message.blocks {
+                    Data::Buffer(bytes) => bytes.len(),
+                    Data::Object(blocks) => blocks.to_bytes_le()?.len(),
+                };
+
                 // Perform the deferred non-blocking deserialization of the blocks.
                 let blocks = match message.blocks.deserialize().await {
                     Ok(blocks) => blocks,
@@ -151,9 +192,15 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                     bail!("Peer '{peer_ip}' sent an invalid block response (range does not match the block request)")
                 }
 
-                // Process the block response.
-                match self.block_response(peer_ip, blocks.0) {
-                    true => Ok(()),
+                // Process the block response, with each block accounting for its share of the serialized size.
+                let block_hashes: Vec<_> = blocks.iter().map(|block| block.hash()).collect();
+                let num_bytes_per_block = num_bytes / blocks.len();
+                let blocks = blocks.0.into_iter().map(|block| SerialBlock::new(block, num_bytes_per_block)).collect();
+                match self.block_response(peer_ip, blocks) {
+                    true => {
+                        self.router().record_blocks(&peer_ip, block_hashes);
+                        Ok(())
+                    }
                     false => bail!("Peer '{peer_ip}' sent an invalid block response"),
                 }
             }
@@ -164,14 +211,20 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
             Message::Disconnect(message) => {
                 bail!("Disconnecting peer '{peer_ip}' for the following reason: {:?}", message.reason)
             }
-            Message::PeerRequest(..) => match self.peer_request(peer_ip) {
+            Message::GetParentTxs(message) => match self.get_parent_txs(peer_ip, &message.transaction_ids) {
                 true => Ok(()),
-                false => bail!("Peer '{peer_ip}' sent an invalid peer request"),
+                false => bail!("Peer '{peer_ip}' sent an invalid parent transactions request"),
             },
-            Message::PeerResponse(message) => match self.peer_response(peer_ip, &message.peers) {
+            Message::PeerRequest(..) => match self.peer_request(peer_ip) {
                 true => Ok(()),
-                false => bail!("Peer '{peer_ip}' sent an invalid peer response"),
+                false => bail!("Peer '{peer_ip}' sent an invalid peer request"),
             },
+            Message::PeerResponse(message) => {
+                match self.peer_response(peer_ip, &message.peers, &message.onion_peers) {
+                    true => Ok(()),
+                    false => bail!("Peer '{peer_ip}' sent an invalid peer response"),
+                }
+            }
             Message::Ping(message) => match self.ping(peer_ip, message) {
                 true => Ok(()),
                 false => bail!("Peer '{peer_ip}' sent an invalid ping"),
@@ -183,8 +236,8 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
             Message::PuzzleRequest(..) => {
                 // Insert the puzzle request for the peer, and fetch the recent frequency.
                 let frequency = self.router().cache.insert_inbound_puzzle_request(peer_ip);
-                // Check if the number of puzzle requests is within the limit.
-                if frequency > Self::MAXIMUM_PUZZLE_REQUESTS_PER_INTERVAL {
+                // Check if the number of puzzle requests is within the limit, unless the peer is whitelisted.
+                if frequency > Self::MAXIMUM_PUZZLE_REQUESTS_PER_INTERVAL && !self.router().is_whitelisted(&peer_ip) {
                     bail!("Peer '{peer_ip}' is not following the protocol (excessive puzzle requests)")
                 }
                 // Process the puzzle request.
@@ -212,12 +265,39 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                     false => bail!("Peer '{peer_ip}' sent an invalid puzzle response"),
                 }
             }
+            Message::SnapshotChunk(message) => {
+                // Ensure this node is downloading a snapshot from the peer.
+                match self.router().snapshots().deliver(peer_ip, SnapshotResponse::Chunk(message)) {
+                    true => Ok(()),
+                    false => bail!("Peer '{peer_ip}' is not following the protocol (unexpected snapshot chunk)"),
+                }
+            }
+            Message::SnapshotManifest(message) => {
+                // Ensure this node is downloading a snapshot from the peer.
+                match self.router().snapshots().deliver(peer_ip, SnapshotResponse::Manifest(message)) {
+                    true => Ok(()),
+                    false => bail!("Peer '{peer_ip}' is not following the protocol (unexpected snapshot manifest)"),
+                }
+            }
+            Message::SnapshotRequest(message) => {
+                // If this node does not serve snapshots, decline the snapshot request, as advertised in the handshake.
+                if !self.router().capabilities().contains(Capabilities::SERVES_SNAPSHOTS) {
+                    debug!("Declining a snapshot request from '{peer_ip}' (this node does not serve snapshots)");
+                    return Ok(());
+                }
+                match self.snapshot_request(peer_ip, message) {
+                    true => Ok(()),
+                    false => bail!("Peer '{peer_ip}' sent an invalid snapshot request"),
+                }
+            }
             Message::UnconfirmedSolution(message) => {
                 // Clone the serialized message.
                 let serialized = message.clone();
-                // Update the timestamp for the unconfirmed solution.
-                let seen_before =
-                    self.router().cache.insert_inbound_solution(peer_ip, message.puzzle_commitment).is_some();
+                // Update the timestamp for the unconfirmed solution, in the inventory known to the peer.
+                let seen_before = self
+                    .router()
+                    .peer_state(&peer_ip)
+                    .map_or(true, |state| state.insert_inbound_solution(message.puzzle_commitment).is_some());
                 // Determine whether to propagate the solution.
                 if seen_before {
                     bail!("Skipping 'UnconfirmedSolution' from '{peer_ip}'")
@@ -240,9 +320,11 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
             Message::UnconfirmedTransaction(message) => {
                 // Clone the serialized message.
                 let serialized = message.clone();
-                // Update the timestamp for the unconfirmed transaction.
-                let seen_before =
-                    self.router().cache.insert_inbound_transaction(peer_ip, message.transaction_id).is_some();
+                // Update the timestamp for the unconfirmed transaction, in the inventory known to the peer.
+                let seen_before = self
+                    .router()
+                    .peer_state(&peer_ip)
+                    .map_or(true, |state| state.insert_inbound_transaction(message.transaction_id).is_some());
                 // Determine whether to propagate the transaction.
                 if seen_before {
                     bail!("Skipping 'UnconfirmedTransaction' from '{peer_ip}'")
@@ -256,8 +338,28 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
                 if message.transaction_id != transaction.id() {
                     bail!("Peer '{peer_ip}' is not following the 'UnconfirmedTransaction' protocol")
                 }
+                // Ensure the transaction is not a coinbase transaction, as it is only valid in a block.
+                if transaction.is_coinbase() {
+                    bail!("Peer '{peer_ip}' sent a coinbase transaction as an unconfirmed transaction")
+                }
+                // Ensure the transaction is well-formed.
+                if let Err(violation) = transaction.validate_structure() {
+                    bail!("Peer '{peer_ip}' sent a malformed transaction - {violation}")
+                }
+                // If the transaction was relayed before its parents, hold it until they are fetched from the peer.
+                let missing = self.missing_parents(&message.parent_ids);
+                if !missing.is_empty() {
+                    match self.router().orphans().insert(peer_ip, serialized, transaction, missing) {
+                        Ok(transaction_ids) if transaction_ids.is_empty() => (),
+                        Ok(transaction_ids) => {
+                            self.send(peer_ip, Message::GetParentTxs(GetParentTxs { transaction_ids }));
+                        }
+                        Err(error) => debug!("Dropped orphan transaction '{}' - {error}", message.transaction_id),
+                    }
+                    return Ok(());
+                }
                 // Handle the unconfirmed transaction.
-                match self.unconfirmed_transaction(peer_ip, serialized, transaction) {
+                match self.handle_unconfirmed_transaction(peer_ip, serialized, transaction) {
                     true => Ok(()),
                     false => bail!("Peer '{peer_ip}' sent an invalid unconfirmed transaction"),
                 }
@@ -287,27 +389,44 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
     fn block_request(&self, peer_ip: SocketAddr, _message: BlockRequest) -> bool;
 
     /// Handles a `BlockResponse` message.
-    fn block_response(&self, peer_ip: SocketAddr, _blocks: Vec<Block<N>>) -> bool;
+    fn block_response(&self, peer_ip: SocketAddr, _blocks: Vec<SerialBlock<N>>) -> bool;
 
     /// Handles a `PeerRequest` message.
     fn peer_request(&self, peer_ip: SocketAddr) -> bool {
-        // Retrieve the connected peers.
-        let peers = self.router().connected_peers();
+        // Retrieve the connected peers that accept inbound connections.
+        let (mut peers, mut onion_peers) = (Vec::new(), Vec::new());
+        for peer in self.router().get_connected_peers().into_iter().filter(|peer| peer.is_listening()) {
+            match self.router().onion_peer(&peer.ip()) {
+                Some(onion) => onion_peers.push(onion),
+                None => peers.push(peer.ip()),
+            }
+        }
+        // Send the onion addresses (including the one of this node) only to the peers able to dial them.
+        let is_onion_capable = self
+            .router()
+            .get_connected_peer(&peer_ip)
+            .map_or(false, |peer| peer.capabilities().contains(Capabilities::ONION_ADDRS));
+        match is_onion_capable {
+            true => onion_peers.extend(self.router().onion_service()),
+            false => onion_peers.clear(),
+        }
         // Send a `PeerResponse` message to the peer.
-        self.send(peer_ip, Message::PeerResponse(PeerResponse { peers }));
+        self.send(peer_ip, Message::PeerResponse(PeerResponse { peers, onion_peers }));
         true
     }
 
     /// Handles a `PeerResponse` message.
-    fn peer_response(&self, _peer_ip: SocketAddr, peers: &[SocketAddr]) -> bool {
+    fn peer_response(&self, _peer_ip: SocketAddr, peers: &[SocketAddr], onion_peers: &[OnionAddr]) -> bool {
         // Adds the given peer IPs to the list of candidate peers.
         self.router().insert_candidate_peers(peers);
+        // Adds the given onion addresses to the list of candidate peers, if they are reachable.
+        self.router().insert_onion_peers(onion_peers);
         true
     }
 
     fn ping(&self, peer_ip: SocketAddr, message: Ping<N>) -> bool {
         // Ensure the message protocol version is not outdated.
-        if message.version < Message::<N>::VERSION {
+        if message.version < self.router().minimum_version() {
             warn!("Dropping '{peer_ip}' on version {} (outdated)", message.version);
             return false;
         }
@@ -380,7 +499,62 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
     fn puzzle_request(&self, peer_ip: SocketAddr) -> bool;
 
     /// Handles a `PuzzleResponse` message.
-    fn puzzle_response(&self, peer_ip: SocketAddr, _challenge: EpochChallenge<N>, _header: Header<N>) -> bool;
+    fn puzzle_response(&self, peer_ip: SocketAddr, _challenge: EpochChallenge<N>, _header: CommittedHeader<N>) -> bool;
+
+    /// Handles a `SnapshotRequest` message, by sending the manifest of the snapshot, or the requested chunk,
+    /// within the limits of the snapshot streams served by this node.
+    fn snapshot_request(&self, peer_ip: SocketAddr, message: SnapshotRequest) -> bool {
+        let snapshots = self.router().snapshots();
+        match message.chunk {
+            None => {
+                if let Err(error) = snapshots.open_stream(peer_ip) {
+                    debug!("Declining a snapshot request from '{peer_ip}' - {error}");
+                    return true;
+                }
+                match self.snapshot_manifest() {
+                    Some(manifest) => {
+                        self.send(peer_ip, Message::SnapshotManifest(manifest));
+                    }
+                    None => debug!("Declining a snapshot request from '{peer_ip}' (no snapshot is available)"),
+                }
+            }
+            Some(index) => {
+                if let Err(error) = snapshots.allow_chunk(peer_ip) {
+                    debug!("Declining a snapshot request from '{peer_ip}' - {error}");
+                    return true;
+                }
+                match self.snapshot_chunk(index) {
+                    Some(bytes) => {
+                        self.send(peer_ip, Message::SnapshotChunk(SnapshotChunk { index, bytes }));
+                    }
+                    None => debug!("Declining a request from '{peer_ip}' for the unknown snapshot chunk {index}"),
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the manifest of the snapshot this node serves.
+    /// By default, this node holds no ledger to serve, so no snapshot is available.
+    fn snapshot_manifest(&self) -> Option<SnapshotManifest<N>> {
+        None
+    }
+
+    /// Returns the bytes of the given chunk of the snapshot this node serves.
+    fn snapshot_chunk(&self, _index: u32) -> Option<Bytes> {
+        None
+    }
+
+    /// Imports the given chunk of the snapshot of the given peer, whose bytes were checked against the manifest.
+    async fn import_snapshot_chunk(
+        &self,
+        _peer_ip: SocketAddr,
+        _manifest: &SnapshotManifest<N>,
+        _index: u32,
+        _bytes: Bytes,
+    ) -> Result<()> {
+        bail!("This node does not import snapshots")
+    }
 
     /// Handles an `UnconfirmedSolution` message.
     async fn unconfirmed_solution(
@@ -397,4 +571,64 @@ pub trait Inbound<N: Network>: Reading + Outbound<N> {
         serialized: UnconfirmedTransaction<N>,
         _transaction: Transaction<N>,
     ) -> bool;
+
+    /// Handles the given unconfirmed transaction, whose parents are known, and then the orphan transactions
+    /// that were only waiting on it, in order of arrival.
+    fn handle_unconfirmed_transaction(
+        &self,
+        peer_ip: SocketAddr,
+        serialized: UnconfirmedTransaction<N>,
+        transaction: Transaction<N>,
+    ) -> bool {
+        let mut queue = VecDeque::from([(peer_ip, serialized, transaction)]);
+        let mut is_first = true;
+        while let Some((sender_ip, serialized, transaction)) = queue.pop_front() {
+            let transaction_id = transaction.id();
+            if !self.unconfirmed_transaction(sender_ip, serialized, transaction) {
+                // Only the validity of the given transaction is reported, as the orphans may be from other peers.
+                match is_first {
+                    true => return false,
+                    false => {
+                        debug!("Dropped orphan transaction '{transaction_id}' from '{sender_ip}'");
+                        continue;
+                    }
+                }
+            }
+            is_first = false;
+            self.router().record_transaction(&sender_ip, transaction_id);
+            // If the transaction is a local transaction under embargo, it reached the network,
+            // so broadcast it to the peers that did not receive it yet.
+            if let Some(message) = self.router().diffusion().end_embargo(&transaction_id) {
+                self.broadcast_local_transaction(message, &[sender_ip]);
+            }
+            // Handle the orphan transactions that are no longer missing any parent.
+            let orphans = self.router().orphans().resolve(&transaction_id);
+            queue.extend(orphans.into_iter().map(|orphan| (orphan.peer_ip, orphan.message, orphan.transaction)));
+        }
+        true
+    }
+
+    /// Returns the given parents of a relayed transaction that are unknown to this node.
+    /// By default, this node does not track the parents of the transactions, so none are missing.
+    fn missing_parents(&self, _parent_ids: &[N::TransactionID]) -> Vec<N::TransactionID> {
+        vec![]
+    }
+
+    /// Returns the messages of the unconfirmed transactions with the given IDs, which this node holds,
+    /// in the order in which they must be received.
+    fn unconfirmed_transactions(&self, _transaction_ids: &[N::TransactionID]) -> Vec<UnconfirmedTransaction<N>> {
+        vec![]
+    }
+
+    /// Handles a `GetParentTxs` message, by sending the requested transactions that this node holds.
+    fn get_parent_txs(&self, peer_ip: SocketAddr, transaction_ids: &[N::TransactionID]) -> bool {
+        for message in self.unconfirmed_transactions(transaction_ids) {
+            // Send the transaction even if it was sent before, as the peer is missing it.
+            if let Some(state) = self.router().peer_state(&peer_ip) {
+                state.remove_outbound_transaction(&message.transaction_id);
+            }
+            self.send(peer_ip, Message::UnconfirmedTransaction(message));
+        }
+        true
+    }
 }
@@ -40,15 +40,42 @@ mod routing;
 pub use routing::*;
 
 use snarkos_account::Account;
-use snarkos_node_messages::NodeType;
-use snarkos_node_tcp::{Config, Tcp};
+use snarkos_node_ledger::{Event, EventBus, NetworkTime};
+use snarkos_node_messages::{
+    Capabilities,
+    Compression,
+    CompressionStats,
+    Message,
+    NodeId,
+    NodeRole,
+    NodeType,
+    NoiseCodec,
+    NoiseState,
+    OnionAddr,
+    PeerCodec,
+    PostHandshakeState,
+};
+#[cfg(feature = "metrics")]
+use snarkos_node_metrics as metrics;
+use snarkos_node_tcp::{is_proxy_error, Config, ProxyConfig, Tcp};
 use snarkvm::prelude::{Address, Network, PrivateKey, ViewKey};
 
 use anyhow::{bail, Result};
 use core::str::FromStr;
 use indexmap::{IndexMap, IndexSet};
 use parking_lot::{Mutex, RwLock};
-use std::{collections::HashSet, future::Future, net::SocketAddr, ops::Deref, sync::Arc, time::Instant};
+use rand::{seq::IteratorRandom, Rng};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    net::SocketAddr,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tokio::task::JoinHandle;
 
 #[derive(Clone)]
@@ -67,6 +94,14 @@ pub struct InnerRouter<N: Network> {
     tcp: Tcp,
     /// The node type.
     node_type: NodeType,
+    /// The role in which the node operates, which determines the capabilities it advertises in the handshake.
+    role: RwLock<NodeRole>,
+    /// The compression of the large messages sent to the peers that advertise it.
+    compression: RwLock<Compression>,
+    /// The onion address of the node, if it is reachable as an onion service.
+    onion_service: RwLock<Option<OnionAddr>>,
+    /// The map of the virtual addresses of the known onion peers to their onion addresses.
+    onion_peers: RwLock<HashMap<SocketAddr, OnionAddr>>,
     /// The account of the node.
     account: Account<N>,
     /// The cache.
@@ -77,8 +112,16 @@ pub struct InnerRouter<N: Network> {
     sync: Sync<N>,
     /// The set of trusted peers.
     trusted_peers: IndexSet<SocketAddr>,
+    /// The transport encryption configuration.
+    noise: NoiseConfig,
+    /// The map of connected peer IPs to the transport state of their encrypted connection.
+    noise_states: RwLock<HashMap<SocketAddr, PostHandshakeState>>,
+    /// The map of connected peer IPs to the links of their in-memory transports.
+    memory_links: RwLock<HashMap<SocketAddr, MemoryLink<N>>>,
     /// The map of connected peer IPs to their peer handlers.
     connected_peers: RwLock<IndexMap<SocketAddr, Peer<N>>>,
+    /// The map of connected peer IPs to the state of their connection, which is only read-locked by their messages.
+    peer_states: RwLock<HashMap<SocketAddr, Arc<PeerState<N>>>>,
     /// The set of handshaking peers. While `Tcp` already recognizes the connecting IP addresses
     /// and prevents duplicate outbound connection attempts to the same IP address, it is unable to
     /// prevent simultaneous "two-way" connections between two peers (i.e. both nodes simultaneously
@@ -86,8 +129,32 @@ pub struct InnerRouter<N: Network> {
     connecting_peers: Mutex<HashSet<SocketAddr>>,
     /// The set of candidate peer IPs.
     candidate_peers: RwLock<IndexSet<SocketAddr>>,
+    /// The DNS seeds, and the candidate peers sourced from them.
+    dns_seeds: DnsSeeds,
     /// The set of restricted peer IPs.
     restricted_peers: RwLock<IndexMap<SocketAddr, Instant>>,
+    /// The set of peer IPs found to be on a different network or chain, with the time they were found.
+    mismatched_peers: RwLock<IndexMap<SocketAddr, Instant>>,
+    /// The peer book, with the statistics of every peer this node has been connected to.
+    peer_book: PeerBook<N>,
+    /// The diffusion of the local transactions.
+    diffusion: Diffusion<N>,
+    /// The transactions relayed before their parents, which are being fetched.
+    orphans: OrphanTransactions<N>,
+    /// The relay queues of the peers, which order the relayed transactions by their fee rate.
+    relay_queues: RelayQueues<N>,
+    /// The snapshot streams served by this node, and its snapshot download.
+    snapshots: Snapshots<N>,
+    /// The event bus, on which the connected, disconnected, and banned peers are published.
+    events: EventBus<N>,
+    /// The guard of the storage operations, which switches the node into degraded mode when the storage stalls.
+    storage: StorageGuard,
+    /// The maximum number of connected peers, which may be lowered while the node is running.
+    max_peers: AtomicUsize,
+    /// The height below which this node pruned the bodies of its blocks, which is advertised in the handshake.
+    pruned_height: AtomicU32,
+    /// The network-adjusted time, which is sampled from the timestamps in the handshakes of the peers.
+    network_time: RwLock<NetworkTime>,
     /// The spawned handles.
     handles: Mutex<Vec<JoinHandle<()>>>,
     /// The boolean flag for the development mode.
@@ -99,6 +166,17 @@ impl<N: Network> Router<N> {
     const MAXIMUM_CANDIDATE_PEERS: usize = 10_000;
     /// The maximum number of connection failures permitted by an inbound connecting peer.
     const MAXIMUM_CONNECTION_FAILURES: usize = 5;
+    /// The maximum number of mismatched peers remembered by the node, beyond which the earliest found are forgotten.
+    const MAXIMUM_MISMATCHED_PEERS: usize = 10_000;
+    /// The duration in seconds in between updates of the gauges of the metrics.
+    #[cfg(feature = "metrics")]
+    const METRICS_UPDATE_IN_SECS: u64 = 5;
+    /// The duration in seconds (1 day) after which a mismatched peer may be dialed again, in case it switched chains.
+    const MISMATCH_EXPIRY_IN_SECS: u64 = 24 * 60 * 60;
+    /// The duration in seconds in between aggregations of the connection states into the peer book.
+    const PEER_BOOK_AGGREGATION_IN_SECS: u64 = 1;
+    /// The duration in seconds in between writes of the peer book to its file.
+    const PEER_BOOK_FLUSH_IN_SECS: u64 = 30;
     /// The duration in seconds after which a connected peer is considered inactive or
     /// disconnected if no message has been received in the meantime.
     const RADIO_SILENCE_IN_SECS: u64 = 150; // 2.5 minutes
@@ -106,32 +184,146 @@ impl<N: Network> Router<N> {
 
 impl<N: Network> Router<N> {
     /// Initializes a new `Router` instance.
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         node_ip: SocketAddr,
         node_type: NodeType,
         account: Account<N>,
         trusted_peers: &[SocketAddr],
+        noise: NoiseConfig,
+        peer_book: PeerBook<N>,
+        events: EventBus<N>,
         max_peers: u16,
         is_dev: bool,
     ) -> Result<Self> {
         // Initialize the TCP stack.
         let tcp = Tcp::new(Config::new(node_ip, max_peers));
         // Initialize the router.
-        Ok(Self(Arc::new(InnerRouter {
+        let router = Self(Arc::new(InnerRouter {
             tcp,
             node_type,
+            role: Default::default(),
+            compression: Default::default(),
+            onion_service: Default::default(),
+            onion_peers: Default::default(),
             account,
             cache: Default::default(),
             resolver: Default::default(),
             sync: Default::default(),
             trusted_peers: trusted_peers.iter().copied().collect(),
+            noise,
+            noise_states: Default::default(),
+            memory_links: Default::default(),
             connected_peers: Default::default(),
+            peer_states: Default::default(),
             connecting_peers: Default::default(),
             candidate_peers: Default::default(),
+            dns_seeds: DnsSeeds::new(match is_dev {
+                true => vec![],
+                false => default_dns_seeds::<N>(),
+            }),
             restricted_peers: Default::default(),
+            mismatched_peers: Default::default(),
+            peer_book,
+            diffusion: Default::default(),
+            orphans: Default::default(),
+            relay_queues: Default::default(),
+            snapshots: Default::default(),
+            events: events.clone(),
+            storage: Default::default(),
+            max_peers: AtomicUsize::new(max_peers as usize),
+            pruned_height: Default::default(),
+            network_time: Default::default(),
             handles: Default::default(),
             is_dev,
-        })))
+        }));
+        // Publish the changes of the sync state on the event bus.
+        router.sync.set_event_bus(events);
+        // Stop requesting new blocks while the storage stalls.
+        router.sync.set_storage_guard(router.storage.clone());
+        // Periodically aggregate the connection states into the peer book.
+        router.initialize_peer_book_aggregation();
+        // If the peer book is persisted, periodically write it to its file.
+        if router.peer_book.path().is_some() {
+            router.initialize_peer_book_flushes();
+        }
+        #[cfg(feature = "metrics")]
+        router.initialize_metrics();
+        Ok(router)
+    }
+
+    /// Spawns a task that aggregates the connection states into the peer book, every `PEER_BOOK_AGGREGATION_IN_SECS`
+    /// seconds, which bounds the staleness of the statistics of the connected peers.
+    fn initialize_peer_book_aggregation(&self) {
+        // Hold a weak reference, so that the task does not keep the router alive.
+        let router = Arc::downgrade(&self.0);
+        self.spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(Self::PEER_BOOK_AGGREGATION_IN_SECS)).await;
+                match router.upgrade() {
+                    Some(router) => Self(router).aggregate_peer_book(),
+                    None => break,
+                }
+            }
+        });
+    }
+
+    /// Spawns a task that writes the peer book to its file in batches, every `PEER_BOOK_FLUSH_IN_SECS` seconds.
+    fn initialize_peer_book_flushes(&self) {
+        // Hold a weak reference, so that the task does not keep the router alive.
+        let router = Arc::downgrade(&self.0);
+        self.spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(Self::PEER_BOOK_FLUSH_IN_SECS)).await;
+                match router.upgrade() {
+                    Some(router) => Self(router).flush_peer_book(),
+                    None => break,
+                }
+            }
+        });
+    }
+
+    /// Spawns a task that updates the gauges of the metrics from the events of the event bus,
+    /// every `METRICS_UPDATE_IN_SECS` seconds.
+    #[cfg(feature = "metrics")]
+    fn initialize_metrics(&self) {
+        use snarkos_node_ledger::Topic;
+
+        // Subscribe to the events that change the gauges.
+        let subscription = self.events.subscribe(&[
+            Topic::BlockConnected,
+            Topic::BlockDisconnected,
+            Topic::PeerConnected,
+            Topic::PeerDisconnected,
+            Topic::PeerBanned,
+        ]);
+        // Hold a weak reference, so that the task does not keep the router alive.
+        let router = Arc::downgrade(&self.0);
+        self.spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(Self::METRICS_UPDATE_IN_SECS)).await;
+                let router = match router.upgrade() {
+                    Some(router) => Self(router),
+                    None => break,
+                };
+                // Update the gauges from the events since the last update.
+                let mut are_peers_changed = false;
+                for event in subscription.try_iter() {
+                    match event {
+                        Event::BlockConnected { height, .. } => metrics::gauge!(metrics::blocks::HEIGHT, height as f64),
+                        Event::BlockDisconnected { height, .. } => {
+                            metrics::gauge!(metrics::blocks::HEIGHT, height.saturating_sub(1) as f64)
+                        }
+                        _ => are_peers_changed = true,
+                    }
+                }
+                if are_peers_changed {
+                    metrics::gauge!(metrics::peers::CONNECTED, router.number_of_connected_peers() as f64);
+                    metrics::gauge!(metrics::peers::CANDIDATE, router.number_of_candidate_peers() as f64);
+                    metrics::gauge!(metrics::peers::RESTRICTED, router.number_of_restricted_peers() as f64);
+                }
+            }
+        });
     }
 
     /// Attempts to connect to the given peer IP.
@@ -151,7 +343,12 @@ impl<N: Network> Router<N> {
                 // If the connection was not allowed, log the error.
                 Err(error) => {
                     router.connecting_peers.lock().remove(&peer_ip);
-                    warn!("Unable to connect to '{peer_ip}' - {error}")
+                    match is_proxy_error(&error) {
+                        true => {
+                            warn!("Unable to connect to '{}' through the proxy - {error}", router.peer_name(peer_ip))
+                        }
+                        false => warn!("Unable to connect to '{peer_ip}' - {error}"),
+                    }
                 }
             }
         });
@@ -175,6 +372,14 @@ impl<N: Network> Router<N> {
         if self.is_restricted(&peer_ip) {
             bail!("Dropping connection attempt to '{peer_ip}' (restricted)")
         }
+        // Ensure the peer is not banned.
+        if self.is_banned(&peer_ip) {
+            bail!("Dropping connection attempt to '{peer_ip}' (banned)")
+        }
+        // Ensure the peer is not on a different network or chain.
+        if self.is_mismatched(&peer_ip) {
+            bail!("Dropping connection attempt to '{peer_ip}' (different network or chain)")
+        }
         // Ensure the node is not already connecting to this peer.
         if !self.connecting_peers.lock().insert(peer_ip) {
             bail!("Dropping connection attempt to '{peer_ip}' (already shaking hands as the initiator)")
@@ -184,6 +389,13 @@ impl<N: Network> Router<N> {
 
     /// Disconnects from the given peer IP, if the peer is connected.
     pub fn disconnect(&self, peer_ip: SocketAddr) {
+        // If the peer is connected over an in-memory transport, close the link.
+        let memory_link = self.memory_links.write().remove(&peer_ip);
+        if let Some(link) = memory_link {
+            link.close();
+            self.remove_connected_peer(peer_ip);
+            return;
+        }
         let router = self.clone();
         tokio::spawn(async move {
             if let Some(peer_addr) = router.resolve_to_ambiguous(&peer_ip) {
@@ -195,8 +407,27 @@ impl<N: Network> Router<N> {
     }
 
     /// Returns the IP address of this node.
+    ///
+    /// If this node does not listen for inbound connections, this is the configured address of the node.
     pub fn local_ip(&self) -> SocketAddr {
-        self.tcp.listening_addr().expect("The TCP listener is not enabled")
+        match self.tcp.listening_addr() {
+            Ok(listening_addr) => listening_addr,
+            Err(_) => match self.listens() {
+                true => panic!("The TCP listener is not enabled"),
+                false => {
+                    let config = self.tcp.config();
+                    SocketAddr::new(
+                        config.listener_ip.expect("The node IP is not configured"),
+                        config.desired_listening_port.unwrap_or_default(),
+                    )
+                }
+            },
+        }
+    }
+
+    /// Returns `true` if this node is listening for inbound connections.
+    pub fn is_listening(&self) -> bool {
+        self.tcp.listening_addr().is_ok()
     }
 
     /// Returns `true` if the given IP is this node.
@@ -210,6 +441,113 @@ impl<N: Network> Router<N> {
         self.node_type
     }
 
+    /// Returns the role in which the node operates.
+    pub fn role(&self) -> NodeRole {
+        *self.role.read()
+    }
+
+    /// Sets the role in which the node operates. This must be called before the routing is initialized.
+    pub fn set_role(&self, role: NodeRole) {
+        *self.role.write() = role;
+    }
+
+    /// Returns the compression of the large messages sent to the peers that advertise it.
+    pub fn compression(&self) -> Compression {
+        *self.compression.read()
+    }
+
+    /// Sets the compression of the large messages, which is negotiated in the handshake.
+    /// This must be called before the routing is initialized.
+    pub fn set_compression(&self, compression: Compression) {
+        *self.compression.write() = compression;
+    }
+
+    /// Returns `true` if this node accepts inbound connections, which it does not in proxy-only mode,
+    /// unless it listens for the connections of its onion service.
+    pub fn listens(&self) -> bool {
+        let is_proxy_only = self.tcp.proxy().map_or(false, |proxy| proxy.proxy_only);
+        self.role().listens() && (!is_proxy_only || self.onion_service.read().is_some())
+    }
+
+    /// Returns the capabilities this node advertises in the handshake.
+    pub fn capabilities(&self) -> Capabilities {
+        // Every node fetches the missing parents of the transactions it is relayed, regardless of its role.
+        let mut capabilities = self.role().capabilities().union(Capabilities::PARENT_TXS);
+        // Only a node with a proxy is able to dial the onion addresses.
+        if self.tcp.proxy().is_some() {
+            capabilities = capabilities.union(Capabilities::ONION_ADDRS);
+        }
+        if !self.listens() {
+            capabilities = capabilities.without(Capabilities::LISTENS);
+        }
+        // Only a node that serves blocks and did not prune any of them is archival.
+        if capabilities.contains(Capabilities::SERVES_BLOCKS) && self.pruned_height() == 0 {
+            capabilities = capabilities.union(Capabilities::ARCHIVAL);
+        }
+        // Only an archival node serves the snapshot of its ledger, if it allows any snapshot stream.
+        if capabilities.contains(Capabilities::ARCHIVAL) && self.snapshots.max_streams() > 0 {
+            capabilities = capabilities.union(Capabilities::SERVES_SNAPSHOTS);
+        }
+        // Only a node with a compression decompresses the messages it receives.
+        if self.compression().is_enabled() {
+            capabilities = capabilities.union(Capabilities::COMPRESSION);
+        }
+        capabilities
+    }
+
+    /// Routes every outbound connection, and the resolution of the DNS seeds, through the given proxy,
+    /// and advertises the given onion address of the node. This must be called before the routing is initialized.
+    ///
+    /// In proxy-only mode, the node only listens for the connections of its onion service, in which case
+    /// the listener must be bound to a loopback address, so that it is only reachable through Tor.
+    pub fn set_proxy(&self, proxy: ProxyConfig, onion_service: Option<OnionAddr>) -> Result<()> {
+        if proxy.proxy_only && onion_service.is_some() {
+            let listener_ip = self.tcp.config().listener_ip;
+            if !listener_ip.map_or(false, |ip| ip.is_loopback()) {
+                bail!("In proxy-only mode, the onion service must be forwarded to a loopback node IP")
+            }
+        }
+        self.dns_seeds.set_resolver(Arc::new(ProxyResolver(proxy.clone())));
+        self.tcp.set_proxy(Some(proxy));
+        *self.onion_service.write() = onion_service;
+        Ok(())
+    }
+
+    /// Returns the onion address of the node, if it is reachable as an onion service.
+    pub fn onion_service(&self) -> Option<OnionAddr> {
+        self.onion_service.read().clone()
+    }
+
+    /// Returns the onion address behind the given virtual address, if it is a known onion peer.
+    pub fn onion_peer(&self, peer_ip: &SocketAddr) -> Option<OnionAddr> {
+        self.onion_peers.read().get(peer_ip).cloned()
+    }
+
+    /// Returns the name of the given peer, which is its onion address if it is an onion peer.
+    fn peer_name(&self, peer_ip: SocketAddr) -> String {
+        match self.onion_peer(&peer_ip) {
+            Some(onion) => onion.to_string(),
+            None => peer_ip.to_string(),
+        }
+    }
+
+    /// Adds the given onion addresses to the candidate peers, under their virtual addresses.
+    /// Note that the onion addresses are ignored without a proxy, as they are unreachable.
+    pub fn insert_onion_peers(&self, onion_peers: &[OnionAddr]) {
+        if self.tcp.proxy().is_none() {
+            return;
+        }
+        let onion_service = self.onion_service();
+        let mut virtual_addrs = Vec::with_capacity(onion_peers.len());
+        for onion in onion_peers.iter().filter(|onion| Some(*onion) != onion_service.as_ref()) {
+            let virtual_addr = onion.virtual_addr();
+            self.tcp.register_domain(virtual_addr, onion.host(), onion.port());
+            self.onion_peers.write().insert(virtual_addr, onion.clone());
+            virtual_addrs.push(virtual_addr);
+        }
+        self.insert_candidate_peers(&virtual_addrs);
+    }
+
     /// Returns the account private key of the node.
     pub fn private_key(&self) -> &PrivateKey<N> {
         self.account.private_key()
@@ -225,6 +563,16 @@ impl<N: Network> Router<N> {
         self.account.address()
     }
 
+    /// Returns the event bus, on which the connected, disconnected, and banned peers are published.
+    pub fn event_bus(&self) -> &EventBus<N> {
+        &self.events
+    }
+
+    /// Returns the guard of the storage operations.
+    pub fn storage_guard(&self) -> &StorageGuard {
+        &self.storage
+    }
+
     /// Returns the sync pool.
     pub fn sync(&self) -> &Sync<N> {
         &self.sync
@@ -235,6 +583,193 @@ impl<N: Network> Router<N> {
         self.is_dev
     }
 
+    /// Returns the static public key of the node, used to encrypt its connections.
+    pub fn noise_public_key(&self) -> &[u8] {
+        self.noise.public_key()
+    }
+
+    /// Returns the node ID of the node, which is derived from its static public key.
+    pub fn node_id(&self) -> NodeId {
+        NodeId::from_public_key(self.noise.public_key())
+    }
+
+    /// Returns the peer book.
+    pub fn peer_book(&self) -> &PeerBook<N> {
+        &self.peer_book
+    }
+
+    /// Returns the diffusion of the local transactions.
+    pub fn diffusion(&self) -> &Diffusion<N> {
+        &self.diffusion
+    }
+
+    /// Returns the transactions relayed before their parents.
+    pub fn orphans(&self) -> &OrphanTransactions<N> {
+        &self.orphans
+    }
+
+    /// Returns the relay queues of the peers.
+    pub fn relay_queues(&self) -> &RelayQueues<N> {
+        &self.relay_queues
+    }
+
+    /// Returns the snapshot streams served by this node, and its snapshot download.
+    pub fn snapshots(&self) -> &Snapshots<N> {
+        &self.snapshots
+    }
+
+    /// Returns the DNS seeds.
+    pub fn dns_seeds(&self) -> &DnsSeeds {
+        &self.dns_seeds
+    }
+
+    /// Returns the state of the connection with the given peer, if it is connected.
+    pub fn peer_state(&self, peer_ip: &SocketAddr) -> Option<Arc<PeerState<N>>> {
+        self.peer_states.read().get(peer_ip).cloned()
+    }
+
+    /// Records a message with the given name that was received from the given connected peer.
+    pub fn record_inbound(&self, peer_ip: &SocketAddr, message_name: &str) {
+        if let Some(state) = self.peer_state(peer_ip) {
+            state.record_inbound(message_name);
+        }
+    }
+
+    /// Records a message with the given name that was sent to the given connected peer.
+    pub fn record_outbound(&self, peer_ip: &SocketAddr, message_name: &str) {
+        if let Some(state) = self.peer_state(peer_ip) {
+            state.record_outbound(message_name);
+        }
+    }
+
+    /// Records a protocol violation by the given connected peer.
+    pub fn record_misbehavior(&self, peer_ip: &SocketAddr) {
+        if let Some(state) = self.peer_state(peer_ip) {
+            state.record_misbehavior();
+        }
+    }
+
+    /// Records the given blocks delivered by the given peer, crediting the peer with the blocks it delivered first.
+    pub fn record_blocks(&self, peer_ip: &SocketAddr, block_hashes: impl IntoIterator<Item = N::BlockHash>) {
+        let num_first_delivered = self.peer_book.insert_delivered_blocks(block_hashes);
+        if let Some(state) = self.peer_state(peer_ip) {
+            state.record_blocks_first_delivered(num_first_delivered);
+        }
+    }
+
+    /// Records the given transaction delivered by the given peer, crediting the peer if it delivered it first.
+    pub fn record_transaction(&self, peer_ip: &SocketAddr, transaction_id: N::TransactionID) {
+        if self.peer_book.insert_delivered_transaction(transaction_id) {
+            if let Some(state) = self.peer_state(peer_ip) {
+                state.record_transaction_first_delivered();
+            }
+        }
+    }
+
+    /// Records that the given connected peer sent a block that is already known to be invalid,
+    /// and returns the penalty added to its misbehavior score, or `0` if the peer is not connected.
+    pub fn record_known_invalid_block(&self, peer_ip: &SocketAddr) -> u64 {
+        self.peer_state(peer_ip).map_or(0, |state| state.record_known_invalid_block())
+    }
+
+    /// Returns the statistics of the connected peers.
+    ///
+    /// Note that the statistics are aggregated from the connections every `PEER_BOOK_AGGREGATION_IN_SECS` seconds,
+    /// so the messages of the last interval may not be counted yet.
+    pub fn peer_info(&self) -> Vec<PeerStatistics> {
+        self.record_peer_traffic();
+        self.connected_peers.read().keys().filter_map(|peer_ip| self.peer_book.get(peer_ip)).collect()
+    }
+
+    /// Returns the statistics of the known peers that are not connected.
+    pub fn peer_history(&self) -> Vec<PeerStatistics> {
+        let connected_peers = self.connected_peers.read();
+        self.peer_book.statistics().into_iter().filter(|peer| !connected_peers.contains_key(&peer.ip)).collect()
+    }
+
+    /// Records the traffic of the connected peers in the peer book.
+    fn record_peer_traffic(&self) {
+        for peer_ip in self.connected_peers() {
+            self.record_traffic(&peer_ip);
+        }
+    }
+
+    /// Records the traffic of the given connected peer in the peer book.
+    fn record_traffic(&self, peer_ip: &SocketAddr) {
+        if let Some(stats) = self.resolve_to_ambiguous(peer_ip).and_then(|addr| self.tcp.known_peers().get(addr)) {
+            let ((_, bytes_sent), (_, bytes_received)) = (stats.sent(), stats.received());
+            self.peer_book.record_traffic(peer_ip, bytes_sent, bytes_received);
+        }
+    }
+
+    /// Sends the pending deltas of the connected peers to the peer book, and aggregates them.
+    pub fn aggregate_peer_book(&self) {
+        // Collect the states first, so that the map is not locked while the deltas are sent.
+        let peer_states = self.peer_states.read().values().cloned().collect::<Vec<_>>();
+        peer_states.iter().for_each(|state| state.flush());
+        self.peer_book.aggregate();
+    }
+
+    /// Writes the peer book to its file, including the latest messages and traffic of the connected peers.
+    pub fn flush_peer_book(&self) {
+        self.aggregate_peer_book();
+        self.record_peer_traffic();
+        if let Err(error) = self.peer_book.flush() {
+            warn!("Failed to write the peer book - {error}");
+        }
+    }
+
+    /// Returns the minimum message version accepted from peers.
+    pub fn minimum_version(&self) -> u32 {
+        match self.noise.allows_plaintext() {
+            true => Message::<N>::PLAINTEXT_VERSION,
+            false => Message::<N>::VERSION,
+        }
+    }
+
+    /// Returns the codec for the connection with the given (ambiguous) peer address,
+    /// which is encrypted if the peer completed the Noise handshake, and compresses the large messages
+    /// if both this node and the peer advertised the compression in the handshake.
+    pub fn codec(&self, peer_addr: SocketAddr) -> PeerCodec<N> {
+        let peer_ip = self.resolve_to_listener(&peer_addr);
+        let noise_state = peer_ip.and_then(|ip| self.noise_states.read().get(&ip).cloned());
+        let mut codec = match noise_state {
+            Some(noise_state) => PeerCodec::Encrypted(NoiseCodec::new(NoiseState::PostHandshake(noise_state))),
+            None => PeerCodec::default(),
+        };
+        codec.update_max_message_len();
+
+        if let Some(stats) = peer_ip.and_then(|peer_ip| self.compression_stats(&peer_ip)) {
+            codec.enable_compression(self.compression(), stats);
+        }
+        codec
+    }
+
+    /// Returns the number of bytes of the compressed messages exchanged with the given connected peer,
+    /// before and after their compression, if both this node and the peer advertised the compression.
+    pub fn compression_stats(&self, peer_ip: &SocketAddr) -> Option<Arc<CompressionStats>> {
+        match self.connected_peers.read().get(peer_ip)?.capabilities().contains(Capabilities::COMPRESSION) {
+            true if self.compression().is_enabled() => Some(self.peer_state(peer_ip)?.compression_stats().clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the link of the in-memory transport to the given peer IP, if the peer is connected over one.
+    pub fn memory_link(&self, peer_ip: &SocketAddr) -> Option<MemoryLink<N>> {
+        self.memory_links.read().get(peer_ip).cloned()
+    }
+
+    /// Routes the messages to the given connected peer IP through the given link of an in-memory transport,
+    /// instead of the TCP stack. The link is closed when the peer is disconnected.
+    pub fn insert_memory_link(&self, peer_ip: SocketAddr, link: MemoryLink<N>) {
+        self.memory_links.write().insert(peer_ip, link);
+    }
+
+    /// Returns `true` if the connection with the given peer IP is encrypted.
+    pub fn is_encrypted(&self, peer_ip: &SocketAddr) -> bool {
+        self.noise_states.read().contains_key(peer_ip)
+    }
+
     /// Returns the listener IP address from the (ambiguous) peer address.
     pub fn resolve_to_listener(&self, peer_addr: &SocketAddr) -> Option<SocketAddr> {
         self.resolver.get_listener(peer_addr)
@@ -284,9 +819,117 @@ impl<N: Network> Router<N> {
             .unwrap_or(false)
     }
 
+    /// Returns `true` if the given IP is banned by the peer policy.
+    pub fn is_banned(&self, ip: &SocketAddr) -> bool {
+        self.peer_book.is_banned(ip)
+    }
+
+    /// Returns `true` if the given IP is exempt from rate limiting by the peer policy.
+    pub fn is_whitelisted(&self, ip: &SocketAddr) -> bool {
+        self.peer_book.is_whitelisted(ip)
+    }
+
+    /// Returns `true` if the given peer IP is a trusted peer, or is connected with a trusted node ID.
+    pub fn is_trusted(&self, peer_ip: &SocketAddr) -> bool {
+        self.trusted_peers.contains(peer_ip) || self.is_trusted_node(peer_ip)
+    }
+
+    /// Returns `true` if the given peer IP is connected with a node ID trusted by the peer policy.
+    ///
+    /// The node ID of a peer is only known once it is authenticated by an encrypted connection,
+    /// so the trust follows the static public key of the peer, regardless of its IP.
+    pub fn is_trusted_node(&self, peer_ip: &SocketAddr) -> bool {
+        match self.connected_peers.read().get(peer_ip).and_then(|peer| peer.node_id()) {
+            Some(node_id) => self.peer_book.is_trusted(&node_id),
+            None => false,
+        }
+    }
+
+    /// Returns the static public key pinned for the given peer IP, by the peer policy or on initialization, if any.
+    pub fn pinned_key(&self, peer_ip: &SocketAddr) -> Option<Vec<u8>> {
+        self.peer_book.pinned_key(peer_ip).or_else(|| self.noise.pinned_key(peer_ip).map(|key| key.to_vec()))
+    }
+
+    /// Returns the peer policy document of the node, including the keys pinned on initialization.
+    pub fn export_peer_policy(&self) -> PeerPolicy {
+        let mut policy = self.peer_book.export_policy();
+        // Add the keys pinned on initialization, unless the peer policy pins another key for the peer.
+        for (peer_ip, key) in self.noise.pinned_keys() {
+            if self.peer_book.pinned_key(peer_ip).is_none() {
+                policy.pinned.push(PinnedPeer { ip: peer_ip.to_string(), public_key: hex::encode(key) });
+            }
+        }
+        policy
+    }
+
+    /// Imports the given peer policy document, and returns the outcome of each entry.
+    ///
+    /// The entries are merged into the current policy, unless `replace` is set, in which case the valid
+    /// entries replace the current policy. The policy takes effect immediately, and is written to the peer book.
+    pub fn import_peer_policy(&self, policy: PeerPolicy, replace: bool) -> Vec<PolicyEntryResult> {
+        let results = self.peer_book.import_policy(policy, replace);
+        // Record the applied bans as critical events, and publish them on the event bus.
+        for result in &results {
+            if result.section == "banned" && result.status == PolicyEntryStatus::Applied {
+                warn!(target: "critical", kind = "ban", "Banned '{}' by a peer policy import", result.ip);
+                self.events.publish(Event::PeerBanned { ip: result.ip.clone() });
+            }
+        }
+        // Disconnect from the connected peers that are now banned.
+        for peer_ip in self.connected_peers() {
+            if self.is_banned(&peer_ip) {
+                info!("Disconnecting from '{peer_ip}' (banned)");
+                self.disconnect(peer_ip);
+            }
+        }
+        // Remove the banned peers from the candidate peers.
+        self.candidate_peers.write().retain(|peer_ip| !self.peer_book.is_banned(peer_ip));
+        // Write the peer policy to the peer book file.
+        self.flush_peer_book();
+        results
+    }
+
+    /// Returns `true` if the given IP was found to be on a different network or chain.
+    pub fn is_mismatched(&self, ip: &SocketAddr) -> bool {
+        self.mismatched_peers
+            .read()
+            .get(ip)
+            .map(|time| time.elapsed().as_secs() < Self::MISMATCH_EXPIRY_IN_SECS)
+            .unwrap_or(false)
+    }
+
     /// Returns the maximum number of connected peers.
     pub fn max_connected_peers(&self) -> usize {
-        self.tcp.config().max_connections as usize
+        self.max_peers.load(Ordering::Relaxed)
+    }
+
+    /// Sets the maximum number of connected peers, or restores the initial maximum if `None`.
+    ///
+    /// The maximum cannot exceed the limit of the TCP stack, which is fixed when the router is initialized.
+    /// If it is lowered, the surplus peers are disconnected on the next heartbeat.
+    pub fn set_max_connected_peers(&self, max_peers: Option<usize>) {
+        let limit = self.tcp.config().max_connections as usize;
+        self.max_peers.store(max_peers.map_or(limit, |max_peers| max_peers.min(limit)), Ordering::Relaxed);
+    }
+
+    /// Returns the height below which this node pruned the bodies of its blocks, or `0` if it serves every block.
+    pub fn pruned_height(&self) -> u32 {
+        self.pruned_height.load(Ordering::Relaxed)
+    }
+
+    /// Sets the height below which this node pruned the bodies of its blocks, which is advertised to the new peers.
+    pub fn set_pruned_height(&self, height: u32) {
+        self.pruned_height.store(height, Ordering::Relaxed);
+    }
+
+    /// Returns the network-adjusted time, which is sampled from the timestamps in the handshakes of the peers.
+    pub fn network_time(&self) -> NetworkTime {
+        self.network_time.read().clone()
+    }
+
+    /// Sets the network-adjusted time, which is shared with the ledger, so that it checks the next blocks against it.
+    pub fn set_network_time(&self, network_time: NetworkTime) {
+        *self.network_time.write() = network_time;
     }
 
     /// Returns the number of connected peers.
@@ -339,6 +982,19 @@ impl<N: Network> Router<N> {
         self.connected_peers.read().keys().copied().collect()
     }
 
+    /// Returns the connected peers to which a local transaction may first be sent, which are the outbound peers
+    /// that relay transactions. If there are none, this falls back to any relaying peer, then to any peer.
+    pub fn diffusion_candidates(&self) -> Vec<SocketAddr> {
+        let connected_peers = self.connected_peers.read();
+        let relaying_peers = connected_peers.iter().filter(|(_, peer)| peer.is_relaying()).collect::<Vec<_>>();
+        let outbound_peers = relaying_peers.iter().filter(|(_, peer)| peer.is_outbound()).collect::<Vec<_>>();
+        match (outbound_peers.is_empty(), relaying_peers.is_empty()) {
+            (false, _) => outbound_peers.into_iter().map(|(ip, _)| **ip).collect(),
+            (true, false) => relaying_peers.into_iter().map(|(ip, _)| *ip).collect(),
+            (true, true) => connected_peers.keys().copied().collect(),
+        }
+    }
+
     /// Returns the list of connected beacons.
     pub fn connected_beacons(&self) -> Vec<SocketAddr> {
         self.connected_peers.read().iter().filter(|(_, peer)| peer.is_beacon()).map(|(ip, _)| *ip).collect()
@@ -369,6 +1025,11 @@ impl<N: Network> Router<N> {
         self.restricted_peers.read().keys().copied().collect()
     }
 
+    /// Returns the list of peers found to be on a different network or chain.
+    pub fn mismatched_peers(&self) -> Vec<SocketAddr> {
+        self.mismatched_peers.read().keys().filter(|peer_ip| self.is_mismatched(peer_ip)).copied().collect()
+    }
+
     /// Returns the list of trusted peers.
     pub fn trusted_peers(&self) -> &IndexSet<SocketAddr> {
         &self.trusted_peers
@@ -407,14 +1068,28 @@ impl<N: Network> Router<N> {
     /// Inserts the given peer into the connected peers.
     pub fn insert_connected_peer(&self, peer: Peer<N>, peer_addr: SocketAddr) {
         let peer_ip = peer.ip();
+        // Record the connection in the peer book.
+        self.peer_book.record_connection(peer_ip, peer.node_type(), peer.version(), self.is_encrypted(&peer_ip));
+        // Initialize the state of the connection, which resumes the penalty for the known invalid blocks of the peer.
+        let known_invalid_blocks = self.peer_book.get(&peer_ip).map_or(0, |peer| peer.known_invalid_blocks);
+        let state = PeerState::new(peer_ip, known_invalid_blocks, self.peer_book.delta_sender());
+        self.peer_states.write().insert(peer_ip, Arc::new(state));
         // Adds a bidirectional map between the listener address and (ambiguous) peer address.
         self.resolver.insert_peer(peer_ip, peer_addr);
+        // Record the blocks that the peer pruned, so that they are not requested from it.
+        self.sync.set_peer_pruned_height(peer_ip, peer.pruned_height());
+        // Record whether the peer serves blocks, so that blocks are only requested from the peers that serve them.
+        self.sync.set_peer_serves_blocks(peer_ip, peer.is_serving_blocks());
+        // Record whether the peer is archival, so that the blocks other peers pruned are only requested from it.
+        self.sync.set_peer_archival(peer_ip, peer.is_archival());
         // Add an entry for this `Peer` in the connected peers.
         self.connected_peers.write().insert(peer_ip, peer);
         // Remove this peer from the candidate peers, if it exists.
         self.candidate_peers.write().remove(&peer_ip);
         // Remove this peer from the restricted peers, if it exists.
         self.restricted_peers.write().remove(&peer_ip);
+        // Publish the connected peer on the event bus.
+        self.events.publish(Event::PeerConnected { peer_ip });
     }
 
     /// Inserts the given peer IPs to the set of candidate peers.
@@ -425,18 +1100,82 @@ impl<N: Network> Router<N> {
         // Compute the maximum number of candidate peers.
         let max_candidate_peers = Self::MAXIMUM_CANDIDATE_PEERS.saturating_sub(self.number_of_candidate_peers());
         // Ensure the combined number of peers does not surpass the threshold.
-        let eligible_peers = peers
-            .iter()
-            .filter(|peer_ip| {
-                // Ensure the peer is not itself, is not already connected, and is not restricted.
-                !self.is_local_ip(peer_ip) && !self.is_connected(peer_ip) && !self.is_restricted(peer_ip)
-            })
-            .take(max_candidate_peers);
+        let eligible_peers =
+            peers.iter().filter(|peer_ip| self.is_eligible_candidate(peer_ip)).take(max_candidate_peers);
 
         // Proceed to insert the eligible candidate peer IPs.
         self.candidate_peers.write().extend(eligible_peers);
     }
 
+    /// Returns `true` if the given peer IP may become a candidate peer, which requires that the peer is not itself,
+    /// is not already connected, is not restricted or banned, is not mismatched, and is not an unknown virtual address.
+    fn is_eligible_candidate(&self, peer_ip: &SocketAddr) -> bool {
+        !self.is_local_ip(peer_ip)
+            && !self.is_connected(peer_ip)
+            && !self.is_restricted(peer_ip)
+            && !self.is_banned(peer_ip)
+            && !self.is_mismatched(peer_ip)
+            && (!OnionAddr::is_virtual_addr(peer_ip) || self.onion_peers.read().contains_key(peer_ip))
+    }
+
+    /// Spawns a resolution of the DNS seeds, unless no seed is set, or the seeds were resolved recently.
+    /// The resolution runs in the background, so that an unavailable DNS does not hold up the node.
+    pub fn spawn_dns_seed_resolution(&self) {
+        if !self.dns_seeds.hosts().is_empty() && self.dns_seeds.begin_resolution() {
+            let router = self.clone();
+            self.spawn(async move { router.resolve_dns_seeds().await });
+        }
+    }
+
+    /// Resolves the DNS seeds, and inserts the accepted addresses into the candidate peers, tagged as seed-sourced.
+    /// If the seeds yield no address, the addresses stored in the peer book are inserted instead.
+    pub async fn resolve_dns_seeds(&self) {
+        let seed_peers = self.dns_seeds.resolve().await;
+        match seed_peers.is_empty() {
+            false => {
+                // Tag the eligible addresses as seed-sourced, before they become candidate peers.
+                let eligible_peers =
+                    seed_peers.into_iter().filter(|peer_ip| self.is_eligible_candidate(peer_ip)).collect::<Vec<_>>();
+                self.dns_seeds.insert_seed_peers(&eligible_peers);
+                self.insert_candidate_peers(&eligible_peers);
+            }
+            true => {
+                warn!("The DNS seeds yielded no address, falling back to the peers in the peer book");
+                let stored_peers = self.peer_book.statistics().into_iter().map(|peer| peer.ip).collect::<Vec<_>>();
+                self.insert_candidate_peers(&stored_peers);
+            }
+        }
+    }
+
+    /// Returns up to `num_peers` random candidate peers to connect to. The candidate peers sourced from the DNS seeds
+    /// are only chosen to make up for the other candidate peers, and never bring the outbound connections to
+    /// seed-sourced peers above half of the given maximum number of peers.
+    pub fn select_candidate_peers<R: Rng>(&self, num_peers: usize, max_peers: usize, rng: &mut R) -> Vec<SocketAddr> {
+        // Split the candidate peers by their source.
+        let (seed_peers, other_peers): (Vec<_>, Vec<_>) =
+            self.candidate_peers().into_iter().partition(|peer_ip| self.dns_seeds.is_seed_peer(peer_ip));
+        // Compute the number of seed-sourced peers that may still be connected to.
+        let max_seed_peers = (max_peers / 2).saturating_sub(self.number_of_outbound_seed_peers());
+
+        let mut selected = other_peers.into_iter().choose_multiple(rng, num_peers);
+        let num_seed_peers = num_peers.saturating_sub(selected.len()).min(max_seed_peers);
+        selected.extend(seed_peers.into_iter().choose_multiple(rng, num_seed_peers));
+        selected
+    }
+
+    /// Returns the number of outbound peers sourced from the DNS seeds, that are connected or connecting.
+    pub fn number_of_outbound_seed_peers(&self) -> usize {
+        let num_connected = self
+            .connected_peers
+            .read()
+            .values()
+            .filter(|peer| peer.is_outbound() && self.dns_seeds.is_seed_peer(&peer.ip()))
+            .count();
+        let num_connecting =
+            self.connecting_peers.lock().iter().filter(|peer_ip| self.dns_seeds.is_seed_peer(peer_ip)).count();
+        num_connected + num_connecting
+    }
+
     /// Inserts the given peer into the restricted peers.
     pub fn insert_restricted_peer(&self, peer_ip: SocketAddr) {
         // Remove this peer from the candidate peers, if it exists.
@@ -445,6 +1184,25 @@ impl<N: Network> Router<N> {
         self.restricted_peers.write().insert(peer_ip, Instant::now());
     }
 
+    /// Inserts the given peer into the restricted peers, and tags it as being on a different network or chain,
+    /// so that it is not dialed again until the tag expires.
+    ///
+    /// This must only be called for a mismatch that this node detected itself, as a peer could otherwise
+    /// claim a mismatch to have any address tagged.
+    pub fn insert_mismatched_peer(&self, peer_ip: SocketAddr) {
+        #[cfg(feature = "metrics")]
+        metrics::increment_counter!(metrics::peers::MISMATCHED);
+        // Restrict the peer, which also removes it from the candidate peers.
+        self.insert_restricted_peer(peer_ip);
+        // Tag the peer as mismatched, and forget the expired tags, and the earliest tags beyond the maximum.
+        let mut mismatched_peers = self.mismatched_peers.write();
+        mismatched_peers.shift_remove(&peer_ip);
+        mismatched_peers.insert(peer_ip, Instant::now());
+        mismatched_peers.retain(|_, time| time.elapsed().as_secs() < Self::MISMATCH_EXPIRY_IN_SECS);
+        let num_evicted = mismatched_peers.len().saturating_sub(Self::MAXIMUM_MISMATCHED_PEERS);
+        mismatched_peers.drain(..num_evicted);
+    }
+
     /// Updates the connected peer with the given function.
     pub fn update_connected_peer<Fn: FnMut(&mut Peer<N>)>(
         &self,
@@ -467,14 +1225,36 @@ impl<N: Network> Router<N> {
 
     /// Removes the connected peer and adds them to the candidate peers.
     pub fn remove_connected_peer(&self, peer_ip: SocketAddr) {
+        // Record the final traffic of the connection in the peer book.
+        self.record_traffic(&peer_ip);
+        self.peer_book.record_disconnection(&peer_ip);
+        // Aggregate the final delta of the connection into the peer book.
+        if let Some(state) = self.peer_states.write().remove(&peer_ip) {
+            state.flush();
+            self.peer_book.aggregate();
+        }
         // Removes the bidirectional map between the listener address and (ambiguous) peer address.
         self.resolver.remove_peer(&peer_ip);
         // Removes the peer from the sync pool.
         self.sync.remove_peer(&peer_ip);
+        // Removes the orphan transactions relayed by the peer, and the parent requests sent to it.
+        self.orphans.remove_peer(&peer_ip);
+        // Removes the relay queue of the peer.
+        self.relay_queues.remove_peer(&peer_ip);
+        // Closes the snapshot stream served to the peer, if it exists.
+        self.snapshots.remove_peer(&peer_ip);
+        // Removes the transport state of the peer, if it exists.
+        self.noise_states.write().remove(&peer_ip);
+        // Removes the in-memory transport of the peer, if it exists.
+        self.memory_links.write().remove(&peer_ip);
         // Remove this peer from the connected peers, if it exists.
-        self.connected_peers.write().remove(&peer_ip);
+        let is_connected = self.connected_peers.write().remove(&peer_ip).is_some();
         // Add the peer to the candidate peers.
         self.candidate_peers.write().insert(peer_ip);
+        // Publish the disconnected peer on the event bus, if it was connected.
+        if is_connected {
+            self.events.publish(Event::PeerDisconnected { peer_ip });
+        }
     }
 
     #[cfg(feature = "test")]
@@ -497,6 +1277,8 @@ impl<N: Network> Router<N> {
         trace!("Shutting down the router...");
         // Abort the tasks.
         self.handles.lock().iter().for_each(|handle| handle.abort());
+        // Write the peer book.
+        self.flush_peer_book();
         // Close the listener.
         self.tcp.shut_down().await;
     }
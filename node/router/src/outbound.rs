@@ -15,13 +15,13 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::Router;
-use snarkos_node_messages::{BlockLocators, Message, Ping};
+use snarkos_node_messages::{BlockLocators, Capabilities, Message, Ping, UnconfirmedTransaction};
 use snarkos_node_tcp::protocols::Writing;
 use snarkvm::prelude::Network;
 use std::io;
 
 use std::net::SocketAddr;
-use tokio::sync::oneshot;
+use tokio::{sync::oneshot, time::Instant};
 
 pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
     /// Returns a reference to the router.
@@ -37,7 +37,7 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
     /// This function returns as soon as the message is queued to be sent,
     /// without waiting for the actual delivery; instead, the caller is provided with a [`oneshot::Receiver`]
     /// which can be used to determine when and whether the message has been delivered.
-    fn send(&self, peer_ip: SocketAddr, message: Message<N>) -> Option<oneshot::Receiver<io::Result<()>>> {
+    fn send(&self, peer_ip: SocketAddr, mut message: Message<N>) -> Option<oneshot::Receiver<io::Result<()>>> {
         // Determine whether to send the message.
         if !self.can_send(peer_ip, &message) {
             return None;
@@ -58,22 +58,58 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
         if matches!(message, Message::PuzzleRequest(_)) {
             self.router().cache.increment_outbound_puzzle_requests(peer_ip);
         }
+        // If the message is an extended announcement, send the parents only to the peers able to fetch them.
+        if let Message::UnconfirmedTransaction(ref mut message) = message {
+            if !message.parent_ids.is_empty()
+                && !self
+                    .router()
+                    .get_connected_peer(&peer_ip)
+                    .map_or(false, |peer| peer.capabilities().contains(Capabilities::PARENT_TXS))
+            {
+                message.parent_ids.clear();
+            }
+        }
         // Retrieve the message name.
         let name = message.name();
         // Send the message to the peer.
         trace!("Sending '{name}' to '{peer_ip}'");
-        let result = self.unicast(peer_addr, message);
-        // If the message was unable to be sent, disconnect.
-        if let Err(e) = &result {
-            warn!("Failed to send '{name}' to '{peer_ip}': {e}");
-            debug!("Disconnecting from '{peer_ip}' (unable to send)");
-            self.router().disconnect(peer_ip);
+        let result = match self.router().memory_link(&peer_ip) {
+            // If the peer is connected over an in-memory transport, send the message over its link.
+            Some(link) => link.send(message).map(|()| {
+                let (sender, receiver) = oneshot::channel();
+                let _ = sender.send(Ok(()));
+                receiver
+            }),
+            None => self.unicast(peer_addr, message),
+        };
+        match &result {
+            // Record the message in the peer book.
+            Ok(_) => self.router().record_outbound(&peer_ip, &name),
+            // If the message was unable to be sent, disconnect.
+            Err(e) => {
+                warn!("Failed to send '{name}' to '{peer_ip}': {e}");
+                debug!("Disconnecting from '{peer_ip}' (unable to send)");
+                self.router().disconnect(peer_ip);
+            }
         }
         result.ok()
     }
 
+    /// Returns `true` if the given message is an unconfirmed message received from a peer,
+    /// and this node does not advertise that it relays such messages.
+    fn skips_relay(&self, message: &Message<N>, excluded_peers: &[SocketAddr]) -> bool {
+        matches!(message, Message::UnconfirmedSolution(_) | Message::UnconfirmedTransaction(_))
+            && !excluded_peers.is_empty()
+            && !self.router().capabilities().contains(Capabilities::RELAYS)
+    }
+
     /// Sends the given message to every connected peer, excluding the sender and any specified peer IPs.
     fn propagate(&self, message: Message<N>, excluded_peers: &[SocketAddr]) {
+        // If this node does not relay, skip relaying the unconfirmed messages received from its peers.
+        if self.skips_relay(&message, excluded_peers) {
+            return;
+        }
+
         // TODO (howardwu): Serialize large messages once only.
         // // Perform ahead-of-time, non-blocking serialization just once for applicable objects.
         // if let Message::BeaconPropose(ref mut message) = message {
@@ -106,8 +142,84 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
         }
     }
 
+    /// Sends the given transaction, which was submitted to this node, to the network. If privacy is on,
+    /// the transaction is first sent to a few random outbound peers, and broadcast to every peer once its
+    /// embargo ends, or once it is received from the network.
+    fn diffuse(&self, message: UnconfirmedTransaction<N>) {
+        // Place the transaction under embargo, or broadcast it immediately if the diffusion is not possible.
+        let candidates = self.router().diffusion_candidates();
+        match self.router().diffusion().embargo(message.clone(), &candidates, &mut rand::thread_rng()) {
+            Some(first_hops) => debug!("Diffusing transaction '{}' through {first_hops:?}", message.transaction_id),
+            None => self.propagate(Message::UnconfirmedTransaction(message), &[]),
+        }
+    }
+
+    /// Sends the local transactions that are due to their first hops, and broadcasts the local transactions
+    /// whose embargo ended.
+    fn diffuse_due(&self) {
+        let (sends, broadcasts) = self.router().diffusion().due(Instant::now());
+        for (peer_ip, message) in sends {
+            // If the first hop disconnected in the meantime, the transaction reaches it in the broadcast.
+            if self.router().is_connected(&peer_ip) {
+                self.send(peer_ip, Message::UnconfirmedTransaction(message));
+            }
+        }
+        for message in broadcasts {
+            debug!("Broadcasting transaction '{}', as its embargo ended", message.transaction_id);
+            self.broadcast_local_transaction(message, &[]);
+        }
+    }
+
+    /// Sends the given local transaction to every connected peer, excluding any specified peer IPs.
+    /// Unlike `propagate`, this applies to the nodes that do not relay, as the transaction is their own.
+    fn broadcast_local_transaction(&self, message: UnconfirmedTransaction<N>, excluded_peers: &[SocketAddr]) {
+        for peer_ip in self.router().connected_peers() {
+            if !excluded_peers.contains(&peer_ip) {
+                self.send(peer_ip, Message::UnconfirmedTransaction(message.clone()));
+            }
+        }
+    }
+
+    /// Queues the given transaction, with the given fee rate (in microcredits per byte), for the relay to the given
+    /// peers, excluding any specified peer IPs, of which the first is the peer that sent the transaction.
+    /// The queued transactions are relayed in order of their fee rate, within the budget of each flush.
+    fn queue_relay(
+        &self,
+        message: UnconfirmedTransaction<N>,
+        fee_rate: f64,
+        peer_ips: &[SocketAddr],
+        excluded_peers: &[SocketAddr],
+    ) {
+        // If this node does not relay, skip relaying the transactions received from its peers.
+        if !excluded_peers.is_empty() && !self.router().capabilities().contains(Capabilities::RELAYS) {
+            return;
+        }
+        let source = excluded_peers.first().copied();
+        for peer_ip in peer_ips.iter().filter(|peer_ip| !excluded_peers.contains(peer_ip)) {
+            self.router().relay_queues().push(*peer_ip, message.clone(), fee_rate, source);
+        }
+    }
+
+    /// Relays the queued transactions that are due in this flush, to each peer.
+    fn relay_due(&self) {
+        for (peer_ip, message) in self.router().relay_queues().flush(Instant::now()) {
+            match self.router().is_connected(&peer_ip) {
+                true => {
+                    self.send(peer_ip, Message::UnconfirmedTransaction(message));
+                }
+                // If the peer disconnected before its queue was flushed, drop its queue.
+                false => self.router().relay_queues().remove_peer(&peer_ip),
+            }
+        }
+    }
+
     /// Sends the given message to every connected beacon, excluding the sender and any specified IPs.
     fn propagate_to_beacons(&self, message: Message<N>, excluded_peers: &[SocketAddr]) {
+        // If this node does not relay, skip relaying the unconfirmed messages received from its peers.
+        if self.skips_relay(&message, excluded_peers) {
+            return;
+        }
+
         // TODO (howardwu): Serialize large messages once only.
         // // Perform ahead-of-time, non-blocking serialization just once for applicable objects.
         // if let Message::BeaconPropose(ref mut message) = message {
@@ -142,6 +254,11 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
 
     /// Sends the given message to every connected validator, excluding the sender and any specified IPs.
     fn propagate_to_validators(&self, message: Message<N>, excluded_peers: &[SocketAddr]) {
+        // If this node does not relay, skip relaying the unconfirmed messages received from its peers.
+        if self.skips_relay(&message, excluded_peers) {
+            return;
+        }
+
         // TODO (howardwu): Serialize large messages once only.
         // // Perform ahead-of-time, non-blocking serialization just once for applicable objects.
         // if let Message::BeaconPropose(ref mut message) = message {
@@ -184,16 +301,20 @@ pub trait Outbound<N: Network>: Writing<Message = Message<N>> {
         // Determine whether to send the message.
         match message {
             Message::UnconfirmedSolution(message) => {
-                // Update the timestamp for the unconfirmed solution.
-                let seen_before =
-                    self.router().cache.insert_outbound_solution(peer_ip, message.puzzle_commitment).is_some();
+                // Update the timestamp for the unconfirmed solution, in the inventory known to the peer.
+                let seen_before = self
+                    .router()
+                    .peer_state(&peer_ip)
+                    .map_or(true, |state| state.insert_outbound_solution(message.puzzle_commitment).is_some());
                 // Determine whether to send the solution.
                 !seen_before
             }
             Message::UnconfirmedTransaction(message) => {
-                // Update the timestamp for the unconfirmed transaction.
-                let seen_before =
-                    self.router().cache.insert_outbound_transaction(peer_ip, message.transaction_id).is_some();
+                // Update the timestamp for the unconfirmed transaction, in the inventory known to the peer.
+                let seen_before = self
+                    .router()
+                    .peer_state(&peer_ip)
+                    .map_or(true, |state| state.insert_outbound_transaction(message.transaction_id).is_some());
                 // Determine whether to send the transaction.
                 !seen_before
             }
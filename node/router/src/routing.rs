@@ -14,15 +14,23 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Heartbeat, Inbound, Outbound};
-use snarkos_node_messages::Message;
+use crate::{Heartbeat, Inbound, Outbound, SnapshotReceiver, SnapshotResponse, MAX_SNAPSHOT_ATTEMPTS};
+use snarkos_node_ledger::{Event, Topic};
+use snarkos_node_messages::{Capabilities, Message, SnapshotRequest};
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake},
     P2P,
 };
 use snarkvm::prelude::Network;
 
+use anyhow::{bail, ensure, Result};
 use core::time::Duration;
+use std::net::SocketAddr;
+
+/// The interval at which the local transactions under embargo are checked, in milliseconds.
+const DIFFUSION_INTERVAL_IN_MS: u64 = 50;
+/// The interval at which the relay queues of the peers are flushed, in milliseconds.
+const RELAY_INTERVAL_IN_MS: u64 = 100;
 
 #[async_trait]
 pub trait Routing<N: Network>: P2P + Disconnect + Handshake + Inbound<N> + Outbound<N> + Heartbeat<N> {
@@ -33,10 +41,19 @@ pub trait Routing<N: Network>: P2P + Disconnect + Handshake + Inbound<N> + Outbo
         self.enable_reading().await;
         self.enable_writing().await;
         self.enable_disconnect().await;
-        // Enable the TCP listener. Note: This must be called after the above protocols.
-        self.enable_listener().await;
+        // Enable the TCP listener, if the node accepts inbound connections. Note: This must be called after the above protocols.
+        match self.router().listens() {
+            true => self.enable_listener().await,
+            false => self.router().sync.set_local_ip(self.router().local_ip()),
+        }
+        // Resolve the DNS seeds into candidate peers, in the background.
+        self.router().spawn_dns_seed_resolution();
         // Initialize the heartbeat.
         self.initialize_heartbeat();
+        // Initialize the diffusion of the local transactions.
+        self.initialize_diffusion();
+        // Initialize the relay of the queued transactions.
+        self.initialize_relay();
         // Initialize the report.
         self.initialize_report();
     }
@@ -60,6 +77,48 @@ pub trait Routing<N: Network>: P2P + Disconnect + Handshake + Inbound<N> + Outbo
         });
     }
 
+    /// Initialize the diffusion of the local transactions, which sends them to their first hops,
+    /// and broadcasts them once their embargo ends.
+    fn initialize_diffusion(&self) {
+        let self_clone = self.clone();
+        self.router().spawn(async move {
+            loop {
+                // Send the local transactions that are due.
+                self_clone.diffuse_due();
+                // Sleep for `DIFFUSION_INTERVAL_IN_MS` milliseconds.
+                tokio::time::sleep(Duration::from_millis(DIFFUSION_INTERVAL_IN_MS)).await;
+            }
+        });
+    }
+
+    /// Initialize the relay of the queued transactions, which flushes the relay queues of the peers,
+    /// after dropping the transactions that were mined or evicted from the memory pool since the last flush.
+    fn initialize_relay(&self) {
+        // Subscribe to the transactions that leave the memory pool.
+        let subscription = self.router().event_bus().subscribe(&[Topic::BlockConnected, Topic::TransactionEvicted]);
+        let self_clone = self.clone();
+        self.router().spawn(async move {
+            loop {
+                // Drop the queued transactions that were mined or evicted.
+                for event in subscription.try_iter() {
+                    match event {
+                        Event::BlockConnected { transaction_ids, .. } => {
+                            self_clone.router().relay_queues().purge(&transaction_ids)
+                        }
+                        Event::TransactionEvicted(evicted) => {
+                            self_clone.router().relay_queues().purge(&[evicted.transaction_id])
+                        }
+                        _ => (),
+                    }
+                }
+                // Relay the queued transactions that are due.
+                self_clone.relay_due();
+                // Sleep for `RELAY_INTERVAL_IN_MS` milliseconds.
+                tokio::time::sleep(Duration::from_millis(RELAY_INTERVAL_IN_MS)).await;
+            }
+        });
+    }
+
     /// Initialize a new instance of the report.
     fn initialize_report(&self) {
         let self_clone = self.clone();
@@ -79,4 +138,85 @@ pub trait Routing<N: Network>: P2P + Disconnect + Handshake + Inbound<N> + Outbo
             }
         });
     }
+
+    /// Bootstraps the ledger from the snapshot of the given peer, and returns the height of the snapshot.
+    ///
+    /// The snapshot is only requested from a trusted peer, over an encrypted connection authenticated by its pinned
+    /// transport key, and every chunk is checked against the manifest of the snapshot before it is imported.
+    async fn bootstrap_from_snapshot(&self, peer_ip: SocketAddr) -> Result<u32> {
+        let router = self.router();
+        // Ensure the peer is trusted, and authenticated by its pinned transport key, or by its trusted node ID.
+        ensure!(router.is_trusted(&peer_ip), "Peer '{peer_ip}' is not a trusted peer");
+        ensure!(
+            router.pinned_key(&peer_ip).is_some() || router.is_trusted_node(&peer_ip),
+            "The transport key of '{peer_ip}' is not pinned"
+        );
+        ensure!(router.is_encrypted(&peer_ip), "The connection to '{peer_ip}' is not encrypted");
+        // Ensure the peer serves snapshots.
+        match router.get_connected_peer(&peer_ip) {
+            Some(peer) if peer.capabilities().contains(Capabilities::SERVES_SNAPSHOTS) => (),
+            Some(_) => bail!("Peer '{peer_ip}' does not serve snapshots"),
+            None => bail!("Peer '{peer_ip}' is not connected"),
+        }
+
+        // Download the snapshot, and end the download regardless of its outcome.
+        let mut receiver = router.snapshots().start_download(peer_ip)?;
+        let result = self.download_snapshot(peer_ip, &mut receiver).await;
+        router.snapshots().finish_download();
+        result
+    }
+
+    /// Downloads the snapshot of the given peer, whose responses are delivered to the given receiver,
+    /// and imports its chunks in order. Each request is retried up to `MAX_SNAPSHOT_ATTEMPTS` times.
+    async fn download_snapshot(&self, peer_ip: SocketAddr, receiver: &mut SnapshotReceiver<N>) -> Result<u32> {
+        // Fetch the manifest of the snapshot.
+        let mut manifest = None;
+        for attempt in 1..=MAX_SNAPSHOT_ATTEMPTS {
+            self.send(peer_ip, Message::SnapshotRequest(SnapshotRequest { chunk: None }));
+            match receiver.next(None).await {
+                Some(SnapshotResponse::Manifest(candidate)) => {
+                    manifest = Some(candidate);
+                    break;
+                }
+                _ => warn!("No snapshot manifest from '{peer_ip}' (attempt {attempt}/{MAX_SNAPSHOT_ATTEMPTS})"),
+            }
+        }
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => bail!("Failed to fetch the snapshot manifest from '{peer_ip}'"),
+        };
+        manifest.check()?;
+
+        let num_chunks = manifest.num_chunks();
+        info!("Downloading a snapshot of {} blocks in {num_chunks} chunks from '{peer_ip}'", manifest.height);
+        for index in 0..num_chunks {
+            // Fetch the chunk, and check it against the manifest.
+            let mut bytes = None;
+            for attempt in 1..=MAX_SNAPSHOT_ATTEMPTS {
+                receiver.pace().await;
+                self.send(peer_ip, Message::SnapshotRequest(SnapshotRequest { chunk: Some(index) }));
+                match receiver.next(Some(index)).await {
+                    Some(SnapshotResponse::Chunk(chunk)) => match manifest.verify_chunk(index, &chunk.bytes) {
+                        Ok(()) => {
+                            bytes = Some(chunk.bytes);
+                            break;
+                        }
+                        Err(error) => warn!("Peer '{peer_ip}' sent a corrupted snapshot chunk - {error}"),
+                    },
+                    _ => {
+                        warn!("No snapshot chunk {index} from '{peer_ip}' (attempt {attempt}/{MAX_SNAPSHOT_ATTEMPTS})")
+                    }
+                }
+            }
+            let bytes = match bytes {
+                Some(bytes) => bytes,
+                None => bail!("Failed to fetch snapshot chunk {index} from '{peer_ip}'"),
+            };
+            // Import the chunk.
+            self.import_snapshot_chunk(peer_ip, &manifest, index, bytes).await?;
+            let progress = (index + 1) as f64 * 100.0 / num_chunks as f64;
+            info!("Imported snapshot chunk {}/{num_chunks} from '{peer_ip}' ({progress:.2}%)", index + 1);
+        }
+        Ok(manifest.height)
+    }
 }
@@ -26,8 +26,22 @@ use std::{
 
 use snarkos_account::Account;
 use snarkos_node_messages::NodeType;
-use snarkos_node_router::Router;
-use snarkvm::prelude::{Block, FromBytes, Network, Testnet3 as CurrentNetwork};
+use snarkos_node_router::{generate_keypair, NoiseConfig, PeerBook, Router};
+use snarkvm::prelude::{
+    Block,
+    ConsensusMemory,
+    ConsensusStore,
+    FromBytes,
+    Network,
+    PrivateKey,
+    Program,
+    TestRng,
+    Testnet3 as CurrentNetwork,
+    Transaction,
+    Value,
+    ViewKey,
+    VM,
+};
 
 /// A helper macro to print the TCP listening address, along with the connected and connecting peers.
 #[macro_export]
@@ -52,6 +66,50 @@ pub fn sample_genesis_block<N: Network>() -> Block<N> {
     Block::<N>::from_bytes_le(N::genesis_bytes()).unwrap()
 }
 
+/// Returns a chain of a deployment of a program, and an execution of the program, which depends on the deployment.
+/// Unlike the transactions of the genesis block, they are not coinbase transactions, so they are relayed.
+#[allow(dead_code)]
+pub fn sample_transaction_chain() -> [Transaction<CurrentNetwork>; 2] {
+    let rng = &mut TestRng::default();
+    let private_key = PrivateKey::<CurrentNetwork>::new(rng).unwrap();
+    let view_key = ViewKey::try_from(&private_key).unwrap();
+
+    // Initialize a VM with a new genesis block, and decrypt the records it mints.
+    let vm = VM::from(ConsensusStore::<CurrentNetwork, ConsensusMemory<CurrentNetwork>>::open(None).unwrap()).unwrap();
+    let genesis = Block::genesis(&vm, &private_key, rng).unwrap();
+    vm.add_next_block(&genesis).unwrap();
+    let records = genesis.records().map(|(_, record)| record.decrypt(&view_key).unwrap()).collect::<Vec<_>>();
+
+    // Deploy a program.
+    let program = Program::<CurrentNetwork>::from_str(
+        r"
+program chain.aleo;
+
+function hello:
+    input r0 as u32.public;
+    input r1 as u32.private;
+    add r0 r1 into r2;
+    output r2 as u32.private;",
+    )
+    .unwrap();
+    let parent = Transaction::deploy(&vm, &private_key, &program, (records[0].clone(), 6_000_000), None, rng).unwrap();
+
+    // Execute the program, which is only added to the process, as the deployment is not committed.
+    vm.process().write().add_program(&program).unwrap();
+    let inputs = [Value::<CurrentNetwork>::from_str("1u32").unwrap(), Value::from_str("2u32").unwrap()];
+    let locator = ("chain.aleo", "hello");
+    let child =
+        Transaction::execute(&vm, &private_key, locator, inputs.iter(), Some((records[1].clone(), 100)), None, rng)
+            .unwrap();
+
+    [parent, child]
+}
+
+/// Returns a transport encryption configuration with a new static keypair.
+pub fn sample_noise_config() -> NoiseConfig {
+    NoiseConfig::new(generate_keypair().unwrap())
+}
+
 /// Enables logging in tests.
 #[allow(dead_code)]
 pub fn initialize_logger(level: u8) {
@@ -79,6 +137,9 @@ pub async fn beacon(listening_port: u16, max_peers: u16) -> TestRouter<CurrentNe
         NodeType::Beacon,
         sample_account(),
         &[],
+        sample_noise_config(),
+        Default::default(),
+        Default::default(),
         max_peers,
         true,
     )
@@ -95,6 +156,9 @@ pub async fn client(listening_port: u16, max_peers: u16) -> TestRouter<CurrentNe
         NodeType::Client,
         sample_account(),
         &[],
+        sample_noise_config(),
+        Default::default(),
+        Default::default(),
         max_peers,
         true,
     )
@@ -111,6 +175,9 @@ pub async fn prover(listening_port: u16, max_peers: u16) -> TestRouter<CurrentNe
         NodeType::Prover,
         sample_account(),
         &[],
+        sample_noise_config(),
+        Default::default(),
+        Default::default(),
         max_peers,
         true,
     )
@@ -127,6 +194,9 @@ pub async fn validator(listening_port: u16, max_peers: u16) -> TestRouter<Curren
         NodeType::Validator,
         sample_account(),
         &[],
+        sample_noise_config(),
+        Default::default(),
+        Default::default(),
         max_peers,
         true,
     )
@@ -134,3 +204,73 @@ pub async fn validator(listening_port: u16, max_peers: u16) -> TestRouter<Curren
     .expect("couldn't create validator router")
     .into()
 }
+
+/// Initializes a validator router with the given transport encryption configuration.
+#[allow(dead_code)]
+pub async fn validator_with_noise(
+    listening_port: u16,
+    max_peers: u16,
+    noise: NoiseConfig,
+) -> TestRouter<CurrentNetwork> {
+    Router::new(
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), listening_port),
+        NodeType::Validator,
+        sample_account(),
+        &[],
+        noise,
+        Default::default(),
+        Default::default(),
+        max_peers,
+        true,
+    )
+    .await
+    .expect("couldn't create validator router")
+    .into()
+}
+
+/// Initializes a client router with the given peer book.
+#[allow(dead_code)]
+pub async fn client_with_peer_book(
+    listening_port: u16,
+    max_peers: u16,
+    peer_book: PeerBook<CurrentNetwork>,
+) -> TestRouter<CurrentNetwork> {
+    Router::new(
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), listening_port),
+        NodeType::Client,
+        sample_account(),
+        &[],
+        sample_noise_config(),
+        peer_book,
+        Default::default(),
+        max_peers,
+        true,
+    )
+    .await
+    .expect("couldn't create client router")
+    .into()
+}
+
+/// Initializes a client router with the given trusted peers, and transport encryption configuration.
+#[allow(dead_code)]
+pub async fn client_with_trusted_peers(
+    listening_port: u16,
+    max_peers: u16,
+    trusted_peers: &[SocketAddr],
+    noise: NoiseConfig,
+) -> TestRouter<CurrentNetwork> {
+    Router::new(
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), listening_port),
+        NodeType::Client,
+        sample_account(),
+        trusted_peers,
+        noise,
+        Default::default(),
+        Default::default(),
+        max_peers,
+        true,
+    )
+    .await
+    .expect("couldn't create client router")
+    .into()
+}
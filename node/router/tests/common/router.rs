@@ -17,15 +17,27 @@
 use crate::common::sample_genesis_block;
 use snarkos_node_messages::{
     BlockRequest,
+    CommittedHeader,
     DisconnectReason,
     Message,
-    MessageCodec,
+    PeerCodec,
     Ping,
     Pong,
+    SnapshotManifest,
     UnconfirmedSolution,
     UnconfirmedTransaction,
 };
-use snarkos_node_router::{Heartbeat, Inbound, Outbound, Router, Routing};
+use snarkos_node_router::{
+    Heartbeat,
+    Inbound,
+    LinkConditions,
+    MemoryTransport,
+    Outbound,
+    PeerTransport,
+    Router,
+    Routing,
+    SerialBlock,
+};
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake, Reading, Writing},
     Connection,
@@ -33,19 +45,170 @@ use snarkos_node_tcp::{
     Tcp,
     P2P,
 };
-use snarkvm::prelude::{Block, EpochChallenge, Header, Network, ProverSolution, Transaction};
+use snarkvm::prelude::{Block, EpochChallenge, FromBytes, Network, ProverSolution, ToBytes, Transaction};
 
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures_util::sink::SinkExt;
-use std::{io, net::SocketAddr};
+use indexmap::IndexMap;
+use parking_lot::{Mutex, RwLock};
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 use tracing::*;
 
+/// The unconfirmed transactions of a test router, in order of admission.
+pub type TestMemoryPool<N> = Arc<Mutex<IndexMap<<N as Network>::TransactionID, UnconfirmedTransaction<N>>>>;
+
+/// The blocks received by a test router in block responses, in order of receipt.
+pub type TestReceivedBlocks<N> = Arc<Mutex<Vec<Block<N>>>>;
+
+/// The number of blocks in each chunk of the snapshot of a test router.
+pub const TEST_BLOCKS_PER_CHUNK: u32 = 50;
+
+/// The canonical chain of a test router, as the hashes of its blocks from height 1 on, which is served as a snapshot
+/// whose chunks are the concatenated hashes of their blocks.
+pub struct TestChain<N: Network> {
+    /// The hashes of the blocks, from height 1 on.
+    hashes: RwLock<Vec<N::BlockHash>>,
+    /// The number of the next snapshot chunks to corrupt, before they are served.
+    corruptions: AtomicUsize,
+}
+
+impl<N: Network> Default for TestChain<N> {
+    fn default() -> Self {
+        Self { hashes: Default::default(), corruptions: Default::default() }
+    }
+}
+
+impl<N: Network> TestChain<N> {
+    /// Returns the hashes of the blocks, from height 1 on.
+    pub fn hashes(&self) -> Vec<N::BlockHash> {
+        self.hashes.read().clone()
+    }
+
+    /// Replaces the hashes of the blocks, from height 1 on.
+    pub fn set_hashes(&self, hashes: Vec<N::BlockHash>) {
+        *self.hashes.write() = hashes;
+    }
+
+    /// Corrupts the given number of the next snapshot chunks served by the router.
+    pub fn corrupt_chunks(&self, num_chunks: usize) {
+        self.corruptions.store(num_chunks, Ordering::SeqCst);
+    }
+
+    /// Returns the bytes of the given snapshot chunk, if the chain holds all of its blocks.
+    fn chunk(&self, index: u32) -> Option<Vec<u8>> {
+        let start = (index * TEST_BLOCKS_PER_CHUNK) as usize;
+        let hashes = self.hashes.read();
+        let hashes = hashes.get(start..start + TEST_BLOCKS_PER_CHUNK as usize)?;
+        Some(hashes.iter().flat_map(|hash| hash.to_bytes_le().unwrap()).collect())
+    }
+}
+
 #[derive(Clone)]
-pub struct TestRouter<N: Network>(Router<N>);
+pub struct TestRouter<N: Network>(
+    Router<N>,
+    N::BlockHash,
+    TestMemoryPool<N>,
+    Arc<TestChain<N>>,
+    TestReceivedBlocks<N>,
+);
 
 impl<N: Network> From<Router<N>> for TestRouter<N> {
     fn from(router: Router<N>) -> Self {
-        Self(router)
+        Self(router, sample_genesis_block::<N>().hash(), Default::default(), Default::default(), Default::default())
+    }
+}
+
+impl<N: Network> TestRouter<N> {
+    /// Sets the genesis block hash announced during the handshake, emulating a node on a different chain.
+    pub fn with_genesis_hash(mut self, genesis_hash: N::BlockHash) -> Self {
+        self.1 = genesis_hash;
+        self
+    }
+
+    /// Returns the unconfirmed transactions of the router, which admits a relayed transaction once every parent
+    /// it is announced with is admitted. The transactions are not verified.
+    pub fn memory_pool(&self) -> &TestMemoryPool<N> {
+        &self.2
+    }
+
+    /// Returns the canonical chain of the router, which it serves as a snapshot, and into which it imports a snapshot.
+    pub fn chain(&self) -> &Arc<TestChain<N>> {
+        &self.3
+    }
+
+    /// Returns the blocks the router received in block responses, in order of receipt.
+    pub fn received_blocks(&self) -> &TestReceivedBlocks<N> {
+        &self.4
+    }
+
+    /// Performs the handshake protocol over the given transport.
+    pub async fn handshake_with_transport<T: PeerTransport<N>>(
+        &self,
+        transport: T,
+        peer_side: ConnectionSide,
+    ) -> io::Result<(SocketAddr, T)> {
+        let peer_addr = transport.peer_addr()?;
+        let genesis_header = *sample_genesis_block().header();
+        self.router().handshake_with_transport(peer_addr, transport, peer_side, genesis_header, self.1).await
+    }
+
+    /// Performs the handshake with the given router over a pair of in-memory transports with the given link conditions.
+    /// Returns the transports of this router and of the given router, to be attached or driven by the test.
+    pub async fn handshake_in_memory(
+        &self,
+        peer: &Self,
+        conditions: LinkConditions,
+    ) -> io::Result<(MemoryTransport<N>, MemoryTransport<N>)> {
+        let (transport, peer_transport) = MemoryTransport::pair(self.local_ip(), peer.local_ip(), conditions);
+        let (result, peer_result) = tokio::join!(
+            self.handshake_with_transport(transport, ConnectionSide::Responder),
+            peer.handshake_with_transport(peer_transport, ConnectionSide::Initiator)
+        );
+        Ok((result?.1, peer_result?.1))
+    }
+
+    /// Routes the messages of the peer of the given in-memory transport through the transport,
+    /// and processes the messages received over it, until the transport is closed.
+    pub fn attach_memory_transport(&self, mut transport: MemoryTransport<N>) -> io::Result<()> {
+        let peer_addr = transport.peer_addr()?;
+        let peer_ip = match self.router().resolve_to_listener(&peer_addr) {
+            Some(peer_ip) => peer_ip,
+            None => return Err(io::Error::new(io::ErrorKind::NotConnected, format!("'{peer_addr}' is not connected"))),
+        };
+        self.router().insert_memory_link(peer_ip, transport.link().clone());
+
+        let node = self.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(message)) = transport.next_message().await {
+                let _ = node.process_message(peer_addr, message).await;
+            }
+            // The peer closed the transport.
+            let _ = transport.close().await;
+            node.handle_disconnect(peer_addr).await;
+        });
+        Ok(())
+    }
+
+    /// Connects to the given router over a pair of in-memory transports with the given link conditions,
+    /// which replace the TCP stack for a fully deterministic run.
+    pub async fn connect_in_memory(&self, peer: &Self, conditions: LinkConditions) -> io::Result<()> {
+        let (mut transport, mut peer_transport) = self.handshake_in_memory(peer, conditions).await?;
+
+        // Send the first `Ping` message to the peer, from both sides.
+        transport.send_message(Message::Ping(Ping::new(self.node_type(), None))).await?;
+        peer_transport.send_message(Message::Ping(Ping::new(peer.node_type(), None))).await?;
+
+        self.attach_memory_transport(transport)?;
+        peer.attach_memory_transport(peer_transport)
     }
 }
 
@@ -73,7 +236,8 @@ impl<N: Network> Handshake for TestRouter<N> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = *sample_genesis_block().header();
-        let (peer_ip, mut framed) = self.router().handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let (peer_ip, mut framed) =
+            self.router().handshake(peer_addr, stream, conn_side, genesis_header, self.1).await?;
 
         // Send the first `Ping` message to the peer.
         let message = Message::Ping(Ping::new(self.node_type(), None));
@@ -96,25 +260,25 @@ impl<N: Network> Disconnect for TestRouter<N> {
 
 #[async_trait]
 impl<N: Network> Writing for TestRouter<N> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates an [`Encoder`] used to write the outbound messages to the target stream.
     /// The `side` parameter indicates the connection side **from the node's perspective**.
-    fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router().codec(peer_addr)
     }
 }
 
 #[async_trait]
 impl<N: Network> Reading for TestRouter<N> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates a [`Decoder`] used to interpret messages from the network.
     /// The `side` param indicates the connection side **from the node's perspective**.
-    fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router().codec(peer_addr)
     }
 
     /// Processes a message received from the network.
@@ -150,7 +314,8 @@ impl<N: Network> Inbound<N> for TestRouter<N> {
     }
 
     /// Handles a `BlockResponse` message.
-    fn block_response(&self, _peer_ip: SocketAddr, _blocks: Vec<Block<N>>) -> bool {
+    fn block_response(&self, _peer_ip: SocketAddr, blocks: Vec<SerialBlock<N>>) -> bool {
+        self.4.lock().extend(blocks.into_iter().map(SerialBlock::into_block));
         true
     }
 
@@ -165,7 +330,12 @@ impl<N: Network> Inbound<N> for TestRouter<N> {
     }
 
     /// Handles an `PuzzleResponse` message.
-    fn puzzle_response(&self, _peer_ip: SocketAddr, _epoch_challenge: EpochChallenge<N>, _header: Header<N>) -> bool {
+    fn puzzle_response(
+        &self,
+        _peer_ip: SocketAddr,
+        _epoch_challenge: EpochChallenge<N>,
+        _header: CommittedHeader<N>,
+    ) -> bool {
         true
     }
 
@@ -183,9 +353,68 @@ impl<N: Network> Inbound<N> for TestRouter<N> {
     fn unconfirmed_transaction(
         &self,
         _peer_ip: SocketAddr,
-        _serialized: UnconfirmedTransaction<N>,
+        serialized: UnconfirmedTransaction<N>,
         _transaction: Transaction<N>,
     ) -> bool {
+        let mut memory_pool = self.2.lock();
+        if serialized.parent_ids.iter().all(|parent_id| memory_pool.contains_key(parent_id)) {
+            memory_pool.entry(serialized.transaction_id).or_insert(serialized);
+        }
         true
     }
+
+    /// Returns the given parents that are not in the memory pool.
+    fn missing_parents(&self, parent_ids: &[N::TransactionID]) -> Vec<N::TransactionID> {
+        let memory_pool = self.2.lock();
+        parent_ids.iter().filter(|parent_id| !memory_pool.contains_key(*parent_id)).copied().collect()
+    }
+
+    /// Returns the requested transactions that are in the memory pool, in order of admission.
+    fn unconfirmed_transactions(&self, transaction_ids: &[N::TransactionID]) -> Vec<UnconfirmedTransaction<N>> {
+        let memory_pool = self.2.lock();
+        memory_pool.values().filter(|message| transaction_ids.contains(&message.transaction_id)).cloned().collect()
+    }
+
+    /// Returns the manifest of the snapshot of the full chunks of the chain.
+    fn snapshot_manifest(&self) -> Option<SnapshotManifest<N>> {
+        let num_chunks = self.3.hashes.read().len() as u32 / TEST_BLOCKS_PER_CHUNK;
+        let chunk_hashes = (0..num_chunks)
+            .map(|index| SnapshotManifest::<N>::chunk_hash(&self.3.chunk(index).unwrap()))
+            .collect::<Vec<_>>();
+        let block_hash = match num_chunks {
+            0 => self.1,
+            _ => self.3.hashes.read()[(num_chunks * TEST_BLOCKS_PER_CHUNK) as usize - 1],
+        };
+        SnapshotManifest::new(TEST_BLOCKS_PER_CHUNK, chunk_hashes, block_hash).ok()
+    }
+
+    /// Returns the bytes of the given snapshot chunk, which are corrupted if requested by the test.
+    fn snapshot_chunk(&self, index: u32) -> Option<Bytes> {
+        let mut bytes = self.3.chunk(index)?;
+        let corruptions = &self.3.corruptions;
+        if corruptions.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+            bytes[0] ^= 1;
+        }
+        Some(bytes.into())
+    }
+
+    /// Appends the blocks of the given snapshot chunk to the chain.
+    async fn import_snapshot_chunk(
+        &self,
+        _peer_ip: SocketAddr,
+        manifest: &SnapshotManifest<N>,
+        index: u32,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let range = manifest.chunk_range(index);
+        let mut hashes = self.3.hashes.write();
+        if hashes.len() as u32 + 1 != range.start {
+            bail!("Snapshot chunk {index} does not extend the chain of {} blocks", hashes.len())
+        }
+        let mut reader = &bytes[..];
+        for _ in range {
+            hashes.push(N::BlockHash::read_le(&mut reader)?);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,119 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::{
+    BlockRequest,
+    BlockResponse,
+    Capabilities,
+    Compression,
+    Data,
+    DataBlocks,
+    Message,
+    COMPRESSION_THRESHOLD,
+};
+use snarkos_node_router::Outbound;
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake, Reading, Writing},
+    P2P,
+};
+use snarkvm::prelude::{Testnet3 as CurrentNetwork, ToBytes};
+
+use core::time::Duration;
+
+/// Initializes a router with the given compression, and enables its protocols and listener.
+async fn router_with_compression(compression: Compression) -> TestRouter<CurrentNetwork> {
+    let node = client(0, 2).await;
+    node.set_compression(compression);
+    node.enable_handshake().await;
+    node.enable_reading().await;
+    node.enable_writing().await;
+    node.enable_disconnect().await;
+    node.tcp().enable_listener().await.unwrap();
+    node
+}
+
+/// Connects the first router to the second one.
+async fn connect(node0: &TestRouter<CurrentNetwork>, node1: &TestRouter<CurrentNetwork>) {
+    node0.connect(node1.local_ip());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node0.number_of_connected_peers(), 1);
+    assert_eq!(node1.number_of_connected_peers(), 1);
+}
+
+/// Sends the genesis block from the first router to the second one, in response to a block request
+/// of the second router.
+async fn send_genesis_block(node0: &TestRouter<CurrentNetwork>, node1: &TestRouter<CurrentNetwork>) {
+    let request = BlockRequest { start_height: 0, end_height: 1 };
+    node1.send(node0.local_ip(), Message::BlockRequest(request)).unwrap();
+    let blocks = Data::Object(DataBlocks(vec![sample_genesis_block::<CurrentNetwork>()]));
+    node0.send(node1.local_ip(), Message::BlockResponse(BlockResponse { request, blocks })).unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+}
+
+#[tokio::test]
+async fn test_blocks_with_compression() {
+    let node0 = router_with_compression(Compression::Zstd(3)).await;
+    let node1 = router_with_compression(Compression::Zstd(9)).await;
+    connect(&node0, &node1).await;
+    send_genesis_block(&node0, &node1).await;
+
+    // Ensure the block is decoded into the same block.
+    let genesis = sample_genesis_block::<CurrentNetwork>();
+    assert!(genesis.to_bytes_le().unwrap().len() > COMPRESSION_THRESHOLD);
+    assert_eq!(*node1.received_blocks().lock(), vec![genesis]);
+
+    // Ensure the block response was compressed, and counted on both sides of the connection.
+    let sent = node0.compression_stats(&node1.local_ip()).unwrap().sent();
+    let received = node1.compression_stats(&node0.local_ip()).unwrap().received();
+    assert!(sent.1 > 0 && sent.1 < sent.0, "{sent:?}");
+    assert_eq!(sent, received);
+}
+
+#[tokio::test]
+async fn test_blocks_without_compression() {
+    let node0 = router_with_compression(Compression::None).await;
+    let node1 = router_with_compression(Compression::None).await;
+    connect(&node0, &node1).await;
+    send_genesis_block(&node0, &node1).await;
+
+    // Ensure the block is decoded into the same block, over an uncompressed connection.
+    assert_eq!(*node1.received_blocks().lock(), vec![sample_genesis_block::<CurrentNetwork>()]);
+    assert!(node0.compression_stats(&node1.local_ip()).is_none());
+    assert!(node1.compression_stats(&node0.local_ip()).is_none());
+}
+
+#[tokio::test]
+async fn test_blocks_with_mixed_compression() {
+    // Ensure the compression is only advertised by the router that enables it.
+    let node0 = router_with_compression(Compression::Zstd(3)).await;
+    let node1 = router_with_compression(Compression::None).await;
+    assert!(node0.capabilities().contains(Capabilities::COMPRESSION));
+    assert!(!node1.capabilities().contains(Capabilities::COMPRESSION));
+
+    // Ensure the routers peer, and exchange the block over an uncompressed connection, in both directions.
+    connect(&node0, &node1).await;
+    send_genesis_block(&node0, &node1).await;
+    assert_eq!(*node1.received_blocks().lock(), vec![sample_genesis_block::<CurrentNetwork>()]);
+    assert!(node0.compression_stats(&node1.local_ip()).is_none());
+    assert!(node1.compression_stats(&node0.local_ip()).is_none());
+
+    send_genesis_block(&node1, &node0).await;
+    assert_eq!(*node0.received_blocks().lock(), vec![sample_genesis_block::<CurrentNetwork>()]);
+    assert_eq!(node0.number_of_connected_peers(), 1);
+}
@@ -17,9 +17,26 @@
 mod common;
 use common::*;
 
-use snarkos_node_tcp::{protocols::Handshake, P2P};
+use snarkos_node_messages::{
+    Capabilities,
+    ChallengeRequest,
+    Data,
+    DisconnectReason,
+    Message,
+    MessageCodec,
+    NodeRole,
+    NodeType,
+    OnionAddr,
+    UnconfirmedTransaction,
+};
+use snarkos_node_router::Outbound;
+use snarkos_node_tcp::{protocols::Handshake, ProxyConfig, P2P};
+use snarkvm::prelude::{Network, Testnet3 as CurrentNetwork};
 
 use core::time::Duration;
+use futures_util::{sink::SinkExt, TryStreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
 
 #[tokio::test]
 async fn test_connect_without_handshake() {
@@ -187,3 +204,217 @@ async fn test_connect_simultaneously_with_handshake() {
         assert_eq!(node1.number_of_connected_peers(), 1);
     }
 }
+
+#[tokio::test]
+async fn test_connect_with_mismatched_genesis() {
+    // Create 2 routers, with node1 on a different chain.
+    let node0 = validator(0, 2).await;
+    let node1 = client(0, 2).await.with_genesis_hash(Default::default());
+    assert_eq!(node0.number_of_connected_peers(), 0);
+    assert_eq!(node1.number_of_connected_peers(), 0);
+
+    // Enable handshake protocol.
+    node0.enable_handshake().await;
+    node1.enable_handshake().await;
+
+    // Start listening.
+    node0.tcp().enable_listener().await.unwrap();
+    node1.tcp().enable_listener().await.unwrap();
+
+    {
+        // Connect node0 to node1.
+        node0.connect(node1.local_ip());
+        // Sleep briefly.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        print_tcp!(node0);
+        print_tcp!(node1);
+
+        // Check the TCP level.
+        assert_eq!(node0.tcp().num_connected(), 0);
+        assert_eq!(node0.tcp().num_connecting(), 0);
+        assert_eq!(node1.tcp().num_connected(), 0);
+        assert_eq!(node1.tcp().num_connecting(), 0);
+
+        // Check the router level.
+        assert_eq!(node0.number_of_connected_peers(), 0);
+        assert_eq!(node1.number_of_connected_peers(), 0);
+
+        // Ensure both nodes restricted each other, and only node1, which detected the mismatch, tagged node0.
+        assert!(node0.is_restricted(&node1.local_ip()));
+        assert!(!node0.is_mismatched(&node1.local_ip()));
+        assert!(node1.is_restricted(&node0.local_ip()));
+        assert!(node1.is_mismatched(&node0.local_ip()));
+    }
+    {
+        // Ensure node0 does not learn of node1 as a candidate peer.
+        node0.insert_candidate_peers(&[node1.local_ip()]);
+        assert_eq!(node0.number_of_candidate_peers(), 0);
+
+        // Connect node0 to node1 again.
+        node0.connect(node1.local_ip());
+        // Sleep briefly.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Ensure the connection attempt was dropped.
+        assert_eq!(node0.tcp().num_connected(), 0);
+        assert_eq!(node1.tcp().num_connected(), 0);
+        assert_eq!(node0.number_of_connected_peers(), 0);
+    }
+}
+
+#[tokio::test]
+async fn test_connect_with_mismatched_network() {
+    // Create a router.
+    let node = validator(0, 2).await;
+    node.enable_handshake().await;
+    node.tcp().enable_listener().await.unwrap();
+
+    // Connect to the router from a peer on a different network.
+    let peer_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer_ip = peer_listener.local_addr().unwrap();
+    let stream = TcpStream::connect(node.local_ip()).await.unwrap();
+    let mut framed = Framed::new(stream, MessageCodec::<CurrentNetwork>::default());
+
+    // Send a challenge request for a different network.
+    let mut request = ChallengeRequest::new(
+        peer_ip.port(),
+        NodeType::Client,
+        sample_account().address(),
+        sample_genesis_block::<CurrentNetwork>().hash(),
+        0,
+        0,
+    );
+    request.network_id = CurrentNetwork::ID + 1;
+    framed.send(Message::ChallengeRequest(request)).await.unwrap();
+
+    // Ensure the router disconnects immediately, with the reason.
+    match tokio::time::timeout(Duration::from_millis(500), framed.try_next()).await {
+        Ok(Ok(Some(Message::Disconnect(disconnect)))) => {
+            assert_eq!(disconnect.reason, DisconnectReason::InvalidNetworkId)
+        }
+        _ => panic!("Expected a disconnect message"),
+    }
+
+    // Ensure the peer is banned.
+    assert_eq!(node.number_of_connected_peers(), 0);
+    assert!(node.is_restricted(&peer_ip));
+    assert!(node.is_mismatched(&peer_ip));
+}
+
+#[tokio::test]
+async fn test_connect_with_lowered_max_peers() {
+    // Create 3 routers.
+    let node0 = validator(0, 2).await;
+    let node1 = client(0, 2).await;
+    let node2 = client(0, 2).await;
+
+    // Ensure the maximum number of peers cannot be raised beyond the limit of the TCP stack.
+    node0.set_max_connected_peers(Some(10));
+    assert_eq!(node0.max_connected_peers(), 2);
+    // Lower the maximum number of peers of node0.
+    node0.set_max_connected_peers(Some(1));
+    assert_eq!(node0.max_connected_peers(), 1);
+
+    // Enable handshake protocol.
+    node0.enable_handshake().await;
+    node1.enable_handshake().await;
+    node2.enable_handshake().await;
+
+    // Start listening.
+    node0.tcp().enable_listener().await.unwrap();
+    node1.tcp().enable_listener().await.unwrap();
+    node2.tcp().enable_listener().await.unwrap();
+
+    // Connect node1 to node0.
+    node1.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node0.number_of_connected_peers(), 1);
+
+    // Ensure node2 is refused, as node0 has reached its lowered maximum.
+    node2.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node0.number_of_connected_peers(), 1);
+    assert_eq!(node2.number_of_connected_peers(), 0);
+
+    // Restore the initial maximum, and ensure node2 is now accepted.
+    node0.set_max_connected_peers(None);
+    assert_eq!(node0.max_connected_peers(), 2);
+    node2.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node0.number_of_connected_peers(), 2);
+}
+
+#[tokio::test]
+async fn test_connect_outbound_only() {
+    // Create 2 routers, where node1 does not accept inbound connections.
+    let node0 = validator(0, 2).await;
+    let node1 = client(0, 2).await;
+    node1.set_role(NodeRole::OutboundOnly);
+
+    // Enable handshake protocol.
+    node0.enable_handshake().await;
+    node1.enable_handshake().await;
+
+    // Start listening on node0 only.
+    node0.tcp().enable_listener().await.unwrap();
+    assert!(node1.tcp().listening_addr().is_err());
+
+    // Connect node1 to node0.
+    node1.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node0.number_of_connected_peers(), 1);
+    assert_eq!(node1.number_of_connected_peers(), 1);
+
+    // Ensure node0 observes the capabilities advertised by node1.
+    let peer = node0.get_connected_peers().pop().unwrap();
+    let expected = NodeRole::OutboundOnly.capabilities().union(Capabilities::ARCHIVAL).union(Capabilities::PARENT_TXS);
+    assert_eq!(peer.capabilities(), expected);
+    assert!(!peer.is_listening());
+    assert!(peer.is_relaying());
+    assert!(peer.is_serving_blocks());
+    // Ensure node1 observes that node0 serves every capability, and every block.
+    let peer = node1.get_connected_peer(&node0.local_ip()).unwrap();
+    assert_eq!(peer.capabilities(), Capabilities::ALL.union(Capabilities::ARCHIVAL).union(Capabilities::PARENT_TXS));
+
+    // Ensure node1 relays the unconfirmed transactions from its peers, unless it operates as a client.
+    let transaction = sample_genesis_block::<CurrentNetwork>().transactions().iter().next().unwrap().clone();
+    let message = UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction));
+    let message = Message::UnconfirmedTransaction(message);
+    assert!(!node1.skips_relay(&message, &[node0.local_ip()]));
+    node1.set_role(NodeRole::Client);
+    assert!(node1.skips_relay(&message, &[node0.local_ip()]));
+    // Ensure a client still propagates the transactions it originates.
+    assert!(!node1.skips_relay(&message, &[]));
+}
+
+#[tokio::test]
+async fn test_proxy_only_mode() {
+    let node = client(0, 2).await;
+    let onion: OnionAddr = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion:4133".parse().unwrap();
+
+    // Ensure the onion addresses are ignored without a proxy.
+    node.insert_onion_peers(&[onion.clone()]);
+    assert_eq!(node.number_of_candidate_peers(), 0);
+
+    // Ensure a node in proxy-only mode does not listen, and advertises that it dials the onion addresses.
+    let proxy = ProxyConfig { address: "127.0.0.1:9050".parse().unwrap(), auth: None, proxy_only: true };
+    node.set_proxy(proxy.clone(), None).unwrap();
+    assert!(!node.listens());
+    assert!(!node.capabilities().contains(Capabilities::LISTENS));
+    assert!(node.capabilities().contains(Capabilities::ONION_ADDRS));
+
+    // Ensure the node listens for the connections of its onion service, which is forwarded to a loopback address.
+    node.set_proxy(proxy, Some(onion.clone())).unwrap();
+    assert!(node.listens());
+    assert!(node.capabilities().contains(Capabilities::LISTENS));
+
+    // Ensure the onion addresses become candidate peers, except its own, and the unknown virtual addresses are ignored.
+    let other: OnionAddr = "juhanurmihxlp77nkq76byazcldy2hlmovfu2epvl5ankdibsot4csyd.onion:4133".parse().unwrap();
+    node.insert_onion_peers(&[onion.clone(), other.clone()]);
+    assert_eq!(node.number_of_candidate_peers(), 1);
+    assert_eq!(node.onion_peer(&other.virtual_addr()), Some(other));
+    assert_eq!(node.tcp().domain(node.candidate_peers().into_iter().next().unwrap()).unwrap().1, 4133);
+    node.insert_candidate_peers(&[onion.virtual_addr()]);
+    assert_eq!(node.number_of_candidate_peers(), 1);
+}
@@ -0,0 +1,127 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::{Data, Message, UnconfirmedTransaction};
+use snarkos_node_router::{DiffusionConfig, Outbound, PeerTransport, Privacy, Routing};
+use snarkos_node_tcp::P2P;
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use core::time::Duration;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tokio::time::Instant;
+
+/// The number of peers of the node that submits the transaction.
+const NUM_PEERS: usize = 6;
+
+/// The settings of the diffusion in the tests.
+fn sample_config() -> DiffusionConfig {
+    DiffusionConfig {
+        privacy: Privacy::On,
+        first_hops: 2,
+        mean_delay: Duration::from_millis(100),
+        min_embargo: Duration::from_secs(2),
+        max_embargo: Duration::from_secs(3),
+    }
+}
+
+/// Returns the message of a sample transaction.
+fn sample_message() -> UnconfirmedTransaction<CurrentNetwork> {
+    let transaction = sample_genesis_block::<CurrentNetwork>().transactions().iter().next().unwrap().clone();
+    UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction))
+}
+
+/// Connects the given router to the given number of scripted peers over in-memory transports,
+/// and returns the times at which each peer received an unconfirmed transaction.
+async fn connect_scripted_peers(node: &TestRouter<CurrentNetwork>, num_peers: usize) -> Vec<Arc<Mutex<Vec<Instant>>>> {
+    let mut receipts = Vec::with_capacity(num_peers);
+    for _ in 0..num_peers {
+        let peer_node = client(0, 1).await;
+        peer_node.tcp().enable_listener().await.unwrap();
+
+        // Connect the router to the scripted peer, as an outbound connection of the router.
+        let (transport, mut peer) = node.handshake_in_memory(&peer_node, Default::default()).await.unwrap();
+        node.attach_memory_transport(transport).unwrap();
+
+        // Record the time at which the peer receives each unconfirmed transaction.
+        let times = Arc::new(Mutex::new(Vec::new()));
+        let times_clone = times.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(message)) = peer.next_message().await {
+                if let Message::UnconfirmedTransaction(_) = message {
+                    times_clone.lock().push(Instant::now());
+                }
+            }
+        });
+        receipts.push(times);
+    }
+    receipts
+}
+
+/// Returns the number of peers that received the transaction.
+fn num_received(receipts: &[Arc<Mutex<Vec<Instant>>>]) -> usize {
+    receipts.iter().filter(|times| !times.lock().is_empty()).count()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_local_transaction_is_diffused_before_broadcast() {
+    let node = client(0, NUM_PEERS as u16).await;
+    node.tcp().enable_listener().await.unwrap();
+    node.router().diffusion().set_config(sample_config());
+    let receipts = connect_scripted_peers(&node, NUM_PEERS).await;
+    assert_eq!(node.router().diffusion_candidates().len(), NUM_PEERS);
+
+    // Submit the transaction, and start the diffusion.
+    let start = Instant::now();
+    node.diffuse(sample_message());
+    node.initialize_diffusion();
+
+    // Ensure no peer received the transaction immediately.
+    tokio::time::sleep(Duration::from_millis(1)).await;
+    assert_eq!(num_received(&receipts), 0);
+
+    // Ensure only the first hops received the transaction before the embargo ended.
+    tokio::time::sleep(sample_config().min_embargo - Duration::from_millis(100)).await;
+    assert_eq!(num_received(&receipts), sample_config().first_hops);
+
+    // Ensure every peer received the transaction once, within the maximum embargo.
+    tokio::time::sleep(sample_config().max_embargo).await;
+    assert_eq!(num_received(&receipts), NUM_PEERS);
+    let bound = start + sample_config().max_embargo + Duration::from_millis(100);
+    for times in &receipts {
+        let times = times.lock();
+        assert_eq!(times.len(), 1);
+        assert!(times[0] <= bound);
+    }
+    assert_eq!(node.router().diffusion().num_embargoed(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_local_transaction_is_broadcast_without_privacy() {
+    let node = client(0, NUM_PEERS as u16).await;
+    node.tcp().enable_listener().await.unwrap();
+    node.router().diffusion().set_config(DiffusionConfig { privacy: Privacy::Off, ..sample_config() });
+    let receipts = connect_scripted_peers(&node, NUM_PEERS).await;
+
+    // Ensure every peer received the transaction immediately.
+    node.diffuse(sample_message());
+    tokio::time::sleep(Duration::from_millis(1)).await;
+    assert_eq!(num_received(&receipts), NUM_PEERS);
+    assert_eq!(node.router().diffusion().num_embargoed(), 0);
+}
@@ -17,7 +17,11 @@
 mod common;
 use common::*;
 
-use snarkos_node_tcp::{protocols::Handshake, P2P};
+use snarkos_node_ledger::{Event, Topic};
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake},
+    P2P,
+};
 
 use core::time::Duration;
 
@@ -110,3 +114,39 @@ async fn test_disconnect_with_handshake() {
     assert_eq!(node1.tcp().num_connected(), 1); // Router 1 has no way of knowing that Router 0 disconnected.
     assert_eq!(node1.tcp().num_connecting(), 0);
 }
+
+#[tokio::test]
+async fn test_peer_events_are_published() {
+    // Create 2 routers.
+    let node0 = validator(0, 1).await;
+    let node1 = client(0, 1).await;
+
+    // Subscribe to the connected and disconnected peers of node0.
+    let subscription = node0.event_bus().subscribe(&[Topic::PeerConnected, Topic::PeerDisconnected]);
+
+    // Enable the handshake and disconnect protocols.
+    node0.enable_handshake().await;
+    node0.enable_disconnect().await;
+    node1.enable_handshake().await;
+
+    // Start listening.
+    node0.tcp().enable_listener().await.unwrap();
+    node1.tcp().enable_listener().await.unwrap();
+
+    // Connect node0 to node1.
+    node0.connect(node1.local_ip());
+    // Sleep briefly.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node0.number_of_connected_peers(), 1);
+
+    // Disconnect node0 from node1.
+    node0.disconnect(node1.local_ip());
+    // Sleep briefly.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Ensure the connection and the disconnection were published, in order.
+    assert_eq!(subscription.try_iter().collect::<Vec<_>>(), vec![
+        Event::PeerConnected { peer_ip: node1.local_ip() },
+        Event::PeerDisconnected { peer_ip: node1.local_ip() }
+    ]);
+}
@@ -0,0 +1,114 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::NodeType;
+use snarkos_node_router::{
+    PeerBook,
+    SeedResolver,
+    DNS_SEED_TIMEOUT,
+    MAXIMUM_ADDRESSES_PER_GROUP,
+    MAXIMUM_ADDRESSES_PER_SEED,
+};
+
+use snarkos_node_tcp::P2P;
+
+use anyhow::{bail, Result};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+/// A resolver with a fixed set of addresses for each hostname, which never responds for the hostname `slow`.
+struct MockResolver(HashMap<String, Vec<SocketAddr>>);
+
+#[async_trait::async_trait]
+impl SeedResolver for MockResolver {
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        if host == "slow" {
+            tokio::time::sleep(DNS_SEED_TIMEOUT * 10).await;
+        }
+        match self.0.get(host) {
+            Some(addresses) => Ok(addresses.iter().map(|addr| SocketAddr::new(addr.ip(), port)).collect()),
+            None => bail!("Unknown host '{host}'"),
+        }
+    }
+}
+
+/// Returns a resolver for which `spread` resolves to many addresses in distinct network groups,
+/// and `clustered` resolves to many addresses in the same network group.
+fn sample_resolver() -> Arc<MockResolver> {
+    let spread = (1..=100).map(|i| SocketAddr::from(([10, i, 0, 1], 0))).collect();
+    let clustered = (1..=100).map(|i| SocketAddr::from(([172, 16, 0, i], 0))).collect();
+    Arc::new(MockResolver(HashMap::from([("spread".to_string(), spread), ("clustered".to_string(), clustered)])))
+}
+
+#[tokio::test]
+async fn test_dns_seed_caps() {
+    let node = client(0, 10).await;
+    node.tcp().enable_listener().await.unwrap();
+    node.dns_seeds().set_hosts(vec!["spread".to_string(), "clustered:4140".to_string()]);
+    node.dns_seeds().set_resolver(sample_resolver());
+    node.resolve_dns_seeds().await;
+
+    // Ensure the addresses accepted from each seed are capped, in number and in network group.
+    let candidates = node.candidate_peers();
+    assert_eq!(candidates.len(), MAXIMUM_ADDRESSES_PER_SEED + MAXIMUM_ADDRESSES_PER_GROUP);
+    assert_eq!(candidates.iter().filter(|ip| ip.port() == 4133).count(), MAXIMUM_ADDRESSES_PER_SEED);
+    assert_eq!(candidates.iter().filter(|ip| ip.port() == 4140).count(), MAXIMUM_ADDRESSES_PER_GROUP);
+    // Ensure the accepted addresses are tagged as seed-sourced.
+    assert!(candidates.iter().all(|ip| node.dns_seeds().is_seed_peer(ip)));
+}
+
+#[tokio::test]
+async fn test_seed_peers_fill_at_most_half_of_outbound() {
+    let node = client(0, 10).await;
+    node.tcp().enable_listener().await.unwrap();
+    node.dns_seeds().set_hosts(vec!["spread".to_string()]);
+    node.dns_seeds().set_resolver(sample_resolver());
+    node.resolve_dns_seeds().await;
+    let rng = &mut rand::thread_rng();
+
+    // Ensure the seed-sourced peers never exceed half of the outbound slots.
+    let selected = node.select_candidate_peers(10, 10, rng);
+    assert_eq!(selected.len(), 5);
+    assert!(selected.iter().all(|ip| node.dns_seeds().is_seed_peer(ip)));
+
+    // Ensure the other candidate peers are preferred over the seed-sourced peers.
+    let gossiped = (1..=3).map(|i| SocketAddr::from(([192, 168, 0, i], 4133))).collect::<Vec<_>>();
+    node.insert_candidate_peers(&gossiped);
+    let selected = node.select_candidate_peers(10, 10, rng);
+    assert_eq!(selected.len(), 8);
+    assert!(gossiped.iter().all(|ip| selected.contains(ip)));
+    assert_eq!(selected.iter().filter(|ip| node.dns_seeds().is_seed_peer(ip)).count(), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dns_seed_fallback_to_stored_peers() {
+    // Initialize a peer book with a stored peer.
+    let stored_ip = SocketAddr::from(([192, 168, 1, 1], 4133));
+    let peer_book = PeerBook::default();
+    peer_book.record_connection(stored_ip, NodeType::Validator, 1, true);
+    peer_book.record_disconnection(&stored_ip);
+
+    // Ensure a failing and an unresponsive seed fall back to the stored peers, once the resolution times out.
+    let node = client_with_peer_book(0, 10, peer_book).await;
+    node.tcp().enable_listener().await.unwrap();
+    node.dns_seeds().set_hosts(vec!["unknown".to_string(), "slow".to_string()]);
+    node.dns_seeds().set_resolver(sample_resolver());
+    node.resolve_dns_seeds().await;
+    assert_eq!(node.candidate_peers().into_iter().collect::<Vec<_>>(), vec![stored_ip]);
+    assert!(node.dns_seeds().seed_peers().is_empty());
+}
@@ -0,0 +1,215 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::{
+    ChallengeRequest,
+    ChallengeResponse,
+    Data,
+    DisconnectReason,
+    Message,
+    NodeType,
+    PeerCodec,
+    Ping,
+};
+use snarkos_node_router::{generate_keypair, noise_handshake};
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake, Reading, Writing},
+    ConnectionSide,
+    P2P,
+};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use bytes::BytesMut;
+use core::time::Duration;
+use futures_util::{sink::SinkExt, TryStreamExt};
+use std::net::SocketAddr;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::codec::{Encoder, Framed};
+
+/// Returns a challenge request from a peer listening on the given address, with the given message version.
+fn sample_challenge_request(peer_ip: SocketAddr, version: u32) -> ChallengeRequest<CurrentNetwork> {
+    let mut request = ChallengeRequest::new(
+        peer_ip.port(),
+        NodeType::Client,
+        sample_account().address(),
+        sample_genesis_block::<CurrentNetwork>().hash(),
+        0,
+        0,
+    );
+    request.version = version;
+    request
+}
+
+#[tokio::test]
+async fn test_connect_with_encryption() {
+    // Create 2 routers.
+    let node0 = validator(0, 2).await;
+    let node1 = client(0, 2).await;
+
+    // Enable the protocols.
+    for node in [&node0, &node1] {
+        node.enable_handshake().await;
+        node.enable_reading().await;
+        node.enable_writing().await;
+        node.enable_disconnect().await;
+        node.tcp().enable_listener().await.unwrap();
+    }
+
+    // Connect node0 to node1.
+    node0.connect(node1.local_ip());
+    // Sleep briefly.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Ensure both nodes are connected over an encrypted connection.
+    assert_eq!(node0.number_of_connected_peers(), 1);
+    assert_eq!(node1.number_of_connected_peers(), 1);
+    assert!(node0.is_encrypted(&node1.local_ip()));
+    assert!(node1.is_encrypted(&node0.local_ip()));
+}
+
+#[tokio::test]
+async fn test_tampered_ciphertext_disconnects() {
+    // Create a router.
+    let node = validator(0, 2).await;
+    node.enable_handshake().await;
+    node.enable_reading().await;
+    node.enable_writing().await;
+    node.enable_disconnect().await;
+    node.tcp().enable_listener().await.unwrap();
+
+    // Connect to the router.
+    let peer_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer_ip = peer_listener.local_addr().unwrap();
+    let stream = TcpStream::connect(node.local_ip()).await.unwrap();
+    let mut framed = Framed::new(stream, PeerCodec::<CurrentNetwork>::default());
+
+    // Send the challenge request, and receive the router's challenge request.
+    let request = sample_challenge_request(peer_ip, Message::<CurrentNetwork>::VERSION);
+    framed.send(Message::ChallengeRequest(request)).await.unwrap();
+    let node_request = match framed.try_next().await.unwrap() {
+        Some(Message::ChallengeRequest(request)) => request,
+        _ => panic!("Expected a challenge request"),
+    };
+
+    // Encrypt the connection, and receive the router's challenge response.
+    let keypair = generate_keypair().unwrap();
+    let mut framed = noise_handshake(framed, &keypair.private, ConnectionSide::Initiator).await.unwrap();
+    assert!(matches!(framed.try_next().await.unwrap(), Some(Message::ChallengeResponse(..))));
+
+    // Send the challenge response, signing the nonce bound to the encrypted session.
+    let handshake_hash = framed.codec().post_handshake_state().unwrap().handshake_hash().to_vec();
+    let message = [node_request.nonce.to_le_bytes().as_slice(), &handshake_hash].concat();
+    let signature = sample_account().sign_bytes(&message, &mut rand::thread_rng()).unwrap();
+    let genesis_header = *sample_genesis_block::<CurrentNetwork>().header();
+    let response = ChallengeResponse { genesis_header, signature: Data::Object(signature) };
+    framed.send(Message::ChallengeResponse(response)).await.unwrap();
+
+    // Ensure the router is connected over an encrypted connection.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node.number_of_connected_peers(), 1);
+    assert!(node.is_encrypted(&peer_ip));
+
+    // Send a ping with a tampered ciphertext.
+    let mut ciphertext = BytesMut::new();
+    let ping = Message::Ping(Ping::new(NodeType::Client, None));
+    framed.codec_mut().encode(ping, &mut ciphertext).unwrap();
+    let last = ciphertext.len() - 1;
+    ciphertext[last] ^= 1;
+    framed.get_mut().write_all(&ciphertext).await.unwrap();
+
+    // Ensure the router disconnects.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node.number_of_connected_peers(), 0);
+    assert_eq!(node.tcp().num_connected(), 0);
+}
+
+#[tokio::test]
+async fn test_connect_with_pinned_key() {
+    // Create a router.
+    let node0 = client(0, 2).await;
+    node0.enable_handshake().await;
+    node0.tcp().enable_listener().await.unwrap();
+
+    // Create a router with a mismatched key pinned for the first router.
+    let wrong_key = generate_keypair().unwrap().public;
+    let noise = sample_noise_config().with_pinned_keys([(node0.local_ip(), wrong_key)].into_iter().collect());
+    let node1 = validator_with_noise(0, 2, noise).await;
+    node1.enable_handshake().await;
+    node1.tcp().enable_listener().await.unwrap();
+
+    // Create a router with the correct key pinned for the first router.
+    let correct_key = node0.noise_public_key().to_vec();
+    let noise = sample_noise_config().with_pinned_keys([(node0.local_ip(), correct_key)].into_iter().collect());
+    let node2 = validator_with_noise(0, 2, noise).await;
+    node2.enable_handshake().await;
+    node2.tcp().enable_listener().await.unwrap();
+
+    // Ensure the connection with the mismatched key is refused.
+    node1.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node1.number_of_connected_peers(), 0);
+    assert_eq!(node1.tcp().num_connected(), 0);
+    assert_eq!(node0.number_of_connected_peers(), 0);
+
+    // Ensure the connection with the correct key succeeds.
+    node2.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node2.number_of_connected_peers(), 1);
+    assert!(node2.is_encrypted(&node0.local_ip()));
+}
+
+#[tokio::test]
+async fn test_connect_without_encryption() {
+    // Create a router, and a router in transition mode.
+    let node0 = validator(0, 2).await;
+    let node1 = validator_with_noise(0, 2, sample_noise_config().with_plaintext_peers(true)).await;
+    for node in [&node0, &node1] {
+        node.enable_handshake().await;
+        node.tcp().enable_listener().await.unwrap();
+    }
+
+    // Connect to each router from a peer without transport encryption.
+    let peer_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer_ip = peer_listener.local_addr().unwrap();
+    for node in [&node0, &node1] {
+        let stream = TcpStream::connect(node.local_ip()).await.unwrap();
+        let mut framed = Framed::new(stream, PeerCodec::<CurrentNetwork>::default());
+        let request = sample_challenge_request(peer_ip, Message::<CurrentNetwork>::PLAINTEXT_VERSION);
+        framed.send(Message::ChallengeRequest(request)).await.unwrap();
+
+        match tokio::time::timeout(Duration::from_millis(500), framed.try_next()).await {
+            // Ensure the router in transition mode continues the unencrypted handshake.
+            Ok(Ok(Some(Message::ChallengeResponse(..))))
+                if node.minimum_version() < Message::<CurrentNetwork>::VERSION =>
+            {
+                assert!(matches!(framed.try_next().await.unwrap(), Some(Message::ChallengeRequest(..))));
+            }
+            // Ensure the router rejects the peer otherwise.
+            Ok(Ok(Some(Message::Disconnect(disconnect))))
+                if node.minimum_version() == Message::<CurrentNetwork>::VERSION =>
+            {
+                assert_eq!(disconnect.reason, DisconnectReason::OutdatedClientVersion)
+            }
+            _ => panic!("Unexpected handshake response"),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_router::{load_or_generate_keypair, NoiseConfig, PeerPolicy};
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake, Reading, Writing},
+    P2P,
+};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use core::time::Duration;
+use deadline::deadline;
+use std::path::Path;
+
+/// Enables the protocols of the given router, along with its listener.
+async fn enable_protocols(node: &TestRouter<CurrentNetwork>) {
+    node.enable_handshake().await;
+    node.enable_reading().await;
+    node.enable_writing().await;
+    node.enable_disconnect().await;
+    node.tcp().enable_listener().await.unwrap();
+}
+
+/// Starts a validator router with the static keypair persisted in the given file.
+async fn validator_with_identity(path: &Path) -> TestRouter<CurrentNetwork> {
+    let node = validator_with_noise(0, 2, NoiseConfig::new(load_or_generate_keypair(path).unwrap())).await;
+    enable_protocols(&node).await;
+    node
+}
+
+/// Connects the given peer to the given router, and waits for the handshake to complete on both sides.
+async fn connect(node: &TestRouter<CurrentNetwork>, peer: &TestRouter<CurrentNetwork>) {
+    peer.connect(node.local_ip());
+    let (node_ip, peer_ip) = (node.local_ip(), peer.local_ip());
+    let (node_, peer_) = (node.clone(), peer.clone());
+    deadline!(Duration::from_secs(3), move || node_.is_connected(&peer_ip) && peer_.is_connected(&node_ip));
+}
+
+#[tokio::test]
+async fn test_node_id_survives_restart() {
+    let directory = std::env::temp_dir().join(format!("router-identity-restart-{}", std::process::id()));
+    let path = directory.join("noise.key");
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let node = client(0, 2).await;
+    enable_protocols(&node).await;
+
+    // Connect a peer with a persisted identity, and ensure the router observes its node ID.
+    let peer = validator_with_identity(&path).await;
+    let node_id = peer.node_id();
+    connect(&node, &peer).await;
+    assert_eq!(node.get_connected_peer(&peer.local_ip()).unwrap().node_id(), Some(node_id));
+
+    // Restart the peer, which reloads its identity.
+    let peer_ip = peer.local_ip();
+    peer.shut_down().await;
+    let node_ = node.clone();
+    deadline!(Duration::from_secs(3), move || !node_.is_connected(&peer_ip));
+    let peer = validator_with_identity(&path).await;
+
+    // Ensure the router observes the same node ID, under the new address of the peer.
+    connect(&node, &peer).await;
+    assert_ne!(peer.local_ip(), peer_ip);
+    assert_eq!(peer.node_id(), node_id);
+    assert_eq!(node.get_connected_peer(&peer.local_ip()).unwrap().node_id(), Some(node_id));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+async fn test_trusted_node_id_survives_ip_change() {
+    let directory = std::env::temp_dir().join(format!("router-identity-trust-{}", std::process::id()));
+    let path = directory.join("noise.key");
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let node = client(0, 3).await;
+    enable_protocols(&node).await;
+
+    // Trust the node ID of the peer, and connect an unrelated peer.
+    let peer = validator_with_identity(&path).await;
+    let policy = PeerPolicy { trusted: vec![peer.node_id().to_string()], ..Default::default() };
+    node.import_peer_policy(policy, false);
+    let other = client(0, 2).await;
+    enable_protocols(&other).await;
+    connect(&node, &peer).await;
+    connect(&node, &other).await;
+    assert!(node.is_trusted(&peer.local_ip()));
+    assert!(!node.is_trusted(&other.local_ip()));
+
+    // Move the peer to a new address, with the same identity.
+    let peer_ip = peer.local_ip();
+    peer.shut_down().await;
+    let node_ = node.clone();
+    deadline!(Duration::from_secs(3), move || !node_.is_connected(&peer_ip));
+    let peer = validator_with_identity(&path).await;
+    connect(&node, &peer).await;
+
+    // Ensure the peer keeps its standing under its new address, which is not trusted on its own.
+    assert_ne!(peer.local_ip(), peer_ip);
+    assert!(!node.trusted_peers().contains(&peer.local_ip()));
+    assert!(node.is_trusted(&peer.local_ip()));
+    assert!(!node.is_trusted(&peer_ip));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
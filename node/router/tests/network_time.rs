@@ -0,0 +1,92 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_ledger::{MAXIMUM_TIME_ADJUSTMENT_IN_SECS, MINIMUM_TIME_SAMPLES};
+use snarkos_node_messages::{ChallengeRequest, ChallengeResponse, Data, Message, NodeType};
+use snarkos_node_router::{MemoryTransport, PeerTransport};
+use snarkos_node_tcp::{ConnectionSide, P2P};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use std::net::{IpAddr, SocketAddr};
+use time::OffsetDateTime;
+
+/// Connects a scripted peer with the given index to the router, reporting a clock offset by the given seconds.
+async fn connect_skewed_peer(node: &TestRouter<CurrentNetwork>, index: u8, skew: i64) {
+    // Initialize a scripted peer, connected to the router over an in-memory transport, from its own IP address.
+    let peer_ip = SocketAddr::new(IpAddr::from([10, 0, 0, index]), 4133);
+    let (transport, mut peer) = MemoryTransport::pair(node.local_ip(), peer_ip, Default::default());
+
+    let peer_handshake = async move {
+        // Send the challenge request, reporting the skewed clock.
+        let genesis = sample_genesis_block::<CurrentNetwork>();
+        let request = ChallengeRequest::new(peer_ip.port(), NodeType::Client, node.address(), genesis.hash(), 0, 0)
+            .with_timestamp(OffsetDateTime::now_utc().unix_timestamp() + skew);
+        peer.send_message(Message::ChallengeRequest(request)).await.unwrap();
+
+        // Receive the challenge request and the challenge response of the router.
+        let node_request = match peer.next_message().await.unwrap() {
+            Some(Message::ChallengeRequest(request)) => request,
+            message => panic!("Expected a challenge request, found {:?}", message.map(|message| message.name())),
+        };
+        assert!(matches!(peer.next_message().await.unwrap(), Some(Message::ChallengeResponse(..))));
+
+        // Send the challenge response, signing the nonce of the router.
+        let signature = sample_account().sign_bytes(&node_request.nonce.to_le_bytes(), &mut rand::thread_rng());
+        let response =
+            ChallengeResponse { genesis_header: *genesis.header(), signature: Data::Object(signature.unwrap()) };
+        peer.send_message(Message::ChallengeResponse(response)).await.unwrap();
+        peer
+    };
+
+    let (result, _peer) =
+        tokio::join!(node.handshake_with_transport(transport, ConnectionSide::Initiator), peer_handshake);
+    result.unwrap();
+    assert!(node.is_connected(&peer_ip));
+}
+
+#[tokio::test]
+async fn test_network_time_from_skewed_peers() {
+    let node = validator(0, 10).await;
+    node.tcp().enable_listener().await.unwrap();
+
+    // Connect the peers, whose median clock is within the warning threshold.
+    for (index, skew) in [-30, 3600, 10, 3600, 30].into_iter().enumerate() {
+        // Ensure the local clock is not adjusted below the minimum number of samples.
+        assert_eq!(node.network_time().offset(), 0);
+        connect_skewed_peer(&node, index as u8, skew).await;
+    }
+
+    // Ensure the local clock is adjusted by the median offset, allowing for the local clock to tick.
+    let network_time = node.network_time();
+    assert_eq!(network_time.num_samples(), MINIMUM_TIME_SAMPLES);
+    assert!((29..=30).contains(&network_time.offset()), "{}", network_time.offset());
+    assert!(!network_time.is_skewed());
+
+    // Connect more skewed peers, which shift the median beyond the warning threshold.
+    for index in 5..7 {
+        connect_skewed_peer(&node, index, 3600).await;
+    }
+
+    // Ensure the skew is reported, and the adjustment is capped.
+    let network_time = node.network_time();
+    assert_eq!(network_time.num_samples(), 7);
+    assert!((3599..=3600).contains(&network_time.median_offset()), "{}", network_time.median_offset());
+    assert_eq!(network_time.offset(), MAXIMUM_TIME_ADJUSTMENT_IN_SECS);
+    assert!(network_time.is_skewed());
+}
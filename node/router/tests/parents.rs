@@ -0,0 +1,66 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::{Data, Message, UnconfirmedTransaction};
+use snarkos_node_router::Outbound;
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake, Reading, Writing},
+    P2P,
+};
+
+use core::time::Duration;
+use deadline::deadline;
+
+#[tokio::test]
+async fn test_child_relayed_before_parent() {
+    // Sample a chain of a parent, and a child that depends on it.
+    let [parent, child] = sample_transaction_chain();
+    let (parent_id, child_id) = (parent.id(), child.id());
+
+    // Create 2 connected routers.
+    let node0 = client(0, 2).await;
+    let node1 = client(0, 2).await;
+    for node in [&node0, &node1] {
+        node.enable_handshake().await;
+        node.enable_reading().await;
+        node.enable_writing().await;
+        node.enable_disconnect().await;
+        node.tcp().enable_listener().await.unwrap();
+    }
+    node1.connect(node0.local_ip());
+    let (node0_ip, node1_ip) = (node0.local_ip(), node1.local_ip());
+    let (node0_, node1_) = (node0.clone(), node1.clone());
+    deadline!(Duration::from_secs(3), move || node0_.is_connected(&node1_ip) && node1_.is_connected(&node0_ip));
+
+    // Admit the chain into the memory pool of node1.
+    let parent = UnconfirmedTransaction::new(parent_id, Data::Object(parent));
+    let child = UnconfirmedTransaction::new(child_id, Data::Object(child)).with_parents(vec![parent_id]);
+    node1.memory_pool().lock().extend([(parent_id, parent), (child_id, child.clone())]);
+
+    // Relay the child to node0, before its parent.
+    node1.send(node0_ip, Message::UnconfirmedTransaction(child));
+
+    // Ensure node0 fetches the parent from node1, and admits both transactions.
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(5), move || node0_.memory_pool().lock().len() == 2);
+    // Ensure the transactions are admitted in dependency order.
+    assert_eq!(node0.memory_pool().lock().keys().copied().collect::<Vec<_>>(), vec![parent_id, child_id]);
+    assert!(node0.orphans().is_empty());
+    assert_eq!(node0.orphans().num_requests(&node1_ip), 0);
+}
@@ -0,0 +1,209 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::{ChallengeRequest, Message, NodeType, PeerRequest};
+use snarkos_node_router::{BannedPeer, Outbound, PeerBook, PeerPolicy};
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake, Reading, Writing},
+    P2P,
+};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use core::time::Duration;
+use deadline::deadline;
+use std::net::SocketAddr;
+
+/// The number of peer requests injected by the peer.
+const NUM_PEER_REQUESTS: u64 = 5;
+/// The number of peer requests injected by a busy peer, which spans several deltas of its connection.
+const NUM_BUSY_PEER_REQUESTS: u64 = 600;
+
+/// Returns a pair of connected routers.
+async fn sample_connected_pair() -> (TestRouter<CurrentNetwork>, TestRouter<CurrentNetwork>) {
+    let node0 = client(0, 2).await;
+    let node1 = client(0, 2).await;
+    for node in [&node0, &node1] {
+        node.enable_handshake().await;
+        node.enable_reading().await;
+        node.enable_writing().await;
+        node.enable_disconnect().await;
+        node.tcp().enable_listener().await.unwrap();
+    }
+    node1.connect(node0.local_ip());
+    let (node0_ip, node1_ip) = (node0.local_ip(), node1.local_ip());
+    let (node0_, node1_) = (node0.clone(), node1.clone());
+    deadline!(Duration::from_secs(3), move || node0_.is_connected(&node1_ip) && node1_.is_connected(&node0_ip));
+    (node0, node1)
+}
+
+/// Returns the number of peer requests received from the given peer, including those not yet aggregated.
+fn num_peer_requests(node: &TestRouter<CurrentNetwork>, peer_ip: SocketAddr) -> u64 {
+    let aggregated = node.peer_info().into_iter().find(|peer| peer.ip == peer_ip);
+    let aggregated = aggregated.and_then(|peer| peer.messages_received.get("PeerRequest").copied()).unwrap_or(0);
+    let pending = node.peer_state(&peer_ip).map(|state| state.pending_delta());
+    aggregated + pending.and_then(|delta| delta.messages_received.get("PeerRequest").copied()).unwrap_or(0)
+}
+
+#[tokio::test]
+async fn test_peer_statistics_survive_restart() {
+    // Initialize a persisted peer book.
+    let directory = std::env::temp_dir().join(format!("router-peer-book-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("peers.bin");
+    let _ = std::fs::remove_file(&path);
+
+    // Create 2 routers.
+    let node0 = client_with_peer_book(0, 2, PeerBook::open(&path).unwrap()).await;
+    let node1 = client(0, 2).await;
+    for node in [&node0, &node1] {
+        node.enable_handshake().await;
+        node.enable_reading().await;
+        node.enable_writing().await;
+        node.enable_disconnect().await;
+        node.tcp().enable_listener().await.unwrap();
+    }
+
+    // Connect node1 to node0.
+    node1.connect(node0.local_ip());
+    let node0_ip = node0.local_ip();
+    let node1_ip = node1.local_ip();
+    let (node0_, node1_) = (node0.clone(), node1.clone());
+    deadline!(Duration::from_secs(3), move || node0_.is_connected(&node1_ip) && node1_.is_connected(&node0_ip));
+
+    // Inject the peer requests.
+    for _ in 0..NUM_PEER_REQUESTS {
+        node1.send(node0_ip, Message::PeerRequest(PeerRequest));
+    }
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(3), move || {
+        let peer = node0_.peer_info().into_iter().find(|peer| peer.ip == node1_ip);
+        peer.and_then(|peer| peer.messages_received.get("PeerRequest").copied()) == Some(NUM_PEER_REQUESTS)
+    });
+
+    // Ensure the connected peer is listed with its statistics.
+    let info = node0.peer_info();
+    assert_eq!(info.len(), 1);
+    assert_eq!(info[0].node_type, NodeType::Client);
+    assert!(info[0].is_encrypted);
+    assert!(info[0].bytes_received > 0);
+    assert!(node0.peer_history().is_empty());
+
+    // Inject a protocol violation.
+    let request = ChallengeRequest::new(
+        node1_ip.port(),
+        NodeType::Client,
+        sample_account().address(),
+        sample_genesis_block::<CurrentNetwork>().hash(),
+        0,
+        0,
+    );
+    node1.send(node0_ip, Message::ChallengeRequest(request));
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(3), move || {
+        node0_.peer_info().into_iter().any(|peer| peer.ip == node1_ip && peer.misbehavior_score == 1)
+    });
+
+    // Disconnect from the peer.
+    node0.disconnect(node1_ip);
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(3), move || !node0_.is_connected(&node1_ip));
+
+    // Ensure the disconnected peer is listed in the history.
+    assert!(node0.peer_info().is_empty());
+    assert_eq!(node0.peer_history().len(), 1);
+
+    // Restart the router, which writes the peer book.
+    node0.shut_down().await;
+    let node0 = client_with_peer_book(0, 2, PeerBook::open(&path).unwrap()).await;
+
+    // Ensure the statistics match the injected messages.
+    let history = node0.peer_history();
+    assert_eq!(history.len(), 1);
+    let peer = &history[0];
+    assert_eq!(peer.ip, node1_ip);
+    assert_eq!(peer.connections, 1);
+    assert_eq!(peer.node_type, NodeType::Client);
+    assert_eq!(peer.messages_received.get("PeerRequest"), Some(&NUM_PEER_REQUESTS));
+    assert_eq!(peer.messages_received.get("ChallengeRequest"), Some(&1));
+    assert_eq!(peer.messages_sent.get("PeerResponse"), Some(&NUM_PEER_REQUESTS));
+    assert_eq!(peer.misbehavior_score, 1);
+    assert!(peer.bytes_sent > 0);
+    assert!(peer.bytes_received > 0);
+    assert!(peer.first_seen <= peer.last_seen);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+async fn test_peer_statistics_converge() {
+    let (node0, node1) = sample_connected_pair().await;
+    let (node0_ip, node1_ip) = (node0.local_ip(), node1.local_ip());
+
+    // Inject the peer requests of a busy peer.
+    for _ in 0..NUM_BUSY_PEER_REQUESTS {
+        node1.send(node0_ip, Message::PeerRequest(PeerRequest));
+    }
+
+    // Ensure the statistics of the connected peer converge within the aggregation interval.
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(5), move || {
+        let peer = node0_.peer_info().into_iter().find(|peer| peer.ip == node1_ip);
+        peer.and_then(|peer| peer.messages_received.get("PeerRequest").copied()) == Some(NUM_BUSY_PEER_REQUESTS)
+    });
+
+    // Disconnect from the peer.
+    node0.disconnect(node1_ip);
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(3), move || !node0_.is_connected(&node1_ip));
+
+    // Ensure the history holds the exact counts of the connection.
+    let history = node0.peer_history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].messages_received.get("PeerRequest"), Some(&NUM_BUSY_PEER_REQUESTS));
+    assert_eq!(history[0].messages_sent.get("PeerResponse"), Some(&NUM_BUSY_PEER_REQUESTS));
+    assert!(node0.peer_state(&node1_ip).is_none());
+}
+
+#[tokio::test]
+async fn test_banned_peer_statistics_converge() {
+    let (node0, node1) = sample_connected_pair().await;
+    let (node0_ip, node1_ip) = (node0.local_ip(), node1.local_ip());
+
+    // Inject the peer requests, and wait until they are received, without waiting for their aggregation.
+    for _ in 0..NUM_PEER_REQUESTS {
+        node1.send(node0_ip, Message::PeerRequest(PeerRequest));
+    }
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(3), move || num_peer_requests(&node0_, node1_ip) == NUM_PEER_REQUESTS);
+
+    // Ban the connected peer.
+    let policy = PeerPolicy {
+        banned: vec![BannedPeer { ip: node1_ip.to_string(), expires_at: None, reason: "spam".to_string() }],
+        ..Default::default()
+    };
+    node0.import_peer_policy(policy, false);
+    let node0_ = node0.clone();
+    deadline!(Duration::from_secs(3), move || !node0_.is_connected(&node1_ip));
+
+    // Ensure the final delta of the banned peer is aggregated into its statistics.
+    assert!(node0.is_banned(&node1_ip));
+    let history = node0.peer_history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].messages_received.get("PeerRequest"), Some(&NUM_PEER_REQUESTS));
+}
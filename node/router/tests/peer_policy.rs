@@ -0,0 +1,92 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_router::{BannedPeer, PeerBook, PeerPolicy, PinnedPeer, PolicyEntryStatus};
+use snarkos_node_tcp::{protocols::Handshake, P2P};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use core::time::Duration;
+use deadline::deadline;
+
+#[tokio::test]
+async fn test_banned_peer_is_refused_after_import() {
+    // Initialize a persisted peer book for the importing router.
+    let directory = std::env::temp_dir().join(format!("router-peer-policy-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("peers.bin");
+    let _ = std::fs::remove_file(&path);
+
+    // Create 4 routers: the exporting router, the importing router, the banned peer, and another peer.
+    let node0 = client(0, 4).await;
+    let node1 = client_with_peer_book(0, 4, PeerBook::open(&path).unwrap()).await;
+    let node2 = client(0, 4).await;
+    let node3 = client(0, 4).await;
+    for node in [&node0, &node1, &node2, &node3] {
+        node.enable_handshake().await;
+        node.tcp().enable_listener().await.unwrap();
+    }
+    let (node1_ip, node2_ip, node3_ip) = (node1.local_ip(), node2.local_ip(), node3.local_ip());
+
+    // Ban the peer and pin the key of the other peer on the exporting router,
+    // along with an expired ban and a malformed entry.
+    let policy = PeerPolicy {
+        banned: vec![
+            BannedPeer { ip: node2_ip.to_string(), expires_at: None, reason: "spam".to_string() },
+            BannedPeer { ip: node3_ip.to_string(), expires_at: Some(0), reason: "expired".to_string() },
+        ],
+        pinned: vec![PinnedPeer { ip: node3_ip.to_string(), public_key: hex::encode(node3.noise_public_key()) }],
+        whitelisted: vec!["localhost".to_string()],
+        trusted: vec![],
+    };
+    let statuses = node0.import_peer_policy(policy, false).into_iter().map(|result| result.status).collect::<Vec<_>>();
+    assert_eq!(statuses[0], PolicyEntryStatus::Applied);
+    assert_eq!(statuses[1], PolicyEntryStatus::SkippedExpired);
+    assert_eq!(statuses[2], PolicyEntryStatus::Applied);
+    assert!(matches!(statuses[3], PolicyEntryStatus::Invalid(_)));
+
+    // Export the policy as JSON, and import it into the other router.
+    let document = serde_json::to_string(&node0.export_peer_policy()).unwrap();
+    let results = node1.import_peer_policy(serde_json::from_str(&document).unwrap(), false);
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.status == PolicyEntryStatus::Applied));
+    assert!(node1.is_banned(&node2_ip));
+    assert!(!node1.is_banned(&node3_ip));
+
+    // Ensure the banned peer is refused on connect, in both directions.
+    node2.connect(node1_ip);
+    node1.connect(node2_ip);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!node1.is_connected(&node2_ip));
+    assert!(!node2.is_connected(&node1_ip));
+
+    // Ensure the other peer is still accepted, with its pinned key.
+    node3.connect(node1_ip);
+    let (node1_, node3_) = (node1.clone(), node3.clone());
+    deadline!(Duration::from_secs(3), move || node1_.is_connected(&node3_ip) && node3_.is_connected(&node1_ip));
+
+    // Ensure the policy was written to the peer book, without waiting for the next flush.
+    assert!(PeerBook::<CurrentNetwork>::open(&path).unwrap().is_banned(&node2_ip));
+
+    // Ensure a replacement lifts the ban.
+    node1.import_peer_policy(PeerPolicy::default(), true);
+    assert!(!node1.is_banned(&node2_ip));
+    assert_eq!(node1.export_peer_policy(), PeerPolicy::default());
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
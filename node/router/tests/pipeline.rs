@@ -0,0 +1,103 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use deadline::deadline;
+use peak_alloc::PeakAlloc;
+use snarkos_node_router::{BlockPipeline, PipelineItem, PipelineStages};
+
+use anyhow::{ensure, Result};
+use core::time::Duration;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+#[global_allocator]
+static PEAK_ALLOC: PeakAlloc = PeakAlloc;
+
+/// The number of blocks in the synthetic chain.
+const NUM_BLOCKS: u32 = 1_000;
+/// The size of each synthetic block, in bytes.
+const BLOCK_SIZE: usize = 64 * 1024;
+/// The byte budget of the pipeline, which holds a small fraction of the chain.
+const BYTE_BUDGET: usize = 2 * 1024 * 1024;
+
+/// A synthetic block, with a payload of a fixed size.
+struct SampleBlock {
+    height: u32,
+    payload: Vec<u8>,
+}
+
+impl PipelineItem for SampleBlock {
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn num_bytes(&self) -> usize {
+        self.payload.len()
+    }
+}
+
+/// The stages, with an artificially slow commit stage, which record the committed heights.
+#[derive(Clone, Default)]
+struct SlowStages {
+    committed: Arc<Mutex<Vec<u32>>>,
+}
+
+impl PipelineStages<SampleBlock> for SlowStages {
+    fn verify(&self, block: &SampleBlock) -> Result<()> {
+        ensure!(block.payload.iter().all(|byte| *byte == block.height as u8), "Block {} is corrupted", block.height);
+        Ok(())
+    }
+
+    fn commit(&self, block: SampleBlock) -> Result<()> {
+        // Simulate a slow write to storage.
+        std::thread::sleep(Duration::from_millis(1));
+        self.committed.lock().push(block.height);
+        Ok(())
+    }
+
+    fn reject(&self, height: u32, error: anyhow::Error) {
+        panic!("Block {height} was rejected - {error}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pipeline_memory_is_bounded() {
+    let stages = SlowStages::default();
+    let committed = stages.committed.clone();
+    committed.lock().reserve(NUM_BLOCKS as usize);
+
+    // Register the heap use before the sync.
+    let heap_before_sync = PEAK_ALLOC.current_usage();
+
+    // Sync the synthetic chain, which is many times larger than the byte budget.
+    let pipeline = BlockPipeline::start(1, BYTE_BUDGET, stages, Default::default());
+    let mut was_stalled = false;
+    for height in 1..=NUM_BLOCKS {
+        let block = SampleBlock { height, payload: vec![height as u8; BLOCK_SIZE] };
+        assert!(pipeline.push(block).await);
+        // Ensure the queues stay within their share of the byte budget.
+        let (verify_bytes, commit_bytes) = pipeline.num_bytes();
+        assert!(verify_bytes <= BYTE_BUDGET / 2 && commit_bytes <= BYTE_BUDGET / 2);
+        was_stalled |= verify_bytes + BLOCK_SIZE > BYTE_BUDGET / 2;
+    }
+    deadline!(Duration::from_secs(30), move || committed.lock().len() == NUM_BLOCKS as usize);
+
+    // Ensure the producer was held back by the slow commit stage.
+    assert!(was_stalled);
+    // Ensure the peak heap use stays within the byte budget, with room for the block being pushed and the runtime.
+    let peak_during_sync = PEAK_ALLOC.peak_usage().saturating_sub(heap_before_sync);
+    assert!(peak_during_sync < BYTE_BUDGET + 4 * BLOCK_SIZE, "Peak heap use of {peak_during_sync} bytes");
+}
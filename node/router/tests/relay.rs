@@ -0,0 +1,130 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_ledger::{Event, EvictedTransaction};
+use snarkos_node_messages::{Data, Message, UnconfirmedTransaction};
+use snarkos_node_router::{Outbound, PeerTransport, Routing, RELAY_BUDGET_PER_FLUSH};
+use snarkos_node_tcp::P2P;
+use snarkvm::prelude::{Field, Network, Testnet3 as CurrentNetwork, Uniform};
+
+use core::time::Duration;
+use parking_lot::Mutex;
+use std::{net::SocketAddr, sync::Arc};
+
+type TransactionID = <CurrentNetwork as Network>::TransactionID;
+
+/// The address of the peer that floods the node with transactions.
+fn flooder() -> SocketAddr {
+    "127.0.0.1:4200".parse().unwrap()
+}
+
+/// Returns the given number of messages of sample transactions, each with a random ID.
+fn sample_messages(num_messages: usize) -> Vec<UnconfirmedTransaction<CurrentNetwork>> {
+    let transaction = sample_genesis_block::<CurrentNetwork>().transactions().iter().next().unwrap().clone();
+    let message = UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction));
+    let rng = &mut rand::thread_rng();
+    (0..num_messages)
+        .map(|_| UnconfirmedTransaction { transaction_id: Field::<CurrentNetwork>::rand(rng).into(), ..message.clone() })
+        .collect()
+}
+
+/// Connects the given router to a scripted peer over an in-memory transport, and returns the peer IP,
+/// along with the IDs of the unconfirmed transactions it receives, in order.
+async fn connect_scripted_peer(node: &TestRouter<CurrentNetwork>) -> (SocketAddr, Arc<Mutex<Vec<TransactionID>>>) {
+    let peer_node = client(0, 1).await;
+    peer_node.tcp().enable_listener().await.unwrap();
+    let peer_ip = peer_node.local_ip();
+
+    // Connect the router to the scripted peer, as an outbound connection of the router.
+    let (transport, mut peer) = node.handshake_in_memory(&peer_node, Default::default()).await.unwrap();
+    node.attach_memory_transport(transport).unwrap();
+    assert!(node.router().is_connected(&peer_ip));
+
+    // Record the ID of each unconfirmed transaction received by the peer.
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_clone = received.clone();
+    tokio::spawn(async move {
+        while let Ok(Some(message)) = peer.next_message().await {
+            if let Message::UnconfirmedTransaction(message) = message {
+                received_clone.lock().push(message.transaction_id);
+            }
+        }
+    });
+    (peer_ip, received)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_high_fee_transaction_is_relayed_through_a_flood() {
+    let node = client(0, 2).await;
+    node.tcp().enable_listener().await.unwrap();
+    let (peer_ip, received) = connect_scripted_peer(&node).await;
+
+    // Saturate the relay of the node with transactions of a low fee rate, then queue one with a high fee rate.
+    let flood = sample_messages(20 * RELAY_BUDGET_PER_FLUSH);
+    for message in flood {
+        node.queue_relay(message, 0.01, &[peer_ip], &[flooder()]);
+    }
+    let high_fee = sample_messages(1).remove(0);
+    node.queue_relay(high_fee.clone(), 25.0, &[peer_ip], &[flooder()]);
+    node.initialize_relay();
+
+    // Ensure the transaction with a high fee rate is relayed in the first flush, within its budget.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let received = received.lock().clone();
+    assert_eq!(received.len(), RELAY_BUDGET_PER_FLUSH);
+    assert_eq!(received[0], high_fee.transaction_id);
+    assert_eq!(node.router().relay_queues().depth(&peer_ip), 19 * RELAY_BUDGET_PER_FLUSH + 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_mined_and_evicted_transactions_are_purged() {
+    let node = client(0, 2).await;
+    node.tcp().enable_listener().await.unwrap();
+    let (peer_ip, received) = connect_scripted_peer(&node).await;
+    node.initialize_relay();
+
+    // Queue more transactions than a flush relays, and commit a block with some of them before the flush.
+    let messages = sample_messages(2 * RELAY_BUDGET_PER_FLUSH);
+    for message in &messages {
+        node.queue_relay(message.clone(), 1.0, &[peer_ip], &[flooder()]);
+    }
+    let mined = messages[..RELAY_BUDGET_PER_FLUSH].iter().map(|message| message.transaction_id).collect::<Vec<_>>();
+    let genesis = sample_genesis_block::<CurrentNetwork>();
+    node.router().event_bus().publish(Event::BlockConnected {
+        height: 1,
+        hash: genesis.hash(),
+        transaction_ids: mined.clone(),
+    });
+    let evicted = messages[RELAY_BUDGET_PER_FLUSH].transaction_id;
+    node.router().event_bus().publish(Event::TransactionEvicted(EvictedTransaction {
+        transaction_id: evicted,
+        reason: "expired".to_string(),
+        replaced_by: None,
+        expired: true,
+    }));
+
+    // Ensure only the transactions that remain in the memory pool are relayed.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let received = received.lock().clone();
+    assert_eq!(received.len(), RELAY_BUDGET_PER_FLUSH - 1);
+    assert!(received.iter().all(|transaction_id| !mined.contains(transaction_id) && *transaction_id != evicted));
+
+    let stats = node.router().relay_queues().stats()[&peer_ip];
+    assert_eq!((stats.depth, stats.relayed, stats.purged), (0, RELAY_BUDGET_PER_FLUSH as u64 - 1, 1 + mined.len() as u64));
+}
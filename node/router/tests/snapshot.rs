@@ -0,0 +1,121 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_router::{Routing, DEFAULT_MAX_SNAPSHOT_STREAMS, MAX_SNAPSHOT_ATTEMPTS};
+use snarkos_node_tcp::{
+    protocols::{Disconnect, Handshake, Reading, Writing},
+    P2P,
+};
+use snarkvm::prelude::{Field, Network, Testnet3 as CurrentNetwork};
+
+use core::time::Duration;
+
+/// The height of the chain of the serving router.
+const CHAIN_HEIGHT: u32 = 300;
+
+/// Returns a chain of synthetic block hashes, from height 1 up to the given height.
+fn sample_chain(height: u32) -> Vec<<CurrentNetwork as Network>::BlockHash> {
+    (1..=height).map(|height| Field::<CurrentNetwork>::from_u32(height).into()).collect()
+}
+
+/// Enables the protocols of the given router, and its listener.
+async fn enable(node: &TestRouter<CurrentNetwork>) {
+    node.enable_handshake().await;
+    node.enable_reading().await;
+    node.enable_writing().await;
+    node.enable_disconnect().await;
+    node.tcp().enable_listener().await.unwrap();
+}
+
+/// Initializes a router serving a snapshot of `CHAIN_HEIGHT` blocks, with the given maximum number of streams.
+async fn serving_router(max_streams: usize) -> TestRouter<CurrentNetwork> {
+    let node = client(0, 2).await;
+    node.snapshots().set_max_streams(max_streams);
+    node.chain().set_hashes(sample_chain(CHAIN_HEIGHT));
+    enable(&node).await;
+    node
+}
+
+/// Initializes a router with an empty chain, which trusts the given router and pins its transport key,
+/// and connects it to the given router.
+async fn requesting_router(server: &TestRouter<CurrentNetwork>) -> TestRouter<CurrentNetwork> {
+    let pinned_key = server.noise_public_key().to_vec();
+    let noise = sample_noise_config().with_pinned_keys([(server.local_ip(), pinned_key)].into_iter().collect());
+    let node = client_with_trusted_peers(0, 2, &[server.local_ip()], noise).await;
+    enable(&node).await;
+    node.connect(server.local_ip());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(node.is_encrypted(&server.local_ip()));
+    node
+}
+
+#[tokio::test]
+async fn test_bootstrap_from_snapshot() {
+    let node0 = serving_router(DEFAULT_MAX_SNAPSHOT_STREAMS).await;
+    let node1 = requesting_router(&node0).await;
+
+    // Ensure the snapshot is imported, and the tips of the routers match.
+    assert_eq!(node1.bootstrap_from_snapshot(node0.local_ip()).await.unwrap(), CHAIN_HEIGHT);
+    assert_eq!(node1.chain().hashes(), node0.chain().hashes());
+    assert_eq!(node1.chain().hashes().last(), node0.chain().hashes().last());
+
+    // Ensure the download ended, and the stream remains open until it times out or the peer disconnects.
+    assert!(node1.snapshots().download_peer().is_none());
+    assert_eq!(node0.snapshots().num_streams(), 1);
+    node1.disconnect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node0.snapshots().num_streams(), 0);
+}
+
+#[tokio::test]
+async fn test_bootstrap_retries_corrupted_chunk() {
+    let node0 = serving_router(DEFAULT_MAX_SNAPSHOT_STREAMS).await;
+    let node1 = requesting_router(&node0).await;
+
+    // Ensure a corrupted chunk is requested again, and the snapshot is imported.
+    node0.chain().corrupt_chunks(1);
+    assert_eq!(node1.bootstrap_from_snapshot(node0.local_ip()).await.unwrap(), CHAIN_HEIGHT);
+    assert_eq!(node1.chain().hashes(), node0.chain().hashes());
+
+    // Ensure the download fails once every attempt of a chunk is corrupted, without importing the chunk.
+    let node2 = requesting_router(&node0).await;
+    node0.chain().corrupt_chunks(MAX_SNAPSHOT_ATTEMPTS);
+    assert!(node2.bootstrap_from_snapshot(node0.local_ip()).await.is_err());
+    assert!(node2.chain().hashes().is_empty());
+    assert!(node2.snapshots().download_peer().is_none());
+}
+
+#[tokio::test]
+async fn test_bootstrap_requires_trusted_server() {
+    // Ensure a snapshot is not requested from a peer that is not trusted.
+    let node0 = serving_router(DEFAULT_MAX_SNAPSHOT_STREAMS).await;
+    let node1 = client(0, 2).await;
+    enable(&node1).await;
+    node1.connect(node0.local_ip());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(node1.number_of_connected_peers(), 1);
+    assert!(node1.bootstrap_from_snapshot(node0.local_ip()).await.is_err());
+    assert_eq!(node0.snapshots().num_streams(), 0);
+
+    // Ensure a snapshot is not requested from a trusted peer that does not serve snapshots.
+    let node2 = serving_router(0).await;
+    let node3 = requesting_router(&node2).await;
+    assert!(node3.bootstrap_from_snapshot(node2.local_ip()).await.is_err());
+    assert!(node3.chain().hashes().is_empty());
+}
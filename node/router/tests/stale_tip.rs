@@ -0,0 +1,166 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::helpers::block_locators::test_helpers::sample_block_locators;
+use snarkos_node_router::{Heartbeat, Routing, SeedResolver, STALE_TIP_ESCALATION_FACTOR};
+use snarkos_node_tcp::protocols::{Disconnect, Handshake, Writing};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use anyhow::Result;
+use core::time::Duration;
+use deadline::deadline;
+use std::{net::SocketAddr, sync::Arc};
+
+/// The stale tip threshold of the node, which stands in for a multiple of the target block time.
+const STALE_TIP_THRESHOLD: Duration = Duration::from_millis(300);
+
+/// A resolver that resolves every hostname to the same address.
+struct FixedResolver(SocketAddr);
+
+#[async_trait::async_trait]
+impl SeedResolver for FixedResolver {
+    async fn resolve(&self, _host: &str, _port: u16) -> Result<Vec<SocketAddr>> {
+        Ok(vec![self.0])
+    }
+}
+
+/// Returns the given number of listening validators.
+async fn sample_peers(num_peers: usize) -> Vec<TestRouter<CurrentNetwork>> {
+    let mut peers = Vec::with_capacity(num_peers);
+    for _ in 0..num_peers {
+        let peer = validator(0, 10).await;
+        peer.enable_handshake().await;
+        peer.enable_listener().await;
+        peers.push(peer);
+    }
+    peers
+}
+
+/// Returns a node at genesis, connected to the given peers, with the stale tip threshold of the tests.
+async fn sample_node(peers: &[TestRouter<CurrentNetwork>]) -> TestRouter<CurrentNetwork> {
+    let node = client(0, 10).await;
+    node.enable_handshake().await;
+    node.enable_writing().await;
+    node.enable_disconnect().await;
+    node.enable_listener().await;
+    node.sync().set_stale_tip_threshold(STALE_TIP_THRESHOLD);
+    node.sync().insert_canon_locators(sample_block_locators(0)).unwrap();
+
+    for peer in peers {
+        node.connect(peer.local_ip());
+    }
+    let (node_, num_peers) = (node.clone(), peers.len());
+    deadline!(Duration::from_secs(3), move || node_.number_of_connected_peers() == num_peers);
+    node
+}
+
+#[tokio::test]
+async fn test_stale_tip_rotates_peers() {
+    // Initialize the partition that holds back its blocks, and the partition that advances.
+    let withholding = sample_peers(2).await;
+    let advanced = sample_peers(2).await;
+    let node = sample_node(&withholding).await;
+    let sync = node.sync();
+
+    // One withholding peer advertises the tip of the advanced partition, without serving its blocks.
+    sync.update_peer_locators(withholding[0].local_ip(), sample_block_locators(100)).unwrap();
+    sync.set_peer_serves_blocks(withholding[0].local_ip(), false);
+    sync.update_peer_locators(withholding[1].local_ip(), sample_block_locators(0)).unwrap();
+    // The node learns of the advanced partition.
+    node.insert_candidate_peers(&advanced.iter().map(|peer| peer.local_ip()).collect::<Vec<_>>());
+
+    // Ensure the tip is not stale, before the threshold.
+    node.handle_stale_tip();
+    assert!(sync.stale_tip().is_none());
+    assert!(withholding.iter().all(|peer| node.is_connected(&peer.local_ip())));
+
+    // Ensure the tip is stale once the threshold elapsed, and the node rotates the withholding peers.
+    tokio::time::sleep(STALE_TIP_THRESHOLD).await;
+    let stale_tip = sync.stale_tip().unwrap();
+    assert_eq!((stale_tip.canon_height, stale_tip.peer_height), (0, 100));
+    assert!(!stale_tip.is_escalated);
+    node.handle_stale_tip();
+
+    let (node_, advanced_ips) = (node.clone(), advanced.iter().map(|peer| peer.local_ip()).collect::<Vec<_>>());
+    deadline!(Duration::from_secs(3), move || advanced_ips.iter().all(|peer_ip| node_.is_connected(peer_ip)));
+    assert!(withholding.iter().all(|peer| !node.is_connected(&peer.local_ip())));
+
+    // Ensure the node requests the blocks from the advanced partition, once it advertises its tip.
+    for peer in &advanced {
+        sync.update_peer_locators(peer.local_ip(), sample_block_locators(100)).unwrap();
+    }
+    let requests = sync.prepare_block_requests();
+    assert!(!requests.is_empty());
+    for (_, (_, _, sync_ips)) in requests {
+        assert!(sync_ips.iter().all(|peer_ip| advanced.iter().any(|peer| peer.local_ip() == *peer_ip)));
+    }
+
+    // Ensure the tip is no longer stale once the node catches up, and the node keeps its peers.
+    sync.insert_canon_locators(sample_block_locators(100)).unwrap();
+    assert!(sync.stale_tip().is_none());
+    node.handle_stale_tip();
+    assert!(!sync.end_stale_tip());
+    assert!(advanced.iter().all(|peer| node.is_connected(&peer.local_ip())));
+}
+
+#[tokio::test]
+async fn test_slow_block_is_not_a_stale_tip() {
+    let peers = sample_peers(2).await;
+    let node = sample_node(&peers).await;
+    let sync = node.sync();
+    for peer in &peers {
+        sync.update_peer_locators(peer.local_ip(), sample_block_locators(0)).unwrap();
+    }
+    node.insert_candidate_peers(&[SocketAddr::from(([127, 0, 0, 1], 4140))]);
+
+    // Ensure the node keeps its peers past the threshold, as no peer claims a higher tip.
+    tokio::time::sleep(STALE_TIP_THRESHOLD * 2).await;
+    for _ in 0..3 {
+        node.handle_stale_tip();
+        assert!(sync.stale_tip().is_none());
+        assert!(peers.iter().all(|peer| node.is_connected(&peer.local_ip())));
+    }
+}
+
+#[tokio::test]
+async fn test_stale_tip_escalates_to_dns_seeds() {
+    let peers = sample_peers(1).await;
+    let node = sample_node(&peers).await;
+    let sync = node.sync();
+    sync.update_peer_locators(peers[0].local_ip(), sample_block_locators(100)).unwrap();
+
+    // Resolve the DNS seeds, so that the next resolution is held back.
+    let seed_ip = SocketAddr::from(([10, 0, 0, 1], 4133));
+    node.dns_seeds().set_hosts(vec!["seed".to_string()]);
+    node.dns_seeds().set_resolver(Arc::new(FixedResolver(seed_ip)));
+    assert!(node.dns_seeds().begin_resolution());
+
+    // Ensure the DNS seeds are not re-queried, before the escalation threshold.
+    tokio::time::sleep(STALE_TIP_THRESHOLD).await;
+    node.handle_stale_tip();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!node.candidate_peers().contains(&seed_ip));
+
+    // Ensure the DNS seeds are re-queried once the tip is stale beyond the escalation threshold.
+    tokio::time::sleep(STALE_TIP_THRESHOLD * STALE_TIP_ESCALATION_FACTOR).await;
+    assert!(sync.stale_tip().unwrap().is_escalated);
+    node.handle_stale_tip();
+    let node_ = node.clone();
+    deadline!(Duration::from_secs(3), move || node_.candidate_peers().contains(&seed_ip));
+}
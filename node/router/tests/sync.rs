@@ -0,0 +1,114 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::helpers::block_locators::test_helpers::sample_block_locators;
+use snarkos_node_router::{Routing, SerialBlock, SyncPeerClass, MAX_BLOCK_REQUESTS, MIN_THROUGHPUT_SAMPLES};
+use snarkos_node_tcp::protocols::Handshake;
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use core::time::Duration;
+use deadline::deadline;
+use indexmap::indexset;
+
+/// The height below which the pruned peer pruned the bodies of its blocks.
+const PRUNED_HEIGHT: u32 = 20;
+
+/// Returns a syncing node, connected to an archival peer and to a peer that pruned the blocks below `PRUNED_HEIGHT`.
+async fn sample_sync_peers() -> (TestRouter<CurrentNetwork>, TestRouter<CurrentNetwork>, TestRouter<CurrentNetwork>) {
+    let node = client(0, 2).await;
+    let archival = validator(0, 2).await;
+    let pruned = validator(0, 2).await;
+    pruned.set_pruned_height(PRUNED_HEIGHT);
+    for router in [&node, &archival, &pruned] {
+        router.enable_handshake().await;
+        router.enable_listener().await;
+    }
+
+    // Connect the node to both peers.
+    node.connect(archival.local_ip());
+    node.connect(pruned.local_ip());
+    let node_ = node.clone();
+    deadline!(Duration::from_secs(3), move || node_.number_of_connected_peers() == 2);
+
+    (node, archival, pruned)
+}
+
+#[tokio::test]
+async fn test_pruned_blocks_are_requested_from_archival_peers() {
+    let (node, archival, pruned) = sample_sync_peers().await;
+    let (archival_ip, pruned_ip) = (archival.local_ip(), pruned.local_ip());
+
+    // Ensure the node classifies its peers from the capabilities they advertised in the handshake.
+    let sync = node.sync();
+    assert_eq!(sync.get_peer_class(&archival_ip), SyncPeerClass::Archival);
+    assert_eq!(sync.get_peer_class(&pruned_ip), SyncPeerClass::Pruned);
+
+    // Advance both peers ahead of the node, which is at genesis.
+    sync.insert_canon_locators(sample_block_locators(0)).unwrap();
+    sync.update_peer_locators(archival_ip, sample_block_locators(100)).unwrap();
+    sync.update_peer_locators(pruned_ip, sample_block_locators(100)).unwrap();
+
+    // Ensure the old blocks are only requested from the archival peer, and the newer blocks from both peers.
+    let requests = sync.prepare_block_requests();
+    assert_eq!(requests.len(), MAX_BLOCK_REQUESTS);
+    for (height, (_, _, sync_ips)) in requests {
+        match height < PRUNED_HEIGHT {
+            true => assert_eq!(sync_ips, indexset![archival_ip]),
+            false => assert_eq!(sync_ips, indexset![archival_ip, pruned_ip]),
+        }
+    }
+
+    // Ensure the source assignments report the class and pruned height of each peer.
+    let sources = sync.get_sync_sources();
+    assert_eq!(sources.len(), 2);
+    assert!(sources.iter().any(|source| source.peer_ip == archival_ip && source.pruned_height == 0));
+    assert!(sources.iter().any(|source| source.peer_ip == pruned_ip && source.pruned_height == PRUNED_HEIGHT));
+}
+
+#[tokio::test]
+async fn test_throttled_peer_is_demoted() {
+    let (node, fast, slow) = sample_sync_peers().await;
+    let (fast_ip, slow_ip) = (fast.local_ip(), slow.local_ip());
+
+    // Demote the peers that deliver below 8 KiB/s.
+    let sync = node.sync();
+    sync.set_throughput_floor(8 * 1024);
+
+    // Deliver a 1 KiB block from each peer repeatedly, with the slow peer throttled to 4 KiB/s.
+    let block = sample_genesis_block::<CurrentNetwork>();
+    for _ in 0..MIN_THROUGHPUT_SAMPLES {
+        // Ensure neither peer is demoted before its throughput is measured.
+        assert!(!sync.is_demoted(&fast_ip));
+        assert!(!sync.is_demoted(&slow_ip));
+
+        for peer_ip in [fast_ip, slow_ip] {
+            sync.insert_block_request(0, (None, None, indexset![peer_ip])).unwrap();
+            if peer_ip == slow_ip {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+            sync.insert_block_response(peer_ip, SerialBlock::new(block.clone(), 1024)).unwrap();
+            sync.remove_block_request(0);
+        }
+    }
+
+    // Ensure the slow peer is demoted once its throughput is measured, while the fast peer is not.
+    assert!(sync.is_demoted(&slow_ip));
+    assert!(!sync.is_demoted(&fast_ip));
+    assert!(sync.get_peer_throughput(&fast_ip).unwrap() > sync.throughput_floor() as f64);
+}
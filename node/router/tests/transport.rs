@@ -0,0 +1,180 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+use common::*;
+
+use snarkos_node_messages::{
+    BlockRequest,
+    BlockResponse,
+    ChallengeRequest,
+    Data,
+    DataBlocks,
+    DisconnectReason,
+    Message,
+    PeerResponse,
+    Ping,
+};
+use snarkos_node_router::{Heartbeat, LinkConditions, MemoryTransport, Outbound, PeerTransport};
+use snarkos_node_tcp::{ConnectionSide, P2P};
+use snarkvm::prelude::Testnet3 as CurrentNetwork;
+
+use core::time::Duration;
+
+/// The duration in seconds after which the router evicts a connected peer that has not sent a message.
+const RADIO_SILENCE_IN_SECS: u64 = 150;
+
+/// Receives the messages over the given transport until it is closed, and returns their names.
+async fn drain(transport: &mut MemoryTransport<CurrentNetwork>) -> Vec<String> {
+    let mut names = vec![];
+    while let Some(message) = transport.next_message().await.unwrap() {
+        names.push(message.name());
+    }
+    names
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_connect_in_memory() {
+    // Create 2 routers.
+    let node0 = client(0, 1).await;
+    let node1 = client(0, 1).await;
+    node0.tcp().enable_listener().await.unwrap();
+    node1.tcp().enable_listener().await.unwrap();
+
+    // Connect node0 to node1 over an in-memory transport, and let the first pings be exchanged.
+    node0.connect_in_memory(&node1, Default::default()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Ensure the routers are connected, without any TCP connection.
+    assert!(node0.is_connected(&node1.local_ip()));
+    assert!(node1.is_connected(&node0.local_ip()));
+    assert!(!node0.is_encrypted(&node1.local_ip()));
+    assert_eq!(node0.tcp().num_connected(), 0);
+    assert_eq!(node1.tcp().num_connected(), 0);
+
+    // Disconnect node0 from node1, and ensure node1 observes the closed transport.
+    node0.router().disconnect(node1.local_ip());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(node0.number_of_connected_peers(), 0);
+    assert_eq!(node1.number_of_connected_peers(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_handshake_version_mismatch() {
+    let node = validator(0, 1).await;
+    node.tcp().enable_listener().await.unwrap();
+
+    // Initialize a scripted peer, connected to the router over an in-memory transport.
+    let peer_ip = "127.0.0.1:4999".parse().unwrap();
+    let (transport, mut peer) = MemoryTransport::pair(node.local_ip(), peer_ip, Default::default());
+
+    // Send a challenge request on an outdated version.
+    let genesis = sample_genesis_block::<CurrentNetwork>();
+    let mut request = ChallengeRequest::new(peer_ip.port(), node.node_type(), node.address(), genesis.hash(), 0, 0);
+    request.version = node.minimum_version() - 1;
+    peer.send_message(Message::ChallengeRequest(request)).await.unwrap();
+
+    // Ensure the router rejects the handshake with the reason, and does not connect to the peer.
+    assert!(node.handshake_with_transport(transport, ConnectionSide::Initiator).await.is_err());
+    match peer.next_message().await.unwrap() {
+        Some(Message::Disconnect(disconnect)) => assert_eq!(disconnect.reason, DisconnectReason::OutdatedClientVersion),
+        message => panic!("Expected a disconnect, found {:?}", message.map(|message| message.name())),
+    }
+    assert!(!node.is_connected(&peer_ip));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_delayed_pong_eviction() {
+    let node = validator(0, 1).await;
+    let peer_node = client(0, 1).await;
+    node.tcp().enable_listener().await.unwrap();
+    peer_node.tcp().enable_listener().await.unwrap();
+
+    // Connect the router to a scripted peer over an in-memory transport.
+    let (transport, mut peer) = node.handshake_in_memory(&peer_node, Default::default()).await.unwrap();
+    node.attach_memory_transport(transport).unwrap();
+    let peer_ip = peer_node.local_ip();
+
+    // Send a keep-alive that is delayed, yet arrives within the radio silence.
+    let latency = Duration::from_secs(RADIO_SILENCE_IN_SECS - 50);
+    peer.link().set_conditions(LinkConditions { latency, ..Default::default() });
+    peer.send_message(Message::Ping(Ping::new(peer_node.node_type(), None))).await.unwrap();
+
+    // Ensure the router keeps the peer after the radio silence since the handshake, as the keep-alive arrived.
+    tokio::time::sleep(Duration::from_secs(RADIO_SILENCE_IN_SECS + 1)).await;
+    node.remove_stale_connected_peers();
+    assert!(node.is_connected(&peer_ip));
+
+    // Send a keep-alive that is delayed past the radio silence since the first keep-alive arrived.
+    let latency = Duration::from_secs(RADIO_SILENCE_IN_SECS + 50);
+    peer.link().set_conditions(LinkConditions { latency, ..Default::default() });
+    peer.send_message(Message::Ping(Ping::new(peer_node.node_type(), None))).await.unwrap();
+
+    // Ensure the router evicts the peer as soon as the radio silence since the first keep-alive is over.
+    tokio::time::sleep(Duration::from_secs(RADIO_SILENCE_IN_SECS - 51)).await;
+    node.remove_stale_connected_peers();
+    assert!(node.is_connected(&peer_ip));
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    node.remove_stale_connected_peers();
+    assert!(!node.is_connected(&peer_ip));
+
+    // Ensure the peer received the reply to the first keep-alive, before the transport was closed.
+    assert_eq!(drain(&mut peer).await, vec!["Pong".to_string()]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_block_response_across_reordered_messages() {
+    let node = validator(0, 1).await;
+    let peer_node = client(0, 1).await;
+    node.tcp().enable_listener().await.unwrap();
+    peer_node.tcp().enable_listener().await.unwrap();
+
+    // Connect the router to a scripted peer over an in-memory transport.
+    let (transport, mut peer) = node.handshake_in_memory(&peer_node, Default::default()).await.unwrap();
+    node.attach_memory_transport(transport).unwrap();
+    let peer_ip = peer_node.local_ip();
+
+    // Request the genesis block from the peer.
+    let request = BlockRequest { start_height: 0, end_height: 1 };
+    node.send(peer_ip, Message::BlockRequest(request));
+    match peer.next_message().await.unwrap() {
+        Some(Message::BlockRequest(received)) => assert_eq!(received, request),
+        message => panic!("Expected a block request, found {:?}", message.map(|message| message.name())),
+    }
+
+    // Send the block response among other messages, which the link delivers in a shuffled order.
+    let genesis = sample_genesis_block::<CurrentNetwork>();
+    let response =
+        || Message::BlockResponse(BlockResponse { request, blocks: Data::Object(DataBlocks(vec![genesis.clone()])) });
+    peer.link().set_conditions(LinkConditions { reorder_window: 3, seed: 1, ..Default::default() });
+    peer.send_message(Message::Ping(Ping::new(peer_node.node_type(), None))).await.unwrap();
+    peer.send_message(Message::PeerResponse(PeerResponse::new(vec![]))).await.unwrap();
+    peer.send_message(response()).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // Ensure the router accepted the block response, regardless of the order of delivery.
+    assert!(node.is_connected(&peer_ip));
+    node.aggregate_peer_book();
+    assert_eq!(node.peer_book().get(&peer_ip).unwrap().blocks_first_delivered, 1);
+
+    // Ensure a duplicate of the block response is rejected, as the request was already fulfilled.
+    peer.link().set_conditions(Default::default());
+    peer.send_message(response()).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(!node.is_connected(&peer_ip));
+    let names = drain(&mut peer).await;
+    assert_eq!(names.last(), Some(&"Disconnect".to_string()));
+}
@@ -16,9 +16,12 @@
 
 mod router;
 
-use crate::traits::NodeInterface;
+use crate::{
+    helpers::{NodeConfig, SnapshotSource},
+    traits::NodeInterface,
+};
 use snarkos_account::Account;
-use snarkos_node_consensus::Consensus;
+use snarkos_node_consensus::{Consensus, ParameterRegistry, SubmissionOutcome, SubmissionSource};
 use snarkos_node_ledger::{Ledger, RecordMap};
 use snarkos_node_messages::{
     BeaconPropose,
@@ -30,7 +33,7 @@ use snarkos_node_messages::{
     UnconfirmedTransaction,
 };
 use snarkos_node_rest::Rest;
-use snarkos_node_router::{Heartbeat, Inbound, Outbound, Router, Routing};
+use snarkos_node_router::{Heartbeat, Inbound, Outbound, Router, Routing, SerialBlock};
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake, Reading, Writing},
     P2P,
@@ -49,7 +52,6 @@ use snarkvm::prelude::{
     Zero,
 };
 
-use ::time::OffsetDateTime;
 use aleo_std::prelude::{finish, lap, timer};
 use anyhow::{bail, Result};
 use core::{str::FromStr, time::Duration};
@@ -80,6 +82,10 @@ pub struct Beacon<N: Network, C: ConsensusStorage<N>> {
     block_generation_time: Arc<AtomicU64>,
     /// The unspent records.
     unspent_records: Arc<RwLock<RecordMap<N>>>,
+    /// The snapshot of the ledger served by the node.
+    snapshot: SnapshotSource<N, C>,
+    /// The settings of the node.
+    config: Arc<NodeConfig>,
     /// The spawned handles.
     handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
     /// The shutdown signal.
@@ -96,11 +102,15 @@ impl<N: Network, C: ConsensusStorage<N>> Beacon<N, C> {
         genesis: Block<N>,
         cdn: Option<String>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
         let timer = timer!("Beacon::new");
 
+        // Load and check the verifying keys, before the ledger uses them.
+        let parameters = ParameterRegistry::<N>::load()?;
+        lap!(timer, "Load the verifying keys");
         // Initialize the ledger.
-        let ledger = Ledger::load(genesis, dev)?;
+        let ledger = crate::helpers::load_ledger(genesis, dev, &config)?;
         lap!(timer, "Initialize the ledger");
 
         // Initialize the CDN.
@@ -114,7 +124,27 @@ impl<N: Network, C: ConsensusStorage<N>> Beacon<N, C> {
         }
 
         // Initialize the consensus.
-        let consensus = Consensus::new(ledger.clone(), dev.is_some())?;
+        let mut consensus = Consensus::new(ledger.clone(), dev.is_some())?;
+        // Set the verifying keys that were checked at startup.
+        consensus.set_parameters(parameters)?;
+        // Set the policy for replacing conflicting transactions in the memory pool.
+        consensus.memory_pool().set_replacement_policy(config.replacement_policy);
+        // Set the policy for expiring transactions from the memory pool.
+        consensus.memory_pool().set_expiry_policy(config.expiry_policy);
+        // Set the fee delta that makes a block template stale.
+        consensus.set_template_fee_delta(config.template_fee_delta);
+        // Register the policy hooks of the memory pool and the block template.
+        crate::helpers::register_policy_hooks(&mut consensus, &config)?;
+        // Restore the number of blocks mined for each coinbase recipient.
+        consensus.set_mined_blocks(crate::helpers::mined_blocks(dev)?);
+        // Persist the memory pool, and restore the persisted transactions.
+        crate::helpers::persist_memory_pool(&consensus, dev)?;
+        // Persist the blocks that are rejected for their contents, and restore the known invalid blocks.
+        crate::helpers::persist_invalid_blocks(&consensus, dev)?;
+        // Persist the blocks that are submitted and committed, and restore the known submitted blocks.
+        crate::helpers::persist_submitted_blocks(&consensus, dev)?;
+        // Persist the audits of the historical blocks, and restore the persisted audits.
+        crate::helpers::persist_audit_log(&consensus, dev)?;
         lap!(timer, "Initialize consensus");
 
         // Initialize the block generation time.
@@ -129,10 +159,35 @@ impl<N: Network, C: ConsensusStorage<N>> Beacon<N, C> {
             NodeType::Beacon,
             account.clone(),
             trusted_peers,
+            crate::helpers::noise_config::<N>(dev, &config)?,
+            crate::helpers::peer_book::<N>(dev)?,
+            ledger.event_bus().clone(),
             Self::MAXIMUM_NUMBER_OF_PEERS as u16,
             dev.is_some(),
         )
         .await?;
+        // Set the role in which the node operates on the network.
+        router.set_role(config.role);
+        // Set the compression of the large messages sent to the peers that advertise it.
+        router.set_compression(config.compression);
+        // Set the settings of the diffusion of the local transactions.
+        router.diffusion().set_config(config.diffusion);
+        // Set the throughput below which a sync peer is demoted.
+        router.sync().set_throughput_floor(config.sync_throughput_floor);
+        // Set the multiple of the block time after which the tip is stale.
+        router.sync().set_stale_tip_multiple(config.stale_tip_multiple);
+        // Set the DNS seeds, if they are specified.
+        crate::helpers::apply_dns_seeds(&router, &config);
+        // Route the outbound connections through the proxy, if one is specified.
+        crate::helpers::apply_proxy(&router, &config)?;
+        // Set the timeouts, retries, and circuit breaker of the storage operations.
+        router.storage_guard().set_policy(config.storage_policy);
+        // Advertise the pruned blocks to the peers.
+        router.set_pruned_height(ledger.pruned_height());
+        // Share the network-adjusted time with the ledger, which checks the next blocks against it.
+        router.set_network_time(ledger.network_time().clone());
+        // Set the maximum number of snapshot streams served to the trusted peers.
+        crate::helpers::apply_snapshot_options(&router, &config);
         lap!(timer, "Initialize the router");
 
         // Initialize the node.
@@ -144,15 +199,52 @@ impl<N: Network, C: ConsensusStorage<N>> Beacon<N, C> {
             rest: None,
             block_generation_time,
             unspent_records: Arc::new(RwLock::new(unspent_records)),
+            snapshot: SnapshotSource::new(ledger.clone()),
+            config: Arc::new(config.clone()),
             handles: Default::default(),
             shutdown: Default::default(),
         };
 
         // Initialize the REST server.
         if let Some(rest_ip) = rest_ip {
-            node.rest = Some(Rest::start(rest_ip, Some(consensus), ledger, Arc::new(node.clone()))?);
+            node.rest = Some(Rest::start(
+                config.rest_listeners(rest_ip),
+                Some(consensus),
+                ledger,
+                Arc::new(node.clone()),
+                config.response_cache(),
+            )?);
             lap!(timer, "Initialize REST server");
         }
+        // Initialize the storage statistics logger.
+        node.handles.lock().push(crate::helpers::spawn_storage_statistics_logger());
+        // Initialize the block pruner.
+        let pruner = crate::helpers::spawn_block_pruner(node.ledger.clone(), node.router.clone(), dev, &config);
+        if let Some(handle) = pruner {
+            node.handles.lock().push(handle);
+        }
+        // Initialize the auditor of the historical blocks.
+        let auditor = crate::helpers::spawn_block_auditor(node.consensus.clone(), node.router.clone(), &config)?;
+        if let Some(handle) = auditor {
+            node.handles.lock().push(handle);
+        }
+        // Initialize the memory pool flusher.
+        if let Some(handle) = crate::helpers::spawn_memory_pool_flusher(node.consensus.memory_pool().clone()) {
+            node.handles.lock().push(handle);
+        }
+        // Initialize the sweeper of the expired invalid blocks.
+        node.handles.lock().push(crate::helpers::spawn_invalid_block_sweeper::<N>(dev)?);
+        // Initialize the live configuration.
+        node.handles.lock().push(crate::helpers::spawn_live_config_task(
+            node.router.clone(),
+            Some(node.consensus.memory_pool().clone()),
+            node.rest.as_ref().map(|rest| rest.rate_limiter().clone()),
+            node.rest.as_ref().map(|rest| rest.telemetry().clone()),
+        ));
+        // Initialize the coinbase recipients, which follow the live configuration.
+        node.handles
+            .lock()
+            .push(crate::helpers::spawn_coinbase_recipients_task(node.consensus.clone(), node.account.address())?);
         // Initialize the routing.
         node.initialize_routing().await;
         // Initialize the block production.
@@ -194,6 +286,12 @@ impl<N: Network, C: ConsensusStorage<N>> NodeInterface<N> for Beacon<N, C> {
         // Shut down the router.
         self.router.shut_down().await;
 
+        // Flush the memory pool.
+        trace!("Flushing the memory pool...");
+        if let Err(error) = self.consensus.memory_pool().flush_storage() {
+            warn!("Failed to persist the memory pool - {error}");
+        }
+
         // Shut down the ledger.
         trace!("Shutting down the ledger...");
         // self.ledger.shut_down().await;
@@ -227,7 +325,7 @@ impl<N: Network, C: ConsensusStorage<N>> Beacon<N, C> {
             // Produce blocks.
             loop {
                 // Fetch the current timestamp.
-                let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+                let current_timestamp = beacon.ledger.network_time().now();
                 // Compute the elapsed time.
                 let elapsed_time = current_timestamp.saturating_sub(beacon.ledger.latest_timestamp()) as u64;
 
@@ -351,18 +449,28 @@ impl<N: Network, C: ConsensusStorage<N>> Beacon<N, C> {
         let next_block = match tokio::task::spawn_blocking(move || {
             let next_block = beacon.consensus.propose_next_block(beacon.private_key(), &mut rand::thread_rng())?;
 
-            // Ensure the block is a valid next block.
-            if let Err(error) = beacon.consensus.check_next_block(&next_block) {
-                // Clear the memory pool of all solutions and transactions.
-                trace!("Clearing the memory pool...");
-                beacon.consensus.clear_memory_pool()?;
-                trace!("Cleared the memory pool");
-                bail!("Proposed an invalid block: {error}")
-            }
-
-            // Advance to the next block.
-            match beacon.consensus.advance_to_next_block(&next_block) {
-                Ok(()) => {
+            // Pre-check, validate, and advance to the next block, unless it duplicates an already submitted block.
+            match beacon.consensus.submit_block(&next_block, SubmissionSource::Miner) {
+                Ok(SubmissionOutcome::Duplicate { block_hash, .. }) => {
+                    bail!("Proposed a duplicate of block '{block_hash}'")
+                }
+                Ok(SubmissionOutcome::PrecheckFailed(failure)) => {
+                    crate::helpers::dump_rejected_block(&next_block, &beacon.config);
+                    bail!("Proposed a block that failed the pre-checks: {failure}")
+                }
+                Ok(SubmissionOutcome::Rejected(error)) => {
+                    crate::helpers::dump_rejected_block(&next_block, &beacon.config);
+                    // Clear the memory pool of all solutions and transactions.
+                    trace!("Clearing the memory pool...");
+                    beacon.consensus.clear_memory_pool()?;
+                    trace!("Cleared the memory pool");
+                    bail!("Proposed an invalid block: {error}")
+                }
+                Ok(SubmissionOutcome::Accepted) => {
+                    // Count the block for each of the coinbase recipients.
+                    if let Err(error) = beacon.consensus.record_mined_block() {
+                        warn!("Failed to record the mined block {} - {error}", next_block.height());
+                    }
                     // If the beacon produced a transaction, save its output records.
                     if let Some(transaction) = beacon_transaction {
                         // Save the unspent records.
@@ -510,6 +618,7 @@ mod tests {
             genesis,
             None,
             dev,
+            Default::default(),
         )
         .await
         .unwrap();
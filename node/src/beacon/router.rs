@@ -16,22 +16,26 @@
 
 use super::*;
 
+use snarkos_node_consensus::{fee_rate, Admission};
 use snarkos_node_messages::{
     BlockRequest,
     BlockResponse,
+    CommittedHeader,
     DataBlocks,
     DisconnectReason,
     Message,
-    MessageCodec,
+    PeerCodec,
     Ping,
     Pong,
+    SnapshotManifest,
 };
 use snarkos_node_router::Routing;
 use snarkos_node_tcp::{Connection, ConnectionSide, Tcp};
-use snarkvm::prelude::{error, EpochChallenge, Header};
+use snarkvm::prelude::{error, EpochChallenge};
 
+use bytes::Bytes;
 use futures_util::sink::SinkExt;
-use std::{io, net::SocketAddr};
+use std::{collections::HashSet, io, net::SocketAddr};
 
 impl<N: Network, C: ConsensusStorage<N>> P2P for Beacon<N, C> {
     /// Returns a reference to the TCP instance.
@@ -49,10 +53,12 @@ impl<N: Network, C: ConsensusStorage<N>> Handshake for Beacon<N, C> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = self.ledger.get_header(0).map_err(|e| error(format!("{e}")))?;
-        let (peer_ip, mut framed) = self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let genesis_hash = self.ledger.get_hash(0).map_err(|e| error(format!("{e}")))?;
+        let (peer_ip, mut framed) =
+            self.router.handshake(peer_addr, stream, conn_side, genesis_header, genesis_hash).await?;
 
         // Retrieve the block locators.
-        let block_locators = match crate::helpers::get_block_locators(&self.ledger) {
+        let block_locators = match crate::helpers::get_block_locators_guarded(&self.router, &self.ledger).await {
             Ok(block_locators) => Some(block_locators),
             Err(e) => {
                 error!("Failed to get block locators: {e}");
@@ -81,25 +87,25 @@ impl<N: Network, C: ConsensusStorage<N>> Disconnect for Beacon<N, C> {
 
 #[async_trait]
 impl<N: Network, C: ConsensusStorage<N>> Writing for Beacon<N, C> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates an [`Encoder`] used to write the outbound messages to the target stream.
     /// The `side` parameter indicates the connection side **from the node's perspective**.
-    fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router.codec(peer_addr)
     }
 }
 
 #[async_trait]
 impl<N: Network, C: ConsensusStorage<N>> Reading for Beacon<N, C> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates a [`Decoder`] used to interpret messages from the network.
     /// The `side` param indicates the connection side **from the node's perspective**.
-    fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router.codec(peer_addr)
     }
 
     /// Processes a message received from the network.
@@ -138,6 +144,13 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Beacon<N, C> {
     fn block_request(&self, peer_ip: SocketAddr, message: BlockRequest) -> bool {
         let BlockRequest { start_height, end_height } = &message;
 
+        // Ignore the request if the blocks are pruned, as the peer was told so in the handshake.
+        let pruned_height = self.ledger.pruned_height();
+        if (*start_height..*end_height).any(|height| height > 0 && height < pruned_height) {
+            debug!("Ignoring a request from '{peer_ip}' for the pruned blocks {start_height} to {end_height}");
+            return true;
+        }
+
         // Retrieve the blocks within the requested range.
         let blocks = match self.ledger.get_blocks(*start_height..*end_height) {
             Ok(blocks) => Data::Object(DataBlocks(blocks)),
@@ -152,9 +165,19 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Beacon<N, C> {
     }
 
     /// Handles a `BlockResponse` message.
-    fn block_response(&self, peer_ip: SocketAddr, blocks: Vec<Block<N>>) -> bool {
+    fn block_response(&self, peer_ip: SocketAddr, blocks: Vec<SerialBlock<N>>) -> bool {
         // Insert the candidate blocks into the sync pool.
         for block in blocks {
+            // Ensure the block is not known to be invalid, penalizing the peer more for every repeat.
+            if let Some(invalid) = self.consensus.invalid_blocks().get_block(block.block()) {
+                let penalty = self.router().record_known_invalid_block(&peer_ip);
+                warn!(
+                    "Peer '{peer_ip}' sent the known invalid block {} (penalty {penalty}) - {}",
+                    invalid.height,
+                    invalid.reason
+                );
+                return false;
+            }
             if let Err(error) = self.router().sync().insert_block_response(peer_ip, block) {
                 warn!("{error}");
                 return false;
@@ -165,9 +188,15 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Beacon<N, C> {
         let mut latest_height = self.ledger.latest_height();
         // Try to advance the ledger with the sync pool.
         while let Some(block) = self.router().sync().remove_block_response(latest_height + 1) {
+            let (sender, block) = (block.peer_ip(), block.into_block());
             // Check the next block.
             if let Err(error) = self.consensus.check_next_block(&block) {
                 warn!("The next block ({}) is invalid - {error}", block.height());
+                crate::helpers::dump_rejected_block(&block, &self.config);
+                // Track the rejected block as a side branch, if it is connected to the known blocks.
+                if let Err(error) = self.consensus.insert_rejected_block(&block, &error.to_string(), sender) {
+                    trace!("Skipped tracking the rejected block ({}) - {error}", block.height());
+                }
                 break;
             }
             // Attempt to advance to the next block.
@@ -193,7 +222,7 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Beacon<N, C> {
             // Check that the peer is still connected.
             if self_clone.router().is_connected(&peer_ip) {
                 // Retrieve the block locators.
-                match crate::helpers::get_block_locators(&self_clone.ledger) {
+                match crate::helpers::get_block_locators_guarded(self_clone.router(), &self_clone.ledger).await {
                     // Send a `Ping` message to the peer.
                     Ok(block_locators) => self_clone.send_ping(peer_ip, Some(block_locators)),
                     Err(e) => error!("Failed to get block locators: {e}"),
@@ -213,15 +242,36 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Beacon<N, C> {
                 return false;
             }
         };
-        // Retrieve the latest block header.
-        let block_header = Data::Object(self.ledger.latest_header());
+        // Retrieve the latest block header, with its commitments from their activation on, signed by this node.
+        let block_header = match self
+            .consensus
+            .committed_header(&self.ledger.latest_block())
+            .and_then(|block_header| block_header.sign(self.router().private_key(), &mut rand::thread_rng()))
+        {
+            // Peers on a version before the commitments are sent the header in the legacy layout.
+            Ok(block_header) => match self.router().get_connected_peer(&peer_ip).map(|peer| peer.version()) {
+                Some(version) if version < Message::<N>::EXTENDED_VERSION => {
+                    Data::Object(CommittedHeader::legacy(block_header.header))
+                }
+                _ => Data::Object(block_header),
+            },
+            Err(error) => {
+                error!("Failed to prepare a puzzle request for '{peer_ip}': {error}");
+                return false;
+            }
+        };
         // Send the `PuzzleResponse` message to the peer.
         self.send(peer_ip, Message::PuzzleResponse(PuzzleResponse { epoch_challenge, block_header }));
         true
     }
 
     /// Disconnects on receipt of a `PuzzleResponse` message.
-    fn puzzle_response(&self, peer_ip: SocketAddr, _epoch_challenge: EpochChallenge<N>, _header: Header<N>) -> bool {
+    fn puzzle_response(
+        &self,
+        peer_ip: SocketAddr,
+        _epoch_challenge: EpochChallenge<N>,
+        _header: CommittedHeader<N>,
+    ) -> bool {
         debug!("Disconnecting '{peer_ip}' for the following reason - {:?}", DisconnectReason::ProtocolViolation);
         false
     }
@@ -251,14 +301,80 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Beacon<N, C> {
         serialized: UnconfirmedTransaction<N>,
         transaction: Transaction<N>,
     ) -> bool {
-        // Add the unconfirmed transaction to the memory pool.
-        if let Err(error) = self.consensus.add_unconfirmed_transaction(transaction) {
-            trace!("[UnconfirmedTransaction] {error}");
-            return true; // Maintain the connection.
-        }
-        let message = Message::UnconfirmedTransaction(serialized);
-        // Propagate the "UnconfirmedTransaction" to the connected beacons.
-        self.propagate_to_beacons(message, &[peer_ip]);
+        // Announce the parents of the transaction in the memory pool, so that the peers are able to fetch them.
+        let parent_ids = self.consensus.memory_pool().unconfirmed_parents(&transaction);
+        let parent_ids = parent_ids.iter().map(|parent| parent.id()).collect();
+        // Compute the fee rate of the transaction, which orders its relay.
+        let fee_rate = fee_rate(&transaction).unwrap_or(0.0);
+        // Add the unconfirmed transaction to the memory pool, unless another peer sent it concurrently,
+        // in which case the transaction is only validated once.
+        let senders = match self.consensus.admit_unconfirmed_transaction(transaction, Some(peer_ip)) {
+            Ok(Admission::Validated { senders, .. }) => senders,
+            // The arrival that validated the transaction propagates it.
+            Ok(Admission::Shared { .. }) => return true,
+            Err(error) => {
+                trace!("[UnconfirmedTransaction] {error}");
+                return true; // Maintain the connection.
+            }
+        };
+        // Queue the "UnconfirmedTransaction" for the relay to the connected beacons, except the peers that sent it.
+        let senders = senders.into_iter().collect::<Vec<_>>();
+        self.queue_relay(serialized.with_parents(parent_ids), fee_rate, &self.router.connected_beacons(), &senders);
         true
     }
+
+    /// Returns the given parents that are neither in the memory pool, nor in the ledger.
+    fn missing_parents(&self, parent_ids: &[N::TransactionID]) -> Vec<N::TransactionID> {
+        parent_ids
+            .iter()
+            .filter(|parent_id| {
+                !self.consensus.memory_pool().contains_unconfirmed_transaction(**parent_id)
+                    && !self.ledger.contains_transaction_id(parent_id).unwrap_or(false)
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Returns the requested transactions that are in the memory pool, along with their own parents.
+    fn unconfirmed_transactions(&self, transaction_ids: &[N::TransactionID]) -> Vec<UnconfirmedTransaction<N>> {
+        let memory_pool = self.consensus.memory_pool();
+        let requested = transaction_ids.iter().collect::<HashSet<_>>();
+        // Send the parents before their children, as some of the requested transactions may depend on others.
+        let mut transactions = memory_pool
+            .unconfirmed_transactions_with_arrival_times()
+            .into_iter()
+            .filter(|(transaction, _)| requested.contains(&transaction.id()))
+            .collect::<Vec<_>>();
+        transactions.sort_by_key(|(_, arrival_time)| *arrival_time);
+        transactions
+            .into_iter()
+            .map(|(transaction, _)| {
+                let parents = memory_pool.unconfirmed_parents(&transaction);
+                let parent_ids = parents.iter().map(|parent| parent.id()).collect();
+                UnconfirmedTransaction::new(transaction.id(), Data::Object(transaction)).with_parents(parent_ids)
+            })
+            .collect()
+    }
+
+    /// Returns the manifest of the snapshot of the ledger.
+    fn snapshot_manifest(&self) -> Option<SnapshotManifest<N>> {
+        match self.snapshot.manifest() {
+            Ok(manifest) => Some(manifest),
+            Err(error) => {
+                error!("Failed to prepare the snapshot manifest - {error}");
+                None
+            }
+        }
+    }
+
+    /// Returns the bytes of the given chunk of the snapshot of the ledger.
+    fn snapshot_chunk(&self, index: u32) -> Option<Bytes> {
+        match self.snapshot.chunk(index) {
+            Ok(bytes) => Some(bytes.into()),
+            Err(error) => {
+                debug!("Failed to prepare snapshot chunk {index} - {error}");
+                None
+            }
+        }
+    }
 }
@@ -16,7 +16,16 @@
 
 use super::*;
 
-use snarkos_node_messages::{BlockRequest, DisconnectReason, MessageCodec, Ping, Pong, UnconfirmedTransaction};
+use snarkos_node_consensus::fee_rate;
+use snarkos_node_messages::{
+    BlockRequest,
+    CommittedHeader,
+    DisconnectReason,
+    PeerCodec,
+    Ping,
+    Pong,
+    UnconfirmedTransaction,
+};
 use snarkos_node_router::Routing;
 use snarkos_node_tcp::{Connection, ConnectionSide, Tcp};
 use snarkvm::prelude::{Network, Transaction};
@@ -40,7 +49,9 @@ impl<N: Network, C: ConsensusStorage<N>> Handshake for Client<N, C> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = *self.genesis.header();
-        let (peer_ip, mut framed) = self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let genesis_hash = self.genesis.hash();
+        let (peer_ip, mut framed) =
+            self.router.handshake(peer_addr, stream, conn_side, genesis_header, genesis_hash).await?;
 
         // Send the first `Ping` message to the peer.
         let message = Message::Ping(Ping::new(self.node_type(), None));
@@ -63,25 +74,25 @@ impl<N: Network, C: ConsensusStorage<N>> Disconnect for Client<N, C> {
 
 #[async_trait]
 impl<N: Network, C: ConsensusStorage<N>> Writing for Client<N, C> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates an [`Encoder`] used to write the outbound messages to the target stream.
     /// The `side` parameter indicates the connection side **from the node's perspective**.
-    fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router.codec(peer_addr)
     }
 }
 
 #[async_trait]
 impl<N: Network, C: ConsensusStorage<N>> Reading for Client<N, C> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates a [`Decoder`] used to interpret messages from the network.
     /// The `side` param indicates the connection side **from the node's perspective**.
-    fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router.codec(peer_addr)
     }
 
     /// Processes a message received from the network.
@@ -120,7 +131,7 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Client<N, C> {
     }
 
     /// Handles a `BlockResponse` message.
-    fn block_response(&self, peer_ip: SocketAddr, _blocks: Vec<Block<N>>) -> bool {
+    fn block_response(&self, peer_ip: SocketAddr, _blocks: Vec<SerialBlock<N>>) -> bool {
         debug!("Disconnecting '{peer_ip}' for the following reason - {:?}", DisconnectReason::ProtocolViolation);
         false
     }
@@ -147,8 +158,28 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Client<N, C> {
         false
     }
 
-    /// Saves the latest epoch challenge and latest block header in the node.
-    fn puzzle_response(&self, peer_ip: SocketAddr, epoch_challenge: EpochChallenge<N>, header: Header<N>) -> bool {
+    /// Checks the relayed block header, and saves the latest epoch challenge and latest block header in the node.
+    fn puzzle_response(
+        &self,
+        peer_ip: SocketAddr,
+        epoch_challenge: EpochChallenge<N>,
+        header: CommittedHeader<N>,
+    ) -> bool {
+        // Ensure the header has the layout of its era, and plausible commitments signed by the peer, before it is used.
+        let peer_address = match self.router.get_connected_peer(&peer_ip) {
+            Some(peer) => peer.address(),
+            None => return false,
+        };
+        if let Err(error) =
+            header.check_layout(self.header_commitments_height).and_then(|_| header.check_signature(&peer_address))
+        {
+            warn!("Peer '{peer_ip}' relayed an invalid block header - {error}");
+            return false;
+        }
+        // Hold the commitments in the sync pool, so that the body of the block is checked against them.
+        self.router.sync().insert_committed_header(header.clone());
+        let header = header.header;
+
         // Retrieve the epoch number.
         let epoch_number = epoch_challenge.epoch_number();
         // Retrieve the block height.
@@ -210,10 +241,11 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Client<N, C> {
         &self,
         peer_ip: SocketAddr,
         serialized: UnconfirmedTransaction<N>,
-        _transaction: Transaction<N>,
+        transaction: Transaction<N>,
     ) -> bool {
-        // Propagate the `UnconfirmedTransaction`.
-        self.propagate(Message::UnconfirmedTransaction(serialized), &[peer_ip]);
+        // Queue the `UnconfirmedTransaction` for the relay to the connected peers.
+        let fee_rate = fee_rate(&transaction).unwrap_or(0.0);
+        self.queue_relay(serialized, fee_rate, &self.router.connected_peers(), &[peer_ip]);
         true
     }
 }
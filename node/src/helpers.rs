@@ -14,12 +14,698 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use snarkos_node_ledger::Ledger;
-use snarkos_node_messages::{BlockLocators, CHECKPOINT_INTERVAL, NUM_RECENTS};
-use snarkvm::prelude::{ConsensusStorage, Network};
+use snarkos_node_cdn::{serialize_block_file, BLOCKS_PER_FILE};
+use snarkos_node_consensus::{
+    AuditEntry,
+    AuditLogStorage,
+    BatchWriter,
+    CoinbaseRecipients,
+    Consensus,
+    ExpiryPolicy,
+    InvalidBlock,
+    InvalidBlockStorage,
+    MemoryPool,
+    MemoryPoolStorage,
+    MinedBlocks,
+    ProgramAllowlist,
+    ReplacementPolicy,
+    SubmittedBlock,
+    SubmittedBlockStorage,
+    DEFAULT_FLUSH_BYTES,
+    DEFAULT_FLUSH_INTERVAL,
+    DEFAULT_TEMPLATE_FEE_DELTA,
+};
+use snarkos_node_ledger::{ConsistencyCheck, Ledger, DEFAULT_RECENT_BLOCKS, DEFAULT_RECENT_BLOCKS_BYTE_BUDGET};
+use snarkos_node_messages::{
+    BlockLocators,
+    Compression,
+    NodeId,
+    NodeRole,
+    OnionAddr,
+    SnapshotManifest,
+    CHECKPOINT_INTERVAL,
+    MAX_SNAPSHOT_CHUNKS,
+    NUM_RECENTS,
+};
+use snarkos_node_rest::{
+    ListenerConfig,
+    RateLimiter,
+    RequestTelemetry,
+    ResponseCache,
+    DEFAULT_CACHE_BYTE_BUDGET,
+    DEFAULT_CACHE_TTL,
+};
+use snarkos_node_router::{
+    load_keypair,
+    load_or_generate_keypair,
+    rotate_keypair,
+    DiffusionConfig,
+    NoiseConfig,
+    OperationClass,
+    PeerBook,
+    Router,
+    StoragePolicy,
+    DEFAULT_MAX_SNAPSHOT_STREAMS,
+    DEFAULT_PIPELINE_BYTE_BUDGET,
+    DEFAULT_STALE_TIP_MULTIPLE,
+    DEFAULT_THROUGHPUT_FLOOR,
+};
+use snarkos_node_store::{
+    rocksdb::{is_bulk_sync, set_bulk_sync, storage_statistics, tuning_profile, MAX_ENTRIES_PER_COLUMN},
+    set_journal_retention,
+    AuditLogStore,
+    BlockPruner,
+    InvalidBlockStore,
+    MemoryPoolStore,
+    SubmittedBlockStore,
+    DEFAULT_JOURNAL_RETENTION,
+    DEFAULT_TTL_SWEEP_INTERVAL,
+};
+use snarkos_node_tcp::ProxyConfig;
+use snarkvm::prelude::{Address, Block, ConsensusStorage, Network, ToBytes, Transaction};
 
-use anyhow::Result;
+use anyhow::{anyhow, ensure, Result};
+use core::time::Duration;
 use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc};
+use tokio::{sync::watch, task::JoinHandle};
+
+/// The interval at which a summary of the storage statistics is logged.
+const STORAGE_STATISTICS_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// The interval at which the blocks beyond the prune depth are pruned.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// The minimum prune depth, which exceeds the maximum fork depth, so that a pruned block is never reorganized.
+pub const MIN_PRUNE_DEPTH: u32 = 4096;
+/// The minimum interval between the audits of the historical blocks.
+pub const MIN_AUDIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The settings of the node, which are fixed once the node is started.
+#[derive(Clone, Debug)]
+pub struct NodeConfig {
+    /// The consistency check performed when the ledger is loaded.
+    pub consistency_check: ConsistencyCheck,
+    /// The static public keys pinned for trusted peers.
+    pub pinned_keys: HashMap<SocketAddr, Vec<u8>>,
+    /// If `true`, the peers without transport encryption are accepted (transition mode).
+    pub allow_plaintext_peers: bool,
+    /// The identity file supplied by the operator, in place of the one persisted next to the ledger.
+    pub identity: Option<PathBuf>,
+    /// If `true`, the identity of the node is rotated, archiving the previous static keypair.
+    pub rotate_identity: bool,
+    /// The directory to dump rejected blocks into, for replay with `snarkos developer replay-block`.
+    pub rejected_blocks_dir: Option<PathBuf>,
+    /// The byte budget of the REST response cache.
+    pub response_cache_byte_budget: usize,
+    /// The time-to-live of the REST response cache.
+    pub response_cache_ttl: Duration,
+    /// The additional listeners of the REST server, next to its primary listener.
+    pub rest_listeners: Vec<ListenerConfig>,
+    /// If `true`, the additional listeners may serve the admin methods on a public address without authentication.
+    pub allow_public_admin: bool,
+    /// The policy for replacing conflicting transactions in the memory pool (replace-by-fee), if one is set.
+    pub replacement_policy: Option<ReplacementPolicy>,
+    /// The policy for expiring transactions from the memory pool, if one is set.
+    pub expiry_policy: Option<ExpiryPolicy>,
+    /// The programs that the transactions must deploy or execute to be admitted into the memory pool, if they are set.
+    pub program_allowlist: Option<Vec<String>>,
+    /// The increase in the fees of the memory pool (in microcredits) that makes a block template stale.
+    pub template_fee_delta: u64,
+    /// The byte budget of the blocks queued for verification and commit while syncing.
+    pub sync_byte_budget: usize,
+    /// The throughput (in bytes per second) below which a sync peer is demoted, or `0` to never demote peers.
+    pub sync_throughput_floor: u64,
+    /// The multiple of the target block time without a canon advance after which the tip is stale,
+    /// or `0` to never detect a stale tip.
+    pub stale_tip_multiple: u32,
+    /// The depth beyond which the bodies of the blocks are pruned, if pruning is enabled.
+    pub prune_depth: Option<u32>,
+    /// The interval between the audits of the historical blocks, if the auditor is enabled.
+    pub audit_interval: Option<Duration>,
+    /// The role in which the node operates on the network, which determines the capabilities it advertises.
+    pub role: NodeRole,
+    /// The compression of the large messages sent to the peers that advertise it in the handshake.
+    pub compression: Compression,
+    /// The settings of the diffusion of the local transactions.
+    pub diffusion: DiffusionConfig,
+    /// The number of recent canonical blocks kept in memory for the shallow reorganizations.
+    pub recent_blocks: usize,
+    /// The byte budget of the recent canonical blocks kept in memory.
+    pub recent_blocks_byte_budget: usize,
+    /// The timeouts, retries, and circuit breaker of the storage operations.
+    pub storage_policy: StoragePolicy,
+    /// The hostnames of the DNS seeds, instead of those of the network, if they are set.
+    pub dns_seeds: Option<Vec<String>>,
+    /// The proxy through which the outbound connections are made, and the onion address of the node, if they are set.
+    pub proxy: Option<(ProxyConfig, Option<OnionAddr>)>,
+    /// The trusted peer to bootstrap an empty ledger from, if one is set.
+    pub snapshot_from: Option<SocketAddr>,
+    /// The maximum number of snapshot streams served concurrently, where `0` disables the serving of snapshots.
+    pub max_snapshot_streams: usize,
+    /// The number of events retained in the chain journal, after which the oldest events are pruned.
+    pub journal_retention: u64,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            consistency_check: Default::default(),
+            pinned_keys: Default::default(),
+            allow_plaintext_peers: false,
+            identity: None,
+            rotate_identity: false,
+            rejected_blocks_dir: None,
+            response_cache_byte_budget: DEFAULT_CACHE_BYTE_BUDGET,
+            response_cache_ttl: DEFAULT_CACHE_TTL,
+            rest_listeners: Default::default(),
+            allow_public_admin: false,
+            replacement_policy: None,
+            expiry_policy: None,
+            program_allowlist: None,
+            template_fee_delta: DEFAULT_TEMPLATE_FEE_DELTA,
+            sync_byte_budget: DEFAULT_PIPELINE_BYTE_BUDGET,
+            sync_throughput_floor: DEFAULT_THROUGHPUT_FLOOR,
+            stale_tip_multiple: DEFAULT_STALE_TIP_MULTIPLE,
+            prune_depth: None,
+            audit_interval: None,
+            role: Default::default(),
+            compression: Default::default(),
+            diffusion: Default::default(),
+            recent_blocks: DEFAULT_RECENT_BLOCKS,
+            recent_blocks_byte_budget: DEFAULT_RECENT_BLOCKS_BYTE_BUDGET,
+            storage_policy: Default::default(),
+            dns_seeds: None,
+            proxy: None,
+            snapshot_from: None,
+            max_snapshot_streams: DEFAULT_MAX_SNAPSHOT_STREAMS,
+            journal_retention: DEFAULT_JOURNAL_RETENTION,
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Ensures the settings are valid. This fails if an additional listener of the REST server serves the admin
+    /// methods on a public address without authentication, unless `allow_public_admin` is set.
+    pub fn check(&self) -> Result<()> {
+        ensure!(
+            self.identity.is_none() || !self.rotate_identity,
+            "The identity supplied by the operator cannot be rotated by the node"
+        );
+        for listener in &self.rest_listeners {
+            listener.check(self.allow_public_admin)?;
+        }
+        if let Some(depth) = self.prune_depth {
+            ensure!(
+                depth >= MIN_PRUNE_DEPTH,
+                "The prune depth must be at least {MIN_PRUNE_DEPTH} blocks (found {depth})"
+            );
+        }
+        if let Some(interval) = self.audit_interval {
+            ensure!(
+                interval >= MIN_AUDIT_INTERVAL,
+                "The audit interval must be at least {MIN_AUDIT_INTERVAL:?} (found {interval:?})"
+            );
+        }
+        self.diffusion.check()?;
+        self.storage_policy.check()?;
+        if let Some(hosts) = &self.dns_seeds {
+            ensure!(hosts.iter().all(|host| !host.trim().is_empty()), "The hostname of a DNS seed must not be empty");
+        }
+        ensure!(self.journal_retention > 0, "The journal must retain at least one event");
+        Ok(())
+    }
+
+    /// Returns the listeners of the REST server, starting with its primary listener on the given IP,
+    /// which serves every method class.
+    pub fn rest_listeners(&self, rest_ip: SocketAddr) -> Vec<ListenerConfig> {
+        let mut listeners = vec![ListenerConfig::new(rest_ip)];
+        listeners.extend(self.rest_listeners.iter().cloned());
+        listeners
+    }
+
+    /// Returns an empty REST response cache, with the configured byte budget and time-to-live.
+    pub fn response_cache<N: Network>(&self) -> ResponseCache<N> {
+        ResponseCache::new(self.response_cache_byte_budget, self.response_cache_ttl)
+    }
+}
+
+/// The settings that can change while the node is running, which are sent to the running components as a whole.
+static LIVE_CONFIG: Lazy<watch::Sender<LiveConfig>> = Lazy::new(|| watch::channel(LiveConfig::default()).0);
+
+/// The settings that can change while the node is running, by reloading the configuration file.
+/// An unset value restores the default of the setting.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiveConfig {
+    /// The additional directives of the log filter.
+    pub log_filter: Option<String>,
+    /// The maximum number of REST requests per second from each IP address.
+    pub rest_rate_limit: Option<u32>,
+    /// The duration (in milliseconds) after which a REST request is logged as slow.
+    pub rest_slow_request_ms: Option<u64>,
+    /// The maximum number of bytes of the unconfirmed transactions in the memory pool.
+    pub mempool_byte_budget: Option<usize>,
+    /// The maximum number of connected peers, which cannot exceed the maximum of the node type.
+    pub max_peers: Option<u16>,
+    /// The recipients of the coinbase, each of the form `{address}:{weight}`, instead of the address of the node.
+    pub coinbase_recipients: Option<Vec<String>>,
+}
+
+/// Sets the settings that can change while the node is running, and notifies the running components if they changed.
+/// The settings must be validated beforehand, as they are applied as a whole.
+pub fn set_live_config(config: LiveConfig) {
+    LIVE_CONFIG.send_if_modified(|current| match *current == config {
+        true => false,
+        false => {
+            *current = config;
+            true
+        }
+    });
+}
+
+/// Returns a receiver for the settings that can change while the node is running.
+pub fn subscribe_to_live_config() -> watch::Receiver<LiveConfig> {
+    LIVE_CONFIG.subscribe()
+}
+
+/// Registers the policy hooks of the given settings on the given consensus, which only apply to the memory pool
+/// and the block template.
+pub fn register_policy_hooks<N: Network, C: ConsensusStorage<N>>(
+    consensus: &mut Consensus<N, C>,
+    config: &NodeConfig,
+) -> Result<()> {
+    if let Some(programs) = &config.program_allowlist {
+        let allowlist = ProgramAllowlist::<N>::new(programs)
+            .map_err(|error| anyhow!("The program allowlist is invalid - {error}"))?;
+        consensus.register_policy_hook(Arc::new(allowlist));
+    }
+    Ok(())
+}
+
+/// Sets the hostnames of the DNS seeds on the given router, if they are set.
+pub fn apply_dns_seeds<N: Network>(router: &Router<N>, config: &NodeConfig) {
+    if let Some(hosts) = &config.dns_seeds {
+        router.dns_seeds().set_hosts(hosts.clone());
+    }
+}
+
+/// Sets the proxy, and the onion address of the node, on the given router, if they are set.
+pub fn apply_proxy<N: Network>(router: &Router<N>, config: &NodeConfig) -> Result<()> {
+    match &config.proxy {
+        Some((proxy, onion_service)) => router.set_proxy(proxy.clone(), onion_service.clone()),
+        None => Ok(()),
+    }
+}
+
+/// Sets the maximum number of snapshot streams served concurrently on the given router.
+pub fn apply_snapshot_options<N: Network>(router: &Router<N>, config: &NodeConfig) {
+    router.snapshots().set_max_streams(config.max_snapshot_streams);
+}
+
+/// The snapshot of the ledger served by the node, whose chunks are the block files of the CDN, in the same encoding.
+///
+/// The snapshot covers the full chunks below the recent blocks, which are final, so the hashes of the chunks are
+/// only computed once, as the snapshot grows.
+#[derive(Clone)]
+pub struct SnapshotSource<N: Network, C: ConsensusStorage<N>> {
+    /// The ledger of the node.
+    ledger: Ledger<N, C>,
+    /// The hashes of the chunks computed so far.
+    chunk_hashes: Arc<Mutex<Vec<[u8; 32]>>>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> SnapshotSource<N, C> {
+    /// Initializes the snapshot of the given ledger.
+    pub fn new(ledger: Ledger<N, C>) -> Self {
+        Self { ledger, chunk_hashes: Default::default() }
+    }
+
+    /// Returns the manifest of the snapshot.
+    pub fn manifest(&self) -> Result<SnapshotManifest<N>> {
+        let final_height = self.ledger.latest_height().saturating_sub(NUM_RECENTS as u32);
+        let num_chunks = (final_height / BLOCKS_PER_FILE).min(MAX_SNAPSHOT_CHUNKS);
+        // Compute the hashes of the chunks that became final.
+        let mut chunk_hashes = self.chunk_hashes.lock();
+        while (chunk_hashes.len() as u32) < num_chunks {
+            let bytes = self.chunk(chunk_hashes.len() as u32)?;
+            chunk_hashes.push(SnapshotManifest::<N>::chunk_hash(&bytes));
+        }
+        let block_hash = self.ledger.get_hash(num_chunks * BLOCKS_PER_FILE)?;
+        SnapshotManifest::new(BLOCKS_PER_FILE, chunk_hashes[..num_chunks as usize].to_vec(), block_hash)
+    }
+
+    /// Returns the bytes of the given chunk.
+    pub fn chunk(&self, index: u32) -> Result<Vec<u8>> {
+        ensure!(index < MAX_SNAPSHOT_CHUNKS, "Snapshot chunk {index} is out of range");
+        let start_height = index * BLOCKS_PER_FILE + 1;
+        let end_height = start_height + BLOCKS_PER_FILE;
+        ensure!(end_height <= self.ledger.latest_height() + 1, "Snapshot chunk {index} is beyond the latest block");
+        serialize_block_file(&self.ledger.get_blocks(start_height..end_height)?)
+    }
+}
+
+/// Loads the ledger from storage, with the configured consistency check, and the pruned height from the database.
+/// Note that the pruned height is loaded even if pruning is disabled, as the pruned blocks remain pruned.
+pub fn load_ledger<N: Network, C: ConsensusStorage<N>>(
+    genesis: Block<N>,
+    dev: Option<u16>,
+    config: &NodeConfig,
+) -> Result<Ledger<N, C>> {
+    // Set the retention of the chain journal, before the ledger opens it.
+    set_journal_retention::<N>(dev, config.journal_retention)?;
+    let pruned_height = BlockPruner::<N>::open(dev)?.pruned_height()?;
+    let ledger = Ledger::load_pruned(genesis, dev, config.consistency_check, pruned_height)?;
+    // Set the number of recent canonical blocks that are kept in memory.
+    ledger.set_recent_blocks_capacity(config.recent_blocks, config.recent_blocks_byte_budget);
+    Ok(ledger)
+}
+
+/// Returns the transport encryption configuration of the node, with its static keypair persisted next to the ledger,
+/// unless the operator supplied an identity file.
+pub fn noise_config<N: Network>(dev: Option<u16>, config: &NodeConfig) -> Result<NoiseConfig> {
+    // Construct the path to the keypair, i.e. `~/.aleo/storage/ledger-{network}-noise.key`.
+    let ledger_dir = aleo_std::aleo_ledger_dir(N::ID, dev);
+    let mut file_name = ledger_dir.file_name().unwrap_or_default().to_os_string();
+    file_name.push("-noise.key");
+    let path = ledger_dir.with_file_name(file_name);
+
+    // Load the keypair, which is the identity of the node.
+    let keypair = match (&config.identity, config.rotate_identity) {
+        (Some(identity), _) => load_keypair(identity)?,
+        (None, true) => {
+            let (keypair, archive_path) = rotate_keypair(&path)?;
+            if let Some(archive_path) = archive_path {
+                info!("Archived the previous identity of the node to '{}'", archive_path.display());
+            }
+            keypair
+        }
+        (None, false) => load_or_generate_keypair(&path)?,
+    };
+    info!("The node ID is {}", NodeId::from_public_key(&keypair.public));
+
+    // Apply the transport options.
+    Ok(NoiseConfig::new(keypair)
+        .with_pinned_keys(config.pinned_keys.clone())
+        .with_plaintext_peers(config.allow_plaintext_peers))
+}
+
+/// Returns the peer book of the node, persisted next to the ledger.
+pub fn peer_book<N: Network>(dev: Option<u16>) -> Result<PeerBook<N>> {
+    // Construct the path to the peer book, i.e. `~/.aleo/storage/ledger-{network}-peers.bin`.
+    let ledger_dir = aleo_std::aleo_ledger_dir(N::ID, dev);
+    let mut file_name = ledger_dir.file_name().unwrap_or_default().to_os_string();
+    file_name.push("-peers.bin");
+    PeerBook::open(&ledger_dir.with_file_name(file_name))
+}
+
+/// Returns the number of blocks mined for each coinbase recipient, persisted next to the ledger.
+pub fn mined_blocks<N: Network>(dev: Option<u16>) -> Result<MinedBlocks<N>> {
+    // Construct the path to the counters, i.e. `~/.aleo/storage/ledger-{network}-mined-blocks.txt`.
+    let ledger_dir = aleo_std::aleo_ledger_dir(N::ID, dev);
+    let mut file_name = ledger_dir.file_name().unwrap_or_default().to_os_string();
+    file_name.push("-mined-blocks.txt");
+    MinedBlocks::open(&ledger_dir.with_file_name(file_name))
+}
+
+/// Returns the recipients of the coinbase in the given settings, or the given address if none are set.
+fn coinbase_recipients<N: Network>(config: &LiveConfig, address: Address<N>) -> Result<CoinbaseRecipients<N>> {
+    match &config.coinbase_recipients {
+        Some(recipients) => CoinbaseRecipients::parse(recipients),
+        None => Ok(CoinbaseRecipients::single(address)),
+    }
+}
+
+/// Sets the recipients of the coinbase from the settings that can change while the node is running,
+/// and spawns a task that updates them whenever the settings change. The initial recipients must be valid,
+/// while invalid recipients on a later change are logged, and leave the current recipients unchanged.
+pub fn spawn_coinbase_recipients_task<N: Network, C: ConsensusStorage<N>>(
+    consensus: Consensus<N, C>,
+    address: Address<N>,
+) -> Result<JoinHandle<()>> {
+    let mut receiver = subscribe_to_live_config();
+    // Ensure the initial recipients are valid.
+    let recipients = coinbase_recipients(&receiver.borrow_and_update(), address)?;
+    consensus.set_coinbase_recipients(recipients);
+    Ok(tokio::spawn(async move {
+        // Wait for the settings to change.
+        while receiver.changed().await.is_ok() {
+            let config = receiver.borrow_and_update().clone();
+            match coinbase_recipients(&config, address) {
+                Ok(recipients) => {
+                    let list = recipients.recipients().iter().map(ToString::to_string).collect::<Vec<_>>();
+                    info!("Updated the coinbase recipients to [{}]", list.join(", "));
+                    consensus.set_coinbase_recipients(recipients);
+                }
+                Err(error) => warn!("Kept the current coinbase recipients - {error}"),
+            }
+        }
+    }))
+}
+
+/// The unconfirmed transactions of the memory pool, persisted in the database next to the ledger.
+struct PersistedMemoryPool<N: Network>(MemoryPoolStore<N>);
+
+impl<N: Network> BatchWriter<N::TransactionID, (Transaction<N>, i64)> for PersistedMemoryPool<N> {
+    fn write_batch(&self, batch: &[(N::TransactionID, Option<(Transaction<N>, i64)>)]) -> Result<()> {
+        self.0.write_batch(batch)
+    }
+}
+
+impl<N: Network> MemoryPoolStorage<N> for PersistedMemoryPool<N> {
+    fn transactions(&self) -> Result<Vec<(Transaction<N>, i64)>> {
+        Ok(self.0.transactions())
+    }
+}
+
+/// Persists the memory pool of the given consensus in the database, and restores the persisted transactions.
+/// Note that the transactions accepted within the last flush interval before a crash are lost.
+pub fn persist_memory_pool<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    dev: Option<u16>,
+) -> Result<()> {
+    let storage = PersistedMemoryPool(MemoryPoolStore::<N>::open(dev)?);
+    consensus.memory_pool().set_storage(Arc::new(storage), DEFAULT_FLUSH_INTERVAL, DEFAULT_FLUSH_BYTES);
+    let num_restored = consensus.restore_unconfirmed_transactions()?;
+    if num_restored > 0 {
+        info!("Restored {num_restored} unconfirmed transactions to the memory pool");
+    }
+    Ok(())
+}
+
+/// The blocks that were rejected for their contents, persisted in the database next to the ledger.
+struct PersistedInvalidBlocks<N: Network>(InvalidBlockStore<N>);
+
+impl<N: Network> BatchWriter<N::BlockHash, InvalidBlock> for PersistedInvalidBlocks<N> {
+    fn write_batch(&self, batch: &[(N::BlockHash, Option<InvalidBlock>)]) -> Result<()> {
+        let batch = batch
+            .iter()
+            .map(|(hash, block)| {
+                let entry = block.as_ref().map(|block| {
+                    (block.height, block.reason.clone(), block.first_seen, block.peer_ip)
+                });
+                (*hash, entry)
+            })
+            .collect::<Vec<_>>();
+        self.0.write_batch(&batch)
+    }
+}
+
+impl<N: Network> InvalidBlockStorage<N> for PersistedInvalidBlocks<N> {
+    fn invalid_blocks(&self) -> Result<Vec<(N::BlockHash, InvalidBlock)>> {
+        Ok(self
+            .0
+            .blocks()
+            .into_iter()
+            .map(|(hash, (height, reason, first_seen, peer_ip))| {
+                (hash, InvalidBlock { height, reason, first_seen, peer_ip })
+            })
+            .collect())
+    }
+}
+
+/// Persists the blocks that the given consensus rejects for their contents in the database,
+/// and restores the persisted invalid blocks, so that they are not validated again after a restart.
+pub fn persist_invalid_blocks<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    dev: Option<u16>,
+) -> Result<()> {
+    let storage = PersistedInvalidBlocks(InvalidBlockStore::<N>::open(dev)?);
+    let num_restored = consensus.invalid_blocks().set_storage(Arc::new(storage))?;
+    if num_restored > 0 {
+        info!("Restored {num_restored} known invalid blocks");
+    }
+    Ok(())
+}
+
+/// The blocks that were submitted and committed, persisted in the database next to the ledger.
+struct PersistedSubmittedBlocks<N: Network>(SubmittedBlockStore<N>);
+
+impl<N: Network> BatchWriter<N::BlockHash, SubmittedBlock> for PersistedSubmittedBlocks<N> {
+    fn write_batch(&self, batch: &[(N::BlockHash, Option<SubmittedBlock>)]) -> Result<()> {
+        let batch = batch
+            .iter()
+            .map(|(hash, block)| {
+                let entry = block.as_ref().map(|block| (block.height, block.coinbase_commitment, block.first_seen));
+                (*hash, entry)
+            })
+            .collect::<Vec<_>>();
+        self.0.write_batch(&batch)
+    }
+}
+
+impl<N: Network> SubmittedBlockStorage<N> for PersistedSubmittedBlocks<N> {
+    fn submitted_blocks(&self) -> Result<Vec<(N::BlockHash, SubmittedBlock)>> {
+        Ok(self
+            .0
+            .blocks()
+            .into_iter()
+            .map(|(hash, (height, coinbase_commitment, first_seen))| {
+                (hash, SubmittedBlock { height, coinbase_commitment, first_seen })
+            })
+            .collect())
+    }
+}
+
+/// Persists the blocks that are submitted to the given consensus and committed in the database,
+/// and restores the persisted submitted blocks, so that a duplicate submission is recognized after a restart.
+pub fn persist_submitted_blocks<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    dev: Option<u16>,
+) -> Result<()> {
+    let storage = PersistedSubmittedBlocks(SubmittedBlockStore::<N>::open(dev)?);
+    let num_restored = consensus.submitted_blocks().set_storage(Arc::new(storage))?;
+    if num_restored > 0 {
+        info!("Restored {num_restored} submitted blocks");
+    }
+    Ok(())
+}
+
+/// The audits of the historical blocks, persisted in the database next to the ledger.
+struct PersistedAuditLog<N: Network>(AuditLogStore<N>);
+
+impl<N: Network> BatchWriter<u64, AuditEntry<N>> for PersistedAuditLog<N> {
+    fn write_batch(&self, batch: &[(u64, Option<AuditEntry<N>>)]) -> Result<()> {
+        let batch = batch
+            .iter()
+            .map(|(sequence, entry)| {
+                let entry =
+                    entry.as_ref().map(|entry| (entry.height, entry.hash, entry.failure.clone(), entry.audited_at));
+                (*sequence, entry)
+            })
+            .collect::<Vec<_>>();
+        self.0.write_batch(&batch)
+    }
+}
+
+impl<N: Network> AuditLogStorage<N> for PersistedAuditLog<N> {
+    fn audit_entries(&self) -> Result<Vec<(u64, AuditEntry<N>)>> {
+        Ok(self
+            .0
+            .entries()
+            .into_iter()
+            .map(|(sequence, (height, hash, failure, audited_at))| {
+                (sequence, AuditEntry { height, hash, failure, audited_at })
+            })
+            .collect())
+    }
+}
+
+/// Persists the audits of the historical blocks of the given consensus in the database, and restores the persisted
+/// audits, so that a failed audit is reported after a restart.
+pub fn persist_audit_log<N: Network, C: ConsensusStorage<N>>(
+    consensus: &Consensus<N, C>,
+    dev: Option<u16>,
+) -> Result<()> {
+    let storage = PersistedAuditLog(AuditLogStore::<N>::open(dev)?);
+    let num_restored = consensus.audit_log().set_storage(Arc::new(storage))?;
+    if let Some(entry) = consensus.audit_log().latest_failure() {
+        error!("Restored {num_restored} audits, in which block {} ({}) failed its audit", entry.height, entry.hash);
+    }
+    Ok(())
+}
+
+/// Spawns a task to audit a random historical block of the given consensus at the audit interval, if it is set.
+///
+/// The audits run one at a time on a dedicated thread, so that they take at most a single core from the
+/// verification of the incoming blocks, and are paused entirely while the node is syncing.
+pub fn spawn_block_auditor<N: Network, C: ConsensusStorage<N>>(
+    consensus: Consensus<N, C>,
+    router: Router<N>,
+    config: &NodeConfig,
+) -> Result<Option<JoinHandle<()>>> {
+    let interval = match config.audit_interval {
+        Some(interval) => interval,
+        None => return Ok(None),
+    };
+    // Initialize the single-threaded pool of the audits, which confines the parallel verification of their proofs.
+    #[cfg(feature = "parallel")]
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new().num_threads(1).thread_name(|_| "snarkos-auditor".to_string()).build()?,
+    );
+    info!("Auditing a random historical block every {}s", interval.as_secs());
+    Ok(Some(tokio::spawn(async move {
+        loop {
+            // Sleep until the next audit is due.
+            tokio::time::sleep(interval).await;
+            // Pause the audits while the node is syncing.
+            if router.sync().is_syncing() {
+                trace!("Skipping the audit of a historical block while syncing");
+                continue;
+            }
+            // Audit a random block in a blocking task, on the pool of the audits.
+            let consensus = consensus.clone();
+            #[cfg(feature = "parallel")]
+            let audit = {
+                let pool = pool.clone();
+                move || pool.install(|| consensus.audit_random_block(&mut rand::thread_rng()))
+            };
+            #[cfg(not(feature = "parallel"))]
+            let audit = move || consensus.audit_random_block(&mut rand::thread_rng());
+            match tokio::task::spawn_blocking(audit).await {
+                Ok(Ok(_)) => (),
+                Ok(Err(error)) => warn!("Failed to audit a historical block - {error}"),
+                Err(error) => warn!("Failed to audit a historical block (JoinError): {error}"),
+            }
+        }
+    })))
+}
+
+/// Spawns a task to flush the queued writes of the memory pool to the database, at the flush interval.
+pub fn spawn_memory_pool_flusher<N: Network>(memory_pool: MemoryPool<N>) -> Option<JoinHandle<()>> {
+    let interval = memory_pool.flush_interval()?;
+    Some(tokio::spawn(async move {
+        loop {
+            // Sleep until the next flush is due.
+            tokio::time::sleep(interval).await;
+            // Flush the queued writes in a blocking task, as it writes to the database.
+            let memory_pool = memory_pool.clone();
+            match tokio::task::spawn_blocking(move || memory_pool.flush_storage()).await {
+                Ok(Ok(())) => (),
+                Ok(Err(error)) => warn!("Failed to persist the memory pool - {error}"),
+                Err(error) => warn!("Failed to persist the memory pool (JoinError): {error}"),
+            }
+        }
+    }))
+}
+
+/// Spawns the sweeper of the persisted invalid blocks, which deletes them from storage once they expire.
+pub fn spawn_invalid_block_sweeper<N: Network>(dev: Option<u16>) -> Result<JoinHandle<()>> {
+    let store = InvalidBlockStore::<N>::open(dev)?;
+    Ok(tokio::spawn(async move {
+        loop {
+            // Sleep until the next sweep is due.
+            tokio::time::sleep(DEFAULT_TTL_SWEEP_INTERVAL).await;
+            // Sweep the expired blocks in a blocking task, as it writes to the database.
+            let store = store.clone();
+            match tokio::task::spawn_blocking(move || store.sweep_expired()).await {
+                Ok(Ok(0)) => (),
+                Ok(Ok(num_swept)) => debug!("Deleted {num_swept} expired invalid blocks"),
+                Ok(Err(error)) => warn!("Failed to delete the expired invalid blocks - {error}"),
+                Err(error) => warn!("Failed to delete the expired invalid blocks (JoinError): {error}"),
+            }
+        }
+    }))
+}
 
 /// Returns the block locators for the given ledger.
 pub fn get_block_locators<N: Network, C: ConsensusStorage<N>>(ledger: &Ledger<N, C>) -> Result<BlockLocators<N>> {
@@ -46,6 +732,23 @@ pub fn get_block_locators<N: Network, C: ConsensusStorage<N>>(ledger: &Ledger<N,
     Ok(BlockLocators::new(recents, checkpoints))
 }
 
+/// Returns the block locators for the given ledger, read through the storage guard of the given router.
+/// If the storage stalls, the block locators of the canonical blocks in the sync pool are returned instead,
+/// so that the pings keep flowing while the node is degraded.
+pub async fn get_block_locators_guarded<N: Network, C: ConsensusStorage<N>>(
+    router: &Router<N>,
+    ledger: &Ledger<N, C>,
+) -> Result<BlockLocators<N>> {
+    let ledger = ledger.clone();
+    match router.storage_guard().run(OperationClass::Read, move || get_block_locators(&ledger)).await {
+        Ok(block_locators) => Ok(block_locators),
+        Err(error) => {
+            warn!("Failed to read the block locators from storage - {error}");
+            router.sync().get_canon_locators()
+        }
+    }
+}
+
 /// A helper to log instructions to recover.
 pub fn log_clean_error(dev: Option<u16>) {
     match dev {
@@ -53,3 +756,107 @@ pub fn log_clean_error(dev: Option<u16>) {
         None => error!("Storage corruption detected! Run `snarkos clean` to reset storage"),
     }
 }
+
+/// Spawns a task to log a summary of the storage statistics once a day.
+pub fn spawn_storage_statistics_logger() -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            // Sleep until the next summary is due.
+            tokio::time::sleep(STORAGE_STATISTICS_INTERVAL).await;
+            // Compute the storage statistics in a blocking task, as it scans the database.
+            match tokio::task::spawn_blocking(|| storage_statistics(MAX_ENTRIES_PER_COLUMN)).await {
+                Ok(Ok(statistics)) => info!("Storage: {statistics}"),
+                Ok(Err(error)) => warn!("Failed to compute the storage statistics: {error}"),
+                Err(error) => warn!("Failed to compute the storage statistics (JoinError): {error}"),
+            }
+        }
+    })
+}
+
+/// Restores the options of the storage tuning profile, if the bulk-sync options are enabled
+/// and the node has reached the tip of its peers.
+pub fn restore_storage_at_tip<N: Network>(router: &Router<N>) {
+    // Ensure the bulk-sync options are enabled, and the node is connected to peers that are not ahead of it.
+    if !is_bulk_sync() || router.number_of_connected_peers() == 0 || router.sync().find_sync_peers().is_some() {
+        return;
+    }
+    match set_bulk_sync(false) {
+        Ok(()) => info!("Reached the tip, restored the '{}' storage profile", tuning_profile().at_tip()),
+        Err(error) => warn!("Failed to restore the storage profile: {error}"),
+    }
+}
+
+/// Spawns a task to prune the bodies of the blocks beyond the prune depth, if one is set.
+///
+/// The ledger and the router stop serving the bodies of the blocks before they are pruned from storage,
+/// and a prune that fails is retried on the next interval, as the pruner resumes from the persisted height.
+pub fn spawn_block_pruner<N: Network, C: ConsensusStorage<N>>(
+    ledger: Ledger<N, C>,
+    router: Router<N>,
+    dev: Option<u16>,
+    config: &NodeConfig,
+) -> Option<JoinHandle<()>> {
+    let depth = config.prune_depth?;
+    Some(tokio::spawn(async move {
+        loop {
+            // Sleep until the next prune is due.
+            tokio::time::sleep(PRUNE_INTERVAL).await;
+            // Stop serving the bodies of the blocks beyond the prune depth.
+            let height = ledger.latest_height().saturating_sub(depth);
+            ledger.set_pruned_height(height);
+            router.set_pruned_height(ledger.pruned_height());
+            // Prune the blocks in a blocking task, as it writes to the database.
+            match tokio::task::spawn_blocking(move || BlockPruner::<N>::open(dev)?.prune(height)).await {
+                Ok(Ok(pruned_height)) => debug!("Pruned the blocks below {pruned_height}"),
+                Ok(Err(error)) => warn!("Failed to prune the blocks below {height} - {error}"),
+                Err(error) => warn!("Failed to prune the blocks below {height} (JoinError): {error}"),
+            }
+        }
+    }))
+}
+
+/// Spawns a task to apply the settings that can change while the node is running, to the given components.
+/// The current settings are applied immediately, and every change is applied as a whole.
+pub fn spawn_live_config_task<N: Network>(
+    router: Router<N>,
+    memory_pool: Option<MemoryPool<N>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    request_telemetry: Option<Arc<RequestTelemetry>>,
+) -> JoinHandle<()> {
+    let mut receiver = subscribe_to_live_config();
+    tokio::spawn(async move {
+        loop {
+            // Apply the current settings.
+            let config = receiver.borrow_and_update().clone();
+            router.set_max_connected_peers(config.max_peers.map(usize::from));
+            if let Some(memory_pool) = &memory_pool {
+                memory_pool.set_byte_budget(config.mempool_byte_budget);
+            }
+            if let Some(rate_limiter) = &rate_limiter {
+                rate_limiter.set_limit(config.rest_rate_limit);
+            }
+            if let Some(request_telemetry) = &request_telemetry {
+                request_telemetry.set_slow_threshold(config.rest_slow_request_ms.map(Duration::from_millis));
+            }
+            debug!("Applied the live configuration {config:?}");
+
+            // Wait for the settings to change.
+            if receiver.changed().await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Records the given rejected block as a critical event, and dumps it to the rejected blocks directory, if one is set.
+pub fn dump_rejected_block<N: Network>(block: &Block<N>, config: &NodeConfig) {
+    warn!(target: "critical", kind = "invalid-block", "Rejected the block {} ('{}')", block.height(), block.hash());
+    if let Some(directory) = &config.rejected_blocks_dir {
+        let path = directory.join(format!("{}-{}.block", block.height(), block.hash()));
+        match block.to_bytes_le().map(|bytes| std::fs::write(&path, bytes)) {
+            Ok(Ok(())) => info!("Dumped the rejected block {} to '{}'", block.height(), path.display()),
+            Ok(Err(error)) => warn!("Failed to dump the rejected block {} - {error}", block.height()),
+            Err(error) => warn!("Failed to serialize the rejected block {} - {error}", block.height()),
+        }
+    }
+}
@@ -35,13 +35,17 @@ mod validator;
 pub use validator::*;
 
 mod helpers;
+pub use helpers::{set_live_config, subscribe_to_live_config, LiveConfig, NodeConfig};
 
 mod traits;
 pub use traits::*;
 
-pub use snarkos_node_messages::NodeType;
+pub use snarkos_node_messages::{Compression, DecodeMode, NodeRole, NodeType, OnionAddr};
+pub use snarkos_node_router::{DiffusionConfig, Privacy, StoragePolicy, DEFAULT_MAX_SNAPSHOT_STREAMS};
+pub use snarkos_node_tcp::ProxyConfig;
 
 use snarkos_account::Account;
+use snarkos_node_router::Outbound;
 use snarkos_node_store::ConsensusDB;
 use snarkvm::prelude::{Address, Block, ConsensusMemory, Network, PrivateKey, ViewKey};
 
@@ -69,8 +73,11 @@ impl<N: Network> Node<N> {
         genesis: Block<N>,
         cdn: Option<String>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
-        Ok(Self::Beacon(Arc::new(Beacon::new(node_ip, rest_ip, account, trusted_peers, genesis, cdn, dev).await?)))
+        Ok(Self::Beacon(Arc::new(
+            Beacon::new(node_ip, rest_ip, account, trusted_peers, genesis, cdn, dev, config).await?,
+        )))
     }
 
     /// Initializes a new validator node.
@@ -82,9 +89,10 @@ impl<N: Network> Node<N> {
         genesis: Block<N>,
         cdn: Option<String>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
         Ok(Self::Validator(Arc::new(
-            Validator::new(node_ip, rest_ip, account, trusted_peers, genesis, cdn, dev).await?,
+            Validator::new(node_ip, rest_ip, account, trusted_peers, genesis, cdn, dev, config).await?,
         )))
     }
 
@@ -95,8 +103,9 @@ impl<N: Network> Node<N> {
         trusted_peers: &[SocketAddr],
         genesis: Block<N>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
-        Ok(Self::Prover(Arc::new(Prover::new(node_ip, account, trusted_peers, genesis, dev).await?)))
+        Ok(Self::Prover(Arc::new(Prover::new(node_ip, account, trusted_peers, genesis, dev, config).await?)))
     }
 
     /// Initializes a new client node.
@@ -106,8 +115,9 @@ impl<N: Network> Node<N> {
         trusted_peers: &[SocketAddr],
         genesis: Block<N>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
-        Ok(Self::Client(Arc::new(Client::new(node_ip, account, trusted_peers, genesis, dev).await?)))
+        Ok(Self::Client(Arc::new(Client::new(node_ip, account, trusted_peers, genesis, dev, config).await?)))
     }
 
     /// Returns the node type.
@@ -159,4 +169,25 @@ impl<N: Network> Node<N> {
             Self::Client(node) => node.is_dev(),
         }
     }
+
+    /// Returns the number of connected peers.
+    pub fn number_of_connected_peers(&self) -> usize {
+        match self {
+            Self::Beacon(node) => node.router().number_of_connected_peers(),
+            Self::Validator(node) => node.router().number_of_connected_peers(),
+            Self::Prover(node) => node.router().number_of_connected_peers(),
+            Self::Client(node) => node.router().number_of_connected_peers(),
+        }
+    }
+}
+
+impl<N: Network> Clone for Node<N> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Beacon(node) => Self::Beacon(node.clone()),
+            Self::Validator(node) => Self::Validator(node.clone()),
+            Self::Prover(node) => Self::Prover(node.clone()),
+            Self::Client(node) => Self::Client(node.clone()),
+        }
+    }
 }
@@ -16,10 +16,11 @@
 
 mod router;
 
-use crate::traits::NodeInterface;
+use crate::{helpers::NodeConfig, traits::NodeInterface};
 use snarkos_account::Account;
+use snarkos_node_consensus::{ConsensusRule, RuleActivations};
 use snarkos_node_messages::{Data, Message, NodeType, UnconfirmedSolution};
-use snarkos_node_router::{Heartbeat, Inbound, Outbound, Router, Routing};
+use snarkos_node_router::{Heartbeat, Inbound, Outbound, Router, Routing, SerialBlock};
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake, Reading, Writing},
     P2P,
@@ -53,6 +54,8 @@ pub struct Prover<N: Network, C: ConsensusStorage<N>> {
     latest_epoch_challenge: Arc<RwLock<Option<Arc<EpochChallenge<N>>>>>,
     /// The latest block header.
     latest_block_header: Arc<RwLock<Option<Header<N>>>>,
+    /// The activation height of the header commitments, if it is scheduled.
+    header_commitments_height: Option<u32>,
     /// The number of puzzle instances.
     puzzle_instances: Arc<AtomicU8>,
     /// The maximum number of puzzle instances.
@@ -73,6 +76,7 @@ impl<N: Network, C: ConsensusStorage<N>> Prover<N, C> {
         trusted_peers: &[SocketAddr],
         genesis: Block<N>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
         // Initialize the node router.
         let router = Router::new(
@@ -80,12 +84,32 @@ impl<N: Network, C: ConsensusStorage<N>> Prover<N, C> {
             NodeType::Prover,
             account,
             trusted_peers,
+            crate::helpers::noise_config::<N>(dev, &config)?,
+            crate::helpers::peer_book::<N>(dev)?,
+            Default::default(),
             Self::MAXIMUM_NUMBER_OF_PEERS as u16,
             dev.is_some(),
         )
         .await?;
+        // Set the role in which the node operates on the network.
+        router.set_role(config.role);
+        // Set the compression of the large messages sent to the peers that advertise it.
+        router.set_compression(config.compression);
+        // Set the settings of the diffusion of the local transactions.
+        router.diffusion().set_config(config.diffusion);
+        // Set the throughput below which a sync peer is demoted.
+        router.sync().set_throughput_floor(config.sync_throughput_floor);
+        // Set the multiple of the block time after which the tip is stale.
+        router.sync().set_stale_tip_multiple(config.stale_tip_multiple);
+        // Set the DNS seeds, if they are specified.
+        crate::helpers::apply_dns_seeds(&router, &config);
+        // Route the outbound connections through the proxy, if one is specified.
+        crate::helpers::apply_proxy(&router, &config)?;
         // Load the coinbase puzzle.
         let coinbase_puzzle = CoinbasePuzzle::<N>::load()?;
+        // Load the activation height of the header commitments, which selects the layout of the relayed headers.
+        let header_commitments_height =
+            RuleActivations::load::<N>()?.activation_height(ConsensusRule::HeaderCommitments);
         // Compute the maximum number of puzzle instances.
         let max_puzzle_instances = num_cpus::get().saturating_sub(2).clamp(1, 6);
         // Initialize the node.
@@ -95,12 +119,15 @@ impl<N: Network, C: ConsensusStorage<N>> Prover<N, C> {
             coinbase_puzzle,
             latest_epoch_challenge: Default::default(),
             latest_block_header: Default::default(),
+            header_commitments_height,
             puzzle_instances: Default::default(),
             max_puzzle_instances: u8::try_from(max_puzzle_instances)?,
             handles: Default::default(),
             shutdown: Default::default(),
             _phantom: Default::default(),
         };
+        // Initialize the live configuration.
+        node.handles.lock().push(crate::helpers::spawn_live_config_task(node.router.clone(), None, None, None));
         // Initialize the routing.
         node.initialize_routing().await;
         // Initialize the coinbase puzzle.
@@ -53,6 +53,7 @@ pub trait NodeInterface<N: Network>: Routing<N> {
             match tokio::signal::ctrl_c().await {
                 Ok(()) => {
                     node.shut_down().await;
+                    info!(target: "critical", kind = "shutdown", "Shut down the node (interrupted)");
                     std::process::exit(0);
                 }
                 Err(error) => error!("tokio::signal::ctrl_c encountered an error: {}", error),
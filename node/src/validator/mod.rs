@@ -16,20 +16,23 @@
 
 mod router;
 
-use crate::traits::NodeInterface;
+use crate::{
+    helpers::{NodeConfig, SnapshotSource},
+    traits::NodeInterface,
+};
 use snarkos_account::Account;
-use snarkos_node_consensus::Consensus;
+use snarkos_node_consensus::{Consensus, ParameterRegistry};
 use snarkos_node_ledger::Ledger;
 use snarkos_node_messages::{BlockRequest, Message, NodeType, PuzzleResponse, UnconfirmedSolution};
 use snarkos_node_rest::Rest;
-use snarkos_node_router::{Heartbeat, Inbound, Outbound, Router, Routing};
+use snarkos_node_router::{BlockPipeline, Heartbeat, Inbound, Outbound, PipelineStages, Router, Routing, SerialBlock};
 use snarkos_node_tcp::{
     protocols::{Disconnect, Handshake, Reading, Writing},
     P2P,
 };
-use snarkvm::prelude::{Block, ConsensusStorage, Header, Network, ProverSolution};
+use snarkvm::prelude::{Block, ConsensusStorage, Network, ProverSolution};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use parking_lot::Mutex;
 use std::{
     net::SocketAddr,
@@ -39,7 +42,10 @@ use std::{
     },
     time::Duration,
 };
-use tokio::task::JoinHandle;
+use tokio::{sync::Notify, task::JoinHandle};
+
+/// The duration in seconds to wait for the connection to the trusted peer to bootstrap the ledger from.
+const SNAPSHOT_CONNECT_TIMEOUT_IN_SECS: u64 = 30;
 
 /// A validator is a full node, capable of validating blocks.
 #[derive(Clone)]
@@ -52,6 +58,14 @@ pub struct Validator<N: Network, C: ConsensusStorage<N>> {
     router: Router<N>,
     /// The REST server of the node.
     rest: Option<Rest<N, C, Self>>,
+    /// The block processing pipeline of the node.
+    pipeline: BlockPipeline<SerialBlock<N>>,
+    /// The notification of new block responses in the sync pool.
+    sync_notify: Arc<Notify>,
+    /// The snapshot of the ledger served by the node.
+    snapshot: SnapshotSource<N, C>,
+    /// The flag that holds the block requests of the sync, while the ledger is bootstrapped from a snapshot.
+    snapshot_pending: Arc<AtomicBool>,
     /// The spawned handles.
     handles: Arc<Mutex<Vec<JoinHandle<()>>>>,
     /// The shutdown signal.
@@ -68,9 +82,12 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
         genesis: Block<N>,
         cdn: Option<String>,
         dev: Option<u16>,
+        config: NodeConfig,
     ) -> Result<Self> {
+        // Load and check the verifying keys, before the ledger uses them.
+        let parameters = ParameterRegistry::<N>::load()?;
         // Initialize the ledger.
-        let ledger = Ledger::load(genesis, dev)?;
+        let ledger = crate::helpers::load_ledger(genesis, dev, &config)?;
         // Initialize the CDN.
         if let Some(base_url) = cdn {
             // Sync the ledger with the CDN.
@@ -80,7 +97,25 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
             }
         }
         // Initialize the consensus.
-        let consensus = Consensus::new(ledger.clone(), dev.is_some())?;
+        let mut consensus = Consensus::new(ledger.clone(), dev.is_some())?;
+        // Set the verifying keys that were checked at startup.
+        consensus.set_parameters(parameters)?;
+        // Set the policy for replacing conflicting transactions in the memory pool.
+        consensus.memory_pool().set_replacement_policy(config.replacement_policy);
+        // Set the policy for expiring transactions from the memory pool.
+        consensus.memory_pool().set_expiry_policy(config.expiry_policy);
+        // Set the fee delta that makes a block template stale.
+        consensus.set_template_fee_delta(config.template_fee_delta);
+        // Register the policy hooks of the memory pool and the block template.
+        crate::helpers::register_policy_hooks(&mut consensus, &config)?;
+        // Persist the memory pool, and restore the persisted transactions.
+        crate::helpers::persist_memory_pool(&consensus, dev)?;
+        // Persist the blocks that are rejected for their contents, and restore the known invalid blocks.
+        crate::helpers::persist_invalid_blocks(&consensus, dev)?;
+        // Persist the blocks that are submitted and committed, and restore the known submitted blocks.
+        crate::helpers::persist_submitted_blocks(&consensus, dev)?;
+        // Persist the audits of the historical blocks, and restore the persisted audits.
+        crate::helpers::persist_audit_log(&consensus, dev)?;
 
         // Initialize the node router.
         let router = Router::new(
@@ -88,10 +123,45 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
             NodeType::Validator,
             account,
             trusted_peers,
+            crate::helpers::noise_config::<N>(dev, &config)?,
+            crate::helpers::peer_book::<N>(dev)?,
+            ledger.event_bus().clone(),
             Self::MAXIMUM_NUMBER_OF_PEERS as u16,
             dev.is_some(),
         )
         .await?;
+        // Set the role in which the node operates on the network.
+        router.set_role(config.role);
+        // Set the compression of the large messages sent to the peers that advertise it.
+        router.set_compression(config.compression);
+        // Set the settings of the diffusion of the local transactions.
+        router.diffusion().set_config(config.diffusion);
+        // Set the throughput below which a sync peer is demoted.
+        router.sync().set_throughput_floor(config.sync_throughput_floor);
+        // Set the multiple of the block time after which the tip is stale.
+        router.sync().set_stale_tip_multiple(config.stale_tip_multiple);
+        // Set the DNS seeds, if they are specified.
+        crate::helpers::apply_dns_seeds(&router, &config);
+        // Route the outbound connections through the proxy, if one is specified.
+        crate::helpers::apply_proxy(&router, &config)?;
+        // Set the timeouts, retries, and circuit breaker of the storage operations.
+        router.storage_guard().set_policy(config.storage_policy);
+        // Advertise the pruned blocks to the peers.
+        router.set_pruned_height(ledger.pruned_height());
+        // Share the network-adjusted time with the ledger, which checks the next blocks against it.
+        router.set_network_time(ledger.network_time().clone());
+        // Set the maximum number of snapshot streams served to the trusted peers.
+        crate::helpers::apply_snapshot_options(&router, &config);
+
+        // Initialize the block processing pipeline.
+        let stages =
+            SyncStages { consensus: consensus.clone(), router: router.clone(), config: Arc::new(config.clone()) };
+        let pipeline = BlockPipeline::start(
+            ledger.latest_height() + 1,
+            config.sync_byte_budget,
+            stages,
+            router.storage_guard().clone(),
+        );
 
         // Initialize the node.
         let mut node = Self {
@@ -99,18 +169,60 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
             consensus: consensus.clone(),
             router,
             rest: None,
+            pipeline,
+            sync_notify: Default::default(),
+            snapshot: SnapshotSource::new(ledger.clone()),
+            snapshot_pending: Default::default(),
             handles: Default::default(),
             shutdown: Default::default(),
         };
 
         // Initialize the REST server.
         if let Some(rest_ip) = rest_ip {
-            node.rest = Some(Rest::start(rest_ip, Some(consensus), ledger, Arc::new(node.clone()))?);
+            node.rest = Some(Rest::start(
+                config.rest_listeners(rest_ip),
+                Some(consensus),
+                ledger,
+                Arc::new(node.clone()),
+                config.response_cache(),
+            )?);
+        }
+        // Initialize the storage statistics logger.
+        node.handles.lock().push(crate::helpers::spawn_storage_statistics_logger());
+        // Initialize the block pruner.
+        let pruner = crate::helpers::spawn_block_pruner(node.ledger.clone(), node.router.clone(), dev, &config);
+        if let Some(handle) = pruner {
+            node.handles.lock().push(handle);
+        }
+        // Initialize the auditor of the historical blocks.
+        let auditor = crate::helpers::spawn_block_auditor(node.consensus.clone(), node.router.clone(), &config)?;
+        if let Some(handle) = auditor {
+            node.handles.lock().push(handle);
+        }
+        // Initialize the memory pool flusher.
+        if let Some(handle) = crate::helpers::spawn_memory_pool_flusher(node.consensus.memory_pool().clone()) {
+            node.handles.lock().push(handle);
         }
+        // Initialize the sweeper of the expired invalid blocks.
+        node.handles.lock().push(crate::helpers::spawn_invalid_block_sweeper::<N>(dev)?);
+        // Initialize the live configuration.
+        node.handles.lock().push(crate::helpers::spawn_live_config_task(
+            node.router.clone(),
+            Some(node.consensus.memory_pool().clone()),
+            node.rest.as_ref().map(|rest| rest.rate_limiter().clone()),
+            node.rest.as_ref().map(|rest| rest.telemetry().clone()),
+        ));
+        // Hold the sync, if the empty ledger is bootstrapped from the snapshot of a trusted peer.
+        let snapshot_peer = config.snapshot_from.filter(|_| node.ledger.latest_height() == 0);
+        node.snapshot_pending.store(snapshot_peer.is_some(), Ordering::Relaxed);
         // Initialize the sync pool.
         node.initialize_sync()?;
         // Initialize the routing.
         node.initialize_routing().await;
+        // Initialize the bootstrap from the snapshot.
+        if let Some(peer_ip) = snapshot_peer {
+            node.initialize_snapshot_bootstrap(peer_ip);
+        }
         // Initialize the signal handler.
         node.handle_signals();
         // Return the node.
@@ -142,9 +254,19 @@ impl<N: Network, C: ConsensusStorage<N>> NodeInterface<N> for Validator<N, C> {
         trace!("Shutting down the validator...");
         self.handles.lock().iter().for_each(|handle| handle.abort());
 
+        // Shut down the block processing pipeline.
+        trace!("Shutting down the block pipeline...");
+        self.pipeline.shut_down();
+
         // Shut down the router.
         self.router.shut_down().await;
 
+        // Flush the memory pool.
+        trace!("Flushing the memory pool...");
+        if let Err(error) = self.consensus.memory_pool().flush_storage() {
+            warn!("Failed to persist the memory pool - {error}");
+        }
+
         // Shut down the ledger.
         trace!("Shutting down the ledger...");
         // self.ledger.shut_down().await;
@@ -161,6 +283,15 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
         // Insert the canon locators into the sync pool.
         self.router.sync().insert_canon_locators(canon_locators).unwrap();
 
+        // Start forwarding the blocks from the sync pool into the block pipeline, on every new block response.
+        let validator = self.clone();
+        self.handles.lock().push(tokio::spawn(async move {
+            loop {
+                validator.sync_notify.notified().await;
+                validator.forward_sync_blocks().await;
+            }
+        }));
+
         // Start the sync loop.
         let validator = self.clone();
         self.handles.lock().push(tokio::spawn(async move {
@@ -174,10 +305,27 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
                 // Sleep briefly to avoid triggering spam detection.
                 tokio::time::sleep(Duration::from_secs(1)).await;
 
+                // If the ledger is being bootstrapped from a snapshot, skip requesting blocks until it ends.
+                if validator.snapshot_pending.load(Ordering::Relaxed) {
+                    trace!("Skipping block requests, as the ledger is being bootstrapped from a snapshot");
+                    continue;
+                }
+
+                // If the block pipeline is full, skip requesting more blocks until it drains.
+                if validator.pipeline.is_stalled() {
+                    trace!("Skipping block requests, as the block pipeline is full");
+                    continue;
+                }
+
                 // Prepare the block requests, if any.
                 let block_requests = validator.router.sync().prepare_block_requests();
                 trace!("Prepared {} block requests", block_requests.len());
 
+                // If there is nothing left to request, restore the storage options that were relaxed for the sync.
+                if block_requests.is_empty() {
+                    crate::helpers::restore_storage_at_tip(&validator.router);
+                }
+
                 // Process the block requests.
                 'outer: for (height, (hash, previous_hash, sync_ips)) in block_requests {
                     // Insert the block request into the sync pool.
@@ -208,31 +356,80 @@ impl<N: Network, C: ConsensusStorage<N>> Validator<N, C> {
         Ok(())
     }
 
-    /// Attempts to advance with blocks from the sync pool.
-    fn advance_with_sync_blocks(&self) {
-        // Retrieve the latest block height.
-        let mut current_height = self.ledger.latest_height();
-        // Try to advance the ledger with the sync pool.
-        while let Some(block) = self.router.sync().remove_block_response(current_height + 1) {
-            // Ensure the block height matches.
-            if block.height() != current_height + 1 {
-                warn!("Block height mismatch: expected {}, found {}", current_height + 1, block.height());
-                break;
+    /// Bootstraps the empty ledger from the snapshot of the given trusted peer, in the background. The sync resumes
+    /// once the bootstrap ends, from the imported blocks, or from the genesis block if the bootstrap failed.
+    fn initialize_snapshot_bootstrap(&self, peer_ip: SocketAddr) {
+        let validator = self.clone();
+        self.handles.lock().push(tokio::spawn(async move {
+            // Wait for the connection to the peer, which the heartbeat keeps connected as a trusted peer.
+            for _ in 0..SNAPSHOT_CONNECT_TIMEOUT_IN_SECS {
+                if validator.router.is_connected(&peer_ip) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
             }
-            // Check the next block.
-            if let Err(error) = self.consensus.check_next_block(&block) {
-                warn!("The next block ({}) is invalid - {error}", block.height());
-                break;
+            // Bootstrap the ledger, and fall back to the sync on failure.
+            match validator.bootstrap_from_snapshot(peer_ip).await {
+                Ok(height) => info!("Bootstrapped the ledger up to block {height} from the snapshot of '{peer_ip}'"),
+                Err(error) => warn!("Failed to bootstrap from the snapshot of '{peer_ip}', syncing instead - {error}"),
             }
-            // Attempt to advance to the next block.
-            if let Err(error) = self.consensus.advance_to_next_block(&block) {
-                warn!("{error}");
+            validator.snapshot_pending.store(false, Ordering::Relaxed);
+        }));
+    }
+
+    /// Forwards the blocks from the sync pool into the block processing pipeline, in order.
+    async fn forward_sync_blocks(&self) {
+        while let Some(block) = self.router.sync().remove_block_response(self.pipeline.next_height()) {
+            let (height, hash) = (block.block().height(), block.block().hash());
+            // Insert the height and hash as canon in the sync pool, as the block is no longer requested.
+            self.router.sync().insert_canon_locator(height, hash);
+            // Push the block into the pipeline, waiting for room if the pipeline is full.
+            if !self.pipeline.push(block).await {
+                // If the pipeline moved back to an earlier block, revert the canon block hashes from this block.
+                self.router.sync().remove_canon_locators(height);
                 break;
             }
-            // Insert the height and hash as canon in the sync pool.
-            self.router.sync().insert_canon_locator(block.height(), block.hash());
-            // Increment the latest height.
-            current_height += 1;
         }
     }
 }
+
+/// The stages of the block processing pipeline of a validator.
+#[derive(Clone)]
+struct SyncStages<N: Network, C: ConsensusStorage<N>> {
+    /// The consensus module of the node.
+    consensus: Consensus<N, C>,
+    /// The router of the node.
+    router: Router<N>,
+    /// The settings of the node.
+    config: Arc<NodeConfig>,
+}
+
+impl<N: Network, C: ConsensusStorage<N>> PipelineStages<SerialBlock<N>> for SyncStages<N, C> {
+    /// Verifies the transactions of the given block, ahead of the blocks before it.
+    fn verify(&self, block: &SerialBlock<N>) -> Result<()> {
+        self.consensus.preverify_block(block.block())
+    }
+
+    /// Checks the given block is the valid next block, and advances the ledger to it.
+    fn commit(&self, block: SerialBlock<N>) -> Result<()> {
+        let peer_ip = block.peer_ip();
+        let block = block.into_block();
+        // Check the next block.
+        if let Err(error) = self.consensus.check_next_block(&block) {
+            crate::helpers::dump_rejected_block(&block, &self.config);
+            // Track the rejected block as a side branch, if it is connected to the known blocks.
+            if let Err(error) = self.consensus.insert_rejected_block(&block, &error.to_string(), peer_ip) {
+                trace!("Skipped tracking the rejected block ({}) - {error}", block.height());
+            }
+            bail!("The next block ({}) is invalid - {error}", block.height());
+        }
+        // Attempt to advance to the next block.
+        self.consensus.advance_to_next_block(&block)
+    }
+
+    /// Reverts the canon block hashes in the sync pool from the rejected block, so that it is requested again.
+    fn reject(&self, height: u32, error: anyhow::Error) {
+        warn!("{error}");
+        self.router.sync().remove_canon_locators(height);
+    }
+}
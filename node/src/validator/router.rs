@@ -16,21 +16,26 @@
 
 use super::*;
 
+use snarkos_node_consensus::fee_rate;
 use snarkos_node_messages::{
     BlockRequest,
     BlockResponse,
+    CommittedHeader,
     Data,
     DataBlocks,
     DisconnectReason,
     Message,
-    MessageCodec,
+    PeerCodec,
     Ping,
     Pong,
+    SnapshotManifest,
     UnconfirmedTransaction,
 };
 use snarkos_node_tcp::{Connection, ConnectionSide, Tcp};
 use snarkvm::prelude::{error, EpochChallenge, Network, Transaction};
 
+use anyhow::{bail, Result};
+use bytes::Bytes;
 use futures_util::sink::SinkExt;
 use std::{io, net::SocketAddr, time::Duration};
 
@@ -50,10 +55,12 @@ impl<N: Network, C: ConsensusStorage<N>> Handshake for Validator<N, C> {
         let conn_side = connection.side();
         let stream = self.borrow_stream(&mut connection);
         let genesis_header = self.ledger.get_header(0).map_err(|e| error(format!("{e}")))?;
-        let (peer_ip, mut framed) = self.router.handshake(peer_addr, stream, conn_side, genesis_header).await?;
+        let genesis_hash = self.ledger.get_hash(0).map_err(|e| error(format!("{e}")))?;
+        let (peer_ip, mut framed) =
+            self.router.handshake(peer_addr, stream, conn_side, genesis_header, genesis_hash).await?;
 
         // Retrieve the block locators.
-        let block_locators = match crate::helpers::get_block_locators(&self.ledger) {
+        let block_locators = match crate::helpers::get_block_locators_guarded(&self.router, &self.ledger).await {
             Ok(block_locators) => Some(block_locators),
             Err(e) => {
                 error!("Failed to get block locators: {e}");
@@ -82,25 +89,25 @@ impl<N: Network, C: ConsensusStorage<N>> Disconnect for Validator<N, C> {
 
 #[async_trait]
 impl<N: Network, C: ConsensusStorage<N>> Writing for Validator<N, C> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates an [`Encoder`] used to write the outbound messages to the target stream.
     /// The `side` parameter indicates the connection side **from the node's perspective**.
-    fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router.codec(peer_addr)
     }
 }
 
 #[async_trait]
 impl<N: Network, C: ConsensusStorage<N>> Reading for Validator<N, C> {
-    type Codec = MessageCodec<N>;
+    type Codec = PeerCodec<N>;
     type Message = Message<N>;
 
     /// Creates a [`Decoder`] used to interpret messages from the network.
     /// The `side` param indicates the connection side **from the node's perspective**.
-    fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.router.codec(peer_addr)
     }
 
     /// Processes a message received from the network.
@@ -139,6 +146,13 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Validator<N, C> {
     fn block_request(&self, peer_ip: SocketAddr, message: BlockRequest) -> bool {
         let BlockRequest { start_height, end_height } = &message;
 
+        // Ignore the request if the blocks are pruned, as the peer was told so in the handshake.
+        let pruned_height = self.ledger.pruned_height();
+        if (*start_height..*end_height).any(|height| height > 0 && height < pruned_height) {
+            debug!("Ignoring a request from '{peer_ip}' for the pruned blocks {start_height} to {end_height}");
+            return true;
+        }
+
         // Retrieve the blocks within the requested range.
         let blocks = match self.ledger.get_blocks(*start_height..*end_height) {
             Ok(blocks) => Data::Object(DataBlocks(blocks)),
@@ -153,17 +167,27 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Validator<N, C> {
     }
 
     /// Handles a `BlockResponse` message.
-    fn block_response(&self, peer_ip: SocketAddr, blocks: Vec<Block<N>>) -> bool {
+    fn block_response(&self, peer_ip: SocketAddr, blocks: Vec<SerialBlock<N>>) -> bool {
         // Insert the candidate blocks into the sync pool.
         for block in blocks {
+            // Ensure the block is not known to be invalid, penalizing the peer more for every repeat.
+            if let Some(invalid) = self.consensus.invalid_blocks().get_block(block.block()) {
+                let penalty = self.router().record_known_invalid_block(&peer_ip);
+                warn!(
+                    "Peer '{peer_ip}' sent the known invalid block {} (penalty {penalty}) - {}",
+                    invalid.height,
+                    invalid.reason
+                );
+                return false;
+            }
             if let Err(error) = self.router().sync().insert_block_response(peer_ip, block) {
                 warn!("{error}");
                 return false;
             }
         }
 
-        // Tries to advance with blocks from the sync pool.
-        self.advance_with_sync_blocks();
+        // Notify the forwarding of the blocks from the sync pool into the block pipeline.
+        self.sync_notify.notify_one();
         true
     }
 
@@ -177,7 +201,7 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Validator<N, C> {
             // Check that the peer is still connected.
             if self_clone.router().is_connected(&peer_ip) {
                 // Retrieve the block locators.
-                match crate::helpers::get_block_locators(&self_clone.ledger) {
+                match crate::helpers::get_block_locators_guarded(self_clone.router(), &self_clone.ledger).await {
                     // Send a `Ping` message to the peer.
                     Ok(block_locators) => self_clone.send_ping(peer_ip, Some(block_locators)),
                     Err(e) => error!("Failed to get block locators: {e}"),
@@ -197,15 +221,36 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Validator<N, C> {
                 return false;
             }
         };
-        // Retrieve the latest block header.
-        let block_header = Data::Object(self.ledger.latest_header());
+        // Retrieve the latest block header, with its commitments from their activation on, signed by this node.
+        let block_header = match self
+            .consensus
+            .committed_header(&self.ledger.latest_block())
+            .and_then(|block_header| block_header.sign(self.router().private_key(), &mut rand::thread_rng()))
+        {
+            // Peers on a version before the commitments are sent the header in the legacy layout.
+            Ok(block_header) => match self.router().get_connected_peer(&peer_ip).map(|peer| peer.version()) {
+                Some(version) if version < Message::<N>::EXTENDED_VERSION => {
+                    Data::Object(CommittedHeader::legacy(block_header.header))
+                }
+                _ => Data::Object(block_header),
+            },
+            Err(error) => {
+                error!("Failed to prepare a puzzle request for '{peer_ip}': {error}");
+                return false;
+            }
+        };
         // Send the `PuzzleResponse` message to the peer.
         self.send(peer_ip, Message::PuzzleResponse(PuzzleResponse { epoch_challenge, block_header }));
         true
     }
 
     /// Disconnects on receipt of a `PuzzleResponse` message.
-    fn puzzle_response(&self, peer_ip: SocketAddr, _epoch_challenge: EpochChallenge<N>, _header: Header<N>) -> bool {
+    fn puzzle_response(
+        &self,
+        peer_ip: SocketAddr,
+        _epoch_challenge: EpochChallenge<N>,
+        _header: CommittedHeader<N>,
+    ) -> bool {
         debug!("Disconnecting '{peer_ip}' for the following reason - {:?}", DisconnectReason::ProtocolViolation);
         false
     }
@@ -235,13 +280,68 @@ impl<N: Network, C: ConsensusStorage<N>> Inbound<N> for Validator<N, C> {
         &self,
         peer_ip: SocketAddr,
         serialized: UnconfirmedTransaction<N>,
-        _transaction: Transaction<N>,
+        transaction: Transaction<N>,
     ) -> bool {
-        let message = Message::UnconfirmedTransaction(serialized);
-        // Propagate the "UnconfirmedTransaction" to the connected beacons.
-        self.propagate_to_beacons(message.clone(), &[peer_ip]);
-        // Propagate the "UnconfirmedTransaction" to the connected validators.
-        self.propagate_to_validators(message, &[peer_ip]);
+        // Queue the "UnconfirmedTransaction" for the relay to the connected beacons and validators.
+        let mut peer_ips = self.router.connected_beacons();
+        peer_ips.extend(self.router.connected_validators());
+        self.queue_relay(serialized, fee_rate(&transaction).unwrap_or(0.0), &peer_ips, &[peer_ip]);
         true
     }
+
+    /// Returns the manifest of the snapshot of the ledger.
+    fn snapshot_manifest(&self) -> Option<SnapshotManifest<N>> {
+        match self.snapshot.manifest() {
+            Ok(manifest) => Some(manifest),
+            Err(error) => {
+                error!("Failed to prepare the snapshot manifest - {error}");
+                None
+            }
+        }
+    }
+
+    /// Returns the bytes of the given chunk of the snapshot of the ledger.
+    fn snapshot_chunk(&self, index: u32) -> Option<Bytes> {
+        match self.snapshot.chunk(index) {
+            Ok(bytes) => Some(bytes.into()),
+            Err(error) => {
+                debug!("Failed to prepare snapshot chunk {index} - {error}");
+                None
+            }
+        }
+    }
+
+    /// Pushes the blocks of the given snapshot chunk into the block pipeline, as if they were synced from the peer.
+    async fn import_snapshot_chunk(
+        &self,
+        peer_ip: SocketAddr,
+        manifest: &SnapshotManifest<N>,
+        index: u32,
+        bytes: Bytes,
+    ) -> Result<()> {
+        // Decode the blocks, and ensure they are the blocks of the chunk.
+        let num_bytes = bytes.len();
+        let blocks =
+            tokio::task::spawn_blocking(move || snarkos_node_cdn::deserialize_block_file::<N>(&bytes)).await??;
+        let range = manifest.chunk_range(index);
+        let heights = blocks.iter().map(|block| block.height());
+        if blocks.len() != range.len() || !heights.eq(range.clone()) {
+            bail!("Snapshot chunk {index} does not hold the blocks {} to {}", range.start, range.end - 1)
+        }
+        // Ensure the chunk extends the blocks imported so far.
+        if self.pipeline.next_height() != range.start {
+            bail!("Snapshot chunk {index} does not extend the ledger (expected block {})", self.pipeline.next_height())
+        }
+        // Push the blocks into the block pipeline, which verifies and commits them in order.
+        let num_bytes_per_block = num_bytes / blocks.len();
+        for block in blocks {
+            let (height, hash) = (block.height(), block.hash());
+            self.router.sync().insert_canon_locator(height, hash);
+            if !self.pipeline.push(SerialBlock::new(block, num_bytes_per_block).with_peer_ip(peer_ip)).await {
+                self.router.sync().remove_canon_locators(height);
+                bail!("The block pipeline rejected block {height} of snapshot chunk {index}")
+            }
+        }
+        Ok(())
+    }
 }
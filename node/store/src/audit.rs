@@ -0,0 +1,103 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    AuditLogMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+/// The persisted audit of a historical block, as `(block height, block hash, failure, audited at)`,
+/// where the audit time is a UNIX timestamp (in seconds).
+pub type AuditLogEntry<N> = (u32, <N as Network>::BlockHash, Option<String>, i64);
+
+/// The audits of the historical blocks, persisted so that a failed audit is reported after a restart.
+#[derive(Clone)]
+pub struct AuditLogStore<N: Network> {
+    /// The mapping of `sequence number` to `audit log entry`.
+    entry_map: DataMap<u64, AuditLogEntry<N>>,
+}
+
+impl<N: Network> AuditLogStore<N> {
+    /// Opens the audit log store of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the audit log store of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self { entry_map: database.map(MapID::AuditLog(AuditLogMap::Entry)) }
+    }
+
+    /// Returns the persisted audits.
+    pub fn entries(&self) -> Vec<(u64, AuditLogEntry<N>)> {
+        self.entry_map.iter().map(|(sequence, entry)| (*sequence, entry.into_owned())).collect()
+    }
+
+    /// Writes the given batch in a single atomic write, where `Some` inserts the audit with the sequence number,
+    /// and `None` removes it.
+    pub fn write_batch(&self, batch: &[(u64, Option<AuditLogEntry<N>>)]) -> Result<()> {
+        self.entry_map.start_atomic();
+        for (sequence, entry) in batch {
+            let result = match entry {
+                Some(entry) => self.entry_map.insert(*sequence, entry.clone()),
+                None => self.entry_map.remove(sequence),
+            };
+            if let Err(error) = result {
+                self.entry_map.abort_atomic();
+                return Err(error);
+            }
+        }
+        self.entry_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    #[serial]
+    fn test_audit_log_store() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let store = AuditLogStore::<CurrentNetwork>::from_database(&database);
+
+        let hash = <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(1));
+        let passed = (5, hash, None, 100);
+        let failed = (6, hash, Some("'proof' failed".to_string()), 200);
+
+        // Insert both audits in a batch.
+        store.write_batch(&[(0, Some(passed.clone())), (1, Some(failed.clone()))]).unwrap();
+        let mut persisted = store.entries();
+        persisted.sort_by_key(|(sequence, _)| *sequence);
+        assert_eq!(persisted, vec![(0, passed), (1, failed.clone())]);
+
+        // Remove an audit, and ensure the store reopens with the remaining one.
+        store.write_batch(&[(0, None)]).unwrap();
+        let store = AuditLogStore::<CurrentNetwork>::from_database(&database);
+        assert_eq!(store.entries(), vec![(1, failed)]);
+    }
+}
@@ -17,15 +17,302 @@
 use crate::{
     rocksdb::{self, DataMap, Database},
     BlockMap,
+    ChainEvent,
+    ChainJournal,
+    ChainMmr,
+    CircuitIndex,
+    DifficultyIndex,
+    HistoryIndex,
     MapID,
+    SequenceIndex,
+    TimestampIndex,
     TransactionDB,
     TransitionDB,
 };
-use snarkvm::prelude::*;
+use snarkvm::{
+    prelude::*,
+    synthesizer::{atomic_write_batch, store::helpers::MapRead},
+};
 
-/// A RocksDB block storage.
+/// A RocksDB block storage, which journals every change to the canonical chain,
+/// indexes the confirmed transactions by the circuits they use, indexes the blocks by timestamp,
+/// indexes the serial numbers and commitments by the height of the block that spent or created them,
+/// indexes the difficulty and estimated hashrate as of each block, commits to the block hashes
+/// in a Merkle mountain range, and numbers the transactions with global sequence numbers.
+///
+/// The changes to the canonical blocks, the journal, and the indexes are written in a single batch.
 #[derive(Clone)]
 pub struct BlockDB<N: Network> {
+    /// The database of the block storage.
+    database: rocksdb::RocksDB,
+    /// The storage of the canonical blocks.
+    canon: CanonDB<N>,
+    /// The journal of the changes to the canonical chain.
+    journal: ChainJournal<N>,
+    /// The index of the confirmed transactions by circuit.
+    circuits: CircuitIndex<N>,
+    /// The index of the canonical blocks by timestamp.
+    timestamps: TimestampIndex<N>,
+    /// The index of the serial numbers and commitments by block height.
+    history: HistoryIndex<N>,
+    /// The index of the difficulty and estimated hashrate by block height.
+    difficulty: DifficultyIndex<N>,
+    /// The Merkle mountain range over the block hashes.
+    mmr: ChainMmr<N>,
+    /// The index of the global sequence numbers of the transactions.
+    sequences: SequenceIndex<N>,
+}
+
+impl<N: Network> BlockDB<N> {
+    /// Returns the journal of the changes to the canonical chain.
+    pub const fn journal(&self) -> &ChainJournal<N> {
+        &self.journal
+    }
+
+    /// Returns the index of the confirmed transactions by circuit.
+    pub const fn circuits(&self) -> &CircuitIndex<N> {
+        &self.circuits
+    }
+
+    /// Returns the index of the canonical blocks by timestamp.
+    pub const fn timestamps(&self) -> &TimestampIndex<N> {
+        &self.timestamps
+    }
+
+    /// Returns the index of the serial numbers and commitments by block height.
+    pub const fn history(&self) -> &HistoryIndex<N> {
+        &self.history
+    }
+
+    /// Returns the index of the difficulty and estimated hashrate by block height.
+    pub const fn difficulty(&self) -> &DifficultyIndex<N> {
+        &self.difficulty
+    }
+
+    /// Returns the Merkle mountain range over the block hashes.
+    pub const fn mmr(&self) -> &ChainMmr<N> {
+        &self.mmr
+    }
+
+    /// Returns the index of the global sequence numbers of the transactions.
+    pub const fn sequences(&self) -> &SequenceIndex<N> {
+        &self.sequences
+    }
+}
+
+impl<N: Network> BlockStorage<N> for BlockDB<N> {
+    type CoinbasePuzzleCommitmentMap = DataMap<PuzzleCommitment<N>, N::BlockHash>;
+    type CoinbaseSolutionMap = DataMap<N::BlockHash, Option<CoinbaseSolution<N>>>;
+    type HeaderMap = DataMap<N::BlockHash, Header<N>>;
+    type IDMap = DataMap<u32, N::BlockHash>;
+    type ReverseIDMap = DataMap<N::BlockHash, u32>;
+    type ReverseStateRootMap = DataMap<N::StateRoot, u32>;
+    type ReverseTransactionsMap = DataMap<N::TransactionID, N::BlockHash>;
+    type SignatureMap = DataMap<N::BlockHash, Signature<N>>;
+    type StateRootMap = DataMap<u32, N::StateRoot>;
+    type TransactionStorage = TransactionDB<N>;
+    type TransactionsMap = DataMap<N::BlockHash, Vec<N::TransactionID>>;
+    type TransitionStorage = TransitionDB<N>;
+
+    /// Initializes the block storage.
+    fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self {
+            database: rocksdb::RocksDB::open(N::ID, dev)?,
+            canon: CanonDB::open(dev)?,
+            journal: ChainJournal::open(dev)?,
+            circuits: CircuitIndex::open(dev)?,
+            timestamps: TimestampIndex::open(dev)?,
+            history: HistoryIndex::open(dev)?,
+            difficulty: DifficultyIndex::open(dev)?,
+            mmr: ChainMmr::open(dev)?,
+            sequences: SequenceIndex::open(dev)?,
+        })
+    }
+
+    /// Returns the state root map.
+    fn state_root_map(&self) -> &Self::StateRootMap {
+        self.canon.state_root_map()
+    }
+
+    /// Returns the reverse state root map.
+    fn reverse_state_root_map(&self) -> &Self::ReverseStateRootMap {
+        self.canon.reverse_state_root_map()
+    }
+
+    /// Returns the ID map.
+    fn id_map(&self) -> &Self::IDMap {
+        self.canon.id_map()
+    }
+
+    /// Returns the reverse ID map.
+    fn reverse_id_map(&self) -> &Self::ReverseIDMap {
+        self.canon.reverse_id_map()
+    }
+
+    /// Returns the header map.
+    fn header_map(&self) -> &Self::HeaderMap {
+        self.canon.header_map()
+    }
+
+    /// Returns the transactions map.
+    fn transactions_map(&self) -> &Self::TransactionsMap {
+        self.canon.transactions_map()
+    }
+
+    /// Returns the reverse transactions map.
+    fn reverse_transactions_map(&self) -> &Self::ReverseTransactionsMap {
+        self.canon.reverse_transactions_map()
+    }
+
+    /// Returns the transaction store.
+    fn transaction_store(&self) -> &TransactionStore<N, Self::TransactionStorage> {
+        self.canon.transaction_store()
+    }
+
+    /// Returns the coinbase solution map.
+    fn coinbase_solution_map(&self) -> &Self::CoinbaseSolutionMap {
+        self.canon.coinbase_solution_map()
+    }
+
+    /// Returns the coinbase puzzle commitment map.
+    fn coinbase_puzzle_commitment_map(&self) -> &Self::CoinbasePuzzleCommitmentMap {
+        self.canon.coinbase_puzzle_commitment_map()
+    }
+
+    /// Returns the signature map.
+    fn signature_map(&self) -> &Self::SignatureMap {
+        self.canon.signature_map()
+    }
+
+    /// Starts an atomic batch write operation.
+    fn start_atomic(&self) {
+        self.canon.start_atomic();
+        self.journal.start_atomic();
+        self.circuits.start_atomic();
+        self.timestamps.start_atomic();
+        self.history.start_atomic();
+        self.difficulty.start_atomic();
+        self.mmr.start_atomic();
+        self.sequences.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    fn is_atomic_in_progress(&self) -> bool {
+        self.canon.is_atomic_in_progress()
+            || self.journal.is_atomic_in_progress()
+            || self.circuits.is_atomic_in_progress()
+            || self.timestamps.is_atomic_in_progress()
+            || self.history.is_atomic_in_progress()
+            || self.difficulty.is_atomic_in_progress()
+            || self.mmr.is_atomic_in_progress()
+            || self.sequences.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    fn abort_atomic(&self) {
+        self.canon.abort_atomic();
+        self.journal.abort_atomic();
+        self.circuits.abort_atomic();
+        self.timestamps.abort_atomic();
+        self.history.abort_atomic();
+        self.difficulty.abort_atomic();
+        self.mmr.abort_atomic();
+        self.sequences.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation, writing the operations of the canonical blocks, the journal,
+    /// and the indexes in a single batch.
+    fn finish_atomic(&self) -> Result<()> {
+        let result = self.database.finish_atomic_together(|| {
+            self.canon.finish_atomic()?;
+            self.journal.finish_atomic()?;
+            self.circuits.finish_atomic()?;
+            self.timestamps.finish_atomic()?;
+            self.history.finish_atomic()?;
+            self.difficulty.finish_atomic()?;
+            self.mmr.finish_atomic()?;
+            self.sequences.finish_atomic()
+        });
+        // If the batch was not written, restore the sequence numbers of the journal from the storage.
+        if result.is_err() {
+            self.journal.reload_sequence();
+        }
+        result
+    }
+
+    /// Stores the given `(state root, block)` pair into storage, and journals and indexes the connected block
+    /// in the same batch.
+    fn insert(&self, state_root: N::StateRoot, block: &Block<N>) -> Result<()> {
+        atomic_write_batch!(self, {
+            // Store the block.
+            self.canon.insert(state_root, block)?;
+            // Index the transactions of the block by circuit.
+            self.circuits.insert_block(block)?;
+            // Index the block by timestamp.
+            self.timestamps.insert(block.height(), block.timestamp())?;
+            // Index the serial numbers and commitments of the block by height.
+            self.history.insert_block(block)?;
+            // Index the difficulty of the block, and the estimated hashrate as of the block.
+            self.difficulty.insert(block.header())?;
+            // Append the block hash to the chain commitment.
+            self.mmr.insert(block.height(), &block.hash())?;
+            // Number the transactions of the block.
+            let transaction_ids = block.transaction_ids().copied().collect::<Vec<_>>();
+            self.sequences.insert(block.height(), &transaction_ids)?;
+            // Journal the connected block.
+            self.journal.append(ChainEvent::Connected {
+                height: block.height(),
+                hash: block.hash(),
+                transaction_ids,
+            })?;
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Removes the block for the given `block hash`, and journals and unindexes the disconnected block
+    /// in the same batch.
+    fn remove(&self, block_hash: &N::BlockHash) -> Result<()> {
+        // Retrieve the block height.
+        let height = match self.get_block_height(block_hash)? {
+            Some(height) => height,
+            None => bail!("Failed to remove block: missing block height for block hash '{block_hash}'"),
+        };
+        // Retrieve the transaction IDs.
+        let transaction_ids = match self.transactions_map().get(block_hash)? {
+            Some(transaction_ids) => transaction_ids.into_owned(),
+            None => bail!("Failed to remove block: missing transactions for block hash '{block_hash}'"),
+        };
+        atomic_write_batch!(self, {
+            // Remove the block.
+            self.canon.remove(block_hash)?;
+            // Remove the transactions of the block from the circuit index.
+            self.circuits.remove_block(&transaction_ids)?;
+            // Remove the block from the timestamp index.
+            self.timestamps.remove(height)?;
+            // Remove the serial numbers and commitments of the block from the history index.
+            self.history.remove(height)?;
+            // Remove the block from the difficulty index.
+            self.difficulty.remove(height)?;
+            // Remove the block hash from the chain commitment.
+            self.mmr.remove(height)?;
+            // Release the sequence numbers of the transactions of the block.
+            let sequences = self.sequences.remove(height)?;
+            // Journal the disconnected block, and the sequence numbers to reassign.
+            self.journal.append(ChainEvent::Disconnected { height, hash: *block_hash })?;
+            if !sequences.is_empty() {
+                let (start, end) = (sequences.start, sequences.end);
+                self.journal.append(ChainEvent::SequencesInvalidated { height, start, end })?;
+            }
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// The RocksDB storage of the canonical blocks.
+#[derive(Clone)]
+struct CanonDB<N: Network> {
     /// The mapping of `block height` to `state root`.
     state_root_map: DataMap<u32, N::StateRoot>,
     /// The mapping of `state root` to `block height`.
@@ -51,7 +338,7 @@ pub struct BlockDB<N: Network> {
 }
 
 #[rustfmt::skip]
-impl<N: Network> BlockStorage<N> for BlockDB<N> {
+impl<N: Network> BlockStorage<N> for CanonDB<N> {
     type StateRootMap = DataMap<u32, N::StateRoot>;
     type ReverseStateRootMap = DataMap<N::StateRoot, u32>;
     type IDMap = DataMap<u32, N::BlockHash>;
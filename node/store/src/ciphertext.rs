@@ -0,0 +1,435 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{
+        iterator::{Iter, Keys, Values},
+        DataMap,
+        Database,
+        RocksDB,
+    },
+    MapID,
+    TransitionOutputMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::{borrow::Borrow, hash::Hash};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+
+/// The content hash of a record ciphertext, which is the SHA-256 digest of its bytes.
+pub type CiphertextHash = [u8; 32];
+
+/// Returns the content hash of the given record ciphertext.
+pub fn ciphertext_hash<N: Network>(record: &Record<N, Ciphertext<N>>) -> Result<CiphertextHash> {
+    Ok(Sha256::digest(record.to_bytes_le()?).into())
+}
+
+/// The value of an output record, which is `(checksum, (optional) record ciphertext)`.
+type RecordValue<N> = (Field<N>, Option<Record<N, Ciphertext<N>>>);
+
+/// The map of the output records, in which each distinct record ciphertext is stored once.
+///
+/// On disk, an output record stores the content hash of its ciphertext in place of the ciphertext, and the
+/// ciphertexts are stored by content hash, along with the number of output records that reference them.
+/// Reads resolve the content hashes back to the ciphertexts, so the map is a drop-in for the record map.
+/// The references and the ciphertexts are written in a single batch, so a ciphertext is removed in the same
+/// batch as the last output record that references it, which is the batch that disconnects its block.
+#[derive(Clone)]
+pub struct RecordCiphertextMap<N: Network> {
+    /// The database of the record map.
+    database: RocksDB,
+    /// The mapping of `commitment` to `(checksum, (optional) ciphertext hash)`.
+    reference_map: DataMap<Field<N>, (Field<N>, Option<CiphertextHash>)>,
+    /// The mapping of `ciphertext hash` to `(number of references, record ciphertext)`.
+    ciphertext_map: DataMap<CiphertextHash, (u64, Record<N, Ciphertext<N>>)>,
+}
+
+impl<N: Network> RecordCiphertextMap<N> {
+    /// Opens the record map of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the record map of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            database: database.clone(),
+            reference_map: database.map(MapID::TransitionOutput(TransitionOutputMap::RecordReference)),
+            ciphertext_map: database.map(MapID::TransitionOutput(TransitionOutputMap::RecordCiphertext)),
+        }
+    }
+
+    /// Returns the number of distinct record ciphertexts in storage.
+    pub fn num_ciphertexts(&self) -> usize {
+        self.ciphertext_map.keys().count()
+    }
+
+    /// Returns the number of output records that reference the given ciphertext, including the writes
+    /// of the atomic batch in progress.
+    pub fn num_references(&self, hash: &CiphertextHash) -> Result<u64> {
+        Ok(self.ciphertext_map.get_speculative(hash)?.map_or(0, |entry| entry.0))
+    }
+
+    /// Returns the number of bytes saved by storing each distinct record ciphertext once.
+    pub fn num_bytes_saved(&self) -> Result<u64> {
+        self.ciphertext_map.values().try_fold(0u64, |num_bytes, entry| {
+            let (num_references, record) = &*entry;
+            let num_duplicates = num_references.saturating_sub(1);
+            Ok(num_bytes.saturating_add(num_duplicates.saturating_mul(record.to_bytes_le()?.len() as u64)))
+        })
+    }
+
+    /// Adds a reference to the given record ciphertext, storing it if it is not referenced yet.
+    fn acquire(&self, record: &Record<N, Ciphertext<N>>) -> Result<CiphertextHash> {
+        let hash = ciphertext_hash(record)?;
+        let num_references = self.num_references(&hash)?;
+        self.ciphertext_map.insert(hash, (num_references.saturating_add(1), record.clone()))?;
+        Ok(hash)
+    }
+
+    /// Removes a reference to the given record ciphertext, removing it once it is no longer referenced.
+    fn release(&self, hash: &CiphertextHash) -> Result<()> {
+        match self.ciphertext_map.get_speculative(hash)? {
+            Some(entry) => match entry.0 > 1 {
+                true => {
+                    let (num_references, record) = entry.into_owned();
+                    self.ciphertext_map.insert(*hash, (num_references - 1, record))
+                }
+                false => self.ciphertext_map.remove(hash),
+            },
+            None => bail!("Missing the record ciphertext '{}'", hex::encode(hash)),
+        }
+    }
+
+    /// Returns the output record for the given on-disk value, resolving its ciphertext hash.
+    fn resolve(&self, (checksum, hash): (Field<N>, Option<CiphertextHash>)) -> Result<RecordValue<N>> {
+        match hash {
+            Some(hash) => match self.ciphertext_map.get_speculative(&hash)? {
+                Some(entry) => Ok((checksum, Some(entry.into_owned().1))),
+                None => bail!("Missing the record ciphertext '{}'", hex::encode(hash)),
+            },
+            None => Ok((checksum, None)),
+        }
+    }
+}
+
+impl<'a, N: Network> Map<'a, Field<N>, RecordValue<N>> for RecordCiphertextMap<N> {
+    ///
+    /// Inserts the given key-value pair into the map.
+    ///
+    fn insert(&self, commitment: Field<N>, (checksum, record): RecordValue<N>) -> Result<()> {
+        // Release the ciphertext of the previous value, so that inserting the same record again is idempotent.
+        if let Some((_, Some(hash))) = self.reference_map.get_speculative(&commitment)?.map(|entry| entry.into_owned())
+        {
+            self.release(&hash)?;
+        }
+        let hash = record.as_ref().map(|record| self.acquire(record)).transpose()?;
+        self.reference_map.insert(commitment, (checksum, hash))
+    }
+
+    ///
+    /// Removes the key-value pair for the given key from the map.
+    ///
+    fn remove(&self, commitment: &Field<N>) -> Result<()> {
+        if let Some((_, Some(hash))) = self.reference_map.get_speculative(commitment)?.map(|entry| entry.into_owned()) {
+            self.release(&hash)?;
+        }
+        self.reference_map.remove(commitment)
+    }
+
+    ///
+    /// Begins an atomic operation. Any further calls to `insert` and `remove` will be queued
+    /// without an actual write taking place until `finish_atomic` is called.
+    ///
+    fn start_atomic(&self) {
+        self.reference_map.start_atomic();
+        self.ciphertext_map.start_atomic();
+    }
+
+    ///
+    /// Checks whether an atomic operation is currently in progress.
+    ///
+    fn is_atomic_in_progress(&self) -> bool {
+        self.reference_map.is_atomic_in_progress() || self.ciphertext_map.is_atomic_in_progress()
+    }
+
+    ///
+    /// Aborts the current atomic operation.
+    ///
+    fn abort_atomic(&self) {
+        self.reference_map.abort_atomic();
+        self.ciphertext_map.abort_atomic();
+    }
+
+    ///
+    /// Finishes an atomic operation, performing all the queued writes.
+    ///
+    fn finish_atomic(&self) -> Result<()> {
+        // Write the references and the ciphertexts in a single batch, so that they never diverge.
+        self.database.finish_atomic_together(|| {
+            self.ciphertext_map.finish_atomic()?;
+            self.reference_map.finish_atomic()
+        })
+    }
+}
+
+impl<'a, N: Network> MapRead<'a, Field<N>, RecordValue<N>> for RecordCiphertextMap<N> {
+    type Iterator = RecordIter<'a, N>;
+    type Keys = Keys<'a, Field<N>>;
+    type Values = RecordValues<'a, N>;
+
+    ///
+    /// Returns `true` if the given key exists in the map.
+    ///
+    fn contains_key<Q>(&self, key: &Q) -> Result<bool>
+    where
+        Field<N>: Borrow<Q>,
+        Q: PartialEq + Eq + Hash + Serialize + ?Sized,
+    {
+        self.reference_map.contains_key(key)
+    }
+
+    ///
+    /// Returns the value for the given key from the map, if it exists.
+    ///
+    fn get<Q>(&'a self, key: &Q) -> Result<Option<Cow<'a, RecordValue<N>>>>
+    where
+        Field<N>: Borrow<Q>,
+        Q: PartialEq + Eq + Hash + Serialize + ?Sized,
+    {
+        match self.reference_map.get(key)? {
+            Some(entry) => Ok(Some(Cow::Owned(self.resolve(entry.into_owned())?))),
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// Returns the current value for the given key if it is scheduled
+    /// to be inserted as part of an atomic batch.
+    ///
+    fn get_batched<Q>(&self, key: &Q) -> Option<Option<RecordValue<N>>>
+    where
+        Field<N>: Borrow<Q>,
+        Q: PartialEq + Eq + Hash + Serialize + ?Sized,
+    {
+        match self.reference_map.get_batched(key)? {
+            Some(entry) => match self.resolve(entry) {
+                Ok(value) => Some(Some(value)),
+                Err(error) => {
+                    error!("Failed to resolve a batched record - {error}");
+                    None
+                }
+            },
+            None => Some(None),
+        }
+    }
+
+    ///
+    /// Returns an iterator visiting each key-value pair in the map.
+    ///
+    fn iter(&'a self) -> Self::Iterator {
+        RecordIter { references: self.reference_map.iter(), map: self }
+    }
+
+    ///
+    /// Returns an iterator over each key in the map.
+    ///
+    fn keys(&'a self) -> Self::Keys {
+        self.reference_map.keys()
+    }
+
+    ///
+    /// Returns an iterator over each value in the map.
+    ///
+    fn values(&'a self) -> Self::Values {
+        RecordValues { references: self.reference_map.values(), map: self }
+    }
+}
+
+/// An iterator over the output records, which resolves their ciphertext hashes.
+pub struct RecordIter<'a, N: Network> {
+    /// The iterator over the on-disk values.
+    references: Iter<'a, Field<N>, (Field<N>, Option<CiphertextHash>)>,
+    /// The record map.
+    map: &'a RecordCiphertextMap<N>,
+}
+
+impl<'a, N: Network> Iterator for RecordIter<'a, N> {
+    type Item = (Cow<'a, Field<N>>, Cow<'a, RecordValue<N>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip the records that cannot be resolved, without ending the iteration.
+        loop {
+            let (commitment, entry) = self.references.next()?;
+            match self.map.resolve(entry.into_owned()) {
+                Ok(value) => return Some((commitment, Cow::Owned(value))),
+                Err(error) => error!("Skipped the unresolvable record '{commitment}' - {error}"),
+            }
+        }
+    }
+}
+
+/// An iterator over the values of the output records, which resolves their ciphertext hashes.
+pub struct RecordValues<'a, N: Network> {
+    /// The iterator over the on-disk values.
+    references: Values<'a, (Field<N>, Option<CiphertextHash>)>,
+    /// The record map.
+    map: &'a RecordCiphertextMap<N>,
+}
+
+impl<'a, N: Network> Iterator for RecordValues<'a, N> {
+    type Item = Cow<'a, RecordValue<N>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip the records that cannot be resolved, without ending the iteration.
+        loop {
+            let entry = self.references.next()?;
+            match self.map.resolve(entry.into_owned()) {
+                Ok(value) => return Some(Cow::Owned(value)),
+                Err(error) => error!("Skipped an unresolvable record - {error}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+    use std::str::FromStr;
+
+    type CurrentNetwork = Testnet3;
+
+    /// The number of transactions that share the dummy record.
+    const NUM_TRANSACTIONS: usize = 1_000;
+
+    /// Samples a dummy record ciphertext.
+    fn sample_dummy_record(rng: &mut TestRng) -> Record<CurrentNetwork, Ciphertext<CurrentNetwork>> {
+        // Encrypt the record with a randomizer that corresponds to its nonce.
+        let randomizer = Scalar::rand(rng);
+        let nonce = CurrentNetwork::g_scalar_multiply(&randomizer);
+        Record::<CurrentNetwork, Plaintext<CurrentNetwork>>::from_str(&format!(
+            "{{ owner: aleo1d5hg2z3ma00382pngntdp68e74zv54jdxy249qhaujhks9c72yrs33ddah.private, gates: 0u64.private, _nonce: {nonce}.public }}"
+        ))
+        .unwrap()
+        .encrypt(randomizer)
+        .unwrap()
+    }
+
+    #[test]
+    #[serial]
+    fn test_shared_record_is_stored_once() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the test database");
+        let map = RecordCiphertextMap::<CurrentNetwork>::from_database(&database);
+
+        // Insert the outputs of the transactions, which share the dummy record, in a single batch.
+        let record = sample_dummy_record(rng);
+        let hash = ciphertext_hash(&record).unwrap();
+        let commitments = (0..NUM_TRANSACTIONS).map(|_| Field::rand(rng)).collect::<Vec<_>>();
+        map.start_atomic();
+        for commitment in &commitments {
+            map.insert(*commitment, (Field::rand(rng), Some(record.clone()))).unwrap();
+        }
+        map.finish_atomic().unwrap();
+
+        // Ensure the record is stored once, and every output resolves to it.
+        assert_eq!(map.num_ciphertexts(), 1);
+        assert_eq!(map.num_references(&hash).unwrap(), NUM_TRANSACTIONS as u64);
+        for commitment in &commitments {
+            assert_eq!(map.get(commitment).unwrap().unwrap().1.as_ref(), Some(&record));
+        }
+        assert!(map.values().all(|value| value.1.as_ref() == Some(&record)));
+        let record_size = record.to_bytes_le().unwrap().len() as u64;
+        assert_eq!(map.num_bytes_saved().unwrap(), (NUM_TRANSACTIONS as u64 - 1) * record_size);
+
+        // Ensure inserting an output again does not add a reference.
+        map.insert(commitments[0], (Field::rand(rng), Some(record.clone()))).unwrap();
+        assert_eq!(map.num_references(&hash).unwrap(), NUM_TRANSACTIONS as u64);
+
+        // Remove all but one output, and ensure the record is kept.
+        for commitment in &commitments[1..] {
+            map.remove(commitment).unwrap();
+        }
+        assert_eq!(map.num_references(&hash).unwrap(), 1);
+        assert_eq!(map.get(&commitments[0]).unwrap().unwrap().1.as_ref(), Some(&record));
+
+        // Remove the last output, and ensure the record is removed.
+        map.remove(&commitments[0]).unwrap();
+        assert_eq!(map.num_ciphertexts(), 0);
+        assert!(map.get(&commitments[0]).unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_record_removed_and_reinserted_in_batch() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the test database");
+        let map = RecordCiphertextMap::<CurrentNetwork>::from_database(&database);
+
+        // Insert an output with the dummy record, and an output without a record.
+        let record = sample_dummy_record(rng);
+        let (first, second, third) = (Field::rand(rng), Field::rand(rng), Field::rand(rng));
+        map.insert(first, (Field::rand(rng), Some(record.clone()))).unwrap();
+        map.insert(second, (Field::rand(rng), None)).unwrap();
+        assert_eq!(map.get(&second).unwrap().unwrap().1, None);
+
+        // Disconnect the output and connect another one with the same record, as in a reorg.
+        map.start_atomic();
+        map.remove(&first).unwrap();
+        map.insert(third, (Field::rand(rng), Some(record.clone()))).unwrap();
+        assert_eq!(map.get_speculative(&third).unwrap().unwrap().1.as_ref(), Some(&record));
+        map.finish_atomic().unwrap();
+
+        // Ensure the record is kept for the new output only.
+        assert_eq!(map.num_ciphertexts(), 1);
+        assert_eq!(map.num_references(&ciphertext_hash(&record).unwrap()).unwrap(), 1);
+        assert!(!map.contains_key(&first).unwrap());
+        assert_eq!(map.get(&third).unwrap().unwrap().1.as_ref(), Some(&record));
+    }
+
+    #[test]
+    #[serial]
+    fn test_unresolvable_record_is_skipped() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the test database");
+        let map = RecordCiphertextMap::<CurrentNetwork>::from_database(&database);
+
+        // Insert three outputs with distinct records, and drop the ciphertext of the first one.
+        let records = (0..3).map(|_| sample_dummy_record(rng)).collect::<Vec<_>>();
+        let commitments = (0..3).map(|_| Field::rand(rng)).collect::<Vec<_>>();
+        for (commitment, record) in commitments.iter().zip(&records) {
+            map.insert(*commitment, (Field::rand(rng), Some(record.clone()))).unwrap();
+        }
+        map.ciphertext_map.remove(&ciphertext_hash(&records[0]).unwrap()).unwrap();
+
+        // Ensure the unresolvable record is reported on reads, and skipped by the iterators.
+        assert!(map.get(&commitments[0]).is_err());
+        let mut resolved = map.iter().map(|(commitment, _)| *commitment).collect::<Vec<_>>();
+        resolved.sort_unstable_by_key(|commitment| commitment.to_string());
+        let mut expected = commitments[1..].to_vec();
+        expected.sort_unstable_by_key(|commitment| commitment.to_string());
+        assert_eq!(resolved, expected);
+        assert_eq!(map.values().count(), 2);
+    }
+}
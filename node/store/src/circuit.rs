@@ -0,0 +1,254 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    CircuitMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+/// The ID of a circuit, i.e. the program ID and function name of a transition.
+pub type CircuitID<N> = (ProgramID<N>, Identifier<N>);
+
+/// Returns the IDs of the circuits used by the given transaction, without duplicates, in order of first use.
+pub fn transaction_circuits<N: Network>(transaction: &Transaction<N>) -> Vec<CircuitID<N>> {
+    let mut circuits = Vec::new();
+    for transition in transaction.transitions() {
+        let circuit = (*transition.program_id(), *transition.function_name());
+        if !circuits.contains(&circuit) {
+            circuits.push(circuit);
+        }
+    }
+    circuits
+}
+
+/// An index of the confirmed transactions by the circuits they use, for circuit upgrade audits.
+///
+/// The transactions of each circuit are numbered in the order they were confirmed, so that a block is
+/// disconnected by removing the latest transactions of its circuits, in the reverse order of their insertion.
+#[derive(Clone)]
+pub struct CircuitIndex<N: Network> {
+    /// The mapping of `circuit ID` to the number of transactions using it.
+    count_map: DataMap<CircuitID<N>, u64>,
+    /// The mapping of `(circuit ID, index)` to `transaction ID`.
+    transaction_map: DataMap<(CircuitID<N>, u64), N::TransactionID>,
+    /// The mapping of `transaction ID` to the IDs of the circuits it uses.
+    reverse_map: DataMap<N::TransactionID, Vec<CircuitID<N>>>,
+}
+
+impl<N: Network> CircuitIndex<N> {
+    /// Opens the circuit index of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the circuit index of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            count_map: database.map(MapID::Circuit(CircuitMap::Count)),
+            transaction_map: database.map(MapID::Circuit(CircuitMap::Transaction)),
+            reverse_map: database.map(MapID::Circuit(CircuitMap::Reverse)),
+        }
+    }
+
+    /// Returns the number of confirmed transactions using each circuit.
+    pub fn circuits(&self) -> Vec<(CircuitID<N>, u64)> {
+        self.count_map.iter().map(|(circuit, count)| (*circuit, *count)).collect()
+    }
+
+    /// Returns the number of confirmed transactions using the given circuit.
+    pub fn num_transactions(&self, circuit: &CircuitID<N>) -> Result<u64> {
+        Ok(self.count_map.get_speculative(circuit)?.map_or(0, |count| *count))
+    }
+
+    /// Returns up to `limit` IDs of the confirmed transactions using the given circuit, starting at the given index.
+    pub fn transactions(&self, circuit: &CircuitID<N>, start: u64, limit: usize) -> Result<Vec<N::TransactionID>> {
+        let end = self.num_transactions(circuit)?.min(start.saturating_add(limit as u64));
+        (start..end)
+            .map(|index| match self.transaction_map.get(&(*circuit, index))? {
+                Some(transaction_id) => Ok(*transaction_id),
+                None => bail!("Missing transaction {index} of circuit '{}/{}'", circuit.0, circuit.1),
+            })
+            .collect()
+    }
+
+    /// Returns the IDs of the circuits used by the given transaction, if it is indexed.
+    pub fn transaction_circuits(&self, transaction_id: &N::TransactionID) -> Result<Option<Vec<CircuitID<N>>>> {
+        Ok(self.reverse_map.get_speculative(transaction_id)?.map(|circuits| circuits.into_owned()))
+    }
+
+    /// Indexes the given transaction under each of the given circuits.
+    /// Note that a transaction that is already indexed is skipped.
+    pub fn insert(&self, transaction_id: N::TransactionID, circuits: Vec<CircuitID<N>>) -> Result<()> {
+        if self.reverse_map.get_speculative(&transaction_id)?.is_some() {
+            return Ok(());
+        }
+        for circuit in &circuits {
+            let count = self.num_transactions(circuit)?;
+            self.transaction_map.insert((*circuit, count), transaction_id)?;
+            self.count_map.insert(*circuit, count + 1)?;
+        }
+        self.reverse_map.insert(transaction_id, circuits)
+    }
+
+    /// Removes the given transaction from the index, which must be the latest transaction of each of its circuits.
+    pub fn remove(&self, transaction_id: &N::TransactionID) -> Result<()> {
+        let circuits = match self.transaction_circuits(transaction_id)? {
+            Some(circuits) => circuits,
+            None => return Ok(()),
+        };
+        for circuit in &circuits {
+            // Retrieve the index of the latest transaction of the circuit.
+            let index = match self.num_transactions(circuit)?.checked_sub(1) {
+                Some(index) => index,
+                None => bail!("Missing the transactions of circuit '{}/{}'", circuit.0, circuit.1),
+            };
+            // Ensure the latest transaction of the circuit is the given transaction.
+            match self.transaction_map.get_speculative(&(*circuit, index))? {
+                Some(latest) if *latest == *transaction_id => (),
+                _ => bail!("Transaction '{transaction_id}' is not the latest of circuit '{}/{}'", circuit.0, circuit.1),
+            }
+            self.transaction_map.remove(&(*circuit, index))?;
+            match index {
+                0 => self.count_map.remove(circuit)?,
+                _ => self.count_map.insert(*circuit, index)?,
+            }
+        }
+        self.reverse_map.remove(transaction_id)
+    }
+
+    /// Indexes the transactions of the given block.
+    pub fn insert_block(&self, block: &Block<N>) -> Result<()> {
+        block
+            .transactions()
+            .iter()
+            .try_for_each(|transaction| self.insert(transaction.id(), transaction_circuits(transaction)))
+    }
+
+    /// Removes the given transactions of a block from the index, in the reverse order of their insertion.
+    pub fn remove_block(&self, transaction_ids: &[N::TransactionID]) -> Result<()> {
+        transaction_ids.iter().rev().try_for_each(|transaction_id| self.remove(transaction_id))
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        self.count_map.start_atomic();
+        self.transaction_map.start_atomic();
+        self.reverse_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.count_map.is_atomic_in_progress()
+            || self.transaction_map.is_atomic_in_progress()
+            || self.reverse_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        self.count_map.abort_atomic();
+        self.transaction_map.abort_atomic();
+        self.reverse_map.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        self.count_map.finish_atomic()?;
+        self.transaction_map.finish_atomic()?;
+        self.reverse_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+    use std::str::FromStr;
+
+    type CurrentNetwork = Testnet3;
+
+    type TransactionID = <CurrentNetwork as Network>::TransactionID;
+
+    /// Returns the ID of the circuit with the given program ID and function name.
+    fn sample_circuit(program_id: &str, function_name: &str) -> CircuitID<CurrentNetwork> {
+        (ProgramID::from_str(program_id).unwrap(), Identifier::from_str(function_name).unwrap())
+    }
+
+    #[test]
+    #[serial]
+    fn test_circuit_index_across_reorg() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let index = CircuitIndex::<CurrentNetwork>::from_database(&database);
+
+        let transfer = sample_circuit("credits.aleo", "transfer");
+        let fee = sample_circuit("credits.aleo", "fee");
+        let mut sample_transaction_id = || -> TransactionID { Field::<CurrentNetwork>::rand(rng).into() };
+
+        // Connect a block with two transfers, and the common block of both forks.
+        let (a, b, c) = (sample_transaction_id(), sample_transaction_id(), sample_transaction_id());
+        index.insert(a, vec![transfer, fee]).unwrap();
+        index.insert(b, vec![transfer, fee]).unwrap();
+        index.insert(c, vec![fee]).unwrap();
+        // Ensure a transaction that is already indexed is not counted twice.
+        index.insert(c, vec![fee]).unwrap();
+        assert_eq!(index.num_transactions(&transfer).unwrap(), 2);
+        assert_eq!(index.num_transactions(&fee).unwrap(), 3);
+
+        // Connect a block of the first fork, within an atomic batch.
+        let (d, e) = (sample_transaction_id(), sample_transaction_id());
+        index.start_atomic();
+        index.insert(d, vec![transfer, fee]).unwrap();
+        index.insert(e, vec![transfer, fee]).unwrap();
+        index.finish_atomic().unwrap();
+        assert_eq!(index.transactions(&transfer, 0, 10).unwrap(), vec![a, b, d, e]);
+
+        // Ensure a transaction that is not the latest of its circuits cannot be removed.
+        assert!(index.remove(&d).is_err());
+
+        // Disconnect the block of the first fork, and connect the block of the second fork.
+        let f = sample_transaction_id();
+        index.start_atomic();
+        index.remove_block(&[d, e]).unwrap();
+        index.insert(f, vec![fee]).unwrap();
+        index.finish_atomic().unwrap();
+
+        // Ensure the index matches the second fork.
+        assert_eq!(index.num_transactions(&transfer).unwrap(), 2);
+        assert_eq!(index.num_transactions(&fee).unwrap(), 4);
+        assert_eq!(index.transactions(&transfer, 0, 10).unwrap(), vec![a, b]);
+        assert_eq!(index.transactions(&fee, 0, 10).unwrap(), vec![a, b, c, f]);
+        assert_eq!(index.transaction_circuits(&d).unwrap(), None);
+        assert_eq!(index.transaction_circuits(&f).unwrap(), Some(vec![fee]));
+
+        // Ensure the transactions are paginated.
+        assert_eq!(index.transactions(&fee, 1, 2).unwrap(), vec![b, c]);
+        assert_eq!(index.transactions(&fee, 3, 10).unwrap(), vec![f]);
+        assert!(index.transactions(&fee, 4, 10).unwrap().is_empty());
+
+        // Ensure a circuit without transactions is removed from the statistics.
+        index.remove_block(&[c, f]).unwrap();
+        index.remove_block(&[a, b]).unwrap();
+        assert!(index.circuits().is_empty());
+    }
+}
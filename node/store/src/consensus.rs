@@ -14,7 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{BlockDB, ProgramDB, TransactionDB, TransitionDB};
+use crate::{
+    rocksdb::{self, Database},
+    BlockDB,
+    ProgramDB,
+    TransactionDB,
+    TransitionDB,
+};
 use snarkvm::prelude::*;
 
 /// An RocksDB consensus storage.
@@ -35,6 +41,8 @@ impl<N: Network> ConsensusStorage<N> for ConsensusDB<N> {
 
     /// Initializes the consensus storage.
     fn open(dev: Option<u16>) -> Result<Self> {
+        // Apply the pending migrations of the database schema.
+        crate::migrations::<N>()?.run(&mut rocksdb::RocksDB::open(N::ID, dev)?)?;
         // Initialize the program store.
         let program_store = ProgramStore::<N, ProgramDB<N>>::open(dev)?;
         // Initialize the block store.
@@ -0,0 +1,383 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    DifficultyMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::marker::PhantomData;
+use serde::{Deserialize, Serialize};
+
+/// The number of trailing blocks over which the hashrate of each block is estimated.
+pub const HASHRATE_WINDOW: u32 = 60;
+/// The maximum number of entries returned by a query of the difficulty history.
+pub const MAX_DIFFICULTY_HISTORY_ENTRIES: usize = 1_000;
+
+/// The scale of the fixed-point difficulty and hashrate, which are stored in millionths.
+pub const FIXED_POINT_SCALE: u128 = 1_000_000;
+
+/// The difficulty of a canonical block, and the estimated hashrate of the network as of the block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifficultyEntry {
+    /// The timestamp of the block.
+    pub timestamp: i64,
+    /// The proof target of the block, which is the minimum target of a prover solution.
+    pub proof_target: u64,
+    /// The coinbase target of the block.
+    pub coinbase_target: u64,
+    /// The difficulty of the block, which is its proof target relative to the genesis proof target, in millionths.
+    pub difficulty_micros: u128,
+    /// The cumulative proof target of the block, which is the total work of the chain up to the block.
+    pub cumulative_proof_target: u128,
+    /// The estimated hashrate, which is the work per second over the trailing `HASHRATE_WINDOW` blocks, in millionths.
+    pub hashrate_micros: u128,
+}
+
+impl DifficultyEntry {
+    /// Returns the difficulty of the block, relative to the genesis proof target.
+    pub fn difficulty(&self) -> f64 {
+        from_fixed_point(self.difficulty_micros)
+    }
+
+    /// Returns the estimated hashrate, in work per second over the trailing `HASHRATE_WINDOW` blocks.
+    pub fn hashrate(&self) -> f64 {
+        from_fixed_point(self.hashrate_micros)
+    }
+}
+
+/// Returns the difficulty of the given proof target, relative to the genesis proof target, in millionths.
+pub fn difficulty_micros<N: Network>(proof_target: u64) -> u128 {
+    (proof_target as u128).saturating_mul(FIXED_POINT_SCALE) / N::GENESIS_PROOF_TARGET.max(1) as u128
+}
+
+/// Returns the difficulty of the given proof target, relative to the genesis proof target.
+pub fn difficulty<N: Network>(proof_target: u64) -> f64 {
+    from_fixed_point(difficulty_micros::<N>(proof_target))
+}
+
+/// Returns the work per second between the given `(timestamp, cumulative proof target)` pairs, in millionths,
+/// where a non-positive duration counts as one second.
+pub fn hashrate_micros(first: (i64, u128), last: (i64, u128)) -> u128 {
+    let work = last.1.saturating_sub(first.1);
+    let duration = last.0.saturating_sub(first.0).max(1);
+    work.saturating_mul(FIXED_POINT_SCALE) / duration as u128
+}
+
+/// Returns the work per second between the given `(timestamp, cumulative proof target)` pairs,
+/// where a non-positive duration counts as one second.
+pub fn hashrate(first: (i64, u128), last: (i64, u128)) -> f64 {
+    from_fixed_point(hashrate_micros(first, last))
+}
+
+/// Returns the value of the given fixed-point number, in millionths.
+fn from_fixed_point(micros: u128) -> f64 {
+    micros as f64 / FIXED_POINT_SCALE as f64
+}
+
+/// Returns the heights from `from_height` to `to_height` (inclusive), every `stride` blocks,
+/// which always include `to_height` so that the latest entry of the range is sampled.
+pub fn sample_heights(from_height: u32, to_height: u32, stride: u32) -> Result<Vec<u32>> {
+    ensure!(stride > 0, "The stride of the difficulty history must be positive");
+    ensure!(from_height <= to_height, "The difficulty history starts at block {from_height} after block {to_height}");
+    // Ensure the number of sampled heights is within the limit.
+    let num_heights = ((to_height - from_height) / stride) as usize + 1;
+    ensure!(
+        num_heights <= MAX_DIFFICULTY_HISTORY_ENTRIES,
+        "The difficulty history has {num_heights} entries, above the limit of {MAX_DIFFICULTY_HISTORY_ENTRIES}"
+    );
+    let mut heights = (from_height..=to_height).step_by(stride as usize).collect::<Vec<_>>();
+    if heights.last() != Some(&to_height) {
+        heights.push(to_height);
+    }
+    Ok(heights)
+}
+
+/// An index of the difficulty of the canonical blocks, and of the estimated hashrate of the network as of each block,
+/// for the charts of the difficulty and hashrate over time.
+///
+/// The entry of a block is removed in the same batch that disconnects it, and the entries of the blocks of the new
+/// branch are computed from their own trailing window, so the index only reflects the current canonical chain.
+#[derive(Clone)]
+pub struct DifficultyIndex<N: Network> {
+    /// The mapping of `block height` to `difficulty entry`.
+    block_map: DataMap<u32, DifficultyEntry>,
+    /// The number of indexed blocks, which is the only value in the map.
+    height_map: DataMap<(), u32>,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> DifficultyIndex<N> {
+    /// Opens the difficulty index of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the difficulty index of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            block_map: database.map(MapID::Difficulty(DifficultyMap::Block)),
+            height_map: database.map(MapID::Difficulty(DifficultyMap::Height)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of indexed blocks, including the writes of the atomic batch in progress.
+    pub fn num_blocks(&self) -> Result<u32> {
+        Ok(self.height_map.get_speculative(&())?.map_or(0, |num_blocks| *num_blocks))
+    }
+
+    /// Returns the difficulty entry of the block at the given height, if it is indexed.
+    pub fn get_entry(&self, height: u32) -> Result<Option<DifficultyEntry>> {
+        Ok(self.block_map.get_speculative(&height)?.map(|entry| *entry))
+    }
+
+    /// Returns the difficulty entries from `from_height` to `to_height` (inclusive), every `stride` blocks.
+    /// The range is clamped to the indexed blocks.
+    pub fn get_history(&self, from_height: u32, to_height: u32, stride: u32) -> Result<Vec<(u32, DifficultyEntry)>> {
+        let to_height = match self.num_blocks()?.checked_sub(1) {
+            Some(latest_height) => to_height.min(latest_height),
+            None => return Ok(Vec::new()),
+        };
+        if from_height > to_height {
+            return Ok(Vec::new());
+        }
+        sample_heights(from_height, to_height, stride)?
+            .into_iter()
+            .map(|height| match self.get_entry(height)? {
+                Some(entry) => Ok((height, entry)),
+                None => bail!("Missing the difficulty entry of block {height}"),
+            })
+            .collect()
+    }
+
+    /// Returns the estimated hashrate of the network over the given number of latest blocks, if any block is indexed.
+    pub fn get_network_hashrate(&self, window: u32) -> Result<Option<f64>> {
+        ensure!(window > 0, "The window of the network hashrate must be positive");
+        let latest_height = match self.num_blocks()?.checked_sub(1) {
+            Some(latest_height) => latest_height,
+            None => return Ok(None),
+        };
+        let first_height = latest_height.saturating_sub(window);
+        match (self.get_entry(first_height)?, self.get_entry(latest_height)?) {
+            (Some(first), Some(last)) => Ok(Some(hashrate(
+                (first.timestamp, first.cumulative_proof_target),
+                (last.timestamp, last.cumulative_proof_target),
+            ))),
+            _ => bail!("Missing the difficulty entry of block {first_height} or {latest_height}"),
+        }
+    }
+
+    /// Indexes the difficulty of the given block header, which must follow the latest indexed block.
+    /// Note that a block that is already indexed is skipped.
+    pub fn insert(&self, header: &Header<N>) -> Result<()> {
+        let height = header.height();
+        let num_blocks = self.num_blocks()?;
+        if height < num_blocks {
+            return Ok(());
+        }
+        // Ensure the block follows the latest indexed block.
+        ensure!(
+            height == num_blocks,
+            "Block {height} does not follow the latest indexed block (expected {num_blocks})"
+        );
+
+        // Estimate the hashrate over the trailing window, which is empty for the genesis block.
+        let first_height = height.saturating_sub(HASHRATE_WINDOW);
+        let hashrate_micros = match height == first_height {
+            true => 0,
+            false => match self.get_entry(first_height)? {
+                Some(first) => hashrate_micros(
+                    (first.timestamp, first.cumulative_proof_target),
+                    (header.timestamp(), header.cumulative_proof_target()),
+                ),
+                None => bail!("Missing the difficulty entry of block {first_height}"),
+            },
+        };
+        let entry = DifficultyEntry {
+            timestamp: header.timestamp(),
+            proof_target: header.proof_target(),
+            coinbase_target: header.coinbase_target(),
+            difficulty_micros: difficulty_micros::<N>(header.proof_target()),
+            cumulative_proof_target: header.cumulative_proof_target(),
+            hashrate_micros,
+        };
+        self.block_map.insert(height, entry)?;
+        self.height_map.insert((), height + 1)
+    }
+
+    /// Removes the block with the given height from the index, which must be the latest indexed block.
+    pub fn remove(&self, height: u32) -> Result<()> {
+        // Ensure the block is the latest indexed block.
+        let num_blocks = self.num_blocks()?;
+        ensure!(height.checked_add(1) == Some(num_blocks), "Block {height} is not the latest indexed block");
+        self.block_map.remove(&height)?;
+        self.height_map.insert((), height)
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        self.block_map.start_atomic();
+        self.height_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.block_map.is_atomic_in_progress() || self.height_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        self.block_map.abort_atomic();
+        self.height_map.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        self.block_map.finish_atomic()?;
+        self.height_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a header at the given height, with the given timestamp and cumulative proof target.
+    fn sample_header(height: u32, timestamp: i64, cumulative_proof_target: u128) -> Header<CurrentNetwork> {
+        let metadata = Metadata::new(
+            CurrentNetwork::ID,
+            height as u64,
+            height,
+            CurrentNetwork::STARTING_SUPPLY,
+            cumulative_proof_target,
+            CurrentNetwork::GENESIS_COINBASE_TARGET,
+            CurrentNetwork::GENESIS_PROOF_TARGET,
+            CurrentNetwork::GENESIS_COINBASE_TARGET,
+            CurrentNetwork::GENESIS_TIMESTAMP,
+            timestamp,
+        )
+        .unwrap();
+        let previous_state_root: Field<CurrentNetwork> = match height {
+            0 => Field::zero(),
+            _ => Field::one(),
+        };
+        Header::from(previous_state_root, Field::one(), Field::zero(), Field::zero(), metadata).unwrap()
+    }
+
+    /// Returns a difficulty index, with a block every `block_time` seconds, each with the given work.
+    fn sample_index(num_blocks: u32, block_time: i64, work: u128) -> DifficultyIndex<CurrentNetwork> {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let index = DifficultyIndex::<CurrentNetwork>::from_database(&database);
+        for height in 0..num_blocks {
+            let timestamp = CurrentNetwork::GENESIS_TIMESTAMP + height as i64 * block_time;
+            index.insert(&sample_header(height, timestamp, height as u128 * work)).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_hashrate() {
+        // 600 units of work over 60 seconds.
+        assert_eq!(hashrate((100, 1_000), (160, 1_600)), 10.0);
+        // 1 unit of work over 4 seconds.
+        assert_eq!(hashrate((0, 0), (4, 1)), 0.25);
+        // A timestamp that goes backwards counts as one second.
+        assert_eq!(hashrate((100, 0), (90, 50)), 50.0);
+        assert_eq!(hashrate((100, 0), (100, 50)), 50.0);
+        // No work yields no hashrate.
+        assert_eq!(hashrate((0, 7), (30, 7)), 0.0);
+        // The difficulty is relative to the genesis proof target.
+        assert_eq!(difficulty::<CurrentNetwork>(CurrentNetwork::GENESIS_PROOF_TARGET * 3), 3.0);
+    }
+
+    #[test]
+    fn test_sample_heights() {
+        assert_eq!(sample_heights(0, 10, 1).unwrap(), (0..=10).collect::<Vec<_>>());
+        assert_eq!(sample_heights(0, 10, 5).unwrap(), vec![0, 5, 10]);
+        // The last height is always sampled.
+        assert_eq!(sample_heights(3, 10, 4).unwrap(), vec![3, 7, 10]);
+        assert_eq!(sample_heights(3, 10, 100).unwrap(), vec![3, 10]);
+        assert_eq!(sample_heights(7, 7, 3).unwrap(), vec![7]);
+        // The invalid ranges are rejected.
+        assert!(sample_heights(0, 10, 0).is_err());
+        assert!(sample_heights(11, 10, 1).is_err());
+        assert!(sample_heights(0, MAX_DIFFICULTY_HISTORY_ENTRIES as u32, 1).is_err());
+        assert_eq!(sample_heights(0, MAX_DIFFICULTY_HISTORY_ENTRIES as u32 - 1, 1).unwrap().len(), 1_000);
+    }
+
+    #[test]
+    #[serial]
+    fn test_insert_and_history() {
+        // A block every 15 seconds, each with 30 units of work, is 2 units of work per second.
+        let index = sample_index(100, 15, 30);
+        assert_eq!(index.num_blocks().unwrap(), 100);
+
+        // The genesis block has no hashrate, and the blocks within the first window use the blocks since genesis.
+        assert_eq!(index.get_entry(0).unwrap().unwrap().hashrate(), 0.0);
+        assert_eq!(index.get_entry(1).unwrap().unwrap().hashrate(), 2.0);
+        assert_eq!(index.get_entry(99).unwrap().unwrap().hashrate(), 2.0);
+        assert_eq!(index.get_entry(99).unwrap().unwrap().difficulty(), 1.0);
+        assert_eq!(index.get_network_hashrate(10).unwrap(), Some(2.0));
+
+        // Check the sampled heights, clamped to the indexed blocks.
+        let heights =
+            |entries: Vec<(u32, DifficultyEntry)>| entries.into_iter().map(|(height, _)| height).collect::<Vec<_>>();
+        assert_eq!(heights(index.get_history(0, 99, 25).unwrap()), vec![0, 25, 50, 75, 99]);
+        assert_eq!(heights(index.get_history(90, 1_000, 4).unwrap()), vec![90, 94, 98, 99]);
+        assert!(index.get_history(100, 200, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_reorg_corrects_entries() {
+        let index = sample_index(100, 15, 30);
+        // Disconnect the latest blocks.
+        for height in (90..100).rev() {
+            index.remove(height).unwrap();
+        }
+        assert!(index.remove(50).is_err());
+        assert_eq!(index.num_blocks().unwrap(), 90);
+        assert_eq!(index.get_entry(90).unwrap(), None);
+
+        // Connect a branch with blocks every 5 seconds, each with 30 units of work.
+        let (timestamp, work) = {
+            let entry = index.get_entry(89).unwrap().unwrap();
+            (entry.timestamp, entry.cumulative_proof_target)
+        };
+        for (i, height) in (90..100).enumerate() {
+            let i = i as i64 + 1;
+            index.insert(&sample_header(height, timestamp + i * 5, work + i as u128 * 30)).unwrap();
+        }
+        // The window of block 99 holds 50 blocks every 15 seconds and 10 blocks every 5 seconds.
+        // The work is 60 * 30 = 1,800 over 50 * 15 + 10 * 5 = 800 seconds.
+        assert_eq!(index.get_entry(99).unwrap().unwrap().hashrate(), 2.25);
+        // The work is 10 * 30 = 300 over 10 * 5 = 50 seconds.
+        assert_eq!(index.get_network_hashrate(10).unwrap(), Some(6.0));
+    }
+}
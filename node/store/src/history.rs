@@ -0,0 +1,335 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    HistoryMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::marker::PhantomData;
+
+/// The serial numbers and commitments of a transaction, as indexed by the history index.
+pub type TransactionRecords<N> = (<N as Network>::TransactionID, Vec<Field<N>>, Vec<Field<N>>);
+
+/// The status of a serial number as of a block height.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SerialNumberStatus<N: Network> {
+    /// The serial number was not spent at or below the height.
+    Unspent,
+    /// The serial number was spent by the transaction in the block at the given height.
+    Spent { height: u32, transaction_id: N::TransactionID },
+    /// The height is above the canonical chain, so the status is not known yet.
+    Unknown,
+}
+
+/// The status of a commitment as of a block height.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommitmentStatus<N: Network> {
+    /// The commitment was not created at or below the height.
+    Absent,
+    /// The commitment was created by the transaction in the block at the given height.
+    Created { height: u32, transaction_id: N::TransactionID },
+    /// The height is above the canonical chain, so the status is not known yet.
+    Unknown,
+}
+
+/// An index of the serial numbers and commitments by the height of the canonical block that spent or created them,
+/// for the point-in-time queries of the records (e.g. "was this record spent as of block 10,000").
+///
+/// The entries of a block are removed in the same batch that disconnects it, so the index only reflects the
+/// current canonical chain, and a spend that a reorg moves to another block is indexed at its new height.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct HistoryIndex<N: Network> {
+    /// The mapping of `serial number` to `(block height, transaction ID)`.
+    serial_number_map: DataMap<Field<N>, (u32, N::TransactionID)>,
+    /// The mapping of `commitment` to `(block height, transaction ID)`.
+    commitment_map: DataMap<Field<N>, (u32, N::TransactionID)>,
+    /// The mapping of `block height` to `([serial number], [commitment])`.
+    block_map: DataMap<u32, (Vec<Field<N>>, Vec<Field<N>>)>,
+    /// The number of indexed blocks, which is the only value in the map.
+    height_map: DataMap<(), u32>,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> HistoryIndex<N> {
+    /// Opens the history index of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the history index of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            serial_number_map: database.map(MapID::History(HistoryMap::SerialNumber)),
+            commitment_map: database.map(MapID::History(HistoryMap::Commitment)),
+            block_map: database.map(MapID::History(HistoryMap::Block)),
+            height_map: database.map(MapID::History(HistoryMap::Height)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of indexed blocks, including the writes of the atomic batch in progress.
+    pub fn num_blocks(&self) -> Result<u32> {
+        Ok(self.height_map.get_speculative(&())?.map_or(0, |num_blocks| *num_blocks))
+    }
+
+    /// Returns the height of the block that spent the given serial number, and the ID of its transaction,
+    /// if it was spent in the canonical chain.
+    pub fn get_spend(&self, serial_number: &Field<N>) -> Result<Option<(u32, N::TransactionID)>> {
+        Ok(self.serial_number_map.get(serial_number)?.map(|entry| *entry))
+    }
+
+    /// Returns the height of the block that created the given commitment, and the ID of its transaction,
+    /// if it was created in the canonical chain.
+    pub fn get_creation(&self, commitment: &Field<N>) -> Result<Option<(u32, N::TransactionID)>> {
+        Ok(self.commitment_map.get(commitment)?.map(|entry| *entry))
+    }
+
+    /// Returns the status of the given serial number as of the given block height.
+    pub fn is_serial_spent_at(&self, serial_number: &Field<N>, height: u32) -> Result<SerialNumberStatus<N>> {
+        match self.get_spend(serial_number)? {
+            // The serial number was spent as of every height from the block that spent it.
+            Some((spent_height, transaction_id)) if spent_height <= height => {
+                Ok(SerialNumberStatus::Spent { height: spent_height, transaction_id })
+            }
+            // Otherwise, it was unspent as of the height, if the height is in the canonical chain.
+            _ => match height < self.num_blocks()? {
+                true => Ok(SerialNumberStatus::Unspent),
+                false => Ok(SerialNumberStatus::Unknown),
+            },
+        }
+    }
+
+    /// Returns the status of the given commitment as of the given block height.
+    pub fn is_commitment_created_at(&self, commitment: &Field<N>, height: u32) -> Result<CommitmentStatus<N>> {
+        match self.get_creation(commitment)? {
+            // The commitment exists as of every height from the block that created it.
+            Some((created_height, transaction_id)) if created_height <= height => {
+                Ok(CommitmentStatus::Created { height: created_height, transaction_id })
+            }
+            // Otherwise, it was absent as of the height, if the height is in the canonical chain.
+            _ => match height < self.num_blocks()? {
+                true => Ok(CommitmentStatus::Absent),
+                false => Ok(CommitmentStatus::Unknown),
+            },
+        }
+    }
+
+    /// Indexes the serial numbers and commitments of the given block, which must follow the latest indexed block.
+    pub fn insert_block(&self, block: &Block<N>) -> Result<()> {
+        let transactions = block
+            .transactions()
+            .iter()
+            .map(|transaction| {
+                (
+                    transaction.id(),
+                    transaction.serial_numbers().copied().collect(),
+                    transaction.commitments().copied().collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.insert(block.height(), &transactions)
+    }
+
+    /// Indexes the serial numbers and commitments of the transactions of the block at the given height,
+    /// which must follow the latest indexed block. Note that a block that is already indexed is skipped.
+    pub fn insert(&self, height: u32, transactions: &[TransactionRecords<N>]) -> Result<()> {
+        let num_blocks = self.num_blocks()?;
+        if height < num_blocks {
+            return Ok(());
+        }
+        // Ensure the block follows the latest indexed block.
+        ensure!(
+            height == num_blocks,
+            "Block {height} does not follow the latest indexed block (expected {num_blocks})"
+        );
+
+        // Index the serial numbers and commitments under the block.
+        let (mut serial_numbers, mut commitments) = (Vec::new(), Vec::new());
+        for (transaction_id, transaction_serial_numbers, transaction_commitments) in transactions {
+            for serial_number in transaction_serial_numbers {
+                self.serial_number_map.insert(*serial_number, (height, *transaction_id))?;
+                serial_numbers.push(*serial_number);
+            }
+            for commitment in transaction_commitments {
+                self.commitment_map.insert(*commitment, (height, *transaction_id))?;
+                commitments.push(*commitment);
+            }
+        }
+        self.block_map.insert(height, (serial_numbers, commitments))?;
+        self.height_map.insert((), height + 1)
+    }
+
+    /// Removes the block with the given height from the index, which must be the latest indexed block.
+    pub fn remove(&self, height: u32) -> Result<()> {
+        // Ensure the block is the latest indexed block.
+        let num_blocks = self.num_blocks()?;
+        ensure!(height.checked_add(1) == Some(num_blocks), "Block {height} is not the latest indexed block");
+
+        // Remove the serial numbers and commitments of the block.
+        let (serial_numbers, commitments) = match self.block_map.get_speculative(&height)? {
+            Some(entry) => entry.into_owned(),
+            None => bail!("Missing the history of block {height}"),
+        };
+        serial_numbers.iter().try_for_each(|serial_number| self.serial_number_map.remove(serial_number))?;
+        commitments.iter().try_for_each(|commitment| self.commitment_map.remove(commitment))?;
+        self.block_map.remove(&height)?;
+        self.height_map.insert((), height)
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        self.serial_number_map.start_atomic();
+        self.commitment_map.start_atomic();
+        self.block_map.start_atomic();
+        self.height_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.serial_number_map.is_atomic_in_progress()
+            || self.commitment_map.is_atomic_in_progress()
+            || self.block_map.is_atomic_in_progress()
+            || self.height_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        self.serial_number_map.abort_atomic();
+        self.commitment_map.abort_atomic();
+        self.block_map.abort_atomic();
+        self.height_map.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        self.serial_number_map.finish_atomic()?;
+        self.commitment_map.finish_atomic()?;
+        self.block_map.finish_atomic()?;
+        self.height_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the sample field element for the given ID.
+    fn field(id: u32) -> Field<CurrentNetwork> {
+        Field::from_u32(id)
+    }
+
+    /// Returns the sample transaction ID for the given ID.
+    fn transaction_id(id: u32) -> <CurrentNetwork as Network>::TransactionID {
+        field(id).into()
+    }
+
+    /// Returns a history index, with the given number of blocks without records.
+    fn sample_index(num_blocks: u32) -> HistoryIndex<CurrentNetwork> {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let index = HistoryIndex::<CurrentNetwork>::from_database(&database);
+        for height in 0..num_blocks {
+            index.insert(height, &[]).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    #[serial]
+    fn test_serial_number_status_around_the_spend() {
+        // Create the record at height 3, and spend it at height 5.
+        let index = sample_index(3);
+        index.insert(3, &[(transaction_id(1), vec![], vec![field(100)])]).unwrap();
+        index.insert(4, &[]).unwrap();
+        index.insert(5, &[(transaction_id(2), vec![field(200)], vec![])]).unwrap();
+        index.insert(6, &[]).unwrap();
+        let spent = SerialNumberStatus::Spent { height: 5, transaction_id: transaction_id(2) };
+
+        // Ensure the serial number is unspent before, and spent at and after, the spending height.
+        assert_eq!(index.is_serial_spent_at(&field(200), 4).unwrap(), SerialNumberStatus::Unspent);
+        assert_eq!(index.is_serial_spent_at(&field(200), 5).unwrap(), spent);
+        assert_eq!(index.is_serial_spent_at(&field(200), 6).unwrap(), spent);
+        // Ensure the spend is known above the canonical chain, while an unspent serial number is not.
+        assert_eq!(index.is_serial_spent_at(&field(200), 100).unwrap(), spent);
+        assert_eq!(index.is_serial_spent_at(&field(300), 6).unwrap(), SerialNumberStatus::Unspent);
+        assert_eq!(index.is_serial_spent_at(&field(300), 7).unwrap(), SerialNumberStatus::Unknown);
+
+        // Ensure the commitment is absent before, and created at and after, the creating height.
+        let created = CommitmentStatus::Created { height: 3, transaction_id: transaction_id(1) };
+        assert_eq!(index.is_commitment_created_at(&field(100), 2).unwrap(), CommitmentStatus::Absent);
+        assert_eq!(index.is_commitment_created_at(&field(100), 3).unwrap(), created);
+        assert_eq!(index.is_commitment_created_at(&field(100), 6).unwrap(), created);
+        assert_eq!(index.is_commitment_created_at(&field(200), 7).unwrap(), CommitmentStatus::Unknown);
+
+        // Ensure a block that is already indexed is skipped, and a block must follow the latest block.
+        index.insert(5, &[]).unwrap();
+        assert_eq!(index.is_serial_spent_at(&field(200), 5).unwrap(), spent);
+        assert!(index.insert(8, &[]).is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_serial_number_status_across_reorg() {
+        // Spend the record at height 5.
+        let index = sample_index(5);
+        index.insert(5, &[(transaction_id(2), vec![field(200)], vec![field(201)])]).unwrap();
+        index.insert(6, &[]).unwrap();
+
+        // Ensure only the latest block can be removed.
+        assert!(index.remove(5).is_err());
+
+        // Disconnect the blocks of the spend in a single batch, as a reorg does.
+        index.start_atomic();
+        index.remove(6).unwrap();
+        index.remove(5).unwrap();
+        index.finish_atomic().unwrap();
+
+        // Ensure the entries of the disconnected blocks no longer count.
+        assert_eq!(index.num_blocks().unwrap(), 5);
+        assert_eq!(index.get_spend(&field(200)).unwrap(), None);
+        assert_eq!(index.is_serial_spent_at(&field(200), 4).unwrap(), SerialNumberStatus::Unspent);
+        assert_eq!(index.is_serial_spent_at(&field(200), 5).unwrap(), SerialNumberStatus::Unknown);
+        assert_eq!(index.is_commitment_created_at(&field(201), 5).unwrap(), CommitmentStatus::Unknown);
+
+        // Connect the blocks of the other fork, which spends the record at height 7 instead.
+        for height in 5..7 {
+            index.insert(height, &[]).unwrap();
+        }
+        index.insert(7, &[(transaction_id(3), vec![field(200)], vec![])]).unwrap();
+
+        // Ensure the spend is queried at its new height.
+        assert_eq!(index.is_serial_spent_at(&field(200), 5).unwrap(), SerialNumberStatus::Unspent);
+        assert_eq!(index.is_serial_spent_at(&field(200), 6).unwrap(), SerialNumberStatus::Unspent);
+        assert_eq!(index.is_serial_spent_at(&field(200), 7).unwrap(), SerialNumberStatus::Spent {
+            height: 7,
+            transaction_id: transaction_id(3)
+        });
+        assert_eq!(index.is_commitment_created_at(&field(201), 7).unwrap(), CommitmentStatus::Absent);
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    DataTtlMap,
+    InvalidBlockMap,
+    MapID,
+    TtlMap,
+    TTL_SWEEP_BATCH_SIZE,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::time::Duration;
+use std::net::SocketAddr;
+
+/// The time to live of a persisted invalid block, after which it is validated again if it is sent again.
+pub const INVALID_BLOCK_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The persisted verdict on an invalid block, as `(block height, rejection reason, first seen, offending peer)`,
+/// where the first seen time is a UNIX timestamp (in seconds).
+pub type InvalidBlockEntry = (u32, String, i64, Option<SocketAddr>);
+
+/// The blocks that were rejected for their contents, persisted so that they are not validated again after a restart,
+/// until they expire.
+#[derive(Clone)]
+pub struct InvalidBlockStore<N: Network> {
+    /// The mapping of `block hash` to `invalid block entry`, which expire after `INVALID_BLOCK_TTL`.
+    block_map: DataTtlMap<N::BlockHash, InvalidBlockEntry>,
+}
+
+impl<N: Network> InvalidBlockStore<N> {
+    /// Opens the invalid block store of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Self::from_database(&RocksDB::open(N::ID, dev)?)
+    }
+
+    /// Opens the invalid block store of the given database, and moves the invalid blocks that were persisted
+    /// before they expired (if any) into it.
+    pub(crate) fn from_database(database: &RocksDB) -> Result<Self> {
+        let block_map = TtlMap::new(
+            database.map(MapID::InvalidBlock(InvalidBlockMap::Entry)),
+            database.map(MapID::InvalidBlock(InvalidBlockMap::Expiry)),
+            database.map(MapID::InvalidBlock(InvalidBlockMap::Clock)),
+        )?;
+        let store = Self { block_map };
+
+        // Move the invalid blocks of the map without expiry, which start their time to live now.
+        let legacy_map: DataMap<N::BlockHash, InvalidBlockEntry> =
+            database.map(MapID::InvalidBlock(InvalidBlockMap::Block));
+        let legacy = legacy_map.iter().map(|(hash, entry)| (*hash, entry.into_owned())).collect::<Vec<_>>();
+        if !legacy.is_empty() {
+            store.write_batch(&legacy.iter().map(|(hash, entry)| (*hash, Some(entry.clone()))).collect::<Vec<_>>())?;
+            legacy_map.start_atomic();
+            for (hash, _) in &legacy {
+                legacy_map.remove(hash)?;
+            }
+            legacy_map.finish_atomic()?;
+        }
+        Ok(store)
+    }
+
+    /// Returns the persisted invalid blocks, which are not expired.
+    pub fn blocks(&self) -> Vec<(N::BlockHash, InvalidBlockEntry)> {
+        self.block_map.entries().into_iter().map(|(hash, entry, _)| (hash, entry)).collect()
+    }
+
+    /// Writes the given batch in a single atomic write, where `Some` inserts the entry of the block,
+    /// which expires after `INVALID_BLOCK_TTL`, and `None` removes the block.
+    pub fn write_batch(&self, batch: &[(N::BlockHash, Option<InvalidBlockEntry>)]) -> Result<()> {
+        self.block_map.write_batch(
+            batch.iter().map(|(hash, entry)| (*hash, entry.clone().map(|entry| (entry, INVALID_BLOCK_TTL)))).collect(),
+        )
+    }
+
+    /// Deletes the expired invalid blocks from storage, and returns the number of deleted blocks.
+    pub fn sweep_expired(&self) -> Result<usize> {
+        self.block_map.sweep_expired(TTL_SWEEP_BATCH_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    #[serial]
+    fn test_invalid_block_store() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let store = InvalidBlockStore::<CurrentNetwork>::from_database(&database).unwrap();
+
+        let a = <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(1));
+        let b = <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(2));
+        let peer_ip = Some("127.0.0.1:4130".parse().unwrap());
+        let entry_a = (5, "Invalid coinbase".to_string(), 100, peer_ip);
+        let entry_b = (6, "Invalid transaction".to_string(), 200, None);
+
+        // Insert both blocks in a batch.
+        store.write_batch(&[(a, Some(entry_a.clone())), (b, Some(entry_b.clone()))]).unwrap();
+        let mut persisted = store.blocks();
+        persisted.sort_by_key(|(_, (height, ..))| *height);
+        assert_eq!(persisted, vec![(a, entry_a), (b, entry_b.clone())]);
+
+        // Remove a block, and ensure the store reopens with the remaining one.
+        store.write_batch(&[(a, None)]).unwrap();
+        let store = InvalidBlockStore::<CurrentNetwork>::from_database(&database).unwrap();
+        assert_eq!(store.blocks(), vec![(b, entry_b)]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_blocks_are_moved_to_the_ttl_column() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let hash = <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(1));
+        let entry = (5, "Invalid coinbase".to_string(), 100, None);
+
+        // Persist an invalid block in the map without expiry.
+        let legacy_map: DataMap<<CurrentNetwork as Network>::BlockHash, InvalidBlockEntry> =
+            database.map(MapID::InvalidBlock(InvalidBlockMap::Block));
+        legacy_map.insert(hash, entry.clone()).unwrap();
+
+        // Ensure the block is moved to the column with expiry once the store is opened.
+        let store = InvalidBlockStore::<CurrentNetwork>::from_database(&database).unwrap();
+        assert_eq!(store.blocks(), vec![(hash, entry)]);
+        assert!(legacy_map.iter().next().is_none());
+        assert_eq!(store.sweep_expired().unwrap(), 0);
+    }
+}
@@ -0,0 +1,407 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{self, DataMap, Database},
+    JournalMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use core::ops::Range;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, sync::Arc};
+
+/// The default number of events retained in the journal.
+pub const DEFAULT_JOURNAL_RETENTION: u64 = 100_000;
+/// The number of events read at once, when scanning the journal.
+const MAX_JOURNAL_PAGE_SIZE: usize = 1_000;
+
+/// Sets the number of events retained in the journal of the database with the given development ID,
+/// after which the oldest events are pruned. This must be called before the journal is opened, after which the
+/// retention is fixed.
+pub fn set_journal_retention<N: Network>(dev: Option<u16>, num_events: u64) -> Result<()> {
+    ensure!(num_events > 0, "The journal must retain at least one event");
+    let database = rocksdb::RocksDB::open(N::ID, dev)?;
+    let state = database.journal_state();
+    // The retention is fixed once it is set, or once the journal is opened, as the database is shared.
+    if state.retention.get().is_some() || state.sequence.get().is_some() {
+        let retention = state.retention();
+        ensure!(retention == num_events, "The journal retention is already set to {retention} (found {num_events})");
+        return Ok(());
+    }
+    state
+        .retention
+        .set(num_events)
+        .map_err(|num_events| anyhow!("The journal retention is already set to {num_events}"))
+}
+
+/// The state of the journal of a database, which is shared by every instance of the journal, as the database is.
+#[derive(Debug, Default)]
+pub(crate) struct JournalState {
+    /// The number of events retained in the journal, if one is set.
+    retention: OnceCell<u64>,
+    /// The sequence numbers of the journal, once it is opened.
+    sequence: OnceCell<Arc<Mutex<JournalSequence>>>,
+}
+
+impl JournalState {
+    /// Returns the number of events retained in the journal.
+    pub(crate) fn retention(&self) -> u64 {
+        self.retention.get().copied().unwrap_or(DEFAULT_JOURNAL_RETENTION)
+    }
+}
+
+/// A change to the canonical chain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum ChainEvent<N: Network> {
+    /// The block was connected to the tip of the canonical chain.
+    Connected { height: u32, hash: N::BlockHash, transaction_ids: Vec<N::TransactionID> },
+    /// The block was disconnected from the tip of the canonical chain.
+    Disconnected { height: u32, hash: N::BlockHash },
+    /// The transaction sequence numbers in `[start, end)` were released by the disconnection of the block at the
+    /// given height, and are reassigned to the transactions of the blocks connected next. Consumers that store the
+    /// transactions by sequence number truncate them from `start`.
+    SequencesInvalidated { height: u32, start: u64, end: u64 },
+}
+
+/// The sequence numbers of the journal.
+#[derive(Copy, Clone, Debug, Default)]
+struct JournalSequence {
+    /// The sequence number of the next committed event.
+    committed: u64,
+    /// The sequence number of the next event in the atomic batch in progress.
+    pending: u64,
+}
+
+/// An append-only journal of the changes to the canonical chain, for external consumers.
+///
+/// Each event is assigned a monotonically increasing sequence number, starting at 1,
+/// and only the latest events are retained.
+#[derive(Clone)]
+pub struct ChainJournal<N: Network> {
+    /// The mapping of `sequence number` to `event`.
+    event_map: DataMap<u64, ChainEvent<N>>,
+    /// The sequence numbers of the journal.
+    sequence: Arc<Mutex<JournalSequence>>,
+    /// The number of events retained in the journal.
+    retention: u64,
+}
+
+impl<N: Network> ChainJournal<N> {
+    /// Opens the journal, and prunes the events beyond the retention, the first time it is opened.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        let database = rocksdb::RocksDB::open(N::ID, dev)?;
+        let event_map: DataMap<u64, ChainEvent<N>> = database.map(MapID::Journal(JournalMap::Event));
+        let state = database.journal_state();
+        let retention = state.retention();
+        // Share the sequence numbers with the other instances, as the database is shared too.
+        let sequence = state.sequence.get_or_try_init(|| Self::load_sequence(&event_map, retention))?.clone();
+        Ok(Self { event_map, sequence, retention })
+    }
+
+    /// Initializes the journal from the given map, and prunes the events beyond the given retention.
+    ///
+    /// Unlike `open`, the sequence numbers are not shared with the other instances of the journal.
+    pub(crate) fn from_map(event_map: DataMap<u64, ChainEvent<N>>, retention: u64) -> Result<Self> {
+        let sequence = Self::load_sequence(&event_map, retention)?;
+        Ok(Self { event_map, sequence, retention })
+    }
+
+    /// Returns the sequence number of the next event of the given map.
+    fn next_sequence(event_map: &DataMap<u64, ChainEvent<N>>) -> u64 {
+        event_map.keys().max().map_or(1, |sequence| *sequence + 1)
+    }
+
+    /// Returns the sequence numbers of the given map, after pruning the events beyond the given retention.
+    fn load_sequence(event_map: &DataMap<u64, ChainEvent<N>>, retention: u64) -> Result<Arc<Mutex<JournalSequence>>> {
+        // Determine the sequence number of the next event.
+        let next = Self::next_sequence(event_map);
+        // Prune the events beyond the retention, in case the retention was lowered.
+        let pruned = event_map
+            .keys()
+            .map(|sequence| *sequence)
+            .filter(|sequence| sequence.saturating_add(retention) < next)
+            .collect::<Vec<_>>();
+        for sequence in pruned {
+            event_map.remove(&sequence)?;
+        }
+        Ok(Arc::new(Mutex::new(JournalSequence { committed: next, pending: next })))
+    }
+
+    /// Returns the sequence number of the latest event, or `0` if the journal is empty.
+    pub fn latest_sequence(&self) -> u64 {
+        self.sequence.lock().committed - 1
+    }
+
+    /// Returns the sequence number of the oldest retained event, or `1` if the journal is empty.
+    pub fn oldest_sequence(&self) -> u64 {
+        self.latest_sequence().saturating_sub(self.retention) + 1
+    }
+
+    /// Returns the height following the tip of the canonical chain, as of the latest event, or `0` if the journal is empty.
+    pub fn next_height(&self) -> Result<u32> {
+        match self.events(self.latest_sequence().saturating_sub(1), 1)?.pop() {
+            Some((_, ChainEvent::Connected { height, .. })) => Ok(height.saturating_add(1)),
+            Some((_, ChainEvent::Disconnected { height, .. })) => Ok(height),
+            Some((_, ChainEvent::SequencesInvalidated { height, .. })) => Ok(height),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns up to `limit` events with a sequence number greater than `since`, in order.
+    ///
+    /// If the events following `since` were pruned, the events start at the oldest retained event.
+    pub fn events(&self, since: u64, limit: usize) -> Result<Vec<(u64, ChainEvent<N>)>> {
+        let latest = self.latest_sequence();
+        // Skip to the oldest retained event, if the requested events were pruned.
+        let mut sequence = since.saturating_add(1).max(latest.saturating_sub(self.retention) + 1);
+
+        let mut events = Vec::with_capacity(limit.min(latest.saturating_sub(since) as usize));
+        while sequence <= latest && events.len() < limit {
+            match self.event_map.get(&sequence)? {
+                Some(event) => events.push((sequence, event.into_owned())),
+                None => bail!("Missing event {sequence} in the journal"),
+            }
+            sequence += 1;
+        }
+        Ok(events)
+    }
+
+    /// Returns the heights in the given range at which a block was disconnected from the canonical chain,
+    /// as of the retained events.
+    pub fn disconnected_heights(&self, heights: Range<u32>) -> Result<BTreeSet<u32>> {
+        let mut disconnected = BTreeSet::new();
+        let mut since = 0;
+        loop {
+            let events = self.events(since, MAX_JOURNAL_PAGE_SIZE)?;
+            match events.last() {
+                Some((sequence, _)) => since = *sequence,
+                None => return Ok(disconnected),
+            }
+            for (_, event) in events {
+                if let ChainEvent::Disconnected { height, .. } = event {
+                    if heights.contains(&height) {
+                        disconnected.insert(height);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends the given event to the journal, and prunes the oldest event beyond the retention.
+    ///
+    /// The event is only committed with the atomic batch in progress, if there is one.
+    pub fn append(&self, event: ChainEvent<N>) -> Result<u64> {
+        let mut sequence = self.sequence.lock();
+        let next = match self.event_map.is_atomic_in_progress() {
+            true => &mut sequence.pending,
+            false => &mut sequence.committed,
+        };
+        let event_sequence = *next;
+
+        // Append the event.
+        self.event_map.insert(event_sequence, event)?;
+        // Prune the oldest event beyond the retention.
+        if event_sequence > self.retention {
+            self.event_map.remove(&(event_sequence - self.retention))?;
+        }
+
+        *next += 1;
+        if !self.event_map.is_atomic_in_progress() {
+            sequence.pending = sequence.committed;
+        }
+        Ok(event_sequence)
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        let mut sequence = self.sequence.lock();
+        sequence.pending = sequence.committed;
+        self.event_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.event_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        let mut sequence = self.sequence.lock();
+        self.event_map.abort_atomic();
+        sequence.pending = sequence.committed;
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        let mut sequence = self.sequence.lock();
+        self.event_map.finish_atomic()?;
+        sequence.committed = sequence.pending;
+        Ok(())
+    }
+
+    /// Restores the sequence numbers from the storage, after the atomic batch that finished last was not written.
+    pub(crate) fn reload_sequence(&self) {
+        let next = Self::next_sequence(&self.event_map);
+        *self.sequence.lock() = JournalSequence { committed: next, pending: next };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the tip of the canonical chain, reconstructed by replaying the given events.
+    fn replay(events: &[(u64, ChainEvent<CurrentNetwork>)]) -> Vec<(u32, <CurrentNetwork as Network>::BlockHash)> {
+        let mut chain = Vec::new();
+        for (_, event) in events {
+            match event {
+                ChainEvent::Connected { height, hash, .. } => {
+                    assert_eq!(*height as usize, chain.len());
+                    chain.push((*height, *hash));
+                }
+                ChainEvent::Disconnected { height, hash } => assert_eq!(chain.pop(), Some((*height, *hash))),
+                ChainEvent::SequencesInvalidated { .. } => (),
+            }
+        }
+        chain
+    }
+
+    fn sample_journal(retention: u64) -> ChainJournal<CurrentNetwork> {
+        let event_map = rocksdb::RocksDB::open_map_testing(temp_dir(), None, MapID::Journal(JournalMap::Event))
+            .expect("Failed to open the journal");
+        ChainJournal::from_map(event_map, retention).unwrap()
+    }
+
+    #[test]
+    #[serial]
+    fn test_journal_reorg() {
+        let rng = &mut TestRng::default();
+        let journal = sample_journal(DEFAULT_JOURNAL_RETENTION);
+        assert_eq!(journal.latest_sequence(), 0);
+
+        let connect = |height: u32, hash| {
+            journal.start_atomic();
+            journal.append(ChainEvent::Connected { height, hash, transaction_ids: vec![] }).unwrap();
+            journal.finish_atomic().unwrap();
+        };
+        let disconnect = |height: u32, hash| {
+            journal.start_atomic();
+            journal.append(ChainEvent::Disconnected { height, hash }).unwrap();
+            journal.finish_atomic().unwrap();
+        };
+
+        // Connect a chain of four blocks.
+        let chain = (0..4).map(|height| (height, Field::<CurrentNetwork>::rand(rng).into())).collect::<Vec<_>>();
+        for (height, hash) in &chain {
+            connect(*height, *hash);
+        }
+        // Reorganize to a fork of the two latest blocks, which is one block longer.
+        let fork = (2..5).map(|height| (height, Field::<CurrentNetwork>::rand(rng).into())).collect::<Vec<_>>();
+        disconnect(3, chain[3].1);
+        disconnect(2, chain[2].1);
+        for (height, hash) in &fork {
+            connect(*height, *hash);
+        }
+
+        // Ensure the journal contains the exact disconnect and connect sequence.
+        let events = journal.events(4, 10).unwrap();
+        assert_eq!(events.iter().map(|(sequence, _)| *sequence).collect::<Vec<_>>(), vec![5, 6, 7, 8, 9]);
+        assert_eq!(events[0].1, ChainEvent::Disconnected { height: 3, hash: chain[3].1 });
+        assert_eq!(events[1].1, ChainEvent::Disconnected { height: 2, hash: chain[2].1 });
+        for (event, (height, hash)) in events[2..].iter().zip(&fork) {
+            assert_eq!(event.1, ChainEvent::Connected { height: *height, hash: *hash, transaction_ids: vec![] });
+        }
+        assert_eq!(journal.latest_sequence(), 9);
+
+        // Ensure replaying the events reconstructs the tip.
+        let replayed = replay(&journal.events(0, 100).unwrap());
+        assert_eq!(replayed.last(), fork.last());
+        assert_eq!(replayed[..2], chain[..2]);
+        assert_eq!(journal.disconnected_heights(0..5).unwrap(), [2, 3].into_iter().collect());
+        assert_eq!(journal.disconnected_heights(3..5).unwrap(), [3].into_iter().collect());
+
+        // Ensure the events are paginated.
+        let mut paginated = Vec::new();
+        let mut since = 0;
+        loop {
+            let page = journal.events(since, 2).unwrap();
+            match page.last() {
+                Some((sequence, _)) => since = *sequence,
+                None => break,
+            }
+            paginated.extend(page);
+        }
+        assert_eq!(paginated, journal.events(0, 100).unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn test_journal_aborted_batch() {
+        let rng = &mut TestRng::default();
+        let journal = sample_journal(DEFAULT_JOURNAL_RETENTION);
+        let hash = Field::<CurrentNetwork>::rand(rng).into();
+
+        // Ensure an aborted event is neither visible nor consumes a sequence number.
+        journal.start_atomic();
+        journal.append(ChainEvent::Connected { height: 0, hash, transaction_ids: vec![] }).unwrap();
+        assert!(journal.events(0, 10).unwrap().is_empty());
+        journal.abort_atomic();
+        assert_eq!(journal.latest_sequence(), 0);
+
+        journal.start_atomic();
+        assert_eq!(journal.append(ChainEvent::Connected { height: 0, hash, transaction_ids: vec![] }).unwrap(), 1);
+        journal.finish_atomic().unwrap();
+        assert_eq!(journal.events(0, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_journal_pruning() {
+        let rng = &mut TestRng::default();
+        let journal = sample_journal(3);
+
+        for height in 0..5 {
+            let hash = Field::<CurrentNetwork>::rand(rng).into();
+            journal.append(ChainEvent::Connected { height, hash, transaction_ids: vec![] }).unwrap();
+        }
+
+        // Ensure only the latest events are retained, and a consumer that fell behind resumes at the oldest one.
+        let events = journal.events(0, 10).unwrap();
+        assert_eq!(events.iter().map(|(sequence, _)| *sequence).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(journal.event_map.keys().count(), 3);
+        assert!(journal.events(5, 10).unwrap().is_empty());
+
+        // Ensure reopening the journal with a lower retention prunes the excess events.
+        let journal = ChainJournal::from_map(journal.event_map.clone(), 1).unwrap();
+        assert_eq!(journal.latest_sequence(), 5);
+        assert_eq!(journal.events(0, 10).unwrap().len(), 1);
+        assert_eq!(journal.event_map.keys().count(), 1);
+    }
+}
@@ -21,21 +21,75 @@ extern crate tracing;
 
 pub mod rocksdb;
 
+mod audit;
+pub use audit::*;
+
 mod block;
 pub use block::*;
 
+mod ciphertext;
+pub use ciphertext::*;
+
+mod circuit;
+pub use circuit::*;
+
 mod consensus;
 pub use consensus::*;
 
+mod difficulty;
+pub use difficulty::*;
+
+mod history;
+pub use history::*;
+
+mod invalid;
+pub use invalid::*;
+
+mod journal;
+pub use journal::*;
+
+mod memory_pool;
+pub use memory_pool::*;
+
+mod migration;
+pub use migration::*;
+
+mod mmr;
+pub use mmr::*;
+
+mod ordering;
+pub use ordering::*;
+
 mod program;
 pub use program::*;
 
+mod pruning;
+pub use pruning::*;
+
+mod replay;
+pub use replay::*;
+
+mod sequence;
+pub use sequence::*;
+
+mod submission;
+pub use submission::*;
+
+mod subscription;
+pub use subscription::*;
+
+mod timestamp;
+pub use timestamp::*;
+
 mod transaction;
 pub use transaction::*;
 
 mod transition;
 pub use transition::*;
 
+mod ttl;
+pub use ttl::*;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[repr(u16)]
 pub enum MapID {
@@ -47,6 +101,19 @@ pub enum MapID {
     TransitionInput(TransitionInputMap),
     TransitionOutput(TransitionOutputMap),
     Program(ProgramMap),
+    Journal(JournalMap),
+    Schema(SchemaMap),
+    Pruning(PruningMap),
+    Circuit(CircuitMap),
+    MemoryPool(MemoryPoolMap),
+    Timestamp(TimestampMap),
+    History(HistoryMap),
+    Difficulty(DifficultyMap),
+    InvalidBlock(InvalidBlockMap),
+    ChainMmr(ChainMmrMap),
+    Sequence(SequenceMap),
+    SubmittedBlock(SubmittedBlockMap),
+    AuditLog(AuditLogMap),
     #[cfg(test)]
     Test(TestMap),
 }
@@ -62,6 +129,19 @@ impl From<MapID> for u16 {
             MapID::TransitionInput(id) => id as u16,
             MapID::TransitionOutput(id) => id as u16,
             MapID::Program(id) => id as u16,
+            MapID::Journal(id) => id as u16,
+            MapID::Schema(id) => id as u16,
+            MapID::Pruning(id) => id as u16,
+            MapID::Circuit(id) => id as u16,
+            MapID::MemoryPool(id) => id as u16,
+            MapID::Timestamp(id) => id as u16,
+            MapID::History(id) => id as u16,
+            MapID::Difficulty(id) => id as u16,
+            MapID::InvalidBlock(id) => id as u16,
+            MapID::ChainMmr(id) => id as u16,
+            MapID::Sequence(id) => id as u16,
+            MapID::SubmittedBlock(id) => id as u16,
+            MapID::AuditLog(id) => id as u16,
             #[cfg(test)]
             MapID::Test(id) => id as u16,
         }
@@ -130,6 +210,8 @@ pub enum TransitionOutputMap {
     Record = DataID::OutputRecordMap as u16,
     RecordNonce = DataID::OutputRecordNonceMap as u16,
     ExternalRecord = DataID::OutputExternalRecordMap as u16,
+    RecordReference = DataID::OutputRecordReferenceMap as u16,
+    RecordCiphertext = DataID::OutputRecordCiphertextMap as u16,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -161,11 +243,108 @@ pub enum ProgramMap {
     Value = DataID::ValueMap as u16,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum JournalMap {
+    Event = DataID::JournalEventMap as u16,
+    Cursor = DataID::JournalCursorMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum SchemaMap {
+    Version = DataID::SchemaVersionMap as u16,
+    Migration = DataID::SchemaMigrationMap as u16,
+    Chunk = DataID::SchemaChunkMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum PruningMap {
+    Height = DataID::PruningHeightMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum CircuitMap {
+    Count = DataID::CircuitCountMap as u16,
+    Transaction = DataID::CircuitTransactionMap as u16,
+    Reverse = DataID::CircuitReverseMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum MemoryPoolMap {
+    Transaction = DataID::MemoryPoolTransactionMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum TimestampMap {
+    Height = DataID::TimestampHeightMap as u16,
+    Block = DataID::TimestampBlockMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum HistoryMap {
+    SerialNumber = DataID::HistorySerialNumberMap as u16,
+    Commitment = DataID::HistoryCommitmentMap as u16,
+    Block = DataID::HistoryBlockMap as u16,
+    Height = DataID::HistoryHeightMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum DifficultyMap {
+    Block = DataID::DifficultyBlockMap as u16,
+    Height = DataID::DifficultyHeightMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum InvalidBlockMap {
+    Block = DataID::InvalidBlockMap as u16,
+    Entry = DataID::InvalidBlockEntryMap as u16,
+    Expiry = DataID::InvalidBlockExpiryMap as u16,
+    Clock = DataID::InvalidBlockClockMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum ChainMmrMap {
+    Node = DataID::ChainMmrNodeMap as u16,
+    Size = DataID::ChainMmrSizeMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum SequenceMap {
+    Block = DataID::SequenceBlockMap as u16,
+    Transaction = DataID::SequenceTransactionMap as u16,
+    Sequence = DataID::SequenceSequenceMap as u16,
+    Height = DataID::SequenceHeightMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum SubmittedBlockMap {
+    Block = DataID::SubmittedBlockMap as u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum AuditLogMap {
+    Entry = DataID::AuditLogEntryMap as u16,
+}
+
 #[cfg(test)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[repr(u16)]
 pub enum TestMap {
     Test = DataID::Test as u16,
+    TtlExpiry = DataID::TestTtlExpiry as u16,
+    TtlClock = DataID::TestTtlClock as u16,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -233,8 +412,158 @@ enum DataID {
     KeyValueIDMap,
     KeyMap,
     ValueMap,
+    // Journal
+    JournalEventMap,
+    // Schema
+    SchemaVersionMap,
+    SchemaMigrationMap,
+    // Pruning
+    PruningHeightMap,
+    // Circuit
+    CircuitCountMap,
+    CircuitTransactionMap,
+    CircuitReverseMap,
+    // Memory pool
+    MemoryPoolTransactionMap,
+    // Schema (continued)
+    SchemaChunkMap,
+    // Timestamp
+    TimestampHeightMap,
+    TimestampBlockMap,
+    // Output (continued)
+    OutputRecordReferenceMap,
+    OutputRecordCiphertextMap,
+    // History
+    HistorySerialNumberMap,
+    HistoryCommitmentMap,
+    HistoryBlockMap,
+    HistoryHeightMap,
+    // Difficulty
+    DifficultyBlockMap,
+    DifficultyHeightMap,
+    // Invalid block
+    InvalidBlockMap,
+    // Chain MMR
+    ChainMmrNodeMap,
+    ChainMmrSizeMap,
+    // Journal (continued)
+    JournalCursorMap,
+    // Sequence
+    SequenceBlockMap,
+    SequenceTransactionMap,
+    SequenceSequenceMap,
+    SequenceHeightMap,
+    // Submitted block
+    SubmittedBlockMap,
+    // Audit log
+    AuditLogEntryMap,
+    // Invalid block (continued)
+    InvalidBlockEntryMap,
+    InvalidBlockExpiryMap,
+    InvalidBlockClockMap,
 
     // Testing
     #[cfg(test)]
     Test,
+    #[cfg(test)]
+    TestTtlExpiry,
+    #[cfg(test)]
+    TestTtlClock,
+}
+
+impl DataID {
+    /// The data IDs of all maps in storage, in order.
+    const ALL: &'static [DataID] = &[
+        DataID::BlockStateRootMap,
+        DataID::BlockReverseStateRootMap,
+        DataID::BlockIDMap,
+        DataID::BlockReverseIDMap,
+        DataID::BlockHeaderMap,
+        DataID::BlockTransactionsMap,
+        DataID::BlockReverseTransactionsMap,
+        DataID::BlockCoinbaseSolutionMap,
+        DataID::BlockCoinbasePuzzleCommitmentMap,
+        DataID::BlockSignatureMap,
+        DataID::DeploymentIDMap,
+        DataID::DeploymentEditionMap,
+        DataID::DeploymentReverseIDMap,
+        DataID::DeploymentOwnerMap,
+        DataID::DeploymentProgramMap,
+        DataID::DeploymentVerifyingKeyMap,
+        DataID::DeploymentCertificateMap,
+        DataID::DeploymentFeeMap,
+        DataID::DeploymentReverseFeeMap,
+        DataID::ExecutionIDMap,
+        DataID::ExecutionReverseIDMap,
+        DataID::ExecutionInclusionMap,
+        DataID::ExecutionFeeMap,
+        DataID::InputIDMap,
+        DataID::InputReverseIDMap,
+        DataID::InputConstantMap,
+        DataID::InputPublicMap,
+        DataID::InputPrivateMap,
+        DataID::InputRecordMap,
+        DataID::InputRecordTagMap,
+        DataID::InputExternalRecordMap,
+        DataID::OutputIDMap,
+        DataID::OutputReverseIDMap,
+        DataID::OutputConstantMap,
+        DataID::OutputPublicMap,
+        DataID::OutputPrivateMap,
+        DataID::OutputRecordMap,
+        DataID::OutputRecordNonceMap,
+        DataID::OutputExternalRecordMap,
+        DataID::TransactionIDMap,
+        DataID::TransitionLocatorMap,
+        DataID::TransitionFinalizeMap,
+        DataID::TransitionProofMap,
+        DataID::TransitionTPKMap,
+        DataID::TransitionReverseTPKMap,
+        DataID::TransitionTCMMap,
+        DataID::TransitionReverseTCMMap,
+        DataID::ProgramIDMap,
+        DataID::ProgramIndexMap,
+        DataID::MappingIDMap,
+        DataID::KeyValueIDMap,
+        DataID::KeyMap,
+        DataID::ValueMap,
+        DataID::JournalEventMap,
+        DataID::SchemaVersionMap,
+        DataID::SchemaMigrationMap,
+        DataID::PruningHeightMap,
+        DataID::CircuitCountMap,
+        DataID::CircuitTransactionMap,
+        DataID::CircuitReverseMap,
+        DataID::MemoryPoolTransactionMap,
+        DataID::SchemaChunkMap,
+        DataID::TimestampHeightMap,
+        DataID::TimestampBlockMap,
+        DataID::OutputRecordReferenceMap,
+        DataID::OutputRecordCiphertextMap,
+        DataID::HistorySerialNumberMap,
+        DataID::HistoryCommitmentMap,
+        DataID::HistoryBlockMap,
+        DataID::HistoryHeightMap,
+        DataID::DifficultyBlockMap,
+        DataID::DifficultyHeightMap,
+        DataID::InvalidBlockMap,
+        DataID::ChainMmrNodeMap,
+        DataID::ChainMmrSizeMap,
+        DataID::JournalCursorMap,
+        DataID::SequenceBlockMap,
+        DataID::SequenceTransactionMap,
+        DataID::SequenceSequenceMap,
+        DataID::SequenceHeightMap,
+        DataID::SubmittedBlockMap,
+        DataID::AuditLogEntryMap,
+        DataID::InvalidBlockEntryMap,
+        DataID::InvalidBlockExpiryMap,
+        DataID::InvalidBlockClockMap,
+        #[cfg(test)]
+        DataID::Test,
+        #[cfg(test)]
+        DataID::TestTtlExpiry,
+        #[cfg(test)]
+        DataID::TestTtlClock,
+    ];
 }
@@ -0,0 +1,102 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    MapID,
+    MemoryPoolMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+/// The unconfirmed transactions of the memory pool, persisted so that they survive a restart.
+#[derive(Clone)]
+pub struct MemoryPoolStore<N: Network> {
+    /// The mapping of `transaction ID` to `(transaction, arrival time)`.
+    transaction_map: DataMap<N::TransactionID, (Transaction<N>, i64)>,
+}
+
+impl<N: Network> MemoryPoolStore<N> {
+    /// Opens the memory pool store of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the memory pool store of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self { transaction_map: database.map(MapID::MemoryPool(MemoryPoolMap::Transaction)) }
+    }
+
+    /// Returns the persisted transactions and their arrival times.
+    pub fn transactions(&self) -> Vec<(Transaction<N>, i64)> {
+        self.transaction_map.values().map(|entry| entry.into_owned()).collect()
+    }
+
+    /// Writes the given batch in a single atomic write, where `Some` inserts the transaction and its arrival time,
+    /// and `None` removes the transaction.
+    #[allow(clippy::type_complexity)]
+    pub fn write_batch(&self, batch: &[(N::TransactionID, Option<(Transaction<N>, i64)>)]) -> Result<()> {
+        self.transaction_map.start_atomic();
+        for (transaction_id, entry) in batch {
+            let result = match entry {
+                Some(entry) => self.transaction_map.insert(*transaction_id, entry.clone()),
+                None => self.transaction_map.remove(transaction_id),
+            };
+            if let Err(error) = result {
+                self.transaction_map.abort_atomic();
+                return Err(error);
+            }
+        }
+        self.transaction_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    #[serial]
+    fn test_memory_pool_store() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let store = MemoryPoolStore::<CurrentNetwork>::from_database(&database);
+
+        // Sample the transactions of the genesis block.
+        let genesis = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let transactions = genesis.transactions().iter().cloned().collect::<Vec<_>>();
+        assert!(transactions.len() >= 2);
+        let (a, b) = (&transactions[0], &transactions[1]);
+
+        // Insert both transactions in a batch.
+        store.write_batch(&[(a.id(), Some((a.clone(), 1))), (b.id(), Some((b.clone(), 2)))]).unwrap();
+        let mut persisted = store.transactions();
+        persisted.sort_by_key(|(_, arrival_time)| *arrival_time);
+        assert_eq!(persisted, vec![(a.clone(), 1), (b.clone(), 2)]);
+
+        // Remove a transaction, and ensure the store reopens with the remaining one.
+        store.write_batch(&[(a.id(), None)]).unwrap();
+        let store = MemoryPoolStore::<CurrentNetwork>::from_database(&database);
+        assert_eq!(store.transactions(), vec![(b.clone(), 2)]);
+    }
+}
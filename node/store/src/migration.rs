@@ -0,0 +1,2078 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, RocksDB},
+    BlockMap,
+    ChainEvent,
+    ChainJournal,
+    ChainMmr,
+    CircuitID,
+    CircuitIndex,
+    DeploymentMap,
+    DifficultyIndex,
+    ExecutionMap,
+    HistoryIndex,
+    JournalMap,
+    MapID,
+    RecordCiphertextMap,
+    SchemaMap,
+    SequenceIndex,
+    TimestampIndex,
+    TransactionRecords,
+    TransitionInputMap,
+    TransitionMap,
+    TransitionOutputMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::{marker::PhantomData, ops::Range};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The number of blocks journaled in each chunk of the journal backfill.
+pub const JOURNAL_BACKFILL_CHUNK_SIZE: u32 = 1_000;
+/// The number of blocks indexed in each chunk of the circuit index backfill.
+pub const CIRCUIT_BACKFILL_CHUNK_SIZE: u32 = 1_000;
+/// The number of blocks indexed in each chunk of the timestamp index backfill.
+pub const TIMESTAMP_BACKFILL_CHUNK_SIZE: u32 = 10_000;
+/// The number of output records moved in each chunk of the record deduplication.
+pub const RECORD_DEDUPLICATION_CHUNK_SIZE: usize = 1_000;
+/// The number of blocks indexed in each chunk of the history index backfill.
+pub const HISTORY_BACKFILL_CHUNK_SIZE: u32 = 1_000;
+/// The number of blocks indexed in each chunk of the difficulty index backfill.
+pub const DIFFICULTY_BACKFILL_CHUNK_SIZE: u32 = 10_000;
+/// The number of blocks appended in each chunk of the chain commitment backfill.
+pub const CHAIN_MMR_BACKFILL_CHUNK_SIZE: u32 = 10_000;
+/// The number of blocks numbered in each chunk of the sequence index backfill.
+pub const SEQUENCE_BACKFILL_CHUNK_SIZE: u32 = 10_000;
+
+/// The status of the latest migration applied by this process, if one was applied.
+static MIGRATION_STATUS: RwLock<Option<MigrationStatus>> = parking_lot::const_rwlock(None);
+
+/// Returns the status of the latest migration applied by this process, if one was applied.
+pub fn migration_status() -> Option<MigrationStatus> {
+    MIGRATION_STATUS.read().clone()
+}
+
+/// Returns the default number of workers of a backfill, which is the available parallelism.
+fn default_num_workers() -> usize {
+    thread::available_parallelism().map_or(1, |num_workers| num_workers.get())
+}
+
+/// Returns the registry of the migrations of the database schema, in order.
+pub fn migrations<N: Network>() -> Result<MigrationRegistry> {
+    MigrationRegistry::default()
+        .register(JournalBackfill::<N>::default())?
+        .register(CircuitIndexBackfill::<N>::default())?
+        .register(TimestampIndexBackfill::<N>::default())?
+        .register(RecordDeduplication::<N>::default())?
+        .register(HistoryIndexBackfill::<N>::default())?
+        .register(DifficultyIndexBackfill::<N>::default())?
+        .register(ChainMmrBackfill::<N>::default())?
+        .register(SequenceIndexBackfill::<N>::default())
+}
+
+/// The progress of a migration, reported after each chunk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// The cursor to resume the migration from.
+    pub cursor: u64,
+    /// The number of items migrated so far.
+    pub num_migrated: u64,
+    /// The total number of items to migrate.
+    pub num_total: u64,
+    /// The number of chunks applied so far, including the chunks applied or skipped before this run.
+    pub num_chunks_done: u64,
+    /// The total number of chunks of the migration.
+    pub num_chunks: u64,
+}
+
+/// The status of a migration, for operators following a long migration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationStatus {
+    /// The schema version of the database after the migration.
+    pub version: u32,
+    /// The description of the migration.
+    pub description: String,
+    /// The number of chunks applied so far, including the chunks applied or skipped before this run.
+    pub num_chunks_done: u64,
+    /// The total number of chunks of the migration.
+    pub num_chunks: u64,
+    /// The number of items migrated so far.
+    pub num_migrated: u64,
+    /// The total number of items to migrate.
+    pub num_total: u64,
+    /// The estimated number of seconds until the migration completes, once a chunk was applied.
+    pub eta_secs: Option<u64>,
+    /// If `true`, the migration completed.
+    pub completed: bool,
+}
+
+/// A forward migration of the database schema.
+pub trait Migration: Send + Sync {
+    /// Returns the schema version of the database after the migration.
+    fn version(&self) -> u32;
+
+    /// Returns the description of the migration.
+    fn description(&self) -> &'static str;
+
+    /// Applies the migration to the given database, resuming from the given cursor, if the migration was interrupted.
+    ///
+    /// The migration reports its progress after each chunk, and the cursor is recorded before the next chunk starts.
+    /// A chunk may be applied again if the migration is interrupted before its cursor is recorded, so each chunk
+    /// must be idempotent. If `progress` returns an error, the migration must stop and return it.
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()>;
+}
+
+/// The record of a migration that was applied, or started, on the database.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationRecord {
+    /// The description of the migration.
+    pub description: String,
+    /// The UNIX timestamp at which the migration started.
+    pub started_at: u64,
+    /// The cursor to resume the migration from, if a chunk was applied.
+    pub cursor: Option<u64>,
+    /// The UNIX timestamp at which the migration completed, if it did.
+    pub completed_at: Option<u64>,
+}
+
+/// The schema of the database, along with the record of the migrations applied on it.
+#[derive(Clone)]
+pub struct SchemaDB {
+    /// The schema version of the database, which is the only value in the map.
+    version_map: DataMap<(), u32>,
+    /// The mapping of `schema version` to the record of the migration to it.
+    migration_map: DataMap<u32, MigrationRecord>,
+    /// The mapping of `(schema version, start height)` to the end height of a completed chunk of the migration to it.
+    chunk_map: DataMap<(u32, u32), u32>,
+}
+
+impl SchemaDB {
+    /// Opens the schema of the given database.
+    pub fn open(database: &RocksDB) -> Self {
+        Self {
+            version_map: database.map(MapID::Schema(SchemaMap::Version)),
+            migration_map: database.map(MapID::Schema(SchemaMap::Migration)),
+            chunk_map: database.map(MapID::Schema(SchemaMap::Chunk)),
+        }
+    }
+
+    /// Returns the schema version of the database, which is `0` if no migration was applied.
+    pub fn version(&self) -> Result<u32> {
+        Ok(self.version_map.get(&())?.map_or(0, |version| *version))
+    }
+
+    /// Returns the record of the migration to the given schema version, if it was started.
+    pub fn migration(&self, version: u32) -> Result<Option<MigrationRecord>> {
+        Ok(self.migration_map.get(&version)?.map(|record| record.into_owned()))
+    }
+
+    /// Returns the records of the migrations started on the database, in order.
+    pub fn migrations(&self) -> Result<Vec<(u32, MigrationRecord)>> {
+        let mut migrations =
+            self.migration_map.iter().map(|(version, record)| (*version, record.into_owned())).collect::<Vec<_>>();
+        migrations.sort_unstable_by_key(|(version, _)| *version);
+        Ok(migrations)
+    }
+
+    /// Returns the number of chunks of the migration to the given schema version that are marked as completed.
+    pub fn num_completed_chunks(&self, version: u32) -> usize {
+        self.chunk_map.keys().filter(|key| key.0 == version).count()
+    }
+
+    /// Clears the chunks of the migration to the given schema version that are marked as completed,
+    /// so that the migration applies them again when it is run anew.
+    pub fn clear_chunks(&self, version: u32) -> Result<()> {
+        let chunks = self.chunk_map.keys().filter(|key| key.0 == version).map(|key| *key).collect::<Vec<_>>();
+        chunks.iter().try_for_each(|key| self.chunk_map.remove(key))
+    }
+
+    /// Returns `true` if the given chunk of the migration to the given schema version is marked as completed.
+    fn is_chunk_completed(&self, version: u32, chunk: &Range<u32>) -> Result<bool> {
+        Ok(self.chunk_map.get(&(version, chunk.start))?.map_or(false, |end| *end >= chunk.end))
+    }
+
+    /// Marks the given chunk of the migration to the given schema version as completed.
+    fn record_chunk(&self, version: u32, chunk: &Range<u32>) -> Result<()> {
+        self.chunk_map.insert((version, chunk.start), chunk.end)
+    }
+
+    /// Records the start of the given migration, and returns its record.
+    fn start(&self, migration: &dyn Migration) -> Result<MigrationRecord> {
+        let record = MigrationRecord {
+            description: migration.description().to_string(),
+            started_at: unix_timestamp(),
+            cursor: None,
+            completed_at: None,
+        };
+        self.migration_map.insert(migration.version(), record.clone())?;
+        Ok(record)
+    }
+
+    /// Records the cursor to resume the migration to the given schema version from.
+    fn record_cursor(&self, version: u32, cursor: u64) -> Result<()> {
+        match self.migration(version)? {
+            Some(record) => self.migration_map.insert(version, MigrationRecord { cursor: Some(cursor), ..record }),
+            None => bail!("Missing the record of migration {version}"),
+        }
+    }
+
+    /// Records the completion of the migration to the given schema version, and updates the schema version.
+    fn complete(&self, version: u32) -> Result<()> {
+        match self.migration(version)? {
+            Some(record) => self
+                .migration_map
+                .insert(version, MigrationRecord { completed_at: Some(unix_timestamp()), ..record })?,
+            None => bail!("Missing the record of migration {version}"),
+        }
+        // Remove the chunk markers of the migration, as it no longer resumes.
+        self.clear_chunks(version)?;
+        self.version_map.insert((), version)
+    }
+}
+
+/// The registry of the migrations of the database schema, which are applied in order when the database is opened.
+#[derive(Default)]
+pub struct MigrationRegistry {
+    /// The migrations, in order of schema version.
+    migrations: Vec<Box<dyn Migration>>,
+}
+
+impl MigrationRegistry {
+    /// Registers the given migration, which must migrate to the schema version following the latest one.
+    pub fn register(mut self, migration: impl Migration + 'static) -> Result<Self> {
+        let expected = self.latest_version() + 1;
+        ensure!(
+            migration.version() == expected,
+            "Migration '{}' has version {} (expected {expected})",
+            migration.description(),
+            migration.version()
+        );
+        self.migrations.push(Box::new(migration));
+        Ok(self)
+    }
+
+    /// Returns the latest schema version supported by the registry.
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.last().map_or(0, |migration| migration.version())
+    }
+
+    /// Applies the pending migrations to the given database, in order, and returns its schema version.
+    ///
+    /// A migration that was interrupted resumes from its last recorded cursor. If the database has a newer
+    /// schema version than the registry supports, it is refused, as it was written by a newer binary.
+    pub fn run(&self, database: &mut RocksDB) -> Result<u32> {
+        let schema = SchemaDB::open(database);
+
+        // Ensure the schema version of the database is supported.
+        let version = schema.version()?;
+        if version > self.latest_version() {
+            bail!(
+                "The database has schema version {version}, but this version of snarkOS only supports up to {} - please upgrade snarkOS",
+                self.latest_version()
+            )
+        }
+
+        for migration in self.migrations.iter().filter(|migration| migration.version() > version) {
+            let (version, description) = (migration.version(), migration.description());
+            // Resume the migration if it was interrupted, or record its start.
+            let record = match schema.migration(version)? {
+                Some(record) => {
+                    info!("Resuming migration {version} ({description}) from {:?}", record.cursor);
+                    record
+                }
+                None => {
+                    info!("Applying migration {version} ({description})");
+                    schema.start(migration.as_ref())?
+                }
+            };
+
+            // Apply the migration, recording its cursor and status after each chunk.
+            let (timer, mut num_chunks_before) = (Instant::now(), None);
+            let mut record_progress = |progress: MigrationProgress| -> Result<()> {
+                schema.record_cursor(version, progress.cursor)?;
+                // Estimate the remaining time from the rate of the chunks applied in this run, as the progress
+                // is reported after each chunk, and includes the chunks applied before this run.
+                let num_chunks_before = *num_chunks_before.get_or_insert(progress.num_chunks_done.saturating_sub(1));
+                let eta_secs = match progress.num_chunks_done.saturating_sub(num_chunks_before) {
+                    0 => None,
+                    num_done => Some(
+                        timer.elapsed().as_secs() * progress.num_chunks.saturating_sub(progress.num_chunks_done)
+                            / num_done,
+                    ),
+                };
+                info!(
+                    "Migration {version} applied {} of {} chunks ({} of {} items){}",
+                    progress.num_chunks_done,
+                    progress.num_chunks,
+                    progress.num_migrated,
+                    progress.num_total,
+                    eta_secs.map_or(String::new(), |eta_secs| format!(" - about {eta_secs}s remaining"))
+                );
+                *MIGRATION_STATUS.write() = Some(MigrationStatus {
+                    version,
+                    description: description.to_string(),
+                    num_chunks_done: progress.num_chunks_done,
+                    num_chunks: progress.num_chunks,
+                    num_migrated: progress.num_migrated,
+                    num_total: progress.num_total,
+                    eta_secs,
+                    completed: false,
+                });
+                Ok(())
+            };
+            if let Err(error) = migration.apply(database, record.cursor, &mut record_progress) {
+                bail!("Migration {version} ({description}) was interrupted - {error}")
+            }
+
+            schema.complete(version)?;
+            if let Some(status) = MIGRATION_STATUS.write().as_mut().filter(|status| status.version == version) {
+                status.eta_secs = Some(0);
+                status.completed = true;
+            }
+            info!("Applied migration {version} ({description}) in {}s", timer.elapsed().as_secs());
+        }
+
+        Ok(version.max(self.latest_version()))
+    }
+}
+
+/// Returns the current UNIX timestamp, in seconds.
+fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+/// A backfill of an index over the blocks of the canonical chain, which is applied in chunks of blocks.
+///
+/// The entries of each chunk are computed by a pool of workers, and written in a single batch. The chunks of an
+/// ordered backfill are written in order of height, as its index depends on the entries before them, while the
+/// chunks of an unordered backfill are written as soon as they are computed, and marked as completed.
+pub(crate) trait Backfill: Sync {
+    /// The entries of a chunk of blocks.
+    type Entries: Send;
+
+    /// Returns `true` if the index depends on the order of its entries, in which case the chunks are written in order.
+    fn is_ordered(&self) -> bool;
+
+    /// Computes the entries of the blocks in the given range of heights, without writing them.
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries>;
+
+    /// Writes the given entries of a chunk of blocks in a single batch, which must be idempotent.
+    fn write(&self, entries: Self::Entries) -> Result<()>;
+}
+
+/// Applies the given backfill of the migration to the given schema version, to the blocks in the given range of
+/// heights, in chunks of `chunk_size` blocks computed by `num_workers` workers.
+///
+/// The chunks of an unordered backfill that were marked as completed, before the migration was interrupted, are
+/// skipped. The reported cursor is the start of the first chunk that is not written, so that the migration resumes
+/// with the same partition of the heights after it. The reported chunks count the chunks below the start of the
+/// heights, and the skipped chunks, as done, so that the progress covers the whole migration.
+pub(crate) fn run_backfill<B: Backfill>(
+    backfill: &B,
+    schema: &SchemaDB,
+    version: u32,
+    heights: Range<u32>,
+    (chunk_size, num_workers): (u32, usize),
+    progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+) -> Result<()> {
+    // Partition the heights into chunks.
+    let chunks = (heights.start..heights.end)
+        .step_by(chunk_size.max(1) as usize)
+        .map(|start| start..start.saturating_add(chunk_size.max(1)).min(heights.end))
+        .collect::<Vec<_>>();
+    // Determine the chunks to apply, skipping the completed chunks of an unordered backfill.
+    let mut completed = Vec::with_capacity(chunks.len());
+    for chunk in &chunks {
+        completed.push(!backfill.is_ordered() && schema.is_chunk_completed(version, chunk)?);
+    }
+    let pending = (0..chunks.len()).filter(|index| !completed[*index]).collect::<Vec<_>>();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    // Determine the number of chunks below the start of the heights, which were applied before this run.
+    let num_chunks_before = heights.start.div_ceil(chunk_size.max(1)) as usize;
+
+    // Returns the progress of the backfill, with the given chunks completed.
+    let report = |completed: &[bool]| {
+        let cursor =
+            completed.iter().position(|is_completed| !is_completed).map_or(heights.end, |index| chunks[index].start);
+        let num_migrated = chunks
+            .iter()
+            .zip(completed)
+            .filter(|(_, is_completed)| **is_completed)
+            .map(|(chunk, _)| chunk.len() as u64);
+        let num_completed = completed.iter().filter(|is_completed| **is_completed).count();
+        MigrationProgress {
+            cursor: cursor as u64,
+            num_migrated: heights.start as u64 + num_migrated.sum::<u64>(),
+            num_total: heights.end as u64,
+            num_chunks_done: (num_chunks_before + num_completed) as u64,
+            num_chunks: (num_chunks_before + chunks.len()) as u64,
+        }
+    };
+
+    let (next, stop) = (AtomicUsize::new(0), AtomicBool::new(false));
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        // Spawn the workers, which compute the pending chunks in order.
+        for _ in 0..num_workers.clamp(1, pending.len()) {
+            let (sender, chunks, pending, next, stop) = (sender.clone(), &chunks, &pending, &next, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Acquire) {
+                    let index = match pending.get(next.fetch_add(1, Ordering::AcqRel)) {
+                        Some(index) => *index,
+                        None => break,
+                    };
+                    if sender.send((index, backfill.compute(chunks[index].clone()))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Write the chunks as they are computed, in order of height if the backfill is ordered.
+        let write_chunks = || -> Result<()> {
+            let (mut buffered, mut num_chunks_done) = (BTreeMap::new(), 0);
+            for (index, entries) in receiver {
+                buffered.insert(index, entries?);
+                loop {
+                    // Retrieve the next chunk to write, which is the next pending chunk if the backfill is ordered.
+                    let index = match backfill.is_ordered() {
+                        true => pending[num_chunks_done],
+                        false => index,
+                    };
+                    let entries = match buffered.remove(&index) {
+                        Some(entries) => entries,
+                        None => break,
+                    };
+                    backfill.write(entries)?;
+                    if !backfill.is_ordered() {
+                        schema.record_chunk(version, &chunks[index])?;
+                    }
+                    completed[index] = true;
+                    num_chunks_done += 1;
+                    progress(report(&completed))?;
+                    if num_chunks_done == pending.len() {
+                        return Ok(());
+                    }
+                }
+            }
+            bail!("Applied {num_chunks_done} of {} chunks of the backfill", pending.len())
+        };
+        let result = write_chunks();
+        // Stop the workers, in case the backfill was interrupted.
+        stop.store(true, Ordering::Release);
+        result
+    })
+}
+
+/// The migration that journals the blocks committed before the chain journal existed.
+///
+/// If the journal already tracks the chain, which is the case for a database created with the journal,
+/// there is nothing to backfill. The blocks are read by a pool of workers, and journaled in order.
+pub struct JournalBackfill<N: Network> {
+    /// The number of blocks journaled in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> JournalBackfill<N> {
+    /// Initializes the journal backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for JournalBackfill<N> {
+    fn default() -> Self {
+        Self::new(JOURNAL_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the journal backfill.
+struct JournalChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `[transaction ID]`.
+    transactions_map: DataMap<N::BlockHash, Vec<N::TransactionID>>,
+    /// The chain journal.
+    journal: ChainJournal<N>,
+}
+
+impl<N: Network> Backfill for JournalChunks<N> {
+    type Entries = Vec<ChainEvent<N>>;
+
+    /// Returns `true`, as the events are journaled in order of height.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        heights
+            .map(|height| {
+                let hash = match self.id_map.get(&height)? {
+                    Some(hash) => *hash,
+                    None => bail!("Missing the block hash for height {height}"),
+                };
+                let transaction_ids = match self.transactions_map.get(&hash)? {
+                    Some(transaction_ids) => transaction_ids.into_owned(),
+                    None => bail!("Missing the transactions for block {height} ('{hash}')"),
+                };
+                Ok(ChainEvent::Connected { height, hash, transaction_ids })
+            })
+            .collect()
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Journal the chunk of blocks in a single batch.
+        self.journal.start_atomic();
+        match entries.into_iter().try_for_each(|event| self.journal.append(event).map(|_| ())) {
+            Ok(()) => self.journal.finish_atomic(),
+            Err(error) => {
+                self.journal.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for JournalBackfill<N> {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the chain journal with the blocks committed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = JournalChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            transactions_map: database.map(MapID::Block(BlockMap::Transactions)),
+            journal: ChainJournal::<N>::from_map(
+                database.map(MapID::Journal(JournalMap::Event)),
+                database.journal_state().retention(),
+            )?,
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        // Resume after the tip of the journal, as the last chunk may have been journaled without recording its cursor.
+        let height = u32::try_from(cursor.unwrap_or(0))?.max(chunks.journal.next_height()?);
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+/// The migration that indexes the transactions confirmed before the circuit index existed.
+///
+/// The circuits of each transaction are read from the locators of its transitions, which are kept
+/// when its block is pruned. A transaction that is already indexed is skipped, so each chunk is idempotent.
+/// The circuits are read by a pool of workers, and indexed in order, as the transactions of each circuit
+/// are numbered in the order they were confirmed.
+pub struct CircuitIndexBackfill<N: Network> {
+    /// The number of blocks indexed in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> CircuitIndexBackfill<N> {
+    /// Initializes the circuit index backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for CircuitIndexBackfill<N> {
+    fn default() -> Self {
+        Self::new(CIRCUIT_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the circuit index backfill.
+#[allow(clippy::type_complexity)]
+struct CircuitIndexChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `[transaction ID]`.
+    transactions_map: DataMap<N::BlockHash, Vec<N::TransactionID>>,
+    /// The mapping of `transaction ID` to `([transition ID], (optional) fee transition ID)`.
+    execution_id_map: DataMap<N::TransactionID, (Vec<N::TransitionID>, Option<N::TransitionID>)>,
+    /// The mapping of `transaction ID` to `(fee transition ID, global state root, (optional) inclusion proof)`.
+    deployment_fee_map: DataMap<N::TransactionID, (N::TransitionID, N::StateRoot, Option<Proof<N>>)>,
+    /// The mapping of `transition ID` to `(program ID, function name)`.
+    locator_map: DataMap<N::TransitionID, CircuitID<N>>,
+    /// The circuit index.
+    index: CircuitIndex<N>,
+}
+
+impl<N: Network> CircuitIndexChunks<N> {
+    /// Returns the IDs of the circuits used by the given transaction, without duplicates, in order of first use.
+    fn transaction_circuits(&self, transaction_id: &N::TransactionID) -> Result<Vec<CircuitID<N>>> {
+        // Retrieve the transition IDs of the execution and its fee, or of the fee of the deployment.
+        let mut transition_ids = Vec::new();
+        if let Some(execution) = self.execution_id_map.get(transaction_id)? {
+            let (execution_transition_ids, fee_transition_id) = execution.into_owned();
+            transition_ids.extend(execution_transition_ids);
+            transition_ids.extend(fee_transition_id);
+        }
+        if let Some(fee) = self.deployment_fee_map.get(transaction_id)? {
+            transition_ids.push(fee.0);
+        }
+        // Retrieve the circuit of each transition.
+        let mut circuits = Vec::new();
+        for transition_id in &transition_ids {
+            let circuit = match self.locator_map.get(transition_id)? {
+                Some(circuit) => *circuit,
+                None => bail!("Missing the locator of transition '{transition_id}'"),
+            };
+            if !circuits.contains(&circuit) {
+                circuits.push(circuit);
+            }
+        }
+        Ok(circuits)
+    }
+}
+
+impl<N: Network> Backfill for CircuitIndexChunks<N> {
+    type Entries = Vec<(N::TransactionID, Vec<CircuitID<N>>)>;
+
+    /// Returns `true`, as the transactions of each circuit are numbered in order.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        let mut entries = Vec::new();
+        for height in heights {
+            let hash = match self.id_map.get(&height)? {
+                Some(hash) => *hash,
+                None => bail!("Missing the block hash for height {height}"),
+            };
+            let transaction_ids = match self.transactions_map.get(&hash)? {
+                Some(transaction_ids) => transaction_ids.into_owned(),
+                None => bail!("Missing the transactions for block {height} ('{hash}')"),
+            };
+            for transaction_id in transaction_ids {
+                entries.push((transaction_id, self.transaction_circuits(&transaction_id)?));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Index the chunk of blocks in a single batch.
+        self.index.start_atomic();
+        match entries.into_iter().try_for_each(|(transaction_id, circuits)| self.index.insert(transaction_id, circuits))
+        {
+            Ok(()) => self.index.finish_atomic(),
+            Err(error) => {
+                self.index.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for CircuitIndexBackfill<N> {
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the circuit index with the transactions confirmed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = CircuitIndexChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            transactions_map: database.map(MapID::Block(BlockMap::Transactions)),
+            execution_id_map: database.map(MapID::Execution(ExecutionMap::ID)),
+            deployment_fee_map: database.map(MapID::Deployment(DeploymentMap::Fee)),
+            locator_map: database.map(MapID::Transition(TransitionMap::Locator)),
+            index: CircuitIndex::<N>::from_database(database),
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        let height = u32::try_from(cursor.unwrap_or(0))?;
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+/// The migration that indexes the blocks committed before the timestamp index existed.
+///
+/// The timestamps are read from the block headers, which are kept when a block is pruned. The headers are read
+/// by a pool of workers, and indexed in order, as each timestamp is indexed with the latest block at or before it.
+/// A block that is already indexed is skipped, so each chunk is idempotent.
+pub struct TimestampIndexBackfill<N: Network> {
+    /// The number of blocks indexed in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> TimestampIndexBackfill<N> {
+    /// Initializes the timestamp index backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for TimestampIndexBackfill<N> {
+    fn default() -> Self {
+        Self::new(TIMESTAMP_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the timestamp index backfill.
+struct TimestampIndexChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `block header`.
+    header_map: DataMap<N::BlockHash, Header<N>>,
+    /// The timestamp index.
+    index: TimestampIndex<N>,
+}
+
+impl<N: Network> Backfill for TimestampIndexChunks<N> {
+    type Entries = Vec<(u32, i64)>;
+
+    /// Returns `true`, as each timestamp is indexed with the latest block at or before it.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        heights
+            .map(|height| {
+                let hash = match self.id_map.get(&height)? {
+                    Some(hash) => *hash,
+                    None => bail!("Missing the block hash for height {height}"),
+                };
+                match self.header_map.get(&hash)? {
+                    Some(header) => Ok((height, header.timestamp())),
+                    None => bail!("Missing the header for block {height} ('{hash}')"),
+                }
+            })
+            .collect()
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Index the chunk of blocks in a single batch.
+        self.index.start_atomic();
+        match entries.into_iter().try_for_each(|(height, timestamp)| self.index.insert(height, timestamp)) {
+            Ok(()) => self.index.finish_atomic(),
+            Err(error) => {
+                self.index.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for TimestampIndexBackfill<N> {
+    fn version(&self) -> u32 {
+        3
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the timestamp index with the blocks committed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = TimestampIndexChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            header_map: database.map(MapID::Block(BlockMap::Header)),
+            index: TimestampIndex::<N>::from_database(database),
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        let height = u32::try_from(cursor.unwrap_or(0))?;
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+/// The migration that moves the output records into the record map with deduplicated ciphertexts.
+///
+/// Each chunk moves the first output records of the legacy record map, and removes them from it, so the
+/// legacy map holds exactly the records that remain to be moved. A chunk that is moved again, if the
+/// migration is interrupted before the legacy records are removed, replaces the same references, so each
+/// chunk is idempotent. The number of bytes saved by the deduplication is reported once the records are moved.
+pub struct RecordDeduplication<N: Network> {
+    /// The number of output records moved in each chunk.
+    chunk_size: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> RecordDeduplication<N> {
+    /// Initializes the record deduplication, with the given number of output records in each chunk.
+    pub fn new(chunk_size: usize) -> Self {
+        Self { chunk_size: chunk_size.max(1), _phantom: PhantomData }
+    }
+}
+
+impl<N: Network> Default for RecordDeduplication<N> {
+    fn default() -> Self {
+        Self::new(RECORD_DEDUPLICATION_CHUNK_SIZE)
+    }
+}
+
+impl<N: Network> Migration for RecordDeduplication<N> {
+    fn version(&self) -> u32 {
+        4
+    }
+
+    fn description(&self) -> &'static str {
+        "Deduplicate the record ciphertexts of the transition outputs"
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let legacy_map: DataMap<Field<N>, (Field<N>, Option<Record<N, Ciphertext<N>>>)> =
+            database.map(MapID::TransitionOutput(TransitionOutputMap::Record));
+        let record_map = RecordCiphertextMap::<N>::from_database(database);
+
+        // Determine the number of output records that remain to be moved.
+        let num_remaining = legacy_map.keys().count() as u64;
+        let mut num_migrated = cursor.unwrap_or(0);
+        let num_total = num_migrated + num_remaining;
+        // Count the chunks moved before this run as done, so that the progress covers the whole migration.
+        let num_chunks_before = num_migrated.div_ceil(self.chunk_size as u64);
+        let num_chunks = num_chunks_before + num_remaining.div_ceil(self.chunk_size as u64);
+
+        for num_chunks_done in num_chunks_before + 1..=num_chunks {
+            // Retrieve the first output records of the legacy map.
+            let records = legacy_map
+                .iter()
+                .take(self.chunk_size)
+                .map(|(commitment, record)| (*commitment, record.into_owned()))
+                .collect::<Vec<_>>();
+            if records.is_empty() {
+                break;
+            }
+
+            // Move the output records into the deduplicated map, and remove them from the legacy map.
+            record_map.start_atomic();
+            legacy_map.start_atomic();
+            let moved = records.iter().try_for_each(|(commitment, record)| {
+                record_map.insert(*commitment, record.clone())?;
+                legacy_map.remove(commitment)
+            });
+            match moved {
+                // Write the deduplicated map first, so that no output record is lost if the migration is interrupted.
+                Ok(()) => {
+                    record_map.finish_atomic()?;
+                    legacy_map.finish_atomic()?;
+                }
+                Err(error) => {
+                    record_map.abort_atomic();
+                    legacy_map.abort_atomic();
+                    return Err(error);
+                }
+            }
+
+            num_migrated += records.len() as u64;
+            progress(MigrationProgress { cursor: num_migrated, num_migrated, num_total, num_chunks_done, num_chunks })?;
+        }
+
+        info!(
+            "Deduplicated {num_total} output records into {} record ciphertexts, saving {} bytes",
+            record_map.num_ciphertexts(),
+            record_map.num_bytes_saved()?
+        );
+        Ok(())
+    }
+}
+
+/// The migration that indexes the serial numbers and commitments of the blocks committed before the history index existed.
+///
+/// The serial numbers are the IDs of the record inputs, and the commitments are the IDs of the record outputs, of the
+/// transitions of each transaction. The transitions are read by a pool of workers, and indexed in order, as each block
+/// must follow the latest indexed block. A block that is already indexed is skipped, so each chunk is idempotent.
+pub struct HistoryIndexBackfill<N: Network> {
+    /// The number of blocks indexed in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> HistoryIndexBackfill<N> {
+    /// Initializes the history index backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for HistoryIndexBackfill<N> {
+    fn default() -> Self {
+        Self::new(HISTORY_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the history index backfill.
+#[allow(clippy::type_complexity)]
+struct HistoryIndexChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `[transaction ID]`.
+    transactions_map: DataMap<N::BlockHash, Vec<N::TransactionID>>,
+    /// The mapping of `transaction ID` to `([transition ID], (optional) fee transition ID)`.
+    execution_id_map: DataMap<N::TransactionID, (Vec<N::TransitionID>, Option<N::TransitionID>)>,
+    /// The mapping of `transaction ID` to `(fee transition ID, global state root, (optional) inclusion proof)`.
+    deployment_fee_map: DataMap<N::TransactionID, (N::TransitionID, N::StateRoot, Option<Proof<N>>)>,
+    /// The mapping of `transition ID` to `[input ID]`.
+    input_id_map: DataMap<N::TransitionID, Vec<Field<N>>>,
+    /// The mapping of `serial number` to `tag`.
+    input_record_map: DataMap<Field<N>, Field<N>>,
+    /// The mapping of `transition ID` to `[output ID]`.
+    output_id_map: DataMap<N::TransitionID, Vec<Field<N>>>,
+    /// The mapping of `commitment` to `record`.
+    output_record_map: RecordCiphertextMap<N>,
+    /// The history index.
+    index: HistoryIndex<N>,
+}
+
+impl<N: Network> HistoryIndexChunks<N> {
+    /// Returns the serial numbers and commitments of the given transaction, in order of its transitions.
+    fn transaction_records(&self, transaction_id: &N::TransactionID) -> Result<TransactionRecords<N>> {
+        // Retrieve the transition IDs of the execution and its fee, or of the fee of the deployment.
+        let mut transition_ids = Vec::new();
+        if let Some(execution) = self.execution_id_map.get(transaction_id)? {
+            let (execution_transition_ids, fee_transition_id) = execution.into_owned();
+            transition_ids.extend(execution_transition_ids);
+            transition_ids.extend(fee_transition_id);
+        }
+        if let Some(fee) = self.deployment_fee_map.get(transaction_id)? {
+            transition_ids.push(fee.0);
+        }
+        // Retrieve the record inputs and outputs of each transition.
+        let (mut serial_numbers, mut commitments) = (Vec::new(), Vec::new());
+        for transition_id in &transition_ids {
+            let input_ids = match self.input_id_map.get(transition_id)? {
+                Some(input_ids) => input_ids.into_owned(),
+                None => bail!("Missing the inputs of transition '{transition_id}'"),
+            };
+            for input_id in input_ids {
+                if self.input_record_map.contains_key(&input_id)? {
+                    serial_numbers.push(input_id);
+                }
+            }
+            let output_ids = match self.output_id_map.get(transition_id)? {
+                Some(output_ids) => output_ids.into_owned(),
+                None => bail!("Missing the outputs of transition '{transition_id}'"),
+            };
+            for output_id in output_ids {
+                if self.output_record_map.contains_key(&output_id)? {
+                    commitments.push(output_id);
+                }
+            }
+        }
+        Ok((*transaction_id, serial_numbers, commitments))
+    }
+}
+
+impl<N: Network> Backfill for HistoryIndexChunks<N> {
+    type Entries = Vec<(u32, Vec<TransactionRecords<N>>)>;
+
+    /// Returns `true`, as each block must follow the latest indexed block.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        heights
+            .map(|height| {
+                let hash = match self.id_map.get(&height)? {
+                    Some(hash) => *hash,
+                    None => bail!("Missing the block hash for height {height}"),
+                };
+                let transaction_ids = match self.transactions_map.get(&hash)? {
+                    Some(transaction_ids) => transaction_ids.into_owned(),
+                    None => bail!("Missing the transactions for block {height} ('{hash}')"),
+                };
+                let transactions = transaction_ids
+                    .iter()
+                    .map(|transaction_id| self.transaction_records(transaction_id))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((height, transactions))
+            })
+            .collect()
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Index the chunk of blocks in a single batch.
+        self.index.start_atomic();
+        match entries.iter().try_for_each(|(height, transactions)| self.index.insert(*height, transactions)) {
+            Ok(()) => self.index.finish_atomic(),
+            Err(error) => {
+                self.index.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for HistoryIndexBackfill<N> {
+    fn version(&self) -> u32 {
+        5
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the history index with the serial numbers and commitments of the blocks committed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = HistoryIndexChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            transactions_map: database.map(MapID::Block(BlockMap::Transactions)),
+            execution_id_map: database.map(MapID::Execution(ExecutionMap::ID)),
+            deployment_fee_map: database.map(MapID::Deployment(DeploymentMap::Fee)),
+            input_id_map: database.map(MapID::TransitionInput(TransitionInputMap::ID)),
+            input_record_map: database.map(MapID::TransitionInput(TransitionInputMap::Record)),
+            output_id_map: database.map(MapID::TransitionOutput(TransitionOutputMap::ID)),
+            output_record_map: RecordCiphertextMap::<N>::from_database(database),
+            index: HistoryIndex::<N>::from_database(database),
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        let height = u32::try_from(cursor.unwrap_or(0))?;
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+/// The migration that indexes the difficulty of the blocks committed before the difficulty index existed.
+///
+/// The difficulties are read from the block headers, which are kept when a block is pruned. The headers are read
+/// by a pool of workers, and indexed in order, as the hashrate of each block is estimated from the blocks before it.
+/// A block that is already indexed is skipped, so each chunk is idempotent.
+pub struct DifficultyIndexBackfill<N: Network> {
+    /// The number of blocks indexed in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> DifficultyIndexBackfill<N> {
+    /// Initializes the difficulty index backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for DifficultyIndexBackfill<N> {
+    fn default() -> Self {
+        Self::new(DIFFICULTY_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the difficulty index backfill.
+struct DifficultyIndexChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `block header`.
+    header_map: DataMap<N::BlockHash, Header<N>>,
+    /// The difficulty index.
+    index: DifficultyIndex<N>,
+}
+
+impl<N: Network> Backfill for DifficultyIndexChunks<N> {
+    type Entries = Vec<Header<N>>;
+
+    /// Returns `true`, as the hashrate of each block is estimated from the blocks before it.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        heights
+            .map(|height| {
+                let hash = match self.id_map.get(&height)? {
+                    Some(hash) => *hash,
+                    None => bail!("Missing the block hash for height {height}"),
+                };
+                match self.header_map.get(&hash)? {
+                    Some(header) => Ok(*header),
+                    None => bail!("Missing the header for block {height} ('{hash}')"),
+                }
+            })
+            .collect()
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Index the chunk of blocks in a single batch.
+        self.index.start_atomic();
+        match entries.iter().try_for_each(|header| self.index.insert(header)) {
+            Ok(()) => self.index.finish_atomic(),
+            Err(error) => {
+                self.index.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for DifficultyIndexBackfill<N> {
+    fn version(&self) -> u32 {
+        6
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the difficulty index with the blocks committed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = DifficultyIndexChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            header_map: database.map(MapID::Block(BlockMap::Header)),
+            index: DifficultyIndex::<N>::from_database(database),
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        let height = u32::try_from(cursor.unwrap_or(0))?;
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+/// The migration that appends the blocks committed before the chain commitment existed to its Merkle mountain range.
+///
+/// The block hashes are read by a pool of workers, and appended in order, as each leaf is merged with the peaks
+/// before it. A block that is already appended is skipped, so each chunk is idempotent.
+pub struct ChainMmrBackfill<N: Network> {
+    /// The number of blocks appended in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> ChainMmrBackfill<N> {
+    /// Initializes the chain commitment backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for ChainMmrBackfill<N> {
+    fn default() -> Self {
+        Self::new(CHAIN_MMR_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the chain commitment backfill.
+struct ChainMmrChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The Merkle mountain range over the block hashes.
+    mmr: ChainMmr<N>,
+}
+
+impl<N: Network> Backfill for ChainMmrChunks<N> {
+    type Entries = Vec<(u32, N::BlockHash)>;
+
+    /// Returns `true`, as each leaf is merged with the peaks before it.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        heights
+            .map(|height| match self.id_map.get(&height)? {
+                Some(hash) => Ok((height, *hash)),
+                None => bail!("Missing the block hash for height {height}"),
+            })
+            .collect()
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Append the chunk of blocks in a single batch.
+        self.mmr.start_atomic();
+        match entries.iter().try_for_each(|(height, hash)| self.mmr.insert(*height, hash)) {
+            Ok(()) => self.mmr.finish_atomic(),
+            Err(error) => {
+                self.mmr.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for ChainMmrBackfill<N> {
+    fn version(&self) -> u32 {
+        7
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the chain commitment with the blocks committed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = ChainMmrChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            mmr: ChainMmr::<N>::from_database(database),
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        let height = u32::try_from(cursor.unwrap_or(0))?;
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+/// The migration that numbers the transactions confirmed before the sequence index existed.
+///
+/// The transaction IDs of each block are read by a pool of workers, and numbered in order, as the sequence numbers
+/// of each block follow the transactions before it. A block that is already numbered is skipped,
+/// so each chunk is idempotent.
+pub struct SequenceIndexBackfill<N: Network> {
+    /// The number of blocks numbered in each chunk.
+    chunk_size: u32,
+    /// The number of workers reading the chunks.
+    num_workers: usize,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> SequenceIndexBackfill<N> {
+    /// Initializes the sequence index backfill, with the given number of blocks in each chunk.
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), num_workers: default_num_workers(), _phantom: PhantomData }
+    }
+
+    /// Sets the number of workers reading the chunks.
+    pub fn with_num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = num_workers.max(1);
+        self
+    }
+}
+
+impl<N: Network> Default for SequenceIndexBackfill<N> {
+    fn default() -> Self {
+        Self::new(SEQUENCE_BACKFILL_CHUNK_SIZE)
+    }
+}
+
+/// The chunks of the sequence index backfill.
+struct SequenceIndexChunks<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `[transaction ID]`.
+    transactions_map: DataMap<N::BlockHash, Vec<N::TransactionID>>,
+    /// The sequence index.
+    index: SequenceIndex<N>,
+}
+
+impl<N: Network> Backfill for SequenceIndexChunks<N> {
+    type Entries = Vec<(u32, Vec<N::TransactionID>)>;
+
+    /// Returns `true`, as the sequence numbers of each block follow the transactions before it.
+    fn is_ordered(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+        heights
+            .map(|height| {
+                let hash = match self.id_map.get(&height)? {
+                    Some(hash) => *hash,
+                    None => bail!("Missing the block hash for height {height}"),
+                };
+                match self.transactions_map.get(&hash)? {
+                    Some(transaction_ids) => Ok((height, transaction_ids.into_owned())),
+                    None => bail!("Missing the transactions for block {height} ('{hash}')"),
+                }
+            })
+            .collect()
+    }
+
+    fn write(&self, entries: Self::Entries) -> Result<()> {
+        // Number the chunk of blocks in a single batch.
+        self.index.start_atomic();
+        match entries.iter().try_for_each(|(height, transaction_ids)| self.index.insert(*height, transaction_ids)) {
+            Ok(()) => self.index.finish_atomic(),
+            Err(error) => {
+                self.index.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<N: Network> Migration for SequenceIndexBackfill<N> {
+    fn version(&self) -> u32 {
+        8
+    }
+
+    fn description(&self) -> &'static str {
+        "Backfill the sequence index with the transactions confirmed before it"
+    }
+
+    fn apply(
+        &self,
+        database: &mut RocksDB,
+        cursor: Option<u64>,
+        progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+    ) -> Result<()> {
+        let chunks = SequenceIndexChunks::<N> {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            transactions_map: database.map(MapID::Block(BlockMap::Transactions)),
+            index: SequenceIndex::<N>::from_database(database),
+        };
+
+        // Determine the number of blocks in the canonical chain.
+        let num_blocks = chunks.id_map.keys().max().map_or(0, |height| *height + 1);
+        // Resume after the latest numbered block, as the last chunk may have been written without recording its cursor.
+        let height = u32::try_from(cursor.unwrap_or(0))?.max(chunks.index.num_blocks()?);
+
+        let schema = SchemaDB::open(database);
+        run_backfill(
+            &chunks,
+            &schema,
+            self.version(),
+            height..num_blocks,
+            (self.chunk_size, self.num_workers),
+            progress,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rocksdb::tests::temp_dir, TestMap};
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+    use std::{str::FromStr, sync::Arc};
+
+    type CurrentNetwork = Testnet3;
+
+    /// The number of blocks in the fixture database.
+    const NUM_BLOCKS: u32 = 10;
+
+    /// A migration that is interrupted after the given number of chunks.
+    struct Interrupted<M: Migration> {
+        migration: M,
+        num_chunks: usize,
+    }
+
+    impl<M: Migration> Migration for Interrupted<M> {
+        fn version(&self) -> u32 {
+            self.migration.version()
+        }
+
+        fn description(&self) -> &'static str {
+            self.migration.description()
+        }
+
+        fn apply(
+            &self,
+            database: &mut RocksDB,
+            cursor: Option<u64>,
+            progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+        ) -> Result<()> {
+            let mut num_chunks = 0;
+            self.migration.apply(database, cursor, &mut |chunk_progress| {
+                ensure!(num_chunks < self.num_chunks, "Interrupted after {num_chunks} chunks");
+                num_chunks += 1;
+                progress(chunk_progress)
+            })
+        }
+    }
+
+    /// Returns a fixture database, with a chain of blocks committed before the chain journal existed.
+    fn sample_fixture_database(rng: &mut TestRng) -> (RocksDB, Vec<<CurrentNetwork as Network>::BlockHash>) {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let id_map: DataMap<u32, <CurrentNetwork as Network>::BlockHash> = database.map(MapID::Block(BlockMap::ID));
+        let transactions_map: DataMap<
+            <CurrentNetwork as Network>::BlockHash,
+            Vec<<CurrentNetwork as Network>::TransactionID>,
+        > = database.map(MapID::Block(BlockMap::Transactions));
+
+        let hashes = (0..NUM_BLOCKS).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect::<Vec<_>>();
+        for (height, hash) in hashes.iter().enumerate() {
+            id_map.insert(height as u32, *hash).unwrap();
+            transactions_map.insert(*hash, vec![Field::<CurrentNetwork>::rand(rng).into()]).unwrap();
+        }
+        (database, hashes)
+    }
+
+    /// Inserts an execution of a transfer, with a fee, for the transaction of each block of the fixture database,
+    /// and returns the circuits of the transfer and the fee, along with the IDs of the transactions.
+    #[allow(clippy::type_complexity)]
+    fn sample_circuit_fixture(
+        database: &RocksDB,
+        hashes: &[<CurrentNetwork as Network>::BlockHash],
+        rng: &mut TestRng,
+    ) -> (CircuitID<CurrentNetwork>, CircuitID<CurrentNetwork>, Vec<<CurrentNetwork as Network>::TransactionID>) {
+        let transactions_map: DataMap<
+            <CurrentNetwork as Network>::BlockHash,
+            Vec<<CurrentNetwork as Network>::TransactionID>,
+        > = database.map(MapID::Block(BlockMap::Transactions));
+        let execution_id_map: DataMap<
+            <CurrentNetwork as Network>::TransactionID,
+            (Vec<<CurrentNetwork as Network>::TransitionID>, Option<<CurrentNetwork as Network>::TransitionID>),
+        > = database.map(MapID::Execution(ExecutionMap::ID));
+        let locator_map: DataMap<<CurrentNetwork as Network>::TransitionID, CircuitID<CurrentNetwork>> =
+            database.map(MapID::Transition(TransitionMap::Locator));
+
+        // Execute a transfer, with a fee, in each block.
+        let transfer = (ProgramID::from_str("credits.aleo").unwrap(), Identifier::from_str("transfer").unwrap());
+        let fee = (ProgramID::from_str("credits.aleo").unwrap(), Identifier::from_str("fee").unwrap());
+        let mut transaction_ids = Vec::new();
+        for hash in hashes {
+            let transaction_id = transactions_map.get(hash).unwrap().unwrap()[0];
+            let (transfer_id, fee_id) =
+                (Field::<CurrentNetwork>::rand(rng).into(), Field::<CurrentNetwork>::rand(rng).into());
+            execution_id_map.insert(transaction_id, (vec![transfer_id], Some(fee_id))).unwrap();
+            locator_map.insert(transfer_id, transfer).unwrap();
+            locator_map.insert(fee_id, fee).unwrap();
+            transaction_ids.push(transaction_id);
+        }
+        (transfer, fee, transaction_ids)
+    }
+
+    /// Returns the entries of the circuit index of the given database, in order.
+    #[allow(clippy::type_complexity)]
+    fn circuit_index_entries(
+        database: &RocksDB,
+    ) -> Vec<(CircuitID<CurrentNetwork>, Vec<<CurrentNetwork as Network>::TransactionID>)> {
+        let index = CircuitIndex::<CurrentNetwork>::from_database(database);
+        let mut circuits = index.circuits();
+        circuits.sort_unstable_by_key(|(circuit, _)| format!("{}/{}", circuit.0, circuit.1));
+        circuits
+            .into_iter()
+            .map(|(circuit, _)| (circuit, index.transactions(&circuit, 0, usize::MAX).unwrap()))
+            .collect()
+    }
+
+    /// A migration that indexes the height of each block by its hash, which does not depend on the order of the blocks.
+    struct HeightIndexBackfill {
+        /// The number of blocks indexed in each chunk.
+        chunk_size: u32,
+        /// The number of workers reading the chunks.
+        num_workers: usize,
+        /// The number of chunks written.
+        num_written: Arc<AtomicUsize>,
+    }
+
+    /// The chunks of the height index backfill.
+    struct HeightIndexChunks<'a> {
+        id_map: DataMap<u32, <CurrentNetwork as Network>::BlockHash>,
+        height_map: DataMap<<CurrentNetwork as Network>::BlockHash, u32>,
+        num_written: &'a AtomicUsize,
+    }
+
+    impl Backfill for HeightIndexChunks<'_> {
+        type Entries = Vec<(<CurrentNetwork as Network>::BlockHash, u32)>;
+
+        fn is_ordered(&self) -> bool {
+            false
+        }
+
+        fn compute(&self, heights: Range<u32>) -> Result<Self::Entries> {
+            heights.map(|height| Ok((*self.id_map.get(&height)?.unwrap(), height))).collect()
+        }
+
+        fn write(&self, entries: Self::Entries) -> Result<()> {
+            self.height_map.start_atomic();
+            entries.into_iter().try_for_each(|(hash, height)| self.height_map.insert(hash, height))?;
+            self.height_map.finish_atomic()?;
+            self.num_written.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    impl Migration for HeightIndexBackfill {
+        fn version(&self) -> u32 {
+            1
+        }
+
+        fn description(&self) -> &'static str {
+            "Index the height of each block by its hash"
+        }
+
+        fn apply(
+            &self,
+            database: &mut RocksDB,
+            cursor: Option<u64>,
+            progress: &mut dyn FnMut(MigrationProgress) -> Result<()>,
+        ) -> Result<()> {
+            let chunks = HeightIndexChunks {
+                id_map: database.map(MapID::Block(BlockMap::ID)),
+                height_map: database.map(MapID::Test(TestMap::Test)),
+                num_written: &self.num_written,
+            };
+            let height = u32::try_from(cursor.unwrap_or(0))?;
+            let schema = SchemaDB::open(database);
+            run_backfill(&chunks, &schema, 1, height..NUM_BLOCKS, (self.chunk_size, self.num_workers), progress)
+        }
+    }
+
+    /// Returns the entries of the height index of the given database, in order of height.
+    fn height_index_entries(database: &RocksDB) -> Vec<(<CurrentNetwork as Network>::BlockHash, u32)> {
+        let height_map: DataMap<<CurrentNetwork as Network>::BlockHash, u32> = database.map(MapID::Test(TestMap::Test));
+        let mut entries = height_map.iter().map(|(hash, height)| (*hash, *height)).collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(_, height)| *height);
+        entries
+    }
+
+    /// Returns the events of the chain journal of the given database.
+    fn journal_events(database: &RocksDB) -> Vec<(u64, ChainEvent<CurrentNetwork>)> {
+        let journal = ChainJournal::<CurrentNetwork>::from_map(
+            database.map(MapID::Journal(JournalMap::Event)),
+            database.journal_state().retention(),
+        )
+        .unwrap();
+        journal.events(0, usize::MAX).unwrap()
+    }
+
+    #[test]
+    #[serial]
+    fn test_journal_backfill() {
+        let rng = &mut TestRng::default();
+        let (mut database, hashes) = sample_fixture_database(rng);
+
+        // Apply the migration on its own, and record its progress.
+        let mut reports = Vec::new();
+        JournalBackfill::<CurrentNetwork>::new(4)
+            .apply(&mut database, None, &mut |progress| {
+                reports.push(progress);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(reports.iter().map(|progress| progress.cursor).collect::<Vec<_>>(), vec![4, 8, 10]);
+        assert!(reports.iter().all(|progress| progress.num_total == NUM_BLOCKS as u64));
+
+        // Ensure the journal contains the chain, in order.
+        let events = journal_events(&database);
+        assert_eq!(events.len(), NUM_BLOCKS as usize);
+        for (height, (_, event)) in events.iter().enumerate() {
+            assert!(
+                matches!(event, ChainEvent::Connected { height: h, hash, .. } if *h == height as u32 && *hash == hashes[height])
+            );
+        }
+
+        // Ensure applying the migration again is a no-op, as the journal already tracks the chain.
+        JournalBackfill::<CurrentNetwork>::new(4).apply(&mut database, None, &mut |_| Ok(())).unwrap();
+        assert_eq!(journal_events(&database), events);
+    }
+
+    #[test]
+    #[serial]
+    fn test_journal_backfill_resumes_after_interrupt() {
+        let rng = &mut TestRng::default();
+        let (mut database, hashes) = sample_fixture_database(rng);
+
+        // Interrupt the migration after two chunks, while the third chunk is journaled without recording its cursor.
+        let interrupted = Interrupted { migration: JournalBackfill::<CurrentNetwork>::new(3), num_chunks: 2 };
+        let registry = MigrationRegistry::default().register(interrupted).unwrap();
+        assert!(registry.run(&mut database).is_err());
+
+        // Ensure the migration is recorded as started, with the cursor of the last recorded chunk.
+        let schema = SchemaDB::open(&database);
+        assert_eq!(schema.version().unwrap(), 0);
+        let record = schema.migration(1).unwrap().unwrap();
+        assert_eq!(record.cursor, Some(6));
+        assert_eq!(record.completed_at, None);
+        assert_eq!(journal_events(&database).len(), 9);
+
+        // Resume the migration.
+        let registry = MigrationRegistry::default().register(JournalBackfill::<CurrentNetwork>::new(3)).unwrap();
+        assert_eq!(registry.run(&mut database).unwrap(), 1);
+
+        // Ensure every block is journaled exactly once, in order.
+        let events = journal_events(&database);
+        assert_eq!(events.len(), NUM_BLOCKS as usize);
+        for (height, (_, event)) in events.iter().enumerate() {
+            assert!(
+                matches!(event, ChainEvent::Connected { height: h, hash, .. } if *h == height as u32 && *hash == hashes[height])
+            );
+        }
+
+        // Ensure the migration is recorded as completed, with its original start.
+        assert_eq!(schema.version().unwrap(), 1);
+        let (version, completed) = schema.migrations().unwrap().pop().unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(completed.started_at, record.started_at);
+        assert!(completed.completed_at.is_some());
+    }
+
+    #[test]
+    #[serial]
+    fn test_circuit_index_backfill() {
+        let rng = &mut TestRng::default();
+        let (mut database, hashes) = sample_fixture_database(rng);
+        let (transfer, fee, transaction_ids) = sample_circuit_fixture(&database, &hashes, rng);
+
+        // Interrupt the migration after the first chunk, and resume it from the start of the chunk.
+        let interrupted = Interrupted { migration: CircuitIndexBackfill::<CurrentNetwork>::new(4), num_chunks: 0 };
+        assert!(interrupted.apply(&mut database, None, &mut |_| Ok(())).is_err());
+        CircuitIndexBackfill::<CurrentNetwork>::new(4).apply(&mut database, None, &mut |_| Ok(())).unwrap();
+
+        // Ensure every transaction is indexed exactly once, in order.
+        let index = CircuitIndex::<CurrentNetwork>::from_database(&database);
+        assert_eq!(index.num_transactions(&transfer).unwrap(), NUM_BLOCKS as u64);
+        assert_eq!(index.transactions(&fee, 0, usize::MAX).unwrap(), transaction_ids);
+        assert_eq!(index.transaction_circuits(&transaction_ids[0]).unwrap(), Some(vec![transfer, fee]));
+    }
+
+    #[test]
+    #[serial]
+    fn test_parallel_circuit_index_backfill_matches_reference() {
+        const SEED: u64 = 1234;
+
+        // Index a fixture database with a single worker, for reference.
+        let rng = &mut TestRng::fixed(SEED);
+        let (mut reference, hashes) = sample_fixture_database(rng);
+        let (_, fee, transaction_ids) = sample_circuit_fixture(&reference, &hashes, rng);
+        CircuitIndexBackfill::<CurrentNetwork>::new(3)
+            .with_num_workers(1)
+            .apply(&mut reference, None, &mut |_| Ok(()))
+            .unwrap();
+
+        // Index the same fixture database with a pool of workers, interrupting it after the second chunk.
+        let rng = &mut TestRng::fixed(SEED);
+        let (mut database, hashes) = sample_fixture_database(rng);
+        sample_circuit_fixture(&database, &hashes, rng);
+        let interrupted = Interrupted {
+            migration: CircuitIndexBackfill::<CurrentNetwork>::new(3).with_num_workers(4),
+            num_chunks: 1,
+        };
+        let registry = MigrationRegistry::default().register(JournalBackfill::<CurrentNetwork>::new(3)).unwrap();
+        let registry = registry.register(interrupted).unwrap();
+        assert!(registry.run(&mut database).is_err());
+
+        // Ensure the chunks were indexed in order, even though they may have been read out of order.
+        let schema = SchemaDB::open(&database);
+        assert_eq!(schema.version().unwrap(), 1);
+        assert_eq!(schema.migration(2).unwrap().unwrap().cursor, Some(3));
+        let index = CircuitIndex::<CurrentNetwork>::from_database(&database);
+        assert_eq!(index.transactions(&fee, 0, usize::MAX).unwrap(), transaction_ids[..6]);
+
+        // Resume the migration, and ensure the index matches the reference.
+        let registry = MigrationRegistry::default().register(JournalBackfill::<CurrentNetwork>::new(3)).unwrap();
+        let registry = registry.register(CircuitIndexBackfill::<CurrentNetwork>::new(3).with_num_workers(4)).unwrap();
+        assert_eq!(registry.run(&mut database).unwrap(), 2);
+        assert_eq!(circuit_index_entries(&database), circuit_index_entries(&reference));
+        for transaction_id in &transaction_ids {
+            assert_eq!(
+                index.transaction_circuits(transaction_id).unwrap(),
+                CircuitIndex::<CurrentNetwork>::from_database(&reference).transaction_circuits(transaction_id).unwrap()
+            );
+        }
+
+        // Ensure the progress of the migration was exposed, with the totals of the chunks of the whole migration,
+        // which count the chunk applied before the interrupt.
+        let status = migration_status().unwrap();
+        assert_eq!((status.version, status.num_chunks_done, status.num_chunks), (2, 4, 4));
+        assert_eq!((status.num_migrated, status.num_total), (NUM_BLOCKS as u64, NUM_BLOCKS as u64));
+        assert!(status.completed);
+    }
+
+    #[test]
+    #[serial]
+    fn test_unordered_backfill_skips_completed_chunks() {
+        const SEED: u64 = 5678;
+
+        // Index a fixture database with a single worker, for reference.
+        let (mut reference, hashes) = sample_fixture_database(&mut TestRng::fixed(SEED));
+        let backfill = HeightIndexBackfill { chunk_size: 2, num_workers: 1, num_written: Default::default() };
+        MigrationRegistry::default().register(backfill).unwrap().run(&mut reference).unwrap();
+        let expected = hashes.iter().enumerate().map(|(height, hash)| (*hash, height as u32)).collect::<Vec<_>>();
+        assert_eq!(height_index_entries(&reference), expected);
+
+        // Index the same fixture database with a pool of workers, interrupting it after the second chunk.
+        let (mut database, _) = sample_fixture_database(&mut TestRng::fixed(SEED));
+        let num_written = Arc::new(AtomicUsize::new(0));
+        let backfill = HeightIndexBackfill { chunk_size: 2, num_workers: 4, num_written: num_written.clone() };
+        let registry =
+            MigrationRegistry::default().register(Interrupted { migration: backfill, num_chunks: 1 }).unwrap();
+        assert!(registry.run(&mut database).is_err());
+
+        // Ensure the written chunks are marked as completed, even if they were written out of order.
+        let schema = SchemaDB::open(&database);
+        assert_eq!(num_written.load(Ordering::SeqCst), 2);
+        assert_eq!(schema.num_completed_chunks(1), 2);
+        assert_eq!(height_index_entries(&database).len(), 4);
+
+        // Resume the migration, and ensure only the remaining chunks are written.
+        let backfill = HeightIndexBackfill { chunk_size: 2, num_workers: 4, num_written: num_written.clone() };
+        let registry = MigrationRegistry::default().register(backfill).unwrap();
+        assert_eq!(registry.run(&mut database).unwrap(), 1);
+        assert_eq!(num_written.load(Ordering::SeqCst), 5);
+        assert_eq!(height_index_entries(&database), height_index_entries(&reference));
+        // Ensure the progress counts the skipped chunks as done, out of the chunks of the whole migration.
+        let status = migration_status().unwrap();
+        assert_eq!((status.version, status.num_chunks_done, status.num_chunks), (1, 5, 5));
+
+        // Ensure the chunk markers are removed once the migration completes.
+        assert_eq!(schema.num_completed_chunks(1), 0);
+        assert!(schema.migration(1).unwrap().unwrap().completed_at.is_some());
+    }
+
+    #[test]
+    #[serial]
+    #[allow(clippy::type_complexity)]
+    fn test_history_index_backfill() {
+        let rng = &mut TestRng::default();
+        let (mut database, hashes) = sample_fixture_database(rng);
+        let (_, _, transaction_ids) = sample_circuit_fixture(&database, &hashes, rng);
+        let execution_id_map: DataMap<
+            <CurrentNetwork as Network>::TransactionID,
+            (Vec<<CurrentNetwork as Network>::TransitionID>, Option<<CurrentNetwork as Network>::TransitionID>),
+        > = database.map(MapID::Execution(ExecutionMap::ID));
+        let input_id_map: DataMap<<CurrentNetwork as Network>::TransitionID, Vec<Field<CurrentNetwork>>> =
+            database.map(MapID::TransitionInput(TransitionInputMap::ID));
+        let input_record_map: DataMap<Field<CurrentNetwork>, Field<CurrentNetwork>> =
+            database.map(MapID::TransitionInput(TransitionInputMap::Record));
+        let output_id_map: DataMap<<CurrentNetwork as Network>::TransitionID, Vec<Field<CurrentNetwork>>> =
+            database.map(MapID::TransitionOutput(TransitionOutputMap::ID));
+        let output_record_map = RecordCiphertextMap::<CurrentNetwork>::from_database(&database);
+
+        // Spend a record and create a record in the transfer of each block, along with a public input,
+        // while the fee has no records.
+        let mut records = Vec::new();
+        for transaction_id in &transaction_ids {
+            let (transition_ids, fee_id) = execution_id_map.get(transaction_id).unwrap().unwrap().into_owned();
+            let (serial_number, commitment) = (Field::rand(rng), Field::rand(rng));
+            input_id_map.insert(transition_ids[0], vec![serial_number, Field::rand(rng)]).unwrap();
+            input_record_map.insert(serial_number, Field::rand(rng)).unwrap();
+            output_id_map.insert(transition_ids[0], vec![commitment]).unwrap();
+            output_record_map.insert(commitment, (Field::rand(rng), None)).unwrap();
+            input_id_map.insert(fee_id.unwrap(), vec![]).unwrap();
+            output_id_map.insert(fee_id.unwrap(), vec![]).unwrap();
+            records.push((serial_number, commitment));
+        }
+
+        // Interrupt the migration after the first chunk, and resume it from the start of the chunk.
+        let interrupted = Interrupted { migration: HistoryIndexBackfill::<CurrentNetwork>::new(4), num_chunks: 0 };
+        assert!(interrupted.apply(&mut database, None, &mut |_| Ok(())).is_err());
+        HistoryIndexBackfill::<CurrentNetwork>::new(4).apply(&mut database, None, &mut |_| Ok(())).unwrap();
+
+        // Ensure every record is indexed at the height of its block, and only the records are indexed.
+        let index = HistoryIndex::<CurrentNetwork>::from_database(&database);
+        assert_eq!(index.num_blocks().unwrap(), NUM_BLOCKS);
+        for (height, ((serial_number, commitment), transaction_id)) in records.iter().zip(&transaction_ids).enumerate()
+        {
+            assert_eq!(index.get_spend(serial_number).unwrap(), Some((height as u32, *transaction_id)));
+            assert_eq!(index.get_creation(commitment).unwrap(), Some((height as u32, *transaction_id)));
+        }
+        let public_input = input_id_map.get(&execution_id_map.get(&transaction_ids[0]).unwrap().unwrap().0[0]).unwrap();
+        assert_eq!(index.get_spend(&public_input.unwrap()[1]).unwrap(), None);
+    }
+
+    #[test]
+    #[serial]
+    #[allow(clippy::type_complexity)]
+    fn test_record_deduplication_resumes_after_interrupt() {
+        let rng = &mut TestRng::default();
+        let mut database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+
+        // Insert the legacy output records, of which all but two share a dummy record.
+        let legacy_map: DataMap<
+            Field<CurrentNetwork>,
+            (Field<CurrentNetwork>, Option<Record<CurrentNetwork, Ciphertext<CurrentNetwork>>>),
+        > = database.map(MapID::TransitionOutput(TransitionOutputMap::Record));
+        let sample_record = |rng: &mut TestRng| {
+            // Encrypt the record with a randomizer that corresponds to its nonce.
+            let randomizer = Scalar::rand(rng);
+            let nonce = CurrentNetwork::g_scalar_multiply(&randomizer);
+            Record::<CurrentNetwork, Plaintext<CurrentNetwork>>::from_str(&format!(
+                "{{ owner: aleo1d5hg2z3ma00382pngntdp68e74zv54jdxy249qhaujhks9c72yrs33ddah.private, gates: 0u64.private, _nonce: {nonce}.public }}"
+            ))
+            .unwrap()
+            .encrypt(randomizer)
+            .unwrap()
+        };
+        let (dummy, unique) = (sample_record(rng), sample_record(rng));
+        let mut records =
+            (0..8).map(|_| (Field::rand(rng), (Field::rand(rng), Some(dummy.clone())))).collect::<Vec<_>>();
+        records.push((Field::rand(rng), (Field::rand(rng), Some(unique.clone()))));
+        records.push((Field::rand(rng), (Field::rand(rng), None)));
+        for (commitment, record) in &records {
+            legacy_map.insert(*commitment, record.clone()).unwrap();
+        }
+
+        // Interrupt the migration after two chunks, while the third chunk is moved without recording its cursor.
+        let migration = RecordDeduplication::<CurrentNetwork>::new(3);
+        let mut cursor = None;
+        let result = migration.apply(&mut database, None, &mut |progress| {
+            ensure!(progress.num_chunks_done <= 2, "Interrupted");
+            cursor = Some(progress.cursor);
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(cursor, Some(6));
+        assert_eq!(legacy_map.keys().count(), 1);
+
+        // Resume the migration.
+        let mut last_progress = None;
+        migration
+            .apply(&mut database, cursor, &mut |progress| {
+                last_progress = Some(progress);
+                Ok(())
+            })
+            .unwrap();
+        // Ensure the progress resumes from the recorded cursor.
+        let progress = last_progress.unwrap();
+        assert_eq!((progress.num_migrated, progress.num_total), (7, 7));
+        assert_eq!((progress.num_chunks_done, progress.num_chunks), (3, 3));
+
+        // Ensure every output record is moved, and each ciphertext is stored once.
+        let record_map = RecordCiphertextMap::<CurrentNetwork>::from_database(&database);
+        assert_eq!(legacy_map.keys().count(), 0);
+        assert_eq!(record_map.num_ciphertexts(), 2);
+        for (commitment, record) in &records {
+            assert_eq!(record_map.get(commitment).unwrap().unwrap().into_owned(), *record);
+        }
+        let dummy_size = dummy.to_bytes_le().unwrap().len() as u64;
+        assert_eq!(record_map.num_bytes_saved().unwrap(), 7 * dummy_size);
+    }
+
+    #[test]
+    #[serial]
+    fn test_sequence_index_backfill() {
+        let rng = &mut TestRng::default();
+        let (mut database, hashes) = sample_fixture_database(rng);
+
+        // Confirm up to 3 transactions in each block, including empty blocks.
+        let transactions_map: DataMap<
+            <CurrentNetwork as Network>::BlockHash,
+            Vec<<CurrentNetwork as Network>::TransactionID>,
+        > = database.map(MapID::Block(BlockMap::Transactions));
+        let mut transaction_ids = Vec::new();
+        for (height, hash) in hashes.iter().enumerate() {
+            let block = (0..height % 4).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect::<Vec<_>>();
+            transactions_map.insert(*hash, block.clone()).unwrap();
+            transaction_ids.extend(block);
+        }
+
+        // Number the transactions in chunks of 3 blocks, interrupting the migration after the second chunk.
+        let interrupted = Interrupted { migration: SequenceIndexBackfill::<CurrentNetwork>::new(3), num_chunks: 1 };
+        assert!(interrupted.apply(&mut database, None, &mut |_| Ok(())).is_err());
+        let index = SequenceIndex::<CurrentNetwork>::from_database(&database);
+        assert_eq!(index.num_blocks().unwrap(), 6);
+
+        // Resume the migration from the cursor of the first chunk, and ensure the numbered blocks are skipped,
+        // and the transactions are numbered densely, in order.
+        SequenceIndexBackfill::<CurrentNetwork>::new(3)
+            .with_num_workers(4)
+            .apply(&mut database, Some(3), &mut |_| Ok(()))
+            .unwrap();
+        assert_eq!(index.num_blocks().unwrap(), NUM_BLOCKS);
+        assert_eq!(index.next_sequence().unwrap(), transaction_ids.len() as u64);
+        for (sequence, transaction_id) in transaction_ids.iter().enumerate() {
+            assert_eq!(index.get_sequence(transaction_id).unwrap(), Some(sequence as u64));
+            assert_eq!(index.get_transaction_id(sequence as u64).unwrap(), Some(*transaction_id));
+        }
+
+        // Ensure the lookups are correct at the boundaries of the chunks, which start at blocks 3, 6, and 9.
+        let mut start = 0;
+        for height in 0..NUM_BLOCKS {
+            let num_transactions = height as u64 % 4;
+            assert_eq!(index.get_block_sequences(height).unwrap(), Some(start..start + num_transactions));
+            if height % 3 == 0 && num_transactions > 0 {
+                let first = transactions_map.get(&hashes[height as usize]).unwrap().unwrap()[0];
+                assert_eq!(index.get_transaction_id(start).unwrap(), Some(first));
+                assert_eq!(index.get_transaction_id(start - 1).unwrap(), Some(transaction_ids[start as usize - 1]));
+            }
+            start += num_transactions;
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_registry_refuses_newer_schema() {
+        let mut database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the database");
+
+        // Ensure a new database is migrated to the latest schema version.
+        let registry = migrations::<CurrentNetwork>().unwrap();
+        assert_eq!(registry.run(&mut database).unwrap(), registry.latest_version());
+
+        // Ensure a database with a newer schema version is refused.
+        SchemaDB::open(&database).version_map.insert((), registry.latest_version() + 1).unwrap();
+        assert!(registry.run(&mut database).is_err());
+
+        // Ensure the migrations must be registered in order.
+        assert!(MigrationRegistry::default()
+            .register(Interrupted { migration: JournalBackfill::<CurrentNetwork>::new(1), num_chunks: 0 })
+            .and_then(|registry| registry.register(JournalBackfill::<CurrentNetwork>::default()))
+            .is_err());
+    }
+}
@@ -0,0 +1,416 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    ChainMmrMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::marker::PhantomData;
+use serde::{Deserialize, Serialize};
+
+/// The commitment to the canonical chain, which is the root of the Merkle mountain range over its block hashes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ChainCommitment<N: Network> {
+    /// The root of the Merkle mountain range.
+    pub root: Field<N>,
+    /// The number of blocks in the Merkle mountain range.
+    pub size: u32,
+}
+
+/// A proof that a block hash is at a given height in the canonical chain, under a chain commitment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ChainProof<N: Network> {
+    /// The height of the block.
+    pub height: u32,
+    /// The hash of the block.
+    pub block_hash: N::BlockHash,
+    /// The number of blocks in the chain commitment.
+    pub size: u32,
+    /// The siblings of the Merkle path from the block to its peak, from the bottom up.
+    pub path: Vec<Field<N>>,
+    /// The peaks of the Merkle mountain range, from the highest to the lowest.
+    pub peaks: Vec<Field<N>>,
+}
+
+/// Checks that the given proof proves its block hash is at its height under the given chain commitment.
+pub fn verify_chain_proof<N: Network>(proof: &ChainProof<N>, commitment: &ChainCommitment<N>) -> Result<()> {
+    // Ensure the proof is for a chain of the same size.
+    ensure!(
+        proof.size == commitment.size,
+        "The chain proof is for a chain of {} blocks, instead of {} blocks",
+        proof.size,
+        commitment.size
+    );
+    let (peak_index, peak_height, index) = match locate(proof.height, proof.size) {
+        Some(location) => location,
+        None => bail!("Block {} is not in a chain of {} blocks", proof.height, proof.size),
+    };
+    // Ensure the proof has a peak for each perfect tree, and a sibling for each level of the tree of the block.
+    let num_peaks = peaks(proof.size).len();
+    ensure!(proof.peaks.len() == num_peaks, "The chain proof has {} peaks, instead of {num_peaks}", proof.peaks.len());
+    ensure!(
+        proof.path.len() == peak_height as usize,
+        "The chain proof has a path of {} nodes, instead of {peak_height}",
+        proof.path.len()
+    );
+
+    // Compute the peak from the block, and ensure it is the peak of the block.
+    let mut node = hash_leaf::<N>(proof.height, &proof.block_hash)?;
+    for (level, sibling) in proof.path.iter().enumerate() {
+        node = match (index >> level) & 1 {
+            0 => hash_node::<N>(&node, sibling)?,
+            _ => hash_node::<N>(sibling, &node)?,
+        };
+    }
+    ensure!(
+        node == proof.peaks[peak_index],
+        "Block {} ('{}') does not belong to its peak in the chain proof",
+        proof.height,
+        proof.block_hash
+    );
+    // Ensure the peaks are for the given root.
+    ensure!(
+        hash_peaks::<N>(proof.size, &proof.peaks)? == commitment.root,
+        "The chain proof is not for the chain commitment '{}'",
+        commitment.root
+    );
+    Ok(())
+}
+
+/// Returns the leaf of the block with the given height and hash.
+fn hash_leaf<N: Network>(height: u32, block_hash: &N::BlockHash) -> Result<Field<N>> {
+    N::hash_psd2(&[Field::from_u32(height), **block_hash])
+}
+
+/// Returns the parent of the given nodes.
+fn hash_node<N: Network>(left: &Field<N>, right: &Field<N>) -> Result<Field<N>> {
+    N::hash_psd2(&[*left, *right])
+}
+
+/// Returns the root of a Merkle mountain range with the given number of leaves and peaks.
+fn hash_peaks<N: Network>(size: u32, peaks: &[Field<N>]) -> Result<Field<N>> {
+    let mut input = Vec::with_capacity(1 + peaks.len());
+    input.push(Field::from_u32(size));
+    input.extend_from_slice(peaks);
+    N::hash_psd2(&input)
+}
+
+/// Returns the number of nodes of a Merkle mountain range with the given number of leaves.
+const fn num_nodes(num_leaves: u32) -> u64 {
+    2 * num_leaves as u64 - num_leaves.count_ones() as u64
+}
+
+/// Returns the height and position of each peak of a Merkle mountain range with the given number of leaves,
+/// from the highest to the lowest.
+fn peaks(num_leaves: u32) -> Vec<(u32, u64)> {
+    let mut peaks = Vec::with_capacity(num_leaves.count_ones() as usize);
+    let mut offset = 0u64;
+    for height in (0..u32::BITS).rev() {
+        if num_leaves & (1 << height) != 0 {
+            let num_tree_nodes = (1u64 << (height + 1)) - 1;
+            peaks.push((height, offset + num_tree_nodes - 1));
+            offset += num_tree_nodes;
+        }
+    }
+    peaks
+}
+
+/// Returns the index and height of the peak that contains the given leaf, with the index of the leaf in the peak,
+/// if the leaf is in a Merkle mountain range with the given number of leaves.
+fn locate(leaf: u32, num_leaves: u32) -> Option<(usize, u32, u32)> {
+    let mut start = 0u64;
+    for (peak_index, (height, _)) in peaks(num_leaves).into_iter().enumerate() {
+        let end = start + (1u64 << height);
+        if (leaf as u64) < end {
+            return Some((peak_index, height, (leaf as u64 - start) as u32));
+        }
+        start = end;
+    }
+    None
+}
+
+/// Returns the positions of the siblings of the Merkle path from the given leaf to the given peak, from the bottom up.
+fn path_positions(peak_position: u64, peak_height: u32, index: u32) -> Vec<u64> {
+    let mut positions = Vec::with_capacity(peak_height as usize);
+    // The position of the first node of the subtree that contains the leaf.
+    let mut start = peak_position + 2 - (1u64 << (peak_height + 1));
+    for height in (1..=peak_height).rev() {
+        // The number of nodes in each child of the subtree.
+        let num_child_nodes = (1u64 << height) - 1;
+        let left = start + num_child_nodes - 1;
+        match (index >> (height - 1)) & 1 {
+            0 => positions.push(left + num_child_nodes),
+            _ => {
+                positions.push(left);
+                start += num_child_nodes;
+            }
+        }
+    }
+    positions.reverse();
+    positions
+}
+
+/// A Merkle mountain range over the canonical block hashes, from which the chain commitment and the chain proofs
+/// of the blocks are computed, so that a mirror can prove it serves an unmodified chain.
+///
+/// The nodes are stored by their position in post-order. The leaf of a block is appended in the same batch that
+/// connects it, and the nodes of a block are removed in the same batch that disconnects it.
+#[derive(Clone)]
+pub struct ChainMmr<N: Network> {
+    /// The mapping of `node position` to `node`.
+    node_map: DataMap<u64, Field<N>>,
+    /// The number of blocks in the Merkle mountain range, which is the only value in the map.
+    size_map: DataMap<(), u32>,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> ChainMmr<N> {
+    /// Opens the Merkle mountain range of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the Merkle mountain range of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            node_map: database.map(MapID::ChainMmr(ChainMmrMap::Node)),
+            size_map: database.map(MapID::ChainMmr(ChainMmrMap::Size)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of blocks in the Merkle mountain range, including the writes of the atomic batch in progress.
+    pub fn size(&self) -> Result<u32> {
+        Ok(self.size_map.get_speculative(&())?.map_or(0, |size| *size))
+    }
+
+    /// Returns the node at the given position.
+    fn get_node(&self, position: u64) -> Result<Field<N>> {
+        match self.node_map.get_speculative(&position)? {
+            Some(node) => Ok(*node),
+            None => bail!("Missing the node at position {position} of the chain commitment"),
+        }
+    }
+
+    /// Returns the peaks of the Merkle mountain range with the given number of blocks, from the highest to the lowest.
+    fn get_peaks(&self, size: u32) -> Result<Vec<Field<N>>> {
+        peaks(size).into_iter().map(|(_, position)| self.get_node(position)).collect()
+    }
+
+    /// Returns the commitment to the canonical chain.
+    pub fn commitment(&self) -> Result<ChainCommitment<N>> {
+        let size = self.size()?;
+        Ok(ChainCommitment { root: hash_peaks::<N>(size, &self.get_peaks(size)?)?, size })
+    }
+
+    /// Returns a proof that the given block hash is at the given height, under the current chain commitment.
+    pub fn generate_chain_proof(&self, height: u32, block_hash: N::BlockHash) -> Result<ChainProof<N>> {
+        let size = self.size()?;
+        let (peak_index, peak_height, index) = match locate(height, size) {
+            Some(location) => location,
+            None => bail!("Block {height} is not in the chain commitment of {size} blocks"),
+        };
+        // Ensure the block hash is at the given height.
+        ensure!(
+            self.get_node(num_nodes(height))? == hash_leaf::<N>(height, &block_hash)?,
+            "Block '{block_hash}' is not at height {height} in the chain commitment"
+        );
+        let peak_position = peaks(size)[peak_index].1;
+        let path = path_positions(peak_position, peak_height, index)
+            .into_iter()
+            .map(|position| self.get_node(position))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ChainProof { height, block_hash, size, path, peaks: self.get_peaks(size)? })
+    }
+
+    /// Appends the block with the given height and hash, which must follow the latest block.
+    /// Note that a block that is already appended is skipped.
+    pub fn insert(&self, height: u32, block_hash: &N::BlockHash) -> Result<()> {
+        let size = self.size()?;
+        if height < size {
+            return Ok(());
+        }
+        // Ensure the block follows the latest block.
+        ensure!(height == size, "Block {height} does not follow the latest block of the chain commitment ({size})");
+
+        // Append the leaf, and merge it with the peaks of the same height.
+        let mut position = num_nodes(height);
+        let mut node = hash_leaf::<N>(height, block_hash)?;
+        self.node_map.insert(position, node)?;
+        let mut num_leaves = height;
+        let mut num_child_nodes = 1u64;
+        while num_leaves & 1 == 1 {
+            let left = self.get_node(position - num_child_nodes)?;
+            node = hash_node::<N>(&left, &node)?;
+            position += 1;
+            self.node_map.insert(position, node)?;
+            num_leaves >>= 1;
+            num_child_nodes = 2 * num_child_nodes + 1;
+        }
+        self.size_map.insert((), height + 1)
+    }
+
+    /// Removes the block with the given height, which must be the latest block.
+    pub fn remove(&self, height: u32) -> Result<()> {
+        // Ensure the block is the latest block.
+        let size = self.size()?;
+        ensure!(height.checked_add(1) == Some(size), "Block {height} is not the latest block of the chain commitment");
+        for position in num_nodes(height)..num_nodes(size) {
+            self.node_map.remove(&position)?;
+        }
+        self.size_map.insert((), height)
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        self.node_map.start_atomic();
+        self.size_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.node_map.is_atomic_in_progress() || self.size_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        self.node_map.abort_atomic();
+        self.size_map.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        self.node_map.finish_atomic()?;
+        self.size_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// Returns the given number of random block hashes.
+    fn sample_hashes(num_blocks: u32, rng: &mut TestRng) -> Vec<BlockHash> {
+        (0..num_blocks).map(|_| BlockHash::from(Field::rand(rng))).collect()
+    }
+
+    /// Returns a Merkle mountain range over the given block hashes.
+    fn sample_mmr(hashes: &[BlockHash]) -> ChainMmr<CurrentNetwork> {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let mmr = ChainMmr::<CurrentNetwork>::from_database(&database);
+        for (height, hash) in hashes.iter().enumerate() {
+            mmr.insert(height as u32, hash).unwrap();
+        }
+        mmr
+    }
+
+    #[test]
+    fn test_positions() {
+        assert_eq!((0..8).map(num_nodes).collect::<Vec<_>>(), vec![0, 1, 3, 4, 7, 8, 10, 11]);
+        // A range of 7 blocks has peaks of 4, 2, and 1 blocks.
+        assert_eq!(peaks(7), vec![(2, 6), (1, 9), (0, 10)]);
+        assert_eq!(locate(0, 7), Some((0, 2, 0)));
+        assert_eq!(locate(5, 7), Some((1, 1, 1)));
+        assert_eq!(locate(6, 7), Some((2, 0, 0)));
+        assert_eq!(locate(7, 7), None);
+        // The leaf 2 (position 3) has the siblings at position 4 and then 2.
+        assert_eq!(path_positions(6, 2, 2), vec![4, 2]);
+        assert_eq!(path_positions(9, 1, 1), vec![7]);
+        assert!(path_positions(10, 0, 0).is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_chain_proofs() {
+        let rng = &mut TestRng::default();
+        let hashes = sample_hashes(13, rng);
+        let mmr = sample_mmr(&hashes);
+        let commitment = mmr.commitment().unwrap();
+        assert_eq!(commitment.size, 13);
+
+        // Ensure every block is proven at its height, including the genesis block and the tip.
+        for (height, hash) in hashes.iter().enumerate() {
+            let proof = mmr.generate_chain_proof(height as u32, *hash).unwrap();
+            verify_chain_proof(&proof, &commitment).unwrap();
+        }
+
+        // Ensure a block is not proven at another height, nor with another hash.
+        assert!(mmr.generate_chain_proof(1, hashes[0]).is_err());
+        assert!(mmr.generate_chain_proof(13, hashes[12]).is_err());
+        let mut proof = mmr.generate_chain_proof(0, hashes[0]).unwrap();
+        proof.height = 1;
+        assert!(verify_chain_proof(&proof, &commitment).is_err());
+        proof.height = 0;
+        proof.block_hash = hashes[1];
+        assert!(verify_chain_proof(&proof, &commitment).is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_chain_proofs_after_reorg() {
+        let rng = &mut TestRng::default();
+        let hashes = sample_hashes(10, rng);
+        let mmr = sample_mmr(&hashes);
+        let stale = mmr.commitment().unwrap();
+        let stale_proof = mmr.generate_chain_proof(9, hashes[9]).unwrap();
+
+        // Disconnect the latest blocks, and ensure the commitment is restored to the shorter chain.
+        for height in (7..10).rev() {
+            mmr.remove(height).unwrap();
+        }
+        assert!(mmr.remove(3).is_err());
+        assert_eq!(mmr.commitment().unwrap(), sample_mmr(&hashes[..7]).commitment().unwrap());
+
+        // Connect a branch of the same length.
+        let branch = sample_hashes(3, rng);
+        for (height, hash) in (7..10).zip(&branch) {
+            mmr.insert(height, hash).unwrap();
+        }
+        let commitment = mmr.commitment().unwrap();
+        assert_eq!(commitment.size, stale.size);
+        assert_ne!(commitment.root, stale.root);
+
+        // Ensure the blocks of the branch and before the fork are proven under the new commitment.
+        verify_chain_proof(&mmr.generate_chain_proof(0, hashes[0]).unwrap(), &commitment).unwrap();
+        verify_chain_proof(&mmr.generate_chain_proof(6, hashes[6]).unwrap(), &commitment).unwrap();
+        verify_chain_proof(&mmr.generate_chain_proof(9, branch[2]).unwrap(), &commitment).unwrap();
+        assert!(mmr.generate_chain_proof(9, hashes[9]).is_err());
+
+        // Ensure the proofs are rejected against a stale root.
+        assert!(verify_chain_proof(&stale_proof, &commitment).is_err());
+        assert!(verify_chain_proof(&mmr.generate_chain_proof(0, hashes[0]).unwrap(), &stale).is_err());
+        mmr.insert(10, &BlockHash::from(Field::rand(rng))).unwrap();
+        let proof = mmr.generate_chain_proof(0, hashes[0]).unwrap();
+        assert!(verify_chain_proof(&proof, &commitment).is_err());
+        verify_chain_proof(&proof, &mmr.commitment().unwrap()).unwrap();
+    }
+}
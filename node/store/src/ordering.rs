@@ -0,0 +1,351 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    BlockMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use serde::Serialize;
+
+/// The maximum number of transactions in a block for which every permutation of the stored order is searched.
+pub const MAX_EXHAUSTIVE_ORDERING_SEARCH: usize = 7;
+/// The maximum number of transactions in a block for which the orders that swap two transactions are searched.
+pub const MAX_SWAP_ORDERING_SEARCH: usize = 128;
+
+/// A canonical block whose stored transaction order does not match the transactions root in its header.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OrderingMismatch {
+    /// The height of the block.
+    pub height: u32,
+    /// The hash of the block.
+    pub block_hash: String,
+    /// The order of the stored transactions that matches the transactions root, where the transaction at each
+    /// position is the stored transaction at the given index, if one is found.
+    pub permutation: Option<Vec<usize>>,
+    /// Whether the stored order was rewritten to the matching order.
+    pub repaired: bool,
+}
+
+/// The report of the audit of the transaction order of the canonical blocks.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct OrderingReport {
+    /// The number of audited blocks.
+    pub num_blocks: u32,
+    /// The blocks whose stored order does not match their header, in ascending order of height.
+    pub mismatches: Vec<OrderingMismatch>,
+}
+
+/// The audit of the transaction order of the canonical blocks, which recomputes the transactions root of each
+/// block from its stored transaction IDs, and compares it against the header of the block.
+///
+/// Only the transaction IDs of one block are held in memory at a time, so the audit streams through the chain,
+/// and covers the pruned blocks, as they keep their headers and transaction IDs.
+#[derive(Clone)]
+pub struct OrderingAudit<N: Network> {
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `block header`.
+    header_map: DataMap<N::BlockHash, Header<N>>,
+    /// The mapping of `block hash` to `[transaction ID]`.
+    transactions_map: DataMap<N::BlockHash, Vec<N::TransactionID>>,
+}
+
+impl<N: Network> OrderingAudit<N> {
+    /// Opens the ordering audit of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the ordering audit of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            header_map: database.map(MapID::Block(BlockMap::Header)),
+            transactions_map: database.map(MapID::Block(BlockMap::Transactions)),
+        }
+    }
+
+    /// Audits the canonical blocks in ascending order, and returns the blocks whose stored order does not
+    /// match their header. The given function is called with each mismatch as it is found.
+    ///
+    /// If `repair` is set, the stored order of each mismatched block is rewritten to the matching order,
+    /// in an atomic batch per block. A block without a matching order is reported, and left as is.
+    pub fn audit(&self, repair: bool, mut on_mismatch: impl FnMut(&OrderingMismatch)) -> Result<OrderingReport> {
+        let mut report = OrderingReport::default();
+        let latest_height = match self.id_map.keys().map(|height| *height).max() {
+            Some(height) => height,
+            None => return Ok(report),
+        };
+        for height in 0..=latest_height {
+            if let Some(mismatch) = self.audit_block(height, repair)? {
+                on_mismatch(&mismatch);
+                report.mismatches.push(mismatch);
+            }
+            report.num_blocks += 1;
+        }
+        Ok(report)
+    }
+
+    /// Rewrites the stored order of the block at the given height to the order that matches its header.
+    /// This fails if the stored order already matches, or if no matching order is found.
+    pub fn repair_block(&self, height: u32) -> Result<Vec<usize>> {
+        match self.audit_block(height, true)? {
+            Some(OrderingMismatch { permutation: Some(permutation), .. }) => Ok(permutation),
+            Some(_) => bail!("Refusing to repair block {height}: no order of its transactions matches its header"),
+            None => bail!("The transactions of block {height} already match its header"),
+        }
+    }
+
+    /// Audits the block at the given height, and returns the mismatch, if any.
+    fn audit_block(&self, height: u32, repair: bool) -> Result<Option<OrderingMismatch>> {
+        let block_hash = match self.id_map.get(&height)? {
+            Some(block_hash) => *block_hash,
+            None => bail!("Failed to audit block {height}: missing block hash"),
+        };
+        let transactions_root = match self.header_map.get(&block_hash)? {
+            Some(header) => header.transactions_root(),
+            None => bail!("Failed to audit block {height}: missing block header"),
+        };
+        let transaction_ids = match self.transactions_map.get(&block_hash)? {
+            Some(transaction_ids) => transaction_ids.into_owned(),
+            None => bail!("Failed to audit block {height}: missing transaction IDs"),
+        };
+
+        // Ensure the stored order matches the header.
+        if to_transactions_root::<N>(transaction_ids.iter())? == transactions_root {
+            return Ok(None);
+        }
+        let permutation = find_permutation::<N>(&transaction_ids, transactions_root)?;
+        warn!("Block {height} ('{block_hash}') has its transactions stored out of order (fix: {permutation:?})");
+
+        // Rewrite the stored order, if requested and a matching order is found.
+        let repaired = match (&permutation, repair) {
+            (Some(permutation), true) => {
+                let reordered = permutation.iter().map(|index| transaction_ids[*index]).collect::<Vec<_>>();
+                self.transactions_map.start_atomic();
+                if let Err(error) = self.transactions_map.insert(block_hash, reordered) {
+                    self.transactions_map.abort_atomic();
+                    return Err(error);
+                }
+                self.transactions_map.finish_atomic()?;
+                true
+            }
+            _ => false,
+        };
+        Ok(Some(OrderingMismatch { height, block_hash: block_hash.to_string(), permutation, repaired }))
+    }
+}
+
+/// Returns the transactions root of the given transaction IDs, in the given order.
+fn to_transactions_root<'a, N: Network>(
+    transaction_ids: impl Iterator<Item = &'a N::TransactionID>,
+) -> Result<Field<N>> {
+    let leaves = transaction_ids.map(|transaction_id| transaction_id.to_bits_le()).collect::<Vec<_>>();
+    Ok(*N::merkle_tree_bhp::<TRANSACTIONS_DEPTH>(&leaves)?.root())
+}
+
+/// Returns the order of the given transaction IDs whose transactions root is the given root, if one is found,
+/// as the index of the transaction ID at each position.
+///
+/// Every order is searched for up to `MAX_EXHAUSTIVE_ORDERING_SEARCH` transactions. Beyond it, the orders that
+/// swap two transactions and the reversed order are searched, for up to `MAX_SWAP_ORDERING_SEARCH` transactions.
+fn find_permutation<N: Network>(transaction_ids: &[N::TransactionID], root: Field<N>) -> Result<Option<Vec<usize>>> {
+    let num_transactions = transaction_ids.len();
+    let matches = |permutation: &[usize]| -> Result<bool> {
+        Ok(to_transactions_root::<N>(permutation.iter().map(|index| &transaction_ids[*index]))? == root)
+    };
+    let mut permutation = (0..num_transactions).collect::<Vec<_>>();
+
+    if num_transactions <= MAX_EXHAUSTIVE_ORDERING_SEARCH {
+        // Search every order, with Heap's algorithm, which yields each order with a single swap.
+        let mut counters = vec![0; num_transactions];
+        let mut index = 0;
+        while index < num_transactions {
+            if counters[index] < index {
+                match index % 2 == 0 {
+                    true => permutation.swap(0, index),
+                    false => permutation.swap(counters[index], index),
+                }
+                if matches(&permutation)? {
+                    return Ok(Some(permutation));
+                }
+                counters[index] += 1;
+                index = 0;
+            } else {
+                counters[index] = 0;
+                index += 1;
+            }
+        }
+    } else if num_transactions <= MAX_SWAP_ORDERING_SEARCH {
+        // Search the orders that swap two transactions.
+        for first in 0..num_transactions {
+            for second in first + 1..num_transactions {
+                permutation.swap(first, second);
+                if matches(&permutation)? {
+                    return Ok(Some(permutation));
+                }
+                permutation.swap(first, second);
+            }
+        }
+        // Search the reversed order.
+        permutation.reverse();
+        if matches(&permutation)? {
+            return Ok(Some(permutation));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+    type TransactionID = <CurrentNetwork as Network>::TransactionID;
+
+    /// The number of blocks in the fixture database.
+    const NUM_BLOCKS: u32 = 4;
+
+    /// Returns a header at the given height, that commits to the given transaction IDs in the given order.
+    fn sample_header(height: u32, transaction_ids: &[TransactionID]) -> Header<CurrentNetwork> {
+        let metadata = Metadata::new(
+            CurrentNetwork::ID,
+            height as u64,
+            height,
+            CurrentNetwork::STARTING_SUPPLY,
+            0,
+            CurrentNetwork::GENESIS_COINBASE_TARGET,
+            CurrentNetwork::GENESIS_PROOF_TARGET,
+            CurrentNetwork::GENESIS_COINBASE_TARGET,
+            CurrentNetwork::GENESIS_TIMESTAMP,
+            CurrentNetwork::GENESIS_TIMESTAMP + height as i64,
+        )
+        .unwrap();
+        let previous_state_root: Field<CurrentNetwork> = match height {
+            0 => Field::zero(),
+            _ => Field::one(),
+        };
+        let transactions_root = to_transactions_root::<CurrentNetwork>(transaction_ids.iter()).unwrap();
+        Header::from(previous_state_root, transactions_root, Field::zero(), Field::zero(), metadata).unwrap()
+    }
+
+    /// Returns the fixture database, with each block committing to its transactions in their stored order.
+    fn sample_audit(rng: &mut TestRng) -> (OrderingAudit<CurrentNetwork>, Vec<(BlockHash, Vec<TransactionID>)>) {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let audit = OrderingAudit::<CurrentNetwork>::from_database(&database);
+        let mut blocks = Vec::new();
+        for height in 0..NUM_BLOCKS {
+            let block_hash: BlockHash = Field::<CurrentNetwork>::rand(rng).into();
+            let transaction_ids: Vec<TransactionID> =
+                (0..3).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect();
+            audit.id_map.insert(height, block_hash).unwrap();
+            audit.header_map.insert(block_hash, sample_header(height, &transaction_ids)).unwrap();
+            audit.transactions_map.insert(block_hash, transaction_ids.clone()).unwrap();
+            blocks.push((block_hash, transaction_ids));
+        }
+        (audit, blocks)
+    }
+
+    #[test]
+    #[serial]
+    fn test_audit_detects_and_repairs_swapped_order() {
+        let rng = &mut TestRng::default();
+        let (audit, blocks) = sample_audit(rng);
+
+        // Ensure an ordered chain passes the audit.
+        let report = audit.audit(false, |_| ()).unwrap();
+        assert_eq!(report, OrderingReport { num_blocks: NUM_BLOCKS, mismatches: vec![] });
+
+        // Plant a block with two of its transactions swapped.
+        let (block_hash, transaction_ids) = &blocks[2];
+        let swapped = vec![transaction_ids[2], transaction_ids[1], transaction_ids[0]];
+        audit.transactions_map.insert(*block_hash, swapped).unwrap();
+
+        // Ensure the mismatch is reported with the order that fixes it, and nothing is rewritten.
+        let mut found = Vec::new();
+        let report = audit.audit(false, |mismatch| found.push(mismatch.height)).unwrap();
+        assert_eq!(found, vec![2]);
+        assert_eq!(report.mismatches, vec![OrderingMismatch {
+            height: 2,
+            block_hash: block_hash.to_string(),
+            permutation: Some(vec![2, 1, 0]),
+            repaired: false,
+        }]);
+        assert_ne!(audit.transactions_map.get(block_hash).unwrap().unwrap().into_owned(), *transaction_ids);
+
+        // Ensure the repair restores the committed order.
+        let report = audit.audit(true, |_| ()).unwrap();
+        assert!(report.mismatches[0].repaired);
+        assert_eq!(audit.transactions_map.get(block_hash).unwrap().unwrap().into_owned(), *transaction_ids);
+        assert!(audit.audit(false, |_| ()).unwrap().mismatches.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_repair_refused_without_matching_order() {
+        let rng = &mut TestRng::default();
+        let (audit, blocks) = sample_audit(rng);
+
+        // Plant a block with a transaction that its header does not commit to.
+        let (block_hash, transaction_ids) = &blocks[1];
+        let replaced = vec![transaction_ids[1], transaction_ids[0], Field::<CurrentNetwork>::rand(rng).into()];
+        audit.transactions_map.insert(*block_hash, replaced.clone()).unwrap();
+
+        // Ensure the mismatch is reported without a fix, and the repair is refused.
+        let report = audit.audit(true, |_| ()).unwrap();
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].permutation, None);
+        assert!(!report.mismatches[0].repaired);
+        assert!(audit.repair_block(1).is_err());
+        assert_eq!(audit.transactions_map.get(block_hash).unwrap().unwrap().into_owned(), replaced);
+
+        // Ensure a block that matches its header is not repaired.
+        assert!(audit.repair_block(0).is_err());
+    }
+
+    #[test]
+    fn test_find_permutation() {
+        let rng = &mut TestRng::default();
+
+        // Ensure the orders of a small block are searched exhaustively.
+        let transaction_ids: Vec<TransactionID> = (0..5).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect();
+        let root = to_transactions_root::<CurrentNetwork>(transaction_ids.iter()).unwrap();
+        let shuffled = [3, 0, 4, 1, 2].iter().map(|index| transaction_ids[*index]).collect::<Vec<_>>();
+        let permutation = find_permutation::<CurrentNetwork>(&shuffled, root).unwrap().unwrap();
+        assert_eq!(permutation.iter().map(|index| shuffled[*index]).collect::<Vec<_>>(), transaction_ids);
+
+        // Ensure a swap is found in a larger block.
+        let transaction_ids: Vec<TransactionID> =
+            (0..MAX_EXHAUSTIVE_ORDERING_SEARCH + 3).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect();
+        let root = to_transactions_root::<CurrentNetwork>(transaction_ids.iter()).unwrap();
+        let mut swapped = transaction_ids.clone();
+        swapped.swap(1, 8);
+        let permutation = find_permutation::<CurrentNetwork>(&swapped, root).unwrap().unwrap();
+        assert_eq!(permutation.iter().map(|index| swapped[*index]).collect::<Vec<_>>(), transaction_ids);
+    }
+}
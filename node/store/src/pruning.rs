@@ -0,0 +1,198 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    BlockMap,
+    DeploymentMap,
+    ExecutionMap,
+    MapID,
+    PruningMap,
+    TransitionMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+/// The pruner of the blocks in the database, which removes the bodies of the blocks below a height.
+///
+/// A pruned block keeps its header, signature, and transaction IDs, along with the metadata of its transactions
+/// and the indexes of its transitions (serial numbers, commitments, and inclusion state roots), so that new
+/// blocks are still validated against it. Only its transition proofs and coinbase solution are removed.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct BlockPruner<N: Network> {
+    /// The height below which the blocks are pruned, which is the only value in the map.
+    height_map: DataMap<(), u32>,
+    /// The mapping of `block height` to `block hash`.
+    id_map: DataMap<u32, N::BlockHash>,
+    /// The mapping of `block hash` to `[transaction ID]`.
+    transactions_map: DataMap<N::BlockHash, Vec<N::TransactionID>>,
+    /// The mapping of `block hash` to `coinbase solution`.
+    coinbase_solution_map: DataMap<N::BlockHash, Option<CoinbaseSolution<N>>>,
+    /// The mapping of `transaction ID` to the transition IDs of the execution, and its optional fee transition ID.
+    execution_id_map: DataMap<N::TransactionID, (Vec<N::TransitionID>, Option<N::TransitionID>)>,
+    /// The mapping of `transaction ID` to the fee transition of the deployment.
+    deployment_fee_map: DataMap<N::TransactionID, (N::TransitionID, N::StateRoot, Option<Proof<N>>)>,
+    /// The mapping of `transition ID` to `proof`.
+    proof_map: DataMap<N::TransitionID, Proof<N>>,
+}
+
+impl<N: Network> BlockPruner<N> {
+    /// Opens the block pruner of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the block pruner of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            height_map: database.map(MapID::Pruning(PruningMap::Height)),
+            id_map: database.map(MapID::Block(BlockMap::ID)),
+            transactions_map: database.map(MapID::Block(BlockMap::Transactions)),
+            coinbase_solution_map: database.map(MapID::Block(BlockMap::CoinbaseSolution)),
+            execution_id_map: database.map(MapID::Execution(ExecutionMap::ID)),
+            deployment_fee_map: database.map(MapID::Deployment(DeploymentMap::Fee)),
+            proof_map: database.map(MapID::Transition(TransitionMap::Proof)),
+        }
+    }
+
+    /// Returns the height below which the blocks are pruned, or `0` if no block is pruned.
+    pub fn pruned_height(&self) -> Result<u32> {
+        Ok(self.height_map.get(&())?.map_or(0, |height| *height))
+    }
+
+    /// Prunes the blocks below the given height, and returns the height below which the blocks are pruned.
+    ///
+    /// The genesis block is never pruned, and the pruned height never decreases. Each block is pruned
+    /// before the pruned height is advanced past it, so that an interrupted prune resumes at that block.
+    pub fn prune(&self, height: u32) -> Result<u32> {
+        let pruned_height = self.pruned_height()?;
+        for block_height in pruned_height.max(1)..height {
+            self.prune_block(block_height)?;
+            self.height_map.insert((), block_height + 1)?;
+        }
+        Ok(pruned_height.max(height))
+    }
+
+    /// Removes the transition proofs and the coinbase solution of the block at the given height.
+    fn prune_block(&self, height: u32) -> Result<()> {
+        // Retrieve the block hash.
+        let block_hash = match self.id_map.get(&height)? {
+            Some(block_hash) => *block_hash,
+            None => bail!("Failed to prune block {height}: missing block hash"),
+        };
+        // Retrieve the transaction IDs.
+        let transaction_ids = match self.transactions_map.get(&block_hash)? {
+            Some(transaction_ids) => transaction_ids.into_owned(),
+            None => bail!("Failed to prune block {height}: missing transaction IDs"),
+        };
+
+        // Remove the proofs of the transitions of each transaction.
+        for transaction_id in &transaction_ids {
+            let mut transition_ids = Vec::new();
+            if let Some(execution) = self.execution_id_map.get(transaction_id)? {
+                let (execution_transition_ids, fee_transition_id) = execution.into_owned();
+                transition_ids.extend(execution_transition_ids);
+                transition_ids.extend(fee_transition_id);
+            }
+            if let Some(fee) = self.deployment_fee_map.get(transaction_id)? {
+                transition_ids.push(fee.0);
+            }
+            for transition_id in &transition_ids {
+                self.proof_map.remove(transition_id)?;
+            }
+        }
+        // Remove the coinbase solution.
+        self.coinbase_solution_map.remove(&block_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+    type TransitionID = <CurrentNetwork as Network>::TransitionID;
+
+    /// The number of blocks in the fixture database.
+    const NUM_BLOCKS: u32 = 6;
+
+    #[test]
+    #[serial]
+    fn test_prune_blocks() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let pruner = BlockPruner::<CurrentNetwork>::from_database(&database);
+
+        // Open stand-ins for the proofs and coinbase solutions, which the pruner only removes by key.
+        let proof_map: DataMap<TransitionID, Vec<u8>> = database.map(MapID::Transition(TransitionMap::Proof));
+        let coinbase_solution_map: DataMap<BlockHash, Vec<u8>> = database.map(MapID::Block(BlockMap::CoinbaseSolution));
+
+        // Insert the blocks, each with an execution and a deployment.
+        let mut blocks = Vec::new();
+        for height in 0..NUM_BLOCKS {
+            let block_hash: BlockHash = Field::<CurrentNetwork>::rand(rng).into();
+            let (execution_id, deployment_id) =
+                (Field::<CurrentNetwork>::rand(rng).into(), Field::<CurrentNetwork>::rand(rng).into());
+            let transition_ids: Vec<TransitionID> = (0..3).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect();
+
+            pruner.id_map.insert(height, block_hash).unwrap();
+            pruner.transactions_map.insert(block_hash, vec![execution_id, deployment_id]).unwrap();
+            pruner.execution_id_map.insert(execution_id, (transition_ids[..2].to_vec(), None)).unwrap();
+            pruner
+                .deployment_fee_map
+                .insert(deployment_id, (transition_ids[2], Field::<CurrentNetwork>::rand(rng).into(), None))
+                .unwrap();
+            for transition_id in &transition_ids {
+                proof_map.insert(*transition_id, vec![1, 2, 3]).unwrap();
+            }
+            coinbase_solution_map.insert(block_hash, vec![4, 5, 6]).unwrap();
+            blocks.push((block_hash, execution_id, transition_ids));
+        }
+        assert_eq!(pruner.pruned_height().unwrap(), 0);
+
+        // Prune the blocks below height 4.
+        assert_eq!(pruner.prune(4).unwrap(), 4);
+        for (height, (block_hash, execution_id, transition_ids)) in blocks.iter().enumerate() {
+            // Ensure the genesis block and the blocks above the pruned height are intact.
+            let is_pruned = height > 0 && height < 4;
+            assert_eq!(coinbase_solution_map.contains_key(block_hash).unwrap(), !is_pruned);
+            for transition_id in transition_ids {
+                assert_eq!(proof_map.contains_key(transition_id).unwrap(), !is_pruned);
+            }
+            // Ensure the metadata of every block is kept.
+            assert!(pruner.transactions_map.contains_key(block_hash).unwrap());
+            assert!(pruner.execution_id_map.contains_key(execution_id).unwrap());
+        }
+
+        // Ensure the pruned height never decreases, and is persisted.
+        assert_eq!(pruner.prune(2).unwrap(), 4);
+        assert_eq!(BlockPruner::<CurrentNetwork>::from_database(&database).pruned_height().unwrap(), 4);
+
+        // Ensure a block with missing data is not skipped.
+        pruner.id_map.remove(&4).unwrap();
+        assert!(pruner.prune(NUM_BLOCKS).is_err());
+        assert_eq!(pruner.pruned_height().unwrap(), 4);
+    }
+}
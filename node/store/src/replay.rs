@@ -0,0 +1,355 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    ChainEvent,
+    ChainJournal,
+    JournalMap,
+    MapID,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::fmt;
+use sha2::{Digest, Sha256};
+
+/// The number of events read from the journal at a time, while replaying it.
+const REPLAY_PAGE_SIZE: usize = 1000;
+
+/// A subsystem that maintains state derived from the changes to the canonical chain, outside of the block commits.
+///
+/// As its updates are not atomic with the block commits, the subsystem persists the sequence number of the latest
+/// journaled event it processed, and replays the events that follow it on startup, before the node goes live.
+pub trait JournalConsumer<N: Network> {
+    /// Returns the name of the subsystem, under which its cursor is persisted.
+    fn name(&self) -> &str;
+
+    /// Applies the given event to the derived state.
+    ///
+    /// This must be idempotent, as an event that was applied before a crash, but whose cursor was not persisted,
+    /// is applied again on startup.
+    fn apply(&mut self, event: &ChainEvent<N>) -> Result<()>;
+
+    /// Rebuilds the derived state from the canonical chain, as the journal no longer has the events it missed.
+    fn rebuild(&mut self) -> Result<()>;
+}
+
+/// The error of a replay, for a cursor that the journal was pruned past.
+/// The subsystem must be rebuilt, as the events it missed are no longer retained.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JournalPruned {
+    /// The name of the subsystem.
+    pub subsystem: String,
+    /// The sequence number of the latest event processed by the subsystem.
+    pub cursor: u64,
+    /// The sequence number of the oldest event retained in the journal.
+    pub oldest_sequence: u64,
+}
+
+impl fmt::Display for JournalPruned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The journal was pruned past the cursor of '{}' (processed {}, the oldest retained event is {})",
+            self.subsystem, self.cursor, self.oldest_sequence
+        )
+    }
+}
+
+impl std::error::Error for JournalPruned {}
+
+/// The persisted cursors of the subsystems that consume the journal, each of which is the sequence number
+/// of the latest event the subsystem processed.
+#[derive(Clone)]
+pub struct JournalCursors {
+    /// The mapping of `subsystem key` to `sequence number`, where the key is the SHA-256 of the subsystem name.
+    cursor_map: DataMap<[u8; 32], u64>,
+}
+
+impl JournalCursors {
+    /// Opens the journal cursors of the database.
+    pub fn open<N: Network>(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the journal cursors of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self { cursor_map: database.map(MapID::Journal(JournalMap::Cursor)) }
+    }
+
+    /// Returns the cursor of the given subsystem, or `0` if it has not processed any event.
+    pub fn get(&self, subsystem: &str) -> Result<u64> {
+        Ok(self.cursor_map.get(&Self::key(subsystem))?.map_or(0, |sequence| *sequence))
+    }
+
+    /// Advances the cursor of the given subsystem to the given sequence number, once the event is applied.
+    pub fn set(&self, subsystem: &str, sequence: u64) -> Result<()> {
+        self.cursor_map.insert(Self::key(subsystem), sequence)
+    }
+
+    /// Returns the key of the cursor of the given subsystem.
+    fn key(subsystem: &str) -> [u8; 32] {
+        Sha256::digest(subsystem.as_bytes()).into()
+    }
+}
+
+/// Replays the journaled events that follow the cursor of the given subsystem, advancing the cursor after each event,
+/// and returns the number of replayed events.
+///
+/// If the journal was pruned past the cursor, a `JournalPruned` error is returned, and no event is applied.
+pub fn replay_journal<N: Network, S: JournalConsumer<N>>(
+    journal: &ChainJournal<N>,
+    cursors: &JournalCursors,
+    subsystem: &mut S,
+) -> Result<u64> {
+    let name = subsystem.name().to_string();
+    let mut cursor = cursors.get(&name)?;
+    // Ensure the events following the cursor are retained, including a cursor ahead of a journal that was reset.
+    let oldest_sequence = journal.oldest_sequence();
+    if cursor.saturating_add(1) < oldest_sequence || cursor > journal.latest_sequence() {
+        return Err(JournalPruned { subsystem: name, cursor, oldest_sequence }.into());
+    }
+
+    let mut num_replayed = 0;
+    loop {
+        let events = journal.events(cursor, REPLAY_PAGE_SIZE)?;
+        if events.is_empty() {
+            break;
+        }
+        for (sequence, event) in events {
+            subsystem.apply(&event)?;
+            cursors.set(&name, sequence)?;
+            cursor = sequence;
+            num_replayed += 1;
+        }
+    }
+    if num_replayed > 0 {
+        info!("Replayed {num_replayed} journaled events for '{name}', up to event {cursor}");
+    }
+    Ok(num_replayed)
+}
+
+/// Replays the journaled events that follow the cursor of the given subsystem, or rebuilds the subsystem
+/// if the journal was pruned past its cursor, and then replays the events that followed the rebuild.
+pub fn replay_or_rebuild<N: Network, S: JournalConsumer<N>>(
+    journal: &ChainJournal<N>,
+    cursors: &JournalCursors,
+    subsystem: &mut S,
+) -> Result<()> {
+    match replay_journal(journal, cursors, subsystem) {
+        Ok(_) => Ok(()),
+        Err(error) => match error.downcast::<JournalPruned>() {
+            Ok(pruned) => {
+                warn!("{pruned} - rebuilding '{}'", pruned.subsystem);
+                // Read the latest sequence number first, so that the events that follow the rebuild are replayed.
+                let latest_sequence = journal.latest_sequence();
+                subsystem.rebuild()?;
+                cursors.set(&pruned.subsystem, latest_sequence)?;
+                replay_journal(journal, cursors, subsystem).map(|_| ())
+            }
+            Err(error) => Err(error),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rocksdb::tests::temp_dir, TestMap};
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// A scanner that persists the canonical blocks it observed, and rebuilds them from the given chain.
+    struct Scanner {
+        /// The mapping of `block height` to `block hash`.
+        block_map: DataMap<u32, BlockHash>,
+        /// The canonical chain, from which the scanner is rebuilt.
+        chain: Vec<(u32, BlockHash)>,
+    }
+
+    impl Scanner {
+        /// Opens the scanner of the given database.
+        fn open(database: &RocksDB, chain: &[(u32, BlockHash)]) -> Self {
+            Self { block_map: database.map(MapID::Test(TestMap::Test)), chain: chain.to_vec() }
+        }
+
+        /// Returns the observed blocks, in order of height.
+        fn blocks(&self) -> Vec<(u32, BlockHash)> {
+            let mut blocks = self.block_map.iter().map(|(height, hash)| (*height, *hash)).collect::<Vec<_>>();
+            blocks.sort_unstable_by_key(|(height, _)| *height);
+            blocks
+        }
+    }
+
+    impl JournalConsumer<CurrentNetwork> for Scanner {
+        fn name(&self) -> &str {
+            "scanner"
+        }
+
+        fn apply(&mut self, event: &ChainEvent<CurrentNetwork>) -> Result<()> {
+            match event {
+                ChainEvent::Connected { height, hash, .. } => self.block_map.insert(*height, *hash),
+                ChainEvent::Disconnected { height, .. } => self.block_map.remove(height),
+                ChainEvent::SequencesInvalidated { .. } => Ok(()),
+            }
+        }
+
+        fn rebuild(&mut self) -> Result<()> {
+            for height in self.block_map.keys().map(|height| *height).collect::<Vec<_>>() {
+                self.block_map.remove(&height)?;
+            }
+            for (height, hash) in &self.chain {
+                self.block_map.insert(*height, *hash)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// The node, which commits the blocks to the journal, and then updates the scanner.
+    struct Harness {
+        database: RocksDB,
+        journal: ChainJournal<CurrentNetwork>,
+        cursors: JournalCursors,
+        scanner: Scanner,
+        chain: Vec<(u32, BlockHash)>,
+    }
+
+    impl Harness {
+        fn new(retention: u64) -> Self {
+            let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+            let journal = ChainJournal::from_map(database.map(MapID::Journal(JournalMap::Event)), retention).unwrap();
+            let cursors = JournalCursors::from_database(&database);
+            let scanner = Scanner::open(&database, &[]);
+            Self { database, journal, cursors, scanner, chain: Vec::new() }
+        }
+
+        /// Commits the given event, and updates the scanner, unless the node crashes before the update.
+        fn commit(&mut self, event: ChainEvent<CurrentNetwork>, crash: bool) {
+            match &event {
+                ChainEvent::Connected { height, hash, .. } => self.chain.push((*height, *hash)),
+                ChainEvent::Disconnected { .. } => assert!(self.chain.pop().is_some()),
+                ChainEvent::SequencesInvalidated { .. } => (),
+            }
+            let sequence = self.journal.append(event.clone()).unwrap();
+            if !crash {
+                self.scanner.apply(&event).unwrap();
+                self.cursors.set(self.scanner.name(), sequence).unwrap();
+            }
+        }
+
+        /// Connects a new block at the tip.
+        fn connect(&mut self, crash: bool, rng: &mut TestRng) {
+            let height = self.chain.len() as u32;
+            let hash = Field::<CurrentNetwork>::rand(rng).into();
+            self.commit(ChainEvent::Connected { height, hash, transaction_ids: vec![] }, crash);
+        }
+
+        /// Disconnects the block at the tip.
+        fn disconnect(&mut self, crash: bool) {
+            let (height, hash) = *self.chain.last().unwrap();
+            self.commit(ChainEvent::Disconnected { height, hash }, crash);
+        }
+
+        /// Restarts the node, which reopens the scanner and replays the journal before going live.
+        fn restart(&mut self) {
+            self.scanner = Scanner::open(&self.database, &self.chain);
+            replay_or_rebuild(&self.journal, &self.cursors, &mut self.scanner).unwrap();
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_replay_after_crash() {
+        let rng = &mut TestRng::default();
+        let mut harness = Harness::new(100);
+
+        // Connect a few blocks, and crash between the commit of a block and the scanner update.
+        for _ in 0..5 {
+            harness.connect(false, rng);
+        }
+        harness.connect(true, rng);
+        assert_eq!(harness.scanner.blocks(), harness.chain[..5]);
+
+        // Ensure the scanner catches up on restart, and matches a scanner that never crashed.
+        harness.restart();
+        assert_eq!(harness.scanner.blocks(), harness.chain);
+        assert_eq!(harness.cursors.get("scanner").unwrap(), harness.journal.latest_sequence());
+
+        // Crash during a reorganization, and ensure the scanner follows it on restart.
+        harness.disconnect(false);
+        harness.disconnect(true);
+        harness.connect(true, rng);
+        harness.restart();
+        assert_eq!(harness.scanner.blocks(), harness.chain);
+        assert_eq!(harness.chain.len(), 5);
+
+        // Ensure a restart without missed events replays nothing.
+        assert_eq!(replay_journal(&harness.journal, &harness.cursors, &mut harness.scanner).unwrap(), 0);
+    }
+
+    #[test]
+    #[serial]
+    fn test_replay_is_idempotent() {
+        let rng = &mut TestRng::default();
+        let mut harness = Harness::new(100);
+        for _ in 0..3 {
+            harness.connect(false, rng);
+        }
+
+        // Apply the next events, but crash before the cursors are persisted.
+        let cursor = harness.cursors.get("scanner").unwrap();
+        harness.connect(false, rng);
+        harness.disconnect(false);
+        harness.cursors.set("scanner", cursor).unwrap();
+
+        // Ensure the events are applied again on restart, to the same state.
+        harness.restart();
+        assert_eq!(harness.scanner.blocks(), harness.chain);
+        assert_eq!(harness.cursors.get("scanner").unwrap(), harness.journal.latest_sequence());
+    }
+
+    #[test]
+    #[serial]
+    fn test_replay_pruned_journal() {
+        let rng = &mut TestRng::default();
+        let mut harness = Harness::new(3);
+        harness.connect(false, rng);
+
+        // Crash, while the journal is pruned past the cursor of the scanner.
+        for _ in 0..5 {
+            harness.connect(true, rng);
+        }
+        let error = replay_journal(&harness.journal, &harness.cursors, &mut harness.scanner).unwrap_err();
+        let pruned = error.downcast::<JournalPruned>().unwrap();
+        assert_eq!(pruned, JournalPruned { subsystem: "scanner".to_string(), cursor: 1, oldest_sequence: 4 });
+        assert_eq!(harness.scanner.blocks(), harness.chain[..1]);
+
+        // Ensure the scanner is rebuilt on restart instead, and then follows the journal again.
+        harness.restart();
+        assert_eq!(harness.scanner.blocks(), harness.chain);
+        assert_eq!(harness.cursors.get("scanner").unwrap(), 6);
+        harness.connect(true, rng);
+        harness.restart();
+        assert_eq!(harness.scanner.blocks(), harness.chain);
+    }
+}
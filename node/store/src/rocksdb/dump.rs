@@ -0,0 +1,222 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::DataID;
+
+use anyhow::ensure;
+use serde::Deserialize;
+
+/// The maximum number of entries returned by a single raw iteration.
+pub const MAX_RAW_ENTRIES: usize = 1000;
+
+/// A raw entry of a column, with the key (excluding the prefix) and the value in hexadecimal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawEntry {
+    /// The hex-encoded key, excluding the prefix.
+    pub key: String,
+    /// The hex-encoded value.
+    pub value: String,
+}
+
+/// A page of the raw entries of a column.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawDump {
+    /// The name of the column.
+    pub column: String,
+    /// The entries, in key order.
+    pub entries: Vec<RawEntry>,
+    /// The hex-encoded key from which to resume, if the limit was reached.
+    pub next_key: Option<String>,
+}
+
+/// Returns up to `limit` raw entries of the given column of the opened database, with hex-encoded keys
+/// in `[start_key, end_key)`.
+pub fn dump_column(column: &str, start_key: Option<&str>, end_key: Option<&str>, limit: usize) -> Result<RawDump> {
+    match DB.get() {
+        Some(database) => database.dump_column(column, start_key, end_key, limit),
+        None => bail!("The database has not been opened"),
+    }
+}
+
+/// Returns the names of the columns of the database, in order of their IDs.
+pub fn column_names() -> Vec<String> {
+    DataID::ALL.iter().map(|data_id| format!("{data_id:?}")).collect()
+}
+
+impl RocksDB {
+    /// Opens the database in the given directory in read-only mode, such as an offline copy of the storage.
+    /// Every write to the returned database fails, and the database may be open in another process.
+    pub fn open_read_only(path: PathBuf, network_id: u16) -> Result<Self> {
+        // Customize database options, with the default tuning profile.
+        let profile = TuningProfile::Default;
+        let mut options = rocksdb::Options::default();
+        profile.options().apply(&mut options)?;
+
+        // Register the prefix length.
+        let prefix_extractor = rocksdb::SliceTransform::create_fixed_prefix(PREFIX_LEN);
+        options.set_prefix_extractor(prefix_extractor);
+
+        // Ensure the database exists, as it is never created in read-only mode.
+        ensure!(path.join("CURRENT").exists(), "No database found in '{}'", path.display());
+        let rocksdb = Arc::new(rocksdb::DB::open_for_read_only(&options, path, false)?);
+
+        Ok(RocksDB {
+            rocksdb,
+            network_id,
+            dev: None,
+            writes: Default::default(),
+            profile,
+            bulk_sync: Default::default(),
+            journal: Default::default(),
+        })
+    }
+
+    /// Returns the prefix of the column with the given name.
+    pub(super) fn column_prefix(&self, name: &str) -> Result<Vec<u8>> {
+        // Retrieve the column.
+        let data_id = match DataID::ALL.iter().find(|data_id| format!("{data_id:?}") == name) {
+            Some(data_id) => *data_id,
+            None => bail!("Unknown column '{name}'"),
+        };
+        // Construct the prefix of the column.
+        let mut prefix = self.network_id.to_le_bytes().to_vec();
+        prefix.extend_from_slice(&(data_id as u16).to_le_bytes());
+        Ok(prefix)
+    }
+
+    /// Returns up to `limit` raw entries of the given column, with hex-encoded keys in `[start_key, end_key)`.
+    pub fn dump_column(
+        &self,
+        column: &str,
+        start_key: Option<&str>,
+        end_key: Option<&str>,
+        limit: usize,
+    ) -> Result<RawDump> {
+        // Decode the bounds of the range.
+        let start_key = start_key.map(hex::decode).transpose().map_err(|e| anyhow!("Invalid start key: {e}"))?;
+        let end_key = end_key.map(hex::decode).transpose().map_err(|e| anyhow!("Invalid end key: {e}"))?;
+
+        // Retrieve the entries.
+        let entries = self.iter_raw(column, start_key.as_deref(), end_key.as_deref(), limit)?;
+
+        // If the limit was reached, resume from the smallest key after the last entry.
+        let next_key = match entries.len() == limit {
+            true => entries.last().map(|(key, _)| hex::encode([key.as_slice(), &[0u8]].concat())),
+            false => None,
+        };
+        Ok(RawDump {
+            column: column.to_string(),
+            entries: entries
+                .into_iter()
+                .map(|(key, value)| RawEntry { key: hex::encode(key), value: hex::encode(value) })
+                .collect(),
+            next_key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rocksdb::tests::temp_dir, TestMap};
+    use snarkvm::{
+        prelude::{Block, Field, FromBytes, Network, Testnet3},
+        synthesizer::store::helpers::{Map, MapRead},
+    };
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a commitment index of the genesis block, mapping each output commitment to its transaction ID.
+    fn sample_commitment_index(
+        database: &RocksDB,
+    ) -> (Block<CurrentNetwork>, DataMap<Field<CurrentNetwork>, <CurrentNetwork as Network>::TransactionID>) {
+        let block = Block::<CurrentNetwork>::from_bytes_le(CurrentNetwork::genesis_bytes()).unwrap();
+        let map = database.map(MapID::Test(TestMap::Test));
+        for transaction in block.transactions().iter() {
+            for commitment in transaction.commitments() {
+                map.insert(*commitment, transaction.id()).unwrap();
+            }
+        }
+        (block, map)
+    }
+
+    #[test]
+    #[serial]
+    fn test_dump_commitment_index() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the test database");
+        let (block, _map) = sample_commitment_index(&database);
+        let num_commitments = block.transactions().commitments().count();
+
+        // Dump the index, and ensure every entry matches the output of its transaction.
+        let dump = database.dump_column("Test", None, None, MAX_RAW_ENTRIES).unwrap();
+        assert_eq!(dump.entries.len(), num_commitments);
+        assert_eq!(dump.next_key, None);
+        for entry in &dump.entries {
+            let commitment: Field<CurrentNetwork> = bincode::deserialize(&hex::decode(&entry.key).unwrap()).unwrap();
+            let transaction_id: <CurrentNetwork as Network>::TransactionID =
+                bincode::deserialize(&hex::decode(&entry.value).unwrap()).unwrap();
+            let transaction = block.transactions().find_transaction_for_commitment(&commitment);
+            assert_eq!(transaction.map(|transaction| transaction.id()), Some(transaction_id));
+        }
+
+        // Ensure the dump resumes from the next key, until the range is exhausted.
+        let first = database.dump_column("Test", None, None, 1).unwrap();
+        assert_eq!(first.entries, dump.entries[..1]);
+        let rest = database.dump_column("Test", first.next_key.as_deref(), None, MAX_RAW_ENTRIES).unwrap();
+        assert_eq!(rest.entries, dump.entries[1..]);
+
+        // Ensure the end key is exclusive.
+        let bounded = database.dump_column("Test", None, Some(&dump.entries[1].key), MAX_RAW_ENTRIES).unwrap();
+        assert_eq!(bounded.entries, dump.entries[..1]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_iter_raw_limits() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the test database");
+        let _ = sample_commitment_index(&database);
+
+        // Ensure the limit is enforced, and the column must exist.
+        assert!(database.iter_raw("Test", None, None, 0).is_err());
+        assert!(database.iter_raw("Test", None, None, MAX_RAW_ENTRIES + 1).is_err());
+        assert!(database.iter_raw("Unknown", None, None, 1).is_err());
+        assert!(database.dump_column("Test", Some("not hex"), None, 1).is_err());
+        // Ensure an empty column has no entries.
+        assert!(database.iter_raw("JournalEventMap", None, None, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_read_only_database_rejects_writes() {
+        let directory = temp_dir();
+        let database = RocksDB::open_testing(directory.clone(), None).expect("Failed to open the test database");
+        let (_, map) = sample_commitment_index(&database);
+        let dump = database.dump_column("Test", None, None, MAX_RAW_ENTRIES).unwrap();
+
+        // Open the same database in read-only mode, and ensure it dumps the same entries.
+        let read_only = RocksDB::open_read_only(directory, database.network_id).unwrap();
+        assert_eq!(read_only.dump_column("Test", None, None, MAX_RAW_ENTRIES).unwrap(), dump);
+
+        // Ensure writes through the read-only database fail.
+        let read_only_map: DataMap<Field<CurrentNetwork>, <CurrentNetwork as Network>::TransactionID> =
+            read_only.map(MapID::Test(TestMap::Test));
+        let (commitment, transaction_id) = map.iter().next().map(|(k, v)| (*k, *v)).unwrap();
+        assert!(read_only_map.insert(commitment, transaction_id).is_err());
+    }
+}
@@ -15,13 +15,19 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use super::*;
+use crate::DataID;
 
 use indexmap::IndexMap;
-use rocksdb::WriteBatch;
 use snarkvm::synthesizer::store::helpers::{Map, MapRead};
 
 use core::{fmt, fmt::Debug, hash::Hash};
 use std::{borrow::Cow, sync::atomic::Ordering};
+use tracing::Span;
+
+/// The name of the span of a request served from storage, within which the storage reads are timed.
+pub const REQUEST_SPAN: &str = "rpc";
+/// The target of the spans of the storage reads.
+pub const STORAGE_TARGET: &str = "snarkos_storage";
 
 #[derive(Clone)]
 pub struct DataMap<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> {
@@ -34,10 +40,10 @@ pub struct DataMap<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOw
 }
 
 impl<
-    'a,
-    K: 'a + Copy + Clone + Debug + PartialEq + Eq + Hash + Serialize + DeserializeOwned + Send + Sync,
-    V: 'a + Clone + PartialEq + Eq + Serialize + DeserializeOwned + Send + Sync,
-> Map<'a, K, V> for DataMap<K, V>
+        'a,
+        K: 'a + Copy + Clone + Debug + PartialEq + Eq + Hash + Serialize + DeserializeOwned + Send + Sync,
+        V: 'a + Clone + PartialEq + Eq + Serialize + DeserializeOwned + Send + Sync,
+    > Map<'a, K, V> for DataMap<K, V>
 {
     ///
     /// Inserts the given key-value pair into the map.
@@ -56,6 +62,8 @@ impl<
                 // Prepare the prefixed key and serialized value.
                 let raw_key = self.create_prefixed_key(&key)?;
                 let raw_value = bincode::serialize(&value)?;
+                // Update the write counters.
+                self.database.writes.record_insert(raw_key.len() + raw_value.len());
                 self.database.put(raw_key, raw_value)?;
             }
         }
@@ -79,6 +87,8 @@ impl<
             false => {
                 // Prepare the prefixed key.
                 let raw_key = self.create_prefixed_key(key)?;
+                // Update the write counters.
+                self.database.writes.record_removal();
                 self.database.delete(raw_key)?;
             }
         }
@@ -124,25 +134,29 @@ impl<
         let operations = core::mem::take(&mut *self.atomic_batch.lock());
 
         if !operations.is_empty() {
-            // Prepare operations batch for underlying database.
-            let mut batch = WriteBatch::default();
+            // Prepare the raw operations for the underlying database.
+            let mut raw_operations = Vec::with_capacity(operations.len());
             for operation in operations {
                 match operation {
                     (key, Some(value)) => {
                         // Prepare the prefixed key and serialized value for insertion.
                         let raw_key = self.create_prefixed_key(&key)?;
                         let raw_value = bincode::serialize(&value)?;
-                        batch.put(raw_key, raw_value);
+                        self.database.writes.record_insert(raw_key.len() + raw_value.len());
+                        raw_operations.push((raw_key, Some(raw_value)));
                     }
                     (key, None) => {
                         // Prepare the prefixed key for deletion.
                         let raw_key = self.create_prefixed_key(&key)?;
-                        batch.delete(raw_key);
+                        self.database.writes.record_removal();
+                        raw_operations.push((raw_key, None));
                     }
                 };
             }
-            // Execute all the operations atomically.
-            self.database.rocksdb.write(batch)?;
+            // Execute all the operations atomically, unless they are collected with the operations of other maps.
+            if let Some(raw_operations) = collect_operations(raw_operations) {
+                self.database.write_operations(raw_operations)?;
+            }
         }
 
         // Set the atomic batch flag to `false`.
@@ -153,10 +167,10 @@ impl<
 }
 
 impl<
-    'a,
-    K: 'a + Copy + Clone + Debug + PartialEq + Eq + Hash + Serialize + DeserializeOwned + Send + Sync,
-    V: 'a + Clone + PartialEq + Eq + Serialize + DeserializeOwned + Send + Sync,
-> MapRead<'a, K, V> for DataMap<K, V>
+        'a,
+        K: 'a + Copy + Clone + Debug + PartialEq + Eq + Hash + Serialize + DeserializeOwned + Send + Sync,
+        V: 'a + Clone + PartialEq + Eq + Serialize + DeserializeOwned + Send + Sync,
+    > MapRead<'a, K, V> for DataMap<K, V>
 {
     type Iterator = Iter<'a, K, V>;
     type Keys = Keys<'a, K>;
@@ -201,7 +215,11 @@ impl<
         K: Borrow<Q>,
         Q: PartialEq + Eq + Hash + Serialize + ?Sized,
     {
-        if self.batch_in_progress.load(Ordering::Acquire) { self.atomic_batch.lock().get(key).cloned() } else { None }
+        if self.batch_in_progress.load(Ordering::Acquire) {
+            self.atomic_batch.lock().get(key).cloned()
+        } else {
+            None
+        }
     }
 
     ///
@@ -244,11 +262,109 @@ impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> DataMap<K
         Q: Serialize + ?Sized,
     {
         let raw_key = self.create_prefixed_key(key)?;
+        let _span = self.read_span("get").entered();
         match self.database.get_pinned(&raw_key)? {
             Some(data) => Ok(Some(data)),
             None => Ok(None),
         }
     }
+
+    /// Returns the span of a read of the given operation on this map, if it is performed within a request span,
+    /// so that the reads of the node itself (e.g. while syncing) are not timed.
+    fn read_span(&self, operation: &'static str) -> Span {
+        match Span::current().metadata() {
+            Some(metadata) if metadata.name() == REQUEST_SPAN => {
+                // Retrieve the name of the column, from its prefix.
+                let id = self.context.get(2..PREFIX_LEN).map(|id| u16::from_le_bytes([id[0], id[1]]));
+                let column = DataID::ALL
+                    .iter()
+                    .find(|data_id| Some(**data_id as u16) == id)
+                    .map(|data_id| format!("{data_id:?}"))
+                    .unwrap_or_default();
+                trace_span!(target: STORAGE_TARGET, "storage", operation, column = %column)
+            }
+            _ => Span::none(),
+        }
+    }
+}
+
+impl<K: Copy + Ord + Hash + Serialize + DeserializeOwned, V: Clone + Serialize + DeserializeOwned> DataMap<K, V> {
+    /// Returns the entry with the greatest key at or before the given key, including the writes of the atomic
+    /// batch in progress. Note that the serialized keys must sort in the same order as the keys,
+    /// such as big-endian byte arrays.
+    pub fn seek_prev_speculative(&self, key: &K) -> Result<Option<(K, V)>> {
+        let batch = self.speculative_batch();
+        // Retrieve the greatest stored key at or before the given key, which is not removed in the batch.
+        let mut stored = None;
+        let mut iterator = self.database.raw_iterator();
+        iterator.seek_for_prev(self.create_prefixed_key(key)?);
+        while let (Some(raw_key), Some(raw_value)) = (iterator.key(), iterator.value()) {
+            if !raw_key.starts_with(&self.context) {
+                break;
+            }
+            let stored_key: K = bincode::deserialize(&raw_key[self.context.len()..])?;
+            match batch.get(&stored_key) {
+                Some(None) => iterator.prev(),
+                Some(Some(value)) => {
+                    stored = Some((stored_key, value.clone()));
+                    break;
+                }
+                None => {
+                    stored = Some((stored_key, bincode::deserialize(raw_value)?));
+                    break;
+                }
+            }
+        }
+        // Retrieve the greatest key inserted in the batch at or before the given key.
+        let batched = batch
+            .into_iter()
+            .filter_map(|(batched_key, value)| value.map(|value| (batched_key, value)))
+            .filter(|(batched_key, _)| batched_key <= key)
+            .max_by_key(|(batched_key, _)| *batched_key);
+        Ok(match (stored, batched) {
+            (Some(stored), Some(batched)) => Some(if batched.0 >= stored.0 { batched } else { stored }),
+            (stored, batched) => stored.or(batched),
+        })
+    }
+
+    /// Returns up to `limit` entries with keys at or after the given key, in order, including the writes of the
+    /// atomic batch in progress. Note that the serialized keys must sort in the same order as the keys,
+    /// such as big-endian byte arrays.
+    pub fn range_speculative(&self, start: &K, limit: usize) -> Result<Vec<(K, V)>> {
+        let batch = self.speculative_batch();
+        // Retrieve the first stored entries at or after the given key, which are not removed in the batch.
+        let mut entries = Vec::new();
+        let mut iterator = self.database.raw_iterator();
+        iterator.seek(self.create_prefixed_key(start)?);
+        while let (Some(raw_key), Some(raw_value)) = (iterator.key(), iterator.value()) {
+            if !raw_key.starts_with(&self.context) || entries.len() >= limit {
+                break;
+            }
+            let stored_key: K = bincode::deserialize(&raw_key[self.context.len()..])?;
+            if !batch.contains_key(&stored_key) {
+                entries.push((stored_key, bincode::deserialize(raw_value)?));
+            }
+            iterator.next();
+        }
+        // Merge the entries inserted in the batch at or after the given key.
+        entries.extend(
+            batch
+                .into_iter()
+                .filter_map(|(batched_key, value)| value.map(|value| (batched_key, value)))
+                .filter(|(batched_key, _)| batched_key >= start),
+        );
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    /// Returns the writes of the atomic batch in progress, if there is one.
+    fn speculative_batch(&self) -> IndexMap<K, Option<V>> {
+        match self.batch_in_progress.load(Ordering::Acquire) {
+            true => self.atomic_batch.lock().clone(),
+            false => Default::default(),
+        }
+    }
 }
 
 impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> fmt::Debug for DataMap<K, V> {
@@ -502,4 +618,34 @@ mod tests {
         // The map should contain NUM_ITEMS items now.
         assert_eq!(map.iter().count(), NUM_ITEMS);
     }
+
+    #[test]
+    #[serial]
+    fn test_ordered_seeks_are_speculative() {
+        // Initialize a map, with big-endian keys.
+        let map: DataMap<[u8; 4], u32> =
+            RocksDB::open_map_testing(temp_dir(), None, MapID::Test(TestMap::Test)).expect("Failed to open data map");
+        for i in [10u32, 20, 300, 4000] {
+            map.insert(i.to_be_bytes(), i).unwrap();
+        }
+
+        // Ensure the seeks follow the order of the keys.
+        assert_eq!(map.seek_prev_speculative(&5u32.to_be_bytes()).unwrap(), None);
+        assert_eq!(map.seek_prev_speculative(&299u32.to_be_bytes()).unwrap().map(|(_, value)| value), Some(20));
+        assert_eq!(map.seek_prev_speculative(&300u32.to_be_bytes()).unwrap().map(|(_, value)| value), Some(300));
+        assert_eq!(map.seek_prev_speculative(&u32::MAX.to_be_bytes()).unwrap().map(|(_, value)| value), Some(4000));
+        let values = |entries: Vec<([u8; 4], u32)>| entries.into_iter().map(|(_, value)| value).collect::<Vec<_>>();
+        assert_eq!(values(map.range_speculative(&11u32.to_be_bytes(), 2).unwrap()), vec![20, 300]);
+        assert_eq!(values(map.range_speculative(&4001u32.to_be_bytes(), 2).unwrap()), Vec::<u32>::new());
+
+        // Ensure the seeks include the writes of the atomic batch in progress.
+        map.start_atomic();
+        map.remove(&20u32.to_be_bytes()).unwrap();
+        map.insert(15u32.to_be_bytes(), 15).unwrap();
+        map.insert(300u32.to_be_bytes(), 301).unwrap();
+        assert_eq!(map.seek_prev_speculative(&299u32.to_be_bytes()).unwrap().map(|(_, value)| value), Some(15));
+        assert_eq!(values(map.range_speculative(&11u32.to_be_bytes(), 3).unwrap()), vec![15, 301, 4000]);
+        map.abort_atomic();
+        assert_eq!(values(map.range_speculative(&0u32.to_be_bytes(), usize::MAX).unwrap()), vec![10, 20, 300, 4000]);
+    }
 }
@@ -20,25 +20,80 @@ pub use map::*;
 pub mod iterator;
 use iterator::*;
 
+mod dump;
+pub use dump::*;
+
+mod profile;
+pub use profile::*;
+
+mod statistics;
+pub use statistics::*;
+
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 
-use crate::MapID;
+use crate::{journal::JournalState, MapID};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use core::{fmt::Debug, hash::Hash};
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use serde::{de::DeserializeOwned, Serialize};
+use rocksdb::WriteBatch;
 use std::{
     borrow::Borrow,
+    cell::RefCell,
     marker::PhantomData,
     ops::Deref,
+    path::PathBuf,
     sync::{atomic::AtomicBool, Arc},
 };
 
 pub const PREFIX_LEN: usize = 4; // N::ID (u16) + DataID (u16)
 
+/// A raw operation on the database: the insertion of a value, or the removal of a key if there is no value.
+type RawOperation = (Vec<u8>, Option<Vec<u8>>);
+
+thread_local! {
+    /// The raw operations of the atomic batches finished on this thread, while they are collected into one batch.
+    static COLLECTED_OPERATIONS: RefCell<Option<Vec<RawOperation>>> = RefCell::new(None);
+}
+
+/// Collects the given raw operations of a finished atomic batch, if the batches finished on this thread
+/// are collected, or returns them to be written at once otherwise.
+fn collect_operations(raw_operations: Vec<RawOperation>) -> Option<Vec<RawOperation>> {
+    COLLECTED_OPERATIONS.with(|collected| match collected.borrow_mut().as_mut() {
+        Some(collected) => {
+            collected.extend(raw_operations);
+            None
+        }
+        None => Some(raw_operations),
+    })
+}
+
+/// The database instance, once opened.
+static DB: OnceCell<RocksDB> = OnceCell::new();
+/// The custom storage directory, if one is set.
+static STORAGE_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Sets a custom directory for the database, such as a storage snapshot.
+/// This must be called before the database is opened, and can only be called once.
+pub fn set_storage_dir(path: PathBuf) -> Result<()> {
+    if DB.get().is_some() {
+        bail!("Cannot set the storage directory after the database is opened")
+    }
+    STORAGE_DIR.set(path).map_err(|path| anyhow!("The storage directory is already set to '{}'", path.display()))
+}
+
+/// Returns the directory of the database with the given network ID and development ID,
+/// which is the custom storage directory if one is set.
+pub fn storage_dir(network_id: u16, dev: Option<u16>) -> PathBuf {
+    match STORAGE_DIR.get() {
+        Some(path) => path.clone(),
+        None => aleo_std::aleo_ledger_dir(network_id, dev),
+    }
+}
+
 pub trait Database {
     /// Opens the database.
     fn open(network_id: u16, dev: Option<u16>) -> Result<Self>
@@ -51,6 +106,16 @@ pub trait Database {
         dev: Option<u16>,
         map_id: MapID,
     ) -> Result<DataMap<K, V>>;
+
+    /// Returns up to `limit` raw key-value pairs of the given column, with keys (excluding the prefix)
+    /// in `[start_key, end_key)`, in key order. The entries are read from a snapshot of the database.
+    fn iter_raw(
+        &self,
+        column: &str,
+        start_key: Option<&[u8]>,
+        end_key: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
 }
 
 /// An instance of a RocksDB database.
@@ -62,6 +127,14 @@ pub struct RocksDB {
     network_id: u16,
     /// The optional development ID.
     dev: Option<u16>,
+    /// The counters for the writes performed on the database.
+    writes: Arc<WriteCounters>,
+    /// The tuning profile with which the database was opened.
+    profile: TuningProfile,
+    /// If `true`, the bulk-sync options are enabled.
+    bulk_sync: Arc<AtomicBool>,
+    /// The state of the chain journal, shared by every instance of the journal of the database.
+    journal: Arc<JournalState>,
 }
 
 impl Deref for RocksDB {
@@ -77,29 +150,37 @@ impl Database for RocksDB {
     ///
     /// In production mode, the database opens directory `~/.aleo/storage/ledger-{network}`.
     /// In development mode, the database opens directory `/path/to/repo/.ledger-{network}-{id}`.
+    /// If a custom storage directory is set, the database opens that directory instead.
     fn open(network_id: u16, dev: Option<u16>) -> Result<Self> {
-        static DB: OnceCell<RocksDB> = OnceCell::new();
-
         // Retrieve the database.
         let database = DB
             .get_or_try_init(|| {
-                // Customize database options.
+                // Customize database options, with the tuning profile.
+                let profile = tuning_profile();
                 let mut options = rocksdb::Options::default();
-                options.set_compression_type(rocksdb::DBCompressionType::Lz4);
+                profile.options().apply(&mut options)?;
 
                 // Register the prefix length.
                 let prefix_extractor = rocksdb::SliceTransform::create_fixed_prefix(PREFIX_LEN);
                 options.set_prefix_extractor(prefix_extractor);
 
-                let primary = aleo_std::aleo_ledger_dir(network_id, dev);
+                let primary = storage_dir(network_id, dev);
                 let rocksdb = {
-                    options.increase_parallelism(2);
                     options.create_if_missing(true);
 
                     Arc::new(rocksdb::DB::open(&options, primary)?)
                 };
 
-                Ok::<_, anyhow::Error>(RocksDB { rocksdb, network_id, dev })
+                let bulk_sync = Arc::new(AtomicBool::new(profile.options().disable_auto_compactions));
+                Ok::<_, anyhow::Error>(RocksDB {
+                    rocksdb,
+                    network_id,
+                    dev,
+                    writes: Default::default(),
+                    profile,
+                    bulk_sync,
+                    journal: Default::default(),
+                })
             })?
             .clone();
 
@@ -126,16 +207,139 @@ impl Database for RocksDB {
         // Return the DataMap.
         Ok(DataMap { database, context, batch_in_progress: Default::default(), atomic_batch: Default::default() })
     }
+
+    /// Returns up to `limit` raw key-value pairs of the given column, with keys (excluding the prefix)
+    /// in `[start_key, end_key)`, in key order. The entries are read from a snapshot of the database.
+    fn iter_raw(
+        &self,
+        column: &str,
+        start_key: Option<&[u8]>,
+        end_key: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        // Ensure the limit is within bounds.
+        if limit == 0 || limit > MAX_RAW_ENTRIES {
+            bail!("The limit must be between 1 and {MAX_RAW_ENTRIES}")
+        }
+        // Retrieve the prefix of the column.
+        let prefix = self.column_prefix(column)?;
+
+        // Iterate over the column from the start key, on a snapshot of the database.
+        let snapshot = self.snapshot();
+        let mut iterator = snapshot.raw_iterator();
+        iterator.seek([prefix.as_slice(), start_key.unwrap_or_default()].concat());
+
+        let mut entries = Vec::new();
+        while let (Some(raw_key), Some(raw_value)) = (iterator.key(), iterator.value()) {
+            // Stop at the end of the column, the end key, or the limit.
+            if !raw_key.starts_with(&prefix) || entries.len() >= limit {
+                break;
+            }
+            let key = &raw_key[prefix.len()..];
+            if let Some(end_key) = end_key {
+                if key >= end_key {
+                    break;
+                }
+            }
+            entries.push((key.to_vec(), raw_value.to_vec()));
+            iterator.next();
+        }
+        // Ensure the iteration completed without errors.
+        iterator.status().map_err(|e| anyhow!("RocksDB iterator error: {e}"))?;
+
+        Ok(entries)
+    }
+}
+
+impl RocksDB {
+    /// Returns the state of the chain journal of the database.
+    pub(crate) fn journal_state(&self) -> &JournalState {
+        &self.journal
+    }
+
+    /// Finishes the atomic batches of several maps with the given function, and writes their operations
+    /// in a single batch, so that either all of them or none of them are written.
+    ///
+    /// If the batches are already collected by an enclosing call, they are written by that call instead.
+    pub fn finish_atomic_together(&self, finish: impl FnOnce() -> Result<()>) -> Result<()> {
+        // Start collecting the operations, unless they are already collected.
+        let is_collecting = COLLECTED_OPERATIONS.with(|collected| {
+            let mut collected = collected.borrow_mut();
+            match collected.is_some() {
+                true => false,
+                false => {
+                    *collected = Some(Vec::new());
+                    true
+                }
+            }
+        });
+        if !is_collecting {
+            return finish();
+        }
+
+        // Finish the batches, and stop collecting their operations, whether they succeed or not.
+        let result = finish();
+        let raw_operations = COLLECTED_OPERATIONS.with(|collected| collected.borrow_mut().take()).unwrap_or_default();
+        result?;
+        self.write_operations(raw_operations)
+    }
+
+    /// Writes the given raw operations atomically.
+    fn write_operations(&self, raw_operations: Vec<RawOperation>) -> Result<()> {
+        if raw_operations.is_empty() {
+            return Ok(());
+        }
+        let mut batch = WriteBatch::default();
+        for (raw_key, raw_value) in raw_operations {
+            match raw_value {
+                Some(raw_value) => batch.put(raw_key, raw_value),
+                None => batch.delete(raw_key),
+            }
+        }
+        // Execute all the operations atomically.
+        self.rocksdb.write(batch)?;
+        // Update the write counters.
+        self.writes.record_batch();
+        Ok(())
+    }
+
+    /// Opens the map with the given `map_id` in this database.
+    pub fn map<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
+        &self,
+        map_id: MapID,
+    ) -> DataMap<K, V> {
+        // Combine contexts to create a new scope.
+        let mut context = self.network_id.to_le_bytes().to_vec();
+        context.extend_from_slice(&(u16::from(map_id)).to_le_bytes());
+
+        // Return the DataMap.
+        DataMap {
+            database: self.clone(),
+            context,
+            batch_in_progress: Default::default(),
+            atomic_batch: Default::default(),
+        }
+    }
 }
 
 impl RocksDB {
     /// Opens the test database.
     #[cfg(test)]
-    fn open_testing(temp_dir: std::path::PathBuf, dev: Option<u16>) -> Result<Self> {
+    pub(crate) fn open_testing(temp_dir: std::path::PathBuf, dev: Option<u16>) -> Result<Self> {
+        Self::open_testing_with_profile(temp_dir, dev, TuningProfile::Default)
+    }
+
+    /// Opens the test database, with the given tuning profile.
+    #[cfg(test)]
+    pub(crate) fn open_testing_with_profile(
+        temp_dir: std::path::PathBuf,
+        dev: Option<u16>,
+        profile: TuningProfile,
+    ) -> Result<Self> {
         let database = {
-            // Customize database options.
+            // Customize database options, with the tuning profile.
             let mut options = rocksdb::Options::default();
-            options.set_compression_type(rocksdb::DBCompressionType::Lz4);
+            profile.options().apply(&mut options)?;
 
             // Register the prefix length.
             let prefix_extractor = rocksdb::SliceTransform::create_fixed_prefix(PREFIX_LEN);
@@ -148,12 +352,20 @@ impl RocksDB {
             };
 
             let rocksdb = {
-                options.increase_parallelism(2);
                 options.create_if_missing(true);
                 Arc::new(rocksdb::DB::open(&options, primary)?)
             };
 
-            Ok::<_, anyhow::Error>(RocksDB { rocksdb, network_id: u16::MAX, dev })
+            let bulk_sync = Arc::new(AtomicBool::new(profile.options().disable_auto_compactions));
+            Ok::<_, anyhow::Error>(RocksDB {
+                rocksdb,
+                network_id: u16::MAX,
+                dev,
+                writes: Default::default(),
+                profile,
+                bulk_sync,
+                journal: Default::default(),
+            })
         }?;
 
         // Ensure the database development ID match.
@@ -165,7 +377,7 @@ impl RocksDB {
 
     /// Opens the test map.
     #[cfg(test)]
-    fn open_map_testing<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
+    pub(crate) fn open_map_testing<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(
         temp_dir: std::path::PathBuf,
         dev: Option<u16>,
         map_id: MapID,
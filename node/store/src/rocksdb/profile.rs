@@ -0,0 +1,402 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use anyhow::Error;
+use core::{fmt, str::FromStr};
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
+
+/// The tuning profile of the database, once set.
+static TUNING_PROFILE: OnceCell<TuningProfile> = OnceCell::new();
+
+/// The number of levels of the database.
+const NUM_LEVELS: usize = 7;
+/// The number of bytes in a mebibyte.
+const MIB: usize = 1 << 20;
+
+/// The named sets of options with which the database is opened.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TuningProfile {
+    /// The options for a typical node.
+    #[default]
+    Default,
+    /// The options for a node that keeps the full history, favouring read throughput and compression ratio.
+    Archival,
+    /// The options for a small node, such as a devnet node, favouring a small memory footprint.
+    LowMemory,
+    /// The options for the initial sync, favouring write throughput, with automatic compactions disabled.
+    BulkSync,
+}
+
+impl TuningProfile {
+    /// The names of the tuning profiles.
+    pub const NAMES: [&'static str; 4] = ["default", "archival", "low-memory", "bulk-sync"];
+
+    /// Returns the database options selected by the profile.
+    pub fn options(&self) -> TuningOptions {
+        // The index columns are small and hot, so the top levels are left uncompressed,
+        // while the bulky transaction bodies settle in the bottom levels.
+        let tiered = |bottommost: Compression| {
+            let mut levels = vec![Compression::None, Compression::None];
+            levels.resize(NUM_LEVELS - 1, Compression::Lz4);
+            levels.push(bottommost);
+            levels
+        };
+        match self {
+            Self::Default => TuningOptions {
+                block_cache_bytes: 8 * MIB,
+                write_buffer_bytes: 64 * MIB,
+                max_write_buffers: 2,
+                compression_per_level: vec![Compression::Lz4; NUM_LEVELS],
+                max_background_jobs: 2,
+                disable_auto_compactions: false,
+            },
+            Self::Archival => TuningOptions {
+                block_cache_bytes: 1024 * MIB,
+                write_buffer_bytes: 128 * MIB,
+                max_write_buffers: 4,
+                compression_per_level: tiered(Compression::Lz4hc),
+                max_background_jobs: 8,
+                disable_auto_compactions: false,
+            },
+            Self::LowMemory => TuningOptions {
+                block_cache_bytes: 4 * MIB,
+                write_buffer_bytes: 8 * MIB,
+                max_write_buffers: 2,
+                compression_per_level: vec![Compression::Lz4; NUM_LEVELS],
+                max_background_jobs: 2,
+                disable_auto_compactions: false,
+            },
+            Self::BulkSync => TuningOptions {
+                block_cache_bytes: 256 * MIB,
+                write_buffer_bytes: 256 * MIB,
+                max_write_buffers: 6,
+                compression_per_level: tiered(Compression::Lz4hc),
+                max_background_jobs: 8,
+                disable_auto_compactions: true,
+            },
+        }
+    }
+
+    /// Returns the profile that the database is restored to once the node reaches the tip.
+    pub const fn at_tip(&self) -> Self {
+        match self {
+            Self::BulkSync => Self::Default,
+            profile => *profile,
+        }
+    }
+}
+
+impl FromStr for TuningProfile {
+    type Err = Error;
+
+    fn from_str(profile: &str) -> Result<Self> {
+        match profile {
+            "default" => Ok(Self::Default),
+            "archival" => Ok(Self::Archival),
+            "low-memory" => Ok(Self::LowMemory),
+            "bulk-sync" => Ok(Self::BulkSync),
+            _ => bail!("Invalid tuning profile '{profile}' (expected one of {})", Self::NAMES.join(", ")),
+        }
+    }
+}
+
+impl fmt::Display for TuningProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Archival => write!(f, "archival"),
+            Self::LowMemory => write!(f, "low-memory"),
+            Self::BulkSync => write!(f, "bulk-sync"),
+        }
+    }
+}
+
+/// The compression of a level of the database.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Lz4,
+    Lz4hc,
+}
+
+impl Compression {
+    /// Returns the RocksDB compression type.
+    const fn rocksdb(&self) -> rocksdb::DBCompressionType {
+        match self {
+            Self::None => rocksdb::DBCompressionType::None,
+            Self::Lz4 => rocksdb::DBCompressionType::Lz4,
+            Self::Lz4hc => rocksdb::DBCompressionType::Lz4hc,
+        }
+    }
+}
+
+/// The database options selected by a tuning profile.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TuningOptions {
+    /// The capacity of the block cache in bytes.
+    pub block_cache_bytes: usize,
+    /// The size of each write buffer (memtable) in bytes.
+    pub write_buffer_bytes: usize,
+    /// The maximum number of write buffers.
+    pub max_write_buffers: i32,
+    /// The compression of each level, from the flushed memtables (level 0) to the bottommost level.
+    pub compression_per_level: Vec<Compression>,
+    /// The maximum number of concurrent background flushes and compactions.
+    pub max_background_jobs: i32,
+    /// If `true`, the database is not compacted automatically.
+    pub disable_auto_compactions: bool,
+}
+
+impl TuningOptions {
+    /// Applies the options to the given RocksDB options.
+    pub(super) fn apply(&self, options: &mut rocksdb::Options) -> Result<()> {
+        // Set the block cache.
+        let mut table_options = rocksdb::BlockBasedOptions::default();
+        table_options.set_block_cache(&rocksdb::Cache::new_lru_cache(self.block_cache_bytes)?);
+        options.set_block_based_table_factory(&table_options);
+        // Set the write buffers.
+        options.set_write_buffer_size(self.write_buffer_bytes);
+        options.set_max_write_buffer_number(self.max_write_buffers);
+        // Set the compression of each level.
+        let compression = self.compression_per_level.iter().map(Compression::rocksdb).collect::<Vec<_>>();
+        options.set_num_levels(compression.len() as i32);
+        options.set_compression_per_level(&compression);
+        // Set the background jobs and compactions.
+        options.set_max_background_jobs(self.max_background_jobs);
+        options.set_disable_auto_compactions(self.disable_auto_compactions);
+        Ok(())
+    }
+
+    /// Returns the options of a running database that switch to these options.
+    ///
+    /// Only the write buffers and automatic compactions can change once the database is open.
+    fn mutable_options(&self) -> [(&'static str, String); 3] {
+        [
+            ("write_buffer_size", self.write_buffer_bytes.to_string()),
+            ("max_write_buffer_number", self.max_write_buffers.to_string()),
+            ("disable_auto_compactions", self.disable_auto_compactions.to_string()),
+        ]
+    }
+}
+
+/// Sets the tuning profile with which the database is opened.
+/// This must be called before the database is opened, and can only be called once.
+pub fn set_tuning_profile(profile: TuningProfile) -> Result<()> {
+    if DB.get().is_some() {
+        bail!("Cannot set the tuning profile after the database is opened")
+    }
+    TUNING_PROFILE.set(profile).map_err(|profile| anyhow!("The tuning profile is already set to '{profile}'"))
+}
+
+/// Returns the tuning profile with which the database is opened.
+pub fn tuning_profile() -> TuningProfile {
+    TUNING_PROFILE.get().copied().unwrap_or_default()
+}
+
+/// Compacts the column with the given name in the opened database.
+pub fn compact_column(name: &str) -> Result<()> {
+    match DB.get() {
+        Some(database) => database.compact_column(name),
+        None => bail!("The database has not been opened"),
+    }
+}
+
+/// Enables or disables the bulk-sync options of the opened database.
+pub fn set_bulk_sync(enabled: bool) -> Result<()> {
+    match DB.get() {
+        Some(database) => database.set_bulk_sync(enabled),
+        None => bail!("The database has not been opened"),
+    }
+}
+
+/// Returns `true` if the bulk-sync options of the opened database are enabled.
+pub fn is_bulk_sync() -> bool {
+    match DB.get() {
+        Some(database) => database.is_bulk_sync(),
+        None => false,
+    }
+}
+
+impl RocksDB {
+    /// Returns the tuning profile with which the database was opened.
+    pub const fn profile(&self) -> TuningProfile {
+        self.profile
+    }
+
+    /// Returns the options that are currently in effect.
+    pub fn tuning_options(&self) -> TuningOptions {
+        match self.is_bulk_sync() {
+            true => self.runtime_options(TuningProfile::BulkSync),
+            false => self.runtime_options(self.profile.at_tip()),
+        }
+    }
+
+    /// Returns `true` if the bulk-sync options are enabled.
+    pub fn is_bulk_sync(&self) -> bool {
+        self.bulk_sync.load(Ordering::SeqCst)
+    }
+
+    /// Enables the bulk-sync options, which disable the automatic compactions until the node reaches the tip,
+    /// or restores the options of the profile the database was opened with.
+    pub fn set_bulk_sync(&self, enabled: bool) -> Result<()> {
+        let options = match enabled {
+            true => self.runtime_options(TuningProfile::BulkSync),
+            false => self.runtime_options(self.profile.at_tip()),
+        };
+        let options = options.mutable_options();
+        let options = options.iter().map(|(key, value)| (*key, value.as_str())).collect::<Vec<_>>();
+        self.set_options(&options)?;
+        self.bulk_sync.store(enabled, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the options of the given profile that can change once the database is open,
+    /// along with the remaining options of the profile the database was opened with.
+    fn runtime_options(&self, profile: TuningProfile) -> TuningOptions {
+        let options = profile.options();
+        TuningOptions {
+            write_buffer_bytes: options.write_buffer_bytes,
+            max_write_buffers: options.max_write_buffers,
+            disable_auto_compactions: options.disable_auto_compactions,
+            ..self.profile.options()
+        }
+    }
+
+    /// Compacts the column with the given name.
+    pub fn compact_column(&self, name: &str) -> Result<()> {
+        // Construct the prefix of the column, and the first key past the column.
+        let prefix = self.column_prefix(name)?;
+        let end = prefix_successor(&prefix);
+        // Compact the keys of the column.
+        self.compact_range(Some(prefix), end);
+        Ok(())
+    }
+}
+
+/// Returns the smallest key that is greater than every key with the given prefix, if there is one.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+    while let Some(byte) = successor.pop() {
+        if byte < u8::MAX {
+            successor.push(byte + 1);
+            return Some(successor);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rocksdb::tests::temp_dir, TestMap};
+    use snarkvm::synthesizer::store::helpers::{Map, MapRead};
+
+    use serial_test::serial;
+
+    /// Returns the contents of the latest options file of the database in the given directory.
+    fn latest_options_file(directory: &std::path::Path) -> String {
+        let mut files = std::fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("OPTIONS-"))
+            .collect::<Vec<_>>();
+        files.sort();
+        std::fs::read_to_string(files.last().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_tuning_profile_names() {
+        for name in TuningProfile::NAMES {
+            assert_eq!(TuningProfile::from_str(name).unwrap().to_string(), name);
+        }
+        assert!(TuningProfile::from_str("fast").is_err());
+        assert_eq!(TuningProfile::BulkSync.at_tip(), TuningProfile::Default);
+        assert_eq!(TuningProfile::Archival.at_tip(), TuningProfile::Archival);
+    }
+
+    #[test]
+    fn test_prefix_successor() {
+        assert_eq!(prefix_successor(&[1, 2]), Some(vec![1, 3]));
+        assert_eq!(prefix_successor(&[1, 255]), Some(vec![2]));
+        assert_eq!(prefix_successor(&[255, 255]), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_open_with_each_profile() {
+        for name in TuningProfile::NAMES {
+            let profile = TuningProfile::from_str(name).unwrap();
+            let options = profile.options();
+
+            // Open the database with the profile.
+            let directory = temp_dir();
+            let database = RocksDB::open_testing_with_profile(directory.clone(), None, profile).unwrap();
+            assert_eq!(database.profile(), profile);
+            assert_eq!(database.is_bulk_sync(), options.disable_auto_compactions);
+
+            // Ensure the options were applied.
+            let file = latest_options_file(&directory);
+            assert!(file.contains(&format!("write_buffer_size={}", options.write_buffer_bytes)), "{name}");
+            assert!(file.contains(&format!("max_write_buffer_number={}", options.max_write_buffers)), "{name}");
+            assert!(file.contains(&format!("max_background_jobs={}", options.max_background_jobs)), "{name}");
+            assert!(file.contains(&format!("disable_auto_compactions={}", options.disable_auto_compactions)), "{name}");
+            assert!(file.contains(&format!("num_levels={NUM_LEVELS}")), "{name}");
+
+            // Ensure the database is functional, including a manual compaction.
+            let map: DataMap<u32, String> = database.map(MapID::Test(TestMap::Test));
+            for i in 0..100 {
+                map.insert(i, i.to_string()).unwrap();
+            }
+            map.remove(&0).unwrap();
+            database.compact_column("Test").unwrap();
+            assert!(database.compact_column("Unknown").is_err());
+            assert!(map.get(&0).unwrap().is_none());
+            assert_eq!(map.get(&99).unwrap().map(|value| value.to_string()), Some("99".to_string()));
+
+            // Ensure the statistics report the profile.
+            let statistics = database.statistics(MAX_ENTRIES_PER_COLUMN).unwrap();
+            assert_eq!(statistics.profile, profile);
+            assert_eq!(statistics.tuning, database.tuning_options());
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_bulk_sync_toggle() {
+        let directory = temp_dir();
+        let database = RocksDB::open_testing_with_profile(directory.clone(), None, TuningProfile::Archival).unwrap();
+        assert!(!database.is_bulk_sync());
+
+        // Enable the bulk-sync options, and ensure the automatic compactions are disabled.
+        database.set_bulk_sync(true).unwrap();
+        assert!(database.is_bulk_sync());
+        assert!(database.tuning_options().disable_auto_compactions);
+        assert!(latest_options_file(&directory).contains("disable_auto_compactions=true"));
+
+        // Restore the profile, as the node reached the tip.
+        database.set_bulk_sync(false).unwrap();
+        assert!(!database.is_bulk_sync());
+        assert_eq!(database.tuning_options(), TuningProfile::Archival.options());
+        let file = latest_options_file(&directory);
+        assert!(file.contains("disable_auto_compactions=false"));
+        assert!(file.contains(&format!("write_buffer_size={}", TuningProfile::Archival.options().write_buffer_bytes)));
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::DataID;
+
+use anyhow::anyhow;
+use core::{cmp::Reverse, fmt};
+use std::{
+    collections::BinaryHeap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The number of largest values to report.
+pub const NUM_LARGEST_VALUES: usize = 10;
+/// The default maximum number of entries to scan in each column.
+pub const MAX_ENTRIES_PER_COLUMN: usize = 1 << 20;
+
+/// The counters for the writes performed on the database.
+#[derive(Debug, Default)]
+pub struct WriteCounters {
+    /// The number of key-value pairs inserted.
+    num_inserts: AtomicU64,
+    /// The number of keys removed.
+    num_removals: AtomicU64,
+    /// The number of atomic write batches committed.
+    num_batches: AtomicU64,
+    /// The number of key and value bytes written.
+    bytes_written: AtomicU64,
+}
+
+impl WriteCounters {
+    /// Records the insertion of a key-value pair of the given size.
+    pub(super) fn record_insert(&self, num_bytes: usize) {
+        self.num_inserts.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written.fetch_add(num_bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Records the removal of a key.
+    pub(super) fn record_removal(&self) {
+        self.num_removals.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the commit of an atomic write batch.
+    pub(super) fn record_batch(&self) {
+        self.num_batches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns a snapshot of the write counters.
+    fn snapshot(&self) -> WriteStatistics {
+        WriteStatistics {
+            num_inserts: self.num_inserts.load(Ordering::Relaxed),
+            num_removals: self.num_removals.load(Ordering::Relaxed),
+            num_batches: self.num_batches.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The writes performed on the database since it was opened.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct WriteStatistics {
+    /// The number of key-value pairs inserted.
+    pub num_inserts: u64,
+    /// The number of keys removed.
+    pub num_removals: u64,
+    /// The number of atomic write batches committed.
+    pub num_batches: u64,
+    /// The number of key and value bytes written.
+    pub bytes_written: u64,
+}
+
+/// The statistics of a single column (map) in the database.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ColumnStatistics {
+    /// The name of the column.
+    pub name: String,
+    /// The number of entries in the column.
+    pub num_entries: u64,
+    /// The number of key bytes in the column, including the prefix.
+    pub key_bytes: u64,
+    /// The number of value bytes in the column.
+    pub value_bytes: u64,
+    /// If `true`, the scan stopped at the entry limit, and the totals are lower bounds.
+    pub is_truncated: bool,
+}
+
+/// A large value found in the database.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LargestValue {
+    /// The name of the column containing the value.
+    pub column: String,
+    /// The hex-encoded key of the value, excluding the prefix.
+    pub key: String,
+    /// The size of the value in bytes.
+    pub num_bytes: u64,
+}
+
+/// The statistics of the database.
+#[derive(Clone, Debug, Serialize)]
+pub struct StorageStatistics {
+    /// The statistics of each non-empty column.
+    pub columns: Vec<ColumnStatistics>,
+    /// The largest values in the database, in descending order of size.
+    pub largest_values: Vec<LargestValue>,
+    /// The writes performed on the database since it was opened.
+    pub writes: WriteStatistics,
+    /// The RocksDB estimate of the number of keys in the database.
+    pub estimated_num_keys: Option<u64>,
+    /// The RocksDB estimate of the live data size in bytes.
+    pub estimated_live_data_bytes: Option<u64>,
+    /// The total size of the SST files in bytes.
+    pub sst_files_bytes: Option<u64>,
+    /// The size of the memtables in bytes.
+    pub memtable_bytes: Option<u64>,
+    /// The tuning profile with which the database was opened.
+    pub profile: TuningProfile,
+    /// The options that are currently in effect.
+    pub tuning: TuningOptions,
+}
+
+impl StorageStatistics {
+    /// Returns the total number of entries scanned.
+    pub fn num_entries(&self) -> u64 {
+        self.columns.iter().map(|column| column.num_entries).sum()
+    }
+
+    /// Returns the total number of key and value bytes scanned.
+    pub fn num_bytes(&self) -> u64 {
+        self.columns.iter().map(|column| column.key_bytes + column.value_bytes).sum()
+    }
+}
+
+impl fmt::Display for StorageStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} entries ({} bytes) in {} columns, {} bytes in SST files, {} bytes written since startup ('{}' profile{})",
+            self.num_entries(),
+            self.num_bytes(),
+            self.columns.len(),
+            self.sst_files_bytes.unwrap_or_default(),
+            self.writes.bytes_written,
+            self.profile,
+            match self.tuning.disable_auto_compactions {
+                true => ", auto-compactions disabled",
+                false => "",
+            },
+        )
+    }
+}
+
+/// Returns the statistics of the opened database, scanning at most `max_entries` entries in each column.
+pub fn storage_statistics(max_entries: usize) -> Result<StorageStatistics> {
+    match DB.get() {
+        Some(database) => database.statistics(max_entries),
+        None => bail!("The database has not been opened"),
+    }
+}
+
+impl RocksDB {
+    /// Returns the statistics of the database, scanning at most `max_entries` entries in each column.
+    ///
+    /// Each column is scanned with its own iterator, which reads from an implicit snapshot,
+    /// so concurrent writes are not blocked while the statistics are computed.
+    pub fn statistics(&self, max_entries: usize) -> Result<StorageStatistics> {
+        let mut columns = Vec::new();
+        let mut largest_values = BinaryHeap::with_capacity(NUM_LARGEST_VALUES + 1);
+
+        for data_id in DataID::ALL {
+            // Construct the prefix of the column.
+            let mut prefix = self.network_id.to_le_bytes().to_vec();
+            prefix.extend_from_slice(&(*data_id as u16).to_le_bytes());
+
+            let mut column = ColumnStatistics { name: format!("{data_id:?}"), ..Default::default() };
+            for entry in self.prefix_iterator(&prefix) {
+                // Stop scanning the column once the entry limit is reached.
+                if column.num_entries as usize >= max_entries {
+                    column.is_truncated = true;
+                    break;
+                }
+                let (key, value) = entry.map_err(|e| anyhow!("RocksDB iterator error: {e}"))?;
+
+                column.num_entries += 1;
+                column.key_bytes += key.len() as u64;
+                column.value_bytes += value.len() as u64;
+
+                // Track the largest values, evicting the smallest once there are too many.
+                largest_values.push(Reverse((value.len(), *data_id as u16, key)));
+                if largest_values.len() > NUM_LARGEST_VALUES {
+                    largest_values.pop();
+                }
+            }
+
+            if column.num_entries > 0 {
+                columns.push(column);
+            }
+            // Yield between columns, to avoid starving other threads on large databases.
+            std::thread::yield_now();
+        }
+
+        // Sort the largest values in descending order of size.
+        let largest_values = largest_values
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((num_bytes, id, key))| LargestValue {
+                column: DataID::ALL
+                    .iter()
+                    .find(|data_id| **data_id as u16 == id)
+                    .map(|data_id| format!("{data_id:?}"))
+                    .unwrap_or_default(),
+                key: hex::encode(&key[PREFIX_LEN..]),
+                num_bytes: num_bytes as u64,
+            })
+            .collect();
+
+        Ok(StorageStatistics {
+            columns,
+            largest_values,
+            writes: self.writes.snapshot(),
+            estimated_num_keys: self.property_int_value("rocksdb.estimate-num-keys")?,
+            estimated_live_data_bytes: self.property_int_value("rocksdb.estimate-live-data-size")?,
+            sst_files_bytes: self.property_int_value("rocksdb.total-sst-files-size")?,
+            memtable_bytes: self.property_int_value("rocksdb.cur-size-all-mem-tables")?,
+            profile: self.profile,
+            tuning: self.tuning_options(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rocksdb::tests::temp_dir, TestMap};
+    use snarkvm::synthesizer::store::helpers::Map;
+
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn test_statistics() {
+        // The number of items that will be inserted into the map.
+        const NUM_ITEMS: u32 = 1000;
+        // The size of each value.
+        const VALUE_SIZE: usize = 100;
+
+        // Initialize a map.
+        let map: DataMap<u32, String> =
+            RocksDB::open_map_testing(temp_dir(), None, MapID::Test(TestMap::Test)).expect("Failed to open data map");
+
+        // Insert the items, and one large item.
+        for i in 0..NUM_ITEMS {
+            map.insert(i, "a".repeat(VALUE_SIZE)).unwrap();
+        }
+        map.insert(NUM_ITEMS, "b".repeat(10 * VALUE_SIZE)).unwrap();
+
+        // Compute the expected sizes (the prefix and a u32 key, and a length-prefixed string value).
+        let expected_key_bytes = u64::from(NUM_ITEMS + 1) * (PREFIX_LEN as u64 + 4);
+        let expected_value_bytes = u64::from(NUM_ITEMS) * (8 + VALUE_SIZE as u64) + (8 + 10 * VALUE_SIZE as u64);
+
+        // Ensure the statistics fall within 1% of the inserted data volumes.
+        let statistics = map.database.statistics(MAX_ENTRIES_PER_COLUMN).unwrap();
+        assert_eq!(statistics.columns.len(), 1);
+        let column = &statistics.columns[0];
+        assert_eq!(column.name, "Test");
+        assert_eq!(column.num_entries, u64::from(NUM_ITEMS + 1));
+        assert!(!column.is_truncated);
+        assert!(column.key_bytes.abs_diff(expected_key_bytes) <= expected_key_bytes / 100);
+        assert!(column.value_bytes.abs_diff(expected_value_bytes) <= expected_value_bytes / 100);
+
+        // Ensure the write counters include every insertion.
+        assert_eq!(statistics.writes.num_inserts, u64::from(NUM_ITEMS + 1));
+        assert_eq!(statistics.writes.bytes_written, expected_key_bytes + expected_value_bytes);
+
+        // Ensure the largest value is reported first.
+        assert_eq!(statistics.largest_values.len(), NUM_LARGEST_VALUES);
+        assert_eq!(statistics.largest_values[0].num_bytes, 8 + 10 * VALUE_SIZE as u64);
+        assert_eq!(statistics.largest_values[0].key, hex_u32(NUM_ITEMS));
+        assert!(statistics.largest_values[1..].iter().all(|value| value.num_bytes == 8 + VALUE_SIZE as u64));
+
+        // Ensure the scan stops at the entry limit.
+        let statistics = map.database.statistics(10).unwrap();
+        assert_eq!(statistics.columns[0].num_entries, 10);
+        assert!(statistics.columns[0].is_truncated);
+    }
+
+    /// Returns the hex encoding of the given bincode-serialized `u32`.
+    fn hex_u32(value: u32) -> String {
+        hex::encode(value.to_le_bytes())
+    }
+}
@@ -233,3 +233,46 @@ fn test_iterator_ordering() {
         assert_eq!(&*v1, v2);
     }
 }
+
+#[test]
+#[serial]
+fn test_finish_atomic_together() {
+    let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open storage");
+    let first: TestMap = database.map(MapID::Test(TestMapID::Test));
+    let second: DataMap<u64, u64> = database.map(MapID::Test(TestMapID::TtlClock));
+
+    // Ensure the batches of the maps are only written once every batch is finished.
+    first.start_atomic();
+    second.start_atomic();
+    first.insert(1, "a".to_string()).expect("Failed to insert");
+    second.insert(2, 3).expect("Failed to insert");
+    database
+        .finish_atomic_together(|| {
+            first.finish_atomic()?;
+            assert!(!first.contains_key(&1)?);
+            second.finish_atomic()
+        })
+        .expect("Failed to finish the batches");
+    assert_eq!(first.get(&1).expect("Failed to get").map(|v| v.to_string()), Some("a".to_string()));
+    assert_eq!(second.get(&2).expect("Failed to get").map(|v| *v), Some(3));
+
+    // Ensure none of the batches is written if one of them fails to finish.
+    first.start_atomic();
+    second.start_atomic();
+    first.insert(4, "b".to_string()).expect("Failed to insert");
+    second.insert(5, 6).expect("Failed to insert");
+    let result = database.finish_atomic_together(|| {
+        first.finish_atomic()?;
+        second.abort_atomic();
+        anyhow::bail!("Failed to finish the second batch")
+    });
+    assert!(result.is_err());
+    assert!(!first.contains_key(&4).expect("Failed to call contains key"));
+    assert!(!second.contains_key(&5).expect("Failed to call contains key"));
+
+    // Ensure the batches are written map by map once they are no longer collected.
+    first.start_atomic();
+    first.insert(7, "c".to_string()).expect("Failed to insert");
+    first.finish_atomic().expect("Failed to finish the batch");
+    assert!(first.contains_key(&7).expect("Failed to call contains key"));
+}
@@ -0,0 +1,266 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    MapID,
+    SequenceMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::{marker::PhantomData, ops::Range};
+
+/// An index of the global sequence numbers of the canonical transactions, which number the transactions of the
+/// canonical chain densely from `0`, in order of block height, and then of position in the block.
+///
+/// The sequence number of a transaction is the number of transactions in the blocks before it, plus its position
+/// in its block. When a block is disconnected, the sequence numbers of its transactions are released in the same
+/// batch, and reassigned to the transactions of the blocks connected next, so the numbering has no gaps.
+#[derive(Clone)]
+pub struct SequenceIndex<N: Network> {
+    /// The mapping of `block height` to `(sequence number of its first transaction, number of transactions)`.
+    block_map: DataMap<u32, (u64, u32)>,
+    /// The mapping of `transaction ID` to `sequence number`.
+    transaction_map: DataMap<N::TransactionID, u64>,
+    /// The mapping of `sequence number` to `transaction ID`.
+    sequence_map: DataMap<u64, N::TransactionID>,
+    /// The number of indexed blocks, which is the only value in the map.
+    height_map: DataMap<(), u32>,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> SequenceIndex<N> {
+    /// Opens the sequence index of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the sequence index of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            block_map: database.map(MapID::Sequence(SequenceMap::Block)),
+            transaction_map: database.map(MapID::Sequence(SequenceMap::Transaction)),
+            sequence_map: database.map(MapID::Sequence(SequenceMap::Sequence)),
+            height_map: database.map(MapID::Sequence(SequenceMap::Height)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of indexed blocks, including the writes of the atomic batch in progress.
+    pub fn num_blocks(&self) -> Result<u32> {
+        Ok(self.height_map.get_speculative(&())?.map_or(0, |num_blocks| *num_blocks))
+    }
+
+    /// Returns the sequence number of the next connected transaction, which is the number of indexed transactions.
+    pub fn next_sequence(&self) -> Result<u64> {
+        match self.num_blocks()?.checked_sub(1) {
+            Some(latest_height) => match self.get_block_sequences(latest_height)? {
+                Some(sequences) => Ok(sequences.end),
+                None => bail!("Missing the sequence numbers of block {latest_height}"),
+            },
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the sequence numbers of the transactions of the block at the given height, if it is indexed.
+    pub fn get_block_sequences(&self, height: u32) -> Result<Option<Range<u64>>> {
+        Ok(self.block_map.get_speculative(&height)?.map(|entry| entry.0..entry.0 + entry.1 as u64))
+    }
+
+    /// Returns the sequence number of the canonical transaction with the given ID, if it is indexed.
+    pub fn get_sequence(&self, transaction_id: &N::TransactionID) -> Result<Option<u64>> {
+        Ok(self.transaction_map.get_speculative(transaction_id)?.map(|sequence| *sequence))
+    }
+
+    /// Returns the ID of the canonical transaction with the given sequence number, if it is indexed.
+    pub fn get_transaction_id(&self, sequence: u64) -> Result<Option<N::TransactionID>> {
+        Ok(self.sequence_map.get_speculative(&sequence)?.map(|transaction_id| *transaction_id))
+    }
+
+    /// Indexes the transactions of the block at the given height, which must follow the latest indexed block.
+    /// Note that a block that is already indexed is skipped.
+    pub fn insert(&self, height: u32, transaction_ids: &[N::TransactionID]) -> Result<()> {
+        let num_blocks = self.num_blocks()?;
+        if height < num_blocks {
+            return Ok(());
+        }
+        // Ensure the block follows the latest indexed block.
+        ensure!(
+            height == num_blocks,
+            "Block {height} does not follow the latest indexed block (expected {num_blocks})"
+        );
+
+        // Number the transactions of the block from the next sequence number.
+        let start = self.next_sequence()?;
+        for (sequence, transaction_id) in (start..).zip(transaction_ids) {
+            self.transaction_map.insert(*transaction_id, sequence)?;
+            self.sequence_map.insert(sequence, *transaction_id)?;
+        }
+        self.block_map.insert(height, (start, transaction_ids.len() as u32))?;
+        self.height_map.insert((), height + 1)
+    }
+
+    /// Removes the block with the given height from the index, which must be the latest indexed block,
+    /// and returns the sequence numbers that are released, to be reassigned to the next connected transactions.
+    pub fn remove(&self, height: u32) -> Result<Range<u64>> {
+        // Ensure the block is the latest indexed block.
+        let num_blocks = self.num_blocks()?;
+        ensure!(height.checked_add(1) == Some(num_blocks), "Block {height} is not the latest indexed block");
+        let sequences = match self.get_block_sequences(height)? {
+            Some(sequences) => sequences,
+            None => bail!("Missing the sequence numbers of block {height}"),
+        };
+        for sequence in sequences.clone() {
+            match self.get_transaction_id(sequence)? {
+                Some(transaction_id) => self.transaction_map.remove(&transaction_id)?,
+                None => bail!("Missing the transaction with sequence number {sequence}"),
+            }
+            self.sequence_map.remove(&sequence)?;
+        }
+        self.block_map.remove(&height)?;
+        self.height_map.insert((), height)?;
+        Ok(sequences)
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        self.block_map.start_atomic();
+        self.transaction_map.start_atomic();
+        self.sequence_map.start_atomic();
+        self.height_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.block_map.is_atomic_in_progress()
+            || self.transaction_map.is_atomic_in_progress()
+            || self.sequence_map.is_atomic_in_progress()
+            || self.height_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        self.block_map.abort_atomic();
+        self.transaction_map.abort_atomic();
+        self.sequence_map.abort_atomic();
+        self.height_map.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        self.block_map.finish_atomic()?;
+        self.transaction_map.finish_atomic()?;
+        self.sequence_map.finish_atomic()?;
+        self.height_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns the given numbers of sampled transaction IDs, one list per block.
+    fn sample_blocks(
+        rng: &mut TestRng,
+        num_transactions: &[usize],
+    ) -> Vec<Vec<<CurrentNetwork as Network>::TransactionID>> {
+        num_transactions
+            .iter()
+            .map(|num_transactions| (0..*num_transactions).map(|_| Field::<CurrentNetwork>::rand(rng).into()).collect())
+            .collect()
+    }
+
+    /// Ensures the given index numbers the transactions of the given blocks densely, in order.
+    fn assert_dense(index: &SequenceIndex<CurrentNetwork>, blocks: &[Vec<<CurrentNetwork as Network>::TransactionID>]) {
+        let transaction_ids = blocks.iter().flatten().collect::<Vec<_>>();
+        assert_eq!(index.num_blocks().unwrap(), blocks.len() as u32);
+        assert_eq!(index.next_sequence().unwrap(), transaction_ids.len() as u64);
+        for (sequence, transaction_id) in transaction_ids.iter().enumerate() {
+            assert_eq!(index.get_sequence(transaction_id).unwrap(), Some(sequence as u64));
+            assert_eq!(index.get_transaction_id(sequence as u64).unwrap(), Some(**transaction_id));
+        }
+        assert_eq!(index.get_transaction_id(transaction_ids.len() as u64).unwrap(), None);
+        assert_eq!(index.sequence_map.iter().count(), transaction_ids.len());
+    }
+
+    #[test]
+    #[serial]
+    fn test_sequence_index() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let index = SequenceIndex::<CurrentNetwork>::from_database(&database);
+
+        // Index blocks with varying numbers of transactions, including an empty block.
+        let blocks = sample_blocks(rng, &[1, 3, 0, 2, 4]);
+        for (height, transaction_ids) in blocks.iter().enumerate() {
+            index.insert(height as u32, transaction_ids).unwrap();
+        }
+        assert_dense(&index, &blocks);
+        assert_eq!(index.get_block_sequences(1).unwrap(), Some(1..4));
+        assert_eq!(index.get_block_sequences(2).unwrap(), Some(4..4));
+        assert_eq!(index.get_block_sequences(4).unwrap(), Some(6..10));
+
+        // Ensure a block that is already indexed is skipped, and a block must follow the latest block.
+        index.insert(4, &blocks[4]).unwrap();
+        assert!(index.insert(6, &blocks[0]).is_err());
+        assert_dense(&index, &blocks);
+    }
+
+    #[test]
+    #[serial]
+    fn test_sequence_index_across_reorg() {
+        let rng = &mut TestRng::default();
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let index = SequenceIndex::<CurrentNetwork>::from_database(&database);
+
+        let blocks = sample_blocks(rng, &[1, 2, 3, 2]);
+        for (height, transaction_ids) in blocks.iter().enumerate() {
+            index.insert(height as u32, transaction_ids).unwrap();
+        }
+
+        // Ensure only the latest block can be removed.
+        assert!(index.remove(2).is_err());
+
+        // Disconnect the latest blocks in a single batch, as a reorg does, and ensure the released sequence numbers
+        // span from the fork point to the tip.
+        index.start_atomic();
+        let released = (2..=3).rev().map(|height| index.remove(height).unwrap()).collect::<Vec<_>>();
+        index.finish_atomic().unwrap();
+        assert_eq!(released, vec![6..8, 3..6]);
+        assert_dense(&index, &blocks[..2]);
+
+        // Connect the blocks of the other fork, and ensure the released sequence numbers are reassigned.
+        let fork = sample_blocks(rng, &[4, 1]);
+        for (height, transaction_ids) in (2..).zip(&fork) {
+            index.insert(height, transaction_ids).unwrap();
+        }
+        let chain = blocks[..2].iter().chain(&fork).cloned().collect::<Vec<_>>();
+        assert_dense(&index, &chain);
+        for transaction_id in blocks[2..].iter().flatten() {
+            assert_eq!(index.get_sequence(transaction_id).unwrap(), None);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    MapID,
+    SubmittedBlockMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+/// The persisted submission of a block, as `(block height, coinbase commitment, first seen)`,
+/// where the first seen time is a UNIX timestamp (in seconds).
+pub type SubmittedBlockEntry = (u32, Option<[u8; 32]>, i64);
+
+/// The blocks that were submitted and committed, persisted so that a duplicate submission is recognized after a restart.
+#[derive(Clone)]
+pub struct SubmittedBlockStore<N: Network> {
+    /// The mapping of `block hash` to `submitted block entry`.
+    block_map: DataMap<N::BlockHash, SubmittedBlockEntry>,
+}
+
+impl<N: Network> SubmittedBlockStore<N> {
+    /// Opens the submitted block store of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the submitted block store of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self { block_map: database.map(MapID::SubmittedBlock(SubmittedBlockMap::Block)) }
+    }
+
+    /// Returns the persisted submitted blocks.
+    pub fn blocks(&self) -> Vec<(N::BlockHash, SubmittedBlockEntry)> {
+        self.block_map.iter().map(|(hash, entry)| (*hash, entry.into_owned())).collect()
+    }
+
+    /// Writes the given batch in a single atomic write, where `Some` inserts the entry of the block,
+    /// and `None` removes the block.
+    pub fn write_batch(&self, batch: &[(N::BlockHash, Option<SubmittedBlockEntry>)]) -> Result<()> {
+        self.block_map.start_atomic();
+        for (hash, entry) in batch {
+            let result = match entry {
+                Some(entry) => self.block_map.insert(*hash, *entry),
+                None => self.block_map.remove(hash),
+            };
+            if let Err(error) = result {
+                self.block_map.abort_atomic();
+                return Err(error);
+            }
+        }
+        self.block_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    #[test]
+    #[serial]
+    fn test_submitted_block_store() {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let store = SubmittedBlockStore::<CurrentNetwork>::from_database(&database);
+
+        let a = <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(1));
+        let b = <CurrentNetwork as Network>::BlockHash::from(Field::from_u64(2));
+        let entry_a = (5, Some([7u8; 32]), 100);
+        let entry_b = (6, None, 200);
+
+        // Insert both blocks in a batch.
+        store.write_batch(&[(a, Some(entry_a)), (b, Some(entry_b))]).unwrap();
+        let mut persisted = store.blocks();
+        persisted.sort_by_key(|(_, (height, ..))| *height);
+        assert_eq!(persisted, vec![(a, entry_a), (b, entry_b)]);
+
+        // Remove a block, and ensure the store reopens with the remaining one.
+        store.write_batch(&[(a, None)]).unwrap();
+        let store = SubmittedBlockStore::<CurrentNetwork>::from_database(&database);
+        assert_eq!(store.blocks(), vec![(b, entry_b)]);
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{ChainEvent, ChainJournal};
+use snarkvm::prelude::*;
+
+use anyhow::Error;
+use core::{fmt, str::FromStr};
+
+/// The number of events read from the journal at a time, while searching it.
+const JOURNAL_PAGE_SIZE: u64 = 1000;
+
+/// The position of a subscriber in the changes to the canonical chain, from which a reconnecting subscriber
+/// resumes without gaps or duplicates.
+///
+/// A token is encoded as `{since}` once the subscriber follows the journal, or as `{since}:{next}:{end}`
+/// while the subscriber catches up on the canonical blocks that are older than the journal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ResumeToken {
+    /// The sequence number of the latest journaled event delivered to the subscriber.
+    pub since: u64,
+    /// The height of the next canonical block to deliver, and the height at which the journal takes over,
+    /// if the subscriber is catching up.
+    pub catch_up: Option<(u32, u32)>,
+}
+
+impl FromStr for ResumeToken {
+    type Err = Error;
+
+    fn from_str(token: &str) -> Result<Self> {
+        let malformed = || anyhow!("Malformed resume token '{token}'");
+        match token.split(':').collect::<Vec<_>>().as_slice() {
+            [since] => Ok(Self { since: since.parse().map_err(|_| malformed())?, catch_up: None }),
+            [since, next, end] => {
+                let since = since.parse().map_err(|_| malformed())?;
+                let next: u32 = next.parse().map_err(|_| malformed())?;
+                let end: u32 = end.parse().map_err(|_| malformed())?;
+                ensure!(next <= end, "Malformed resume token '{token}' (the next height exceeds the end height)");
+                Ok(Self { since, catch_up: Some((next, end)) })
+            }
+            _ => Err(malformed()),
+        }
+    }
+}
+
+impl fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.catch_up {
+            Some((next, end)) => write!(f, "{}:{next}:{end}", self.since),
+            None => write!(f, "{}", self.since),
+        }
+    }
+}
+
+/// A subscription to the changes to the canonical chain, from a given height.
+///
+/// The subscriber first catches up on the canonical blocks that are older than the journal, which are
+/// assumed to be final, as the journal retains far more events than the depth of any reorganization.
+/// The subscriber then follows the journal, so that every reorganization is delivered as it happened.
+pub struct BlockSubscription<N: Network> {
+    /// The journal of the changes to the canonical chain.
+    journal: ChainJournal<N>,
+    /// The position of the subscriber.
+    token: ResumeToken,
+}
+
+impl<N: Network> BlockSubscription<N> {
+    /// Starts a subscription at the given height, where `next_height` returns the height following the tip of the
+    /// canonical chain. The journal is replayed from the latest connection of the block at the given height.
+    pub fn start(journal: ChainJournal<N>, start_height: u32, next_height: impl FnOnce() -> u32) -> Result<Self> {
+        // Retrieve the latest sequence number before the tip, so that no block is missed.
+        let latest = journal.latest_sequence();
+        let next_height = next_height();
+        ensure!(start_height <= next_height, "The start height {start_height} exceeds the next height {next_height}");
+
+        // If the subscriber only follows the new blocks, skip the search.
+        if start_height == next_height {
+            let token = ResumeToken { since: latest, catch_up: Some((start_height, start_height)) };
+            return Ok(Self { journal, token });
+        }
+
+        // Search the journal backwards, for the latest connection of the block at the start height,
+        // and for the earliest connection of any block.
+        let mut latest_connection = None;
+        let mut earliest_connection = None;
+        let mut until = latest;
+        while latest_connection.is_none() && until > 0 {
+            let since = until.saturating_sub(JOURNAL_PAGE_SIZE);
+            let page = journal.events(since, JOURNAL_PAGE_SIZE as usize)?;
+            for (sequence, event) in page.iter().rev().filter(|(sequence, _)| *sequence <= until) {
+                if let ChainEvent::Connected { height, .. } = event {
+                    earliest_connection = Some((*sequence, *height));
+                    if *height == start_height {
+                        latest_connection = Some(*sequence);
+                        break;
+                    }
+                }
+            }
+            // Stop at the oldest retained event.
+            match page.first() {
+                Some((sequence, _)) if *sequence == since + 1 => until = since,
+                _ => break,
+            }
+        }
+
+        let token = match (latest_connection, earliest_connection) {
+            // Replay the journal from the latest connection of the block at the start height.
+            (Some(sequence), _) => ResumeToken { since: sequence - 1, catch_up: None },
+            // Catch up on the canonical blocks that are older than the journal, then replay the entire journal.
+            (None, Some((sequence, height))) if height > start_height => {
+                ResumeToken { since: sequence - 1, catch_up: Some((start_height, height)) }
+            }
+            // The block at the start height was connected after the search started, so follow the new events.
+            (None, Some(_)) => ResumeToken { since: latest, catch_up: Some((start_height, start_height)) },
+            // Catch up on the canonical blocks, as the journal has no connected block.
+            (None, None) => ResumeToken { since: latest, catch_up: Some((start_height, next_height)) },
+        };
+        Ok(Self { journal, token })
+    }
+
+    /// Resumes a subscription from the given token.
+    pub fn resume(journal: ChainJournal<N>, token: ResumeToken) -> Result<Self> {
+        // Ensure the token does not exceed the journal.
+        let latest = journal.latest_sequence();
+        ensure!(token.since <= latest, "The resume token '{token}' exceeds the latest sequence number {latest}");
+        Ok(Self { journal, token })
+    }
+
+    /// Returns the position of the subscriber.
+    pub const fn token(&self) -> ResumeToken {
+        self.token
+    }
+
+    /// Returns up to `limit` next events, each with the position of the subscriber once it is delivered,
+    /// where `canon` returns the connection of the canonical block at the given height.
+    pub fn next_events(
+        &mut self,
+        limit: usize,
+        canon: impl Fn(u32) -> Result<ChainEvent<N>>,
+    ) -> Result<Vec<(ResumeToken, ChainEvent<N>)>> {
+        let mut events = Vec::new();
+
+        // Deliver the canonical blocks that are older than the journal.
+        if let Some((mut next, end)) = self.token.catch_up {
+            while next < end && events.len() < limit {
+                let event = canon(next)?;
+                next += 1;
+                self.token.catch_up = Some((next, end));
+                events.push((self.token, event));
+            }
+            if next < end {
+                return Ok(events);
+            }
+        }
+
+        // Follow the journal.
+        let journaled = self.journal.events(self.token.since, limit - events.len())?;
+        // Ensure the events following the latest delivered event were not pruned.
+        if let Some((sequence, _)) = journaled.first() {
+            ensure!(
+                *sequence == self.token.since + 1,
+                "The journal no longer retains the events after {}, as the subscriber fell behind",
+                self.token.since
+            );
+        }
+        for (sequence, event) in journaled {
+            // Skip a block that was delivered as a canonical block, as it was connected while the subscription started.
+            let is_delivered = match (self.token.catch_up, &event) {
+                (Some((_, end)), ChainEvent::Connected { height, .. }) => *height < end,
+                _ => false,
+            };
+            self.token = ResumeToken { since: sequence, catch_up: None };
+            if !is_delivered {
+                events.push((self.token, event));
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rocksdb::{self, tests::temp_dir},
+        JournalMap,
+        MapID,
+        DEFAULT_JOURNAL_RETENTION,
+    };
+    use snarkvm::prelude::{TestRng, Testnet3, Uniform};
+
+    use parking_lot::Mutex;
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+    type BlockHash = <CurrentNetwork as Network>::BlockHash;
+
+    /// A canonical chain, whose changes after a given height are journaled.
+    struct SampleChain {
+        journal: ChainJournal<CurrentNetwork>,
+        blocks: Mutex<Vec<BlockHash>>,
+    }
+
+    impl SampleChain {
+        /// Returns a chain of the given number of blocks, of which only those from `first_journaled` are journaled.
+        fn new(num_blocks: u32, first_journaled: u32, rng: &mut TestRng) -> Self {
+            let event_map = rocksdb::RocksDB::open_map_testing(temp_dir(), None, MapID::Journal(JournalMap::Event))
+                .expect("Failed to open the journal");
+            let journal = ChainJournal::from_map(event_map, DEFAULT_JOURNAL_RETENTION).unwrap();
+            let chain = Self { journal, blocks: Default::default() };
+            for height in 0..num_blocks {
+                let hash = Field::<CurrentNetwork>::rand(rng).into();
+                match height >= first_journaled {
+                    true => chain.connect(hash),
+                    false => chain.blocks.lock().push(hash),
+                }
+            }
+            chain
+        }
+
+        fn connect(&self, hash: BlockHash) {
+            let mut blocks = self.blocks.lock();
+            let height = blocks.len() as u32;
+            self.journal.append(ChainEvent::Connected { height, hash, transaction_ids: vec![] }).unwrap();
+            blocks.push(hash);
+        }
+
+        fn disconnect(&self) {
+            let mut blocks = self.blocks.lock();
+            let hash = blocks.pop().unwrap();
+            self.journal.append(ChainEvent::Disconnected { height: blocks.len() as u32, hash }).unwrap();
+        }
+
+        fn next_height(&self) -> u32 {
+            self.blocks.lock().len() as u32
+        }
+
+        fn canon(&self, height: u32) -> Result<ChainEvent<CurrentNetwork>> {
+            match self.blocks.lock().get(height as usize) {
+                Some(hash) => Ok(ChainEvent::Connected { height, hash: *hash, transaction_ids: vec![] }),
+                None => bail!("Missing block {height}"),
+            }
+        }
+
+        /// Returns every remaining event of the given subscription.
+        fn drain(&self, subscription: &mut BlockSubscription<CurrentNetwork>) -> Vec<ChainEvent<CurrentNetwork>> {
+            let mut events = Vec::new();
+            loop {
+                let page = subscription.next_events(2, |height| self.canon(height)).unwrap();
+                if page.is_empty() {
+                    return events;
+                }
+                events.extend(page.into_iter().map(|(_, event)| event));
+            }
+        }
+    }
+
+    /// Returns the chain reconstructed by applying the given events to the blocks before the given height.
+    fn replay(blocks: &[BlockHash], start_height: u32, events: &[ChainEvent<CurrentNetwork>]) -> Vec<(u32, BlockHash)> {
+        let mut chain =
+            blocks[..start_height as usize].iter().copied().zip(0..).map(|(hash, h)| (h, hash)).collect::<Vec<_>>();
+        for event in events {
+            match event {
+                ChainEvent::Connected { height, hash, .. } => {
+                    assert_eq!(*height as usize, chain.len());
+                    chain.push((*height, *hash));
+                }
+                ChainEvent::Disconnected { height, hash } => assert_eq!(chain.pop(), Some((*height, *hash))),
+                ChainEvent::SequencesInvalidated { .. } => (),
+            }
+        }
+        chain
+    }
+
+    #[test]
+    fn test_resume_token_encoding() {
+        for token in [ResumeToken { since: 7, catch_up: None }, ResumeToken { since: 0, catch_up: Some((3, 9)) }] {
+            assert_eq!(ResumeToken::from_str(&token.to_string()).unwrap(), token);
+        }
+        assert!(ResumeToken::from_str("").is_err());
+        assert!(ResumeToken::from_str("1:2").is_err());
+        assert!(ResumeToken::from_str("1:9:3").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_subscription_resumes_without_gaps_or_duplicates() {
+        let rng = &mut TestRng::default();
+        // Sample a chain of 10 blocks, of which the first 5 are older than the journal.
+        let chain = SampleChain::new(10, 5, rng);
+        let start_height = 2;
+
+        // Start a reference subscription, and an interrupted one.
+        let mut reference =
+            BlockSubscription::start(chain.journal.clone(), start_height, || chain.next_height()).unwrap();
+        let mut interrupted =
+            BlockSubscription::start(chain.journal.clone(), start_height, || chain.next_height()).unwrap();
+        assert_eq!(reference.token(), ResumeToken { since: 0, catch_up: Some((2, 5)) });
+
+        // Deliver a few canonical blocks to both subscribers, then drop the interrupted one mid-catch-up.
+        let mut expected = reference.next_events(2, |height| chain.canon(height)).unwrap();
+        let mut received = interrupted.next_events(2, |height| chain.canon(height)).unwrap();
+        assert_eq!(received, expected);
+        let token = received.last().unwrap().0.to_string();
+        drop(interrupted);
+
+        // Reorganize to a fork of the two latest blocks, which is one block longer.
+        chain.disconnect();
+        chain.disconnect();
+        for _ in 0..3 {
+            chain.connect(Field::<CurrentNetwork>::rand(rng).into());
+        }
+
+        // Reconnect with the token, and ensure the concatenated events match the uninterrupted ones.
+        let mut resumed = BlockSubscription::resume(chain.journal.clone(), token.parse().unwrap()).unwrap();
+        let expected =
+            expected.drain(..).map(|(_, event)| event).chain(chain.drain(&mut reference)).collect::<Vec<_>>();
+        let received = received.drain(..).map(|(_, event)| event).chain(chain.drain(&mut resumed)).collect::<Vec<_>>();
+        assert_eq!(received, expected);
+        assert_eq!(resumed.token(), reference.token());
+
+        // Ensure the events reconstruct the canonical chain, including the reorganization.
+        let blocks = chain.blocks.lock().clone();
+        let replayed = replay(&blocks, start_height, &received);
+        assert_eq!(replayed.iter().map(|(_, hash)| *hash).collect::<Vec<_>>(), blocks);
+        assert!(received.iter().any(|event| matches!(event, ChainEvent::Disconnected { .. })));
+    }
+
+    #[test]
+    #[serial]
+    fn test_subscription_replays_the_journal() {
+        let rng = &mut TestRng::default();
+        let chain = SampleChain::new(6, 0, rng);
+        // Reorganize the latest block.
+        chain.disconnect();
+        chain.connect(Field::<CurrentNetwork>::rand(rng).into());
+
+        // Ensure a subscription within the journal replays it from the latest connection of the start height.
+        let mut subscription = BlockSubscription::start(chain.journal.clone(), 5, || chain.next_height()).unwrap();
+        assert_eq!(subscription.token(), ResumeToken { since: 7, catch_up: None });
+        let events = chain.drain(&mut subscription);
+        assert_eq!(events, vec![chain.canon(5).unwrap()]);
+
+        // Ensure a subscription from the genesis block replays the reorganization.
+        let mut subscription = BlockSubscription::start(chain.journal.clone(), 0, || chain.next_height()).unwrap();
+        let events = chain.drain(&mut subscription);
+        assert_eq!(events.len(), 8);
+        let blocks = chain.blocks.lock().clone();
+        assert_eq!(replay(&blocks, 0, &events).iter().map(|(_, hash)| *hash).collect::<Vec<_>>(), blocks);
+
+        // Ensure a subscription at the next height only follows the new blocks.
+        let mut subscription = BlockSubscription::start(chain.journal.clone(), 6, || chain.next_height()).unwrap();
+        assert!(chain.drain(&mut subscription).is_empty());
+        chain.connect(Field::<CurrentNetwork>::rand(rng).into());
+        assert_eq!(chain.drain(&mut subscription), vec![chain.canon(6).unwrap()]);
+
+        // Ensure a subscription cannot start beyond the next height, nor resume beyond the journal.
+        assert!(BlockSubscription::start(chain.journal.clone(), 9, || chain.next_height()).is_err());
+        assert!(BlockSubscription::resume(chain.journal.clone(), ResumeToken { since: 100, catch_up: None }).is_err());
+    }
+}
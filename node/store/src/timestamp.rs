@@ -0,0 +1,349 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    rocksdb::{DataMap, Database, RocksDB},
+    MapID,
+    TimestampMap,
+};
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{Map, MapRead},
+};
+
+use core::{marker::PhantomData, ops::RangeInclusive};
+use serde::{Deserialize, Serialize};
+
+/// The block to select for a timestamp, in the queries of blocks by timestamp.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockTimeMode {
+    /// The latest block with a timestamp at or before the timestamp.
+    Before,
+    /// The earliest block from which every block has a timestamp at or after the timestamp.
+    After,
+    /// The closer of the `before` and `after` blocks, preferring the `before` block if both are equally close.
+    #[default]
+    Nearest,
+}
+
+/// Returns the key of the given timestamp, whose serialized bytes sort in the order of the timestamps.
+fn timestamp_key(timestamp: i64) -> [u8; 8] {
+    ((timestamp as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+/// Returns the timestamp of the given key.
+fn key_timestamp(key: [u8; 8]) -> i64 {
+    (u64::from_be_bytes(key) ^ (1 << 63)) as i64
+}
+
+/// An index of the canonical blocks by timestamp, for the queries of the block closest to a point in time.
+///
+/// As the timestamps of consecutive blocks are not necessarily increasing, each timestamp of a block is indexed
+/// with the greatest height of the blocks with a timestamp at or before it. The latest block is thus indexed under
+/// its timestamp and every later timestamp, which are reindexed when it is disconnected.
+#[derive(Clone)]
+pub struct TimestampIndex<N: Network> {
+    /// The mapping of `timestamp` to `(greatest height at or before it, number of blocks with the timestamp)`.
+    height_map: DataMap<[u8; 8], (u32, u32)>,
+    /// The mapping of `block height` to `block timestamp`.
+    block_map: DataMap<u32, i64>,
+    /// PhantomData.
+    _phantom: PhantomData<N>,
+}
+
+impl<N: Network> TimestampIndex<N> {
+    /// Opens the timestamp index of the database.
+    pub fn open(dev: Option<u16>) -> Result<Self> {
+        Ok(Self::from_database(&RocksDB::open(N::ID, dev)?))
+    }
+
+    /// Opens the timestamp index of the given database.
+    pub(crate) fn from_database(database: &RocksDB) -> Self {
+        Self {
+            height_map: database.map(MapID::Timestamp(TimestampMap::Height)),
+            block_map: database.map(MapID::Timestamp(TimestampMap::Block)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the height of the latest indexed block, which is indexed under the latest timestamp.
+    pub fn latest_height(&self) -> Result<Option<u32>> {
+        Ok(self.height_map.seek_prev_speculative(&[u8::MAX; 8])?.map(|(_, (height, _))| height))
+    }
+
+    /// Returns the timestamp of the block at the given height, if it is indexed.
+    pub fn get_timestamp(&self, height: u32) -> Result<Option<i64>> {
+        Ok(self.block_map.get_speculative(&height)?.map(|timestamp| *timestamp))
+    }
+
+    /// Returns the height of the latest block with a timestamp at or before the given timestamp.
+    pub fn height_at_or_before(&self, timestamp: i64) -> Result<Option<u32>> {
+        Ok(self.height_map.seek_prev_speculative(&timestamp_key(timestamp))?.map(|(_, (height, _))| height))
+    }
+
+    /// Returns the height of the earliest block from which every block has a timestamp at or after the given timestamp.
+    pub fn height_at_or_after(&self, timestamp: i64) -> Result<Option<u32>> {
+        let latest_height = match self.latest_height()? {
+            Some(height) => height,
+            None => return Ok(None),
+        };
+        // Every block after the latest block with an earlier timestamp has a timestamp at or after the given one.
+        let height = match timestamp.checked_sub(1) {
+            Some(earlier) => self.height_at_or_before(earlier)?.map_or(0, |height| height.saturating_add(1)),
+            None => 0,
+        };
+        match height <= latest_height {
+            true => Ok(Some(height)),
+            false => Ok(None),
+        }
+    }
+
+    /// Returns the height of the block for the given timestamp, selected with the given mode.
+    pub fn get_height_by_time(&self, timestamp: i64, mode: BlockTimeMode) -> Result<Option<u32>> {
+        match mode {
+            BlockTimeMode::Before => self.height_at_or_before(timestamp),
+            BlockTimeMode::After => self.height_at_or_after(timestamp),
+            BlockTimeMode::Nearest => {
+                match (self.height_at_or_before(timestamp)?, self.height_at_or_after(timestamp)?) {
+                    (Some(before), Some(after)) => {
+                        // Retrieve the timestamps of both blocks.
+                        let (before_timestamp, after_timestamp) =
+                            match (self.get_timestamp(before)?, self.get_timestamp(after)?) {
+                                (Some(before_timestamp), Some(after_timestamp)) => (before_timestamp, after_timestamp),
+                                _ => bail!("Missing the timestamp of block {before} or {after}"),
+                            };
+                        match timestamp.saturating_sub(before_timestamp) <= after_timestamp.saturating_sub(timestamp) {
+                            true => Ok(Some(before)),
+                            false => Ok(Some(after)),
+                        }
+                    }
+                    (before, after) => Ok(before.or(after)),
+                }
+            }
+        }
+    }
+
+    /// Returns the heights of the blocks in the given time window, for windowed statistics such as fee estimates.
+    ///
+    /// The window starts at the earliest block from which every block has a timestamp at or after `start`,
+    /// and ends at the latest block with a timestamp at or before `end`.
+    pub fn heights_in_window(&self, start: i64, end: i64) -> Result<Option<RangeInclusive<u32>>> {
+        match (self.height_at_or_after(start)?, self.height_at_or_before(end)?) {
+            (Some(first), Some(last)) if first <= last => Ok(Some(first..=last)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Indexes the block with the given height and timestamp, which must be the latest block.
+    /// Note that a block that is already indexed is skipped.
+    pub fn insert(&self, height: u32, timestamp: i64) -> Result<()> {
+        if self.block_map.get_speculative(&height)?.is_some() {
+            return Ok(());
+        }
+        // Ensure the block follows the latest indexed block.
+        let expected = self.latest_height()?.map_or(0, |latest_height| latest_height.saturating_add(1));
+        ensure!(height == expected, "Block {height} does not follow the latest indexed block (expected {expected})");
+        self.block_map.insert(height, timestamp)?;
+
+        // Index the block under its timestamp, and under every later timestamp.
+        let key = timestamp_key(timestamp);
+        let num_blocks = self.height_map.get_speculative(&key)?.map_or(0, |entry| entry.1);
+        self.height_map.insert(key, (height, num_blocks + 1))?;
+        for (later, (_, num_blocks)) in self.height_map.range_speculative(&key, usize::MAX)? {
+            if later != key {
+                self.height_map.insert(later, (height, num_blocks))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the block with the given height from the index, which must be the latest block.
+    pub fn remove(&self, height: u32) -> Result<()> {
+        let timestamp = match self.get_timestamp(height)? {
+            Some(timestamp) => timestamp,
+            None => return Ok(()),
+        };
+        // Ensure the block is the latest indexed block.
+        match self.latest_height()? {
+            Some(latest_height) if latest_height == height => (),
+            _ => bail!("Block {height} is not the latest indexed block"),
+        }
+        self.block_map.remove(&height)?;
+
+        // Reindex the timestamp of the block, and every later timestamp, as they are all indexed under the block.
+        let key = timestamp_key(timestamp);
+        for (indexed, (_, num_blocks)) in self.height_map.range_speculative(&key, usize::MAX)? {
+            let num_blocks = match indexed == key {
+                true => num_blocks.saturating_sub(1),
+                false => num_blocks,
+            };
+            // Remove the timestamp once no block has it.
+            if num_blocks == 0 {
+                self.height_map.remove(&indexed)?;
+                continue;
+            }
+            // Retrieve the latest remaining block with a timestamp at or before the indexed timestamp.
+            match self.latest_block_at_or_before(height, key_timestamp(indexed))? {
+                Some(latest_height) => self.height_map.insert(indexed, (latest_height, num_blocks))?,
+                None => bail!("Missing the blocks with timestamp {}", key_timestamp(indexed)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the height of the latest block below the given height with a timestamp at or before the given timestamp.
+    fn latest_block_at_or_before(&self, below: u32, timestamp: i64) -> Result<Option<u32>> {
+        for height in (0..below).rev() {
+            match self.get_timestamp(height)? {
+                Some(block_timestamp) if block_timestamp <= timestamp => return Ok(Some(height)),
+                Some(_) => continue,
+                None => bail!("Missing the timestamp of block {height}"),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Starts an atomic batch write operation.
+    pub fn start_atomic(&self) {
+        self.height_map.start_atomic();
+        self.block_map.start_atomic();
+    }
+
+    /// Checks if an atomic batch is in progress.
+    pub fn is_atomic_in_progress(&self) -> bool {
+        self.height_map.is_atomic_in_progress() || self.block_map.is_atomic_in_progress()
+    }
+
+    /// Aborts an atomic batch write operation.
+    pub fn abort_atomic(&self) {
+        self.height_map.abort_atomic();
+        self.block_map.abort_atomic();
+    }
+
+    /// Finishes an atomic batch write operation.
+    pub fn finish_atomic(&self) -> Result<()> {
+        self.height_map.finish_atomic()?;
+        self.block_map.finish_atomic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocksdb::tests::temp_dir;
+    use snarkvm::prelude::Testnet3;
+
+    use serial_test::serial;
+
+    type CurrentNetwork = Testnet3;
+
+    /// Returns a timestamp index, with the blocks of the given timestamps.
+    fn sample_index(timestamps: &[i64]) -> TimestampIndex<CurrentNetwork> {
+        let database = RocksDB::open_testing(temp_dir(), None).expect("Failed to open the fixture database");
+        let index = TimestampIndex::<CurrentNetwork>::from_database(&database);
+        for (height, timestamp) in timestamps.iter().enumerate() {
+            index.insert(height as u32, *timestamp).unwrap();
+        }
+        index
+    }
+
+    /// Returns the height of the block for the given timestamp, with each mode.
+    fn query(index: &TimestampIndex<CurrentNetwork>, timestamp: i64) -> [Option<u32>; 3] {
+        [BlockTimeMode::Before, BlockTimeMode::After, BlockTimeMode::Nearest]
+            .map(|mode| index.get_height_by_time(timestamp, mode).unwrap())
+    }
+
+    #[test]
+    #[serial]
+    fn test_timestamp_index_queries() {
+        let index = sample_index(&[100, 110, 120, 130]);
+        assert_eq!(index.latest_height().unwrap(), Some(3));
+
+        // Ensure the queries between blocks select the adjacent blocks.
+        assert_eq!(query(&index, 110), [Some(1), Some(1), Some(1)]);
+        assert_eq!(query(&index, 114), [Some(1), Some(2), Some(1)]);
+        assert_eq!(query(&index, 115), [Some(1), Some(2), Some(1)]);
+        assert_eq!(query(&index, 116), [Some(1), Some(2), Some(2)]);
+
+        // Ensure the queries before genesis and after the tip select the genesis and the tip.
+        assert_eq!(query(&index, 0), [None, Some(0), Some(0)]);
+        assert_eq!(query(&index, i64::MIN), [None, Some(0), Some(0)]);
+        assert_eq!(query(&index, 1_000), [Some(3), None, Some(3)]);
+        assert_eq!(query(&index, i64::MAX), [Some(3), None, Some(3)]);
+
+        // Ensure the time windows are bounded by the chain.
+        assert_eq!(index.heights_in_window(105, 125).unwrap(), Some(1..=2));
+        assert_eq!(index.heights_in_window(0, 1_000).unwrap(), Some(0..=3));
+        assert_eq!(index.heights_in_window(111, 119).unwrap(), None);
+        assert_eq!(index.heights_in_window(1_000, 2_000).unwrap(), None);
+
+        // Ensure an empty index has no blocks.
+        assert_eq!(query(&sample_index(&[]), 100), [None, None, None]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_timestamp_index_with_out_of_order_timestamps() {
+        // Commit consecutive blocks with timestamps that go backwards, and repeat.
+        let index = sample_index(&[100, 150, 120, 150, 110, 160]);
+
+        // Ensure `before` selects the latest block at or before the timestamp, even if an earlier block is later.
+        assert_eq!(index.height_at_or_before(119).unwrap(), Some(4));
+        assert_eq!(index.height_at_or_before(150).unwrap(), Some(4));
+        assert_eq!(index.height_at_or_before(105).unwrap(), Some(0));
+        // Ensure `after` selects the earliest block from which every block is at or after the timestamp.
+        assert_eq!(index.height_at_or_after(101).unwrap(), Some(1));
+        assert_eq!(index.height_at_or_after(111).unwrap(), Some(5));
+        assert_eq!(index.height_at_or_after(161).unwrap(), None);
+
+        // Ensure a block that is already indexed is skipped, and a block must follow the latest block.
+        index.insert(5, 160).unwrap();
+        assert!(index.insert(3, 150).is_ok());
+        assert!(index.insert(7, 170).is_err());
+        assert_eq!(index.latest_height().unwrap(), Some(5));
+    }
+
+    #[test]
+    #[serial]
+    fn test_timestamp_index_across_reorg() {
+        let index = sample_index(&[100, 150, 120, 150, 110, 160]);
+        let reference = sample_index(&[100, 150, 120]);
+
+        // Ensure only the latest block can be removed.
+        assert!(index.remove(3).is_err());
+
+        // Disconnect the latest blocks in a single batch, as a reorg does.
+        index.start_atomic();
+        for height in (3..=5).rev() {
+            index.remove(height).unwrap();
+        }
+        index.finish_atomic().unwrap();
+
+        // Ensure the index matches the index of the remaining blocks.
+        for timestamp in [0, 100, 105, 110, 119, 120, 149, 150, 160, 1_000] {
+            assert_eq!(query(&index, timestamp), query(&reference, timestamp), "Mismatch at timestamp {timestamp}");
+        }
+        assert_eq!(index.latest_height().unwrap(), Some(2));
+        assert_eq!(index.get_timestamp(3).unwrap(), None);
+        assert_eq!(index.height_map.iter().count(), 3);
+
+        // Connect the blocks of the other fork.
+        index.insert(3, 130).unwrap();
+        assert_eq!(query(&index, 140), [Some(3), None, Some(3)]);
+        assert_eq!(query(&index, 125), [Some(2), Some(3), Some(2)]);
+    }
+}
@@ -17,6 +17,7 @@
 use crate::{
     rocksdb::{self, DataMap, Database},
     MapID,
+    RecordCiphertextMap,
     TransitionInputMap,
     TransitionMap,
     TransitionOutputMap,
@@ -228,8 +229,8 @@ pub struct OutputDB<N: Network> {
     public: DataMap<Field<N>, Option<Plaintext<N>>>,
     /// The mapping of `ciphertext hash` to `(optional) ciphertext`.
     private: DataMap<Field<N>, Option<Ciphertext<N>>>,
-    /// The mapping of `commitment` to `(checksum, (optional) record ciphertext)`.
-    record: DataMap<Field<N>, (Field<N>, Option<Record<N, Ciphertext<N>>>)>,
+    /// The mapping of `commitment` to `(checksum, (optional) record ciphertext)`, with deduplicated ciphertexts.
+    record: RecordCiphertextMap<N>,
     /// The mapping of `record nonce` to `commitment`.
     record_nonce: DataMap<Group<N>, Field<N>>,
     /// The mapping of `external commitment` to `()`. Note: This is **not** the record commitment.
@@ -245,7 +246,7 @@ impl<N: Network> OutputStorage<N> for OutputDB<N> {
     type ConstantMap = DataMap<Field<N>, Option<Plaintext<N>>>;
     type PublicMap = DataMap<Field<N>, Option<Plaintext<N>>>;
     type PrivateMap = DataMap<Field<N>, Option<Ciphertext<N>>>;
-    type RecordMap = DataMap<Field<N>, (Field<N>, Option<Record<N, Ciphertext<N>>>)>;
+    type RecordMap = RecordCiphertextMap<N>;
     type RecordNonceMap = DataMap<Group<N>, Field<N>>;
     type ExternalRecordMap = DataMap<Field<N>, ()>;
 
@@ -257,7 +258,7 @@ impl<N: Network> OutputStorage<N> for OutputDB<N> {
             constant: rocksdb::RocksDB::open_map(N::ID, dev, MapID::TransitionOutput(TransitionOutputMap::Constant))?,
             public: rocksdb::RocksDB::open_map(N::ID, dev, MapID::TransitionOutput(TransitionOutputMap::Public))?,
             private: rocksdb::RocksDB::open_map(N::ID, dev, MapID::TransitionOutput(TransitionOutputMap::Private))?,
-            record: rocksdb::RocksDB::open_map(N::ID, dev, MapID::TransitionOutput(TransitionOutputMap::Record))?,
+            record: RecordCiphertextMap::open(dev)?,
             record_nonce: rocksdb::RocksDB::open_map(N::ID, dev, MapID::TransitionOutput(TransitionOutputMap::RecordNonce))?,
             external_record: rocksdb::RocksDB::open_map(N::ID, dev, MapID::TransitionOutput(TransitionOutputMap::ExternalRecord))?,
             dev,
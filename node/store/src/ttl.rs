@@ -0,0 +1,392 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::rocksdb::DataMap;
+use snarkvm::{
+    prelude::*,
+    synthesizer::store::helpers::{memory_map::MemoryMap, Map},
+};
+
+use core::{fmt::Debug, hash::Hash, marker::PhantomData, time::Duration};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The default interval between the sweeps of the expired entries.
+pub const DEFAULT_TTL_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// The maximum number of expired entries that are deleted in a single batch.
+pub const TTL_SWEEP_BATCH_SIZE: usize = 1_000;
+
+/// The key of the expiry index, as `(expiry time, key)`, where the expiry time is big-endian,
+/// so that the index sorts by expiry time in storage.
+pub type ExpiryKey<K> = ([u8; 8], K);
+
+/// A column of entries with a time to live, persisted in the database.
+pub type DataTtlMap<K, V> = TtlMap<K, V, DataMap<K, (V, u64)>, DataMap<ExpiryKey<K>, ()>, DataMap<(), u64>>;
+/// A column of entries with a time to live, kept in memory.
+pub type MemoryTtlMap<K, V> = TtlMap<K, V, MemoryMap<K, (V, u64)>, MemoryMap<ExpiryKey<K>, ()>, MemoryMap<(), u64>>;
+
+/// A column of entries with a time to live, which are treated as absent once they expire, and deleted in batches
+/// by `sweep`, in order of expiry, through a secondary index on the expiry time.
+///
+/// The expiry is measured with the TTL clock, which is the UNIX time (in seconds), but never runs backwards:
+/// the latest time the clock read is persisted with every write, so that an entry that expired before a restart
+/// does not come back if the wall clock is set back in the meantime.
+#[derive(Clone)]
+pub struct TtlMap<K, V, E, X, C> {
+    /// The mapping of `key` to `(value, expiry time)`.
+    entry_map: E,
+    /// The mapping of `(expiry time, key)` to `()`.
+    expiry_map: X,
+    /// The latest time of the TTL clock, as persisted.
+    clock_map: C,
+    /// The latest time of the TTL clock.
+    clock: Arc<Mutex<u64>>,
+    /// The source of the wall clock, as a UNIX timestamp (in seconds).
+    wall_clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+    /// The lock of the writes, which serializes the atomic writes on the maps.
+    write_lock: Arc<Mutex<()>>,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<
+    K: Copy + Debug + Eq + Hash + Serialize + DeserializeOwned + Send + Sync,
+    V: Clone + Eq + Serialize + DeserializeOwned + Send + Sync,
+    E: for<'a> Map<'a, K, (V, u64)>,
+    X: for<'a> Map<'a, ExpiryKey<K>, ()>,
+    C: for<'a> Map<'a, (), u64>,
+> TtlMap<K, V, E, X, C>
+{
+    /// Initializes the column from the given maps, resuming its TTL clock from the persisted time.
+    pub fn new(entry_map: E, expiry_map: X, clock_map: C) -> Result<Self> {
+        Self::with_wall_clock(entry_map, expiry_map, clock_map, Arc::new(unix_timestamp))
+    }
+
+    /// Initializes the column from the given maps, with the given source of the wall clock.
+    pub(crate) fn with_wall_clock(
+        entry_map: E,
+        expiry_map: X,
+        clock_map: C,
+        wall_clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+    ) -> Result<Self> {
+        let clock = clock_map.get(&())?.map_or(0, |time| *time);
+        Ok(Self {
+            entry_map,
+            expiry_map,
+            clock_map,
+            clock: Arc::new(Mutex::new(clock)),
+            wall_clock,
+            write_lock: Default::default(),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns the current time of the TTL clock, which is the later of the wall clock and the latest time read.
+    pub fn now(&self) -> u64 {
+        let mut clock = self.clock.lock();
+        *clock = (*clock).max((self.wall_clock)());
+        *clock
+    }
+
+    /// Returns the value of the given key, unless it is absent or expired.
+    pub fn get(&self, key: &K) -> Result<Option<V>> {
+        let now = self.now();
+        Ok(self.entry_map.get(key)?.and_then(|entry| match entry.1 > now {
+            true => Some(entry.into_owned().0),
+            false => None,
+        }))
+    }
+
+    /// Returns `true` if the given key is present, and not expired.
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Returns the entries that are not expired, with their expiry times, in key order.
+    pub fn entries(&self) -> Vec<(K, V, u64)> {
+        let now = self.now();
+        self.entry_map
+            .iter()
+            .filter(|(_, entry)| entry.1 > now)
+            .map(|(key, entry)| {
+                let (value, expiry) = entry.into_owned();
+                (*key, value, expiry)
+            })
+            .collect()
+    }
+
+    /// Inserts the given key-value pair, which expires after the given time to live, replacing the previous
+    /// value of the key, if any.
+    pub fn put_with_ttl(&self, key: K, value: V, ttl: Duration) -> Result<()> {
+        self.write_batch(vec![(key, Some((value, ttl)))])
+    }
+
+    /// Removes the given key.
+    pub fn remove(&self, key: &K) -> Result<()> {
+        self.write_batch(vec![(*key, None)])
+    }
+
+    /// Writes the given batch in a single atomic write, where `Some` inserts the value of the key with the given
+    /// time to live, and `None` removes the key. The time of the TTL clock is persisted along with the batch.
+    pub fn write_batch(&self, batch: Vec<(K, Option<(V, Duration)>)>) -> Result<()> {
+        // Retain the last write of each key.
+        let batch = batch.into_iter().collect::<IndexMap<_, _>>();
+        let _lock = self.write_lock.lock();
+        let now = self.now();
+
+        self.start_atomic();
+        let result = batch.into_iter().try_for_each(|(key, entry)| {
+            // Remove the previous expiry of the key from the index.
+            if let Some(previous) = self.entry_map.get(&key)? {
+                self.expiry_map.remove(&(previous.1.to_be_bytes(), key))?;
+            }
+            match entry {
+                Some((value, ttl)) => {
+                    let expiry = now.saturating_add(ttl.as_secs().max(1));
+                    self.entry_map.insert(key, (value, expiry))?;
+                    self.expiry_map.insert((expiry.to_be_bytes(), key), ())
+                }
+                None => self.entry_map.remove(&key),
+            }
+        });
+        self.finish_atomic(now, result)
+    }
+
+    /// Deletes up to `limit` expired entries in a single atomic write, in order of expiry,
+    /// and returns the number of deleted entries.
+    pub fn sweep(&self, limit: usize) -> Result<usize> {
+        let _lock = self.write_lock.lock();
+        let now = self.now();
+        // Retrieve the earliest expired keys from the index, which sorts by expiry time.
+        let expired = self
+            .expiry_map
+            .keys()
+            .map(|expiry_key| *expiry_key)
+            .take_while(|(expiry, _)| u64::from_be_bytes(*expiry) <= now)
+            .take(limit)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        self.start_atomic();
+        let result = expired.iter().try_for_each(|expiry_key| {
+            self.expiry_map.remove(expiry_key)?;
+            // Delete the entry, unless it was written again with a later expiry.
+            match self.entry_map.get(&expiry_key.1)? {
+                Some(entry) if entry.1.to_be_bytes() == expiry_key.0 => self.entry_map.remove(&expiry_key.1),
+                _ => Ok(()),
+            }
+        });
+        self.finish_atomic(now, result)?;
+        Ok(expired.len())
+    }
+
+    /// Deletes every expired entry, in batches of `batch_size` entries, and returns the number of deleted entries.
+    pub fn sweep_expired(&self, batch_size: usize) -> Result<usize> {
+        ensure!(batch_size > 0, "The sweep batch size must be positive");
+        let mut num_swept = 0;
+        loop {
+            let num_deleted = self.sweep(batch_size)?;
+            num_swept += num_deleted;
+            if num_deleted < batch_size {
+                return Ok(num_swept);
+            }
+        }
+    }
+
+    /// Starts an atomic write on every map of the column.
+    fn start_atomic(&self) {
+        self.entry_map.start_atomic();
+        self.expiry_map.start_atomic();
+        self.clock_map.start_atomic();
+    }
+
+    /// Persists the given time of the TTL clock, and finishes the atomic write on every map of the column,
+    /// or aborts it if the given result is an error.
+    fn finish_atomic(&self, now: u64, result: Result<()>) -> Result<()> {
+        match result.and_then(|()| self.clock_map.insert((), now)) {
+            Ok(()) => {
+                self.entry_map.finish_atomic()?;
+                self.expiry_map.finish_atomic()?;
+                self.clock_map.finish_atomic()
+            }
+            Err(error) => {
+                self.entry_map.abort_atomic();
+                self.expiry_map.abort_atomic();
+                self.clock_map.abort_atomic();
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Returns the UNIX timestamp of the wall clock, in seconds.
+fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rocksdb::{tests::temp_dir, RocksDB},
+        MapID,
+        TestMap,
+    };
+
+    use serial_test::serial;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Returns a wall clock that reads the given time, and the source of the wall clock.
+    fn wall_clock(time: u64) -> (Arc<AtomicU64>, Arc<dyn Fn() -> u64 + Send + Sync>) {
+        let clock = Arc::new(AtomicU64::new(time));
+        let clock_ = clock.clone();
+        (clock, Arc::new(move || clock_.load(Ordering::SeqCst)))
+    }
+
+    /// Opens a column of the given database, with the given source of the wall clock.
+    fn open_rocksdb(database: &RocksDB, wall_clock: Arc<dyn Fn() -> u64 + Send + Sync>) -> DataTtlMap<u32, String> {
+        let (entry, expiry, clock) = (TestMap::Test, TestMap::TtlExpiry, TestMap::TtlClock);
+        let (entry_map, expiry_map, clock_map) =
+            (database.map(MapID::Test(entry)), database.map(MapID::Test(expiry)), database.map(MapID::Test(clock)));
+        TtlMap::with_wall_clock(entry_map, expiry_map, clock_map, wall_clock).unwrap()
+    }
+
+    /// Opens a column in memory, with the given source of the wall clock.
+    fn open_memory(wall_clock: Arc<dyn Fn() -> u64 + Send + Sync>) -> MemoryTtlMap<u32, String> {
+        TtlMap::with_wall_clock(Default::default(), Default::default(), Default::default(), wall_clock).unwrap()
+    }
+
+    /// Ensures the entries of the given column are absent once they expire, and deleted once swept.
+    fn check_read_after_expiry<E, X, C>(map: TtlMap<u32, String, E, X, C>, clock: &AtomicU64)
+    where
+        E: for<'a> Map<'a, u32, (String, u64)>,
+        X: for<'a> Map<'a, ExpiryKey<u32>, ()>,
+        C: for<'a> Map<'a, (), u64>,
+    {
+        map.put_with_ttl(1, "a".to_string(), Duration::from_secs(10)).unwrap();
+        map.put_with_ttl(2, "b".to_string(), Duration::from_secs(20)).unwrap();
+        assert_eq!(map.get(&1).unwrap(), Some("a".to_string()));
+
+        // Ensure an entry is absent once it expires, before it is swept.
+        clock.store(1_010, Ordering::SeqCst);
+        assert_eq!(map.get(&1).unwrap(), None);
+        assert!(!map.contains_key(&1).unwrap());
+        assert_eq!(map.entries(), vec![(2, "b".to_string(), 1_020)]);
+
+        // Ensure a write extends the expiry of the entry, and drops its previous expiry from the index.
+        map.put_with_ttl(2, "c".to_string(), Duration::from_secs(100)).unwrap();
+        assert_eq!(map.expiry_map.keys().count(), 2);
+        clock.store(1_050, Ordering::SeqCst);
+        assert_eq!(map.get(&2).unwrap(), Some("c".to_string()));
+
+        // Ensure the sweep deletes the expired entry only.
+        assert_eq!(map.sweep_expired(TTL_SWEEP_BATCH_SIZE).unwrap(), 1);
+        assert_eq!(map.entry_map.keys().map(|key| *key).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(map.expiry_map.keys().count(), 1);
+
+        // Ensure a removed entry is dropped from the index.
+        map.remove(&2).unwrap();
+        assert_eq!((map.entry_map.keys().count(), map.expiry_map.keys().count()), (0, 0));
+    }
+
+    /// Ensures the expired entries of the given column are swept in batches, in order of expiry.
+    fn check_sweep_batching<E, X, C>(map: TtlMap<u32, String, E, X, C>, clock: &AtomicU64)
+    where
+        E: for<'a> Map<'a, u32, (String, u64)>,
+        X: for<'a> Map<'a, ExpiryKey<u32>, ()>,
+        C: for<'a> Map<'a, (), u64>,
+    {
+        // Insert the entries with decreasing keys, and increasing expiry times, spanning a byte boundary.
+        for index in 0..300u32 {
+            map.put_with_ttl(1_000 - index, index.to_string(), Duration::from_secs(1 + index as u64)).unwrap();
+        }
+        clock.store(1_250, Ordering::SeqCst);
+
+        // Ensure each sweep deletes a batch of the earliest expired entries.
+        assert_eq!(map.sweep(100).unwrap(), 100);
+        assert_eq!(map.entry_map.keys().count(), 200);
+        assert!(map.get(&901).unwrap().is_none() && map.entry_map.get(&900).unwrap().is_some());
+        assert_eq!(map.sweep(100).unwrap(), 100);
+        assert_eq!(map.sweep(100).unwrap(), 50);
+        assert_eq!(map.sweep(100).unwrap(), 0);
+        assert_eq!(map.entry_map.keys().count(), 50);
+        assert_eq!(map.entries().len(), 50);
+
+        // Ensure the remaining entries are swept once they expire.
+        clock.store(2_000, Ordering::SeqCst);
+        assert_eq!(map.sweep_expired(20).unwrap(), 50);
+        assert_eq!((map.entry_map.keys().count(), map.expiry_map.keys().count()), (0, 0));
+    }
+
+    #[test]
+    #[serial]
+    fn test_read_after_expiry() {
+        let (clock, source) = wall_clock(1_000);
+        check_read_after_expiry(open_memory(source), &clock);
+
+        let (clock, source) = wall_clock(1_000);
+        let database = RocksDB::open_testing(temp_dir(), None).unwrap();
+        check_read_after_expiry(open_rocksdb(&database, source), &clock);
+    }
+
+    #[test]
+    #[serial]
+    fn test_sweep_batching() {
+        let (clock, source) = wall_clock(1_000);
+        check_sweep_batching(open_memory(source), &clock);
+
+        let (clock, source) = wall_clock(1_000);
+        let database = RocksDB::open_testing(temp_dir(), None).unwrap();
+        check_sweep_batching(open_rocksdb(&database, source), &clock);
+    }
+
+    #[test]
+    #[serial]
+    fn test_restart_does_not_resurrect_expired_entries() {
+        let directory = temp_dir();
+        let (clock, source) = wall_clock(1_000);
+        {
+            let database = RocksDB::open_testing(directory.clone(), None).unwrap();
+            let map = open_rocksdb(&database, source);
+            map.put_with_ttl(1, "a".to_string(), Duration::from_secs(10)).unwrap();
+            map.put_with_ttl(2, "b".to_string(), Duration::from_secs(1_000)).unwrap();
+            // Let the first entry expire, without sweeping it.
+            clock.store(1_100, Ordering::SeqCst);
+            map.put_with_ttl(3, "c".to_string(), Duration::from_secs(1_000)).unwrap();
+        }
+
+        // Restart with a wall clock that was set back before the expiry.
+        let (clock, source) = wall_clock(900);
+        let database = RocksDB::open_testing(directory, None).unwrap();
+        let map = open_rocksdb(&database, source);
+
+        // Ensure the expired entry stays expired, while the other entries are kept.
+        assert_eq!(map.now(), 1_100);
+        assert_eq!(map.get(&1).unwrap(), None);
+        assert_eq!(map.entries().into_iter().map(|(key, ..)| key).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(map.sweep_expired(TTL_SWEEP_BATCH_SIZE).unwrap(), 1);
+
+        // Ensure the clock resumes once the wall clock catches up.
+        clock.store(2_000, Ordering::SeqCst);
+        assert_eq!(map.get(&2).unwrap(), None);
+        assert_eq!(map.get(&3).unwrap(), Some("c".to_string()));
+    }
+}
@@ -23,6 +23,9 @@ pub use connections::{Connection, ConnectionSide};
 mod known_peers;
 pub use known_peers::KnownPeers;
 
+mod proxy;
+pub use proxy::{connect_through_proxy, is_proxy_error, resolve_through_proxy, ProxyConfig, ProxyError, ProxyTarget};
+
 mod stats;
 pub use stats::Stats;
 
@@ -0,0 +1,496 @@
+// Copyright (C) 2019-2023 Aleo Systems Inc.
+// This file is part of the snarkOS library.
+
+// The snarkOS library is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// The snarkOS library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// The version of the SOCKS protocol.
+const SOCKS_VERSION: u8 = 5;
+/// The version of the username and password authentication.
+const AUTH_VERSION: u8 = 1;
+/// The method without authentication.
+const METHOD_NONE: u8 = 0x00;
+/// The method with a username and password.
+const METHOD_PASSWORD: u8 = 0x02;
+/// The reply of a proxy that accepts none of the offered methods.
+const METHOD_UNACCEPTABLE: u8 = 0xff;
+/// The command that connects to the target.
+const COMMAND_CONNECT: u8 = 0x01;
+/// The command that resolves a hostname, which is an extension of Tor.
+const COMMAND_RESOLVE: u8 = 0xf0;
+/// The type of an IPv4 address.
+const ADDRESS_IPV4: u8 = 0x01;
+/// The type of a domain name.
+const ADDRESS_DOMAIN: u8 = 0x03;
+/// The type of an IPv6 address.
+const ADDRESS_IPV6: u8 = 0x04;
+
+/// The configuration of the SOCKS5 proxy (typically Tor) through which the outbound connections are made.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// The address of the proxy.
+    pub address: SocketAddr,
+    /// The username and password of the proxy, if it requires authentication.
+    pub auth: Option<(String, String)>,
+    /// If `true`, the node does not accept inbound connections, unless it listens for those of its onion service.
+    pub proxy_only: bool,
+}
+
+impl fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Ensure the password is never logged.
+        f.debug_struct("ProxyConfig")
+            .field("address", &self.address)
+            .field("username", &self.auth.as_ref().map(|(username, _)| username))
+            .field("proxy_only", &self.proxy_only)
+            .finish()
+    }
+}
+
+/// The target of a connection through the proxy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProxyTarget {
+    /// An IP address, which is not resolved.
+    Addr(SocketAddr),
+    /// A hostname (such as an onion address) and a port, which are resolved by the proxy.
+    Domain(String, u16),
+}
+
+impl fmt::Display for ProxyTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Addr(addr) => write!(f, "{addr}"),
+            Self::Domain(host, port) => write!(f, "{host}:{port}"),
+        }
+    }
+}
+
+/// The error of a connection through the proxy, which is distinct from the error of a direct connection.
+#[derive(Debug)]
+pub enum ProxyError {
+    /// The proxy could not be reached.
+    Unreachable(io::Error),
+    /// The proxy does not follow the SOCKS5 protocol, or does not accept the offered authentication.
+    Handshake(String),
+    /// The proxy could not reach the target, with the given reply code.
+    Refused(u8),
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable(error) => write!(f, "the proxy is unreachable ({error})"),
+            Self::Handshake(reason) => write!(f, "the proxy handshake failed ({reason})"),
+            Self::Refused(code) => write!(f, "the proxy could not reach the target ({})", reply_reason(*code)),
+        }
+    }
+}
+
+impl std::error::Error for ProxyError {}
+
+impl From<ProxyError> for io::Error {
+    fn from(error: ProxyError) -> Self {
+        let kind = match &error {
+            ProxyError::Unreachable(error) => error.kind(),
+            ProxyError::Handshake(_) => io::ErrorKind::InvalidData,
+            ProxyError::Refused(_) => io::ErrorKind::ConnectionRefused,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
+/// Returns `true` if the given error is the error of a connection through the proxy.
+pub fn is_proxy_error(error: &io::Error) -> bool {
+    error.get_ref().map_or(false, |error| error.is::<ProxyError>())
+}
+
+/// Returns the reason of the given reply code of the proxy.
+fn reply_reason(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by the ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// Connects to the given target through the given proxy, and returns the stream to the target.
+pub async fn connect_through_proxy(proxy: &ProxyConfig, target: &ProxyTarget) -> io::Result<TcpStream> {
+    let mut stream = open_session(proxy).await?;
+    request(&mut stream, COMMAND_CONNECT, target).await?;
+    Ok(stream)
+}
+
+/// Resolves the given hostname through the given proxy, so that the resolution is not leaked.
+/// Note that the resolution is an extension of Tor, which the other proxies may not support.
+pub async fn resolve_through_proxy(proxy: &ProxyConfig, host: &str) -> io::Result<IpAddr> {
+    let mut stream = open_session(proxy).await?;
+    let target = ProxyTarget::Domain(host.to_string(), 0);
+    Ok(request(&mut stream, COMMAND_RESOLVE, &target).await?.ip())
+}
+
+/// Connects to the given proxy, and authenticates with it.
+async fn open_session(proxy: &ProxyConfig) -> Result<TcpStream, ProxyError> {
+    let mut stream = TcpStream::connect(proxy.address).await.map_err(ProxyError::Unreachable)?;
+
+    // Offer the method that matches the configuration.
+    let method = match proxy.auth {
+        Some(_) => METHOD_PASSWORD,
+        None => METHOD_NONE,
+    };
+    write(&mut stream, &[SOCKS_VERSION, 1, method]).await?;
+    let mut reply = [0u8; 2];
+    read(&mut stream, &mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(ProxyError::Handshake(format!("unsupported version {}", reply[0])));
+    }
+    if reply[1] == METHOD_UNACCEPTABLE || reply[1] != method {
+        return Err(ProxyError::Handshake("the authentication method is not accepted".to_string()));
+    }
+
+    // Authenticate with the username and password, if they are configured.
+    if let Some((username, password)) = &proxy.auth {
+        let (username, password) = (username.as_bytes(), password.as_bytes());
+        if username.len() > u8::MAX as usize || password.len() > u8::MAX as usize {
+            return Err(ProxyError::Handshake("the username or password is too long".to_string()));
+        }
+        let mut message = vec![AUTH_VERSION, username.len() as u8];
+        message.extend_from_slice(username);
+        message.push(password.len() as u8);
+        message.extend_from_slice(password);
+        write(&mut stream, &message).await?;
+        read(&mut stream, &mut reply).await?;
+        if reply[1] != 0 {
+            return Err(ProxyError::Handshake("the username or password is rejected".to_string()));
+        }
+    }
+    Ok(stream)
+}
+
+/// Sends the given command for the given target, and returns the address bound by the proxy.
+async fn request(stream: &mut TcpStream, command: u8, target: &ProxyTarget) -> Result<SocketAddr, ProxyError> {
+    let mut message = vec![SOCKS_VERSION, command, 0];
+    let port = match target {
+        ProxyTarget::Addr(SocketAddr::V4(addr)) => {
+            message.push(ADDRESS_IPV4);
+            message.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        ProxyTarget::Addr(SocketAddr::V6(addr)) => {
+            message.push(ADDRESS_IPV6);
+            message.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        ProxyTarget::Domain(host, port) => {
+            if host.is_empty() || host.len() > u8::MAX as usize {
+                return Err(ProxyError::Handshake(format!("invalid hostname '{host}'")));
+            }
+            message.push(ADDRESS_DOMAIN);
+            message.push(host.len() as u8);
+            message.extend_from_slice(host.as_bytes());
+            *port
+        }
+    };
+    message.extend_from_slice(&port.to_be_bytes());
+    write(stream, &message).await?;
+
+    // Read the reply, followed by the bound address.
+    let mut reply = [0u8; 4];
+    read(stream, &mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(ProxyError::Handshake(format!("unsupported version {}", reply[0])));
+    }
+    if reply[1] != 0 {
+        return Err(ProxyError::Refused(reply[1]));
+    }
+    let ip = match reply[3] {
+        ADDRESS_IPV4 => {
+            let mut octets = [0u8; 4];
+            read(stream, &mut octets).await?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        ADDRESS_IPV6 => {
+            let mut octets = [0u8; 16];
+            read(stream, &mut octets).await?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        address_type => return Err(ProxyError::Handshake(format!("unexpected address type {address_type}"))),
+    };
+    let mut port = [0u8; 2];
+    read(stream, &mut port).await?;
+    Ok(SocketAddr::new(ip, u16::from_be_bytes(port)))
+}
+
+/// Writes the given bytes to the proxy.
+async fn write(stream: &mut TcpStream, bytes: &[u8]) -> Result<(), ProxyError> {
+    stream.write_all(bytes).await.map_err(|error| ProxyError::Handshake(error.to_string()))
+}
+
+/// Reads the exact number of bytes from the proxy.
+async fn read(stream: &mut TcpStream, bytes: &mut [u8]) -> Result<(), ProxyError> {
+    stream.read_exact(bytes).await.map(|_| ()).map_err(|error| ProxyError::Handshake(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Tcp};
+
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    /// An in-process SOCKS5 proxy, which records the targets of its connections, and relays them.
+    struct TestProxy {
+        /// The address of the proxy.
+        address: SocketAddr,
+        /// The targets requested from the proxy, in order.
+        targets: Arc<Mutex<Vec<ProxyTarget>>>,
+    }
+
+    impl TestProxy {
+        /// Starts a proxy that requires the given username and password, if any, and relays the connections
+        /// to the domain targets to the given address.
+        async fn start(auth: Option<(&str, &str)>, domain_addr: Option<SocketAddr>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let targets = Arc::new(Mutex::new(Vec::new()));
+            let auth = auth.map(|(username, password)| (username.to_string(), password.to_string()));
+            let targets_clone = targets.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (auth, targets) = (auth.clone(), targets_clone.clone());
+                    tokio::spawn(async move {
+                        let _ = Self::serve(stream, auth, targets, domain_addr).await;
+                    });
+                }
+            });
+            Self { address, targets }
+        }
+
+        /// Serves a single session of the proxy.
+        async fn serve(
+            mut stream: TcpStream,
+            auth: Option<(String, String)>,
+            targets: Arc<Mutex<Vec<ProxyTarget>>>,
+            domain_addr: Option<SocketAddr>,
+        ) -> io::Result<()> {
+            // Negotiate the method.
+            let mut header = [0u8; 2];
+            stream.read_exact(&mut header).await?;
+            let mut methods = vec![0u8; header[1] as usize];
+            stream.read_exact(&mut methods).await?;
+            let method = if auth.is_some() { METHOD_PASSWORD } else { METHOD_NONE };
+            if !methods.contains(&method) {
+                return stream.write_all(&[SOCKS_VERSION, METHOD_UNACCEPTABLE]).await;
+            }
+            stream.write_all(&[SOCKS_VERSION, method]).await?;
+
+            // Check the username and password.
+            if let Some((username, password)) = auth {
+                let mut length = [0u8; 2];
+                stream.read_exact(&mut length).await?;
+                let mut received_username = vec![0u8; length[1] as usize];
+                stream.read_exact(&mut received_username).await?;
+                stream.read_exact(&mut length[..1]).await?;
+                let mut received_password = vec![0u8; length[0] as usize];
+                stream.read_exact(&mut received_password).await?;
+                let is_valid = received_username == username.as_bytes() && received_password == password.as_bytes();
+                stream.write_all(&[AUTH_VERSION, if is_valid { 0 } else { 1 }]).await?;
+                if !is_valid {
+                    return Ok(());
+                }
+            }
+
+            // Read the request.
+            let mut request = [0u8; 4];
+            stream.read_exact(&mut request).await?;
+            let target = match request[3] {
+                ADDRESS_IPV4 => {
+                    let mut octets = [0u8; 4];
+                    stream.read_exact(&mut octets).await?;
+                    SocketAddr::from((octets, stream.read_u16().await?))
+                }
+                ADDRESS_DOMAIN => {
+                    let mut host = vec![0u8; stream.read_u8().await? as usize];
+                    stream.read_exact(&mut host).await?;
+                    let host = String::from_utf8(host).unwrap();
+                    let port = stream.read_u16().await?;
+                    targets.lock().push(ProxyTarget::Domain(host, port));
+                    match (request[1], domain_addr) {
+                        // Resolve every hostname to the loopback address.
+                        (COMMAND_RESOLVE, _) => {
+                            return stream.write_all(&[SOCKS_VERSION, 0, 0, ADDRESS_IPV4, 127, 0, 0, 1, 0, 0]).await;
+                        }
+                        (_, Some(domain_addr)) => domain_addr,
+                        (_, None) => {
+                            return stream.write_all(&[SOCKS_VERSION, 0x04, 0, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0]).await
+                        }
+                    }
+                }
+                _ => return stream.write_all(&[SOCKS_VERSION, 0x08, 0, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0]).await,
+            };
+            if request[3] == ADDRESS_IPV4 {
+                targets.lock().push(ProxyTarget::Addr(target));
+            }
+
+            // Connect to the target, and relay the streams.
+            let mut outbound = match TcpStream::connect(target).await {
+                Ok(outbound) => outbound,
+                Err(_) => return stream.write_all(&[SOCKS_VERSION, 0x05, 0, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0]).await,
+            };
+            stream.write_all(&[SOCKS_VERSION, 0, 0, ADDRESS_IPV4, 0, 0, 0, 0, 0, 0]).await?;
+            tokio::io::copy_bidirectional(&mut stream, &mut outbound).await.map(|_| ())
+        }
+    }
+
+    /// Returns the configuration of the given proxy.
+    fn proxy_config(address: SocketAddr, auth: Option<(&str, &str)>) -> ProxyConfig {
+        let auth = auth.map(|(username, password)| (username.to_string(), password.to_string()));
+        ProxyConfig { address, auth, proxy_only: true }
+    }
+
+    /// Returns the configuration of a Tcp that listens on the loopback address.
+    fn local_config() -> Config {
+        Config {
+            listener_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            desired_listening_port: Some(0),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_through_proxy() {
+        let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_addr = target.local_addr().unwrap();
+        let proxy = TestProxy::start(Some(("user", "secret")), Some(target_addr)).await;
+
+        // Ensure the connection to an address reaches the target through the proxy.
+        let mut stream = connect_through_proxy(
+            &proxy_config(proxy.address, Some(("user", "secret"))),
+            &ProxyTarget::Addr(target_addr),
+        )
+        .await
+        .unwrap();
+        let (mut accepted, _) = target.accept().await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buffer = [0u8; 4];
+        accepted.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"ping");
+
+        // Ensure the hostname of a domain target is sent to the proxy, instead of being resolved.
+        let onion = ProxyTarget::Domain("example.onion".to_string(), 4133);
+        connect_through_proxy(&proxy_config(proxy.address, Some(("user", "secret"))), &onion).await.unwrap();
+        assert_eq!(*proxy.targets.lock(), vec![ProxyTarget::Addr(target_addr), onion]);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_errors() {
+        let proxy = TestProxy::start(Some(("user", "secret")), None).await;
+
+        // Ensure the rejected credentials, the unreachable targets, and an unreachable proxy are proxy errors.
+        let error = connect_through_proxy(
+            &proxy_config(proxy.address, Some(("user", "wrong"))),
+            &ProxyTarget::Domain("example.onion".to_string(), 4133),
+        )
+        .await
+        .unwrap_err();
+        assert!(is_proxy_error(&error));
+        let error = connect_through_proxy(
+            &proxy_config(proxy.address, Some(("user", "secret"))),
+            &ProxyTarget::Domain("example.onion".to_string(), 4133),
+        )
+        .await
+        .unwrap_err();
+        assert!(is_proxy_error(&error));
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        let unreachable = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let error = connect_through_proxy(&proxy_config(unreachable, None), &ProxyTarget::Addr(proxy.address))
+            .await
+            .unwrap_err();
+        assert!(is_proxy_error(&error));
+
+        // Ensure a direct error is not a proxy error.
+        assert!(!is_proxy_error(&TcpStream::connect(unreachable).await.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connects_through_proxy() {
+        let tcp = Tcp::new(local_config());
+        tcp.enable_listener().await.unwrap();
+        let peer = Tcp::new(local_config());
+        let peer_ip = peer.enable_listener().await.unwrap();
+        let onion_peer = Tcp::new(local_config());
+        let onion_peer_ip = onion_peer.enable_listener().await.unwrap();
+
+        // Ensure a virtual address is never dialed without a proxy.
+        let virtual_addr = "[fd87:d87e:eb43::1]:4133".parse().unwrap();
+        tcp.register_domain(virtual_addr, "example.onion".to_string(), 4133);
+        tcp.connect(virtual_addr).await.unwrap_err();
+
+        // Ensure every outbound connection traverses the proxy, including those to the onion addresses.
+        let proxy = TestProxy::start(None, Some(onion_peer_ip)).await;
+        tcp.set_proxy(Some(proxy_config(proxy.address, None)));
+        tcp.connect(peer_ip).await.unwrap();
+        tcp.connect(virtual_addr).await.unwrap();
+        assert!(tcp.is_connected(peer_ip));
+        assert!(tcp.is_connected(virtual_addr));
+        assert_eq!(
+            *proxy.targets.lock(),
+            vec![ProxyTarget::Addr(peer_ip), ProxyTarget::Domain("example.onion".to_string(), 4133)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_never_dials_directly_with_proxy() {
+        let tcp = Tcp::new(local_config());
+        tcp.enable_listener().await.unwrap();
+        let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_addr = target.local_addr().unwrap();
+
+        // Ensure the connection fails with a proxy error when the proxy is unreachable.
+        let unreachable = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        tcp.set_proxy(Some(proxy_config(unreachable, None)));
+        let error = tcp.connect(target_addr).await.unwrap_err();
+        assert!(is_proxy_error(&error));
+        assert!(!tcp.is_connecting(target_addr));
+
+        // Ensure the target was never dialed directly.
+        let accepted = tokio::time::timeout(std::time::Duration::from_millis(200), target.accept()).await;
+        assert!(accepted.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_through_proxy() {
+        let proxy = TestProxy::start(None, None).await;
+        let ip = resolve_through_proxy(&proxy_config(proxy.address, None), "seed.example.com").await.unwrap();
+        assert_eq!(ip, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(*proxy.targets.lock(), vec![ProxyTarget::Domain("seed.example.com".to_string(), 0)]);
+    }
+}
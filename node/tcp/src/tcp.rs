@@ -15,7 +15,7 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt,
     io,
     net::{IpAddr, SocketAddr},
@@ -27,7 +27,7 @@ use std::{
 };
 
 use once_cell::sync::OnceCell;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use tokio::{
     io::split,
     net::{TcpListener, TcpStream},
@@ -38,6 +38,7 @@ use tracing::*;
 
 use crate::{
     connections::{Connection, ConnectionSide, Connections},
+    helpers::{connect_through_proxy, is_proxy_error, ProxyConfig, ProxyTarget},
     protocols::{Protocol, Protocols},
     Config,
     KnownPeers,
@@ -77,6 +78,10 @@ pub struct InnerTcp {
     known_peers: KnownPeers,
     /// Collects statistics related to the node itself.
     stats: Stats,
+    /// The proxy through which the outbound connections are made, if any.
+    proxy: RwLock<Option<ProxyConfig>>,
+    /// The hostnames (such as onion addresses) behind the virtual addresses, only reachable through the proxy.
+    domains: RwLock<HashMap<SocketAddr, (String, u16)>>,
     /// The node's tasks.
     pub(crate) tasks: Mutex<Vec<JoinHandle<()>>>,
 }
@@ -102,6 +107,8 @@ impl Tcp {
             connections: Default::default(),
             known_peers: Default::default(),
             stats: Default::default(),
+            proxy: Default::default(),
+            domains: Default::default(),
             tasks: Default::default(),
         }));
 
@@ -171,6 +178,27 @@ impl Tcp {
         &self.stats
     }
 
+    /// Returns the proxy through which the outbound connections are made, if any.
+    pub fn proxy(&self) -> Option<ProxyConfig> {
+        self.proxy.read().clone()
+    }
+
+    /// Sets the proxy through which every outbound connection is made from then on.
+    pub fn set_proxy(&self, proxy: Option<ProxyConfig>) {
+        *self.proxy.write() = proxy;
+    }
+
+    /// Registers the hostname and port behind the given virtual address, so that a connection to the
+    /// address is made to the hostname through the proxy, without resolving it locally.
+    pub fn register_domain(&self, addr: SocketAddr, host: String, port: u16) {
+        self.domains.write().insert(addr, (host, port));
+    }
+
+    /// Returns the hostname and port behind the given virtual address, if it is registered.
+    pub fn domain(&self, addr: SocketAddr) -> Option<(String, u16)> {
+        self.domains.read().get(&addr).cloned()
+    }
+
     /// Returns the tracing [`Span`] associated with Tcp.
     #[inline]
     pub fn span(&self) -> &Span {
@@ -225,8 +253,11 @@ impl Tcp {
             return Err(io::ErrorKind::AlreadyExists.into());
         }
 
-        let stream = TcpStream::connect(addr).await.map_err(|e| {
+        let stream = self.dial(addr).await.map_err(|e| {
             self.connecting.lock().remove(&addr);
+            if is_proxy_error(&e) {
+                warn!(parent: self.span(), "Unable to connect to {addr} through the proxy: {e}");
+            }
             e
         })?;
 
@@ -241,6 +272,21 @@ impl Tcp {
         ret
     }
 
+    /// Opens a stream to the provided `SocketAddr`, through the proxy if one is set.
+    async fn dial(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let domain = self.domain(addr);
+        match (self.proxy(), domain) {
+            (Some(proxy), Some((host, port))) => connect_through_proxy(&proxy, &ProxyTarget::Domain(host, port)).await,
+            (Some(proxy), None) => connect_through_proxy(&proxy, &ProxyTarget::Addr(addr)).await,
+            // A hostname is never resolved locally, so that the resolution is not leaked.
+            (None, Some((host, port))) => {
+                error!(parent: self.span(), "Unable to connect to {host}:{port} ({addr}) without a proxy");
+                Err(io::ErrorKind::Unsupported.into())
+            }
+            (None, None) => TcpStream::connect(addr).await,
+        }
+    }
+
     /// Disconnects from the provided `SocketAddr`.
     pub async fn disconnect(&self, addr: SocketAddr) -> bool {
         if let Some(handler) = self.protocols.disconnect.get() {
@@ -30,6 +30,7 @@ pub async fn beacon() -> Beacon<CurrentNetwork, ConsensusMemory<CurrentNetwork>>
         sample_genesis_block(), // Should load the current network's genesis block.
         None,                   // No CDN.
         None,
+        Default::default(),
     )
     .await
     .expect("couldn't create beacon instance")
@@ -42,6 +43,7 @@ pub async fn client() -> Client<CurrentNetwork, ConsensusMemory<CurrentNetwork>>
         &[],
         sample_genesis_block(),
         None,
+        Default::default(),
     )
     .await
     .expect("couldn't create client instance")
@@ -54,6 +56,7 @@ pub async fn prover() -> Prover<CurrentNetwork, ConsensusMemory<CurrentNetwork>>
         &[],
         sample_genesis_block(),
         None,
+        Default::default(),
     )
     .await
     .expect("couldn't create prover instance")
@@ -68,6 +71,7 @@ pub async fn validator() -> Validator<CurrentNetwork, ConsensusMemory<CurrentNet
         sample_genesis_block(), // Should load the current network's genesis block.
         None,                   // No CDN.
         None,
+        Default::default(),
     )
     .await
     .expect("couldn't create validator instance")
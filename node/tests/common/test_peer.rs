@@ -15,17 +15,31 @@
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
 use snarkos_account::Account;
-use snarkos_node_messages::{ChallengeRequest, ChallengeResponse, Data, Message, MessageCodec, MessageTrait, NodeType};
-use snarkos_node_router::expect_message;
+use snarkos_node_messages::{
+    ChallengeRequest,
+    ChallengeResponse,
+    Data,
+    Message,
+    MessageTrait,
+    NodeType,
+    NoiseCodec,
+    NoiseState,
+    PeerCodec,
+    PostHandshakeState,
+};
+use snarkos_node_router::{expect_message, generate_keypair, noise_handshake};
 use snarkvm::prelude::{error, Address, Block, FromBytes, Network, TestRng, Testnet3 as CurrentNetwork};
 
 use std::{
+    collections::HashMap,
     io,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
+    sync::Arc,
 };
 
-use futures_util::{sink::SinkExt, TryStreamExt};
+use futures_util::sink::SinkExt;
+use parking_lot::RwLock;
 use pea2pea::{
     protocols::{Disconnect, Handshake, Reading, Writing},
     Config,
@@ -55,6 +69,8 @@ pub struct TestPeer {
     node: Node,
     node_type: NodeType,
     account: Account<CurrentNetwork>,
+    noise_private_key: Arc<[u8]>,
+    noise_states: Arc<RwLock<HashMap<SocketAddr, PostHandshakeState>>>,
 }
 
 impl Pea2Pea for TestPeer {
@@ -89,6 +105,8 @@ impl TestPeer {
             }),
             node_type,
             account,
+            noise_private_key: generate_keypair().unwrap().private.into(),
+            noise_states: Default::default(),
         };
 
         peer.enable_handshake().await;
@@ -112,6 +130,21 @@ impl TestPeer {
     pub fn address(&self) -> Address<CurrentNetwork> {
         self.account.address()
     }
+
+    /// Returns the codec for the connection with the given peer address.
+    fn peer_codec(&self, peer_addr: SocketAddr) -> PeerCodec<CurrentNetwork> {
+        let mut codec = match self.noise_states.read().get(&peer_addr).cloned() {
+            Some(noise_state) => PeerCodec::Encrypted(NoiseCodec::new(NoiseState::PostHandshake(noise_state))),
+            None => PeerCodec::default(),
+        };
+        codec.update_max_message_len();
+        codec
+    }
+}
+
+/// Returns the message to sign for the given nonce, bound to the given Noise handshake hash.
+fn challenge_message(nonce: u64, handshake_hash: &[u8]) -> Vec<u8> {
+    [nonce.to_le_bytes().as_slice(), handshake_hash].concat()
 }
 
 #[async_trait::async_trait]
@@ -123,25 +156,43 @@ impl Handshake for TestPeer {
 
         let peer_addr = conn.addr();
         let node_side = !conn.side();
+        let noise_side = match node_side {
+            ConnectionSide::Initiator => snarkos_node_tcp::ConnectionSide::Initiator,
+            ConnectionSide::Responder => snarkos_node_tcp::ConnectionSide::Responder,
+        };
         let stream = self.borrow_stream(&mut conn);
-        let mut framed = Framed::new(stream, MessageCodec::<CurrentNetwork>::default());
+        let mut framed = Framed::new(stream, PeerCodec::<CurrentNetwork>::default());
 
-        // Retrieve the genesis block header.
-        let genesis_header = *sample_genesis_block().header();
+        // Retrieve the genesis block header and hash.
+        let genesis = sample_genesis_block();
+        let genesis_header = *genesis.header();
 
         // TODO(nkls): add assertions on the contents of messages.
         match node_side {
             ConnectionSide::Initiator => {
                 // Send a challenge request to the peer.
-                let our_request = ChallengeRequest::new(local_ip.port(), self.node_type(), self.address(), rng.gen());
+                let our_request = ChallengeRequest::new(
+                    local_ip.port(),
+                    self.node_type(),
+                    self.address(),
+                    genesis.hash(),
+                    rng.gen(),
+                    0,
+                );
                 framed.send(Message::ChallengeRequest(our_request)).await?;
 
-                // Receive the peer's challenge bundle.
-                let _peer_response = expect_message!(Message::ChallengeResponse, framed, peer_addr);
+                // Receive the peer's challenge request, and encrypt the connection.
                 let peer_request = expect_message!(Message::ChallengeRequest, framed, peer_addr);
+                let mut framed = noise_handshake(framed, &self.noise_private_key, noise_side).await?;
+                let handshake_hash = framed.codec().post_handshake_state().unwrap().handshake_hash().to_vec();
+                self.noise_states.write().insert(peer_addr, framed.codec().post_handshake_state().unwrap().clone());
+
+                // Receive the peer's challenge response.
+                let _peer_response = expect_message!(Message::ChallengeResponse, framed, peer_addr);
 
-                // Sign the nonce.
-                let signature = self.account().sign_bytes(&peer_request.nonce.to_le_bytes(), rng).unwrap();
+                // Sign the nonce, bound to the handshake hash.
+                let signature =
+                    self.account().sign_bytes(&challenge_message(peer_request.nonce, &handshake_hash), rng).unwrap();
 
                 // Send the challenge response.
                 let our_response = ChallengeResponse { genesis_header, signature: Data::Object(signature) };
@@ -151,14 +202,27 @@ impl Handshake for TestPeer {
                 // Listen for the challenge request.
                 let peer_request = expect_message!(Message::ChallengeRequest, framed, peer_addr);
 
-                // Sign the nonce.
-                let signature = self.account().sign_bytes(&peer_request.nonce.to_le_bytes(), rng).unwrap();
+                // Send our challenge request, and encrypt the connection.
+                let our_request = ChallengeRequest::new(
+                    local_ip.port(),
+                    self.node_type(),
+                    self.address(),
+                    genesis.hash(),
+                    rng.gen(),
+                    0,
+                );
+                framed.send(Message::ChallengeRequest(our_request)).await?;
+                let mut framed = noise_handshake(framed, &self.noise_private_key, noise_side).await?;
+                let handshake_hash = framed.codec().post_handshake_state().unwrap().handshake_hash().to_vec();
+                self.noise_states.write().insert(peer_addr, framed.codec().post_handshake_state().unwrap().clone());
+
+                // Sign the nonce, bound to the handshake hash.
+                let signature =
+                    self.account().sign_bytes(&challenge_message(peer_request.nonce, &handshake_hash), rng).unwrap();
 
-                // Send our challenge bundle.
+                // Send our challenge response.
                 let our_response = ChallengeResponse { genesis_header, signature: Data::Object(signature) };
                 framed.send(Message::ChallengeResponse(our_response)).await?;
-                let our_request = ChallengeRequest::new(local_ip.port(), self.node_type(), self.address(), rng.gen());
-                framed.send(Message::ChallengeRequest(our_request)).await?;
 
                 // Listen for the challenge response.
                 let _peer_response = expect_message!(Message::ChallengeResponse, framed, peer_addr);
@@ -171,21 +235,21 @@ impl Handshake for TestPeer {
 
 #[async_trait::async_trait]
 impl Writing for TestPeer {
-    type Codec = MessageCodec<CurrentNetwork>;
+    type Codec = PeerCodec<CurrentNetwork>;
     type Message = Message<CurrentNetwork>;
 
-    fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.peer_codec(addr)
     }
 }
 
 #[async_trait::async_trait]
 impl Reading for TestPeer {
-    type Codec = MessageCodec<CurrentNetwork>;
+    type Codec = PeerCodec<CurrentNetwork>;
     type Message = Message<CurrentNetwork>;
 
-    fn codec(&self, _peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Default::default()
+    fn codec(&self, peer_addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
+        self.peer_codec(peer_addr)
     }
 
     async fn process_message(&self, _peer_ip: SocketAddr, _message: Self::Message) -> io::Result<()> {
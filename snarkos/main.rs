@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with the snarkOS library. If not, see <https://www.gnu.org/licenses/>.
 
-use snarkos_cli::{commands::CLI, helpers::Updater};
+use snarkos_cli::{
+    commands::{DbError, CLI},
+    helpers::Updater,
+};
 
 use clap::Parser;
 use tikv_jemallocator::Jemalloc;
@@ -30,7 +33,14 @@ fn main() -> anyhow::Result<()> {
     // Run the CLI.
     match cli.command.parse() {
         Ok(output) => println!("{output}\n"),
-        Err(error) => println!("⚠️  {error}\n"),
+        Err(error) => match error.downcast_ref::<DbError>() {
+            // Exit with the code of the failure class of the database commands, for the scripts.
+            Some(failure) => {
+                eprintln!("⚠️  {error}\n");
+                std::process::exit(failure.exit_code())
+            }
+            None => println!("⚠️  {error}\n"),
+        },
     }
     Ok(())
 }